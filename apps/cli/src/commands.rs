@@ -0,0 +1,978 @@
+//! The subcommands, on an open repository
+//!
+//! Each command takes the repository manager and a writer for its output, so
+//! `main` only deals with arguments and the master password. Commands that
+//! change the vault save it before they return. [`send_lock_command`] is
+//! the exception: it talks to the running desktop app, not to a vault.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Result};
+use ziplock_client::ClientError;
+use ziplock_shared::core::FileOperationProvider;
+use ziplock_shared::models::{
+    RecurrenceInterval, RecurringCredential, RecurringDraft, VaultMember,
+};
+use ziplock_shared::utils::reminders::due_date_label;
+use ziplock_shared::utils::time_utils::{
+    current_timestamp, format_duration_since, format_timestamp, parse_date,
+};
+use ziplock_shared::utils::{
+    lock_socket_path, AuditDocument, BackupManager, CredentialDocument, CredentialList,
+    CsvImporter, ExportFormat, ExportOptions, ImportReport, ImportSource, LockCommand, LockStatus,
+    PassphraseOptions, PasswordGenerator, PasswordOptions, ReminderEngine, SearchResults,
+    SecurityAuditReport, SecurityAuditor, ShareIdentity, ShareRecipient, SharedVault,
+    SharedVaultPackage, TotpGenerator, SHARED_VAULT_EXTENSION,
+};
+use ziplock_shared::{CredentialField, CredentialRecord, FieldType, UnifiedRepositoryManager};
+
+use crate::exit::{fail, ExitStatus};
+
+/// Shown instead of sensitive values unless they are revealed
+const MASK: &str = "********";
+
+/// Fields of a new credential given on the command line
+#[derive(Debug, Default)]
+pub struct NewCredential {
+    pub title: String,
+    pub credential_type: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub url: Option<String>,
+    pub totp_secret: Option<String>,
+    pub notes: Option<String>,
+    pub tags: Vec<String>,
+    /// `name=value` pairs for any other text field
+    pub fields: Vec<String>,
+    /// `YYYY-MM-DD` the credential expires
+    pub expires: Option<String>,
+    /// Days after which the password should be changed
+    pub rotate_every: Option<u32>,
+}
+
+/// A recurring credential given on the command line
+#[derive(Debug, Default)]
+pub struct NewRecurring {
+    pub title: String,
+    pub template: String,
+    /// Interval like `30d`, `2w` or `1m`
+    pub every: String,
+    /// `YYYY-MM-DD` the first draft is due; today if not given
+    pub first: Option<String>,
+    pub folder: Option<String>,
+    pub tags: Vec<String>,
+    pub carry_over: bool,
+}
+
+/// How `generate` builds a password
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Generate {
+    Password { length: usize, symbols: bool },
+    Passphrase { words: usize },
+}
+
+/// Add a credential, save, and return its ID
+pub fn add<F: FileOperationProvider>(
+    manager: &mut UnifiedRepositoryManager<F>,
+    new: NewCredential,
+) -> Result<String> {
+    let mut credential = CredentialRecord::new(new.title, new.credential_type);
+    if let Some(username) = new.username {
+        credential.set_field("username", CredentialField::username(username));
+    }
+    if let Some(password) = new.password {
+        credential.set_field("password", CredentialField::password(password));
+    }
+    if let Some(url) = new.url {
+        credential.set_field("url", CredentialField::url(url));
+    }
+    if let Some(secret) = new.totp_secret {
+        credential.set_field("totp_secret", CredentialField::totp_secret(secret));
+    }
+    for pair in new.fields {
+        let (name, value) = pair
+            .split_once('=')
+            .ok_or_else(|| anyhow!("Expected NAME=VALUE, got '{}'", pair))?;
+        credential.set_field(name.trim(), CredentialField::text(value));
+    }
+    credential.notes = new.notes;
+    for tag in new.tags {
+        credential.add_tag(tag);
+    }
+    if let Some(date) = &new.expires {
+        credential.expires_at = Some(date_arg(date)?);
+    }
+    credential.rotation_days = new.rotate_every;
+
+    let id = credential.id.clone();
+    manager.add_credential(credential)?;
+    manager.save_repository()?;
+    Ok(id)
+}
+
+/// The credential with ID `query`, or the only one titled `query`
+pub fn find<'a, F: FileOperationProvider>(
+    manager: &'a UnifiedRepositoryManager<F>,
+    query: &str,
+) -> Result<&'a CredentialRecord> {
+    if manager.contains_credential(query) {
+        return Ok(manager.get_credential_readonly(query)?);
+    }
+    let matches: Vec<String> = manager
+        .list_credentials()?
+        .into_iter()
+        .filter(|c| c.title.eq_ignore_ascii_case(query))
+        .map(|c| c.id)
+        .collect();
+    match matches.as_slice() {
+        [id] => Ok(manager.get_credential_readonly(id)?),
+        [] => Err(fail(
+            ExitStatus::NotFound,
+            format!("No credential with ID or title '{}'", query),
+        )),
+        ids => bail!(
+            "{} credentials are titled '{}'; use an ID: {}",
+            ids.len(),
+            query,
+            ids.join(", ")
+        ),
+    }
+}
+
+/// Print one credential, masking sensitive values unless `reveal`
+///
+/// With `field` only that value is printed, unmasked, for piping into other
+/// programs.
+pub fn show<F: FileOperationProvider>(
+    manager: &UnifiedRepositoryManager<F>,
+    query: &str,
+    field: Option<&str>,
+    reveal: bool,
+    json: bool,
+    out: &mut impl Write,
+) -> Result<()> {
+    let credential = &manager.get_credential_with_notes(&find(manager, query)?.id)?;
+
+    if let Some(name) = field {
+        let field = credential
+            .get_field(name)
+            .ok_or_else(|| anyhow!("'{}' has no field '{}'", credential.title, name))?;
+        if field.protection.is_some() {
+            bail!("Field '{}' is protected by its own passphrase", name);
+        }
+        writeln!(out, "{}", field.value)?;
+        return Ok(());
+    }
+
+    if json {
+        let document = CredentialDocument::new(credential, reveal);
+        writeln!(out, "{}", serde_json::to_string_pretty(&document)?)?;
+        return Ok(());
+    }
+
+    writeln!(out, "Title: {}", credential.title)?;
+    writeln!(out, "Type:  {}", credential.credential_type)?;
+    writeln!(out, "ID:    {}", credential.id)?;
+    if let Some(folder) = &credential.folder_path {
+        writeln!(out, "Folder: {}", folder)?;
+    }
+    if !credential.tags.is_empty() {
+        writeln!(out, "Tags:  {}", credential.tags.join(", "))?;
+    }
+    let mut names: Vec<&String> = credential.fields.keys().collect();
+    names.sort();
+    for name in names {
+        let field = &credential.fields[name];
+        let value = if field.protection.is_some() {
+            "(protected)"
+        } else if field.sensitive && !reveal {
+            MASK
+        } else {
+            field.value.as_str()
+        };
+        write!(out, "{}: {}", field.label.as_deref().unwrap_or(name), value)?;
+        if field.field_type == FieldType::Password {
+            if let Some(changed_at) = field.value_updated_at {
+                write!(out, " (changed {})", format_duration_since(changed_at))?;
+            }
+        }
+        writeln!(out)?;
+    }
+    if let Some(notes) = &credential.notes {
+        writeln!(out, "Notes:\n{}", notes)?;
+    }
+    Ok(())
+}
+
+/// Print earlier field values, most recent first, as
+/// `REPLACED_AT<TAB>DATE<TAB>FIELD<TAB>VALUE` lines; removed fields end with
+/// `<TAB>removed`
+pub fn history<F: FileOperationProvider>(
+    manager: &UnifiedRepositoryManager<F>,
+    query: &str,
+    reveal: bool,
+    out: &mut impl Write,
+) -> Result<()> {
+    let credential = find(manager, query)?;
+    for entry in credential.field_history.iter().rev() {
+        write!(
+            out,
+            "{}\t{}\t{}\t{}",
+            entry.replaced_at,
+            format_timestamp(entry.replaced_at),
+            entry.field,
+            if reveal { entry.value.as_str() } else { MASK }
+        )?;
+        if entry.removed {
+            write!(out, "\tremoved")?;
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+/// Put back the value `field` had before `replaced_at`, and save
+pub fn restore<F: FileOperationProvider>(
+    manager: &mut UnifiedRepositoryManager<F>,
+    query: &str,
+    field: &str,
+    replaced_at: i64,
+) -> Result<()> {
+    let id = find(manager, query)?.id.clone();
+    manager.restore_previous_value(&id, field, replaced_at)?;
+    manager.save_repository()?;
+    Ok(())
+}
+
+/// Add a recurring credential, save, and return its ID
+pub fn add_recurring<F: FileOperationProvider>(
+    manager: &mut UnifiedRepositoryManager<F>,
+    new: NewRecurring,
+) -> Result<String> {
+    let interval: RecurrenceInterval = new.every.parse().map_err(|e: String| anyhow!(e))?;
+    let first_due = match &new.first {
+        Some(date) => date_arg(date)?,
+        None => current_timestamp(),
+    };
+    let mut recurring = RecurringCredential::new(new.template, new.title, interval, first_due)
+        .with_tags(new.tags)
+        .with_carry_over(new.carry_over);
+    if let Some(folder) = new.folder {
+        recurring = recurring.with_folder(folder);
+    }
+    let id = recurring.id.clone();
+    manager.set_recurring_credential(recurring)?;
+    manager.save_repository()?;
+    Ok(id)
+}
+
+/// Print recurring credentials as `ID<TAB>NEXT_DUE<TAB>INTERVAL<TAB>TITLE`
+/// lines, soonest first
+pub fn list_recurring<F: FileOperationProvider>(
+    manager: &UnifiedRepositoryManager<F>,
+    out: &mut impl Write,
+) -> Result<()> {
+    let mut recurring = manager.list_recurring_credentials()?;
+    recurring.sort_by_key(|r| r.next_due);
+    for r in recurring {
+        writeln!(
+            out,
+            "{}\t{}\t{}\t{}",
+            r.id,
+            format_timestamp(r.next_due),
+            r.interval,
+            r.title
+        )?;
+    }
+    Ok(())
+}
+
+/// Print expirations and password changes due within `within_days`, or
+/// already overdue, soonest first
+pub fn reminders<F: FileOperationProvider>(
+    manager: &UnifiedRepositoryManager<F>,
+    within_days: u32,
+    out: &mut impl Write,
+) -> Result<()> {
+    let engine = ReminderEngine::new().with_lead_days(within_days);
+    for reminder in manager.reminders(&engine)? {
+        writeln!(
+            out,
+            "{}\t{}\t{}\t{}",
+            reminder.credential_id,
+            due_date_label(reminder.due_at),
+            reminder.title,
+            reminder.summary()
+        )?;
+    }
+    Ok(())
+}
+
+fn date_arg(date: &str) -> Result<i64> {
+    parse_date(date).ok_or_else(|| anyhow!("'{}' is not a date like 2026-01-31", date))
+}
+
+/// Remove a recurring credential and save; drafts already made are kept
+pub fn remove_recurring<F: FileOperationProvider>(
+    manager: &mut UnifiedRepositoryManager<F>,
+    id: &str,
+) -> Result<RecurringCredential> {
+    let removed = manager
+        .remove_recurring_credential(id)
+        .map_err(|e| fail(ExitStatus::NotFound, e.to_string()))?;
+    manager.save_repository()?;
+    Ok(removed)
+}
+
+/// Create the drafts that are due, saving if any were made
+pub fn run_recurring<F: FileOperationProvider>(
+    manager: &mut UnifiedRepositoryManager<F>,
+) -> Result<Vec<RecurringDraft>> {
+    let drafts = manager.create_due_drafts(current_timestamp())?;
+    if !drafts.is_empty() {
+        manager.save_repository()?;
+    }
+    Ok(drafts)
+}
+
+/// Add a member to share the vault with, or replace their key, and save
+pub fn add_member<F: FileOperationProvider>(
+    manager: &mut UnifiedRepositoryManager<F>,
+    name: &str,
+    public_key: &str,
+) -> Result<()> {
+    let recipient: ShareRecipient = public_key
+        .parse()
+        .map_err(|_| anyhow!("'{}' is not a public key like zlpk1...", public_key))?;
+    manager.set_member(VaultMember::new(name, &recipient))?;
+    manager.save_repository()?;
+    Ok(())
+}
+
+/// Print members as `NAME<TAB>PUBLIC_KEY<TAB>SHARED_CREDENTIALS` lines
+pub fn list_members<F: FileOperationProvider>(
+    manager: &UnifiedRepositoryManager<F>,
+    out: &mut impl Write,
+) -> Result<()> {
+    let credentials = manager.list_credentials()?;
+    for member in manager.list_members()? {
+        let shared = credentials
+            .iter()
+            .filter(|c| c.is_shared_with(&member.name))
+            .count();
+        writeln!(out, "{}\t{}\t{}", member.name, member.public_key, shared)?;
+    }
+    Ok(())
+}
+
+/// Remove a member, taking them off every credential, and save
+pub fn remove_member<F: FileOperationProvider>(
+    manager: &mut UnifiedRepositoryManager<F>,
+    name: &str,
+) -> Result<VaultMember> {
+    let removed = manager
+        .remove_member(name)
+        .map_err(|e| fail(ExitStatus::NotFound, e.to_string()))?;
+    manager.save_repository()?;
+    Ok(removed)
+}
+
+/// Share a credential with a member, or stop sharing it, and save; returns
+/// the credential's title
+pub fn set_access<F: FileOperationProvider>(
+    manager: &mut UnifiedRepositoryManager<F>,
+    member: &str,
+    query: &str,
+    shared: bool,
+) -> Result<String> {
+    if !manager.list_members()?.iter().any(|m| m.name == member) {
+        return Err(fail(
+            ExitStatus::NotFound,
+            format!("No vault member '{}'", member),
+        ));
+    }
+    let mut credential = find(manager, query)?.clone();
+    credential.shared_with.retain(|name| name != member);
+    if shared {
+        credential.shared_with.push(member.to_string());
+    }
+    let title = credential.title.clone();
+    manager.update_credential(credential)?;
+    manager.save_repository()?;
+    Ok(title)
+}
+
+/// Write each member's encrypted share of the vault into `folder`
+pub fn export_members<F: FileOperationProvider>(
+    manager: &UnifiedRepositoryManager<F>,
+    folder: &Path,
+) -> Result<Vec<PathBuf>> {
+    if manager.list_members()?.is_empty() {
+        bail!("The vault has no members; add one with 'members add'");
+    }
+    Ok(SharedVault::write_to_folder(
+        manager.memory_repository(),
+        folder,
+    )?)
+}
+
+/// A new sharing key pair: the secret key and its public key
+pub fn keygen() -> (String, String) {
+    let identity = ShareIdentity::generate();
+    (identity.to_string(), identity.recipient().to_string())
+}
+
+/// Send `command` to the lock socket of the running desktop app
+pub fn send_lock_command(command: LockCommand) -> Result<LockStatus> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_io()
+        .build()?;
+    let path = lock_socket_path();
+    runtime
+        .block_on(ziplock_client::send_lock_command(&path, command))
+        .map_err(|e| match e {
+            ClientError::NotRunning { .. } => anyhow!("ZipLock is not running ({:?})", path),
+            other => other.into(),
+        })
+}
+
+/// Print credentials as `ID<TAB>TITLE<TAB>USERNAME` lines, or as a
+/// `ziplock.credential-list.v1` document
+pub fn print_list(
+    credentials: &[CredentialRecord],
+    json: bool,
+    out: &mut impl Write,
+) -> Result<()> {
+    if json {
+        let document = CredentialList::new(credentials);
+        writeln!(out, "{}", serde_json::to_string_pretty(&document)?)?;
+        return Ok(());
+    }
+    print_lines(credentials, out)
+}
+
+/// Print search results like [`print_list`], or as a
+/// `ziplock.search-results.v1` document
+pub fn print_search(
+    query: &str,
+    found: &[CredentialRecord],
+    json: bool,
+    out: &mut impl Write,
+) -> Result<()> {
+    if json {
+        let document = SearchResults::new(query, found);
+        writeln!(out, "{}", serde_json::to_string_pretty(&document)?)?;
+        return Ok(());
+    }
+    print_lines(found, out)
+}
+
+fn print_lines(credentials: &[CredentialRecord], out: &mut impl Write) -> Result<()> {
+    for credential in credentials {
+        let username = credential
+            .get_field("username")
+            .map(|f| f.value.as_str())
+            .unwrap_or_default();
+        writeln!(out, "{}\t{}\t{}", credential.id, credential.title, username)?;
+    }
+    Ok(())
+}
+
+/// All credentials, sorted by title
+pub fn list<F: FileOperationProvider>(
+    manager: &UnifiedRepositoryManager<F>,
+) -> Result<Vec<CredentialRecord>> {
+    let mut credentials = manager.list_credentials()?;
+    credentials.sort_by_key(|c| c.title.to_lowercase());
+    Ok(credentials)
+}
+
+/// Audit every credential, without checking passwords against breaches
+pub fn audit<F: FileOperationProvider>(
+    manager: &UnifiedRepositoryManager<F>,
+) -> Result<SecurityAuditReport> {
+    Ok(SecurityAuditor::default().audit(&manager.list_credentials()?))
+}
+
+/// Print each finding as `CATEGORY<TAB>TITLE<TAB>DETAIL`, or the report as
+/// a `ziplock.audit-report.v1` document
+pub fn print_audit(report: SecurityAuditReport, json: bool, out: &mut impl Write) -> Result<()> {
+    if json {
+        let document = AuditDocument::new(report);
+        writeln!(out, "{}", serde_json::to_string_pretty(&document)?)?;
+        return Ok(());
+    }
+    for group in &report.reused {
+        for title in &group.titles {
+            writeln!(out, "reused\t{}\tshared by {}", title, group.titles.len())?;
+        }
+    }
+    for weak in &report.weak {
+        writeln!(out, "weak\t{}\t{}", weak.title, weak.strength)?;
+    }
+    for old in &report.old {
+        writeln!(out, "old\t{}\t{} days", old.title, old.age_days)?;
+    }
+    for missing in &report.missing_totp {
+        let domain = missing.domain.as_deref().unwrap_or("important");
+        writeln!(out, "no-totp\t{}\t{}", missing.title, domain)?;
+    }
+    for breached in &report.breached {
+        writeln!(out, "breached\t{}\t", breached.title)?;
+    }
+    for recycled in &report.recycled {
+        writeln!(out, "recycled\t{}\t{}", recycled.title, recycled.field)?;
+    }
+    for secret in &report.exposed_secrets {
+        let place = secret.field.as_deref().unwrap_or("notes");
+        writeln!(
+            out,
+            "exposed\t{}\t{} in {}",
+            secret.title,
+            secret.kind.name(),
+            place
+        )?;
+    }
+    Ok(())
+}
+
+/// Current TOTP code of a credential and the seconds it stays valid
+pub fn totp<F: FileOperationProvider>(
+    manager: &UnifiedRepositoryManager<F>,
+    query: &str,
+) -> Result<(String, u64)> {
+    let credential = find(manager, query)?;
+    let field = credential
+        .fields
+        .values()
+        .find(|f| f.field_type == FieldType::TotpSecret && !f.value.is_empty())
+        .ok_or_else(|| anyhow!("'{}' has no TOTP secret", credential.title))?;
+    let generator = TotpGenerator::from_field(field)?;
+    Ok((generator.generate()?, generator.seconds_remaining()))
+}
+
+/// A new random password or passphrase
+pub fn generate(options: &Generate) -> Result<String> {
+    let generated = match *options {
+        Generate::Password { length, symbols } => PasswordGenerator::generate(&PasswordOptions {
+            length,
+            include_symbols: symbols,
+            ..PasswordOptions::default()
+        }),
+        Generate::Passphrase { words } => {
+            PasswordGenerator::generate_passphrase_with(&PassphraseOptions {
+                word_count: words,
+                ..PassphraseOptions::default()
+            })
+            .map(|generated| generated.passphrase)
+        }
+    };
+    generated.map_err(|e| anyhow!(e))
+}
+
+/// Format names accepted by `export`
+pub const EXPORT_FORMATS: [&str; 5] = ["json", "csv", "yaml", "bitwarden", "html"];
+
+/// The whole vault in `format`, sensitive values included
+///
+/// `only` limits the export to the credentials it names. The `html` viewer
+/// is encrypted with `passphrase`, which it needs.
+pub fn export<F: FileOperationProvider>(
+    manager: &UnifiedRepositoryManager<F>,
+    format: &str,
+    only: &[String],
+    passphrase: Option<String>,
+) -> Result<Vec<u8>> {
+    let format = match format {
+        "json" => ExportFormat::Json,
+        "csv" => ExportFormat::Csv,
+        "yaml" => ExportFormat::Yaml,
+        "bitwarden" => ExportFormat::BitwardenJson,
+        "html" => ExportFormat::HtmlViewer,
+        other => bail!("Unknown export format '{}'", other),
+    };
+    if format == ExportFormat::HtmlViewer && passphrase.is_none() {
+        bail!("An HTML viewer needs a passphrase");
+    }
+    let credential_ids = if only.is_empty() {
+        None
+    } else {
+        Some(
+            only.iter()
+                .map(|query| Ok(find(manager, query)?.id.clone()))
+                .collect::<Result<Vec<_>>>()?,
+        )
+    };
+    let options = ExportOptions {
+        format,
+        credential_ids,
+        encryption_password: passphrase,
+        ..ExportOptions::default()
+    };
+    Ok(BackupManager::export_repository(
+        manager.memory_repository(),
+        &options,
+    )?)
+}
+
+/// Source names accepted by `import --from`
+pub const IMPORT_SOURCES: [&str; 6] = [
+    "proton-pass",
+    "dashlane",
+    "enpass",
+    "apple-passwords",
+    "bitwarden",
+    "1password",
+];
+
+/// Import `data` read from `file_name` and save
+///
+/// Without `source` the file is a ZipLock export, a shared vault package
+/// opened with `identity`, or CSV from another manager whose layout is
+/// detected from the header. Credentials from another manager that match
+/// one already in the repository are held back as duplicates.
+pub fn import<F: FileOperationProvider>(
+    manager: &mut UnifiedRepositoryManager<F>,
+    data: &[u8],
+    file_name: &str,
+    source: Option<&str>,
+    assign_new_ids: bool,
+    identity: Option<&ShareIdentity>,
+) -> Result<ImportReport> {
+    let repository = manager.memory_repository_mut();
+    let shared_vault = format!(".{}", SHARED_VAULT_EXTENSION);
+    let report = match source {
+        Some(source) => {
+            let source = match source {
+                "proton-pass" => ImportSource::ProtonPass,
+                "dashlane" => ImportSource::Dashlane,
+                "enpass" => ImportSource::Enpass,
+                "apple-passwords" => ImportSource::ApplePasswords,
+                "bitwarden" => ImportSource::Bitwarden,
+                "1password" => ImportSource::OnePassword,
+                other => bail!("Unknown import source '{}'", other),
+            };
+            let mut report = source.parse(data)?;
+            if assign_new_ids {
+                report = report.assign_new_ids();
+            }
+            report
+                .detect_duplicates(&repository.list_credentials()?)
+                .import_into(repository)?
+        }
+        None if file_name.to_lowercase().ends_with(&shared_vault) => {
+            let identity =
+                identity.ok_or_else(|| anyhow!("A shared vault package needs --identity"))?;
+            let mut report = SharedVault::open(&SharedVaultPackage::parse(data)?, identity)?;
+            if assign_new_ids {
+                report = report.assign_new_ids();
+            }
+            report.import_into(repository)?
+        }
+        None if file_name.to_lowercase().ends_with(".csv") => {
+            let text = String::from_utf8_lossy(data);
+            CsvImporter::detect(&text)?
+                .assign_new_ids(assign_new_ids)
+                .import_into(&text, repository)?
+        }
+        None => BackupManager::import_into(data, None, repository, assign_new_ids)?,
+    };
+    manager.save_repository()?;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ziplock_shared::DesktopFileProvider;
+
+    fn vault(dir: &tempfile::TempDir) -> UnifiedRepositoryManager<DesktopFileProvider> {
+        let path = dir.path().join("vault.7z");
+        let mut manager = UnifiedRepositoryManager::new(DesktopFileProvider::new());
+        manager
+            .create_repository(path.to_str().unwrap(), "password")
+            .unwrap();
+        manager
+    }
+
+    fn output(write: impl FnOnce(&mut Vec<u8>) -> Result<()>) -> String {
+        let mut out = Vec::new();
+        write(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_add_show_and_list() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut manager = vault(&dir);
+        let id = add(
+            &mut manager,
+            NewCredential {
+                title: "Bank".to_string(),
+                credential_type: "login".to_string(),
+                username: Some("jane".to_string()),
+                password: Some("s3cret".to_string()),
+                totp_secret: Some("JBSWY3DPEHPK3PXP".to_string()),
+                tags: vec!["finance".to_string()],
+                fields: vec!["pin=1234".to_string()],
+                ..NewCredential::default()
+            },
+        )
+        .unwrap();
+        assert!(!manager.is_modified());
+
+        let shown = output(|out| show(&manager, "bank", None, false, false, out));
+        assert!(shown.contains("jane"));
+        assert!(shown.contains("1234"));
+        assert!(!shown.contains("s3cret"));
+        assert!(shown.contains("(changed just now)"));
+        let revealed = output(|out| show(&manager, &id, None, true, false, out));
+        assert!(revealed.contains("s3cret"));
+        let field = output(|out| show(&manager, &id, Some("password"), false, false, out));
+        assert_eq!(field, "s3cret\n");
+
+        let listed = output(|out| print_list(&list(&manager)?, false, out));
+        assert_eq!(listed, format!("{}\tBank\tjane\n", id));
+        let json = output(|out| print_list(&list(&manager)?, true, out));
+        assert!(json.contains("ziplock.credential-list.v1"));
+        assert!(!json.contains("s3cret"));
+        let json = output(|out| show(&manager, &id, None, false, true, out));
+        assert!(json.contains("ziplock.credential.v1"));
+        assert!(!json.contains("s3cret"));
+
+        let report = audit(&manager).unwrap();
+        assert!(report.weak.iter().any(|weak| weak.title == "Bank"));
+        let audited = output(|out| print_audit(report, false, out));
+        assert!(audited.contains("weak\tBank\t"));
+
+        let (code, remaining) = totp(&manager, "Bank").unwrap();
+        assert_eq!(code.len(), 6);
+        assert!(remaining <= 30);
+
+        let missing = find(&manager, "missing").unwrap_err();
+        assert_eq!(ExitStatus::of(&missing), ExitStatus::NotFound);
+        add(
+            &mut manager,
+            NewCredential {
+                title: "bank".to_string(),
+                credential_type: "login".to_string(),
+                ..NewCredential::default()
+            },
+        )
+        .unwrap();
+        assert!(find(&manager, "Bank").is_err());
+        assert!(find(&manager, &id).is_ok());
+    }
+
+    #[test]
+    fn test_history_and_restore() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut manager = vault(&dir);
+        let id = add(
+            &mut manager,
+            NewCredential {
+                title: "Forum".to_string(),
+                credential_type: "login".to_string(),
+                password: Some("first".to_string()),
+                ..NewCredential::default()
+            },
+        )
+        .unwrap();
+        let mut changed = manager.get_credential_readonly(&id).unwrap().clone();
+        changed.fields.get_mut("password").unwrap().value = "second".to_string();
+        manager.update_credential(changed).unwrap();
+
+        let listed = output(|out| history(&manager, "Forum", false, out));
+        assert!(listed.ends_with("\tpassword\t********\n"));
+        let revealed = output(|out| history(&manager, "Forum", true, out));
+        assert!(revealed.ends_with("\tpassword\tfirst\n"));
+
+        let replaced_at: i64 = listed.split('\t').next().unwrap().parse().unwrap();
+        restore(&mut manager, "Forum", "password", replaced_at).unwrap();
+        assert!(!manager.is_modified());
+        let field = output(|out| show(&manager, &id, Some("password"), false, false, out));
+        assert_eq!(field, "first\n");
+    }
+
+    #[test]
+    fn test_recurring() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut manager = vault(&dir);
+        let id = add_recurring(
+            &mut manager,
+            NewRecurring {
+                title: "Door code {year}".to_string(),
+                template: "login".to_string(),
+                every: "1m".to_string(),
+                first: Some("2020-01-15".to_string()),
+                ..NewRecurring::default()
+            },
+        )
+        .unwrap();
+        assert!(!manager.is_modified());
+        let listed = output(|out| list_recurring(&manager, out));
+        assert_eq!(
+            listed,
+            format!("{}\t2020-01-15 00:00:00 UTC\t1m\tDoor code {{year}}\n", id)
+        );
+
+        let drafts = run_recurring(&mut manager).unwrap();
+        assert_eq!(drafts.len(), 1);
+        assert_eq!(drafts[0].title, "Door code 2020");
+        assert!(run_recurring(&mut manager).unwrap().is_empty());
+        let draft = find(&manager, &drafts[0].credential_id).unwrap();
+        assert!(draft.tags.iter().any(|tag| tag == "inbox"));
+
+        let bad = NewRecurring {
+            title: "Code".to_string(),
+            template: "login".to_string(),
+            every: "monthly".to_string(),
+            ..NewRecurring::default()
+        };
+        assert!(add_recurring(&mut manager, bad).is_err());
+
+        remove_recurring(&mut manager, &id).unwrap();
+        assert!(manager.list_recurring_credentials().unwrap().is_empty());
+        let missing = remove_recurring(&mut manager, &id).unwrap_err();
+        assert_eq!(ExitStatus::of(&missing), ExitStatus::NotFound);
+    }
+
+    #[test]
+    fn test_members() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut manager = vault(&dir);
+        let id = add(
+            &mut manager,
+            NewCredential {
+                title: "Team wiki".to_string(),
+                credential_type: "login".to_string(),
+                password: Some("s3cret".to_string()),
+                ..NewCredential::default()
+            },
+        )
+        .unwrap();
+        let (secret, public_key) = keygen();
+        add_member(&mut manager, "alice", &public_key).unwrap();
+        assert!(add_member(&mut manager, "bob", "not-a-key").is_err());
+        assert_eq!(
+            set_access(&mut manager, "alice", &id, true).unwrap(),
+            "Team wiki"
+        );
+        let missing = set_access(&mut manager, "bob", &id, true).unwrap_err();
+        assert_eq!(ExitStatus::of(&missing), ExitStatus::NotFound);
+        assert!(!manager.is_modified());
+        let listed = output(|out| list_members(&manager, out));
+        assert_eq!(listed, format!("alice\t{}\t1\n", public_key));
+
+        let shared = dir.path().join("shared");
+        let written = export_members(&manager, &shared).unwrap();
+        assert_eq!(written, [shared.join("alice.zlvault")]);
+
+        let other = tempfile::tempdir().unwrap();
+        let mut target = vault(&other);
+        let data = std::fs::read(&written[0]).unwrap();
+        assert!(import(&mut target, &data, "alice.zlvault", None, false, None).is_err());
+        let identity: ShareIdentity = secret.parse().unwrap();
+        let report = import(
+            &mut target,
+            &data,
+            "alice.zlvault",
+            None,
+            false,
+            Some(&identity),
+        )
+        .unwrap();
+        assert_eq!(report.imported_count(), 1);
+        assert_eq!(find(&target, &id).unwrap().title, "Team wiki");
+
+        set_access(&mut manager, "alice", &id, false).unwrap();
+        remove_member(&mut manager, "alice").unwrap();
+        assert!(manager.list_members().unwrap().is_empty());
+        assert!(export_members(&manager, &shared).is_err());
+    }
+
+    #[test]
+    fn test_reminders() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut manager = vault(&dir);
+        let id = add(
+            &mut manager,
+            NewCredential {
+                title: "Passport".to_string(),
+                credential_type: "identity".to_string(),
+                expires: Some("2020-06-30".to_string()),
+                ..NewCredential::default()
+            },
+        )
+        .unwrap();
+        add(
+            &mut manager,
+            NewCredential {
+                title: "Router".to_string(),
+                credential_type: "login".to_string(),
+                password: Some("hunter2".to_string()),
+                rotate_every: Some(3650),
+                ..NewCredential::default()
+            },
+        )
+        .unwrap();
+
+        let listed = output(|out| reminders(&manager, 30, out));
+        assert_eq!(listed.lines().count(), 1);
+        assert!(listed.starts_with(&format!("{}\t2020-06-30\tPassport\tExpires ", id)));
+        assert!(listed.ends_with(" days ago\n"));
+
+        let bad = NewCredential {
+            title: "Licence".to_string(),
+            credential_type: "identity".to_string(),
+            expires: Some("30/06/2020".to_string()),
+            ..NewCredential::default()
+        };
+        assert!(add(&mut manager, bad).is_err());
+    }
+
+    #[test]
+    fn test_export_and_import_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut source = vault(&dir);
+        add(
+            &mut source,
+            NewCredential {
+                title: "Mail".to_string(),
+                credential_type: "login".to_string(),
+                password: Some("hunter2".to_string()),
+                ..NewCredential::default()
+            },
+        )
+        .unwrap();
+        let exported = export(&source, "json", &[], None).unwrap();
+        assert!(export(&source, "xml", &[], None).is_err());
+        assert!(export(&source, "html", &[], None).is_err());
+        assert!(export(&source, "json", &["Nothing".to_string()], None).is_err());
+
+        let other = tempfile::tempdir().unwrap();
+        let mut target = vault(&other);
+        let report = import(&mut target, &exported, "export.json", None, true, None).unwrap();
+        assert_eq!(report.imported_count(), 1);
+        assert_eq!(list(&target).unwrap()[0].title, "Mail");
+    }
+
+    #[test]
+    fn test_generate() {
+        let password = generate(&Generate::Password {
+            length: 24,
+            symbols: false,
+        })
+        .unwrap();
+        assert_eq!(password.len(), 24);
+        assert!(password.chars().all(char::is_alphanumeric));
+        assert!(generate(&Generate::Password {
+            length: 0,
+            symbols: true
+        })
+        .is_err());
+        let passphrase = generate(&Generate::Passphrase { words: 5 }).unwrap();
+        assert!(passphrase.split_whitespace().count() <= 5);
+    }
+}
@@ -0,0 +1,91 @@
+//! Exit codes scripts can branch on
+//!
+//! 0 is success and 2 is a usage error reported by clap. Everything else a
+//! script may want to tell apart has its own code; any other failure exits
+//! with 1. With `--json` the error is also written to stderr as a
+//! `ziplock.error.v1` document carrying the same code name.
+
+use std::fmt;
+
+/// Why `ziplock-cli` exited unsuccessfully
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitStatus {
+    Failure,
+    WrongPassword,
+    /// The vault or the credential asked for does not exist
+    NotFound,
+    /// An import finished but skipped some entries
+    PartialImport,
+    /// `audit` found problems
+    IssuesFound,
+}
+
+impl ExitStatus {
+    pub fn code(self) -> i32 {
+        match self {
+            Self::Failure => 1,
+            Self::WrongPassword => 3,
+            Self::NotFound => 4,
+            Self::PartialImport => 5,
+            Self::IssuesFound => 6,
+        }
+    }
+
+    /// Code name in JSON error output
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Failure => "failure",
+            Self::WrongPassword => "wrong_password",
+            Self::NotFound => "not_found",
+            Self::PartialImport => "partial_import",
+            Self::IssuesFound => "issues_found",
+        }
+    }
+
+    /// The status of an error returned by a command
+    pub fn of(error: &anyhow::Error) -> Self {
+        error
+            .downcast_ref::<Failed>()
+            .map_or(Self::Failure, |failed| failed.status)
+    }
+}
+
+/// An error with a specific exit status
+#[derive(Debug)]
+pub struct Failed {
+    status: ExitStatus,
+    message: String,
+}
+
+impl fmt::Display for Failed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for Failed {}
+
+/// Fail with `status` instead of the generic exit code 1
+pub fn fail(status: ExitStatus, message: impl Into<String>) -> anyhow::Error {
+    Failed {
+        status,
+        message: message.into(),
+    }
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_survives_context() {
+        let error = fail(ExitStatus::NotFound, "gone").context("Cannot show");
+        assert_eq!(ExitStatus::of(&error), ExitStatus::NotFound);
+        assert_eq!(
+            ExitStatus::of(&anyhow::anyhow!("other")),
+            ExitStatus::Failure
+        );
+        assert_eq!(format!("{:#}", error), "Cannot show: gone");
+    }
+}
@@ -0,0 +1,782 @@
+//! ZipLock command-line interface
+//!
+//! Manages a vault without a GUI, for scripts and servers: create a vault,
+//! add, show, list and search credentials, generate passwords and TOTP
+//! codes, audit it, and export or import the whole vault. The master
+//! password is prompted for, read from stdin with `--password-stdin`, or
+//! taken from `ZIPLOCK_PASSWORD`. `lock` and `lock-status` talk to the
+//! running desktop app instead and need neither. `--json` output and the
+//! exit codes in [`exit`] are meant for scripts.
+
+mod commands;
+mod exit;
+mod password;
+
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Context, Result};
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use ziplock_shared::core::{CoreError, FileError};
+use ziplock_shared::utils::{
+    ErrorDocument, GpgCommand, LockCommand, PgpExportParts, PgpKeyExporter, ShareIdentity,
+};
+use ziplock_shared::{ConfigManager, ConfigPaths, DesktopFileProvider, UnifiedRepositoryManager};
+
+use commands::{Generate, NewCredential, NewRecurring, EXPORT_FORMATS, IMPORT_SOURCES};
+use exit::{fail, ExitStatus};
+use password::PasswordSource;
+
+/// Environment variable naming the vault when `--vault` is not given
+const VAULT_ENV_VAR: &str = "ZIPLOCK_VAULT";
+
+fn cli() -> Command {
+    let json = || {
+        Arg::new("json")
+            .long("json")
+            .help("Print JSON instead of text")
+            .action(ArgAction::SetTrue)
+    };
+    let credential = || {
+        Arg::new("credential")
+            .value_name("ID_OR_TITLE")
+            .required(true)
+            .help("Credential ID, or its title if no other credential has it")
+    };
+
+    Command::new("ziplock-cli")
+        .version(env!("CARGO_PKG_VERSION"))
+        .about("Manage ZipLock vaults from the command line")
+        .subcommand_required(true)
+        .arg(
+            Arg::new("vault")
+                .long("vault")
+                .short('v')
+                .value_name("ARCHIVE")
+                .global(true)
+                .help("Path to the .7z archive (default: $ZIPLOCK_VAULT, then the most recently used repository)"),
+        )
+        .arg(
+            Arg::new("password-stdin")
+                .long("password-stdin")
+                .global(true)
+                .help("Read the master password from the first line of stdin instead of $ZIPLOCK_PASSWORD or a prompt")
+                .action(ArgAction::SetTrue),
+        )
+        .subcommand(Command::new("init").about("Create a new, empty vault"))
+        .subcommand(
+            Command::new("add")
+                .about("Add a credential and print its ID")
+                .arg(Arg::new("title").required(true))
+                .arg(
+                    Arg::new("type")
+                        .long("type")
+                        .default_value("login")
+                        .help("Credential type, e.g. login, note, credit_card"),
+                )
+                .arg(Arg::new("username").long("username").short('u'))
+                .arg(
+                    Arg::new("password")
+                        .long("password")
+                        .short('p')
+                        .conflicts_with("generate")
+                        .help("Password; visible to other users in the process list, prefer --generate"),
+                )
+                .arg(
+                    Arg::new("generate")
+                        .long("generate")
+                        .short('g')
+                        .value_name("LENGTH")
+                        .num_args(0..=1)
+                        .default_missing_value("20")
+                        .value_parser(clap::value_parser!(usize))
+                        .help("Generate a random password of LENGTH characters (default 20)"),
+                )
+                .arg(Arg::new("url").long("url"))
+                .arg(
+                    Arg::new("totp")
+                        .long("totp")
+                        .value_name("SECRET")
+                        .help("TOTP secret or otpauth:// URI"),
+                )
+                .arg(Arg::new("notes").long("notes"))
+                .arg(
+                    Arg::new("tag")
+                        .long("tag")
+                        .short('t')
+                        .action(ArgAction::Append)
+                        .help("Tag; repeat for several"),
+                )
+                .arg(
+                    Arg::new("field")
+                        .long("field")
+                        .short('f')
+                        .value_name("NAME=VALUE")
+                        .action(ArgAction::Append)
+                        .help("Extra text field; repeat for several"),
+                )
+                .arg(
+                    Arg::new("expires")
+                        .long("expires")
+                        .value_name("YYYY-MM-DD")
+                        .help("Date the credential expires, for reminders"),
+                )
+                .arg(
+                    Arg::new("rotate-every")
+                        .long("rotate-every")
+                        .value_name("DAYS")
+                        .value_parser(clap::value_parser!(u32))
+                        .help("Remind to change the password this many days after each change"),
+                ),
+        )
+        .subcommand(
+            Command::new("show")
+                .about("Print a credential")
+                .arg(credential())
+                .arg(
+                    Arg::new("reveal")
+                        .long("reveal")
+                        .help("Print sensitive values instead of masking them")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("field")
+                        .long("field")
+                        .short('f')
+                        .value_name("NAME")
+                        .help("Print only the value of this field, e.g. password"),
+                )
+                .arg(json()),
+        )
+        .subcommand(
+            Command::new("history")
+                .about("List earlier passwords of a credential, or put one back")
+                .arg(credential())
+                .arg(
+                    Arg::new("reveal")
+                        .long("reveal")
+                        .help("Print the earlier values instead of masking them")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("restore")
+                        .long("restore")
+                        .value_name("REPLACED_AT")
+                        .value_parser(clap::value_parser!(i64))
+                        .help("Restore the value replaced at this timestamp, as listed"),
+                )
+                .arg(
+                    Arg::new("field")
+                        .long("field")
+                        .short('f')
+                        .value_name("NAME")
+                        .default_value("password")
+                        .help("Field to restore"),
+                ),
+        )
+        .subcommand(
+            Command::new("recurring")
+                .about("Credentials created from a template on a schedule, as drafts tagged 'inbox'")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("add")
+                        .about("Create a draft every interval; prints the definition's ID")
+                        .arg(
+                            Arg::new("title")
+                                .required(true)
+                                .help("Title of each draft; {date}, {month} and {year} are filled in"),
+                        )
+                        .arg(
+                            Arg::new("template")
+                                .long("template")
+                                .short('t')
+                                .default_value("login")
+                                .help("Template drafts are made from"),
+                        )
+                        .arg(
+                            Arg::new("every")
+                                .long("every")
+                                .value_name("INTERVAL")
+                                .required(true)
+                                .help("How often, e.g. 30d, 2w or 1m"),
+                        )
+                        .arg(
+                            Arg::new("first")
+                                .long("first")
+                                .value_name("DATE")
+                                .help("Day the first draft is due, as YYYY-MM-DD (default: today)"),
+                        )
+                        .arg(Arg::new("folder").long("folder").value_name("PATH"))
+                        .arg(
+                            Arg::new("tag")
+                                .long("tag")
+                                .action(ArgAction::Append)
+                                .help("Tag each draft also gets; may be repeated"),
+                        )
+                        .arg(
+                            Arg::new("carry-over")
+                                .long("carry-over")
+                                .help("Copy the non-sensitive values of the previous draft")
+                                .action(ArgAction::SetTrue),
+                        ),
+                )
+                .subcommand(
+                    Command::new("list")
+                        .about("List definitions as ID, next due date, interval and title"),
+                )
+                .subcommand(
+                    Command::new("remove")
+                        .about("Stop creating a recurring credential")
+                        .arg(Arg::new("id").required(true)),
+                )
+                .subcommand(
+                    Command::new("run")
+                        .about("Create the drafts that are due and print their IDs and titles"),
+                ),
+        )
+        .subcommand(
+            Command::new("members")
+                .about("People the vault is shared with, and which credentials each may see")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("add")
+                        .about("Add a member, or replace their key")
+                        .arg(Arg::new("name").required(true))
+                        .arg(
+                            Arg::new("public-key")
+                                .required(true)
+                                .help("The member's public key from 'ziplock-cli keygen' (zlpk1...)"),
+                        ),
+                )
+                .subcommand(
+                    Command::new("list")
+                        .about("List members as name, public key and number of shared credentials"),
+                )
+                .subcommand(
+                    Command::new("remove")
+                        .about("Remove a member and stop sharing everything with them")
+                        .arg(Arg::new("name").required(true)),
+                )
+                .subcommand(
+                    Command::new("grant")
+                        .about("Share a credential with a member")
+                        .arg(Arg::new("name").required(true))
+                        .arg(credential()),
+                )
+                .subcommand(
+                    Command::new("revoke")
+                        .about("Stop sharing a credential with a member")
+                        .arg(Arg::new("name").required(true))
+                        .arg(credential()),
+                )
+                .subcommand(
+                    Command::new("export")
+                        .about("Write NAME.zlvault for each member, encrypted to their key, e.g. into a synced folder")
+                        .arg(Arg::new("folder").required(true)),
+                ),
+        )
+        .subcommand(
+            Command::new("keygen")
+                .about("Create a sharing key pair; prints the secret key and writes the public key to stderr"),
+        )
+        .subcommand(
+            Command::new("reminders")
+                .about("List expirations and password changes that are due, soonest first")
+                .arg(
+                    Arg::new("within")
+                        .long("within")
+                        .value_name("DAYS")
+                        .default_value("30")
+                        .value_parser(clap::value_parser!(u32))
+                        .help("Also list what falls due within this many days"),
+                ),
+        )
+        .subcommand(
+            Command::new("list")
+                .about("List credentials as ID, title and username")
+                .arg(json()),
+        )
+        .subcommand(
+            Command::new("search")
+                .about("Search credentials, e.g. 'tag:bank type:login gmail'")
+                .arg(Arg::new("query").required(true))
+                .arg(json()),
+        )
+        .subcommand(
+            Command::new("audit")
+                .about("List weak, reused and old passwords and other problems; exits with 6 if any are found")
+                .arg(json()),
+        )
+        .subcommand(
+            Command::new("generate")
+                .about("Print a random password; does not open a vault")
+                .arg(
+                    Arg::new("length")
+                        .long("length")
+                        .short('l')
+                        .default_value("20")
+                        .value_parser(clap::value_parser!(usize)),
+                )
+                .arg(
+                    Arg::new("no-symbols")
+                        .long("no-symbols")
+                        .help("Use letters and digits only")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("words")
+                        .long("words")
+                        .short('w')
+                        .value_parser(clap::value_parser!(usize))
+                        .conflicts_with_all(["length", "no-symbols"])
+                        .help("Generate a passphrase of this many words instead"),
+                ),
+        )
+        .subcommand(
+            Command::new("totp")
+                .about("Print the current TOTP code of a credential")
+                .arg(credential()),
+        )
+        .subcommand(
+            Command::new("export")
+                .about("Write credentials to a file or stdout; unencrypted except as an html viewer")
+                .arg(
+                    Arg::new("format")
+                        .long("format")
+                        .default_value("json")
+                        .value_parser(EXPORT_FORMATS)
+                        .help("html writes a page that opens in any browser with a passphrase, asked for or taken from $ZIPLOCK_VIEWER_PASSPHRASE"),
+                )
+                .arg(
+                    Arg::new("only")
+                        .long("only")
+                        .value_name("CREDENTIAL")
+                        .action(ArgAction::Append)
+                        .help("Export only this credential, by ID or title; repeat for several"),
+                )
+                .arg(
+                    Arg::new("output")
+                        .long("output")
+                        .short('o')
+                        .value_name("FILE")
+                        .help("File to write (default: stdout)"),
+                ),
+        )
+        .subcommand(
+            Command::new("pgp-export")
+                .about("Write a stored PGP key, ASCII-armored, to a file or stdout, or import it into gpg")
+                .arg(credential())
+                .arg(
+                    Arg::new("output")
+                        .long("output")
+                        .short('o')
+                        .value_name("FILE")
+                        .help("File to write, readable by you only (default: stdout)"),
+                )
+                .arg(
+                    Arg::new("gpg")
+                        .long("gpg")
+                        .help("Import the key into your gpg keyring instead")
+                        .conflicts_with("output")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("revocation")
+                        .long("revocation")
+                        .help("Export only the revocation certificate; importing it revokes the key")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("import")
+                .about("Import a ZipLock export, or CSV or exports from other password managers")
+                .arg(Arg::new("file").required(true))
+                .arg(
+                    Arg::new("from")
+                        .long("from")
+                        .value_parser(IMPORT_SOURCES)
+                        .help("Password manager that wrote the file, if it is not a ZipLock export or CSV"),
+                )
+                .arg(
+                    Arg::new("assign-new-ids")
+                        .long("assign-new-ids")
+                        .help("Import every credential as new instead of updating ones with the same ID")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("identity")
+                        .long("identity")
+                        .value_name("FILE")
+                        .help("File holding the secret key a .zlvault package is encrypted to"),
+                ),
+        )
+        .subcommand(
+            Command::new("lock")
+                .about("Lock the running app at once, e.g. from a screen locker hook, and print its state"),
+        )
+        .subcommand(
+            Command::new("lock-status")
+                .about("Print whether the running app is locked or unlocked"),
+        )
+}
+
+fn main() {
+    let matches = cli().get_matches();
+    let json = matches
+        .subcommand()
+        .and_then(|(_, args)| args.try_get_one::<bool>("json").ok().flatten().copied())
+        .unwrap_or(false);
+
+    if let Err(e) = run(matches) {
+        // The reader went away, e.g. `ziplock-cli list | head`
+        let broken_pipe = e
+            .downcast_ref::<std::io::Error>()
+            .is_some_and(|e| e.kind() == std::io::ErrorKind::BrokenPipe);
+        if broken_pipe {
+            return;
+        }
+        let status = ExitStatus::of(&e);
+        if json {
+            let document = ErrorDocument::new(status.name(), format!("{:#}", e));
+            eprintln!("{}", serde_json::to_string(&document).unwrap_or_default());
+        } else {
+            eprintln!("Error: {:#}", e);
+        }
+        std::process::exit(status.code());
+    }
+}
+
+fn run(matches: ArgMatches) -> Result<()> {
+    let (command, args) = matches.subcommand().expect("a subcommand is required");
+    let mut out = std::io::stdout().lock();
+
+    if command == "generate" {
+        let options = match args.get_one::<usize>("words") {
+            Some(&words) => Generate::Passphrase { words },
+            None => Generate::Password {
+                length: *args.get_one::<usize>("length").expect("has a default"),
+                symbols: !args.get_flag("no-symbols"),
+            },
+        };
+        writeln!(out, "{}", commands::generate(&options)?)?;
+        return Ok(());
+    }
+    if command == "lock" || command == "lock-status" {
+        let lock = if command == "lock" {
+            LockCommand::Lock
+        } else {
+            LockCommand::Status
+        };
+        writeln!(out, "{}", commands::send_lock_command(lock)?.state.name())?;
+        return Ok(());
+    }
+    if command == "keygen" {
+        let (secret, public_key) = commands::keygen();
+        eprintln!("Public key: {}", public_key);
+        writeln!(out, "{}", secret)?;
+        return Ok(());
+    }
+
+    let vault = vault_path(&matches)?;
+    let source = PasswordSource::choose(matches.get_flag("password-stdin"));
+    let mut manager = UnifiedRepositoryManager::new(DesktopFileProvider::new());
+
+    if command == "init" {
+        if Path::new(&vault).exists() {
+            bail!("{} already exists", vault);
+        }
+        let password = source.read(true)?;
+        manager
+            .create_repository(&vault, &password)
+            .with_context(|| format!("Cannot create {}", vault))?;
+        writeln!(out, "Created {}", vault)?;
+        return Ok(());
+    }
+
+    let password = source.read(false)?;
+    manager
+        .open_repository(&vault, &password)
+        .map_err(|e| match e {
+            CoreError::FileOperation(FileError::InvalidPassword) => {
+                fail(ExitStatus::WrongPassword, "Incorrect master password")
+            }
+            CoreError::FileOperation(FileError::NotFound { .. }) => {
+                fail(ExitStatus::NotFound, format!("Vault not found: {}", vault))
+            }
+            other => anyhow!("Cannot open {}: {}", vault, other),
+        })?;
+    drop(password);
+
+    let result = run_on_vault(&mut manager, command, args, &mut out);
+    // Commands save what they change; nothing is left to write here
+    let _ = manager.close_repository(false);
+    result
+}
+
+fn run_on_vault(
+    manager: &mut UnifiedRepositoryManager<DesktopFileProvider>,
+    command: &str,
+    args: &ArgMatches,
+    out: &mut impl Write,
+) -> Result<()> {
+    let text = |name: &str| args.get_one::<String>(name).cloned();
+    let texts = |name: &str| {
+        args.get_many::<String>(name)
+            .map(|values| values.cloned().collect())
+            .unwrap_or_default()
+    };
+
+    match command {
+        "add" => {
+            let password = match args.get_one::<usize>("generate") {
+                Some(&length) => Some(commands::generate(&Generate::Password {
+                    length,
+                    symbols: true,
+                })?),
+                None => text("password"),
+            };
+            let id = commands::add(
+                manager,
+                NewCredential {
+                    title: text("title").expect("required"),
+                    credential_type: text("type").expect("has a default"),
+                    username: text("username"),
+                    password,
+                    url: text("url"),
+                    totp_secret: text("totp"),
+                    notes: text("notes"),
+                    tags: texts("tag"),
+                    fields: texts("field"),
+                    expires: text("expires"),
+                    rotate_every: args.get_one::<u32>("rotate-every").copied(),
+                },
+            )?;
+            writeln!(out, "{}", id)?;
+        }
+        "show" => commands::show(
+            manager,
+            &text("credential").expect("required"),
+            args.get_one::<String>("field").map(String::as_str),
+            args.get_flag("reveal"),
+            args.get_flag("json"),
+            out,
+        )?,
+        "history" => {
+            let query = text("credential").expect("required");
+            match args.get_one::<i64>("restore") {
+                Some(&replaced_at) => {
+                    let field = text("field").expect("has a default");
+                    commands::restore(manager, &query, &field, replaced_at)?;
+                    writeln!(out, "Restored {}", field)?;
+                }
+                None => commands::history(manager, &query, args.get_flag("reveal"), out)?,
+            }
+        }
+        "recurring" => {
+            let (action, args) = args.subcommand().expect("a subcommand is required");
+            let text = |name: &str| args.get_one::<String>(name).cloned();
+            match action {
+                "add" => {
+                    let id = commands::add_recurring(
+                        manager,
+                        NewRecurring {
+                            title: text("title").expect("required"),
+                            template: text("template").expect("has a default"),
+                            every: text("every").expect("required"),
+                            first: text("first"),
+                            folder: text("folder"),
+                            tags: args
+                                .get_many::<String>("tag")
+                                .map(|values| values.cloned().collect())
+                                .unwrap_or_default(),
+                            carry_over: args.get_flag("carry-over"),
+                        },
+                    )?;
+                    writeln!(out, "{}", id)?;
+                }
+                "list" => commands::list_recurring(manager, out)?,
+                "remove" => {
+                    let removed =
+                        commands::remove_recurring(manager, &text("id").expect("required"))?;
+                    writeln!(out, "Removed {}", removed.title)?;
+                }
+                "run" => {
+                    for draft in commands::run_recurring(manager)? {
+                        writeln!(out, "{}\t{}", draft.credential_id, draft.title)?;
+                    }
+                }
+                other => unreachable!("unknown recurring subcommand {}", other),
+            }
+        }
+        "members" => {
+            let (action, args) = args.subcommand().expect("a subcommand is required");
+            let text = |name: &str| args.get_one::<String>(name).cloned().unwrap_or_default();
+            match action {
+                "add" => {
+                    commands::add_member(manager, &text("name"), &text("public-key"))?;
+                    writeln!(out, "Added {}", text("name"))?;
+                }
+                "list" => commands::list_members(manager, out)?,
+                "remove" => {
+                    let removed = commands::remove_member(manager, &text("name"))?;
+                    writeln!(out, "Removed {}", removed.name)?;
+                }
+                "grant" | "revoke" => {
+                    let shared = action == "grant";
+                    let title =
+                        commands::set_access(manager, &text("name"), &text("credential"), shared)?;
+                    let verb = if shared { "Shared" } else { "Stopped sharing" };
+                    writeln!(out, "{} {} with {}", verb, title, text("name"))?;
+                }
+                "export" => {
+                    for path in commands::export_members(manager, Path::new(&text("folder")))? {
+                        writeln!(out, "Wrote {}", path.display())?;
+                    }
+                }
+                other => unreachable!("unknown members subcommand {}", other),
+            }
+        }
+        "reminders" => commands::reminders(
+            manager,
+            *args.get_one::<u32>("within").expect("has a default"),
+            out,
+        )?,
+        "list" => commands::print_list(&commands::list(manager)?, args.get_flag("json"), out)?,
+        "search" => {
+            let query = text("query").expect("required");
+            let found = manager.search_credentials(&query)?;
+            commands::print_search(&query, &found, args.get_flag("json"), out)?;
+        }
+        "audit" => {
+            let report = commands::audit(manager)?;
+            let issues = report.issue_count();
+            commands::print_audit(report, args.get_flag("json"), out)?;
+            if issues > 0 {
+                return Err(fail(
+                    ExitStatus::IssuesFound,
+                    format!(
+                        "{} {} found",
+                        issues,
+                        if issues == 1 { "issue" } else { "issues" }
+                    ),
+                ));
+            }
+        }
+        "totp" => {
+            let (code, remaining) =
+                commands::totp(manager, &text("credential").expect("required"))?;
+            writeln!(out, "{}", code)?;
+            eprintln!("Valid for {} more seconds", remaining);
+        }
+        "export" => {
+            let format = text("format").expect("has a default");
+            let passphrase = if format == "html" {
+                Some(password::read_viewer_passphrase()?)
+            } else {
+                None
+            };
+            let data = commands::export(manager, &format, &texts("only"), passphrase)?;
+            match text("output") {
+                Some(path) => {
+                    std::fs::write(&path, data).with_context(|| format!("Cannot write {}", path))?
+                }
+                None => out.write_all(&data)?,
+            }
+        }
+        "pgp-export" => {
+            let parts = if args.get_flag("revocation") {
+                PgpExportParts::revocation_only()
+            } else {
+                PgpExportParts::default()
+            };
+            let credential = commands::find(manager, &text("credential").expect("required"))?;
+            if args.get_flag("gpg") {
+                let report =
+                    PgpKeyExporter::import_into_gpg(credential, parts, &GpgCommand::new())?;
+                eprintln!("{}", report);
+            } else if let Some(path) = text("output") {
+                PgpKeyExporter::write_file(credential, parts, Path::new(&path))?;
+                writeln!(out, "Wrote {}", path)?;
+            } else {
+                out.write_all(PgpKeyExporter::armor(credential, parts)?.as_bytes())?;
+            }
+        }
+        "import" => {
+            let file = text("file").expect("required");
+            let data = std::fs::read(&file).with_context(|| format!("Cannot read {}", file))?;
+            let identity = match text("identity") {
+                Some(path) => Some(
+                    std::fs::read_to_string(&path)
+                        .with_context(|| format!("Cannot read {}", path))?
+                        .parse::<ShareIdentity>()
+                        .with_context(|| format!("{} does not hold a secret key", path))?,
+                ),
+                None => None,
+            };
+            let report = commands::import(
+                manager,
+                &data,
+                &file,
+                args.get_one::<String>("from").map(String::as_str),
+                args.get_flag("assign-new-ids"),
+                identity.as_ref(),
+            )?;
+            for error in &report.errors {
+                eprintln!(
+                    "{}: {}",
+                    error.title.as_deref().unwrap_or("(untitled)"),
+                    error.message
+                );
+            }
+            for duplicate in &report.duplicates {
+                eprintln!(
+                    "{}: already in the repository, not imported",
+                    duplicate.credential.title
+                );
+            }
+            writeln!(
+                out,
+                "Added {}, updated {}, failed {}",
+                report.imported_count(),
+                report.updated.len(),
+                report.errors.len()
+            )?;
+            if !report.duplicates.is_empty() || report.skipped_rows > 0 {
+                writeln!(
+                    out,
+                    "Skipped {} duplicates and {} other entries",
+                    report.duplicates.len(),
+                    report.skipped_rows
+                )?;
+            }
+            if report.has_errors() {
+                return Err(fail(
+                    ExitStatus::PartialImport,
+                    "Some entries could not be imported",
+                ));
+            }
+        }
+        other => unreachable!("unknown subcommand {}", other),
+    }
+    Ok(())
+}
+
+/// The vault to work on: `--vault`, `$ZIPLOCK_VAULT`, or the most recently
+/// used repository
+fn vault_path(matches: &ArgMatches) -> Result<String> {
+    if let Some(path) = matches.get_one::<String>("vault") {
+        return Ok(path.clone());
+    }
+    if let Some(path) = std::env::var(VAULT_ENV_VAR).ok().filter(|p| !p.is_empty()) {
+        return Ok(path);
+    }
+
+    let mut config = ConfigManager::new(DesktopFileProvider::new(), ConfigPaths::app_config_file());
+    // A missing or broken configuration just means there is no recent vault
+    let _ = config.load();
+    config
+        .get_recent_repositories()
+        .first()
+        .map(|repo| repo.path.clone())
+        .ok_or_else(|| anyhow!("No vault given; use --vault or set {}", VAULT_ENV_VAR))
+}
@@ -0,0 +1,97 @@
+//! Where the master password comes from
+//!
+//! Interactive use prompts on the terminal without echo. Scripts either pipe
+//! the password in with `--password-stdin` or set `ZIPLOCK_PASSWORD`; the
+//! flag wins over the variable so a stray environment cannot override an
+//! explicit choice.
+
+use std::io::BufRead;
+
+use anyhow::{anyhow, bail, Context, Result};
+
+/// Environment variable holding the master password for scripts
+pub const PASSWORD_ENV_VAR: &str = "ZIPLOCK_PASSWORD";
+
+/// Environment variable holding the passphrase for HTML viewer exports
+pub const VIEWER_PASSPHRASE_ENV_VAR: &str = "ZIPLOCK_VIEWER_PASSPHRASE";
+
+/// How to obtain the master password
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PasswordSource {
+    /// First line of stdin
+    Stdin,
+    /// [`PASSWORD_ENV_VAR`]
+    Env,
+    /// Ask on the terminal
+    Prompt,
+}
+
+impl PasswordSource {
+    /// The source to use given the `--password-stdin` flag
+    pub fn choose(password_stdin: bool) -> Self {
+        if password_stdin {
+            Self::Stdin
+        } else if std::env::var_os(PASSWORD_ENV_VAR).is_some() {
+            Self::Env
+        } else {
+            Self::Prompt
+        }
+    }
+
+    /// Read the password; `confirm` asks twice when prompting, for new vaults
+    pub fn read(self, confirm: bool) -> Result<String> {
+        let password = match self {
+            Self::Stdin => read_line(std::io::stdin().lock())?,
+            Self::Env => std::env::var(PASSWORD_ENV_VAR)
+                .with_context(|| format!("{} is not valid UTF-8", PASSWORD_ENV_VAR))?,
+            Self::Prompt => {
+                let password = rpassword::prompt_password("Master password: ")
+                    .context("Cannot read the master password from the terminal")?;
+                if confirm && rpassword::prompt_password("Repeat master password: ")? != password {
+                    bail!("Passwords do not match");
+                }
+                password
+            }
+        };
+        if password.is_empty() {
+            return Err(anyhow!("The master password is empty"));
+        }
+        Ok(password)
+    }
+}
+
+/// The passphrase for a new HTML viewer, from
+/// [`VIEWER_PASSPHRASE_ENV_VAR`] or asked for twice
+pub fn read_viewer_passphrase() -> Result<String> {
+    if let Ok(passphrase) = std::env::var(VIEWER_PASSPHRASE_ENV_VAR) {
+        return Ok(passphrase);
+    }
+    let passphrase = rpassword::prompt_password("Viewer passphrase: ")
+        .context("Cannot read the viewer passphrase from the terminal")?;
+    if rpassword::prompt_password("Repeat viewer passphrase: ")? != passphrase {
+        bail!("Passphrases do not match");
+    }
+    Ok(passphrase)
+}
+
+/// The first line of `reader` without its line ending
+fn read_line(mut reader: impl BufRead) -> Result<String> {
+    let mut line = String::new();
+    reader
+        .read_line(&mut line)
+        .context("Cannot read the master password from stdin")?;
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_line_strips_line_ending() {
+        assert_eq!(read_line(&b"s3cret\r\nrest"[..]).unwrap(), "s3cret");
+        assert_eq!(read_line(&b"  spaced  \n"[..]).unwrap(), "  spaced  ");
+        assert_eq!(read_line(&b""[..]).unwrap(), "");
+        assert_eq!(PasswordSource::choose(true), PasswordSource::Stdin);
+    }
+}
@@ -3,6 +3,9 @@
 //! This module provides a thin wrapper around the shared configuration
 //! management functionality, adding any Linux-specific extensions.
 
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
 use anyhow::Result;
 use tracing::{debug, info};
 
@@ -10,9 +13,48 @@ use tracing::{debug, info};
 use chrono::Utc;
 use ziplock_shared::config::{RepositorySettings, SortOrder, ViewMode};
 pub use ziplock_shared::{
-    AppConfig, ConfigManager as SharedConfigManager, DesktopFileProvider, RepositoryInfo,
+    AppConfig, ConfigManager as SharedConfigManager, ConfigPaths, DesktopFileProvider,
+    RepositoryInfo,
 };
 
+/// Environment variable naming the profile when `--profile` is not given
+pub const PROFILE_ENV_VAR: &str = "ZIPLOCK_PROFILE";
+
+static ACTIVE_PROFILE: OnceLock<Option<String>> = OnceLock::new();
+
+/// Choose the profile whose settings, recent repositories and theme this
+/// process uses
+///
+/// Call once at startup, before anything creates a [`ConfigManager`]; later
+/// calls are ignored. `None` and `"default"` select the settings kept
+/// directly in the config directory.
+pub fn set_active_profile(profile: Option<String>) -> Result<()> {
+    let profile = profile.filter(|name| name != ConfigPaths::DEFAULT_PROFILE);
+    if let Some(name) = &profile {
+        if !ConfigPaths::is_valid_profile_name(name) {
+            anyhow::bail!(
+                "Invalid profile name '{}': use up to 64 letters, digits, '-' or '_'",
+                name
+            );
+        }
+    }
+    if ACTIVE_PROFILE.set(profile).is_err() {
+        debug!("Active profile was already chosen");
+    }
+    Ok(())
+}
+
+/// The profile chosen with [`set_active_profile`], `None` for the default
+pub fn active_profile() -> Option<&'static str> {
+    ACTIVE_PROFILE.get().and_then(|profile| profile.as_deref())
+}
+
+/// Directory holding the encrypted per-vault state of the active profile,
+/// such as where the main view was when the vault locked
+pub fn secure_store_dir() -> PathBuf {
+    PathBuf::from(ConfigPaths::profile_config_dir(active_profile())).join("state")
+}
+
 /// Linux-specific configuration manager
 ///
 /// This wraps the shared ConfigManager and adds any Linux-specific functionality
@@ -26,14 +68,13 @@ impl ConfigManager {
         debug!("Creating Linux app configuration manager");
 
         let file_provider = DesktopFileProvider::new();
-        let config_path = dirs::config_dir()
-            .unwrap_or_else(|| dirs::home_dir().unwrap_or_default().join(".config"))
-            .join("ziplock")
-            .join("config.yml")
-            .to_string_lossy()
-            .to_string();
+        let config_path = ConfigPaths::profile_config_file(active_profile());
 
-        debug!("Config file path: {}", config_path);
+        debug!(
+            "Config file path for profile '{}': {}",
+            active_profile().unwrap_or(ConfigPaths::DEFAULT_PROFILE),
+            config_path
+        );
 
         let shared_manager = SharedConfigManager::new(file_provider, config_path);
         info!("Configuration manager initialized successfully");
@@ -96,6 +137,20 @@ impl ConfigManager {
         self.shared_manager.touch_repository(path);
     }
 
+    /// Refuse to unlock `path` while failed attempts throttle it
+    pub fn check_unlock(&self, path: &str) -> Result<()> {
+        self.shared_manager
+            .check_unlock(path)
+            .map_err(anyhow::Error::from)
+    }
+
+    /// Record whether unlocking `path` got the password right, and save
+    pub fn record_unlock_attempt(&mut self, path: &str, succeeded: bool) -> Result<()> {
+        self.shared_manager
+            .record_unlock_attempt(path, succeeded)
+            .map_err(|e| anyhow::anyhow!("Failed to save unlock attempts: {}", e))
+    }
+
     /// Get recent repositories sorted by last accessed (most recent first)
     pub fn get_recent_repositories(&self) -> Vec<&RepositoryInfo> {
         self.shared_manager.get_recent_repositories()
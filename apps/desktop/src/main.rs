@@ -7,6 +7,7 @@
 #![cfg_attr(target_os = "windows", windows_subsystem = "windows")]
 
 use clap::{Arg, Command};
+use iced::futures::SinkExt;
 use iced::{
     widget::{button, svg, text},
     Element, Task, Theme,
@@ -27,17 +28,17 @@ use ui::components::button::{destructive_button, primary_button, secondary_butto
 use services::{ClipboardManager, UpdateChecker};
 
 use ui::components::toast::{ToastManager, ToastPosition};
-use ui::components::{UpdateDialog, UpdateDialogMessage};
+use ui::components::{UpdateDialog, UpdateDialogMessage, WhatsNewDialog, WhatsNewMessage};
 use ui::theme::alerts::AlertMessage;
 
 use ui::{create_ziplock_theme, theme};
 
-use config::{ConfigManager, RepositoryInfo};
+use config::{ConfigManager, ConfigPaths, RepositoryInfo};
 use ui::views::main::{MainView, MainViewMessage};
 use ui::views::{
     AddCredentialMessage, AddCredentialView, EditCredentialMessage, EditCredentialView,
-    OpenRepositoryMessage, OpenRepositoryView, RepositoryWizard, SettingsMessage, SettingsView,
-    WizardMessage,
+    OpenRepositoryMessage, OpenRepositoryView, RepositoryWizard, SettingsMessage, SettingsTab,
+    SettingsView, WizardMessage,
 };
 
 /// Utility function to detect if running in production mode
@@ -111,7 +112,12 @@ pub enum Message {
     // Settings messages
     Settings(SettingsMessage),
     ShowSettings,
+    ShowSettingsTab(SettingsTab),
     HideSettings,
+    MasterPasswordChangeProgress(ziplock_shared::core::PasswordChangeStage),
+    MasterPasswordChanged(Result<Vec<String>, String>),
+    SecurityKeyRegistered(Result<(), String>),
+    OsKeychainUpdated(Result<bool, String>),
 
     // Alert management
     ShowAlert(AlertMessage),
@@ -127,6 +133,11 @@ pub enum Message {
 
     // Session management
     SessionTimeout,
+    SessionExpired(ziplock_shared::core::SessionExpiry),
+
+    // Scripting API reads waiting for the user's approval
+    ApprovalRequested(ziplock_shared::utils::ApprovalRequest),
+    ApprovalDecided(ziplock_shared::utils::ApprovalDecision),
 
     // Auto-lock management
     AutoLockTimerTick,
@@ -138,6 +149,8 @@ pub enum Message {
     ShowUpdateDialog(services::UpdateCheckResult),
     HideUpdateDialog,
     AutoUpdateCheck,
+    ShowWhatsNew(Vec<services::Release>),
+    WhatsNew(WhatsNewMessage),
 
     // Clipboard management
     CopyToClipboard {
@@ -145,6 +158,24 @@ pub enum Message {
         content_type: services::ClipboardContentType,
     },
 
+    // Off-site backups
+    OffsiteBackupTick,
+    SystemSleep(services::SleepEvent),
+    LockRequested(services::LockOutcome),
+    // A stored SSH key the agent could not load
+    SshKeyUnusable(services::UnusableSshKey),
+    Activated(services::ActivationRequest),
+    RunOffsiteBackup,
+    OffsiteBackupCompleted(Result<ziplock_shared::utils::UploadOutcome, String>),
+
+    // Recurring credentials
+    CreateDueDrafts,
+    DraftsCreated(Result<Vec<ziplock_shared::models::RecurringDraft>, String>),
+
+    // Breach checking
+    RunBreachScan,
+    BreachScanCompleted(Result<Vec<String>, String>),
+
     // General
     Quit,
     QuittingWithLogout,
@@ -165,10 +196,22 @@ pub enum AppState {
     EditCredentialActive(EditCredentialView),
     SettingsActive(SettingsView),
     UpdateDialogActive(UpdateDialog),
+    WhatsNewActive(WhatsNewDialog),
     MainInterface(MainView),
     Error(String),
 }
 
+/// What the command line asked the window to do at startup
+#[derive(Debug, Clone, Default)]
+pub struct LaunchOptions {
+    /// Show the unlock dialog for this repository instead of the recent one
+    pub open_path: Option<std::path::PathBuf>,
+    /// Focus the search field as soon as the vault is unlocked
+    pub quick_search: bool,
+    /// Don't offer to unlock a repository until the user picks one
+    pub locked: bool,
+}
+
 /// Main application structure
 pub struct ZipLockApp {
     state: AppState,
@@ -184,10 +227,75 @@ pub struct ZipLockApp {
     update_checker: UpdateChecker,
     // Clipboard manager
     clipboard_manager: ClipboardManager,
+    // Whether an off-site backup upload is in progress
+    offsite_backup_running: bool,
+    // Scripting API reads waiting for the user, oldest first
+    pending_approvals: std::collections::VecDeque<ziplock_shared::utils::ApprovalRequest>,
+    // Startup requests from the command line, consumed once handled
+    launch: LaunchOptions,
 }
 
 impl ZipLockApp {
-    pub fn new() -> (Self, Task<Message>) {
+    /// Show the release notes of versions the user has not seen yet
+    fn whats_new(&self) -> Task<Message> {
+        let Some(config_manager) = &self.config_manager else {
+            return Task::none();
+        };
+        let releases = services::ReleaseNotes::bundled().unseen(
+            config_manager.config().last_seen_version.as_deref(),
+            env!("CARGO_PKG_VERSION"),
+        );
+        if releases.is_empty() {
+            return Task::none();
+        }
+        info!(
+            "Showing release notes for {} new version(s)",
+            releases.len()
+        );
+        Task::perform(async move { releases }, Message::ShowWhatsNew)
+    }
+
+    /// Raise a desktop notification for a security event, if enabled in settings
+    fn notify(&self, event: services::SecurityEvent) -> Task<Message> {
+        let config = self
+            .config_manager
+            .as_ref()
+            .map(|cm| cm.config().notifications.clone())
+            .unwrap_or_default();
+        Task::future(services::NotificationService::notify(config, event)).discard()
+    }
+
+    /// Store a key to the open repository in the OS keychain, or remove it
+    fn update_os_keychain(enabled: bool) -> Task<Message> {
+        Task::perform(
+            async move {
+                let service = services::get_repository_service();
+                if enabled {
+                    service.remember_in_os_keychain().await
+                } else {
+                    service.forget_os_keychain().await.map(|()| false)
+                }
+                .map_err(|e| e.to_string())
+            },
+            Message::OsKeychainUpdated,
+        )
+    }
+
+    /// Auto-lock policy from settings; the desktop idle timeout is in minutes
+    fn auto_lock_policy(&self) -> ziplock_shared::core::AutoLockPolicy {
+        self.config_manager
+            .as_ref()
+            .map(|cm| {
+                let config = cm.config();
+                ziplock_shared::core::AutoLockPolicy::from_config(
+                    &config.security,
+                    config.ui.auto_lock_timeout * 60,
+                )
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn new(launch: LaunchOptions) -> (Self, Task<Message>) {
         info!("Initializing ZipLock Linux app with unified architecture");
 
         // Initialize shared library
@@ -204,6 +312,9 @@ impl ZipLockApp {
             auto_lock_enabled: false,
             update_checker: UpdateChecker::new(),
             clipboard_manager: ClipboardManager::new(),
+            offsite_backup_running: false,
+            pending_approvals: std::collections::VecDeque::new(),
+            launch,
         };
 
         let load_config_task = Task::perform(Self::load_config_async(), Message::ConfigLoaded);
@@ -211,8 +322,9 @@ impl ZipLockApp {
         (app, load_config_task)
     }
 
+    /// Window title, naming the profile unless it is the default one
     pub fn title(&self) -> String {
-        match &self.state {
+        let title = match &self.state {
             AppState::Loading => "ZipLock - Loading...".to_string(),
             AppState::DetectingRepositories => "ZipLock - Detecting Repositories...".to_string(),
             AppState::RepositorySelection(_) => "ZipLock - Select Repository".to_string(),
@@ -224,8 +336,13 @@ impl ZipLockApp {
             AppState::EditCredentialActive(_) => "ZipLock - Edit Credential".to_string(),
             AppState::SettingsActive(_) => "ZipLock - Settings".to_string(),
             AppState::UpdateDialogActive(_) => "ZipLock - Update Available".to_string(),
+            AppState::WhatsNewActive(_) => "ZipLock - What's New".to_string(),
             AppState::MainInterface(_) => "ZipLock Password Manager".to_string(),
             AppState::Error(_) => "ZipLock - Error".to_string(),
+        };
+        match config::active_profile() {
+            Some(profile) => format!("{} ({})", title, profile),
+            None => title,
         }
     }
 
@@ -256,9 +373,36 @@ impl ZipLockApp {
                     ui::theme::utils::typography::init_font_size(font_scale);
                     info!("Font scaling initialized with scale factor: {}", font_scale);
 
+                    services::get_repository_service()
+                        .set_session_policy(config_manager.config().security.session);
+                    services::get_repository_service()
+                        .set_activity_logging(config_manager.config().security.activity_log);
+                    services::get_approval_broker()
+                        .set_policy(config_manager.config().security.api_approval.clone());
+
+                    // A repository named on the command line comes first
+                    if let Some(path) = self.launch.open_path.take() {
+                        if path.is_file() {
+                            info!("Opening repository from the command line: {:?}", path);
+                            self.state = AppState::OpenRepositoryActive(
+                                OpenRepositoryView::with_repository(path),
+                            );
+                            self.config_manager = Some(config_manager);
+                            return Task::none();
+                        }
+                        warn!("Repository from the command line not found: {:?}", path);
+                        self.toast_manager
+                            .warning(format!("Repository not found: {}", path.display()));
+                    }
+
                     // Check if we should show the wizard immediately
                     if config_manager.should_show_wizard() {
                         debug!("No repositories found, showing setup wizard");
+                        // A fresh install has nothing new to show
+                        config_manager
+                            .config_mut()
+                            .last_seen_version
+                            .get_or_insert_with(|| env!("CARGO_PKG_VERSION").to_string());
                         self.state = AppState::WizardRequired;
                         self.config_manager = Some(config_manager);
                         return Task::none();
@@ -272,7 +416,9 @@ impl ZipLockApp {
                         debug!("Recent repo: {} -> {}", repo.name, repo.path);
                     }
 
-                    if let Some(most_recent_path) =
+                    if self.launch.locked {
+                        debug!("Started locked, not opening the recent repository");
+                    } else if let Some(most_recent_path) =
                         config_manager.get_most_recent_accessible_repository()
                     {
                         info!(
@@ -308,7 +454,7 @@ impl ZipLockApp {
                 if repositories.is_empty() {
                     debug!("No repositories detected, showing wizard");
                     self.state = AppState::WizardRequired;
-                } else if repositories.len() == 1 {
+                } else if repositories.len() == 1 && !self.launch.locked {
                     // Auto-select single repository and show open dialog
                     debug!("Single repository found, showing open dialog");
                     let repo = &repositories[0];
@@ -444,6 +590,19 @@ impl ZipLockApp {
             }
 
             Message::OpenRepository(open_msg) => {
+                if matches!(
+                    open_msg,
+                    OpenRepositoryMessage::OpenRepository
+                        | OpenRepositoryMessage::OpenWithProtector(_)
+                ) {
+                    if let Err(reason) = self
+                        .auto_lock_policy()
+                        .unlock_allowed(chrono::Local::now().naive_local())
+                    {
+                        self.toast_manager.error(reason.describe());
+                        return Task::none();
+                    }
+                }
                 if let AppState::OpenRepositoryActive(open_view) = &mut self.state {
                     let command = open_view
                         .update(open_msg.clone())
@@ -472,15 +631,34 @@ impl ZipLockApp {
                             let mut main_view = MainView::new();
                             main_view.set_session_id(Some(session_id.clone()));
                             self.state = AppState::MainInterface(main_view);
+                            let focus_search = if std::mem::take(&mut self.launch.quick_search) {
+                                MainView::focus_search().map(Message::MainView)
+                            } else {
+                                Task::none()
+                            };
                             // Enable auto-lock timer when session is established
                             self.auto_lock_enabled = true;
                             self.last_activity = std::time::Instant::now();
+                            let remember_in_keychain = self
+                                .config_manager
+                                .as_ref()
+                                .is_some_and(|cm| cm.config().security.os_keychain_unlock);
                             // Trigger initial refresh to update authentication state
                             return Task::batch([
                                 command,
                                 Task::perform(async {}, |_| {
                                     Message::MainView(MainViewMessage::RefreshCredentials)
                                 }),
+                                Task::perform(async {}, |_| Message::OffsiteBackupTick),
+                                Task::perform(async {}, |_| Message::RunBreachScan),
+                                Task::perform(async {}, |_| Message::CreateDueDrafts),
+                                self.whats_new(),
+                                focus_search,
+                                if remember_in_keychain {
+                                    Self::update_os_keychain(true)
+                                } else {
+                                    Task::none()
+                                },
                             ]);
                         }
                         return command;
@@ -566,6 +744,13 @@ impl ZipLockApp {
 
             Message::MainView(main_msg) => {
                 if let AppState::MainInterface(main_view) = &mut self.state {
+                    if matches!(main_msg, MainViewMessage::CredentialsLoaded(_)) {
+                        main_view.set_last_backup(
+                            self.config_manager
+                                .as_ref()
+                                .and_then(|cm| cm.config().offsite_backup.status.last_success),
+                        );
+                    }
                     match main_msg {
                         MainViewMessage::ShowError(error) => {
                             // Check if this is a session timeout error
@@ -617,6 +802,49 @@ impl ZipLockApp {
                             );
                             Task::none()
                         }
+                        MainViewMessage::AutoTypeSelected => match main_view.auto_type_target() {
+                            Some(credential_id) => {
+                                Task::perform(async move { credential_id }, |id| {
+                                    Message::MainView(MainViewMessage::AutoType(id))
+                                })
+                            }
+                            None => {
+                                self.toast_manager
+                                    .warning("Select a credential to auto-type");
+                                Task::none()
+                            }
+                        },
+                        MainViewMessage::AutoType(credential_id) => {
+                            main_view.set_auto_type_config(
+                                self.config_manager
+                                    .as_ref()
+                                    .map(|cm| cm.config().security.auto_type)
+                                    .unwrap_or_default(),
+                            );
+                            let auto_type = main_view
+                                .update(MainViewMessage::AutoType(credential_id))
+                                .map(Message::MainView);
+                            if main_view.auto_type_dry_run() {
+                                return auto_type;
+                            }
+                            // Minimize so focus returns to the window being typed into
+                            let minimize = iced::window::get_latest()
+                                .and_then(|id| iced::window::minimize(id, true));
+                            Task::batch([minimize, auto_type])
+                        }
+                        MainViewMessage::AutoTypeCompleted(result) => {
+                            if let Err(error) = result {
+                                self.toast_manager.error(error);
+                            }
+                            Task::none()
+                        }
+                        MainViewMessage::AutoTypePreviewed(result) => {
+                            match result {
+                                Ok(preview) => self.toast_manager.info(preview),
+                                Err(error) => self.toast_manager.error(error),
+                            };
+                            Task::none()
+                        }
                         MainViewMessage::OperationCompleted(result) => {
                             // Forward operation results to main app for toast handling
                             Task::perform(async move { result }, Message::OperationResult)
@@ -791,7 +1019,15 @@ impl ZipLockApp {
             Message::ShowSettings => {
                 info!("Showing settings view");
                 if let Some(config_manager) = &self.config_manager {
-                    let settings_view = SettingsView::new(config_manager.config().clone());
+                    let mut settings_view = SettingsView::new(config_manager.config().clone());
+                    settings_view.set_api_available(self.session_id.is_some());
+                    settings_view.set_repository_unlocked(self.session_id.is_some());
+                    settings_view.set_api_sessions(
+                        services::get_api_tokens()
+                            .lock()
+                            .unwrap()
+                            .sessions(chrono::Utc::now()),
+                    );
                     self.state = AppState::SettingsActive(settings_view);
                 } else {
                     self.toast_manager
@@ -800,6 +1036,19 @@ impl ZipLockApp {
                 Task::none()
             }
 
+            Message::ShowSettingsTab(tab) => {
+                let task = self.update(Message::ShowSettings);
+                if let AppState::SettingsActive(settings_view) = &mut self.state {
+                    return Task::batch([
+                        task,
+                        settings_view
+                            .update(SettingsMessage::SelectTab(tab))
+                            .map(Message::Settings),
+                    ]);
+                }
+                task
+            }
+
             Message::HideSettings => {
                 debug!("Hiding settings view, returning to main interface");
                 if let Some(session_id) = &self.session_id {
@@ -822,11 +1071,103 @@ impl ZipLockApp {
                         SettingsMessage::Cancel => {
                             return Task::perform(async {}, |_| Message::HideSettings);
                         }
+                        SettingsMessage::RunOffsiteBackup => {
+                            return Task::perform(async {}, |_| Message::RunOffsiteBackup);
+                        }
+                        SettingsMessage::GenerateApiToken => {
+                            if self.session_id.is_none() {
+                                return Task::none();
+                            }
+                            let (label, scopes, ttl) = settings_view.api_token_request();
+                            let mut tokens = services::get_api_tokens().lock().unwrap();
+                            match tokens.issue(&label, &scopes, ttl) {
+                                Ok(issued) => {
+                                    info!("Issued API token '{}'", issued.session.label);
+                                    services::ApiServer::ensure_running();
+                                    settings_view.set_issued_api_token(Some(issued.token));
+                                    settings_view
+                                        .set_api_sessions(tokens.sessions(chrono::Utc::now()));
+                                }
+                                Err(e) => {
+                                    self.toast_manager.error(e.to_string());
+                                }
+                            }
+                            return Task::none();
+                        }
+                        SettingsMessage::CopyApiToken => {
+                            if let Some(token) = settings_view.issued_api_token() {
+                                let content = token.to_string();
+                                return Task::perform(async move { content }, |content| {
+                                    Message::CopyToClipboard {
+                                        content,
+                                        content_type: services::ClipboardContentType::Password,
+                                    }
+                                });
+                            }
+                            return Task::none();
+                        }
+                        SettingsMessage::ChangeMasterPassword => {
+                            let Some((old_password, new_password)) =
+                                settings_view.master_password_change_request()
+                            else {
+                                return Task::none();
+                            };
+                            settings_view.set_password_change_stage(Some(
+                                ziplock_shared::core::PasswordChangeStage::Encrypting,
+                            ));
+                            let events = iced::stream::channel(8, |mut output| async move {
+                                let mut progress = output.clone();
+                                let result = services::get_repository_service()
+                                    .change_master_password(
+                                        old_password,
+                                        new_password,
+                                        move |stage| {
+                                            let _ = progress.try_send(
+                                                Message::MasterPasswordChangeProgress(stage),
+                                            );
+                                        },
+                                    )
+                                    .await
+                                    .map_err(|e| e.to_string());
+                                let _ = output.send(Message::MasterPasswordChanged(result)).await;
+                            });
+                            return Task::run(events, |message| message);
+                        }
+                        SettingsMessage::RegisterSecurityKey => {
+                            let Some(label) = settings_view.security_key_request() else {
+                                return Task::none();
+                            };
+                            settings_view.set_registering_security_key(true);
+                            return Task::perform(
+                                async move {
+                                    services::get_repository_service()
+                                        .register_security_key(label)
+                                        .await
+                                        .map_err(|e| e.to_string())
+                                },
+                                Message::SecurityKeyRegistered,
+                            );
+                        }
+                        SettingsMessage::RevokeApiToken(id) => {
+                            let mut tokens = services::get_api_tokens().lock().unwrap();
+                            if tokens.revoke(id) {
+                                self.toast_manager.success("API token revoked".to_string());
+                            }
+                            settings_view.set_issued_api_token(None);
+                            settings_view.set_api_sessions(tokens.sessions(chrono::Utc::now()));
+                            return Task::none();
+                        }
                         SettingsMessage::Save => {
                             // Handle settings save
                             if !settings_view.has_validation_errors() {
                                 if let Some(config_manager) = &mut self.config_manager {
-                                    let updated_config = settings_view.get_updated_config();
+                                    let mut updated_config = settings_view.get_updated_config();
+                                    // Approvals granted while the settings were open
+                                    updated_config.security.api_approval =
+                                        services::get_approval_broker().policy();
+                                    let keychain_toggled =
+                                        updated_config.security.os_keychain_unlock
+                                            != config_manager.config().security.os_keychain_unlock;
                                     // Update the config manager
                                     *config_manager.config_mut() = updated_config;
 
@@ -834,15 +1175,31 @@ impl ZipLockApp {
                                     ui::theme::utils::typography::init_font_size(
                                         config_manager.config().ui.font_scale.unwrap_or(1.0),
                                     );
+                                    services::get_repository_service().set_session_policy(
+                                        config_manager.config().security.session,
+                                    );
+                                    services::get_repository_service().set_activity_logging(
+                                        config_manager.config().security.activity_log,
+                                    );
 
                                     // Save the configuration
                                     match config_manager.save() {
                                         Ok(_) => {
                                             self.toast_manager
                                                 .success("Settings saved successfully".to_string());
-                                            return Task::perform(async {}, |_| {
-                                                Message::HideSettings
-                                            });
+                                            let hide =
+                                                Task::perform(async {}, |_| Message::HideSettings);
+                                            if keychain_toggled && self.session_id.is_some() {
+                                                let enabled = config_manager
+                                                    .config()
+                                                    .security
+                                                    .os_keychain_unlock;
+                                                return Task::batch([
+                                                    hide,
+                                                    Self::update_os_keychain(enabled),
+                                                ]);
+                                            }
+                                            return hide;
                                         }
                                         Err(e) => {
                                             self.toast_manager
@@ -868,10 +1225,76 @@ impl ZipLockApp {
                 Task::none()
             }
 
+            Message::MasterPasswordChangeProgress(stage) => {
+                if let AppState::SettingsActive(settings_view) = &mut self.state {
+                    settings_view.set_password_change_stage(Some(stage));
+                }
+                Task::none()
+            }
+
+            Message::MasterPasswordChanged(result) => {
+                if let AppState::SettingsActive(settings_view) = &mut self.state {
+                    settings_view.set_password_change_stage(None);
+                    if result.is_ok() {
+                        settings_view.clear_master_password_form();
+                    }
+                }
+                match result {
+                    Ok(removed) if removed.is_empty() => {
+                        self.toast_manager
+                            .success("Master password changed".to_string());
+                    }
+                    Ok(removed) => {
+                        self.toast_manager.warning(format!(
+                            "Master password changed. Register these unlock methods again: {}",
+                            removed.join(", ")
+                        ));
+                    }
+                    Err(e) => {
+                        self.toast_manager.error(e);
+                    }
+                }
+                Task::none()
+            }
+
+            Message::SecurityKeyRegistered(result) => {
+                if let AppState::SettingsActive(settings_view) = &mut self.state {
+                    settings_view.set_registering_security_key(false);
+                }
+                match result {
+                    Ok(()) => {
+                        self.toast_manager
+                            .success("Security key registered".to_string());
+                    }
+                    Err(e) => {
+                        self.toast_manager.error(e);
+                    }
+                }
+                Task::none()
+            }
+
+            Message::OsKeychainUpdated(result) => {
+                match result {
+                    Ok(true) => {
+                        self.toast_manager.success(
+                            "You can now unlock this repository with your OS login".to_string(),
+                        );
+                    }
+                    Ok(false) => {}
+                    Err(e) => {
+                        self.toast_manager.warning(e);
+                    }
+                }
+                Task::none()
+            }
+
             Message::SessionTimeout => {
                 info!("Session timeout detected, redirecting to login");
                 // Clear session state
                 self.session_id = None;
+                services::get_api_tokens().lock().unwrap().clear();
+                services::get_approval_broker().deny_all();
+                self.pending_approvals.clear();
                 // Show repository selection or wizard based on configuration
                 if let Some(config_manager) = &self.config_manager {
                     if config_manager.should_show_wizard() {
@@ -900,29 +1323,306 @@ impl ZipLockApp {
                 Task::none()
             }
 
+            Message::SessionExpired(reason) => {
+                // The repository service has already locked the repository
+                info!("Session expired: {}", reason.describe());
+                let lock = Task::perform(async {}, |_| Message::SessionTimeout);
+                match reason {
+                    ziplock_shared::core::SessionExpiry::Idle => {
+                        Task::batch([self.notify(services::SecurityEvent::AutoLocked), lock])
+                    }
+                    ziplock_shared::core::SessionExpiry::Lifetime => lock,
+                }
+            }
+
+            Message::ApprovalRequested(request) => {
+                info!(
+                    "'{}' asks to use '{}'",
+                    request.client, request.credential_title
+                );
+                self.pending_approvals.push_back(request);
+                Task::none()
+            }
+
+            Message::ApprovalDecided(decision) => {
+                let Some(request) = self.pending_approvals.pop_front() else {
+                    return Task::none();
+                };
+                let broker = services::get_approval_broker();
+                if !broker.respond(&request, decision) {
+                    self.toast_manager.warning(format!(
+                        "The request from '{}' had already expired",
+                        request.client
+                    ));
+                    return Task::none();
+                }
+                if decision == ziplock_shared::utils::ApprovalDecision::AllowAlways {
+                    if let Some(config_manager) = &mut self.config_manager {
+                        config_manager.config_mut().security.api_approval = broker.policy();
+                        if let Err(e) = config_manager.save() {
+                            warn!("Failed to save approval: {}", e);
+                            self.toast_manager
+                                .warning("The approval applies until ZipLock is restarted");
+                        }
+                    }
+                }
+                Task::none()
+            }
+
             Message::AutoLockTimerTick => {
                 // Check if auto-lock is enabled and we have a session
                 if self.auto_lock_enabled && self.session_id.is_some() {
-                    if let Some(config_manager) = &self.config_manager {
-                        let timeout_minutes = config_manager.config().ui.auto_lock_timeout;
-                        // Only check timeout if it's not disabled (0)
-                        if timeout_minutes > 0 {
-                            let timeout_duration =
-                                std::time::Duration::from_secs(timeout_minutes as u64 * 60);
-                            if self.last_activity.elapsed() >= timeout_duration {
-                                info!("Auto-lock timeout reached, locking application");
-                                // Trigger session timeout to lock the application
-                                return Task::perform(async {}, |_| Message::SessionTimeout);
+                    let decision = self.auto_lock_policy().evaluate(
+                        self.last_activity.elapsed(),
+                        chrono::Local::now().naive_local(),
+                    );
+                    if let ziplock_shared::core::LockDecision::Lock { reason } = decision {
+                        info!("Auto-lock triggered: {}", reason.describe());
+                        let lock = Task::perform(async {}, |_| Message::SessionTimeout);
+                        return match reason {
+                            ziplock_shared::core::LockReason::Idle => Task::batch([
+                                self.notify(services::SecurityEvent::AutoLocked),
+                                lock,
+                            ]),
+                            // Scheduled locks also close the repository so no
+                            // key material stays in memory during the window
+                            ziplock_shared::core::LockReason::Scheduled { .. } => {
+                                self.toast_manager.warning(reason.describe());
+                                Task::batch([
+                                    Task::future(async {
+                                        if let Err(e) = services::get_repository_service()
+                                            .close_repository()
+                                            .await
+                                        {
+                                            warn!("Failed to close repository: {}", e);
+                                        }
+                                    })
+                                    .discard(),
+                                    lock,
+                                ])
                             }
-                        }
+                        };
+                    }
+                }
+                Task::none()
+            }
+
+            Message::SystemSleep(event) => match event {
+                services::SleepEvent::Suspended(Ok(None)) | services::SleepEvent::Resumed => {
+                    Task::none()
+                }
+                services::SleepEvent::Suspended(Ok(Some(_))) => {
+                    self.toast_manager
+                        .info("ZipLock was locked before the system went to sleep");
+                    Task::perform(async {}, |_| Message::SessionTimeout)
+                }
+                services::SleepEvent::Suspended(Err(e)) => {
+                    self.toast_manager.error(format!(
+                        "ZipLock was locked before sleep, but unsaved changes were lost: {}",
+                        e
+                    ));
+                    Task::perform(async {}, |_| Message::SessionTimeout)
+                }
+            },
+            Message::LockRequested(outcome) => {
+                if let Err(e) = outcome {
+                    self.toast_manager.error(format!(
+                        "ZipLock was locked, but unsaved changes were lost: {}",
+                        e
+                    ));
+                }
+                Task::perform(async {}, |_| Message::SessionTimeout)
+            }
+            Message::SshKeyUnusable(key) => {
+                self.toast_manager.warning(format!(
+                    "The SSH agent cannot use '{}': {}",
+                    key.title, key.error
+                ));
+                Task::none()
+            }
+            Message::Activated(request) => {
+                debug!("Activated by another launch: {:?}", request);
+                let mut tasks = vec![iced::window::get_latest().and_then(iced::window::gain_focus)];
+                if let Some(path) = request.open_path {
+                    let current = self
+                        .config_manager
+                        .as_ref()
+                        .and_then(|cm| cm.repository_path())
+                        .map(std::path::PathBuf::from);
+                    if self.session_id.is_none() {
+                        self.state = AppState::OpenRepositoryActive(
+                            OpenRepositoryView::with_repository(path),
+                        );
+                    } else if current.as_ref() != Some(&path) {
+                        // Don't throw away the open vault and its unsaved changes
+                        self.toast_manager.warning(format!(
+                            "Lock ZipLock before opening {}",
+                            path.file_name()
+                                .map(|name| name.to_string_lossy())
+                                .unwrap_or_else(|| path.to_string_lossy())
+                        ));
+                    }
+                }
+                if request.quick_search {
+                    if matches!(self.state, AppState::MainInterface(_)) {
+                        tasks.push(MainView::focus_search().map(Message::MainView));
+                    } else {
+                        self.launch.quick_search = true;
                     }
                 }
+                Task::batch(tasks)
+            }
+            Message::OffsiteBackupTick => {
+                let due = self.config_manager.as_ref().is_some_and(|config_manager| {
+                    config_manager
+                        .config()
+                        .offsite_backup
+                        .is_due(chrono::Utc::now())
+                });
+                if due && self.session_id.is_some() {
+                    return Task::perform(async {}, |_| Message::RunOffsiteBackup);
+                }
                 Task::none()
             }
 
+            Message::RunOffsiteBackup => {
+                if self.offsite_backup_running {
+                    return Task::none();
+                }
+                let Some(config_manager) = &self.config_manager else {
+                    return Task::none();
+                };
+
+                let config = config_manager.config().offsite_backup.clone();
+                self.offsite_backup_running = true;
+                if let AppState::SettingsActive(settings_view) = &mut self.state {
+                    settings_view.set_offsite_backup_running(true);
+                }
+                Task::perform(services::OffsiteBackupService::run(config), |result| {
+                    Message::OffsiteBackupCompleted(result.map_err(|e| e.to_string()))
+                })
+            }
+
+            Message::OffsiteBackupCompleted(result) => {
+                self.offsite_backup_running = false;
+                let Some(config_manager) = &mut self.config_manager else {
+                    return Task::none();
+                };
+
+                let now = chrono::Utc::now();
+                let notifications = config_manager.config().notifications.clone();
+                let mut notify_task = Task::none();
+                let status = &mut config_manager.config_mut().offsite_backup.status;
+                match result {
+                    Ok(outcome) => {
+                        info!(
+                            "Off-site backup {} uploaded to {} ({} old backups removed)",
+                            outcome.file_name,
+                            outcome.destination,
+                            outcome.removed.len()
+                        );
+                        status.record_success(outcome.file_name, now);
+                        match outcome.rotation_error {
+                            Some(error) => self.toast_manager.warning(format!(
+                                "Backup uploaded, but old backups could not be removed: {}",
+                                error
+                            )),
+                            None => self
+                                .toast_manager
+                                .success(format!("Backup uploaded to {}", outcome.destination)),
+                        };
+                    }
+                    Err(error) => {
+                        error!("Off-site backup failed: {}", error);
+                        self.toast_manager
+                            .error(format!("Off-site backup failed: {}", error));
+                        notify_task = Task::future(services::NotificationService::notify(
+                            notifications,
+                            services::SecurityEvent::BackupFailed(error.clone()),
+                        ))
+                        .discard();
+                        status.record_failure(error, now);
+                    }
+                }
+
+                let status = status.clone();
+                if let Err(e) = config_manager.save() {
+                    warn!("Failed to save off-site backup status: {}", e);
+                }
+                if let AppState::SettingsActive(settings_view) = &mut self.state {
+                    settings_view.set_offsite_backup_running(false);
+                    settings_view.set_offsite_status(status);
+                }
+                notify_task
+            }
+
+            Message::RunBreachScan => {
+                let enabled = self
+                    .config_manager
+                    .as_ref()
+                    .is_some_and(|cm| cm.config().security.check_breaches);
+                if !enabled || self.session_id.is_none() {
+                    return Task::none();
+                }
+                Task::perform(
+                    services::BreachScanService::scan(),
+                    Message::BreachScanCompleted,
+                )
+            }
+
+            Message::CreateDueDrafts => {
+                if self.session_id.is_none() {
+                    return Task::none();
+                }
+                Task::perform(
+                    async {
+                        services::get_repository_service()
+                            .create_due_drafts()
+                            .await
+                            .map_err(|e| e.to_string())
+                    },
+                    Message::DraftsCreated,
+                )
+            }
+
+            Message::DraftsCreated(result) => match result {
+                Ok(drafts) if drafts.is_empty() => Task::none(),
+                Ok(drafts) => {
+                    let message = match drafts.as_slice() {
+                        [draft] => format!("'{}' was added to the inbox", draft.title),
+                        _ => format!(
+                            "{} recurring credentials were added to the inbox",
+                            drafts.len()
+                        ),
+                    };
+                    self.toast_manager.info(message);
+                    self.notify(services::SecurityEvent::DraftsCreated(drafts.len()))
+                }
+                Err(error) => {
+                    warn!("Creating recurring credentials failed: {}", error);
+                    Task::none()
+                }
+            },
+
+            Message::BreachScanCompleted(result) => match result {
+                Ok(breached) if breached.is_empty() => Task::none(),
+                Ok(breached) => {
+                    self.toast_manager.warning(format!(
+                        "{} credential(s) use a password found in a data breach",
+                        breached.len()
+                    ));
+                    self.notify(services::SecurityEvent::BreachDetected(breached.len()))
+                }
+                Err(error) => {
+                    warn!("Breach scan failed: {}", error);
+                    Task::none()
+                }
+            },
+
             Message::UserActivity => {
                 // Reset the activity timer
                 self.last_activity = std::time::Instant::now();
+                services::get_repository_service().touch_session();
                 Task::none()
             }
 
@@ -982,6 +1682,30 @@ impl ZipLockApp {
                 Task::none()
             }
 
+            Message::ShowWhatsNew(releases) => {
+                // Only over the vault itself, never in the middle of an edit
+                if matches!(self.state, AppState::MainInterface(_)) {
+                    self.state = AppState::WhatsNewActive(WhatsNewDialog::new(releases));
+                }
+                Task::none()
+            }
+
+            Message::WhatsNew(whats_new_msg) => {
+                if let Some(config_manager) = &mut self.config_manager {
+                    config_manager.config_mut().last_seen_version =
+                        Some(env!("CARGO_PKG_VERSION").to_string());
+                    if let Err(e) = config_manager.save() {
+                        warn!("Failed to save last seen version: {}", e);
+                    }
+                }
+                match whats_new_msg {
+                    WhatsNewMessage::Close => self.update(Message::HideUpdateDialog),
+                    WhatsNewMessage::OpenSettings(section) => {
+                        self.update(Message::ShowSettingsTab(section.into()))
+                    }
+                }
+            }
+
             Message::AutoUpdateCheck => {
                 // Check if auto-update checking is enabled and if we should check now
                 if let Some(config_manager) = &self.config_manager {
@@ -1051,6 +1775,9 @@ impl ZipLockApp {
 
                 // Clear session and return to repository detection/selection
                 self.session_id = None;
+                services::get_api_tokens().lock().unwrap().clear();
+                services::get_approval_broker().deny_all();
+                self.pending_approvals.clear();
                 self.auto_lock_enabled = false;
 
                 // Clear clipboard content
@@ -1098,16 +1825,8 @@ impl ZipLockApp {
                     clipboard_manager.clear_tracked_content().await;
                 }));
 
-                // If we have an active session, try to logout first
-                if self.session_id.is_some() {
-                    info!("Active session detected, logging out before quit");
-                    Task::perform(Self::logout_and_quit_async(self.session_id.clone()), |_| {
-                        Message::QuittingWithLogout
-                    })
-                } else {
-                    // No active session, quit immediately
-                    std::process::exit(0);
-                }
+                // Tell scripting clients, save pending changes and wipe keys
+                Task::perform(services::Shutdown::run(), |_| Message::QuittingWithLogout)
             }
 
             Message::QuittingWithLogout => {
@@ -1134,6 +1853,7 @@ impl ZipLockApp {
                 edit_view.view().map(Message::EditCredential)
             }
             AppState::SettingsActive(settings_view) => settings_view.view().map(Message::Settings),
+            AppState::WhatsNewActive(whats_new) => whats_new.view().map(Message::WhatsNew),
             AppState::UpdateDialogActive(update_dialog) => {
                 update_dialog.view().map(|dialog_msg| match dialog_msg {
                     UpdateDialogMessage::Close => Message::HideUpdateDialog,
@@ -1181,6 +1901,7 @@ impl ZipLockApp {
             iced::Event::Window(iced::window::Event::CloseRequested) => Some(Message::Quit),
             _ => None,
         });
+        let signal_subscription = services::Shutdown::signals().map(|_| Message::Quit);
 
         // Track user activity for auto-lock
         let activity_subscription = iced::event::listen_with(|event, _status, _id| match event {
@@ -1197,23 +1918,17 @@ impl ZipLockApp {
         };
 
         // Auto-lock timer subscription - check every 10 seconds
-        let auto_lock_subscription = if self.auto_lock_enabled && self.session_id.is_some() {
-            if let Some(config_manager) = &self.config_manager {
-                let timeout_minutes = config_manager.config().ui.auto_lock_timeout;
-                if timeout_minutes > 0 {
-                    time::every(std::time::Duration::from_secs(10))
-                        .map(|_| Message::AutoLockTimerTick)
-                } else {
-                    iced::Subscription::none()
-                }
-            } else {
-                iced::Subscription::none()
-            }
+        let auto_lock_subscription = if self.auto_lock_enabled
+            && self.session_id.is_some()
+            && self.auto_lock_policy().is_active()
+        {
+            time::every(std::time::Duration::from_secs(10)).map(|_| Message::AutoLockTimerTick)
         } else {
             iced::Subscription::none()
         };
 
         let view_subscription = match &self.state {
+            AppState::MainInterface(view) => view.subscription().map(Message::MainView),
             AppState::AddCredentialActive(view) => view.subscription().map(Message::AddCredential),
             AppState::EditCredentialActive(view) => {
                 view.subscription().map(Message::EditCredential)
@@ -1233,12 +1948,68 @@ impl ZipLockApp {
             iced::Subscription::none()
         };
 
+        // Off-site backups run monthly; checking every 15 minutes is plenty
+        let offsite_backup_subscription = match &self.config_manager {
+            Some(config_manager)
+                if config_manager.config().offsite_backup.enabled && self.session_id.is_some() =>
+            {
+                time::every(std::time::Duration::from_secs(15 * 60))
+                    .map(|_| Message::OffsiteBackupTick)
+            }
+            _ => iced::Subscription::none(),
+        };
+
+        // Recurring credentials fall due by the day at most
+        let recurring_subscription = if self.session_id.is_some() {
+            time::every(std::time::Duration::from_secs(60 * 60)).map(|_| Message::CreateDueDrafts)
+        } else {
+            iced::Subscription::none()
+        };
+
+        let session_subscription =
+            services::RepositoryService::session_expiries().map(Message::SessionExpired);
+        let approval_subscription =
+            services::ApprovalBroker::prompts().map(Message::ApprovalRequested);
+        let lock_control_subscription =
+            services::LockControl::subscription().map(Message::LockRequested);
+        let activation_subscription = services::Activation::subscription().map(Message::Activated);
+
+        // Wipe keys before suspend/hibernate while a repository is open
+        let sleep_subscription = match &self.config_manager {
+            Some(config_manager)
+                if config_manager.config().security.lock_on_suspend
+                    && self.session_id.is_some() =>
+            {
+                services::SleepMonitor::subscription().map(Message::SystemSleep)
+            }
+            _ => iced::Subscription::none(),
+        };
+
+        // Serve stored SSH keys while a repository is open
+        let ssh_agent_subscription = match &self.config_manager {
+            Some(config_manager)
+                if config_manager.config().security.ssh_agent && self.session_id.is_some() =>
+            {
+                services::SshAgent::subscription().map(Message::SshKeyUnusable)
+            }
+            _ => iced::Subscription::none(),
+        };
+
         iced::Subscription::batch([
             close_subscription,
+            signal_subscription,
             activity_subscription,
             toast_subscription,
             auto_lock_subscription,
             auto_update_subscription,
+            offsite_backup_subscription,
+            recurring_subscription,
+            sleep_subscription,
+            session_subscription,
+            approval_subscription,
+            lock_control_subscription,
+            ssh_agent_subscription,
+            activation_subscription,
             view_subscription,
         ])
     }
@@ -1256,6 +2027,17 @@ impl ZipLockApp {
         let content_with_toasts =
             render_toast_overlay(&self.toast_manager, content, Message::DismissToast);
 
+        // Scripting API reads waiting for the user go above everything else
+        let content_with_toasts = match self.pending_approvals.front() {
+            Some(request) => column![
+                self.view_approval_prompt(request),
+                Space::with_height(Length::Fixed(10.0)),
+                content_with_toasts,
+            ]
+            .into(),
+            None => content_with_toasts,
+        };
+
         // Then wrap with alert if present (for backwards compatibility)
         let content_with_alerts = if let Some(alert) = &self.current_alert {
             column![
@@ -1270,6 +2052,80 @@ impl ZipLockApp {
 
         content_with_alerts
     }
+
+    /// Banner asking whether a script may read a credential, or an SSH
+    /// client may sign with a stored key
+    fn view_approval_prompt<'a>(
+        &self,
+        request: &'a ziplock_shared::utils::ApprovalRequest,
+    ) -> Element<'a, Message> {
+        use iced::widget::{column, container, row, text, Space};
+        use iced::{Alignment, Length};
+        use ui::theme::utils::typography;
+        use ziplock_shared::utils::{ApprovalDecision, ApprovalPurpose};
+
+        let waiting = match self.pending_approvals.len() {
+            0 | 1 => String::new(),
+            n => format!("{} more waiting", n - 1),
+        };
+        let (question, buttons) = match request.purpose {
+            ApprovalPurpose::Read => {
+                let action = if request.reveal {
+                    "reveal the passwords of"
+                } else {
+                    "read"
+                };
+                (
+                    format!(
+                        "The script using the API token '{}' wants to {} '{}'.",
+                        request.client, action, request.credential_title
+                    ),
+                    row![
+                        primary_button(
+                            "Allow once",
+                            Some(Message::ApprovalDecided(ApprovalDecision::AllowOnce))
+                        ),
+                        secondary_button(
+                            "Allow always",
+                            Some(Message::ApprovalDecided(ApprovalDecision::AllowAlways))
+                        ),
+                    ],
+                )
+            }
+            ApprovalPurpose::SshSign => (
+                format!(
+                    "{} wants to sign in with the SSH key '{}'.",
+                    request.client, request.credential_title
+                ),
+                row![primary_button(
+                    "Allow",
+                    Some(Message::ApprovalDecided(ApprovalDecision::AllowOnce))
+                )],
+            ),
+        };
+
+        container(
+            column![
+                text(question).size(typography::normal_text_size()),
+                row![
+                    buttons.spacing(10),
+                    destructive_button(
+                        "Deny",
+                        Some(Message::ApprovalDecided(ApprovalDecision::Deny))
+                    ),
+                    Space::with_width(Length::Fill),
+                    text(waiting).size(typography::small_text_size()),
+                ]
+                .spacing(10)
+                .align_y(Alignment::Center),
+            ]
+            .spacing(10),
+        )
+        .padding(ui::theme::utils::alert_padding())
+        .width(Length::Fill)
+        .into()
+    }
+
     /// View loading screen
     fn view_loading(&self) -> Element<'_, Message> {
         use iced::widget::{container, text, Space};
@@ -1506,17 +2362,6 @@ impl ZipLockApp {
     }
 
     /// Async function to connect to backend
-    async fn logout_and_quit_async(session_id: Option<String>) -> Result<(), String> {
-        if let Some(_sid) = session_id.clone() {
-            // No longer need separate client, repository service handles this
-            info!("Using repository service for operations");
-
-            // Repository service automatically handles cleanup when closed
-            info!("Repository service will handle cleanup automatically");
-        }
-        Ok(())
-    }
-
     async fn connect_backend_async() -> Result<(), String> {
         // Repository service is already initialized and available
         info!("Repository service ready for operations");
@@ -1527,12 +2372,75 @@ impl ZipLockApp {
     }
 }
 
+/// Run one `--api` request against the running app and print the JSON result
+fn run_api_command(method: &str, matches: &clap::ArgMatches) -> i32 {
+    use ziplock_shared::utils::{ApiMethod, ApiRequest};
+
+    let Ok(token) = std::env::var(services::api_server::TOKEN_ENV_VAR) else {
+        eprintln!(
+            "Set {} to a token generated in Settings → Security",
+            services::api_server::TOKEN_ENV_VAR
+        );
+        return 2;
+    };
+    let argument = |name: &str| matches.get_one::<String>(name).cloned();
+    let method = match (method, argument("query"), argument("id")) {
+        ("list", _, _) => ApiMethod::List,
+        ("search", Some(query), _) => ApiMethod::Search { query },
+        ("get", _, Some(id)) => ApiMethod::Get {
+            id,
+            reveal: matches.get_flag("reveal"),
+        },
+        ("search", None, _) => {
+            eprintln!("--api search requires --query");
+            return 2;
+        }
+        _ => {
+            eprintln!("--api get requires --id");
+            return 2;
+        }
+    };
+
+    match services::api_server::send_request(&ApiRequest { token, method }) {
+        Ok(result) => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&result).unwrap_or_default()
+            );
+            0
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
+}
+
 fn main() -> iced::Result {
     // Parse command line arguments first (before any other initialization)
     let matches = Command::new("ZipLock Password Manager")
         .version(env!("CARGO_PKG_VERSION"))
         .about("A secure, cross-platform password manager using encrypted 7z archives")
         .author("James Angus <james@ejangi.com>")
+        .disable_version_flag(true)
+        .disable_help_flag(true)
+        .arg(
+            Arg::new("vault")
+                .value_name("ARCHIVE")
+                .help("Repository to show the unlock dialog for instead of the most recent one"),
+        )
+        .arg(
+            Arg::new("quick-search")
+                .long("quick-search")
+                .help("Jump straight to the search field once the vault is unlocked")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("locked")
+                .long("locked")
+                .help("Start without opening the most recent repository")
+                .action(clap::ArgAction::SetTrue),
+        )
         .arg(
             Arg::new("version")
                 .long("version")
@@ -1553,6 +2461,43 @@ fn main() -> iced::Result {
                 .help("Run in headless mode for testing (prints version and exits)")
                 .action(clap::ArgAction::SetTrue),
         )
+        .arg(
+            Arg::new("api")
+                .long("api")
+                .value_name("METHOD")
+                .value_parser(["list", "search", "get"])
+                .help("Query the running app with the token in ZIPLOCK_API_TOKEN and exit"),
+        )
+        .arg(
+            Arg::new("query")
+                .long("query")
+                .value_name("TEXT")
+                .help("Search text for --api search"),
+        )
+        .arg(
+            Arg::new("id")
+                .long("id")
+                .value_name("ID")
+                .help("Credential ID for --api get"),
+        )
+        .arg(
+            Arg::new("reveal")
+                .long("reveal")
+                .help("Include sensitive field values with --api get")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("profile")
+                .long("profile")
+                .value_name("NAME")
+                .help("Use the settings, recent repositories and theme of this profile (default: $ZIPLOCK_PROFILE or \"default\")"),
+        )
+        .arg(
+            Arg::new("list-profiles")
+                .long("list-profiles")
+                .help("Print the profiles created so far and exit")
+                .action(clap::ArgAction::SetTrue),
+        )
         .get_matches();
 
     // Handle version flag - exit early before any GUI initialization
@@ -1568,6 +2513,56 @@ fn main() -> iced::Result {
         std::process::exit(0);
     }
 
+    if matches.get_flag("list-profiles") {
+        println!("{}", ConfigPaths::DEFAULT_PROFILE);
+        for profile in ConfigPaths::list_profiles() {
+            println!("{}", profile);
+        }
+        std::process::exit(0);
+    }
+
+    // Choose the profile before anything reads the configuration
+    let profile = matches
+        .get_one::<String>("profile")
+        .cloned()
+        .or_else(|| std::env::var(config::PROFILE_ENV_VAR).ok())
+        .filter(|name| !name.is_empty());
+    if let Err(e) = config::set_active_profile(profile) {
+        eprintln!("{}", e);
+        std::process::exit(2);
+    }
+
+    // Handle scripting API requests - talks to an already running instance
+    if let Some(method) = matches.get_one::<String>("api") {
+        std::process::exit(run_api_command(method, &matches));
+    }
+
+    // The running app may have a different working directory
+    let launch = LaunchOptions {
+        open_path: matches.get_one::<String>("vault").map(|path| {
+            std::fs::canonicalize(path).unwrap_or_else(|_| std::path::PathBuf::from(path))
+        }),
+        quick_search: matches.get_flag("quick-search"),
+        locked: matches.get_flag("locked"),
+    };
+
+    // Hand over to the running app instead of opening a second window
+    #[cfg(unix)]
+    {
+        let request = services::ActivationRequest {
+            open_path: launch.open_path.clone(),
+            quick_search: launch.quick_search,
+        };
+        match services::activation::claim_instance(&request) {
+            services::InstanceClaim::Primary => {}
+            services::InstanceClaim::Forwarded => std::process::exit(0),
+            services::InstanceClaim::Unresponsive => {
+                eprintln!("ZipLock is already running but does not respond");
+                std::process::exit(1);
+            }
+        }
+    }
+
     // Determine if running in production mode
     let is_production = is_production_mode();
 
@@ -1605,6 +2600,25 @@ fn main() -> iced::Result {
         }
     );
 
+    // Check the cryptography before any vault is opened; off the UI thread
+    // since the archive check derives a 7z key
+    std::thread::spawn(|| {
+        let report = ziplock_shared::core::self_test::run();
+        for check in report.failures() {
+            error!(
+                "Self-test '{}' failed: {}",
+                check.name,
+                check.detail.as_deref().unwrap_or("no detail")
+            );
+        }
+        if report.passed() {
+            info!(
+                "Cryptographic self-test passed in {} ms",
+                report.duration_ms
+            );
+        }
+    });
+
     // Log Windows-specific production settings
     #[cfg(windows)]
     if is_production {
@@ -1613,14 +2627,13 @@ fn main() -> iced::Result {
     }
 
     // Use new Iced 0.13 application architecture
-    iced::application(
-        "ZipLock Password Manager",
-        ZipLockApp::update,
-        ZipLockApp::view,
-    )
-    .subscription(ZipLockApp::subscription)
-    .theme(ZipLockApp::theme)
-    .window_size((1000.0, 700.0))
-    .antialiasing(true)
-    .run_with(ZipLockApp::new)
+    if let Some(profile) = config::active_profile() {
+        info!("Using profile '{}'", profile);
+    }
+    iced::application(ZipLockApp::title, ZipLockApp::update, ZipLockApp::view)
+        .subscription(ZipLockApp::subscription)
+        .theme(ZipLockApp::theme)
+        .window_size((1000.0, 700.0))
+        .antialiasing(true)
+        .run_with(move || ZipLockApp::new(launch))
 }
@@ -0,0 +1,228 @@
+//! Keeps ZipLock to one window per desktop session
+//!
+//! The file manager starts `ziplock path/to/vault.7z` when a vault is
+//! double-clicked (see `resources/ziplock.desktop`). Before opening a window
+//! every launch calls [`claim_instance`], which binds
+//! [`activation_socket_path`] without replacing a socket that is there. The
+//! launch that binds it is the running app; any later one finds the address
+//! in use, hands its [`ActivationRequest`] over and exits, so two windows
+//! never hold the same vault. The running window comes to the front and
+//! shows the unlock dialog.
+//!
+//! The socket lives in the per-user runtime directory rather than the Linux
+//! abstract namespace, because abstract sockets are visible to every user on
+//! the machine and would let another account answer in ZipLock's place.
+//!
+//! The guard is Unix-only: [`claim_instance`] does not exist elsewhere, and
+//! on Windows every launch opens its own window.
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use ziplock_shared::utils::{api_socket_path, ApiResponse};
+
+/// What a second launch asks the running app to do
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ActivationRequest {
+    /// Show the unlock dialog for this repository
+    pub open_path: Option<PathBuf>,
+    /// Focus the search field
+    pub quick_search: bool,
+}
+
+/// Path of the socket the running app accepts activation requests on
+pub fn activation_socket_path() -> PathBuf {
+    api_socket_path().with_file_name("activate.sock")
+}
+
+/// What [`claim_instance`] found
+#[cfg(unix)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InstanceClaim {
+    /// This process is the running app
+    Primary,
+    /// The running app took the request over; this process should exit
+    Forwarded,
+    /// Another app holds the socket but did not answer
+    Unresponsive,
+}
+
+/// Become the running app, or hand `request` to the one already running
+///
+/// Runs before the GUI starts, so it is blocking.
+#[cfg(unix)]
+pub fn claim_instance(request: &ActivationRequest) -> InstanceClaim {
+    use std::io::ErrorKind;
+
+    use crate::services::api_server::bind_new_user_socket;
+
+    let path = activation_socket_path();
+    for _ in 0..2 {
+        match bind_new_user_socket(&path) {
+            Ok(listener) => {
+                *unix::CLAIMED.lock().unwrap() = Some(listener);
+                return InstanceClaim::Primary;
+            }
+            Err(e) if e.kind() == ErrorKind::AddrInUse => match forward(request) {
+                Ok(true) => return InstanceClaim::Forwarded,
+                // A socket left behind by a crashed app refuses connections
+                Ok(false) => {
+                    let _ = std::fs::remove_file(&path);
+                }
+                Err(_) => return InstanceClaim::Unresponsive,
+            },
+            // Run without the guard rather than not at all
+            Err(_) => return InstanceClaim::Primary,
+        }
+    }
+    InstanceClaim::Primary
+}
+
+/// Serves activation requests from later launches
+pub struct Activation;
+
+impl Activation {
+    /// Subscription delivering each request a later launch handed over
+    pub fn subscription() -> iced::Subscription<ActivationRequest> {
+        #[cfg(unix)]
+        {
+            iced::Subscription::run(unix::serve)
+        }
+        #[cfg(not(unix))]
+        {
+            iced::Subscription::none()
+        }
+    }
+
+    /// Parse one request line into the reply and the request to act on
+    pub fn handle_line(line: &str) -> (ApiResponse, Option<ActivationRequest>) {
+        match serde_json::from_str::<ActivationRequest>(line) {
+            Ok(request) => (ApiResponse::ok(serde_json::json!({})), Some(request)),
+            Err(e) => (ApiResponse::error(format!("Invalid request: {}", e)), None),
+        }
+    }
+}
+
+#[cfg(unix)]
+mod unix {
+    use std::sync::Mutex;
+
+    use iced::futures::{SinkExt, Stream};
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tracing::{info, warn};
+
+    use super::{activation_socket_path, Activation, ActivationRequest};
+    use crate::services::api_server::{bind_user_socket, SocketFile};
+
+    /// Listener bound by [`super::claim_instance`], waiting for the window
+    pub(super) static CLAIMED: Mutex<Option<std::os::unix::net::UnixListener>> = Mutex::new(None);
+
+    fn claimed_listener() -> std::io::Result<tokio::net::UnixListener> {
+        match CLAIMED.lock().unwrap().take() {
+            Some(listener) => {
+                listener.set_nonblocking(true)?;
+                tokio::net::UnixListener::from_std(listener)
+            }
+            None => bind_user_socket(&activation_socket_path()),
+        }
+    }
+
+    pub fn serve() -> impl Stream<Item = ActivationRequest> {
+        iced::stream::channel(4, |output| async move {
+            let path = activation_socket_path();
+            let listener = match claimed_listener() {
+                Ok(listener) => listener,
+                Err(e) => {
+                    warn!("Failed to listen for activation on {:?}: {}", path, e);
+                    return;
+                }
+            };
+            let _socket = SocketFile(path.clone());
+            info!("Listening for activation on {:?}", path);
+
+            loop {
+                let stream = match listener.accept().await {
+                    Ok((stream, _)) => stream,
+                    Err(e) => {
+                        warn!("Activation socket accept failed: {}", e);
+                        continue;
+                    }
+                };
+                let mut output = output.clone();
+                tokio::spawn(async move {
+                    let (reader, mut writer) = stream.into_split();
+                    let mut lines = BufReader::new(reader).lines();
+                    let Ok(Some(line)) = lines.next_line().await else {
+                        return;
+                    };
+                    let (response, request) = Activation::handle_line(&line);
+                    if let Some(request) = request {
+                        let _ = output.send(request).await;
+                    }
+                    let mut reply = serde_json::to_string(&response).unwrap_or_default();
+                    reply.push('\n');
+                    let _ = writer.write_all(reply.as_bytes()).await;
+                });
+            }
+        })
+    }
+}
+
+/// Send `request` to the running app; `Ok(false)` if nothing listens
+#[cfg(unix)]
+fn forward(request: &ActivationRequest) -> std::io::Result<bool> {
+    use std::io::{BufRead, BufReader, ErrorKind, Write};
+    use std::os::unix::net::UnixStream;
+    use std::time::Duration;
+
+    let mut stream = match UnixStream::connect(activation_socket_path()) {
+        Ok(stream) => stream,
+        Err(e) if matches!(e.kind(), ErrorKind::ConnectionRefused | ErrorKind::NotFound) => {
+            return Ok(false)
+        }
+        Err(e) => return Err(e),
+    };
+    stream.set_read_timeout(Some(Duration::from_secs(2)))?;
+    let mut line = serde_json::to_string(request)?;
+    line.push('\n');
+    stream.write_all(line.as_bytes())?;
+
+    let mut response = String::new();
+    BufReader::new(stream).read_line(&mut response)?;
+    match serde_json::from_str(&response)? {
+        ApiResponse::Ok { .. } => Ok(true),
+        _ => Err(std::io::Error::other("activation refused")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_lines() {
+        let (response, request) = Activation::handle_line(r#"{"open_path":"/home/jane/vault.7z"}"#);
+        assert!(matches!(response, ApiResponse::Ok { .. }));
+        assert_eq!(
+            request,
+            Some(ActivationRequest {
+                open_path: Some(PathBuf::from("/home/jane/vault.7z")),
+                quick_search: false,
+            })
+        );
+
+        let (response, request) = Activation::handle_line("{}");
+        assert!(matches!(response, ApiResponse::Ok { .. }));
+        assert_eq!(request, Some(ActivationRequest::default()));
+
+        let (response, request) = Activation::handle_line("open please");
+        assert!(matches!(response, ApiResponse::Error { .. }));
+        assert!(request.is_none());
+        assert_eq!(
+            activation_socket_path().parent(),
+            api_socket_path().parent()
+        );
+    }
+}
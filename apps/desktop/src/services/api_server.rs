@@ -0,0 +1,482 @@
+//! Local scripting API for the unlocked vault
+//!
+//! Scripts talk to the running app over a Unix socket in the user's runtime
+//! directory, sending one JSON [`ApiRequest`] per line and reading one
+//! [`ApiResponse`] line back. Every request carries a token issued from
+//! Settings; the socket only starts listening once the first token exists,
+//! and all tokens are revoked when the vault locks. The API is read-only.
+//!
+//! A script that sends `subscribe` is pushed a `changed` event line for every
+//! change made through the repository service, so it can stay in sync
+//! without polling. The subscription ends when the vault locks.
+//!
+//! Reads the [`ApprovalPolicy`](ziplock_shared::utils::ApprovalPolicy)
+//! guards wait until the user allows them in the window, see
+//! [`ApprovalBroker`](super::ApprovalBroker); refused reads get an error
+//! with the `approval_denied` code.
+//!
+//! When the app quits, [`ApiServer::shutdown`] sends every connected script a
+//! `server_shutdown` event, closes the connections and removes the socket.
+
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+use tokio::sync::{broadcast, watch};
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+use super::{get_approval_broker, get_repository_service};
+use ziplock_shared::core::RepositoryEvent;
+use ziplock_shared::utils::{
+    api_socket_path, ApiCredentialSummary, ApiEvent, ApiMethod, ApiRequest, ApiResponse,
+    ApiTokenRegistry,
+};
+use ziplock_shared::CoreError;
+
+/// Environment variable the `--api` command line client reads the token from
+pub const TOKEN_ENV_VAR: &str = "ZIPLOCK_API_TOKEN";
+
+static API_TOKENS: OnceLock<Mutex<ApiTokenRegistry>> = OnceLock::new();
+static SERVER_STARTED: OnceLock<()> = OnceLock::new();
+static SHUTDOWN: OnceLock<watch::Sender<bool>> = OnceLock::new();
+static LISTENER: Mutex<Option<JoinHandle<()>>> = Mutex::new(None);
+
+/// How long quitting waits for scripts to be told
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Get the global registry of issued API tokens
+pub fn get_api_tokens() -> &'static Mutex<ApiTokenRegistry> {
+    API_TOKENS.get_or_init(|| Mutex::new(ApiTokenRegistry::new()))
+}
+
+/// Serves API requests from local scripts
+pub struct ApiServer;
+
+impl ApiServer {
+    /// Path of the API socket
+    pub fn socket_path() -> PathBuf {
+        api_socket_path()
+    }
+
+    /// Start listening if the server is not already running
+    ///
+    /// Must be called from within the Tokio runtime.
+    pub fn ensure_running() {
+        if SERVER_STARTED.set(()).is_err() {
+            return;
+        }
+        Self::spawn_listener();
+    }
+
+    /// Tell connected scripts the app is going away, close their
+    /// connections, remove the socket and revoke every token
+    ///
+    /// Returns once every connection is closed, or after a short timeout.
+    pub async fn shutdown() {
+        shutdown_signal().send_replace(true);
+        get_api_tokens().lock().unwrap().clear();
+        let listener = LISTENER.lock().unwrap().take();
+        if let Some(listener) = listener {
+            if tokio::time::timeout(SHUTDOWN_TIMEOUT, listener)
+                .await
+                .is_err()
+            {
+                warn!("Scripting API did not shut down in time");
+            }
+        }
+    }
+
+    #[cfg(unix)]
+    fn spawn_listener() {
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+        let path = Self::socket_path();
+        let listener = match bind_user_socket(&path) {
+            Ok(listener) => listener,
+            Err(e) => {
+                warn!("Failed to start scripting API on {:?}: {}", path, e);
+                return;
+            }
+        };
+        info!("Scripting API listening on {:?}", path);
+
+        let mut shutdown = shutdown_signal().subscribe();
+        let handle = tokio::spawn(async move {
+            let mut connections = tokio::task::JoinSet::new();
+            loop {
+                let stream = tokio::select! {
+                    accepted = listener.accept() => match accepted {
+                        Ok((stream, _)) => stream,
+                        Err(e) => {
+                            warn!("Scripting API accept failed: {}", e);
+                            continue;
+                        }
+                    },
+                    _ = stopped(&mut shutdown) => break,
+                };
+                let mut shutdown = shutdown_signal().subscribe();
+                connections.spawn(async move {
+                    let (reader, mut writer) = stream.into_split();
+                    let mut lines = BufReader::new(reader).lines();
+                    let mut changes = None;
+                    loop {
+                        let line = tokio::select! {
+                            line = lines.next_line() => match line {
+                                Ok(Some(line)) => line,
+                                _ => break,
+                            },
+                            event = next_change(&mut changes) => {
+                                let event = ApiResponse::event(event);
+                                if writer.write_all(&response_line(&event)).await.is_err() {
+                                    break;
+                                }
+                                continue;
+                            }
+                            _ = stopped(&mut shutdown) => {
+                                let _ = writer.write_all(&response_line(&ApiResponse::server_shutdown())).await;
+                                break;
+                            }
+                        };
+                        if line.trim().is_empty() {
+                            continue;
+                        }
+                        let (response, subscribe) = Self::handle_line(&line).await;
+                        if subscribe && changes.is_none() {
+                            changes = Some(get_repository_service().subscribe());
+                        }
+                        if writer
+                            .write_all(&response_line(&response))
+                            .await
+                            .is_err()
+                        {
+                            break;
+                        }
+                    }
+                });
+            }
+
+            while connections.join_next().await.is_some() {}
+            let _ = std::fs::remove_file(&path);
+            info!("Scripting API stopped");
+        });
+        *LISTENER.lock().unwrap() = Some(handle);
+    }
+
+    #[cfg(not(unix))]
+    fn spawn_listener() {
+        warn!("The scripting API is not supported on this platform yet");
+    }
+
+    /// Answer one request line, and whether it started a subscription
+    pub async fn handle_line(line: &str) -> (ApiResponse, bool) {
+        let request: ApiRequest = match serde_json::from_str(line) {
+            Ok(request) => request,
+            Err(e) => return (ApiResponse::error(format!("Invalid request: {}", e)), false),
+        };
+
+        let session = {
+            let mut tokens = get_api_tokens().lock().unwrap();
+            tokens.authorize(&request.token, request.method.required_scope())
+        };
+        let session = match session {
+            Ok(session) => session,
+            Err(e) => return (ApiResponse::error(e), false),
+        };
+        debug!("API request from token '{}'", session.label);
+
+        let subscribe = request.method == ApiMethod::Subscribe;
+        match Self::handle(request.method, &session.label).await {
+            Ok(result) => (ApiResponse::ok(result), subscribe),
+            Err(response) => (response, false),
+        }
+    }
+
+    async fn handle(method: ApiMethod, client: &str) -> Result<serde_json::Value, ApiResponse> {
+        let repository = get_repository_service();
+        if let Some(reason) = repository.session_expiry() {
+            return Err(ApiResponse::session_expired(reason));
+        }
+        if !repository.is_open().await {
+            return Err(ApiResponse::error("Vault is locked"));
+        }
+
+        let value = match method {
+            ApiMethod::List => {
+                let credentials = repository
+                    .list_credentials()
+                    .await
+                    .map_err(repository_error)?;
+                serde_json::to_value(
+                    credentials
+                        .iter()
+                        .map(ApiCredentialSummary::from)
+                        .collect::<Vec<_>>(),
+                )
+            }
+            ApiMethod::Search { query } => {
+                let credentials = repository
+                    .search_credentials(query)
+                    .await
+                    .map_err(repository_error)?;
+                serde_json::to_value(
+                    credentials
+                        .iter()
+                        .map(ApiCredentialSummary::from)
+                        .collect::<Vec<_>>(),
+                )
+            }
+            ApiMethod::Get { id, reveal } => {
+                let credential = repository
+                    .get_credential(id.clone())
+                    .await
+                    .map_err(repository_error)?
+                    .ok_or_else(|| ApiResponse::error(format!("Credential not found: {}", id)))?;
+                let decision = get_approval_broker()
+                    .authorize(client, &credential, reveal)
+                    .await;
+                if !decision.is_allowed() {
+                    return Err(ApiResponse::approval_denied());
+                }
+                // The vault may have locked while the user was asked
+                if !repository.is_open().await {
+                    return Err(ApiResponse::error("Vault is locked"));
+                }
+                if reveal {
+                    serde_json::to_value(credential)
+                } else {
+                    serde_json::to_value(credential.sanitized())
+                }
+            }
+            ApiMethod::Subscribe => Ok(serde_json::json!({ "subscribed": true })),
+        };
+        value.map_err(ApiResponse::error)
+    }
+}
+
+/// The response for a failed repository call, typed if the session expired
+fn repository_error(error: anyhow::Error) -> ApiResponse {
+    match error.downcast_ref::<CoreError>() {
+        Some(CoreError::SessionExpired { reason }) => ApiResponse::session_expired(*reason),
+        _ => ApiResponse::error(error),
+    }
+}
+
+fn shutdown_signal() -> &'static watch::Sender<bool> {
+    SHUTDOWN.get_or_init(|| watch::channel(false).0)
+}
+
+/// Resolves once shutdown has been signalled
+async fn stopped(shutdown: &mut watch::Receiver<bool>) {
+    let _ = shutdown.wait_for(|stopping| *stopping).await;
+}
+
+/// The next change for a subscribed connection; never resolves without a
+/// subscription
+///
+/// The subscription is dropped after the vault locks, since the connection's
+/// token is revoked with it.
+async fn next_change(changes: &mut Option<broadcast::Receiver<RepositoryEvent>>) -> ApiEvent {
+    let Some(receiver) = changes.as_mut() else {
+        return std::future::pending().await;
+    };
+    let event = match receiver.recv().await {
+        Ok(event) => event,
+        Err(broadcast::error::RecvError::Lagged(missed)) => return ApiEvent::Lagged { missed },
+        Err(broadcast::error::RecvError::Closed) => {
+            *changes = None;
+            return std::future::pending().await;
+        }
+    };
+    if event.is_lock() {
+        *changes = None;
+    }
+    ApiEvent::Changed(event)
+}
+
+/// Listen on `path`, readable and writable only by the current user
+///
+/// The directory is created if needed, and a socket left behind by a
+/// previous run is replaced.
+#[cfg(unix)]
+pub(super) fn bind_user_socket(
+    path: &std::path::Path,
+) -> std::io::Result<tokio::net::UnixListener> {
+    // A socket left behind by a previous run would make bind fail
+    let _ = std::fs::remove_file(path);
+
+    let listener = bind_new_user_socket(path)?;
+    listener.set_nonblocking(true)?;
+    tokio::net::UnixListener::from_std(listener)
+}
+
+/// Like [`bind_user_socket`], but fails with `AddrInUse` instead of
+/// replacing an existing socket
+#[cfg(unix)]
+pub(super) fn bind_new_user_socket(
+    path: &std::path::Path,
+) -> std::io::Result<std::os::unix::net::UnixListener> {
+    use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+
+    if let Some(dir) = path.parent() {
+        std::fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(dir)?;
+    }
+
+    let listener = std::os::unix::net::UnixListener::bind(path)?;
+    if let Err(e) = std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600)) {
+        warn!("Failed to restrict permissions of {:?}: {}", path, e);
+    }
+    Ok(listener)
+}
+
+/// Removes a socket bound with [`bind_user_socket`] once dropped
+#[cfg(unix)]
+pub(super) struct SocketFile(pub std::path::PathBuf);
+
+#[cfg(unix)]
+impl Drop for SocketFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// A response as one line of JSON
+fn response_line(response: &ApiResponse) -> Vec<u8> {
+    let mut out = serde_json::to_string(response)
+        .unwrap_or_else(|_| r#"{"ok":false,"error":"internal"}"#.into());
+    out.push('\n');
+    out.into_bytes()
+}
+
+/// Send one request to a running ZipLock instance and return its result
+///
+/// Used by `ziplock --api`; runs before the GUI starts, so it is blocking.
+#[cfg(unix)]
+pub fn send_request(request: &ApiRequest) -> Result<serde_json::Value, String> {
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::net::UnixStream;
+
+    let path = ApiServer::socket_path();
+    let mut stream = UnixStream::connect(&path).map_err(|e| {
+        format!(
+            "Could not connect to ZipLock at {:?} ({}). Is the app running with an API token issued?",
+            path, e
+        )
+    })?;
+    let mut line = serde_json::to_string(request).map_err(|e| e.to_string())?;
+    line.push('\n');
+    stream
+        .write_all(line.as_bytes())
+        .map_err(|e| e.to_string())?;
+
+    let mut response = String::new();
+    BufReader::new(stream)
+        .read_line(&mut response)
+        .map_err(|e| e.to_string())?;
+    match serde_json::from_str(&response).map_err(|e| format!("Invalid response: {}", e))? {
+        ApiResponse::Ok { result, .. } => Ok(result),
+        ApiResponse::Error { error, .. } => Err(error),
+        ApiResponse::Event { .. } => Err("ZipLock is shutting down".to_string()),
+    }
+}
+
+#[cfg(not(unix))]
+pub fn send_request(_request: &ApiRequest) -> Result<serde_json::Value, String> {
+    Err("The scripting API is not supported on this platform yet".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use ziplock_shared::utils::ApiScope;
+
+    #[tokio::test]
+    async fn test_requests_need_a_valid_token() {
+        let response = ApiServer::handle_line("not json").await.0;
+        assert!(matches!(response, ApiResponse::Error { .. }));
+
+        let response = ApiServer::handle_line(r#"{"token":"zlapi1_0000_bogus","method":"list"}"#)
+            .await
+            .0;
+        assert_eq!(response, ApiResponse::error("Invalid or revoked API token"));
+    }
+
+    #[tokio::test]
+    async fn test_reveal_needs_read_secrets_scope() {
+        let issued = get_api_tokens()
+            .lock()
+            .unwrap()
+            .issue("test", &[ApiScope::Read], Duration::minutes(5))
+            .unwrap();
+
+        let line = format!(
+            r#"{{"token":"{}","method":"get","id":"x","reveal":true}}"#,
+            issued.token
+        );
+        assert_eq!(
+            ApiServer::handle_line(&line).await.0,
+            ApiResponse::error("API token does not have the 'read_secrets' scope")
+        );
+
+        get_api_tokens().lock().unwrap().revoke(&issued.session.id);
+    }
+
+    #[tokio::test]
+    async fn test_subscribed_connection_gets_changes_until_locked() {
+        use ziplock_shared::core::EventBus;
+
+        let bus = EventBus::new(2);
+        let mut changes = Some(bus.subscribe());
+        let deleted = RepositoryEvent::CredentialDeleted {
+            id: "x".to_string(),
+        };
+        bus.publish(deleted.clone());
+        assert_eq!(
+            next_change(&mut changes).await,
+            ApiEvent::Changed(deleted.clone())
+        );
+
+        for _ in 0..3 {
+            bus.publish(deleted.clone());
+        }
+        assert_eq!(
+            next_change(&mut changes).await,
+            ApiEvent::Lagged { missed: 1 }
+        );
+        next_change(&mut changes).await;
+        next_change(&mut changes).await;
+
+        bus.publish(RepositoryEvent::Locked);
+        assert_eq!(
+            next_change(&mut changes).await,
+            ApiEvent::Changed(RepositoryEvent::Locked)
+        );
+        assert!(changes.is_none());
+        assert_eq!(bus.subscriber_count(), 0);
+    }
+
+    #[test]
+    fn test_expired_session_gets_a_typed_error() {
+        use ziplock_shared::core::SessionExpiry;
+        use ziplock_shared::utils::ApiErrorCode;
+
+        let expired = anyhow::Error::from(CoreError::SessionExpired {
+            reason: SessionExpiry::Lifetime,
+        });
+        assert!(matches!(
+            repository_error(expired),
+            ApiResponse::Error {
+                code: Some(ApiErrorCode::SessionExpired),
+                ..
+            }
+        ));
+        assert_eq!(
+            repository_error(anyhow::anyhow!("No repository is open")),
+            ApiResponse::error("No repository is open")
+        );
+    }
+}
@@ -0,0 +1,222 @@
+//! Relays scripting API approval prompts to the window
+//!
+//! When the [`ApprovalPolicy`] says a read needs the user's approval, the API
+//! server parks the request in the [`ApprovalBroker`] and waits; the
+//! ssh-agent does the same before signing with a key marked for
+//! confirmation. The broker
+//! hands an [`ApprovalRequest`] to the window, which shows it until the user
+//! decides or [`APPROVAL_TIMEOUT`] passes. Nobody watching, a timeout or the
+//! vault locking all count as a refusal.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock, RwLock};
+use std::time::Duration;
+
+use tokio::sync::{broadcast, oneshot};
+use tracing::{debug, info};
+
+use ziplock_shared::models::CredentialRecord;
+use ziplock_shared::utils::{ApprovalDecision, ApprovalPolicy, ApprovalPurpose, ApprovalRequest};
+
+/// How long a script waits for the user before its read is refused
+pub const APPROVAL_TIMEOUT: Duration = Duration::from_secs(60);
+
+static BROKER: OnceLock<ApprovalBroker> = OnceLock::new();
+
+/// Get the global approval broker
+pub fn get_approval_broker() -> &'static ApprovalBroker {
+    BROKER.get_or_init(ApprovalBroker::new)
+}
+
+/// Pending approval requests and the policy deciding which reads need one
+pub struct ApprovalBroker {
+    policy: RwLock<ApprovalPolicy>,
+    pending: Mutex<HashMap<u64, oneshot::Sender<ApprovalDecision>>>,
+    next_id: AtomicU64,
+    prompts: broadcast::Sender<ApprovalRequest>,
+}
+
+impl ApprovalBroker {
+    fn new() -> Self {
+        Self {
+            policy: RwLock::new(ApprovalPolicy::default()),
+            pending: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(1),
+            prompts: broadcast::channel(16).0,
+        }
+    }
+
+    /// Replace the policy, e.g. after the settings were loaded or saved
+    pub fn set_policy(&self, policy: ApprovalPolicy) {
+        *self.policy.write().unwrap() = policy;
+    }
+
+    /// The policy including grants made since it was set
+    pub fn policy(&self) -> ApprovalPolicy {
+        self.policy.read().unwrap().clone()
+    }
+
+    /// Ask the user whether `client` may read `credential`, unless the
+    /// policy allows it without asking
+    pub async fn authorize(
+        &self,
+        client: &str,
+        credential: &CredentialRecord,
+        reveal: bool,
+    ) -> ApprovalDecision {
+        if !self
+            .policy
+            .read()
+            .unwrap()
+            .requires_approval(client, credential, reveal)
+        {
+            return ApprovalDecision::AllowOnce;
+        }
+
+        let request = ApprovalRequest {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            client: client.to_string(),
+            credential_id: credential.id.clone(),
+            credential_title: credential.title.clone(),
+            reveal,
+            purpose: ApprovalPurpose::Read,
+        };
+        self.ask(request).await
+    }
+
+    /// Ask the user whether `client` may sign with the SSH key stored in
+    /// `credential_id`; there are no standing approvals for signatures
+    pub async fn confirm_ssh_signature(
+        &self,
+        client: &str,
+        credential_id: &str,
+        credential_title: &str,
+    ) -> ApprovalDecision {
+        let request = ApprovalRequest {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            client: client.to_string(),
+            credential_id: credential_id.to_string(),
+            credential_title: credential_title.to_string(),
+            reveal: false,
+            purpose: ApprovalPurpose::SshSign,
+        };
+        self.ask(request).await
+    }
+
+    async fn ask(&self, request: ApprovalRequest) -> ApprovalDecision {
+        let (sender, receiver) = oneshot::channel();
+        self.pending.lock().unwrap().insert(request.id, sender);
+        let id = request.id;
+        if self.prompts.send(request).is_err() {
+            debug!("No window to ask for approval, refusing");
+            self.pending.lock().unwrap().remove(&id);
+            return ApprovalDecision::Deny;
+        }
+
+        let decision = match tokio::time::timeout(APPROVAL_TIMEOUT, receiver).await {
+            Ok(Ok(decision)) => decision,
+            Ok(Err(_)) => ApprovalDecision::Deny,
+            Err(_) => {
+                info!("Approval request {} timed out", id);
+                ApprovalDecision::Deny
+            }
+        };
+        self.pending.lock().unwrap().remove(&id);
+        decision
+    }
+
+    /// Pass the user's decision on to the waiting script
+    ///
+    /// "Allow always" is added to the policy before the script continues.
+    /// Returns `false` if the request is no longer waiting.
+    pub fn respond(&self, request: &ApprovalRequest, decision: ApprovalDecision) -> bool {
+        let Some(sender) = self.pending.lock().unwrap().remove(&request.id) else {
+            return false;
+        };
+        if decision == ApprovalDecision::AllowAlways {
+            self.policy.write().unwrap().grant(request);
+        }
+        sender.send(decision).is_ok()
+    }
+
+    /// Refuse every waiting request, e.g. because the vault locked
+    pub fn deny_all(&self) {
+        for (_, sender) in self.pending.lock().unwrap().drain() {
+            let _ = sender.send(ApprovalDecision::Deny);
+        }
+    }
+
+    /// Subscription delivering each request that needs the user's decision
+    pub fn prompts() -> iced::Subscription<ApprovalRequest> {
+        iced::Subscription::run(prompt_stream)
+    }
+}
+
+fn prompt_stream() -> impl iced::futures::Stream<Item = ApprovalRequest> {
+    use iced::futures::SinkExt;
+
+    iced::stream::channel(16, |mut output| async move {
+        let mut receiver = get_approval_broker().prompts.subscribe();
+        loop {
+            match receiver.recv().await {
+                Ok(request) => {
+                    if output.send(request).await.is_err() {
+                        break;
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_reads_wait_for_the_window() {
+        let broker = ApprovalBroker::new();
+        broker.set_policy(ApprovalPolicy {
+            tags: vec!["banking".to_string()],
+            ..Default::default()
+        });
+        let mut bank = CredentialRecord::new("Bank".to_string(), "login".to_string());
+        bank.add_tag("banking");
+        let forum = CredentialRecord::new("Forum".to_string(), "login".to_string());
+
+        assert!(broker.authorize("script", &forum, true).await.is_allowed());
+        // No window listening
+        assert_eq!(
+            broker.authorize("script", &bank, false).await,
+            ApprovalDecision::Deny
+        );
+
+        let mut prompts = broker.prompts.subscribe();
+        let (decision, ()) = tokio::join!(broker.authorize("script", &bank, false), async {
+            let request = prompts.recv().await.unwrap();
+            assert_eq!(request.credential_title, "Bank");
+            assert!(broker.respond(&request, ApprovalDecision::AllowAlways));
+            assert!(!broker.respond(&request, ApprovalDecision::Deny));
+        });
+        assert_eq!(decision, ApprovalDecision::AllowAlways);
+        assert_eq!(broker.policy().grants.len(), 1);
+        assert!(broker.authorize("script", &bank, false).await.is_allowed());
+
+        let (decision, ()) = tokio::join!(broker.authorize("other", &bank, false), async {
+            prompts.recv().await.unwrap();
+            broker.deny_all();
+        });
+        assert_eq!(decision, ApprovalDecision::Deny);
+
+        let (decision, ()) = tokio::join!(broker.confirm_ssh_signature("git", "1", "Key"), async {
+            let request = prompts.recv().await.unwrap();
+            assert_eq!(request.purpose, ApprovalPurpose::SshSign);
+            assert!(broker.respond(&request, ApprovalDecision::AllowAlways));
+        });
+        assert!(decision.is_allowed());
+        assert_eq!(broker.policy().grants.len(), 1);
+    }
+}
@@ -0,0 +1,1048 @@
+//! Auto-type service that types credential values into the focused window
+//!
+//! Sequences use KeePass-style placeholders such as `{USERNAME}{TAB}{PASSWORD}{ENTER}`.
+//! Keystrokes are injected with `wtype` or `ydotool` on Wayland, `xdotool` on X11
+//! and `SendInput` on Windows.
+//!
+//! A [`TypingCadence`] turns the resolved actions into the keystrokes actually
+//! sent: optionally one character at a time with random pauses, and with
+//! checks that the window typed into still has focus.
+
+use rand::Rng;
+use std::time::Duration;
+use tracing::debug;
+use ziplock_shared::config::AutoTypeConfig;
+use ziplock_shared::models::{CredentialRecord, FieldType};
+use ziplock_shared::utils::TotpGenerator;
+
+/// Sequence used when a credential does not define its own
+pub const DEFAULT_SEQUENCE: &str = "{USERNAME}{TAB}{PASSWORD}{ENTER}";
+
+/// Special keys that can appear in an auto-type sequence
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AutoTypeKey {
+    Tab,
+    Enter,
+    Space,
+    Backspace,
+    Escape,
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl AutoTypeKey {
+    fn from_placeholder(name: &str) -> Option<Self> {
+        match name {
+            "TAB" => Some(Self::Tab),
+            "ENTER" => Some(Self::Enter),
+            "SPACE" => Some(Self::Space),
+            "BACKSPACE" | "BS" => Some(Self::Backspace),
+            "ESC" => Some(Self::Escape),
+            "UP" => Some(Self::Up),
+            "DOWN" => Some(Self::Down),
+            "LEFT" => Some(Self::Left),
+            "RIGHT" => Some(Self::Right),
+            _ => None,
+        }
+    }
+
+    /// X keysym name, understood by both `wtype -k` and `xdotool key`
+    #[cfg_attr(not(unix), allow(dead_code))]
+    fn keysym(self) -> &'static str {
+        match self {
+            Self::Tab => "Tab",
+            Self::Enter => "Return",
+            Self::Space => "space",
+            Self::Backspace => "BackSpace",
+            Self::Escape => "Escape",
+            Self::Up => "Up",
+            Self::Down => "Down",
+            Self::Left => "Left",
+            Self::Right => "Right",
+        }
+    }
+
+    /// Linux input event code, used by `ydotool key`
+    #[cfg_attr(not(unix), allow(dead_code))]
+    fn evdev_code(self) -> u16 {
+        match self {
+            Self::Tab => 15,
+            Self::Enter => 28,
+            Self::Space => 57,
+            Self::Backspace => 14,
+            Self::Escape => 1,
+            Self::Up => 103,
+            Self::Down => 108,
+            Self::Left => 105,
+            Self::Right => 106,
+        }
+    }
+}
+
+/// A single step of a resolved auto-type sequence
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AutoTypeAction {
+    Text(String),
+    Key(AutoTypeKey),
+    Delay(u64),
+}
+
+/// Parsed sequence token, before credential values are substituted
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Literal(String),
+    Field(String),
+    Key(AutoTypeKey),
+    Delay(u64),
+}
+
+/// A parsed auto-type sequence
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AutoTypeSequence {
+    tokens: Vec<Token>,
+}
+
+impl AutoTypeSequence {
+    /// Parse a sequence such as `{USERNAME}{TAB}{PASSWORD}{ENTER}`
+    ///
+    /// Literal braces are written as `{{}` and `{}}`, custom fields as
+    /// `{S:field_name}` and pauses as `{DELAY 500}` (milliseconds).
+    pub fn parse(sequence: &str) -> Result<Self, AutoTypeError> {
+        let mut tokens = Vec::new();
+        let mut literal = String::new();
+        let mut rest = sequence;
+
+        while let Some(start) = rest.find('{') {
+            literal.push_str(&rest[..start]);
+            rest = &rest[start + 1..];
+
+            // `{}}` is a literal closing brace, so the search starts one character in
+            let end = rest
+                .char_indices()
+                .skip(1)
+                .find(|(_, c)| *c == '}')
+                .map(|(i, _)| i)
+                .or_else(|| rest.starts_with('}').then_some(0))
+                .ok_or_else(|| {
+                    AutoTypeError::InvalidSequence(format!(
+                        "unclosed placeholder in '{}'",
+                        sequence
+                    ))
+                })?;
+            let placeholder = &rest[..end];
+            rest = &rest[end + 1..];
+
+            if placeholder == "{" || placeholder == "}" {
+                literal.push_str(placeholder);
+                continue;
+            }
+
+            let token = Self::parse_placeholder(placeholder)?;
+            if !literal.is_empty() {
+                tokens.push(Token::Literal(std::mem::take(&mut literal)));
+            }
+            tokens.push(token);
+        }
+
+        if rest.contains('}') {
+            return Err(AutoTypeError::InvalidSequence(format!(
+                "unmatched '}}' in '{}'",
+                sequence
+            )));
+        }
+        literal.push_str(rest);
+        if !literal.is_empty() {
+            tokens.push(Token::Literal(literal));
+        }
+
+        Ok(Self { tokens })
+    }
+
+    /// The sequence configured on a credential, or the default one
+    pub fn for_credential(credential: &CredentialRecord) -> Result<Self, AutoTypeError> {
+        match credential.auto_type.as_deref() {
+            Some(sequence) if !sequence.trim().is_empty() => Self::parse(sequence),
+            _ => Self::parse(DEFAULT_SEQUENCE),
+        }
+    }
+
+    fn parse_placeholder(placeholder: &str) -> Result<Token, AutoTypeError> {
+        if let Some(name) = placeholder.strip_prefix("S:") {
+            return Ok(Token::Field(name.to_string()));
+        }
+
+        let upper = placeholder.trim().to_uppercase();
+        if let Some(ms) = upper.strip_prefix("DELAY ") {
+            return ms.trim().parse().map(Token::Delay).map_err(|_| {
+                AutoTypeError::InvalidSequence(format!("invalid delay '{}'", placeholder))
+            });
+        }
+        if let Some(key) = AutoTypeKey::from_placeholder(&upper) {
+            return Ok(Token::Key(key));
+        }
+
+        match upper.as_str() {
+            "USERNAME" | "PASSWORD" | "TITLE" | "URL" | "NOTES" | "TOTP" => Ok(Token::Field(upper)),
+            _ => Err(AutoTypeError::InvalidSequence(format!(
+                "unknown placeholder '{{{}}}'",
+                placeholder
+            ))),
+        }
+    }
+
+    /// Substitute credential values, producing the keystrokes to send
+    ///
+    /// Placeholders for values the credential does not have type nothing.
+    pub fn resolve(
+        &self,
+        credential: &CredentialRecord,
+    ) -> Result<Vec<AutoTypeAction>, AutoTypeError> {
+        let mut actions: Vec<AutoTypeAction> = Vec::new();
+
+        for token in &self.tokens {
+            let action = match token {
+                Token::Literal(text) => AutoTypeAction::Text(text.clone()),
+                Token::Key(key) => AutoTypeAction::Key(*key),
+                Token::Delay(ms) => AutoTypeAction::Delay(*ms),
+                Token::Field(name) => match Self::field_value(credential, name)? {
+                    Some(value) if !value.is_empty() => AutoTypeAction::Text(value),
+                    _ => continue,
+                },
+            };
+
+            // Merge adjacent text so each backend call types a whole run
+            match (actions.last_mut(), action) {
+                (Some(AutoTypeAction::Text(previous)), AutoTypeAction::Text(text)) => {
+                    previous.push_str(&text)
+                }
+                (_, action) => actions.push(action),
+            }
+        }
+
+        Ok(actions)
+    }
+
+    fn field_value(
+        credential: &CredentialRecord,
+        name: &str,
+    ) -> Result<Option<String>, AutoTypeError> {
+        let by_type = |field_type: FieldType| {
+            credential
+                .fields
+                .values()
+                .find(|f| f.field_type == field_type)
+                .map(|f| f.value.clone())
+        };
+        let named_or_typed = |field_name: &str, field_type: FieldType| {
+            credential
+                .get_field(field_name)
+                .map(|f| f.value.clone())
+                .or_else(|| by_type(field_type))
+        };
+
+        let value = match name {
+            "USERNAME" => named_or_typed("username", FieldType::Username),
+            "PASSWORD" => named_or_typed("password", FieldType::Password),
+            "URL" => named_or_typed("url", FieldType::Url),
+            "TITLE" => Some(credential.title.clone()),
+            "NOTES" => credential.notes.clone(),
+            "TOTP" => {
+                let field = credential.get_field("totp").or_else(|| {
+                    credential
+                        .fields
+                        .values()
+                        .find(|f| f.field_type == FieldType::TotpSecret)
+                });
+                match field {
+                    Some(field) => Some(
+                        TotpGenerator::from_field(field)
+                            .and_then(|totp| totp.generate())
+                            .map_err(|e| AutoTypeError::InvalidSequence(e.to_string()))?,
+                    ),
+                    None => None,
+                }
+            }
+            custom => credential.get_field(custom).map(|f| f.value.clone()),
+        };
+
+        Ok(value)
+    }
+}
+
+/// A mechanism for injecting keystrokes into the focused window
+pub trait AutoTypeBackend: Send + Sync {
+    /// Short name used in logs and error messages
+    fn name(&self) -> &'static str;
+
+    /// Type a run of text
+    fn type_text(&self, text: &str) -> Result<(), AutoTypeError>;
+
+    /// Press and release a single special key
+    fn press_key(&self, key: AutoTypeKey) -> Result<(), AutoTypeError>;
+
+    /// Identifier of the window that has keyboard focus, if the session
+    /// lets the backend find out
+    fn focused_window(&self) -> Option<String> {
+        None
+    }
+}
+
+/// External keystroke injection tools supported on Linux and the BSDs
+#[cfg(unix)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputTool {
+    /// `wtype`, for wlroots-based Wayland compositors
+    Wtype,
+    /// `ydotool`, which works under any compositor through uinput
+    Ydotool,
+    /// `xdotool`, for X11 sessions
+    Xdotool,
+}
+
+#[cfg(unix)]
+impl InputTool {
+    fn program(self) -> &'static str {
+        match self {
+            Self::Wtype => "wtype",
+            Self::Ydotool => "ydotool",
+            Self::Xdotool => "xdotool",
+        }
+    }
+
+    /// Arguments for typing text read from stdin, which keeps secrets out of
+    /// the process list
+    fn type_args(self) -> Vec<String> {
+        let args: &[&str] = match self {
+            Self::Wtype => &["-"],
+            Self::Ydotool => &["type", "--file", "-"],
+            Self::Xdotool => &["type", "--clearmodifiers", "--file", "-"],
+        };
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    fn key_args(self, key: AutoTypeKey) -> Vec<String> {
+        match self {
+            Self::Wtype => vec!["-k".to_string(), key.keysym().to_string()],
+            Self::Ydotool => {
+                let code = key.evdev_code();
+                vec![
+                    "key".to_string(),
+                    format!("{}:1", code),
+                    format!("{}:0", code),
+                ]
+            }
+            Self::Xdotool => vec![
+                "key".to_string(),
+                "--clearmodifiers".to_string(),
+                key.keysym().to_string(),
+            ],
+        }
+    }
+}
+
+/// Backend that shells out to one of the [`InputTool`]s
+#[cfg(unix)]
+pub struct CommandBackend {
+    tool: InputTool,
+}
+
+#[cfg(unix)]
+impl CommandBackend {
+    pub fn new(tool: InputTool) -> Self {
+        Self { tool }
+    }
+
+    fn run(&self, args: &[String], stdin: Option<&str>) -> Result<(), AutoTypeError> {
+        use std::io::Write;
+        use std::process::{Command, Stdio};
+
+        let mut child = Command::new(self.tool.program())
+            .args(args)
+            .stdin(if stdin.is_some() {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            })
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        if let (Some(input), Some(mut pipe)) = (stdin, child.stdin.take()) {
+            pipe.write_all(input.as_bytes())?;
+        }
+
+        let output = child.wait_with_output()?;
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(AutoTypeError::BackendFailed {
+                backend: self.name(),
+                message: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            })
+        }
+    }
+}
+
+#[cfg(unix)]
+impl AutoTypeBackend for CommandBackend {
+    fn name(&self) -> &'static str {
+        self.tool.program()
+    }
+
+    fn type_text(&self, text: &str) -> Result<(), AutoTypeError> {
+        self.run(&self.tool.type_args(), Some(text))
+    }
+
+    fn press_key(&self, key: AutoTypeKey) -> Result<(), AutoTypeError> {
+        self.run(&self.tool.key_args(key), None)
+    }
+
+    /// Only `xdotool` can ask; Wayland does not tell clients which window
+    /// has focus
+    fn focused_window(&self) -> Option<String> {
+        if self.tool != InputTool::Xdotool {
+            return None;
+        }
+        let output = std::process::Command::new(self.tool.program())
+            .arg("getactivewindow")
+            .stderr(std::process::Stdio::null())
+            .output()
+            .ok()?;
+        let window = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        (output.status.success() && !window.is_empty()).then_some(window)
+    }
+}
+
+/// Backend using the Win32 `SendInput` API
+#[cfg(windows)]
+pub struct SendInputBackend;
+
+#[cfg(windows)]
+impl SendInputBackend {
+    fn send(
+        inputs: &[windows::Win32::UI::Input::KeyboardAndMouse::INPUT],
+    ) -> Result<(), AutoTypeError> {
+        use windows::Win32::UI::Input::KeyboardAndMouse::{SendInput, INPUT};
+
+        let sent = unsafe { SendInput(inputs, std::mem::size_of::<INPUT>() as i32) };
+        if sent as usize == inputs.len() {
+            Ok(())
+        } else {
+            Err(AutoTypeError::BackendFailed {
+                backend: "SendInput",
+                message: std::io::Error::last_os_error().to_string(),
+            })
+        }
+    }
+
+    fn keyboard_input(
+        vk: windows::Win32::UI::Input::KeyboardAndMouse::VIRTUAL_KEY,
+        scan: u16,
+        flags: u32,
+    ) -> windows::Win32::UI::Input::KeyboardAndMouse::INPUT {
+        use windows::Win32::UI::Input::KeyboardAndMouse::{
+            INPUT, INPUT_0, INPUT_KEYBOARD, KEYBDINPUT, KEYBD_EVENT_FLAGS,
+        };
+
+        INPUT {
+            r#type: INPUT_KEYBOARD,
+            Anonymous: INPUT_0 {
+                ki: KEYBDINPUT {
+                    wVk: vk,
+                    wScan: scan,
+                    dwFlags: KEYBD_EVENT_FLAGS(flags),
+                    time: 0,
+                    dwExtraInfo: 0,
+                },
+            },
+        }
+    }
+}
+
+#[cfg(windows)]
+impl AutoTypeBackend for SendInputBackend {
+    fn name(&self) -> &'static str {
+        "SendInput"
+    }
+
+    fn type_text(&self, text: &str) -> Result<(), AutoTypeError> {
+        use windows::Win32::UI::Input::KeyboardAndMouse::{
+            KEYEVENTF_KEYUP, KEYEVENTF_UNICODE, VIRTUAL_KEY,
+        };
+
+        let inputs: Vec<_> = text
+            .encode_utf16()
+            .flat_map(|unit| {
+                [
+                    Self::keyboard_input(VIRTUAL_KEY(0), unit, KEYEVENTF_UNICODE.0),
+                    Self::keyboard_input(
+                        VIRTUAL_KEY(0),
+                        unit,
+                        KEYEVENTF_UNICODE.0 | KEYEVENTF_KEYUP.0,
+                    ),
+                ]
+            })
+            .collect();
+        Self::send(&inputs)
+    }
+
+    fn press_key(&self, key: AutoTypeKey) -> Result<(), AutoTypeError> {
+        use windows::Win32::UI::Input::KeyboardAndMouse::{
+            KEYEVENTF_KEYUP, VK_BACK, VK_DOWN, VK_ESCAPE, VK_LEFT, VK_RETURN, VK_RIGHT, VK_SPACE,
+            VK_TAB, VK_UP,
+        };
+
+        let vk = match key {
+            AutoTypeKey::Tab => VK_TAB,
+            AutoTypeKey::Enter => VK_RETURN,
+            AutoTypeKey::Space => VK_SPACE,
+            AutoTypeKey::Backspace => VK_BACK,
+            AutoTypeKey::Escape => VK_ESCAPE,
+            AutoTypeKey::Up => VK_UP,
+            AutoTypeKey::Down => VK_DOWN,
+            AutoTypeKey::Left => VK_LEFT,
+            AutoTypeKey::Right => VK_RIGHT,
+        };
+        Self::send(&[
+            Self::keyboard_input(vk, 0, 0),
+            Self::keyboard_input(vk, 0, KEYEVENTF_KEYUP.0),
+        ])
+    }
+
+    fn focused_window(&self) -> Option<String> {
+        use windows::Win32::UI::WindowsAndMessaging::GetForegroundWindow;
+
+        let window = unsafe { GetForegroundWindow() };
+        (window.0 != 0).then(|| format!("{:x}", window.0))
+    }
+}
+
+/// Pick the keystroke injection backend for the current session
+pub fn detect_backend() -> Result<Box<dyn AutoTypeBackend>, AutoTypeError> {
+    #[cfg(windows)]
+    {
+        Ok(Box::new(SendInputBackend))
+    }
+
+    #[cfg(unix)]
+    {
+        let wayland = std::env::var_os("WAYLAND_DISPLAY").is_some();
+        let x11 = std::env::var_os("DISPLAY").is_some();
+        select_tool(wayland, x11, |program| find_in_path(program).is_some())
+            .map(|tool| Box::new(CommandBackend::new(tool)) as Box<dyn AutoTypeBackend>)
+            .ok_or(AutoTypeError::NoBackend)
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    {
+        Err(AutoTypeError::NoBackend)
+    }
+}
+
+/// Preferred tools per session type; `ydotool` is the fallback everywhere
+#[cfg(unix)]
+fn select_tool(wayland: bool, x11: bool, available: impl Fn(&str) -> bool) -> Option<InputTool> {
+    let candidates: &[InputTool] = if wayland {
+        &[InputTool::Wtype, InputTool::Ydotool]
+    } else if x11 {
+        &[InputTool::Xdotool, InputTool::Ydotool]
+    } else {
+        &[InputTool::Ydotool]
+    };
+
+    candidates
+        .iter()
+        .copied()
+        .find(|tool| available(tool.program()))
+}
+
+#[cfg(unix)]
+fn find_in_path(program: &str) -> Option<std::path::PathBuf> {
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path)
+        .map(|dir| dir.join(program))
+        .find(|candidate| candidate.is_file())
+}
+
+/// One keystroke or check, as sent to the backend
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TypingStep {
+    Text(String),
+    Key(AutoTypeKey),
+    Pause(u64),
+    /// Stop unless the window focused at the start still has focus
+    CheckFocus,
+}
+
+/// Chance of an extra focus check after any one character
+const DECOY_CHECK_CHANCE: f64 = 0.15;
+
+/// Pacing and focus checks applied to resolved actions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TypingCadence {
+    randomize: bool,
+    min_delay_ms: u64,
+    max_delay_ms: u64,
+    verify_focus: bool,
+}
+
+impl Default for TypingCadence {
+    fn default() -> Self {
+        Self::from_config(&AutoTypeConfig::default())
+    }
+}
+
+impl TypingCadence {
+    pub fn from_config(config: &AutoTypeConfig) -> Self {
+        let (min_delay_ms, max_delay_ms) = config.key_delay_range();
+        Self {
+            randomize: config.randomize_cadence,
+            min_delay_ms,
+            max_delay_ms,
+            verify_focus: config.verify_focus,
+        }
+    }
+
+    /// The steps that type `actions`
+    ///
+    /// Focus is checked before every value and, when typing a character at
+    /// a time, after randomly chosen characters too, so a window raised in
+    /// the middle of a password is caught before the rest goes into it.
+    pub fn plan(&self, actions: &[AutoTypeAction], rng: &mut impl Rng) -> Vec<TypingStep> {
+        let mut steps = Vec::new();
+        for action in actions {
+            match action {
+                AutoTypeAction::Text(text) => {
+                    if self.verify_focus {
+                        steps.push(TypingStep::CheckFocus);
+                    }
+                    if !self.randomize {
+                        steps.push(TypingStep::Text(text.clone()));
+                        continue;
+                    }
+                    for c in text.chars() {
+                        steps.push(TypingStep::Text(c.to_string()));
+                        self.pause(&mut steps, rng);
+                        if self.verify_focus && rng.gen_bool(DECOY_CHECK_CHANCE) {
+                            steps.push(TypingStep::CheckFocus);
+                        }
+                    }
+                }
+                AutoTypeAction::Key(key) => {
+                    steps.push(TypingStep::Key(*key));
+                    self.pause(&mut steps, rng);
+                }
+                AutoTypeAction::Delay(ms) => steps.push(TypingStep::Pause(*ms)),
+            }
+        }
+
+        while matches!(
+            steps.last(),
+            Some(TypingStep::Pause(_)) | Some(TypingStep::CheckFocus)
+        ) {
+            steps.pop();
+        }
+        steps
+    }
+
+    fn pause(&self, steps: &mut Vec<TypingStep>, rng: &mut impl Rng) {
+        if self.randomize {
+            steps.push(TypingStep::Pause(
+                rng.gen_range(self.min_delay_ms..=self.max_delay_ms),
+            ));
+        }
+    }
+}
+
+/// What an auto-type would do, with typed characters masked
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AutoTypePreview {
+    /// Keys in order: `•` for each character, `[Tab]` and the like for keys
+    pub outline: String,
+    pub keystrokes: usize,
+    pub focus_checks: usize,
+    /// Time spent in pauses, in milliseconds, including the start delay
+    pub duration_ms: u64,
+}
+
+impl AutoTypePreview {
+    fn new(steps: &[TypingStep], start_delay: Duration) -> Self {
+        let mut preview = Self {
+            outline: String::new(),
+            keystrokes: 0,
+            focus_checks: 0,
+            duration_ms: start_delay.as_millis() as u64,
+        };
+        for step in steps {
+            match step {
+                TypingStep::Text(text) => {
+                    let count = text.chars().count();
+                    preview.outline.extend(std::iter::repeat_n('•', count));
+                    preview.keystrokes += count;
+                }
+                TypingStep::Key(key) => {
+                    preview.outline.push_str(&format!("[{:?}]", key));
+                    preview.keystrokes += 1;
+                }
+                TypingStep::Pause(ms) => preview.duration_ms += ms,
+                TypingStep::CheckFocus => preview.focus_checks += 1,
+            }
+        }
+        preview
+    }
+
+    /// One-line description, e.g. for a notification
+    pub fn summary(&self) -> String {
+        format!(
+            "{} — {} keys over about {:.1} s, {} focus checks",
+            self.outline,
+            self.keystrokes,
+            self.duration_ms as f64 / 1000.0,
+            self.focus_checks
+        )
+    }
+}
+
+/// Send planned steps, stopping if focus moves away from `window`
+fn send_steps(
+    backend: &dyn AutoTypeBackend,
+    steps: &[TypingStep],
+    window: Option<&str>,
+) -> Result<(), AutoTypeError> {
+    for step in steps {
+        match step {
+            TypingStep::Text(text) => backend.type_text(text)?,
+            TypingStep::Key(key) => backend.press_key(*key)?,
+            TypingStep::Pause(ms) => std::thread::sleep(Duration::from_millis(*ms)),
+            TypingStep::CheckFocus => {
+                if let Some(window) = window {
+                    if backend.focused_window().as_deref() != Some(window) {
+                        return Err(AutoTypeError::FocusChanged);
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Auto-type service
+#[derive(Debug, Clone)]
+pub struct AutoTypeService {
+    /// Pause before typing, giving focus time to return to the target window
+    start_delay: Duration,
+    cadence: TypingCadence,
+}
+
+impl AutoTypeService {
+    /// Create a new auto-type service
+    pub fn new() -> Self {
+        Self {
+            start_delay: Duration::from_millis(500),
+            cadence: TypingCadence::default(),
+        }
+    }
+
+    /// Pace keystrokes and check focus as configured
+    pub fn with_config(mut self, config: &AutoTypeConfig) -> Self {
+        self.cadence = TypingCadence::from_config(config);
+        self
+    }
+
+    /// Override the pause before the first keystroke
+    #[allow(dead_code)]
+    pub fn with_start_delay(mut self, delay: Duration) -> Self {
+        self.start_delay = delay;
+        self
+    }
+
+    /// Type the credential's auto-type sequence into the focused window
+    pub async fn perform(&self, credential: CredentialRecord) -> Result<(), AutoTypeError> {
+        let actions = AutoTypeSequence::for_credential(&credential)?.resolve(&credential)?;
+        let steps = self.cadence.plan(&actions, &mut rand::thread_rng());
+        let backend = detect_backend()?;
+        let start_delay = self.start_delay;
+
+        debug!(
+            "Auto-typing {} steps for '{}' using {}",
+            steps.len(),
+            credential.title,
+            backend.name()
+        );
+
+        tokio::task::spawn_blocking(move || {
+            std::thread::sleep(start_delay);
+            let window = backend.focused_window();
+            if window.is_none() && steps.contains(&TypingStep::CheckFocus) {
+                debug!("{} cannot report the focused window", backend.name());
+            }
+            send_steps(backend.as_ref(), &steps, window.as_deref())
+        })
+        .await?
+    }
+
+    /// Work out what [`perform`](Self::perform) would type, without typing
+    pub fn preview(&self, credential: &CredentialRecord) -> Result<AutoTypePreview, AutoTypeError> {
+        let actions = AutoTypeSequence::for_credential(credential)?.resolve(credential)?;
+        let steps = self.cadence.plan(&actions, &mut rand::thread_rng());
+        Ok(AutoTypePreview::new(&steps, self.start_delay))
+    }
+}
+
+impl Default for AutoTypeService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Errors that can occur during auto-type
+#[derive(Debug, thiserror::Error)]
+pub enum AutoTypeError {
+    /// The auto-type sequence could not be parsed or resolved
+    #[error("Invalid auto-type sequence: {0}")]
+    InvalidSequence(String),
+
+    /// No keystroke injection tool is available for this session
+    #[error("No auto-type backend available; install wtype, ydotool or xdotool")]
+    NoBackend,
+
+    /// Another window took focus while typing
+    #[error("Auto-type stopped because another window took focus")]
+    FocusChanged,
+
+    /// The backend reported a failure
+    #[error("Auto-type via {backend} failed: {message}")]
+    BackendFailed {
+        backend: &'static str,
+        message: String,
+    },
+
+    /// Error launching or talking to an external tool
+    #[error("Auto-type I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// Error from async task execution
+    #[error("Async task error: {0}")]
+    TaskError(#[from] tokio::task::JoinError),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ziplock_shared::models::CredentialField;
+
+    fn login() -> CredentialRecord {
+        let mut credential = CredentialRecord::new("Example".to_string(), "login".to_string());
+        credential.set_field("username", CredentialField::username("jane"));
+        credential.set_field("password", CredentialField::password("s3cret"));
+        credential.set_field("account_id", CredentialField::text("4242"));
+        credential
+    }
+
+    #[test]
+    fn test_default_sequence() {
+        let actions = AutoTypeSequence::for_credential(&login())
+            .unwrap()
+            .resolve(&login())
+            .unwrap();
+        assert_eq!(
+            actions,
+            vec![
+                AutoTypeAction::Text("jane".to_string()),
+                AutoTypeAction::Key(AutoTypeKey::Tab),
+                AutoTypeAction::Text("s3cret".to_string()),
+                AutoTypeAction::Key(AutoTypeKey::Enter),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_custom_sequence() {
+        let mut credential = login();
+        credential.auto_type =
+            Some("{S:account_id}{tab}{USERNAME}!{{}x{}}{DELAY 250}{ENTER}".into());
+        let actions = AutoTypeSequence::for_credential(&credential)
+            .unwrap()
+            .resolve(&credential)
+            .unwrap();
+        assert_eq!(
+            actions,
+            vec![
+                AutoTypeAction::Text("4242".to_string()),
+                AutoTypeAction::Key(AutoTypeKey::Tab),
+                AutoTypeAction::Text("jane!{x}".to_string()),
+                AutoTypeAction::Delay(250),
+                AutoTypeAction::Key(AutoTypeKey::Enter),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_missing_values_type_nothing() {
+        let credential = CredentialRecord::new("Empty".to_string(), "login".to_string());
+        let actions = AutoTypeSequence::parse(DEFAULT_SEQUENCE)
+            .unwrap()
+            .resolve(&credential)
+            .unwrap();
+        assert_eq!(
+            actions,
+            vec![
+                AutoTypeAction::Key(AutoTypeKey::Tab),
+                AutoTypeAction::Key(AutoTypeKey::Enter),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_sequences() {
+        for sequence in ["{USERNAME", "{BOGUS}", "{DELAY soon}", "user}"] {
+            assert!(
+                matches!(
+                    AutoTypeSequence::parse(sequence),
+                    Err(AutoTypeError::InvalidSequence(_))
+                ),
+                "{} should be rejected",
+                sequence
+            );
+        }
+    }
+
+    #[test]
+    fn test_cadence_plan() {
+        use rand::SeedableRng;
+
+        let actions = AutoTypeSequence::for_credential(&login())
+            .unwrap()
+            .resolve(&login())
+            .unwrap();
+        let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+
+        // Without a cadence each value is typed at once, after a focus check
+        let steps = TypingCadence::default().plan(&actions, &mut rng);
+        assert_eq!(
+            steps,
+            vec![
+                TypingStep::CheckFocus,
+                TypingStep::Text("jane".to_string()),
+                TypingStep::Key(AutoTypeKey::Tab),
+                TypingStep::CheckFocus,
+                TypingStep::Text("s3cret".to_string()),
+                TypingStep::Key(AutoTypeKey::Enter),
+            ]
+        );
+
+        let config = AutoTypeConfig {
+            randomize_cadence: true,
+            min_key_delay_ms: 20,
+            max_key_delay_ms: 40,
+            verify_focus: false,
+            dry_run: false,
+        };
+        let steps = TypingCadence::from_config(&config).plan(&actions, &mut rng);
+        let typed: String = steps
+            .iter()
+            .filter_map(|step| match step {
+                TypingStep::Text(text) => Some(text.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(typed, "janes3cret");
+        // A pause between each of the 12 keystrokes, none after the last
+        let pauses: Vec<u64> = steps
+            .iter()
+            .filter_map(|step| match step {
+                TypingStep::Pause(ms) => Some(*ms),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(pauses.len(), 11);
+        assert!(pauses.iter().all(|ms| (20..=40).contains(ms)));
+        assert_eq!(steps.last(), Some(&TypingStep::Key(AutoTypeKey::Enter)));
+
+        let preview = AutoTypePreview::new(&steps, Duration::ZERO);
+        assert_eq!(preview.outline, "••••[Tab]••••••[Enter]");
+        assert_eq!(preview.keystrokes, 12);
+        assert_eq!(preview.duration_ms, pauses.iter().sum::<u64>());
+    }
+
+    #[test]
+    fn test_focus_change_stops_typing() {
+        use std::sync::Mutex;
+
+        /// Records what is typed; focus moves away after the first value
+        struct Recorder(Mutex<Vec<String>>);
+
+        impl AutoTypeBackend for Recorder {
+            fn name(&self) -> &'static str {
+                "recorder"
+            }
+
+            fn type_text(&self, text: &str) -> Result<(), AutoTypeError> {
+                self.0.lock().unwrap().push(text.to_string());
+                Ok(())
+            }
+
+            fn press_key(&self, key: AutoTypeKey) -> Result<(), AutoTypeError> {
+                self.0.lock().unwrap().push(format!("{:?}", key));
+                Ok(())
+            }
+
+            fn focused_window(&self) -> Option<String> {
+                let typed = self.0.lock().unwrap().len();
+                Some(if typed < 2 { "login" } else { "chat" }.to_string())
+            }
+        }
+
+        let actions = AutoTypeSequence::for_credential(&login())
+            .unwrap()
+            .resolve(&login())
+            .unwrap();
+        let steps = TypingCadence::default().plan(&actions, &mut rand::thread_rng());
+        let backend = Recorder(Mutex::new(Vec::new()));
+
+        let result = send_steps(&backend, &steps, Some("login"));
+        assert!(matches!(result, Err(AutoTypeError::FocusChanged)));
+        assert_eq!(*backend.0.lock().unwrap(), vec!["jane", "Tab"]);
+
+        // Without a known window the checks pass
+        let backend = Recorder(Mutex::new(Vec::new()));
+        send_steps(&backend, &steps, None).unwrap();
+        assert_eq!(backend.0.lock().unwrap().len(), 4);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_tool_selection() {
+        let all = |_: &str| true;
+        let only_ydotool = |program: &str| program == "ydotool";
+
+        assert_eq!(select_tool(true, true, all), Some(InputTool::Wtype));
+        assert_eq!(select_tool(false, true, all), Some(InputTool::Xdotool));
+        assert_eq!(
+            select_tool(true, false, only_ydotool),
+            Some(InputTool::Ydotool)
+        );
+        assert_eq!(select_tool(false, false, |_| false), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_tool_arguments() {
+        // Text goes over stdin so it never appears in the process list
+        assert_eq!(InputTool::Wtype.type_args(), vec!["-"]);
+        assert!(InputTool::Xdotool
+            .type_args()
+            .contains(&"--file".to_string()));
+
+        assert_eq!(
+            InputTool::Wtype.key_args(AutoTypeKey::Enter),
+            vec!["-k", "Return"]
+        );
+        assert_eq!(
+            InputTool::Ydotool.key_args(AutoTypeKey::Tab),
+            vec!["key", "15:1", "15:0"]
+        );
+    }
+}
@@ -0,0 +1,80 @@
+//! Background check of stored passwords against Have I Been Pwned
+//!
+//! Runs after unlocking when enabled in Settings. Only the first five
+//! characters of each password's SHA-1 hash are sent (k-anonymity), and each
+//! distinct password is checked once.
+
+use std::collections::HashSet;
+
+use tokio::task;
+use tracing::{info, warn};
+
+use super::get_repository_service;
+use ziplock_shared::models::FieldType;
+use ziplock_shared::utils::BreachChecker;
+
+/// Checks the open repository for breached passwords
+pub struct BreachScanService;
+
+impl BreachScanService {
+    /// IDs of credentials whose password appears in a known breach
+    pub async fn scan() -> Result<Vec<String>, String> {
+        let credentials = get_repository_service()
+            .list_credentials()
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let passwords: Vec<(String, String)> = credentials
+            .into_iter()
+            .filter_map(|credential| {
+                credential
+                    .fields
+                    .values()
+                    .find(|f| f.field_type == FieldType::Password && !f.value.is_empty())
+                    .map(|f| (credential.id.clone(), f.value.clone()))
+            })
+            .collect();
+
+        task::spawn_blocking(move || {
+            let checker = BreachChecker::online().map_err(|e| e.to_string())?;
+            let mut breached_passwords = HashSet::new();
+            let mut clean_passwords = HashSet::new();
+            let mut breached = Vec::new();
+
+            for (id, password) in &passwords {
+                let is_breached = if breached_passwords.contains(password) {
+                    true
+                } else if clean_passwords.contains(password) {
+                    false
+                } else {
+                    match checker.check(password) {
+                        Ok(status) if status.is_breached() => {
+                            breached_passwords.insert(password.clone());
+                            true
+                        }
+                        Ok(_) => {
+                            clean_passwords.insert(password.clone());
+                            false
+                        }
+                        Err(e) => {
+                            warn!("Breach scan stopped early: {}", e);
+                            return Err(e.to_string());
+                        }
+                    }
+                };
+                if is_breached {
+                    breached.push(id.clone());
+                }
+            }
+
+            info!(
+                "Breach scan checked {} passwords, {} credentials breached",
+                breached_passwords.len() + clean_passwords.len(),
+                breached.len()
+            );
+            Ok(breached)
+        })
+        .await
+        .map_err(|e| e.to_string())?
+    }
+}
@@ -0,0 +1,149 @@
+//! Lets the desktop session lock ZipLock
+//!
+//! Window manager key bindings (`ziplock-cli lock` bound to Super+L), screen
+//! locker hooks and status bar widgets (`ziplock-cli lock-status`) send a
+//! [`LockCommand`] line to [`lock_socket_path`] and get a [`LockStatus`]
+//! back. Unlike the scripting API no token is needed, see
+//! [`ziplock_shared::utils::lock_control`]. The socket is served for as long
+//! as the app subscribes to [`LockControl::subscription`], which also tells
+//! the window about every lock it performed.
+
+use tracing::{info, warn};
+
+use super::get_repository_service;
+use ziplock_shared::utils::{
+    lock_socket_path, ApiResponse, LockCommand, LockStatus, VaultLockState,
+};
+
+/// Outcome of a lock requested over the socket: the path of the repository
+/// that was locked, or why its pending changes could not be saved (they are
+/// discarded either way)
+pub type LockOutcome = Result<String, String>;
+
+/// Serves lock commands from the desktop session
+pub struct LockControl;
+
+impl LockControl {
+    /// Subscription serving the lock socket, delivering each lock it
+    /// performed while a repository was open
+    pub fn subscription() -> iced::Subscription<LockOutcome> {
+        #[cfg(unix)]
+        {
+            iced::Subscription::run(unix::serve)
+        }
+        #[cfg(not(unix))]
+        {
+            iced::Subscription::none()
+        }
+    }
+
+    /// Answer one request line, and report the lock it performed if any
+    pub async fn handle_line(line: &str) -> (ApiResponse, Option<LockOutcome>) {
+        let command: LockCommand = match serde_json::from_str(line) {
+            Ok(command) => command,
+            Err(e) => return (ApiResponse::error(format!("Invalid request: {}", e)), None),
+        };
+
+        let repository = get_repository_service();
+        let outcome = match command {
+            LockCommand::Status => None,
+            LockCommand::Lock => {
+                info!("Locking on request from the desktop session");
+                match repository.lock_all().await {
+                    Ok(locked) => locked.map(Ok),
+                    Err(e) => Some(Err(e.to_string())),
+                }
+            }
+        };
+
+        let state = if repository.is_open().await {
+            VaultLockState::Unlocked
+        } else {
+            VaultLockState::Locked
+        };
+        let response = match serde_json::to_value(LockStatus { state }) {
+            Ok(status) => ApiResponse::ok(status),
+            Err(e) => ApiResponse::error(e),
+        };
+        if let Some(Err(e)) = &outcome {
+            warn!("Locked, but pending changes were lost: {}", e);
+        }
+        (response, outcome)
+    }
+}
+
+#[cfg(unix)]
+mod unix {
+    use iced::futures::{SinkExt, Stream};
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tracing::{info, warn};
+
+    use super::{lock_socket_path, LockControl, LockOutcome};
+    use crate::services::api_server::{bind_user_socket, SocketFile};
+
+    pub fn serve() -> impl Stream<Item = LockOutcome> {
+        iced::stream::channel(4, |output| async move {
+            let path = lock_socket_path();
+            let listener = match bind_user_socket(&path) {
+                Ok(listener) => listener,
+                Err(e) => {
+                    warn!("Failed to listen for lock commands on {:?}: {}", path, e);
+                    return;
+                }
+            };
+            let _socket = SocketFile(path.clone());
+            info!("Listening for lock commands on {:?}", path);
+
+            loop {
+                let stream = match listener.accept().await {
+                    Ok((stream, _)) => stream,
+                    Err(e) => {
+                        warn!("Lock socket accept failed: {}", e);
+                        continue;
+                    }
+                };
+                let mut output = output.clone();
+                tokio::spawn(async move {
+                    let (reader, mut writer) = stream.into_split();
+                    let mut lines = BufReader::new(reader).lines();
+                    while let Ok(Some(line)) = lines.next_line().await {
+                        if line.trim().is_empty() {
+                            continue;
+                        }
+                        let (response, outcome) = LockControl::handle_line(&line).await;
+                        if let Some(outcome) = outcome {
+                            let _ = output.send(outcome).await;
+                        }
+                        let mut reply = serde_json::to_string(&response).unwrap_or_default();
+                        reply.push('\n');
+                        if writer.write_all(reply.as_bytes()).await.is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_status_and_lock_without_open_vault() {
+        let locked = ApiResponse::ok(serde_json::json!({ "state": "locked" }));
+
+        let (response, outcome) = LockControl::handle_line(r#"{"method":"status"}"#).await;
+        assert_eq!(response, locked);
+        assert!(outcome.is_none());
+
+        // Nothing was open, so there is nothing to tell the window
+        let (response, outcome) = LockControl::handle_line(r#"{"method":"lock"}"#).await;
+        assert_eq!(response, locked);
+        assert!(outcome.is_none());
+
+        let (response, _) = LockControl::handle_line(r#"{"method":"unlock"}"#).await;
+        assert!(matches!(response, ApiResponse::Error { .. }));
+    }
+}
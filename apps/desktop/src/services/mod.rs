@@ -3,12 +3,38 @@
 //! This module contains various services that provide functionality
 //! across the application, such as clipboard management.
 
+pub mod activation;
+pub mod api_server;
+pub mod approvals;
+pub mod auto_type;
+pub mod breach_scan;
 pub mod clipboard;
 pub mod credential_store;
+pub mod lock_control;
+pub mod notifications;
+pub mod offsite_backup;
+pub mod release_notes;
 pub mod repository_service;
+pub mod shutdown;
+pub mod sleep_monitor;
+pub mod ssh_agent;
 pub mod update_checker;
 
+#[cfg(unix)]
+pub use activation::InstanceClaim;
+pub use activation::{Activation, ActivationRequest};
+pub use api_server::{get_api_tokens, ApiServer};
+pub use approvals::{get_approval_broker, ApprovalBroker};
+pub use auto_type::AutoTypeService;
+pub use breach_scan::BreachScanService;
 pub use clipboard::{ClipboardContentType, ClipboardManager};
 pub use credential_store::get_credential_store;
-pub use repository_service::get_repository_service;
+pub use lock_control::{LockControl, LockOutcome};
+pub use notifications::{NotificationService, SecurityEvent};
+pub use offsite_backup::OffsiteBackupService;
+pub use release_notes::{FeatureNote, Release, ReleaseNotes, SettingsSection};
+pub use repository_service::{get_repository_service, RepositoryService};
+pub use shutdown::Shutdown;
+pub use sleep_monitor::{SleepEvent, SleepMonitor};
+pub use ssh_agent::{SshAgent, UnusableSshKey};
 pub use update_checker::{InstallationMethod, UpdateCheckResult, UpdateChecker};
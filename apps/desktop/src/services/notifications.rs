@@ -0,0 +1,250 @@
+//! Desktop notifications for security events
+//!
+//! On Linux notifications are sent through the XDG desktop portal
+//! (`org.freedesktop.portal.Notification`), so they work the same inside a
+//! Flatpak sandbox and in a regular install. `notify-send` is used when no
+//! portal is running. Other platforms rely on the in-app toasts for now.
+
+use tokio::task;
+use tracing::{debug, warn};
+use ziplock_shared::config::NotificationConfig;
+
+/// Security-relevant events that can raise a desktop notification
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SecurityEvent {
+    /// The repository was locked after inactivity
+    AutoLocked,
+    /// A scheduled off-site backup failed with the given error
+    BackupFailed(String),
+    /// This many stored credentials use a breached password
+    BreachDetected(usize),
+    /// Recurring credentials added this many drafts to the inbox
+    DraftsCreated(usize),
+}
+
+impl SecurityEvent {
+    /// Whether the user has notifications for this event's category turned on
+    pub fn is_enabled(&self, config: &NotificationConfig) -> bool {
+        match self {
+            SecurityEvent::AutoLocked => config.auto_lock,
+            SecurityEvent::BackupFailed(_) => config.backup_failed,
+            SecurityEvent::BreachDetected(_) => config.breach_detected,
+            SecurityEvent::DraftsCreated(_) => config.drafts_created,
+        }
+    }
+
+    /// Notification ID; a newer notification of the same kind replaces the old one
+    fn id(&self) -> &'static str {
+        match self {
+            SecurityEvent::AutoLocked => "ziplock-auto-lock",
+            SecurityEvent::BackupFailed(_) => "ziplock-backup-failed",
+            SecurityEvent::BreachDetected(_) => "ziplock-breach-detected",
+            SecurityEvent::DraftsCreated(_) => "ziplock-drafts-created",
+        }
+    }
+
+    fn title(&self) -> &'static str {
+        match self {
+            SecurityEvent::AutoLocked => "ZipLock locked",
+            SecurityEvent::BackupFailed(_) => "ZipLock backup failed",
+            SecurityEvent::BreachDetected(_) => "Breached passwords found",
+            SecurityEvent::DraftsCreated(_) => "New credentials to fill in",
+        }
+    }
+
+    /// Notification text; never includes credential names or secrets, since
+    /// notifications can show on the lock screen
+    fn body(&self) -> String {
+        match self {
+            SecurityEvent::AutoLocked => {
+                "Your repository was locked after a period of inactivity.".to_string()
+            }
+            SecurityEvent::BackupFailed(error) => format!("Off-site backup failed: {}", error),
+            SecurityEvent::BreachDetected(1) => {
+                "1 stored password appears in a known data breach. Open ZipLock to change it."
+                    .to_string()
+            }
+            SecurityEvent::BreachDetected(count) => format!(
+                "{} stored passwords appear in known data breaches. Open ZipLock to change them.",
+                count
+            ),
+            SecurityEvent::DraftsCreated(1) => {
+                "A recurring credential is waiting in your inbox.".to_string()
+            }
+            SecurityEvent::DraftsCreated(count) => {
+                format!("{} recurring credentials are waiting in your inbox.", count)
+            }
+        }
+    }
+
+    /// Portal priority (`low`, `normal`, `high` or `urgent`)
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    fn priority(&self) -> &'static str {
+        match self {
+            SecurityEvent::AutoLocked => "normal",
+            SecurityEvent::BackupFailed(_) => "high",
+            SecurityEvent::BreachDetected(_) => "urgent",
+            SecurityEvent::DraftsCreated(_) => "low",
+        }
+    }
+}
+
+/// Sends desktop notifications
+pub struct NotificationService;
+
+impl NotificationService {
+    /// Show a notification for `event` if its category is enabled
+    ///
+    /// Failures are logged rather than surfaced; the in-app toast already
+    /// tells the user what happened.
+    pub async fn notify(config: NotificationConfig, event: SecurityEvent) {
+        if !event.is_enabled(&config) {
+            debug!("Notifications disabled for {:?}", event);
+            return;
+        }
+
+        match task::spawn_blocking(move || send(&event)).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => warn!("Failed to show desktop notification: {}", e),
+            Err(e) => warn!("Notification task failed: {}", e),
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn send(event: &SecurityEvent) -> Result<(), String> {
+    use std::process::{Command, Stdio};
+
+    let notification = format!(
+        "{{'title': <{}>, 'body': <{}>, 'priority': <{}>}}",
+        gvariant_string(event.title()),
+        gvariant_string(&event.body()),
+        gvariant_string(event.priority()),
+    );
+    let portal = Command::new("gdbus")
+        .args([
+            "call",
+            "--session",
+            "--dest",
+            "org.freedesktop.portal.Desktop",
+            "--object-path",
+            "/org/freedesktop/portal/desktop",
+            "--method",
+            "org.freedesktop.portal.Notification.AddNotification",
+            event.id(),
+            &notification,
+        ])
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .output();
+
+    let portal_error = match portal {
+        Ok(output) if output.status.success() => return Ok(()),
+        Ok(output) => String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        Err(e) => e.to_string(),
+    };
+    debug!(
+        "Notification portal unavailable ({}), trying notify-send",
+        portal_error
+    );
+
+    let urgency = if event.priority() == "urgent" {
+        "critical"
+    } else {
+        "normal"
+    };
+    let status = Command::new("notify-send")
+        .args([
+            "--app-name=ZipLock",
+            "--icon=ziplock",
+            &format!("--urgency={}", urgency),
+            event.title(),
+            &event.body(),
+        ])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map_err(|e| {
+            format!(
+                "no notification portal ({}) and notify-send failed: {}",
+                portal_error, e
+            )
+        })?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("notify-send exited with {}", status))
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn send(event: &SecurityEvent) -> Result<(), String> {
+    debug!(
+        "Desktop notifications are not supported on this platform: {}",
+        event.title()
+    );
+    Ok(())
+}
+
+/// Quote a string as a GVariant text literal for `gdbus call`
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn gvariant_string(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('\'');
+    for c in value.chars() {
+        match c {
+            '\'' | '\\' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            '\n' => quoted.push_str("\\n"),
+            c if c.is_control() => {}
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('\'');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_categories_follow_config() {
+        let mut config = NotificationConfig::default();
+        assert!(SecurityEvent::AutoLocked.is_enabled(&config));
+
+        config.backup_failed = false;
+        assert!(!SecurityEvent::BackupFailed("timeout".to_string()).is_enabled(&config));
+        assert!(SecurityEvent::BreachDetected(2).is_enabled(&config));
+
+        config.breach_detected = false;
+        assert!(!SecurityEvent::BreachDetected(2).is_enabled(&config));
+
+        config.drafts_created = false;
+        assert!(!SecurityEvent::DraftsCreated(1).is_enabled(&config));
+    }
+
+    #[test]
+    fn test_bodies() {
+        assert!(SecurityEvent::BreachDetected(1)
+            .body()
+            .starts_with("1 stored password appears"));
+        assert!(SecurityEvent::BreachDetected(3)
+            .body()
+            .starts_with("3 stored passwords appear"));
+        assert!(SecurityEvent::BackupFailed("host unreachable".to_string())
+            .body()
+            .contains("host unreachable"));
+    }
+
+    #[test]
+    fn test_gvariant_string_escaping() {
+        assert_eq!(gvariant_string("plain"), "'plain'");
+        assert_eq!(gvariant_string("it's"), "'it\\'s'");
+        assert_eq!(gvariant_string("a\\b"), "'a\\\\b'");
+        assert_eq!(gvariant_string("two\nlines\x07"), "'two\\nlines'");
+    }
+}
@@ -0,0 +1,86 @@
+//! Scheduled off-site backup service
+//!
+//! Uploads the saved repository archive to the configured SFTP or WebDAV
+//! destination. The WebDAV password is read from a credential in the open
+//! repository so it never has to be stored in the config file.
+
+use std::path::PathBuf;
+
+use chrono::Utc;
+use thiserror::Error;
+use tokio::task;
+use tracing::info;
+
+use super::get_repository_service;
+use ziplock_shared::config::{BackupDestinationConfig, OffsiteBackupConfig};
+use ziplock_shared::models::FieldType;
+use ziplock_shared::utils::{OffsiteUploader, UploadOutcome};
+
+/// Off-site backup errors
+#[derive(Error, Debug)]
+pub enum OffsiteBackupError {
+    #[error("No off-site backup destination is configured")]
+    NotConfigured,
+    #[error("No repository is open")]
+    NoRepository,
+    #[error("Backup password credential not found: {0}")]
+    MissingPassword(String),
+    #[error("{0}")]
+    Upload(String),
+}
+
+/// Runs off-site backup uploads
+pub struct OffsiteBackupService;
+
+impl OffsiteBackupService {
+    /// Upload the currently open repository's archive
+    pub async fn run(config: OffsiteBackupConfig) -> Result<UploadOutcome, OffsiteBackupError> {
+        let destination = config
+            .destination
+            .as_ref()
+            .ok_or(OffsiteBackupError::NotConfigured)?;
+
+        let repository = get_repository_service();
+        let archive = repository
+            .current_path()
+            .await
+            .map(PathBuf::from)
+            .ok_or(OffsiteBackupError::NoRepository)?;
+
+        let password = match destination {
+            BackupDestinationConfig::WebDav {
+                password_credential: Some(id),
+                ..
+            } => Some(Self::password_from_credential(id).await?),
+            _ => None,
+        };
+
+        info!(
+            "Uploading off-site backup to {}",
+            destination.display_location()
+        );
+        task::spawn_blocking(move || {
+            OffsiteUploader::from_config(&config, password)
+                .and_then(|uploader| uploader.upload_file(&archive, Utc::now()))
+                .map_err(|e| OffsiteBackupError::Upload(e.to_string()))
+        })
+        .await
+        .map_err(|e| OffsiteBackupError::Upload(e.to_string()))?
+    }
+
+    async fn password_from_credential(id: &str) -> Result<String, OffsiteBackupError> {
+        let credential = get_repository_service()
+            .get_credential(id.to_string())
+            .await
+            .ok()
+            .flatten()
+            .ok_or_else(|| OffsiteBackupError::MissingPassword(id.to_string()))?;
+
+        credential
+            .fields
+            .values()
+            .find(|field| field.field_type == FieldType::Password)
+            .map(|field| field.value.clone())
+            .ok_or_else(|| OffsiteBackupError::MissingPassword(credential.title.clone()))
+    }
+}
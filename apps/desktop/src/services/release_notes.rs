@@ -0,0 +1,153 @@
+//! Release notes shown after an upgrade
+//!
+//! The notes ship inside the binary, from `resources/release-notes.toml`, so
+//! the "what's new" view works offline and always matches the installed
+//! version. The version the user last saw notes for is kept in
+//! `AppConfig::last_seen_version`; every release after it, up to the running
+//! one, is shown once.
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use super::update_checker::parse_version;
+
+const BUNDLED_NOTES: &str = include_str!("../../resources/release-notes.toml");
+
+/// Settings tab a feature note links to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SettingsSection {
+    Interface,
+    Application,
+    Repository,
+    Security,
+}
+
+/// One change worth telling users about
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeatureNote {
+    pub title: String,
+    pub description: String,
+    /// Where the feature is configured, if anywhere
+    #[serde(default)]
+    pub settings: Option<SettingsSection>,
+}
+
+/// The notes for one version
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Release {
+    pub version: String,
+    #[serde(default)]
+    pub summary: Option<String>,
+    #[serde(default)]
+    pub features: Vec<FeatureNote>,
+}
+
+/// Release notes for every version, newest first
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReleaseNotes {
+    #[serde(default, rename = "release")]
+    pub releases: Vec<Release>,
+}
+
+impl ReleaseNotes {
+    /// Read notes in the format of the bundled file
+    pub fn parse(source: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(source)
+    }
+
+    /// The notes built into this binary; empty if they cannot be read
+    pub fn bundled() -> Self {
+        Self::parse(BUNDLED_NOTES).unwrap_or_else(|e| {
+            warn!("Bundled release notes are invalid: {}", e);
+            Self::default()
+        })
+    }
+
+    /// Releases after `last_seen` up to and including `current`, newest
+    /// first
+    ///
+    /// Without a last seen version only the notes for `current` are
+    /// returned, so users upgrading from a build that did not record it see
+    /// this release rather than the whole history.
+    pub fn unseen(&self, last_seen: Option<&str>, current: &str) -> Vec<Release> {
+        let Some(current) = parse_version(current) else {
+            return Vec::new();
+        };
+        let after = last_seen.and_then(parse_version);
+
+        let mut releases: Vec<(_, &Release)> = self
+            .releases
+            .iter()
+            .filter_map(|release| Some((parse_version(&release.version)?, release)))
+            .filter(|&(version, _)| match after {
+                Some(after) => version > after && version <= current,
+                None => version == current,
+            })
+            .collect();
+        releases.sort_by_key(|&(version, _)| std::cmp::Reverse(version));
+        releases
+            .into_iter()
+            .map(|(_, release)| release.clone())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundled_notes_parse() {
+        let notes = ReleaseNotes::parse(BUNDLED_NOTES).unwrap();
+        assert!(!notes.releases.is_empty());
+        for release in &notes.releases {
+            assert!(
+                parse_version(&release.version).is_some(),
+                "{}",
+                release.version
+            );
+            assert!(!release.features.is_empty(), "{}", release.version);
+        }
+    }
+
+    #[test]
+    fn test_unseen_releases() {
+        let notes = ReleaseNotes::parse(
+            r#"
+            [[release]]
+            version = "1.2.0"
+            [[release.features]]
+            title = "Backups"
+            description = "Scheduled uploads"
+            settings = "repository"
+
+            [[release]]
+            version = "1.1.0"
+
+            [[release]]
+            version = "1.0.0"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            notes.releases[0].features[0].settings,
+            Some(SettingsSection::Repository)
+        );
+
+        let versions = |releases: Vec<Release>| -> Vec<String> {
+            releases
+                .into_iter()
+                .map(|release| release.version)
+                .collect()
+        };
+        assert_eq!(
+            versions(notes.unseen(Some("1.0.0"), "1.2.0")),
+            ["1.2.0", "1.1.0"]
+        );
+        assert_eq!(versions(notes.unseen(Some("1.0.0"), "1.1.3")), ["1.1.0"]);
+        assert!(notes.unseen(Some("1.2.0"), "1.2.0").is_empty());
+        assert_eq!(versions(notes.unseen(None, "1.2.0")), ["1.2.0"]);
+        assert!(notes.unseen(None, "1.3.0").is_empty());
+    }
+}
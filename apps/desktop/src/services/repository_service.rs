@@ -5,13 +5,28 @@
 //! It bridges between the async UI layer and the sync repository operations.
 
 use anyhow::Result;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 
-use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use tokio::sync::broadcast;
 use tokio::task;
 use tracing::{debug, error, info, warn};
 
-use ziplock_shared::{CoreError, CredentialRecord, DesktopFileProvider, UnifiedRepositoryManager};
+use crate::config::secure_store_dir;
+use ziplock_shared::core::keyprotection::keychain::SystemKeychain;
+use ziplock_shared::core::keyprotection::{Fido2Protector, KeychainProtector, LibFido2Device};
+use ziplock_shared::core::{
+    EventBus, KeyProtector, PasswordChangeStage, ProtectorKind, RepositoryEvent, SessionExpiry,
+    SessionPolicy, VaultSession,
+};
+use ziplock_shared::models::{CredentialFormSchema, RecurringDraft};
+use ziplock_shared::utils::{Reminder, ReminderEngine};
+use ziplock_shared::{
+    CoreError, CredentialRecord, DesktopFileProvider, FileError, UnifiedRepositoryManager,
+};
 
 /// Repository service statistics
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -22,10 +37,37 @@ pub struct RepositoryStats {
     pub current_path: Option<String>,
 }
 
+/// How often a session without a deadline is looked at again, in case the
+/// policy is changed to one with limits
+const SESSION_RECHECK: Duration = Duration::from_secs(60);
+
+/// The session of the open repository; `generation` tells a watchdog whether
+/// the session it was started for is still the current one
+#[derive(Default)]
+struct SessionSlot {
+    policy: SessionPolicy,
+    generation: u64,
+    session: Option<VaultSession>,
+    /// Why the last session ended, until the next one starts
+    expired: Option<SessionExpiry>,
+}
+
+type ManagerSlot = RwLock<Option<UnifiedRepositoryManager<DesktopFileProvider>>>;
+
 /// Repository service that provides async interface to UnifiedRepositoryManager
+///
+/// The service enforces the [`SessionPolicy`] itself: requests touch the
+/// session, and once it runs past its idle timeout or maximum lifetime the
+/// repository is locked, a [`RepositoryEvent::SessionExpired`] is published
+/// and requests fail with [`CoreError::SessionExpired`], whether they come
+/// from the UI or a script.
 pub struct RepositoryService {
-    manager: Arc<RwLock<Option<UnifiedRepositoryManager<DesktopFileProvider>>>>,
+    manager: Arc<ManagerSlot>,
     current_stats: Arc<RwLock<RepositoryStats>>,
+    events: EventBus,
+    session: Arc<Mutex<SessionSlot>>,
+    /// Whether repositories opened from now on keep an activity log
+    activity_logging: Arc<AtomicBool>,
 }
 
 impl RepositoryService {
@@ -39,20 +81,83 @@ impl RepositoryService {
                 is_modified: false,
                 current_path: None,
             })),
+            events: EventBus::default(),
+            session: Arc::new(Mutex::new(SessionSlot::default())),
+            activity_logging: Arc::new(AtomicBool::new(true)),
+        }
+    }
+
+    /// Set the limits sessions are held to, including the current one
+    pub fn set_session_policy(&self, policy: SessionPolicy) {
+        let mut slot = self.session.lock().unwrap();
+        slot.policy = policy;
+        if let Some(session) = slot.session.as_mut() {
+            session.set_policy(policy);
+        }
+    }
+
+    /// Turn the activity log on or off, including for the open repository
+    pub fn set_activity_logging(&self, enabled: bool) {
+        self.activity_logging.store(enabled, Ordering::Relaxed);
+        if let Some(manager) = self.manager.write().unwrap().as_mut() {
+            manager.set_activity_logging(enabled);
+        }
+    }
+
+    /// Record activity that does not reach the service, such as the user
+    /// working in the window
+    pub fn touch_session(&self) {
+        if let Some(session) = self.session.lock().unwrap().session.as_mut() {
+            session.touch();
         }
     }
 
+    /// Why the last session ended on its own, if the repository has not been
+    /// opened again since
+    pub fn session_expiry(&self) -> Option<SessionExpiry> {
+        self.session.lock().unwrap().expired
+    }
+
+    /// Subscription reporting sessions the service ended, so the UI can show
+    /// the lock screen
+    pub fn session_expiries() -> iced::Subscription<SessionExpiry> {
+        iced::Subscription::run(session_expiry_stream)
+    }
+
+    /// Receive an event for every change made through this service
+    pub fn subscribe(&self) -> broadcast::Receiver<RepositoryEvent> {
+        self.events.subscribe()
+    }
+
+    /// Subscription reporting credentials added, changed or deleted through
+    /// the global service, for views that list them
+    pub fn credential_changes() -> iced::Subscription<RepositoryEvent> {
+        iced::Subscription::run(credential_change_stream)
+    }
+
+    /// Subscription firing when a sync client or another program changes
+    /// the open vault's archive
+    pub fn archive_changes() -> iced::Subscription<()> {
+        iced::Subscription::run(archive_change_stream)
+    }
+
     /// Create a new repository at the specified path
     #[allow(dead_code)]
     pub async fn create_repository(&self, path: String, password: String) -> Result<()> {
         let manager_clone = Arc::clone(&self.manager);
         let stats_clone = Arc::clone(&self.current_stats);
+        let activity_logging = self.activity_logging.load(Ordering::Relaxed);
+        let events = self.events.clone();
 
         task::spawn_blocking(move || {
             info!("Creating new repository at: {}", path);
 
             let file_provider = DesktopFileProvider::new();
             let mut manager = UnifiedRepositoryManager::new(file_provider);
+            manager.set_secure_store_dir(Some(secure_store_dir()));
+            manager.set_activity_logging(activity_logging);
+            manager.set_watch_archive(true);
+            manager.on_change(forward_changes(events));
 
             match manager.create_repository(&path, &password) {
                 Ok(()) => {
@@ -81,50 +186,39 @@ impl RepositoryService {
                 }
             }
         })
-        .await?
+        .await??;
+        self.start_session();
+        self.events.publish(RepositoryEvent::Opened);
+        Ok(())
     }
 
     /// Open an existing repository
     pub async fn open_repository(&self, path: String, password: String) -> Result<()> {
         let manager_clone = Arc::clone(&self.manager);
         let stats_clone = Arc::clone(&self.current_stats);
+        let activity_logging = self.activity_logging.load(Ordering::Relaxed);
+        let events = self.events.clone();
 
         task::spawn_blocking(move || {
             info!("Opening repository: {}", path);
 
             let file_provider = DesktopFileProvider::new();
             let mut manager = UnifiedRepositoryManager::new(file_provider);
+            manager.set_secure_store_dir(Some(secure_store_dir()));
+            manager.set_activity_logging(activity_logging);
+            manager.set_watch_archive(true);
+            manager.on_change(forward_changes(events));
 
             match manager.open_repository(&path, &password) {
                 Ok(()) => {
                     info!("Repository opened successfully: {}", path);
-
-                    // Get credential count
-                    let credential_count = manager
-                        .list_credentials()
-                        .map(|creds| creds.len())
-                        .unwrap_or(0);
-
-                    // Update stats
-                    {
-                        let mut stats = stats_clone.write().unwrap();
-                        stats.is_open = true;
-                        stats.current_path = Some(path.clone());
-                        stats.credential_count = credential_count;
-                        stats.is_modified = false;
-                    }
-
-                    // Store manager
-                    {
-                        let mut mgr_guard = manager_clone.write().unwrap();
-                        *mgr_guard = Some(manager);
-                    }
-
+                    Self::install(&manager_clone, &stats_clone, manager, path);
                     Ok(())
                 }
-                Err(CoreError::FileOperation(ziplock_shared::FileError::InvalidPassword)) => {
+                Err(e @ CoreError::FileOperation(ziplock_shared::FileError::InvalidPassword)) => {
                     warn!("Invalid password for repository: {}", path);
-                    Err(anyhow::anyhow!("Invalid password"))
+                    // Keep the error underneath so callers can count failures
+                    Err(anyhow::Error::from(e).context("Invalid password"))
                 }
                 Err(CoreError::FileOperation(ziplock_shared::FileError::NotFound { .. })) => {
                     warn!("Repository file not found: {}", path);
@@ -136,14 +230,202 @@ impl RepositoryService {
                 }
             }
         })
+        .await??;
+        self.start_session();
+        self.events.publish(RepositoryEvent::Opened);
+        Ok(())
+    }
+
+    /// Open a repository with a registered key protector of `kind`
+    ///
+    /// Blocks a worker thread while the protector works, e.g. until a
+    /// security key is touched. On error the repository stays closed and the
+    /// master password has to be used.
+    pub async fn open_with_key_protector(&self, path: String, kind: ProtectorKind) -> Result<()> {
+        let manager_clone = Arc::clone(&self.manager);
+        let stats_clone = Arc::clone(&self.current_stats);
+        let activity_logging = self.activity_logging.load(Ordering::Relaxed);
+        let events = self.events.clone();
+
+        task::spawn_blocking(move || {
+            info!("Opening repository with {}: {}", kind.name(), path);
+
+            let protector = Self::key_protector(kind)?;
+            let mut manager = UnifiedRepositoryManager::new(DesktopFileProvider::new());
+            manager.set_secure_store_dir(Some(secure_store_dir()));
+            manager.set_activity_logging(activity_logging);
+            manager.set_watch_archive(true);
+            manager.on_change(forward_changes(events));
+            match manager.open_repository_with_protector(&path, protector.as_ref()) {
+                Ok(()) => {
+                    info!("Repository opened with {}: {}", kind.name(), path);
+                    Self::install(&manager_clone, &stats_clone, manager, path);
+                    Ok(())
+                }
+                Err(CoreError::FileOperation(FileError::InvalidPassword)) => Err(anyhow::anyhow!(
+                    "{} is registered for an old master password",
+                    kind.name()
+                )),
+                Err(CoreError::KeyProtection(e)) => {
+                    warn!("{} unlock failed: {}", kind.name(), e);
+                    Err(e.into())
+                }
+                Err(e) => {
+                    error!("Failed to open repository {}: {}", path, e);
+                    Err(anyhow::anyhow!("Failed to open repository: {}", e))
+                }
+            }
+        })
+        .await??;
+        self.start_session();
+        self.events.publish(RepositoryEvent::Opened);
+        Ok(())
+    }
+
+    /// Whether a key protector of `kind` is registered for the archive at `path`
+    pub fn key_protector_registered(path: &str, kind: ProtectorKind) -> bool {
+        UnifiedRepositoryManager::new(DesktopFileProvider::new())
+            .key_protectors(path)
+            .is_ok_and(|records| records.iter().any(|record| record.kind == kind))
+    }
+
+    /// The protector that handles records of `kind` on this machine
+    fn key_protector(kind: ProtectorKind) -> Result<Box<dyn KeyProtector>> {
+        Ok(match kind {
+            ProtectorKind::Fido2HmacSecret => {
+                Box::new(Fido2Protector::new(LibFido2Device::first()?))
+            }
+            ProtectorKind::OsKeychain => Box::new(KeychainProtector::new(SystemKeychain)),
+            ProtectorKind::Biometric => {
+                anyhow::bail!("Biometric unlock is only available in the mobile apps")
+            }
+        })
+    }
+
+    /// Register the connected FIDO2 security key for the open repository
+    pub async fn register_security_key(&self, label: String) -> Result<()> {
+        let manager_clone = Arc::clone(&self.manager);
+
+        task::spawn_blocking(move || {
+            let device = LibFido2Device::first()?;
+            let mut mgr_guard = manager_clone.write().unwrap();
+            let Some(manager) = mgr_guard.as_mut() else {
+                return Err(anyhow::anyhow!("No repository is open"));
+            };
+            manager
+                .register_key_protector(&Fido2Protector::new(device), &label)
+                .map(|record| info!("Registered security key '{}'", record.label))
+                .map_err(|e| anyhow::anyhow!("Failed to register security key: {}", e))
+        })
         .await?
     }
 
+    /// Keep a key to the open repository in the OS keychain
+    ///
+    /// Does nothing if one is already registered, or if the repository needs
+    /// a key file. Returns whether a new key was stored.
+    pub async fn remember_in_os_keychain(&self) -> Result<bool> {
+        let manager_clone = Arc::clone(&self.manager);
+
+        task::spawn_blocking(move || {
+            let mut mgr_guard = manager_clone.write().unwrap();
+            let Some(manager) = mgr_guard.as_mut() else {
+                return Err(anyhow::anyhow!("No repository is open"));
+            };
+            let Some(path) = manager.current_path().map(str::to_string) else {
+                return Ok(false);
+            };
+            if manager.uses_key_file()
+                || manager
+                    .key_protectors(&path)?
+                    .iter()
+                    .any(|record| record.kind == ProtectorKind::OsKeychain)
+            {
+                return Ok(false);
+            }
+
+            let protector = KeychainProtector::new(SystemKeychain);
+            manager
+                .register_key_protector(&protector, ProtectorKind::OsKeychain.name())
+                .map_err(|e| {
+                    anyhow::anyhow!(
+                        "Failed to store the unlock key in {}: {}",
+                        protector.keychain_name(),
+                        e
+                    )
+                })?;
+            info!(
+                "Stored unlock key for {} in {}",
+                path,
+                protector.keychain_name()
+            );
+            Ok(true)
+        })
+        .await?
+    }
+
+    /// Remove the OS keychain keys of the open repository
+    pub async fn forget_os_keychain(&self) -> Result<()> {
+        let manager_clone = Arc::clone(&self.manager);
+
+        task::spawn_blocking(move || {
+            let mgr_guard = manager_clone.read().unwrap();
+            let Some(manager) = mgr_guard.as_ref() else {
+                return Ok(());
+            };
+            let Some(path) = manager.current_path() else {
+                return Ok(());
+            };
+
+            let protector = KeychainProtector::new(SystemKeychain);
+            for record in manager
+                .key_protectors(path)?
+                .iter()
+                .filter(|record| record.kind == ProtectorKind::OsKeychain)
+            {
+                if let Err(e) = protector.forget(record) {
+                    warn!(
+                        "Failed to delete unlock key from {}: {}",
+                        protector.keychain_name(),
+                        e
+                    );
+                }
+                manager.remove_key_protector(path, &record.id)?;
+            }
+            Ok(())
+        })
+        .await?
+    }
+
+    /// Keep a freshly opened manager and publish its stats
+    fn install(
+        manager_slot: &RwLock<Option<UnifiedRepositoryManager<DesktopFileProvider>>>,
+        stats: &RwLock<RepositoryStats>,
+        manager: UnifiedRepositoryManager<DesktopFileProvider>,
+        path: String,
+    ) {
+        let credential_count = manager
+            .list_credentials()
+            .map(|creds| creds.len())
+            .unwrap_or(0);
+
+        {
+            let mut stats = stats.write().unwrap();
+            stats.is_open = true;
+            stats.current_path = Some(path);
+            stats.credential_count = credential_count;
+            stats.is_modified = false;
+        }
+
+        *manager_slot.write().unwrap() = Some(manager);
+    }
+
     /// Close the current repository
     #[allow(dead_code)]
     pub async fn close_repository(&self) -> Result<()> {
         let manager_clone = Arc::clone(&self.manager);
         let stats_clone = Arc::clone(&self.current_stats);
+        self.end_session();
 
         task::spawn_blocking(move || {
             info!("Closing repository");
@@ -168,13 +450,285 @@ impl RepositoryService {
             }
 
             info!("Repository closed");
+        })
+        .await?;
+        self.events.publish(RepositoryEvent::Locked);
+        Ok(())
+    }
+
+    /// Save pending changes and wipe key material before the system sleeps
+    ///
+    /// Returns the path of the repository that was locked, if one was open.
+    pub async fn prepare_for_suspend(&self) -> Result<Option<String>> {
+        self.lock_wiping()
+            .await?
+            .map_err(|e| anyhow::anyhow!("Failed to save before suspend: {}", e))
+    }
+
+    /// Lock right away, e.g. because the desktop session is being locked
+    ///
+    /// Like [`prepare_for_suspend`](Self::prepare_for_suspend), pending
+    /// changes are saved and key material is wiped; the repository is locked
+    /// even if saving fails. Returns the path of the repository that was
+    /// locked, if one was open.
+    pub async fn lock_all(&self) -> Result<Option<String>> {
+        self.lock_wiping()
+            .await?
+            .map_err(|e| anyhow::anyhow!("Failed to save before locking: {}", e))
+    }
+
+    async fn lock_wiping(&self) -> Result<Result<Option<String>, CoreError>> {
+        let manager_clone = Arc::clone(&self.manager);
+        let stats_clone = Arc::clone(&self.current_stats);
+        self.end_session();
+
+        let result =
+            task::spawn_blocking(move || Self::lock_and_wipe(&manager_clone, &stats_clone)).await?;
+        // Nothing to announce if no repository was open
+        if !matches!(result, Ok(None)) {
+            self.events.publish(RepositoryEvent::Locked);
+        }
+        Ok(result)
+    }
+
+    /// Save pending changes, wipe key material and forget the manager
+    fn lock_and_wipe(
+        manager_slot: &ManagerSlot,
+        stats: &RwLock<RepositoryStats>,
+    ) -> Result<Option<String>, CoreError> {
+        let mut mgr_guard = manager_slot.write().unwrap();
+        let result = match mgr_guard.as_mut() {
+            Some(manager) => manager.prepare_for_suspend(),
+            None => Ok(None),
+        };
+        *mgr_guard = None;
+
+        {
+            let mut stats = stats.write().unwrap();
+            stats.is_open = false;
+            stats.current_path = None;
+            stats.credential_count = 0;
+            stats.is_modified = false;
+        }
+
+        result
+    }
+
+    /// Start the session of a freshly opened repository and watch it
+    fn start_session(&self) {
+        let generation = {
+            let mut slot = self.session.lock().unwrap();
+            slot.generation += 1;
+            slot.session = Some(VaultSession::new(slot.policy));
+            slot.expired = None;
+            slot.generation
+        };
+
+        let session = Arc::clone(&self.session);
+        let manager = Arc::clone(&self.manager);
+        let stats = Arc::clone(&self.current_stats);
+        let events = self.events.clone();
+        tokio::spawn(async move {
+            // Sleep until the session would end, then look again since
+            // activity may have pushed the deadline back
+            let reason = loop {
+                let deadline = {
+                    let slot = session.lock().unwrap();
+                    match &slot.session {
+                        Some(current) if slot.generation == generation => current.deadline(),
+                        _ => return,
+                    }
+                };
+                let wait = deadline.map_or(SESSION_RECHECK, |deadline| {
+                    deadline.saturating_duration_since(std::time::Instant::now())
+                });
+                tokio::time::sleep(wait).await;
+
+                let mut slot = session.lock().unwrap();
+                if slot.generation != generation {
+                    return;
+                }
+                match slot.session.as_ref().map(VaultSession::check) {
+                    Some(Ok(())) => continue,
+                    Some(Err(reason)) => {
+                        slot.session = None;
+                        slot.expired = Some(reason);
+                        break reason;
+                    }
+                    None => return,
+                }
+            };
+            Self::expire(manager, stats, &events, reason).await;
+        });
+    }
+
+    /// Forget the session without locking, e.g. because the repository is
+    /// being closed anyway
+    fn end_session(&self) {
+        let mut slot = self.session.lock().unwrap();
+        slot.session = None;
+        slot.expired = None;
+    }
+
+    /// Touch the session, or lock the repository if the session is over
+    ///
+    /// Called at the start of every request.
+    async fn enforce_session(&self) -> Result<()> {
+        let reason = {
+            let mut slot = self.session.lock().unwrap();
+            let Some(session) = slot.session.as_mut() else {
+                return match slot.expired {
+                    Some(reason) => Err(CoreError::SessionExpired { reason }.into()),
+                    None => Ok(()),
+                };
+            };
+            match session.check() {
+                Ok(()) => {
+                    session.touch();
+                    return Ok(());
+                }
+                Err(reason) => {
+                    slot.session = None;
+                    slot.expired = Some(reason);
+                    reason
+                }
+            }
+        };
+
+        Self::expire(
+            Arc::clone(&self.manager),
+            Arc::clone(&self.current_stats),
+            &self.events,
+            reason,
+        )
+        .await;
+        Err(CoreError::SessionExpired { reason }.into())
+    }
+
+    /// Lock the repository because its session ended
+    async fn expire(
+        manager: Arc<ManagerSlot>,
+        stats: Arc<RwLock<RepositoryStats>>,
+        events: &EventBus,
+        reason: SessionExpiry,
+    ) {
+        info!("Locking repository: {}", reason.describe());
+        match task::spawn_blocking(move || Self::lock_and_wipe(&manager, &stats)).await {
+            Ok(Err(e)) => warn!("Failed to save before the session expired: {}", e),
+            Err(e) => error!("Failed to lock expired session: {}", e),
+            Ok(Ok(_)) => {}
+        }
+        events.publish(RepositoryEvent::SessionExpired { reason });
+    }
+
+    /// Re-encrypt the repository with a new master password
+    ///
+    /// `progress` is called from a worker thread as each stage starts.
+    /// Returns the names of the key protectors that were removed because
+    /// they wrapped the old password.
+    pub async fn change_master_password(
+        &self,
+        old_password: String,
+        new_password: String,
+        progress: impl FnMut(PasswordChangeStage) + Send + 'static,
+    ) -> Result<Vec<String>> {
+        let manager_clone = Arc::clone(&self.manager);
+        let stats_clone = Arc::clone(&self.current_stats);
+
+        task::spawn_blocking(move || {
+            let mut mgr_guard = manager_clone.write().unwrap();
+            let Some(manager) = mgr_guard.as_mut() else {
+                return Err(anyhow::anyhow!("No repository is open"));
+            };
+
+            match manager.change_master_password_with_progress(
+                &old_password,
+                &new_password,
+                progress,
+            ) {
+                Ok(removed) => {
+                    info!(
+                        "Master password changed, {} key protectors removed",
+                        removed.len()
+                    );
+                    stats_clone.write().unwrap().is_modified = false;
+                    Ok(removed.into_iter().map(|record| record.label).collect())
+                }
+                Err(CoreError::FileOperation(FileError::InvalidPassword)) => {
+                    Err(anyhow::anyhow!("The current master password is incorrect"))
+                }
+                Err(e) => {
+                    error!("Failed to change master password: {}", e);
+                    Err(anyhow::anyhow!("Failed to change master password: {}", e))
+                }
+            }
+        })
+        .await?
+    }
+
+    /// Discard unsaved changes and reopen the vault as another device left it
+    pub async fn reload_from_disk(&self) -> Result<()> {
+        self.enforce_session().await?;
+        let manager_clone = Arc::clone(&self.manager);
+        let stats_clone = Arc::clone(&self.current_stats);
+
+        task::spawn_blocking(move || {
+            let mut mgr_guard = manager_clone.write().unwrap();
+            let Some(manager) = mgr_guard.as_mut() else {
+                return Err(anyhow::anyhow!("No repository is open"));
+            };
+
+            if let Err(e) = manager.reload_from_disk() {
+                error!("Failed to reload repository: {}", e);
+                return Err(anyhow::anyhow!("Failed to reload the vault: {}", e));
+            }
+            info!("Reloaded repository after it changed on disk");
+            let mut stats = stats_clone.write().unwrap();
+            stats.credential_count = manager.list_credentials().map_or(0, |c| c.len());
+            stats.is_modified = false;
             Ok(())
         })
         .await?
     }
 
+    /// Combine the vault with the copy another device saved over it and save
+    /// the result
+    ///
+    /// Credentials edited on both sides keep the newer edit. Returns the
+    /// number of credentials taken over from the other copy.
+    pub async fn merge_from_disk(&self) -> Result<usize> {
+        self.enforce_session().await?;
+        let manager_clone = Arc::clone(&self.manager);
+        let stats_clone = Arc::clone(&self.current_stats);
+
+        task::spawn_blocking(move || {
+            let mut mgr_guard = manager_clone.write().unwrap();
+            let Some(manager) = mgr_guard.as_mut() else {
+                return Err(anyhow::anyhow!("No repository is open"));
+            };
+
+            let merged = manager.merge_from_disk().and_then(|mut merge| {
+                merge.resolve_all_suggested();
+                let written = merge.apply_to(manager)?;
+                manager.save_repository()?;
+                Ok(written)
+            });
+            let written = merged.map_err(|e| {
+                error!("Failed to merge repository with the copy on disk: {}", e);
+                anyhow::anyhow!("Failed to merge the vault: {}", e)
+            })?;
+            info!("Merged {} credential(s) from the copy on disk", written);
+            let mut stats = stats_clone.write().unwrap();
+            stats.credential_count = manager.list_credentials().map_or(0, |c| c.len());
+            stats.is_modified = false;
+            Ok(written)
+        })
+        .await?
+    }
+
     /// Add a new credential
     pub async fn add_credential(&self, credential: CredentialRecord) -> Result<String> {
+        self.enforce_session().await?;
         let manager_clone = Arc::clone(&self.manager);
         let stats_clone = Arc::clone(&self.current_stats);
         let credential_id = credential.id.clone();
@@ -217,15 +771,37 @@ impl RepositoryService {
         .await?
     }
 
+    /// Form for a new credential, with the defaults of its folder resolved
+    pub async fn credential_form_schema(
+        &self,
+        folder_path: Option<String>,
+        template: Option<String>,
+    ) -> Result<CredentialFormSchema> {
+        self.enforce_session().await?;
+        let manager_clone = Arc::clone(&self.manager);
+
+        task::spawn_blocking(move || {
+            let mgr_guard = manager_clone.read().unwrap();
+            match mgr_guard.as_ref() {
+                Some(manager) => manager
+                    .credential_form_schema(folder_path.as_deref(), template.as_deref())
+                    .map_err(|e| anyhow::anyhow!("Failed to build credential form: {}", e)),
+                None => Err(anyhow::anyhow!("No repository is open")),
+            }
+        })
+        .await?
+    }
+
     /// Get a credential by ID
     pub async fn get_credential(&self, id: String) -> Result<Option<CredentialRecord>> {
+        self.enforce_session().await?;
         let manager_clone = Arc::clone(&self.manager);
 
         task::spawn_blocking(move || {
             let mgr_guard = manager_clone.read().unwrap();
             match mgr_guard.as_ref() {
-                Some(manager) => match manager.get_credential_readonly(&id) {
-                    Ok(credential) => Ok(Some(credential.clone())),
+                Some(manager) => match manager.get_credential_with_notes(&id) {
+                    Ok(credential) => Ok(Some(credential)),
                     Err(CoreError::CredentialNotFound { .. }) => Ok(None),
                     Err(e) => {
                         error!("Failed to get credential {}: {}", id, e);
@@ -243,9 +819,10 @@ impl RepositoryService {
 
     /// Update an existing credential
     pub async fn update_credential(&self, credential: CredentialRecord) -> Result<()> {
+        self.enforce_session().await?;
         let manager_clone = Arc::clone(&self.manager);
         let stats_clone = Arc::clone(&self.current_stats);
-        let credential_id = credential.id.clone();
+        let id_clone = credential.id.clone();
 
         task::spawn_blocking(move || {
             let mut mgr_guard = manager_clone.write().unwrap();
@@ -253,7 +830,7 @@ impl RepositoryService {
                 Some(manager) => {
                     match manager.update_credential(credential) {
                         Ok(()) => {
-                            debug!("Updated credential: {}", credential_id);
+                            debug!("Updated credential: {}", id_clone);
 
                             // Update stats
                             {
@@ -270,7 +847,7 @@ impl RepositoryService {
                             Ok(())
                         }
                         Err(e) => {
-                            error!("Failed to update credential {}: {}", credential_id, e);
+                            error!("Failed to update credential {}: {}", id_clone, e);
                             Err(anyhow::anyhow!("Failed to update credential: {}", e))
                         }
                     }
@@ -284,8 +861,40 @@ impl RepositoryService {
         .await?
     }
 
+    /// Create the drafts of recurring credentials that are due, saving if
+    /// any were made
+    pub async fn create_due_drafts(&self) -> Result<Vec<RecurringDraft>> {
+        self.enforce_session().await?;
+        let manager_clone = Arc::clone(&self.manager);
+        let stats_clone = Arc::clone(&self.current_stats);
+
+        task::spawn_blocking(move || {
+            let mut mgr_guard = manager_clone.write().unwrap();
+            let Some(manager) = mgr_guard.as_mut() else {
+                return Err(anyhow::anyhow!("No repository is open"));
+            };
+            let drafts = manager
+                .create_due_drafts(chrono::Utc::now().timestamp())
+                .map_err(|e| anyhow::anyhow!("Failed to create drafts: {}", e))?;
+            if !drafts.is_empty() {
+                {
+                    let mut stats = stats_clone.write().unwrap();
+                    stats.credential_count += drafts.len();
+                    stats.is_modified = true;
+                }
+                if let Err(e) = manager.save_repository() {
+                    error!("Failed to auto-save after creating drafts: {}", e);
+                    return Err(anyhow::anyhow!("Failed to save: {}", e));
+                }
+            }
+            Ok(drafts)
+        })
+        .await?
+    }
+
     /// Delete a credential
     pub async fn delete_credential(&self, id: String) -> Result<()> {
+        self.enforce_session().await?;
         let manager_clone = Arc::clone(&self.manager);
         let stats_clone = Arc::clone(&self.current_stats);
 
@@ -329,6 +938,7 @@ impl RepositoryService {
 
     /// List all credentials
     pub async fn list_credentials(&self) -> Result<Vec<CredentialRecord>> {
+        self.enforce_session().await?;
         let manager_clone = Arc::clone(&self.manager);
 
         task::spawn_blocking(move || {
@@ -353,29 +963,39 @@ impl RepositoryService {
         .await?
     }
 
-    /// Search credentials
+    /// Expirations and password changes needing attention, soonest first
+    pub async fn reminders(&self) -> Result<Vec<Reminder>> {
+        self.enforce_session().await?;
+        let manager_clone = Arc::clone(&self.manager);
+
+        task::spawn_blocking(move || {
+            let mgr_guard = manager_clone.read().unwrap();
+            match mgr_guard.as_ref() {
+                Some(manager) => manager
+                    .reminders(&ReminderEngine::new())
+                    .map_err(|e| anyhow::anyhow!("Failed to compute reminders: {}", e)),
+                None => Err(anyhow::anyhow!("No repository is open")),
+            }
+        })
+        .await?
+    }
+
+    /// Search credentials, best matches first
+    ///
+    /// Supports the filters of [`ziplock_shared::utils::ParsedSearch`], e.g.
+    /// `tag:bank type:login gmail`.
     #[allow(dead_code)]
     pub async fn search_credentials(&self, query: String) -> Result<Vec<CredentialRecord>> {
+        self.enforce_session().await?;
         let manager_clone = Arc::clone(&self.manager);
 
         task::spawn_blocking(move || {
             let mgr_guard = manager_clone.read().unwrap();
             match mgr_guard.as_ref() {
-                Some(manager) => match manager.list_credentials() {
-                    Ok(credentials) => {
-                        let query_lower = query.to_lowercase();
-                        let filtered: Vec<CredentialRecord> = credentials
-                            .into_iter()
-                            .filter(|cred| {
-                                cred.title.to_lowercase().contains(&query_lower)
-                                    || cred.fields.iter().any(|(_, field)| {
-                                        field.display_value().to_lowercase().contains(&query_lower)
-                                    })
-                            })
-                            .collect();
-
-                        debug!("Search '{}' returned {} results", query, filtered.len());
-                        Ok(filtered)
+                Some(manager) => match manager.search_credentials(&query) {
+                    Ok(found) => {
+                        debug!("Search '{}' returned {} results", query, found.len());
+                        Ok(found)
                     }
                     Err(e) => {
                         error!("Failed to search credentials: {}", e);
@@ -398,6 +1018,37 @@ impl RepositoryService {
         Ok(stats)
     }
 
+    /// A value kept in the secure store of the open repository
+    pub async fn view_state<T: DeserializeOwned + Send + 'static>(&self, key: &str) -> Option<T> {
+        let manager_clone = Arc::clone(&self.manager);
+        let key = key.to_string();
+
+        task::spawn_blocking(move || {
+            let mgr_guard = manager_clone.read().unwrap();
+            mgr_guard.as_ref()?.secure_store()?.get(&key)
+        })
+        .await
+        .ok()
+        .flatten()
+    }
+
+    /// Keep `value` in the secure store of the open repository; it is
+    /// written when the repository is closed or locked
+    pub async fn remember_view_state<T: Serialize + Send + 'static>(&self, key: &str, value: T) {
+        let manager_clone = Arc::clone(&self.manager);
+        let key = key.to_string();
+
+        let _ = task::spawn_blocking(move || {
+            let mut mgr_guard = manager_clone.write().unwrap();
+            if let Some(store) = mgr_guard.as_mut().and_then(|m| m.secure_store_mut()) {
+                if let Err(e) = store.set(&key, &value) {
+                    warn!("Failed to remember {}: {}", key, e);
+                }
+            }
+        })
+        .await;
+    }
+
     /// Check if repository is open
     pub async fn is_open(&self) -> bool {
         self.current_stats.read().unwrap().is_open
@@ -424,6 +1075,58 @@ pub fn get_repository_service() -> &'static RepositoryService {
     REPOSITORY_SERVICE.get_or_init(RepositoryService::new)
 }
 
+/// Listener publishing the credential changes of a manager on `events`,
+/// whichever request made them, and changes to its archive made elsewhere
+///
+/// Opening and locking are published by the service itself, once the
+/// session has been started or ended.
+fn forward_changes(events: EventBus) -> impl Fn(&RepositoryEvent) + Send + Sync + 'static {
+    move |event| {
+        if event.credential_id().is_some() || *event == RepositoryEvent::ArchiveChanged {
+            events.publish(event.clone());
+        }
+    }
+}
+
+fn archive_change_stream() -> impl iced::futures::Stream<Item = ()> {
+    repository_event_stream(|event| (event == RepositoryEvent::ArchiveChanged).then_some(()))
+}
+
+fn credential_change_stream() -> impl iced::futures::Stream<Item = RepositoryEvent> {
+    repository_event_stream(|event| event.credential_id().is_some().then_some(event))
+}
+
+fn session_expiry_stream() -> impl iced::futures::Stream<Item = SessionExpiry> {
+    repository_event_stream(|event| match event {
+        RepositoryEvent::SessionExpired { reason } => Some(reason),
+        _ => None,
+    })
+}
+
+/// Events of the global service picked out by `select`
+fn repository_event_stream<T: Send + 'static>(
+    select: fn(RepositoryEvent) -> Option<T>,
+) -> impl iced::futures::Stream<Item = T> {
+    use iced::futures::SinkExt;
+
+    iced::stream::channel(16, move |mut output| async move {
+        let mut receiver = get_repository_service().subscribe();
+        loop {
+            match receiver.recv().await {
+                Ok(event) => {
+                    if let Some(item) = select(event) {
+                        if output.send(item).await.is_err() {
+                            break;
+                        }
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -536,6 +1239,50 @@ mod tests {
         assert_eq!(com_results.len(), 2);
     }
 
+    #[tokio::test]
+    async fn test_idle_session_is_locked_by_the_service() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo_path = temp_dir.path().join("session_test.7z");
+        let repo_path_str = repo_path.to_string_lossy().to_string();
+
+        let service = RepositoryService::new();
+        service.set_session_policy(SessionPolicy::new(1, 0));
+        let mut events = service.subscribe();
+        service
+            .create_repository(repo_path_str.clone(), "testpass".to_string())
+            .await
+            .unwrap();
+        assert_eq!(events.recv().await.unwrap(), RepositoryEvent::Opened);
+
+        let expired = tokio::time::timeout(Duration::from_secs(10), events.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            expired,
+            RepositoryEvent::SessionExpired {
+                reason: SessionExpiry::Idle
+            }
+        );
+        assert!(!service.is_open().await);
+        assert_eq!(service.session_expiry(), Some(SessionExpiry::Idle));
+        let error = service.list_credentials().await.unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<CoreError>(),
+            Some(CoreError::SessionExpired {
+                reason: SessionExpiry::Idle
+            })
+        ));
+
+        service.set_session_policy(SessionPolicy::default());
+        service
+            .open_repository(repo_path_str, "testpass".to_string())
+            .await
+            .unwrap();
+        assert_eq!(service.session_expiry(), None);
+        assert!(service.list_credentials().await.unwrap().is_empty());
+    }
+
     #[tokio::test]
     async fn test_error_handling() {
         let service = RepositoryService::new();
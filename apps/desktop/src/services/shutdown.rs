@@ -0,0 +1,78 @@
+//! Orderly shutdown when the app quits or is asked to terminate
+//!
+//! Closing the window and SIGTERM, SIGINT or SIGHUP (sent by the session
+//! manager on logout, by `kill`, or by Ctrl+C in a terminal) all end up in
+//! [`Shutdown::run`]: connected scripting clients are told the app is going
+//! away, pending changes are saved and the master password and decrypted
+//! credentials are wiped before the process exits.
+
+use tracing::{info, warn};
+
+use crate::services::{get_repository_service, ApiServer};
+
+/// Shutdown handling for the desktop app
+pub struct Shutdown;
+
+impl Shutdown {
+    /// Subscription that fires when the process is asked to terminate
+    pub fn signals() -> iced::Subscription<()> {
+        #[cfg(unix)]
+        {
+            iced::Subscription::run(unix::signals)
+        }
+        #[cfg(not(unix))]
+        {
+            iced::Subscription::none()
+        }
+    }
+
+    /// Notify scripting clients, save pending changes and wipe keys
+    ///
+    /// Returns the path of the repository that was closed, if one was open.
+    pub async fn run() -> Result<Option<String>, String> {
+        ApiServer::shutdown().await;
+
+        let result = get_repository_service()
+            .prepare_for_suspend()
+            .await
+            .map_err(|e| e.to_string());
+        match &result {
+            Ok(Some(path)) => info!("Saved and closed {} before exiting", path),
+            Ok(None) => {}
+            Err(e) => warn!("Exiting with unsaved changes: {}", e),
+        }
+        result
+    }
+}
+
+#[cfg(unix)]
+mod unix {
+    use iced::futures::{SinkExt, Stream};
+    use tokio::signal::unix::{signal, SignalKind};
+    use tracing::{info, warn};
+
+    pub fn signals() -> impl Stream<Item = ()> {
+        iced::stream::channel(1, |mut output| async move {
+            let (Ok(mut terminate), Ok(mut interrupt), Ok(mut hangup)) = (
+                signal(SignalKind::terminate()),
+                signal(SignalKind::interrupt()),
+                signal(SignalKind::hangup()),
+            ) else {
+                warn!("Cannot listen for termination signals");
+                return;
+            };
+
+            loop {
+                let name = tokio::select! {
+                    _ = terminate.recv() => "SIGTERM",
+                    _ = interrupt.recv() => "SIGINT",
+                    _ = hangup.recv() => "SIGHUP",
+                };
+                info!("Received {}, shutting down", name);
+                if output.send(()).await.is_err() {
+                    break;
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,165 @@
+//! Wipes key material before the system suspends or hibernates
+//!
+//! On Linux, logind announces suspend and hibernate with the
+//! `PrepareForSleep` signal. While a repository is open the monitor holds a
+//! "delay" inhibitor lock, so the system waits (up to logind's
+//! `InhibitDelayMaxSec`) while pending changes are saved and the master
+//! password and decrypted credentials are wiped. The lock is then released
+//! and sleep continues without the keys in memory or in a hibernation image.
+//! Other platforms have no hook yet and rely on the auto-lock timeout.
+
+/// System sleep transitions seen by the monitor
+#[derive(Debug, Clone)]
+pub enum SleepEvent {
+    /// The repository was locked before sleeping; `Err` if pending changes
+    /// could not be saved (they are discarded either way)
+    Suspended(Result<Option<String>, String>),
+    /// The system woke up again
+    Resumed,
+}
+
+/// Watches for system sleep
+pub struct SleepMonitor;
+
+impl SleepMonitor {
+    /// Subscription that reports sleep transitions, wiping keys on suspend
+    ///
+    /// Only subscribe while a repository is open; dropping the subscription
+    /// releases the inhibitor lock.
+    pub fn subscription() -> iced::Subscription<SleepEvent> {
+        #[cfg(target_os = "linux")]
+        {
+            iced::Subscription::run(linux::events)
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            iced::Subscription::none()
+        }
+    }
+}
+
+/// Parse a `gdbus monitor` line; `Some(true)` before sleep, `Some(false)` after
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_prepare_for_sleep(line: &str) -> Option<bool> {
+    let (_, args) = line.split_once("org.freedesktop.login1.Manager.PrepareForSleep")?;
+    match args.trim() {
+        "(true,)" => Some(true),
+        "(false,)" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::process::Stdio;
+
+    use iced::futures::{SinkExt, Stream};
+    use tokio::io::{AsyncBufReadExt, BufReader};
+    use tokio::process::{Child, Command};
+    use tracing::{info, warn};
+
+    use super::{parse_prepare_for_sleep, SleepEvent};
+    use crate::services::get_repository_service;
+
+    pub fn events() -> impl Stream<Item = SleepEvent> {
+        iced::stream::channel(4, |mut output| async move {
+            let monitor = Command::new("gdbus")
+                .args([
+                    "monitor",
+                    "--system",
+                    "--dest",
+                    "org.freedesktop.login1",
+                    "--object-path",
+                    "/org/freedesktop/login1",
+                ])
+                .stdin(Stdio::null())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .kill_on_drop(true)
+                .spawn();
+            let mut monitor = match monitor {
+                Ok(monitor) => monitor,
+                Err(e) => {
+                    warn!(
+                        "Cannot watch for system sleep, keys will not be wiped on suspend: {}",
+                        e
+                    );
+                    return;
+                }
+            };
+            let Some(stdout) = monitor.stdout.take() else {
+                return;
+            };
+
+            let mut inhibitor = take_inhibitor();
+            let mut lines = BufReader::new(stdout).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                match parse_prepare_for_sleep(&line) {
+                    Some(true) => {
+                        info!("System is going to sleep, wiping key material");
+                        let result = get_repository_service()
+                            .prepare_for_suspend()
+                            .await
+                            .map_err(|e| e.to_string());
+                        // Releasing the inhibitor lets the system go to sleep
+                        drop(inhibitor.take());
+                        let _ = output.send(SleepEvent::Suspended(result)).await;
+                    }
+                    Some(false) => {
+                        inhibitor = take_inhibitor();
+                        let _ = output.send(SleepEvent::Resumed).await;
+                    }
+                    None => {}
+                }
+            }
+            warn!("System sleep monitor stopped");
+        })
+    }
+
+    /// Hold a logind delay lock for as long as the returned process runs
+    fn take_inhibitor() -> Option<Child> {
+        Command::new("systemd-inhibit")
+            .args([
+                "--what=sleep",
+                "--mode=delay",
+                "--who=ZipLock",
+                "--why=Wiping keys from memory",
+                "sleep",
+                "infinity",
+            ])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| warn!("Failed to take sleep inhibitor lock: {}", e))
+            .ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_prepare_for_sleep() {
+        assert_eq!(
+            parse_prepare_for_sleep(
+                "/org/freedesktop/login1: org.freedesktop.login1.Manager.PrepareForSleep (true,)"
+            ),
+            Some(true)
+        );
+        assert_eq!(
+            parse_prepare_for_sleep(
+                "/org/freedesktop/login1: org.freedesktop.login1.Manager.PrepareForSleep (false,)"
+            ),
+            Some(false)
+        );
+        assert_eq!(
+            parse_prepare_for_sleep(
+                "/org/freedesktop/login1: org.freedesktop.login1.Manager.SessionNew ('3', objectpath '/org/freedesktop/login1/session/_33')"
+            ),
+            None
+        );
+    }
+}
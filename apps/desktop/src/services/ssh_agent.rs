@@ -0,0 +1,252 @@
+//! Serves the SSH keys stored in the open vault to SSH clients
+//!
+//! With `security.ssh_agent` enabled, the app subscribes to
+//! [`SshAgent::subscription`] for as long as a vault is unlocked. It listens
+//! on [`ssh_agent_socket_path`] (on Windows, the named pipe
+//! [`SSH_AGENT_PIPE_NAME`]); pointing `SSH_AUTH_SOCK` there lets `ssh` and
+//! `git` use the keys without them ever being written to disk. Keys are read
+//! from the vault when a client connects, and every request checks that the
+//! vault is still open. Signing with a key tagged for confirmation waits for
+//! the user in the window, see [`ApprovalBroker`](super::ApprovalBroker).
+
+use std::collections::HashSet;
+
+use iced::futures::channel::mpsc;
+use iced::futures::{SinkExt, StreamExt};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tracing::{debug, info, warn};
+
+use super::{get_approval_broker, get_repository_service};
+#[cfg(windows)]
+use ziplock_shared::utils::ssh_agent::SSH_AGENT_PIPE_NAME;
+use ziplock_shared::utils::ssh_agent::{find_key, message_len, respond};
+#[cfg(unix)]
+use ziplock_shared::utils::ssh_agent_socket_path;
+use ziplock_shared::utils::{AgentRequest, AgentResponse, SshAgentKey};
+
+/// A stored SSH key the agent cannot use
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnusableSshKey {
+    pub title: String,
+    pub error: String,
+}
+
+/// Serves stored SSH keys over the ssh-agent protocol
+pub struct SshAgent;
+
+impl SshAgent {
+    /// Subscription serving the agent, delivering each stored key that could
+    /// not be loaded, once
+    pub fn subscription() -> iced::Subscription<UnusableSshKey> {
+        iced::Subscription::run(serve)
+    }
+
+    /// Answer one message from `client`, loading the keys on first use
+    async fn handle(
+        body: &[u8],
+        client: &str,
+        keys: &mut Option<Vec<SshAgentKey>>,
+        unusable: &mut mpsc::Sender<UnusableSshKey>,
+    ) -> AgentResponse {
+        if !get_repository_service().is_open().await {
+            *keys = None;
+            return AgentResponse::Failure;
+        }
+        let request = match AgentRequest::parse(body) {
+            Ok(request) => request,
+            Err(e) => {
+                debug!("{}", e);
+                return AgentResponse::Failure;
+            }
+        };
+        if keys.is_none() {
+            *keys = load_keys(unusable).await;
+        }
+        let Some(keys) = keys.as_deref() else {
+            return AgentResponse::Failure;
+        };
+
+        if let AgentRequest::Sign { key_blob, .. } = &request {
+            let Some(key) = find_key(keys, key_blob) else {
+                return AgentResponse::Failure;
+            };
+            if key.confirm
+                && !get_approval_broker()
+                    .confirm_ssh_signature(client, &key.credential_id, &key.title)
+                    .await
+                    .is_allowed()
+            {
+                info!("Signature with '{}' refused", key.title);
+                return AgentResponse::Failure;
+            }
+            info!("Signing for {} with '{}'", client, key.title);
+        }
+        respond(keys, &request)
+    }
+}
+
+/// The SSH keys in the open vault; decrypting them is slow, so it runs off
+/// the async threads
+async fn load_keys(unusable: &mut mpsc::Sender<UnusableSshKey>) -> Option<Vec<SshAgentKey>> {
+    let credentials = match get_repository_service().list_credentials().await {
+        Ok(credentials) => credentials,
+        Err(e) => {
+            warn!("SSH agent cannot read the vault: {}", e);
+            return None;
+        }
+    };
+    let (keys, errors) = tokio::task::spawn_blocking(move || SshAgentKey::load_all(&credentials))
+        .await
+        .ok()?;
+    for (title, error) in errors {
+        let _ = unusable
+            .send(UnusableSshKey {
+                title,
+                error: error.to_string(),
+            })
+            .await;
+    }
+    Some(keys)
+}
+
+/// Answer messages on one connection until the client hangs up
+async fn serve_connection<S>(
+    mut stream: S,
+    client: String,
+    mut unusable: mpsc::Sender<UnusableSshKey>,
+) where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut keys = None;
+    loop {
+        let mut prefix = [0u8; 4];
+        if stream.read_exact(&mut prefix).await.is_err() {
+            break;
+        }
+        let len = match message_len(prefix) {
+            Ok(len) => len,
+            Err(e) => {
+                debug!("{}", e);
+                break;
+            }
+        };
+        let mut body = vec![0u8; len];
+        if stream.read_exact(&mut body).await.is_err() {
+            break;
+        }
+        let response = SshAgent::handle(&body, &client, &mut keys, &mut unusable).await;
+        if stream.write_all(&response.encode()).await.is_err() {
+            break;
+        }
+    }
+}
+
+/// Listen for clients while subscribed; dropping the subscription stops
+/// listening and removes the socket
+fn serve() -> impl iced::futures::Stream<Item = UnusableSshKey> {
+    iced::stream::channel(16, |output| async move {
+        let (unusable, reports) = mpsc::channel(16);
+        iced::futures::join!(listen(unusable), report(reports, output));
+    })
+}
+
+/// Pass each unusable key on once, however many clients come across it
+async fn report(
+    mut reports: mpsc::Receiver<UnusableSshKey>,
+    mut output: mpsc::Sender<UnusableSshKey>,
+) {
+    let mut reported = HashSet::new();
+    while let Some(key) = reports.next().await {
+        if reported.insert(key.title.clone()) && output.send(key).await.is_err() {
+            break;
+        }
+    }
+}
+
+#[cfg(unix)]
+async fn listen(unusable: mpsc::Sender<UnusableSshKey>) {
+    use super::api_server::{bind_user_socket, SocketFile};
+
+    let path = ssh_agent_socket_path();
+    let listener = match bind_user_socket(&path) {
+        Ok(listener) => listener,
+        Err(e) => {
+            warn!("Failed to serve the SSH agent on {:?}: {}", path, e);
+            return;
+        }
+    };
+    let _socket = SocketFile(path.clone());
+    info!("SSH agent listening on {:?}", path);
+
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(e) => {
+                warn!("SSH agent accept failed: {}", e);
+                continue;
+            }
+        };
+        let client = peer_name(&stream);
+        tokio::spawn(serve_connection(stream, client, unusable.clone()));
+    }
+}
+
+/// Name of the process on the other end, shown when asking for confirmation
+#[cfg(unix)]
+fn peer_name(stream: &tokio::net::UnixStream) -> String {
+    let process = stream
+        .peer_cred()
+        .ok()
+        .and_then(|cred| cred.pid())
+        .and_then(|pid| std::fs::read_to_string(format!("/proc/{}/comm", pid)).ok());
+    match process {
+        Some(name) if !name.trim().is_empty() => name.trim().to_string(),
+        _ => "an SSH client".to_string(),
+    }
+}
+
+#[cfg(windows)]
+async fn listen(unusable: mpsc::Sender<UnusableSshKey>) {
+    use tokio::net::windows::named_pipe::ServerOptions;
+
+    let mut server = match ServerOptions::new()
+        .first_pipe_instance(true)
+        .reject_remote_clients(true)
+        .create(SSH_AGENT_PIPE_NAME)
+    {
+        Ok(server) => server,
+        Err(e) => {
+            warn!(
+                "Failed to serve the SSH agent on {}: {}",
+                SSH_AGENT_PIPE_NAME, e
+            );
+            return;
+        }
+    };
+    info!("SSH agent listening on {}", SSH_AGENT_PIPE_NAME);
+
+    loop {
+        if let Err(e) = server.connect().await {
+            warn!("SSH agent accept failed: {}", e);
+            continue;
+        }
+        // Create the next instance before handing this one over, so
+        // clients never find the pipe missing
+        let next = match ServerOptions::new()
+            .reject_remote_clients(true)
+            .create(SSH_AGENT_PIPE_NAME)
+        {
+            Ok(next) => next,
+            Err(e) => {
+                warn!("SSH agent stopped: {}", e);
+                return;
+            }
+        };
+        let connected = std::mem::replace(&mut server, next);
+        tokio::spawn(serve_connection(
+            connected,
+            "an SSH client".to_string(),
+            unusable.clone(),
+        ));
+    }
+}
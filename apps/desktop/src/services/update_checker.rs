@@ -225,17 +225,7 @@ impl UpdateChecker {
 
     /// Parse a semantic version string into comparable parts
     fn parse_version(&self, version: &str) -> Option<(u32, u32, u32)> {
-        let parts: Vec<&str> = version.trim_start_matches('v').split('.').collect();
-        if parts.len() >= 3 {
-            if let (Ok(major), Ok(minor), Ok(patch)) = (
-                parts[0].parse::<u32>(),
-                parts[1].parse::<u32>(),
-                parts[2].parse::<u32>(),
-            ) {
-                return Some((major, minor, patch));
-            }
-        }
-        None
+        parse_version(version)
     }
 
     /// Get current version
@@ -273,6 +263,21 @@ impl UpdateChecker {
     }
 }
 
+/// Parse a semantic version string such as `v1.2.3` into comparable parts
+pub fn parse_version(version: &str) -> Option<(u32, u32, u32)> {
+    let parts: Vec<&str> = version.trim_start_matches('v').split('.').collect();
+    if parts.len() >= 3 {
+        if let (Ok(major), Ok(minor), Ok(patch)) = (
+            parts[0].parse::<u32>(),
+            parts[1].parse::<u32>(),
+            parts[2].parse::<u32>(),
+        ) {
+            return Some((major, minor, patch));
+        }
+    }
+    None
+}
+
 impl Default for UpdateChecker {
     fn default() -> Self {
         Self::new()
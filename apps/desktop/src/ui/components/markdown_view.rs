@@ -0,0 +1,151 @@
+//! Markdown Preview Component
+//!
+//! Renders the blocks read by the shared Markdown reader with iced widgets,
+//! for previewing notes written in Markdown. Links are reported through
+//! `on_link` instead of being opened here.
+
+use iced::{
+    font,
+    widget::{column, container, horizontal_rule, rich_text, row, span, text, text::Span, Space},
+    Background, Border, Element, Font, Length,
+};
+use ziplock_shared::utils::{parse_markdown, InlineSpan, MarkdownBlock};
+
+use crate::ui::theme::{self, utils::typography};
+
+/// Render `source` as Markdown
+pub fn markdown_view<'a, Message: Clone + 'static>(
+    source: &str,
+    on_link: impl Fn(String) -> Message + Copy,
+) -> Element<'a, Message> {
+    let blocks: Vec<Element<'a, Message>> = parse_markdown(source)
+        .into_iter()
+        .map(|block| view_block(block, on_link))
+        .collect();
+
+    column(blocks).spacing(8).width(Length::Fill).into()
+}
+
+fn view_block<'a, Message: Clone + 'static>(
+    block: MarkdownBlock,
+    on_link: impl Fn(String) -> Message + Copy,
+) -> Element<'a, Message> {
+    match block {
+        MarkdownBlock::Heading { level, spans } => {
+            let size = match level {
+                1 => typography::header_text_size(),
+                2 => typography::large_text_size(),
+                _ => typography::medium_text_size(),
+            };
+            let spans: Vec<_> = spans
+                .into_iter()
+                .map(|s| view_span(InlineSpan { strong: true, ..s }, on_link))
+                .collect();
+            rich_text(spans).size(size).into()
+        }
+        MarkdownBlock::Paragraph(spans) => paragraph(spans, on_link),
+        MarkdownBlock::ListItem {
+            depth,
+            number,
+            checked,
+            spans,
+        } => {
+            let marker = match (checked, number) {
+                (Some(true), _) => "☑".to_string(),
+                (Some(false), _) => "☐".to_string(),
+                (None, Some(number)) => format!("{}.", number),
+                (None, None) => "•".to_string(),
+            };
+            row![
+                Space::with_width(Length::Fixed(16.0 * depth as f32)),
+                text(marker).size(typography::normal_text_size()),
+                paragraph(spans, on_link),
+            ]
+            .spacing(6)
+            .into()
+        }
+        MarkdownBlock::Quote(spans) => {
+            let spans: Vec<_> = spans
+                .into_iter()
+                .map(|s| view_span(s, on_link).color(theme::LIGHT_GRAY_TEXT))
+                .collect();
+            container(rich_text(spans).size(typography::normal_text_size()))
+                .padding([6, 12])
+                .width(Length::Fill)
+                .style(|_| container::Style {
+                    background: Some(Background::Color(theme::LOGO_PURPLE_SUBTLE)),
+                    border: Border {
+                        color: theme::LOGO_PURPLE_LIGHT,
+                        width: 1.0,
+                        radius: 4.0.into(),
+                    },
+                    ..container::Style::default()
+                })
+                .into()
+        }
+        MarkdownBlock::CodeBlock { code, .. } => container(
+            text(code)
+                .font(Font::MONOSPACE)
+                .size(typography::small_text_size()),
+        )
+        .padding(10)
+        .width(Length::Fill)
+        .style(|_| container::Style {
+            background: Some(Background::Color(theme::VERY_LIGHT_GRAY)),
+            border: Border {
+                color: theme::EXTRA_LIGHT_GRAY,
+                width: 1.0,
+                radius: 4.0.into(),
+            },
+            ..container::Style::default()
+        })
+        .into(),
+        MarkdownBlock::Rule => horizontal_rule(1).into(),
+    }
+}
+
+fn paragraph<'a, Message: Clone + 'static>(
+    spans: Vec<InlineSpan>,
+    on_link: impl Fn(String) -> Message + Copy,
+) -> Element<'a, Message> {
+    let spans: Vec<_> = spans.into_iter().map(|s| view_span(s, on_link)).collect();
+    rich_text(spans)
+        .size(typography::normal_text_size())
+        .width(Length::Fill)
+        .into()
+}
+
+fn view_span<'a, Message: Clone + 'static>(
+    inline: InlineSpan,
+    on_link: impl Fn(String) -> Message,
+) -> Span<'a, Message, Font> {
+    let font = Font {
+        weight: if inline.strong {
+            font::Weight::Bold
+        } else {
+            font::Weight::Normal
+        },
+        style: if inline.emphasis {
+            font::Style::Italic
+        } else {
+            font::Style::Normal
+        },
+        ..if inline.code {
+            Font::MONOSPACE
+        } else {
+            Font::DEFAULT
+        }
+    };
+
+    let mut styled = span(inline.text).font(font);
+    if inline.code {
+        styled = styled.background(Background::Color(theme::VERY_LIGHT_GRAY));
+    }
+    if let Some(url) = inline.link {
+        styled = styled
+            .color(theme::LOGO_PURPLE)
+            .underline(true)
+            .link(on_link(url));
+    }
+    styled
+}
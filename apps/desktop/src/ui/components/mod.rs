@@ -4,9 +4,11 @@
 
 pub mod button;
 pub mod credential_form;
+pub mod markdown_view;
 pub mod toast;
 pub mod totp_field;
 pub mod update_dialog;
+pub mod whats_new;
 
 // Future UI components will be added here as needed:
 // - Password strength indicator
@@ -18,3 +20,4 @@ pub mod update_dialog;
 // Re-export components that are actually used by other modules
 pub use credential_form::{CredentialForm, CredentialFormConfig, CredentialFormMessage};
 pub use update_dialog::{UpdateDialog, UpdateDialogMessage};
+pub use whats_new::{WhatsNewDialog, WhatsNewMessage};
@@ -263,7 +263,6 @@ impl ToastManager {
     }
 
     /// Add an info toast
-    #[allow(dead_code)] // Future toast functionality
     pub fn info<S: Into<String>>(&mut self, message: S) -> usize {
         self.add_toast(AlertMessage::info(message))
     }
@@ -0,0 +1,121 @@
+//! What's New Dialog Component
+//!
+//! Shown once after an upgrade with the release notes of every version
+//! since the one the user last saw. Features that have settings link to
+//! the settings tab they live on.
+
+use iced::{
+    alignment::Horizontal,
+    widget::{button, column, container, row, scrollable, text, Space},
+    Alignment, Element, Length,
+};
+
+use crate::services::{FeatureNote, Release, SettingsSection};
+use crate::ui::theme::{self, utils};
+use crate::ui::views::SettingsTab;
+
+/// Messages for the what's new dialog
+#[derive(Debug, Clone)]
+pub enum WhatsNewMessage {
+    /// Close the dialog
+    Close,
+    /// Close the dialog and open the settings for a feature
+    OpenSettings(SettingsSection),
+}
+
+/// What's new dialog component
+#[derive(Debug)]
+pub struct WhatsNewDialog {
+    releases: Vec<Release>,
+}
+
+impl WhatsNewDialog {
+    /// Create a dialog for `releases`, newest first
+    pub fn new(releases: Vec<Release>) -> Self {
+        Self { releases }
+    }
+
+    /// Create the dialog view
+    pub fn view(&self) -> Element<'_, WhatsNewMessage> {
+        let title = text("What's New")
+            .size(utils::typography::header_text_size())
+            .align_x(Horizontal::Center);
+
+        let releases: Vec<Element<'_, WhatsNewMessage>> = self
+            .releases
+            .iter()
+            .map(|release| self.create_release(release))
+            .collect();
+
+        let close_button = button(text("Got it").align_x(Horizontal::Center))
+            .on_press(WhatsNewMessage::Close)
+            .padding(12)
+            .width(Length::Fixed(100.0))
+            .style(theme::button_styles::primary());
+
+        let content = column![
+            title,
+            Space::with_height(Length::Fixed(20.0)),
+            scrollable(column(releases).spacing(20).padding(15)).height(Length::Fill),
+            Space::with_height(Length::Fixed(20.0)),
+            container(close_button)
+                .width(Length::Fill)
+                .center_x(Length::Fill),
+        ]
+        .spacing(0)
+        .padding(30)
+        .max_width(600)
+        .width(Length::Fill);
+
+        container(content)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .center_x(Length::Fill)
+            .center_y(Length::Fill)
+            .into()
+    }
+
+    /// Create the notes for one version
+    fn create_release<'a>(&self, release: &'a Release) -> Element<'a, WhatsNewMessage> {
+        let mut notes =
+            column![text(format!("Version {}", release.version))
+                .size(utils::typography::large_text_size())]
+            .spacing(10);
+
+        if let Some(summary) = &release.summary {
+            notes = notes.push(
+                text(summary)
+                    .size(utils::typography::normal_text_size())
+                    .color(theme::LIGHT_GRAY_TEXT),
+            );
+        }
+        for feature in &release.features {
+            notes = notes.push(self.create_feature(feature));
+        }
+        notes.into()
+    }
+
+    /// Create one feature, with a link to its settings if it has any
+    fn create_feature<'a>(&self, feature: &'a FeatureNote) -> Element<'a, WhatsNewMessage> {
+        let description = column![
+            text(&feature.title).size(utils::typography::medium_text_size()),
+            text(&feature.description).size(utils::typography::normal_text_size()),
+        ]
+        .spacing(4)
+        .width(Length::Fill);
+
+        let mut feature_row = row![description].spacing(15).align_y(Alignment::Center);
+        if let Some(section) = feature.settings {
+            feature_row = feature_row.push(
+                button(
+                    text(format!("{} settings", SettingsTab::from(section).label()))
+                        .size(utils::typography::small_text_size()),
+                )
+                .on_press(WhatsNewMessage::OpenSettings(section))
+                .padding([6, 12])
+                .style(theme::button_styles::secondary()),
+            );
+        }
+        feature_row.into()
+    }
+}
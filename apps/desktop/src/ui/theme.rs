@@ -13,6 +13,7 @@ use iced::{
     widget::{button, svg, text_editor, text_input},
     Background, Border, Color, Shadow, Theme,
 };
+use ziplock_theme::{palette, Rgba};
 
 /// Embedded ZipLock logo SVG for use across all views
 pub const ZIPLOCK_LOGO_SVG: &[u8] = include_bytes!("../../resources/icons/ziplock-logo.svg");
@@ -155,45 +156,51 @@ pub fn license_icon() -> svg::Handle {
     svg::Handle::from_memory(LICENSE_ICON_SVG)
 }
 
-// ZipLock Brand Colors - Restored Original Values
+// ZipLock brand colors, from the shared design tokens in `ziplock_theme`
+
+/// Convert a design token into an iced color
+pub const fn color(token: Rgba) -> Color {
+    Color::from_rgba(token.r, token.g, token.b, token.a)
+}
+
 /// Logo purple color from design.md (#8338ec)
-pub const LOGO_PURPLE: Color = Color::from_rgb(0.514, 0.220, 0.925);
+pub const LOGO_PURPLE: Color = color(palette::ACCENT);
 
 /// Logo purple hover state (slightly darker)
-pub const LOGO_PURPLE_HOVER: Color = Color::from_rgb(0.45, 0.18, 0.82);
+pub const LOGO_PURPLE_HOVER: Color = color(palette::ACCENT_HOVER);
 
 /// Logo purple pressed state (even darker)
-pub const LOGO_PURPLE_PRESSED: Color = Color::from_rgb(0.40, 0.15, 0.75);
+pub const LOGO_PURPLE_PRESSED: Color = color(palette::ACCENT_PRESSED);
 
 /// Logo purple with low opacity for hover backgrounds
-pub const LOGO_PURPLE_LIGHT: Color = Color::from_rgba(0.514, 0.220, 0.925, 0.1);
+pub const LOGO_PURPLE_LIGHT: Color = color(palette::ACCENT_TINT);
 
 /// Logo purple with medium opacity for pressed backgrounds
-pub const LOGO_PURPLE_MEDIUM: Color = Color::from_rgba(0.514, 0.220, 0.925, 0.2);
+pub const LOGO_PURPLE_MEDIUM: Color = color(palette::ACCENT_TINT_STRONG);
 
 /// Logo purple with very light opacity for subtle backgrounds
-pub const LOGO_PURPLE_SUBTLE: Color = Color::from_rgba(0.514, 0.220, 0.925, 0.05);
+pub const LOGO_PURPLE_SUBTLE: Color = color(palette::ACCENT_TINT_SUBTLE);
 
 /// Success/Valid color from design.md (#06d6a0)
-pub const SUCCESS_GREEN: Color = Color::from_rgb(0.024, 0.839, 0.627);
+pub const SUCCESS_GREEN: Color = color(palette::SUCCESS);
 
 /// Error/Invalid color from design.md (#ef476f)
-pub const ERROR_RED: Color = Color::from_rgb(0.937, 0.278, 0.435);
+pub const ERROR_RED: Color = color(palette::DANGER);
 
 /// Error red hover state (slightly darker)
-pub const ERROR_RED_HOVER: Color = Color::from_rgb(0.85, 0.25, 0.40);
+pub const ERROR_RED_HOVER: Color = color(palette::DANGER_HOVER);
 
 /// Error red pressed state (even darker)
-pub const ERROR_RED_PRESSED: Color = Color::from_rgb(0.80, 0.22, 0.35);
+pub const ERROR_RED_PRESSED: Color = color(palette::DANGER_PRESSED);
 
 /// Warning color from design.md (#fcbf49)
-pub const WARNING_YELLOW: Color = Color::from_rgb(0.988, 0.749, 0.286);
+pub const WARNING_YELLOW: Color = color(palette::WARNING);
 
 /// Light background color from design.md (#F8F9FA)
-pub const LIGHT_BACKGROUND: Color = Color::from_rgb(0.97, 0.976, 0.98);
+pub const LIGHT_BACKGROUND: Color = color(palette::SURFACE);
 
 /// Dark text color from design.md (#212529)
-pub const DARK_TEXT: Color = Color::from_rgb(0.129, 0.145, 0.161);
+pub const DARK_TEXT: Color = color(palette::ON_SURFACE);
 
 /// White color constant
 pub const WHITE: Color = Color::WHITE;
@@ -202,31 +209,31 @@ pub const WHITE: Color = Color::WHITE;
 pub const TRANSPARENT: Color = Color::TRANSPARENT;
 
 /// Disabled background color (light gray)
-pub const DISABLED_BACKGROUND: Color = Color::from_rgb(0.8, 0.8, 0.8);
+pub const DISABLED_BACKGROUND: Color = color(palette::DISABLED);
 
 /// Disabled text color (medium gray)
-pub const DISABLED_TEXT: Color = Color::from_rgb(0.5, 0.5, 0.5);
+pub const DISABLED_TEXT: Color = color(palette::ON_DISABLED);
 
 /// Disabled border color (darker gray)
-pub const DISABLED_BORDER: Color = Color::from_rgb(0.7, 0.7, 0.7);
+pub const DISABLED_BORDER: Color = color(palette::DISABLED_BORDER);
 
 /// Standard shadow color (black with low opacity)
-pub const SHADOW_COLOR: Color = Color::from_rgba(0.0, 0.0, 0.0, 0.1);
+pub const SHADOW_COLOR: Color = color(palette::SHADOW);
 
 /// Light gray text color for help text
-pub const LIGHT_GRAY_TEXT: Color = Color::from_rgb(0.6, 0.6, 0.6);
+pub const LIGHT_GRAY_TEXT: Color = color(palette::ON_SURFACE_MUTED);
 
 /// Light gray border color for text inputs
-pub const LIGHT_GRAY_BORDER: Color = Color::from_rgb(0.8, 0.8, 0.8);
+pub const LIGHT_GRAY_BORDER: Color = color(palette::BORDER);
 
 /// Medium gray color for icons and placeholders
-pub const MEDIUM_GRAY: Color = Color::from_rgb(0.5, 0.5, 0.5);
+pub const MEDIUM_GRAY: Color = color(palette::ICON);
 
 /// Very light gray background for disabled inputs
-pub const VERY_LIGHT_GRAY: Color = Color::from_rgb(0.95, 0.95, 0.95);
+pub const VERY_LIGHT_GRAY: Color = color(palette::SURFACE_SUNKEN);
 
 /// Extra light gray border for disabled elements
-pub const EXTRA_LIGHT_GRAY: Color = Color::from_rgb(0.9, 0.9, 0.9);
+pub const EXTRA_LIGHT_GRAY: Color = color(palette::BORDER_SUBTLE);
 
 /// Creates the ZipLock custom theme with brand colors
 pub fn create_ziplock_theme() -> Theme {
@@ -1145,105 +1152,106 @@ pub mod text_editor_styles {
 /// Utility functions for consistent spacing, sizing, and styling
 pub mod utils {
     use iced::Padding;
+    use ziplock_theme::spacing;
 
     /// Creates a consistent spacing value for UI elements
     pub fn standard_spacing() -> u16 {
-        20
+        spacing::STANDARD
     }
 
     /// Creates a consistent padding value for buttons
     pub fn button_padding() -> Padding {
-        Padding::from([10, 20])
+        Padding::from(spacing::BUTTON)
     }
 
     /// Creates a consistent padding value for small buttons
     pub fn small_button_padding() -> Padding {
-        Padding::from([4, 8])
+        Padding::from(spacing::BUTTON_SMALL)
     }
 
     /// Creates a consistent padding value for standard UI buttons
     pub fn standard_button_padding() -> Padding {
-        Padding::from([12, 24])
+        Padding::from(spacing::BUTTON_STANDARD)
     }
 
     /// Creates a consistent padding value for repository buttons
     pub fn repository_button_padding() -> Padding {
-        Padding::from([15, 20])
+        Padding::from(spacing::LIST)
     }
 
     /// Creates a consistent padding value for setup buttons
     pub fn setup_button_padding() -> Padding {
-        Padding::from([12, 32])
+        Padding::from(spacing::BUTTON_WIDE)
     }
 
     /// Creates a consistent padding value for text inputs
     pub fn text_input_padding() -> Padding {
-        Padding::from([10, 15])
+        Padding::from(spacing::TEXT_INPUT)
     }
 
     /// Creates a consistent padding value for title text inputs (larger)
     pub fn title_input_padding() -> Padding {
-        Padding::from([15, 20])
+        Padding::from(spacing::TITLE_INPUT)
     }
 
     /// Creates a consistent padding value for toast dismiss buttons
     pub fn toast_dismiss_padding() -> Padding {
-        Padding::from([5, 8])
+        Padding::from(spacing::TOAST_DISMISS)
     }
 
     /// Creates a consistent padding value for small elements
     pub fn small_element_padding() -> Padding {
-        Padding::from([8, 12])
+        Padding::from(spacing::SMALL_ELEMENT)
     }
 
     /// Creates a consistent padding value for logo containers
     pub fn logo_container_padding() -> Padding {
-        Padding::from([20, 40])
+        Padding::from(spacing::LOGO_CONTAINER)
     }
 
     /// Creates a consistent padding value for main content areas
     pub fn main_content_padding() -> Padding {
-        Padding::from([20, 30])
+        Padding::from(spacing::MAIN_CONTENT)
     }
 
     /// Creates a consistent padding value for search bars
     pub fn search_bar_padding() -> Padding {
-        Padding::from([12, 16])
+        Padding::from(spacing::SEARCH_BAR)
     }
 
     /// Creates a consistent padding value for add credential buttons
     pub fn add_credential_button_padding() -> Padding {
-        Padding::from([10, 16])
+        Padding::from(spacing::BUTTON_ADD)
     }
 
     /// Creates a consistent padding value for scrollable lists
     pub fn list_padding() -> Padding {
-        Padding::from([15, 20])
+        Padding::from(spacing::LIST)
     }
 
     /// Creates a consistent padding value for error containers
     pub fn error_container_padding() -> Padding {
-        Padding::from([20, 25])
+        Padding::from(spacing::ERROR_CONTAINER)
     }
 
     /// Creates a consistent padding value for completion buttons
     pub fn completion_button_padding() -> Padding {
-        Padding::from([12, 20])
+        Padding::from(spacing::BUTTON_COMPLETION)
     }
 
     /// Creates a consistent border radius for UI elements
     pub fn border_radius() -> f32 {
-        10.0
+        spacing::RADIUS
     }
 
     /// Creates a consistent padding for alert components
     pub fn alert_padding() -> Padding {
-        Padding::from([15, 20])
+        Padding::from(spacing::ALERT)
     }
 
     /// Creates a consistent padding for password visibility toggle buttons
     pub fn password_toggle_padding() -> Padding {
-        Padding::from([8, 12])
+        Padding::from(spacing::SMALL_ELEMENT)
     }
 
     /// Creates a password visibility toggle button with eye icon and proper styling
@@ -1279,6 +1287,8 @@ pub mod utils {
     pub mod typography {
         use std::sync::OnceLock;
 
+        use ziplock_theme::TypeScale;
+
         static FONT_SIZE: OnceLock<f32> = OnceLock::new();
 
         /// Initialize the global font size
@@ -1286,39 +1296,39 @@ pub mod utils {
             let _ = FONT_SIZE.set(size);
         }
 
-        /// Get the base font size, defaulting to 14.0 if not set
-        fn base_font_size() -> f32 {
-            *FONT_SIZE.get().unwrap_or(&14.0)
+        /// Scale from the base font size, defaulting to 14.0 if not set
+        fn scale() -> TypeScale {
+            TypeScale::new(*FONT_SIZE.get().unwrap_or(&TypeScale::DEFAULT_BASE))
         }
 
         /// Get normal text size
         pub fn normal_text_size() -> f32 {
-            base_font_size()
+            scale().normal()
         }
 
         /// Get text input size
         pub fn text_input_size() -> f32 {
-            base_font_size()
+            scale().normal()
         }
 
         /// Get medium text size (slightly larger than normal)
         pub fn medium_text_size() -> f32 {
-            base_font_size() + 2.0
+            scale().medium()
         }
 
         /// Get small text size (smaller than normal)
         pub fn small_text_size() -> f32 {
-            base_font_size() - 2.0
+            scale().small()
         }
 
         /// Get header text size (larger than medium)
         pub fn header_text_size() -> f32 {
-            base_font_size() + 4.0
+            scale().header()
         }
 
         /// Get large text size (larger than header)
         pub fn large_text_size() -> f32 {
-            base_font_size() + 6.0
+            scale().large()
         }
 
         /// Get the icon SVG for a credential type
@@ -1330,7 +1340,7 @@ pub mod utils {
                 "identity" => crate::ui::theme::user_icon(),
                 "password" => crate::ui::theme::lock_icon(),
                 "document" => crate::ui::theme::document_icon(),
-                "ssh_key" => crate::ui::theme::settings_icon(),
+                "ssh_key" | "pgp_key" => crate::ui::theme::settings_icon(),
                 "bank_account" => crate::ui::theme::bank_icon(),
                 "api_credentials" => crate::ui::theme::settings_icon(),
                 "crypto_wallet" => crate::ui::theme::wallet_icon(),
@@ -1342,12 +1352,12 @@ pub mod utils {
 
         /// Get extra large text size (largest size)
         pub fn extra_large_text_size() -> f32 {
-            base_font_size() + 10.0
+            scale().extra_large()
         }
 
         /// Get title input size (for larger title inputs)
         pub fn title_input_size() -> f32 {
-            base_font_size() + 2.0
+            scale().medium()
         }
     }
 }
@@ -9,11 +9,14 @@
 use crate::services::get_repository_service;
 use iced::{
     alignment,
-    widget::{button, column, container, row, scrollable, svg, text, Space},
+    widget::{button, column, container, row, scrollable, svg, text, text_input, Space},
     Alignment, Element, Length, Task,
 };
 use std::collections::HashMap;
-use ziplock_shared::models::{CommonTemplates, CredentialField, CredentialTemplate, FieldType};
+use ziplock_shared::models::{
+    normalize_folder_path, CommonTemplates, CredentialField, CredentialFormSchema,
+    CredentialTemplate, FieldType, NoteFormat,
+};
 
 use crate::ui::components::{
     button as btn, CredentialForm, CredentialFormConfig, CredentialFormMessage,
@@ -27,7 +30,9 @@ pub enum AddCredentialMessage {
     Cancel,
 
     // Type selection
+    FolderChanged(String),
     TypeSelected(String),
+    SchemaLoaded(Result<CredentialFormSchema, String>),
     RefreshTypes,
     TypesLoaded(Result<Vec<CredentialTemplate>, String>),
 
@@ -57,7 +62,13 @@ impl PartialEq for AddCredentialMessage {
             (AddCredentialMessage::TypeSelected(a), AddCredentialMessage::TypeSelected(b)) => {
                 a == b
             }
+            (AddCredentialMessage::FolderChanged(a), AddCredentialMessage::FolderChanged(b)) => {
+                a == b
+            }
             (AddCredentialMessage::RefreshTypes, AddCredentialMessage::RefreshTypes) => true,
+            (AddCredentialMessage::SchemaLoaded(a), AddCredentialMessage::SchemaLoaded(b)) => {
+                a == b
+            }
             (AddCredentialMessage::CreateCredential, AddCredentialMessage::CreateCredential) => {
                 true
             }
@@ -111,6 +122,12 @@ pub struct AddCredentialView {
     /// Currently selected credential type
     selected_type: Option<CredentialTemplate>,
 
+    /// Folder the credential is created in
+    folder_path: String,
+
+    /// Form defaults resolved for the folder
+    schema: Option<CredentialFormSchema>,
+
     /// The credential form component
     form: CredentialForm,
 
@@ -134,6 +151,8 @@ impl AddCredentialView {
             state: AddCredentialState::SelectingType,
             available_types: Self::get_builtin_templates(),
             selected_type: None,
+            folder_path: String::new(),
+            schema: None,
             form: CredentialForm::new(),
             is_loading: false,
             session_id: None,
@@ -157,6 +176,7 @@ impl AddCredentialView {
             CommonTemplates::password(),
             CommonTemplates::document(),
             CommonTemplates::ssh_key(),
+            CommonTemplates::pgp_key(),
             CommonTemplates::bank_account(),
             CommonTemplates::api_credentials(),
             CommonTemplates::crypto_wallet(),
@@ -173,6 +193,33 @@ impl AddCredentialView {
                 Task::none()
             }
 
+            AddCredentialMessage::FolderChanged(folder_path) => {
+                self.folder_path = folder_path;
+                // Preview the folder's defaults while a type is being picked
+                Task::perform(
+                    Self::load_schema_async(self.folder_path.clone(), None),
+                    AddCredentialMessage::SchemaLoaded,
+                )
+            }
+
+            AddCredentialMessage::SchemaLoaded(result) => {
+                match result {
+                    // Ignore answers for a folder the user has already typed past
+                    Ok(schema)
+                        if schema.folder_path.as_deref().unwrap_or_default()
+                            == normalize_folder_path(&self.folder_path) =>
+                    {
+                        self.schema = Some(schema);
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        tracing::warn!("Failed to load folder defaults: {}", e);
+                        self.schema = None;
+                    }
+                }
+                Task::none()
+            }
+
             AddCredentialMessage::TypeSelected(type_name) => {
                 if let Some(template) = self
                     .available_types
@@ -309,6 +356,8 @@ impl AddCredentialView {
                         self.session_id.clone(),
                         self.form.title().to_string(),
                         self.form.field_values().clone(),
+                        self.form.note_formats().clone(),
+                        normalize_folder_path(&self.folder_path),
                         self.selected_type
                             .as_ref()
                             .map(|t| t.name.clone())
@@ -382,6 +431,13 @@ impl AddCredentialView {
 
     /// Render the type selection state
     fn view_type_selection(&self) -> Element<'_, AddCredentialMessage> {
+        // The folder's template is highlighted
+        let suggested = self
+            .schema
+            .as_ref()
+            .filter(|schema| schema.template_inherited)
+            .map(|schema| schema.template.name.as_str());
+
         // Create a responsive grid layout for credential type buttons
         let mut grid_rows = vec![];
         let templates_per_row = 4; // Increased to 4 for better use of space
@@ -420,8 +476,12 @@ impl AddCredentialView {
                 .on_press(AddCredentialMessage::TypeSelected(template.name.clone()))
                 .width(Length::Fill)
                 .height(Length::Fixed(90.0))
-                .padding([15, 10])
-                .style(crate::ui::theme::button_styles::secondary());
+                .padding([15, 10]);
+                let button_element = if suggested == Some(template.name.as_str()) {
+                    button_element.style(crate::ui::theme::button_styles::primary())
+                } else {
+                    button_element.style(crate::ui::theme::button_styles::secondary())
+                };
 
                 row_buttons.push(button_element.into());
             }
@@ -458,6 +518,8 @@ impl AddCredentialView {
                     .size(crate::ui::theme::utils::typography::large_text_size())
                     .align_x(alignment::Horizontal::Center),
                 Space::with_height(Length::Fixed(25.0)),
+                self.view_folder_input(),
+                Space::with_height(Length::Fixed(20.0)),
                 grid_container,
                 Space::with_height(Length::Fixed(20.0)),
                 cancel_button,
@@ -471,6 +533,44 @@ impl AddCredentialView {
         .into()
     }
 
+    /// Folder input with a summary of the defaults the folder passes on
+    fn view_folder_input(&self) -> Element<'_, AddCredentialMessage> {
+        let inherited = self.schema.as_ref().map(|schema| {
+            let mut parts = Vec::new();
+            if schema.template_inherited {
+                parts.push(format!("type {}", schema.template.to_display_name()));
+            }
+            if let Some(icon) = &schema.icon {
+                parts.push(format!("icon {}", icon));
+            }
+            if let Some(color) = &schema.color {
+                parts.push(format!("color {}", color));
+            }
+            parts
+        });
+        let hint = match inherited {
+            Some(parts) if !parts.is_empty() => {
+                format!("New credentials here get {}", parts.join(", "))
+            }
+            _ => String::new(),
+        };
+
+        container(
+            column![
+                text_input("Folder (optional), e.g. Work/Servers", &self.folder_path)
+                    .on_input(AddCredentialMessage::FolderChanged)
+                    .padding(theme::utils::text_input_padding())
+                    .size(theme::utils::typography::text_input_size())
+                    .style(theme::text_input_styles::standard()),
+                text(hint).size(theme::utils::typography::small_text_size()),
+            ]
+            .spacing(6),
+        )
+        .padding([0, 30])
+        .width(Length::Fill)
+        .into()
+    }
+
     /// Render the credential form state
     fn view_credential_form(&self) -> Element<'_, AddCredentialMessage> {
         container(
@@ -558,6 +658,7 @@ impl AddCredentialView {
             CommonTemplates::password(),
             CommonTemplates::document(),
             CommonTemplates::ssh_key(),
+            CommonTemplates::pgp_key(),
             CommonTemplates::bank_account(),
             CommonTemplates::api_credentials(),
             CommonTemplates::crypto_wallet(),
@@ -566,11 +667,24 @@ impl AddCredentialView {
         ])
     }
 
+    /// Load the form defaults of a folder
+    async fn load_schema_async(
+        folder_path: String,
+        template: Option<String>,
+    ) -> Result<CredentialFormSchema, String> {
+        get_repository_service()
+            .credential_form_schema(Some(folder_path), template)
+            .await
+            .map_err(|e| e.to_string())
+    }
+
     /// Create a credential asynchronously
     async fn create_credential_async(
         _session_id: Option<String>,
         title: String,
         field_values: HashMap<String, String>,
+        note_formats: HashMap<String, NoteFormat>,
+        folder_path: String,
         credential_type: String,
     ) -> Result<String, String> {
         // Use repository service for unified architecture
@@ -585,6 +699,7 @@ impl AddCredentialView {
             "password" => Some(ziplock_shared::models::CommonTemplates::password()),
             "document" => Some(ziplock_shared::models::CommonTemplates::document()),
             "ssh_key" => Some(ziplock_shared::models::CommonTemplates::ssh_key()),
+            "pgp_key" => Some(ziplock_shared::models::CommonTemplates::pgp_key()),
             "bank_account" => Some(ziplock_shared::models::CommonTemplates::bank_account()),
             "api_credentials" => Some(ziplock_shared::models::CommonTemplates::api_credentials()),
             "crypto_wallet" => Some(ziplock_shared::models::CommonTemplates::crypto_wallet()),
@@ -615,13 +730,18 @@ impl AddCredentialView {
                     (FieldType::Text, false, field_name.clone())
                 };
 
-                let field = CredentialField {
+                let mut field = CredentialField {
                     field_type,
                     value,
                     sensitive,
                     label: Some(label),
                     metadata: HashMap::new(),
+                    protection: None,
+                    value_updated_at: None,
                 };
+                if let Some(&format) = note_formats.get(&field_name) {
+                    field.set_note_format(format);
+                }
                 (field_name, field)
             })
             .collect();
@@ -636,6 +756,8 @@ impl AddCredentialView {
         credential.fields = fields;
         credential.tags = Vec::new();
         credential.notes = None;
+        // The repository gives the credential its folder's icon and color
+        credential.folder_path = Some(folder_path).filter(|path| !path.is_empty());
 
         repo_service
             .add_credential(credential)
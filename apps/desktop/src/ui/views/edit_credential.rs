@@ -12,7 +12,9 @@ use std::collections::HashMap;
 
 use crate::ui::components::{CredentialForm, CredentialFormConfig, CredentialFormMessage};
 
-use ziplock_shared::models::{CredentialField, CredentialRecord, CredentialTemplate, FieldType};
+use ziplock_shared::models::{
+    CredentialField, CredentialRecord, CredentialTemplate, FieldType, NoteFormat,
+};
 
 /// Messages for the edit credential view
 #[derive(Debug, Clone)]
@@ -22,7 +24,7 @@ pub enum EditCredentialMessage {
     /// Load the credential data
     LoadCredential,
     /// Credential data was loaded
-    CredentialLoaded(Result<CredentialRecord, String>),
+    CredentialLoaded(Box<Result<CredentialRecord, String>>),
     /// Load available credential types
     RefreshTypes,
     /// Credential types were loaded
@@ -121,6 +123,7 @@ impl EditCredentialView {
             CommonTemplates::password(),
             CommonTemplates::document(),
             CommonTemplates::ssh_key(),
+            CommonTemplates::pgp_key(),
             CommonTemplates::bank_account(),
             CommonTemplates::api_credentials(),
             CommonTemplates::crypto_wallet(),
@@ -145,7 +148,7 @@ impl EditCredentialView {
                             self.session_id.clone(),
                             self.credential_id.clone(),
                         ),
-                        EditCredentialMessage::CredentialLoaded,
+                        |result| EditCredentialMessage::CredentialLoaded(Box::new(result)),
                     ),
                     Task::perform(
                         Self::load_credential_types_async(self.session_id.clone()),
@@ -155,7 +158,7 @@ impl EditCredentialView {
             }
 
             EditCredentialMessage::CredentialLoaded(result) => {
-                match result {
+                match *result {
                     Ok(credential) => {
                         // Find the matching template for this credential type
                         let template = self
@@ -195,6 +198,12 @@ impl EditCredentialView {
                         }
                         tracing::info!("Setting {} field values on form...", field_values.len());
                         self.form.set_field_values(field_values);
+                        for (field_name, field) in &credential.fields {
+                            if field.field_type == FieldType::TextArea {
+                                self.form
+                                    .set_note_format(field_name.clone(), field.note_format());
+                            }
+                        }
 
                         // Configure form to show delete button with title styling
                         let config = CredentialFormConfig {
@@ -326,6 +335,7 @@ impl EditCredentialView {
                         self.credential_id.clone(),
                         self.form.title().to_string(),
                         self.form.field_values().clone(),
+                        self.form.note_formats().clone(),
                         self.credential
                             .as_ref()
                             .map(|c| c.credential_type.clone())
@@ -452,17 +462,32 @@ impl EditCredentialView {
 
     /// Render the editing state
     fn view_editing(&self) -> Element<'_, EditCredentialMessage> {
-        container(
-            column![
-                Space::with_height(Length::Fixed(20.0)),
-                self.form.view().map(EditCredentialMessage::FormMessage),
-            ]
-            .spacing(10),
-        )
-        .padding(40)
-        .width(Length::Fill)
-        .height(Length::Fill)
-        .into()
+        let mut content = column![Space::with_height(Length::Fixed(20.0))].spacing(10);
+        if let Some(changed) = self.password_age() {
+            content = content.push(
+                text(format!("Password last changed {}", changed))
+                    .size(crate::ui::theme::utils::typography::small_text_size())
+                    .color(crate::ui::theme::LIGHT_GRAY_TEXT),
+            );
+        }
+        container(content.push(self.form.view().map(EditCredentialMessage::FormMessage)))
+            .padding(40)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .into()
+    }
+
+    /// How long ago the password was changed, if the credential has one and
+    /// the time is known
+    fn password_age(&self) -> Option<String> {
+        self.credential
+            .as_ref()?
+            .fields
+            .values()
+            .filter(|field| field.field_type == FieldType::Password)
+            .filter_map(|field| field.value_updated_at)
+            .max()
+            .map(ziplock_shared::utils::time_utils::format_duration_since)
     }
 
     /// Render the saving state
@@ -593,6 +618,7 @@ impl EditCredentialView {
         id: String,
         title: String,
         field_values: HashMap<String, String>,
+        note_formats: HashMap<String, NoteFormat>,
         credential_type: String,
     ) -> Result<(), String> {
         // Use hybrid client for unified architecture
@@ -607,6 +633,7 @@ impl EditCredentialView {
             "password" => Some(ziplock_shared::models::CommonTemplates::password()),
             "document" => Some(ziplock_shared::models::CommonTemplates::document()),
             "ssh_key" => Some(ziplock_shared::models::CommonTemplates::ssh_key()),
+            "pgp_key" => Some(ziplock_shared::models::CommonTemplates::pgp_key()),
             "bank_account" => Some(ziplock_shared::models::CommonTemplates::bank_account()),
             "api_credentials" => Some(ziplock_shared::models::CommonTemplates::api_credentials()),
             "crypto_wallet" => Some(ziplock_shared::models::CommonTemplates::crypto_wallet()),
@@ -637,13 +664,18 @@ impl EditCredentialView {
                     (FieldType::Text, false, field_name.clone())
                 };
 
-                let field = CredentialField {
+                let mut field = CredentialField {
                     field_type,
                     value,
                     sensitive,
                     label: Some(label),
                     metadata: HashMap::new(),
+                    protection: None,
+                    value_updated_at: None,
                 };
+                if let Some(&format) = note_formats.get(&field_name) {
+                    field.set_note_format(format);
+                }
                 (field_name, field)
             })
             .collect();
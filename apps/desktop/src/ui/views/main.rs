@@ -3,13 +3,18 @@
 //! This view represents the primary interface shown after the initial setup wizard.
 //! It demonstrates how to use the shared theme system across different views.
 
-use crate::services::get_repository_service;
+use crate::services::{get_repository_service, AutoTypeService, RepositoryService};
 
 use crate::ui::{components::button as btn, theme, utils};
 use iced::{
+    keyboard::{self, Key, Modifiers},
+    widget::scrollable::RelativeOffset,
     widget::{button, column, container, row, scrollable, svg, text, text_input, Space},
     Alignment, Element, Length, Task,
 };
+use serde::{Deserialize, Serialize};
+use ziplock_shared::config::AutoTypeConfig;
+use ziplock_shared::utils::{Reminder, ReminderStatus, VaultHealth, VaultHealthAnalyzer};
 
 /// Messages for the main application view
 #[derive(Debug, Clone)]
@@ -26,11 +31,22 @@ pub enum MainViewMessage {
     DeleteCredential(String),
     RefreshCredentials,
 
+    // Auto-type
+    AutoType(String),
+    AutoTypeSelected,
+    AutoTypeCompleted(Result<String, String>),
+    AutoTypePreviewed(Result<String, String>),
+
     // Data operations
     CredentialsLoaded(Result<(Vec<CredentialItem>, Option<String>, bool), String>),
+    SearchResultsLoaded(Result<(Vec<CredentialItem>, Option<String>, bool), String>),
+    ViewStateLoaded(Option<MainViewState>),
+    HealthUpdated(Option<Box<VaultHealth>>),
+    RemindersUpdated(Vec<Reminder>),
     OperationCompleted(Result<String, String>),
 
     // UI actions
+    ListScrolled(f32),
     LockDatabase,
     ShowSettings,
     ShowAbout,
@@ -39,6 +55,10 @@ pub enum MainViewMessage {
     // Repository management
     CloseRepository,
     RepositoryOperationComplete(Result<String, String>),
+    ArchiveChangedOnDisk,
+    ReloadFromDisk,
+    MergeFromDisk,
+    ChangesOnDiskHandled(Result<String, String>),
 
     // Session management
     SessionTimeout,
@@ -51,6 +71,26 @@ pub enum MainViewMessage {
     TriggerValidationError,
 }
 
+/// Id of the search field, see [`MainView::focus_search`]
+const SEARCH_INPUT_ID: &str = "credential_search";
+
+/// Id of the credential list, scrolled back into place after unlocking
+const CREDENTIAL_LIST_ID: &str = "credential_list";
+
+/// Key of [`MainViewState`] in the vault's secure store
+const VIEW_STATE_KEY: &str = "desktop.main_view";
+
+/// Where the user was in the main view, kept encrypted per vault so that
+/// unlocking again after an auto-lock returns to the same place
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MainViewState {
+    pub search_query: String,
+    pub selected_credential: Option<String>,
+    /// Relative scroll position of the credential list, 0.0 to 1.0
+    pub scroll_offset: f32,
+}
+
 /// Main application view state
 #[derive(Debug, Default)]
 pub struct MainView {
@@ -61,6 +101,14 @@ pub struct MainView {
     is_authenticated: bool,
     selected_credential: Option<String>,
     is_loading: bool,
+    health: Option<Box<VaultHealth>>,
+    reminders: Vec<Reminder>,
+    auto_type: AutoTypeConfig,
+    last_backup: Option<chrono::DateTime<chrono::Utc>>,
+    scroll_offset: f32,
+    view_state_requested: bool,
+    /// Another device saved the vault since it was opened or last saved
+    changed_on_disk: bool,
 }
 
 /// Represents a credential item in the list
@@ -87,6 +135,42 @@ impl MainView {
         // Authentication status will be updated when we actually load credentials
     }
 
+    /// Set how auto-type paces keystrokes, and whether it only previews
+    pub fn set_auto_type_config(&mut self, config: AutoTypeConfig) {
+        self.auto_type = config;
+    }
+
+    /// Whether auto-type describes what it would type instead of typing
+    pub fn auto_type_dry_run(&self) -> bool {
+        self.auto_type.dry_run
+    }
+
+    /// Set the time of the last successful backup, used for the health score
+    pub fn set_last_backup(&mut self, last_backup: Option<chrono::DateTime<chrono::Utc>>) {
+        self.last_backup = last_backup;
+    }
+
+    /// Move the keyboard focus to the search field
+    pub fn focus_search() -> Task<MainViewMessage> {
+        text_input::focus(text_input::Id::new(SEARCH_INPUT_ID))
+    }
+
+    /// Keep the search, selection and scroll position in the vault's secure
+    /// store, where the next view restores them from
+    fn remember_view_state(&self) -> Task<MainViewMessage> {
+        let state = MainViewState {
+            search_query: self.search_query.clone(),
+            selected_credential: self.selected_credential.clone(),
+            scroll_offset: self.scroll_offset,
+        };
+        Task::future(async move {
+            get_repository_service()
+                .remember_view_state(VIEW_STATE_KEY, state)
+                .await
+        })
+        .discard()
+    }
+
     /// Create a command to refresh credentials if we have a session
     pub fn initial_refresh_command(&self) -> Task<MainViewMessage> {
         if self.session_id.is_some() {
@@ -112,13 +196,50 @@ impl MainView {
         }
     }
 
+    /// Keyboard shortcuts for the credential list
+    pub fn subscription(&self) -> iced::Subscription<MainViewMessage> {
+        if self.is_authenticated {
+            // Reload whenever credentials change, wherever the change was made
+            iced::Subscription::batch([
+                keyboard::on_key_press(Self::handle_key_press),
+                RepositoryService::credential_changes()
+                    .map(|_| MainViewMessage::RefreshCredentials),
+                RepositoryService::archive_changes().map(|_| MainViewMessage::ArchiveChangedOnDisk),
+            ])
+        } else {
+            iced::Subscription::none()
+        }
+    }
+
+    /// Ctrl+Shift+A (Cmd+Shift+A on macOS) auto-types the selected credential
+    fn handle_key_press(key: Key, modifiers: Modifiers) -> Option<MainViewMessage> {
+        match key.as_ref() {
+            Key::Character(c)
+                if modifiers.command() && modifiers.shift() && c.eq_ignore_ascii_case("a") =>
+            {
+                Some(MainViewMessage::AutoTypeSelected)
+            }
+            _ => None,
+        }
+    }
+
+    /// The credential the auto-type shortcut acts on: the selected one if it is
+    /// still visible, otherwise the top search result
+    pub fn auto_type_target(&self) -> Option<String> {
+        self.selected_credential
+            .as_ref()
+            .filter(|id| self.filtered_credentials.iter().any(|c| &c.id == *id))
+            .cloned()
+            .or_else(|| self.filtered_credentials.first().map(|c| c.id.clone()))
+    }
+
     /// Update the main view based on messages
     pub fn update(&mut self, message: MainViewMessage) -> Task<MainViewMessage> {
         match message {
             MainViewMessage::SearchChanged(query) => {
                 self.search_query = query;
                 self.filter_credentials();
-                Task::none()
+                self.remember_view_state()
             }
 
             MainViewMessage::SearchSubmitted => {
@@ -127,7 +248,7 @@ impl MainView {
                     self.is_loading = true;
                     Task::perform(
                         Self::search_credentials_async(self.search_query.clone()),
-                        MainViewMessage::CredentialsLoaded,
+                        MainViewMessage::SearchResultsLoaded,
                     )
                 } else {
                     self.filter_credentials();
@@ -138,7 +259,7 @@ impl MainView {
             MainViewMessage::ClearSearch => {
                 self.search_query.clear();
                 self.filter_credentials();
-                Task::none()
+                self.remember_view_state()
             }
 
             MainViewMessage::AddCredential => {
@@ -155,7 +276,7 @@ impl MainView {
             MainViewMessage::CredentialClicked(id) => {
                 self.selected_credential = Some(id);
                 // TODO: Show edit credential dialog
-                Task::none()
+                self.remember_view_state()
             }
 
             MainViewMessage::DeleteCredential(_id) => {
@@ -163,6 +284,27 @@ impl MainView {
                 Task::none()
             }
 
+            MainViewMessage::AutoType(id) => {
+                self.selected_credential = Some(id.clone());
+                if self.auto_type.dry_run {
+                    return Task::perform(
+                        Self::preview_auto_type_async(id, self.auto_type),
+                        MainViewMessage::AutoTypePreviewed,
+                    );
+                }
+                Task::perform(
+                    Self::auto_type_async(id, self.auto_type),
+                    MainViewMessage::AutoTypeCompleted,
+                )
+            }
+
+            MainViewMessage::AutoTypeSelected
+            | MainViewMessage::AutoTypeCompleted(_)
+            | MainViewMessage::AutoTypePreviewed(_) => {
+                // Window handling and result toasts are done at the application level
+                Task::none()
+            }
+
             MainViewMessage::RefreshCredentials => {
                 self.is_loading = true;
                 Task::perform(
@@ -188,6 +330,25 @@ impl MainView {
                                 "Successfully loaded {} credentials, authenticated=true",
                                 cred_count
                             );
+                            let health = Task::batch([
+                                Task::perform(
+                                    Self::compute_health_async(self.last_backup),
+                                    MainViewMessage::HealthUpdated,
+                                ),
+                                Task::perform(
+                                    Self::load_reminders_async(),
+                                    MainViewMessage::RemindersUpdated,
+                                ),
+                            ]);
+                            if self.view_state_requested {
+                                return health;
+                            }
+                            self.view_state_requested = true;
+                            let view_state = Task::perform(
+                                async { get_repository_service().view_state(VIEW_STATE_KEY).await },
+                                MainViewMessage::ViewStateLoaded,
+                            );
+                            return Task::batch([health, view_state]);
                         } else {
                             tracing::debug!(
                                 "Loaded {} credentials but authenticated=false",
@@ -207,6 +368,62 @@ impl MainView {
                 Task::none()
             }
 
+            MainViewMessage::SearchResultsLoaded(result) => {
+                self.is_loading = false;
+                match result {
+                    // Ranked hits replace the substring filter, but only while
+                    // the query they were found for is still shown
+                    Ok((found, _, _)) if !self.search_query.trim().is_empty() => {
+                        self.filtered_credentials = found;
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        if let Some(timeout_command) = self.handle_potential_session_timeout(&e) {
+                            return timeout_command;
+                        }
+                    }
+                }
+                Task::none()
+            }
+
+            MainViewMessage::ViewStateLoaded(state) => {
+                let Some(state) = state else {
+                    return Task::none();
+                };
+                self.search_query = state.search_query;
+                self.selected_credential = state
+                    .selected_credential
+                    .filter(|id| self.credentials.iter().any(|c| &c.id == id));
+                self.scroll_offset = state.scroll_offset.clamp(0.0, 1.0);
+                self.filter_credentials();
+                scrollable::snap_to(
+                    scrollable::Id::new(CREDENTIAL_LIST_ID),
+                    RelativeOffset {
+                        x: 0.0,
+                        y: self.scroll_offset,
+                    },
+                )
+            }
+
+            MainViewMessage::ListScrolled(offset) => {
+                // Lists shorter than the view report no offset
+                if !offset.is_finite() || offset == self.scroll_offset {
+                    return Task::none();
+                }
+                self.scroll_offset = offset;
+                self.remember_view_state()
+            }
+
+            MainViewMessage::HealthUpdated(health) => {
+                self.health = health;
+                Task::none()
+            }
+
+            MainViewMessage::RemindersUpdated(reminders) => {
+                self.reminders = reminders;
+                Task::none()
+            }
+
             MainViewMessage::OperationCompleted(result) => {
                 self.is_loading = false;
                 match result {
@@ -297,6 +514,36 @@ impl MainView {
                 )
             }
 
+            MainViewMessage::ArchiveChangedOnDisk => {
+                self.changed_on_disk = true;
+                Task::none()
+            }
+
+            MainViewMessage::ReloadFromDisk => Task::perform(
+                Self::reload_from_disk_async(),
+                MainViewMessage::ChangesOnDiskHandled,
+            ),
+
+            MainViewMessage::MergeFromDisk => Task::perform(
+                Self::merge_from_disk_async(),
+                MainViewMessage::ChangesOnDiskHandled,
+            ),
+
+            MainViewMessage::ChangesOnDiskHandled(result) => match result {
+                Ok(message) => {
+                    self.changed_on_disk = false;
+                    self.is_loading = true;
+                    Task::batch([
+                        Task::perform(
+                            Self::load_credentials_async(self.session_id.clone()),
+                            MainViewMessage::CredentialsLoaded,
+                        ),
+                        Task::done(MainViewMessage::OperationCompleted(Ok(message))),
+                    ])
+                }
+                Err(error) => Task::done(MainViewMessage::ShowError(error)),
+            },
+
             MainViewMessage::RepositoryOperationComplete(result) => {
                 match result {
                     Ok(message) => {
@@ -424,6 +671,20 @@ impl MainView {
             Space::with_height(Length::Fixed(utils::standard_spacing().into())),
         ];
 
+        if let Some(health) = &self.health {
+            if self.search_query.is_empty() {
+                content_column = content_column.push(Self::view_health(health));
+            }
+        }
+
+        if self.changed_on_disk {
+            content_column = content_column.push(Self::view_changed_on_disk());
+        }
+
+        if !self.reminders.is_empty() && self.search_query.is_empty() {
+            content_column = content_column.push(self.view_reminders());
+        }
+
         let credential_list = self.view_credential_list();
         content_column = content_column.push(credential_list);
 
@@ -437,10 +698,113 @@ impl MainView {
             .into()
     }
 
+    /// Render the vault health score and its top checklist items
+    fn view_health(health: &VaultHealth) -> Element<'_, MainViewMessage> {
+        let color = match health.score {
+            75.. => theme::SUCCESS_GREEN,
+            50..=74 => theme::WARNING_YELLOW,
+            _ => theme::ERROR_RED,
+        };
+
+        let mut items = column![row![
+            text(format!("Vault health: {}/100", health.score))
+                .size(crate::ui::theme::utils::typography::medium_text_size())
+                .color(color),
+            Space::with_width(Length::Fixed(10.0)),
+            text(health.grade())
+                .size(crate::ui::theme::utils::typography::small_text_size())
+                .color(theme::MEDIUM_GRAY),
+        ]
+        .align_y(Alignment::Center)]
+        .spacing(4);
+
+        for item in health.checklist.iter().take(3) {
+            items = items.push(
+                text(format!("• {} (+{})", item.title, item.points))
+                    .size(crate::ui::theme::utils::typography::small_text_size()),
+            );
+        }
+
+        container(items)
+            .padding(utils::list_padding())
+            .width(Length::Fill)
+            .into()
+    }
+
+    /// Render the "needs attention" list of expirations and password changes
+    fn view_reminders(&self) -> Element<'_, MainViewMessage> {
+        let mut items =
+            column![text("Needs attention")
+                .size(crate::ui::theme::utils::typography::medium_text_size())]
+            .spacing(4);
+
+        for reminder in self.reminders.iter().take(5) {
+            let color = match reminder.status {
+                ReminderStatus::Overdue => theme::ERROR_RED,
+                ReminderStatus::Upcoming => theme::WARNING_YELLOW,
+            };
+            items = items.push(
+                button(
+                    row![
+                        text(&reminder.title)
+                            .size(crate::ui::theme::utils::typography::small_text_size()),
+                        Space::with_width(Length::Fixed(10.0)),
+                        text(reminder.summary())
+                            .size(crate::ui::theme::utils::typography::small_text_size())
+                            .color(color),
+                    ]
+                    .align_y(Alignment::Center),
+                )
+                .on_press(MainViewMessage::EditCredential(
+                    reminder.credential_id.clone(),
+                ))
+                .padding(2)
+                .style(theme::button_styles::credential_list_item()),
+            );
+        }
+        if self.reminders.len() > 5 {
+            items = items.push(
+                text(format!("and {} more", self.reminders.len() - 5))
+                    .size(crate::ui::theme::utils::typography::small_text_size())
+                    .color(theme::MEDIUM_GRAY),
+            );
+        }
+
+        container(items)
+            .padding(utils::list_padding())
+            .width(Length::Fill)
+            .into()
+    }
+
+    /// Render the notice that another device saved the vault, with the
+    /// ways to go on
+    fn view_changed_on_disk() -> Element<'static, MainViewMessage> {
+        let notice = column![
+            text("This vault was changed on another device")
+                .size(crate::ui::theme::utils::typography::medium_text_size())
+                .color(theme::WARNING_YELLOW),
+            text("Saving here would overwrite those changes. Reload to use the other copy, or merge both.")
+                .size(crate::ui::theme::utils::typography::small_text_size())
+                .color(theme::MEDIUM_GRAY),
+            row![
+                btn::small_button("Reload", Some(MainViewMessage::ReloadFromDisk)),
+                btn::small_secondary_button("Merge", Some(MainViewMessage::MergeFromDisk)),
+            ]
+            .spacing(8),
+        ]
+        .spacing(4);
+
+        container(notice)
+            .padding(utils::list_padding())
+            .width(Length::Fill)
+            .into()
+    }
+
     /// Render the search bar
     fn view_search_bar(&self) -> Element<'_, MainViewMessage> {
         row![
             text_input("Search credentials...", &self.search_query)
+                .id(text_input::Id::new(SEARCH_INPUT_ID))
                 .on_input(MainViewMessage::SearchChanged)
                 .on_submit(MainViewMessage::SearchSubmitted)
                 .width(Length::FillPortion(3))
@@ -542,6 +906,8 @@ impl MainView {
                 .spacing(10)
                 .padding(utils::list_padding()),
         )
+        .id(scrollable::Id::new(CREDENTIAL_LIST_ID))
+        .on_scroll(|viewport| MainViewMessage::ListScrolled(viewport.relative_offset().y))
         .height(Length::Fill)
         .into()
     }
@@ -587,7 +953,11 @@ impl MainView {
                     )
                     .width(Length::Fill)
                     .align_x(iced::alignment::Horizontal::Left)
-                }
+                },
+                btn::small_secondary_button(
+                    "Auto-type",
+                    Some(MainViewMessage::AutoType(credential.id.clone())),
+                )
             ]
             .spacing(12)
             .padding(15)
@@ -672,6 +1042,34 @@ impl MainView {
         }
     }
 
+    /// Score the open repository's credentials
+    async fn compute_health_async(
+        last_backup: Option<chrono::DateTime<chrono::Utc>>,
+    ) -> Option<Box<VaultHealth>> {
+        match get_repository_service().list_credentials().await {
+            Ok(credentials) => Some(Box::new(
+                VaultHealthAnalyzer::new()
+                    .with_last_backup(last_backup)
+                    .analyze(&credentials),
+            )),
+            Err(e) => {
+                tracing::warn!("Failed to compute vault health: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Expiry and password-change reminders of the open repository
+    async fn load_reminders_async() -> Vec<Reminder> {
+        get_repository_service()
+            .reminders()
+            .await
+            .unwrap_or_else(|e| {
+                tracing::warn!("Failed to compute reminders: {}", e);
+                Vec::new()
+            })
+    }
+
     /// Async function to lock the database
     /// Handle potential session timeout errors
     fn handle_potential_session_timeout(
@@ -778,6 +1176,76 @@ impl MainView {
         }
     }
 
+    /// Async function to auto-type a credential into the previously focused window
+    async fn auto_type_async(
+        credential_id: String,
+        config: AutoTypeConfig,
+    ) -> Result<String, String> {
+        let credential = match get_repository_service()
+            .get_credential(credential_id.clone())
+            .await
+        {
+            Ok(Some(credential)) => credential,
+            Ok(None) => return Err(format!("Credential {} not found", credential_id)),
+            Err(e) => return Err(format!("Failed to load credential: {}", e)),
+        };
+
+        let title = credential.title.clone();
+        match AutoTypeService::new()
+            .with_config(&config)
+            .perform(credential)
+            .await
+        {
+            Ok(()) => {
+                tracing::info!("Auto-typed credential '{}'", title);
+                Ok(format!("Auto-typed '{}'", title))
+            }
+            Err(e) => {
+                tracing::error!("Auto-type failed for '{}': {}", title, e);
+                Err(e.to_string())
+            }
+        }
+    }
+
+    /// Describe what auto-typing a credential would do, without typing
+    async fn preview_auto_type_async(
+        credential_id: String,
+        config: AutoTypeConfig,
+    ) -> Result<String, String> {
+        let credential = match get_repository_service()
+            .get_credential(credential_id.clone())
+            .await
+        {
+            Ok(Some(credential)) => credential,
+            Ok(None) => return Err(format!("Credential {} not found", credential_id)),
+            Err(e) => return Err(format!("Failed to load credential: {}", e)),
+        };
+
+        AutoTypeService::new()
+            .with_config(&config)
+            .preview(&credential)
+            .map(|preview| format!("Auto-type preview: {}", preview.summary()))
+            .map_err(|e| e.to_string())
+    }
+
+    /// Reopen the vault as another device saved it
+    async fn reload_from_disk_async() -> Result<String, String> {
+        get_repository_service()
+            .reload_from_disk()
+            .await
+            .map(|()| "Reloaded the vault from disk".to_string())
+            .map_err(|e| e.to_string())
+    }
+
+    /// Merge the copy another device saved into the vault
+    async fn merge_from_disk_async() -> Result<String, String> {
+        get_repository_service()
+            .merge_from_disk()
+            .await
+            .map(|count| format!("Merged {} credential(s) from the other copy", count))
+            .map_err(|e| e.to_string())
+    }
+
     /// Async function to close the repository
     async fn close_repository_async() -> Result<String, String> {
         let repository_service = get_repository_service();
@@ -22,5 +22,5 @@ pub mod wizard;
 pub use add_credential::{AddCredentialMessage, AddCredentialView};
 pub use edit_credential::{EditCredentialMessage, EditCredentialView};
 pub use open_repository::{OpenRepositoryMessage, OpenRepositoryView};
-pub use settings::{SettingsMessage, SettingsView};
+pub use settings::{SettingsMessage, SettingsTab, SettingsView};
 pub use wizard::{RepositoryWizard, WizardMessage};
@@ -9,9 +9,10 @@ use iced::{Alignment, Element, Length, Task};
 use std::path::PathBuf;
 use tracing::{debug, error, info, warn};
 use uuid::Uuid;
+use ziplock_shared::core::{CoreError, FileError, ProtectorKind};
 
 use crate::config::ConfigManager;
-use crate::services::get_repository_service;
+use crate::services::{get_repository_service, RepositoryService};
 use crate::ui::{
     components::button as btn,
     theme::{self, utils},
@@ -32,6 +33,10 @@ pub enum OpenRepositoryMessage {
     TogglePassphraseVisibility,
     /// Attempt to open the repository
     OpenRepository,
+    /// Unlock with a registered key protector instead of the passphrase
+    OpenWithProtector(ProtectorKind),
+    /// Key protector unlock finished; on failure the passphrase is still asked
+    ProtectorUnlockComplete(Result<String, String>),
     /// Cancel and return to previous view
     Cancel,
     /// Try again after error (preserves repository selection)
@@ -72,6 +77,12 @@ pub struct OpenRepositoryView {
     session_id: Option<String>,
     /// Whether this repository was auto-selected from recent history
     auto_selected: bool,
+    /// Whether a security key is registered for the selected repository
+    has_security_key: bool,
+    /// Whether the selected repository can be unlocked with the OS login
+    has_os_keychain: bool,
+    /// Why the last security key or OS login unlock failed
+    unlock_error: Option<String>,
 }
 
 impl Default for OpenRepositoryView {
@@ -91,12 +102,15 @@ impl OpenRepositoryView {
             can_open: false,
             session_id: None,
             auto_selected: false,
+            has_security_key: false,
+            has_os_keychain: false,
+            unlock_error: None,
         }
     }
 
     /// Create a new open repository view with a pre-selected repository file
     pub fn with_repository(repository_path: PathBuf) -> Self {
-        Self {
+        let mut view = Self {
             state: OpenState::Input,
             selected_file: Some(repository_path),
             passphrase: String::new(),
@@ -104,7 +118,12 @@ impl OpenRepositoryView {
             can_open: false,
             session_id: None,
             auto_selected: true,
-        }
+            has_security_key: false,
+            has_os_keychain: false,
+            unlock_error: None,
+        };
+        view.update_key_protectors();
+        view
     }
 
     /// Update the view with a message
@@ -121,6 +140,7 @@ impl OpenRepositoryView {
             OpenRepositoryMessage::SelectSpecificFile(path) => {
                 debug!("Directly selecting repository file: {:?}", path);
                 self.selected_file = Some(path);
+                self.update_key_protectors();
                 self.state = OpenState::Input;
                 Task::none()
             }
@@ -128,7 +148,9 @@ impl OpenRepositoryView {
             OpenRepositoryMessage::FileSelected(file_path) => {
                 if let Some(path) = file_path {
                     info!("Repository file selected: {:?}", path);
+                    self.unlock_error = None;
                     self.selected_file = Some(path);
+                    self.update_key_protectors();
                 } else {
                     debug!("File selection cancelled");
                 }
@@ -164,6 +186,30 @@ impl OpenRepositoryView {
                 }
             }
 
+            OpenRepositoryMessage::OpenWithProtector(kind) => {
+                let Some(file_path) = self.selected_file.clone() else {
+                    return Task::none();
+                };
+                info!("Attempting to open repository with {}", kind.name());
+                self.state = OpenState::Opening;
+                self.unlock_error = None;
+                Task::perform(
+                    Self::open_with_protector_async(file_path, kind),
+                    OpenRepositoryMessage::ProtectorUnlockComplete,
+                )
+            }
+
+            OpenRepositoryMessage::ProtectorUnlockComplete(Err(error)) => {
+                warn!("Key protector unlock failed: {}", error);
+                self.state = OpenState::Input;
+                self.unlock_error = Some(error);
+                Task::none()
+            }
+
+            OpenRepositoryMessage::ProtectorUnlockComplete(Ok(session_id)) => {
+                self.update(OpenRepositoryMessage::OpenComplete(Ok(session_id)))
+            }
+
             OpenRepositoryMessage::Cancel => {
                 debug!("Open repository cancelled");
                 self.state = OpenState::Cancelled;
@@ -344,7 +390,7 @@ impl OpenRepositoryView {
             OpenRepositoryMessage::TogglePassphraseVisibility,
         );
 
-        column![
+        let mut section = column![
             text("Master Passphrase")
                 .size(crate::ui::theme::utils::typography::medium_text_size())
                 .align_x(iced::alignment::Horizontal::Center),
@@ -356,8 +402,42 @@ impl OpenRepositoryView {
             ]
             .align_y(Alignment::Center),
         ]
-        .width(Length::Fill)
-        .into()
+        .width(Length::Fill);
+
+        let mut unlock_buttons = row![].spacing(10);
+        if self.has_os_keychain {
+            unlock_buttons = unlock_buttons.push(
+                button(text("Unlock with OS Login"))
+                    .on_press(OpenRepositoryMessage::OpenWithProtector(
+                        ProtectorKind::OsKeychain,
+                    ))
+                    .padding(utils::button_padding())
+                    .style(theme::button_styles::secondary()),
+            );
+        }
+        if self.has_security_key {
+            unlock_buttons = unlock_buttons.push(
+                button(text("Unlock with Security Key"))
+                    .on_press(OpenRepositoryMessage::OpenWithProtector(
+                        ProtectorKind::Fido2HmacSecret,
+                    ))
+                    .padding(utils::button_padding())
+                    .style(theme::button_styles::secondary()),
+            );
+        }
+        if self.has_os_keychain || self.has_security_key {
+            section = section
+                .push(Space::with_height(Length::Fixed(12.0)))
+                .push(unlock_buttons);
+        }
+        if let Some(error) = &self.unlock_error {
+            section = section.push(Space::with_height(Length::Fixed(8.0))).push(
+                text(format!("{}. Enter your passphrase instead.", error))
+                    .size(crate::ui::theme::utils::typography::small_text_size()),
+            );
+        }
+
+        section.into()
     }
 
     /// Render navigation buttons
@@ -485,6 +565,27 @@ impl OpenRepositoryView {
         self.can_open = false;
         self.session_id = None;
         self.auto_selected = false;
+        self.has_security_key = false;
+        self.has_os_keychain = false;
+        self.unlock_error = None;
+    }
+
+    /// Look up the ways the selected repository can be unlocked without the
+    /// passphrase
+    ///
+    /// OS login is only offered while it is enabled in the security settings.
+    fn update_key_protectors(&mut self) {
+        let Some(path) = &self.selected_file else {
+            self.has_security_key = false;
+            self.has_os_keychain = false;
+            return;
+        };
+        let path = path.to_string_lossy();
+        self.has_security_key =
+            RepositoryService::key_protector_registered(&path, ProtectorKind::Fido2HmacSecret);
+        self.has_os_keychain = ConfigManager::new()
+            .is_ok_and(|config_manager| config_manager.config().security.os_keychain_unlock)
+            && RepositoryService::key_protector_registered(&path, ProtectorKind::OsKeychain);
     }
 
     /// Check if the opening process is complete
@@ -530,20 +631,58 @@ impl OpenRepositoryView {
         }
     }
 
+    /// Async function to open a repository with a key protector
+    async fn open_with_protector_async(
+        archive_path: PathBuf,
+        kind: ProtectorKind,
+    ) -> Result<String, String> {
+        get_repository_service()
+            .open_with_key_protector(archive_path.to_string_lossy().to_string(), kind)
+            .await
+            .map(|()| Uuid::new_v4().to_string())
+            .map_err(|e| e.to_string())
+    }
+
     /// Async function to open a repository
     async fn open_repository_async(
         archive_path: PathBuf,
         master_password: String,
     ) -> Result<String, String> {
         info!("Opening repository: {}", archive_path.display());
+        let path = archive_path.to_string_lossy().to_string();
+
+        // Failed attempts are throttled through the config, not the archive
+        let mut config_manager = ConfigManager::new()
+            .and_then(|mut config_manager| config_manager.load().map(|()| config_manager))
+            .map_err(|e| warn!("Unlock attempts are not tracked: {}", e))
+            .ok();
+        if let Some(config_manager) = &config_manager {
+            config_manager
+                .check_unlock(&path)
+                .map_err(|e| e.to_string())?;
+        }
 
         // Use the new unified repository service
         let repository_service = get_repository_service();
+        let result = repository_service
+            .open_repository(path.clone(), master_password)
+            .await;
+
+        let wrong_password = result.as_ref().err().is_some_and(|e| {
+            matches!(
+                e.downcast_ref::<CoreError>(),
+                Some(CoreError::FileOperation(FileError::InvalidPassword))
+            )
+        });
+        if let Some(config_manager) = config_manager.as_mut() {
+            if result.is_ok() || wrong_password {
+                if let Err(e) = config_manager.record_unlock_attempt(&path, result.is_ok()) {
+                    warn!("{}", e);
+                }
+            }
+        }
 
-        match repository_service
-            .open_repository(archive_path.to_string_lossy().to_string(), master_password)
-            .await
-        {
+        match result {
             Ok(()) => {
                 info!("Repository opened successfully via repository service");
 
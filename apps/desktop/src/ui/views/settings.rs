@@ -1,18 +1,54 @@
 use iced::{
-    widget::{button, checkbox, column, container, row, scrollable, text, text_input, Space},
+    widget::{
+        button, checkbox, column, container, progress_bar, row, scrollable, text, text_input, Space,
+    },
     Alignment, Element, Length, Task,
 };
 
 use std::path::PathBuf;
 use tracing::info;
 
+use crate::services::SettingsSection;
 use crate::ui::{
     components::button as btn,
     theme::{self, utils},
 };
 use ziplock_shared::config::{
-    AppBehaviorConfig, AppConfig, RepositoryManagementConfig, SecurityConfig, UiConfig,
+    AppBehaviorConfig, AppConfig, AutoTypeConfig, NotificationConfig, OffsiteBackupStatus,
+    RepositoryManagementConfig, SecurityConfig, UiConfig,
 };
+use ziplock_shared::core::PasswordChangeStage;
+use ziplock_shared::utils::{ApiScope, ApiSession};
+use ziplock_shared::{PasswordAnalyzer, PasswordStrength};
+
+/// Lifetimes offered for new API tokens, in minutes
+const API_TOKEN_TTL_OPTIONS: [(u32, &str); 3] = [(15, "15 min"), (60, "1 hour"), (480, "8 hours")];
+
+/// Progress text for a master password change
+fn password_change_stage_label(stage: PasswordChangeStage) -> &'static str {
+    match stage {
+        PasswordChangeStage::Encrypting => "Encrypting with the new password...",
+        PasswordChangeStage::Writing => "Writing the new archive...",
+        PasswordChangeStage::Verifying => "Checking the new archive...",
+        PasswordChangeStage::Replacing => "Replacing the old archive...",
+        PasswordChangeStage::Complete => "Password changed",
+    }
+}
+
+/// Where SSH clients find the agent
+fn ssh_agent_hint() -> String {
+    #[cfg(windows)]
+    let address = ziplock_shared::utils::ssh_agent::SSH_AGENT_PIPE_NAME.to_string();
+    #[cfg(not(windows))]
+    let address = ziplock_shared::utils::ssh_agent_socket_path()
+        .display()
+        .to_string();
+    format!(
+        "While unlocked, point SSH_AUTH_SOCK at {}. Keys tagged \"{}\" ask before each use.",
+        address,
+        ziplock_shared::utils::ssh_agent::CONFIRM_TAG
+    )
+}
 
 #[derive(Debug, Clone)]
 pub enum SettingsMessage {
@@ -40,11 +76,23 @@ pub enum SettingsMessage {
     MinimizeToTrayToggled(bool),
     StartMinimizedToggled(bool),
     AutoCheckUpdatesToggled(bool),
+    CheckBreachesToggled(bool),
+    OsKeychainUnlockToggled(bool),
+    SshAgentToggled(bool),
+    ActivityLogToggled(bool),
+    AutoTypeCadenceToggled(bool),
+    AutoTypeFocusCheckToggled(bool),
+    AutoTypeDryRunToggled(bool),
+    NotifyAutoLockToggled(bool),
+    NotifyBackupFailedToggled(bool),
+    NotifyBreachDetectedToggled(bool),
+    NotifyDraftsCreatedToggled(bool),
 
     // Repository Settings
     DefaultDirectoryChanged(String),
     BrowseDefaultDirectory,
     AutoDetectToggled(bool),
+    RunOffsiteBackup,
 
     // Security Settings
     MinPasswordLengthChanged(String),
@@ -61,6 +109,24 @@ pub enum SettingsMessage {
     MinUniqueCharsIncrement,
     MinUniqueCharsDecrement,
 
+    // Scripting API tokens
+    ApiTokenLabelChanged(String),
+    ApiTokenTtlSelected(u32),
+    ApiTokenRevealToggled(bool),
+    GenerateApiToken,
+    CopyApiToken,
+    RevokeApiToken(String),
+
+    // Master password change
+    CurrentMasterPasswordChanged(String),
+    NewMasterPasswordChanged(String),
+    ConfirmMasterPasswordChanged(String),
+    ChangeMasterPassword,
+
+    // Security keys
+    SecurityKeyLabelChanged(String),
+    RegisterSecurityKey,
+
     // Actions
     Save,
     Reset,
@@ -86,7 +152,7 @@ impl SettingsTab {
         ]
     }
 
-    fn label(&self) -> &'static str {
+    pub fn label(&self) -> &'static str {
         match self {
             Self::Interface => "Interface",
             Self::Application => "Application",
@@ -96,6 +162,17 @@ impl SettingsTab {
     }
 }
 
+impl From<SettingsSection> for SettingsTab {
+    fn from(section: SettingsSection) -> Self {
+        match section {
+            SettingsSection::Interface => Self::Interface,
+            SettingsSection::Application => Self::Application,
+            SettingsSection::Repository => Self::Repository,
+            SettingsSection::Security => Self::Security,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct SettingsView {
     // Current tab
@@ -117,10 +194,17 @@ pub struct SettingsView {
     minimize_to_tray: bool,
     start_minimized: bool,
     auto_check_updates: bool,
+    check_breaches: bool,
+    os_keychain_unlock: bool,
+    ssh_agent: bool,
+    activity_log: bool,
+    auto_type: AutoTypeConfig,
+    notifications: NotificationConfig,
 
     // Repository Settings
     default_directory: String,
     auto_detect: bool,
+    offsite_backup_running: bool,
 
     // Security Settings
     min_password_length: String,
@@ -131,6 +215,23 @@ pub struct SettingsView {
     max_password_length: String,
     min_unique_chars: String,
 
+    // Scripting API tokens; these are session state, not saved config
+    api_available: bool,
+    api_token_label: String,
+    api_token_ttl_minutes: u32,
+    api_token_reveal: bool,
+    issued_api_token: Option<String>,
+    api_sessions: Vec<ApiSession>,
+
+    // Master password change; needs an unlocked repository
+    repository_unlocked: bool,
+    current_master_password: String,
+    new_master_password: String,
+    confirm_master_password: String,
+    password_change_stage: Option<PasswordChangeStage>,
+    security_key_label: String,
+    registering_security_key: bool,
+
     // Validation and state
     validation_errors: Vec<String>,
     validation_warnings: Vec<String>,
@@ -168,6 +269,12 @@ impl SettingsView {
             minimize_to_tray: config.ui.minimize_to_tray,
             start_minimized: config.ui.start_minimized,
             auto_check_updates: config.behavior.auto_check_updates,
+            check_breaches: config.security.check_breaches,
+            os_keychain_unlock: config.security.os_keychain_unlock,
+            ssh_agent: config.security.ssh_agent,
+            activity_log: config.security.activity_log,
+            auto_type: config.security.auto_type,
+            notifications: config.notifications.clone(),
 
             default_directory: config
                 .repository_settings
@@ -176,6 +283,7 @@ impl SettingsView {
                 .map(|p| p.to_string_lossy().to_string())
                 .unwrap_or_default(),
             auto_detect: config.repository_settings.auto_detect,
+            offsite_backup_running: false,
 
             // Default security settings
             min_password_length: "12".to_string(),
@@ -186,6 +294,21 @@ impl SettingsView {
             max_password_length: "0".to_string(),
             min_unique_chars: "8".to_string(),
 
+            api_available: false,
+            api_token_label: String::new(),
+            api_token_ttl_minutes: API_TOKEN_TTL_OPTIONS[0].0,
+            api_token_reveal: false,
+            issued_api_token: None,
+            api_sessions: Vec::new(),
+
+            repository_unlocked: false,
+            current_master_password: String::new(),
+            new_master_password: String::new(),
+            confirm_master_password: String::new(),
+            security_key_label: String::new(),
+            registering_security_key: false,
+            password_change_stage: None,
+
             // Store original values for change detection
             original_backup_count: config.behavior.backup_count.to_string(),
             original_min_password_length: "12".to_string(),
@@ -392,6 +515,61 @@ impl SettingsView {
                 self.validate();
                 Task::none()
             }
+            SettingsMessage::CheckBreachesToggled(value) => {
+                self.check_breaches = value;
+                self.check_for_changes();
+                Task::none()
+            }
+            SettingsMessage::OsKeychainUnlockToggled(value) => {
+                self.os_keychain_unlock = value;
+                self.check_for_changes();
+                Task::none()
+            }
+            SettingsMessage::SshAgentToggled(value) => {
+                self.ssh_agent = value;
+                self.check_for_changes();
+                Task::none()
+            }
+            SettingsMessage::ActivityLogToggled(value) => {
+                self.activity_log = value;
+                self.check_for_changes();
+                Task::none()
+            }
+            SettingsMessage::AutoTypeCadenceToggled(value) => {
+                self.auto_type.randomize_cadence = value;
+                self.check_for_changes();
+                Task::none()
+            }
+            SettingsMessage::AutoTypeFocusCheckToggled(value) => {
+                self.auto_type.verify_focus = value;
+                self.check_for_changes();
+                Task::none()
+            }
+            SettingsMessage::AutoTypeDryRunToggled(value) => {
+                self.auto_type.dry_run = value;
+                self.check_for_changes();
+                Task::none()
+            }
+            SettingsMessage::NotifyAutoLockToggled(value) => {
+                self.notifications.auto_lock = value;
+                self.check_for_changes();
+                Task::none()
+            }
+            SettingsMessage::NotifyBackupFailedToggled(value) => {
+                self.notifications.backup_failed = value;
+                self.check_for_changes();
+                Task::none()
+            }
+            SettingsMessage::NotifyBreachDetectedToggled(value) => {
+                self.notifications.breach_detected = value;
+                self.check_for_changes();
+                Task::none()
+            }
+            SettingsMessage::NotifyDraftsCreatedToggled(value) => {
+                self.notifications.drafts_created = value;
+                self.check_for_changes();
+                Task::none()
+            }
 
             // Repository Settings
             SettingsMessage::DefaultDirectoryChanged(value) => {
@@ -404,6 +582,10 @@ impl SettingsView {
                 // TODO: Open file dialog
                 Task::none()
             }
+            SettingsMessage::RunOffsiteBackup => {
+                // Handled by the application, which owns the upload
+                Task::none()
+            }
             SettingsMessage::AutoDetectToggled(value) => {
                 self.auto_detect = value;
                 self.check_for_changes();
@@ -536,6 +718,45 @@ impl SettingsView {
                 Task::none()
             }
 
+            SettingsMessage::ApiTokenLabelChanged(value) => {
+                self.api_token_label = value;
+                Task::none()
+            }
+            SettingsMessage::ApiTokenTtlSelected(minutes) => {
+                self.api_token_ttl_minutes = minutes;
+                Task::none()
+            }
+            SettingsMessage::ApiTokenRevealToggled(value) => {
+                self.api_token_reveal = value;
+                Task::none()
+            }
+            // Token issuing, copying and revoking are handled by the app
+            SettingsMessage::GenerateApiToken
+            | SettingsMessage::CopyApiToken
+            | SettingsMessage::RevokeApiToken(_) => Task::none(),
+
+            SettingsMessage::CurrentMasterPasswordChanged(value) => {
+                self.current_master_password = value;
+                Task::none()
+            }
+            SettingsMessage::NewMasterPasswordChanged(value) => {
+                self.new_master_password = value;
+                Task::none()
+            }
+            SettingsMessage::ConfirmMasterPasswordChanged(value) => {
+                self.confirm_master_password = value;
+                Task::none()
+            }
+            // Re-encrypting the repository is handled by the app
+            SettingsMessage::ChangeMasterPassword => Task::none(),
+
+            SettingsMessage::SecurityKeyLabelChanged(value) => {
+                self.security_key_label = value;
+                Task::none()
+            }
+            // Talking to the security key is handled by the app
+            SettingsMessage::RegisterSecurityKey => Task::none(),
+
             // Actions
             SettingsMessage::Save => {
                 if self.validation_errors.is_empty() {
@@ -718,6 +939,78 @@ impl SettingsView {
                 self.show_password_strength,
                 SettingsMessage::ShowPasswordStrengthToggled
             ),
+            self.create_checkbox_row(
+                "Check passwords against known data breaches after unlocking",
+                self.check_breaches,
+                SettingsMessage::CheckBreachesToggled
+            ),
+            text("Only the first 5 characters of each password's SHA-1 hash are sent to Have I Been Pwned.")
+                .size(crate::ui::theme::utils::typography::small_text_size()),
+            self.create_checkbox_row(
+                "Offer to unlock with your OS login",
+                self.os_keychain_unlock,
+                SettingsMessage::OsKeychainUnlockToggled
+            ),
+            text("A key to the repository is kept in the system keychain after the next unlock. Anyone who can use your logged-in session can then open it.")
+                .size(crate::ui::theme::utils::typography::small_text_size()),
+            self.create_checkbox_row(
+                "Act as an SSH agent for stored SSH keys",
+                self.ssh_agent,
+                SettingsMessage::SshAgentToggled
+            ),
+            text(ssh_agent_hint())
+                .size(crate::ui::theme::utils::typography::small_text_size()),
+            self.create_checkbox_row(
+                "Keep an activity log in the repository",
+                self.activity_log,
+                SettingsMessage::ActivityLogToggled
+            ),
+            text("Opens, saves and credential changes are recorded with this computer's name. Entries already in the log are kept when this is off.")
+                .size(crate::ui::theme::utils::typography::small_text_size()),
+            self.create_checkbox_row(
+                "Auto-type at a varying, human-like pace",
+                self.auto_type.randomize_cadence,
+                SettingsMessage::AutoTypeCadenceToggled
+            ),
+            self.create_checkbox_row(
+                "Stop auto-type if another window takes focus",
+                self.auto_type.verify_focus,
+                SettingsMessage::AutoTypeFocusCheckToggled
+            ),
+            self.create_checkbox_row(
+                "Preview auto-type without typing",
+                self.auto_type.dry_run,
+                SettingsMessage::AutoTypeDryRunToggled
+            ),
+            text("Focus can't be checked on most Wayland desktops. The preview masks every typed character.")
+                .size(crate::ui::theme::utils::typography::small_text_size()),
+        ]
+        .spacing(10);
+
+        let notification_settings = column![
+            text("Desktop Notifications")
+                .size(crate::ui::theme::utils::typography::large_text_size()),
+            Space::with_height(Length::Fixed(10.0)),
+            self.create_checkbox_row(
+                "When the repository is locked after inactivity",
+                self.notifications.auto_lock,
+                SettingsMessage::NotifyAutoLockToggled
+            ),
+            self.create_checkbox_row(
+                "When an off-site backup fails",
+                self.notifications.backup_failed,
+                SettingsMessage::NotifyBackupFailedToggled
+            ),
+            self.create_checkbox_row(
+                "When a stored password is found in a data breach",
+                self.notifications.breach_detected,
+                SettingsMessage::NotifyBreachDetectedToggled
+            ),
+            self.create_checkbox_row(
+                "When a recurring credential adds a draft to the inbox",
+                self.notifications.drafts_created,
+                SettingsMessage::NotifyDraftsCreatedToggled
+            ),
         ]
         .spacing(10);
 
@@ -745,6 +1038,8 @@ impl SettingsView {
         column![
             security_settings,
             Space::with_height(Length::Fixed(30.0)),
+            notification_settings,
+            Space::with_height(Length::Fixed(30.0)),
             system_settings,
         ]
         .spacing(0)
@@ -796,11 +1091,82 @@ impl SettingsView {
             repository_settings,
             Space::with_height(Length::Fixed(30.0)),
             backup_settings,
+            Space::with_height(Length::Fixed(30.0)),
+            self.view_offsite_backup_status(),
         ]
         .spacing(0)
         .into()
     }
 
+    fn view_offsite_backup_status(&self) -> Element<'_, SettingsMessage> {
+        let offsite = &self.original_config.offsite_backup;
+        let small = crate::ui::theme::utils::typography::small_text_size();
+        let format_time = |time: chrono::DateTime<chrono::Utc>| {
+            time.with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M")
+                .to_string()
+        };
+
+        let mut status = column![
+            text("Off-site Backup").size(crate::ui::theme::utils::typography::large_text_size()),
+            Space::with_height(Length::Fixed(10.0)),
+        ]
+        .spacing(10);
+
+        let Some(destination) = &offsite.destination else {
+            return status
+                .push(
+                    text("No destination configured. Add an SFTP or WebDAV destination to offsite_backup in the config file.")
+                        .size(small),
+                )
+                .into();
+        };
+
+        status = status.push(text(format!(
+            "Destination: {}",
+            destination.display_location()
+        )));
+        status = status.push(text(match (offsite.enabled, offsite.next_due()) {
+            (true, Some(due)) if due > chrono::Utc::now() => format!(
+                "Uploads every {} days, next on {}",
+                offsite.interval_days,
+                format_time(due)
+            ),
+            (true, _) => "Next upload: due now".to_string(),
+            (false, _) => "Scheduled uploads are disabled".to_string(),
+        }));
+        status = status.push(text(
+            match (offsite.status.last_success, &offsite.status.last_file) {
+                (Some(at), Some(file)) => format!("Last backup: {} ({})", format_time(at), file),
+                (Some(at), None) => format!("Last backup: {}", format_time(at)),
+                _ => "Last backup: never".to_string(),
+            },
+        ));
+        if let (Some(error), Some(at)) = (&offsite.status.last_error, offsite.status.last_attempt) {
+            status = status.push(
+                text(format!(
+                    "Last attempt failed on {}: {}",
+                    format_time(at),
+                    error
+                ))
+                .size(small)
+                .color(theme::ERROR_RED),
+            );
+        }
+
+        let label = if self.offsite_backup_running {
+            "Uploading..."
+        } else {
+            "Back up now"
+        };
+        status
+            .push(btn::secondary_button(
+                label,
+                (!self.offsite_backup_running).then_some(SettingsMessage::RunOffsiteBackup),
+            ))
+            .into()
+    }
+
     fn view_security_settings(&self) -> Element<'_, SettingsMessage> {
         let password_settings = column![
             text("Master Password Requirements")
@@ -853,7 +1219,282 @@ impl SettingsView {
         ]
         .spacing(10);
 
-        column![password_settings].spacing(0).into()
+        column![
+            password_settings,
+            Space::with_height(Length::Fixed(30.0)),
+            self.view_change_master_password(),
+            Space::with_height(Length::Fixed(30.0)),
+            self.view_security_keys(),
+            Space::with_height(Length::Fixed(30.0)),
+            self.view_api_tokens(),
+        ]
+        .spacing(0)
+        .into()
+    }
+
+    fn view_change_master_password(&self) -> Element<'_, SettingsMessage> {
+        let small = crate::ui::theme::utils::typography::small_text_size();
+        let section = column![
+            text("Change Master Password")
+                .size(crate::ui::theme::utils::typography::large_text_size()),
+            Space::with_height(Length::Fixed(10.0)),
+            text("The repository is re-encrypted with the new password. The old password keeps working until the new archive has been written and checked.")
+                .size(small),
+        ]
+        .spacing(10);
+
+        if !self.repository_unlocked {
+            return section
+                .push(text("Unlock a repository to change its password.").size(small))
+                .into();
+        }
+
+        if let Some(stage) = self.password_change_stage {
+            return section
+                .push(progress_bar(
+                    0.0..=PasswordChangeStage::COUNT as f32,
+                    stage.step() as f32,
+                ))
+                .push(text(password_change_stage_label(stage)).size(small))
+                .into();
+        }
+
+        let password_row =
+            |label: &'static str, value: &str, on_input: fn(String) -> SettingsMessage| {
+                row![
+                    container(
+                        text(label).size(crate::ui::theme::utils::typography::normal_text_size())
+                    )
+                    .width(Length::Fixed(200.0)),
+                    text_input("", value)
+                        .on_input(on_input)
+                        .secure(true)
+                        .padding(utils::text_input_padding())
+                        .size(crate::ui::theme::utils::typography::text_input_size())
+                        .style(theme::text_input_styles::standard())
+                        .width(Length::Fixed(300.0)),
+                ]
+                .align_y(Alignment::Center)
+                .spacing(10)
+            };
+
+        let mut section = section
+            .push(password_row(
+                "Current password:",
+                &self.current_master_password,
+                SettingsMessage::CurrentMasterPasswordChanged,
+            ))
+            .push(password_row(
+                "New password:",
+                &self.new_master_password,
+                SettingsMessage::NewMasterPasswordChanged,
+            ))
+            .push(password_row(
+                "Confirm new password:",
+                &self.confirm_master_password,
+                SettingsMessage::ConfirmMasterPasswordChanged,
+            ));
+
+        if !self.new_master_password.is_empty() {
+            let analysis = PasswordAnalyzer::analyze(&self.new_master_password);
+            section = section.push(text(format!("Strength: {:?}", analysis.strength)).size(small));
+        }
+        if !self.confirm_master_password.is_empty()
+            && self.new_master_password != self.confirm_master_password
+        {
+            section = section.push(
+                text("Passwords do not match")
+                    .size(small)
+                    .color(theme::ERROR_RED),
+            );
+        }
+
+        section
+            .push(btn::secondary_button(
+                "Change password",
+                self.master_password_change_request()
+                    .map(|_| SettingsMessage::ChangeMasterPassword),
+            ))
+            .into()
+    }
+
+    fn view_security_keys(&self) -> Element<'_, SettingsMessage> {
+        let small = crate::ui::theme::utils::typography::small_text_size();
+        let section = column![
+            text("Security Keys").size(crate::ui::theme::utils::typography::large_text_size()),
+            Space::with_height(Length::Fixed(10.0)),
+            text("Unlock this repository by touching a FIDO2 security key with hmac-secret support. The master password keeps working, and registered keys are removed when it changes.")
+                .size(small),
+        ]
+        .spacing(10);
+
+        if !self.repository_unlocked {
+            return section
+                .push(text("Unlock a repository to register a security key.").size(small))
+                .into();
+        }
+        if self.registering_security_key {
+            return section
+                .push(text("Touch your security key when it blinks...").size(small))
+                .into();
+        }
+
+        let label = self.security_key_label.trim();
+        section
+            .push(
+                row![
+                    container(
+                        text("Key name:")
+                            .size(crate::ui::theme::utils::typography::normal_text_size())
+                    )
+                    .width(Length::Fixed(200.0)),
+                    text_input("e.g. YubiKey on keyring", &self.security_key_label)
+                        .on_input(SettingsMessage::SecurityKeyLabelChanged)
+                        .padding(utils::text_input_padding())
+                        .size(crate::ui::theme::utils::typography::text_input_size())
+                        .style(theme::text_input_styles::standard())
+                        .width(Length::Fixed(300.0)),
+                ]
+                .align_y(Alignment::Center)
+                .spacing(10),
+            )
+            .push(btn::secondary_button(
+                "Register security key",
+                (!label.is_empty()).then_some(SettingsMessage::RegisterSecurityKey),
+            ))
+            .into()
+    }
+
+    fn view_api_tokens(&self) -> Element<'_, SettingsMessage> {
+        let small = crate::ui::theme::utils::typography::small_text_size();
+        let mut section = column![
+            text("Scripting API Tokens")
+                .size(crate::ui::theme::utils::typography::large_text_size()),
+            Space::with_height(Length::Fixed(10.0)),
+            text("Short-lived, read-only tokens for scripts using `ziplock --api`. All tokens are revoked when the repository locks.")
+                .size(small),
+        ]
+        .spacing(10);
+
+        if !self.api_available {
+            return section
+                .push(text("Unlock a repository to issue tokens.").size(small))
+                .into();
+        }
+
+        let ttl_buttons =
+            API_TOKEN_TTL_OPTIONS
+                .iter()
+                .fold(row![].spacing(5), |buttons, &(minutes, label)| {
+                    let message = Some(SettingsMessage::ApiTokenTtlSelected(minutes));
+                    buttons.push(if minutes == self.api_token_ttl_minutes {
+                        btn::small_button(label, message)
+                    } else {
+                        btn::small_secondary_button(label, message)
+                    })
+                });
+
+        section = section
+            .push(
+                row![
+                    container(
+                        text("Token name:")
+                            .size(crate::ui::theme::utils::typography::normal_text_size())
+                    )
+                    .width(Length::Fixed(200.0)),
+                    text_input("e.g. deploy script", &self.api_token_label)
+                        .on_input(SettingsMessage::ApiTokenLabelChanged)
+                        .padding(utils::text_input_padding())
+                        .size(crate::ui::theme::utils::typography::text_input_size())
+                        .style(theme::text_input_styles::standard())
+                        .width(Length::Fixed(300.0)),
+                ]
+                .align_y(Alignment::Center)
+                .spacing(10),
+            )
+            .push(
+                row![
+                    container(
+                        text("Expires after:")
+                            .size(crate::ui::theme::utils::typography::normal_text_size())
+                    )
+                    .width(Length::Fixed(200.0)),
+                    ttl_buttons,
+                ]
+                .align_y(Alignment::Center)
+                .spacing(10),
+            )
+            .push(self.create_checkbox_row(
+                "Allow reading passwords and other sensitive fields",
+                self.api_token_reveal,
+                SettingsMessage::ApiTokenRevealToggled,
+            ))
+            .push(btn::secondary_button(
+                "Generate token",
+                Some(SettingsMessage::GenerateApiToken),
+            ));
+
+        if let Some(token) = &self.issued_api_token {
+            section = section.push(
+                column![
+                    text("Copy this token now; it will not be shown again.")
+                        .size(small)
+                        .color(theme::ERROR_RED),
+                    row![
+                        text(token.as_str()).size(small),
+                        btn::small_button("Copy", Some(SettingsMessage::CopyApiToken)),
+                    ]
+                    .align_y(Alignment::Center)
+                    .spacing(10),
+                ]
+                .spacing(5),
+            );
+        }
+
+        if self.api_sessions.is_empty() {
+            return section.push(text("No active tokens.").size(small)).into();
+        }
+
+        let format_time = |time: chrono::DateTime<chrono::Utc>| {
+            time.with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M")
+                .to_string()
+        };
+        section = section.push(
+            text("Active tokens").size(crate::ui::theme::utils::typography::normal_text_size()),
+        );
+        for session in &self.api_sessions {
+            let scopes: Vec<&str> = session.scopes.iter().map(|s| s.name()).collect();
+            let last_used = session
+                .last_used_at
+                .map(|at| format!("last used {}", format_time(at)))
+                .unwrap_or_else(|| "never used".to_string());
+            section = section.push(
+                row![
+                    column![
+                        text(session.label.as_str()),
+                        text(format!(
+                            "{} · expires {} · {} ({} requests)",
+                            scopes.join(", "),
+                            format_time(session.expires_at),
+                            last_used,
+                            session.request_count
+                        ))
+                        .size(small),
+                    ]
+                    .spacing(2)
+                    .width(Length::Fill),
+                    btn::destructive_button(
+                        "Revoke",
+                        Some(SettingsMessage::RevokeApiToken(session.id.clone())),
+                    ),
+                ]
+                .align_y(Alignment::Center)
+                .spacing(10),
+            );
+        }
+
+        section.into()
     }
 
     // Helper methods for creating form elements
@@ -1237,8 +1878,30 @@ impl SettingsView {
             auto_updates_changed
         );
 
+        let check_breaches_changed =
+            self.check_breaches != self.original_config.security.check_breaches;
+        let os_keychain_changed =
+            self.os_keychain_unlock != self.original_config.security.os_keychain_unlock;
+        let ssh_agent_changed = self.ssh_agent != self.original_config.security.ssh_agent;
+        let activity_log_changed = self.activity_log != self.original_config.security.activity_log;
+        let auto_type_changed = self.auto_type != self.original_config.security.auto_type;
+        let notifications_changed = self.notifications != self.original_config.notifications;
+        info!(
+            "Check breaches: {} vs {} = {}, notifications changed = {}",
+            self.check_breaches,
+            self.original_config.security.check_breaches,
+            check_breaches_changed,
+            notifications_changed
+        );
+
         let app_changed = auto_lock_changed
             || clipboard_changed
+            || check_breaches_changed
+            || os_keychain_changed
+            || ssh_agent_changed
+            || activity_log_changed
+            || auto_type_changed
+            || notifications_changed
             || backup_enabled_changed
             || password_strength_changed
             || minimize_tray_changed
@@ -1350,6 +2013,12 @@ impl SettingsView {
         self.minimize_to_tray = config.ui.minimize_to_tray;
         self.start_minimized = config.ui.start_minimized;
         self.auto_check_updates = config.behavior.auto_check_updates;
+        self.check_breaches = config.security.check_breaches;
+        self.os_keychain_unlock = config.security.os_keychain_unlock;
+        self.ssh_agent = config.security.ssh_agent;
+        self.activity_log = config.security.activity_log;
+        self.auto_type = config.security.auto_type;
+        self.notifications = config.notifications.clone();
 
         // Reset repository settings
         self.default_directory = config
@@ -1406,6 +2075,15 @@ impl SettingsView {
                 clear_clipboard_on_lock: self.original_config.security.clear_clipboard_on_lock,
                 max_auth_attempts: self.original_config.security.max_auth_attempts,
                 lockout_duration: self.original_config.security.lockout_duration,
+                unlock_backoff_secs: self.original_config.security.unlock_backoff_secs,
+                check_breaches: self.check_breaches,
+                os_keychain_unlock: self.os_keychain_unlock,
+                ssh_agent: self.ssh_agent,
+                lock_windows: self.original_config.security.lock_windows.clone(),
+                session: self.original_config.security.session,
+                api_approval: self.original_config.security.api_approval.clone(),
+                auto_type: self.auto_type,
+                activity_log: self.activity_log,
             },
             behavior: AppBehaviorConfig {
                 auto_check_updates: self.auto_check_updates,
@@ -1429,10 +2107,113 @@ impl SettingsView {
                     .search_directories
                     .clone(),
             },
+            offsite_backup: self.original_config.offsite_backup.clone(),
+            notifications: self.notifications.clone(),
+            plugins: self.original_config.plugins.clone(),
+            automation_scripts: self.original_config.automation_scripts.clone(),
             repositories: self.original_config.repositories.clone(),
+            last_seen_version: self.original_config.last_seen_version.clone(),
+            unlock_attempts: self.original_config.unlock_attempts.clone(),
         }
     }
 
+    /// Show the result of an off-site backup that finished while settings are open
+    pub fn set_offsite_status(&mut self, status: OffsiteBackupStatus) {
+        self.original_config.offsite_backup.status = status;
+    }
+
+    /// Disable the "Back up now" button while an upload is running
+    pub fn set_offsite_backup_running(&mut self, running: bool) {
+        self.offsite_backup_running = running;
+    }
+
+    /// Let the view issue API tokens; only possible while a repository is unlocked
+    pub fn set_api_available(&mut self, available: bool) {
+        self.api_available = available;
+    }
+
+    /// Allow changing the master password; only possible while a repository is unlocked
+    pub fn set_repository_unlocked(&mut self, unlocked: bool) {
+        self.repository_unlocked = unlocked;
+    }
+
+    /// Current and new master password, once the form is complete
+    ///
+    /// The new password must be confirmed and rate at least "good".
+    pub fn master_password_change_request(&self) -> Option<(String, String)> {
+        let strength = PasswordAnalyzer::analyze(&self.new_master_password).strength;
+        let acceptable = matches!(
+            strength,
+            PasswordStrength::Good | PasswordStrength::Strong | PasswordStrength::VeryStrong
+        );
+        (self.password_change_stage.is_none()
+            && !self.current_master_password.is_empty()
+            && acceptable
+            && self.new_master_password == self.confirm_master_password
+            && self.new_master_password != self.current_master_password)
+            .then(|| {
+                (
+                    self.current_master_password.clone(),
+                    self.new_master_password.clone(),
+                )
+            })
+    }
+
+    /// Show the running password change, or `None` once it has finished
+    pub fn set_password_change_stage(&mut self, stage: Option<PasswordChangeStage>) {
+        self.password_change_stage = stage;
+    }
+
+    /// Clear the password form after a successful change
+    pub fn clear_master_password_form(&mut self) {
+        self.current_master_password.clear();
+        self.new_master_password.clear();
+        self.confirm_master_password.clear();
+    }
+
+    /// Name for the next security key, if one was entered
+    pub fn security_key_request(&self) -> Option<String> {
+        let label = self.security_key_label.trim();
+        (!self.registering_security_key && !label.is_empty()).then(|| label.to_string())
+    }
+
+    /// Show that a security key is being registered; clears the name once done
+    pub fn set_registering_security_key(&mut self, registering: bool) {
+        if self.registering_security_key && !registering {
+            self.security_key_label.clear();
+        }
+        self.registering_security_key = registering;
+    }
+
+    /// Label, scopes and lifetime for the next API token
+    pub fn api_token_request(&self) -> (String, Vec<ApiScope>, chrono::Duration) {
+        let mut scopes = vec![ApiScope::Read];
+        if self.api_token_reveal {
+            scopes.push(ApiScope::ReadSecrets);
+        }
+        (
+            self.api_token_label.clone(),
+            scopes,
+            chrono::Duration::minutes(self.api_token_ttl_minutes as i64),
+        )
+    }
+
+    /// Show a freshly issued token once
+    pub fn set_issued_api_token(&mut self, token: Option<String>) {
+        if token.is_some() {
+            self.api_token_label.clear();
+        }
+        self.issued_api_token = token;
+    }
+
+    pub fn issued_api_token(&self) -> Option<&str> {
+        self.issued_api_token.as_deref()
+    }
+
+    pub fn set_api_sessions(&mut self, sessions: Vec<ApiSession>) {
+        self.api_sessions = sessions;
+    }
+
     pub fn get_updated_config(&self) -> AppConfig {
         self.build_current_config()
     }
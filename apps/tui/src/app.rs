@@ -0,0 +1,598 @@
+//! TUI application state and key handling
+//!
+//! Everything here is independent of the terminal so it can be driven from
+//! tests: the event loop feeds key presses and clock ticks in, and `ui`
+//! renders whatever state results.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ziplock_shared::utils::TotpGenerator;
+use ziplock_shared::{
+    CredentialRecord, CredentialSearchEngine, FieldType, PasswordGenerator, PasswordOptions,
+    SearchQuery,
+};
+
+use crate::clipboard::ClipboardBackend;
+use crate::vault::Vault;
+
+/// Shortest and longest generated passwords
+const GENERATOR_LENGTH: (usize, usize) = (8, 128);
+
+/// How long status messages stay visible
+const STATUS_TTL: Duration = Duration::from_secs(4);
+
+/// Which screen is shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Screen {
+    Unlock,
+    List,
+    Detail,
+    Generator,
+}
+
+/// Timeouts taken from the app configuration; zero disables each one
+#[derive(Debug, Clone, Copy)]
+pub struct Timeouts {
+    pub auto_lock: Duration,
+    pub clipboard: Duration,
+}
+
+/// A message shown in the status bar
+#[derive(Debug, Clone)]
+pub struct Status {
+    pub text: String,
+    pub is_error: bool,
+    shown_at: Instant,
+}
+
+pub struct App {
+    pub archive_path: String,
+    pub screen: Screen,
+    pub password_input: String,
+    pub search: String,
+    pub searching: bool,
+    pub visible: Vec<CredentialRecord>,
+    pub selected: usize,
+    pub reveal: bool,
+    pub generated: String,
+    pub generator_options: PasswordOptions,
+    pub status: Option<Status>,
+    pub should_quit: bool,
+    credentials: Vec<CredentialRecord>,
+    vault: Option<Vault>,
+    clipboard: Box<dyn ClipboardBackend>,
+    timeouts: Timeouts,
+    last_activity: Instant,
+    clipboard_set_at: Option<Instant>,
+}
+
+impl App {
+    pub fn new(
+        archive_path: String,
+        clipboard: Box<dyn ClipboardBackend>,
+        timeouts: Timeouts,
+        now: Instant,
+    ) -> Self {
+        Self {
+            archive_path,
+            screen: Screen::Unlock,
+            password_input: String::new(),
+            search: String::new(),
+            searching: false,
+            visible: Vec::new(),
+            selected: 0,
+            reveal: false,
+            generated: String::new(),
+            generator_options: PasswordOptions {
+                length: 20,
+                ..PasswordOptions::default()
+            },
+            status: None,
+            should_quit: false,
+            credentials: Vec::new(),
+            vault: None,
+            clipboard,
+            timeouts,
+            last_activity: now,
+            clipboard_set_at: None,
+        }
+    }
+
+    /// The credential under the cursor
+    pub fn selected_credential(&self) -> Option<&CredentialRecord> {
+        self.visible.get(self.selected)
+    }
+
+    /// Number of credentials in the unlocked vault
+    pub fn total_credentials(&self) -> usize {
+        self.credentials.len()
+    }
+
+    /// Seconds until auto-lock, if enabled and unlocked
+    pub fn auto_lock_remaining(&self, now: Instant) -> Option<Duration> {
+        if self.vault.is_none() || self.timeouts.auto_lock.is_zero() {
+            return None;
+        }
+        Some(
+            self.timeouts
+                .auto_lock
+                .saturating_sub(now.duration_since(self.last_activity)),
+        )
+    }
+
+    /// Periodic housekeeping: auto-lock, clipboard clearing, status expiry
+    pub fn tick(&mut self, now: Instant) {
+        if let Some(set_at) = self.clipboard_set_at {
+            if !self.timeouts.clipboard.is_zero()
+                && now.duration_since(set_at) >= self.timeouts.clipboard
+            {
+                self.clipboard.clear();
+                self.clipboard_set_at = None;
+            }
+        }
+
+        if self.auto_lock_remaining(now) == Some(Duration::ZERO) {
+            self.lock(now);
+            self.set_status("Locked after inactivity", false, now);
+        }
+
+        if let Some(status) = &self.status {
+            if now.duration_since(status.shown_at) >= STATUS_TTL {
+                self.status = None;
+            }
+        }
+    }
+
+    /// Handle a key press
+    pub fn handle_key(&mut self, key: KeyEvent, now: Instant) {
+        self.last_activity = now;
+
+        if key.modifiers.contains(KeyModifiers::CONTROL) {
+            match key.code {
+                KeyCode::Char('c') => self.quit(),
+                KeyCode::Char('l') if self.vault.is_some() => {
+                    self.lock(now);
+                    self.set_status("Locked", false, now);
+                }
+                _ => {}
+            }
+            return;
+        }
+
+        match self.screen {
+            Screen::Unlock => self.handle_unlock_key(key, now),
+            Screen::List if self.searching => self.handle_search_key(key),
+            Screen::List => self.handle_list_key(key, now),
+            Screen::Detail => self.handle_detail_key(key, now),
+            Screen::Generator => self.handle_generator_key(key, now),
+        }
+    }
+
+    fn handle_unlock_key(&mut self, key: KeyEvent, now: Instant) {
+        match key.code {
+            KeyCode::Enter => self.unlock(now),
+            KeyCode::Backspace => {
+                self.password_input.pop();
+            }
+            KeyCode::Esc => self.quit(),
+            KeyCode::Char(c) => self.password_input.push(c),
+            _ => {}
+        }
+    }
+
+    fn handle_search_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Enter | KeyCode::Down => self.searching = false,
+            KeyCode::Esc => {
+                self.searching = false;
+                self.search.clear();
+                self.apply_filter();
+            }
+            KeyCode::Backspace => {
+                self.search.pop();
+                self.apply_filter();
+            }
+            KeyCode::Char(c) => {
+                self.search.push(c);
+                self.apply_filter();
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_list_key(&mut self, key: KeyEvent, now: Instant) {
+        match key.code {
+            KeyCode::Char('q') => self.quit(),
+            KeyCode::Char('/') => self.searching = true,
+            KeyCode::Esc if !self.search.is_empty() => {
+                self.search.clear();
+                self.apply_filter();
+            }
+            KeyCode::Down | KeyCode::Char('j') => self.move_selection(1),
+            KeyCode::Up | KeyCode::Char('k') => self.move_selection(-1),
+            KeyCode::PageDown => self.move_selection(10),
+            KeyCode::PageUp => self.move_selection(-10),
+            KeyCode::Home | KeyCode::Char('g') => self.selected = 0,
+            KeyCode::End | KeyCode::Char('G') => {
+                self.selected = self.visible.len().saturating_sub(1)
+            }
+            KeyCode::Enter | KeyCode::Right | KeyCode::Char('l') => {
+                if self.selected_credential().is_some() {
+                    self.reveal = false;
+                    self.screen = Screen::Detail;
+                }
+            }
+            KeyCode::Char('n') => self.open_generator(),
+            KeyCode::Char('L') => {
+                self.lock(now);
+                self.set_status("Locked", false, now);
+            }
+            code => self.handle_copy_key(code, now),
+        }
+    }
+
+    fn handle_detail_key(&mut self, key: KeyEvent, now: Instant) {
+        match key.code {
+            KeyCode::Esc | KeyCode::Left | KeyCode::Char('h') | KeyCode::Char('q') => {
+                self.reveal = false;
+                self.screen = Screen::List;
+            }
+            KeyCode::Char('r') => self.reveal = !self.reveal,
+            KeyCode::Char('L') => {
+                self.lock(now);
+                self.set_status("Locked", false, now);
+            }
+            code => self.handle_copy_key(code, now),
+        }
+    }
+
+    fn handle_generator_key(&mut self, key: KeyEvent, now: Instant) {
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => self.screen = Screen::List,
+            KeyCode::Char('r') | KeyCode::Char(' ') => self.regenerate(),
+            KeyCode::Char('+') | KeyCode::Right => {
+                self.generator_options.length =
+                    (self.generator_options.length + 1).min(GENERATOR_LENGTH.1);
+                self.regenerate();
+            }
+            KeyCode::Char('-') | KeyCode::Left => {
+                self.generator_options.length =
+                    (self.generator_options.length - 1).max(GENERATOR_LENGTH.0);
+                self.regenerate();
+            }
+            KeyCode::Char('s') => {
+                self.generator_options.include_symbols = !self.generator_options.include_symbols;
+                self.regenerate();
+            }
+            KeyCode::Char('a') => {
+                self.generator_options.exclude_ambiguous =
+                    !self.generator_options.exclude_ambiguous;
+                self.regenerate();
+            }
+            KeyCode::Enter | KeyCode::Char('c') => {
+                let password = self.generated.clone();
+                self.copy("Generated password", &password, now);
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_copy_key(&mut self, code: KeyCode, now: Instant) {
+        let Some(credential) = self.selected_credential() else {
+            return;
+        };
+        let (label, value) = match code {
+            KeyCode::Char('u') => ("Username", field_value(credential, FieldType::Username)),
+            KeyCode::Char('p') => ("Password", field_value(credential, FieldType::Password)),
+            KeyCode::Char('o') => ("URL", field_value(credential, FieldType::Url)),
+            KeyCode::Char('t') => match totp_code(credential) {
+                Some(Ok(code)) => ("TOTP code", Some(code)),
+                Some(Err(e)) => {
+                    self.set_status(format!("Invalid TOTP secret: {}", e), true, now);
+                    return;
+                }
+                None => ("TOTP code", None),
+            },
+            _ => return,
+        };
+
+        match value {
+            Some(value) => self.copy(label, &value, now),
+            None => self.set_status(format!("{} not set for this credential", label), true, now),
+        }
+    }
+
+    fn copy(&mut self, label: &str, value: &str, now: Instant) {
+        match self.clipboard.set_text(value) {
+            Ok(()) => {
+                self.clipboard_set_at = Some(now);
+                let message = if self.timeouts.clipboard.is_zero() {
+                    format!("{} copied", label)
+                } else {
+                    format!(
+                        "{} copied; clipboard clears in {}s",
+                        label,
+                        self.timeouts.clipboard.as_secs()
+                    )
+                };
+                self.set_status(message, false, now);
+            }
+            Err(e) => self.set_status(e, true, now),
+        }
+    }
+
+    fn unlock(&mut self, now: Instant) {
+        if self.password_input.is_empty() {
+            return;
+        }
+
+        let result = Vault::unlock(&self.archive_path, &self.password_input)
+            .and_then(|vault| Ok((vault.credentials()?, vault)));
+        self.password_input.clear();
+        match result {
+            Ok((credentials, vault)) => {
+                self.set_status(
+                    format!("Unlocked {} credentials", credentials.len()),
+                    false,
+                    now,
+                );
+                self.credentials = credentials;
+                self.vault = Some(vault);
+                self.screen = Screen::List;
+                self.apply_filter();
+            }
+            Err(e) => self.set_status(e.to_string(), true, now),
+        }
+    }
+
+    /// Forget all decrypted data and return to the unlock screen
+    pub fn lock(&mut self, _now: Instant) {
+        if let Some(vault) = self.vault.take() {
+            vault.lock();
+        }
+        if self.clipboard_set_at.take().is_some() {
+            self.clipboard.clear();
+        }
+        self.credentials.clear();
+        self.visible.clear();
+        self.search.clear();
+        self.searching = false;
+        self.selected = 0;
+        self.reveal = false;
+        self.generated.clear();
+        self.screen = Screen::Unlock;
+    }
+
+    fn quit(&mut self) {
+        self.lock(Instant::now());
+        self.should_quit = true;
+    }
+
+    fn open_generator(&mut self) {
+        self.screen = Screen::Generator;
+        self.regenerate();
+    }
+
+    fn regenerate(&mut self) {
+        self.generated =
+            PasswordGenerator::generate(&self.generator_options).unwrap_or_else(|e| e.to_string());
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        if self.visible.is_empty() {
+            self.selected = 0;
+            return;
+        }
+        let max = self.visible.len() as isize - 1;
+        self.selected = (self.selected as isize + delta).clamp(0, max) as usize;
+    }
+
+    fn apply_filter(&mut self) {
+        let query = self.search.trim();
+        self.visible = if query.is_empty() {
+            self.credentials.clone()
+        } else {
+            let by_id: HashMap<String, CredentialRecord> = self
+                .credentials
+                .iter()
+                .map(|c| (c.id.clone(), c.clone()))
+                .collect();
+            CredentialSearchEngine::search(&by_id, &SearchQuery::text(query))
+                .into_iter()
+                .map(|result| result.credential)
+                .collect()
+        };
+        self.selected = self.selected.min(self.visible.len().saturating_sub(1));
+    }
+
+    fn set_status(&mut self, text: impl Into<String>, is_error: bool, now: Instant) {
+        self.status = Some(Status {
+            text: text.into(),
+            is_error,
+            shown_at: now,
+        });
+    }
+}
+
+/// First non-empty value of the given field type
+pub fn field_value(credential: &CredentialRecord, field_type: FieldType) -> Option<String> {
+    credential
+        .fields
+        .values()
+        .find(|field| field.field_type == field_type && !field.value.is_empty())
+        .map(|field| field.value.clone())
+}
+
+/// Current TOTP code, if the credential has a secret
+pub fn totp_code(credential: &CredentialRecord) -> Option<Result<String, String>> {
+    let field = credential
+        .fields
+        .values()
+        .find(|field| field.field_type == FieldType::TotpSecret && !field.value.is_empty())?;
+    Some(
+        TotpGenerator::from_field(field)
+            .and_then(|generator| generator.generate())
+            .map_err(|e| e.to_string()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clipboard::MemoryClipboard;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use tempfile::TempDir;
+    use ziplock_shared::{CredentialField, DesktopFileProvider, UnifiedRepositoryManager};
+
+    const PASSWORD: &str = "correct horse battery staple";
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    fn type_text(app: &mut App, text: &str, now: Instant) {
+        for c in text.chars() {
+            app.handle_key(key(KeyCode::Char(c)), now);
+        }
+    }
+
+    fn archive(dir: &TempDir) -> String {
+        let path = dir.path().join("vault.7z").to_string_lossy().to_string();
+        let mut manager = UnifiedRepositoryManager::new(DesktopFileProvider::new());
+        manager.create_repository(&path, PASSWORD).unwrap();
+        for (title, user, pass) in [
+            ("GitHub", "octocat", "gh-secret"),
+            ("Bank", "me", "bank-secret"),
+            ("Email", "me@example.com", "mail-secret"),
+        ] {
+            let mut credential = CredentialRecord::new(title.to_string(), "login".to_string());
+            credential.set_field("username", CredentialField::username(user));
+            credential.set_field("password", CredentialField::password(pass));
+            manager.add_credential(credential).unwrap();
+        }
+        manager.save_repository().unwrap();
+        path
+    }
+
+    fn unlocked(dir: &TempDir) -> (App, Rc<RefCell<Option<String>>>, Instant) {
+        let clipboard = MemoryClipboard::default();
+        let contents = clipboard.contents.clone();
+        let now = Instant::now();
+        let mut app = App::new(
+            archive(dir),
+            Box::new(clipboard),
+            Timeouts {
+                auto_lock: Duration::from_secs(60),
+                clipboard: Duration::from_secs(10),
+            },
+            now,
+        );
+        type_text(&mut app, PASSWORD, now);
+        app.handle_key(key(KeyCode::Enter), now);
+        assert_eq!(app.screen, Screen::List);
+        (app, contents, now)
+    }
+
+    #[test]
+    fn test_wrong_password_stays_locked() {
+        let dir = TempDir::new().unwrap();
+        let now = Instant::now();
+        let mut app = App::new(
+            archive(&dir),
+            Box::new(MemoryClipboard::default()),
+            Timeouts {
+                auto_lock: Duration::ZERO,
+                clipboard: Duration::ZERO,
+            },
+            now,
+        );
+        type_text(&mut app, "wrong", now);
+        app.handle_key(key(KeyCode::Enter), now);
+
+        assert_eq!(app.screen, Screen::Unlock);
+        assert!(app.status.as_ref().unwrap().is_error);
+        assert!(app.password_input.is_empty());
+    }
+
+    #[test]
+    fn test_list_is_sorted_and_searchable() {
+        let dir = TempDir::new().unwrap();
+        let (mut app, _, now) = unlocked(&dir);
+        let titles: Vec<_> = app.visible.iter().map(|c| c.title.as_str()).collect();
+        assert_eq!(titles, vec!["Bank", "Email", "GitHub"]);
+
+        app.handle_key(key(KeyCode::Char('/')), now);
+        type_text(&mut app, "git", now);
+        assert_eq!(app.visible.len(), 1);
+        assert_eq!(app.visible[0].title, "GitHub");
+
+        app.handle_key(key(KeyCode::Esc), now);
+        assert_eq!(app.visible.len(), 3);
+        assert!(!app.searching);
+    }
+
+    #[test]
+    fn test_copy_and_clipboard_clearing() {
+        let dir = TempDir::new().unwrap();
+        let (mut app, clipboard, now) = unlocked(&dir);
+
+        app.handle_key(key(KeyCode::Char('j')), now);
+        app.handle_key(key(KeyCode::Char('p')), now);
+        assert_eq!(clipboard.borrow().as_deref(), Some("mail-secret"));
+
+        app.tick(now + Duration::from_secs(5));
+        assert!(clipboard.borrow().is_some());
+        app.tick(now + Duration::from_secs(11));
+        assert!(clipboard.borrow().is_none());
+
+        app.handle_key(key(KeyCode::Char('t')), now);
+        assert!(app.status.as_ref().unwrap().is_error);
+    }
+
+    #[test]
+    fn test_auto_lock_clears_everything() {
+        let dir = TempDir::new().unwrap();
+        let (mut app, clipboard, now) = unlocked(&dir);
+        app.handle_key(key(KeyCode::Char('u')), now);
+
+        app.tick(now + Duration::from_secs(30));
+        assert_eq!(app.screen, Screen::List);
+
+        app.tick(now + Duration::from_secs(61));
+        assert_eq!(app.screen, Screen::Unlock);
+        assert_eq!(app.total_credentials(), 0);
+        assert!(app.visible.is_empty());
+        assert!(clipboard.borrow().is_none());
+    }
+
+    #[test]
+    fn test_detail_and_generator_navigation() {
+        let dir = TempDir::new().unwrap();
+        let (mut app, clipboard, now) = unlocked(&dir);
+
+        app.handle_key(key(KeyCode::Enter), now);
+        assert_eq!(app.screen, Screen::Detail);
+        app.handle_key(key(KeyCode::Char('r')), now);
+        assert!(app.reveal);
+        app.handle_key(key(KeyCode::Esc), now);
+        assert_eq!(app.screen, Screen::List);
+        assert!(!app.reveal);
+
+        app.handle_key(key(KeyCode::Char('n')), now);
+        assert_eq!(app.screen, Screen::Generator);
+        assert_eq!(app.generated.len(), 20);
+        app.handle_key(key(KeyCode::Char('+')), now);
+        assert_eq!(app.generated.len(), 21);
+        app.handle_key(key(KeyCode::Enter), now);
+        assert_eq!(clipboard.borrow().as_deref(), Some(app.generated.as_str()));
+
+        app.handle_key(
+            KeyEvent::new(KeyCode::Char('l'), KeyModifiers::CONTROL),
+            now,
+        );
+        assert_eq!(app.screen, Screen::Unlock);
+    }
+}
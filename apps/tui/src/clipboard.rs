@@ -0,0 +1,60 @@
+//! Clipboard access with automatic clearing
+
+/// Somewhere copied secrets can be placed
+pub trait ClipboardBackend {
+    fn set_text(&mut self, text: &str) -> Result<(), String>;
+    fn clear(&mut self);
+}
+
+/// The system clipboard, connected on first use
+///
+/// Connecting can fail on headless servers; copying then reports an error
+/// instead of aborting the TUI.
+#[derive(Default)]
+pub struct SystemClipboard {
+    inner: Option<arboard::Clipboard>,
+}
+
+impl SystemClipboard {
+    fn connect(&mut self) -> Result<&mut arboard::Clipboard, String> {
+        if self.inner.is_none() {
+            self.inner = Some(
+                arboard::Clipboard::new().map_err(|e| format!("Clipboard unavailable: {}", e))?,
+            );
+        }
+        Ok(self.inner.as_mut().expect("clipboard was just connected"))
+    }
+}
+
+impl ClipboardBackend for SystemClipboard {
+    fn set_text(&mut self, text: &str) -> Result<(), String> {
+        self.connect()?
+            .set_text(text)
+            .map_err(|e| format!("Failed to copy: {}", e))
+    }
+
+    fn clear(&mut self) {
+        if let Some(clipboard) = self.inner.as_mut() {
+            let _ = clipboard.clear();
+        }
+    }
+}
+
+/// In-memory clipboard for tests
+#[cfg(test)]
+#[derive(Default)]
+pub struct MemoryClipboard {
+    pub contents: std::rc::Rc<std::cell::RefCell<Option<String>>>,
+}
+
+#[cfg(test)]
+impl ClipboardBackend for MemoryClipboard {
+    fn set_text(&mut self, text: &str) -> Result<(), String> {
+        *self.contents.borrow_mut() = Some(text.to_string());
+        Ok(())
+    }
+
+    fn clear(&mut self) {
+        *self.contents.borrow_mut() = None;
+    }
+}
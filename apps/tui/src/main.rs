@@ -0,0 +1,87 @@
+//! ZipLock terminal frontend
+//!
+//! A keyboard-driven TUI over the shared repository service for servers and
+//! users who prefer the terminal. It is read-only: credentials can be listed,
+//! searched, viewed and copied, and passwords generated, but the archive is
+//! never modified.
+
+mod app;
+mod clipboard;
+mod ui;
+mod vault;
+
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use clap::{Arg, Command};
+use ratatui::crossterm::event::{self, Event, KeyEventKind};
+use ziplock_shared::{ConfigManager, ConfigPaths, DesktopFileProvider};
+
+use app::{App, Timeouts};
+use clipboard::SystemClipboard;
+
+/// How often the event loop wakes up to check timers
+const TICK_RATE: Duration = Duration::from_millis(250);
+
+fn main() -> Result<()> {
+    let matches = Command::new("ziplock-tui")
+        .version(env!("CARGO_PKG_VERSION"))
+        .about("Keyboard-driven terminal frontend for ZipLock")
+        .arg(
+            Arg::new("archive")
+                .help("Path to the .7z archive (defaults to the most recently used repository)"),
+        )
+        .get_matches();
+
+    let mut config = ConfigManager::new(DesktopFileProvider::new(), ConfigPaths::app_config_file());
+    if let Err(e) = config.load() {
+        eprintln!(
+            "Warning: failed to load configuration, using defaults: {}",
+            e
+        );
+    }
+
+    let archive_path = match matches.get_one::<String>("archive") {
+        Some(path) => path.clone(),
+        None => config
+            .get_recent_repositories()
+            .first()
+            .map(|repo| repo.path.clone())
+            .ok_or_else(|| anyhow!("No archive given and no recent repositories configured"))?,
+    };
+
+    let timeouts = Timeouts {
+        auto_lock: Duration::from_secs(config.config().ui.auto_lock_timeout),
+        clipboard: Duration::from_secs(config.config().security.clipboard_timeout),
+    };
+    let mut app = App::new(
+        archive_path,
+        Box::new(SystemClipboard::default()),
+        timeouts,
+        Instant::now(),
+    );
+
+    let mut terminal = ratatui::init();
+    let result = run(&mut terminal, &mut app);
+    ratatui::restore();
+    result
+}
+
+fn run(terminal: &mut ratatui::DefaultTerminal, app: &mut App) -> Result<()> {
+    while !app.should_quit {
+        terminal.draw(|frame| ui::draw(frame, app))?;
+
+        if event::poll(TICK_RATE)? {
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press {
+                    app.handle_key(key, Instant::now());
+                }
+            }
+        }
+        app.tick(Instant::now());
+    }
+
+    // Make sure nothing copied outlives the session
+    app.lock(Instant::now());
+    Ok(())
+}
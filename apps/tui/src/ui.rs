@@ -0,0 +1,250 @@
+//! Rendering of the TUI screens
+
+use std::time::Instant;
+
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::Frame;
+use ziplock_shared::{CredentialRecord, FieldType, PasswordAnalyzer};
+use ziplock_theme::{palette, Rgba};
+
+use crate::app::{totp_code, App, Screen};
+
+const MASK: &str = "••••••••";
+
+/// A design token as a true-color terminal color
+fn color(token: Rgba) -> Color {
+    let [r, g, b] = token.to_rgb8();
+    Color::Rgb(r, g, b)
+}
+
+pub fn draw(frame: &mut Frame, app: &App) {
+    let [body, status] =
+        Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(frame.area());
+
+    match app.screen {
+        Screen::Unlock => draw_unlock(frame, body, app),
+        Screen::List => draw_list(frame, body, app),
+        Screen::Detail => {
+            draw_list(frame, body, app);
+            draw_detail(frame, popup(body, 70, 80), app);
+        }
+        Screen::Generator => {
+            draw_list(frame, body, app);
+            draw_generator(frame, popup(body, 60, 40), app);
+        }
+    }
+
+    draw_status(frame, status, app);
+}
+
+fn draw_unlock(frame: &mut Frame, area: Rect, app: &App) {
+    let area = popup(area, 60, 30);
+    let masked = "•".repeat(app.password_input.chars().count());
+    let text = vec![
+        Line::from(vec!["Archive: ".dim(), Span::raw(&app.archive_path)]),
+        Line::default(),
+        Line::from("Master password:"),
+        Line::from(Span::raw(format!("{}█", masked)).bold()),
+    ];
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(
+        Paragraph::new(text)
+            .wrap(Wrap { trim: false })
+            .block(Block::bordered().title(" ZipLock ")),
+        area,
+    );
+}
+
+fn draw_list(frame: &mut Frame, area: Rect, app: &App) {
+    let [search, list] = Layout::vertical([Constraint::Length(3), Constraint::Min(1)]).areas(area);
+
+    let search_style = if app.searching {
+        Style::default().fg(color(palette::ACCENT))
+    } else {
+        Style::default()
+    };
+    frame.render_widget(
+        Paragraph::new(app.search.as_str())
+            .style(search_style)
+            .block(Block::bordered().title(" Search (/) ")),
+        search,
+    );
+
+    let items: Vec<ListItem> = app
+        .visible
+        .iter()
+        .map(|credential| {
+            let username = crate::app::field_value(credential, FieldType::Username)
+                .map(|u| format!("  {}", u))
+                .unwrap_or_default();
+            ListItem::new(Line::from(vec![
+                Span::raw(&credential.title),
+                Span::raw(username).dim(),
+            ]))
+        })
+        .collect();
+
+    let title = format!(
+        " Credentials ({}/{}) ",
+        app.visible.len(),
+        app.total_credentials()
+    );
+    let mut state = ListState::default().with_selected(Some(app.selected));
+    frame.render_stateful_widget(
+        List::new(items)
+            .block(Block::bordered().title(title))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+            .highlight_symbol("> "),
+        list,
+        &mut state,
+    );
+}
+
+fn draw_detail(frame: &mut Frame, area: Rect, app: &App) {
+    let Some(credential) = app.selected_credential() else {
+        return;
+    };
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(
+        Paragraph::new(detail_lines(credential, app.reveal))
+            .wrap(Wrap { trim: false })
+            .block(
+                Block::bordered()
+                    .title(format!(" {} ", credential.title))
+                    .title_bottom(" r reveal · u/p/t/o copy · Esc back "),
+            ),
+        area,
+    );
+}
+
+fn detail_lines(credential: &CredentialRecord, reveal: bool) -> Vec<Line<'_>> {
+    let mut fields: Vec<_> = credential.fields.iter().collect();
+    fields.sort_by_key(|(name, field)| (field_order(&field.field_type), name.as_str()));
+
+    let mut lines = vec![Line::from(vec![
+        "Type: ".dim(),
+        Span::raw(&credential.credential_type),
+    ])];
+    if !credential.tags.is_empty() {
+        lines.push(Line::from(vec![
+            "Tags: ".dim(),
+            Span::raw(credential.tags.join(", ")),
+        ]));
+    }
+    lines.push(Line::default());
+
+    for (name, field) in fields {
+        let label = field.label.as_deref().unwrap_or(name);
+        let value = match field.field_type {
+            FieldType::TotpSecret => match totp_code(credential) {
+                Some(Ok(code)) if reveal => code,
+                Some(Ok(_)) => "(press t to copy code)".to_string(),
+                Some(Err(e)) => format!("invalid: {}", e),
+                None => String::new(),
+            },
+            _ if field.sensitive && !reveal => MASK.to_string(),
+            _ => field.value.clone(),
+        };
+        lines.push(Line::from(vec![
+            Span::raw(format!("{}: ", label)).dim(),
+            Span::raw(value),
+        ]));
+    }
+
+    if let Some(notes) = credential.notes.as_deref().filter(|n| !n.is_empty()) {
+        lines.push(Line::default());
+        lines.push(Line::from("Notes:".dim()));
+        lines.extend(notes.lines().map(|l| Line::from(l.to_string())));
+    }
+
+    lines
+}
+
+fn field_order(field_type: &FieldType) -> u8 {
+    match field_type {
+        FieldType::Username => 0,
+        FieldType::Password => 1,
+        FieldType::Url => 2,
+        FieldType::TotpSecret => 3,
+        _ => 4,
+    }
+}
+
+fn draw_generator(frame: &mut Frame, area: Rect, app: &App) {
+    let options = &app.generator_options;
+    let strength = PasswordAnalyzer::analyze(&app.generated);
+    let toggle = |on: bool| if on { "on" } else { "off" };
+
+    let text = vec![
+        Line::from(Span::raw(&app.generated).bold()),
+        Line::default(),
+        Line::from(vec![
+            "Strength: ".dim(),
+            Span::raw(format!("{:?} ({})", strength.strength, strength.score)),
+        ]),
+        Line::from(vec![
+            "Length: ".dim(),
+            Span::raw(options.length.to_string()),
+            "   Symbols: ".dim(),
+            Span::raw(toggle(options.include_symbols)),
+            "   Exclude ambiguous: ".dim(),
+            Span::raw(toggle(options.exclude_ambiguous)),
+        ]),
+    ];
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(
+        Paragraph::new(text).wrap(Wrap { trim: false }).block(
+            Block::bordered()
+                .title(" Generate password ")
+                .title_bottom(" r new · +/- length · s symbols · a ambiguous · c copy · Esc "),
+        ),
+        area,
+    );
+}
+
+fn draw_status(frame: &mut Frame, area: Rect, app: &App) {
+    let line = match &app.status {
+        Some(status) if status.is_error => Line::from(status.text.as_str().red()),
+        Some(status) => Line::from(status.text.as_str().green()),
+        None => {
+            let mut hint = match app.screen {
+                Screen::Unlock => "Enter unlock · Esc quit".to_string(),
+                _ if app.searching => "type to filter · Enter done · Esc clear".to_string(),
+                _ => {
+                    "/ search · Enter open · u/p/t copy · n generate · L lock · q quit".to_string()
+                }
+            };
+            if let Some(remaining) = app.auto_lock_remaining(Instant::now()) {
+                hint.push_str(&format!(" · locks in {}s", remaining.as_secs()));
+            }
+            Line::from(hint.dim())
+        }
+    };
+    frame.render_widget(
+        Paragraph::new(line).block(Block::default().borders(Borders::NONE)),
+        area,
+    );
+}
+
+/// A centered rectangle taking the given percentages of `area`
+fn popup(area: Rect, percent_x: u16, percent_y: u16) -> Rect {
+    let [_, vertical, _] = Layout::vertical([
+        Constraint::Percentage((100 - percent_y) / 2),
+        Constraint::Percentage(percent_y),
+        Constraint::Percentage((100 - percent_y) / 2),
+    ])
+    .areas(area);
+    let [_, horizontal, _] = Layout::horizontal([
+        Constraint::Percentage((100 - percent_x) / 2),
+        Constraint::Percentage(percent_x),
+        Constraint::Percentage((100 - percent_x) / 2),
+    ])
+    .areas(vertical);
+    horizontal
+}
@@ -0,0 +1,44 @@
+//! Access to the repository archive
+//!
+//! Wraps the shared `UnifiedRepositoryManager` so the rest of the TUI only
+//! deals with unlocking, reading credentials and locking again.
+
+use anyhow::{anyhow, Result};
+use ziplock_shared::core::{CoreError, FileError};
+use ziplock_shared::{CredentialRecord, DesktopFileProvider, UnifiedRepositoryManager};
+
+/// An unlocked repository
+pub struct Vault {
+    manager: UnifiedRepositoryManager<DesktopFileProvider>,
+}
+
+impl Vault {
+    /// Open and decrypt the archive at `path`
+    pub fn unlock(path: &str, password: &str) -> Result<Self> {
+        let mut manager = UnifiedRepositoryManager::new(DesktopFileProvider::new());
+        manager
+            .open_repository(path, password)
+            .map_err(|e| match e {
+                CoreError::FileOperation(FileError::InvalidPassword) => {
+                    anyhow!("Incorrect master password")
+                }
+                CoreError::FileOperation(FileError::NotFound { .. }) => {
+                    anyhow!("Archive not found: {}", path)
+                }
+                other => anyhow!("Failed to open archive: {}", other),
+            })?;
+        Ok(Self { manager })
+    }
+
+    /// All credentials, sorted by title
+    pub fn credentials(&self) -> Result<Vec<CredentialRecord>> {
+        let mut credentials = self.manager.list_credentials()?;
+        credentials.sort_by_key(|c| c.title.to_lowercase());
+        Ok(credentials)
+    }
+
+    /// Close the repository; the TUI is read-only so nothing is saved
+    pub fn lock(mut self) {
+        let _ = self.manager.close_repository(false);
+    }
+}
@@ -0,0 +1,92 @@
+//! Errors returned by the client
+
+use std::fmt;
+use std::path::PathBuf;
+
+use ziplock_shared::utils::ApiErrorCode;
+
+/// Errors talking to a running ZipLock app
+#[derive(Debug)]
+pub enum ClientError {
+    /// Nothing is listening on the socket, usually because the app is not
+    /// running or no API token has been issued yet
+    NotRunning { path: PathBuf, message: String },
+
+    /// Reading from or writing to the socket failed
+    Io { message: String },
+
+    /// The app answered with something that is not a protocol message
+    Protocol { message: String },
+
+    /// The token is invalid, revoked, expired or lacks the needed scope
+    Unauthorized { message: String },
+
+    /// The app refused the request, e.g. because the vault is locked or the
+    /// credential does not exist
+    Rejected { message: String },
+
+    /// The vault was locked because it was idle or unlocked for too long
+    SessionExpired { message: String },
+
+    /// The credential needs the user's approval in the app, which was
+    /// refused or not given in time
+    ApprovalDenied { message: String },
+
+    /// The app is shutting down and has closed the connection
+    ServerShutdown,
+
+    /// The scripting API is not available on this platform
+    Unsupported,
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::NotRunning { path, message } => write!(
+                f,
+                "Could not connect to ZipLock at {:?} ({}). Is the app running with an API token issued?",
+                path, message
+            ),
+            ClientError::Io { message } => write!(f, "Connection error: {}", message),
+            ClientError::Protocol { message } => write!(f, "Invalid response: {}", message),
+            ClientError::Unauthorized { message } => write!(f, "{}", message),
+            ClientError::Rejected { message } => write!(f, "{}", message),
+            ClientError::SessionExpired { message } => write!(f, "{}", message),
+            ClientError::ApprovalDenied { message } => write!(f, "{}", message),
+            ClientError::ServerShutdown => write!(f, "ZipLock is shutting down"),
+            ClientError::Unsupported => {
+                write!(f, "The scripting API is not supported on this platform yet")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ClientError {}
+
+impl From<std::io::Error> for ClientError {
+    fn from(error: std::io::Error) -> Self {
+        ClientError::Io {
+            message: error.to_string(),
+        }
+    }
+}
+
+impl ClientError {
+    /// Classify an `error` line from the app
+    pub(crate) fn from_server(message: String, code: Option<ApiErrorCode>) -> Self {
+        match code {
+            Some(ApiErrorCode::SessionExpired) => return ClientError::SessionExpired { message },
+            Some(ApiErrorCode::ApprovalDenied) => return ClientError::ApprovalDenied { message },
+            None => {}
+        }
+        // Token errors come from `ApiTokenError`'s messages
+        if message.contains("API token") {
+            ClientError::Unauthorized { message }
+        } else {
+            ClientError::Rejected { message }
+        }
+    }
+}
+
+/// Result type for client operations
+pub type ClientResult<T> = Result<T, ClientError>;
@@ -0,0 +1,468 @@
+//! Client for the ZipLock desktop app's scripting API
+//!
+//! Alternative frontends and scripts can use [`ZipLockClient`] instead of
+//! speaking the line-based JSON protocol themselves. A client holds one
+//! connection and the API token it authenticates with, issued from
+//! Settings → Security → Scripting API Tokens in the desktop app:
+//!
+//! ```no_run
+//! # async fn example() -> ziplock_client::ClientResult<()> {
+//! let token = std::env::var("ZIPLOCK_API_TOKEN").unwrap();
+//! let mut client = ziplock_client::ZipLockClient::connect(token).await?;
+//! for credential in client.search("github").await? {
+//!     println!("{} {}", credential.id, credential.title);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The API is read-only: credentials can be listed, searched and fetched,
+//! with sensitive fields only revealed to tokens with the `read_secrets`
+//! scope. After [`ZipLockClient::subscribe`], [`ZipLockClient::next_event`]
+//! reports every change to the vault, so a frontend can keep its list
+//! current without polling.
+//!
+//! The app locks the vault itself once it has been idle or unlocked for
+//! longer than its settings allow; calls made after that fail with
+//! [`ClientError::SessionExpired`]. Reading a credential the user asked to
+//! be consulted about waits until they answer in the app, and fails with
+//! [`ClientError::ApprovalDenied`] if they refuse.
+//!
+//! [`lock_state`] and [`lock_all`] need no token. They are meant for status
+//! bar widgets and screen locker hooks, which may show whether the vault is
+//! unlocked and lock it, but not read it.
+
+mod error;
+
+use std::collections::VecDeque;
+use std::path::Path;
+
+use serde::de::DeserializeOwned;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader, Lines};
+
+pub use error::{ClientError, ClientResult};
+pub use ziplock_shared::core::RepositoryEvent;
+pub use ziplock_shared::models::CredentialRecord;
+pub use ziplock_shared::utils::{
+    api_socket_path, lock_socket_path, ApiCredentialSummary, ApiEvent, LockCommand, LockStatus,
+    VaultLockState,
+};
+
+use ziplock_shared::utils::{ApiMethod, ApiRequest, ApiResponse};
+
+type Reader = Lines<BufReader<Box<dyn AsyncRead + Send + Unpin>>>;
+type Writer = Box<dyn AsyncWrite + Send + Unpin>;
+
+/// A connection to a running ZipLock app
+pub struct ZipLockClient {
+    reader: Reader,
+    writer: Writer,
+    token: String,
+    closed: bool,
+    /// Events that arrived while waiting for a response
+    pending: VecDeque<ApiEvent>,
+}
+
+impl ZipLockClient {
+    /// Connect to the app on its default socket
+    pub async fn connect(token: impl Into<String>) -> ClientResult<Self> {
+        Self::connect_to(api_socket_path(), token).await
+    }
+
+    /// Connect to the app listening on the socket at `path`
+    #[cfg(unix)]
+    pub async fn connect_to(
+        path: impl AsRef<Path>,
+        token: impl Into<String>,
+    ) -> ClientResult<Self> {
+        let path = path.as_ref();
+        let stream =
+            tokio::net::UnixStream::connect(path)
+                .await
+                .map_err(|e| ClientError::NotRunning {
+                    path: path.to_path_buf(),
+                    message: e.to_string(),
+                })?;
+        Ok(Self::from_stream(stream, token))
+    }
+
+    #[cfg(not(unix))]
+    pub async fn connect_to(
+        _path: impl AsRef<Path>,
+        _token: impl Into<String>,
+    ) -> ClientResult<Self> {
+        Err(ClientError::Unsupported)
+    }
+
+    /// Use an already connected stream, e.g. one forwarded from elsewhere
+    pub fn from_stream<S>(stream: S, token: impl Into<String>) -> Self
+    where
+        S: AsyncRead + AsyncWrite + Send + 'static,
+    {
+        let (reader, writer) = tokio::io::split(stream);
+        let reader: Box<dyn AsyncRead + Send + Unpin> = Box::new(reader);
+        Self {
+            reader: BufReader::new(reader).lines(),
+            writer: Box::new(writer),
+            token: token.into(),
+            closed: false,
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Titles, types and tags of every credential
+    pub async fn list(&mut self) -> ClientResult<Vec<ApiCredentialSummary>> {
+        self.call(ApiMethod::List).await
+    }
+
+    /// Credentials whose title or non-sensitive fields match `query`
+    pub async fn search(&mut self, query: &str) -> ClientResult<Vec<ApiCredentialSummary>> {
+        self.call(ApiMethod::Search {
+            query: query.to_string(),
+        })
+        .await
+    }
+
+    /// One credential with its sensitive fields masked
+    pub async fn get(&mut self, id: &str) -> ClientResult<CredentialRecord> {
+        self.call(ApiMethod::Get {
+            id: id.to_string(),
+            reveal: false,
+        })
+        .await
+    }
+
+    /// One credential including the values of its sensitive fields
+    ///
+    /// The token needs the `read_secrets` scope.
+    pub async fn reveal(&mut self, id: &str) -> ClientResult<CredentialRecord> {
+        self.call(ApiMethod::Get {
+            id: id.to_string(),
+            reveal: true,
+        })
+        .await
+    }
+
+    /// Ask the app to send an [`ApiEvent::Changed`] event for every change
+    /// to the vault
+    ///
+    /// Events are delivered by [`next_event`](Self::next_event) until the
+    /// vault locks, which is reported as [`RepositoryEvent::Locked`]. Other
+    /// calls can still be made in between. [`ApiEvent::Lagged`] means
+    /// changes were dropped because events were not read fast enough, and
+    /// the credentials should be listed again.
+    pub async fn subscribe(&mut self) -> ClientResult<()> {
+        self.call::<serde_json::Value>(ApiMethod::Subscribe)
+            .await
+            .map(|_| ())
+    }
+
+    /// Wait for the next event the app sends without a request
+    ///
+    /// Returns `None` once the connection is closed. After
+    /// [`ApiEvent::ServerShutdown`] the connection is closed and every
+    /// further call fails with [`ClientError::ServerShutdown`].
+    pub async fn next_event(&mut self) -> ClientResult<Option<ApiEvent>> {
+        if let Some(event) = self.pending.pop_front() {
+            return Ok(Some(event));
+        }
+        if self.closed {
+            return Ok(None);
+        }
+        loop {
+            match self.read_response().await? {
+                Some(ApiResponse::Event { event, .. }) => {
+                    self.closed = event == ApiEvent::ServerShutdown;
+                    return Ok(Some(event));
+                }
+                Some(_) => continue,
+                None => return Ok(None),
+            }
+        }
+    }
+
+    /// Whether the app has closed the connection
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    async fn call<T: DeserializeOwned>(&mut self, method: ApiMethod) -> ClientResult<T> {
+        if self.closed {
+            return Err(ClientError::ServerShutdown);
+        }
+
+        let request = ApiRequest {
+            token: self.token.clone(),
+            method,
+        };
+        let mut line = serde_json::to_string(&request).map_err(|e| ClientError::Protocol {
+            message: e.to_string(),
+        })?;
+        line.push('\n');
+        self.writer.write_all(line.as_bytes()).await?;
+        self.writer.flush().await?;
+
+        loop {
+            match self.read_response().await? {
+                Some(ApiResponse::Ok { result, .. }) => {
+                    return serde_json::from_value(result).map_err(|e| ClientError::Protocol {
+                        message: e.to_string(),
+                    })
+                }
+                Some(ApiResponse::Error { error, code, .. }) => {
+                    return Err(ClientError::from_server(error, code))
+                }
+                // Changes sent before the response are kept for next_event
+                Some(ApiResponse::Event { event, .. }) if event != ApiEvent::ServerShutdown => {
+                    self.pending.push_back(event)
+                }
+                Some(ApiResponse::Event { .. }) | None => {
+                    self.closed = true;
+                    return Err(ClientError::ServerShutdown);
+                }
+            }
+        }
+    }
+
+    /// The next response line, or `None` when the connection is closed
+    async fn read_response(&mut self) -> ClientResult<Option<ApiResponse>> {
+        loop {
+            let Some(line) = self.reader.next_line().await? else {
+                self.closed = true;
+                return Ok(None);
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            return serde_json::from_str(&line)
+                .map(Some)
+                .map_err(|e| ClientError::Protocol {
+                    message: e.to_string(),
+                });
+        }
+    }
+}
+
+/// Whether the app has a vault open
+pub async fn lock_state() -> ClientResult<VaultLockState> {
+    let status = send_lock_command(lock_socket_path(), LockCommand::Status).await?;
+    Ok(status.state)
+}
+
+/// Make the app save pending changes, wipe its keys and lock at once
+pub async fn lock_all() -> ClientResult<()> {
+    send_lock_command(lock_socket_path(), LockCommand::Lock).await?;
+    Ok(())
+}
+
+/// Send one command to the lock socket at `path`
+#[cfg(unix)]
+pub async fn send_lock_command(
+    path: impl AsRef<Path>,
+    command: LockCommand,
+) -> ClientResult<LockStatus> {
+    let path = path.as_ref();
+    let mut stream =
+        tokio::net::UnixStream::connect(path)
+            .await
+            .map_err(|e| ClientError::NotRunning {
+                path: path.to_path_buf(),
+                message: e.to_string(),
+            })?;
+    let mut line = serde_json::to_string(&command).map_err(|e| ClientError::Protocol {
+        message: e.to_string(),
+    })?;
+    line.push('\n');
+    stream.write_all(line.as_bytes()).await?;
+
+    let response = BufReader::new(stream).lines().next_line().await?;
+    let response: ApiResponse = serde_json::from_str(response.as_deref().unwrap_or_default())
+        .map_err(|e| ClientError::Protocol {
+            message: e.to_string(),
+        })?;
+    match response {
+        ApiResponse::Ok { result, .. } => {
+            serde_json::from_value(result).map_err(|e| ClientError::Protocol {
+                message: e.to_string(),
+            })
+        }
+        ApiResponse::Error { error, code, .. } => Err(ClientError::from_server(error, code)),
+        ApiResponse::Event { .. } => Err(ClientError::ServerShutdown),
+    }
+}
+
+#[cfg(not(unix))]
+pub async fn send_lock_command(
+    _path: impl AsRef<Path>,
+    _command: LockCommand,
+) -> ClientResult<LockStatus> {
+    Err(ClientError::Unsupported)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::DuplexStream;
+
+    /// Answer each request line read from `server` with the next of `replies`
+    fn serve(
+        server: DuplexStream,
+        replies: Vec<&'static str>,
+    ) -> tokio::task::JoinHandle<Vec<String>> {
+        tokio::spawn(async move {
+            let (reader, mut writer) = tokio::io::split(server);
+            let mut lines = BufReader::new(reader).lines();
+            let mut requests = Vec::new();
+            for reply in replies {
+                if reply.contains("server_shutdown") {
+                    writer.write_all(reply.as_bytes()).await.unwrap();
+                    break;
+                }
+                let Ok(Some(line)) = lines.next_line().await else {
+                    break;
+                };
+                requests.push(line);
+                writer.write_all(reply.as_bytes()).await.unwrap();
+            }
+            requests
+        })
+    }
+
+    #[tokio::test]
+    async fn test_typed_results_and_errors() {
+        let (client, server) = tokio::io::duplex(4096);
+        let server = serve(
+            server,
+            vec![
+                "{\"ok\":true,\"result\":[{\"id\":\"1\",\"title\":\"GitHub\",\"credential_type\":\"login\",\"tags\":[\"dev\"]}]}\n",
+                "{\"ok\":false,\"error\":\"API token does not have the 'read_secrets' scope\"}\n",
+                "{\"ok\":false,\"error\":\"Vault is locked\"}\n",
+                "{\"ok\":false,\"error\":\"Session reached its maximum length\",\"code\":\"session_expired\"}\n",
+                "{\"ok\":false,\"error\":\"The request was not approved in ZipLock\",\"code\":\"approval_denied\"}\n",
+            ],
+        );
+        let mut client = ZipLockClient::from_stream(client, "zlapi1_test");
+
+        let found = client.search("git").await.unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].title, "GitHub");
+        assert_eq!(found[0].tags, vec!["dev".to_string()]);
+        assert!(matches!(
+            client.reveal("1").await,
+            Err(ClientError::Unauthorized { .. })
+        ));
+        assert!(matches!(
+            client.list().await,
+            Err(ClientError::Rejected { message }) if message == "Vault is locked"
+        ));
+        assert!(matches!(
+            client.get("1").await,
+            Err(ClientError::SessionExpired { .. })
+        ));
+        assert!(matches!(
+            client.reveal("1").await,
+            Err(ClientError::ApprovalDenied { .. })
+        ));
+
+        let requests = server.await.unwrap();
+        let search: ApiRequest = serde_json::from_str(&requests[0]).unwrap();
+        assert_eq!(search.token, "zlapi1_test");
+        assert_eq!(
+            search.method,
+            ApiMethod::Search {
+                query: "git".to_string()
+            }
+        );
+        let reveal: ApiRequest = serde_json::from_str(&requests[1]).unwrap();
+        assert_eq!(
+            reveal.method,
+            ApiMethod::Get {
+                id: "1".to_string(),
+                reveal: true
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_server_shutdown_closes_the_client() {
+        let (client, server) = tokio::io::duplex(4096);
+        let server = serve(
+            server,
+            vec!["{\"ok\":false,\"event\":\"server_shutdown\"}\n"],
+        );
+        let mut client = ZipLockClient::from_stream(client, "zlapi1_test");
+
+        assert_eq!(
+            client.next_event().await.unwrap(),
+            Some(ApiEvent::ServerShutdown)
+        );
+        assert!(client.is_closed());
+        assert!(matches!(
+            client.list().await,
+            Err(ClientError::ServerShutdown)
+        ));
+        assert_eq!(client.next_event().await.unwrap(), None);
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_changes_are_kept_across_calls() {
+        let (client, server) = tokio::io::duplex(4096);
+        let server = serve(
+            server,
+            vec![
+                "{\"ok\":true,\"result\":{\"subscribed\":true}}\n",
+                "{\"ok\":true,\"event\":{\"changed\":{\"type\":\"credential_added\",\"id\":\"1\"}}}\n{\"ok\":true,\"result\":[]}\n",
+                "{\"ok\":false,\"event\":\"server_shutdown\"}\n",
+            ],
+        );
+        let mut client = ZipLockClient::from_stream(client, "zlapi1_test");
+
+        client.subscribe().await.unwrap();
+        assert!(client.list().await.unwrap().is_empty());
+        assert_eq!(
+            client.next_event().await.unwrap(),
+            Some(ApiEvent::Changed(RepositoryEvent::CredentialAdded {
+                id: "1".to_string()
+            }))
+        );
+        assert_eq!(
+            client.next_event().await.unwrap(),
+            Some(ApiEvent::ServerShutdown)
+        );
+
+        let requests = server.await.unwrap();
+        let subscribe: ApiRequest = serde_json::from_str(&requests[0]).unwrap();
+        assert_eq!(subscribe.method, ApiMethod::Subscribe);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_lock_command_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("lock.sock");
+        let listener = tokio::net::UnixListener::bind(&path).unwrap();
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (reader, mut writer) = stream.into_split();
+            let request = BufReader::new(reader).lines().next_line().await.unwrap();
+            writer
+                .write_all(b"{\"ok\":true,\"result\":{\"state\":\"locked\"}}\n")
+                .await
+                .unwrap();
+            request.unwrap()
+        });
+
+        let status = send_lock_command(&path, LockCommand::Lock).await.unwrap();
+        assert_eq!(status.state, VaultLockState::Locked);
+        assert_eq!(server.await.unwrap(), r#"{"method":"lock"}"#);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_connect_without_app_running() {
+        let dir = tempfile::tempdir().unwrap();
+        let result = ZipLockClient::connect_to(dir.path().join("api.sock"), "t").await;
+        assert!(matches!(result, Err(ClientError::NotRunning { .. })));
+    }
+}
@@ -0,0 +1,110 @@
+#!/bin/bash
+set -e
+
+# ZipLock Bindings Generator
+# Generates the Kotlin and Swift bindings of the typed mobile API
+# (shared/src/ffi/bindings.rs) with UniFFI
+
+SCRIPT_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
+PROJECT_ROOT="$(cd "$SCRIPT_DIR/../.." && pwd)"
+SHARED_DIR="$PROJECT_ROOT/shared"
+
+# Colors for output
+RED='\033[0;31m'
+GREEN='\033[0;32m'
+BLUE='\033[0;34m'
+NC='\033[0m' # No Color
+
+# Logging functions
+log_info() {
+    echo -e "${BLUE}[INFO]${NC} $1"
+}
+
+log_success() {
+    echo -e "${GREEN}[SUCCESS]${NC} $1"
+}
+
+log_error() {
+    echo -e "${RED}[ERROR]${NC} $1"
+}
+
+# Default values
+LANGUAGES="kotlin swift"
+OUTPUT_DIR="$PROJECT_ROOT/target/bindings"
+
+# Function to show usage
+show_usage() {
+    cat << EOF
+Usage: $0 [OPTIONS]
+
+Generate Kotlin and Swift bindings for the typed mobile API.
+
+OPTIONS:
+    -l, --language LANGUAGE    kotlin, swift, or all (default: all)
+    -o, --output DIR           Output directory (default: $OUTPUT_DIR)
+    -h, --help                 Show this help message
+
+The bindings are written to DIR/kotlin and DIR/swift. Settings such as the
+Kotlin package come from shared/uniffi.toml.
+EOF
+}
+
+# Parse command line arguments
+while [[ $# -gt 0 ]]; do
+    case $1 in
+        -l|--language)
+            case $2 in
+                kotlin|swift) LANGUAGES="$2" ;;
+                all) LANGUAGES="kotlin swift" ;;
+                *)
+                    log_error "Invalid language: $2. Must be 'kotlin', 'swift', or 'all'"
+                    exit 1
+                    ;;
+            esac
+            shift 2
+            ;;
+        -o|--output)
+            OUTPUT_DIR="$2"
+            shift 2
+            ;;
+        -h|--help)
+            show_usage
+            exit 0
+            ;;
+        *)
+            log_error "Unknown option: $1"
+            show_usage
+            exit 1
+            ;;
+    esac
+done
+
+if ! command -v cargo &> /dev/null; then
+    log_error "Cargo is not installed. Please install Rust and Cargo."
+    exit 1
+fi
+
+cd "$PROJECT_ROOT"
+
+# The bindings are read from the metadata UniFFI embeds in the library
+log_info "Building shared library with the uniffi feature..."
+cargo build -p ziplock-shared --features uniffi --lib
+
+case "$(uname -s)" in
+    Darwin) LIBRARY="$PROJECT_ROOT/target/debug/libziplock_shared.dylib" ;;
+    MINGW*|MSYS*|CYGWIN*) LIBRARY="$PROJECT_ROOT/target/debug/ziplock_shared.dll" ;;
+    *) LIBRARY="$PROJECT_ROOT/target/debug/libziplock_shared.so" ;;
+esac
+
+for LANGUAGE in $LANGUAGES; do
+    log_info "Generating $LANGUAGE bindings..."
+    mkdir -p "$OUTPUT_DIR/$LANGUAGE"
+    cargo run -p ziplock-shared --features uniffi-bindgen --bin uniffi-bindgen -- \
+        generate \
+        --library "$LIBRARY" \
+        --language "$LANGUAGE" \
+        --config "$SHARED_DIR/uniffi.toml" \
+        --out-dir "$OUTPUT_DIR/$LANGUAGE"
+done
+
+log_success "Bindings written to $OUTPUT_DIR"
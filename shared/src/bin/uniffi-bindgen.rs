@@ -0,0 +1,8 @@
+//! Generates the Kotlin and Swift bindings of the typed mobile API
+//!
+//! Run through `scripts/build/generate-bindings.sh`, which builds the
+//! library with the `uniffi` feature and points this at it.
+
+fn main() {
+    uniffi::uniffi_bindgen_main()
+}
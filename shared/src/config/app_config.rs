@@ -5,8 +5,12 @@
 //! through their native frameworks and use only subset of these structures.
 
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 
+use crate::core::{SessionPolicy, UnlockAttempts};
+use crate::utils::{ApprovalPolicy, AutomationScript};
+
 /// Main application configuration structure
 ///
 /// Contains all user preferences and settings for desktop applications.
@@ -26,8 +30,31 @@ pub struct AppConfig {
     /// Repository management settings
     pub repository_settings: RepositoryManagementConfig,
 
+    /// Scheduled off-site backup settings
+    pub offsite_backup: OffsiteBackupConfig,
+
+    /// Desktop notifications for security events
+    pub notifications: NotificationConfig,
+
+    /// Plugins loaded from shared libraries
+    pub plugins: PluginsConfig,
+
+    /// Scripts run on vault events
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub automation_scripts: Vec<AutomationScript>,
+
     /// List of recent repositories
     pub repositories: Vec<RepositoryInfo>,
+
+    /// Version whose release notes the user last saw; the desktop app
+    /// shows what changed since then after an upgrade
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_seen_version: Option<String>,
+
+    /// Failed unlock attempts by repository path, kept here rather than in
+    /// the archive
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub unlock_attempts: BTreeMap<String, UnlockAttempts>,
 }
 
 /// User interface configuration
@@ -84,11 +111,110 @@ pub struct SecurityConfig {
     /// Whether to clear clipboard on lock
     pub clear_clipboard_on_lock: bool,
 
-    /// Maximum number of failed authentication attempts
+    /// Maximum number of failed authentication attempts (0 = no lockout)
     pub max_auth_attempts: u32,
 
     /// Lockout duration after max attempts (seconds)
     pub lockout_duration: u64,
+
+    /// Seconds to wait after a wrong master password, doubled after each
+    /// further one (0 = no delay)
+    pub unlock_backoff_secs: u64,
+
+    /// Whether to check stored passwords against Have I Been Pwned after
+    /// unlocking (only a 5-character hash prefix is sent)
+    pub check_breaches: bool,
+
+    /// Whether to offer unlocking with the OS keychain, which keeps a key to
+    /// the master password available while the user is logged in (desktop only)
+    pub os_keychain_unlock: bool,
+
+    /// Whether to serve stored SSH keys to SSH clients as an ssh-agent while
+    /// the vault is unlocked (desktop only)
+    pub ssh_agent: bool,
+
+    /// Scheduled lock windows, evaluated in local time
+    pub lock_windows: Vec<LockWindow>,
+
+    /// Idle timeout and maximum lifetime the app enforces on every client of
+    /// an unlocked vault, including scripts using the API
+    pub session: SessionPolicy,
+
+    /// Scripting API reads that need the user's approval in the app, and
+    /// the ones allowed always
+    pub api_approval: ApprovalPolicy,
+
+    /// Keystroke pacing and window checks for auto-type (desktop only)
+    pub auto_type: AutoTypeConfig,
+
+    /// Whether opens, saves and credential changes are recorded in the
+    /// vault's activity log, along with this device's name
+    pub activity_log: bool,
+}
+
+/// How auto-type paces keystrokes and guards the window it types into
+///
+/// With `randomize_cadence`, values are typed one character at a time with
+/// a random pause between keys, so the timing does not give away that the
+/// input is scripted. `verify_focus` stops typing when another window takes
+/// focus; where the session cannot report the focused window (most Wayland
+/// compositors), the check is skipped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutoTypeConfig {
+    /// Type with a random pause between keys
+    pub randomize_cadence: bool,
+
+    /// Shortest pause between keys, in milliseconds
+    pub min_key_delay_ms: u64,
+
+    /// Longest pause between keys, in milliseconds
+    pub max_key_delay_ms: u64,
+
+    /// Check before each value, and at random points within it, that the
+    /// window focused when typing began still has focus
+    pub verify_focus: bool,
+
+    /// Describe what would be typed instead of typing it
+    pub dry_run: bool,
+}
+
+impl AutoTypeConfig {
+    /// Longest pause accepted between keys
+    pub const MAX_KEY_DELAY_MS: u64 = 1_000;
+
+    /// The pause range, ordered and capped at [`Self::MAX_KEY_DELAY_MS`]
+    pub fn key_delay_range(&self) -> (u64, u64) {
+        let min = self.min_key_delay_ms.min(Self::MAX_KEY_DELAY_MS);
+        let max = self.max_key_delay_ms.min(Self::MAX_KEY_DELAY_MS);
+        (min.min(max), min.max(max))
+    }
+}
+
+/// A recurring time-of-day window with its own lock policy
+///
+/// `start` and `end` are local `HH:MM` times. A window whose end is not after
+/// its start runs past midnight, and one with equal start and end lasts a full
+/// day. `days` lists the weekdays the window starts on; empty means every day.
+///
+/// Without `idle_timeout_secs` the window is a hard lock: the vault locks as
+/// soon as it starts and cannot be unlocked until it ends. With it, the window
+/// only shortens the inactivity timeout.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockWindow {
+    /// Shown to the user when the window locks the vault
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub days: Vec<chrono::Weekday>,
+
+    pub start: String,
+
+    pub end: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idle_timeout_secs: Option<u64>,
 }
 
 /// Application behavior configuration
@@ -105,6 +231,119 @@ pub struct AppBehaviorConfig {
     pub backup_count: u32,
 }
 
+/// Desktop notification configuration (desktop only)
+///
+/// Each category can be turned off separately; in-app toasts are shown
+/// regardless.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct NotificationConfig {
+    /// Notify when the repository is locked after inactivity
+    pub auto_lock: bool,
+
+    /// Notify when a scheduled off-site backup fails
+    pub backup_failed: bool,
+
+    /// Notify when a stored password is found in a data breach
+    pub breach_detected: bool,
+
+    /// Notify when recurring credentials put new drafts in the inbox
+    pub drafts_created: bool,
+}
+
+/// Plugin library loading (desktop only)
+///
+/// Off by default, since a plugin library runs with the app's privileges.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[serde(default)]
+pub struct PluginsConfig {
+    /// Whether to load plugin libraries at startup
+    pub load_dynamic: bool,
+
+    /// Directory holding plugin libraries; `plugins` in the app config
+    /// directory if unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub directory: Option<PathBuf>,
+}
+
+impl PluginsConfig {
+    /// Directory scanned for plugin libraries
+    pub fn directory(&self) -> PathBuf {
+        self.directory
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(super::ConfigPaths::plugins_dir()))
+    }
+}
+
+/// Scheduled off-site backup configuration
+///
+/// The repository archive is uploaded as-is, so remote copies stay encrypted
+/// with the master password.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct OffsiteBackupConfig {
+    /// Whether scheduled uploads are enabled
+    pub enabled: bool,
+
+    /// Where backups are uploaded
+    pub destination: Option<BackupDestinationConfig>,
+
+    /// Days between uploads
+    pub interval_days: u32,
+
+    /// Number of remote copies to keep (0 = keep all)
+    pub keep: u32,
+
+    /// Outcome of the most recent uploads
+    pub status: OffsiteBackupStatus,
+}
+
+/// Remote location for off-site backups
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum BackupDestinationConfig {
+    /// SFTP server, using the system `sftp` client and its SSH keys/agent
+    Sftp {
+        host: String,
+        #[serde(default = "default_sftp_port")]
+        port: u16,
+        username: String,
+        remote_dir: String,
+        #[serde(default)]
+        identity_file: Option<PathBuf>,
+    },
+
+    /// WebDAV collection (Nextcloud, ownCloud, Apache mod_dav, ...)
+    WebDav {
+        url: String,
+        username: String,
+        /// ID of the credential in the open repository holding the password
+        #[serde(default)]
+        password_credential: Option<String>,
+    },
+}
+
+/// Last known state of scheduled off-site backups
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[serde(default)]
+pub struct OffsiteBackupStatus {
+    /// When an upload was last attempted
+    pub last_attempt: Option<chrono::DateTime<chrono::Utc>>,
+
+    /// When an upload last succeeded
+    pub last_success: Option<chrono::DateTime<chrono::Utc>>,
+
+    /// Remote name of the last uploaded backup
+    pub last_file: Option<String>,
+
+    /// Error from the last attempt, cleared on success
+    pub last_error: Option<String>,
+}
+
+fn default_sftp_port() -> u16 {
+    22
+}
+
 /// Repository management configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
@@ -202,6 +441,117 @@ impl Default for AppBehaviorConfig {
     }
 }
 
+impl Default for AutoTypeConfig {
+    fn default() -> Self {
+        Self {
+            randomize_cadence: false,
+            min_key_delay_ms: 30,
+            max_key_delay_ms: 120,
+            verify_focus: true,
+            dry_run: false,
+        }
+    }
+}
+
+impl Default for NotificationConfig {
+    fn default() -> Self {
+        Self {
+            auto_lock: true,
+            backup_failed: true,
+            breach_detected: true,
+            drafts_created: true,
+        }
+    }
+}
+
+impl Default for OffsiteBackupConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            destination: None,
+            interval_days: 30, // Monthly
+            keep: 6,
+            status: OffsiteBackupStatus::default(),
+        }
+    }
+}
+
+impl OffsiteBackupConfig {
+    /// When the next scheduled upload is due, if uploads are enabled
+    pub fn next_due(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        if !self.enabled || self.destination.is_none() {
+            return None;
+        }
+
+        match self.status.last_success {
+            Some(last) => Some(last + chrono::Duration::days(self.interval_days.max(1) as i64)),
+            None => Some(chrono::DateTime::<chrono::Utc>::MIN_UTC),
+        }
+    }
+
+    /// Whether an upload should run now
+    ///
+    /// After a failure the next attempt waits at least an hour so an
+    /// unreachable server doesn't produce a notification on every check.
+    pub fn is_due(&self, now: chrono::DateTime<chrono::Utc>) -> bool {
+        let Some(due) = self.next_due() else {
+            return false;
+        };
+
+        let retry_wait = match (self.status.last_attempt, &self.status.last_error) {
+            (Some(attempt), Some(_)) => now - attempt < chrono::Duration::hours(1),
+            _ => false,
+        };
+
+        now >= due && !retry_wait
+    }
+}
+
+impl OffsiteBackupStatus {
+    /// Record a successful upload
+    pub fn record_success(&mut self, file: String, at: chrono::DateTime<chrono::Utc>) {
+        self.last_attempt = Some(at);
+        self.last_success = Some(at);
+        self.last_file = Some(file);
+        self.last_error = None;
+    }
+
+    /// Record a failed upload
+    pub fn record_failure(&mut self, error: String, at: chrono::DateTime<chrono::Utc>) {
+        self.last_attempt = Some(at);
+        self.last_error = Some(error);
+    }
+}
+
+impl BackupDestinationConfig {
+    /// Short human-readable location, without credentials
+    pub fn display_location(&self) -> String {
+        match self {
+            BackupDestinationConfig::Sftp {
+                host,
+                port,
+                username,
+                remote_dir,
+                ..
+            } => {
+                let port = if *port == 22 {
+                    String::new()
+                } else {
+                    format!(":{}", port)
+                };
+                format!(
+                    "sftp://{}@{}{}/{}",
+                    username,
+                    host,
+                    port,
+                    remote_dir.trim_start_matches('/')
+                )
+            }
+            BackupDestinationConfig::WebDav { url, .. } => url.clone(),
+        }
+    }
+}
+
 impl Default for RepositoryManagementConfig {
     fn default() -> Self {
         Self {
@@ -240,6 +590,15 @@ impl Default for SecurityConfig {
             clear_clipboard_on_lock: true,
             max_auth_attempts: 5,
             lockout_duration: 300, // 5 minutes
+            unlock_backoff_secs: 1,
+            check_breaches: false,
+            os_keychain_unlock: false,
+            ssh_agent: false,
+            lock_windows: Vec::new(),
+            session: SessionPolicy::default(),
+            api_approval: ApprovalPolicy::default(),
+            auto_type: AutoTypeConfig::default(),
+            activity_log: true,
         }
     }
 }
@@ -386,6 +745,68 @@ mod tests {
         assert!(config.repository_settings.auto_detect);
         assert_eq!(config.repository_settings.max_recent, 10);
         assert!(config.repositories.is_empty());
+        assert!(!config.offsite_backup.enabled);
+        assert_eq!(config.offsite_backup.interval_days, 30);
+        assert!(config.notifications.auto_lock);
+        assert!(config.notifications.backup_failed);
+        assert!(config.notifications.breach_detected);
+        assert!(config.notifications.drafts_created);
+        assert!(!config.security.check_breaches);
+        assert!(!config.security.os_keychain_unlock);
+        assert!(!config.security.auto_type.randomize_cadence);
+        assert!(config.security.auto_type.verify_focus);
+    }
+
+    #[test]
+    fn test_auto_type_delay_range() {
+        let mut auto_type = AutoTypeConfig::default();
+        assert_eq!(auto_type.key_delay_range(), (30, 120));
+
+        auto_type.min_key_delay_ms = 5_000;
+        auto_type.max_key_delay_ms = 10;
+        assert_eq!(
+            auto_type.key_delay_range(),
+            (10, AutoTypeConfig::MAX_KEY_DELAY_MS)
+        );
+    }
+
+    #[test]
+    fn test_offsite_backup_schedule() {
+        let now = chrono::Utc::now();
+        let mut config = OffsiteBackupConfig::default();
+        assert!(!config.is_due(now));
+
+        config.enabled = true;
+        config.destination = Some(BackupDestinationConfig::WebDav {
+            url: "https://dav.example.com/backups/".to_string(),
+            username: "jane".to_string(),
+            password_credential: None,
+        });
+        assert!(config.is_due(now), "never uploaded");
+
+        config
+            .status
+            .record_success("a.7z".to_string(), now - chrono::Duration::days(10));
+        assert!(!config.is_due(now));
+        assert!(config.is_due(now + chrono::Duration::days(20)));
+
+        // A recent failure postpones the retry by an hour
+        let later = now + chrono::Duration::days(25);
+        config
+            .status
+            .record_failure("timeout".to_string(), later - chrono::Duration::minutes(5));
+        assert!(!config.is_due(later));
+        assert!(config.is_due(later + chrono::Duration::hours(1)));
+    }
+
+    #[test]
+    fn test_backup_destination_serialization() {
+        let yaml = "kind: sftp\nhost: backup.example.com\nusername: jane\nremote_dir: ziplock\n";
+        let destination: BackupDestinationConfig = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(
+            destination.display_location(),
+            "sftp://jane@backup.example.com/ziplock"
+        );
     }
 
     #[test]
@@ -18,7 +18,9 @@ pub mod repository_config;
 pub use app_config::*;
 pub use repository_config::*;
 
-use crate::core::{CoreError, CoreResult, FileOperationProvider};
+use crate::core::{
+    CoreError, CoreResult, FileOperationProvider, UnlockStatus, UnlockThrottlePolicy,
+};
 
 /// Configuration manager for desktop applications
 ///
@@ -143,6 +145,51 @@ impl<F: FileOperationProvider> ConfigManager<F> {
         }
     }
 
+    /// How unlocking the repository at `path` is throttled right now
+    pub fn unlock_status(&self, path: &str) -> UnlockStatus {
+        let attempts = self
+            .app_config
+            .unlock_attempts
+            .get(path)
+            .cloned()
+            .unwrap_or_default();
+        self.unlock_policy().status(&attempts, chrono::Utc::now())
+    }
+
+    /// Refuse to unlock the repository at `path` while it is throttled
+    pub fn check_unlock(&self, path: &str) -> CoreResult<()> {
+        match self.app_config.unlock_attempts.get(path) {
+            Some(attempts) => self.unlock_policy().check(attempts, chrono::Utc::now()),
+            None => Ok(()),
+        }
+    }
+
+    /// Record whether an unlock of the repository at `path` got the
+    /// password right, and save the configuration
+    ///
+    /// Saving right away keeps a restart from resetting the count.
+    pub fn record_unlock_attempt(&mut self, path: &str, succeeded: bool) -> CoreResult<()> {
+        let policy = self.unlock_policy();
+        let attempts = self
+            .app_config
+            .unlock_attempts
+            .entry(path.to_string())
+            .or_default();
+        if succeeded {
+            policy.record_success(attempts);
+        } else {
+            policy.record_failure(attempts, chrono::Utc::now());
+        }
+        if attempts.is_clear() {
+            self.app_config.unlock_attempts.remove(path);
+        }
+        self.save()
+    }
+
+    fn unlock_policy(&self) -> UnlockThrottlePolicy {
+        UnlockThrottlePolicy::from_config(&self.app_config.security)
+    }
+
     /// Get recent repositories sorted by last accessed (most recent first)
     pub fn get_recent_repositories(&self) -> Vec<&RepositoryInfo> {
         let mut repos: Vec<&RepositoryInfo> = self.app_config.repositories.iter().collect();
@@ -160,6 +207,9 @@ impl<F: FileOperationProvider> ConfigManager<F> {
 pub struct ConfigPaths;
 
 impl ConfigPaths {
+    /// Name of the profile used when none is chosen
+    pub const DEFAULT_PROFILE: &'static str = "default";
+
     /// Get the default application config directory for the current platform
     #[cfg(target_os = "linux")]
     pub fn app_config_dir() -> String {
@@ -200,6 +250,57 @@ impl ConfigPaths {
         format!("{}/config.yml", Self::app_config_dir())
     }
 
+    /// Default directory holding plugin libraries
+    pub fn plugins_dir() -> String {
+        format!("{}/plugins", Self::app_config_dir())
+    }
+
+    /// Directory holding the named profiles, each in a subdirectory
+    pub fn profiles_dir() -> String {
+        format!("{}/profiles", Self::app_config_dir())
+    }
+
+    /// Config directory of a profile
+    ///
+    /// `None` and [`DEFAULT_PROFILE`](Self::DEFAULT_PROFILE) are the app
+    /// config directory itself, so existing settings become the default
+    /// profile. The name must pass [`is_valid_profile_name`](Self::is_valid_profile_name).
+    pub fn profile_config_dir(profile: Option<&str>) -> String {
+        match profile {
+            None | Some(Self::DEFAULT_PROFILE) => Self::app_config_dir(),
+            Some(name) => format!("{}/{}", Self::profiles_dir(), name),
+        }
+    }
+
+    /// Config file of a profile, see [`profile_config_dir`](Self::profile_config_dir)
+    pub fn profile_config_file(profile: Option<&str>) -> String {
+        format!("{}/config.yml", Self::profile_config_dir(profile))
+    }
+
+    /// Whether `name` can be used as a profile name: 1 to 64 ASCII letters,
+    /// digits, `-` or `_`, so it is always a single path component
+    pub fn is_valid_profile_name(name: &str) -> bool {
+        (1..=64).contains(&name.len())
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    }
+
+    /// Named profiles that have been created, sorted, without the default
+    pub fn list_profiles() -> Vec<String> {
+        let Ok(entries) = std::fs::read_dir(Self::profiles_dir()) else {
+            return Vec::new();
+        };
+        let mut profiles: Vec<String> = entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().is_dir())
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .filter(|name| Self::is_valid_profile_name(name) && name != Self::DEFAULT_PROFILE)
+            .collect();
+        profiles.sort();
+        profiles
+    }
+
     /// Get the default repositories directory
     pub fn default_repositories_dir() -> String {
         #[cfg(target_os = "linux")]
@@ -262,6 +363,15 @@ impl ConfigValidator {
             errors.push("Clipboard timeout should not exceed 5 minutes for security".to_string());
         }
 
+        for window in
+            crate::core::AutoLockPolicy::from_config(&config.security, 0).invalid_windows()
+        {
+            errors.push(format!(
+                "Lock window '{}'-'{}' must use HH:MM times",
+                window.start, window.end
+            ));
+        }
+
         // Validate repository paths
         for repo in &config.repositories {
             if repo.path.is_empty() {
@@ -333,6 +443,30 @@ mod tests {
         assert_eq!(recent[0].name, "Test Repo 2"); // Most recently accessed first
     }
 
+    #[test]
+    fn test_unlock_attempts_are_tracked_per_repository() {
+        let provider = MockFileProvider::new();
+        let mut manager = ConfigManager::new(provider, "/test/config.yml".to_string());
+        manager.load().unwrap();
+        manager.config_mut().security.max_auth_attempts = 2;
+
+        manager.record_unlock_attempt("/a.7z", false).unwrap();
+        let status = manager.unlock_status("/a.7z");
+        assert_eq!(status.remaining_attempts, Some(1));
+        assert_eq!(status.retry_in_secs, 1);
+        assert!(matches!(
+            manager.check_unlock("/a.7z"),
+            Err(CoreError::UnlockThrottled { .. })
+        ));
+        assert!(manager.check_unlock("/b.7z").is_ok());
+
+        manager.record_unlock_attempt("/a.7z", false).unwrap();
+        assert!(manager.unlock_status("/a.7z").locked_out);
+
+        manager.record_unlock_attempt("/a.7z", true).unwrap();
+        assert!(manager.config().unlock_attempts.is_empty());
+    }
+
     #[test]
     fn test_config_validation() {
         let mut config = AppConfig::default();
@@ -370,4 +504,24 @@ mod tests {
         let repos_dir = ConfigPaths::default_repositories_dir();
         assert!(!repos_dir.is_empty());
     }
+
+    #[test]
+    fn test_profile_paths() {
+        assert_eq!(
+            ConfigPaths::profile_config_file(None),
+            ConfigPaths::app_config_file()
+        );
+        assert_eq!(
+            ConfigPaths::profile_config_dir(Some("default")),
+            ConfigPaths::app_config_dir()
+        );
+        let work = ConfigPaths::profile_config_file(Some("work"));
+        assert!(work.starts_with(&ConfigPaths::profiles_dir()));
+        assert!(work.ends_with("work/config.yml"));
+
+        assert!(ConfigPaths::is_valid_profile_name("work-2_a"));
+        for invalid in ["", "..", "a/b", "a b", "ü", &"x".repeat(65)] {
+            assert!(!ConfigPaths::is_valid_profile_name(invalid), "{invalid}");
+        }
+    }
 }
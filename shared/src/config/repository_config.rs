@@ -7,9 +7,14 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
-use crate::core::{CoreError, CoreResult};
+use crate::core::{
+    ArchiveOptions, CoreError, CoreResult, FileError, FileOperationProvider, RetentionQuotas,
+};
 use crate::models::{CredentialTemplate, FieldType};
 
+/// Suffix of the file next to an archive that holds its configuration
+pub const REPOSITORY_CONFIG_SUFFIX: &str = ".config.yml";
+
 /// Repository-specific configuration
 ///
 /// This configuration is typically stored within the repository itself
@@ -37,6 +42,14 @@ pub struct RepositoryConfig {
 
     /// Integration settings (cloud sync, etc.)
     pub integration: IntegrationConfig,
+
+    /// Compression and encryption settings for writing the archive
+    #[serde(default)]
+    pub archive: ArchiveOptions,
+
+    /// Limits on password history and the trash, enforced on save
+    #[serde(default)]
+    pub quotas: RetentionQuotas,
 }
 
 /// Repository metadata and identification
@@ -102,6 +115,13 @@ pub struct RepositorySecurity {
 
     /// Password history length (prevent reusing recent passwords)
     pub password_history_length: u32,
+
+    /// Whether opening the repository needs a key file besides the master password
+    ///
+    /// Set when a repository is created with a key file, and checked before
+    /// the archive is decrypted so a missing key file gets its own error.
+    #[serde(default)]
+    pub key_file_required: bool,
 }
 
 /// Validation configuration for repository data
@@ -461,6 +481,7 @@ impl Default for RepositorySecurity {
             iterations: 100000,
             enforce_password_policy: false,
             password_history_length: 5,
+            key_file_required: false,
         }
     }
 }
@@ -620,6 +641,10 @@ impl RepositoryConfig {
             });
         }
 
+        self.archive
+            .validate()
+            .map_err(|message| CoreError::ValidationError { message })?;
+
         // Validate TOTP settings
         if self.integration.two_factor.totp_settings.digits < 6
             || self.integration.two_factor.totp_settings.digits > 8
@@ -678,6 +703,46 @@ impl RepositoryConfig {
     pub fn get_template(&self, template_name: &str) -> Option<&CredentialTemplate> {
         self.templates.iter().find(|t| t.name == template_name)
     }
+
+    /// Path of the configuration file for the archive at `archive_path`
+    ///
+    /// The file is kept outside the archive, unencrypted, so settings such
+    /// as `key_file_required` can be read before the archive is opened. It
+    /// holds no secrets.
+    pub fn path_for(archive_path: &str) -> String {
+        format!("{}{}", archive_path, REPOSITORY_CONFIG_SUFFIX)
+    }
+
+    /// Read the configuration next to an archive; none if there is no file
+    pub fn load_for<F: FileOperationProvider>(
+        provider: &F,
+        archive_path: &str,
+    ) -> CoreResult<Option<Self>> {
+        match provider.read_archive(&Self::path_for(archive_path)) {
+            Ok(data) => {
+                serde_yaml::from_slice(&data)
+                    .map(Some)
+                    .map_err(|e| CoreError::SerializationError {
+                        message: format!("Invalid repository configuration: {}", e),
+                    })
+            }
+            Err(FileError::NotFound { .. }) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Write the configuration next to an archive
+    pub fn save_for<F: FileOperationProvider>(
+        &self,
+        provider: &F,
+        archive_path: &str,
+    ) -> CoreResult<()> {
+        let data = serde_yaml::to_string(self).map_err(|e| CoreError::SerializationError {
+            message: format!("Failed to serialize repository configuration: {}", e),
+        })?;
+        provider.write_archive(&Self::path_for(archive_path), data.as_bytes())?;
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -727,6 +792,13 @@ mod tests {
         // Low iterations should fail
         config.security.iterations = 1000;
         assert!(config.validate().is_err());
+
+        // Reset iterations
+        config.security.iterations = 100000;
+
+        // Compression level above 9 should fail
+        config.archive.compression_level = 12;
+        assert!(config.validate().is_err());
     }
 
     #[test]
@@ -771,6 +843,14 @@ mod tests {
             config.security.min_password_length,
             deserialized.security.min_password_length
         );
+
+        // Configurations saved before the archive section get the defaults
+        let mut value: serde_yaml::Value = serde_yaml::from_str(&yaml).unwrap();
+        value.as_mapping_mut().unwrap().remove("archive");
+        let older: RepositoryConfig = serde_yaml::from_value(value).unwrap();
+        assert_eq!(older.archive, ArchiveOptions::default());
+        let partial: ArchiveOptions = serde_yaml::from_str("solid: true").unwrap();
+        assert!(partial.solid && partial.encrypt_header);
     }
 
     #[test]
@@ -0,0 +1,381 @@
+//! Activity log kept inside the archive
+//!
+//! Every open, save and credential change made through a repository is
+//! appended to an [`ActivityLog`], stored in the archive as
+//! [`ACTIVITY_LOG_FILE`]. Each entry carries the SHA-256 of the one before
+//! it, so editing, reordering or removing an entry breaks the chain and
+//! [`ActivityLog::verify`] reports where. Entries name credentials by ID
+//! only, never by title or value.
+//!
+//! Pruning drops the oldest entries and keeps the hash of the last one
+//! dropped as the log's anchor, then appends a [`ActivityAction::Pruned`]
+//! entry so the gap shows up in the log itself.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::utils::encryption::EncryptionUtils;
+
+/// Directory of the activity log in the archive
+pub const AUDIT_DIR: &str = "audit";
+
+/// Archive entry holding the activity log
+pub const ACTIVITY_LOG_FILE: &str = "audit/activity.yml";
+
+/// Hash the first entry of a log that was never pruned refers to
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// What happened to the repository
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActivityAction {
+    Opened,
+    Saved,
+    CredentialCreated,
+    CredentialUpdated,
+    CredentialDeleted,
+    /// Older entries were removed from the log
+    Pruned,
+}
+
+impl ActivityAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ActivityAction::Opened => "opened",
+            ActivityAction::Saved => "saved",
+            ActivityAction::CredentialCreated => "credential_created",
+            ActivityAction::CredentialUpdated => "credential_updated",
+            ActivityAction::CredentialDeleted => "credential_deleted",
+            ActivityAction::Pruned => "pruned",
+        }
+    }
+}
+
+/// One line of the activity log
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ActivityEntry {
+    /// Position in the log, counting pruned entries
+    pub sequence: u64,
+
+    /// When it happened (Unix timestamp)
+    pub timestamp: i64,
+
+    /// Name of the device that made the change
+    pub device: String,
+
+    pub action: ActivityAction,
+
+    /// Credential the entry is about, for credential actions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub credential_id: Option<String>,
+
+    /// Number of entries removed, for [`ActivityAction::Pruned`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub removed: Option<u64>,
+
+    /// Hash of the previous entry, or the log's anchor for the first one
+    pub previous_hash: String,
+
+    /// SHA-256 of this entry's other fields, hex encoded
+    pub hash: String,
+}
+
+impl ActivityEntry {
+    fn compute_hash(&self) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(
+            format!(
+                "{}\n{}\n{}\n{}\n{}\n{}\n{}",
+                self.sequence,
+                self.timestamp,
+                self.device,
+                self.action.as_str(),
+                self.credential_id.as_deref().unwrap_or_default(),
+                self.removed.map(|n| n.to_string()).unwrap_or_default(),
+                self.previous_hash
+            )
+            .as_bytes(),
+        );
+        EncryptionUtils::to_hex(&hasher.finalize())
+    }
+}
+
+/// Where the chain of an activity log breaks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChainBreak {
+    /// Sequence number of the first entry that does not follow from the
+    /// ones before it
+    pub sequence: u64,
+}
+
+/// Filters for [`ActivityLog::query`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ActivityQuery {
+    /// Only entries at or after this time
+    pub since: Option<i64>,
+    /// Only entries before this time
+    pub until: Option<i64>,
+    pub action: Option<ActivityAction>,
+    pub credential_id: Option<String>,
+    pub device: Option<String>,
+    /// Only the newest `limit` matches
+    pub limit: Option<usize>,
+}
+
+impl ActivityQuery {
+    /// Every entry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_since(mut self, since: i64) -> Self {
+        self.since = Some(since);
+        self
+    }
+
+    pub fn with_until(mut self, until: i64) -> Self {
+        self.until = Some(until);
+        self
+    }
+
+    pub fn with_action(mut self, action: ActivityAction) -> Self {
+        self.action = Some(action);
+        self
+    }
+
+    pub fn with_credential_id<S: Into<String>>(mut self, id: S) -> Self {
+        self.credential_id = Some(id.into());
+        self
+    }
+
+    pub fn with_device<S: Into<String>>(mut self, device: S) -> Self {
+        self.device = Some(device.into());
+        self
+    }
+
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    fn matches(&self, entry: &ActivityEntry) -> bool {
+        self.since.is_none_or(|since| entry.timestamp >= since)
+            && self.until.is_none_or(|until| entry.timestamp < until)
+            && self.action.is_none_or(|action| entry.action == action)
+            && self
+                .credential_id
+                .as_ref()
+                .is_none_or(|id| entry.credential_id.as_ref() == Some(id))
+            && self
+                .device
+                .as_ref()
+                .is_none_or(|device| &entry.device == device)
+    }
+}
+
+/// Hash-chained list of repository activity, oldest first
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ActivityLog {
+    /// Hash of the last pruned entry, or [`GENESIS_HASH`]
+    pub anchor: String,
+
+    #[serde(default)]
+    pub entries: Vec<ActivityEntry>,
+}
+
+impl Default for ActivityLog {
+    fn default() -> Self {
+        Self {
+            anchor: GENESIS_HASH.to_string(),
+            entries: Vec::new(),
+        }
+    }
+}
+
+impl ActivityLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Append an entry for `action` and return it
+    pub fn record(
+        &mut self,
+        action: ActivityAction,
+        credential_id: Option<&str>,
+        device: &str,
+        timestamp: i64,
+    ) -> &ActivityEntry {
+        self.append(ActivityEntry {
+            sequence: 0,
+            timestamp,
+            device: device.to_string(),
+            action,
+            credential_id: credential_id.map(str::to_string),
+            removed: None,
+            previous_hash: String::new(),
+            hash: String::new(),
+        })
+    }
+
+    fn append(&mut self, mut entry: ActivityEntry) -> &ActivityEntry {
+        let (sequence, previous_hash) = match self.entries.last() {
+            Some(last) => (last.sequence + 1, last.hash.clone()),
+            None => (0, self.anchor.clone()),
+        };
+        entry.sequence = sequence;
+        entry.previous_hash = previous_hash;
+        entry.hash = entry.compute_hash();
+        self.entries.push(entry);
+        self.entries.last().expect("just pushed")
+    }
+
+    /// Check every entry's hash and link to the one before it
+    pub fn verify(&self) -> Result<(), ChainBreak> {
+        let mut previous = self.anchor.as_str();
+        let mut sequence = None;
+        for entry in &self.entries {
+            let in_order = sequence.is_none_or(|s: u64| entry.sequence == s + 1);
+            if !in_order || entry.previous_hash != previous || entry.hash != entry.compute_hash() {
+                return Err(ChainBreak {
+                    sequence: entry.sequence,
+                });
+            }
+            previous = &entry.hash;
+            sequence = Some(entry.sequence);
+        }
+        Ok(())
+    }
+
+    /// Entries matching `query`, oldest first
+    pub fn query(&self, query: &ActivityQuery) -> Vec<&ActivityEntry> {
+        let mut matches: Vec<&ActivityEntry> =
+            self.entries.iter().filter(|e| query.matches(e)).collect();
+        if let Some(limit) = query.limit {
+            matches.drain(..matches.len().saturating_sub(limit));
+        }
+        matches
+    }
+
+    /// Remove the entries before `before` and record that they were removed
+    ///
+    /// Returns the number of entries removed; nothing is recorded when there
+    /// was nothing to remove.
+    pub fn prune_before(&mut self, before: i64, device: &str, now: i64) -> usize {
+        let count = self
+            .entries
+            .iter()
+            .take_while(|entry| entry.timestamp < before)
+            .count();
+        if count == 0 {
+            return 0;
+        }
+
+        self.anchor = self.entries[count - 1].hash.clone();
+        let last_sequence = self.entries.last().map(|e| e.sequence);
+        self.entries.drain(..count);
+        let mut pruned = ActivityEntry {
+            sequence: 0,
+            timestamp: now,
+            device: device.to_string(),
+            action: ActivityAction::Pruned,
+            credential_id: None,
+            removed: Some(count as u64),
+            previous_hash: String::new(),
+            hash: String::new(),
+        };
+        if self.entries.is_empty() {
+            // Keep counting from where the removed entries left off
+            pruned.sequence = last_sequence.map_or(0, |s| s + 1);
+            pruned.previous_hash = self.anchor.clone();
+            pruned.hash = pruned.compute_hash();
+            self.entries.push(pruned);
+        } else {
+            self.append(pruned);
+        }
+        count
+    }
+}
+
+/// Name recorded for this device when the app does not set one
+pub fn default_device_name() -> String {
+    ["HOSTNAME", "COMPUTERNAME"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .chain(std::fs::read_to_string("/etc/hostname").ok())
+        .map(|name| name.trim().to_string())
+        .find(|name| !name.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log() -> ActivityLog {
+        let mut log = ActivityLog::new();
+        log.record(ActivityAction::Opened, None, "laptop", 100);
+        log.record(ActivityAction::CredentialCreated, Some("a"), "laptop", 110);
+        log.record(ActivityAction::CredentialUpdated, Some("a"), "phone", 200);
+        log.record(ActivityAction::Saved, None, "phone", 210);
+        log
+    }
+
+    #[test]
+    fn test_chain_detects_tampering() {
+        let log = log();
+        assert_eq!(log.verify(), Ok(()));
+        assert_eq!(log.entries[0].previous_hash, GENESIS_HASH);
+
+        let mut edited = log.clone();
+        edited.entries[1].credential_id = Some("b".to_string());
+        assert_eq!(edited.verify(), Err(ChainBreak { sequence: 1 }));
+
+        let mut removed = log.clone();
+        removed.entries.remove(2);
+        assert_eq!(removed.verify(), Err(ChainBreak { sequence: 3 }));
+
+        // Recomputing the hash of an edited entry breaks the next link
+        let mut rehashed = log;
+        rehashed.entries[0].device = "elsewhere".to_string();
+        rehashed.entries[0].hash = rehashed.entries[0].compute_hash();
+        assert_eq!(rehashed.verify(), Err(ChainBreak { sequence: 1 }));
+    }
+
+    #[test]
+    fn test_query() {
+        let log = log();
+        let by_credential = log.query(&ActivityQuery::new().with_credential_id("a"));
+        assert_eq!(by_credential.len(), 2);
+        let from_phone = log.query(&ActivityQuery::new().with_device("phone").with_limit(1));
+        assert_eq!(from_phone[0].action, ActivityAction::Saved);
+        let window = log.query(&ActivityQuery::new().with_since(110).with_until(210));
+        assert_eq!(window.len(), 2);
+    }
+
+    #[test]
+    fn test_prune_keeps_chain() {
+        let mut log = log();
+        assert_eq!(log.prune_before(50, "laptop", 300), 0);
+        assert_eq!(log.len(), 4);
+
+        assert_eq!(log.prune_before(200, "laptop", 300), 2);
+        assert_eq!(log.verify(), Ok(()));
+        let last = log.entries.last().unwrap();
+        assert_eq!(last.action, ActivityAction::Pruned);
+        assert_eq!(last.removed, Some(2));
+        assert_eq!(last.sequence, 4);
+
+        assert_eq!(log.prune_before(1_000, "laptop", 1_000), 3);
+        assert_eq!(log.verify(), Ok(()));
+        assert_eq!(log.len(), 1);
+        assert_eq!(log.entries[0].sequence, 5);
+    }
+}
@@ -0,0 +1,981 @@
+//! Container formats for repository archives
+//!
+//! An [`ArchiveBackend`] turns a file map into an encrypted container and
+//! back. [`DesktopFileProvider`](crate::core::DesktopFileProvider) uses one
+//! for extraction and creation, so another format (an age-encrypted tar, a
+//! SQLCipher file) only needs a new backend; the repository logic works on
+//! file maps either way. 7z is the default, and the name of the backend that
+//! wrote an archive is recorded in its metadata.
+
+use serde::{Deserialize, Serialize};
+use sevenz_rust2::encoder_options::{AesEncoderOptions, LZMA2Options};
+use sevenz_rust2::{
+    ArchiveEntry, ArchiveReader, ArchiveWriter, EncoderConfiguration, EncoderMethod, Password,
+    SourceReader,
+};
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Cursor;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::{Mutex, PoisonError};
+use tracing::{debug, error};
+use zeroize::Zeroizing;
+
+use crate::core::age_backend::{AgeTarBackend, AGE_TAR_BACKEND};
+use crate::core::duress::DuressArchive;
+use crate::core::errors::{FileError, FileResult};
+use crate::core::file_provider::{
+    write_file_atomically, DesktopFileProvider, FileOperationProvider,
+};
+use crate::core::seven_zip_update;
+use crate::core::types::{FileMap, METADATA_FILE};
+use crate::core::zip_backend::{ZipAesBackend, ZIP_BACKEND};
+use crate::utils::yaml::{deserialize_metadata, serialize_metadata};
+
+/// Name of the 7z backend, also assumed for archives that predate the field
+pub const SEVEN_ZIP_BACKEND: &str = "7z";
+
+/// Signature at the start of every 7z archive
+pub(crate) const SEVEN_ZIP_SIGNATURE: &[u8] = b"7z\xBC\xAF\x27\x1C";
+
+/// Highest compression level; 0 stores entries without compressing them
+pub const MAX_COMPRESSION_LEVEL: u32 = 9;
+
+/// Smallest LZMA2 dictionary size in bytes
+pub const MIN_DICTIONARY_SIZE: u32 = 4 * 1024;
+
+/// Largest LZMA2 dictionary size in bytes
+pub const MAX_DICTIONARY_SIZE: u32 = 1536 * 1024 * 1024;
+
+/// How archives are compressed and encrypted when they are written
+///
+/// The defaults match what every version before these options wrote. Lower
+/// levels and a smaller dictionary save large vaults much faster at the cost
+/// of a bigger file; attachments that are already compressed gain little
+/// from anything above level 1. Backends other than 7z may ignore any of
+/// these.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ArchiveOptions {
+    /// LZMA2 preset from 0 to [`MAX_COMPRESSION_LEVEL`]; 0 stores entries
+    /// uncompressed
+    pub compression_level: u32,
+
+    /// Compress all entries as one block, which packs many small entries
+    /// tighter but means reading any entry decompresses the ones before it
+    pub solid: bool,
+
+    /// LZMA2 dictionary size in bytes, or the level's own when `None`
+    pub dictionary_size: Option<u32>,
+
+    /// Encrypt the list of entry names as well as their contents
+    pub encrypt_header: bool,
+}
+
+impl Default for ArchiveOptions {
+    fn default() -> Self {
+        Self {
+            compression_level: 6,
+            solid: false,
+            dictionary_size: None,
+            encrypt_header: true,
+        }
+    }
+}
+
+impl ArchiveOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_compression_level(mut self, level: u32) -> Self {
+        self.compression_level = level;
+        self
+    }
+
+    pub fn with_solid(mut self, solid: bool) -> Self {
+        self.solid = solid;
+        self
+    }
+
+    pub fn with_dictionary_size(mut self, size: u32) -> Self {
+        self.dictionary_size = Some(size);
+        self
+    }
+
+    pub fn with_encrypt_header(mut self, encrypt_header: bool) -> Self {
+        self.encrypt_header = encrypt_header;
+        self
+    }
+
+    /// Check that the level and dictionary size are in range
+    pub fn validate(&self) -> Result<(), String> {
+        if self.compression_level > MAX_COMPRESSION_LEVEL {
+            return Err(format!(
+                "Compression level must be between 0 and {}",
+                MAX_COMPRESSION_LEVEL
+            ));
+        }
+        if let Some(size) = self.dictionary_size {
+            if !(MIN_DICTIONARY_SIZE..=MAX_DICTIONARY_SIZE).contains(&size) {
+                return Err(format!(
+                    "Dictionary size must be between {} KiB and {} MiB",
+                    MIN_DICTIONARY_SIZE / 1024,
+                    MAX_DICTIONARY_SIZE / (1024 * 1024)
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// The 7z method entries are compressed with
+    fn compression_method(&self) -> EncoderConfiguration {
+        if self.compression_level == 0 {
+            return EncoderMethod::COPY.into();
+        }
+        let mut lzma2 = LZMA2Options::from_level(self.compression_level.min(MAX_COMPRESSION_LEVEL));
+        if let Some(size) = self.dictionary_size {
+            lzma2.set_dictionary_size(size);
+        }
+        lzma2.into()
+    }
+}
+
+/// An encrypted container format for repository archives
+pub trait ArchiveBackend: Send + Sync {
+    /// Short name recorded in the repository metadata, e.g. `7z`
+    fn name(&self) -> &'static str;
+
+    /// Whether `data` looks like a container of this format
+    fn recognizes(&self, data: &[u8]) -> bool;
+
+    /// Decrypt and unpack a container into a file map
+    ///
+    /// A wrong password must be reported as [`FileError::InvalidPassword`].
+    fn extract(&self, data: &[u8], password: &str) -> FileResult<FileMap>;
+
+    /// Pack and encrypt a file map into a container
+    fn create(&self, files: FileMap, password: &str) -> FileResult<Vec<u8>>;
+
+    /// Pack and encrypt a file map with the given compression settings
+    ///
+    /// The default ignores `options`; formats with settings of their own
+    /// override it.
+    fn create_with_options(
+        &self,
+        files: FileMap,
+        password: &str,
+        _options: &ArchiveOptions,
+    ) -> FileResult<Vec<u8>> {
+        self.create(files, password)
+    }
+
+    /// Decrypt a container and hand its files to `visit` one at a time,
+    /// stopping early when it returns `false`
+    ///
+    /// The default extracts everything first; formats that can decrypt entry
+    /// by entry override it so that only the current file is in memory.
+    fn for_each_file(
+        &self,
+        data: &[u8],
+        password: &str,
+        visit: &mut dyn FnMut(&str, &[u8]) -> bool,
+    ) -> FileResult<()> {
+        for (path, content) in self.extract(data, password)? {
+            if !visit(&path, &content) {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Apply changed and removed entries to an existing container
+    ///
+    /// The default unpacks, patches and repacks the whole container; formats
+    /// that can change single entries override it.
+    fn update(
+        &self,
+        data: &[u8],
+        password: &str,
+        changed: &FileMap,
+        removed: &[String],
+    ) -> FileResult<Vec<u8>> {
+        repack(self, data, password, changed, removed)
+    }
+
+    /// Apply changed and removed entries to the container file at `path`
+    ///
+    /// The default reads the file, calls [`ArchiveBackend::update`] and
+    /// writes the result back through a temporary file; formats that can
+    /// patch a file in place override it so that only the changed parts
+    /// reach the disk.
+    fn update_file(
+        &self,
+        path: &Path,
+        password: &str,
+        changed: &FileMap,
+        removed: &[String],
+    ) -> FileResult<()> {
+        let data = std::fs::read(path)?;
+        let updated = self.update(&data, password, changed, removed)?;
+        write_file_atomically(&path.to_string_lossy(), &updated)
+    }
+}
+
+/// Unpack, patch and repack a whole container
+fn repack<B: ArchiveBackend + ?Sized>(
+    backend: &B,
+    data: &[u8],
+    password: &str,
+    changed: &FileMap,
+    removed: &[String],
+) -> FileResult<Vec<u8>> {
+    let mut files = backend.extract(data, password)?;
+    for path in removed {
+        files.remove(path);
+    }
+    files.extend(
+        changed
+            .iter()
+            .map(|(path, content)| (path.clone(), content.clone())),
+    );
+    backend.create(files, password)
+}
+
+/// Re-pack an archive in another backend's format, e.g. to export a SQLCipher
+/// database as 7z
+///
+/// The repository metadata inside is updated to name the new backend.
+pub fn convert_archive(
+    data: &[u8],
+    password: &str,
+    from: &dyn ArchiveBackend,
+    to: &dyn ArchiveBackend,
+) -> FileResult<Vec<u8>> {
+    let mut files = from.extract(data, password)?;
+    if let Some(yaml) = files.get_mut(METADATA_FILE) {
+        let mut metadata = std::str::from_utf8(yaml)
+            .ok()
+            .and_then(|yaml| deserialize_metadata(yaml).ok())
+            .ok_or_else(|| FileError::CorruptedArchive {
+                message: "Invalid repository metadata".to_string(),
+            })?;
+        metadata.archive_backend = to.name().to_string();
+        *yaml = serialize_metadata(&metadata)
+            .map_err(|e| FileError::CreationFailed {
+                message: e.to_string(),
+            })?
+            .into_bytes();
+    }
+    to.create(files, password)
+}
+
+/// Convert the repository archive at `path` to `target` in place
+///
+/// The current format is detected from the file. The converted archive is
+/// read back and checked before it replaces the original, which is kept as
+/// `<path>.bak` like on any other save. Returns the format the archive was
+/// in; nothing is written if that is already `target`.
+///
+/// Of a 7z archive, the part `password` opens is converted; converting to
+/// another format drops the [hidden section](crate::core::duress), and with
+/// it any duress password.
+pub fn convert_repository(
+    path: &str,
+    password: &str,
+    target: ArchiveFormat,
+) -> FileResult<ArchiveFormat> {
+    let provider = DesktopFileProvider::new();
+    let data = provider.read_archive(path)?;
+    let source = ArchiveFormat::detect(&data).ok_or_else(unknown_format)?;
+    if source == target {
+        return Ok(source);
+    }
+
+    let to = target.backend();
+    let part = match DuressArchive::from_bytes(&data) {
+        Ok(container) => container.part(password).0.into_owned(),
+        Err(_) => data,
+    };
+    let converted = convert_archive(&part, password, source.backend().as_ref(), to.as_ref())?;
+    let (converted, _) = DuressArchive::with_archive(None, converted, password)?;
+    let verify = |written: &[u8]| {
+        let files = to.extract(written, password)?;
+        if !files.contains_key(METADATA_FILE) {
+            return Err(FileError::VerificationFailed {
+                message: "Converted archive has no repository metadata".to_string(),
+            });
+        }
+        Ok(())
+    };
+    provider.write_archive_verified(path, &converted, &verify)?;
+    Ok(source)
+}
+
+/// Container formats ZipLock can read and write
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ArchiveFormat {
+    /// AES-256 encrypted 7z, the default
+    #[default]
+    SevenZip,
+    /// ZIP with WinZip AES-256 encrypted entries
+    Zip,
+    /// Gzip-compressed tar encrypted with age
+    AgeTar,
+}
+
+impl ArchiveFormat {
+    pub const ALL: [ArchiveFormat; 3] = [
+        ArchiveFormat::SevenZip,
+        ArchiveFormat::Zip,
+        ArchiveFormat::AgeTar,
+    ];
+
+    /// Backend name, as recorded in the repository metadata
+    pub fn name(&self) -> &'static str {
+        match self {
+            ArchiveFormat::SevenZip => SEVEN_ZIP_BACKEND,
+            ArchiveFormat::Zip => ZIP_BACKEND,
+            ArchiveFormat::AgeTar => AGE_TAR_BACKEND,
+        }
+    }
+
+    /// Usual file extension, without the leading dot
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ArchiveFormat::SevenZip => "7z",
+            ArchiveFormat::Zip => "zip",
+            ArchiveFormat::AgeTar => "tar.gz.age",
+        }
+    }
+
+    /// The format of `data`, judged by its signature
+    pub fn detect(data: &[u8]) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|format| format.backend().recognizes(data))
+    }
+
+    /// A backend for this format with its default settings
+    pub fn backend(&self) -> Box<dyn ArchiveBackend> {
+        match self {
+            ArchiveFormat::SevenZip => Box::new(SevenZipBackend),
+            ArchiveFormat::Zip => Box::new(ZipAesBackend),
+            ArchiveFormat::AgeTar => Box::new(AgeTarBackend::new()),
+        }
+    }
+}
+
+impl fmt::Display for ArchiveFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for ArchiveFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|format| format.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                format!(
+                    "Unknown archive format '{}'; expected one of 7z, zip, age-tar",
+                    s
+                )
+            })
+    }
+}
+
+fn unknown_format() -> FileError {
+    FileError::CorruptedArchive {
+        message: "Not an archive in a supported format".to_string(),
+    }
+}
+
+/// Opens archives in any [`ArchiveFormat`] and saves them in the format
+/// they were opened in
+///
+/// The format is detected from each archive's signature. Archives are
+/// written in the format of the last one extracted, or the default format
+/// until one is, so a ZIP vault stays a ZIP vault across saves while new
+/// vaults are 7z unless another default is given.
+#[derive(Debug, Default)]
+pub struct MultiFormatBackend {
+    default: ArchiveFormat,
+    age: AgeTarBackend,
+    current: Mutex<Option<ArchiveFormat>>,
+}
+
+impl MultiFormatBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Write new archives in `format`
+    pub fn with_format(format: ArchiveFormat) -> Self {
+        Self {
+            default: format,
+            ..Self::default()
+        }
+    }
+
+    /// Use `age` for age-encrypted tarballs, e.g. to change the work factor
+    pub fn with_age_backend(mut self, age: AgeTarBackend) -> Self {
+        self.age = age;
+        self
+    }
+
+    /// The format archives are written in
+    pub fn format(&self) -> ArchiveFormat {
+        self.current
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .unwrap_or(self.default)
+    }
+
+    fn backend(&self, format: ArchiveFormat) -> &dyn ArchiveBackend {
+        match format {
+            ArchiveFormat::SevenZip => &SevenZipBackend,
+            ArchiveFormat::Zip => &ZipAesBackend,
+            ArchiveFormat::AgeTar => &self.age,
+        }
+    }
+
+    /// The format of `data`, which is written from now on
+    fn detect(&self, data: &[u8]) -> FileResult<ArchiveFormat> {
+        let format = ArchiveFormat::detect(data).ok_or_else(unknown_format)?;
+        *self.current.lock().unwrap_or_else(PoisonError::into_inner) = Some(format);
+        Ok(format)
+    }
+}
+
+impl ArchiveBackend for MultiFormatBackend {
+    fn name(&self) -> &'static str {
+        self.format().name()
+    }
+
+    fn recognizes(&self, data: &[u8]) -> bool {
+        ArchiveFormat::detect(data).is_some()
+    }
+
+    fn extract(&self, data: &[u8], password: &str) -> FileResult<FileMap> {
+        let format = self.detect(data)?;
+        self.backend(format).extract(data, password)
+    }
+
+    fn for_each_file(
+        &self,
+        data: &[u8],
+        password: &str,
+        visit: &mut dyn FnMut(&str, &[u8]) -> bool,
+    ) -> FileResult<()> {
+        let format = self.detect(data)?;
+        self.backend(format).for_each_file(data, password, visit)
+    }
+
+    fn create(&self, files: FileMap, password: &str) -> FileResult<Vec<u8>> {
+        self.backend(self.format()).create(files, password)
+    }
+
+    fn create_with_options(
+        &self,
+        files: FileMap,
+        password: &str,
+        options: &ArchiveOptions,
+    ) -> FileResult<Vec<u8>> {
+        self.backend(self.format())
+            .create_with_options(files, password, options)
+    }
+
+    fn update(
+        &self,
+        data: &[u8],
+        password: &str,
+        changed: &FileMap,
+        removed: &[String],
+    ) -> FileResult<Vec<u8>> {
+        let format = self.detect(data)?;
+        self.backend(format)
+            .update(data, password, changed, removed)
+    }
+}
+
+/// AES-256 encrypted 7z archives via sevenz-rust2
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SevenZipBackend;
+
+impl ArchiveBackend for SevenZipBackend {
+    fn name(&self) -> &'static str {
+        SEVEN_ZIP_BACKEND
+    }
+
+    fn recognizes(&self, data: &[u8]) -> bool {
+        data.starts_with(SEVEN_ZIP_SIGNATURE)
+    }
+
+    /// Decrypts in memory; no plaintext is written to disk
+    fn extract(&self, data: &[u8], password: &str) -> FileResult<FileMap> {
+        debug!("Starting archive extraction: {} bytes", data.len());
+
+        let mut file_map = HashMap::new();
+        self.for_each_file(data, password, &mut |path, content| {
+            file_map.insert(path.to_string(), content.to_vec());
+            true
+        })?;
+        debug!(
+            "Successfully extracted {} files from archive",
+            file_map.len()
+        );
+        Ok(file_map)
+    }
+
+    fn for_each_file(
+        &self,
+        data: &[u8],
+        password: &str,
+        visit: &mut dyn FnMut(&str, &[u8]) -> bool,
+    ) -> FileResult<()> {
+        let result = ArchiveReader::new(Cursor::new(data), Password::from(password)).and_then(
+            |mut reader| {
+                reader.for_each_entries(|entry, content| {
+                    if entry.is_directory {
+                        return Ok(true);
+                    }
+                    let mut buffer = Zeroizing::new(Vec::with_capacity(entry.size as usize));
+                    content.read_to_end(&mut buffer)?;
+                    // Archives written on Windows by older versions use backslashes
+                    Ok(visit(&entry.name.replace('\\', "/"), &buffer))
+                })
+            },
+        );
+
+        result.map_err(|e| {
+            error!("Archive extraction failed: {}", e);
+
+            // Check for password-related errors
+            let error_str = e.to_string().to_lowercase();
+            if error_str.contains("password")
+                || error_str.contains("wrong")
+                || error_str.contains("decrypt")
+            {
+                FileError::InvalidPassword
+            } else {
+                FileError::ExtractionFailed {
+                    message: format!("Failed to extract 7z archive: {}", e),
+                }
+            }
+        })
+    }
+
+    fn create(&self, files: FileMap, password: &str) -> FileResult<Vec<u8>> {
+        self.create_with_options(files, password, &ArchiveOptions::default())
+    }
+
+    /// Compresses and encrypts in memory; no plaintext is written to disk
+    fn create_with_options(
+        &self,
+        files: FileMap,
+        password: &str,
+        options: &ArchiveOptions,
+    ) -> FileResult<Vec<u8>> {
+        debug!(
+            "Creating archive from {} files with {:?}",
+            files.len(),
+            options
+        );
+
+        let creation_failed = |e: sevenz_rust2::Error| {
+            error!("Archive creation failed: {}", e);
+            FileError::CreationFailed {
+                message: format!("Failed to create 7z archive: {}", e),
+            }
+        };
+
+        let mut writer = ArchiveWriter::new(Cursor::new(Vec::new())).map_err(creation_failed)?;
+        let mut methods = Vec::with_capacity(2);
+        if !password.is_empty() {
+            methods.push(AesEncoderOptions::new(Password::from(password)).into());
+        }
+        methods.push(options.compression_method());
+        writer.set_content_methods(methods);
+        writer.set_encrypt_header(options.encrypt_header);
+
+        // Sorted so the same repository always produces the same entry order
+        let mut paths: Vec<&String> = files.keys().collect();
+        paths.sort();
+        if options.solid {
+            // Empty entries have no data to put in the block
+            let (empty, full): (Vec<&String>, Vec<&String>) =
+                paths.into_iter().partition(|path| files[*path].is_empty());
+            for path in empty {
+                writer
+                    .push_archive_entry::<&[u8]>(ArchiveEntry::new_file(path), None)
+                    .map_err(creation_failed)?;
+            }
+            if !full.is_empty() {
+                let entries = full
+                    .iter()
+                    .map(|path| ArchiveEntry::new_file(path))
+                    .collect();
+                let readers = full
+                    .iter()
+                    .map(|path| SourceReader::from(files[*path].as_slice()))
+                    .collect();
+                writer
+                    .push_archive_entries(entries, readers)
+                    .map_err(creation_failed)?;
+            }
+        } else {
+            for path in paths {
+                writer
+                    .push_archive_entry(ArchiveEntry::new_file(path), Some(files[path].as_slice()))
+                    .map_err(creation_failed)?;
+            }
+        }
+
+        let archive_data = writer
+            .finish()
+            .map_err(|e| creation_failed(e.into()))?
+            .into_inner();
+        debug!("Archive created: {} bytes", archive_data.len());
+        Ok(archive_data)
+    }
+
+    /// Copies the packed streams of unchanged entries and encrypts only the
+    /// changed ones, unless the archive is solid or otherwise can't be
+    /// patched, in which case it is repacked. Bytes after the end of the
+    /// archive, such as a [hidden section](crate::core::duress), are kept.
+    fn update(
+        &self,
+        data: &[u8],
+        password: &str,
+        changed: &FileMap,
+        removed: &[String],
+    ) -> FileResult<Vec<u8>> {
+        let mut updated = match seven_zip_update::update(data, password, changed, removed) {
+            Some(updated) => {
+                debug!(
+                    "Updated archive in place: {} changed, {} removed",
+                    changed.len(),
+                    removed.len()
+                );
+                updated
+            }
+            None => repack(self, data, password, changed, removed)?,
+        };
+        if let Some(len) = seven_zip_update::archive_len(data) {
+            updated.extend_from_slice(&data[len..]);
+        }
+        Ok(updated)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{DesktopFileProvider, UnifiedRepositoryManager};
+    use crate::models::CredentialRecord;
+
+    #[test]
+    fn test_seven_zip_backend_round_trip() {
+        let backend = SevenZipBackend;
+        let mut files = FileMap::new();
+        files.insert("metadata.yml".to_string(), b"version: 1.0".to_vec());
+        files.insert(
+            "credentials/abc/record.yml".to_string(),
+            b"id: abc".to_vec(),
+        );
+
+        let archive = backend.create(files.clone(), "password").unwrap();
+        assert!(backend.recognizes(&archive));
+        assert!(!backend.recognizes(b"PK\x03\x04"));
+        assert_eq!(backend.extract(&archive, "password").unwrap(), files);
+        assert!(matches!(
+            backend.extract(&archive, "wrong"),
+            Err(FileError::InvalidPassword)
+        ));
+    }
+
+    #[test]
+    fn test_seven_zip_backend_archive_options() {
+        let backend = SevenZipBackend;
+        let mut files = FileMap::new();
+        files.insert("metadata.yml".to_string(), b"version: 1.0".to_vec());
+        files.insert("attachments/empty".to_string(), Vec::new());
+        files.insert("attachments/notes.txt".to_string(), b"notes ".repeat(4096));
+
+        let default = backend.create(files.clone(), "password").unwrap();
+        let stored = backend
+            .create_with_options(
+                files.clone(),
+                "password",
+                &ArchiveOptions::new().with_compression_level(0),
+            )
+            .unwrap();
+        assert!(stored.len() > default.len());
+
+        let solid = ArchiveOptions::new()
+            .with_solid(true)
+            .with_compression_level(1)
+            .with_dictionary_size(64 * 1024)
+            .with_encrypt_header(false);
+        let archive = backend
+            .create_with_options(files.clone(), "password", &solid)
+            .unwrap();
+        assert_eq!(backend.extract(&archive, "password").unwrap(), files);
+        assert!(backend.extract(&archive, "wrong").is_err());
+        // Entry names are readable without the password
+        let reader = ArchiveReader::new(Cursor::new(&archive), Password::empty()).unwrap();
+        assert_eq!(reader.archive().files.len(), 3);
+        assert!(ArchiveReader::new(Cursor::new(&default), Password::empty()).is_err());
+
+        assert!(ArchiveOptions::new()
+            .with_compression_level(10)
+            .validate()
+            .is_err());
+        assert!(ArchiveOptions::new()
+            .with_dictionary_size(1)
+            .validate()
+            .is_err());
+        assert!(solid.validate().is_ok());
+    }
+
+    #[test]
+    fn test_seven_zip_backend_reads_directory_archives() {
+        // Earlier versions packed a directory written to disk
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("source");
+        std::fs::create_dir_all(source.join("credentials").join("abc")).unwrap();
+        std::fs::write(source.join("metadata.yml"), b"version: 1.0").unwrap();
+        std::fs::write(
+            source.join("credentials").join("abc").join("record.yml"),
+            b"id: abc",
+        )
+        .unwrap();
+        let archive_path = dir.path().join("old.7z");
+        sevenz_rust2::compress_to_path_encrypted(&source, &archive_path, "password".into())
+            .unwrap();
+
+        let files = SevenZipBackend
+            .extract(&std::fs::read(&archive_path).unwrap(), "password")
+            .unwrap();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files["metadata.yml"], b"version: 1.0");
+        assert_eq!(files["credentials/abc/record.yml"], b"id: abc");
+    }
+
+    /// Unencrypted JSON container that only checks the password
+    struct JsonBackend;
+
+    impl ArchiveBackend for JsonBackend {
+        fn name(&self) -> &'static str {
+            "json"
+        }
+
+        fn recognizes(&self, data: &[u8]) -> bool {
+            data.starts_with(b"[")
+        }
+
+        fn extract(&self, data: &[u8], password: &str) -> FileResult<FileMap> {
+            let (stored, files): (String, FileMap) =
+                serde_json::from_slice(data).map_err(|e| FileError::CorruptedArchive {
+                    message: e.to_string(),
+                })?;
+            if stored != password {
+                return Err(FileError::InvalidPassword);
+            }
+            Ok(files)
+        }
+
+        fn create(&self, files: FileMap, password: &str) -> FileResult<Vec<u8>> {
+            serde_json::to_vec(&(password, files)).map_err(|e| FileError::CreationFailed {
+                message: e.to_string(),
+            })
+        }
+    }
+
+    #[test]
+    fn test_repository_saved_with_other_backend() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vault.json");
+        let path = path.to_str().unwrap();
+
+        let mut manager =
+            UnifiedRepositoryManager::new(DesktopFileProvider::with_backend(JsonBackend));
+        manager.create_repository(path, "password").unwrap();
+        manager.close_repository(false).unwrap();
+        assert!(JsonBackend.recognizes(&std::fs::read(path).unwrap()));
+
+        manager.open_repository(path, "password").unwrap();
+        assert_eq!(
+            manager.memory_repository().get_metadata().archive_backend,
+            "json"
+        );
+
+        let mut seven_zip = UnifiedRepositoryManager::new(DesktopFileProvider::new());
+        seven_zip
+            .create_repository(&format!("{}.7z", path), "password")
+            .unwrap();
+        assert_eq!(
+            seven_zip.memory_repository().get_metadata().archive_backend,
+            SEVEN_ZIP_BACKEND
+        );
+    }
+
+    #[test]
+    fn test_formats_are_detected_and_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vault.zip");
+        let path = path.to_str().unwrap();
+        let mut manager = UnifiedRepositoryManager::new(DesktopFileProvider::with_backend(
+            MultiFormatBackend::with_format(ArchiveFormat::Zip),
+        ));
+        manager.create_repository(path, "password").unwrap();
+        manager.close_repository(false).unwrap();
+        let format = |path: &str| ArchiveFormat::detect(&std::fs::read(path).unwrap());
+        assert_eq!(format(path), Some(ArchiveFormat::Zip));
+
+        // A provider that writes 7z by default keeps the vault a ZIP file
+        let mut manager = UnifiedRepositoryManager::new(DesktopFileProvider::new());
+        manager.open_repository(path, "password").unwrap();
+        manager
+            .add_credential(CredentialRecord::new(
+                "Router".to_string(),
+                "login".to_string(),
+            ))
+            .unwrap();
+        manager.save_repository().unwrap();
+        manager.close_repository(false).unwrap();
+        assert_eq!(format(path), Some(ArchiveFormat::Zip));
+
+        assert_eq!(
+            convert_repository(path, "password", ArchiveFormat::SevenZip).unwrap(),
+            ArchiveFormat::Zip
+        );
+        assert_eq!(format(path), Some(ArchiveFormat::SevenZip));
+        assert_eq!(format(&format!("{}.bak", path)), Some(ArchiveFormat::Zip));
+        assert!(convert_repository(path, "wrong", ArchiveFormat::Zip).is_err());
+
+        manager.open_repository(path, "password").unwrap();
+        assert_eq!(manager.list_credentials().unwrap().len(), 1);
+        assert_eq!(
+            manager.memory_repository().get_metadata().archive_backend,
+            SEVEN_ZIP_BACKEND
+        );
+        assert_eq!("age-tar".parse(), Ok(ArchiveFormat::AgeTar));
+        assert!("rar".parse::<ArchiveFormat>().is_err());
+    }
+
+    /// Changed and removed paths of one update
+    type Update = (Vec<String>, Vec<String>);
+
+    /// JSON container that records the entries each update touches
+    #[derive(Default, Clone)]
+    struct RecordingBackend {
+        updates: std::sync::Arc<std::sync::Mutex<Vec<Update>>>,
+    }
+
+    impl ArchiveBackend for RecordingBackend {
+        fn name(&self) -> &'static str {
+            "json"
+        }
+
+        fn recognizes(&self, data: &[u8]) -> bool {
+            JsonBackend.recognizes(data)
+        }
+
+        fn extract(&self, data: &[u8], password: &str) -> FileResult<FileMap> {
+            JsonBackend.extract(data, password)
+        }
+
+        fn create(&self, files: FileMap, password: &str) -> FileResult<Vec<u8>> {
+            JsonBackend.create(files, password)
+        }
+
+        fn update(
+            &self,
+            data: &[u8],
+            password: &str,
+            changed: &FileMap,
+            removed: &[String],
+        ) -> FileResult<Vec<u8>> {
+            let mut paths: Vec<String> = changed.keys().cloned().collect();
+            paths.sort();
+            self.updates.lock().unwrap().push((paths, removed.to_vec()));
+            JsonBackend.update(data, password, changed, removed)
+        }
+    }
+
+    #[test]
+    fn test_incremental_save_writes_only_changed_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vault.json");
+        let path = path.to_str().unwrap();
+        let backend = RecordingBackend::default();
+        let mut manager =
+            UnifiedRepositoryManager::new(DesktopFileProvider::with_backend(backend.clone()));
+        manager.set_incremental_saves(true);
+        manager.create_repository(path, "password").unwrap();
+        assert!(backend.updates.lock().unwrap().is_empty());
+
+        let first = CredentialRecord::new("First".to_string(), "login".to_string());
+        let second = CredentialRecord::new("Second".to_string(), "login".to_string());
+        let (first_id, second_id) = (first.id.clone(), second.id.clone());
+        manager.add_credential(first).unwrap();
+        manager.add_credential(second).unwrap();
+        manager.save_repository().unwrap();
+
+        let mut first = manager.get_credential_readonly(&first_id).unwrap().clone();
+        first.notes = Some("changed".to_string());
+        manager.update_credential(first).unwrap();
+        manager.delete_credential(&second_id).unwrap();
+        manager.save_repository().unwrap();
+
+        let updates = backend.updates.lock().unwrap().clone();
+        assert_eq!(updates.len(), 2);
+        let first_file = format!("credentials/{}/record.yml", first_id);
+        let second_file = format!("credentials/{}/record.yml", second_id);
+        assert!(updates[0].0.contains(&first_file) && updates[0].0.contains(&second_file));
+        assert!(updates[1].0.contains(&first_file));
+        assert!(!updates[1].0.contains(&second_file));
+        assert_eq!(updates[1].1, vec![second_file]);
+
+        // The patched archive opens with the same contents
+        manager.close_repository(false).unwrap();
+        manager.open_repository(path, "password").unwrap();
+        let credentials = manager.list_credentials().unwrap();
+        assert_eq!(credentials.len(), 1);
+        assert_eq!(credentials[0].notes.as_deref(), Some("changed"));
+
+        // Saving elsewhere writes a complete archive
+        let copy = format!("{}.copy", path);
+        manager.save_repository_to_path(&copy, "password").unwrap();
+        assert_eq!(backend.updates.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_convert_archive_to_7z() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vault.json");
+        let path = path.to_str().unwrap();
+        let mut manager =
+            UnifiedRepositoryManager::new(DesktopFileProvider::with_backend(JsonBackend));
+        manager.create_repository(path, "password").unwrap();
+
+        let converted = convert_archive(
+            &std::fs::read(path).unwrap(),
+            "password",
+            &JsonBackend,
+            &SevenZipBackend,
+        )
+        .unwrap();
+        assert!(SevenZipBackend.recognizes(&converted));
+        let files = SevenZipBackend.extract(&converted, "password").unwrap();
+        let metadata =
+            deserialize_metadata(std::str::from_utf8(&files[METADATA_FILE]).unwrap()).unwrap();
+        assert_eq!(metadata.archive_backend, SEVEN_ZIP_BACKEND);
+    }
+}
@@ -0,0 +1,325 @@
+//! Non-blocking repository manager
+//!
+//! [`AsyncRepositoryManager`] has the same lifecycle as
+//! [`UnifiedRepositoryManager`](crate::core::UnifiedRepositoryManager) but
+//! awaits an [`AsyncFileOperationProvider`] for reading, writing, extracting
+//! and compressing archives, so UI threads stay responsive. Credential
+//! operations are in-memory and go through
+//! [`AsyncRepositoryManager::memory_repository_mut`].
+//!
+//! Any blocking provider can be used by wrapping it in
+//! [`BlockingFileProvider`](crate::core::BlockingFileProvider).
+
+use zeroize::Zeroizing;
+
+use crate::core::duress::{DuressArchive, OpenedArchive};
+use crate::core::errors::{CoreError, CoreResult, FileResult};
+use crate::core::file_provider::AsyncFileOperationProvider;
+use crate::core::integrity::{self, IntegrityIssue};
+use crate::core::memory_repository::UnifiedMemoryRepository;
+use crate::core::progress::{self, ArchivePhase, ProgressObserver};
+use crate::core::types::FileMap;
+
+/// Repository manager whose file operations are asynchronous
+pub struct AsyncRepositoryManager<P: AsyncFileOperationProvider> {
+    /// Pure memory repository for credential operations
+    memory_repo: UnifiedMemoryRepository,
+
+    /// Asynchronous file operation provider
+    file_provider: P,
+
+    /// Current archive file path (if any)
+    current_path: Option<String>,
+
+    /// Current archive password (kept in memory for save operations)
+    master_password: Option<Zeroizing<String>>,
+
+    /// Whether a repository is currently open
+    is_open: bool,
+
+    /// Receives progress of open and save operations
+    progress_observer: Option<Box<dyn ProgressObserver>>,
+
+    /// Issues fixed when the repository was opened
+    repair_log: Vec<IntegrityIssue>,
+
+    /// The 7z archive and hidden section of the open file, and which of the
+    /// two is open; see [`crate::core::duress`]
+    container: Option<OpenedArchive>,
+}
+
+impl<P: AsyncFileOperationProvider> AsyncRepositoryManager<P> {
+    /// Create a new repository manager with the given file provider
+    pub fn new(file_provider: P) -> Self {
+        Self {
+            memory_repo: UnifiedMemoryRepository::new(),
+            file_provider,
+            current_path: None,
+            master_password: None,
+            is_open: false,
+            progress_observer: None,
+            repair_log: Vec::new(),
+            container: None,
+        }
+    }
+
+    /// Report progress of opening and saving to `observer`, or stop
+    /// reporting with `None`
+    pub fn set_progress_observer(&mut self, observer: Option<Box<dyn ProgressObserver>>) {
+        self.progress_observer = observer;
+    }
+
+    /// Create a new, empty repository at `path` and save it
+    pub async fn create_repository(&mut self, path: &str, master_password: &str) -> CoreResult<()> {
+        if self.is_open {
+            return Err(CoreError::AlreadyInitialized);
+        }
+
+        self.memory_repo = UnifiedMemoryRepository::new();
+        self.memory_repo.initialize()?;
+        self.current_path = Some(path.to_string());
+        self.master_password = Some(Zeroizing::new(master_password.to_string()));
+        self.container = None;
+        self.is_open = true;
+
+        self.save_repository().await
+    }
+
+    /// Open an existing repository from `path`
+    pub async fn open_repository(&mut self, path: &str, master_password: &str) -> CoreResult<()> {
+        if self.is_open {
+            return Err(CoreError::AlreadyInitialized);
+        }
+
+        self.report_progress(ArchivePhase::Reading);
+        let archive_data = self.file_provider.read_archive(path).await?;
+        self.report_progress(ArchivePhase::Decrypting);
+        let (file_map, container) = self.extract_file_map(archive_data, master_password).await?;
+
+        self.report_progress(ArchivePhase::Loading);
+        let (file_map, repairs) = integrity::repair_benign(file_map)?;
+        let mut memory_repo = UnifiedMemoryRepository::new();
+        memory_repo.load_from_files(file_map)?;
+        if !repairs.is_empty() {
+            memory_repo.mark_modified();
+        }
+        self.report_progress(ArchivePhase::Complete);
+        self.memory_repo = memory_repo;
+        self.repair_log = repairs;
+        self.current_path = Some(path.to_string());
+        self.master_password = Some(Zeroizing::new(master_password.to_string()));
+        self.container = container;
+        self.is_open = true;
+
+        Ok(())
+    }
+
+    /// Save the repository to its current path
+    pub async fn save_repository(&mut self) -> CoreResult<()> {
+        if !self.is_open {
+            return Err(CoreError::NotInitialized);
+        }
+
+        let path = self
+            .current_path
+            .clone()
+            .ok_or_else(|| CoreError::StructureError {
+                message: "No current path set for repository".to_string(),
+            })?;
+        let password = self
+            .master_password
+            .clone()
+            .ok_or_else(|| CoreError::StructureError {
+                message: "No master password set for repository".to_string(),
+            })?;
+
+        self.save_repository_to_path(&path, &password).await
+    }
+
+    /// Save the repository to `path`, which becomes the current path
+    pub async fn save_repository_to_path(
+        &mut self,
+        path: &str,
+        master_password: &str,
+    ) -> CoreResult<()> {
+        if !self.is_open {
+            return Err(CoreError::NotInitialized);
+        }
+
+        self.report_progress(ArchivePhase::Serializing);
+        self.memory_repo
+            .set_archive_backend(self.file_provider.archive_backend());
+        let file_map = self.memory_repo.serialize_to_files()?;
+        self.report_progress(ArchivePhase::Encrypting);
+        let archive = self
+            .file_provider
+            .create_archive(file_map, master_password.to_string())
+            .await?;
+        let (archive_data, container) =
+            DuressArchive::with_archive(self.container.as_ref(), archive, master_password)?;
+        self.report_progress(ArchivePhase::Writing);
+        self.file_provider.write_archive(path, archive_data).await?;
+        self.container = container;
+        self.report_progress(ArchivePhase::Complete);
+
+        self.memory_repo.mark_saved();
+        if self.current_path.as_deref() != Some(path) {
+            self.current_path = Some(path.to_string());
+        }
+        if self.master_password.as_deref().map(String::as_str) != Some(master_password) {
+            self.master_password = Some(Zeroizing::new(master_password.to_string()));
+        }
+
+        Ok(())
+    }
+
+    /// Close the current repository, saving first if asked and modified
+    pub async fn close_repository(&mut self, save_if_modified: bool) -> CoreResult<()> {
+        if !self.is_open {
+            return Ok(());
+        }
+
+        if save_if_modified && self.memory_repo.is_modified() {
+            self.save_repository().await?;
+        }
+
+        self.master_password = None;
+        self.container = None;
+        self.memory_repo.wipe();
+        self.repair_log.clear();
+        self.is_open = false;
+        self.current_path = None;
+
+        Ok(())
+    }
+
+    /// Whether a repository is open
+    pub fn is_open(&self) -> bool {
+        self.is_open
+    }
+
+    /// Whether the open repository has unsaved changes
+    pub fn is_modified(&self) -> bool {
+        self.is_open && self.memory_repo.is_modified()
+    }
+
+    /// Issues fixed when the repository was opened, as
+    /// [`UnifiedRepositoryManager::repair_log`](crate::core::UnifiedRepositoryManager::repair_log)
+    pub fn repair_log(&self) -> &[IntegrityIssue] {
+        &self.repair_log
+    }
+
+    /// Path of the open repository
+    pub fn current_path(&self) -> Option<&str> {
+        self.current_path.as_deref()
+    }
+
+    /// The in-memory repository, for reading credentials
+    pub fn memory_repository(&self) -> &UnifiedMemoryRepository {
+        &self.memory_repo
+    }
+
+    /// The in-memory repository, for changing credentials
+    pub fn memory_repository_mut(&mut self) -> &mut UnifiedMemoryRepository {
+        &mut self.memory_repo
+    }
+
+    fn report_progress(&self, phase: ArchivePhase) {
+        progress::report(self.progress_observer.as_deref(), phase);
+    }
+
+    /// Unpack archive bytes, opening whichever part of a 7z archive and its
+    /// hidden section `password` opens
+    async fn extract_file_map(
+        &self,
+        data: Vec<u8>,
+        password: &str,
+    ) -> FileResult<(FileMap, Option<OpenedArchive>)> {
+        if !DuressArchive::recognizes(&data) {
+            let file_map = self
+                .file_provider
+                .extract_archive(data, password.to_string())
+                .await?;
+            return Ok((file_map, None));
+        }
+        let container = DuressArchive::from_bytes(&data)?;
+        let (archive, slot) = container.part(password);
+        let file_map = self
+            .file_provider
+            .extract_archive(archive.into_owned(), password.to_string())
+            .await?;
+        Ok((file_map, Some((container, slot))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::file_provider::{BlockingFileProvider, DesktopFileProvider};
+    use crate::core::UnifiedRepositoryManager;
+    use crate::models::CredentialRecord;
+
+    #[tokio::test]
+    async fn test_async_create_save_and_open() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("async.7z");
+        let path = path.to_str().unwrap();
+        let provider = BlockingFileProvider::new(DesktopFileProvider::new());
+
+        let mut manager = AsyncRepositoryManager::new(provider.clone());
+        manager.create_repository(path, "password").await.unwrap();
+        manager
+            .memory_repository_mut()
+            .add_credential(CredentialRecord::new(
+                "GitHub".to_string(),
+                "login".to_string(),
+            ))
+            .unwrap();
+        assert!(manager.is_modified());
+        manager.close_repository(true).await.unwrap();
+        assert!(!manager.is_open());
+
+        let mut reopened = AsyncRepositoryManager::new(provider);
+        assert!(reopened.open_repository(path, "wrong").await.is_err());
+        reopened.open_repository(path, "password").await.unwrap();
+        let credentials = reopened.memory_repository().list_credentials().unwrap();
+        assert_eq!(credentials.len(), 1);
+        assert_eq!(credentials[0].title, "GitHub");
+        assert_eq!(reopened.current_path(), Some(path));
+    }
+
+    #[tokio::test]
+    async fn test_async_save_keeps_the_hidden_section() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vault.7z");
+        let path = path.to_str().unwrap();
+        let mut manager = UnifiedRepositoryManager::new(DesktopFileProvider::new());
+        manager.create_repository(path, "master").unwrap();
+        manager
+            .set_duress_password(
+                "duress",
+                vec![CredentialRecord::new(
+                    "Forum".to_string(),
+                    "login".to_string(),
+                )],
+            )
+            .unwrap();
+        manager.close_repository(false).unwrap();
+
+        let mut decoy =
+            AsyncRepositoryManager::new(BlockingFileProvider::new(DesktopFileProvider::new()));
+        decoy.open_repository(path, "duress").await.unwrap();
+        decoy
+            .memory_repository_mut()
+            .add_credential(CredentialRecord::new(
+                "Mail".to_string(),
+                "login".to_string(),
+            ))
+            .unwrap();
+        decoy.close_repository(true).await.unwrap();
+
+        manager.open_repository(path, "master").unwrap();
+        assert!(manager.has_duress_password());
+        assert!(manager.list_credentials().unwrap().is_empty());
+    }
+}
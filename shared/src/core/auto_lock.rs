@@ -0,0 +1,277 @@
+//! Auto-lock policy shared by all platforms
+//!
+//! Apps keep track of user activity and ask [`AutoLockPolicy::evaluate`]
+//! whether the vault should lock. Besides the plain inactivity timeout the
+//! policy applies the scheduled [`LockWindow`]s from `SecurityConfig`, so the
+//! same "hard-lock outside business hours" rule holds on desktop and mobile.
+
+use chrono::{Datelike, NaiveDateTime, NaiveTime};
+use serde::{Deserialize, Serialize};
+
+use crate::config::{LockWindow, SecurityConfig};
+
+/// Why the vault should lock
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum LockReason {
+    /// No activity for longer than the effective timeout
+    Idle,
+    /// A hard-lock window is active
+    Scheduled { label: Option<String> },
+}
+
+impl LockReason {
+    /// Message suitable for a toast or lock screen
+    pub fn describe(&self) -> String {
+        match self {
+            LockReason::Idle => "Locked after a period of inactivity".to_string(),
+            LockReason::Scheduled { label: Some(label) } => {
+                format!("Locked by schedule: {}", label)
+            }
+            LockReason::Scheduled { label: None } => {
+                "Locked by schedule until the lock window ends".to_string()
+            }
+        }
+    }
+}
+
+/// Result of evaluating the policy
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "decision", rename_all = "snake_case")]
+pub enum LockDecision {
+    /// Stay unlocked; `lock_in_secs` is the time left before the idle
+    /// timeout, if one applies
+    Unlocked { lock_in_secs: Option<u64> },
+    Lock {
+        #[serde(flatten)]
+        reason: LockReason,
+    },
+}
+
+impl LockDecision {
+    pub fn should_lock(&self) -> bool {
+        matches!(self, LockDecision::Lock { .. })
+    }
+}
+
+/// Inactivity timeout plus scheduled lock windows
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutoLockPolicy {
+    /// Inactivity timeout outside any window (0 = disabled)
+    pub idle_timeout_secs: u64,
+    pub windows: Vec<LockWindow>,
+}
+
+impl AutoLockPolicy {
+    pub fn new(idle_timeout_secs: u64, windows: Vec<LockWindow>) -> Self {
+        Self {
+            idle_timeout_secs,
+            windows,
+        }
+    }
+
+    /// Build a policy from the security settings and the app's idle timeout
+    pub fn from_config(security: &SecurityConfig, idle_timeout_secs: u64) -> Self {
+        Self::new(idle_timeout_secs, security.lock_windows.clone())
+    }
+
+    /// Whether the policy can ever lock; apps can skip their timer when not
+    pub fn is_active(&self) -> bool {
+        self.idle_timeout_secs > 0 || !self.windows.is_empty()
+    }
+
+    /// The hard-lock window active at local time `now`, if any
+    pub fn hard_lock_window(&self, now: NaiveDateTime) -> Option<&LockWindow> {
+        self.windows
+            .iter()
+            .find(|window| window.idle_timeout_secs.is_none() && window_contains(window, now))
+    }
+
+    /// Whether the vault may be unlocked at local time `now`
+    pub fn unlock_allowed(&self, now: NaiveDateTime) -> Result<(), LockReason> {
+        match self.hard_lock_window(now) {
+            Some(window) => Err(LockReason::Scheduled {
+                label: window.label.clone(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Decide whether to lock after `idle` without activity at local time `now`
+    pub fn evaluate(&self, idle: std::time::Duration, now: NaiveDateTime) -> LockDecision {
+        if let Err(reason) = self.unlock_allowed(now) {
+            return LockDecision::Lock { reason };
+        }
+
+        let timeout = self
+            .windows
+            .iter()
+            .filter(|window| window_contains(window, now))
+            .filter_map(|window| window.idle_timeout_secs)
+            .chain((self.idle_timeout_secs > 0).then_some(self.idle_timeout_secs))
+            .min();
+
+        match timeout {
+            Some(timeout) if idle.as_secs() >= timeout => LockDecision::Lock {
+                reason: LockReason::Idle,
+            },
+            Some(timeout) => LockDecision::Unlocked {
+                lock_in_secs: Some(timeout - idle.as_secs()),
+            },
+            None => LockDecision::Unlocked { lock_in_secs: None },
+        }
+    }
+
+    /// Windows whose times cannot be parsed; these never match
+    pub fn invalid_windows(&self) -> Vec<&LockWindow> {
+        self.windows
+            .iter()
+            .filter(|window| {
+                parse_time(&window.start).is_none() || parse_time(&window.end).is_none()
+            })
+            .collect()
+    }
+}
+
+/// Parse a local `HH:MM` (or `HH:MM:SS`) time
+fn parse_time(value: &str) -> Option<NaiveTime> {
+    let value = value.trim();
+    NaiveTime::parse_from_str(value, "%H:%M")
+        .or_else(|_| NaiveTime::parse_from_str(value, "%H:%M:%S"))
+        .ok()
+}
+
+fn window_contains(window: &LockWindow, now: NaiveDateTime) -> bool {
+    let (Some(start), Some(end)) = (parse_time(&window.start), parse_time(&window.end)) else {
+        return false;
+    };
+    let starts_on =
+        |date: chrono::NaiveDate| window.days.is_empty() || window.days.contains(&date.weekday());
+
+    let time = now.time();
+    let today = now.date();
+    if start < end {
+        return starts_on(today) && time >= start && time < end;
+    }
+
+    // Runs past midnight (or a full day when start == end): either it
+    // started today, or it started yesterday and has not ended yet
+    (starts_on(today) && time >= start) || (today.pred_opt().is_some_and(starts_on) && time < end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Weekday;
+    use std::time::Duration as StdDuration;
+
+    fn at(date: &str, time: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(&format!("{} {}", date, time), "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    fn window(start: &str, end: &str) -> LockWindow {
+        LockWindow {
+            label: Some("After hours".to_string()),
+            days: Vec::new(),
+            start: start.to_string(),
+            end: end.to_string(),
+            idle_timeout_secs: None,
+        }
+    }
+
+    #[test]
+    fn test_idle_timeout_only() {
+        let policy = AutoLockPolicy::new(300, Vec::new());
+        let now = at("2024-06-03", "10:00");
+
+        assert_eq!(
+            policy.evaluate(StdDuration::from_secs(60), now),
+            LockDecision::Unlocked {
+                lock_in_secs: Some(240)
+            }
+        );
+        assert_eq!(
+            policy.evaluate(StdDuration::from_secs(300), now),
+            LockDecision::Lock {
+                reason: LockReason::Idle
+            }
+        );
+        assert!(!AutoLockPolicy::default()
+            .evaluate(StdDuration::from_secs(10_000), now)
+            .should_lock());
+    }
+
+    #[test]
+    fn test_hard_lock_outside_business_hours() {
+        // 2024-06-03 is a Monday
+        let policy = AutoLockPolicy::new(0, vec![window("18:00", "08:00")]);
+
+        assert!(policy.unlock_allowed(at("2024-06-03", "12:00")).is_ok());
+        assert!(policy.unlock_allowed(at("2024-06-03", "08:00")).is_ok());
+        assert!(policy.unlock_allowed(at("2024-06-03", "07:59")).is_err());
+        assert_eq!(
+            policy.evaluate(StdDuration::ZERO, at("2024-06-03", "18:00")),
+            LockDecision::Lock {
+                reason: LockReason::Scheduled {
+                    label: Some("After hours".to_string())
+                }
+            }
+        );
+    }
+
+    #[test]
+    fn test_weekday_filter_and_full_day_windows() {
+        let mut weekend = window("00:00", "00:00");
+        weekend.days = vec![Weekday::Sat, Weekday::Sun];
+        let policy = AutoLockPolicy::new(0, vec![weekend]);
+
+        assert!(policy.unlock_allowed(at("2024-06-07", "23:59")).is_ok()); // Friday
+        assert!(policy.unlock_allowed(at("2024-06-08", "00:00")).is_err()); // Saturday
+        assert!(policy.unlock_allowed(at("2024-06-09", "23:59")).is_err()); // Sunday
+        assert!(policy.unlock_allowed(at("2024-06-10", "00:00")).is_ok()); // Monday
+    }
+
+    #[test]
+    fn test_idle_timeout_windows_shorten_the_timeout() {
+        let mut strict = window("08:00", "18:00");
+        strict.idle_timeout_secs = Some(60);
+        let policy = AutoLockPolicy::new(900, vec![strict]);
+
+        assert!(policy
+            .evaluate(StdDuration::from_secs(90), at("2024-06-03", "09:00"))
+            .should_lock());
+        assert!(!policy
+            .evaluate(StdDuration::from_secs(90), at("2024-06-03", "19:00"))
+            .should_lock());
+        // Idle-timeout windows never block unlocking
+        assert!(policy.unlock_allowed(at("2024-06-03", "09:00")).is_ok());
+    }
+
+    #[test]
+    fn test_config_round_trip_and_invalid_times() {
+        let yaml = r#"
+lock_windows:
+  - label: Weekend
+    days: [Sat, Sun]
+    start: "00:00"
+    end: "00:00"
+  - start: "8am"
+    end: "18:00"
+    idle_timeout_secs: 120
+"#;
+        let security: SecurityConfig = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(security.lock_windows.len(), 2);
+        assert_eq!(
+            security.lock_windows[0].days,
+            vec![Weekday::Sat, Weekday::Sun]
+        );
+
+        let policy = AutoLockPolicy::from_config(&security, 0);
+        assert!(policy.is_active());
+        assert_eq!(policy.invalid_windows().len(), 1);
+        assert!(!policy
+            .evaluate(StdDuration::from_secs(600), at("2024-06-03", "09:00"))
+            .should_lock());
+    }
+}
@@ -0,0 +1,300 @@
+//! Archive-format conformance fixtures and the runner that checks them
+//!
+//! `shared/tests/fixtures/conformance` holds vault archives covering the
+//! format's features (key files, protected fields, templates, folders) and
+//! older layouts, together with a `manifest.json` giving each archive's
+//! password and the credentials it must yield. [`verify_compatibility`]
+//! opens every fixture, compares what it reads with the manifest, saves it
+//! again and checks that the files written are the same bytes as the ones
+//! read. Another implementation can use the manifest to test its reader,
+//! and [`Fixture::verify_read`] to test archives its writer produced.
+//!
+//! Archives themselves are never compared: 7z encryption draws a random IV,
+//! so only the files inside are expected to match.
+//!
+//! Fixtures are added, never regenerated; an archive that stops opening is
+//! a broken vault for whoever still has one like it.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::archive_backend::{ArchiveBackend, SevenZipBackend};
+use crate::core::errors::{CoreError, CoreResult};
+use crate::core::key_file::KeyFile;
+use crate::core::memory_repository::UnifiedMemoryRepository;
+use crate::core::types::FileMap;
+use crate::models::CredentialRecord;
+
+/// Name of the fixture list in a conformance directory
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// Every fixture in a conformance directory
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FixtureManifest {
+    pub fixtures: Vec<Fixture>,
+}
+
+/// One archive and what it must contain
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Fixture {
+    /// Archive file name, relative to the manifest
+    pub file: String,
+    pub description: String,
+    /// Version of the library that produced the archive
+    pub generated_by: String,
+    /// `ARCHIVE_FORMAT_VERSION` of the layout inside
+    pub archive_format_version: String,
+    pub password: String,
+    /// Key file contents in hex, for archives that require one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_file: Option<String>,
+    /// Whether saving the vault again must write the same bytes; false for
+    /// older layouts, which are upgraded on save
+    pub canonical: bool,
+    /// Protected field values, opened with their own passphrase
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub protected_fields: Vec<ProtectedFieldCheck>,
+    /// Templates the vault defines, by name
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub templates: Vec<String>,
+    /// The credentials, sorted by ID, as they read after loading
+    pub credentials: Vec<CredentialRecord>,
+}
+
+/// A protected field and the value its passphrase must reveal
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProtectedFieldCheck {
+    pub credential_id: String,
+    pub field: String,
+    pub passphrase: String,
+    pub value: String,
+}
+
+/// Outcome for one fixture
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FixtureResult {
+    pub file: String,
+    /// Empty when the fixture passed
+    pub failures: Vec<String>,
+}
+
+impl FixtureResult {
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/// Outcome for a whole conformance directory
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompatibilityReport {
+    pub results: Vec<FixtureResult>,
+}
+
+impl CompatibilityReport {
+    pub fn passed(&self) -> bool {
+        self.results.iter().all(FixtureResult::passed)
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = &FixtureResult> {
+        self.results.iter().filter(|result| !result.passed())
+    }
+}
+
+/// Read, check and rewrite every fixture listed in `dir`'s manifest
+pub fn verify_compatibility(dir: impl AsRef<Path>) -> CoreResult<CompatibilityReport> {
+    let dir = dir.as_ref();
+    let manifest = read_manifest(dir)?;
+    let results = manifest
+        .fixtures
+        .iter()
+        .map(|fixture| {
+            let failures = match std::fs::read(dir.join(&fixture.file)) {
+                Ok(archive) => fixture.verify(&archive),
+                Err(e) => vec![format!("cannot read archive: {}", e)],
+            };
+            FixtureResult {
+                file: fixture.file.clone(),
+                failures,
+            }
+        })
+        .collect();
+    Ok(CompatibilityReport { results })
+}
+
+/// The manifest of a conformance directory
+pub fn read_manifest(dir: impl AsRef<Path>) -> CoreResult<FixtureManifest> {
+    let path = dir.as_ref().join(MANIFEST_FILE);
+    let json = std::fs::read_to_string(&path).map_err(|e| CoreError::StructureError {
+        message: format!("Cannot read {}: {}", path.display(), e),
+    })?;
+    serde_json::from_str(&json).map_err(|e| CoreError::SerializationError {
+        message: format!("Invalid conformance manifest: {}", e),
+    })
+}
+
+impl Fixture {
+    /// The password the archive is encrypted with, combined with the key
+    /// file if there is one
+    pub fn archive_password(&self) -> CoreResult<String> {
+        match &self.key_file {
+            Some(hex) => {
+                let contents = decode_hex(hex).ok_or_else(|| CoreError::ValidationError {
+                    message: format!("Key file of {} is not hex", self.file),
+                })?;
+                Ok(KeyFile::from_bytes(&contents)?.composite_password(&self.password))
+            }
+            None => Ok(self.password.clone()),
+        }
+    }
+
+    /// Failures reading `archive` as this fixture, then writing it back
+    pub fn verify(&self, archive: &[u8]) -> Vec<String> {
+        let (files, repository, mut failures) = match self.read(archive) {
+            Ok(read) => read,
+            Err(failure) => return vec![failure],
+        };
+        failures.extend(self.verify_write(&files, &repository));
+        failures
+    }
+
+    /// Failures reading `archive` as this fixture
+    ///
+    /// `archive` need not be the fixture file itself: pass what another
+    /// implementation wrote after reading the fixture to check that its
+    /// writer kept every credential intact.
+    pub fn verify_read(&self, archive: &[u8]) -> Vec<String> {
+        match self.read(archive) {
+            Ok((_, _, failures)) => failures,
+            Err(failure) => vec![failure],
+        }
+    }
+
+    fn read(
+        &self,
+        archive: &[u8],
+    ) -> Result<(FileMap, UnifiedMemoryRepository, Vec<String>), String> {
+        let password = self.archive_password().map_err(|e| e.to_string())?;
+        let files = SevenZipBackend
+            .extract(archive, &password)
+            .map_err(|e| format!("archive does not open: {}", e))?;
+        let mut repository = UnifiedMemoryRepository::new();
+        repository
+            .load_from_files(files.clone())
+            .map_err(|e| format!("vault does not load: {}", e))?;
+
+        let mut failures = compare_credentials(&self.credentials, &repository);
+        for check in &self.protected_fields {
+            match repository.unlock_field(&check.credential_id, &check.field, &check.passphrase) {
+                Ok(value) if value.as_str() == check.value => {}
+                Ok(_) => failures.push(format!(
+                    "protected field {}/{} opens to the wrong value",
+                    check.credential_id, check.field
+                )),
+                Err(e) => failures.push(format!(
+                    "protected field {}/{} does not open: {}",
+                    check.credential_id, check.field, e
+                )),
+            }
+        }
+        let templates: BTreeSet<String> = repository
+            .list_templates()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|template| template.name)
+            .collect();
+        if templates != self.templates.iter().cloned().collect() {
+            failures.push(format!(
+                "templates are {:?}, expected {:?}",
+                templates, self.templates
+            ));
+        }
+        Ok((files, repository, failures))
+    }
+
+    fn verify_write(&self, read: &FileMap, repository: &UnifiedMemoryRepository) -> Vec<String> {
+        let written = match repository.serialize_to_files() {
+            Ok(written) => written,
+            Err(e) => return vec![format!("vault does not serialize: {}", e)],
+        };
+        let mut failures = Vec::new();
+        if self.canonical {
+            failures.extend(compare_files(read, &written));
+        } else {
+            let mut reloaded = UnifiedMemoryRepository::new();
+            match reloaded.load_from_files(written.clone()) {
+                Ok(()) => failures.extend(
+                    compare_credentials(&self.credentials, &reloaded)
+                        .into_iter()
+                        .map(|failure| format!("after upgrade, {}", failure)),
+                ),
+                Err(e) => failures.push(format!("upgraded vault does not load: {}", e)),
+            }
+        }
+
+        let password = match self.archive_password() {
+            Ok(password) => password,
+            Err(e) => return vec![e.to_string()],
+        };
+        let round_trip = SevenZipBackend
+            .create(written.clone(), &password)
+            .and_then(|archive| SevenZipBackend.extract(&archive, &password));
+        match round_trip {
+            Ok(files) if files == written => {}
+            Ok(_) => failures.push("rewritten archive holds different files".to_string()),
+            Err(e) => failures.push(format!("rewritten archive does not open: {}", e)),
+        }
+        failures
+    }
+}
+
+fn compare_credentials(
+    expected: &[CredentialRecord],
+    repository: &UnifiedMemoryRepository,
+) -> Vec<String> {
+    let loaded: BTreeMap<String, CredentialRecord> = match repository.list_credentials() {
+        Ok(credentials) => credentials
+            .into_iter()
+            .map(|credential| (credential.id.clone(), credential))
+            .collect(),
+        Err(e) => return vec![format!("credentials cannot be listed: {}", e)],
+    };
+    let mut failures = Vec::new();
+    for credential in expected {
+        match loaded.get(&credential.id) {
+            Some(found) if found == credential => {}
+            Some(_) => failures.push(format!("credential {} differs", credential.id)),
+            None => failures.push(format!("credential {} is missing", credential.id)),
+        }
+    }
+    for id in loaded.keys() {
+        if !expected.iter().any(|credential| &credential.id == id) {
+            failures.push(format!("unexpected credential {}", id));
+        }
+    }
+    failures
+}
+
+fn compare_files(read: &FileMap, written: &FileMap) -> Vec<String> {
+    let paths: BTreeSet<&String> = read.keys().chain(written.keys()).collect();
+    paths
+        .into_iter()
+        .filter_map(|path| match (read.get(path), written.get(path)) {
+            (Some(a), Some(b)) if a == b => None,
+            (Some(_), Some(_)) => Some(format!("{} is written differently", path)),
+            (Some(_), None) => Some(format!("{} is dropped on save", path)),
+            (None, _) => Some(format!("{} is added on save", path)),
+        })
+        .collect()
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
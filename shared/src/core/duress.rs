@@ -0,0 +1,435 @@
+//! Hidden credential sets for duress passwords
+//!
+//! A repository can have a duress password besides its master password.
+//! Entering it opens a separate, innocuous set of credentials instead of the
+//! real ones. Every 7z archive the repository manager writes is followed by
+//! a hidden section, which 7z readers ignore because it comes after the end
+//! of the archive:
+//!
+//! ```text
+//! 7z archive || salt (16 bytes) || AES-256-CTR(key, length (u64 LE) || 7z archive || padding)
+//!
+//! key = EncryptionUtils::derive_key(password, salt)
+//! ```
+//!
+//! Without a duress password the hidden section is random bytes. With one,
+//! the archive at the front holds the decoy credentials, encrypted with the
+//! duress password, and the hidden section holds the real ones, encrypted
+//! with the master password. Either way the file is a 7z archive that opens
+//! with the password given up, followed by 16 to 128 KiB of random-looking
+//! bytes. Opening derives the hidden section's key for every password and
+//! then extracts exactly one 7z archive: the hidden one if the password
+//! decrypts the section, the one at the front otherwise. Every unlock
+//! therefore runs the same two key derivations, which take most of the
+//! time, whether the password is the master password, the duress password
+//! or a wrong one.
+//!
+//! For the same reason nothing tells a session opened with the duress
+//! password that the hidden section holds anything, so a new duress
+//! password is only set over a hidden section known to be free: one this
+//! process filled with random bytes, or none at all.
+//!
+//! The hidden section is padded to a random size in the same range as the
+//! random bytes, so real credentials that pack to more than
+//! [`MAX_HIDDEN_LEN`] make it stand out. Someone holding several versions of
+//! the file can also see which of the two parts changed between them.
+
+use std::borrow::Cow;
+
+use aes::Aes256;
+use ctr::cipher::{KeyIvInit, StreamCipher};
+use rand::rngs::OsRng;
+use rand::{Rng, RngCore};
+
+use crate::core::errors::{FileError, FileResult};
+use crate::core::seven_zip_update::archive_len;
+use crate::core::types::FileMap;
+use crate::utils::encryption::EncryptionUtils;
+
+/// Size of the hidden section's salt in bytes
+const SALT_LEN: usize = 16;
+
+/// Size of the length in front of the hidden archive
+const LENGTH_LEN: usize = 8;
+
+/// Smallest hidden section written
+pub const MIN_HIDDEN_LEN: usize = 16 * 1024;
+
+/// Largest hidden section written, unless the hidden archive needs more
+pub const MAX_HIDDEN_LEN: usize = 128 * 1024;
+
+type Aes256Ctr = ctr::Ctr128BE<Aes256>;
+
+/// A 7z archive and the hidden section after it
+#[derive(Clone, PartialEq, Eq)]
+pub struct DuressArchive {
+    archive: Vec<u8>,
+    hidden: Vec<u8>,
+    /// Whether the hidden section is known to hold no archive
+    hidden_free: bool,
+}
+
+impl std::fmt::Debug for DuressArchive {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DuressArchive")
+            .field("archive_size", &self.archive.len())
+            .field("hidden_size", &self.hidden.len())
+            .finish()
+    }
+}
+
+/// A [`DuressArchive`] and the part of it that is open
+pub type OpenedArchive = (DuressArchive, SectionSlot);
+
+/// Which part of a [`DuressArchive`] a password opened
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SectionSlot {
+    /// The 7z archive at the front; the only one without a duress password
+    Archive,
+    /// The hidden section, which holds the real credentials of a repository
+    /// with a duress password
+    Hidden,
+}
+
+impl DuressArchive {
+    /// Whether `data` can carry a hidden section, which only 7z archives do
+    pub fn recognizes(data: &[u8]) -> bool {
+        archive_len(data).is_some()
+    }
+
+    /// `archive` followed by random bytes in place of a hidden section
+    pub fn new(archive: &[u8]) -> FileResult<Self> {
+        let mut duress = Self::from_bytes(archive)?;
+        duress.clear_hidden();
+        Ok(duress)
+    }
+
+    /// Split a 7z archive from the hidden section after it
+    ///
+    /// An archive written without one gets an empty hidden section.
+    pub fn from_bytes(data: &[u8]) -> FileResult<Self> {
+        let len = archive_len(data).ok_or_else(|| FileError::CorruptedArchive {
+            message: "Not a 7z archive".to_string(),
+        })?;
+        Ok(Self {
+            archive: data[..len].to_vec(),
+            hidden: data[len..].to_vec(),
+            hidden_free: len == data.len(),
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.archive.len() + self.hidden.len());
+        bytes.extend_from_slice(&self.archive);
+        bytes.extend_from_slice(&self.hidden);
+        bytes
+    }
+
+    /// The only 7z archive `password` may open: the hidden section's if
+    /// `password` decrypts it, otherwise the one at the front
+    ///
+    /// The hidden section's key is derived every time and only one archive
+    /// is returned, so opening it costs the same whichever part the password
+    /// belongs to.
+    pub fn part(&self, password: &str) -> (Cow<'_, [u8]>, SectionSlot) {
+        match self.unseal(password) {
+            Some(hidden) => (Cow::Owned(hidden), SectionSlot::Hidden),
+            None => (Cow::Borrowed(&self.archive[..]), SectionSlot::Archive),
+        }
+    }
+
+    /// Decrypt the part `password` opens, with `extract` unpacking a 7z
+    /// archive given its password
+    ///
+    /// `extract` is called once, on [`DuressArchive::part`]; a password that
+    /// opens neither part fails with its error, such as
+    /// [`FileError::InvalidPassword`].
+    pub fn open(
+        &self,
+        password: &str,
+        extract: impl Fn(&[u8], &str) -> FileResult<FileMap>,
+    ) -> FileResult<(FileMap, SectionSlot)> {
+        let (archive, slot) = self.part(password);
+        Ok((extract(&archive, password)?, slot))
+    }
+
+    /// Whether the hidden section is known to hold no archive, so a duress
+    /// password can be set over it
+    ///
+    /// True for a section this process filled with random bytes and for a
+    /// file without one. A random-looking section read from disk may hold
+    /// the real credentials of a file opened with its duress password.
+    pub fn hidden_is_free(&self) -> bool {
+        self.hidden_free
+    }
+
+    /// Whether `password` opens the part that `slot` does not
+    pub fn opens_other(
+        &self,
+        slot: SectionSlot,
+        password: &str,
+        extract: impl Fn(&[u8], &str) -> FileResult<FileMap>,
+    ) -> FileResult<bool> {
+        let result = match slot {
+            SectionSlot::Archive => match self.unseal(password) {
+                Some(archive) => extract(&archive, password),
+                None => return Ok(false),
+            },
+            SectionSlot::Hidden => extract(&self.archive, password),
+        };
+        match result {
+            Ok(_) => Ok(true),
+            Err(FileError::InvalidPassword) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Replace the part in `slot` with the 7z `archive`, encrypted with
+    /// `password`, leaving the other one as it is
+    ///
+    /// A file written without a hidden section gets random bytes in its
+    /// place.
+    pub fn replace(&mut self, slot: SectionSlot, archive: &[u8], password: &str) -> FileResult<()> {
+        let len = archive_len(archive).ok_or_else(|| FileError::CreationFailed {
+            message: "Only 7z archives can share a file with a hidden section".to_string(),
+        })?;
+        let archive = &archive[..len];
+        match slot {
+            SectionSlot::Archive => self.archive = archive.to_vec(),
+            SectionSlot::Hidden => {
+                self.hidden = Self::seal(archive, password)?;
+                self.hidden_free = false;
+            }
+        }
+        if self.hidden.is_empty() {
+            self.clear_hidden();
+        }
+        Ok(())
+    }
+
+    /// The file to write after saving `archive`, encrypted with `password`,
+    /// in place of the open part of `container`
+    ///
+    /// A 7z archive without a container gets a hidden section of random
+    /// bytes; other formats are written as they are. Returns the file and its
+    /// container, if any.
+    pub fn with_archive(
+        container: Option<&OpenedArchive>,
+        archive: Vec<u8>,
+        password: &str,
+    ) -> FileResult<(Vec<u8>, Option<OpenedArchive>)> {
+        let (container, slot) = match container {
+            Some((container, slot))
+                if *slot == SectionSlot::Hidden || Self::recognizes(&archive) =>
+            {
+                let mut container = container.clone();
+                container.replace(*slot, &archive, password)?;
+                (container, *slot)
+            }
+            _ if Self::recognizes(&archive) => (Self::new(&archive)?, SectionSlot::Archive),
+            _ => return Ok((archive, None)),
+        };
+        Ok((container.to_bytes(), Some((container, slot))))
+    }
+
+    /// Overwrite the hidden section with random bytes
+    pub fn clear_hidden(&mut self) {
+        self.hidden = vec![0u8; Self::random_len()];
+        OsRng.fill_bytes(&mut self.hidden);
+        self.hidden_free = true;
+    }
+
+    /// A size for a hidden section, between [`MIN_HIDDEN_LEN`] and
+    /// [`MAX_HIDDEN_LEN`]
+    fn random_len() -> usize {
+        OsRng.gen_range(MIN_HIDDEN_LEN..=MAX_HIDDEN_LEN)
+    }
+
+    /// The cipher for a hidden section with `salt`
+    fn cipher(password: &str, salt: &[u8]) -> FileResult<Aes256Ctr> {
+        let key =
+            EncryptionUtils::derive_key(password, salt).map_err(|e| FileError::CreationFailed {
+                message: e.to_string(),
+            })?;
+        Ok(Aes256Ctr::new(key.expose().into(), &[0u8; 16].into()))
+    }
+
+    /// Encrypt `archive` into a hidden section of random size
+    fn seal(archive: &[u8], password: &str) -> FileResult<Vec<u8>> {
+        let len = (SALT_LEN + LENGTH_LEN + archive.len()).max(Self::random_len());
+        let mut hidden = vec![0u8; len];
+        OsRng.fill_bytes(&mut hidden);
+
+        let (salt, body) = hidden.split_at_mut(SALT_LEN);
+        body[..LENGTH_LEN].copy_from_slice(&(archive.len() as u64).to_le_bytes());
+        body[LENGTH_LEN..LENGTH_LEN + archive.len()].copy_from_slice(archive);
+        Self::cipher(password, salt)?.apply_keystream(body);
+        Ok(hidden)
+    }
+
+    /// The 7z archive in the hidden section, if `password` decrypts one
+    fn unseal(&self, password: &str) -> Option<Vec<u8>> {
+        let salt = self.hidden.get(..SALT_LEN)?;
+        let mut cipher = Self::cipher(password, salt).ok()?;
+        let mut body = self.hidden[SALT_LEN..].to_vec();
+        if body.len() < LENGTH_LEN {
+            return None;
+        }
+        cipher.apply_keystream(&mut body);
+
+        let len = u64::from_le_bytes(body[..LENGTH_LEN].try_into().ok()?);
+        let end = usize::try_from(len).ok()?.checked_add(LENGTH_LEN)?;
+        let archive = body.get(LENGTH_LEN..end)?;
+        (archive_len(archive) == Some(archive.len())).then(|| archive.to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{ArchiveBackend, SevenZipBackend};
+
+    fn archive(name: &str, password: &str) -> Vec<u8> {
+        SevenZipBackend
+            .create(FileMap::from([(name.to_string(), b"x".to_vec())]), password)
+            .unwrap()
+    }
+
+    fn extract(data: &[u8], password: &str) -> FileResult<FileMap> {
+        SevenZipBackend.extract(data, password)
+    }
+
+    #[test]
+    fn test_each_password_opens_its_part() {
+        let mut duress = DuressArchive::new(&archive("decoy", "duress")).unwrap();
+        duress
+            .replace(SectionSlot::Hidden, &archive("real", "master"), "master")
+            .unwrap();
+        let bytes = duress.to_bytes();
+        assert!(bytes.len() >= MIN_HIDDEN_LEN);
+
+        // Other tools see only the decoy
+        assert!(extract(&bytes, "duress").unwrap().contains_key("decoy"));
+        assert!(matches!(
+            extract(&bytes, "master"),
+            Err(FileError::InvalidPassword)
+        ));
+
+        let duress = DuressArchive::from_bytes(&bytes).unwrap();
+        let (files, slot) = duress.open("master", extract).unwrap();
+        assert!(files.contains_key("real"));
+        assert_eq!(slot, SectionSlot::Hidden);
+        let (files, slot) = duress.open("duress", extract).unwrap();
+        assert!(files.contains_key("decoy"));
+        assert_eq!(slot, SectionSlot::Archive);
+        assert!(matches!(
+            duress.open("guess", extract),
+            Err(FileError::InvalidPassword)
+        ));
+
+        assert!(duress
+            .opens_other(SectionSlot::Hidden, "duress", extract)
+            .unwrap());
+        assert!(duress
+            .opens_other(SectionSlot::Archive, "master", extract)
+            .unwrap());
+        assert!(!duress
+            .opens_other(SectionSlot::Archive, "duress", extract)
+            .unwrap());
+    }
+
+    #[test]
+    fn test_every_password_extracts_one_archive() {
+        let mut duress = DuressArchive::new(&archive("decoy", "duress")).unwrap();
+        duress
+            .replace(SectionSlot::Hidden, &archive("real", "master"), "master")
+            .unwrap();
+        let duress = DuressArchive::from_bytes(&duress.to_bytes()).unwrap();
+
+        for password in ["master", "duress", "guess"] {
+            let calls = std::cell::Cell::new(0);
+            let _ = duress.open(password, |data, password| {
+                calls.set(calls.get() + 1);
+                extract(data, password)
+            });
+            assert_eq!(calls.get(), 1, "{}", password);
+        }
+    }
+
+    #[test]
+    fn test_only_generated_hidden_sections_are_free() {
+        let bytes = archive("real", "master");
+        assert!(DuressArchive::from_bytes(&bytes).unwrap().hidden_is_free());
+
+        let mut duress = DuressArchive::new(&bytes).unwrap();
+        assert!(duress.hidden_is_free());
+        // Read back, random bytes look like a sealed archive
+        let mut read = DuressArchive::from_bytes(&duress.to_bytes()).unwrap();
+        assert!(!read.hidden_is_free());
+        read.clear_hidden();
+        assert!(read.hidden_is_free());
+
+        duress
+            .replace(SectionSlot::Hidden, &archive("real", "master"), "master")
+            .unwrap();
+        assert!(!duress.hidden_is_free());
+    }
+
+    #[test]
+    fn test_hidden_section_is_random_without_a_duress_password() {
+        let duress = DuressArchive::new(&archive("real", "master")).unwrap();
+        assert!((MIN_HIDDEN_LEN..=MAX_HIDDEN_LEN).contains(&duress.hidden.len()));
+        assert!(duress.unseal("master").is_none());
+
+        let (files, slot) = duress.open("master", extract).unwrap();
+        assert!(files.contains_key("real"));
+        assert_eq!(slot, SectionSlot::Archive);
+        assert!(!duress
+            .opens_other(SectionSlot::Archive, "master", extract)
+            .unwrap());
+    }
+
+    #[test]
+    fn test_replace_keeps_the_other_part() {
+        let mut duress = DuressArchive::new(&archive("decoy", "duress")).unwrap();
+        duress
+            .replace(SectionSlot::Hidden, &archive("real", "master"), "master")
+            .unwrap();
+        let hidden = duress.hidden.clone();
+        duress
+            .replace(
+                SectionSlot::Archive,
+                &archive("decoy-v2", "duress"),
+                "duress",
+            )
+            .unwrap();
+        assert_eq!(duress.hidden, hidden);
+
+        duress
+            .replace(
+                SectionSlot::Hidden,
+                &archive("real-v2", "new master"),
+                "new master",
+            )
+            .unwrap();
+        let (files, _) = duress.open("new master", extract).unwrap();
+        assert!(files.contains_key("real-v2"));
+        let (files, _) = duress.open("duress", extract).unwrap();
+        assert!(files.contains_key("decoy-v2"));
+        assert!(duress.open("master", extract).is_err());
+    }
+
+    #[test]
+    fn test_only_7z_archives_have_a_hidden_section() {
+        let bytes = archive("real", "master");
+        assert!(DuressArchive::recognizes(&bytes));
+        assert!(!DuressArchive::recognizes(b"PK\x03\x04"));
+        assert!(!DuressArchive::recognizes(&bytes[..bytes.len() - 1]));
+        assert!(DuressArchive::from_bytes(b"junk").is_err());
+
+        let mut duress = DuressArchive::new(&bytes).unwrap();
+        assert!(duress
+            .replace(SectionSlot::Hidden, b"PK\x03\x04", "master")
+            .is_err());
+    }
+}
@@ -5,6 +5,9 @@
 
 use std::fmt;
 
+use crate::core::session::SessionExpiry;
+use crate::utils::localization::Message;
+
 /// Core errors for memory repository operations
 #[derive(Debug, Clone, PartialEq)]
 pub enum CoreError {
@@ -34,6 +37,24 @@ pub enum CoreError {
 
     /// File operation error (wrapped)
     FileOperation(FileError),
+
+    /// Hardware key or other key protector error (wrapped)
+    KeyProtection(KeyProtectionError),
+
+    /// The unlocked session ended and the repository was locked
+    SessionExpired { reason: SessionExpiry },
+
+    /// The passphrase does not open the protected field
+    FieldPassphraseInvalid { field: String },
+
+    /// The repository was created with a key file and opening it needs one
+    KeyFileRequired,
+
+    /// Too many wrong passwords; the next attempt has to wait
+    UnlockThrottled {
+        retry_in_secs: u64,
+        locked_out: bool,
+    },
 }
 
 /// File operation errors
@@ -59,14 +80,163 @@ pub enum FileError {
 
     /// General I/O error
     IoError { message: String },
+
+    /// Archive was changed by someone else since it was read
+    Conflict { path: String },
+
+    /// Not enough space left to write the archive
+    DiskFull { path: String },
+
+    /// The new archive could not be flushed to disk; the previous archive
+    /// is unchanged
+    SyncFailed { path: String, message: String },
+
+    /// The new archive did not read back or open as written; the previous
+    /// archive is unchanged
+    VerificationFailed { message: String },
+
+    /// The previous archive could not be kept as a backup at `path`; it is
+    /// unchanged
+    BackupFailed { path: String, message: String },
+
+    /// The new archive could not be moved over the previous one, which is
+    /// unchanged
+    ReplaceFailed { path: String, message: String },
+}
+
+/// Key protector errors
+///
+/// All of them mean the vault can still be unlocked with the master password.
+#[derive(Debug, Clone, PartialEq)]
+pub enum KeyProtectionError {
+    /// No protector of the requested kind is registered for the repository
+    NotRegistered,
+
+    /// The device is missing or its tooling is not installed
+    DeviceUnavailable { message: String },
+
+    /// The device refused or failed the operation (e.g. no touch in time)
+    DeviceError { message: String },
+
+    /// The protector did not recover the key, e.g. a different security key
+    UnwrapFailed,
+
+    /// The unlock token is past its expiry
+    Expired,
 }
 
-/// Result type for core operations
 pub type CoreResult<T> = Result<T, CoreError>;
 
 /// Result type for file operations
 pub type FileResult<T> = Result<T, FileError>;
 
+/// Result type for key protector operations
+pub type KeyProtectionResult<T> = Result<T, KeyProtectionError>;
+
+impl CoreError {
+    /// The error as a catalog message, for showing it in the user's language
+    ///
+    /// Wrapped file and key protector errors give their own message, without
+    /// the category prefix of the `Display` text.
+    pub fn message(&self) -> Message {
+        match self {
+            CoreError::NotInitialized => Message::new("error.not_initialized"),
+            CoreError::AlreadyInitialized => Message::new("error.already_initialized"),
+            CoreError::CredentialNotFound { id } => {
+                Message::new("error.credential_not_found").with_arg("id", id)
+            }
+            CoreError::ValidationError { message } => {
+                Message::new("error.validation").with_arg("message", message)
+            }
+            CoreError::SerializationError { message } => {
+                Message::new("error.serialization").with_arg("message", message)
+            }
+            CoreError::InvalidCredential { message } => {
+                Message::new("error.invalid_credential").with_arg("message", message)
+            }
+            CoreError::StructureError { message } => {
+                Message::new("error.structure").with_arg("message", message)
+            }
+            CoreError::InternalError { message } => {
+                Message::new("error.internal").with_arg("message", message)
+            }
+            CoreError::FileOperation(err) => err.message(),
+            CoreError::KeyProtection(err) => err.message(),
+            CoreError::SessionExpired { reason } => Message::new(match reason {
+                SessionExpiry::Idle => "session.idle",
+                SessionExpiry::Lifetime => "session.lifetime",
+            }),
+            CoreError::FieldPassphraseInvalid { field } => {
+                Message::new("error.field_passphrase_invalid").with_arg("field", field)
+            }
+            CoreError::KeyFileRequired => Message::new("error.key_file_required"),
+            CoreError::UnlockThrottled {
+                retry_in_secs,
+                locked_out,
+            } => Message::new(if *locked_out {
+                "unlock.locked_out"
+            } else {
+                "unlock.backoff"
+            })
+            .with_arg("secs", retry_in_secs),
+        }
+    }
+}
+
+impl FileError {
+    /// The error as a catalog message
+    pub fn message(&self) -> Message {
+        match self {
+            FileError::NotFound { path } => Message::new("file.not_found").with_arg("path", path),
+            FileError::PermissionDenied { path } => {
+                Message::new("file.permission_denied").with_arg("path", path)
+            }
+            FileError::ExtractionFailed { message } => {
+                Message::new("file.extraction_failed").with_arg("message", message)
+            }
+            FileError::CreationFailed { message } => {
+                Message::new("file.creation_failed").with_arg("message", message)
+            }
+            FileError::InvalidPassword => Message::new("file.invalid_password"),
+            FileError::CorruptedArchive { message } => {
+                Message::new("file.corrupted_archive").with_arg("message", message)
+            }
+            FileError::IoError { message } => Message::new("file.io").with_arg("message", message),
+            FileError::Conflict { path } => Message::new("file.conflict").with_arg("path", path),
+            FileError::DiskFull { path } => Message::new("file.disk_full").with_arg("path", path),
+            FileError::SyncFailed { path, message } => Message::new("file.sync_failed")
+                .with_arg("path", path)
+                .with_arg("message", message),
+            FileError::VerificationFailed { message } => {
+                Message::new("file.verification_failed").with_arg("message", message)
+            }
+            FileError::BackupFailed { path, message } => Message::new("file.backup_failed")
+                .with_arg("path", path)
+                .with_arg("message", message),
+            FileError::ReplaceFailed { path, message } => Message::new("file.replace_failed")
+                .with_arg("path", path)
+                .with_arg("message", message),
+        }
+    }
+}
+
+impl KeyProtectionError {
+    /// The error as a catalog message
+    pub fn message(&self) -> Message {
+        match self {
+            KeyProtectionError::NotRegistered => Message::new("key_protection.not_registered"),
+            KeyProtectionError::DeviceUnavailable { message } => {
+                Message::new("key_protection.device_unavailable").with_arg("message", message)
+            }
+            KeyProtectionError::DeviceError { message } => {
+                Message::new("key_protection.device_error").with_arg("message", message)
+            }
+            KeyProtectionError::UnwrapFailed => Message::new("key_protection.unwrap_failed"),
+            KeyProtectionError::Expired => Message::new("key_protection.expired"),
+        }
+    }
+}
+
 impl fmt::Display for CoreError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -83,6 +253,25 @@ impl fmt::Display for CoreError {
             CoreError::StructureError { message } => write!(f, "Structure error: {message}"),
             CoreError::InternalError { message } => write!(f, "Internal error: {message}"),
             CoreError::FileOperation(err) => write!(f, "File operation error: {err}"),
+            CoreError::KeyProtection(err) => write!(f, "Key protection error: {err}"),
+            CoreError::SessionExpired { reason } => write!(f, "{}", reason.describe()),
+            CoreError::FieldPassphraseInvalid { field } => {
+                write!(f, "Wrong passphrase for protected field '{field}'")
+            }
+            CoreError::KeyFileRequired => write!(
+                f,
+                "This repository needs its key file as well as the master password"
+            ),
+            CoreError::UnlockThrottled {
+                retry_in_secs,
+                locked_out: true,
+            } => write!(
+                f,
+                "Too many failed attempts; try again in {retry_in_secs} seconds"
+            ),
+            CoreError::UnlockThrottled { retry_in_secs, .. } => {
+                write!(f, "Wait {retry_in_secs} seconds before trying again")
+            }
         }
     }
 }
@@ -97,12 +286,53 @@ impl fmt::Display for FileError {
             FileError::InvalidPassword => write!(f, "Invalid password"),
             FileError::CorruptedArchive { message } => write!(f, "Corrupted archive: {message}"),
             FileError::IoError { message } => write!(f, "I/O error: {message}"),
+            FileError::Conflict { path } => {
+                write!(f, "Archive was modified by another device: {path}")
+            }
+            FileError::DiskFull { path } => write!(f, "Not enough disk space to save {path}"),
+            FileError::SyncFailed { path, message } => {
+                write!(f, "Could not flush {path} to disk: {message}")
+            }
+            FileError::VerificationFailed { message } => write!(
+                f,
+                "The saved archive did not open again, so the previous one was kept: {message}"
+            ),
+            FileError::BackupFailed { path, message } => {
+                write!(
+                    f,
+                    "Could not keep the previous archive as {path}: {message}"
+                )
+            }
+            FileError::ReplaceFailed { path, message } => {
+                write!(f, "Could not replace {path}: {message}")
+            }
+        }
+    }
+}
+
+impl fmt::Display for KeyProtectionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyProtectionError::NotRegistered => {
+                write!(f, "No unlock method of this kind is registered")
+            }
+            KeyProtectionError::DeviceUnavailable { message } => write!(f, "{message}"),
+            KeyProtectionError::DeviceError { message } => {
+                write!(f, "Key protector error: {message}")
+            }
+            KeyProtectionError::UnwrapFailed => {
+                write!(f, "The stored key could not unlock this repository")
+            }
+            KeyProtectionError::Expired => {
+                write!(f, "The unlock token has expired; enter the master password")
+            }
         }
     }
 }
 
 impl std::error::Error for CoreError {}
 impl std::error::Error for FileError {}
+impl std::error::Error for KeyProtectionError {}
 
 impl From<FileError> for CoreError {
     fn from(err: FileError) -> Self {
@@ -110,6 +340,12 @@ impl From<FileError> for CoreError {
     }
 }
 
+impl From<KeyProtectionError> for CoreError {
+    fn from(err: KeyProtectionError) -> Self {
+        CoreError::KeyProtection(err)
+    }
+}
+
 impl From<serde_yaml::Error> for CoreError {
     fn from(err: serde_yaml::Error) -> Self {
         CoreError::SerializationError {
@@ -143,6 +379,75 @@ mod tests {
         assert_eq!(file_err.to_string(), "File not found: /test/path");
     }
 
+    #[test]
+    fn test_messages_match_display() {
+        let errors = [
+            CoreError::NotInitialized,
+            CoreError::CredentialNotFound { id: "x".into() },
+            CoreError::ValidationError {
+                message: "Title cannot be empty".into(),
+            },
+            CoreError::SessionExpired {
+                reason: SessionExpiry::Idle,
+            },
+            CoreError::FieldPassphraseInvalid {
+                field: "pin".into(),
+            },
+            CoreError::KeyFileRequired,
+            CoreError::UnlockThrottled {
+                retry_in_secs: 8,
+                locked_out: false,
+            },
+            CoreError::UnlockThrottled {
+                retry_in_secs: 300,
+                locked_out: true,
+            },
+        ];
+        for error in errors {
+            assert_eq!(error.message().to_string(), error.to_string());
+        }
+
+        let file_errors = [
+            FileError::Conflict {
+                path: "/vault.7z".into(),
+            },
+            FileError::DiskFull {
+                path: "/vault.7z".into(),
+            },
+            FileError::SyncFailed {
+                path: "/vault.7z".into(),
+                message: "EIO".into(),
+            },
+            FileError::VerificationFailed {
+                message: "bad header".into(),
+            },
+            FileError::BackupFailed {
+                path: "/vault.7z.bak".into(),
+                message: "EACCES".into(),
+            },
+            FileError::ReplaceFailed {
+                path: "/vault.7z".into(),
+                message: "EXDEV".into(),
+            },
+        ];
+        for error in &file_errors {
+            assert_eq!(error.message().to_string(), error.to_string());
+        }
+        let file_error = file_errors[0].clone();
+        assert_eq!(
+            CoreError::FileOperation(file_error.clone()).message(),
+            file_error.message()
+        );
+        assert_eq!(
+            KeyProtectionError::Expired.message().to_string(),
+            KeyProtectionError::Expired.to_string()
+        );
+        assert_eq!(
+            CoreError::NotInitialized.message().localize("fr"),
+            "Coffre non initialisé"
+        );
+    }
+
     #[test]
     fn test_error_conversion() {
         let file_err = FileError::InvalidPassword;
@@ -0,0 +1,321 @@
+//! Repository change events
+//!
+//! Frontends that show the vault, such as the desktop list view or a script
+//! connected to the scripting API, need to know when it changes without
+//! re-reading it on a timer. Whoever changes the repository publishes a
+//! [`RepositoryEvent`] on an [`EventBus`], and every subscriber receives it.
+//!
+//! The repository itself reports its changes to the callbacks registered
+//! with `on_change` on the memory repository or the manager, held in
+//! [`ChangeListeners`]. Those run synchronously on the thread making the
+//! change; forwarding to an [`EventBus`] is the usual way to get the events
+//! to async code or another thread.
+//!
+//! Events only carry credential IDs, never titles or field values, so they
+//! can be passed to less trusted listeners as they are.
+
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, Weak};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+
+use crate::core::session::SessionExpiry;
+
+/// Something that changed in the repository
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RepositoryEvent {
+    /// A repository was opened or created
+    Opened,
+    /// The repository was closed or locked and its contents wiped
+    Locked,
+    /// The session ran past its [`SessionPolicy`](crate::core::SessionPolicy)
+    /// limits, so the repository was locked and its contents wiped
+    SessionExpired { reason: SessionExpiry },
+    /// A credential was added
+    CredentialAdded { id: String },
+    /// A credential was changed
+    CredentialUpdated { id: String },
+    /// A credential was deleted
+    CredentialDeleted { id: String },
+    /// Pending changes were written to the archive
+    Saved,
+    /// Another program, such as a sync client, changed the archive since it
+    /// was last read or written; saving fails until it is reloaded, merged
+    /// or deliberately overwritten
+    ArchiveChanged,
+}
+
+impl RepositoryEvent {
+    /// The credential the event is about, if any
+    pub fn credential_id(&self) -> Option<&str> {
+        match self {
+            RepositoryEvent::CredentialAdded { id }
+            | RepositoryEvent::CredentialUpdated { id }
+            | RepositoryEvent::CredentialDeleted { id } => Some(id),
+            RepositoryEvent::Opened
+            | RepositoryEvent::Locked
+            | RepositoryEvent::SessionExpired { .. }
+            | RepositoryEvent::Saved
+            | RepositoryEvent::ArchiveChanged => None,
+        }
+    }
+
+    /// Whether the repository is no longer unlocked after this event
+    pub fn is_lock(&self) -> bool {
+        matches!(
+            self,
+            RepositoryEvent::Locked | RepositoryEvent::SessionExpired { .. }
+        )
+    }
+}
+
+/// Fans repository events out to any number of subscribers
+///
+/// Publishing never blocks. A subscriber that falls more than `capacity`
+/// events behind misses the oldest ones and is told how many with
+/// [`broadcast::error::RecvError::Lagged`], after which it should reload
+/// whatever it shows.
+#[derive(Debug, Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<RepositoryEvent>,
+}
+
+impl EventBus {
+    /// Default number of events kept for slow subscribers
+    pub const DEFAULT_CAPACITY: usize = 64;
+
+    /// Create a bus keeping up to `capacity` events for slow subscribers
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity.max(1));
+        Self { sender }
+    }
+
+    /// Send `event` to every current subscriber
+    ///
+    /// Returns how many subscribers there were; events published while
+    /// nobody listens are dropped.
+    pub fn publish(&self, event: RepositoryEvent) -> usize {
+        self.sender.send(event).unwrap_or(0)
+    }
+
+    /// Receive every event published from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<RepositoryEvent> {
+        self.sender.subscribe()
+    }
+
+    /// Number of current subscribers
+    pub fn subscriber_count(&self) -> usize {
+        self.sender.receiver_count()
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_CAPACITY)
+    }
+}
+
+/// Callback told about every change to a repository
+pub type ChangeListener = Box<dyn Fn(&RepositoryEvent) + Send + Sync>;
+
+/// Identifies a registered [`ChangeListener`], for removing it again
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ListenerId(u64);
+
+#[derive(Default)]
+struct ListenerSet {
+    next_id: u64,
+    listeners: Vec<(ListenerId, ChangeListener)>,
+}
+
+/// The listeners of one repository, called in the order they were added
+///
+/// Listeners run while the repository is borrowed for the change, so they
+/// cannot call back into it and should not do slow work. A clone starts
+/// without listeners: a copy of a repository is not the one they watch.
+#[derive(Default)]
+pub struct ChangeListeners {
+    set: Arc<Mutex<ListenerSet>>,
+}
+
+impl ChangeListeners {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Call `listener` for every event from now on
+    pub fn add(
+        &mut self,
+        listener: impl Fn(&RepositoryEvent) + Send + Sync + 'static,
+    ) -> ListenerId {
+        let mut set = lock(&self.set);
+        let id = ListenerId(set.next_id);
+        set.next_id += 1;
+        set.listeners.push((id, Box::new(listener)));
+        id
+    }
+
+    /// Stop calling the listener `id`; returns whether it was registered
+    pub fn remove(&mut self, id: ListenerId) -> bool {
+        let mut set = lock(&self.set);
+        let before = set.listeners.len();
+        set.listeners.retain(|(registered, _)| *registered != id);
+        set.listeners.len() != before
+    }
+
+    /// Call every listener with `event`
+    pub fn emit(&self, event: &RepositoryEvent) {
+        emit_to(&self.set, event);
+    }
+
+    /// A handle for reporting events from another thread, such as a file
+    /// watcher
+    pub fn emitter(&self) -> ChangeEmitter {
+        ChangeEmitter {
+            set: Arc::downgrade(&self.set),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        lock(&self.set).listeners.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Clone for ChangeListeners {
+    fn clone(&self) -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for ChangeListeners {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChangeListeners")
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+/// Reports events to the listeners of a repository from anywhere
+///
+/// Does nothing once the repository and its listeners are gone.
+#[derive(Clone)]
+pub struct ChangeEmitter {
+    set: Weak<Mutex<ListenerSet>>,
+}
+
+impl ChangeEmitter {
+    /// Call every listener with `event`
+    pub fn emit(&self, event: &RepositoryEvent) {
+        if let Some(set) = self.set.upgrade() {
+            emit_to(&set, event);
+        }
+    }
+}
+
+impl fmt::Debug for ChangeEmitter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChangeEmitter")
+            .field("connected", &(self.set.strong_count() > 0))
+            .finish()
+    }
+}
+
+fn emit_to(set: &Mutex<ListenerSet>, event: &RepositoryEvent) {
+    for (_, listener) in &lock(set).listeners {
+        listener(event);
+    }
+}
+
+/// A listener that panicked leaves the set as it was, so carry on with it
+fn lock(set: &Mutex<ListenerSet>) -> MutexGuard<'_, ListenerSet> {
+    set.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::broadcast::error::RecvError;
+
+    #[tokio::test]
+    async fn test_publish_reaches_every_subscriber() {
+        let bus = EventBus::default();
+        assert_eq!(bus.publish(RepositoryEvent::Opened), 0);
+
+        let mut first = bus.subscribe();
+        let mut second = bus.clone().subscribe();
+        assert_eq!(bus.subscriber_count(), 2);
+
+        let added = RepositoryEvent::CredentialAdded {
+            id: "abc".to_string(),
+        };
+        assert_eq!(bus.publish(added.clone()), 2);
+        assert_eq!(first.recv().await.unwrap(), added);
+        assert_eq!(second.recv().await.unwrap(), added);
+        assert_eq!(added.credential_id(), Some("abc"));
+        assert_eq!(RepositoryEvent::Locked.credential_id(), None);
+
+        let json = serde_json::to_string(&added).unwrap();
+        assert_eq!(json, r#"{"type":"credential_added","id":"abc"}"#);
+        assert_eq!(
+            serde_json::to_string(&RepositoryEvent::Locked).unwrap(),
+            r#"{"type":"locked"}"#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_slow_subscriber_is_told_it_lagged() {
+        let bus = EventBus::new(2);
+        let mut receiver = bus.subscribe();
+        for id in ["a", "b", "c"] {
+            bus.publish(RepositoryEvent::CredentialDeleted { id: id.to_string() });
+        }
+
+        assert!(matches!(receiver.recv().await, Err(RecvError::Lagged(1))));
+        assert_eq!(receiver.recv().await.unwrap().credential_id(), Some("b"));
+    }
+
+    #[test]
+    fn test_change_listeners() {
+        use std::sync::{Arc, Mutex};
+
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let mut listeners = ChangeListeners::new();
+        let first = listeners.add({
+            let seen = Arc::clone(&seen);
+            move |event| seen.lock().unwrap().push(("first", event.clone()))
+        });
+        listeners.add({
+            let seen = Arc::clone(&seen);
+            move |event| seen.lock().unwrap().push(("second", event.clone()))
+        });
+        assert!(listeners.clone().is_empty());
+
+        listeners.emit(&RepositoryEvent::Saved);
+        assert!(listeners.remove(first));
+        assert!(!listeners.remove(first));
+        let emitter = listeners.emitter();
+        std::thread::spawn(move || emitter.emit(&RepositoryEvent::ArchiveChanged))
+            .join()
+            .unwrap();
+
+        assert_eq!(
+            *seen.lock().unwrap(),
+            [
+                ("first", RepositoryEvent::Saved),
+                ("second", RepositoryEvent::Saved),
+                ("second", RepositoryEvent::ArchiveChanged),
+            ]
+        );
+
+        let emitter = listeners.emitter();
+        drop(listeners);
+        emitter.emit(&RepositoryEvent::Locked);
+        assert_eq!(seen.lock().unwrap().len(), 3);
+    }
+}
@@ -4,11 +4,17 @@
 //! allowing the memory repository to delegate file I/O to platform-specific
 //! providers while maintaining clean separation of concerns.
 
-use std::collections::HashMap;
-use tracing::{debug, error, warn};
-
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::future::Future;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use crate::core::archive_backend::{
+    ArchiveBackend, ArchiveOptions, MultiFormatBackend, SevenZipBackend, SEVEN_ZIP_BACKEND,
+};
 use crate::core::errors::{FileError, FileResult};
 use crate::core::types::FileMap;
+use crate::utils::encryption::EncryptionUtils;
 
 /// Trait for providing file operations to the repository manager
 ///
@@ -37,6 +43,28 @@ pub trait FileOperationProvider: Send + Sync {
     /// * `Err(FileError)` - If file cannot be written
     fn write_archive(&self, path: &str, data: &[u8]) -> FileResult<()>;
 
+    /// Write an archive so that a failure at any point leaves the previous
+    /// archive at `path` as it was
+    ///
+    /// `verify` receives the archive as read back from storage and must
+    /// accept it before the previous archive is replaced. Used for saves;
+    /// the default verifies `data` itself and calls `write_archive`.
+    /// Providers that can stage the archive first should override it.
+    ///
+    /// # Returns
+    /// * `Ok(())` - If the verified archive is now at `path`
+    /// * `Err(FileError)` - The failing step, e.g.
+    ///   [`FileError::VerificationFailed`] or [`FileError::ReplaceFailed`]
+    fn write_archive_verified(
+        &self,
+        path: &str,
+        data: &[u8],
+        verify: &dyn Fn(&[u8]) -> FileResult<()>,
+    ) -> FileResult<()> {
+        verify(data)?;
+        self.write_archive(path, data)
+    }
+
     /// Extract archive contents to a file map
     ///
     /// This method should use platform-appropriate 7z libraries to extract
@@ -65,405 +93,708 @@ pub trait FileOperationProvider: Send + Sync {
     /// * `Ok(Vec<u8>)` - Created archive as bytes
     /// * `Err(FileError)` - If archive creation fails
     fn create_archive(&self, files: FileMap, password: &str) -> FileResult<Vec<u8>>;
+
+    /// Create an encrypted archive with the given compression settings
+    ///
+    /// The default ignores `options` and calls `create_archive`; providers
+    /// that pack archives with an [`ArchiveBackend`] pass them on to it.
+    fn create_archive_with_options(
+        &self,
+        files: FileMap,
+        password: &str,
+        _options: &ArchiveOptions,
+    ) -> FileResult<Vec<u8>> {
+        self.create_archive(files, password)
+    }
+
+    /// Apply changed and removed entries to the archive at `path`
+    ///
+    /// Used for incremental saves, which must be as safe as full ones: the
+    /// updated archive goes through the same `verify` and replacement as
+    /// [`FileOperationProvider::write_archive_verified`]. The default reads,
+    /// extracts, patches and recreates the whole archive; providers whose
+    /// backend can change single entries should override it.
+    ///
+    /// # Arguments
+    /// * `path` - Path of the archive to update
+    /// * `changed` - New or changed entries
+    /// * `removed` - Paths of entries to delete
+    /// * `password` - Archive password
+    /// * `verify` - Check of the updated archive as read back from storage
+    ///
+    /// # Returns
+    /// * `Ok(())` - If the verified archive now holds the updated entries
+    /// * `Err(FileError)` - If the archive cannot be read, updated, verified
+    ///   or replaced
+    fn update_archive(
+        &self,
+        path: &str,
+        changed: &FileMap,
+        removed: &[String],
+        password: &str,
+        verify: &dyn Fn(&[u8]) -> FileResult<()>,
+    ) -> FileResult<()> {
+        let mut files = self.extract_archive(&self.read_archive(path)?, password)?;
+        for entry in removed {
+            files.remove(entry);
+        }
+        files.extend(
+            changed
+                .iter()
+                .map(|(entry, content)| (entry.clone(), content.clone())),
+        );
+        let data = self.create_archive(files, password)?;
+        self.write_archive_verified(path, &data, verify)
+    }
+
+    /// Name of the [`ArchiveBackend`] that `create_archive` writes, recorded
+    /// in the repository metadata on save
+    fn archive_backend(&self) -> &str {
+        SEVEN_ZIP_BACKEND
+    }
+
+    /// Whether someone else changed the archive at `path` since this
+    /// provider last read or wrote it
+    ///
+    /// Providers that refuse such writes with [`FileError::Conflict`]
+    /// override this; the default knows of no changes.
+    fn archive_changed(&self, _path: &str) -> FileResult<bool> {
+        Ok(false)
+    }
+
+    /// Call `on_change` whenever someone else, such as a sync client,
+    /// changes the archive at `path`, until the returned watch is dropped
+    ///
+    /// Writes made through this provider are not reported. Providers that
+    /// cannot watch return `Ok(None)`, the default.
+    fn watch_archive(
+        &self,
+        _path: &str,
+        _on_change: Box<dyn Fn() + Send + Sync>,
+    ) -> FileResult<Option<ArchiveWatch>> {
+        Ok(None)
+    }
+}
+
+/// Keeps watching an archive until dropped, see
+/// [`FileOperationProvider::watch_archive`]
+pub struct ArchiveWatch {
+    _watcher: Mutex<Box<dyn Send>>,
+}
+
+impl ArchiveWatch {
+    /// Keep `watcher` alive for as long as the watch
+    pub fn new(watcher: impl Send + 'static) -> Self {
+        Self {
+            _watcher: Mutex::new(Box::new(watcher)),
+        }
+    }
+}
+
+impl fmt::Debug for ArchiveWatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ArchiveWatch").finish_non_exhaustive()
+    }
+}
+
+/// Asynchronous counterpart of [`FileOperationProvider`]
+///
+/// Used by [`AsyncRepositoryManager`](crate::core::AsyncRepositoryManager) so
+/// that file I/O and 7z compression never run on the caller's thread. Data
+/// and passwords are passed by value because implementations usually move
+/// them onto another thread.
+pub trait AsyncFileOperationProvider: Send + Sync {
+    /// Read an archive file from the filesystem
+    fn read_archive(&self, path: &str) -> impl Future<Output = FileResult<Vec<u8>>> + Send;
+
+    /// Write archive data to the filesystem
+    fn write_archive(
+        &self,
+        path: &str,
+        data: Vec<u8>,
+    ) -> impl Future<Output = FileResult<()>> + Send;
+
+    /// Extract archive contents to a file map
+    fn extract_archive(
+        &self,
+        data: Vec<u8>,
+        password: String,
+    ) -> impl Future<Output = FileResult<FileMap>> + Send;
+
+    /// Create an encrypted archive from a file map
+    fn create_archive(
+        &self,
+        files: FileMap,
+        password: String,
+    ) -> impl Future<Output = FileResult<Vec<u8>>> + Send;
+
+    /// Name of the archive backend that `create_archive` writes
+    fn archive_backend(&self) -> &str {
+        SEVEN_ZIP_BACKEND
+    }
+}
+
+/// Runs a blocking [`FileOperationProvider`] on tokio's blocking thread pool
+#[derive(Debug)]
+pub struct BlockingFileProvider<F> {
+    inner: Arc<F>,
+}
+
+impl<F> BlockingFileProvider<F> {
+    /// Wrap a blocking provider
+    pub fn new(provider: F) -> Self {
+        Self {
+            inner: Arc::new(provider),
+        }
+    }
+
+    /// The wrapped provider
+    pub fn inner(&self) -> &F {
+        &self.inner
+    }
+}
+
+impl<F> Clone for BlockingFileProvider<F> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+impl<F: FileOperationProvider + 'static> BlockingFileProvider<F> {
+    async fn run<T: Send + 'static>(
+        &self,
+        operation: impl FnOnce(&F) -> FileResult<T> + Send + 'static,
+    ) -> FileResult<T> {
+        let provider = Arc::clone(&self.inner);
+        tokio::task::spawn_blocking(move || operation(&provider))
+            .await
+            .map_err(|e| FileError::IoError {
+                message: format!("File operation did not complete: {}", e),
+            })?
+    }
+}
+
+impl<F: FileOperationProvider + 'static> AsyncFileOperationProvider for BlockingFileProvider<F> {
+    async fn read_archive(&self, path: &str) -> FileResult<Vec<u8>> {
+        let path = path.to_string();
+        self.run(move |provider| provider.read_archive(&path)).await
+    }
+
+    async fn write_archive(&self, path: &str, data: Vec<u8>) -> FileResult<()> {
+        let path = path.to_string();
+        self.run(move |provider| provider.write_archive(&path, &data))
+            .await
+    }
+
+    async fn extract_archive(&self, data: Vec<u8>, password: String) -> FileResult<FileMap> {
+        let password = zeroize::Zeroizing::new(password);
+        self.run(move |provider| provider.extract_archive(&data, &password))
+            .await
+    }
+
+    async fn create_archive(&self, files: FileMap, password: String) -> FileResult<Vec<u8>> {
+        let password = zeroize::Zeroizing::new(password);
+        self.run(move |provider| provider.create_archive(files, &password))
+            .await
+    }
+
+    fn archive_backend(&self) -> &str {
+        self.inner.archive_backend()
+    }
+}
+
+/// Desktop file provider using the local filesystem
+///
+/// Archives in any [`ArchiveFormat`](crate::core::ArchiveFormat) open and
+/// are saved in the format they were in; new ones are 7z. Another
+/// [`ArchiveBackend`] can be given instead.
+///
+/// Archives are written to a temporary file in the same directory, flushed
+/// to disk and renamed into place, so a crash leaves either the old or the
+/// new archive. Saves also read the new archive back for verification and
+/// keep the one they replace as `<path>.bak`.
+///
+/// The provider remembers the SHA-256 of every archive it reads or writes.
+/// Writing over an archive that has changed since, for example because a
+/// sync client brought in a copy saved on another device, fails with
+/// [`FileError::Conflict`] instead of losing that copy.
+#[derive(Debug, Default)]
+pub struct DesktopFileProvider<B: ArchiveBackend = MultiFormatBackend> {
+    backend: B,
+    known: Arc<Mutex<KnownArchives>>,
 }
 
-/// Desktop file provider using sevenz-rust2 for direct archive operations
+/// The archives as a [`DesktopFileProvider`] last read or wrote them,
+/// shared with its watchers
 #[derive(Debug, Default)]
-pub struct DesktopFileProvider;
+struct KnownArchives {
+    digests: HashMap<String, Vec<u8>>,
+    /// Archives being written right now, whose watchers keep quiet
+    writing: HashSet<String>,
+}
 
 impl DesktopFileProvider {
     /// Create a new desktop file provider
     pub fn new() -> Self {
-        Self
+        Self::with_backend(MultiFormatBackend::new())
     }
 }
 
-impl FileOperationProvider for DesktopFileProvider {
-    fn read_archive(&self, path: &str) -> FileResult<Vec<u8>> {
-        std::fs::read(path).map_err(|e| match e.kind() {
-            std::io::ErrorKind::NotFound => FileError::NotFound {
-                path: path.to_string(),
-            },
-            std::io::ErrorKind::PermissionDenied => FileError::PermissionDenied {
+impl<B: ArchiveBackend> DesktopFileProvider<B> {
+    /// Create a desktop file provider that stores archives in another format
+    pub fn with_backend(backend: B) -> Self {
+        Self {
+            backend,
+            known: Arc::default(),
+        }
+    }
+
+    fn known(&self) -> MutexGuard<'_, KnownArchives> {
+        lock_known(&self.known)
+    }
+
+    fn check_unchanged(&self, path: &str) -> FileResult<()> {
+        if self.archive_changed(path)? {
+            return Err(FileError::Conflict {
                 path: path.to_string(),
-            },
-            _ => FileError::IoError {
-                message: format!("Failed to read archive '{}': {}", path, e),
-            },
-        })
+            });
+        }
+        Ok(())
+    }
+
+    /// Run `write` on the archive at `path` if nobody else changed it, and
+    /// remember the digest it returns
+    ///
+    /// A failed write may leave the file half written, so the archive is
+    /// forgotten rather than reported as changed on the next write.
+    fn write_tracked(
+        &self,
+        path: &str,
+        write: impl FnOnce() -> FileResult<Vec<u8>>,
+    ) -> FileResult<()> {
+        self.check_unchanged(path)?;
+        self.known().writing.insert(path.to_string());
+        let result = write();
+        let mut known = self.known();
+        match &result {
+            Ok(digest) => known.digests.insert(path.to_string(), digest.clone()),
+            Err(_) => known.digests.remove(path),
+        };
+        known.writing.remove(path);
+        result.map(|_| ())
+    }
+}
+
+fn lock_known(known: &Mutex<KnownArchives>) -> MutexGuard<'_, KnownArchives> {
+    known.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Read the archive at `path`
+fn read_file(path: &str) -> FileResult<Vec<u8>> {
+    std::fs::read(path).map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => FileError::NotFound {
+            path: path.to_string(),
+        },
+        std::io::ErrorKind::PermissionDenied => FileError::PermissionDenied {
+            path: path.to_string(),
+        },
+        _ => FileError::IoError {
+            message: format!("Failed to read archive '{}': {}", path, e),
+        },
+    })
+}
+
+/// SHA-256 of the file at `path`, or `None` if it cannot be read
+fn file_digest(path: &str) -> Option<Vec<u8>> {
+    std::fs::read(path)
+        .ok()
+        .map(|data| EncryptionUtils::hash_sha256(&data))
+}
+
+impl<B: ArchiveBackend> FileOperationProvider for DesktopFileProvider<B> {
+    fn read_archive(&self, path: &str) -> FileResult<Vec<u8>> {
+        let data = read_file(path)?;
+        self.known()
+            .digests
+            .insert(path.to_string(), EncryptionUtils::hash_sha256(&data));
+        Ok(data)
     }
 
     fn write_archive(&self, path: &str, data: &[u8]) -> FileResult<()> {
-        // Ensure parent directory exists
-        if let Some(parent) = std::path::Path::new(path).parent() {
-            if let Err(e) = std::fs::create_dir_all(parent) {
-                if e.kind() != std::io::ErrorKind::AlreadyExists {
-                    return Err(FileError::IoError {
-                        message: format!("Failed to create directory for '{}': {}", path, e),
-                    });
-                }
-            }
-        }
+        self.write_tracked(path, || {
+            stage_file(path, data)?.persist_to(path)?;
+            Ok(EncryptionUtils::hash_sha256(data))
+        })
+    }
 
-        std::fs::write(path, data).map_err(|e| match e.kind() {
-            std::io::ErrorKind::PermissionDenied => FileError::PermissionDenied {
-                path: path.to_string(),
-            },
-            _ => FileError::IoError {
-                message: format!("Failed to write archive '{}': {}", path, e),
-            },
+    fn write_archive_verified(
+        &self,
+        path: &str,
+        data: &[u8],
+        verify: &dyn Fn(&[u8]) -> FileResult<()>,
+    ) -> FileResult<()> {
+        self.write_tracked(path, || {
+            let staged = stage_file(path, data)?;
+            let written =
+                std::fs::read(staged.path()).map_err(|e| FileError::VerificationFailed {
+                    message: format!("Failed to read the new archive back: {}", e),
+                })?;
+            if written != data {
+                return Err(FileError::VerificationFailed {
+                    message: "The new archive reads back differently from what was written"
+                        .to_string(),
+                });
+            }
+            verify(&written)?;
+            keep_backup(path)?;
+            staged.persist_to(path)?;
+            Ok(EncryptionUtils::hash_sha256(data))
         })
     }
 
     fn extract_archive(&self, data: &[u8], password: &str) -> FileResult<FileMap> {
-        debug!("Starting archive extraction: {} bytes", data.len());
-        debug!(
-            "Archive encryption: {}",
-            if password.is_empty() {
-                "disabled"
-            } else {
-                "enabled"
-            }
-        );
+        self.backend.extract(data, password)
+    }
 
-        // Write buffer to temporary file first since sevenz-rust2 API requires file paths
-        let temp_archive =
-            std::env::temp_dir().join(format!("ziplock_archive_{}.7z", uuid::Uuid::new_v4()));
-        let temp_dir =
-            std::env::temp_dir().join(format!("ziplock_extract_{}", uuid::Uuid::new_v4()));
-
-        debug!("Temp archive path: {:?}", temp_archive);
-        debug!("Temp extract dir: {:?}", temp_dir);
-
-        // Write archive data to temp file
-        std::fs::write(&temp_archive, data).map_err(|e| {
-            error!(
-                "Failed to write temp archive file {:?}: {}",
-                temp_archive, e
-            );
-            FileError::ExtractionFailed {
-                message: format!("Failed to write temp archive file: {}", e),
-            }
-        })?;
+    fn create_archive(&self, files: FileMap, password: &str) -> FileResult<Vec<u8>> {
+        self.backend.create(files, password)
+    }
 
-        debug!("Archive data written to temp file: {} bytes", data.len());
+    fn create_archive_with_options(
+        &self,
+        files: FileMap,
+        password: &str,
+        options: &ArchiveOptions,
+    ) -> FileResult<Vec<u8>> {
+        self.backend.create_with_options(files, password, options)
+    }
 
-        std::fs::create_dir_all(&temp_dir).map_err(|e| {
-            error!("Failed to create temp directory {:?}: {}", temp_dir, e);
-            FileError::ExtractionFailed {
-                message: format!("Failed to create temp directory: {}", e),
-            }
-        })?;
+    fn update_archive(
+        &self,
+        path: &str,
+        changed: &FileMap,
+        removed: &[String],
+        password: &str,
+        verify: &dyn Fn(&[u8]) -> FileResult<()>,
+    ) -> FileResult<()> {
+        self.write_tracked(path, || {
+            // The backend patches a staged copy, never the archive itself, so
+            // the previous archive stays intact until the copy is verified
+            let staged = stage_file(path, &read_file(path)?)?;
+            self.backend
+                .update_file(staged.path(), password, changed, removed)?;
+            sync_file(&staged.path().to_string_lossy())?;
+            let written =
+                std::fs::read(staged.path()).map_err(|e| FileError::VerificationFailed {
+                    message: format!("Failed to read the updated archive back: {}", e),
+                })?;
+            verify(&written)?;
+            keep_backup(path)?;
+            staged.persist_to(path)?;
+            Ok(EncryptionUtils::hash_sha256(&written))
+        })
+    }
 
-        debug!("Temp extraction directory created: {:?}", temp_dir);
+    fn archive_backend(&self) -> &str {
+        self.backend.name()
+    }
 
-        // Use sevenz-rust2 helper functions
-        let result = if password.is_empty() {
-            debug!("Calling sevenz_rust2::decompress_file without password");
-            sevenz_rust2::decompress_file(&temp_archive, &temp_dir)
-        } else {
-            debug!("Calling sevenz_rust2::decompress_file_with_password with password");
-            sevenz_rust2::decompress_file_with_password(&temp_archive, &temp_dir, password.into())
+    fn archive_changed(&self, path: &str) -> FileResult<bool> {
+        let Some(known) = self.known().digests.get(path).cloned() else {
+            return Ok(false);
         };
+        // A removed archive is written again without losing anything
+        Ok(file_digest(path).is_some_and(|current| current != known))
+    }
 
-        match result {
-            Ok(()) => {
-                debug!("Archive extraction successful, reading extracted files");
-
-                // Read extracted files into memory
-                let mut file_map = HashMap::new();
-
-                fn read_dir_recursive(
-                    dir: &std::path::Path,
-                    base_path: &std::path::Path,
-                    file_map: &mut HashMap<String, Vec<u8>>,
-                ) -> std::io::Result<()> {
-                    for entry in std::fs::read_dir(dir)? {
-                        let entry = entry?;
-                        let path = entry.path();
-
-                        if path.is_file() {
-                            let relative_path = path
-                                .strip_prefix(base_path)
-                                .map_err(|_| std::io::Error::other("Path error"))?;
-                            let content = std::fs::read(&path)?;
-                            let relative_path_str = relative_path.to_string_lossy().to_string();
-                            debug!(
-                                "Extracted file: {} ({} bytes)",
-                                relative_path_str,
-                                content.len()
-                            );
-                            file_map.insert(relative_path_str, content);
-                        } else if path.is_dir() {
-                            debug!("Recursing into directory: {:?}", path);
-                            read_dir_recursive(&path, base_path, file_map)?;
-                        }
-                    }
-                    Ok(())
-                }
-
-                read_dir_recursive(&temp_dir, &temp_dir, &mut file_map).map_err(|e| {
-                    error!("Failed to read extracted files from {:?}: {}", temp_dir, e);
-                    FileError::ExtractionFailed {
-                        message: format!("Failed to read extracted files: {}", e),
-                    }
-                })?;
+    #[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+    fn watch_archive(
+        &self,
+        path: &str,
+        on_change: Box<dyn Fn() + Send + Sync>,
+    ) -> FileResult<Option<ArchiveWatch>> {
+        use notify::{EventKind, RecursiveMode, Watcher};
 
-                debug!(
-                    "Successfully extracted {} files from archive",
-                    file_map.len()
-                );
-                for (path, content) in &file_map {
-                    debug!("  - {}: {} bytes", path, content.len());
+        let watch_error = |e: notify::Error| FileError::IoError {
+            message: format!("Failed to watch archive '{}': {}", path, e),
+        };
+        let archive = std::path::PathBuf::from(path);
+        let name = archive.file_name().map(|name| name.to_os_string());
+        // Sync clients replace the file rather than write to it, which would
+        // end a watch on the file itself
+        let directory = parent_dir(&archive).to_path_buf();
+
+        let known = Arc::clone(&self.known);
+        let path = path.to_string();
+        let reported = Mutex::new(None);
+        let mut watcher =
+            notify::recommended_watcher(move |result: notify::Result<notify::Event>| {
+                let Ok(event) = result else {
+                    return;
+                };
+                if matches!(event.kind, EventKind::Access(_))
+                    || !event
+                        .paths
+                        .iter()
+                        .any(|changed| changed.file_name() == name.as_deref())
+                {
+                    return;
                 }
 
-                // Clean up temp files
-                debug!("Cleaning up temporary files");
-                if let Err(e) = std::fs::remove_file(&temp_archive) {
-                    warn!("Failed to remove temp archive {:?}: {}", temp_archive, e);
-                }
-                if let Err(e) = std::fs::remove_dir_all(&temp_dir) {
-                    warn!("Failed to remove temp directory {:?}: {}", temp_dir, e);
+                let current = file_digest(&path);
+                let external = {
+                    let known = lock_known(&known);
+                    !known.writing.contains(&path)
+                        && matches!(
+                            (known.digests.get(&path), &current),
+                            (Some(known), Some(current)) if known != current
+                        )
+                };
+                // A sync client may touch the file several times for one
+                // new version; report each version once
+                let mut reported = reported.lock().unwrap_or_else(PoisonError::into_inner);
+                if !external {
+                    *reported = None;
+                } else if *reported != current {
+                    *reported = current;
+                    on_change();
                 }
+            })
+            .map_err(watch_error)?;
+        watcher
+            .watch(&directory, RecursiveMode::NonRecursive)
+            .map_err(watch_error)?;
+        Ok(Some(ArchiveWatch::new(watcher)))
+    }
+}
 
-                debug!("Archive extraction completed successfully");
-                Ok(file_map)
-            }
-            Err(e) => {
-                error!("Archive extraction failed: {}", e);
-
-                // Log directory contents for debugging
-                debug!("Checking temp directory contents after extraction failure:");
-                if let Ok(entries) = std::fs::read_dir(&temp_dir) {
-                    let mut file_count = 0;
-                    for entry in entries {
-                        if let Ok(entry) = entry {
-                            debug!("  - {:?}", entry.path());
-                            file_count += 1;
-                        }
-                    }
-                    debug!(
-                        "Found {} items in temp directory after failed extraction",
-                        file_count
-                    );
-                } else {
-                    debug!("Could not read temp directory contents for debugging");
-                }
+/// Write `data` to `path` through a temporary file that is flushed to disk
+/// and renamed into place
+pub(crate) fn write_file_atomically(path: &str, data: &[u8]) -> FileResult<()> {
+    stage_file(path, data)?.persist_to(path)
+}
 
-                // Clean up temp files on error
-                let _ = std::fs::remove_file(&temp_archive);
-                let _ = std::fs::remove_dir_all(&temp_dir);
+/// A file written and flushed next to the archive it will replace
+///
+/// Dropping it without [`persist_to`](Self::persist_to) removes it.
+struct StagedFile(tempfile::NamedTempFile);
 
-                // Check for password-related errors
-                let error_str = e.to_string().to_lowercase();
-                if error_str.contains("password")
-                    || error_str.contains("wrong")
-                    || error_str.contains("decrypt")
-                {
-                    error!("Archive extraction failed due to password issue");
-                    Err(FileError::InvalidPassword)
-                } else {
-                    error!("Archive extraction failed due to format/corruption issue");
-                    Err(FileError::ExtractionFailed {
-                        message: format!("Failed to extract 7z archive: {}", e),
-                    })
-                }
-            }
-        }
+impl StagedFile {
+    fn path(&self) -> &std::path::Path {
+        self.0.path()
     }
 
-    fn create_archive(&self, files: FileMap, password: &str) -> FileResult<Vec<u8>> {
-        // Create temporary directory to write files
-        let temp_dir =
-            std::env::temp_dir().join(format!("ziplock_create_{}", uuid::Uuid::new_v4()));
+    /// Rename the file over `path` and flush the directory entry
+    fn persist_to(self, path: &str) -> FileResult<()> {
+        self.0.persist(path).map_err(|e| match e.error.kind() {
+            std::io::ErrorKind::PermissionDenied => FileError::PermissionDenied {
+                path: path.to_string(),
+            },
+            _ => FileError::ReplaceFailed {
+                path: path.to_string(),
+                message: e.error.to_string(),
+            },
+        })?;
+        sync_parent(path);
+        Ok(())
+    }
+}
 
-        debug!(
-            "Creating temp directory for archive creation: {:?}",
-            temp_dir
-        );
-        debug!("Files to be archived: {} files", files.len());
-        for (path, content) in &files {
-            debug!("  - File: {} ({} bytes)", path, content.len());
+/// Write `data` to a new temporary file in the directory of `path` and
+/// flush it to disk
+fn stage_file(path: &str, data: &[u8]) -> FileResult<StagedFile> {
+    use std::io::Write;
+
+    let target = std::path::Path::new(path);
+    let directory = parent_dir(target);
+    if let Err(e) = std::fs::create_dir_all(directory) {
+        if e.kind() != std::io::ErrorKind::AlreadyExists {
+            return Err(FileError::IoError {
+                message: format!("Failed to create directory for '{}': {}", path, e),
+            });
         }
+    }
 
-        std::fs::create_dir_all(&temp_dir).map_err(|e| {
-            error!("Failed to create temp directory {:?}: {}", temp_dir, e);
-            FileError::CreationFailed {
-                message: format!("Failed to create temp directory: {}", e),
-            }
+    let write_error = |e: std::io::Error| match e.kind() {
+        std::io::ErrorKind::StorageFull => FileError::DiskFull {
+            path: path.to_string(),
+        },
+        std::io::ErrorKind::PermissionDenied => FileError::PermissionDenied {
+            path: path.to_string(),
+        },
+        _ => FileError::IoError {
+            message: format!("Failed to write archive '{}': {}", path, e),
+        },
+    };
+    let name = target
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mut file = tempfile::Builder::new()
+        .prefix(&format!(".{}.", name))
+        .suffix(".tmp")
+        .tempfile_in(directory)
+        .map_err(write_error)?;
+    file.write_all(data).map_err(write_error)?;
+    file.as_file()
+        .sync_all()
+        .map_err(|e| FileError::SyncFailed {
+            path: path.to_string(),
+            message: e.to_string(),
         })?;
+    Ok(StagedFile(file))
+}
 
-        debug!("Temp directory created successfully: {:?}", temp_dir);
-
-        // Write files to temporary directory with Windows path separator fix
-        let mut files_written = 0;
-        for (path, content) in files {
-            // Simple Windows path fix: convert forward slashes to backslashes on Windows
-            let normalized_path = if cfg!(windows) {
-                path.replace('/', "\\")
-            } else {
-                path.clone()
-            };
-
-            let file_path = temp_dir.join(&normalized_path);
-            debug!(
-                "Writing file: {} -> {:?} ({} bytes)",
-                path,
-                file_path,
-                content.len()
-            );
-
-            // Create parent directory
-            if let Some(parent) = file_path.parent() {
-                debug!("Creating parent directory: {:?}", parent);
-                std::fs::create_dir_all(parent).map_err(|e| FileError::CreationFailed {
-                    message: format!("Failed to create directory structure: {}", e),
-                })?;
-                debug!("Parent directory created successfully: {:?}", parent);
-            }
+/// Keep the archive at `path`, if there is one, as `<path>.bak`
+///
+/// A hard link costs no space and keeps pointing at the old archive once
+/// the new one is renamed over `path`; filesystems without links get a copy.
+fn keep_backup(path: &str) -> FileResult<()> {
+    if !std::path::Path::new(path).exists() {
+        return Ok(());
+    }
+    let backup = format!("{}.bak", path);
+    let backup_error = |e: std::io::Error| FileError::BackupFailed {
+        path: backup.clone(),
+        message: e.to_string(),
+    };
+    match std::fs::remove_file(&backup) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(backup_error(e)),
+        _ => {}
+    }
+    if std::fs::hard_link(path, &backup).is_err() {
+        std::fs::copy(path, &backup).map_err(backup_error)?;
+        sync_file(&backup).map_err(|e| FileError::BackupFailed {
+            path: backup.clone(),
+            message: e.to_string(),
+        })?;
+    }
+    sync_parent(&backup);
+    Ok(())
+}
 
-            // Write file
-            std::fs::write(&file_path, &content).map_err(|e| FileError::CreationFailed {
-                message: format!("Failed to write file '{}': {}", path, e),
-            })?;
-
-            // Verify file was written correctly
-            let written_size = std::fs::metadata(&file_path).map(|m| m.len()).unwrap_or(0);
-            debug!(
-                "File written successfully: {} ({} bytes on disk)",
-                path, written_size
-            );
-
-            if written_size != content.len() as u64 {
-                warn!(
-                    "Size mismatch for file {}: expected {} bytes, found {} bytes",
-                    path,
-                    content.len(),
-                    written_size
-                );
-            }
+/// Flush the file at `path` to disk
+fn sync_file(path: &str) -> FileResult<()> {
+    std::fs::File::open(path)
+        .and_then(|file| file.sync_all())
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => FileError::NotFound {
+                path: path.to_string(),
+            },
+            _ => FileError::SyncFailed {
+                path: path.to_string(),
+                message: e.to_string(),
+            },
+        })
+}
 
-            files_written += 1;
-        }
+/// Flush the directory entry of `path`, so a rename survives a crash
+///
+/// Some filesystems cannot sync directories; the rename has happened either
+/// way, so a failure is only logged.
+fn sync_parent(path: &str) {
+    #[cfg(unix)]
+    if let Err(e) =
+        std::fs::File::open(parent_dir(std::path::Path::new(path))).and_then(|dir| dir.sync_all())
+    {
+        tracing::warn!("Failed to flush the directory of '{}': {}", path, e);
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+}
 
-        debug!(
-            "Successfully wrote {} files to temp directory",
-            files_written
-        );
+fn parent_dir(path: &std::path::Path) -> &std::path::Path {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => std::path::Path::new("."),
+    }
+}
 
-        // Verify directory contents before archiving
-        debug!("Verifying temp directory contents before archiving:");
-        match std::fs::read_dir(&temp_dir) {
-            Ok(entries) => {
-                for entry in entries {
-                    if let Ok(entry) = entry {
-                        let path = entry.path();
-                        if let Ok(metadata) = entry.metadata() {
-                            if metadata.is_dir() {
-                                debug!("  DIR:  {:?}", path);
-                            } else {
-                                debug!("  FILE: {:?} ({} bytes)", path, metadata.len());
-                            }
-                        } else {
-                            warn!("Could not read metadata for {:?}", path);
-                            debug!("  UNKNOWN: {:?}", path);
-                        }
-                    }
-                }
-            }
-            Err(e) => {
-                error!("Failed to read temp directory contents: {}", e);
-            }
-        }
+/// Reads the archive bytes stored under a path
+pub type ReadArchiveFn = dyn Fn(&str) -> FileResult<Vec<u8>> + Send + Sync;
 
-        // Create archive from temporary directory
-        let temp_archive = temp_dir.with_extension("7z");
-        debug!(
-            "Creating archive: {:?} from directory: {:?}",
-            temp_archive, temp_dir
-        );
+/// Stores archive bytes under a path
+pub type WriteArchiveFn = dyn Fn(&str, &[u8]) -> FileResult<()> + Send + Sync;
 
-        let has_password = !password.is_empty();
-        debug!(
-            "Archive encryption: {}",
-            if has_password { "enabled" } else { "disabled" }
-        );
+/// File provider for browsers, which have no filesystem to open paths on
+///
+/// The archive bytes are read and written through callbacks, which the web
+/// app backs with the Origin Private File System or IndexedDB; a path is just
+/// the key the archive is stored under. Archives are built and opened in
+/// memory by the [`ArchiveBackend`], 7z unless another one is given.
+///
+/// The callbacks are synchronous. OPFS offers that through sync access
+/// handles in a worker; an IndexedDB store has to be loaded into memory
+/// before the vault is opened and written back after it is saved.
+pub struct WasmFileProvider<B: ArchiveBackend = SevenZipBackend> {
+    read: Box<ReadArchiveFn>,
+    write: Box<WriteArchiveFn>,
+    backend: B,
+}
 
-        let result = if password.is_empty() {
-            debug!("Calling sevenz_rust2::compress_to_path without password");
-            sevenz_rust2::compress_to_path(&temp_dir, &temp_archive)
-        } else {
-            debug!("Calling sevenz_rust2::compress_to_path_encrypted with password");
-            sevenz_rust2::compress_to_path_encrypted(&temp_dir, &temp_archive, password.into())
-        };
+impl WasmFileProvider {
+    /// Create a provider that stores archives through `read` and `write`
+    pub fn new(
+        read: impl Fn(&str) -> FileResult<Vec<u8>> + Send + Sync + 'static,
+        write: impl Fn(&str, &[u8]) -> FileResult<()> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            read: Box::new(read),
+            write: Box::new(write),
+            backend: SevenZipBackend,
+        }
+    }
+}
 
-        match result {
-            Ok(()) => {
-                debug!("Archive creation successful, verifying archive file");
+impl<B: ArchiveBackend> WasmFileProvider<B> {
+    /// Store archives in another format
+    pub fn with_backend<C: ArchiveBackend>(self, backend: C) -> WasmFileProvider<C> {
+        WasmFileProvider {
+            read: self.read,
+            write: self.write,
+            backend,
+        }
+    }
+}
 
-                // Verify archive was created
-                let archive_metadata = std::fs::metadata(&temp_archive).map_err(|e| {
-                    error!("Archive file not found after creation: {}", e);
-                    FileError::CreationFailed {
-                        message: format!("Archive file not found after creation: {}", e),
-                    }
-                })?;
+impl<B: ArchiveBackend> std::fmt::Debug for WasmFileProvider<B> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WasmFileProvider")
+            .field("backend", &self.backend.name())
+            .finish()
+    }
+}
 
-                debug!(
-                    "Archive created successfully: {:?} ({} bytes)",
-                    temp_archive,
-                    archive_metadata.len()
-                );
-
-                // Read the created archive into memory
-                let archive_data = std::fs::read(&temp_archive).map_err(|e| {
-                    error!("Failed to read created archive {:?}: {}", temp_archive, e);
-                    FileError::CreationFailed {
-                        message: format!("Failed to read created archive: {}", e),
-                    }
-                })?;
+impl<B: ArchiveBackend> FileOperationProvider for WasmFileProvider<B> {
+    fn read_archive(&self, path: &str) -> FileResult<Vec<u8>> {
+        (self.read)(path)
+    }
 
-                debug!(
-                    "Archive data read into memory: {} bytes",
-                    archive_data.len()
-                );
+    fn write_archive(&self, path: &str, data: &[u8]) -> FileResult<()> {
+        (self.write)(path, data)
+    }
 
-                // Clean up temporary files
-                debug!("Cleaning up temporary files");
-                if let Err(e) = std::fs::remove_dir_all(&temp_dir) {
-                    warn!("Failed to remove temp directory {:?}: {}", temp_dir, e);
-                }
-                if let Err(e) = std::fs::remove_file(&temp_archive) {
-                    warn!("Failed to remove temp archive {:?}: {}", temp_archive, e);
-                }
+    fn extract_archive(&self, data: &[u8], password: &str) -> FileResult<FileMap> {
+        self.backend.extract(data, password)
+    }
 
-                debug!("Archive creation completed successfully");
-                Ok(archive_data)
-            }
-            Err(e) => {
-                error!("Archive creation failed: {}", e);
-
-                // Log directory contents for debugging
-                debug!("Directory contents at failure:");
-                if let Ok(entries) = std::fs::read_dir(&temp_dir) {
-                    for entry in entries {
-                        if let Ok(entry) = entry {
-                            debug!("  - {:?}", entry.path());
-                        }
-                    }
-                } else {
-                    debug!("Could not read temp directory for debugging");
-                }
+    fn create_archive(&self, files: FileMap, password: &str) -> FileResult<Vec<u8>> {
+        self.backend.create(files, password)
+    }
 
-                // Clean up temporary files on error
-                let _ = std::fs::remove_dir_all(&temp_dir);
-                let _ = std::fs::remove_file(&temp_archive);
+    fn create_archive_with_options(
+        &self,
+        files: FileMap,
+        password: &str,
+        options: &ArchiveOptions,
+    ) -> FileResult<Vec<u8>> {
+        self.backend.create_with_options(files, password, options)
+    }
 
-                Err(FileError::CreationFailed {
-                    message: format!("Failed to create 7z archive: {}", e),
-                })
-            }
-        }
+    fn archive_backend(&self) -> &str {
+        self.backend.name()
     }
 }
 
@@ -611,8 +942,128 @@ mod tests {
     fn test_desktop_file_provider_creation() {
         let provider = DesktopFileProvider::new();
 
-        // Test that we can create the provider (actual file operations would need real files)
-        assert!(std::mem::size_of_val(&provider) == 0); // Zero-sized type
+        // Archives it has not read or written are never reported as changed
+        assert!(!provider.archive_changed("/nonexistent/vault.7z").unwrap());
+    }
+
+    #[test]
+    fn test_desktop_verified_write_keeps_previous_archive() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vault.7z");
+        let path = path.to_str().unwrap();
+        let backup = format!("{}.bak", path);
+        let provider = DesktopFileProvider::new();
+        let accept = |_: &[u8]| Ok(());
+
+        provider
+            .write_archive_verified(path, b"first", &accept)
+            .unwrap();
+        assert!(!std::path::Path::new(&backup).exists());
+        provider
+            .write_archive_verified(path, b"second", &accept)
+            .unwrap();
+        assert_eq!(std::fs::read(path).unwrap(), b"second");
+        assert_eq!(std::fs::read(&backup).unwrap(), b"first");
+
+        let reject = |written: &[u8]| {
+            assert_eq!(written, b"third");
+            Err(FileError::VerificationFailed {
+                message: "does not open".to_string(),
+            })
+        };
+        assert!(matches!(
+            provider.write_archive_verified(path, b"third", &reject),
+            Err(FileError::VerificationFailed { .. })
+        ));
+        assert_eq!(std::fs::read(path).unwrap(), b"second");
+        assert_eq!(std::fs::read(&backup).unwrap(), b"first");
+
+        // No temporary files are left behind
+        let mut names: Vec<String> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        assert_eq!(names, ["vault.7z", "vault.7z.bak"]);
+    }
+
+    #[test]
+    fn test_desktop_update_is_verified_and_keeps_previous_archive() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vault.7z");
+        let path = path.to_str().unwrap();
+        let provider = DesktopFileProvider::new();
+        let files = FileMap::from([("a.yml".to_string(), b"first".to_vec())]);
+        let original = provider.create_archive(files, "password").unwrap();
+        provider.write_archive(path, &original).unwrap();
+
+        let changed = FileMap::from([("b.yml".to_string(), b"second".to_vec())]);
+        let reject = |written: &[u8]| {
+            assert!(provider
+                .extract_archive(written, "password")
+                .unwrap()
+                .contains_key("b.yml"));
+            Err(FileError::VerificationFailed {
+                message: "does not open".to_string(),
+            })
+        };
+        assert!(matches!(
+            provider.update_archive(path, &changed, &[], "password", &reject),
+            Err(FileError::VerificationFailed { .. })
+        ));
+        assert_eq!(std::fs::read(path).unwrap(), original);
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+
+        let accept = |_: &[u8]| Ok(());
+        provider
+            .update_archive(path, &changed, &[], "password", &accept)
+            .unwrap();
+        let updated = provider
+            .extract_archive(&std::fs::read(path).unwrap(), "password")
+            .unwrap();
+        assert_eq!(updated.len(), 2);
+        assert_eq!(std::fs::read(format!("{}.bak", path)).unwrap(), original);
+    }
+
+    #[test]
+    fn test_wasm_file_provider_round_trip() {
+        use crate::core::UnifiedRepositoryManager;
+        use crate::models::CredentialRecord;
+        use std::sync::Mutex;
+
+        let store: Arc<Mutex<HashMap<String, Vec<u8>>>> = Arc::default();
+        let (reads, writes) = (store.clone(), store.clone());
+        let provider = WasmFileProvider::new(
+            move |path| {
+                reads
+                    .lock()
+                    .unwrap()
+                    .get(path)
+                    .cloned()
+                    .ok_or_else(|| FileError::NotFound {
+                        path: path.to_string(),
+                    })
+            },
+            move |path, data| {
+                writes
+                    .lock()
+                    .unwrap()
+                    .insert(path.to_string(), data.to_vec());
+                Ok(())
+            },
+        );
+
+        let mut manager = UnifiedRepositoryManager::new(provider);
+        manager.create_repository("vault", "password").unwrap();
+        let credential = CredentialRecord::new("Bank".to_string(), "login".to_string());
+        let id = credential.id.clone();
+        manager.add_credential(credential).unwrap();
+        manager.close_repository(true).unwrap();
+        assert!(store.lock().unwrap().contains_key("vault"));
+
+        manager.open_repository("vault", "password").unwrap();
+        assert_eq!(manager.get_credential_readonly(&id).unwrap().title, "Bank");
+        assert!(manager.open_repository("missing", "password").is_err());
     }
 
     // Note: Full desktop provider tests would require setting up test files
@@ -0,0 +1,771 @@
+//! Integrity checks and repair for repository archives
+//!
+//! Loading an archive stops at the first problem it meets. [`check_files`]
+//! looks at the same extracted contents and lists every problem instead: a
+//! missing or unreadable `metadata.yml`, a format version newer than this
+//! library, credential files that do not parse or are stored under another
+//! ID, duplicate IDs, a credential count the metadata disagrees with,
+//! credentials that fail validation or use a newer record layout, files
+//! nothing refers to and references to attachments that are not in the
+//! archive.
+//!
+//! [`repair_files`] fixes what can be fixed without guessing. Entries that
+//! cannot be read, and extra copies of a duplicated ID, are moved under
+//! `quarantine/` instead of being deleted, so the rest of the repository
+//! opens and nothing is lost. [`repair_benign`] applies the fixes that need
+//! no decision at all and is run on every open.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
+use crate::core::activity_log::AUDIT_DIR;
+use crate::core::errors::{CoreError, CoreResult};
+use crate::core::memory_repository::{is_template_path, note_path};
+use crate::core::trash::is_trash_path;
+use crate::core::types::{
+    FileMap, RepositoryMetadata, ATTACHMENTS_DIR, CREDENTIALS_DIR, CREDENTIALS_INDEX_FILE,
+    CURRENT_VERSION, METADATA_FILE, QUARANTINE_DIR,
+};
+use crate::models::{CredentialRecord, CREDENTIAL_SCHEMA_VERSION};
+use crate::utils::validation::validate_credential;
+use crate::utils::yaml::{deserialize_credential, deserialize_metadata, serialize_metadata};
+
+/// A problem found in a repository archive
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum IntegrityIssue {
+    /// `metadata.yml` is missing
+    MissingMetadata,
+    /// `metadata.yml` cannot be parsed
+    UnreadableMetadata { message: String },
+    /// The archive was written by a newer version of ZipLock
+    UnsupportedVersion { version: String },
+    /// The metadata's credential count disagrees with the credential files
+    CredentialCountMismatch { expected: usize, found: usize },
+    /// A credential file is not valid UTF-8 YAML
+    UnreadableCredential { path: String, message: String },
+    /// A credential file is not stored under its own ID
+    MisplacedCredential { path: String, id: String },
+    /// Several credential files have the same ID
+    DuplicateId { id: String, paths: Vec<String> },
+    /// A credential was written with a newer record layout, whose extra
+    /// fields would be dropped on save
+    UnsupportedRecordVersion {
+        path: String,
+        id: String,
+        version: u32,
+    },
+    /// A file that is not part of the repository layout and that no
+    /// credential refers to
+    OrphanedEntry { path: String },
+    /// A credential fails validation
+    InvalidCredential {
+        id: String,
+        title: String,
+        errors: Vec<String>,
+    },
+    /// A field refers to an attachment that is not in the archive
+    DanglingAttachment {
+        id: String,
+        field: String,
+        path: String,
+    },
+}
+
+impl IntegrityIssue {
+    /// Whether [`repair_files`] fixes this issue
+    ///
+    /// Invalid credentials and dangling attachments need the user to decide
+    /// what the right value is; a newer format cannot be read at all.
+    pub fn is_repairable(&self) -> bool {
+        !matches!(
+            self,
+            IntegrityIssue::UnsupportedVersion { .. }
+                | IntegrityIssue::InvalidCredential { .. }
+                | IntegrityIssue::DanglingAttachment { .. }
+        )
+    }
+
+    /// Whether the fix loses nothing and needs no decision, so
+    /// [`repair_benign`] applies it when the repository is opened
+    ///
+    /// Orphaned entries are moved to `quarantine/`, which keeps them in the
+    /// archive; without that they would be dropped on the next save.
+    pub fn is_benign(&self) -> bool {
+        matches!(
+            self,
+            IntegrityIssue::CredentialCountMismatch { .. }
+                | IntegrityIssue::MisplacedCredential { .. }
+                | IntegrityIssue::OrphanedEntry { .. }
+        )
+    }
+}
+
+impl fmt::Display for IntegrityIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IntegrityIssue::MissingMetadata => write!(f, "{} is missing", METADATA_FILE),
+            IntegrityIssue::UnreadableMetadata { message } => {
+                write!(f, "{} cannot be read: {}", METADATA_FILE, message)
+            }
+            IntegrityIssue::UnsupportedVersion { version } => write!(
+                f,
+                "Repository format {} is newer than this version of ZipLock supports",
+                version
+            ),
+            IntegrityIssue::CredentialCountMismatch { expected, found } => write!(
+                f,
+                "Metadata claims {} credentials but found {}",
+                expected, found
+            ),
+            IntegrityIssue::UnreadableCredential { path, message } => {
+                write!(f, "Credential file {} cannot be read: {}", path, message)
+            }
+            IntegrityIssue::MisplacedCredential { path, id } => {
+                write!(f, "Credential {} is stored at {}", id, path)
+            }
+            IntegrityIssue::DuplicateId { id, paths } => {
+                write!(f, "Credential ID {} is used by {}", id, paths.join(", "))
+            }
+            IntegrityIssue::UnsupportedRecordVersion { path, version, .. } => write!(
+                f,
+                "Credential file {} uses record layout {}, newer than this version of ZipLock supports",
+                path, version
+            ),
+            IntegrityIssue::OrphanedEntry { path } => {
+                write!(f, "{} is not part of the repository", path)
+            }
+            IntegrityIssue::InvalidCredential { title, errors, .. } => {
+                write!(f, "Invalid credential '{}': {}", title, errors.join("; "))
+            }
+            IntegrityIssue::DanglingAttachment { id, field, path } => write!(
+                f,
+                "Field '{}' of credential {} refers to missing attachment {}",
+                field, id, path
+            ),
+        }
+    }
+}
+
+/// Result of checking a repository archive
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IntegrityReport {
+    /// Everything found, in the order it was checked
+    pub issues: Vec<IntegrityIssue>,
+    /// Number of credential files looked at
+    pub credentials_checked: usize,
+}
+
+impl IntegrityReport {
+    /// Whether no issues were found
+    pub fn is_healthy(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// Whether [`repair_files`] would change anything
+    pub fn needs_repair(&self) -> bool {
+        self.issues.iter().any(IntegrityIssue::is_repairable)
+    }
+}
+
+/// Result of repairing a repository archive
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RepairReport {
+    /// Issues that were fixed
+    pub repaired: Vec<IntegrityIssue>,
+    /// Archive paths the unreadable entries were moved to
+    pub quarantined: Vec<String>,
+    /// Issues left for the user
+    pub remaining: Vec<IntegrityIssue>,
+}
+
+/// Check the extracted contents of an archive
+pub fn check_files(file_map: &FileMap) -> IntegrityReport {
+    let mut issues = Vec::new();
+
+    let metadata = match file_map.get(METADATA_FILE) {
+        None => {
+            issues.push(IntegrityIssue::MissingMetadata);
+            None
+        }
+        Some(bytes) => match parse_metadata(bytes) {
+            Ok(metadata) => Some(metadata),
+            Err(message) => {
+                issues.push(IntegrityIssue::UnreadableMetadata { message });
+                None
+            }
+        },
+    };
+    if let Some(metadata) = &metadata {
+        if is_newer_version(&metadata.version) {
+            issues.push(IntegrityIssue::UnsupportedVersion {
+                version: metadata.version.clone(),
+            });
+        }
+    }
+
+    let scan = Scan::new(file_map);
+    for (path, message) in &scan.unreadable {
+        issues.push(IntegrityIssue::UnreadableCredential {
+            path: path.clone(),
+            message: message.clone(),
+        });
+    }
+    for (id, copies) in &scan.credentials {
+        if copies.len() > 1 {
+            issues.push(IntegrityIssue::DuplicateId {
+                id: id.clone(),
+                paths: copies.iter().map(|(path, _)| path.clone()).collect(),
+            });
+        }
+        let (path, credential) = &copies[0];
+        if *path != record_path(id) {
+            issues.push(IntegrityIssue::MisplacedCredential {
+                path: path.clone(),
+                id: id.clone(),
+            });
+        }
+        if credential.schema_version > CREDENTIAL_SCHEMA_VERSION {
+            issues.push(IntegrityIssue::UnsupportedRecordVersion {
+                path: path.clone(),
+                id: id.clone(),
+                version: credential.schema_version,
+            });
+        }
+    }
+    if let Some(metadata) = &metadata {
+        if metadata.credential_count != scan.credentials.len() {
+            issues.push(IntegrityIssue::CredentialCountMismatch {
+                expected: metadata.credential_count,
+                found: scan.credentials.len(),
+            });
+        }
+    }
+
+    for (id, copies) in &scan.credentials {
+        let credential = &copies[0].1;
+        let validation = validate_credential(credential);
+        if !validation.is_valid {
+            issues.push(IntegrityIssue::InvalidCredential {
+                id: id.clone(),
+                title: credential.title.clone(),
+                errors: validation.errors,
+            });
+        }
+
+        let mut fields: Vec<_> = credential.fields.iter().collect();
+        fields.sort_by(|a, b| a.0.cmp(b.0));
+        for (name, field) in fields {
+            let reference = match field.note_file() {
+                Some(file) => note_path(id, file),
+                None => field.value.trim().replace('\\', "/"),
+            };
+            let is_reference =
+                field.is_note_unloaded() || reference.starts_with(&format!("{}/", ATTACHMENTS_DIR));
+            if is_reference && !file_map.contains_key(&reference) {
+                issues.push(IntegrityIssue::DanglingAttachment {
+                    id: id.clone(),
+                    field: name.clone(),
+                    path: reference,
+                });
+            }
+        }
+    }
+
+    for path in orphaned_entries(file_map, &scan) {
+        issues.push(IntegrityIssue::OrphanedEntry { path });
+    }
+
+    IntegrityReport {
+        issues,
+        credentials_checked: scan.records,
+    }
+}
+
+/// Apply only the [benign](IntegrityIssue::is_benign) fixes
+///
+/// Returns the contents unchanged if there is nothing to fix or if fixing
+/// needs more than benign changes; opening then proceeds, or fails, as it
+/// would have. The returned issues are the ones fixed.
+pub fn repair_benign(file_map: FileMap) -> CoreResult<(FileMap, Vec<IntegrityIssue>)> {
+    let report = check_files(&file_map);
+    let blocked = report.issues.iter().any(|issue| {
+        matches!(issue, IntegrityIssue::UnsupportedVersion { .. })
+            || (issue.is_repairable() && !issue.is_benign())
+    });
+    if blocked || !report.needs_repair() {
+        return Ok((file_map, Vec::new()));
+    }
+
+    let (repaired, report) = repair_files(file_map)?;
+    Ok((repaired, report.repaired))
+}
+
+/// Fix the repairable issues in the extracted contents of an archive
+///
+/// Unreadable credential files, credentials with a newer record layout,
+/// extra copies of a duplicated ID, orphaned entries and an unreadable
+/// `metadata.yml` are moved under `quarantine/`; the first copy of a
+/// duplicated ID is kept, preferring the one stored under its own ID.
+/// Misplaced credentials are moved to their own ID and the metadata is
+/// recreated or its credential count corrected. Fails if the archive was
+/// written by a newer version of ZipLock.
+pub fn repair_files(file_map: FileMap) -> CoreResult<(FileMap, RepairReport)> {
+    let report = check_files(&file_map);
+    if let Some(issue) = report
+        .issues
+        .iter()
+        .find(|issue| matches!(issue, IntegrityIssue::UnsupportedVersion { .. }))
+    {
+        return Err(CoreError::StructureError {
+            message: issue.to_string(),
+        });
+    }
+    if !report.needs_repair() {
+        return Ok((
+            file_map,
+            RepairReport {
+                remaining: report.issues,
+                ..RepairReport::default()
+            },
+        ));
+    }
+
+    let scan = Scan::new(&file_map);
+    let mut repaired = FileMap::new();
+    let mut quarantined = Vec::new();
+
+    for (path, _) in &scan.unreadable {
+        quarantine(&mut repaired, &mut quarantined, path, &file_map[path]);
+    }
+    let mut kept = 0;
+    for (id, copies) in &scan.credentials {
+        let (path, credential) = &copies[0];
+        let extra = if credential.schema_version > CREDENTIAL_SCHEMA_VERSION {
+            &copies[..]
+        } else {
+            repaired.insert(record_path(id), file_map[path].clone());
+            kept += 1;
+            &copies[1..]
+        };
+        for (path, _) in extra {
+            quarantine(&mut repaired, &mut quarantined, path, &file_map[path]);
+        }
+    }
+
+    let mut metadata = match file_map
+        .get(METADATA_FILE)
+        .map(|bytes| parse_metadata(bytes))
+    {
+        Some(Ok(metadata)) => metadata,
+        Some(Err(_)) => {
+            quarantine(
+                &mut repaired,
+                &mut quarantined,
+                METADATA_FILE,
+                &file_map[METADATA_FILE],
+            );
+            RepositoryMetadata::default()
+        }
+        None => RepositoryMetadata::default(),
+    };
+    metadata.credential_count = kept;
+    repaired.insert(
+        METADATA_FILE.to_string(),
+        serialize_metadata(&metadata)?.into_bytes(),
+    );
+
+    let orphaned: HashSet<String> = orphaned_entries(&file_map, &scan).into_iter().collect();
+    for path in &orphaned {
+        quarantine(&mut repaired, &mut quarantined, path, &file_map[path]);
+    }
+
+    // Anything else (attachments, the index, earlier quarantine) is kept
+    for (path, content) in file_map {
+        if path != METADATA_FILE
+            && !is_record_path(&path)
+            && !orphaned.contains(&path)
+            && !repaired.contains_key(&path)
+        {
+            repaired.insert(path, content);
+        }
+    }
+
+    let remaining = check_files(&repaired).issues;
+    Ok((
+        repaired,
+        RepairReport {
+            repaired: report
+                .issues
+                .into_iter()
+                .filter(IntegrityIssue::is_repairable)
+                .collect(),
+            quarantined,
+            remaining,
+        },
+    ))
+}
+
+/// Credential files of an archive, read the way loading reads them
+struct Scan {
+    /// Readable credentials by ID with the paths they were read from; the
+    /// copy to keep comes first
+    credentials: BTreeMap<String, Vec<(String, CredentialRecord)>>,
+    /// Paths of credential files that cannot be read, with the reason
+    unreadable: Vec<(String, String)>,
+    /// Number of credential files
+    records: usize,
+}
+
+impl Scan {
+    fn new(file_map: &FileMap) -> Self {
+        let mut paths: Vec<&String> = file_map.keys().filter(|p| is_record_path(p)).collect();
+        paths.sort();
+
+        let mut scan = Scan {
+            credentials: BTreeMap::new(),
+            unreadable: Vec::new(),
+            records: paths.len(),
+        };
+        for path in paths {
+            let parsed = std::str::from_utf8(&file_map[path])
+                .map_err(|e| format!("Invalid UTF-8: {}", e))
+                .and_then(|yaml| deserialize_credential(yaml).map_err(|e| e.to_string()));
+            match parsed {
+                Ok(credential) => scan
+                    .credentials
+                    .entry(credential.id.clone())
+                    .or_default()
+                    .push((path.clone(), credential)),
+                Err(message) => scan.unreadable.push((path.clone(), message)),
+            }
+        }
+        for (id, copies) in scan.credentials.iter_mut() {
+            let expected = record_path(id);
+            copies.sort_by_key(|(path, _)| *path != expected);
+        }
+        scan
+    }
+}
+
+/// Paths, sorted, that are not part of the repository layout and that no
+/// credential refers to as an attachment or note body
+fn orphaned_entries(file_map: &FileMap, scan: &Scan) -> Vec<String> {
+    let attachments: HashSet<String> = scan
+        .credentials
+        .iter()
+        .flat_map(|(id, copies)| copies[0].1.fields.values().map(move |field| (id, field)))
+        .filter_map(|(id, field)| match field.note_file() {
+            Some(file) => Some(note_path(id, file)),
+            None => Some(field.value.trim().replace('\\', "/"))
+                .filter(|value| value.starts_with(&format!("{}/", ATTACHMENTS_DIR))),
+        })
+        .collect();
+
+    let mut orphaned: Vec<String> = file_map
+        .keys()
+        .filter(|path| {
+            let normalized = path.replace('\\', "/");
+            normalized != METADATA_FILE
+                && normalized != CREDENTIALS_INDEX_FILE
+                && !is_record_path(&normalized)
+                && !is_template_path(&normalized)
+                && !normalized.starts_with(&format!("{}/", QUARANTINE_DIR))
+                && !normalized.starts_with(&format!("{}/", AUDIT_DIR))
+                && !is_trash_path(&normalized)
+                && !attachments.contains(&normalized)
+        })
+        .cloned()
+        .collect();
+    orphaned.sort();
+    orphaned
+}
+
+/// Whether `path` is a credential file, matching what loading accepts
+fn is_record_path(path: &str) -> bool {
+    let path = path.replace('\\', "/");
+    path.starts_with(CREDENTIALS_DIR) && path.ends_with("/record.yml")
+}
+
+fn record_path(id: &str) -> String {
+    format!("{}/{}/record.yml", CREDENTIALS_DIR, id)
+}
+
+fn parse_metadata(bytes: &[u8]) -> Result<RepositoryMetadata, String> {
+    let yaml = std::str::from_utf8(bytes).map_err(|e| format!("Invalid UTF-8: {}", e))?;
+    deserialize_metadata(yaml).map_err(|e| e.to_string())
+}
+
+/// Whether `version` has a higher major version than this library writes
+fn is_newer_version(version: &str) -> bool {
+    let major = |v: &str| {
+        v.split('.')
+            .next()
+            .and_then(|m| m.trim().parse::<u32>().ok())
+    };
+    match (major(version), major(CURRENT_VERSION)) {
+        (Some(found), Some(current)) => found > current,
+        _ => false,
+    }
+}
+
+/// Move `content` from `path` to a free path under `quarantine/`
+fn quarantine(files: &mut FileMap, quarantined: &mut Vec<String>, path: &str, content: &[u8]) {
+    let base = format!("{}/{}", QUARANTINE_DIR, path.replace('\\', "/"));
+    let mut target = base.clone();
+    let mut n = 1;
+    while files.contains_key(&target) {
+        target = format!("{}.{}", base, n);
+        n += 1;
+    }
+    files.insert(target.clone(), content.to_vec());
+    quarantined.push(target);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::memory_repository::UnifiedMemoryRepository;
+    use crate::models::CredentialField;
+    use crate::utils::yaml::serialize_credential;
+
+    fn healthy_files(titles: &[&str]) -> (FileMap, Vec<CredentialRecord>) {
+        let mut repo = UnifiedMemoryRepository::new();
+        repo.initialize().unwrap();
+        let mut credentials = Vec::new();
+        for title in titles {
+            let credential = CredentialRecord::new(title.to_string(), "login".to_string());
+            repo.add_credential(credential.clone()).unwrap();
+            credentials.push(credential);
+        }
+        (repo.serialize_to_files().unwrap(), credentials)
+    }
+
+    #[test]
+    fn test_healthy_archive_has_no_issues() {
+        let (files, _) = healthy_files(&["GitHub", "Email"]);
+        let report = check_files(&files);
+        assert!(report.is_healthy(), "{:?}", report.issues);
+        assert_eq!(report.credentials_checked, 2);
+
+        let (repaired, repair) = repair_files(files.clone()).unwrap();
+        assert_eq!(repaired, files);
+        assert!(repair.repaired.is_empty());
+    }
+
+    #[test]
+    fn test_check_reports_every_problem() {
+        let (mut files, credentials) = healthy_files(&["GitHub", "Email"]);
+        let github = &credentials[0];
+
+        files.insert(
+            "credentials/broken/record.yml".to_string(),
+            b"title: [unterminated".to_vec(),
+        );
+        // A second copy of GitHub under another directory
+        files.insert(
+            "credentials/copy/record.yml".to_string(),
+            serialize_credential(github).unwrap().into_bytes(),
+        );
+        let mut linked = credentials[1].clone();
+        linked.title = String::new();
+        linked.set_field(
+            "scan",
+            CredentialField::text("attachments/missing.pdf".to_string()),
+        );
+        files.insert(
+            record_path(&linked.id),
+            serialize_credential(&linked).unwrap().into_bytes(),
+        );
+
+        let issues = check_files(&files).issues;
+        assert!(issues.iter().any(|i| matches!(
+            i,
+            IntegrityIssue::UnreadableCredential { path, .. } if path == "credentials/broken/record.yml"
+        )));
+        assert!(issues.contains(&IntegrityIssue::DuplicateId {
+            id: github.id.clone(),
+            paths: vec![
+                record_path(&github.id),
+                "credentials/copy/record.yml".to_string()
+            ],
+        }));
+        assert!(issues.iter().any(|i| matches!(
+            i,
+            IntegrityIssue::InvalidCredential { id, .. } if *id == linked.id
+        )));
+        assert!(issues.contains(&IntegrityIssue::DanglingAttachment {
+            id: linked.id.clone(),
+            field: "scan".to_string(),
+            path: "attachments/missing.pdf".to_string(),
+        }));
+
+        files.remove(METADATA_FILE);
+        assert!(check_files(&files)
+            .issues
+            .contains(&IntegrityIssue::MissingMetadata));
+    }
+
+    #[test]
+    fn test_repair_quarantines_corrupt_entries() {
+        let (mut files, credentials) = healthy_files(&["GitHub", "Email"]);
+        let github = &credentials[0];
+        let email = &credentials[1];
+        files.insert(
+            "credentials/broken/record.yml".to_string(),
+            vec![0xff, 0xfe],
+        );
+        files.insert(
+            "credentials/copy/record.yml".to_string(),
+            serialize_credential(github).unwrap().into_bytes(),
+        );
+        // Email stored under the wrong directory
+        let email_yaml = files.remove(&record_path(&email.id)).unwrap();
+        files.insert("credentials/old-id/record.yml".to_string(), email_yaml);
+        files.insert(METADATA_FILE.to_string(), b"version: [".to_vec());
+
+        let mut repo = UnifiedMemoryRepository::new();
+        assert!(repo.load_from_files(files.clone()).is_err());
+
+        let (repaired, report) = repair_files(files).unwrap();
+        assert!(report.remaining.is_empty(), "{:?}", report.remaining);
+        assert!(report
+            .repaired
+            .contains(&IntegrityIssue::MisplacedCredential {
+                path: "credentials/old-id/record.yml".to_string(),
+                id: email.id.clone(),
+            }));
+        let mut quarantined = report.quarantined.clone();
+        quarantined.sort();
+        assert_eq!(
+            quarantined,
+            vec![
+                "quarantine/credentials/broken/record.yml".to_string(),
+                "quarantine/credentials/copy/record.yml".to_string(),
+                "quarantine/metadata.yml".to_string(),
+            ]
+        );
+        assert_eq!(
+            repaired["quarantine/credentials/broken/record.yml"],
+            vec![0xff, 0xfe]
+        );
+
+        let mut repo = UnifiedMemoryRepository::new();
+        repo.load_from_files(repaired).unwrap();
+        assert_eq!(repo.list_credentials().unwrap().len(), 2);
+        assert_eq!(repo.quarantined_entries().len(), 3);
+        assert!(repo
+            .serialize_to_files()
+            .unwrap()
+            .contains_key("quarantine/metadata.yml"));
+    }
+
+    #[test]
+    fn test_orphans_and_record_versions() {
+        let (mut files, credentials) = healthy_files(&["GitHub", "Email"]);
+        let mut scan = credentials[0].clone();
+        scan.set_field("scan", CredentialField::text("attachments/scan.pdf"));
+        files.insert(
+            record_path(&scan.id),
+            serialize_credential(&scan).unwrap().into_bytes(),
+        );
+        files.insert("attachments/scan.pdf".to_string(), b"%PDF".to_vec());
+        files.insert("attachments/old.pdf".to_string(), b"%PDF".to_vec());
+        files.insert("notes.txt".to_string(), b"stray".to_vec());
+        files.insert(CREDENTIALS_INDEX_FILE.to_string(), Vec::new());
+        files.insert("quarantine/old.yml".to_string(), Vec::new());
+        files.insert("templates/vpn.yml".to_string(), Vec::new());
+
+        let mut newer = credentials[1].clone();
+        newer.schema_version = CREDENTIAL_SCHEMA_VERSION + 1;
+        files.insert(
+            record_path(&newer.id),
+            serialize_credential(&newer).unwrap().into_bytes(),
+        );
+
+        let issues = check_files(&files).issues;
+        assert_eq!(
+            issues,
+            vec![
+                IntegrityIssue::UnsupportedRecordVersion {
+                    path: record_path(&newer.id),
+                    id: newer.id.clone(),
+                    version: CREDENTIAL_SCHEMA_VERSION + 1,
+                },
+                IntegrityIssue::OrphanedEntry {
+                    path: "attachments/old.pdf".to_string()
+                },
+                IntegrityIssue::OrphanedEntry {
+                    path: "notes.txt".to_string()
+                },
+            ]
+        );
+
+        // A newer record is not benign, so opening leaves everything alone
+        let (unchanged, fixed) = repair_benign(files.clone()).unwrap();
+        assert_eq!(unchanged, files);
+        assert!(fixed.is_empty());
+
+        let (repaired, report) = repair_files(files).unwrap();
+        assert!(report.remaining.is_empty(), "{:?}", report.remaining);
+        assert!(repaired.contains_key(&format!("quarantine/{}", record_path(&newer.id))));
+        assert!(repaired.contains_key("quarantine/notes.txt"));
+        assert!(repaired.contains_key("attachments/scan.pdf"));
+        assert!(repaired.contains_key("quarantine/old.yml"));
+        let metadata = parse_metadata(&repaired[METADATA_FILE]).unwrap();
+        assert_eq!(metadata.credential_count, 1);
+    }
+
+    #[test]
+    fn test_repair_benign_fixes_count_and_orphans() {
+        let (mut files, credentials) = healthy_files(&["GitHub"]);
+        let mut metadata = parse_metadata(&files[METADATA_FILE]).unwrap();
+        metadata.credential_count = 5;
+        files.insert(
+            METADATA_FILE.to_string(),
+            serialize_metadata(&metadata).unwrap().into_bytes(),
+        );
+        files.insert("notes.txt".to_string(), b"stray".to_vec());
+
+        let (repaired, fixed) = repair_benign(files).unwrap();
+        assert_eq!(
+            fixed,
+            vec![
+                IntegrityIssue::CredentialCountMismatch {
+                    expected: 5,
+                    found: 1
+                },
+                IntegrityIssue::OrphanedEntry {
+                    path: "notes.txt".to_string()
+                },
+            ]
+        );
+        assert!(check_files(&repaired).is_healthy());
+        assert!(repaired.contains_key(&record_path(&credentials[0].id)));
+
+        // Unreadable records need an explicit repair
+        let (mut files, _) = healthy_files(&["GitHub"]);
+        files.insert("credentials/x/record.yml".to_string(), b"[".to_vec());
+        let (unchanged, fixed) = repair_benign(files.clone()).unwrap();
+        assert_eq!(unchanged, files);
+        assert!(fixed.is_empty());
+    }
+
+    #[test]
+    fn test_repair_refuses_newer_formats() {
+        let (mut files, _) = healthy_files(&["GitHub"]);
+        let mut metadata = parse_metadata(&files[METADATA_FILE]).unwrap();
+        metadata.version = "2.0".to_string();
+        metadata.credential_count = 0;
+        files.insert(
+            METADATA_FILE.to_string(),
+            serialize_metadata(&metadata).unwrap().into_bytes(),
+        );
+
+        let report = check_files(&files);
+        assert!(report.issues.contains(&IntegrityIssue::UnsupportedVersion {
+            version: "2.0".to_string()
+        }));
+        assert!(repair_files(files).is_err());
+    }
+}
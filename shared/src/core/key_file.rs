@@ -0,0 +1,125 @@
+//! Key files as a second factor for archive encryption
+//!
+//! A repository can require a key file in addition to the master password.
+//! The file's contents never leave the device; only their SHA-256 digest is
+//! kept, and it is combined with the password into the composite key that
+//! encrypts the archive:
+//!
+//! ```text
+//! hex(SHA-256(SHA-256(password) || SHA-256(key file)))
+//! ```
+//!
+//! Any file can serve as a key file, but [`KeyFile::generate_contents`]
+//! gives one with 256 bits of randomness.
+
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::path::Path;
+use zeroize::Zeroize;
+
+use crate::core::errors::{CoreError, CoreResult, FileError};
+use crate::utils::encryption::EncryptionUtils;
+
+/// Size of a generated key file in bytes
+pub const GENERATED_KEY_FILE_LEN: usize = 64;
+
+/// A loaded key file, reduced to its digest
+#[derive(Clone, PartialEq, Eq)]
+pub struct KeyFile {
+    digest: [u8; 32],
+}
+
+impl KeyFile {
+    /// Use the given bytes as the key file contents
+    pub fn from_bytes(contents: &[u8]) -> CoreResult<Self> {
+        if contents.is_empty() {
+            return Err(CoreError::ValidationError {
+                message: "Key file is empty".to_string(),
+            });
+        }
+        Ok(Self {
+            digest: Sha256::digest(contents).into(),
+        })
+    }
+
+    /// Read a key file from disk
+    pub fn from_path<P: AsRef<Path>>(path: P) -> CoreResult<Self> {
+        let path = path.as_ref();
+        let mut contents = std::fs::read(path).map_err(|e| {
+            let path = path.display().to_string();
+            CoreError::FileOperation(match e.kind() {
+                std::io::ErrorKind::NotFound => FileError::NotFound { path },
+                std::io::ErrorKind::PermissionDenied => FileError::PermissionDenied { path },
+                _ => FileError::IoError {
+                    message: format!("Failed to read key file {}: {}", path, e),
+                },
+            })
+        })?;
+        let key_file = Self::from_bytes(&contents);
+        contents.zeroize();
+        key_file
+    }
+
+    /// Random contents for a new key file
+    pub fn generate_contents() -> Vec<u8> {
+        EncryptionUtils::random_bytes(GENERATED_KEY_FILE_LEN)
+    }
+
+    /// The archive password for `password` combined with this key file
+    pub fn composite_password(&self, password: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(Sha256::digest(password.as_bytes()));
+        hasher.update(self.digest);
+        EncryptionUtils::to_hex(&hasher.finalize())
+    }
+}
+
+impl Drop for KeyFile {
+    fn drop(&mut self) {
+        self.digest.zeroize();
+    }
+}
+
+impl fmt::Debug for KeyFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KeyFile").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_composite_password_depends_on_both_factors() {
+        let key = KeyFile::from_bytes(b"key file contents").unwrap();
+        let other = KeyFile::from_bytes(b"other contents").unwrap();
+
+        let composite = key.composite_password("password");
+        assert_eq!(composite.len(), 64);
+        assert_eq!(composite, key.composite_password("password"));
+        assert_ne!(composite, key.composite_password("Password"));
+        assert_ne!(composite, other.composite_password("password"));
+        assert_ne!(composite, "password");
+    }
+
+    #[test]
+    fn test_key_file_sources() {
+        let contents = KeyFile::generate_contents();
+        assert_eq!(contents.len(), GENERATED_KEY_FILE_LEN);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vault.key");
+        std::fs::write(&path, &contents).unwrap();
+        assert_eq!(
+            KeyFile::from_path(&path).unwrap(),
+            KeyFile::from_bytes(&contents).unwrap()
+        );
+
+        assert!(matches!(
+            KeyFile::from_path(dir.path().join("missing.key")),
+            Err(CoreError::FileOperation(FileError::NotFound { .. }))
+        ));
+        assert!(KeyFile::from_bytes(&[]).is_err());
+    }
+}
@@ -0,0 +1,152 @@
+//! Biometric unlock tokens for mobile apps
+//!
+//! The app keeps a key in the platform keystore (Android Keystore, iOS
+//! Keychain with `biometryCurrentSet`) that can only be used after a
+//! fingerprint or face check, and hands its secret to the library. The
+//! token seals the archive password with that secret, so opening it needs a
+//! successful biometric prompt. Enrolling a new fingerprint invalidates the
+//! keystore key and with it every token.
+//!
+//! The expiry is sealed together with the password, so editing the stored
+//! token cannot extend it.
+
+use base64::prelude::*;
+use chrono::{DateTime, Duration, Utc};
+use hkdf::Hkdf;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use zeroize::Zeroizing;
+
+use super::{ProtectorKind, ProtectorRecord};
+use crate::core::errors::{KeyProtectionError, KeyProtectionResult};
+use crate::utils::encryption::EncryptionUtils;
+
+/// Days a token stays valid unless the app asks for something else
+pub const DEFAULT_TOKEN_TTL_DAYS: i64 = 30;
+
+/// Prefix of encoded tokens
+const TOKEN_PREFIX: &str = "zlbt1:";
+
+const SECRET_INFO: &[u8] = b"ziplock-biometric-token-v1";
+
+/// The archive password sealed with a platform keystore secret
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BiometricToken {
+    /// When the token stops working (Unix seconds); informational, the
+    /// sealed copy is the one enforced
+    pub expires_at: i64,
+    record: ProtectorRecord,
+}
+
+impl BiometricToken {
+    /// Seal `password` with the keystore secret for `ttl`
+    pub fn create(
+        keystore_secret: &[u8],
+        password: &[u8],
+        ttl: Duration,
+        now: DateTime<Utc>,
+    ) -> KeyProtectionResult<Self> {
+        if keystore_secret.is_empty() || ttl <= Duration::zero() {
+            return Err(KeyProtectionError::DeviceError {
+                message: "Invalid biometric token parameters".to_string(),
+            });
+        }
+
+        let expires_at = (now + ttl).timestamp();
+        let salt = EncryptionUtils::random_bytes(32);
+        let mut payload = Zeroizing::new(expires_at.to_be_bytes().to_vec());
+        payload.extend_from_slice(password);
+
+        let record = ProtectorRecord::seal(
+            ProtectorKind::Biometric,
+            ProtectorKind::Biometric.name(),
+            String::new(),
+            &salt,
+            &derive_secret(keystore_secret, &salt),
+            &payload,
+        );
+        Ok(Self { expires_at, record })
+    }
+
+    /// Recover the password, failing if the token has expired or the secret
+    /// is not the one it was sealed with
+    pub fn unlock(
+        &self,
+        keystore_secret: &[u8],
+        now: DateTime<Utc>,
+    ) -> KeyProtectionResult<Zeroizing<Vec<u8>>> {
+        let salt = self.record.salt_bytes()?;
+        let payload = self.record.open(&derive_secret(keystore_secret, &salt))?;
+        if payload.len() < 8 {
+            return Err(KeyProtectionError::UnwrapFailed);
+        }
+
+        let (expiry, password) = payload.split_at(8);
+        let expires_at = i64::from_be_bytes(expiry.try_into().expect("split at 8 bytes"));
+        if now.timestamp() >= expires_at {
+            return Err(KeyProtectionError::Expired);
+        }
+        Ok(Zeroizing::new(password.to_vec()))
+    }
+
+    /// Encode for storage by the app
+    pub fn encode(&self) -> String {
+        let json = serde_json::to_vec(self).expect("token serialization cannot fail");
+        format!("{}{}", TOKEN_PREFIX, BASE64_URL_SAFE_NO_PAD.encode(json))
+    }
+
+    /// Decode a token from [`BiometricToken::encode`]
+    pub fn decode(encoded: &str) -> KeyProtectionResult<Self> {
+        encoded
+            .trim()
+            .strip_prefix(TOKEN_PREFIX)
+            .and_then(|data| BASE64_URL_SAFE_NO_PAD.decode(data).ok())
+            .and_then(|json| serde_json::from_slice(&json).ok())
+            .ok_or(KeyProtectionError::UnwrapFailed)
+    }
+}
+
+/// Stretch a keystore secret of any length into the record secret
+fn derive_secret(keystore_secret: &[u8], salt: &[u8]) -> Zeroizing<[u8; 32]> {
+    let mut secret = Zeroizing::new([0u8; 32]);
+    Hkdf::<Sha256>::new(Some(salt), keystore_secret)
+        .expand(SECRET_INFO, secret.as_mut())
+        .expect("32 bytes is a valid HKDF-SHA256 output length");
+    secret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_biometric_token_round_trip_and_expiry() {
+        let now = Utc::now();
+        let token =
+            BiometricToken::create(b"keystore secret", b"hunter2", Duration::days(7), now).unwrap();
+        let decoded = BiometricToken::decode(&token.encode()).unwrap();
+        assert_eq!(decoded, token);
+
+        assert_eq!(
+            decoded.unlock(b"keystore secret", now).unwrap().as_slice(),
+            b"hunter2"
+        );
+        assert_eq!(
+            decoded.unlock(b"new fingerprint", now),
+            Err(KeyProtectionError::UnwrapFailed)
+        );
+        assert_eq!(
+            decoded.unlock(b"keystore secret", now + Duration::days(8)),
+            Err(KeyProtectionError::Expired)
+        );
+
+        // The visible expiry is not the one enforced
+        let mut extended = decoded.clone();
+        extended.expires_at += 86_400 * 365;
+        assert_eq!(
+            extended.unlock(b"keystore secret", now + Duration::days(8)),
+            Err(KeyProtectionError::Expired)
+        );
+        assert!(BiometricToken::decode("not a token").is_err());
+    }
+}
@@ -0,0 +1,278 @@
+//! FIDO2 security keys as key protectors
+//!
+//! Registration creates a non-resident credential with the hmac-secret
+//! extension. Unlocking asks the key for `HMAC(credential secret, salt)`
+//! with the salt stored in the record; the 32-byte answer never leaves the
+//! key in any other form, so only that key (and a touch) recovers the
+//! password.
+
+use base64::prelude::*;
+use zeroize::Zeroizing;
+
+use super::{KeyProtector, ProtectorKind, ProtectorRecord};
+use crate::core::errors::{CoreResult, KeyProtectionError, KeyProtectionResult};
+use crate::utils::encryption::EncryptionUtils;
+
+/// Relying party ID the credentials are registered under
+pub const RELYING_PARTY_ID: &str = "ziplock.local";
+
+/// An authenticator that supports the hmac-secret extension
+pub trait HmacSecretDevice {
+    /// Create a credential with hmac-secret enabled and return its ID
+    fn make_credential(&self, rp_id: &str, user_name: &str) -> KeyProtectionResult<Vec<u8>>;
+
+    /// Evaluate the credential's hmac-secret over a 32-byte salt
+    fn hmac_secret(
+        &self,
+        rp_id: &str,
+        credential_id: &[u8],
+        salt: &[u8; 32],
+    ) -> KeyProtectionResult<Zeroizing<[u8; 32]>>;
+}
+
+/// Key protector backed by a FIDO2 security key
+pub struct Fido2Protector<D: HmacSecretDevice> {
+    device: D,
+}
+
+impl<D: HmacSecretDevice> Fido2Protector<D> {
+    pub fn new(device: D) -> Self {
+        Self { device }
+    }
+}
+
+impl<D: HmacSecretDevice> KeyProtector for Fido2Protector<D> {
+    fn kind(&self) -> ProtectorKind {
+        ProtectorKind::Fido2HmacSecret
+    }
+
+    fn protect(&self, label: &str, password: &[u8]) -> CoreResult<ProtectorRecord> {
+        let credential_id = self.device.make_credential(RELYING_PARTY_ID, label)?;
+        let salt: [u8; 32] = EncryptionUtils::random_bytes(32)
+            .try_into()
+            .expect("random_bytes returns the requested length");
+        let secret = self
+            .device
+            .hmac_secret(RELYING_PARTY_ID, &credential_id, &salt)?;
+        Ok(ProtectorRecord::seal(
+            self.kind(),
+            label,
+            BASE64_STANDARD.encode(&credential_id),
+            &salt,
+            &secret,
+            password,
+        ))
+    }
+
+    fn unprotect(&self, record: &ProtectorRecord) -> CoreResult<Zeroizing<Vec<u8>>> {
+        let credential_id = BASE64_STANDARD
+            .decode(&record.handle)
+            .map_err(|_| KeyProtectionError::UnwrapFailed)?;
+        let salt: [u8; 32] = record
+            .salt_bytes()?
+            .try_into()
+            .map_err(|_| KeyProtectionError::UnwrapFailed)?;
+        let secret = self
+            .device
+            .hmac_secret(RELYING_PARTY_ID, &credential_id, &salt)?;
+        Ok(record.open(&secret)?)
+    }
+}
+
+pub use libfido2::LibFido2Device;
+
+/// Security keys driven through the libfido2 command line tools
+///
+/// `fido2-token`, `fido2-cred` and `fido2-assert` ship with libfido2 on
+/// Linux, Homebrew and Windows; mobile apps implement [`HmacSecretDevice`]
+/// over the platform FIDO APIs instead. Keys that require a PIN are not supported
+/// yet because the tools ask for it on a terminal.
+mod libfido2 {
+    use base64::prelude::*;
+    use zeroize::Zeroizing;
+
+    use super::super::run_tool;
+    use super::HmacSecretDevice;
+    use crate::core::errors::{KeyProtectionError, KeyProtectionResult};
+    use crate::utils::encryption::EncryptionUtils;
+
+    /// A security key found by `fido2-token -L`
+    #[derive(Debug, Clone)]
+    pub struct LibFido2Device {
+        path: String,
+    }
+
+    impl LibFido2Device {
+        /// The first connected security key
+        pub fn first() -> KeyProtectionResult<Self> {
+            let output = run("fido2-token", &["-L"], "")?;
+            output
+                .lines()
+                .find_map(parse_device_path)
+                .map(|path| Self { path })
+                .ok_or_else(|| KeyProtectionError::DeviceUnavailable {
+                    message: "No security key is connected".to_string(),
+                })
+        }
+
+        /// Device path as reported by libfido2, e.g. `/dev/hidraw3`
+        pub fn path(&self) -> &str {
+            &self.path
+        }
+    }
+
+    impl HmacSecretDevice for LibFido2Device {
+        fn make_credential(&self, rp_id: &str, user_name: &str) -> KeyProtectionResult<Vec<u8>> {
+            let input = format!(
+                "{}\n{}\n{}\n{}\n",
+                client_data_hash(),
+                rp_id,
+                user_name,
+                BASE64_STANDARD.encode(EncryptionUtils::random_bytes(16)),
+            );
+            let output = run("fido2-cred", &["-M", "-h", &self.path], &input)?;
+            // client data hash, rp id, format, authdata, credential id, signature, ...
+            output
+                .lines()
+                .nth(4)
+                .and_then(|line| BASE64_STANDARD.decode(line.trim()).ok())
+                .ok_or_else(|| unexpected_output("fido2-cred"))
+        }
+
+        fn hmac_secret(
+            &self,
+            rp_id: &str,
+            credential_id: &[u8],
+            salt: &[u8; 32],
+        ) -> KeyProtectionResult<Zeroizing<[u8; 32]>> {
+            let input = format!(
+                "{}\n{}\n{}\n{}\n",
+                client_data_hash(),
+                rp_id,
+                BASE64_STANDARD.encode(credential_id),
+                BASE64_STANDARD.encode(salt),
+            );
+            let output = Zeroizing::new(run("fido2-assert", &["-G", "-h", &self.path], &input)?);
+            // client data hash, rp id, authdata, signature, hmac-secret
+            let secret = output
+                .lines()
+                .nth(4)
+                .and_then(|line| BASE64_STANDARD.decode(line.trim()).ok())
+                .map(Zeroizing::new)
+                .ok_or_else(|| unexpected_output("fido2-assert"))?;
+            let mut result = Zeroizing::new([0u8; 32]);
+            if secret.len() != 32 {
+                return Err(unexpected_output("fido2-assert"));
+            }
+            result.copy_from_slice(&secret);
+            Ok(result)
+        }
+    }
+
+    /// Device path from a `fido2-token -L` line such as
+    /// `/dev/hidraw3: vendor=0x1050, product=0x0407 (Yubico YubiKey)`
+    pub(super) fn parse_device_path(line: &str) -> Option<String> {
+        let (path, _) = line.split_once(": ")?;
+        let path = path.trim();
+        (!path.is_empty()).then(|| path.to_string())
+    }
+
+    /// The key only signs the hash; nobody verifies the signature here
+    fn client_data_hash() -> String {
+        BASE64_STANDARD.encode(EncryptionUtils::random_bytes(32))
+    }
+
+    fn run(tool: &str, args: &[&str], input: &str) -> KeyProtectionResult<String> {
+        run_tool(tool, "libfido2", args, input)
+    }
+
+    fn unexpected_output(tool: &str) -> KeyProtectionError {
+        KeyProtectionError::DeviceError {
+            message: format!("Unexpected output from {}", tool),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hmac::{Hmac, Mac};
+    use sha2::Sha256;
+    use std::cell::Cell;
+
+    /// Authenticator that answers hmac-secret with HMAC-SHA256 over a device key
+    struct SoftKey {
+        device_key: [u8; 32],
+        present: Cell<bool>,
+    }
+
+    impl HmacSecretDevice for SoftKey {
+        fn make_credential(&self, _rp_id: &str, _user: &str) -> KeyProtectionResult<Vec<u8>> {
+            Ok(b"credential".to_vec())
+        }
+
+        fn hmac_secret(
+            &self,
+            rp_id: &str,
+            credential_id: &[u8],
+            salt: &[u8; 32],
+        ) -> KeyProtectionResult<Zeroizing<[u8; 32]>> {
+            if !self.present.get() {
+                return Err(KeyProtectionError::DeviceUnavailable {
+                    message: "unplugged".to_string(),
+                });
+            }
+            let mut mac = Hmac::<Sha256>::new_from_slice(&self.device_key).unwrap();
+            mac.update(rp_id.as_bytes());
+            mac.update(credential_id);
+            mac.update(salt);
+            Ok(Zeroizing::new(mac.finalize().into_bytes().into()))
+        }
+    }
+
+    #[test]
+    fn test_fido2_protector_round_trip() {
+        let key = Fido2Protector::new(SoftKey {
+            device_key: [1u8; 32],
+            present: Cell::new(true),
+        });
+        let record = key.protect("YubiKey", b"hunter2").unwrap();
+        assert_eq!(record.kind, ProtectorKind::Fido2HmacSecret);
+        assert_eq!(key.unprotect(&record).unwrap().as_slice(), b"hunter2");
+
+        let other = Fido2Protector::new(SoftKey {
+            device_key: [2u8; 32],
+            present: Cell::new(true),
+        });
+        assert!(matches!(
+            other.unprotect(&record),
+            Err(crate::core::CoreError::KeyProtection(
+                KeyProtectionError::UnwrapFailed
+            ))
+        ));
+
+        key.device.present.set(false);
+        assert!(matches!(
+            key.unprotect(&record),
+            Err(crate::core::CoreError::KeyProtection(
+                KeyProtectionError::DeviceUnavailable { .. }
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_parse_device_path() {
+        assert_eq!(
+            libfido2::parse_device_path(
+                "/dev/hidraw3: vendor=0x1050, product=0x0407 (Yubico YubiKey OTP+FIDO+CCID)"
+            )
+            .as_deref(),
+            Some("/dev/hidraw3")
+        );
+        assert_eq!(
+            libfido2::parse_device_path("ioreg://4294969943: vendor=0x1050").as_deref(),
+            Some("ioreg://4294969943")
+        );
+        assert_eq!(libfido2::parse_device_path(""), None);
+    }
+}
@@ -0,0 +1,233 @@
+//! The operating system's keychain as a key protector
+//!
+//! Registering stores a random 32-byte key in the user's keychain and wraps
+//! the archive password with it. The keychain opens when the user logs in,
+//! so the repository can be unlocked without the master password for as long
+//! as that session lasts. The wrapped password stays in the `.keys` file next
+//! to the archive: the keychain entry alone reveals nothing, and neither does
+//! the archive directory without the user's login.
+
+use uuid::Uuid;
+use zeroize::Zeroizing;
+
+use super::{KeyProtector, ProtectorKind, ProtectorRecord};
+use crate::core::errors::{CoreResult, KeyProtectionError, KeyProtectionResult};
+use crate::utils::encryption::EncryptionUtils;
+
+/// Service name the keychain entries are stored under
+pub const KEYCHAIN_SERVICE: &str = "ziplock";
+
+/// A place to keep small secrets that unlocks with the user's login
+pub trait KeychainProvider {
+    /// Name of the keychain shown to the user, e.g. "Secret Service"
+    fn name(&self) -> &'static str;
+
+    /// Store `secret` under `account`, replacing any existing entry
+    fn store(&self, account: &str, secret: &[u8]) -> KeyProtectionResult<()>;
+
+    /// Read the secret stored under `account`
+    ///
+    /// A missing entry is [`KeyProtectionError::UnwrapFailed`]: the record
+    /// exists but nothing can open it any more.
+    fn load(&self, account: &str) -> KeyProtectionResult<Zeroizing<Vec<u8>>>;
+
+    /// Remove the entry for `account`; removing a missing entry succeeds
+    fn delete(&self, account: &str) -> KeyProtectionResult<()>;
+}
+
+/// Key protector backed by the operating system's keychain
+pub struct KeychainProtector<K: KeychainProvider> {
+    keychain: K,
+}
+
+impl<K: KeychainProvider> KeychainProtector<K> {
+    pub fn new(keychain: K) -> Self {
+        Self { keychain }
+    }
+
+    /// Name of the underlying keychain
+    pub fn keychain_name(&self) -> &'static str {
+        self.keychain.name()
+    }
+
+    /// Delete the keychain entry of a record that is being removed
+    pub fn forget(&self, record: &ProtectorRecord) -> CoreResult<()> {
+        Ok(self.keychain.delete(&record.handle)?)
+    }
+}
+
+impl<K: KeychainProvider> KeyProtector for KeychainProtector<K> {
+    fn kind(&self) -> ProtectorKind {
+        ProtectorKind::OsKeychain
+    }
+
+    fn protect(&self, label: &str, password: &[u8]) -> CoreResult<ProtectorRecord> {
+        let account = Uuid::new_v4().to_string();
+        let secret: Zeroizing<[u8; 32]> = Zeroizing::new(
+            EncryptionUtils::random_bytes(32)
+                .try_into()
+                .expect("random_bytes returns the requested length"),
+        );
+        self.keychain.store(&account, secret.as_ref())?;
+
+        // Some keychain tools report success without storing anything
+        if self.keychain.load(&account)?.as_slice() != secret.as_ref() {
+            let _ = self.keychain.delete(&account);
+            return Err(KeyProtectionError::DeviceError {
+                message: format!("{} did not keep the unlock key", self.keychain.name()),
+            }
+            .into());
+        }
+
+        Ok(ProtectorRecord::seal(
+            self.kind(),
+            label,
+            account,
+            &[],
+            &secret,
+            password,
+        ))
+    }
+
+    fn unprotect(&self, record: &ProtectorRecord) -> CoreResult<Zeroizing<Vec<u8>>> {
+        let stored = self.keychain.load(&record.handle)?;
+        let secret: &[u8; 32] = stored
+            .as_slice()
+            .try_into()
+            .map_err(|_| KeyProtectionError::UnwrapFailed)?;
+        Ok(record.open(secret)?)
+    }
+}
+
+#[cfg(any(windows, all(unix, not(any(target_os = "ios", target_os = "android")))))]
+pub use system::SystemKeychain;
+
+/// The keychain of the platform the app runs on, through the `keyring`
+/// crate: Secret Service (GNOME Keyring, KWallet) on Linux and the BSDs, the
+/// login keychain on macOS and Credential Manager on Windows
+///
+/// The secret goes straight to the keychain's API and is never handed to
+/// another process.
+#[cfg(any(windows, all(unix, not(any(target_os = "ios", target_os = "android")))))]
+mod system {
+    use zeroize::Zeroizing;
+
+    use super::{KeychainProvider, KEYCHAIN_SERVICE};
+    use crate::core::errors::{KeyProtectionError, KeyProtectionResult};
+
+    #[derive(Debug, Clone, Default)]
+    pub struct SystemKeychain;
+
+    impl SystemKeychain {
+        fn entry(&self, account: &str) -> KeyProtectionResult<keyring::Entry> {
+            keyring::Entry::new(KEYCHAIN_SERVICE, account).map_err(|e| self.device_error(e))
+        }
+
+        fn device_error(&self, e: keyring::Error) -> KeyProtectionError {
+            match e {
+                keyring::Error::NoStorageAccess(_) | keyring::Error::PlatformFailure(_) => {
+                    KeyProtectionError::DeviceUnavailable {
+                        message: format!("{}: {}", self.name(), e),
+                    }
+                }
+                _ => KeyProtectionError::DeviceError {
+                    message: format!("{}: {}", self.name(), e),
+                },
+            }
+        }
+    }
+
+    impl KeychainProvider for SystemKeychain {
+        fn name(&self) -> &'static str {
+            if cfg!(windows) {
+                "Windows Credential Manager"
+            } else if cfg!(target_os = "macos") {
+                "macOS Keychain"
+            } else {
+                "Secret Service"
+            }
+        }
+
+        fn store(&self, account: &str, secret: &[u8]) -> KeyProtectionResult<()> {
+            self.entry(account)?
+                .set_secret(secret)
+                .map_err(|e| self.device_error(e))
+        }
+
+        fn load(&self, account: &str) -> KeyProtectionResult<Zeroizing<Vec<u8>>> {
+            match self.entry(account)?.get_secret() {
+                Ok(secret) => Ok(Zeroizing::new(secret)),
+                Err(keyring::Error::NoEntry) => Err(KeyProtectionError::UnwrapFailed),
+                Err(e) => Err(self.device_error(e)),
+            }
+        }
+
+        fn delete(&self, account: &str) -> KeyProtectionResult<()> {
+            match self.entry(account)?.delete_credential() {
+                Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+                Err(e) => Err(self.device_error(e)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+
+    #[derive(Default)]
+    struct MemoryKeychain {
+        entries: RefCell<HashMap<String, Vec<u8>>>,
+    }
+
+    impl KeychainProvider for MemoryKeychain {
+        fn name(&self) -> &'static str {
+            "Memory"
+        }
+
+        fn store(&self, account: &str, secret: &[u8]) -> KeyProtectionResult<()> {
+            self.entries
+                .borrow_mut()
+                .insert(account.to_string(), secret.to_vec());
+            Ok(())
+        }
+
+        fn load(&self, account: &str) -> KeyProtectionResult<Zeroizing<Vec<u8>>> {
+            self.entries
+                .borrow()
+                .get(account)
+                .cloned()
+                .map(Zeroizing::new)
+                .ok_or(KeyProtectionError::UnwrapFailed)
+        }
+
+        fn delete(&self, account: &str) -> KeyProtectionResult<()> {
+            self.entries.borrow_mut().remove(account);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_keychain_protector_round_trip() {
+        let protector = KeychainProtector::new(MemoryKeychain::default());
+        let record = protector.protect("OS login", b"hunter2").unwrap();
+        assert_eq!(record.kind, ProtectorKind::OsKeychain);
+        assert_eq!(protector.keychain.entries.borrow().len(), 1);
+        assert_eq!(protector.unprotect(&record).unwrap().as_slice(), b"hunter2");
+
+        // Another user's keychain has no entry for the record
+        let other = KeychainProtector::new(MemoryKeychain::default());
+        assert!(matches!(
+            other.unprotect(&record),
+            Err(crate::core::CoreError::KeyProtection(
+                KeyProtectionError::UnwrapFailed
+            ))
+        ));
+
+        protector.forget(&record).unwrap();
+        assert!(protector.keychain.entries.borrow().is_empty());
+        assert!(protector.unprotect(&record).is_err());
+    }
+}
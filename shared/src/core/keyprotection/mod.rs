@@ -0,0 +1,273 @@
+//! Key protectors that unlock a repository without typing the master password
+//!
+//! A key protector wraps the archive password with a secret only it can
+//! reproduce, such as the hmac-secret output of a FIDO2 security key, a
+//! random key kept in the operating system's keychain, or a mobile keystore
+//! key unlocked by a fingerprint. The
+//! wrapped copies are kept in a `<archive>.keys` file next to the archive as
+//! [`ProtectorRecord`]s; they are useless without the protector, and the
+//! master password keeps working whatever happens to them.
+//!
+//! Every protector seals the password the same way: its 32-byte secret is
+//! stretched with HKDF-SHA256 into a ChaCha20-Poly1305 key, with the record's
+//! ID, kind and salt as associated data.
+
+pub mod biometric;
+pub mod fido2;
+pub mod keychain;
+
+use base64::prelude::*;
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use chrono::Utc;
+use hkdf::Hkdf;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use uuid::Uuid;
+use zeroize::Zeroizing;
+
+use crate::core::errors::{
+    CoreError, CoreResult, FileError, KeyProtectionError, KeyProtectionResult,
+};
+use crate::core::file_provider::FileOperationProvider;
+use crate::utils::encryption::{EncryptionUtils, SecretBytes};
+
+pub use biometric::BiometricToken;
+pub use fido2::{Fido2Protector, HmacSecretDevice, LibFido2Device};
+pub use keychain::{KeychainProtector, KeychainProvider};
+
+/// Suffix of the file next to the archive that holds the protector records
+pub const PROTECTOR_FILE_SUFFIX: &str = ".keys";
+
+const HKDF_INFO: &[u8] = b"ziplock-key-protector-v1";
+
+/// Kinds of key protector
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProtectorKind {
+    /// FIDO2 security key using the hmac-secret extension
+    Fido2HmacSecret,
+    /// Random key stored in the operating system's keychain
+    OsKeychain,
+    /// Platform keystore key gated by a fingerprint or face check
+    Biometric,
+}
+
+impl ProtectorKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ProtectorKind::Fido2HmacSecret => "Security key",
+            ProtectorKind::OsKeychain => "OS login",
+            ProtectorKind::Biometric => "Biometrics",
+        }
+    }
+}
+
+/// A wrapped copy of the archive password
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProtectorRecord {
+    pub id: String,
+    pub kind: ProtectorKind,
+    /// Name the user gave the protector, e.g. "YubiKey on keyring"
+    pub label: String,
+    pub created_at: i64,
+    /// Protector-specific reference, e.g. the FIDO2 credential ID (base64)
+    pub handle: String,
+    /// Salt (base64) the protector derives its secret from
+    pub salt: String,
+    pub nonce: String,
+    pub ciphertext: String,
+}
+
+impl ProtectorRecord {
+    /// Seal `password` with `secret`, producing a new record
+    pub fn seal(
+        kind: ProtectorKind,
+        label: &str,
+        handle: String,
+        salt: &[u8],
+        secret: &[u8; 32],
+        password: &[u8],
+    ) -> Self {
+        let mut record = Self {
+            id: Uuid::new_v4().to_string(),
+            kind,
+            label: label.to_string(),
+            created_at: Utc::now().timestamp(),
+            handle,
+            salt: BASE64_STANDARD.encode(salt),
+            nonce: String::new(),
+            ciphertext: String::new(),
+        };
+        let nonce = EncryptionUtils::random_bytes(12);
+        let ciphertext = cipher(secret)
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: password,
+                    aad: &record.associated_data(),
+                },
+            )
+            .expect("ChaCha20-Poly1305 encryption of an in-memory buffer cannot fail");
+        record.nonce = BASE64_STANDARD.encode(nonce);
+        record.ciphertext = BASE64_STANDARD.encode(ciphertext);
+        record
+    }
+
+    /// Recover the password with the protector's `secret`
+    pub fn open(&self, secret: &[u8; 32]) -> Result<Zeroizing<Vec<u8>>, KeyProtectionError> {
+        let (Ok(nonce), Ok(ciphertext)) = (
+            BASE64_STANDARD.decode(&self.nonce),
+            BASE64_STANDARD.decode(&self.ciphertext),
+        ) else {
+            return Err(KeyProtectionError::UnwrapFailed);
+        };
+        if nonce.len() != 12 {
+            return Err(KeyProtectionError::UnwrapFailed);
+        }
+        cipher(secret)
+            .decrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &ciphertext,
+                    aad: &self.associated_data(),
+                },
+            )
+            .map(Zeroizing::new)
+            .map_err(|_| KeyProtectionError::UnwrapFailed)
+    }
+
+    /// The decoded salt
+    pub fn salt_bytes(&self) -> Result<Vec<u8>, KeyProtectionError> {
+        BASE64_STANDARD
+            .decode(&self.salt)
+            .map_err(|_| KeyProtectionError::UnwrapFailed)
+    }
+
+    fn associated_data(&self) -> Vec<u8> {
+        format!("{}|{:?}|{}", self.id, self.kind, self.salt).into_bytes()
+    }
+}
+
+/// Something that can wrap the archive password and unwrap it again
+pub trait KeyProtector {
+    /// Kind of the records this protector makes and opens
+    fn kind(&self) -> ProtectorKind;
+
+    /// Wrap `password`, talking to the device as needed
+    fn protect(&self, label: &str, password: &[u8]) -> CoreResult<ProtectorRecord>;
+
+    /// Recover the password from a record made by [`KeyProtector::protect`]
+    fn unprotect(&self, record: &ProtectorRecord) -> CoreResult<Zeroizing<Vec<u8>>>;
+}
+
+/// The protector records of one archive
+pub struct ProtectorStore;
+
+impl ProtectorStore {
+    /// Path of the records file for the archive at `archive_path`
+    pub fn path_for(archive_path: &str) -> String {
+        format!("{}{}", archive_path, PROTECTOR_FILE_SUFFIX)
+    }
+
+    /// Read the records of an archive; none if the file does not exist
+    pub fn load<F: FileOperationProvider>(
+        provider: &F,
+        archive_path: &str,
+    ) -> CoreResult<Vec<ProtectorRecord>> {
+        match provider.read_archive(&Self::path_for(archive_path)) {
+            Ok(data) => serde_json::from_slice(&data).map_err(|e| CoreError::SerializationError {
+                message: format!("Invalid key protector file: {}", e),
+            }),
+            Err(FileError::NotFound { .. }) => Ok(Vec::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Write the records of an archive
+    pub fn save<F: FileOperationProvider>(
+        provider: &F,
+        archive_path: &str,
+        records: &[ProtectorRecord],
+    ) -> CoreResult<()> {
+        let data =
+            serde_json::to_vec_pretty(records).map_err(|e| CoreError::SerializationError {
+                message: format!("Failed to serialize key protectors: {}", e),
+            })?;
+        provider.write_archive(&Self::path_for(archive_path), &data)?;
+        Ok(())
+    }
+}
+
+/// Run a helper tool from `package`, feeding it `input` on stdin
+fn run_tool(tool: &str, package: &str, args: &[&str], input: &str) -> KeyProtectionResult<String> {
+    let mut child = Command::new(tool)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| KeyProtectionError::DeviceUnavailable {
+            message: format!("Cannot run {} (is {} installed?): {}", tool, package, e),
+        })?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(input.as_bytes())
+            .map_err(|e| KeyProtectionError::DeviceError {
+                message: format!("Failed to talk to {}: {}", tool, e),
+            })?;
+    }
+    let output = child
+        .wait_with_output()
+        .map_err(|e| KeyProtectionError::DeviceError {
+            message: format!("{} failed: {}", tool, e),
+        })?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(KeyProtectionError::DeviceError {
+            message: format!("{} failed: {}", tool, stderr.trim()),
+        });
+    }
+    String::from_utf8(output.stdout).map_err(|_| KeyProtectionError::DeviceError {
+        message: format!("Unexpected output from {}", tool),
+    })
+}
+
+fn cipher(secret: &[u8; 32]) -> ChaCha20Poly1305 {
+    let mut key = SecretBytes::zeroed(32);
+    Hkdf::<Sha256>::new(None, secret)
+        .expand(HKDF_INFO, key.expose_mut())
+        .expect("32 bytes is a valid HKDF-SHA256 output length");
+    ChaCha20Poly1305::new(Key::from_slice(key.expose()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_and_open() {
+        let secret = [7u8; 32];
+        let record = ProtectorRecord::seal(
+            ProtectorKind::Fido2HmacSecret,
+            "Key",
+            "handle".to_string(),
+            &[1u8; 32],
+            &secret,
+            b"master password",
+        );
+
+        assert_eq!(record.open(&secret).unwrap().as_slice(), b"master password");
+        assert_eq!(
+            record.open(&[8u8; 32]),
+            Err(KeyProtectionError::UnwrapFailed)
+        );
+
+        // The ciphertext is bound to the record
+        let mut moved = record.clone();
+        moved.id = "other".to_string();
+        assert!(moved.open(&secret).is_err());
+    }
+}
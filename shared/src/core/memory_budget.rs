@@ -0,0 +1,286 @@
+//! Memory caps for devices that cannot keep a whole vault decrypted
+//!
+//! By default [`UnifiedMemoryRepository`](crate::core::UnifiedMemoryRepository)
+//! keeps every credential as a decoded record. With a [`MemoryBudget`] it
+//! keeps at most `max_decrypted_records` of them; the ones used least
+//! recently are serialized and sealed with a key that only lives in memory,
+//! and are decrypted again when something asks for them. Sealed records are
+//! a single compact buffer each instead of a record with a map of owned
+//! strings, and their secrets are not lying around in plain text.
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use rand::{thread_rng, RngCore};
+use serde::{Deserialize, Serialize};
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::core::errors::{CoreError, CoreResult};
+use crate::models::CredentialRecord;
+use crate::utils::encryption::SecretBytes;
+use crate::utils::yaml::{deserialize_credential, serialize_credential};
+
+/// How much decrypted data a repository may hold; `None` means no cap
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MemoryBudget {
+    /// Credentials kept decoded; the rest stay sealed until used
+    pub max_decrypted_records: Option<usize>,
+    /// Largest attachment, in bytes, read into memory while an archive is
+    /// extracted; bigger ones are left in the archive
+    pub max_attachment_buffer: Option<usize>,
+}
+
+impl MemoryBudget {
+    /// No caps, for desktops
+    pub fn unlimited() -> Self {
+        Self::default()
+    }
+
+    /// Caps suited to phones with little RAM
+    pub fn low_memory() -> Self {
+        Self {
+            max_decrypted_records: Some(200),
+            max_attachment_buffer: Some(4 * 1024 * 1024),
+        }
+    }
+
+    pub fn with_max_decrypted_records(mut self, records: usize) -> Self {
+        self.max_decrypted_records = Some(records.max(1));
+        self
+    }
+
+    pub fn with_max_attachment_buffer(mut self, bytes: usize) -> Self {
+        self.max_attachment_buffer = Some(bytes);
+        self
+    }
+
+    /// Whether an attachment of `len` bytes may be read into memory
+    pub fn allows_attachment(&self, len: usize) -> bool {
+        self.max_attachment_buffer.is_none_or(|max| len <= max)
+    }
+}
+
+/// Credentials evicted under a [`MemoryBudget`], sealed with a per-session key
+#[derive(Clone)]
+pub(crate) struct SealedRecords {
+    key: SecretBytes,
+    entries: HashMap<String, SealedRecord>,
+}
+
+#[derive(Clone)]
+struct SealedRecord {
+    nonce: [u8; 12],
+    ciphertext: Vec<u8>,
+    /// Decoded copy handed out by [`SealedRecords::get`] until the next
+    /// [`SealedRecords::close_opened`]
+    opened: OnceLock<CredentialRecord>,
+}
+
+impl std::fmt::Debug for SealedRecords {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SealedRecords")
+            .field("count", &self.entries.len())
+            .finish()
+    }
+}
+
+impl Default for SealedRecords {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SealedRecords {
+    pub fn new() -> Self {
+        let mut key = SecretBytes::zeroed(32);
+        thread_rng().fill_bytes(key.expose_mut());
+        Self {
+            key,
+            entries: HashMap::new(),
+        }
+    }
+
+    fn cipher(&self) -> ChaCha20Poly1305 {
+        ChaCha20Poly1305::new(Key::from_slice(self.key.expose()))
+    }
+
+    /// Seal a copy of `record`; the caller drops its decoded one
+    pub fn seal(&mut self, record: &CredentialRecord) -> CoreResult<()> {
+        let yaml = Zeroizing::new(serialize_credential(record)?);
+        let mut nonce = [0u8; 12];
+        thread_rng().fill_bytes(&mut nonce);
+        let ciphertext = self
+            .cipher()
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: yaml.as_bytes(),
+                    aad: record.id.as_bytes(),
+                },
+            )
+            .map_err(|e| CoreError::InternalError {
+                message: format!("Could not seal credential: {}", e),
+            })?;
+        self.entries.insert(
+            record.id.clone(),
+            SealedRecord {
+                nonce,
+                ciphertext,
+                opened: OnceLock::new(),
+            },
+        );
+        Ok(())
+    }
+
+    /// The serialized record under `id`, as it is written to the archive
+    pub fn yaml(&self, id: &str) -> Option<CoreResult<Zeroizing<String>>> {
+        let entry = self.entries.get(id)?;
+        let plaintext = self
+            .cipher()
+            .decrypt(
+                Nonce::from_slice(&entry.nonce),
+                Payload {
+                    msg: &entry.ciphertext,
+                    aad: id.as_bytes(),
+                },
+            )
+            .map(Zeroizing::new)
+            .map_err(|_| CoreError::InternalError {
+                message: format!("Sealed credential {} does not open", id),
+            });
+        Some(plaintext.and_then(|bytes| {
+            String::from_utf8(bytes.to_vec())
+                .map(Zeroizing::new)
+                .map_err(|e| CoreError::SerializationError {
+                    message: e.to_string(),
+                })
+        }))
+    }
+
+    fn decode(&self, id: &str) -> Option<CoreResult<CredentialRecord>> {
+        if let Some(record) = self.entries.get(id)?.opened.get() {
+            return Some(Ok(record.clone()));
+        }
+        Some(
+            self.yaml(id)?
+                .and_then(|yaml| deserialize_credential(&yaml)),
+        )
+    }
+
+    /// The record under `id`, decoded until the next
+    /// [`close_opened`](Self::close_opened)
+    pub fn get(&self, id: &str) -> Option<CoreResult<&CredentialRecord>> {
+        let entry = self.entries.get(id)?;
+        if entry.opened.get().is_none() {
+            match self.decode(id)? {
+                Ok(record) => {
+                    // Another reader may have opened it meanwhile; either
+                    // copy is the same record
+                    let _ = entry.opened.set(record);
+                }
+                Err(e) => return Some(Err(e)),
+            }
+        }
+        entry.opened.get().map(Ok)
+    }
+
+    /// A decoded copy of the record under `id` that the caller owns
+    pub fn open(&self, id: &str) -> Option<CoreResult<CredentialRecord>> {
+        self.decode(id)
+    }
+
+    /// Remove the record under `id` and return it decoded
+    pub fn take(&mut self, id: &str) -> Option<CoreResult<CredentialRecord>> {
+        let decoded = self.decode(id)?;
+        if decoded.is_err() {
+            return Some(decoded);
+        }
+        if let Some(mut entry) = self.entries.remove(id) {
+            if let Some(mut opened) = entry.opened.take() {
+                wipe_record(&mut opened);
+            }
+        }
+        Some(decoded)
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.entries.contains_key(id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn ids(&self) -> impl Iterator<Item = &String> {
+        self.entries.keys()
+    }
+
+    /// Drop the decoded copies [`get`](Self::get) handed out
+    pub fn close_opened(&mut self) {
+        for entry in self.entries.values_mut() {
+            if let Some(mut opened) = entry.opened.take() {
+                wipe_record(&mut opened);
+            }
+        }
+    }
+
+    /// Forget every record and start over with a new key
+    pub fn wipe(&mut self) {
+        self.close_opened();
+        *self = Self::new();
+    }
+}
+
+/// Overwrite the secrets of a record that is about to be dropped
+pub(crate) fn wipe_record(credential: &mut CredentialRecord) {
+    for field in credential.fields.values_mut() {
+        field.value.zeroize();
+    }
+    for entry in &mut credential.field_history {
+        entry.value.zeroize();
+    }
+    if let Some(notes) = credential.notes.as_mut() {
+        notes.zeroize();
+    }
+    credential.title.zeroize();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::CredentialField;
+
+    #[test]
+    fn test_seal_and_open() {
+        let mut record = CredentialRecord::new("Bank".to_string(), "login".to_string());
+        record.set_field("password", CredentialField::password("s3cret"));
+
+        let mut sealed = SealedRecords::new();
+        sealed.seal(&record).unwrap();
+        assert!(sealed.contains(&record.id));
+        assert!(!format!("{:?}", sealed).contains("s3cret"));
+        assert!(
+            !String::from_utf8_lossy(&sealed.entries[&record.id].ciphertext).contains("s3cret")
+        );
+
+        assert_eq!(sealed.get(&record.id).unwrap().unwrap(), &record);
+        sealed.close_opened();
+        assert_eq!(sealed.open(&record.id).unwrap().unwrap(), record);
+        assert_eq!(sealed.take(&record.id).unwrap().unwrap(), record);
+        assert!(sealed.get(&record.id).is_none());
+
+        let budget = MemoryBudget::low_memory();
+        assert!(budget.allows_attachment(1024));
+        assert!(!budget.allows_attachment(64 * 1024 * 1024));
+        assert!(MemoryBudget::unlimited().allows_attachment(usize::MAX));
+        assert_eq!(
+            MemoryBudget::unlimited()
+                .with_max_decrypted_records(0)
+                .max_decrypted_records,
+            Some(1)
+        );
+    }
+}
@@ -6,14 +6,33 @@
 
 use chrono::Utc;
 use std::collections::HashMap;
+use zeroize::{Zeroize, Zeroizing};
 
+use crate::core::activity_log::{
+    default_device_name, ActivityAction, ActivityLog, ACTIVITY_LOG_FILE,
+};
 use crate::core::errors::{CoreError, CoreResult};
+use crate::core::events::{ChangeEmitter, ChangeListeners, ListenerId, RepositoryEvent};
+use crate::core::memory_budget::{wipe_record, MemoryBudget, SealedRecords};
+use crate::core::trash::{
+    is_trash_path, trash_path, PruneReport, PrunedEntries, RetentionQuotas, TrashedCredential,
+};
 use crate::core::types::{
     FileMap, RepositoryMetadata, RepositoryStats, CREDENTIALS_DIR, METADATA_FILE,
+    NOTE_INLINE_LENGTH, QUARANTINE_DIR, TEMPLATES_DIR,
 };
-use crate::models::CredentialRecord;
+use crate::models::note_file_name;
+use crate::models::{
+    apply_folder_defaults, credential_form_schema, find_template, normalize_folder_path,
+    CommonTemplates, CredentialField, CredentialFormSchema, CredentialRecord, CredentialTemplate,
+    FieldHistoryEntry, FieldType, FolderDefaults, FolderDefaultsMap, RecurringCredential,
+    RecurringDraft, TemplateMap, VaultMember, NOTE_FILE_KEY,
+};
+use crate::utils::encryption::CredentialCrypto;
+use crate::utils::search_index::SearchIndex;
 use crate::utils::yaml::{
-    deserialize_credential, deserialize_metadata, serialize_credential, serialize_metadata,
+    deserialize_credential, deserialize_from_yaml, deserialize_metadata, serialize_credential,
+    serialize_metadata, serialize_pretty,
 };
 
 /// Pure in-memory repository for credential operations
@@ -22,14 +41,62 @@ pub struct UnifiedMemoryRepository {
     /// Whether the repository has been initialized
     initialized: bool,
 
-    /// All credentials stored in memory, keyed by ID
+    /// Decoded credentials, keyed by ID
     credentials: HashMap<String, CredentialRecord>,
 
+    /// Credentials evicted under `budget`; an ID is in here or in
+    /// `credentials`, never both
+    sealed: SealedRecords,
+
+    /// Caps on what is kept decoded
+    budget: MemoryBudget,
+
     /// Repository metadata
     metadata: RepositoryMetadata,
 
     /// Whether repository has unsaved changes
     modified: bool,
+
+    /// Entries under `quarantine/`, carried along unchanged on save
+    quarantine: FileMap,
+
+    /// User-defined credential templates, stored under `templates/`
+    templates: TemplateMap,
+
+    /// Full-text index over `credentials`, kept in step with every change
+    search_index: SearchIndex,
+
+    /// Limits on password history and on the trash
+    quotas: RetentionQuotas,
+
+    /// Deleted credentials, by ID, stored under `trash/`
+    trash: HashMap<String, TrashedCredential>,
+
+    /// Whether a save must check sealed credentials for history over the
+    /// limit, as after opening or lowering the limit
+    history_prune_pending: bool,
+
+    /// Bodies of large notes not decoded yet, by credential ID and field
+    /// name; see [`note`](crate::models::note)
+    unloaded_notes: HashMap<String, HashMap<String, UnloadedNote>>,
+
+    /// Hash-chained record of opens, saves and credential changes, stored
+    /// under `audit/`
+    activity: ActivityLog,
+
+    /// Device named in new activity entries; `None` stops recording
+    activity_device: Option<String>,
+
+    /// Callbacks told about credential changes, saves and locks
+    listeners: ChangeListeners,
+}
+
+/// A note body left as it was read from the archive
+#[derive(Debug, Clone)]
+struct UnloadedNote {
+    /// Entry name within the credential's directory
+    file: String,
+    body: Vec<u8>,
 }
 
 impl Default for UnifiedMemoryRepository {
@@ -44,8 +111,20 @@ impl UnifiedMemoryRepository {
         Self {
             initialized: false,
             credentials: HashMap::new(),
+            sealed: SealedRecords::new(),
+            budget: MemoryBudget::default(),
             metadata: RepositoryMetadata::default(),
             modified: false,
+            quarantine: HashMap::new(),
+            templates: TemplateMap::new(),
+            search_index: SearchIndex::new(),
+            quotas: RetentionQuotas::default(),
+            trash: HashMap::new(),
+            history_prune_pending: false,
+            unloaded_notes: HashMap::new(),
+            activity: ActivityLog::new(),
+            activity_device: Some(default_device_name()),
+            listeners: ChangeListeners::new(),
         }
     }
 
@@ -91,10 +170,45 @@ impl UnifiedMemoryRepository {
 
         // Load credentials
         self.credentials.clear();
+        self.sealed.wipe();
+        self.quarantine.clear();
+        self.templates.clear();
+        self.wipe_trash();
+        self.wipe_unloaded_notes();
+        self.activity = match file_map.get(ACTIVITY_LOG_FILE) {
+            Some(data) => {
+                let yaml =
+                    String::from_utf8(data.clone()).map_err(|e| CoreError::SerializationError {
+                        message: format!("Invalid UTF-8 in {}: {}", ACTIVITY_LOG_FILE, e),
+                    })?;
+                deserialize_from_yaml(&yaml)?
+            }
+            None => ActivityLog::new(),
+        };
         for (file_path, file_data) in &file_map {
             // Normalize path separators for cross-platform compatibility
             let normalized_path = file_path.replace('\\', "/");
-            if normalized_path.starts_with(CREDENTIALS_DIR)
+            if normalized_path.starts_with(&format!("{}/", QUARANTINE_DIR)) {
+                self.quarantine.insert(normalized_path, file_data.clone());
+            } else if is_template_path(&normalized_path) {
+                let template_str = String::from_utf8(file_data.clone()).map_err(|e| {
+                    CoreError::SerializationError {
+                        message: format!("Invalid UTF-8 in template file {}: {}", file_path, e),
+                    }
+                })?;
+
+                let template: CredentialTemplate = deserialize_from_yaml(&template_str)?;
+                self.templates.insert(template.name.clone(), template);
+            } else if is_trash_path(&normalized_path) {
+                let trashed_str = String::from_utf8(file_data.clone()).map_err(|e| {
+                    CoreError::SerializationError {
+                        message: format!("Invalid UTF-8 in trash file {}: {}", file_path, e),
+                    }
+                })?;
+
+                let trashed: TrashedCredential = deserialize_from_yaml(&trashed_str)?;
+                self.trash.insert(trashed.credential.id.clone(), trashed);
+            } else if normalized_path.starts_with(CREDENTIALS_DIR)
                 && normalized_path.ends_with("/record.yml")
             {
                 let credential_str = String::from_utf8(file_data.clone()).map_err(|e| {
@@ -108,6 +222,31 @@ impl UnifiedMemoryRepository {
             }
         }
 
+        // Large note bodies stay as read until their credential is opened
+        for credential in self.credentials.values() {
+            for (name, field) in &credential.fields {
+                let Some(file) = field.note_file() else {
+                    continue;
+                };
+                let path = note_path(&credential.id, file);
+                let body = file_map
+                    .get(&path)
+                    .or_else(|| file_map.get(&path.replace('/', "\\")));
+                if let Some(body) = body {
+                    self.unloaded_notes
+                        .entry(credential.id.clone())
+                        .or_default()
+                        .insert(
+                            name.clone(),
+                            UnloadedNote {
+                                file: file.to_string(),
+                                body: body.clone(),
+                            },
+                        );
+                }
+            }
+        }
+
         // Validate loaded data with Windows debugging
         #[cfg(windows)]
         {
@@ -145,6 +284,8 @@ impl UnifiedMemoryRepository {
 
         self.initialized = true;
         self.modified = false;
+        // Another device may have written history under a higher limit
+        self.history_prune_pending = true;
 
         // Repair any credentials with missing or empty IDs
         if let Ok(repaired_count) = self.repair_all_credentials() {
@@ -155,6 +296,8 @@ impl UnifiedMemoryRepository {
                 );
             }
         }
+        self.search_index.rebuild(self.credentials.values());
+        self.apply_memory_budget(None);
 
         Ok(())
     }
@@ -192,9 +335,41 @@ impl UnifiedMemoryRepository {
             METADATA_FILE, metadata_len
         );
 
+        for (path, content) in &self.quarantine {
+            file_map.insert(path.clone(), content.clone());
+        }
+
+        if self.activity != ActivityLog::default() {
+            file_map.insert(
+                ACTIVITY_LOG_FILE.to_string(),
+                serialize_pretty(&self.activity)?.into_bytes(),
+            );
+        }
+
+        for template in self.templates.values() {
+            file_map.insert(
+                template_path(&template.name),
+                serialize_pretty(template)?.into_bytes(),
+            );
+        }
+
+        for (id, trashed) in &self.trash {
+            file_map.insert(trash_path(id), serialize_pretty(trashed)?.into_bytes());
+        }
+
+        for (id, notes) in &self.unloaded_notes {
+            for note in notes.values() {
+                file_map.insert(note_path(id, &note.file), note.body.clone());
+            }
+        }
+
         // Serialize each credential
         for credential in self.credentials.values() {
-            let credential_yaml = serialize_credential(credential)?;
+            let (credential, notes) = split_large_notes(credential);
+            for (file, body) in notes {
+                file_map.insert(note_path(&credential.id, &file), body.into_bytes());
+            }
+            let credential_yaml = serialize_credential(&credential)?;
             let file_path = format!("{}/{}/record.yml", CREDENTIALS_DIR, credential.id);
 
             #[cfg(windows)]
@@ -212,6 +387,25 @@ impl UnifiedMemoryRepository {
 
             file_map.insert(file_path, credential_yaml.into_bytes());
         }
+        for id in self.sealed.ids() {
+            let Some(yaml) = self.sealed.yaml(id) else {
+                continue;
+            };
+            let mut yaml = yaml?;
+            // Only a record this long can hold a note to move out
+            if yaml.len() > NOTE_INLINE_LENGTH {
+                let mut credential = deserialize_credential(&yaml)?;
+                let (mut split, notes) = split_large_notes(&credential);
+                wipe_record(&mut credential);
+                for (file, body) in notes {
+                    file_map.insert(note_path(id, &file), body.into_bytes());
+                }
+                yaml = Zeroizing::new(serialize_credential(&split)?);
+                wipe_record(&mut split);
+            }
+            let file_path = format!("{}/{}/record.yml", CREDENTIALS_DIR, id);
+            file_map.insert(file_path, yaml.as_bytes().to_vec());
+        }
 
         #[cfg(windows)]
         {
@@ -246,21 +440,29 @@ impl UnifiedMemoryRepository {
         }
 
         // Check for duplicate ID
-        if self.credentials.contains_key(&credential.id) {
+        if self.contains_credential(&credential.id) {
             return Err(CoreError::ValidationError {
                 message: format!("Credential with ID '{}' already exists", credential.id),
             });
         }
 
+        apply_folder_defaults(&self.metadata.folders, &mut credential);
+
         // Update timestamps
         let now = Utc::now().timestamp();
         credential.created_at = now;
         credential.updated_at = now;
         credential.accessed_at = now;
+        credential.stamp_field_changes(None, now);
 
+        let id = credential.id.clone();
+        self.search_index.insert(&credential);
         self.credentials.insert(credential.id.clone(), credential);
         self.modified = true;
         self.update_metadata();
+        self.apply_memory_budget(Some(&id));
+        self.record_activity(ActivityAction::CredentialCreated, Some(&id));
+        self.emit(RepositoryEvent::CredentialAdded { id });
 
         Ok(())
     }
@@ -271,27 +473,55 @@ impl UnifiedMemoryRepository {
             return Err(CoreError::NotInitialized);
         }
 
+        self.unseal(id)?;
+        let notes = self.unloaded_notes.remove(id);
         let credential = self
             .credentials
             .get_mut(id)
             .ok_or_else(|| CoreError::CredentialNotFound { id: id.to_string() })?;
+        if let Some(notes) = notes {
+            fill_notes(credential, notes);
+        }
 
         // Update accessed timestamp
         credential.accessed_at = Utc::now().timestamp();
         self.modified = true;
+        self.apply_memory_budget(Some(id));
+
+        self.credentials
+            .get(id)
+            .ok_or_else(|| CoreError::CredentialNotFound { id: id.to_string() })
+    }
 
+    /// A copy of a credential with the bodies of its large notes filled in
+    ///
+    /// [`get_credential_readonly`](Self::get_credential_readonly) and the
+    /// list and search APIs leave those bodies in the archive, with an empty
+    /// value.
+    pub fn get_credential_with_notes(&self, id: &str) -> CoreResult<CredentialRecord> {
+        let mut credential = self.get_credential_readonly(id)?.clone();
+        if let Some(notes) = self.unloaded_notes.get(id) {
+            fill_notes(&mut credential, notes.clone());
+        }
         Ok(credential)
     }
 
     /// Get a credential by ID without updating access time
+    ///
+    /// A sealed credential stays decoded, over the memory budget, until the
+    /// next change to the repository.
     pub fn get_credential_readonly(&self, id: &str) -> CoreResult<&CredentialRecord> {
         if !self.initialized {
             return Err(CoreError::NotInitialized);
         }
 
-        self.credentials
-            .get(id)
-            .ok_or_else(|| CoreError::CredentialNotFound { id: id.to_string() })
+        match self.credentials.get(id) {
+            Some(credential) => Ok(credential),
+            None => self
+                .sealed
+                .get(id)
+                .unwrap_or_else(|| Err(CoreError::CredentialNotFound { id: id.to_string() })),
+        }
     }
 
     /// Update an existing credential
@@ -302,6 +532,7 @@ impl UnifiedMemoryRepository {
 
         // Repair credential ID if missing or empty
         let original_id = credential.id.clone();
+        self.unseal(&original_id)?;
         let was_repaired = crate::utils::validation::repair_credential_id(&mut credential);
         if was_repaired {
             eprintln!(
@@ -323,7 +554,7 @@ impl UnifiedMemoryRepository {
                     id: format!("credential with title '{}' and empty ID", credential.title),
                 });
             }
-            &original_id // Use empty ID for removal
+            original_id.clone() // Use empty ID for removal
         } else {
             // Normal case - check if credential exists
             if !self.credentials.contains_key(&credential.id) {
@@ -331,7 +562,7 @@ impl UnifiedMemoryRepository {
                     id: credential.id.clone(),
                 });
             }
-            &credential.id
+            credential.id.clone()
         };
 
         // Validate the credential
@@ -343,16 +574,45 @@ impl UnifiedMemoryRepository {
         }
 
         // Preserve created_at, update other timestamps
-        if let Some(existing) = self.credentials.get(lookup_id) {
+        let now = Utc::now().timestamp();
+        if let Some(existing) = self.credentials.get(&lookup_id) {
             credential.created_at = existing.created_at;
+            credential.field_history = existing.field_history.clone();
+            credential.record_field_history(
+                existing,
+                now,
+                self.quotas.max_revisions_per_credential,
+            );
+        }
+        credential.stamp_field_changes(self.credentials.get(&lookup_id), now);
+        credential.updated_at = now;
+        credential.accessed_at = now;
+
+        // Bodies not loaded stay in the archive as long as the update keeps
+        // referring to them
+        if let Some(mut notes) = self.unloaded_notes.remove(&lookup_id) {
+            notes.retain(|name, note| {
+                let kept = credential
+                    .fields
+                    .get(name)
+                    .is_some_and(|field| field.note_file() == Some(note.file.as_str()));
+                if !kept {
+                    note.body.zeroize();
+                }
+                kept
+            });
+            if !notes.is_empty() {
+                self.unloaded_notes.insert(credential.id.clone(), notes);
+            }
         }
-        credential.updated_at = Utc::now().timestamp();
-        credential.accessed_at = Utc::now().timestamp();
 
         // Remove old entry (either empty ID or changed ID)
-        self.credentials.remove(lookup_id);
+        self.credentials.remove(&lookup_id);
+        self.search_index.remove(&lookup_id);
 
         // Insert with new ID
+        let id = credential.id.clone();
+        self.search_index.insert(&credential);
         self.credentials.insert(credential.id.clone(), credential);
         eprintln!(
             "DEBUG: Updated credential - old key: '{}', new key: '{}'",
@@ -364,6 +624,9 @@ impl UnifiedMemoryRepository {
         );
         self.modified = true;
         self.update_metadata();
+        self.apply_memory_budget(Some(&id));
+        self.record_activity(ActivityAction::CredentialUpdated, Some(&id));
+        self.emit(RepositoryEvent::CredentialUpdated { id });
 
         Ok(())
     }
@@ -395,6 +658,8 @@ impl UnifiedMemoryRepository {
             );
 
             self.credentials.remove(&old_id);
+            self.search_index.remove(&old_id);
+            self.search_index.insert(&repaired_credential);
             self.credentials
                 .insert(repaired_credential.id.clone(), repaired_credential);
             repaired_count += 1;
@@ -412,217 +677,1253 @@ impl UnifiedMemoryRepository {
         Ok(repaired_count)
     }
 
-    /// Delete a credential by ID
+    /// Delete a credential by ID, moving it to the trash unless the
+    /// [`RetentionQuotas`] keep no trash
     pub fn delete_credential(&mut self, id: &str) -> CoreResult<CredentialRecord> {
         if !self.initialized {
             return Err(CoreError::NotInitialized);
         }
 
+        self.unseal(id)?;
         let credential = self
             .credentials
             .remove(id)
             .ok_or_else(|| CoreError::CredentialNotFound { id: id.to_string() })?;
+        self.search_index.remove(id);
+        let notes = self.unloaded_notes.remove(id).unwrap_or_default();
+        if self.quotas.max_trash_items > 0 {
+            let mut trashed = credential.clone();
+            fill_notes(&mut trashed, notes);
+            self.trash.insert(
+                id.to_string(),
+                TrashedCredential {
+                    deleted_at: Utc::now().timestamp(),
+                    credential: trashed,
+                },
+            );
+        } else {
+            for (_, mut note) in notes {
+                note.body.zeroize();
+            }
+        }
 
         self.modified = true;
         self.update_metadata();
+        self.record_activity(ActivityAction::CredentialDeleted, Some(id));
+        self.emit(RepositoryEvent::CredentialDeleted { id: id.to_string() });
 
         Ok(credential)
     }
 
-    /// List all credentials (returns cloned credentials)
-    pub fn list_credentials(&self) -> CoreResult<Vec<CredentialRecord>> {
-        if !self.initialized {
-            return Err(CoreError::NotInitialized);
+    /// Encrypt a field of a credential with a passphrase of its own
+    ///
+    /// From then on read APIs only see
+    /// [`PROTECTED_FIELD_PLACEHOLDER`](crate::models::PROTECTED_FIELD_PLACEHOLDER) as its
+    /// value until it is opened with [`unlock_field`](Self::unlock_field).
+    pub fn protect_field(
+        &mut self,
+        id: &str,
+        field_name: &str,
+        passphrase: &str,
+    ) -> CoreResult<()> {
+        let field = self.field_mut(id, field_name)?;
+        if field.is_protected() {
+            return Err(CoreError::ValidationError {
+                message: format!("Field '{field_name}' is already protected"),
+            });
         }
+        CredentialCrypto::protect_field(field, passphrase).map_err(|e| {
+            CoreError::ValidationError {
+                message: format!("Could not protect field '{field_name}': {e}"),
+            }
+        })?;
+        self.touch_credential(id);
+        Ok(())
+    }
 
-        Ok(self.credentials.values().cloned().collect())
+    /// The real value of a protected field
+    pub fn unlock_field(
+        &self,
+        id: &str,
+        field_name: &str,
+        passphrase: &str,
+    ) -> CoreResult<Zeroizing<String>> {
+        let field = self
+            .get_credential_readonly(id)?
+            .get_field(field_name)
+            .ok_or_else(|| Self::no_such_field(field_name))?;
+        if !field.is_protected() {
+            return Err(Self::not_protected(field_name));
+        }
+        CredentialCrypto::unlock_field(field, passphrase).map_err(|_| {
+            CoreError::FieldPassphraseInvalid {
+                field: field_name.to_string(),
+            }
+        })
     }
 
-    /// Get credential IDs and titles for listings
-    pub fn list_credential_summaries(&self) -> CoreResult<Vec<(String, String)>> {
-        if !self.initialized {
-            return Err(CoreError::NotInitialized);
+    /// Decrypt a protected field so it is stored like any other again
+    pub fn unprotect_field(
+        &mut self,
+        id: &str,
+        field_name: &str,
+        passphrase: &str,
+    ) -> CoreResult<()> {
+        let field = self.field_mut(id, field_name)?;
+        if !field.is_protected() {
+            return Err(Self::not_protected(field_name));
+        }
+        CredentialCrypto::unprotect_field(field, passphrase).map_err(|_| {
+            CoreError::FieldPassphraseInvalid {
+                field: field_name.to_string(),
+            }
+        })?;
+        self.touch_credential(id);
+        Ok(())
+    }
+
+    /// Change the passphrase of a protected field
+    pub fn change_field_passphrase(
+        &mut self,
+        id: &str,
+        field_name: &str,
+        old_passphrase: &str,
+        new_passphrase: &str,
+    ) -> CoreResult<()> {
+        let field = self.field_mut(id, field_name)?;
+        let Some(envelope) = &field.protection else {
+            return Err(Self::not_protected(field_name));
+        };
+        if new_passphrase.is_empty() {
+            return Err(CoreError::ValidationError {
+                message: "The new passphrase cannot be empty".to_string(),
+            });
         }
+        let rewrapped = CredentialCrypto::rewrap_envelope(envelope, old_passphrase, new_passphrase)
+            .map_err(|_| CoreError::FieldPassphraseInvalid {
+                field: field_name.to_string(),
+            })?;
+        field.protection = Some(rewrapped);
+        self.touch_credential(id);
+        Ok(())
+    }
 
-        Ok(self
-            .credentials
-            .values()
-            .map(|c| (c.id.clone(), c.title.clone()))
-            .collect())
+    /// Put an earlier value of a password field back, identified by when it
+    /// was replaced
+    ///
+    /// The current value goes into the history like any other change, so a
+    /// restore can be undone. A removed field is added again.
+    pub fn restore_previous_value(
+        &mut self,
+        id: &str,
+        field_name: &str,
+        replaced_at: i64,
+    ) -> CoreResult<()> {
+        let mut credential = self.get_credential_readonly(id)?.clone();
+        let entry = credential
+            .previous_values(field_name)
+            .into_iter()
+            .find(|entry| entry.replaced_at == replaced_at)
+            .cloned()
+            .ok_or_else(|| CoreError::ValidationError {
+                message: format!("Field '{field_name}' has no value replaced at {replaced_at}"),
+            })?;
+        match credential.fields.get_mut(field_name) {
+            Some(field) if field.is_protected() => {
+                return Err(CoreError::ValidationError {
+                    message: format!(
+                    "Field '{field_name}' is protected and must be unprotected before it is edited"
+                ),
+                })
+            }
+            Some(field) => field.value = entry.value,
+            None => {
+                credential.fields.insert(
+                    field_name.to_string(),
+                    CredentialField::password(entry.value),
+                );
+            }
+        }
+        self.update_credential(credential)
     }
 
-    /// Get all credentials as a reference to the internal map
-    pub fn get_credentials_ref(&self) -> CoreResult<&HashMap<String, CredentialRecord>> {
+    /// Forget earlier values of one field, or of every field with `None`;
+    /// returns how many were removed
+    pub fn clear_field_history(&mut self, id: &str, field_name: Option<&str>) -> CoreResult<usize> {
         if !self.initialized {
             return Err(CoreError::NotInitialized);
         }
+        self.unseal(id)?;
+        let credential = self
+            .credentials
+            .get_mut(id)
+            .ok_or_else(|| CoreError::CredentialNotFound { id: id.to_string() })?;
+        let (cleared, kept): (Vec<FieldHistoryEntry>, Vec<FieldHistoryEntry>) = credential
+            .field_history
+            .drain(..)
+            .partition(|entry| field_name.is_none_or(|name| entry.field == name));
+        credential.field_history = kept;
+        let count = cleared.len();
+        for mut entry in cleared {
+            entry.value.zeroize();
+        }
+        if count > 0 {
+            self.touch_credential(id);
+        }
+        Ok(count)
+    }
 
-        Ok(&self.credentials)
+    /// How many earlier values are kept per password field
+    pub fn field_history_limit(&self) -> usize {
+        self.quotas.max_revisions_per_credential
     }
 
-    /// Check if repository has unsaved changes
-    pub fn is_modified(&self) -> bool {
-        self.modified
+    /// Keep at most `limit` earlier values per password field; 0 turns the
+    /// history off
+    ///
+    /// Existing entries over the limit are dropped by the next
+    /// [`prune`](Self::prune).
+    pub fn set_field_history_limit(&mut self, limit: usize) {
+        self.set_retention_quotas(self.quotas.with_max_revisions_per_credential(limit));
     }
 
-    /// Mark repository as saved (clears modified flag)
-    pub fn mark_saved(&mut self) {
-        self.modified = false;
+    /// Limits on password history and on the trash
+    pub fn retention_quotas(&self) -> RetentionQuotas {
+        self.quotas
     }
 
-    /// Get repository statistics
-    pub fn get_stats(&self) -> CoreResult<RepositoryStats> {
-        if !self.initialized {
-            return Err(CoreError::NotInitialized);
+    /// Keep history and trash within `quotas` from the next
+    /// [`prune`](Self::prune) on
+    pub fn set_retention_quotas(&mut self, quotas: RetentionQuotas) {
+        if quotas.max_revisions_per_credential < self.quotas.max_revisions_per_credential {
+            self.history_prune_pending = true;
         }
-
-        Ok(RepositoryStats {
-            credential_count: self.credentials.len(),
-            metadata: self.metadata.clone(),
-            initialized: self.initialized,
-            modified: self.modified,
-        })
+        self.quotas = quotas;
     }
 
-    /// Get repository metadata
-    pub fn get_metadata(&self) -> &RepositoryMetadata {
-        &self.metadata
+    /// Deleted credentials still in the trash, most recently deleted first
+    pub fn list_trash(&self) -> CoreResult<Vec<&TrashedCredential>> {
+        if !self.initialized {
+            return Err(CoreError::NotInitialized);
+        }
+        let mut trashed: Vec<&TrashedCredential> = self.trash.values().collect();
+        trashed.sort_by(|a, b| {
+            b.deleted_at
+                .cmp(&a.deleted_at)
+                .then_with(|| a.credential.id.cmp(&b.credential.id))
+        });
+        Ok(trashed)
     }
 
-    /// Clear all credentials and reset repository
-    pub fn clear(&mut self) -> CoreResult<()> {
+    /// Move a credential out of the trash and back into the repository
+    pub fn restore_credential(&mut self, id: &str) -> CoreResult<()> {
         if !self.initialized {
             return Err(CoreError::NotInitialized);
         }
-
-        self.credentials.clear();
-        self.modified = true;
-        self.update_metadata();
-
-        Ok(())
+        if self.credentials.contains_key(id) || self.sealed.contains(id) {
+            return Err(CoreError::ValidationError {
+                message: format!("A credential with ID '{}' already exists", id),
+            });
+        }
+        let trashed = self
+            .trash
+            .remove(id)
+            .ok_or_else(|| CoreError::CredentialNotFound { id: id.to_string() })?;
+        self.add_credential(trashed.credential)
     }
 
-    /// Check if a credential exists by ID
-    pub fn contains_credential(&self, id: &str) -> bool {
-        self.credentials.contains_key(id)
+    /// Delete everything in the trash for good; returns how many
+    /// credentials were removed
+    pub fn empty_trash(&mut self) -> CoreResult<usize> {
+        if !self.initialized {
+            return Err(CoreError::NotInitialized);
+        }
+        let count = self.trash.len();
+        self.wipe_trash();
+        if count > 0 {
+            self.modified = true;
+        }
+        Ok(count)
     }
 
-    /// Update repository metadata based on current state
-    fn update_metadata(&mut self) {
-        self.metadata.credential_count = self.credentials.len();
-        self.metadata.last_modified = Utc::now().timestamp();
+    /// Drop trashed credentials and password history over the
+    /// [`RetentionQuotas`] as of `now`, a Unix time
+    ///
+    /// Trash past the age limit goes first, then the oldest entries over the
+    /// item limit.
+    pub fn prune(&mut self, now: i64) -> CoreResult<PruneReport> {
+        self.prune_keeping(now).map(|(report, _)| report)
     }
 
-    /// Import credentials from another repository
-    pub fn import_credentials(&mut self, credentials: Vec<CredentialRecord>) -> CoreResult<usize> {
+    /// [`prune`](Self::prune), keeping what was removed so that
+    /// [`restore_pruned`](Self::restore_pruned) can put it back
+    ///
+    /// Run on every save by the repository manager, which restores the
+    /// entries if the archive cannot be written.
+    pub(crate) fn prune_keeping(&mut self, now: i64) -> CoreResult<(PruneReport, PrunedEntries)> {
         if !self.initialized {
             return Err(CoreError::NotInitialized);
         }
+        let mut report = PruneReport::default();
+        let mut pruned = PrunedEntries {
+            history_prune_pending: self.history_prune_pending,
+            modified: self.modified,
+            trash: Vec::new(),
+            history: Vec::new(),
+        };
 
-        let mut imported_count = 0;
-        let mut errors = Vec::new();
-
-        for credential in credentials {
-            match self.add_credential(credential.clone()) {
-                Ok(()) => imported_count += 1,
-                Err(e) => {
-                    // For import, we continue on validation errors but collect them
-                    errors.push(format!("Failed to import '{}': {}", credential.title, e));
-
-                    // If it's a duplicate ID, try with a new ID
-                    if matches!(e, CoreError::ValidationError { .. })
-                        && e.to_string().contains("already exists")
-                    {
-                        let mut new_credential = credential;
-                        new_credential.id = uuid::Uuid::new_v4().to_string();
-
-                        if self.add_credential(new_credential).is_ok() {
-                            imported_count += 1;
-                            errors.pop(); // Remove the error since we recovered
-                        }
-                    }
+        let mut trashed: Vec<(i64, String)> = self
+            .trash
+            .values()
+            .map(|t| (t.deleted_at, t.credential.id.clone()))
+            .collect();
+        trashed.sort();
+        let over = trashed.len().saturating_sub(self.quotas.max_trash_items);
+        for (n, (deleted_at, id)) in trashed.into_iter().enumerate() {
+            if n < over || self.quotas.is_expired(deleted_at, now) {
+                if let Some(removed) = self.trash.remove(&id) {
+                    pruned.trash.push(removed);
                 }
+                report.trash_removed.push(id);
             }
         }
 
-        // If we had errors but imported some credentials, log the errors but don't fail
-        if !errors.is_empty() && imported_count == 0 {
-            return Err(CoreError::ValidationError {
-                message: errors.join("; "),
-            });
+        let limit = self.quotas.max_revisions_per_credential;
+        let mut over_limit: Vec<String> = Vec::new();
+        let mut check = |credential: &CredentialRecord| {
+            if has_history_over(credential, limit) {
+                over_limit.push(credential.id.clone());
+            }
+        };
+        if self.history_prune_pending {
+            self.visit_credentials(&mut check)?;
+        } else {
+            self.credentials.values().for_each(&mut check);
+        }
+        over_limit.sort();
+        for id in over_limit {
+            self.unseal(&id)?;
+            if let Some(credential) = self.credentials.get_mut(&id) {
+                let before = credential.field_history.clone();
+                credential.trim_field_history(limit);
+                report.history_entries_removed += before.len() - credential.field_history.len();
+                self.search_index.insert(credential);
+                pruned.history.push((id.clone(), before));
+                report.history_trimmed.push(id);
+            }
         }
+        self.history_prune_pending = false;
 
-        Ok(imported_count)
+        if !report.is_empty() {
+            self.modified = true;
+        }
+        Ok((report, pruned))
     }
 
-    /// Export all credentials
-    pub fn export_credentials(&self) -> CoreResult<Vec<CredentialRecord>> {
+    /// Undo a [`prune_keeping`](Self::prune_keeping) whose save failed
+    pub(crate) fn restore_pruned(&mut self, mut pruned: PrunedEntries) {
+        for removed in pruned.trash.drain(..) {
+            self.trash.insert(removed.credential.id.clone(), removed);
+        }
+        for (id, history) in pruned.history.drain(..) {
+            if let Some(credential) = self.credentials.get_mut(&id) {
+                credential.field_history = history;
+                self.search_index.insert(credential);
+            }
+        }
+        self.history_prune_pending = pruned.history_prune_pending;
+        self.modified = pruned.modified;
+    }
+
+    fn field_mut(&mut self, id: &str, field_name: &str) -> CoreResult<&mut CredentialField> {
         if !self.initialized {
             return Err(CoreError::NotInitialized);
         }
+        self.unseal(id)?;
+        self.credentials
+            .get_mut(id)
+            .ok_or_else(|| CoreError::CredentialNotFound { id: id.to_string() })?
+            .fields
+            .get_mut(field_name)
+            .ok_or_else(|| Self::no_such_field(field_name))
+    }
 
-        Ok(self.credentials.values().cloned().collect())
+    fn touch_credential(&mut self, id: &str) {
+        if let Some(credential) = self.credentials.get_mut(id) {
+            credential.updated_at = Utc::now().timestamp();
+            self.search_index.insert(credential);
+        }
+        self.modified = true;
+        self.update_metadata();
+        self.apply_memory_budget(Some(id));
+        self.emit(RepositoryEvent::CredentialUpdated { id: id.to_string() });
     }
 
-    /// Get credentials by tag
-    pub fn get_credentials_by_tag(&self, tag: &str) -> CoreResult<Vec<CredentialRecord>> {
+    fn no_such_field(field_name: &str) -> CoreError {
+        CoreError::ValidationError {
+            message: format!("Credential has no field '{field_name}'"),
+        }
+    }
+
+    fn not_protected(field_name: &str) -> CoreError {
+        CoreError::ValidationError {
+            message: format!("Field '{field_name}' is not protected"),
+        }
+    }
+
+    /// List all credentials (returns cloned credentials)
+    pub fn list_credentials(&self) -> CoreResult<Vec<CredentialRecord>> {
         if !self.initialized {
             return Err(CoreError::NotInitialized);
         }
 
+        self.collect_credentials(|_| true)
+    }
+
+    /// Search credentials with the [`ParsedSearch`](crate::utils::ParsedSearch)
+    /// syntax, best matches first
+    pub fn search_credentials(&self, query: &str) -> CoreResult<Vec<CredentialRecord>> {
         Ok(self
-            .credentials
-            .values()
-            .filter(|c| c.has_tag(tag))
-            .cloned()
+            .search_credentials_scored(query)?
+            .into_iter()
+            .map(|(credential, _)| credential)
             .collect())
     }
 
-    /// Get credentials by type
-    pub fn get_credentials_by_type(
+    /// Like [`search_credentials`](Self::search_credentials), with the score
+    /// of each match, for merging results from several repositories
+    pub fn search_credentials_scored(
         &self,
-        credential_type: &str,
-    ) -> CoreResult<Vec<CredentialRecord>> {
+        query: &str,
+    ) -> CoreResult<Vec<(CredentialRecord, f64)>> {
         if !self.initialized {
             return Err(CoreError::NotInitialized);
         }
 
-        Ok(self
-            .credentials
-            .values()
-            .filter(|c| c.credential_type == credential_type)
-            .cloned()
-            .collect())
+        self.search_index
+            .search(query)
+            .into_iter()
+            .filter_map(|hit| {
+                let credential = match self.credentials.get(&hit.id) {
+                    Some(credential) => Ok(credential.clone()),
+                    None => self.sealed.open(&hit.id)?,
+                };
+                Some(credential.map(|credential| (credential, hit.score)))
+            })
+            .collect()
     }
 
-    /// Get favorite credentials
-    pub fn get_favorite_credentials(&self) -> CoreResult<Vec<CredentialRecord>> {
+    /// Get credential IDs and titles for listings
+    pub fn list_credential_summaries(&self) -> CoreResult<Vec<(String, String)>> {
         if !self.initialized {
             return Err(CoreError::NotInitialized);
         }
 
-        Ok(self
-            .credentials
-            .values()
-            .filter(|c| c.favorite)
-            .cloned()
-            .collect())
+        let mut summaries = Vec::with_capacity(self.credential_count());
+        self.visit_credentials(|c| summaries.push((c.id.clone(), c.title.clone())))?;
+        Ok(summaries)
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::models::{CredentialField, CredentialRecord};
+    /// Get all credentials as a reference to the internal map
+    ///
+    /// Credentials sealed under the [memory budget](Self::set_memory_budget)
+    /// are not in it; use [`list_credentials`](Self::list_credentials) to
+    /// see all of them.
+    pub fn get_credentials_ref(&self) -> CoreResult<&HashMap<String, CredentialRecord>> {
+        if !self.initialized {
+            return Err(CoreError::NotInitialized);
+        }
 
-    fn create_test_credential(title: &str) -> CredentialRecord {
-        let mut credential = CredentialRecord::new(title.to_string(), "test".to_string());
-        credential.set_field("username", CredentialField::username("testuser"));
-        credential.set_field("password", CredentialField::password("testpass"));
-        credential
+        Ok(&self.credentials)
     }
 
-    #[test]
+    /// Check if repository has unsaved changes
+    pub fn is_modified(&self) -> bool {
+        self.modified
+    }
+
+    /// Mark repository as saved (clears modified flag)
+    pub fn mark_saved(&mut self) {
+        self.modified = false;
+        self.emit(RepositoryEvent::Saved);
+    }
+
+    /// Mark the repository as having unsaved changes, for fixes made while
+    /// loading
+    pub(crate) fn mark_modified(&mut self) {
+        self.modified = true;
+    }
+
+    /// Get repository statistics
+    pub fn get_stats(&self) -> CoreResult<RepositoryStats> {
+        if !self.initialized {
+            return Err(CoreError::NotInitialized);
+        }
+
+        Ok(RepositoryStats {
+            credential_count: self.credential_count(),
+            metadata: self.metadata.clone(),
+            initialized: self.initialized,
+            modified: self.modified,
+        })
+    }
+
+    /// Get repository metadata
+    pub fn get_metadata(&self) -> &RepositoryMetadata {
+        &self.metadata
+    }
+
+    /// Record the archive backend the repository is about to be saved with
+    ///
+    /// This describes the container rather than the contents, so it does not
+    /// mark the repository modified.
+    pub fn set_archive_backend(&mut self, backend: &str) {
+        if self.metadata.archive_backend != backend {
+            self.metadata.archive_backend = backend.to_string();
+        }
+    }
+
+    /// Defaults set directly on folders
+    pub fn folder_defaults(&self) -> &FolderDefaultsMap {
+        &self.metadata.folders
+    }
+
+    /// Set the defaults of a folder; empty defaults remove them
+    pub fn set_folder_defaults(
+        &mut self,
+        folder_path: &str,
+        defaults: FolderDefaults,
+    ) -> CoreResult<()> {
+        if !self.initialized {
+            return Err(CoreError::NotInitialized);
+        }
+
+        let path = normalize_folder_path(folder_path);
+        if path.is_empty() {
+            return Err(CoreError::ValidationError {
+                message: "Folder path cannot be empty".to_string(),
+            });
+        }
+        defaults
+            .validate(&self.templates)
+            .map_err(|errors| CoreError::ValidationError {
+                message: errors.join("; "),
+            })?;
+
+        if defaults.is_empty() {
+            self.metadata.folders.remove(&path);
+        } else {
+            self.metadata.folders.insert(path, defaults);
+        }
+        self.modified = true;
+        self.update_metadata();
+        Ok(())
+    }
+
+    /// Credentials created on a schedule
+    pub fn recurring_credentials(&self) -> &[RecurringCredential] {
+        &self.metadata.recurring
+    }
+
+    /// Add a recurring credential, or replace the one with the same ID
+    pub fn set_recurring_credential(&mut self, recurring: RecurringCredential) -> CoreResult<()> {
+        if !self.initialized {
+            return Err(CoreError::NotInitialized);
+        }
+        recurring
+            .validate()
+            .map_err(|message| CoreError::ValidationError { message })?;
+        if find_template(&self.templates, &recurring.template).is_none() {
+            return Err(CoreError::ValidationError {
+                message: format!("Unknown template '{}'", recurring.template),
+            });
+        }
+
+        match self
+            .metadata
+            .recurring
+            .iter_mut()
+            .find(|existing| existing.id == recurring.id)
+        {
+            Some(existing) => *existing = recurring,
+            None => self.metadata.recurring.push(recurring),
+        }
+        self.modified = true;
+        self.update_metadata();
+        Ok(())
+    }
+
+    /// Stop creating a recurring credential; drafts already made are kept
+    pub fn remove_recurring_credential(&mut self, id: &str) -> CoreResult<RecurringCredential> {
+        if !self.initialized {
+            return Err(CoreError::NotInitialized);
+        }
+        let index = self
+            .metadata
+            .recurring
+            .iter()
+            .position(|recurring| recurring.id == id)
+            .ok_or_else(|| CoreError::ValidationError {
+                message: format!("No recurring credential '{id}'"),
+            })?;
+        self.modified = true;
+        self.update_metadata();
+        Ok(self.metadata.recurring.remove(index))
+    }
+
+    /// Create a draft for every recurring credential due at `now`
+    ///
+    /// A definition whose template has since been deleted is skipped and
+    /// stays due.
+    pub fn create_due_drafts(&mut self, now: i64) -> CoreResult<Vec<RecurringDraft>> {
+        if !self.initialized {
+            return Err(CoreError::NotInitialized);
+        }
+
+        let mut drafts = Vec::new();
+        for index in 0..self.metadata.recurring.len() {
+            let recurring = self.metadata.recurring[index].clone();
+            if !recurring.is_due(now) {
+                continue;
+            }
+            let Some(template) = find_template(&self.templates, &recurring.template) else {
+                continue;
+            };
+            let previous = match &recurring.last_credential_id {
+                Some(id) if self.contains_credential(id) => {
+                    Some(self.get_credential_readonly(id)?.clone())
+                }
+                _ => None,
+            };
+            let draft = recurring
+                .create_draft(&template, previous.as_ref())
+                .map_err(|message| CoreError::ValidationError { message })?;
+            drafts.push(RecurringDraft {
+                recurring_id: recurring.id.clone(),
+                credential_id: draft.id.clone(),
+                title: draft.title.clone(),
+            });
+            let id = draft.id.clone();
+            self.add_credential(draft)?;
+
+            let stored = &mut self.metadata.recurring[index];
+            stored.last_credential_id = Some(id);
+            stored.advance(now);
+        }
+        if !drafts.is_empty() {
+            self.update_metadata();
+        }
+        Ok(drafts)
+    }
+
+    /// People the vault is shared with
+    pub fn members(&self) -> &[VaultMember] {
+        &self.metadata.members
+    }
+
+    /// Add a member, or replace the key of the member with the same name
+    pub fn set_member(&mut self, member: VaultMember) -> CoreResult<()> {
+        if !self.initialized {
+            return Err(CoreError::NotInitialized);
+        }
+        member
+            .validate()
+            .map_err(|message| CoreError::ValidationError { message })?;
+
+        match self
+            .metadata
+            .members
+            .iter_mut()
+            .find(|existing| existing.name == member.name)
+        {
+            Some(existing) => existing.public_key = member.public_key,
+            None => self.metadata.members.push(member),
+        }
+        self.modified = true;
+        self.update_metadata();
+        Ok(())
+    }
+
+    /// Remove a member and take them off every credential's access list
+    pub fn remove_member(&mut self, name: &str) -> CoreResult<VaultMember> {
+        if !self.initialized {
+            return Err(CoreError::NotInitialized);
+        }
+        let index = self
+            .metadata
+            .members
+            .iter()
+            .position(|member| member.name == name)
+            .ok_or_else(|| CoreError::ValidationError {
+                message: format!("No vault member '{name}'"),
+            })?;
+
+        for credential in self.collect_credentials(|c| c.is_shared_with(name))? {
+            self.unseal(&credential.id)?;
+            if let Some(stored) = self.credentials.get_mut(&credential.id) {
+                stored.shared_with.retain(|member| member != name);
+            }
+            self.touch_credential(&credential.id);
+        }
+        self.modified = true;
+        self.update_metadata();
+        Ok(self.metadata.members.remove(index))
+    }
+
+    /// Copies of the credentials the member `name` may see
+    pub fn credentials_shared_with(&self, name: &str) -> CoreResult<Vec<CredentialRecord>> {
+        if !self.initialized {
+            return Err(CoreError::NotInitialized);
+        }
+        self.collect_credentials(|c| c.is_shared_with(name))
+    }
+
+    /// Form for a new credential in `folder_path`, with the folder's defaults
+    /// filled in
+    pub fn credential_form_schema(
+        &self,
+        folder_path: Option<&str>,
+        template: Option<&str>,
+    ) -> CoreResult<CredentialFormSchema> {
+        if !self.initialized {
+            return Err(CoreError::NotInitialized);
+        }
+
+        credential_form_schema(
+            &self.metadata.folders,
+            &self.templates,
+            folder_path,
+            template,
+        )
+        .map_err(|message| CoreError::ValidationError { message })
+    }
+
+    /// User-defined templates, sorted by name
+    pub fn list_templates(&self) -> CoreResult<Vec<CredentialTemplate>> {
+        if !self.initialized {
+            return Err(CoreError::NotInitialized);
+        }
+
+        Ok(self.templates.values().cloned().collect())
+    }
+
+    /// Save a new user-defined template
+    ///
+    /// Fails if the template is invalid, see [`CredentialTemplate::validate`],
+    /// or its name is already taken by a built-in or user-defined template.
+    pub fn add_template(&mut self, template: CredentialTemplate) -> CoreResult<()> {
+        if !self.initialized {
+            return Err(CoreError::NotInitialized);
+        }
+
+        if CommonTemplates::get_by_name(&template.name).is_some()
+            || self.templates.contains_key(&template.name)
+        {
+            return Err(CoreError::ValidationError {
+                message: format!("Template '{}' already exists", template.name),
+            });
+        }
+        self.store_template(template)
+    }
+
+    /// Replace the user-defined template with the same name
+    ///
+    /// Credentials already created from it keep their fields.
+    pub fn update_template(&mut self, template: CredentialTemplate) -> CoreResult<()> {
+        if !self.initialized {
+            return Err(CoreError::NotInitialized);
+        }
+
+        if !self.templates.contains_key(&template.name) {
+            return Err(CoreError::ValidationError {
+                message: format!("Unknown template '{}'", template.name),
+            });
+        }
+        self.store_template(template)
+    }
+
+    fn store_template(&mut self, template: CredentialTemplate) -> CoreResult<()> {
+        template
+            .validate()
+            .map_err(|errors| CoreError::ValidationError {
+                message: errors.join("; "),
+            })?;
+
+        self.templates.insert(template.name.clone(), template);
+        self.modified = true;
+        self.update_metadata();
+        Ok(())
+    }
+
+    /// Clear all credentials and reset repository
+    pub fn clear(&mut self) -> CoreResult<()> {
+        if !self.initialized {
+            return Err(CoreError::NotInitialized);
+        }
+
+        let mut ids: Vec<String> = self.credentials.keys().cloned().collect();
+        ids.extend(self.sealed.ids().cloned());
+        ids.sort();
+        self.credentials.clear();
+        self.sealed.wipe();
+        self.search_index.clear();
+        self.wipe_unloaded_notes();
+        for id in &ids {
+            self.record_activity(ActivityAction::CredentialDeleted, Some(id));
+        }
+        self.modified = true;
+        self.update_metadata();
+        for id in ids {
+            self.emit(RepositoryEvent::CredentialDeleted { id });
+        }
+
+        Ok(())
+    }
+
+    /// Overwrite all credential data in memory and return to the
+    /// uninitialized state
+    ///
+    /// Used before the process may be paged out (suspend, hibernate, app
+    /// backgrounding) so secrets do not end up in a memory image. Unsaved
+    /// changes are discarded.
+    pub fn wipe(&mut self) {
+        let was_initialized = self.initialized;
+        for (_, mut credential) in self.credentials.drain() {
+            wipe_record(&mut credential);
+        }
+        self.credentials = HashMap::new();
+        self.sealed.wipe();
+        self.search_index.wipe();
+        self.wipe_unloaded_notes();
+        self.wipe_trash();
+        for (_, mut content) in self.quarantine.drain() {
+            content.zeroize();
+        }
+        self.templates.clear();
+        self.metadata = RepositoryMetadata::default();
+        self.activity = ActivityLog::new();
+        self.initialized = false;
+        self.modified = false;
+        if was_initialized {
+            self.emit(RepositoryEvent::Locked);
+        }
+    }
+
+    /// Call `listener` after every credential change, save and lock
+    ///
+    /// Runs on the thread making the change, before the method making it
+    /// returns. Credentials are named by ID only.
+    pub fn on_change(
+        &mut self,
+        listener: impl Fn(&RepositoryEvent) + Send + Sync + 'static,
+    ) -> ListenerId {
+        self.listeners.add(listener)
+    }
+
+    /// Stop calling a listener added with [`on_change`](Self::on_change)
+    pub fn remove_listener(&mut self, id: ListenerId) -> bool {
+        self.listeners.remove(id)
+    }
+
+    /// Tell the listeners about `event`
+    pub(crate) fn emit(&self, event: RepositoryEvent) {
+        self.listeners.emit(&event);
+    }
+
+    /// A handle for telling the listeners about events from another thread;
+    /// it follows them when they move to another repository
+    pub(crate) fn change_emitter(&self) -> ChangeEmitter {
+        self.listeners.emitter()
+    }
+
+    /// Move the listeners over from `other`, e.g. a repository this one
+    /// replaces
+    pub(crate) fn take_listeners_from(&mut self, other: &mut UnifiedMemoryRepository) {
+        self.listeners = std::mem::take(&mut other.listeners);
+    }
+
+    /// The activity log as loaded, plus what was recorded since
+    pub fn activity_log(&self) -> &ActivityLog {
+        &self.activity
+    }
+
+    /// Name new activity entries with `device`, or stop recording with
+    /// `None`; entries already in the log are kept either way
+    pub fn set_activity_device(&mut self, device: Option<String>) {
+        self.activity_device = device;
+    }
+
+    /// Append an entry to the activity log, unless recording is off
+    ///
+    /// Does not mark the repository modified: an entry on its own, such as
+    /// an open, is written with the next save.
+    pub fn record_activity(&mut self, action: ActivityAction, credential_id: Option<&str>) {
+        if let Some(device) = &self.activity_device {
+            self.activity
+                .record(action, credential_id, device, Utc::now().timestamp());
+        }
+    }
+
+    /// Remove activity entries older than `before` (Unix timestamp)
+    ///
+    /// The removal itself is recorded, even with recording off, so a gap in
+    /// the log can always be told apart from tampering.
+    pub fn prune_activity_before(&mut self, before: i64) -> CoreResult<usize> {
+        if !self.initialized {
+            return Err(CoreError::NotInitialized);
+        }
+        let device = self
+            .activity_device
+            .clone()
+            .unwrap_or_else(default_device_name);
+        let removed = self
+            .activity
+            .prune_before(before, &device, Utc::now().timestamp());
+        if removed > 0 {
+            self.modified = true;
+        }
+        Ok(removed)
+    }
+
+    /// Entries moved under `quarantine/` by a repair, keyed by archive path
+    pub fn quarantined_entries(&self) -> &FileMap {
+        &self.quarantine
+    }
+
+    /// Delete the quarantined entries on the next save
+    pub fn clear_quarantine(&mut self) {
+        if !self.quarantine.is_empty() {
+            self.quarantine.clear();
+            self.modified = true;
+        }
+    }
+
+    /// Check if a credential exists by ID
+    pub fn contains_credential(&self, id: &str) -> bool {
+        self.credentials.contains_key(id) || self.sealed.contains(id)
+    }
+
+    /// Number of credentials, decoded or sealed
+    fn credential_count(&self) -> usize {
+        self.credentials.len() + self.sealed.len()
+    }
+
+    /// Update repository metadata based on current state
+    fn update_metadata(&mut self) {
+        self.metadata.credential_count = self.credential_count();
+        self.metadata.last_modified = Utc::now().timestamp();
+    }
+
+    /// Import credentials from another repository
+    ///
+    /// Credentials keep their IDs, so one that is already here (for example
+    /// from an earlier import of the same export) is updated in place rather
+    /// than added again.
+    pub fn import_credentials(&mut self, credentials: Vec<CredentialRecord>) -> CoreResult<usize> {
+        if !self.initialized {
+            return Err(CoreError::NotInitialized);
+        }
+
+        let mut imported_count = 0;
+        let mut errors = Vec::new();
+
+        for credential in credentials {
+            let title = credential.title.clone();
+            let result = if self.contains_credential(&credential.id) {
+                self.update_credential(credential)
+            } else {
+                self.add_credential(credential)
+            };
+            match result {
+                Ok(()) => imported_count += 1,
+                // For import, we continue on validation errors but collect them
+                Err(e) => errors.push(format!("Failed to import '{}': {}", title, e)),
+            }
+        }
+
+        // If we had errors but imported some credentials, log the errors but don't fail
+        if !errors.is_empty() && imported_count == 0 {
+            return Err(CoreError::ValidationError {
+                message: errors.join("; "),
+            });
+        }
+
+        Ok(imported_count)
+    }
+
+    /// Export all credentials, with the bodies of large notes
+    pub fn export_credentials(&self) -> CoreResult<Vec<CredentialRecord>> {
+        if !self.initialized {
+            return Err(CoreError::NotInitialized);
+        }
+
+        let mut credentials = self.collect_credentials(|_| true)?;
+        for credential in &mut credentials {
+            if let Some(notes) = self.unloaded_notes.get(&credential.id) {
+                fill_notes(credential, notes.clone());
+            }
+        }
+        Ok(credentials)
+    }
+
+    /// Get credentials by tag
+    pub fn get_credentials_by_tag(&self, tag: &str) -> CoreResult<Vec<CredentialRecord>> {
+        if !self.initialized {
+            return Err(CoreError::NotInitialized);
+        }
+
+        self.collect_credentials(|c| c.has_tag(tag))
+    }
+
+    /// Get credentials by type
+    pub fn get_credentials_by_type(
+        &self,
+        credential_type: &str,
+    ) -> CoreResult<Vec<CredentialRecord>> {
+        if !self.initialized {
+            return Err(CoreError::NotInitialized);
+        }
+
+        self.collect_credentials(|c| c.credential_type == credential_type)
+    }
+
+    /// Get favorite credentials
+    pub fn get_favorite_credentials(&self) -> CoreResult<Vec<CredentialRecord>> {
+        if !self.initialized {
+            return Err(CoreError::NotInitialized);
+        }
+
+        self.collect_credentials(|c| c.favorite)
+    }
+
+    /// Caps on how many credentials are kept decoded
+    pub fn memory_budget(&self) -> &MemoryBudget {
+        &self.budget
+    }
+
+    /// Limit how many credentials are kept decoded
+    ///
+    /// Credentials over the cap that were used least recently are sealed
+    /// right away, and again after each change.
+    pub fn set_memory_budget(&mut self, budget: MemoryBudget) {
+        self.budget = budget;
+        self.apply_memory_budget(None);
+    }
+
+    /// Seal the least recently used credentials over the budget, leaving
+    /// `keep` decoded
+    fn apply_memory_budget(&mut self, keep: Option<&str>) {
+        self.sealed.close_opened();
+        let Some(max) = self.budget.max_decrypted_records else {
+            return;
+        };
+        let excess = self.credentials.len().saturating_sub(max.max(1));
+        if excess == 0 {
+            return;
+        }
+
+        let mut by_use: Vec<(i64, String)> = self
+            .credentials
+            .values()
+            .filter(|c| Some(c.id.as_str()) != keep)
+            .map(|c| (c.accessed_at.max(c.updated_at), c.id.clone()))
+            .collect();
+        by_use.sort();
+        for (_, id) in by_use.into_iter().take(excess) {
+            let Some(credential) = self.credentials.get(&id) else {
+                continue;
+            };
+            // A record that cannot be sealed simply stays decoded
+            if self.sealed.seal(credential).is_ok() {
+                if let Some(mut credential) = self.credentials.remove(&id) {
+                    wipe_record(&mut credential);
+                }
+            }
+        }
+    }
+
+    fn wipe_unloaded_notes(&mut self) {
+        for (_, notes) in self.unloaded_notes.drain() {
+            for (_, mut note) in notes {
+                note.body.zeroize();
+            }
+        }
+    }
+
+    fn wipe_trash(&mut self) {
+        for (_, mut trashed) in self.trash.drain() {
+            wipe_record(&mut trashed.credential);
+        }
+    }
+
+    /// Decode the sealed credential `id`, if it is sealed
+    fn unseal(&mut self, id: &str) -> CoreResult<()> {
+        if let Some(credential) = self.sealed.take(id) {
+            self.credentials.insert(id.to_string(), credential?);
+        }
+        Ok(())
+    }
+
+    /// Call `visit` with every credential, opening sealed ones one at a time
+    fn visit_credentials(&self, mut visit: impl FnMut(&CredentialRecord)) -> CoreResult<()> {
+        self.credentials.values().for_each(&mut visit);
+        for id in self.sealed.ids() {
+            if let Some(credential) = self.sealed.open(id) {
+                let mut credential = credential?;
+                visit(&credential);
+                wipe_record(&mut credential);
+            }
+        }
+        Ok(())
+    }
+
+    /// Copies of the credentials `filter` accepts
+    fn collect_credentials(
+        &self,
+        filter: impl Fn(&CredentialRecord) -> bool,
+    ) -> CoreResult<Vec<CredentialRecord>> {
+        let mut credentials = Vec::new();
+        self.visit_credentials(|c| {
+            if filter(c) {
+                credentials.push(c.clone());
+            }
+        })?;
+        Ok(credentials)
+    }
+}
+
+/// Whether `path` is a user-defined template file
+pub(crate) fn is_template_path(path: &str) -> bool {
+    path.strip_prefix(TEMPLATES_DIR)
+        .and_then(|rest| rest.strip_prefix('/'))
+        .is_some_and(|name| !name.contains('/') && name.ends_with(".yml"))
+}
+
+fn template_path(name: &str) -> String {
+    format!("{}/{}.yml", TEMPLATES_DIR, name)
+}
+
+/// Archive path of the note body `file` of credential `id`
+pub(crate) fn note_path(id: &str, file: &str) -> String {
+    format!("{}/{}/{}", CREDENTIALS_DIR, id, file)
+}
+
+/// `credential` with multi-line values over [`NOTE_INLINE_LENGTH`] moved
+/// out, and those values by entry name
+fn split_large_notes(credential: &CredentialRecord) -> (CredentialRecord, Vec<(String, String)>) {
+    let mut credential = credential.clone();
+    let mut names: Vec<String> = credential
+        .fields
+        .iter()
+        .filter(|(_, field)| {
+            field.field_type == FieldType::TextArea
+                && !field.is_protected()
+                && field.value.len() > NOTE_INLINE_LENGTH
+        })
+        .map(|(name, _)| name.clone())
+        .collect();
+    names.sort();
+
+    let mut notes: Vec<(String, String)> = Vec::new();
+    for name in names {
+        let Some(field) = credential.fields.get_mut(&name) else {
+            continue;
+        };
+        let taken: Vec<String> = notes.iter().map(|(file, _)| file.clone()).collect();
+        let file = note_file_name(&name, field.note_format(), &taken);
+        field
+            .metadata
+            .insert(NOTE_FILE_KEY.to_string(), file.clone());
+        notes.push((file, std::mem::take(&mut field.value)));
+    }
+    (credential, notes)
+}
+
+/// Whether any field of `credential` has more than `limit` history entries
+fn has_history_over(credential: &CredentialRecord, limit: usize) -> bool {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    credential.field_history.iter().any(|entry| {
+        let count = counts.entry(entry.field.as_str()).or_default();
+        *count += 1;
+        *count > limit
+    })
+}
+
+/// Put note bodies read from the archive back into `credential`
+fn fill_notes(credential: &mut CredentialRecord, notes: HashMap<String, UnloadedNote>) {
+    for (name, mut note) in notes {
+        if let Some(field) = credential.fields.get_mut(&name) {
+            field.value = String::from_utf8_lossy(&note.body).into_owned();
+            field.metadata.remove(NOTE_FILE_KEY);
+        }
+        note.body.zeroize();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{CredentialField, CredentialRecord};
+
+    fn create_test_credential(title: &str) -> CredentialRecord {
+        let mut credential = CredentialRecord::new(title.to_string(), "test".to_string());
+        credential.set_field("username", CredentialField::username("testuser"));
+        credential.set_field("password", CredentialField::password("testpass"));
+        credential
+    }
+
+    #[test]
+    fn test_memory_budget_seals_and_reopens() {
+        let mut repo = UnifiedMemoryRepository::new();
+        repo.initialize().unwrap();
+        let mut ids = Vec::new();
+        for i in 0..5 {
+            let credential = create_test_credential(&format!("Site {i}"));
+            ids.push(credential.id.clone());
+            repo.add_credential(credential).unwrap();
+        }
+
+        repo.set_memory_budget(MemoryBudget::unlimited().with_max_decrypted_records(2));
+        assert_eq!(repo.credentials.len(), 2);
+        assert_eq!(repo.get_stats().unwrap().credential_count, 5);
+        assert_eq!(repo.list_credentials().unwrap().len(), 5);
+        assert_eq!(repo.search_credentials("site").unwrap().len(), 5);
+
+        let sealed = ids
+            .iter()
+            .find(|id| !repo.credentials.contains_key(*id))
+            .unwrap()
+            .clone();
+        assert_eq!(
+            repo.get_credential_readonly(&sealed)
+                .unwrap()
+                .get_field("password")
+                .unwrap()
+                .value,
+            "testpass"
+        );
+        let mut renamed = repo.get_credential(&sealed).unwrap().clone();
+        assert!(repo.credentials.contains_key(&sealed));
+        assert_eq!(repo.credentials.len(), 2);
+        renamed.title = "Renamed".to_string();
+        repo.update_credential(renamed).unwrap();
+
+        let files = repo.serialize_to_files().unwrap();
+        let mut reopened = UnifiedMemoryRepository::new();
+        reopened.load_from_files(files).unwrap();
+        assert_eq!(reopened.list_credentials().unwrap().len(), 5);
+        assert_eq!(
+            reopened.get_credential_readonly(&sealed).unwrap().title,
+            "Renamed"
+        );
+
+        repo.delete_credential(&ids[0]).unwrap();
+        repo.delete_credential(&ids[1]).unwrap();
+        assert_eq!(repo.get_stats().unwrap().credential_count, 3);
+        assert!(!repo.contains_credential(&ids[0]));
+    }
+
+    #[test]
     fn test_repository_lifecycle() {
         let mut repo = UnifiedMemoryRepository::new();
 
@@ -698,6 +1999,64 @@ mod tests {
         assert!(!new_repo.is_modified()); // Should not be modified after load
     }
 
+    #[test]
+    fn test_large_notes_load_lazily() {
+        use crate::core::integrity::check_files;
+        use crate::core::types::MAX_NOTE_LENGTH;
+        use crate::models::NoteFormat;
+
+        let mut repo = UnifiedMemoryRepository::new();
+        repo.initialize().unwrap();
+        let body = "- [ ] rotate the door code\n".repeat(NOTE_INLINE_LENGTH / 10);
+        let mut note = CommonTemplates::secure_note()
+            .create_credential("Runbook".to_string())
+            .unwrap();
+        let content = note.fields.get_mut("content").unwrap();
+        content.value = body.clone();
+        content.set_note_format(NoteFormat::Markdown);
+        let id = note.id.clone();
+        repo.add_credential(note.clone()).unwrap();
+
+        let files = repo.serialize_to_files().unwrap();
+        let entry = format!("{}/{}/content.md", CREDENTIALS_DIR, id);
+        assert_eq!(files[&entry], body.as_bytes());
+        let record =
+            String::from_utf8(files[&format!("{}/{}/record.yml", CREDENTIALS_DIR, id)].clone())
+                .unwrap();
+        assert!(!record.contains("rotate the door code"));
+        assert!(check_files(&files).issues.is_empty());
+
+        let mut loaded = UnifiedMemoryRepository::new();
+        loaded.load_from_files(files).unwrap();
+        let listed = loaded.get_credential_readonly(&id).unwrap();
+        assert!(listed.fields["content"].is_note_unloaded());
+        assert_eq!(listed.fields["content"].value, "");
+        let opened = loaded.get_credential_with_notes(&id).unwrap();
+        assert_eq!(opened.fields["content"].value, body);
+        assert_eq!(opened.fields["content"].note_format(), NoteFormat::Markdown);
+        assert!(!opened.fields["content"].is_note_unloaded());
+        assert_eq!(
+            loaded.export_credentials().unwrap()[0].fields["content"].value,
+            body
+        );
+
+        // Changing other fields keeps the body where it is
+        let mut renamed = listed.clone();
+        renamed.title = "Runbook 2026".to_string();
+        loaded.update_credential(renamed).unwrap();
+        let files = loaded.serialize_to_files().unwrap();
+        assert_eq!(files[&entry], body.as_bytes());
+        assert_eq!(
+            loaded.get_credential(&id).unwrap().fields["content"].value,
+            body
+        );
+        assert!(loaded.unloaded_notes.is_empty());
+
+        note.fields.get_mut("content").unwrap().value = "x".repeat(MAX_NOTE_LENGTH + 1);
+        let error = repo.update_credential(note).unwrap_err();
+        assert!(error.to_string().contains("too long"));
+    }
+
     #[test]
     fn test_repository_stats() {
         let mut repo = UnifiedMemoryRepository::new();
@@ -738,6 +2097,404 @@ mod tests {
         assert!(repo.get_stats().is_err());
     }
 
+    #[test]
+    fn test_user_defined_templates() {
+        use crate::models::{FieldTemplate, FieldType};
+
+        let mut repo = UnifiedMemoryRepository::new();
+        repo.initialize().unwrap();
+
+        let mut vpn = CredentialTemplate::new("vpn", "VPN account");
+        vpn.fields = vec![
+            FieldTemplate::new("server", "Server", FieldType::Url, true),
+            FieldTemplate::new("username", "Username", FieldType::Username, true),
+        ];
+        repo.add_template(vpn.clone()).unwrap();
+        assert!(repo.add_template(vpn.clone()).is_err());
+        assert!(repo
+            .add_template(CredentialTemplate {
+                name: "login".to_string(),
+                ..vpn.clone()
+            })
+            .is_err());
+        assert!(repo
+            .update_template(CredentialTemplate::new("wifi", "Built in"))
+            .is_err());
+
+        let mut colliding = vpn.clone();
+        colliding.fields.push(FieldTemplate::new(
+            "Username",
+            "Login",
+            FieldType::Text,
+            false,
+        ));
+        assert!(matches!(
+            repo.update_template(colliding),
+            Err(CoreError::ValidationError { message }) if message.contains("'Username'")
+        ));
+
+        vpn.description = "Company VPN".to_string();
+        repo.update_template(vpn.clone()).unwrap();
+        repo.set_folder_defaults(
+            "Work",
+            FolderDefaults {
+                template: Some("vpn".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let files = repo.serialize_to_files().unwrap();
+        assert!(files.contains_key("templates/vpn.yml"));
+        let mut loaded = UnifiedMemoryRepository::new();
+        loaded.load_from_files(files).unwrap();
+        assert_eq!(loaded.list_templates().unwrap(), vec![vpn]);
+        let schema = loaded.credential_form_schema(Some("Work"), None).unwrap();
+        assert_eq!(schema.template.description, "Company VPN");
+        assert_eq!(schema.template.fields.len(), 2);
+    }
+
+    #[test]
+    fn test_protected_fields() {
+        use crate::models::PROTECTED_FIELD_PLACEHOLDER;
+
+        let mut repo = UnifiedMemoryRepository::new();
+        repo.initialize().unwrap();
+        let mut credential = create_test_credential("Bank");
+        credential.set_field("recovery", CredentialField::text("alpha bravo charlie"));
+        let id = credential.id.clone();
+        repo.add_credential(credential).unwrap();
+
+        repo.protect_field(&id, "recovery", "extra").unwrap();
+        assert!(repo.protect_field(&id, "recovery", "extra").is_err());
+        assert!(repo.protect_field(&id, "missing", "extra").is_err());
+        let stored = repo.get_credential_readonly(&id).unwrap().clone();
+        assert_eq!(
+            stored.get_field("recovery").unwrap().value,
+            PROTECTED_FIELD_PLACEHOLDER
+        );
+
+        // Round-trips through an update and the archive files
+        repo.update_credential(stored.clone()).unwrap();
+        let files = repo.serialize_to_files().unwrap();
+        let yaml = String::from_utf8_lossy(&files[&format!("credentials/{id}/record.yml")]);
+        assert!(!yaml.contains("alpha bravo"));
+        let mut loaded = UnifiedMemoryRepository::new();
+        loaded.load_from_files(files).unwrap();
+
+        assert_eq!(
+            loaded.unlock_field(&id, "recovery", "wrong"),
+            Err(CoreError::FieldPassphraseInvalid {
+                field: "recovery".to_string()
+            })
+        );
+        assert_eq!(
+            loaded
+                .unlock_field(&id, "recovery", "extra")
+                .unwrap()
+                .as_str(),
+            "alpha bravo charlie"
+        );
+        assert!(loaded.unlock_field(&id, "username", "extra").is_err());
+
+        let mut edited = stored;
+        edited.fields.get_mut("recovery").unwrap().value = "changed".to_string();
+        assert!(loaded.update_credential(edited).is_err());
+
+        loaded
+            .change_field_passphrase(&id, "recovery", "extra", "new")
+            .unwrap();
+        loaded.unprotect_field(&id, "recovery", "new").unwrap();
+        let field = loaded
+            .get_credential_readonly(&id)
+            .unwrap()
+            .get_field("recovery")
+            .unwrap();
+        assert!(!field.is_protected());
+        assert_eq!(field.value, "alpha bravo charlie");
+    }
+
+    #[test]
+    fn test_deleted_credentials_go_to_trash() {
+        let mut repo = UnifiedMemoryRepository::new();
+        repo.initialize().unwrap();
+        repo.set_retention_quotas(RetentionQuotas::new().with_max_trash_items(10));
+        let credential = create_test_credential("Old Bank");
+        let id = credential.id.clone();
+        repo.add_credential(credential).unwrap();
+
+        repo.delete_credential(&id).unwrap();
+        assert!(repo.get_credential_readonly(&id).is_err());
+        assert_eq!(repo.list_trash().unwrap()[0].credential.title, "Old Bank");
+
+        // The trash survives a save and open, outside the credential count
+        let files = repo.serialize_to_files().unwrap();
+        assert!(files.contains_key(&trash_path(&id)));
+        let mut reopened = UnifiedMemoryRepository::new();
+        reopened.load_from_files(files).unwrap();
+        assert_eq!(reopened.list_credentials().unwrap().len(), 0);
+        assert_eq!(reopened.list_trash().unwrap().len(), 1);
+
+        reopened.restore_credential(&id).unwrap();
+        assert!(reopened.list_trash().unwrap().is_empty());
+        assert_eq!(
+            reopened.get_credential_readonly(&id).unwrap().title,
+            "Old Bank"
+        );
+
+        // By default there is no trash and deleting removes the credential
+        // outright
+        assert_eq!(reopened.retention_quotas().max_trash_items, 0);
+        reopened.delete_credential(&id).unwrap();
+        assert!(reopened.list_trash().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_prune_enforces_quotas() {
+        let mut repo = UnifiedMemoryRepository::new();
+        repo.initialize().unwrap();
+        repo.set_retention_quotas(RetentionQuotas::new().with_max_trash_items(10));
+        let mut ids = Vec::new();
+        for title in ["One", "Two", "Three"] {
+            let credential = create_test_credential(title);
+            ids.push(credential.id.clone());
+            repo.add_credential(credential).unwrap();
+        }
+        let kept = ids.pop().unwrap();
+        for password in ["second", "third", "fourth"] {
+            let mut edited = repo.get_credential_readonly(&kept).unwrap().clone();
+            edited.fields.get_mut("password").unwrap().value = password.to_string();
+            repo.update_credential(edited).unwrap();
+        }
+        for id in &ids {
+            repo.delete_credential(id).unwrap();
+        }
+        let now = Utc::now().timestamp();
+        assert!(repo.prune(now).unwrap().is_empty());
+
+        repo.set_retention_quotas(
+            RetentionQuotas::new()
+                .with_max_revisions_per_credential(1)
+                .with_max_trash_items(1),
+        );
+        let report = repo.prune(now).unwrap();
+        assert_eq!(report.trash_removed.len(), 1);
+        assert_eq!(report.history_trimmed, vec![kept.clone()]);
+        assert_eq!(report.history_entries_removed, 2);
+        assert_eq!(repo.list_trash().unwrap().len(), 1);
+        let stored = repo.get_credential_readonly(&kept).unwrap();
+        assert_eq!(stored.previous_values("password")[0].value, "third");
+
+        // Past the age limit the rest of the trash goes as well
+        let report = repo.prune(now + 31 * 86_400).unwrap();
+        assert_eq!(report.trash_removed.len(), 1);
+        assert!(repo.list_trash().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_password_history() {
+        let mut repo = UnifiedMemoryRepository::new();
+        repo.initialize().unwrap();
+        repo.set_field_history_limit(2);
+        let credential = create_test_credential("Forum");
+        let id = credential.id.clone();
+        repo.add_credential(credential).unwrap();
+
+        for password in ["second", "third", "fourth"] {
+            let mut edited = repo.get_credential_readonly(&id).unwrap().clone();
+            edited.fields.get_mut("password").unwrap().value = password.to_string();
+            // What callers send is not taken as history
+            edited.field_history.clear();
+            repo.update_credential(edited).unwrap();
+        }
+        let mut edited = repo.get_credential_readonly(&id).unwrap().clone();
+        edited.fields.get_mut("username").unwrap().value = "renamed".to_string();
+        repo.update_credential(edited).unwrap();
+
+        let stored = repo.get_credential_readonly(&id).unwrap();
+        let password_changed = stored.field_changed_at("password").unwrap();
+        assert!(password_changed >= stored.created_at);
+        assert!(stored.field_changed_at("username").unwrap() >= password_changed);
+        let previous: Vec<&str> = stored
+            .previous_values("password")
+            .iter()
+            .map(|entry| entry.value.as_str())
+            .collect();
+        assert_eq!(previous, ["third", "second"]);
+        assert!(stored.previous_values("username").is_empty());
+
+        // Removing the field keeps its value, and restoring brings it back
+        let mut edited = stored.clone();
+        edited.fields.remove("password");
+        repo.update_credential(edited).unwrap();
+        let removed = repo
+            .get_credential_readonly(&id)
+            .unwrap()
+            .previous_values("password")[0]
+            .clone();
+        assert!(removed.removed);
+        assert_eq!(removed.value, "fourth");
+        repo.restore_previous_value(&id, "password", removed.replaced_at)
+            .unwrap();
+        let restored = repo.get_credential_readonly(&id).unwrap();
+        assert_eq!(restored.get_field("password").unwrap().value, "fourth");
+        assert!(repo.restore_previous_value(&id, "password", 0).is_err());
+
+        // History survives the archive files and can be cleared
+        let files = repo.serialize_to_files().unwrap();
+        let mut loaded = UnifiedMemoryRepository::new();
+        loaded.load_from_files(files).unwrap();
+        assert_eq!(
+            loaded.get_credential_readonly(&id).unwrap().field_history,
+            repo.get_credential_readonly(&id).unwrap().field_history
+        );
+        assert_eq!(
+            loaded.clear_field_history(&id, Some("password")).unwrap(),
+            2
+        );
+        assert!(loaded
+            .get_credential_readonly(&id)
+            .unwrap()
+            .field_history
+            .is_empty());
+    }
+
+    #[test]
+    fn test_recurring_drafts() {
+        use crate::models::{RecurrenceInterval, INBOX_TAG};
+
+        let mut repo = UnifiedMemoryRepository::new();
+        repo.initialize().unwrap();
+        let recurring = RecurringCredential::new(
+            "login",
+            "Door code {date}",
+            RecurrenceInterval::Days(30),
+            1_000_000,
+        )
+        .with_carry_over(true);
+        let id = recurring.id.clone();
+        repo.set_recurring_credential(recurring.clone()).unwrap();
+        let mut unknown = recurring.clone();
+        unknown.template = "missing".to_string();
+        assert!(repo.set_recurring_credential(unknown).is_err());
+
+        assert!(repo.create_due_drafts(999_999).unwrap().is_empty());
+        let drafts = repo.create_due_drafts(1_000_000 + 90 * 86_400).unwrap();
+        assert_eq!(drafts.len(), 1);
+        assert_eq!(drafts[0].title, "Door code 1970-01-12");
+        let draft = repo
+            .get_credential_readonly(&drafts[0].credential_id)
+            .unwrap()
+            .clone();
+        assert!(draft.tags.iter().any(|tag| tag == INBOX_TAG));
+        assert!(repo
+            .create_due_drafts(1_000_000 + 90 * 86_400)
+            .unwrap()
+            .is_empty());
+
+        // The next draft starts from the filled-in one
+        let mut filled = draft;
+        filled.fields.get_mut("username").unwrap().value = "side door".to_string();
+        repo.update_credential(filled).unwrap();
+        let next_due = repo.recurring_credentials()[0].next_due;
+        let drafts = repo.create_due_drafts(next_due).unwrap();
+        let draft = repo
+            .get_credential_readonly(&drafts[0].credential_id)
+            .unwrap();
+        assert_eq!(draft.get_field("username").unwrap().value, "side door");
+
+        // Definitions are kept in the metadata
+        let files = repo.serialize_to_files().unwrap();
+        let mut loaded = UnifiedMemoryRepository::new();
+        loaded.load_from_files(files).unwrap();
+        assert_eq!(loaded.recurring_credentials(), repo.recurring_credentials());
+        loaded.remove_recurring_credential(&id).unwrap();
+        assert!(loaded.recurring_credentials().is_empty());
+        assert!(loaded.remove_recurring_credential(&id).is_err());
+    }
+
+    #[test]
+    fn test_members_and_access_lists() {
+        use crate::utils::ShareIdentity;
+
+        let mut repo = UnifiedMemoryRepository::new();
+        repo.initialize().unwrap();
+        let recipient = ShareIdentity::generate().recipient();
+        repo.set_member(VaultMember::new("alice", &recipient))
+            .unwrap();
+        repo.set_member(VaultMember::new("bob", &recipient))
+            .unwrap();
+        assert!(repo
+            .set_member(VaultMember::new("no/slash", &recipient))
+            .is_err());
+
+        let mut shared = create_test_credential("Shared");
+        shared.shared_with = vec!["alice".to_string(), "bob".to_string()];
+        repo.add_credential(shared.clone()).unwrap();
+        repo.add_credential(create_test_credential("Private"))
+            .unwrap();
+        assert_eq!(repo.credentials_shared_with("bob").unwrap().len(), 1);
+
+        let files = repo.serialize_to_files().unwrap();
+        let mut loaded = UnifiedMemoryRepository::new();
+        loaded.load_from_files(files).unwrap();
+        assert_eq!(loaded.members(), repo.members());
+
+        loaded.remove_member("bob").unwrap();
+        assert_eq!(loaded.members().len(), 1);
+        assert!(loaded.credentials_shared_with("bob").unwrap().is_empty());
+        let stored = loaded.get_credential_readonly(&shared.id).unwrap();
+        assert_eq!(stored.shared_with, ["alice"]);
+        assert!(loaded.remove_member("bob").is_err());
+    }
+
+    #[test]
+    fn test_search_index_follows_changes() {
+        let mut repo = UnifiedMemoryRepository::new();
+        assert!(repo.search_credentials("bank").is_err());
+        repo.initialize().unwrap();
+
+        let mut bank = create_test_credential("Bank");
+        bank.set_field("recovery", CredentialField::text("alpha bravo"));
+        bank.add_tag("finance");
+        let id = bank.id.clone();
+        repo.add_credential(bank).unwrap();
+        repo.add_credential(create_test_credential("Forum"))
+            .unwrap();
+
+        let titles = |repo: &UnifiedMemoryRepository, query: &str| -> Vec<String> {
+            repo.search_credentials(query)
+                .unwrap()
+                .into_iter()
+                .map(|c| c.title)
+                .collect()
+        };
+        assert_eq!(titles(&repo, "tag:finance bnak"), vec!["Bank"]);
+        assert_eq!(titles(&repo, "bravo"), vec!["Bank"]);
+        assert_eq!(titles(&repo, "testuser").len(), 2);
+
+        // Protected values drop out of the index
+        repo.protect_field(&id, "recovery", "extra").unwrap();
+        assert!(titles(&repo, "bravo").is_empty());
+
+        let mut renamed = repo.get_credential_readonly(&id).unwrap().clone();
+        renamed.title = "Savings".to_string();
+        repo.update_credential(renamed).unwrap();
+        assert!(titles(&repo, "bank").is_empty());
+        assert_eq!(titles(&repo, "savings"), vec!["Savings"]);
+
+        let files = repo.serialize_to_files().unwrap();
+        repo.delete_credential(&id).unwrap();
+        assert!(titles(&repo, "savings").is_empty());
+
+        let mut loaded = UnifiedMemoryRepository::new();
+        loaded.load_from_files(files).unwrap();
+        assert_eq!(titles(&loaded, "savings"), vec!["Savings"]);
+        loaded.wipe();
+        loaded.initialize().unwrap();
+        assert!(titles(&loaded, "").is_empty());
+    }
+
     #[test]
     fn test_credential_filtering() {
         let mut repo = UnifiedMemoryRepository::new();
@@ -792,9 +2549,13 @@ mod tests {
         let mut repo2 = UnifiedMemoryRepository::new();
         repo2.initialize().unwrap();
 
-        let imported_count = repo2.import_credentials(exported).unwrap();
+        let imported_count = repo2.import_credentials(exported.clone()).unwrap();
         assert_eq!(imported_count, 2);
         assert_eq!(repo2.credentials.len(), 2);
+
+        // Importing the same export again updates instead of duplicating
+        assert_eq!(repo2.import_credentials(exported).unwrap(), 2);
+        assert_eq!(repo2.credentials.len(), 2);
     }
 
     #[test]
@@ -0,0 +1,533 @@
+//! Merging diverged copies of a repository
+//!
+//! Sync services such as Dropbox keep both versions when an archive is saved
+//! on two devices at once ("conflicted copy"). The merger compares the
+//! credentials of two copies by ID: credentials that exist on one side only
+//! are carried over, and edits that don't overlap (a field or tag added on one
+//! side) are combined. Credentials edited differently on both sides become
+//! conflicts for the user to resolve; the newer edit is suggested.
+//!
+//! Merging never deletes: a credential missing from one copy may have been
+//! deleted there or added to the other, and the two cannot be told apart.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::core::duress::DuressArchive;
+use crate::core::errors::{CoreError, CoreResult};
+use crate::core::file_provider::FileOperationProvider;
+use crate::core::memory_repository::UnifiedMemoryRepository;
+use crate::core::repository_manager::UnifiedRepositoryManager;
+use crate::models::{CredentialField, CredentialRecord};
+
+/// How a credential ended up in the merged set
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MergeChange {
+    /// Only present in the other copy
+    AddedFromTheirs,
+    /// Only present in our copy
+    KeptOurs,
+    /// Both copies edited different parts; the edits were combined
+    Combined,
+}
+
+/// A credential edited differently in both copies
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MergeConflict {
+    /// Credential ID (the same in both copies)
+    pub id: String,
+    /// Our version
+    pub ours: CredentialRecord,
+    /// The other copy's version
+    pub theirs: CredentialRecord,
+    /// Names of the differing properties (`title`, `notes`, `field:password`, ...)
+    pub differences: Vec<String>,
+    /// Resolution suggested from `updated_at`
+    pub suggested: ConflictResolution,
+}
+
+/// How to resolve a conflict
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ConflictResolution {
+    /// Keep our version
+    KeepOurs,
+    /// Keep the other copy's version
+    KeepTheirs,
+    /// Keep ours and add theirs as a separate credential
+    KeepBoth,
+    /// Use a hand-edited version
+    Custom(Box<CredentialRecord>),
+}
+
+/// Outcome of comparing two copies
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MergeResult {
+    /// Credentials that merged cleanly
+    pub merged: Vec<CredentialRecord>,
+    /// What happened to credentials that differed between the copies, by ID
+    pub changes: Vec<(String, MergeChange)>,
+    /// Unresolved conflicts
+    pub conflicts: Vec<MergeConflict>,
+}
+
+impl MergeResult {
+    /// Whether every conflict has been resolved
+    pub fn is_resolved(&self) -> bool {
+        self.conflicts.is_empty()
+    }
+
+    /// Resolve one conflict, moving the chosen version(s) into `merged`
+    pub fn resolve(&mut self, id: &str, resolution: ConflictResolution) -> CoreResult<()> {
+        let index = self
+            .conflicts
+            .iter()
+            .position(|c| c.id == id)
+            .ok_or_else(|| CoreError::CredentialNotFound { id: id.to_string() })?;
+        let conflict = self.conflicts.remove(index);
+        self.apply_resolution(conflict, resolution);
+        Ok(())
+    }
+
+    /// Resolve every remaining conflict with its suggestion
+    pub fn resolve_all_suggested(&mut self) {
+        for conflict in std::mem::take(&mut self.conflicts) {
+            let suggested = conflict.suggested.clone();
+            self.apply_resolution(conflict, suggested);
+        }
+    }
+
+    fn apply_resolution(&mut self, conflict: MergeConflict, resolution: ConflictResolution) {
+        match resolution {
+            ConflictResolution::KeepOurs => self.merged.push(conflict.ours),
+            ConflictResolution::KeepTheirs => self.merged.push(conflict.theirs),
+            ConflictResolution::KeepBoth => {
+                let mut copy = conflict.theirs;
+                copy.id = uuid::Uuid::new_v4().to_string();
+                copy.title = format!("{} (conflicted copy)", copy.title);
+                self.merged.push(conflict.ours);
+                self.merged.push(copy);
+            }
+            ConflictResolution::Custom(mut credential) => {
+                credential.id = conflict.id;
+                self.merged.push(*credential);
+            }
+        }
+    }
+
+    /// Write the merged credentials into an open repository
+    ///
+    /// Existing credentials are updated and missing ones added. Fails if any
+    /// conflict is unresolved. Returns the number of credentials written.
+    pub fn apply_to<F: FileOperationProvider>(
+        &self,
+        manager: &mut UnifiedRepositoryManager<F>,
+    ) -> CoreResult<usize> {
+        if !self.is_resolved() {
+            return Err(CoreError::ValidationError {
+                message: format!("{} merge conflict(s) are unresolved", self.conflicts.len()),
+            });
+        }
+
+        let mut written = 0;
+        for credential in &self.merged {
+            match manager.get_credential_readonly(&credential.id) {
+                Ok(existing) if same_content(existing, credential) => continue,
+                Ok(_) => manager.update_credential(credential.clone())?,
+                Err(_) => manager.add_credential(credential.clone())?,
+            }
+            written += 1;
+        }
+        Ok(written)
+    }
+}
+
+/// Compares and merges two copies of a repository
+pub struct RepositoryMerger<F: FileOperationProvider> {
+    file_provider: F,
+}
+
+impl<F: FileOperationProvider> RepositoryMerger<F> {
+    /// Create a merger that reads archives with `file_provider`
+    pub fn new(file_provider: F) -> Self {
+        Self { file_provider }
+    }
+
+    /// Read the credentials of an archive without opening it for editing
+    ///
+    /// Of a 7z archive, reads whichever part `master_password` opens; see
+    /// [`crate::core::duress`].
+    pub fn load(&self, path: &str, master_password: &str) -> CoreResult<Vec<CredentialRecord>> {
+        let data = self.file_provider.read_archive(path)?;
+        let files = match DuressArchive::from_bytes(&data) {
+            Ok(container) => {
+                container
+                    .open(master_password, |archive, password| {
+                        self.file_provider.extract_archive(archive, password)
+                    })?
+                    .0
+            }
+            Err(_) => self.file_provider.extract_archive(&data, master_password)?,
+        };
+
+        let mut repository = UnifiedMemoryRepository::new();
+        repository.load_from_files(files)?;
+        repository.list_credentials()
+    }
+
+    /// Compare two archives that share a master password
+    pub fn merge_archives(
+        &self,
+        ours_path: &str,
+        theirs_path: &str,
+        master_password: &str,
+    ) -> CoreResult<MergeResult> {
+        let ours = self.load(ours_path, master_password)?;
+        let theirs = self.load(theirs_path, master_password)?;
+        Ok(Self::merge(&ours, &theirs))
+    }
+
+    /// Compare two sets of credentials
+    pub fn merge(ours: &[CredentialRecord], theirs: &[CredentialRecord]) -> MergeResult {
+        let mut theirs_by_id: HashMap<&str, &CredentialRecord> =
+            theirs.iter().map(|c| (c.id.as_str(), c)).collect();
+        let mut result = MergeResult::default();
+
+        for our in ours {
+            let Some(their) = theirs_by_id.remove(our.id.as_str()) else {
+                result.changes.push((our.id.clone(), MergeChange::KeptOurs));
+                result.merged.push(our.clone());
+                continue;
+            };
+
+            if our == their {
+                result.merged.push(our.clone());
+                continue;
+            }
+
+            match combine(our, their) {
+                Ok(combined) => {
+                    // Only timestamps differed; nothing worth reporting
+                    if !same_content(&combined, our) || !same_content(&combined, their) {
+                        result.changes.push((our.id.clone(), MergeChange::Combined));
+                    }
+                    result.merged.push(combined);
+                }
+                Err(differences) => result.conflicts.push(MergeConflict {
+                    id: our.id.clone(),
+                    suggested: if their.updated_at > our.updated_at {
+                        ConflictResolution::KeepTheirs
+                    } else {
+                        ConflictResolution::KeepOurs
+                    },
+                    ours: our.clone(),
+                    theirs: their.clone(),
+                    differences,
+                }),
+            }
+        }
+
+        // Preserve the other copy's order for credentials we don't have
+        for their in theirs {
+            if theirs_by_id.remove(their.id.as_str()).is_some() {
+                result
+                    .changes
+                    .push((their.id.clone(), MergeChange::AddedFromTheirs));
+                result.merged.push(their.clone());
+            }
+        }
+
+        result
+    }
+}
+
+/// Combine two versions of a credential whose edits don't overlap
+///
+/// A property set on one side and empty on the other takes the set value;
+/// tags are unioned. Returns the names of properties that differ on both
+/// sides if the versions can't be combined.
+fn combine(
+    ours: &CredentialRecord,
+    theirs: &CredentialRecord,
+) -> Result<CredentialRecord, Vec<String>> {
+    let mut merged = ours.clone();
+    let mut differences = Vec::new();
+
+    let mut text = |name: &str, a: &str, b: &str| -> Option<String> {
+        match (a.is_empty(), b.is_empty()) {
+            _ if a == b => Some(a.to_string()),
+            (true, false) => Some(b.to_string()),
+            (false, true) => Some(a.to_string()),
+            _ => {
+                differences.push(name.to_string());
+                None
+            }
+        }
+    };
+
+    if let Some(title) = text("title", &ours.title, &theirs.title) {
+        merged.title = title;
+    }
+    if let Some(kind) = text("type", &ours.credential_type, &theirs.credential_type) {
+        merged.credential_type = kind;
+    }
+    let optional = |value: &Option<String>| value.clone().unwrap_or_default();
+    if let Some(notes) = text("notes", &optional(&ours.notes), &optional(&theirs.notes)) {
+        merged.notes = Some(notes).filter(|n| !n.is_empty());
+    }
+    if let Some(folder) = text(
+        "folder",
+        &optional(&ours.folder_path),
+        &optional(&theirs.folder_path),
+    ) {
+        merged.folder_path = Some(folder).filter(|f| !f.is_empty());
+    }
+    if let Some(sequence) = text(
+        "auto_type",
+        &optional(&ours.auto_type),
+        &optional(&theirs.auto_type),
+    ) {
+        merged.auto_type = Some(sequence).filter(|s| !s.is_empty());
+    }
+
+    for (name, their_field) in &theirs.fields {
+        match ours.fields.get(name) {
+            None => {
+                merged.fields.insert(name.clone(), their_field.clone());
+            }
+            Some(our_field) if our_field == their_field => {}
+            Some(our_field) => match combine_field(our_field, their_field) {
+                Some(field) => {
+                    merged.fields.insert(name.clone(), field);
+                }
+                None => differences.push(format!("field:{}", name)),
+            },
+        }
+    }
+
+    for tag in &theirs.tags {
+        if !merged.tags.contains(tag) {
+            merged.tags.push(tag.clone());
+        }
+    }
+
+    if ours.favorite != theirs.favorite {
+        // Favouriting is cheap to undo; keep the newer choice
+        merged.favorite = if theirs.updated_at > ours.updated_at {
+            theirs.favorite
+        } else {
+            ours.favorite
+        };
+    }
+
+    if !differences.is_empty() {
+        differences.sort();
+        return Err(differences);
+    }
+
+    merged.created_at = ours.created_at.min(theirs.created_at);
+    merged.updated_at = ours.updated_at.max(theirs.updated_at);
+    merged.accessed_at = ours.accessed_at.max(theirs.accessed_at);
+    Ok(merged)
+}
+
+/// Combine two versions of a field when only one side has a value
+fn combine_field(ours: &CredentialField, theirs: &CredentialField) -> Option<CredentialField> {
+    if ours.field_type != theirs.field_type {
+        return None;
+    }
+
+    let mut field = match (ours.value.is_empty(), theirs.value.is_empty()) {
+        _ if ours.value == theirs.value => ours.clone(),
+        (true, false) => theirs.clone(),
+        (false, true) => ours.clone(),
+        _ => return None,
+    };
+
+    let (other, label) = if field.value == ours.value {
+        (theirs, ours.label.clone().or_else(|| theirs.label.clone()))
+    } else {
+        (ours, theirs.label.clone().or_else(|| ours.label.clone()))
+    };
+    field.label = label;
+    for (key, value) in &other.metadata {
+        field
+            .metadata
+            .entry(key.clone())
+            .or_insert_with(|| value.clone());
+    }
+    Some(field)
+}
+
+/// Equality ignoring timestamps
+fn same_content(a: &CredentialRecord, b: &CredentialRecord) -> bool {
+    let mut b = b.clone();
+    b.created_at = a.created_at;
+    b.updated_at = a.updated_at;
+    b.accessed_at = a.accessed_at;
+    *a == b
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::file_provider::DesktopFileProvider;
+    use crate::models::CredentialField;
+
+    fn login(title: &str, password: &str) -> CredentialRecord {
+        let mut credential = CredentialRecord::new(title.to_string(), "login".to_string());
+        credential.set_field("username", CredentialField::username("jane"));
+        credential.set_field("password", CredentialField::password(password));
+        credential
+    }
+
+    #[test]
+    fn test_identical_copies_merge_cleanly() {
+        let ours = vec![login("GitHub", "a"), login("GitLab", "b")];
+        let result = RepositoryMerger::<DesktopFileProvider>::merge(&ours, &ours);
+        assert!(result.is_resolved());
+        assert!(result.changes.is_empty());
+        assert_eq!(result.merged, ours);
+    }
+
+    #[test]
+    fn test_one_sided_credentials_are_kept() {
+        let shared = login("Shared", "a");
+        let ours = vec![shared.clone(), login("Only ours", "b")];
+        let theirs = vec![login("Only theirs", "c"), shared];
+
+        let result = RepositoryMerger::<DesktopFileProvider>::merge(&ours, &theirs);
+        assert!(result.is_resolved());
+        assert_eq!(result.merged.len(), 3);
+
+        let kinds: Vec<MergeChange> = result.changes.iter().map(|(_, c)| *c).collect();
+        assert_eq!(
+            kinds,
+            vec![MergeChange::KeptOurs, MergeChange::AddedFromTheirs]
+        );
+    }
+
+    #[test]
+    fn test_non_overlapping_edits_are_combined() {
+        let base = login("GitHub", "secret");
+
+        let mut ours = base.clone();
+        ours.tags.push("work".to_string());
+        ours.notes = Some("Recovery codes in safe".to_string());
+        ours.updated_at += 10;
+
+        let mut theirs = base.clone();
+        theirs.tags.push("dev".to_string());
+        theirs.set_field("totp", CredentialField::totp_secret("JBSWY3DPEHPK3PXP"));
+        theirs.updated_at += 20;
+
+        let result = RepositoryMerger::<DesktopFileProvider>::merge(&[ours], &[theirs]);
+        assert!(result.is_resolved(), "{:?}", result.conflicts);
+        assert_eq!(
+            result.changes,
+            vec![(base.id.clone(), MergeChange::Combined)]
+        );
+
+        let merged = &result.merged[0];
+        assert_eq!(merged.tags, vec!["work".to_string(), "dev".to_string()]);
+        assert!(merged.get_field("totp").is_some());
+        assert_eq!(merged.notes.as_deref(), Some("Recovery codes in safe"));
+        assert_eq!(merged.updated_at, base.updated_at + 20);
+    }
+
+    #[test]
+    fn test_overlapping_edits_conflict() {
+        let base = login("GitHub", "old");
+        let mut ours = base.clone();
+        ours.set_field("password", CredentialField::password("from-laptop"));
+        ours.updated_at += 5;
+        let mut theirs = base.clone();
+        theirs.set_field("password", CredentialField::password("from-phone"));
+        theirs.title = "GitHub (personal)".to_string();
+        theirs.updated_at += 50;
+
+        let mut result =
+            RepositoryMerger::<DesktopFileProvider>::merge(&[ours.clone()], &[theirs.clone()]);
+        assert!(result.merged.is_empty());
+        assert_eq!(result.conflicts.len(), 1);
+
+        let conflict = &result.conflicts[0];
+        assert_eq!(conflict.differences, vec!["field:password", "title"]);
+        assert_eq!(conflict.suggested, ConflictResolution::KeepTheirs);
+
+        result
+            .resolve(&base.id, ConflictResolution::KeepBoth)
+            .unwrap();
+        assert!(result.is_resolved());
+        assert_eq!(result.merged.len(), 2);
+        assert_eq!(result.merged[0], ours);
+        assert_ne!(result.merged[1].id, base.id);
+        assert_eq!(
+            result.merged[1].title,
+            "GitHub (personal) (conflicted copy)"
+        );
+
+        assert!(result
+            .resolve(&base.id, ConflictResolution::KeepOurs)
+            .is_err());
+    }
+
+    #[test]
+    fn test_resolve_all_suggested() {
+        let base = login("GitHub", "old");
+        let mut ours = base.clone();
+        ours.set_field("password", CredentialField::password("newer"));
+        ours.updated_at += 100;
+        let mut theirs = base;
+        theirs.set_field("password", CredentialField::password("older"));
+        theirs.updated_at += 1;
+
+        let mut result = RepositoryMerger::<DesktopFileProvider>::merge(&[ours.clone()], &[theirs]);
+        result.resolve_all_suggested();
+        assert_eq!(result.merged, vec![ours]);
+    }
+
+    #[test]
+    fn test_merge_archives_and_apply() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let ours_path = dir.path().join("vault.7z").to_string_lossy().to_string();
+        let theirs_path = dir
+            .path()
+            .join("vault (conflicted copy).7z")
+            .to_string_lossy()
+            .to_string();
+
+        let shared = login("Shared", "a");
+        let mut manager = UnifiedRepositoryManager::new(DesktopFileProvider::new());
+        manager.create_repository(&ours_path, "password").unwrap();
+        manager.add_credential(shared.clone()).unwrap();
+        manager.add_credential(login("Laptop", "b")).unwrap();
+        manager.save_repository().unwrap();
+
+        let mut other = UnifiedRepositoryManager::new(DesktopFileProvider::new());
+        other.create_repository(&theirs_path, "password").unwrap();
+        other.add_credential(shared).unwrap();
+        other.add_credential(login("Phone", "c")).unwrap();
+        other.save_repository().unwrap();
+
+        let merger = RepositoryMerger::new(DesktopFileProvider::new());
+        let result = merger
+            .merge_archives(&ours_path, &theirs_path, "password")
+            .unwrap();
+        assert!(result.is_resolved());
+        assert_eq!(result.merged.len(), 3);
+
+        assert_eq!(result.apply_to(&mut manager).unwrap(), 1);
+        let mut titles: Vec<String> = manager
+            .list_credentials()
+            .unwrap()
+            .into_iter()
+            .map(|c| c.title)
+            .collect();
+        titles.sort();
+        assert_eq!(titles, vec!["Laptop", "Phone", "Shared"]);
+
+        assert!(merger
+            .merge_archives(&ours_path, &theirs_path, "wrong")
+            .is_err());
+    }
+}
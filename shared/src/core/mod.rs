@@ -2,27 +2,115 @@
 //!
 //! This module contains the core components of the unified architecture:
 //! - Pure memory repository for credential operations
-//! - File operation provider interface for platform abstraction
-//! - Repository manager that coordinates memory and file operations
+//! - File operation provider interface for platform abstraction, including a
+//!   callback-backed provider for browsers
+//! - Archive backends for the encrypted container format (7z by default,
+//!   SQLCipher for large vaults)
+//! - Repository manager that coordinates memory and file operations, with an
+//!   async variant that keeps file I/O off the caller's thread
+//! - Registry of several open repositories with combined search
+//! - S3-compatible storage provider (`s3` feature)
+//! - Plugins loaded from shared libraries (`dynamic-plugins` feature)
+//! - Merger for reconciling diverged copies of a repository
+//! - Integrity checks and repair that quarantines corrupt entries
+//! - Progress reporting for opening and saving large repositories
+//! - Change events for keeping frontends in sync with the repository
+//! - Trash of deleted credentials, pruned on save against retention quotas
+//! - Hash-chained activity log of opens, saves and credential changes, kept in the archive
+//! - Auto-lock policy with scheduled lock windows
+//! - Session idle timeout and maximum lifetime enforced by whoever serves the vault
+//! - Key files as a second factor for archive encryption
+//! - Key protectors such as FIDO2 security keys and the OS keychain for unlocking without the password
+//! - Known-answer self-test of the cryptography, run by apps at startup
+//! - 7z, AES-encrypted ZIP and age-encrypted tar archives, detected on open
+//! - Archive-format conformance fixtures and the runner that checks them
 //! - Error handling and type definitions
 
+pub mod activity_log;
+pub mod age_backend;
+pub mod archive_backend;
+pub mod async_repository_manager;
+pub mod auto_lock;
+pub mod conformance;
+pub mod duress;
 pub mod errors;
+pub mod events;
 pub mod file_provider;
+pub mod integrity;
+pub mod key_file;
+pub mod keyprotection;
+pub mod memory_budget;
 pub mod memory_repository;
+pub mod merge;
+#[cfg(all(feature = "dynamic-plugins", not(target_arch = "wasm32")))]
+pub mod plugin_loader;
 pub mod plugins;
+pub mod progress;
 pub mod repository_manager;
+pub mod repository_registry;
+#[cfg(all(feature = "s3", not(target_arch = "wasm32")))]
+pub mod s3_provider;
+pub mod self_test;
+pub mod session;
+pub(crate) mod seven_zip_update;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod sqlcipher_backend;
+#[cfg(feature = "test-vectors")]
+pub mod test_vectors;
+pub mod trash;
 pub mod types;
+pub mod unlock_throttle;
+pub mod zip_backend;
 
 // Re-export commonly used items
-pub use errors::{CoreError, CoreResult, FileError, FileResult};
-pub use file_provider::{DesktopFileProvider, FileOperationProvider, MockFileProvider};
+pub use activity_log::{ActivityAction, ActivityEntry, ActivityLog, ActivityQuery, ChainBreak};
+pub use age_backend::AgeTarBackend;
+pub use archive_backend::{
+    convert_archive, convert_repository, ArchiveBackend, ArchiveFormat, ArchiveOptions,
+    MultiFormatBackend, SevenZipBackend,
+};
+pub use async_repository_manager::AsyncRepositoryManager;
+pub use auto_lock::{AutoLockPolicy, LockDecision, LockReason};
+pub use conformance::{verify_compatibility, CompatibilityReport, Fixture, FixtureResult};
+pub use duress::{DuressArchive, OpenedArchive, SectionSlot};
+pub use errors::{
+    CoreError, CoreResult, FileError, FileResult, KeyProtectionError, KeyProtectionResult,
+};
+pub use events::{
+    ChangeEmitter, ChangeListener, ChangeListeners, EventBus, ListenerId, RepositoryEvent,
+};
+pub use file_provider::{
+    ArchiveWatch, AsyncFileOperationProvider, BlockingFileProvider, DesktopFileProvider,
+    FileOperationProvider, MockFileProvider, WasmFileProvider,
+};
+pub use integrity::{IntegrityIssue, IntegrityReport, RepairReport};
+pub use key_file::KeyFile;
+pub use keyprotection::{KeyProtector, ProtectorKind, ProtectorRecord};
+pub use memory_budget::MemoryBudget;
 pub use memory_repository::UnifiedMemoryRepository;
+pub use merge::{ConflictResolution, MergeChange, MergeConflict, MergeResult, RepositoryMerger};
+#[cfg(all(feature = "dynamic-plugins", not(target_arch = "wasm32")))]
+pub use plugin_loader::{DynamicPlugin, PluginLoadFailure, PluginLoadReport};
 pub use plugins::{
-    Plugin, PluginCapability, PluginManager, PluginMetadata, PluginRegistry, ValidationRule,
+    ActionProvider, AvailableFormat, ImportExportProvider, Plugin, PluginAction, PluginCapability,
+    PluginFormat, PluginManager, PluginMetadata, PluginRegistry, ValidationRule,
     ValidationSeverity,
 };
+pub use progress::{ArchivePhase, ProgressObserver};
 pub use repository_manager::UnifiedRepositoryManager;
-pub use types::{FileMap, RepositoryMetadata, RepositoryStats};
+pub use repository_registry::{RegistryHit, RepositoryRegistry, SharedRepository};
+#[cfg(all(feature = "s3", not(target_arch = "wasm32")))]
+pub use s3_provider::{ObjectVersion, S3Config, S3FileProvider};
+pub use self_test::{SelfTestCheck, SelfTestReport};
+pub use session::{SessionExpiry, SessionPolicy, VaultSession};
+#[cfg(not(target_arch = "wasm32"))]
+pub use sqlcipher_backend::SqlCipherBackend;
+#[cfg(feature = "test-vectors")]
+pub use test_vectors::{TestVector, TestVectorSet};
+pub use trash::{PruneReport, RetentionQuotas, TrashedCredential};
+pub use types::{FileMap, PasswordChangeStage, RepositoryMetadata, RepositoryStats};
+pub use unlock_throttle::{UnlockAttempts, UnlockStatus, UnlockThrottlePolicy};
+pub use zip_backend::ZipAesBackend;
 
 /// Version information for the core library
 pub const CORE_VERSION: &str = env!("CARGO_PKG_VERSION");
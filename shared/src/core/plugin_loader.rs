@@ -0,0 +1,811 @@
+//! Plugins loaded from shared libraries (`dynamic-plugins` feature)
+//!
+//! A plugin library exports one C function, `ziplock_plugin_entry`, which
+//! takes the host's ABI version and capability mask and returns a
+//! [`PluginVTable`]. Every call after that goes through the table's `call`
+//! function with an operation name and a byte payload, so the ABI stays the
+//! same as plugins gain operations: formats, actions and metadata travel as
+//! JSON, imported and exported files as raw bytes.
+//!
+//! Plugins written in Rust implement [`Plugin`] as usual and export it with
+//! [`export_plugin!`](crate::export_plugin), which catches panics inside the
+//! library and reports them as a status code, since unwinding into the host
+//! would abort the password manager. A plugin that panics once is disabled
+//! for the rest of the session. Panics are all this guards against: a
+//! library runs with the app's privileges, so only load plugins from a
+//! directory the user alone can write to.
+
+use crate::config::PluginsConfig;
+use crate::core::plugins::{
+    ActionProvider, ImportExportProvider, Plugin, PluginAction, PluginCapability, PluginFormat,
+    PluginManager, PluginMetadata, PluginRegistry,
+};
+use crate::core::{CoreError, CoreResult};
+use crate::models::CredentialRecord;
+use libloading::Library;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::any::Any;
+use std::ffi::c_void;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::ptr::NonNull;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Version of the plugin ABI; bumped whenever [`PluginVTable`] or the
+/// meaning of an operation changes
+pub const PLUGIN_ABI_VERSION: u32 = 1;
+
+/// Name of the entry point a plugin library exports
+pub const PLUGIN_ENTRY_SYMBOL: &str = "ziplock_plugin_entry";
+
+/// `call` succeeded and the output holds the result
+pub const STATUS_OK: i32 = 0;
+/// `call` failed and the output holds a UTF-8 error message
+pub const STATUS_ERROR: i32 = 1;
+/// The plugin panicked and the output holds the panic message
+pub const STATUS_PANICKED: i32 = 2;
+
+/// Operation returning the plugin's [`PluginMetadata`] as JSON
+pub const OP_METADATA: &str = "metadata";
+/// Operation returning the import [`PluginFormat`]s as JSON
+pub const OP_IMPORT_FORMATS: &str = "import_formats";
+/// Operation returning the export [`PluginFormat`]s as JSON
+pub const OP_EXPORT_FORMATS: &str = "export_formats";
+/// Operation returning the [`PluginAction`]s as JSON
+pub const OP_ACTIONS: &str = "actions";
+/// Prefix of `import:<format>`: file bytes in, credentials as JSON out
+pub const OP_IMPORT_PREFIX: &str = "import:";
+/// Prefix of `export:<format>`: credentials as JSON in, file bytes out
+pub const OP_EXPORT_PREFIX: &str = "export:";
+/// Prefix of `action:<id>`: a credential as JSON in, the changed credential
+/// or `null` out
+pub const OP_ACTION_PREFIX: &str = "action:";
+
+/// Capabilities the host can route to a loaded library
+///
+/// The other capabilities hand Rust function pointers or trait objects to
+/// the host, which cannot cross the C ABI.
+pub const HOST_CAPABILITIES: u64 = capability_bit(PluginCapability::ImportExport)
+    | capability_bit(PluginCapability::CustomActions);
+
+/// Bytes passed from a plugin to the host, freed with the plugin's
+/// `free_buffer`
+#[repr(C)]
+pub struct PluginBuffer {
+    pub data: *mut u8,
+    pub len: usize,
+}
+
+impl PluginBuffer {
+    fn empty() -> Self {
+        Self {
+            data: std::ptr::null_mut(),
+            len: 0,
+        }
+    }
+
+    fn from_vec(bytes: Vec<u8>) -> Self {
+        let len = bytes.len();
+        let data = Box::into_raw(bytes.into_boxed_slice()).cast::<u8>();
+        Self { data, len }
+    }
+}
+
+/// Function table a plugin library hands to the host
+///
+/// `abi_version` comes first so the host can check it before trusting the
+/// rest of the layout.
+#[repr(C)]
+pub struct PluginVTable {
+    /// [`PLUGIN_ABI_VERSION`] the library was built against
+    pub abi_version: u32,
+    /// Bit mask of the capabilities the plugin provides
+    pub capabilities: u64,
+    /// Plugin state, passed back to `call`
+    pub instance: *mut c_void,
+    /// Run an operation; returns one of the `STATUS_` codes and fills
+    /// `output`, which the host frees with `free_buffer`. Must be safe to
+    /// call from several threads at once.
+    pub call: unsafe extern "C" fn(
+        instance: *mut c_void,
+        operation: *const u8,
+        operation_len: usize,
+        input: *const u8,
+        input_len: usize,
+        output: *mut PluginBuffer,
+    ) -> i32,
+    /// Free a buffer returned by `call`
+    pub free_buffer: unsafe extern "C" fn(buffer: PluginBuffer),
+    /// Shut the plugin down and free the table; the host calls it once,
+    /// before unloading the library
+    pub destroy: unsafe extern "C" fn(vtable: *mut PluginVTable),
+}
+
+/// Signature of `ziplock_plugin_entry`
+///
+/// Returns null if the plugin does not support the host's ABI version.
+pub type PluginEntry =
+    unsafe extern "C" fn(host_abi_version: u32, host_capabilities: u64) -> *mut PluginVTable;
+
+const fn capability_bit(capability: PluginCapability) -> u64 {
+    // Fixed positions, since they are part of the ABI
+    match capability {
+        PluginCapability::CustomFieldTypes => 1 << 0,
+        PluginCapability::CredentialTemplates => 1 << 1,
+        PluginCapability::ImportExport => 1 << 2,
+        PluginCapability::Validation => 1 << 3,
+        PluginCapability::TotpGeneration => 1 << 4,
+        PluginCapability::PasswordGeneration => 1 << 5,
+        PluginCapability::SearchFilters => 1 << 6,
+        PluginCapability::BackupFormats => 1 << 7,
+        PluginCapability::CustomActions => 1 << 8,
+    }
+}
+
+/// Bit mask of `capabilities`, as in [`PluginVTable::capabilities`]
+pub fn capability_bits(capabilities: &[PluginCapability]) -> u64 {
+    capabilities
+        .iter()
+        .fold(0, |bits, &capability| bits | capability_bit(capability))
+}
+
+/// Capabilities set in `bits`; unknown bits are ignored
+pub fn capabilities_from_bits(bits: u64) -> Vec<PluginCapability> {
+    [
+        PluginCapability::CustomFieldTypes,
+        PluginCapability::CredentialTemplates,
+        PluginCapability::ImportExport,
+        PluginCapability::Validation,
+        PluginCapability::TotpGeneration,
+        PluginCapability::PasswordGeneration,
+        PluginCapability::SearchFilters,
+        PluginCapability::BackupFormats,
+        PluginCapability::CustomActions,
+    ]
+    .into_iter()
+    .filter(|&capability| bits & capability_bit(capability) != 0)
+    .collect()
+}
+
+fn to_json<T: Serialize + ?Sized>(value: &T) -> CoreResult<Vec<u8>> {
+    serde_json::to_vec(value).map_err(|e| CoreError::SerializationError {
+        message: e.to_string(),
+    })
+}
+
+fn from_json<T: DeserializeOwned>(bytes: &[u8]) -> CoreResult<T> {
+    serde_json::from_slice(bytes).map_err(|e| CoreError::SerializationError {
+        message: e.to_string(),
+    })
+}
+
+/// A plugin running in a loaded library
+pub struct DynamicPlugin {
+    vtable: NonNull<PluginVTable>,
+    metadata: PluginMetadata,
+    capabilities: Vec<PluginCapability>,
+    panicked: AtomicBool,
+    // Dropped after `Drop::drop` has destroyed the vtable
+    _library: Option<Library>,
+}
+
+// The ABI requires `call` to be thread-safe, and `export_plugin!` only
+// exports plugins that are `Send + Sync`
+unsafe impl Send for DynamicPlugin {}
+unsafe impl Sync for DynamicPlugin {}
+
+impl DynamicPlugin {
+    /// Load the plugin library at `path`
+    pub fn load(path: &Path) -> CoreResult<Self> {
+        let failed = |e: libloading::Error| CoreError::InternalError {
+            message: format!("Failed to load plugin {}: {}", path.display(), e),
+        };
+        // Loading runs the library's initialisers; see the module docs
+        let library = unsafe { Library::new(path) }.map_err(failed)?;
+        let entry = unsafe { library.get::<PluginEntry>(PLUGIN_ENTRY_SYMBOL.as_bytes()) }
+            .map(|symbol| *symbol)
+            .map_err(failed)?;
+        unsafe { Self::from_entry(entry, Some(library)) }
+    }
+
+    /// Negotiate with a plugin through its entry point
+    ///
+    /// # Safety
+    ///
+    /// `entry` must follow the plugin ABI, and stay callable for as long as
+    /// `library` is loaded.
+    unsafe fn from_entry(entry: PluginEntry, library: Option<Library>) -> CoreResult<Self> {
+        let vtable =
+            NonNull::new(entry(PLUGIN_ABI_VERSION, HOST_CAPABILITIES)).ok_or_else(|| {
+                CoreError::ValidationError {
+                    message: format!(
+                        "Plugin does not support plugin ABI version {}",
+                        PLUGIN_ABI_VERSION
+                    ),
+                }
+            })?;
+        let table = vtable.as_ref();
+        if table.abi_version != PLUGIN_ABI_VERSION {
+            // The rest of the table may have another layout, so it is
+            // leaked rather than destroyed
+            return Err(CoreError::ValidationError {
+                message: format!(
+                    "Plugin uses ABI version {}, expected {}",
+                    table.abi_version, PLUGIN_ABI_VERSION
+                ),
+            });
+        }
+
+        let mut plugin = Self {
+            vtable,
+            metadata: PluginMetadata {
+                id: String::new(),
+                name: String::new(),
+                version: String::new(),
+                description: String::new(),
+                author: String::new(),
+                min_ziplock_version: String::new(),
+                capabilities: Vec::new(),
+                config_schema: None,
+            },
+            capabilities: capabilities_from_bits(table.capabilities & HOST_CAPABILITIES),
+            panicked: AtomicBool::new(false),
+            _library: library,
+        };
+
+        plugin.metadata = from_json(&plugin.call(OP_METADATA, &[])?)?;
+        if plugin.metadata.id.is_empty() {
+            return Err(CoreError::ValidationError {
+                message: "Plugin metadata has no ID".to_string(),
+            });
+        }
+        if plugin.capabilities.is_empty() && table.capabilities != 0 {
+            return Err(CoreError::ValidationError {
+                message: format!(
+                    "Plugin '{}' provides no capability this version supports",
+                    plugin.metadata.id
+                ),
+            });
+        }
+
+        Ok(plugin)
+    }
+
+    /// Whether the plugin panicked, which disables it
+    pub fn has_panicked(&self) -> bool {
+        self.panicked.load(Ordering::Relaxed)
+    }
+
+    fn call(&self, operation: &str, input: &[u8]) -> CoreResult<Vec<u8>> {
+        if self.has_panicked() {
+            return Err(CoreError::InternalError {
+                message: format!(
+                    "Plugin '{}' was disabled after it panicked",
+                    self.metadata.id
+                ),
+            });
+        }
+
+        let table = unsafe { self.vtable.as_ref() };
+        let mut output = PluginBuffer::empty();
+        let status = unsafe {
+            (table.call)(
+                table.instance,
+                operation.as_ptr(),
+                operation.len(),
+                input.as_ptr(),
+                input.len(),
+                &mut output,
+            )
+        };
+        let bytes = if output.data.is_null() {
+            Vec::new()
+        } else {
+            unsafe { std::slice::from_raw_parts(output.data, output.len) }.to_vec()
+        };
+        unsafe { (table.free_buffer)(output) };
+
+        let message = || String::from_utf8_lossy(&bytes).into_owned();
+        match status {
+            STATUS_OK => Ok(bytes),
+            STATUS_PANICKED => {
+                self.panicked.store(true, Ordering::Relaxed);
+                Err(CoreError::InternalError {
+                    message: format!("Plugin '{}' panicked: {}", self.metadata.id, message()),
+                })
+            }
+            _ => Err(CoreError::ValidationError {
+                message: format!("Plugin '{}': {}", self.metadata.id, message()),
+            }),
+        }
+    }
+
+    /// A list the plugin returns, empty if the call fails
+    fn list<T: DeserializeOwned>(&self, operation: &str) -> Vec<T> {
+        self.call(operation, &[])
+            .and_then(|bytes| from_json(&bytes))
+            .unwrap_or_default()
+    }
+}
+
+impl Drop for DynamicPlugin {
+    fn drop(&mut self) {
+        let table = unsafe { self.vtable.as_ref() };
+        unsafe { (table.destroy)(self.vtable.as_ptr()) };
+    }
+}
+
+impl Plugin for DynamicPlugin {
+    fn metadata(&self) -> &PluginMetadata {
+        &self.metadata
+    }
+
+    fn capabilities(&self) -> Vec<PluginCapability> {
+        if self.has_panicked() {
+            return Vec::new();
+        }
+        self.capabilities.clone()
+    }
+
+    fn handle_operation(&self, operation: &str, data: &[u8]) -> CoreResult<Vec<u8>> {
+        self.call(operation, data)
+    }
+
+    fn import_export(&self) -> Option<&dyn ImportExportProvider> {
+        self.capabilities
+            .contains(&PluginCapability::ImportExport)
+            .then_some(self as &dyn ImportExportProvider)
+    }
+
+    fn actions(&self) -> Option<&dyn ActionProvider> {
+        self.capabilities
+            .contains(&PluginCapability::CustomActions)
+            .then_some(self as &dyn ActionProvider)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl ImportExportProvider for DynamicPlugin {
+    fn get_import_formats(&self) -> Vec<PluginFormat> {
+        self.list(OP_IMPORT_FORMATS)
+    }
+
+    fn get_export_formats(&self) -> Vec<PluginFormat> {
+        self.list(OP_EXPORT_FORMATS)
+    }
+
+    fn import_credentials(&self, format: &str, data: &[u8]) -> CoreResult<Vec<CredentialRecord>> {
+        from_json(&self.call(&format!("{}{}", OP_IMPORT_PREFIX, format), data)?)
+    }
+
+    fn export_credentials(
+        &self,
+        format: &str,
+        credentials: &[CredentialRecord],
+    ) -> CoreResult<Vec<u8>> {
+        self.call(
+            &format!("{}{}", OP_EXPORT_PREFIX, format),
+            &to_json(credentials)?,
+        )
+    }
+}
+
+impl ActionProvider for DynamicPlugin {
+    fn get_actions(&self) -> Vec<PluginAction> {
+        self.list(OP_ACTIONS)
+    }
+
+    fn run_action(
+        &self,
+        action_id: &str,
+        credential: &CredentialRecord,
+    ) -> CoreResult<Option<CredentialRecord>> {
+        from_json(&self.call(
+            &format!("{}{}", OP_ACTION_PREFIX, action_id),
+            &to_json(credential)?,
+        )?)
+    }
+}
+
+/// A plugin library that could not be loaded
+#[derive(Debug, Clone)]
+pub struct PluginLoadFailure {
+    pub path: PathBuf,
+    pub message: String,
+}
+
+/// Outcome of loading a plugin directory
+#[derive(Debug, Clone, Default)]
+pub struct PluginLoadReport {
+    /// IDs of the plugins registered
+    pub loaded: Vec<String>,
+    pub failed: Vec<PluginLoadFailure>,
+}
+
+impl PluginRegistry {
+    /// Load and register every plugin library in `directory`
+    ///
+    /// Files with the platform's library extension are loaded in name
+    /// order; one that fails to load or negotiate is reported and skipped.
+    /// A missing directory loads nothing.
+    pub fn load_directory(&self, directory: &Path) -> PluginLoadReport {
+        let mut report = PluginLoadReport::default();
+        let entries = match std::fs::read_dir(directory) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return report,
+            Err(e) => {
+                report.failed.push(PluginLoadFailure {
+                    path: directory.to_path_buf(),
+                    message: e.to_string(),
+                });
+                return report;
+            }
+        };
+
+        let mut paths: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| {
+                path.is_file()
+                    && path.extension().and_then(|e| e.to_str())
+                        == Some(std::env::consts::DLL_EXTENSION)
+            })
+            .collect();
+        paths.sort();
+
+        for path in paths {
+            let result = DynamicPlugin::load(&path).and_then(|plugin| {
+                let id = plugin.metadata().id.clone();
+                self.register_plugin(Box::new(plugin)).map(|_| id)
+            });
+            match result {
+                Ok(id) => report.loaded.push(id),
+                Err(e) => report.failed.push(PluginLoadFailure {
+                    path,
+                    message: e.to_string(),
+                }),
+            }
+        }
+
+        report
+    }
+}
+
+impl PluginManager {
+    /// Load plugin libraries if `config` turns dynamic loading on
+    pub fn load_dynamic_plugins(&self, config: &PluginsConfig) -> PluginLoadReport {
+        if !config.load_dynamic {
+            return PluginLoadReport::default();
+        }
+        self.registry().load_directory(&config.directory())
+    }
+}
+
+/// Plugin side of the ABI, used by [`export_plugin!`](crate::export_plugin)
+#[doc(hidden)]
+pub mod guest {
+    use super::*;
+
+    /// Build the vtable for the plugin `create` returns
+    pub fn entry(host_abi_version: u32, create: fn() -> Box<dyn Plugin>) -> *mut PluginVTable {
+        if host_abi_version != PLUGIN_ABI_VERSION {
+            return std::ptr::null_mut();
+        }
+        let plugin = match catch_unwind(create) {
+            Ok(plugin) => plugin,
+            Err(_) => return std::ptr::null_mut(),
+        };
+        let capabilities = capability_bits(&plugin.capabilities());
+        Box::into_raw(Box::new(PluginVTable {
+            abi_version: PLUGIN_ABI_VERSION,
+            capabilities,
+            instance: Box::into_raw(Box::new(plugin)).cast(),
+            call,
+            free_buffer,
+            destroy,
+        }))
+    }
+
+    unsafe extern "C" fn call(
+        instance: *mut c_void,
+        operation: *const u8,
+        operation_len: usize,
+        input: *const u8,
+        input_len: usize,
+        output: *mut PluginBuffer,
+    ) -> i32 {
+        let plugin = &*instance.cast::<Box<dyn Plugin>>();
+        let operation = std::slice::from_raw_parts(operation, operation_len);
+        let input = std::slice::from_raw_parts(input, input_len);
+
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            let operation =
+                std::str::from_utf8(operation).map_err(|e| CoreError::ValidationError {
+                    message: format!("Operation name is not UTF-8: {}", e),
+                })?;
+            dispatch(plugin.as_ref(), operation, input)
+        }));
+        let (status, bytes) = match result {
+            Ok(Ok(bytes)) => (STATUS_OK, bytes),
+            Ok(Err(e)) => (STATUS_ERROR, e.to_string().into_bytes()),
+            Err(panic) => (STATUS_PANICKED, panic_message(panic.as_ref()).into_bytes()),
+        };
+        *output = PluginBuffer::from_vec(bytes);
+        status
+    }
+
+    unsafe extern "C" fn free_buffer(buffer: PluginBuffer) {
+        if !buffer.data.is_null() {
+            drop(Box::from_raw(std::ptr::slice_from_raw_parts_mut(
+                buffer.data,
+                buffer.len,
+            )));
+        }
+    }
+
+    unsafe extern "C" fn destroy(vtable: *mut PluginVTable) {
+        let vtable = Box::from_raw(vtable);
+        let mut plugin = Box::from_raw(vtable.instance.cast::<Box<dyn Plugin>>());
+        let _ = catch_unwind(AssertUnwindSafe(move || {
+            let _ = plugin.shutdown();
+            drop(plugin);
+        }));
+    }
+
+    fn dispatch(plugin: &dyn Plugin, operation: &str, input: &[u8]) -> CoreResult<Vec<u8>> {
+        let unsupported = || CoreError::ValidationError {
+            message: format!("Operation '{}' not supported by plugin", operation),
+        };
+        let formats = || plugin.import_export().ok_or_else(unsupported);
+        let actions = || plugin.actions().ok_or_else(unsupported);
+
+        match operation {
+            OP_METADATA => to_json(plugin.metadata()),
+            OP_IMPORT_FORMATS => to_json(&formats()?.get_import_formats()),
+            OP_EXPORT_FORMATS => to_json(&formats()?.get_export_formats()),
+            OP_ACTIONS => to_json(&actions()?.get_actions()),
+            _ => {
+                if let Some(format) = operation.strip_prefix(OP_IMPORT_PREFIX) {
+                    to_json(&formats()?.import_credentials(format, input)?)
+                } else if let Some(format) = operation.strip_prefix(OP_EXPORT_PREFIX) {
+                    let credentials: Vec<CredentialRecord> = from_json(input)?;
+                    formats()?.export_credentials(format, &credentials)
+                } else if let Some(action) = operation.strip_prefix(OP_ACTION_PREFIX) {
+                    let credential: CredentialRecord = from_json(input)?;
+                    to_json(&actions()?.run_action(action, &credential)?)
+                } else {
+                    plugin.handle_operation(operation, input)
+                }
+            }
+        }
+    }
+
+    fn panic_message(panic: &(dyn Any + Send)) -> String {
+        panic
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| panic.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown panic".to_string())
+    }
+}
+
+/// Export a [`Plugin`] from a `cdylib` as a ZipLock plugin library
+///
+/// ```ignore
+/// ziplock_shared::export_plugin!(MyPlugin::new());
+/// ```
+///
+/// The expression is evaluated once per load. Panics in the plugin are
+/// caught and reported to the host, which disables the plugin.
+#[macro_export]
+macro_rules! export_plugin {
+    ($plugin:expr) => {
+        #[no_mangle]
+        pub unsafe extern "C" fn ziplock_plugin_entry(
+            host_abi_version: u32,
+            _host_capabilities: u64,
+        ) -> *mut $crate::core::plugin_loader::PluginVTable {
+            $crate::core::plugin_loader::guest::entry(host_abi_version, || {
+                Box::new($plugin) as Box<dyn $crate::core::plugins::Plugin>
+            })
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::CredentialField;
+    use tempfile::TempDir;
+
+    /// Imports `title,password` lines, and panics on the `crash` operation
+    struct CrashyPlugin {
+        metadata: PluginMetadata,
+    }
+
+    impl CrashyPlugin {
+        fn new() -> Self {
+            Self {
+                metadata: PluginMetadata {
+                    id: "test.crashy".to_string(),
+                    name: "Crashy".to_string(),
+                    version: "1.0.0".to_string(),
+                    description: "Imports lines, panics on request".to_string(),
+                    author: "Test Author".to_string(),
+                    min_ziplock_version: "0.1.0".to_string(),
+                    capabilities: vec!["ImportExport".to_string()],
+                    config_schema: None,
+                },
+            }
+        }
+    }
+
+    impl Plugin for CrashyPlugin {
+        fn metadata(&self) -> &PluginMetadata {
+            &self.metadata
+        }
+
+        fn capabilities(&self) -> Vec<PluginCapability> {
+            // Validation cannot cross the ABI and is dropped by the host
+            vec![PluginCapability::ImportExport, PluginCapability::Validation]
+        }
+
+        fn handle_operation(&self, operation: &str, data: &[u8]) -> CoreResult<Vec<u8>> {
+            match operation {
+                "crash" => panic!("plugin bug"),
+                "echo" => Ok(data.to_vec()),
+                _ => Err(CoreError::ValidationError {
+                    message: format!("Operation '{}' not supported by plugin", operation),
+                }),
+            }
+        }
+
+        fn import_export(&self) -> Option<&dyn ImportExportProvider> {
+            Some(self)
+        }
+
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+    }
+
+    impl ImportExportProvider for CrashyPlugin {
+        fn get_import_formats(&self) -> Vec<PluginFormat> {
+            vec![PluginFormat::new("lines", "Lines", "txt", "text/plain")]
+        }
+
+        fn get_export_formats(&self) -> Vec<PluginFormat> {
+            Vec::new()
+        }
+
+        fn import_credentials(
+            &self,
+            _format: &str,
+            data: &[u8],
+        ) -> CoreResult<Vec<CredentialRecord>> {
+            Ok(String::from_utf8_lossy(data)
+                .lines()
+                .filter_map(|line| line.split_once(','))
+                .map(|(title, password)| {
+                    let mut credential =
+                        CredentialRecord::new(title.to_string(), "login".to_string());
+                    credential.set_field("password", CredentialField::password(password));
+                    credential
+                })
+                .collect())
+        }
+
+        fn export_credentials(
+            &self,
+            format: &str,
+            _credentials: &[CredentialRecord],
+        ) -> CoreResult<Vec<u8>> {
+            Err(CoreError::ValidationError {
+                message: format!("Cannot export {}", format),
+            })
+        }
+    }
+
+    crate::export_plugin!(CrashyPlugin::new());
+
+    #[test]
+    fn test_capability_bits() {
+        let capabilities = vec![
+            PluginCapability::ImportExport,
+            PluginCapability::CustomActions,
+        ];
+        let bits = capability_bits(&capabilities);
+        assert_eq!(bits, HOST_CAPABILITIES);
+        assert_eq!(capabilities_from_bits(bits | 1 << 40), capabilities);
+    }
+
+    #[test]
+    fn test_loaded_plugin_routing_and_panic() {
+        let plugin = unsafe { DynamicPlugin::from_entry(ziplock_plugin_entry, None) }.unwrap();
+        assert_eq!(plugin.metadata().id, "test.crashy");
+        assert_eq!(plugin.capabilities(), vec![PluginCapability::ImportExport]);
+        assert!(plugin.actions().is_none());
+        assert_eq!(plugin.handle_operation("echo", b"hi").unwrap(), b"hi");
+        assert!(matches!(
+            plugin.handle_operation("missing", &[]),
+            Err(CoreError::ValidationError { .. })
+        ));
+
+        let manager = PluginManager::new();
+        manager
+            .registry()
+            .register_plugin(Box::new(plugin))
+            .unwrap();
+        let report = manager.import("lines", b"Mail,secret\n").unwrap();
+        assert_eq!(report.credentials[0].title, "Mail");
+        assert_eq!(
+            report.credentials[0].get_field("password").unwrap().value,
+            "secret"
+        );
+        assert!(manager
+            .export_with_plugin("lines", &report.credentials)
+            .is_err());
+
+        // A panic is reported as an error and disables the plugin
+        let plugin = unsafe { DynamicPlugin::from_entry(ziplock_plugin_entry, None) }.unwrap();
+        let error = plugin.handle_operation("crash", &[]).unwrap_err();
+        assert!(error.to_string().contains("plugin bug"));
+        assert!(plugin.has_panicked());
+        assert!(plugin.capabilities().is_empty());
+        assert!(plugin.handle_operation("echo", b"hi").is_err());
+    }
+
+    unsafe extern "C" fn future_entry(_: u32, _: u64) -> *mut PluginVTable {
+        let vtable = guest::entry(PLUGIN_ABI_VERSION, || {
+            Box::new(CrashyPlugin::new()) as Box<dyn Plugin>
+        });
+        (*vtable).abi_version = PLUGIN_ABI_VERSION + 1;
+        vtable
+    }
+
+    unsafe extern "C" fn refusing_entry(_: u32, _: u64) -> *mut PluginVTable {
+        std::ptr::null_mut()
+    }
+
+    #[test]
+    fn test_abi_version_mismatch() {
+        assert!(guest::entry(PLUGIN_ABI_VERSION + 1, || {
+            Box::new(CrashyPlugin::new()) as Box<dyn Plugin>
+        })
+        .is_null());
+        for entry in [future_entry as PluginEntry, refusing_entry] {
+            let result = unsafe { DynamicPlugin::from_entry(entry, None) };
+            assert!(matches!(result, Err(CoreError::ValidationError { .. })));
+        }
+    }
+
+    #[test]
+    fn test_load_directory() {
+        let temp_dir = TempDir::new().unwrap();
+        let broken = temp_dir
+            .path()
+            .join(format!("broken.{}", std::env::consts::DLL_EXTENSION));
+        std::fs::write(&broken, b"not a library").unwrap();
+        std::fs::write(temp_dir.path().join("README.txt"), b"ignored").unwrap();
+
+        let registry = PluginRegistry::new();
+        let report = registry.load_directory(temp_dir.path());
+        assert!(report.loaded.is_empty());
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].path, broken);
+        assert!(registry.list_plugins().is_empty());
+
+        let missing = registry.load_directory(&temp_dir.path().join("missing"));
+        assert!(missing.loaded.is_empty() && missing.failed.is_empty());
+
+        let manager = PluginManager::new();
+        let disabled = PluginsConfig {
+            load_dynamic: false,
+            directory: Some(temp_dir.path().to_path_buf()),
+        };
+        assert!(manager.load_dynamic_plugins(&disabled).failed.is_empty());
+    }
+}
@@ -2,11 +2,18 @@
 //!
 //! This module provides a flexible plugin architecture that allows extending
 //! ZipLock's functionality without modifying core code. Plugins can provide
-//! custom field types, credential templates, import/export formats, and
-//! validation rules.
+//! custom field types, credential templates, import/export formats, custom
+//! actions on credentials, and validation rules.
+//!
+//! Import and export formats are picked by ID. [`PluginManager::import`] and
+//! [`PluginManager::export`] route a format ID to the enabled plugin that
+//! registered it; built-in export formats keep their IDs and cannot be
+//! replaced by a plugin.
 
-use crate::core::{CoreError, CoreResult};
+use crate::core::{CoreError, CoreResult, UnifiedMemoryRepository};
 use crate::models::{CommonTemplates, CredentialRecord, CredentialTemplate};
+use crate::utils::backup::{BackupManager, ExportFormat, ExportOptions};
+use crate::utils::import::{finish, ImportReport};
 use serde::{Deserialize, Serialize};
 use std::any::Any;
 use std::collections::HashMap;
@@ -31,6 +38,8 @@ pub enum PluginCapability {
     SearchFilters,
     /// Can provide backup formats
     BackupFormats,
+    /// Can provide actions the user runs on a credential
+    CustomActions,
 }
 
 /// Plugin metadata
@@ -105,6 +114,90 @@ pub struct CustomFieldType {
     pub default_sensitive: bool,
 }
 
+/// A file format a plugin imports or exports
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PluginFormat {
+    /// Identifier the format is picked by, such as `keepass-xml`
+    pub id: String,
+    /// Display name
+    pub name: String,
+    /// File extension, without the dot
+    pub extension: String,
+    pub mime_type: String,
+}
+
+impl PluginFormat {
+    pub fn new<S: Into<String>>(id: S, name: S, extension: S, mime_type: S) -> Self {
+        Self {
+            id: id.into(),
+            name: name.into(),
+            extension: extension.into(),
+            mime_type: mime_type.into(),
+        }
+    }
+}
+
+/// An export format offered to the user, built in or from a plugin
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AvailableFormat {
+    Builtin(ExportFormat),
+    Plugin {
+        plugin_id: String,
+        format: PluginFormat,
+    },
+}
+
+impl AvailableFormat {
+    pub fn id(&self) -> &str {
+        match self {
+            AvailableFormat::Builtin(format) => format.id(),
+            AvailableFormat::Plugin { format, .. } => &format.id,
+        }
+    }
+
+    pub fn description(&self) -> &str {
+        match self {
+            AvailableFormat::Builtin(format) => format.description(),
+            AvailableFormat::Plugin { format, .. } => &format.name,
+        }
+    }
+
+    pub fn extension(&self) -> &str {
+        match self {
+            AvailableFormat::Builtin(format) => format.extension(),
+            AvailableFormat::Plugin { format, .. } => &format.extension,
+        }
+    }
+
+    pub fn mime_type(&self) -> &str {
+        match self {
+            AvailableFormat::Builtin(format) => format.mime_type(),
+            AvailableFormat::Plugin { format, .. } => &format.mime_type,
+        }
+    }
+}
+
+/// An action a plugin offers on credentials, such as "Rotate on server"
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PluginAction {
+    /// Identifier, unique within the plugin
+    pub id: String,
+    /// Label shown to the user
+    pub name: String,
+    pub description: String,
+    /// Credential types the action applies to; empty for all types
+    #[serde(default)]
+    pub credential_types: Vec<String>,
+}
+
+impl PluginAction {
+    /// Whether the action is offered for `credential`
+    pub fn applies_to(&self, credential: &CredentialRecord) -> bool {
+        self.credential_types.is_empty()
+            || self.credential_types.contains(&credential.credential_type)
+    }
+}
+
 /// Plugin trait that all plugins must implement
 pub trait Plugin: Send + Sync {
     /// Get plugin metadata
@@ -130,6 +223,18 @@ pub trait Plugin: Send + Sync {
         })
     }
 
+    /// The importers and exporters of a plugin with the `ImportExport`
+    /// capability; implementations return `Some(self)`
+    fn import_export(&self) -> Option<&dyn ImportExportProvider> {
+        None
+    }
+
+    /// The actions of a plugin with the `CustomActions` capability;
+    /// implementations return `Some(self)`
+    fn actions(&self) -> Option<&dyn ActionProvider> {
+        None
+    }
+
     /// Get the plugin as Any for downcasting
     fn as_any(&self) -> &dyn Any;
 }
@@ -159,10 +264,10 @@ pub trait TemplateProvider: Plugin {
 /// Import/Export provider trait
 pub trait ImportExportProvider: Plugin {
     /// Get supported import formats
-    fn get_import_formats(&self) -> Vec<String>;
+    fn get_import_formats(&self) -> Vec<PluginFormat>;
 
     /// Get supported export formats
-    fn get_export_formats(&self) -> Vec<String>;
+    fn get_export_formats(&self) -> Vec<PluginFormat>;
 
     /// Import credentials from data
     fn import_credentials(&self, format: &str, data: &[u8]) -> CoreResult<Vec<CredentialRecord>>;
@@ -175,6 +280,20 @@ pub trait ImportExportProvider: Plugin {
     ) -> CoreResult<Vec<u8>>;
 }
 
+/// Custom action provider trait
+pub trait ActionProvider: Plugin {
+    /// Get the actions this plugin offers
+    fn get_actions(&self) -> Vec<PluginAction>;
+
+    /// Run an action on a credential, returning the credential changed by
+    /// the action, or `None` if it left the credential alone
+    fn run_action(
+        &self,
+        action_id: &str,
+        credential: &CredentialRecord,
+    ) -> CoreResult<Option<CredentialRecord>>;
+}
+
 /// Validation provider trait
 pub trait ValidationProvider: Plugin {
     /// Validate a credential
@@ -399,49 +518,211 @@ impl PluginManager {
         Vec::new()
     }
 
-    /// Get available import formats from plugins
-    pub fn get_import_formats(&self) -> Vec<String> {
-        // Simplified implementation - return empty for now
-        // TODO: Implement proper plugin system with concrete types
+    /// Import formats registered by enabled plugins
+    pub fn get_import_formats(&self) -> Vec<PluginFormat> {
+        self.enabled_plugins(PluginCapability::ImportExport, |_, plugin| {
+            plugin.import_export().map(|p| p.get_import_formats())
+        })
+        .into_iter()
+        .flatten()
+        .collect()
+    }
 
-        Vec::new()
+    /// Export formats registered by enabled plugins
+    pub fn get_export_formats(&self) -> Vec<PluginFormat> {
+        self.enabled_plugins(PluginCapability::ImportExport, |_, plugin| {
+            plugin.import_export().map(|p| p.get_export_formats())
+        })
+        .into_iter()
+        .flatten()
+        .collect()
     }
 
-    /// Get available export formats from plugins
-    pub fn get_export_formats(&self) -> Vec<String> {
-        // Simplified implementation - return empty for now
-        // TODO: Implement proper plugin system with concrete types
+    /// Every export format: the built-in ones, then those of enabled
+    /// plugins
+    ///
+    /// A plugin format whose ID is taken by a built-in format or an earlier
+    /// plugin is left out.
+    pub fn export_formats(&self) -> Vec<AvailableFormat> {
+        let mut formats: Vec<AvailableFormat> = ExportFormat::ALL
+            .into_iter()
+            .map(AvailableFormat::Builtin)
+            .collect();
+        let plugin_formats = self.enabled_plugins(PluginCapability::ImportExport, |id, plugin| {
+            let formats = plugin.import_export()?.get_export_formats();
+            Some((id.to_string(), formats))
+        });
+        for (plugin_id, plugin_formats) in plugin_formats {
+            for format in plugin_formats {
+                if formats.iter().all(|f| f.id() != format.id) {
+                    formats.push(AvailableFormat::Plugin {
+                        plugin_id: plugin_id.clone(),
+                        format,
+                    });
+                }
+            }
+        }
+        formats
+    }
 
-        Vec::new()
+    /// Import `data` in `format` with the plugin that registered it
+    ///
+    /// Credentials that fail validation are reported as errors, the same as
+    /// with the built-in importers.
+    pub fn import(&self, format: &str, data: &[u8]) -> CoreResult<ImportReport> {
+        let mut report = ImportReport::default();
+        for (index, credential) in self
+            .import_with_plugin(format, data)?
+            .into_iter()
+            .enumerate()
+        {
+            report.push(index + 1, finish(credential));
+        }
+        Ok(report)
     }
 
     /// Import credentials using appropriate plugin
     pub fn import_with_plugin(
         &self,
         format: &str,
-        _data: &[u8],
+        data: &[u8],
     ) -> CoreResult<Vec<CredentialRecord>> {
-        // Simplified implementation - return error for now
-        // TODO: Implement proper plugin system with concrete types
-
-        Err(CoreError::ValidationError {
-            message: format!("No plugin found for import format: {}", format),
+        self.enabled_plugins(PluginCapability::ImportExport, |_, plugin| {
+            let provider = plugin.import_export()?;
+            provider
+                .get_import_formats()
+                .iter()
+                .any(|f| f.id == format)
+                .then(|| provider.import_credentials(format, data))
+        })
+        .into_iter()
+        .next()
+        .unwrap_or_else(|| {
+            Err(CoreError::ValidationError {
+                message: format!("No plugin found for import format: {}", format),
+            })
         })
     }
 
+    /// Export the credentials `options` selects in `format`, built in or
+    /// from a plugin
+    ///
+    /// `options.format` is ignored in favour of `format`.
+    pub fn export(
+        &self,
+        format: &str,
+        repository: &UnifiedMemoryRepository,
+        options: &ExportOptions,
+    ) -> CoreResult<Vec<u8>> {
+        if let Some(builtin) = ExportFormat::from_id(format) {
+            let options = ExportOptions {
+                format: builtin,
+                ..options.clone()
+            };
+            return BackupManager::export_repository(repository, &options);
+        }
+
+        let backup = BackupManager::create_backup(repository, options, None)?;
+        self.export_with_plugin(format, &backup.credentials)
+    }
+
     /// Export credentials using appropriate plugin
     pub fn export_with_plugin(
         &self,
         format: &str,
-        _credentials: &[CredentialRecord],
+        credentials: &[CredentialRecord],
     ) -> CoreResult<Vec<u8>> {
-        // Simplified implementation - return error for now
-        // TODO: Implement proper plugin system with concrete types
+        self.enabled_plugins(PluginCapability::ImportExport, |_, plugin| {
+            let provider = plugin.import_export()?;
+            provider
+                .get_export_formats()
+                .iter()
+                .any(|f| f.id == format)
+                .then(|| provider.export_credentials(format, credentials))
+        })
+        .into_iter()
+        .next()
+        .unwrap_or_else(|| {
+            Err(CoreError::ValidationError {
+                message: format!("No plugin found for export format: {}", format),
+            })
+        })
+    }
 
-        Err(CoreError::ValidationError {
-            message: format!("No plugin found for export format: {}", format),
+    /// Actions of enabled plugins that apply to `credential`, with the ID
+    /// of the plugin offering each
+    pub fn get_actions(&self, credential: &CredentialRecord) -> Vec<(String, PluginAction)> {
+        self.enabled_plugins(PluginCapability::CustomActions, |id, plugin| {
+            let actions = plugin.actions()?.get_actions();
+            Some(
+                actions
+                    .into_iter()
+                    .filter(|action| action.applies_to(credential))
+                    .map(|action| (id.to_string(), action))
+                    .collect::<Vec<_>>(),
+            )
+        })
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+
+    /// Run a plugin action on a credential; see [`ActionProvider::run_action`]
+    pub fn run_action(
+        &self,
+        plugin_id: &str,
+        action_id: &str,
+        credential: &CredentialRecord,
+    ) -> CoreResult<Option<CredentialRecord>> {
+        self.enabled_plugins(PluginCapability::CustomActions, |id, plugin| {
+            if id != plugin_id {
+                return None;
+            }
+            let provider = plugin.actions()?;
+            provider
+                .get_actions()
+                .iter()
+                .any(|action| action.id == action_id && action.applies_to(credential))
+                .then(|| provider.run_action(action_id, credential))
+        })
+        .into_iter()
+        .next()
+        .unwrap_or_else(|| {
+            Err(CoreError::ValidationError {
+                message: format!(
+                    "Plugin '{}' has no action '{}' for this credential",
+                    plugin_id, action_id
+                ),
+            })
         })
     }
+
+    /// Call `visit` on each enabled plugin with `capability`, in ID order,
+    /// collecting what it returns
+    fn enabled_plugins<R>(
+        &self,
+        capability: PluginCapability,
+        mut visit: impl FnMut(&str, &dyn Plugin) -> Option<R>,
+    ) -> Vec<R> {
+        let plugins = match self.registry.plugins.read() {
+            Ok(plugins) => plugins,
+            Err(_) => return Vec::new(),
+        };
+        let mut ids: Vec<&String> = plugins.keys().collect();
+        ids.sort();
+
+        ids.into_iter()
+            .filter(|id| self.registry.is_plugin_enabled(id))
+            .filter_map(|id| {
+                let plugin = &plugins[id];
+                if plugin.capabilities().contains(&capability) {
+                    visit(id, plugin.as_ref())
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
 }
 
 impl Default for PluginManager {
@@ -747,6 +1028,195 @@ mod tests {
         assert!(provider.validate_field("unknown", "value").is_err());
     }
 
+    /// Reads and writes `title,password` lines, and offers an action that
+    /// upper-cases login titles
+    struct LinesPlugin {
+        metadata: PluginMetadata,
+    }
+
+    impl LinesPlugin {
+        fn new() -> Self {
+            Self {
+                metadata: PluginMetadata {
+                    id: "test.lines".to_string(),
+                    name: "Lines".to_string(),
+                    version: "1.0.0".to_string(),
+                    description: "Title and password per line".to_string(),
+                    author: "Test Author".to_string(),
+                    min_ziplock_version: "0.1.0".to_string(),
+                    capabilities: vec!["ImportExport".to_string(), "CustomActions".to_string()],
+                    config_schema: None,
+                },
+            }
+        }
+
+        fn format(id: &str) -> PluginFormat {
+            PluginFormat::new(id, "Lines", "txt", "text/plain")
+        }
+    }
+
+    impl Plugin for LinesPlugin {
+        fn metadata(&self) -> &PluginMetadata {
+            &self.metadata
+        }
+
+        fn capabilities(&self) -> Vec<PluginCapability> {
+            vec![
+                PluginCapability::ImportExport,
+                PluginCapability::CustomActions,
+            ]
+        }
+
+        fn import_export(&self) -> Option<&dyn ImportExportProvider> {
+            Some(self)
+        }
+
+        fn actions(&self) -> Option<&dyn ActionProvider> {
+            Some(self)
+        }
+
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+    }
+
+    impl ImportExportProvider for LinesPlugin {
+        fn get_import_formats(&self) -> Vec<PluginFormat> {
+            vec![Self::format("lines")]
+        }
+
+        fn get_export_formats(&self) -> Vec<PluginFormat> {
+            // "json" is built in and must not be taken over
+            vec![Self::format("lines"), Self::format("json")]
+        }
+
+        fn import_credentials(
+            &self,
+            _format: &str,
+            data: &[u8],
+        ) -> CoreResult<Vec<CredentialRecord>> {
+            Ok(String::from_utf8_lossy(data)
+                .lines()
+                .filter_map(|line| line.split_once(','))
+                .map(|(title, password)| {
+                    let mut credential =
+                        CredentialRecord::new(title.to_string(), "login".to_string());
+                    credential.set_field(
+                        "password",
+                        crate::models::CredentialField::password(password),
+                    );
+                    credential
+                })
+                .collect())
+        }
+
+        fn export_credentials(
+            &self,
+            _format: &str,
+            credentials: &[CredentialRecord],
+        ) -> CoreResult<Vec<u8>> {
+            Ok(credentials
+                .iter()
+                .map(|c| {
+                    let password = c.get_field("password").map(|f| f.value.as_str());
+                    format!("{},{}\n", c.title, password.unwrap_or(""))
+                })
+                .collect::<String>()
+                .into_bytes())
+        }
+    }
+
+    impl ActionProvider for LinesPlugin {
+        fn get_actions(&self) -> Vec<PluginAction> {
+            vec![PluginAction {
+                id: "shout".to_string(),
+                name: "Shout".to_string(),
+                description: "Upper-case the title".to_string(),
+                credential_types: vec!["login".to_string()],
+            }]
+        }
+
+        fn run_action(
+            &self,
+            _action_id: &str,
+            credential: &CredentialRecord,
+        ) -> CoreResult<Option<CredentialRecord>> {
+            let mut changed = credential.clone();
+            changed.title = credential.title.to_uppercase();
+            Ok(Some(changed))
+        }
+    }
+
+    #[test]
+    fn test_plugin_import_export_routing() {
+        let manager = PluginManager::new();
+        manager
+            .registry()
+            .register_plugin(Box::new(LinesPlugin::new()))
+            .unwrap();
+
+        let report = manager.import("lines", b"GitHub,hunter2\n,\n").unwrap();
+        assert_eq!(report.imported_count(), 2);
+        assert_eq!(report.credentials[1].title, "Untitled");
+        assert!(manager.import("keepass", b"").is_err());
+
+        let mut repo = UnifiedMemoryRepository::new();
+        repo.initialize().unwrap();
+        report.import_into(&mut repo).unwrap();
+        let exported = manager
+            .export("lines", &repo, &ExportOptions::default())
+            .unwrap();
+        let exported = String::from_utf8(exported).unwrap();
+        assert!(exported.contains("GitHub,hunter2\n"));
+
+        // Built-in formats are not routed to the plugin
+        let json = manager
+            .export("json", &repo, &ExportOptions::default())
+            .unwrap();
+        assert!(json.starts_with(b"{"));
+
+        let ids: Vec<String> = manager
+            .export_formats()
+            .iter()
+            .map(|f| f.id().to_string())
+            .collect();
+        assert_eq!(ids.len(), ExportFormat::ALL.len() + 1);
+        assert_eq!(ids.iter().filter(|id| *id == "json").count(), 1);
+        assert!(matches!(
+            manager.export_formats().last(),
+            Some(AvailableFormat::Plugin { plugin_id, .. }) if plugin_id == "test.lines"
+        ));
+
+        manager
+            .registry()
+            .set_plugin_enabled("test.lines", false)
+            .unwrap();
+        assert!(manager.get_import_formats().is_empty());
+        assert!(manager.import("lines", b"a,b").is_err());
+        assert_eq!(manager.export_formats().len(), ExportFormat::ALL.len());
+    }
+
+    #[test]
+    fn test_plugin_actions() {
+        let manager = PluginManager::new();
+        manager
+            .registry()
+            .register_plugin(Box::new(LinesPlugin::new()))
+            .unwrap();
+
+        let login = CredentialRecord::new("github".to_string(), "login".to_string());
+        let note = CredentialRecord::new("note".to_string(), "secure_note".to_string());
+        let actions = manager.get_actions(&login);
+        assert_eq!(actions.len(), 1);
+        assert_eq!(actions[0].0, "test.lines");
+        assert!(manager.get_actions(&note).is_empty());
+
+        let changed = manager.run_action("test.lines", "shout", &login).unwrap();
+        assert_eq!(changed.unwrap().title, "GITHUB");
+        assert!(manager.run_action("test.lines", "shout", &note).is_err());
+        assert!(manager.run_action("test.lines", "whisper", &login).is_err());
+    }
+
     #[test]
     fn test_validation_severity() {
         let severity = ValidationSeverity::Warning;
@@ -0,0 +1,62 @@
+//! Progress reporting for opening and saving repositories
+//!
+//! Large vaults, especially ones with attachments, take long enough to open
+//! or save that the UI needs something to show. A [`ProgressObserver`]
+//! registered on the repository manager is told which [`ArchivePhase`] the
+//! operation has reached and how far along the whole operation is.
+
+/// Phases of opening or saving a repository archive
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchivePhase {
+    /// Reading the archive from storage
+    Reading,
+    /// Decrypting and decompressing the archive
+    Decrypting,
+    /// Parsing the metadata and credentials
+    Loading,
+    /// Turning the credentials into files
+    Serializing,
+    /// Compressing and encrypting the files into an archive
+    Encrypting,
+    /// Writing the archive to storage
+    Writing,
+    /// The open or save has finished
+    Complete,
+}
+
+impl ArchivePhase {
+    /// Percentage of the whole operation done when this phase starts
+    ///
+    /// Decrypting and encrypting dominate, so they get most of the bar.
+    pub fn percent(&self) -> u8 {
+        match self {
+            ArchivePhase::Reading | ArchivePhase::Serializing => 0,
+            ArchivePhase::Decrypting | ArchivePhase::Encrypting => 10,
+            ArchivePhase::Loading => 80,
+            ArchivePhase::Writing => 85,
+            ArchivePhase::Complete => 100,
+        }
+    }
+}
+
+/// Receives progress of repository open and save operations
+///
+/// Called on the thread doing the work, so implementations should hand the
+/// update to the UI rather than block.
+pub trait ProgressObserver: Send + Sync {
+    /// `phase` has started, with `percent` (0-100) of the operation done
+    fn on_progress(&self, phase: ArchivePhase, percent: u8);
+}
+
+impl<F: Fn(ArchivePhase, u8) + Send + Sync> ProgressObserver for F {
+    fn on_progress(&self, phase: ArchivePhase, percent: u8) {
+        self(phase, percent)
+    }
+}
+
+/// Tell `observer`, if any, that `phase` has started
+pub(crate) fn report(observer: Option<&dyn ProgressObserver>, phase: ArchivePhase) {
+    if let Some(observer) = observer {
+        observer.on_progress(phase, phase.percent());
+    }
+}
@@ -5,11 +5,40 @@
 //! implementing the complete repository lifecycle with proper separation
 //! of concerns.
 
-use crate::core::errors::{CoreError, CoreResult};
-use crate::core::file_provider::FileOperationProvider;
+use crate::config::RepositoryConfig;
+use crate::core::activity_log::{
+    default_device_name, ActivityAction, ActivityEntry, ActivityQuery,
+};
+use crate::core::archive_backend::ArchiveOptions;
+use crate::core::duress::{DuressArchive, OpenedArchive, SectionSlot};
+use crate::core::errors::{CoreError, CoreResult, FileError, FileResult, KeyProtectionError};
+use crate::core::events::{ListenerId, RepositoryEvent};
+use crate::core::file_provider::{ArchiveWatch, FileOperationProvider};
+use crate::core::integrity::{self, IntegrityIssue, IntegrityReport, RepairReport};
+use crate::core::key_file::KeyFile;
+use crate::core::keyprotection::{KeyProtector, ProtectorRecord, ProtectorStore};
 use crate::core::memory_repository::UnifiedMemoryRepository;
-use crate::core::types::{FileMap, RepositoryStats};
-use crate::models::CredentialRecord;
+use crate::core::merge::{MergeResult, RepositoryMerger};
+use crate::core::progress::{self, ArchivePhase, ProgressObserver};
+use crate::core::trash::{PruneReport, RetentionQuotas, TrashedCredential};
+use crate::core::types::{FileMap, PasswordChangeStage, RepositoryStats, METADATA_FILE};
+use crate::models::{
+    CredentialFormSchema, CredentialRecord, CredentialTemplate, FolderDefaults,
+    RecurringCredential, RecurringDraft, VaultMember,
+};
+use crate::utils::audit::{AuditOptions, ComplianceReport, SecurityAuditReport, SecurityAuditor};
+#[cfg(feature = "automation")]
+use crate::utils::automation::{AutomationEngine, AutomationResult, HookOutcome, ScriptMessage};
+use crate::utils::backup_scheduler::{BackupEntry, BackupOutcome, BackupScheduler};
+use crate::utils::encryption::EncryptionUtils;
+use crate::utils::reminders::{Reminder, ReminderEngine};
+use crate::utils::secure_store::SecureStore;
+use chrono::Utc;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tracing::warn;
+use zeroize::{Zeroize, Zeroizing};
 
 /// Repository manager that coordinates memory operations with file I/O
 pub struct UnifiedRepositoryManager<F: FileOperationProvider> {
@@ -22,11 +51,69 @@ pub struct UnifiedRepositoryManager<F: FileOperationProvider> {
     /// Current archive file path (if any)
     current_path: Option<String>,
 
-    /// Current master password (kept in memory for save operations)
-    master_password: Option<String>,
+    /// Current archive password (kept in memory for save operations); the
+    /// composite key when a key file is in use
+    master_password: Option<Zeroizing<String>>,
+
+    /// Key file required alongside the master password, if any
+    key_file: Option<KeyFile>,
 
     /// Whether a repository is currently open
     is_open: bool,
+
+    /// Receives progress of open and save operations
+    progress_observer: Option<Box<dyn ProgressObserver>>,
+
+    /// Whether saves patch the archive instead of rewriting it
+    incremental_saves: bool,
+
+    /// Compression and encryption settings for archives written from now on
+    archive_options: ArchiveOptions,
+
+    /// Limits on password history and the trash, enforced on save
+    retention_quotas: RetentionQuotas,
+
+    /// What the last save pruned
+    last_prune: PruneReport,
+
+    /// SHA-256 of every entry in the archive as last opened or saved
+    saved_entries: HashMap<String, Vec<u8>>,
+
+    /// Writes rotating backups of the archive after saves
+    backup_scheduler: Option<BackupScheduler>,
+
+    /// Issues fixed when the repository was opened
+    repair_log: Vec<IntegrityIssue>,
+
+    /// Directory holding the secure store of each repository
+    secure_store_dir: Option<PathBuf>,
+
+    /// Secure store of the open repository
+    secure_store: Option<SecureStore>,
+
+    /// Whether opens, saves and credential changes go into the activity log
+    activity_logging: bool,
+
+    /// Device named in activity log entries
+    device_name: String,
+
+    /// Whether to watch the open archive for changes made elsewhere
+    watch_archive: bool,
+
+    /// Watch on the open archive, while one is kept
+    archive_watch: Option<ArchiveWatch>,
+
+    /// The 7z archive and hidden section of the open file, and which of the
+    /// two is open; see [`crate::core::duress`]
+    container: Option<OpenedArchive>,
+
+    /// Scripts run when credentials are added, before saves and after audits
+    #[cfg(feature = "automation")]
+    automation: Option<AutomationEngine>,
+
+    /// Warnings from automation scripts not yet taken by the app
+    #[cfg(feature = "automation")]
+    automation_warnings: Vec<ScriptMessage>,
 }
 
 impl<F: FileOperationProvider> UnifiedRepositoryManager<F> {
@@ -37,8 +124,205 @@ impl<F: FileOperationProvider> UnifiedRepositoryManager<F> {
             file_provider,
             current_path: None,
             master_password: None,
+            key_file: None,
             is_open: false,
+            progress_observer: None,
+            incremental_saves: false,
+            archive_options: ArchiveOptions::default(),
+            retention_quotas: RetentionQuotas::default(),
+            last_prune: PruneReport::default(),
+            saved_entries: HashMap::new(),
+            backup_scheduler: None,
+            repair_log: Vec::new(),
+            secure_store_dir: None,
+            secure_store: None,
+            activity_logging: true,
+            device_name: default_device_name(),
+            watch_archive: false,
+            archive_watch: None,
+            container: None,
+            #[cfg(feature = "automation")]
+            automation: None,
+            #[cfg(feature = "automation")]
+            automation_warnings: Vec::new(),
+        }
+    }
+
+    /// Back up the archive with `scheduler` after saves, or stop with `None`
+    ///
+    /// A failed backup does not fail the save; it is available from
+    /// [`BackupScheduler::last_error`].
+    pub fn set_backup_scheduler(&mut self, scheduler: Option<BackupScheduler>) {
+        self.backup_scheduler = scheduler;
+    }
+
+    /// The backup scheduler, if one is set
+    pub fn backup_scheduler(&self) -> Option<&BackupScheduler> {
+        self.backup_scheduler.as_ref()
+    }
+
+    /// Keep a [`SecureStore`] for each repository in `directory`, or none
+    /// with `None`
+    ///
+    /// The store of a repository opens with it and is written back when it
+    /// is closed or locked. Takes effect the next time a repository is opened.
+    pub fn set_secure_store_dir(&mut self, directory: Option<PathBuf>) {
+        self.secure_store_dir = directory;
+    }
+
+    /// The secure store of the open repository, if a directory is set
+    pub fn secure_store(&self) -> Option<&SecureStore> {
+        self.secure_store.as_ref()
+    }
+
+    /// The secure store of the open repository, for changing values
+    pub fn secure_store_mut(&mut self) -> Option<&mut SecureStore> {
+        self.secure_store.as_mut()
+    }
+
+    /// Only write the entries that changed since the last open or save
+    ///
+    /// Saving to the current path with the current password then passes
+    /// the changed and removed entries to
+    /// [`FileOperationProvider::update_archive`] instead of recreating the
+    /// archive. How much that saves depends on the archive backend; the
+    /// SQLCipher backend rewrites just the affected rows, and the 7z backend
+    /// re-encrypts just the changed entries. As with a full save, the
+    /// changes are made to a copy that must open before it replaces the
+    /// archive, and the archive is kept as `.bak`. The archive must not be
+    /// changed by anyone else between saves.
+    pub fn set_incremental_saves(&mut self, enabled: bool) {
+        self.incremental_saves = enabled;
+    }
+
+    /// Write archives with `options`, e.g. the `archive` section of the
+    /// repository's [`RepositoryConfig`](crate::config::RepositoryConfig)
+    ///
+    /// Takes effect on the next create or save. While the options differ
+    /// from the defaults every save rewrites the whole archive, since
+    /// incremental saves patch it with the backend's own settings.
+    pub fn set_archive_options(&mut self, options: ArchiveOptions) -> CoreResult<()> {
+        options
+            .validate()
+            .map_err(|message| CoreError::ValidationError { message })?;
+        self.archive_options = options;
+        Ok(())
+    }
+
+    /// Settings archives are written with
+    pub fn archive_options(&self) -> &ArchiveOptions {
+        &self.archive_options
+    }
+
+    /// Keep password history and the trash within `quotas`
+    ///
+    /// Opening a repository applies the `quotas` section of its
+    /// [`RepositoryConfig`](crate::config::RepositoryConfig); this overrides
+    /// them until the next open. Anything over the limits is removed on the
+    /// next save.
+    pub fn set_retention_quotas(&mut self, quotas: RetentionQuotas) {
+        self.retention_quotas = quotas;
+        self.memory_repo.set_retention_quotas(quotas);
+    }
+
+    /// Limits on password history and the trash
+    pub fn retention_quotas(&self) -> RetentionQuotas {
+        self.retention_quotas
+    }
+
+    /// What the last save removed to stay within the retention quotas
+    pub fn last_prune_report(&self) -> &PruneReport {
+        &self.last_prune
+    }
+
+    /// Record opens, saves and credential changes in the repository's
+    /// activity log, or stop recording
+    ///
+    /// Entries already in the log stay in the archive when recording is
+    /// off.
+    pub fn set_activity_logging(&mut self, enabled: bool) {
+        self.activity_logging = enabled;
+        self.apply_activity_settings();
+    }
+
+    /// Name this device in activity log entries, instead of the host name
+    pub fn set_device_name<S: Into<String>>(&mut self, name: S) {
+        self.device_name = name.into();
+        self.apply_activity_settings();
+    }
+
+    /// Call `listener` whenever a repository is opened, saved or locked and
+    /// whenever a credential is added, changed or deleted
+    ///
+    /// Listeners stay registered across repositories, so one registration
+    /// covers every repository this manager opens. They run on the thread
+    /// making the change, while the manager is borrowed; see
+    /// [`ChangeListeners`](crate::core::ChangeListeners).
+    pub fn on_change(
+        &mut self,
+        listener: impl Fn(&RepositoryEvent) + Send + Sync + 'static,
+    ) -> ListenerId {
+        self.memory_repo.on_change(listener)
+    }
+
+    /// Stop calling a listener added with [`on_change`](Self::on_change)
+    pub fn remove_listener(&mut self, id: ListenerId) -> bool {
+        self.memory_repo.remove_listener(id)
+    }
+
+    /// Watch the open archive and send [`RepositoryEvent::ArchiveChanged`]
+    /// to the listeners when another program, such as a sync client,
+    /// changes it
+    ///
+    /// Only providers that can watch their storage report changes; see
+    /// [`FileOperationProvider::watch_archive`]. The event comes from the
+    /// provider's watcher thread.
+    pub fn set_watch_archive(&mut self, enabled: bool) {
+        self.watch_archive = enabled;
+        self.watch_current_archive();
+    }
+
+    /// Run automation scripts from `engine` when credentials are added and
+    /// before saves, or stop with `None`
+    ///
+    /// A rejecting or failing script makes the add or save fail with a
+    /// validation error. Scripts for finished audits run in
+    /// [`security_audit`](Self::security_audit).
+    #[cfg(feature = "automation")]
+    pub fn set_automation(&mut self, engine: Option<AutomationEngine>) {
+        self.automation = engine;
+    }
+
+    /// Warnings scripts raised since the last call
+    #[cfg(feature = "automation")]
+    pub fn take_automation_warnings(&mut self) -> Vec<ScriptMessage> {
+        std::mem::take(&mut self.automation_warnings)
+    }
+
+    /// Keep the warnings of `outcome`, or fail if a script rejected
+    #[cfg(feature = "automation")]
+    fn check_automation(&mut self, outcome: AutomationResult<HookOutcome>) -> CoreResult<()> {
+        let outcome = outcome.map_err(|e| CoreError::ValidationError {
+            message: e.to_string(),
+        })?;
+        if outcome.is_rejected() {
+            let reasons: Vec<String> = outcome
+                .rejections
+                .iter()
+                .map(|r| format!("{} ({})", r.message, r.script))
+                .collect();
+            return Err(CoreError::ValidationError {
+                message: format!("Rejected by automation: {}", reasons.join("; ")),
+            });
         }
+        self.automation_warnings.extend(outcome.warnings);
+        Ok(())
+    }
+
+    /// Report progress of opening and saving to `observer`, or stop
+    /// reporting with `None`
+    pub fn set_progress_observer(&mut self, observer: Option<Box<dyn ProgressObserver>>) {
+        self.progress_observer = observer;
     }
 
     /// Create a new repository at the specified path
@@ -58,16 +342,21 @@ impl<F: FileOperationProvider> UnifiedRepositoryManager<F> {
         }
 
         // Initialize empty memory repository
-        self.memory_repo = UnifiedMemoryRepository::new();
+        self.replace_memory_repo(UnifiedMemoryRepository::new());
         self.memory_repo.initialize()?;
 
         // Set up manager state
         self.current_path = Some(path.to_string());
-        self.master_password = Some(master_password.to_string());
+        self.master_password = Some(Zeroizing::new(master_password.to_string()));
+        self.key_file = None;
+        self.container = None;
         self.is_open = true;
+        self.open_secure_store();
+        self.memory_repo.emit(RepositoryEvent::Opened);
 
         // Save the empty repository
         self.save_repository()?;
+        self.watch_current_archive();
 
         Ok(())
     }
@@ -80,29 +369,380 @@ impl<F: FileOperationProvider> UnifiedRepositoryManager<F> {
     ///
     /// # Returns
     /// * `Ok(())` - If repository was opened successfully
+    /// * `Err(CoreError::KeyFileRequired)` - If the repository's
+    ///   configuration says it needs a key file; use
+    ///   [`open_repository_with_key_file`](Self::open_repository_with_key_file)
     /// * `Err(CoreError)` - If opening fails
+    ///
+    /// The retention quotas of the repository's configuration, if it has
+    /// one, replace those of the manager.
     pub fn open_repository(&mut self, path: &str, master_password: &str) -> CoreResult<()> {
         if self.is_open {
             return Err(CoreError::AlreadyInitialized);
         }
+        let config = self.load_config(path);
+        if config
+            .as_ref()
+            .is_some_and(|config| config.security.key_file_required)
+        {
+            return Err(CoreError::KeyFileRequired);
+        }
+        self.open_with_password(path, master_password)?;
+        self.apply_config(config);
+        Ok(())
+    }
+
+    /// The configuration stored next to the archive at `path`, if readable
+    fn load_config(&self, path: &str) -> Option<RepositoryConfig> {
+        RepositoryConfig::load_for(&self.file_provider, path)
+            // The password alone fails anyway if a key file was needed
+            .unwrap_or_else(|e| {
+                warn!("Ignoring unreadable repository configuration: {}", e);
+                None
+            })
+    }
+
+    /// Apply the settings of `config` that the manager enforces
+    fn apply_config(&mut self, config: Option<RepositoryConfig>) {
+        if let Some(config) = config {
+            self.set_retention_quotas(config.quotas);
+        }
+    }
+
+    /// Open the archive at `path` with the password it was encrypted with
+    fn open_with_password(&mut self, path: &str, master_password: &str) -> CoreResult<()> {
+        if self.is_open {
+            return Err(CoreError::AlreadyInitialized);
+        }
 
         // Read archive file
+        self.report_progress(ArchivePhase::Reading);
         let archive_data = self.file_provider.read_archive(path)?;
 
         // Extract archive contents
-        let file_map = self
-            .file_provider
-            .extract_archive(&archive_data, master_password)?;
-
-        // Load into memory repository
-        self.memory_repo = UnifiedMemoryRepository::new();
-        self.memory_repo.load_from_files(file_map)?;
+        self.report_progress(ArchivePhase::Decrypting);
+        let (file_map, container) = self.extract_file_map(&archive_data, master_password)?;
+
+        // Load into memory repository, fixing benign issues on the way
+        self.report_progress(ArchivePhase::Loading);
+        let digests = entry_digests(&file_map);
+        let (file_map, repairs) = integrity::repair_benign(file_map)?;
+        let mut memory_repo = UnifiedMemoryRepository::new();
+        memory_repo.load_from_files(file_map)?;
+        self.replace_memory_repo(memory_repo);
+        self.memory_repo
+            .record_activity(ActivityAction::Opened, None);
+        if !repairs.is_empty() {
+            self.memory_repo.mark_modified();
+        }
+        self.repair_log = repairs;
+        self.saved_entries = digests;
+        self.report_progress(ArchivePhase::Complete);
 
         // Set up manager state
         self.current_path = Some(path.to_string());
-        self.master_password = Some(master_password.to_string());
+        self.master_password = Some(Zeroizing::new(master_password.to_string()));
+        self.key_file = None;
+        self.container = container;
         self.is_open = true;
+        self.open_secure_store();
+        self.memory_repo.emit(RepositoryEvent::Opened);
+        self.watch_current_archive();
+
+        Ok(())
+    }
+
+    /// Create a new repository that needs both the master password and a key file
+    ///
+    /// `key_file_required` is set in the repository's configuration file, so
+    /// opening it without the key file fails with
+    /// [`CoreError::KeyFileRequired`].
+    ///
+    /// # Arguments
+    /// * `path` - Path where to create the new repository
+    /// * `master_password` - Password for encrypting the repository
+    /// * `key_file` - Key file loaded with `KeyFile::from_path` or `KeyFile::from_bytes`
+    pub fn create_repository_with_key_file(
+        &mut self,
+        path: &str,
+        master_password: &str,
+        key_file: &KeyFile,
+    ) -> CoreResult<()> {
+        self.create_repository(path, &key_file.composite_password(master_password))?;
+        self.key_file = Some(key_file.clone());
+
+        let mut config = RepositoryConfig::load_for(&self.file_provider, path)?.unwrap_or_default();
+        config.security.key_file_required = true;
+        config.save_for(&self.file_provider, path)
+    }
+
+    /// Open a repository protected by the master password and a key file
+    ///
+    /// A wrong key file fails the same way as a wrong password.
+    pub fn open_repository_with_key_file(
+        &mut self,
+        path: &str,
+        master_password: &str,
+        key_file: &KeyFile,
+    ) -> CoreResult<()> {
+        self.open_with_password(path, &key_file.composite_password(master_password))?;
+        self.key_file = Some(key_file.clone());
+        let config = self.load_config(path);
+        self.apply_config(config);
+        Ok(())
+    }
+
+    /// Whether the open repository needs a key file in addition to the password
+    pub fn uses_key_file(&self) -> bool {
+        self.key_file.is_some()
+    }
+
+    /// Let `protector` unlock the open repository without the master password
+    ///
+    /// The archive password is wrapped by the protector and the record is
+    /// added to the `.keys` file next to the archive. Repositories that use a
+    /// key file cannot have protectors yet.
+    pub fn register_key_protector(
+        &mut self,
+        protector: &dyn KeyProtector,
+        label: &str,
+    ) -> CoreResult<ProtectorRecord> {
+        if !self.is_open {
+            return Err(CoreError::NotInitialized);
+        }
+        if self.key_file.is_some() {
+            return Err(CoreError::ValidationError {
+                message: "Repositories that use a key file cannot be unlocked another way"
+                    .to_string(),
+            });
+        }
+        let label = label.trim();
+        if label.is_empty() {
+            return Err(CoreError::ValidationError {
+                message: "Key protector name cannot be empty".to_string(),
+            });
+        }
+        let (Some(path), Some(password)) = (&self.current_path, &self.master_password) else {
+            return Err(CoreError::StructureError {
+                message: "No current path set for repository".to_string(),
+            });
+        };
+
+        let record = protector.protect(label, password.as_bytes())?;
+        let mut records = ProtectorStore::load(&self.file_provider, path)?;
+        records.push(record.clone());
+        ProtectorStore::save(&self.file_provider, path, &records)?;
+        Ok(record)
+    }
+
+    /// Protectors registered for the archive at `path`
+    pub fn key_protectors(&self, path: &str) -> CoreResult<Vec<ProtectorRecord>> {
+        ProtectorStore::load(&self.file_provider, path)
+    }
+
+    /// Forget a protector of the archive at `path`
+    pub fn remove_key_protector(&self, path: &str, id: &str) -> CoreResult<()> {
+        let mut records = ProtectorStore::load(&self.file_provider, path)?;
+        let count = records.len();
+        records.retain(|record| record.id != id);
+        if records.len() == count {
+            return Err(CoreError::ValidationError {
+                message: format!("No key protector with ID '{}'", id),
+            });
+        }
+        ProtectorStore::save(&self.file_provider, path, &records)
+    }
+
+    /// Open a repository with a registered protector instead of the password
+    ///
+    /// Each record of the protector's kind is tried in turn. Any error,
+    /// including [`KeyProtectionError::NotRegistered`] when there is none,
+    /// leaves the manager closed so the caller can ask for the password.
+    pub fn open_repository_with_protector(
+        &mut self,
+        path: &str,
+        protector: &dyn KeyProtector,
+    ) -> CoreResult<()> {
+        if self.is_open {
+            return Err(CoreError::AlreadyInitialized);
+        }
+
+        let mut last_error = CoreError::KeyProtection(KeyProtectionError::NotRegistered);
+        for record in ProtectorStore::load(&self.file_provider, path)?
+            .iter()
+            .filter(|record| record.kind == protector.kind())
+        {
+            match protector.unprotect(record) {
+                Ok(password) => {
+                    let password = String::from_utf8(password.to_vec())
+                        .map_err(|_| CoreError::KeyProtection(KeyProtectionError::UnwrapFailed))?;
+                    return self.open_repository(path, &password);
+                }
+                Err(e) => last_error = e,
+            }
+        }
+        Err(last_error)
+    }
+
+    /// Add a duress password that opens `decoy` instead of this repository
+    ///
+    /// The decoy credentials become the 7z archive at the front of the file
+    /// and the open credentials move to its hidden section, and the file is
+    /// saved straight away. Opening it with the duress password works like
+    /// opening any repository without one; see [`crate::core::duress`].
+    /// Repositories that use a key file or a format other than 7z cannot
+    /// have a duress password.
+    ///
+    /// A session opened with the duress password cannot tell that the
+    /// hidden section holds the real credentials, so this fails unless the
+    /// section is known to be free: the file has none, or this manager
+    /// filled it with random bytes, as when the repository was created or
+    /// its duress password removed. Call
+    /// [`UnifiedRepositoryManager::discard_hidden_section`] first to set a
+    /// duress password on an existing repository.
+    pub fn set_duress_password(
+        &mut self,
+        duress_password: &str,
+        decoy: Vec<CredentialRecord>,
+    ) -> CoreResult<()> {
+        if !self.is_open {
+            return Err(CoreError::NotInitialized);
+        }
+        if self.key_file.is_some() {
+            return Err(CoreError::ValidationError {
+                message: "Repositories that use a key file cannot have a duress password"
+                    .to_string(),
+            });
+        }
+        let Some((container, slot)) = self.container.clone() else {
+            return Err(CoreError::ValidationError {
+                message: "Only 7z repositories can have a duress password".to_string(),
+            });
+        };
+        if slot == SectionSlot::Hidden {
+            return Err(CoreError::ValidationError {
+                message: "This repository already has a duress password".to_string(),
+            });
+        }
+        if !container.hidden_is_free() {
+            return Err(CoreError::ValidationError {
+                message: "The hidden section of this repository may hold other credentials; \
+                          discard it before setting a duress password"
+                    .to_string(),
+            });
+        }
+        if duress_password.is_empty() {
+            return Err(CoreError::ValidationError {
+                message: "Duress password cannot be empty".to_string(),
+            });
+        }
+        let master_password =
+            self.master_password
+                .as_deref()
+                .ok_or_else(|| CoreError::StructureError {
+                    message: "No master password set for repository".to_string(),
+                })?;
+        if EncryptionUtils::secure_compare(duress_password.as_bytes(), master_password.as_bytes()) {
+            return Err(CoreError::ValidationError {
+                message: "The duress password must differ from the master password".to_string(),
+            });
+        }
+
+        let mut decoy_repo = UnifiedMemoryRepository::new();
+        decoy_repo.initialize()?;
+        for credential in decoy {
+            decoy_repo.add_credential(credential)?;
+        }
+        decoy_repo.set_archive_backend(self.file_provider.archive_backend());
+        let decoy_files = decoy_repo.serialize_to_files();
+        decoy_repo.wipe();
+        let decoy_archive = self.file_provider.create_archive_with_options(
+            decoy_files?,
+            duress_password,
+            &self.archive_options,
+        )?;
+
+        // The hidden section is filled in by the save
+        let mut with_decoy = container.clone();
+        with_decoy.replace(SectionSlot::Archive, &decoy_archive, duress_password)?;
+        self.container = Some((with_decoy, SectionSlot::Hidden));
+        self.saved_entries.clear();
+        if let Err(e) = self.save_repository() {
+            self.container = Some((container, slot));
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Whether the open repository has a duress password
+    ///
+    /// Only known when it was opened with the master password; opened with
+    /// the duress password it looks like any repository without one.
+    pub fn has_duress_password(&self) -> bool {
+        matches!(self.container, Some((_, SectionSlot::Hidden)))
+    }
+
+    /// Overwrite the hidden section with random bytes, destroying whatever
+    /// it holds, and save straight away
+    ///
+    /// This is the only way to set a duress password on a repository that
+    /// was not created in this session. Opened with the duress password, it
+    /// deletes the real credentials, which is why
+    /// [`UnifiedRepositoryManager::set_duress_password`] does not do it
+    /// implicitly. Fails when the open credentials are the hidden section's;
+    /// use [`UnifiedRepositoryManager::remove_duress_password`] there.
+    pub fn discard_hidden_section(&mut self) -> CoreResult<()> {
+        if !self.is_open {
+            return Err(CoreError::NotInitialized);
+        }
+        let Some((container, slot)) = self.container.clone() else {
+            return Err(CoreError::ValidationError {
+                message: "Only 7z repositories have a hidden section".to_string(),
+            });
+        };
+        if slot == SectionSlot::Hidden {
+            return Err(CoreError::ValidationError {
+                message: "The open credentials are in the hidden section".to_string(),
+            });
+        }
+
+        let mut cleared = container.clone();
+        cleared.clear_hidden();
+        self.container = Some((cleared, SectionSlot::Archive));
+        self.saved_entries.clear();
+        if let Err(e) = self.save_repository() {
+            self.container = Some((container, slot));
+            return Err(e);
+        }
+        Ok(())
+    }
 
+    /// Remove the other credential set, given the password that opens it
+    ///
+    /// Only the open credentials are kept, the hidden section is overwritten
+    /// with random bytes and the file is saved straight away.
+    /// `other_password` is the duress password when the repository was
+    /// opened with the master password, and the other way round; anything
+    /// else fails as a wrong password.
+    pub fn remove_duress_password(&mut self, other_password: &str) -> CoreResult<()> {
+        if !self.is_open {
+            return Err(CoreError::NotInitialized);
+        }
+        if !self.opens_other_section(other_password)? {
+            return Err(CoreError::FileOperation(FileError::InvalidPassword));
+        }
+        let Some((container, slot)) = self.container.clone() else {
+            return Err(CoreError::FileOperation(FileError::InvalidPassword));
+        };
+
+        let mut cleared = container.clone();
+        cleared.clear_hidden();
+        self.container = Some((cleared, SectionSlot::Archive));
+        self.saved_entries.clear();
+        if let Err(e) = self.save_repository() {
+            self.container = Some((container, slot));
+            return Err(e);
+        }
         Ok(())
     }
 
@@ -135,6 +775,13 @@ impl<F: FileOperationProvider> UnifiedRepositoryManager<F> {
         self.save_repository_to_path(&path, &password)
     }
 
+    /// Save the repository and report what was pruned to stay within the
+    /// [retention quotas](Self::set_retention_quotas)
+    pub fn save_repository_with_report(&mut self) -> CoreResult<PruneReport> {
+        self.save_repository()?;
+        Ok(self.last_prune.clone())
+    }
+
     /// Save the repository to a specific path
     ///
     /// # Arguments
@@ -149,16 +796,26 @@ impl<F: FileOperationProvider> UnifiedRepositoryManager<F> {
             return Err(CoreError::NotInitialized);
         }
 
-        // Serialize memory repository to file map
-        let file_map = self.memory_repo.serialize_to_files()?;
-
-        // Create encrypted archive
-        let archive_data = self
-            .file_provider
-            .create_archive(file_map, master_password)?;
+        #[cfg(feature = "automation")]
+        if let Some(engine) = &self.automation {
+            let outcome = engine.before_save(&self.memory_repo.list_credentials()?);
+            self.check_automation(outcome)?;
+        }
 
-        // Write archive to filesystem
-        self.file_provider.write_archive(path, &archive_data)?;
+        // Serialize memory repository to file map
+        self.report_progress(ArchivePhase::Serializing);
+        let (report, pruned) = self.memory_repo.prune_keeping(Utc::now().timestamp())?;
+        let digests = match self.write_archive_to_path(path, master_password) {
+            Ok(digests) => digests,
+            Err(e) => {
+                // Nothing pruned is lost until the archive without it is saved
+                self.memory_repo.restore_pruned(pruned);
+                return Err(e);
+            }
+        };
+        self.saved_entries = digests;
+        self.last_prune = report;
+        self.report_progress(ArchivePhase::Complete);
 
         // Mark repository as saved
         self.memory_repo.mark_saved();
@@ -166,16 +823,126 @@ impl<F: FileOperationProvider> UnifiedRepositoryManager<F> {
         // Update current path if different
         if self.current_path.as_deref() != Some(path) {
             self.current_path = Some(path.to_string());
+            self.watch_current_archive();
         }
 
         // Update password if different
-        if self.master_password.as_deref() != Some(master_password) {
-            self.master_password = Some(master_password.to_string());
+        if self.master_password.as_deref().map(String::as_str) != Some(master_password) {
+            self.master_password = Some(Zeroizing::new(master_password.to_string()));
         }
 
+        // The error, if any, is kept on the scheduler
+        let _ = self.run_scheduled_backup();
+
         Ok(())
     }
 
+    /// Serialize the repository and write it to `path` through a verified
+    /// write, returning the digests of the saved entries
+    fn write_archive_to_path(
+        &mut self,
+        path: &str,
+        master_password: &str,
+    ) -> CoreResult<HashMap<String, Vec<u8>>> {
+        self.memory_repo
+            .record_activity(ActivityAction::Saved, None);
+        let file_map = self.serialize_for_archive()?;
+        let digests = entry_digests(&file_map);
+
+        self.report_progress(ArchivePhase::Encrypting);
+        let verify = |written: &[u8]| self.verify_written(written, master_password);
+        if self.can_update_archive(path, master_password) {
+            // Patch the changed entries into a copy of the existing archive
+            let (changed, removed) = self.changes_since_save(file_map, &digests);
+            self.report_progress(ArchivePhase::Writing);
+            self.file_provider.update_archive(
+                path,
+                &changed,
+                &removed,
+                master_password,
+                &verify,
+            )?;
+        } else {
+            // Create encrypted archive
+            let (archive_data, container) = self.create_archive_data(file_map, master_password)?;
+
+            // Write archive to filesystem, replacing the old one only once
+            // the new one is known to open
+            self.report_progress(ArchivePhase::Writing);
+            self.file_provider
+                .write_archive_verified(path, &archive_data, &verify)?;
+            self.container = container;
+        }
+        Ok(digests)
+    }
+
+    /// Back up the saved archive if the scheduler's interval has passed
+    ///
+    /// Runs after every save; call it on a timer as well to take backups
+    /// every so often while the repository is open. Unsaved changes are not
+    /// included, and an archive identical to the newest backup is skipped.
+    pub fn run_scheduled_backup(&mut self) -> CoreResult<Option<BackupOutcome>> {
+        if !self.is_open {
+            return Err(CoreError::NotInitialized);
+        }
+        let (Some(scheduler), Some(path)) =
+            (self.backup_scheduler.as_mut(), self.current_path.as_deref())
+        else {
+            return Ok(None);
+        };
+
+        let now = Utc::now();
+        if !scheduler.is_due(now) {
+            return Ok(None);
+        }
+        let result = self
+            .file_provider
+            .read_archive(path)
+            .map_err(CoreError::from)
+            .and_then(|archive| scheduler.backup(&archive, now));
+        scheduler.set_last_error(result.as_ref().err().map(|e| e.to_string()));
+        result
+    }
+
+    /// Backups of the repository, newest first; none without a scheduler
+    pub fn list_backups(&self) -> CoreResult<Vec<BackupEntry>> {
+        match &self.backup_scheduler {
+            Some(scheduler) => scheduler.list(),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Replace the open repository's archive with a backup and reopen it
+    ///
+    /// `master_password` is the password the backup was saved with. The
+    /// backup is checked to open before anything is replaced, the current
+    /// archive is backed up first so the restore can be undone, and unsaved
+    /// changes are discarded.
+    pub fn restore_backup(&mut self, backup_path: &str, master_password: &str) -> CoreResult<()> {
+        if !self.is_open {
+            return Err(CoreError::NotInitialized);
+        }
+        let path = self
+            .current_path
+            .clone()
+            .ok_or_else(|| CoreError::StructureError {
+                message: "No current path set for repository".to_string(),
+            })?;
+
+        let backup = self.file_provider.read_archive(backup_path)?;
+        let (file_map, _) = self.extract_file_map(&backup, master_password)?;
+        UnifiedMemoryRepository::new().load_from_files(file_map)?;
+
+        if let Some(scheduler) = self.backup_scheduler.as_mut() {
+            let current = self.file_provider.read_archive(&path)?;
+            scheduler.backup_now(&current, Utc::now())?;
+        }
+
+        self.close_repository(false)?;
+        self.file_provider.write_archive(&path, &backup)?;
+        self.open_repository(&path, master_password)
+    }
+
     /// Close the current repository
     ///
     /// # Arguments
@@ -193,79 +960,585 @@ impl<F: FileOperationProvider> UnifiedRepositoryManager<F> {
             self.save_repository()?;
         }
 
-        // Reset state
-        self.memory_repo = UnifiedMemoryRepository::new();
+        self.wipe_secrets();
         self.current_path = None;
-        self.master_password = None;
-        self.is_open = false;
 
         Ok(())
     }
 
-    /// Add a new credential to the repository
-    pub fn add_credential(&mut self, credential: CredentialRecord) -> CoreResult<()> {
+    /// Evict all key material before the system suspends or hibernates
+    ///
+    /// Pending changes are saved first, then the master password and all
+    /// decrypted credentials are overwritten in memory. If saving fails the
+    /// secrets are wiped anyway and the save error is returned, since the
+    /// unsaved changes must not be written into a hibernation image.
+    ///
+    /// # Returns
+    /// * `Ok(Some(path))` - The repository that was locked, for reopening on resume
+    /// * `Ok(None)` - If no repository was open
+    /// * `Err(CoreError)` - If pending changes could not be saved
+    pub fn prepare_for_suspend(&mut self) -> CoreResult<Option<String>> {
         if !self.is_open {
-            return Err(CoreError::NotInitialized);
+            return Ok(None);
         }
 
-        self.memory_repo.add_credential(credential)
+        let saved = if self.memory_repo.is_modified() {
+            self.save_repository()
+        } else {
+            Ok(())
+        };
+        self.wipe_secrets();
+        let path = self.current_path.take();
+
+        saved.map(|_| path)
     }
 
-    /// Get a credential by ID
-    pub fn get_credential(&mut self, id: &str) -> CoreResult<&CredentialRecord> {
+    /// Whether another program changed the archive since it was last opened
+    /// or saved
+    ///
+    /// While it has, saving fails with [`FileError::Conflict`] for providers
+    /// that check. Use [`reload_from_disk`](Self::reload_from_disk),
+    /// [`merge_from_disk`](Self::merge_from_disk) or
+    /// [`keep_local_changes`](Self::keep_local_changes) to go on.
+    pub fn has_changes_on_disk(&self) -> CoreResult<bool> {
         if !self.is_open {
             return Err(CoreError::NotInitialized);
         }
+        match &self.current_path {
+            Some(path) => Ok(self.file_provider.archive_changed(path)?),
+            None => Ok(false),
+        }
+    }
 
-        self.memory_repo.get_credential(id)
+    /// Discard unsaved changes and reopen the archive as it is now
+    ///
+    /// The archive is checked to open with the current password before the
+    /// repository is closed, so a copy saved with another password leaves
+    /// everything as it was.
+    pub fn reload_from_disk(&mut self) -> CoreResult<()> {
+        let (path, password) = self.current_archive()?;
+        self.load_from_disk(&path, &password)?;
+
+        let key_file = self.key_file.clone();
+        self.close_repository(false)?;
+        self.open_repository(&path, &password)?;
+        self.key_file = key_file;
+        Ok(())
     }
 
-    /// Get a credential by ID without updating access time
-    pub fn get_credential_readonly(&self, id: &str) -> CoreResult<&CredentialRecord> {
-        if !self.is_open {
-            return Err(CoreError::NotInitialized);
-        }
+    /// Compare the open repository with the archive as another program
+    /// left it
+    ///
+    /// Apply the result with [`MergeResult::apply_to`] and save to keep the
+    /// changes from both. The next save overwrites the archive either way,
+    /// so saving without applying the result keeps only this copy.
+    pub fn merge_from_disk(&mut self) -> CoreResult<MergeResult> {
+        let (path, password) = self.current_archive()?;
+        let theirs = self.load_from_disk(&path, &password)?.list_credentials()?;
+        let ours = self.memory_repo.list_credentials()?;
+        self.saved_entries.clear();
+        Ok(RepositoryMerger::<F>::merge(&ours, &theirs))
+    }
 
-        self.memory_repo.get_credential_readonly(id)
+    /// Overwrite the changes another program made to the archive with this
+    /// copy on the next save
+    pub fn keep_local_changes(&mut self) -> CoreResult<()> {
+        let (path, _) = self.current_archive()?;
+        self.file_provider.read_archive(&path)?;
+        self.saved_entries.clear();
+        self.memory_repo.mark_modified();
+        Ok(())
     }
 
-    /// Update an existing credential
-    pub fn update_credential(&mut self, credential: CredentialRecord) -> CoreResult<()> {
-        if !self.is_open {
-            return Err(CoreError::NotInitialized);
+    /// Check that a newly written archive decrypts with `master_password`
+    /// and holds the repository metadata
+    fn verify_written(&self, written: &[u8], master_password: &str) -> FileResult<()> {
+        let (file_map, _) = self
+            .extract_file_map(written, master_password)
+            .map_err(|e| FileError::VerificationFailed {
+                message: e.to_string(),
+            })?;
+        if !file_map.contains_key(METADATA_FILE) {
+            return Err(FileError::VerificationFailed {
+                message: format!("{} is missing", METADATA_FILE),
+            });
         }
-
-        self.memory_repo.update_credential(credential)
+        Ok(())
     }
 
-    /// Delete a credential by ID
-    pub fn delete_credential(&mut self, id: &str) -> CoreResult<CredentialRecord> {
-        if !self.is_open {
-            return Err(CoreError::NotInitialized);
+    /// Unpack archive bytes, opening whichever part of a 7z archive and its
+    /// hidden section `password` opens
+    fn extract_file_map(
+        &self,
+        data: &[u8],
+        password: &str,
+    ) -> FileResult<(FileMap, Option<OpenedArchive>)> {
+        if !DuressArchive::recognizes(data) {
+            return Ok((self.file_provider.extract_archive(data, password)?, None));
         }
+        let container = DuressArchive::from_bytes(data)?;
+        let (file_map, slot) = container.open(password, |archive, password| {
+            self.file_provider.extract_archive(archive, password)
+        })?;
+        Ok((file_map, Some((container, slot))))
+    }
 
-        self.memory_repo.delete_credential(id)
+    /// Encrypt `file_map` into archive bytes, as the open part of the file
+    /// next to the other one; see [`DuressArchive::with_archive`]
+    fn create_archive_data(
+        &self,
+        file_map: FileMap,
+        password: &str,
+    ) -> FileResult<(Vec<u8>, Option<OpenedArchive>)> {
+        let archive = self.file_provider.create_archive_with_options(
+            file_map,
+            password,
+            &self.archive_options,
+        )?;
+        DuressArchive::with_archive(self.container.as_ref(), archive, password)
     }
 
-    /// List all credentials
-    pub fn list_credentials(&self) -> CoreResult<Vec<CredentialRecord>> {
-        if !self.is_open {
-            return Err(CoreError::NotInitialized);
+    /// Whether `password` opens the part of the file that is not open
+    fn opens_other_section(&self, password: &str) -> FileResult<bool> {
+        match &self.container {
+            Some((container, slot)) => container.opens_other(*slot, password, |archive, key| {
+                self.file_provider.extract_archive(archive, key)
+            }),
+            None => Ok(false),
         }
-
-        self.memory_repo.list_credentials()
     }
 
-    /// Get credential summaries (ID and title only)
-    pub fn list_credential_summaries(&self) -> CoreResult<Vec<(String, String)>> {
+    /// Path and password of the open archive
+    fn current_archive(&self) -> CoreResult<(String, Zeroizing<String>)> {
         if !self.is_open {
             return Err(CoreError::NotInitialized);
         }
+        match (&self.current_path, &self.master_password) {
+            (Some(path), Some(password)) => Ok((path.clone(), password.clone())),
+            _ => Err(CoreError::StructureError {
+                message: "No current path set for repository".to_string(),
+            }),
+        }
+    }
 
-        self.memory_repo.list_credential_summaries()
+    /// Read the archive at `path` as it is on disk, without opening it
+    ///
+    /// The provider remembers this version, so the next save replaces it.
+    fn load_from_disk(&self, path: &str, password: &str) -> CoreResult<UnifiedMemoryRepository> {
+        let archive_data = self.file_provider.read_archive(path)?;
+        let (file_map, _) = self.extract_file_map(&archive_data, password)?;
+        let (file_map, _) = integrity::repair_benign(file_map)?;
+        let mut memory_repo = UnifiedMemoryRepository::new();
+        memory_repo.load_from_files(file_map)?;
+        Ok(memory_repo)
     }
 
-    /// Check if repository is currently open
+    /// Watch the current archive if watching is on, replacing any earlier
+    /// watch
+    fn watch_current_archive(&mut self) {
+        self.archive_watch = None;
+        let Some(path) = self.current_path.as_deref() else {
+            return;
+        };
+        if !self.watch_archive || !self.is_open {
+            return;
+        }
+
+        let emitter = self.memory_repo.change_emitter();
+        let on_change = Box::new(move || emitter.emit(&RepositoryEvent::ArchiveChanged));
+        match self.file_provider.watch_archive(path, on_change) {
+            Ok(watch) => self.archive_watch = watch,
+            Err(e) => warn!("Failed to watch archive for changes: {}", e),
+        }
+    }
+
+    /// Serialize the repository, recording the provider's archive backend
+    fn serialize_for_archive(&mut self) -> CoreResult<FileMap> {
+        self.memory_repo
+            .set_archive_backend(self.file_provider.archive_backend());
+        self.memory_repo.serialize_to_files()
+    }
+
+    /// Whether a save to `path` can patch the archive that was last opened
+    /// or saved
+    fn can_update_archive(&self, path: &str, master_password: &str) -> bool {
+        self.incremental_saves
+            && self.archive_options == ArchiveOptions::default()
+            && !self.saved_entries.is_empty()
+            && !self.has_duress_password()
+            && self.current_path.as_deref() == Some(path)
+            && self.master_password.as_deref().map(String::as_str) == Some(master_password)
+    }
+
+    /// Entries of `file_map` that differ from the saved archive, and the
+    /// saved entries that are gone
+    fn changes_since_save(
+        &self,
+        mut file_map: FileMap,
+        digests: &HashMap<String, Vec<u8>>,
+    ) -> (FileMap, Vec<String>) {
+        file_map.retain(|path, _| self.saved_entries.get(path) != digests.get(path));
+        let removed = self
+            .saved_entries
+            .keys()
+            .filter(|path| !digests.contains_key(*path))
+            .cloned()
+            .collect();
+        (file_map, removed)
+    }
+
+    /// Use `memory_repo` from now on, keeping the listeners and activity
+    /// settings of the one it replaces
+    fn replace_memory_repo(&mut self, mut memory_repo: UnifiedMemoryRepository) {
+        memory_repo.take_listeners_from(&mut self.memory_repo);
+        memory_repo.set_retention_quotas(self.retention_quotas);
+        self.memory_repo = memory_repo;
+        self.apply_activity_settings();
+    }
+
+    fn apply_activity_settings(&mut self) {
+        self.memory_repo
+            .set_activity_device(self.activity_logging.then(|| self.device_name.clone()));
+    }
+
+    fn report_progress(&self, phase: ArchivePhase) {
+        progress::report(self.progress_observer.as_deref(), phase);
+    }
+
+    /// Open the secure store of the current repository
+    fn open_secure_store(&mut self) {
+        self.secure_store = match (
+            &self.secure_store_dir,
+            &self.current_path,
+            &self.master_password,
+        ) {
+            (Some(dir), Some(path), Some(password)) => {
+                match SecureStore::open(SecureStore::path_for(dir, path), password) {
+                    Ok(store) => Some(store),
+                    Err(e) => {
+                        warn!("Failed to open secure store: {}", e);
+                        None
+                    }
+                }
+            }
+            _ => None,
+        };
+    }
+
+    /// Write back the secure store, overwrite the master password and
+    /// credential data and mark closed
+    fn wipe_secrets(&mut self) {
+        self.archive_watch = None;
+        if let Some(mut store) = self.secure_store.take() {
+            if let Err(e) = store.save() {
+                warn!("Failed to save secure store: {}", e);
+            }
+        }
+        self.master_password = None;
+        self.key_file = None;
+        self.container = None;
+        self.memory_repo.wipe();
+        self.saved_entries.clear();
+        self.repair_log.clear();
+        self.is_open = false;
+    }
+
+    /// Add a new credential to the repository
+    pub fn add_credential(&mut self, credential: CredentialRecord) -> CoreResult<()> {
+        if !self.is_open {
+            return Err(CoreError::NotInitialized);
+        }
+
+        #[cfg(feature = "automation")]
+        let credential = {
+            let mut credential = credential;
+            if let Some(engine) = &self.automation {
+                let outcome = engine.credential_created(&mut credential);
+                self.check_automation(outcome)?;
+            }
+            credential
+        };
+
+        self.memory_repo.add_credential(credential)
+    }
+
+    /// Get a credential by ID
+    pub fn get_credential(&mut self, id: &str) -> CoreResult<&CredentialRecord> {
+        if !self.is_open {
+            return Err(CoreError::NotInitialized);
+        }
+
+        self.memory_repo.get_credential(id)
+    }
+
+    /// A copy of a credential with the bodies of its large notes, see
+    /// [`UnifiedMemoryRepository::get_credential_with_notes`]
+    pub fn get_credential_with_notes(&self, id: &str) -> CoreResult<CredentialRecord> {
+        if !self.is_open {
+            return Err(CoreError::NotInitialized);
+        }
+
+        self.memory_repo.get_credential_with_notes(id)
+    }
+
+    /// Get a credential by ID without updating access time
+    pub fn get_credential_readonly(&self, id: &str) -> CoreResult<&CredentialRecord> {
+        if !self.is_open {
+            return Err(CoreError::NotInitialized);
+        }
+
+        self.memory_repo.get_credential_readonly(id)
+    }
+
+    /// Update an existing credential
+    pub fn update_credential(&mut self, credential: CredentialRecord) -> CoreResult<()> {
+        if !self.is_open {
+            return Err(CoreError::NotInitialized);
+        }
+
+        self.memory_repo.update_credential(credential)
+    }
+
+    /// Delete a credential by ID
+    pub fn delete_credential(&mut self, id: &str) -> CoreResult<CredentialRecord> {
+        if !self.is_open {
+            return Err(CoreError::NotInitialized);
+        }
+
+        self.memory_repo.delete_credential(id)
+    }
+
+    /// Deleted credentials still in the trash, most recently deleted first
+    pub fn list_trash(&self) -> CoreResult<Vec<TrashedCredential>> {
+        if !self.is_open {
+            return Err(CoreError::NotInitialized);
+        }
+
+        Ok(self
+            .memory_repo
+            .list_trash()?
+            .into_iter()
+            .cloned()
+            .collect())
+    }
+
+    /// Move a credential out of the trash, see
+    /// [`UnifiedMemoryRepository::restore_credential`]
+    pub fn restore_credential(&mut self, id: &str) -> CoreResult<()> {
+        if !self.is_open {
+            return Err(CoreError::NotInitialized);
+        }
+
+        self.memory_repo.restore_credential(id)
+    }
+
+    /// Delete everything in the trash for good
+    pub fn empty_trash(&mut self) -> CoreResult<usize> {
+        if !self.is_open {
+            return Err(CoreError::NotInitialized);
+        }
+
+        self.memory_repo.empty_trash()
+    }
+
+    /// Encrypt a field with a passphrase of its own, see
+    /// [`UnifiedMemoryRepository::protect_field`]
+    pub fn protect_field(
+        &mut self,
+        id: &str,
+        field_name: &str,
+        passphrase: &str,
+    ) -> CoreResult<()> {
+        if !self.is_open {
+            return Err(CoreError::NotInitialized);
+        }
+
+        self.memory_repo.protect_field(id, field_name, passphrase)
+    }
+
+    /// The real value of a protected field
+    pub fn unlock_field(
+        &self,
+        id: &str,
+        field_name: &str,
+        passphrase: &str,
+    ) -> CoreResult<Zeroizing<String>> {
+        if !self.is_open {
+            return Err(CoreError::NotInitialized);
+        }
+
+        self.memory_repo.unlock_field(id, field_name, passphrase)
+    }
+
+    /// Store a protected field like any other again
+    pub fn unprotect_field(
+        &mut self,
+        id: &str,
+        field_name: &str,
+        passphrase: &str,
+    ) -> CoreResult<()> {
+        if !self.is_open {
+            return Err(CoreError::NotInitialized);
+        }
+
+        self.memory_repo.unprotect_field(id, field_name, passphrase)
+    }
+
+    /// Change the passphrase of a protected field
+    pub fn change_field_passphrase(
+        &mut self,
+        id: &str,
+        field_name: &str,
+        old_passphrase: &str,
+        new_passphrase: &str,
+    ) -> CoreResult<()> {
+        if !self.is_open {
+            return Err(CoreError::NotInitialized);
+        }
+
+        self.memory_repo
+            .change_field_passphrase(id, field_name, old_passphrase, new_passphrase)
+    }
+
+    /// Put an earlier password back, see
+    /// [`UnifiedMemoryRepository::restore_previous_value`]
+    pub fn restore_previous_value(
+        &mut self,
+        id: &str,
+        field_name: &str,
+        replaced_at: i64,
+    ) -> CoreResult<()> {
+        if !self.is_open {
+            return Err(CoreError::NotInitialized);
+        }
+
+        self.memory_repo
+            .restore_previous_value(id, field_name, replaced_at)
+    }
+
+    /// Credentials created again on a schedule
+    pub fn list_recurring_credentials(&self) -> CoreResult<Vec<RecurringCredential>> {
+        if !self.is_open {
+            return Err(CoreError::NotInitialized);
+        }
+
+        Ok(self.memory_repo.recurring_credentials().to_vec())
+    }
+
+    /// Add or replace a recurring credential, see
+    /// [`UnifiedMemoryRepository::set_recurring_credential`]
+    pub fn set_recurring_credential(&mut self, recurring: RecurringCredential) -> CoreResult<()> {
+        if !self.is_open {
+            return Err(CoreError::NotInitialized);
+        }
+
+        self.memory_repo.set_recurring_credential(recurring)
+    }
+
+    /// Stop creating a recurring credential
+    pub fn remove_recurring_credential(&mut self, id: &str) -> CoreResult<RecurringCredential> {
+        if !self.is_open {
+            return Err(CoreError::NotInitialized);
+        }
+
+        self.memory_repo.remove_recurring_credential(id)
+    }
+
+    /// People the vault is shared with
+    pub fn list_members(&self) -> CoreResult<Vec<VaultMember>> {
+        if !self.is_open {
+            return Err(CoreError::NotInitialized);
+        }
+
+        Ok(self.memory_repo.members().to_vec())
+    }
+
+    /// Add a vault member or replace their key
+    pub fn set_member(&mut self, member: VaultMember) -> CoreResult<()> {
+        if !self.is_open {
+            return Err(CoreError::NotInitialized);
+        }
+
+        self.memory_repo.set_member(member)
+    }
+
+    /// Remove a vault member, see
+    /// [`UnifiedMemoryRepository::remove_member`]
+    pub fn remove_member(&mut self, name: &str) -> CoreResult<VaultMember> {
+        if !self.is_open {
+            return Err(CoreError::NotInitialized);
+        }
+
+        self.memory_repo.remove_member(name)
+    }
+
+    /// Create the drafts of recurring credentials that are due
+    pub fn create_due_drafts(&mut self, now: i64) -> CoreResult<Vec<RecurringDraft>> {
+        if !self.is_open {
+            return Err(CoreError::NotInitialized);
+        }
+
+        self.memory_repo.create_due_drafts(now)
+    }
+
+    /// Expirations and password changes that are overdue or coming up
+    pub fn reminders(&self, engine: &ReminderEngine) -> CoreResult<Vec<Reminder>> {
+        if !self.is_open {
+            return Err(CoreError::NotInitialized);
+        }
+
+        Ok(engine.reminders(&self.memory_repo.list_credentials()?))
+    }
+
+    /// Forget earlier values of a credential's fields
+    pub fn clear_field_history(&mut self, id: &str, field_name: Option<&str>) -> CoreResult<usize> {
+        if !self.is_open {
+            return Err(CoreError::NotInitialized);
+        }
+
+        self.memory_repo.clear_field_history(id, field_name)
+    }
+
+    /// List all credentials
+    pub fn list_credentials(&self) -> CoreResult<Vec<CredentialRecord>> {
+        if !self.is_open {
+            return Err(CoreError::NotInitialized);
+        }
+
+        self.memory_repo.list_credentials()
+    }
+
+    /// Search credentials, best matches first, e.g. `tag:bank type:login gmail`
+    pub fn search_credentials(&self, query: &str) -> CoreResult<Vec<CredentialRecord>> {
+        if !self.is_open {
+            return Err(CoreError::NotInitialized);
+        }
+
+        self.memory_repo.search_credentials(query)
+    }
+
+    /// Search credentials with the score of each match, best first
+    pub fn search_credentials_scored(
+        &self,
+        query: &str,
+    ) -> CoreResult<Vec<(CredentialRecord, f64)>> {
+        if !self.is_open {
+            return Err(CoreError::NotInitialized);
+        }
+
+        self.memory_repo.search_credentials_scored(query)
+    }
+
+    /// Get credential summaries (ID and title only)
+    pub fn list_credential_summaries(&self) -> CoreResult<Vec<(String, String)>> {
+        if !self.is_open {
+            return Err(CoreError::NotInitialized);
+        }
+
+        self.memory_repo.list_credential_summaries()
+    }
+
+    /// Check if repository is currently open
     pub fn is_open(&self) -> bool {
         self.is_open
     }
@@ -293,6 +1566,103 @@ impl<F: FileOperationProvider> UnifiedRepositoryManager<F> {
         self.memory_repo.get_stats()
     }
 
+    /// Set the defaults new credentials in a folder inherit
+    pub fn set_folder_defaults(
+        &mut self,
+        folder_path: &str,
+        defaults: FolderDefaults,
+    ) -> CoreResult<()> {
+        if !self.is_open {
+            return Err(CoreError::NotInitialized);
+        }
+
+        self.memory_repo.set_folder_defaults(folder_path, defaults)
+    }
+
+    /// Form for a new credential in `folder_path`, see
+    /// [`UnifiedMemoryRepository::credential_form_schema`]
+    pub fn credential_form_schema(
+        &self,
+        folder_path: Option<&str>,
+        template: Option<&str>,
+    ) -> CoreResult<CredentialFormSchema> {
+        if !self.is_open {
+            return Err(CoreError::NotInitialized);
+        }
+
+        self.memory_repo
+            .credential_form_schema(folder_path, template)
+    }
+
+    /// User-defined templates, see [`UnifiedMemoryRepository::list_templates`]
+    pub fn list_templates(&self) -> CoreResult<Vec<CredentialTemplate>> {
+        if !self.is_open {
+            return Err(CoreError::NotInitialized);
+        }
+
+        self.memory_repo.list_templates()
+    }
+
+    /// Save a new user-defined template, see
+    /// [`UnifiedMemoryRepository::add_template`]
+    pub fn add_template(&mut self, template: CredentialTemplate) -> CoreResult<()> {
+        if !self.is_open {
+            return Err(CoreError::NotInitialized);
+        }
+
+        self.memory_repo.add_template(template)
+    }
+
+    /// Replace a user-defined template, see
+    /// [`UnifiedMemoryRepository::update_template`]
+    pub fn update_template(&mut self, template: CredentialTemplate) -> CoreResult<()> {
+        if !self.is_open {
+            return Err(CoreError::NotInitialized);
+        }
+
+        self.memory_repo.update_template(template)
+    }
+
+    /// Run a security audit over all credentials
+    ///
+    /// With a breach check configured this may perform network requests or
+    /// read a bloom filter file, so call it off the UI thread.
+    pub fn security_audit(&self, options: AuditOptions) -> CoreResult<SecurityAuditReport> {
+        if !self.is_open {
+            return Err(CoreError::NotInitialized);
+        }
+
+        let credentials = self.memory_repo.list_credentials()?;
+        let report = SecurityAuditor::new(options).audit(&credentials);
+
+        #[cfg(feature = "automation")]
+        let report = {
+            let mut report = report;
+            if let Some(engine) = &self.automation {
+                let outcome =
+                    engine
+                        .audit_completed(&report)
+                        .map_err(|e| CoreError::ValidationError {
+                            message: e.to_string(),
+                        })?;
+                report.script_findings = outcome.into_messages();
+            }
+            report
+        };
+
+        Ok(report)
+    }
+
+    /// Build the access review report from each credential's compliance metadata
+    pub fn compliance_report(&self, options: AuditOptions) -> CoreResult<ComplianceReport> {
+        if !self.is_open {
+            return Err(CoreError::NotInitialized);
+        }
+
+        let credentials = self.memory_repo.list_credentials()?;
+        Ok(SecurityAuditor::new(options).compliance_report(&credentials))
+    }
+
     /// Export repository data for backup or migration
     pub fn export_to_file_map(&self) -> CoreResult<FileMap> {
         if !self.is_open {
@@ -308,31 +1678,165 @@ impl<F: FileOperationProvider> UnifiedRepositoryManager<F> {
             return Err(CoreError::AlreadyInitialized);
         }
 
-        self.memory_repo = UnifiedMemoryRepository::new();
-        self.memory_repo.load_from_files(file_map)?;
+        let mut memory_repo = UnifiedMemoryRepository::new();
+        memory_repo.load_from_files(file_map)?;
+        self.replace_memory_repo(memory_repo);
+        self.saved_entries.clear();
         self.is_open = true;
+        self.memory_repo.emit(RepositoryEvent::Opened);
 
         Ok(())
     }
 
     /// Change the master password for the repository
     ///
+    /// See [`Self::change_master_password_with_progress`].
+    pub fn change_master_password(
+        &mut self,
+        old_password: &str,
+        new_password: &str,
+    ) -> CoreResult<Vec<ProtectorRecord>> {
+        self.change_master_password_with_progress(old_password, new_password, |_| {})
+    }
+
+    /// Change the master password, reporting each stage to `progress`
+    ///
+    /// The repository is re-encrypted and saved like any other save: the new
+    /// archive is read back and decrypted with the new password before it
+    /// replaces the original, which is kept as `.bak`. Until the swap the
+    /// original archive is left untouched, so a failure at any stage keeps
+    /// the old password working. A key file in use stays required.
+    ///
+    /// Key protectors wrap the old password, so they are removed; the
+    /// caller should tell the user to register them again.
+    ///
     /// # Arguments
+    /// * `old_password` - Current master password, to confirm the change
     /// * `new_password` - New password for encryption
+    /// * `progress` - Called as each stage starts, and once more on completion
     ///
     /// # Returns
-    /// * `Ok(())` - If password change was successful
-    /// * `Err(CoreError)` - If password change fails
-    pub fn change_master_password(&mut self, new_password: &str) -> CoreResult<()> {
+    /// * `Ok(records)` - The key protectors that no longer unlock the
+    ///   repository
+    /// * `Err(CoreError)` - If `old_password` is wrong or the new archive
+    ///   could not be written and verified
+    pub fn change_master_password_with_progress(
+        &mut self,
+        old_password: &str,
+        new_password: &str,
+        progress: impl FnMut(PasswordChangeStage),
+    ) -> CoreResult<Vec<ProtectorRecord>> {
         if !self.is_open {
             return Err(CoreError::NotInitialized);
         }
+        if new_password.is_empty() {
+            return Err(CoreError::ValidationError {
+                message: "New master password cannot be empty".to_string(),
+            });
+        }
+
+        let composite = |password: &str| match &self.key_file {
+            Some(key_file) => key_file.composite_password(password),
+            None => password.to_string(),
+        };
+        let current = self
+            .master_password
+            .as_deref()
+            .ok_or_else(|| CoreError::StructureError {
+                message: "No master password set for repository".to_string(),
+            })?;
+        let mut old_key = composite(old_password);
+        let old_matches = EncryptionUtils::secure_compare(old_key.as_bytes(), current.as_bytes());
+        old_key.zeroize();
+        if !old_matches {
+            return Err(CoreError::FileOperation(FileError::InvalidPassword));
+        }
 
-        // Update stored password
-        self.master_password = Some(new_password.to_string());
+        let path = self
+            .current_path
+            .clone()
+            .ok_or_else(|| CoreError::StructureError {
+                message: "No current path set for repository".to_string(),
+            })?;
+        let mut new_key = composite(new_password);
+        if self.opens_other_section(&new_key)? {
+            new_key.zeroize();
+            return Err(CoreError::ValidationError {
+                message: "This password cannot be used for this repository".to_string(),
+            });
+        }
+
+        let progress = RefCell::new(progress);
+        let result = (|| {
+            (progress.borrow_mut())(PasswordChangeStage::Encrypting);
+            let file_map = self.serialize_for_archive()?;
+            let digests = entry_digests(&file_map);
+            let (archive_data, container) = self.create_archive_data(file_map, &new_key)?;
+
+            (progress.borrow_mut())(PasswordChangeStage::Writing);
+            let expected = self.memory_repo.get_stats()?.credential_count;
+            let verify = |written: &[u8]| {
+                (progress.borrow_mut())(PasswordChangeStage::Verifying);
+                self.verify_rekeyed(written, &new_key, expected)?;
+                (progress.borrow_mut())(PasswordChangeStage::Replacing);
+                Ok(())
+            };
+            self.file_provider
+                .write_archive_verified(&path, &archive_data, &verify)?;
+            Ok((digests, container))
+        })();
+
+        let (digests, container) = match result {
+            Ok(saved) => saved,
+            Err(e) => {
+                new_key.zeroize();
+                return Err(e);
+            }
+        };
+        self.saved_entries = digests;
+        self.container = container;
+        if let Some(store) = self.secure_store.as_mut() {
+            store.set_passphrase(&new_key);
+        }
+        self.master_password = Some(Zeroizing::new(new_key));
+        self.memory_repo.mark_saved();
+
+        // Protectors wrap the old password and cannot be re-wrapped without
+        // the protector itself, e.g. a touch of the security key
+        let invalidated = ProtectorStore::load(&self.file_provider, &path).unwrap_or_default();
+        if !invalidated.is_empty() {
+            if let Err(e) = ProtectorStore::save(&self.file_provider, &path, &[]) {
+                warn!("Failed to remove key protectors of {}: {}", path, e);
+            }
+        }
+        (progress.into_inner())(PasswordChangeStage::Complete);
+
+        Ok(invalidated)
+    }
 
-        // Save with new password (will re-encrypt)
-        self.save_repository()
+    /// Check that `written`, an archive re-encrypted with `password`, opens
+    /// and holds all `expected` credentials
+    fn verify_rekeyed(&self, written: &[u8], password: &str, expected: usize) -> FileResult<()> {
+        let (file_map, _) = self.extract_file_map(written, password)?;
+        let mut check = UnifiedMemoryRepository::new();
+        let loaded = check
+            .load_from_files(file_map)
+            .and_then(|()| check.get_stats());
+        check.wipe();
+        let found = loaded
+            .map_err(|e| FileError::VerificationFailed {
+                message: e.to_string(),
+            })?
+            .credential_count;
+        if found != expected {
+            return Err(FileError::CorruptedArchive {
+                message: format!(
+                    "Re-encrypted archive has {} credentials, expected {}",
+                    found, expected
+                ),
+            });
+        }
+        Ok(())
     }
 
     /// Get credentials by tag
@@ -371,108 +1875,767 @@ impl<F: FileOperationProvider> UnifiedRepositoryManager<F> {
             return Err(CoreError::NotInitialized);
         }
 
-        self.memory_repo.import_credentials(credentials)
+        self.memory_repo.import_credentials(credentials)
+    }
+
+    /// Export all credentials
+    pub fn export_credentials(&self) -> CoreResult<Vec<CredentialRecord>> {
+        if !self.is_open {
+            return Err(CoreError::NotInitialized);
+        }
+
+        self.memory_repo.export_credentials()
+    }
+
+    /// Clear all credentials from repository
+    pub fn clear_credentials(&mut self) -> CoreResult<()> {
+        if !self.is_open {
+            return Err(CoreError::NotInitialized);
+        }
+
+        self.memory_repo.clear()
+    }
+
+    /// Check if a credential exists by ID
+    pub fn contains_credential(&self, id: &str) -> bool {
+        if !self.is_open {
+            return false;
+        }
+
+        self.memory_repo.contains_credential(id)
+    }
+
+    /// Issues fixed when the repository was opened
+    ///
+    /// Opening fixes [benign](IntegrityIssue::is_benign) issues such as a
+    /// wrong credential count or files nothing refers to, and marks the
+    /// repository modified so the fixes are written on the next save.
+    /// After [`repair`](Self::repair) this lists everything it fixed.
+    pub fn repair_log(&self) -> &[IntegrityIssue] {
+        &self.repair_log
+    }
+
+    /// Activity log entries matching `query`, oldest first
+    pub fn query_activity(&self, query: &ActivityQuery) -> CoreResult<Vec<ActivityEntry>> {
+        if !self.is_open {
+            return Err(CoreError::NotInitialized);
+        }
+
+        Ok(self
+            .memory_repo
+            .activity_log()
+            .query(query)
+            .into_iter()
+            .cloned()
+            .collect())
+    }
+
+    /// Check that no activity log entry was changed or removed
+    ///
+    /// Fails with an integrity error naming the first entry whose hash or
+    /// link does not match.
+    pub fn verify_activity_log(&self) -> CoreResult<()> {
+        if !self.is_open {
+            return Err(CoreError::NotInitialized);
+        }
+
+        self.memory_repo
+            .activity_log()
+            .verify()
+            .map_err(|broken| CoreError::StructureError {
+                message: format!("Activity log was altered at entry {}", broken.sequence),
+            })
+    }
+
+    /// Remove activity log entries older than `before` (Unix timestamp),
+    /// see [`UnifiedMemoryRepository::prune_activity_before`]
+    pub fn prune_activity_before(&mut self, before: i64) -> CoreResult<usize> {
+        if !self.is_open {
+            return Err(CoreError::NotInitialized);
+        }
+
+        self.memory_repo.prune_activity_before(before)
+    }
+
+    /// Verify the integrity of the open repository's archive
+    ///
+    /// Checks the archive as last saved, so save first to include unsaved
+    /// changes.
+    pub fn verify_integrity(&self) -> CoreResult<IntegrityReport> {
+        if !self.is_open {
+            return Err(CoreError::NotInitialized);
+        }
+
+        let (Some(path), Some(password)) = (&self.current_path, &self.master_password) else {
+            return Err(CoreError::StructureError {
+                message: "No current path set for repository".to_string(),
+            });
+        };
+        self.verify_archive(path, password)
+    }
+
+    /// Verify the integrity of the archive at `path` without opening it
+    ///
+    /// Works on archives that fail to open, to find out why.
+    pub fn verify_archive(&self, path: &str, master_password: &str) -> CoreResult<IntegrityReport> {
+        let archive_data = self.file_provider.read_archive(path)?;
+        let (file_map, _) = self.extract_file_map(&archive_data, master_password)?;
+        Ok(integrity::check_files(&file_map))
+    }
+
+    /// Open the repository at `path`, repairing what can be repaired
+    ///
+    /// Entries that cannot be read are moved under `quarantine/` in the
+    /// archive instead of failing the open; see
+    /// [`integrity::repair_files`]. If anything was repaired the fixed
+    /// archive is saved straight away.
+    pub fn repair(&mut self, path: &str, master_password: &str) -> CoreResult<RepairReport> {
+        if self.is_open {
+            return Err(CoreError::AlreadyInitialized);
+        }
+
+        let archive_data = self.file_provider.read_archive(path)?;
+        let (file_map, container) = self.extract_file_map(&archive_data, master_password)?;
+        let (file_map, report) = integrity::repair_files(file_map)?;
+
+        let mut memory_repo = UnifiedMemoryRepository::new();
+        memory_repo.load_from_files(file_map)?;
+        self.replace_memory_repo(memory_repo);
+        self.memory_repo
+            .record_activity(ActivityAction::Opened, None);
+        self.saved_entries.clear();
+        self.repair_log = report.repaired.clone();
+        self.current_path = Some(path.to_string());
+        self.master_password = Some(Zeroizing::new(master_password.to_string()));
+        self.key_file = None;
+        self.container = container;
+        self.is_open = true;
+        self.open_secure_store();
+        self.memory_repo.emit(RepositoryEvent::Opened);
+        self.watch_current_archive();
+
+        if !report.repaired.is_empty() {
+            self.save_repository()?;
+        }
+        Ok(report)
+    }
+
+    /// Get a reference to the internal memory repository
+    ///
+    /// This is primarily for advanced use cases and testing.
+    pub fn memory_repository(&self) -> &UnifiedMemoryRepository {
+        &self.memory_repo
+    }
+
+    /// Get a mutable reference to the internal memory repository
+    ///
+    /// This is primarily for advanced use cases and testing.
+    pub fn memory_repository_mut(&mut self) -> &mut UnifiedMemoryRepository {
+        &mut self.memory_repo
+    }
+}
+
+/// SHA-256 of each entry, to find the ones that changed without keeping
+/// their contents
+fn entry_digests(file_map: &FileMap) -> HashMap<String, Vec<u8>> {
+    file_map
+        .iter()
+        .map(|(path, content)| (path.clone(), EncryptionUtils::hash_sha256(content)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::archive_backend::{ArchiveBackend, SevenZipBackend};
+    use crate::core::file_provider::{DesktopFileProvider, MockFileProvider};
+    use crate::models::{CredentialField, CredentialRecord};
+    use crate::utils::backup_scheduler::BackupScheduler;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    fn create_test_credential(title: &str) -> CredentialRecord {
+        let mut credential = CredentialRecord::new(title.to_string(), "test".to_string());
+        credential.set_field("username", CredentialField::username("testuser"));
+        credential.set_field("password", CredentialField::password("testpass"));
+        credential
+    }
+
+    #[test]
+    fn test_repository_creation() {
+        let provider = MockFileProvider::new();
+        let mut manager = UnifiedRepositoryManager::new(provider);
+
+        assert!(!manager.is_open());
+        assert!(manager.create_repository("/test.7z", "password").is_ok());
+        assert!(manager.is_open());
+        assert!(!manager.is_modified()); // Should be clean after creation and save
+    }
+
+    #[test]
+    fn test_duress_password_opens_decoy_credentials() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vault.7z");
+        let path = path.to_str().unwrap();
+        let titles = |manager: &UnifiedRepositoryManager<DesktopFileProvider>| {
+            let mut titles: Vec<String> = manager
+                .list_credentials()
+                .unwrap()
+                .into_iter()
+                .map(|c| c.title)
+                .collect();
+            titles.sort();
+            titles
+        };
+
+        let hidden_section = || {
+            let file = std::fs::read(path).unwrap();
+            let len = crate::core::seven_zip_update::archive_len(&file).unwrap();
+            file[len..].to_vec()
+        };
+
+        let mut manager = UnifiedRepositoryManager::new(DesktopFileProvider::new());
+        manager.create_repository(path, "master").unwrap();
+        manager
+            .add_credential(create_test_credential("Bank"))
+            .unwrap();
+        manager.save_repository().unwrap();
+        let random = hidden_section();
+        assert!(random.len() >= crate::core::duress::MIN_HIDDEN_LEN);
+        assert!(!manager.has_duress_password());
+
+        assert!(manager.set_duress_password("master", Vec::new()).is_err());
+        manager
+            .set_duress_password("duress", vec![create_test_credential("Forum")])
+            .unwrap();
+        assert!(manager.set_duress_password("other", Vec::new()).is_err());
+        manager.close_repository(false).unwrap();
+        assert_ne!(hidden_section(), random);
+
+        // Other 7z tools see only the decoy
+        let file = std::fs::read(path).unwrap();
+        assert!(SevenZipBackend.extract(&file, "duress").is_ok());
+        assert!(matches!(
+            SevenZipBackend.extract(&file, "master"),
+            Err(FileError::InvalidPassword)
+        ));
+
+        // The duress password opens a repository like any other
+        manager.open_repository(path, "duress").unwrap();
+        assert!(!manager.has_duress_password());
+        assert_eq!(titles(&manager), vec!["Forum"]);
+        manager
+            .add_credential(create_test_credential("Mail"))
+            .unwrap();
+        assert!(manager.change_master_password("duress", "master").is_err());
+        let hidden = hidden_section();
+        manager.close_repository(true).unwrap();
+        assert_eq!(hidden_section(), hidden);
+
+        manager.open_repository(path, "master").unwrap();
+        assert!(manager.has_duress_password());
+        assert_eq!(titles(&manager), vec!["Bank"]);
+        manager
+            .change_master_password("master", "new master")
+            .unwrap();
+        manager.close_repository(false).unwrap();
+        assert!(matches!(
+            manager.open_repository(path, "master"),
+            Err(CoreError::FileOperation(FileError::InvalidPassword))
+        ));
+
+        manager.open_repository(path, "duress").unwrap();
+        assert_eq!(titles(&manager), vec!["Forum", "Mail"]);
+        manager.close_repository(false).unwrap();
+
+        // Removing needs the password of the other credential set
+        manager.open_repository(path, "new master").unwrap();
+        assert!(manager.remove_duress_password("guess").is_err());
+        manager.remove_duress_password("duress").unwrap();
+        assert!(!manager.has_duress_password());
+        manager.close_repository(false).unwrap();
+        assert!(manager.open_repository(path, "duress").is_err());
+        manager.open_repository(path, "new master").unwrap();
+        assert_eq!(titles(&manager), vec!["Bank"]);
+    }
+
+    #[test]
+    fn test_duress_session_cannot_overwrite_the_hidden_section() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vault.7z");
+        let path = path.to_str().unwrap();
+
+        let mut manager = UnifiedRepositoryManager::new(DesktopFileProvider::new());
+        manager.create_repository(path, "master").unwrap();
+        manager
+            .add_credential(create_test_credential("Bank"))
+            .unwrap();
+        manager
+            .set_duress_password("duress", vec![create_test_credential("Forum")])
+            .unwrap();
+        manager.close_repository(false).unwrap();
+
+        manager.open_repository(path, "duress").unwrap();
+        let file = std::fs::read(path).unwrap();
+        assert!(matches!(
+            manager.set_duress_password("second", Vec::new()),
+            Err(CoreError::ValidationError { .. })
+        ));
+        assert_eq!(std::fs::read(path).unwrap(), file);
+        manager.close_repository(false).unwrap();
+
+        // A plain repository read from disk looks the same, so it needs the
+        // explicit discard too
+        manager.open_repository(path, "master").unwrap();
+        manager.remove_duress_password("duress").unwrap();
+        manager.close_repository(false).unwrap();
+        manager.open_repository(path, "master").unwrap();
+        assert!(manager.set_duress_password("duress", Vec::new()).is_err());
+        manager.discard_hidden_section().unwrap();
+        manager.set_duress_password("duress", Vec::new()).unwrap();
+        manager.close_repository(false).unwrap();
+        manager.open_repository(path, "master").unwrap();
+        assert!(manager.has_duress_password());
+        assert!(manager.discard_hidden_section().is_err());
+        assert_eq!(manager.list_credentials().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_no_plaintext_left_in_temp_dir() {
+        fn contains_marker(path: &std::path::Path, marker: &[u8]) -> bool {
+            if path.is_dir() {
+                std::fs::read_dir(path)
+                    .map(|entries| {
+                        entries
+                            .flatten()
+                            .any(|entry| contains_marker(&entry.path(), marker))
+                    })
+                    .unwrap_or(false)
+            } else {
+                std::fs::read(path)
+                    .map(|data| data.windows(marker.len()).any(|w| w == marker))
+                    .unwrap_or(false)
+            }
+        }
+
+        let marker = format!("plaintext-marker-{}", uuid::Uuid::new_v4());
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vault.7z");
+        let path = path.to_str().unwrap();
+
+        let mut manager = UnifiedRepositoryManager::new(DesktopFileProvider::new());
+        manager.create_repository(path, "password").unwrap();
+        manager
+            .add_credential(create_test_credential(&marker))
+            .unwrap();
+        manager.close_repository(true).unwrap();
+        manager.open_repository(path, "password").unwrap();
+        manager.close_repository(false).unwrap();
+
+        // Only the encrypted archive holds the credential
+        let temp_entries: Vec<_> = std::fs::read_dir(std::env::temp_dir())
+            .unwrap()
+            .flatten()
+            .filter(|entry| entry.file_name().to_string_lossy().starts_with("ziplock_"))
+            .map(|entry| entry.path())
+            .collect();
+        for entry in temp_entries {
+            assert!(
+                !contains_marker(&entry, marker.as_bytes()),
+                "plaintext found in {:?}",
+                entry
+            );
+        }
+        assert!(!contains_marker(dir.path(), marker.as_bytes()));
+    }
+
+    #[test]
+    fn test_open_applies_configured_quotas() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vault.7z");
+        let path = path.to_str().unwrap();
+        let mut manager = UnifiedRepositoryManager::new(DesktopFileProvider::new());
+        manager.create_repository(path, "password").unwrap();
+        manager.close_repository(false).unwrap();
+
+        let quotas = RetentionQuotas::new()
+            .with_max_trash_items(1)
+            .with_max_revisions_per_credential(2);
+        let mut config = RepositoryConfig::load_for(&manager.file_provider, path)
+            .unwrap()
+            .unwrap_or_default();
+        config.quotas = quotas;
+        config.save_for(&manager.file_provider, path).unwrap();
+
+        manager.open_repository(path, "password").unwrap();
+        assert_eq!(manager.retention_quotas(), quotas);
+        for title in ["One", "Two"] {
+            let credential = create_test_credential(title);
+            let id = credential.id.clone();
+            manager.add_credential(credential).unwrap();
+            manager.delete_credential(&id).unwrap();
+        }
+        let report = manager.save_repository_with_report().unwrap();
+        assert_eq!(report.trash_removed.len(), 1);
     }
 
-    /// Export all credentials
-    pub fn export_credentials(&self) -> CoreResult<Vec<CredentialRecord>> {
-        if !self.is_open {
-            return Err(CoreError::NotInitialized);
+    #[test]
+    fn test_save_reports_pruned_trash() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vault.7z");
+        let path = path.to_str().unwrap();
+        let mut manager = UnifiedRepositoryManager::new(DesktopFileProvider::new());
+        manager.create_repository(path, "password").unwrap();
+        manager.set_retention_quotas(RetentionQuotas::new().with_max_trash_items(1));
+
+        let mut ids = Vec::new();
+        for title in ["First", "Second"] {
+            let credential = CredentialRecord::new(title.to_string(), "login".to_string());
+            ids.push(credential.id.clone());
+            manager.add_credential(credential).unwrap();
         }
+        for id in &ids {
+            manager.delete_credential(id).unwrap();
+        }
+        assert_eq!(manager.list_trash().unwrap().len(), 2);
+
+        let report = manager.save_repository_with_report().unwrap();
+        assert_eq!(report.trash_removed.len(), 1);
+        assert_eq!(manager.last_prune_report(), &report);
+
+        manager.close_repository(false).unwrap();
+        manager.open_repository(path, "password").unwrap();
+        let trash = manager.list_trash().unwrap();
+        assert_eq!(trash.len(), 1);
+        assert!(!report.trash_removed.contains(&trash[0].credential.id));
+        assert!(manager.save_repository_with_report().unwrap().is_empty());
+    }
 
-        self.memory_repo.export_credentials()
+    /// Desktop storage that refuses to save while `fail` is set
+    struct SaveFails {
+        inner: DesktopFileProvider,
+        fail: Arc<AtomicBool>,
     }
 
-    /// Clear all credentials from repository
-    pub fn clear_credentials(&mut self) -> CoreResult<()> {
-        if !self.is_open {
-            return Err(CoreError::NotInitialized);
+    impl SaveFails {
+        fn check(&self, path: &str) -> FileResult<()> {
+            if self.fail.load(Ordering::SeqCst) {
+                return Err(FileError::DiskFull {
+                    path: path.to_string(),
+                });
+            }
+            Ok(())
         }
-
-        self.memory_repo.clear()
     }
 
-    /// Check if a credential exists by ID
-    pub fn contains_credential(&self, id: &str) -> bool {
-        if !self.is_open {
-            return false;
+    impl FileOperationProvider for SaveFails {
+        fn read_archive(&self, path: &str) -> FileResult<Vec<u8>> {
+            self.inner.read_archive(path)
         }
 
-        self.memory_repo.contains_credential(id)
-    }
+        fn write_archive(&self, path: &str, data: &[u8]) -> FileResult<()> {
+            self.check(path)?;
+            self.inner.write_archive(path, data)
+        }
 
-    /// Verify repository integrity
-    ///
-    /// This performs various checks to ensure the repository is in a valid state.
-    pub fn verify_integrity(&self) -> CoreResult<Vec<String>> {
-        if !self.is_open {
-            return Err(CoreError::NotInitialized);
+        fn write_archive_verified(
+            &self,
+            path: &str,
+            data: &[u8],
+            verify: &dyn Fn(&[u8]) -> FileResult<()>,
+        ) -> FileResult<()> {
+            self.check(path)?;
+            self.inner.write_archive_verified(path, data, verify)
         }
 
-        let mut issues = Vec::new();
-        let stats = self.memory_repo.get_stats()?;
+        fn update_archive(
+            &self,
+            path: &str,
+            changed: &FileMap,
+            removed: &[String],
+            password: &str,
+            verify: &dyn Fn(&[u8]) -> FileResult<()>,
+        ) -> FileResult<()> {
+            self.check(path)?;
+            self.inner
+                .update_archive(path, changed, removed, password, verify)
+        }
 
-        // Check metadata consistency
-        if stats.credential_count != stats.metadata.credential_count {
-            issues.push(format!(
-                "Metadata credential count mismatch: expected {}, found {}",
-                stats.metadata.credential_count, stats.credential_count
-            ));
+        fn extract_archive(&self, data: &[u8], password: &str) -> FileResult<FileMap> {
+            self.inner.extract_archive(data, password)
         }
 
-        // Validate each credential
-        let credentials = self.memory_repo.list_credentials()?;
-        for credential in &credentials {
-            let validation_result = crate::utils::validation::validate_credential(credential);
-            if !validation_result.is_valid {
-                issues.push(format!(
-                    "Invalid credential '{}': {}",
-                    credential.title,
-                    validation_result.errors.join("; ")
-                ));
-            }
+        fn create_archive(&self, files: FileMap, password: &str) -> FileResult<Vec<u8>> {
+            self.inner.create_archive(files, password)
         }
+    }
 
-        Ok(issues)
+    #[test]
+    fn test_failed_save_keeps_pruned_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vault.7z");
+        let path = path.to_str().unwrap();
+        let fail = Arc::new(AtomicBool::new(false));
+        let mut manager = UnifiedRepositoryManager::new(SaveFails {
+            inner: DesktopFileProvider::new(),
+            fail: fail.clone(),
+        });
+        manager.create_repository(path, "password").unwrap();
+        manager.set_retention_quotas(RetentionQuotas::new().with_max_trash_items(2));
+        for title in ["First", "Second"] {
+            let credential = CredentialRecord::new(title.to_string(), "login".to_string());
+            let id = credential.id.clone();
+            manager.add_credential(credential).unwrap();
+            manager.delete_credential(&id).unwrap();
+        }
+        manager.save_repository().unwrap();
+
+        manager.set_retention_quotas(RetentionQuotas::new().with_max_trash_items(1));
+        fail.store(true, Ordering::SeqCst);
+        assert!(matches!(
+            manager.save_repository_with_report(),
+            Err(CoreError::FileOperation(FileError::DiskFull { .. }))
+        ));
+        assert_eq!(manager.list_trash().unwrap().len(), 2);
+        assert!(manager.last_prune_report().is_empty());
+
+        fail.store(false, Ordering::SeqCst);
+        let report = manager.save_repository_with_report().unwrap();
+        assert_eq!(report.trash_removed.len(), 1);
+        assert_eq!(manager.list_trash().unwrap().len(), 1);
     }
 
-    /// Get a reference to the internal memory repository
-    ///
-    /// This is primarily for advanced use cases and testing.
-    pub fn memory_repository(&self) -> &UnifiedMemoryRepository {
-        &self.memory_repo
+    #[test]
+    fn test_incremental_save_keeps_previous_archive() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vault.7z");
+        let path = path.to_str().unwrap();
+        let mut manager = UnifiedRepositoryManager::new(DesktopFileProvider::new());
+        manager.set_incremental_saves(true);
+        manager.create_repository(path, "password").unwrap();
+        let before = std::fs::read(path).unwrap();
+
+        manager
+            .add_credential(create_test_credential("Added"))
+            .unwrap();
+        assert!(manager.can_update_archive(path, "password"));
+        manager.save_repository().unwrap();
+        assert_eq!(std::fs::read(format!("{}.bak", path)).unwrap(), before);
+
+        manager.close_repository(false).unwrap();
+        manager.open_repository(path, "password").unwrap();
+        assert_eq!(manager.get_stats().unwrap().credential_count, 1);
     }
 
-    /// Get a mutable reference to the internal memory repository
-    ///
-    /// This is primarily for advanced use cases and testing.
-    pub fn memory_repository_mut(&mut self) -> &mut UnifiedMemoryRepository {
-        &mut self.memory_repo
+    #[test]
+    fn test_progress_is_reported_for_save_and_open() {
+        use std::sync::{Arc, Mutex};
+
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let recorder = seen.clone();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("progress.7z");
+        let path = path.to_str().unwrap();
+        let mut manager = UnifiedRepositoryManager::new(DesktopFileProvider::new());
+        manager.set_progress_observer(Some(Box::new(move |phase, percent| {
+            recorder.lock().unwrap().push((phase, percent));
+        })));
+
+        manager.create_repository(path, "password").unwrap();
+        assert_eq!(
+            seen.lock().unwrap().drain(..).collect::<Vec<_>>(),
+            vec![
+                (ArchivePhase::Serializing, 0),
+                (ArchivePhase::Encrypting, 10),
+                (ArchivePhase::Writing, 85),
+                (ArchivePhase::Complete, 100),
+            ]
+        );
+
+        manager.close_repository(false).unwrap();
+        manager.open_repository(path, "password").unwrap();
+        let phases: Vec<ArchivePhase> = seen.lock().unwrap().iter().map(|(p, _)| *p).collect();
+        assert_eq!(
+            phases,
+            vec![
+                ArchivePhase::Reading,
+                ArchivePhase::Decrypting,
+                ArchivePhase::Loading,
+                ArchivePhase::Complete,
+            ]
+        );
+
+        // Nothing is reported once the observer is removed
+        manager.set_progress_observer(None);
+        seen.lock().unwrap().clear();
+        manager.save_repository().unwrap();
+        assert!(seen.lock().unwrap().is_empty());
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::core::file_provider::MockFileProvider;
-    use crate::models::{CredentialField, CredentialRecord};
+    #[test]
+    fn test_key_file_is_part_of_the_archive_password() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.7z");
+        let path = path.to_str().unwrap();
+        let mut manager = UnifiedRepositoryManager::new(DesktopFileProvider::new());
+        let key_file = KeyFile::from_bytes(b"second factor").unwrap();
 
-    fn create_test_credential(title: &str) -> CredentialRecord {
-        let mut credential = CredentialRecord::new(title.to_string(), "test".to_string());
-        credential.set_field("username", CredentialField::username("testuser"));
-        credential.set_field("password", CredentialField::password("testpass"));
-        credential
+        manager
+            .create_repository_with_key_file(path, "password", &key_file)
+            .unwrap();
+        assert!(manager.uses_key_file());
+        assert_eq!(
+            manager.master_password.as_deref().map(String::as_str),
+            Some(key_file.composite_password("password").as_str())
+        );
+
+        // Changing the password keeps the key file requirement
+        manager
+            .change_master_password("password", "newpass")
+            .unwrap();
+        assert_eq!(
+            manager.master_password.as_deref().map(String::as_str),
+            Some(key_file.composite_password("newpass").as_str())
+        );
+
+        manager.close_repository(false).unwrap();
+        assert!(!manager.uses_key_file());
+
+        // The requirement is recorded next to the archive and checked on open
+        let config = RepositoryConfig::load_for(&manager.file_provider, path)
+            .unwrap()
+            .unwrap();
+        assert!(config.security.key_file_required);
+        assert_eq!(
+            manager.open_repository(path, "newpass"),
+            Err(CoreError::KeyFileRequired)
+        );
+        manager
+            .open_repository_with_key_file(path, "newpass", &key_file)
+            .unwrap();
+        assert!(manager.uses_key_file());
     }
 
     #[test]
-    fn test_repository_creation() {
+    fn test_security_key_unlock_with_password_fallback() {
+        use crate::core::errors::KeyProtectionResult;
+        use crate::core::keyprotection::{Fido2Protector, HmacSecretDevice};
+        use sha2::{Digest, Sha256};
+        use zeroize::Zeroizing;
+
+        struct TestKey(u8);
+        impl HmacSecretDevice for TestKey {
+            fn make_credential(&self, _: &str, _: &str) -> KeyProtectionResult<Vec<u8>> {
+                Ok(vec![self.0])
+            }
+            fn hmac_secret(
+                &self,
+                _: &str,
+                credential_id: &[u8],
+                salt: &[u8; 32],
+            ) -> KeyProtectionResult<Zeroizing<[u8; 32]>> {
+                let digest = Sha256::new()
+                    .chain_update([self.0])
+                    .chain_update(credential_id)
+                    .chain_update(salt)
+                    .finalize();
+                Ok(Zeroizing::new(digest.into()))
+            }
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.7z");
+        let path = path.to_str().unwrap();
+        let key = Fido2Protector::new(TestKey(1));
+        let mut manager = UnifiedRepositoryManager::new(DesktopFileProvider::new());
+
+        manager.create_repository(path, "password").unwrap();
+        assert!(matches!(
+            manager.register_key_protector(&key, " "),
+            Err(CoreError::ValidationError { .. })
+        ));
+        let record = manager.register_key_protector(&key, "YubiKey").unwrap();
+        assert_eq!(manager.key_protectors(path).unwrap(), vec![record.clone()]);
+        manager.close_repository(false).unwrap();
+
+        manager.open_repository_with_protector(path, &key).unwrap();
+        assert_eq!(
+            manager.master_password.as_deref().map(String::as_str),
+            Some("password")
+        );
+        manager.close_repository(false).unwrap();
+
+        // Another key fails and leaves the password as the way in
+        let other = Fido2Protector::new(TestKey(2));
+        assert_eq!(
+            manager.open_repository_with_protector(path, &other),
+            Err(CoreError::KeyProtection(KeyProtectionError::UnwrapFailed))
+        );
+        assert!(!manager.is_open());
+        manager.open_repository(path, "password").unwrap();
+
+        // Changing the password drops the now outdated protectors and says so
+        let removed = manager
+            .change_master_password("password", "newpass")
+            .unwrap();
+        assert_eq!(removed, vec![record]);
+        assert!(manager.key_protectors(path).unwrap().is_empty());
+        manager.close_repository(false).unwrap();
+        assert_eq!(
+            manager.open_repository_with_protector(path, &key),
+            Err(CoreError::KeyProtection(KeyProtectionError::NotRegistered))
+        );
+    }
+
+    #[test]
+    fn test_prepare_for_suspend_evicts_secrets() {
         let provider = MockFileProvider::new();
         let mut manager = UnifiedRepositoryManager::new(provider);
+        assert_eq!(manager.prepare_for_suspend().unwrap(), None);
+
+        manager.create_repository("/test.7z", "password").unwrap();
+        manager
+            .add_credential(create_test_credential("Test Credential"))
+            .unwrap();
 
+        assert_eq!(
+            manager.prepare_for_suspend().unwrap(),
+            Some("/test.7z".to_string())
+        );
         assert!(!manager.is_open());
-        assert!(manager.create_repository("/test.7z", "password").is_ok());
-        assert!(manager.is_open());
-        assert!(!manager.is_modified()); // Should be clean after creation and save
+        assert!(manager.master_password.is_none());
+        assert!(manager.list_credentials().is_err());
+        assert!(manager.save_repository().is_err());
+    }
+
+    #[test]
+    fn test_secure_store_survives_lock() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vault.7z");
+        let path = path.to_str().unwrap();
+        let mut manager = UnifiedRepositoryManager::new(DesktopFileProvider::new());
+        manager.set_secure_store_dir(Some(dir.path().join("state")));
+        manager.create_repository(path, "password").unwrap();
+        manager
+            .secure_store_mut()
+            .unwrap()
+            .set("search", &"github")
+            .unwrap();
+        manager.prepare_for_suspend().unwrap();
+        assert!(manager.secure_store().is_none());
+
+        manager.open_repository(path, "password").unwrap();
+        let store = manager.secure_store().unwrap();
+        assert_eq!(store.get::<String>("search").as_deref(), Some("github"));
+
+        // Still readable after the master password changes
+        manager
+            .change_master_password("password", "new password")
+            .unwrap();
+        manager.close_repository(false).unwrap();
+        manager.open_repository(path, "new password").unwrap();
+        let store = manager.secure_store().unwrap();
+        assert_eq!(store.get::<String>("search").as_deref(), Some("github"));
     }
 
     #[test]
@@ -563,16 +2726,92 @@ mod tests {
 
     #[test]
     fn test_change_master_password() {
-        let provider = MockFileProvider::new();
-        let mut manager = UnifiedRepositoryManager::new(provider);
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.7z");
+        let path = path.to_str().unwrap();
+        let mut manager = UnifiedRepositoryManager::new(DesktopFileProvider::new());
 
-        manager.create_repository("/test.7z", "oldpass").unwrap();
+        manager.create_repository(path, "oldpass").unwrap();
         manager
             .add_credential(create_test_credential("Test"))
             .unwrap();
 
-        assert!(manager.change_master_password("newpass").is_ok());
+        // The current password must be confirmed
+        assert_eq!(
+            manager.change_master_password("wrongpass", "newpass"),
+            Err(CoreError::FileOperation(FileError::InvalidPassword))
+        );
+        assert!(manager.is_modified());
+
+        let mut stages = Vec::new();
+        manager
+            .change_master_password_with_progress("oldpass", "newpass", |stage| stages.push(stage))
+            .unwrap();
+        assert_eq!(
+            stages,
+            vec![
+                PasswordChangeStage::Encrypting,
+                PasswordChangeStage::Writing,
+                PasswordChangeStage::Verifying,
+                PasswordChangeStage::Replacing,
+                PasswordChangeStage::Complete,
+            ]
+        );
         assert!(!manager.is_modified()); // Should be saved after password change
+
+        // The change is saved like any other save, keeping the old archive
+        let mut old = UnifiedRepositoryManager::new(DesktopFileProvider::new());
+        old.open_repository(&format!("{}.bak", path), "oldpass")
+            .unwrap();
+
+        // The container in memory is the one now on disk
+        let (container, _) = manager.container.as_ref().unwrap();
+        assert_eq!(container.to_bytes(), std::fs::read(path).unwrap());
+
+        manager.close_repository(false).unwrap();
+        assert!(manager.open_repository(path, "oldpass").is_err());
+        manager.open_repository(path, "newpass").unwrap();
+        assert_eq!(manager.get_stats().unwrap().credential_count, 1);
+    }
+
+    #[test]
+    fn test_failed_password_change_keeps_old_password() {
+        let provider = MockFileProvider::new();
+        let mut manager = UnifiedRepositoryManager::new(provider);
+        manager.create_repository("/test.7z", "oldpass").unwrap();
+
+        // The mock provider cannot read back the new archive
+        assert!(manager
+            .change_master_password("oldpass", "newpass")
+            .is_err());
+        assert_eq!(
+            manager.master_password.as_deref().map(String::as_str),
+            Some("oldpass")
+        );
+    }
+
+    #[test]
+    fn test_failed_password_change_keeps_the_archive() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.7z");
+        let path = path.to_str().unwrap();
+        let fail = Arc::new(AtomicBool::new(false));
+        let mut manager = UnifiedRepositoryManager::new(SaveFails {
+            inner: DesktopFileProvider::new(),
+            fail: fail.clone(),
+        });
+        manager.create_repository(path, "oldpass").unwrap();
+        let before = std::fs::read(path).unwrap();
+
+        fail.store(true, Ordering::SeqCst);
+        assert!(matches!(
+            manager.change_master_password("oldpass", "newpass"),
+            Err(CoreError::FileOperation(FileError::DiskFull { .. }))
+        ));
+        assert_eq!(std::fs::read(path).unwrap(), before);
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+        manager.close_repository(false).unwrap();
+        manager.open_repository(path, "oldpass").unwrap();
     }
 
     #[test]
@@ -606,15 +2845,531 @@ mod tests {
 
     #[test]
     fn test_verify_integrity() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vault.7z");
+        let path = path.to_str().unwrap();
+        let mut manager = UnifiedRepositoryManager::new(DesktopFileProvider::new());
+        assert!(manager.verify_integrity().is_err());
+
+        manager.create_repository(path, "password").unwrap();
+        manager
+            .add_credential(create_test_credential("Test"))
+            .unwrap();
+        manager.save_repository().unwrap();
+
+        let report = manager.verify_integrity().unwrap();
+        assert!(report.is_healthy(), "{:?}", report.issues);
+        assert_eq!(report.credentials_checked, 1);
+    }
+
+    #[test]
+    fn test_activity_log() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vault.7z");
+        let path = path.to_str().unwrap();
+        let mut manager = UnifiedRepositoryManager::new(DesktopFileProvider::new());
+        manager.set_device_name("laptop");
+        manager.create_repository(path, "password").unwrap();
+        let credential = create_test_credential("Test");
+        let id = credential.id.clone();
+        manager.add_credential(credential).unwrap();
+        manager.save_repository().unwrap();
+        manager.close_repository(false).unwrap();
+
+        manager.open_repository(path, "password").unwrap();
+        assert!(!manager.is_modified());
+        let actions: Vec<ActivityAction> = manager
+            .query_activity(&ActivityQuery::new())
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.action)
+            .collect();
+        assert_eq!(
+            actions,
+            [
+                ActivityAction::Saved,
+                ActivityAction::CredentialCreated,
+                ActivityAction::Saved,
+                ActivityAction::Opened,
+            ]
+        );
+        let about = manager
+            .query_activity(&ActivityQuery::new().with_credential_id(&id))
+            .unwrap();
+        assert_eq!(about.len(), 1);
+        assert_eq!(about[0].device, "laptop");
+        manager.verify_activity_log().unwrap();
+        assert!(manager.verify_integrity().unwrap().is_healthy());
+
+        // Switched off, nothing new is recorded but the log is kept
+        manager.set_activity_logging(false);
+        manager.delete_credential(&id).unwrap();
+        manager.save_repository().unwrap();
+        assert_eq!(
+            manager.query_activity(&ActivityQuery::new()).unwrap().len(),
+            4
+        );
+
+        let future = Utc::now().timestamp() + 60;
+        assert_eq!(manager.prune_activity_before(future).unwrap(), 4);
+        let remaining = manager.query_activity(&ActivityQuery::new()).unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].action, ActivityAction::Pruned);
+        manager.verify_activity_log().unwrap();
+    }
+
+    #[test]
+    fn test_change_events() {
+        use std::sync::{Arc, Mutex};
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vault.7z");
+        let path = path.to_str().unwrap();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let mut manager = UnifiedRepositoryManager::new(DesktopFileProvider::new());
+        let listener = manager.on_change({
+            let events = Arc::clone(&events);
+            move |event| events.lock().unwrap().push(event.clone())
+        });
+        let take = || std::mem::take(&mut *events.lock().unwrap());
+
+        manager.create_repository(path, "password").unwrap();
+        assert_eq!(take(), [RepositoryEvent::Opened, RepositoryEvent::Saved]);
+
+        let mut credential = create_test_credential("Test");
+        let id = credential.id.clone();
+        manager.add_credential(credential.clone()).unwrap();
+        credential.title = "Renamed".to_string();
+        manager.update_credential(credential).unwrap();
+        manager.delete_credential(&id).unwrap();
+        assert_eq!(
+            take(),
+            [
+                RepositoryEvent::CredentialAdded { id: id.clone() },
+                RepositoryEvent::CredentialUpdated { id: id.clone() },
+                RepositoryEvent::CredentialDeleted { id },
+            ]
+        );
+
+        // Listeners carry over to the next repository the manager opens
+        manager.close_repository(true).unwrap();
+        manager.open_repository(path, "password").unwrap();
+        assert_eq!(
+            take(),
+            [
+                RepositoryEvent::Saved,
+                RepositoryEvent::Locked,
+                RepositoryEvent::Opened
+            ]
+        );
+
+        assert!(manager.remove_listener(listener));
+        manager
+            .add_credential(create_test_credential("Quiet"))
+            .unwrap();
+        assert!(take().is_empty());
+    }
+
+    #[test]
+    fn test_archive_options() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vault.7z");
+        let path = path.to_str().unwrap();
+
+        let mut manager = UnifiedRepositoryManager::new(DesktopFileProvider::new());
+        assert!(manager
+            .set_archive_options(ArchiveOptions::new().with_compression_level(10))
+            .is_err());
+        manager
+            .set_archive_options(
+                ArchiveOptions::new()
+                    .with_compression_level(0)
+                    .with_solid(true)
+                    .with_encrypt_header(false),
+            )
+            .unwrap();
+        manager.set_incremental_saves(true);
+        manager.create_repository(path, "password").unwrap();
+        let mut credential = CredentialRecord::new("Notes".to_string(), "note".to_string());
+        credential.notes = Some("stored ".repeat(100));
+        manager.add_credential(credential).unwrap();
+        manager.save_repository().unwrap();
+
+        // Stored, not compressed
+        let compressed = dir.path().join("compressed.7z");
+        let compressed = compressed.to_str().unwrap();
+        manager.set_archive_options(ArchiveOptions::new()).unwrap();
+        manager
+            .save_repository_to_path(compressed, "password")
+            .unwrap();
+        manager.close_repository(false).unwrap();
+        let size = |path: &str| std::fs::metadata(path).unwrap().len();
+        assert!(size(path) > size(compressed));
+
+        let mut manager = UnifiedRepositoryManager::new(DesktopFileProvider::new());
+        assert!(manager.open_repository(path, "wrong").is_err());
+        manager.open_repository(path, "password").unwrap();
+        assert_eq!(manager.list_credentials().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_changes_made_elsewhere() {
+        use std::sync::mpsc;
+        use std::time::Duration;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vault.7z");
+        let path = path.to_str().unwrap();
+        let edit_elsewhere = |title: &str| {
+            let mut other = UnifiedRepositoryManager::new(DesktopFileProvider::new());
+            other.open_repository(path, "password").unwrap();
+            other.add_credential(create_test_credential(title)).unwrap();
+            other.close_repository(true).unwrap();
+        };
+
+        let mut manager = UnifiedRepositoryManager::new(DesktopFileProvider::new());
+        let (sender, changes) = mpsc::channel();
+        manager.on_change(move |event| {
+            if *event == RepositoryEvent::ArchiveChanged {
+                let _ = sender.send(());
+            }
+        });
+        manager.set_watch_archive(true);
+        manager.create_repository(path, "password").unwrap();
+        manager
+            .add_credential(create_test_credential("Ours"))
+            .unwrap();
+        manager.save_repository().unwrap();
+        assert!(!manager.has_changes_on_disk().unwrap());
+
+        // Saving over a copy written by another device would lose it
+        edit_elsewhere("Theirs");
+        assert!(changes.recv_timeout(Duration::from_secs(10)).is_ok());
+        assert!(manager.has_changes_on_disk().unwrap());
+        manager
+            .add_credential(create_test_credential("Later"))
+            .unwrap();
+        assert!(matches!(
+            manager.save_repository(),
+            Err(CoreError::FileOperation(FileError::Conflict { .. }))
+        ));
+
+        let mut merge = manager.merge_from_disk().unwrap();
+        merge.resolve_all_suggested();
+        merge.apply_to(&mut manager).unwrap();
+        manager.save_repository().unwrap();
+        let mut titles: Vec<String> = manager
+            .list_credentials()
+            .unwrap()
+            .into_iter()
+            .map(|c| c.title)
+            .collect();
+        titles.sort();
+        assert_eq!(titles, ["Later", "Ours", "Theirs"]);
+
+        edit_elsewhere("Newest");
+        manager
+            .add_credential(create_test_credential("Dropped"))
+            .unwrap();
+        manager.reload_from_disk().unwrap();
+        assert!(!manager.is_modified());
+        assert_eq!(manager.list_credentials().unwrap().len(), 4);
+        assert!(manager.search_credentials("Dropped").unwrap().is_empty());
+
+        edit_elsewhere("Overwritten");
+        manager.keep_local_changes().unwrap();
+        manager.save_repository().unwrap();
+        assert_eq!(manager.list_credentials().unwrap().len(), 4);
+    }
+
+    #[test]
+    fn test_open_fixes_benign_issues() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vault.7z");
+        let path = path.to_str().unwrap();
+        let provider = DesktopFileProvider::new();
+
+        let mut manager = UnifiedRepositoryManager::new(DesktopFileProvider::new());
+        manager.create_repository(path, "password").unwrap();
+        manager
+            .add_credential(create_test_credential("Test"))
+            .unwrap();
+        manager.close_repository(true).unwrap();
+
+        // A stray file another tool put into the archive
+        let mut files = provider
+            .extract_archive(&provider.read_archive(path).unwrap(), "password")
+            .unwrap();
+        files.insert("thumbs.db".to_string(), b"stray".to_vec());
+        let data = provider.create_archive(files, "password").unwrap();
+        provider.write_archive(path, &data).unwrap();
+
+        manager.open_repository(path, "password").unwrap();
+        assert_eq!(
+            manager.repair_log(),
+            &[IntegrityIssue::OrphanedEntry {
+                path: "thumbs.db".to_string()
+            }]
+        );
+        assert!(manager.is_modified());
+        manager.save_repository().unwrap();
+        assert!(manager.verify_integrity().unwrap().is_healthy());
+        manager.close_repository(false).unwrap();
+        assert!(manager.repair_log().is_empty());
+
+        manager.open_repository(path, "password").unwrap();
+        assert!(manager.repair_log().is_empty());
+        assert!(manager
+            .memory_repository()
+            .quarantined_entries()
+            .contains_key("quarantine/thumbs.db"));
+    }
+
+    #[test]
+    fn test_backups_after_save_and_restore() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vault.7z");
+        let path = path.to_str().unwrap();
+        let mut manager = UnifiedRepositoryManager::new(DesktopFileProvider::new());
+        manager.set_backup_scheduler(Some(BackupScheduler::new(dir.path().join("backups"))));
+
+        manager.create_repository(path, "password").unwrap();
+        manager
+            .add_credential(create_test_credential("First"))
+            .unwrap();
+        manager.save_repository().unwrap();
+        let backups = manager.list_backups().unwrap();
+        assert!(!backups.is_empty());
+        assert!(manager.backup_scheduler().unwrap().last_error().is_none());
+
+        // Saves within the same second share a backup name, so keep a copy
+        let one_credential = dir.path().join("one.7z");
+        std::fs::copy(&backups[0].path, &one_credential).unwrap();
+        let one_credential = one_credential.to_str().unwrap();
+
+        manager
+            .add_credential(create_test_credential("Second"))
+            .unwrap();
+        manager.save_repository().unwrap();
+        assert_eq!(manager.list_credentials().unwrap().len(), 2);
+
+        assert!(manager.restore_backup(one_credential, "wrong").is_err());
+        assert_eq!(manager.list_credentials().unwrap().len(), 2);
+
+        manager.restore_backup(one_credential, "password").unwrap();
+        let credentials = manager.list_credentials().unwrap();
+        assert_eq!(credentials.len(), 1);
+        assert_eq!(credentials[0].title, "First");
+        assert!(!manager.is_modified());
+    }
+
+    #[test]
+    fn test_repair_opens_archive_with_corrupt_entry() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vault.7z");
+        let path = path.to_str().unwrap();
+        let provider = DesktopFileProvider::new();
+
+        let mut manager = UnifiedRepositoryManager::new(DesktopFileProvider::new());
+        manager.create_repository(path, "password").unwrap();
+        manager
+            .add_credential(create_test_credential("Test"))
+            .unwrap();
+        manager.save_repository().unwrap();
+        manager.close_repository(false).unwrap();
+
+        // Corrupt the archive by adding an unreadable credential file
+        let mut files = provider
+            .extract_archive(&provider.read_archive(path).unwrap(), "password")
+            .unwrap();
+        files.insert(
+            "credentials/broken/record.yml".to_string(),
+            b"fields: [".to_vec(),
+        );
+        let data = provider.create_archive(files, "password").unwrap();
+        provider.write_archive(path, &data).unwrap();
+
+        assert!(manager.open_repository(path, "password").is_err());
+        let report = manager.verify_archive(path, "password").unwrap();
+        assert!(report.needs_repair());
+
+        let report = manager.repair(path, "password").unwrap();
+        assert_eq!(
+            report.quarantined,
+            vec!["quarantine/credentials/broken/record.yml".to_string()]
+        );
+        assert_eq!(manager.list_credentials().unwrap().len(), 1);
+        assert!(manager.verify_integrity().unwrap().is_healthy());
+        manager.close_repository(false).unwrap();
+
+        // The repaired archive opens normally and still has the entry
+        manager.open_repository(path, "password").unwrap();
+        assert_eq!(
+            manager.memory_repository().quarantined_entries()
+                ["quarantine/credentials/broken/record.yml"],
+            b"fields: [".to_vec()
+        );
+    }
+
+    #[test]
+    fn test_security_audit() {
         let provider = MockFileProvider::new();
         let mut manager = UnifiedRepositoryManager::new(provider);
+        assert!(manager.security_audit(AuditOptions::default()).is_err());
 
         manager.create_repository("/test.7z", "password").unwrap();
         manager
-            .add_credential(create_test_credential("Test"))
+            .add_credential(create_test_credential("One"))
+            .unwrap();
+        manager
+            .add_credential(create_test_credential("Two"))
+            .unwrap();
+
+        let report = manager.security_audit(AuditOptions::default()).unwrap();
+        assert_eq!(report.total_credentials, 2);
+        assert_eq!(report.reused.len(), 1);
+        assert_eq!(report.weak.len(), 2);
+    }
+
+    #[cfg(feature = "automation")]
+    #[test]
+    fn test_automation_hooks() {
+        use crate::utils::automation::{AutomationEngine, AutomationEvent, AutomationScript};
+
+        let provider = MockFileProvider::new();
+        let mut manager = UnifiedRepositoryManager::new(provider);
+        manager.create_repository("/test.7z", "password").unwrap();
+        let engine = AutomationEngine::from_scripts(&[
+            AutomationScript::new(
+                "prefix",
+                AutomationEvent::CredentialCreated,
+                r#"credential.title = "Team - " + credential.title; warn("renamed");"#,
+            ),
+            AutomationScript::new(
+                "no-drafts",
+                AutomationEvent::BeforeSave,
+                r#"if credential.title.contains("DRAFT") { reject("drafts cannot be saved"); }"#,
+            ),
+            AutomationScript::new(
+                "audit",
+                AutomationEvent::AuditCompleted,
+                r#"warn(`${audit.total_credentials} audited`);"#,
+            ),
+        ])
+        .unwrap();
+        manager.set_automation(Some(engine));
+
+        let credential = create_test_credential("One");
+        let id = credential.id.clone();
+        manager.add_credential(credential).unwrap();
+        assert_eq!(manager.get_credential(&id).unwrap().title, "Team - One");
+        assert_eq!(manager.take_automation_warnings().len(), 1);
+        assert!(manager.take_automation_warnings().is_empty());
+        manager.save_repository().unwrap();
+
+        manager
+            .add_credential(create_test_credential("DRAFT"))
+            .unwrap();
+        let error = manager.save_repository().unwrap_err();
+        assert!(error.to_string().contains("drafts cannot be saved"));
+
+        let report = manager.security_audit(AuditOptions::default()).unwrap();
+        assert_eq!(report.script_findings[0].message, "2 audited");
+    }
+
+    #[test]
+    fn test_compliance_report() {
+        let provider = MockFileProvider::new();
+        let mut manager = UnifiedRepositoryManager::new(provider);
+        assert!(manager.compliance_report(AuditOptions::default()).is_err());
+
+        manager.create_repository("/test.7z", "password").unwrap();
+        let mut credential = create_test_credential("Payroll");
+        credential.compliance_mut().owner = Some("Finance".to_string());
+        manager.add_credential(credential).unwrap();
+
+        // Compliance metadata survives a round trip through the file format
+        let file_map = manager.export_to_file_map().unwrap();
+        let mut manager = UnifiedRepositoryManager::new(MockFileProvider::new());
+        manager.import_from_file_map(file_map).unwrap();
+
+        let report = manager.compliance_report(AuditOptions::default()).unwrap();
+        assert_eq!(report.entries.len(), 1);
+        assert_eq!(report.entries[0].owner.as_deref(), Some("Finance"));
+        assert_eq!(report.never_reviewed_count, 1);
+    }
+
+    #[test]
+    fn test_reminders() {
+        let provider = MockFileProvider::new();
+        let mut manager = UnifiedRepositoryManager::new(provider);
+        assert!(manager.reminders(&ReminderEngine::new()).is_err());
+
+        manager.create_repository("/test.7z", "password").unwrap();
+        let now = Utc::now().timestamp();
+        let mut certificate = create_test_credential("TLS certificate");
+        certificate.expires_at = Some(now + 7 * 86_400);
+        manager.add_credential(certificate).unwrap();
+        manager
+            .add_credential(create_test_credential("Unchanging"))
+            .unwrap();
+
+        // The expiry date survives a round trip through the file format
+        let file_map = manager.export_to_file_map().unwrap();
+        let mut manager = UnifiedRepositoryManager::new(MockFileProvider::new());
+        manager.import_from_file_map(file_map).unwrap();
+
+        let reminders = manager
+            .reminders(&ReminderEngine::new().with_now(now))
+            .unwrap();
+        assert_eq!(reminders.len(), 1);
+        assert_eq!(reminders[0].title, "TLS certificate");
+        assert_eq!(reminders[0].days_left, 7);
+    }
+
+    #[test]
+    fn test_folder_defaults() {
+        let provider = MockFileProvider::new();
+        let mut manager = UnifiedRepositoryManager::new(provider);
+        assert!(manager.credential_form_schema(None, None).is_err());
+
+        manager.create_repository("/test.7z", "password").unwrap();
+        manager
+            .set_folder_defaults(
+                "/Work/",
+                FolderDefaults {
+                    icon: Some("server".to_string()),
+                    color: Some("#1e88e5".to_string()),
+                    template: Some("database".to_string()),
+                },
+            )
+            .unwrap();
+        assert!(manager
+            .set_folder_defaults("", FolderDefaults::default())
+            .is_err());
+
+        // Folder defaults are stored with the repository
+        let file_map = manager.export_to_file_map().unwrap();
+        let mut manager = UnifiedRepositoryManager::new(MockFileProvider::new());
+        manager.import_from_file_map(file_map).unwrap();
+
+        let schema = manager
+            .credential_form_schema(Some("Work/DB"), None)
             .unwrap();
+        assert_eq!(schema.template.name, "database");
+        assert_eq!(schema.icon.as_deref(), Some("server"));
 
-        let issues = manager.verify_integrity().unwrap();
-        assert!(issues.is_empty()); // Should have no integrity issues
+        let mut credential = create_test_credential("Postgres");
+        credential.folder_path = Some("Work/DB".to_string());
+        let id = credential.id.clone();
+        manager.add_credential(credential).unwrap();
+        let stored = manager.get_credential_readonly(&id).unwrap();
+        assert_eq!(stored.color.as_deref(), Some("#1e88e5"));
+
+        // Empty defaults clear the folder
+        manager
+            .set_folder_defaults("Work", FolderDefaults::default())
+            .unwrap();
+        assert!(manager.memory_repository().folder_defaults().is_empty());
     }
 }
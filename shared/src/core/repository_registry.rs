@@ -0,0 +1,364 @@
+//! Several open repositories in one process
+//!
+//! [`RepositoryRegistry`] keeps any number of
+//! [`UnifiedRepositoryManager`]s open at once under IDs chosen by the app,
+//! such as `personal` and `work`. Each repository has its own lock, so a
+//! slow save of one vault does not hold up reads from another, and
+//! [`RepositoryRegistry::search`] looks through all of them and merges the
+//! results. The same archive cannot be open under two IDs, since two
+//! managers saving one file would overwrite each other's changes.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use crate::core::errors::{CoreError, CoreResult};
+use crate::core::file_provider::FileOperationProvider;
+use crate::core::repository_manager::UnifiedRepositoryManager;
+use crate::models::CredentialRecord;
+
+/// One open repository, locked separately from the others
+pub type SharedRepository<F> = Arc<Mutex<UnifiedRepositoryManager<F>>>;
+
+/// A search match and the repository it came from
+#[derive(Debug, Clone)]
+pub struct RegistryHit {
+    pub repository_id: String,
+    pub credential: CredentialRecord,
+    /// Higher is better; repositories score with the same rules, so hits
+    /// from different vaults can be compared
+    pub score: f64,
+}
+
+/// A registered repository and the archive it was opened from
+struct Registered<F: FileOperationProvider> {
+    path: Option<String>,
+    repository: SharedRepository<F>,
+}
+
+type Repositories<F> = BTreeMap<String, Registered<F>>;
+
+/// Open repositories keyed by ID
+pub struct RepositoryRegistry<F: FileOperationProvider> {
+    repositories: RwLock<Repositories<F>>,
+}
+
+impl<F: FileOperationProvider> Default for RepositoryRegistry<F> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<F: FileOperationProvider> RepositoryRegistry<F> {
+    /// An empty registry
+    pub fn new() -> Self {
+        Self {
+            repositories: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    /// Open the archive at `path` and keep it under `id`
+    pub fn open(&self, id: &str, file_provider: F, path: &str, password: &str) -> CoreResult<()> {
+        self.check_available(id, path)?;
+        let mut manager = UnifiedRepositoryManager::new(file_provider);
+        manager.open_repository(path, password)?;
+        self.insert(id, manager)
+    }
+
+    /// Create a new archive at `path` and keep it under `id`
+    pub fn create(&self, id: &str, file_provider: F, path: &str, password: &str) -> CoreResult<()> {
+        self.check_available(id, path)?;
+        let mut manager = UnifiedRepositoryManager::new(file_provider);
+        manager.create_repository(path, password)?;
+        self.insert(id, manager)
+    }
+
+    /// Keep an already open manager under `id`, e.g. one opened with a key
+    /// file or security key
+    pub fn insert(&self, id: &str, manager: UnifiedRepositoryManager<F>) -> CoreResult<()> {
+        if !manager.is_open() {
+            return Err(CoreError::NotInitialized);
+        }
+        let path = manager.current_path().map(str::to_string);
+        let mut repositories = self.write()?;
+        Self::check_free(&repositories, id, path.as_deref())?;
+        repositories.insert(
+            id.to_string(),
+            Registered {
+                path,
+                repository: Arc::new(Mutex::new(manager)),
+            },
+        );
+        Ok(())
+    }
+
+    /// The repository under `id`, to lock and use directly
+    pub fn get(&self, id: &str) -> CoreResult<SharedRepository<F>> {
+        self.read()?
+            .get(id)
+            .map(|registered| Arc::clone(&registered.repository))
+            .ok_or_else(|| unknown(id))
+    }
+
+    /// Run `operation` on the repository under `id` while holding its lock
+    pub fn with_repository<T>(
+        &self,
+        id: &str,
+        operation: impl FnOnce(&mut UnifiedRepositoryManager<F>) -> CoreResult<T>,
+    ) -> CoreResult<T> {
+        let repository = self.get(id)?;
+        let mut manager = lock(&repository)?;
+        operation(&mut manager)
+    }
+
+    /// IDs of the open repositories, in order
+    pub fn ids(&self) -> Vec<String> {
+        self.read()
+            .map(|repositories| repositories.keys().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Whether a repository is open under `id`
+    pub fn contains(&self, id: &str) -> bool {
+        self.read()
+            .map(|repositories| repositories.contains_key(id))
+            .unwrap_or(false)
+    }
+
+    /// Number of open repositories
+    pub fn len(&self) -> usize {
+        self.read()
+            .map(|repositories| repositories.len())
+            .unwrap_or(0)
+    }
+
+    /// Whether no repository is open
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// IDs of the repositories with unsaved changes
+    pub fn modified(&self) -> CoreResult<Vec<String>> {
+        let mut modified = Vec::new();
+        for (id, repository) in self.snapshot()? {
+            if lock(&repository)?.is_modified() {
+                modified.push(id);
+            }
+        }
+        Ok(modified)
+    }
+
+    /// Search every open repository, best matches first
+    ///
+    /// Repositories are locked one at a time, so a search never waits for
+    /// more than one of them at once. Equal scores keep the order of the
+    /// repository IDs.
+    pub fn search(&self, query: &str) -> CoreResult<Vec<RegistryHit>> {
+        let mut hits = Vec::new();
+        for (id, repository) in self.snapshot()? {
+            let found = lock(&repository)?.search_credentials_scored(query)?;
+            hits.extend(found.into_iter().map(|(credential, score)| RegistryHit {
+                repository_id: id.clone(),
+                credential,
+                score,
+            }));
+        }
+        hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+        Ok(hits)
+    }
+
+    /// Save every repository with unsaved changes
+    ///
+    /// Continues past failures and returns the first one.
+    pub fn save_all(&self) -> CoreResult<()> {
+        let mut first_error = None;
+        for (_, repository) in self.snapshot()? {
+            let mut manager = lock(&repository)?;
+            if manager.is_modified() {
+                if let Err(e) = manager.save_repository() {
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        first_error.map_or(Ok(()), Err)
+    }
+
+    /// Close the repository under `id` and remove it from the registry
+    ///
+    /// If closing fails, for example because saving failed, the repository
+    /// stays open and registered.
+    pub fn close(&self, id: &str, save_if_modified: bool) -> CoreResult<()> {
+        let repository = self.get(id)?;
+        lock(&repository)?.close_repository(save_if_modified)?;
+        self.write()?.remove(id);
+        Ok(())
+    }
+
+    /// Close every repository; returns the first failure after trying all
+    pub fn close_all(&self, save_if_modified: bool) -> CoreResult<()> {
+        let mut first_error = None;
+        for id in self.ids() {
+            if let Err(e) = self.close(&id, save_if_modified) {
+                first_error.get_or_insert(e);
+            }
+        }
+        first_error.map_or(Ok(()), Err)
+    }
+
+    fn check_available(&self, id: &str, path: &str) -> CoreResult<()> {
+        let repositories = self.read()?;
+        Self::check_free(&repositories, id, Some(path))
+    }
+
+    fn check_free(repositories: &Repositories<F>, id: &str, path: Option<&str>) -> CoreResult<()> {
+        if repositories.contains_key(id) {
+            return Err(CoreError::ValidationError {
+                message: format!("A repository is already open as '{}'", id),
+            });
+        }
+        if let Some((other_id, _)) = repositories
+            .iter()
+            .find(|(_, registered)| path.is_some() && registered.path.as_deref() == path)
+        {
+            return Err(CoreError::ValidationError {
+                message: format!("This archive is already open as '{}'", other_id),
+            });
+        }
+        Ok(())
+    }
+
+    /// The registered repositories, so their locks can be taken without
+    /// holding the registry's
+    fn snapshot(&self) -> CoreResult<Vec<(String, SharedRepository<F>)>> {
+        Ok(self
+            .read()?
+            .iter()
+            .map(|(id, registered)| (id.clone(), Arc::clone(&registered.repository)))
+            .collect())
+    }
+
+    fn read(&self) -> CoreResult<RwLockReadGuard<'_, Repositories<F>>> {
+        self.repositories.read().map_err(|_| poisoned())
+    }
+
+    fn write(&self) -> CoreResult<RwLockWriteGuard<'_, Repositories<F>>> {
+        self.repositories.write().map_err(|_| poisoned())
+    }
+}
+
+fn lock<F: FileOperationProvider>(
+    repository: &SharedRepository<F>,
+) -> CoreResult<MutexGuard<'_, UnifiedRepositoryManager<F>>> {
+    repository.lock().map_err(|_| poisoned())
+}
+
+fn poisoned() -> CoreError {
+    CoreError::InternalError {
+        message: "Repository lock poisoned".to_string(),
+    }
+}
+
+fn unknown(id: &str) -> CoreError {
+    CoreError::ValidationError {
+        message: format!("No repository is open as '{}'", id),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::file_provider::MockFileProvider;
+    use crate::models::CredentialField;
+
+    fn login(title: &str) -> CredentialRecord {
+        let mut credential = CredentialRecord::new(title.to_string(), "login".to_string());
+        credential.set_field("username", CredentialField::username("me"));
+        credential
+    }
+
+    fn registry() -> RepositoryRegistry<MockFileProvider> {
+        let registry = RepositoryRegistry::new();
+        for (id, path, title) in [
+            ("work", "/work.7z", "GitHub work"),
+            ("personal", "/personal.7z", "GitHub personal"),
+        ] {
+            registry
+                .create(id, MockFileProvider::new(), path, "password")
+                .unwrap();
+            registry
+                .with_repository(id, |manager| manager.add_credential(login(title)))
+                .unwrap();
+        }
+        registry
+    }
+
+    #[test]
+    fn test_combined_search() {
+        let registry = registry();
+        assert_eq!(registry.ids(), ["personal", "work"]);
+        registry
+            .with_repository("work", |manager| manager.add_credential(login("Jira")))
+            .unwrap();
+
+        let hits = registry.search("github").unwrap();
+        let mut found: Vec<(&str, &str)> = hits
+            .iter()
+            .map(|hit| (hit.repository_id.as_str(), hit.credential.title.as_str()))
+            .collect();
+        found.sort();
+        assert_eq!(
+            found,
+            [("personal", "GitHub personal"), ("work", "GitHub work")]
+        );
+
+        let hits = registry.search("jira").unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].repository_id, "work");
+    }
+
+    #[test]
+    fn test_ids_and_paths_are_unique() {
+        let registry = registry();
+        assert!(matches!(
+            registry.create("work", MockFileProvider::new(), "/other.7z", "password"),
+            Err(CoreError::ValidationError { .. })
+        ));
+        assert!(matches!(
+            registry.create("again", MockFileProvider::new(), "/work.7z", "password"),
+            Err(CoreError::ValidationError { .. })
+        ));
+        assert!(registry
+            .insert(
+                "closed",
+                UnifiedRepositoryManager::new(MockFileProvider::new())
+            )
+            .is_err());
+        assert_eq!(registry.len(), 2);
+    }
+
+    #[test]
+    fn test_locks_are_per_repository() {
+        let registry = registry();
+        let work = registry.get("work").unwrap();
+        let _held = work.lock().unwrap();
+
+        // Another vault stays usable while one is locked
+        let count = registry
+            .with_repository("personal", |manager| Ok(manager.list_credentials()?.len()))
+            .unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn test_save_and_close() {
+        let registry = registry();
+        assert_eq!(registry.modified().unwrap(), ["personal", "work"]);
+        registry.save_all().unwrap();
+        assert!(registry.modified().unwrap().is_empty());
+
+        registry.close("work", false).unwrap();
+        assert!(!registry.contains("work"));
+        assert!(registry.get("work").is_err());
+        registry.close_all(false).unwrap();
+        assert!(registry.is_empty());
+    }
+}
@@ -0,0 +1,341 @@
+//! Known-answer tests of the cryptography the library relies on
+//!
+//! A miscompiled or tampered build can still open and save vaults while
+//! producing keys or codes nobody else can reproduce. The self-test runs the
+//! primitives against published vectors (FIPS-197 for AES, FIPS 180 for
+//! SHA-256, RFC 8439 for ChaCha20-Poly1305, RFC 6238 for TOTP), opens an
+//! archive built by a known-good release, and checks the security
+//! parameters compiled into the library. Apps run it at startup; a strict
+//! mode can refuse to open vaults until it has passed, see [`ensure`].
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use std::time::Instant;
+
+use aes::cipher::BlockEncrypt;
+use aes::Aes256;
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::core::archive_backend::{ArchiveBackend, SevenZipBackend};
+use crate::core::errors::{CoreError, CoreResult};
+use crate::models::CREDENTIAL_SCHEMA_VERSION;
+use crate::utils::encryption::{EncryptionUtils, AES_KEY_SIZE, PBKDF2_ITERATIONS, SALT_SIZE};
+use crate::utils::totp::{TotpAlgorithm, TotpGenerator};
+
+/// A single known-answer test; `Err` explains what differed
+type Check = fn() -> Result<(), String>;
+
+/// Outcome of one check
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SelfTestCheck {
+    pub name: String,
+    pub passed: bool,
+    /// What differed, for failed checks
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+/// Outcome of a full self-test run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SelfTestReport {
+    /// Library version that ran the checks
+    pub version: String,
+    pub checks: Vec<SelfTestCheck>,
+    pub duration_ms: u64,
+}
+
+impl SelfTestReport {
+    /// Whether every check passed
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|check| check.passed)
+    }
+
+    /// Checks that failed
+    pub fn failures(&self) -> impl Iterator<Item = &SelfTestCheck> {
+        self.checks.iter().filter(|check| !check.passed)
+    }
+}
+
+/// Run every check and report each one
+pub fn run() -> SelfTestReport {
+    let started = Instant::now();
+    let checks: [(&str, Check); 8] = [
+        ("aes-256", check_aes),
+        ("sha-256", check_sha256),
+        ("key-derivation", check_key_derivation),
+        ("chacha20-poly1305", check_chacha20_poly1305),
+        ("totp", check_totp),
+        ("archive", check_archive),
+        ("random", check_random),
+        ("build-constants", check_build_constants),
+    ];
+    let checks = checks
+        .into_iter()
+        .map(|(name, check)| {
+            // A panicking primitive is a failed check, not a crashed app
+            let result =
+                std::panic::catch_unwind(check).unwrap_or_else(|_| Err("panicked".to_string()));
+            SelfTestCheck {
+                name: name.to_string(),
+                passed: result.is_ok(),
+                detail: result.err(),
+            }
+        })
+        .collect();
+
+    SelfTestReport {
+        version: crate::VERSION.to_string(),
+        checks,
+        duration_ms: started.elapsed().as_millis() as u64,
+    }
+}
+
+/// Run the self-test once per process and fail if it did not pass
+///
+/// Later calls return the first outcome without running the checks again.
+pub fn ensure() -> CoreResult<()> {
+    static OUTCOME: OnceLock<Result<(), String>> = OnceLock::new();
+    OUTCOME
+        .get_or_init(|| {
+            let report = run();
+            if report.passed() {
+                return Ok(());
+            }
+            let failed: Vec<String> = report
+                .failures()
+                .map(|check| match &check.detail {
+                    Some(detail) => format!("{} ({})", check.name, detail),
+                    None => check.name.clone(),
+                })
+                .collect();
+            Err(failed.join(", "))
+        })
+        .clone()
+        .map_err(|failed| CoreError::InternalError {
+            message: format!("Cryptographic self-test failed: {}", failed),
+        })
+}
+
+fn expect(what: &str, actual: &[u8], expected: &str) -> Result<(), String> {
+    let actual = EncryptionUtils::to_hex(actual);
+    if actual == expected {
+        Ok(())
+    } else {
+        Err(format!("{}: expected {}, got {}", what, expected, actual))
+    }
+}
+
+pub(crate) fn from_hex(hex: &str) -> Vec<u8> {
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).expect("valid test vector"))
+        .collect()
+}
+
+/// FIPS-197 appendix C.3, the block cipher inside 7z's AES-256-CBC
+fn check_aes() -> Result<(), String> {
+    let key = from_hex("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f");
+    let mut block = aes::Block::clone_from_slice(&from_hex("00112233445566778899aabbccddeeff"));
+    Aes256::new_from_slice(&key)
+        .map_err(|e| e.to_string())?
+        .encrypt_block(&mut block);
+    expect("ciphertext", &block, "8ea2b7ca516745bfeafc49904b496089")
+}
+
+/// FIPS 180-2 appendix B.1 and B.2
+fn check_sha256() -> Result<(), String> {
+    expect(
+        "SHA-256(\"abc\")",
+        &Sha256::digest(b"abc"),
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+    )?;
+    expect(
+        "SHA-256 of two blocks",
+        &Sha256::digest(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"),
+        "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1",
+    )
+}
+
+/// The key protected fields are wrapped with; a regression vector, since
+/// changing the derivation would lock users out of those fields
+fn check_key_derivation() -> Result<(), String> {
+    let key = EncryptionUtils::derive_key("password", &[0u8; 16]).map_err(|e| e.to_string())?;
+    expect(
+        "derived key",
+        key.expose(),
+        "6a54f635fa686f45649b1dc441513812e1aa3710d1d4c8cee1288e918bae63b6",
+    )
+}
+
+/// RFC 8439 section 2.8.2
+fn check_chacha20_poly1305() -> Result<(), String> {
+    let key = from_hex("808182838485868788898a8b8c8d8e8f909192939495969798999a9b9c9d9e9f");
+    let nonce = from_hex("070000004041424344454647");
+    let aad = from_hex("50515253c0c1c2c3c4c5c6c7");
+    let plaintext: &[u8] = b"Ladies and Gentlemen of the class of '99: If I could offer you \
+only one tip for the future, sunscreen would be it.";
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(&key));
+    let sealed = cipher
+        .encrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: plaintext,
+                aad: &aad,
+            },
+        )
+        .map_err(|e| e.to_string())?;
+    let (ciphertext, tag) = sealed.split_at(plaintext.len());
+    expect(
+        "first ciphertext bytes",
+        &ciphertext[..16],
+        "d31a8d34648e60db7b86afbc53ef7ec2",
+    )?;
+    expect("tag", tag, "1ae10b594f09e26a7e902ecbd0600691")?;
+
+    let mut tampered = sealed.clone();
+    tampered[0] ^= 1;
+    let forged = cipher.decrypt(
+        Nonce::from_slice(&nonce),
+        Payload {
+            msg: &tampered,
+            aad: &aad,
+        },
+    );
+    if forged.is_ok() {
+        return Err("accepted a tampered ciphertext".to_string());
+    }
+    Ok(())
+}
+
+/// RFC 6238 appendix B
+fn check_totp() -> Result<(), String> {
+    let vectors = [
+        (TotpAlgorithm::Sha1, 20, 59, "94287082"),
+        (TotpAlgorithm::Sha1, 20, 1111111109, "07081804"),
+        (TotpAlgorithm::Sha256, 32, 59, "46119246"),
+        (TotpAlgorithm::Sha256, 32, 1111111109, "68084774"),
+        (TotpAlgorithm::Sha512, 64, 59, "90693936"),
+        (TotpAlgorithm::Sha512, 64, 1111111109, "25091201"),
+    ];
+    for (algorithm, secret_len, time, expected) in vectors {
+        let secret: Vec<u8> = b"1234567890"
+            .iter()
+            .cycle()
+            .take(secret_len)
+            .copied()
+            .collect();
+        let code = TotpGenerator::from_bytes(secret)
+            .with_algorithm(algorithm)
+            .with_digits(8)
+            .and_then(|totp| totp.generate_at(time))
+            .map_err(|e| e.to_string())?;
+        if code != expected {
+            return Err(format!(
+                "{} at {}: expected {}, got {}",
+                algorithm.name(),
+                time,
+                expected,
+                code
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Password of [`KNOWN_ARCHIVE`]
+pub(crate) const KNOWN_ARCHIVE_PASSWORD: &str = "ziplock-self-test";
+
+/// A 7z archive written by ZipLock 0.4.0 holding `self-test.txt`, so the 7z
+/// key derivation and AES-256-CBC are checked together as vaults use them
+pub(crate) const KNOWN_ARCHIVE: &str = "\
+    377abcaf271c00025b66fd2b20000000000000007600000000000000f8ff1393dffccef216ed5687255f2dd8\
+    a4f6e387469c9974ec083c714170ae6e6f37ac75010406000109200a01368e664200070b0100022406f10701\
+    22c8ff45e24f7f1c5f9f13afd6c5e35ca2ff608d4d4b106092f3b3313c9a75c5e632d52121011601000c1511\
+    0a01c79fed7100080d01090a0100000501111d00730065006c0066002d0074006500730074002e0074007800\
+    740000000000";
+
+/// Contents of `self-test.txt` in [`KNOWN_ARCHIVE`]
+pub(crate) const KNOWN_ARCHIVE_CONTENT: &[u8] = b"ZipLock self-test";
+
+fn check_archive() -> Result<(), String> {
+    let backend = SevenZipBackend;
+    let files = backend
+        .extract(&from_hex(KNOWN_ARCHIVE), KNOWN_ARCHIVE_PASSWORD)
+        .map_err(|e| format!("known archive does not open: {}", e))?;
+    if files.get("self-test.txt").map(Vec::as_slice) != Some(KNOWN_ARCHIVE_CONTENT) {
+        return Err("known archive has the wrong contents".to_string());
+    }
+    if backend
+        .extract(&from_hex(KNOWN_ARCHIVE), "wrong password")
+        .is_ok()
+    {
+        return Err("known archive opened with the wrong password".to_string());
+    }
+
+    let mut files = HashMap::new();
+    files.insert("round-trip.txt".to_string(), KNOWN_ARCHIVE_CONTENT.to_vec());
+    let created = backend
+        .create(files.clone(), KNOWN_ARCHIVE_PASSWORD)
+        .map_err(|e| e.to_string())?;
+    if backend.extract(&created, KNOWN_ARCHIVE_PASSWORD).ok() != Some(files) {
+        return Err("new archive does not round-trip".to_string());
+    }
+    Ok(())
+}
+
+/// Catches a random source stuck on one value, not a weak one
+fn check_random() -> Result<(), String> {
+    let first = EncryptionUtils::random_bytes(32);
+    let second = EncryptionUtils::random_bytes(32);
+    if first == second || first.iter().all(|&b| b == first[0]) {
+        return Err("random source repeats itself".to_string());
+    }
+    Ok(())
+}
+
+/// Security parameters compiled into the library
+fn check_build_constants() -> Result<(), String> {
+    let checks = [
+        (AES_KEY_SIZE == 32, "keys are not 256 bits"),
+        (SALT_SIZE >= 16, "salts are shorter than 128 bits"),
+        (
+            PBKDF2_ITERATIONS >= 100_000,
+            "key derivation has fewer than 100,000 iterations",
+        ),
+        (
+            crate::ARCHIVE_FORMAT_VERSION == "1.0",
+            "unexpected archive format version",
+        ),
+        (
+            CREDENTIAL_SCHEMA_VERSION >= 1,
+            "unexpected credential schema version",
+        ),
+        (
+            crate::VERSION.split('.').count() == 3,
+            "library version is not MAJOR.MINOR.PATCH",
+        ),
+    ];
+    match checks.iter().find(|(ok, _)| !ok) {
+        Some((_, problem)) => Err(problem.to_string()),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_self_test_passes() {
+        let report = run();
+        let failures: Vec<_> = report.failures().collect();
+        assert!(failures.is_empty(), "{:?}", failures);
+        assert_eq!(report.checks.len(), 8);
+        assert!(ensure().is_ok());
+        assert!(expect("x", &[0xab], "ab").is_ok());
+        assert!(expect("x", &[0xab], "cd").is_err());
+    }
+}
@@ -0,0 +1,162 @@
+//! Limits on how long an unlocked vault stays unlocked
+//!
+//! Frontends lock on their own after user inactivity, but every frontend has
+//! its own timer and scripts have none. A [`VaultSession`] is kept next to
+//! the open repository by whoever serves it, is touched on each request and
+//! refuses further requests once the [`SessionPolicy`] says the session is
+//! over, whichever client is asking.
+
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+/// Idle timeout and absolute lifetime of an unlocked vault
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionPolicy {
+    /// Seconds without any request after which the vault locks (0 = never)
+    pub idle_timeout_secs: u64,
+    /// Seconds after unlocking at which the vault locks however busy it is
+    /// (0 = never)
+    pub max_lifetime_secs: u64,
+}
+
+impl SessionPolicy {
+    pub fn new(idle_timeout_secs: u64, max_lifetime_secs: u64) -> Self {
+        Self {
+            idle_timeout_secs,
+            max_lifetime_secs,
+        }
+    }
+
+    /// Whether the policy can ever end a session
+    pub fn is_active(&self) -> bool {
+        self.idle_timeout_secs > 0 || self.max_lifetime_secs > 0
+    }
+}
+
+/// Why a session ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionExpiry {
+    /// Nothing used the vault for longer than the idle timeout
+    Idle,
+    /// The vault has been unlocked for longer than the maximum lifetime
+    Lifetime,
+}
+
+impl SessionExpiry {
+    /// Message suitable for a toast or lock screen
+    pub fn describe(&self) -> &'static str {
+        match self {
+            SessionExpiry::Idle => "Session expired after a period of inactivity",
+            SessionExpiry::Lifetime => "Session reached its maximum length",
+        }
+    }
+}
+
+/// Tracks the activity of one unlocked vault against a [`SessionPolicy`]
+#[derive(Debug, Clone)]
+pub struct VaultSession {
+    policy: SessionPolicy,
+    opened_at: Instant,
+    last_activity: Instant,
+}
+
+impl VaultSession {
+    /// Start a session now
+    pub fn new(policy: SessionPolicy) -> Self {
+        Self::new_at(policy, Instant::now())
+    }
+
+    /// Start a session as of `now`
+    pub fn new_at(policy: SessionPolicy, now: Instant) -> Self {
+        Self {
+            policy,
+            opened_at: now,
+            last_activity: now,
+        }
+    }
+
+    /// Apply changed settings to the running session
+    pub fn set_policy(&mut self, policy: SessionPolicy) {
+        self.policy = policy;
+    }
+
+    /// Record activity now
+    pub fn touch(&mut self) {
+        self.touch_at(Instant::now());
+    }
+
+    /// Record activity as of `now`
+    pub fn touch_at(&mut self, now: Instant) {
+        self.last_activity = self.last_activity.max(now);
+    }
+
+    /// Check the session now
+    pub fn check(&self) -> Result<(), SessionExpiry> {
+        self.check_at(Instant::now())
+    }
+
+    /// Check the session as of `now`; the lifetime is checked first since
+    /// activity cannot extend it
+    pub fn check_at(&self, now: Instant) -> Result<(), SessionExpiry> {
+        if let Some(end) = self.lifetime_end() {
+            if now >= end {
+                return Err(SessionExpiry::Lifetime);
+            }
+        }
+        if let Some(end) = self.idle_end() {
+            if now >= end {
+                return Err(SessionExpiry::Idle);
+            }
+        }
+        Ok(())
+    }
+
+    /// When the session ends if there is no further activity
+    pub fn deadline(&self) -> Option<Instant> {
+        match (self.lifetime_end(), self.idle_end()) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+
+    fn lifetime_end(&self) -> Option<Instant> {
+        (self.policy.max_lifetime_secs > 0)
+            .then(|| self.opened_at + Duration::from_secs(self.policy.max_lifetime_secs))
+    }
+
+    fn idle_end(&self) -> Option<Instant> {
+        (self.policy.idle_timeout_secs > 0)
+            .then(|| self.last_activity + Duration::from_secs(self.policy.idle_timeout_secs))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_idle_and_lifetime_limits() {
+        let start = Instant::now();
+        let secs = |s| start + Duration::from_secs(s);
+        let mut session = VaultSession::new_at(SessionPolicy::new(60, 300), start);
+        assert_eq!(session.deadline(), Some(secs(60)));
+
+        session.touch_at(secs(50));
+        assert_eq!(session.check_at(secs(100)), Ok(()));
+        assert_eq!(session.check_at(secs(110)), Err(SessionExpiry::Idle));
+
+        for t in (100..300).step_by(30) {
+            session.touch_at(secs(t));
+        }
+        assert_eq!(session.deadline(), Some(secs(300)));
+        assert_eq!(session.check_at(secs(300)), Err(SessionExpiry::Lifetime));
+
+        session.set_policy(SessionPolicy::default());
+        assert!(!SessionPolicy::default().is_active());
+        assert_eq!(session.deadline(), None);
+        assert_eq!(session.check_at(secs(100_000)), Ok(()));
+    }
+}
@@ -0,0 +1,1087 @@
+//! Updating 7z archives without repacking them
+//!
+//! A 7z archive is a run of packed streams followed by a header that says
+//! which coders, sizes and files each stream holds. Archives written by
+//! [`SevenZipBackend`] put every entry in a block of its own, so an update can
+//! copy the packed streams of unchanged entries byte for byte, compress and
+//! encrypt only the changed ones, and write a new header after them.
+//!
+//! The header is parsed here, but decoding and encoding it (LZMA, AES) is
+//! left to sevenz-rust2: an encoded header is decoded by reading it as the
+//! single entry of a synthetic archive, and a new header is encoded by
+//! packing it as one. Anything outside what ZipLock writes, such as solid
+//! blocks that mix kept and changed entries, anti-items or archive
+//! properties, makes [`update`] return `None` so the caller repacks instead.
+
+use std::collections::HashSet;
+use std::io::Cursor;
+use std::ops::Range;
+
+use sevenz_rust2::{ArchiveReader, Password};
+use zeroize::Zeroizing;
+
+use crate::core::archive_backend::{
+    ArchiveBackend, ArchiveOptions, SevenZipBackend, SEVEN_ZIP_SIGNATURE,
+};
+use crate::core::types::FileMap;
+
+const START_HEADER_LEN: usize = 32;
+
+const K_END: u8 = 0x00;
+const K_HEADER: u8 = 0x01;
+const K_MAIN_STREAMS_INFO: u8 = 0x04;
+const K_FILES_INFO: u8 = 0x05;
+const K_PACK_INFO: u8 = 0x06;
+const K_UNPACK_INFO: u8 = 0x07;
+const K_SUB_STREAMS_INFO: u8 = 0x08;
+const K_SIZE: u8 = 0x09;
+const K_CRC: u8 = 0x0A;
+const K_FOLDER: u8 = 0x0B;
+const K_CODERS_UNPACK_SIZE: u8 = 0x0C;
+const K_NUM_UNPACK_STREAM: u8 = 0x0D;
+const K_EMPTY_STREAM: u8 = 0x0E;
+const K_EMPTY_FILE: u8 = 0x0F;
+const K_ANTI: u8 = 0x10;
+const K_NAME: u8 = 0x11;
+const K_CTIME: u8 = 0x12;
+const K_ATIME: u8 = 0x13;
+const K_MTIME: u8 = 0x14;
+const K_WIN_ATTRIBUTES: u8 = 0x15;
+const K_ENCODED_HEADER: u8 = 0x17;
+const K_DUMMY: u8 = 0x19;
+
+/// Method ID of 7-Zip's AES-256 + SHA-256 coder
+const AES_METHOD_ID: &[u8] = &[0x06, 0xF1, 0x07, 0x01];
+
+/// Name of the lone entry of the synthetic archives headers are decoded and
+/// encoded through
+const HEADER_ENTRY: &str = "header";
+
+/// Apply changed and removed entries to a 7z archive, copying the packed
+/// streams of every other entry unchanged
+///
+/// Returns `None` when the archive can't be patched, including when
+/// `password` doesn't open it; the caller then falls back to a full repack,
+/// which reports the actual error.
+pub(crate) fn update(
+    data: &[u8],
+    password: &str,
+    changed: &FileMap,
+    removed: &[String],
+) -> Option<Vec<u8>> {
+    let (raw, header_encrypted) = read_header(data, password)?;
+    let old = Header::parse(&raw)?;
+
+    let dropped: HashSet<&str> = changed
+        .keys()
+        .map(String::as_str)
+        .chain(removed.iter().map(String::as_str))
+        .collect();
+    let is_dropped = |entry: &FileEntry| dropped.contains(entry.name.replace('\\', "/").as_str());
+
+    // Each block is kept or dropped whole; a solid block holding both kept
+    // and dropped entries would have to be recompressed
+    let stream_entries: Vec<&FileEntry> = old.files.iter().filter(|f| f.has_stream).collect();
+    let mut next_entry = 0;
+    let mut kept_folders = Vec::new();
+    let mut kept_entries = Vec::new();
+    for folder in &old.folders {
+        let entries = stream_entries.get(next_entry..next_entry + folder.substreams.len())?;
+        next_entry += folder.substreams.len();
+        let dropped_count = entries.iter().filter(|entry| is_dropped(entry)).count();
+        if dropped_count == 0 && !entries.is_empty() {
+            kept_folders.push(folder);
+            kept_entries.extend(entries.iter().map(|entry| (*entry).clone()));
+        } else if dropped_count != entries.len() {
+            return None;
+        }
+    }
+    if next_entry != stream_entries.len() {
+        return None;
+    }
+
+    // Changed entries are packed by the regular writer and their streams
+    // lifted out of the result
+    let (full, empty): (FileMap, FileMap) = changed
+        .iter()
+        .map(|(path, content)| (path.clone(), content.clone()))
+        .partition(|(_, content)| !content.is_empty());
+    let new_archive;
+    let mut new = Header::default();
+    if !full.is_empty() {
+        new_archive = SevenZipBackend
+            .create_with_options(
+                full,
+                password,
+                &ArchiveOptions::default().with_encrypt_header(false),
+            )
+            .ok()?;
+        new = Header::parse(&read_header(&new_archive, "")?.0)?;
+    } else {
+        new_archive = Vec::new();
+    }
+
+    let mut out = vec![0u8; START_HEADER_LEN];
+    let mut folders = Vec::with_capacity(kept_folders.len() + new.folders.len());
+    for folder in kept_folders {
+        out.extend_from_slice(folder.packed(data)?);
+        folders.push(folder.clone());
+    }
+    for folder in &new.folders {
+        out.extend_from_slice(folder.packed(&new_archive)?);
+        folders.push(folder.clone());
+    }
+
+    let kept_names: Vec<String> = kept_entries
+        .iter()
+        .map(|entry| entry.name.clone())
+        .collect();
+    let mut files = kept_entries;
+    files.extend(new.files);
+    files.extend(
+        old.files
+            .into_iter()
+            .filter(|entry| !entry.has_stream && !is_dropped(entry)),
+    );
+    let mut empty_paths: Vec<String> = empty.into_keys().collect();
+    empty_paths.sort();
+    files.extend(empty_paths.into_iter().map(FileEntry::empty_file));
+
+    let header = Header { folders, files }.write();
+    let header = if header_encrypted {
+        encode_header(&mut out, &header, password)?
+    } else {
+        header
+    };
+    let header_offset = (out.len() - START_HEADER_LEN) as u64;
+    out.extend_from_slice(&header);
+    out[..START_HEADER_LEN].copy_from_slice(&start_header(
+        header_offset,
+        header.len() as u64,
+        crc32fast::hash(&header),
+    ));
+
+    verify(&out, password, &kept_names).then_some(out)
+}
+
+/// Check that `password` opens one of the kept entries of the updated
+/// archive, since with an unencrypted header nothing else has proven it
+fn verify(data: &[u8], password: &str, kept_names: &[String]) -> bool {
+    let Ok(mut reader) = ArchiveReader::new(Cursor::new(data), Password::from(password)) else {
+        return false;
+    };
+    let smallest = reader
+        .archive()
+        .files
+        .iter()
+        .filter(|entry| entry.has_stream && kept_names.contains(&entry.name))
+        .min_by_key(|entry| entry.size)
+        .map(|entry| entry.name.clone());
+    match smallest {
+        Some(name) => reader.read_file(&name).map(Zeroizing::new).is_ok(),
+        None => true,
+    }
+}
+
+/// Length of the 7z archive at the start of `data`, which ends with its
+/// header; anything after that is not part of the archive
+pub(crate) fn archive_len(data: &[u8]) -> Option<usize> {
+    let (header, _) = header_location(data)?;
+    (header.end <= data.len()).then_some(header.end)
+}
+
+/// Where the start header of a 7z archive says its header is, and the
+/// header's CRC
+fn header_location(data: &[u8]) -> Option<(Range<usize>, u32)> {
+    if !data.starts_with(SEVEN_ZIP_SIGNATURE) || data.len() < START_HEADER_LEN || data[6] != 0 {
+        return None;
+    }
+    let stored_crc = u32::from_le_bytes(data[8..12].try_into().ok()?);
+    if crc32fast::hash(&data[12..START_HEADER_LEN]) != stored_crc {
+        return None;
+    }
+    let offset = u64::from_le_bytes(data[12..20].try_into().ok()?);
+    let size = u64::from_le_bytes(data[20..28].try_into().ok()?);
+    let header_crc = u32::from_le_bytes(data[28..32].try_into().ok()?);
+    let start = START_HEADER_LEN.checked_add(usize::try_from(offset).ok()?)?;
+    let end = start.checked_add(usize::try_from(size).ok()?)?;
+    Some((start..end, header_crc))
+}
+
+/// The decoded header of a 7z archive, and whether it was encrypted
+fn read_header(data: &[u8], password: &str) -> Option<(Vec<u8>, bool)> {
+    let (location, header_crc) = header_location(data)?;
+    let header = data.get(location)?;
+    if crc32fast::hash(header) != header_crc {
+        return None;
+    }
+
+    match *header.first()? {
+        K_HEADER => Some((header.to_vec(), false)),
+        K_ENCODED_HEADER => {
+            let mut reader = Reader::new(&header[1..]);
+            let folders = reader.streams_info()?;
+            let [folder] = folders.as_slice() else {
+                return None;
+            };
+            let encrypted = folder.coder_ids.iter().any(|id| id == AES_METHOD_ID);
+
+            // The same streams, moved to the start of an archive whose one
+            // entry is the header
+            let mut synthetic = Header {
+                folders: vec![folder.clone()],
+                files: vec![FileEntry::stream(HEADER_ENTRY)],
+            };
+            synthetic.folders[0].substreams = vec![SubStream {
+                size: folder.unpack_size(),
+                crc: None,
+            }];
+            let mut archive = vec![0u8; START_HEADER_LEN];
+            archive.extend_from_slice(folder.packed(data)?);
+            let synthetic = synthetic.write();
+            let synthetic_offset = (archive.len() - START_HEADER_LEN) as u64;
+            archive.extend_from_slice(&synthetic);
+            archive[..START_HEADER_LEN].copy_from_slice(&start_header(
+                synthetic_offset,
+                synthetic.len() as u64,
+                crc32fast::hash(&synthetic),
+            ));
+
+            let decoded = ArchiveReader::new(Cursor::new(archive), Password::from(password))
+                .and_then(|mut reader| reader.read_file(HEADER_ENTRY))
+                .ok()?;
+            (decoded.first() == Some(&K_HEADER)).then_some((decoded, encrypted))
+        }
+        _ => None,
+    }
+}
+
+/// Compress and encrypt `header`, append its packed stream to `out` and
+/// return the encoded header that points at it
+fn encode_header(out: &mut Vec<u8>, header: &[u8], password: &str) -> Option<Vec<u8>> {
+    let archive = SevenZipBackend
+        .create_with_options(
+            FileMap::from([(HEADER_ENTRY.to_string(), header.to_vec())]),
+            password,
+            &ArchiveOptions::default().with_encrypt_header(false),
+        )
+        .ok()?;
+    let mut packed = Header::parse(&read_header(&archive, "")?.0)?;
+    let mut folder = packed.folders.pop()?;
+    let [substream] = folder.substreams.as_slice() else {
+        return None;
+    };
+    folder.crc = folder.crc.or(substream.crc);
+
+    let pack_pos = (out.len() - START_HEADER_LEN) as u64;
+    out.extend_from_slice(folder.packed(&archive)?);
+    folder.substreams = vec![SubStream {
+        size: folder.unpack_size(),
+        crc: folder.crc,
+    }];
+    let mut encoded = vec![K_ENCODED_HEADER];
+    write_streams_info(&mut encoded, pack_pos, std::slice::from_ref(&folder));
+    Some(encoded)
+}
+
+fn start_header(offset: u64, size: u64, crc: u32) -> [u8; START_HEADER_LEN] {
+    let mut start = [0u8; START_HEADER_LEN];
+    start[..6].copy_from_slice(SEVEN_ZIP_SIGNATURE);
+    start[7] = 4;
+    start[12..20].copy_from_slice(&offset.to_le_bytes());
+    start[20..28].copy_from_slice(&size.to_le_bytes());
+    start[28..32].copy_from_slice(&crc.to_le_bytes());
+    let start_crc = crc32fast::hash(&start[12..]);
+    start[8..12].copy_from_slice(&start_crc.to_le_bytes());
+    start
+}
+
+/// The parts of an archive header an update carries over
+#[derive(Debug, Default)]
+struct Header {
+    folders: Vec<Folder>,
+    files: Vec<FileEntry>,
+}
+
+/// A block: its coders, packed streams and the entries it unpacks to
+#[derive(Debug, Clone)]
+struct Folder {
+    /// The folder record as stored, from the coder count to the packed
+    /// stream indices
+    definition: Vec<u8>,
+    coder_ids: Vec<Vec<u8>>,
+    /// Index of the output stream that isn't bound to another coder
+    main_output: usize,
+    unpack_sizes: Vec<u64>,
+    crc: Option<u32>,
+    /// Offset of the first packed stream, from the end of the start header
+    pack_start: u64,
+    pack_sizes: Vec<u64>,
+    substreams: Vec<SubStream>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct SubStream {
+    size: u64,
+    crc: Option<u32>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct FileEntry {
+    name: String,
+    has_stream: bool,
+    /// For entries without a stream: a file rather than a directory
+    empty_file: bool,
+    ctime: Option<u64>,
+    atime: Option<u64>,
+    mtime: Option<u64>,
+    attributes: Option<u32>,
+}
+
+impl FileEntry {
+    fn stream(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            has_stream: true,
+            ..Self::default()
+        }
+    }
+
+    fn empty_file(name: String) -> Self {
+        Self {
+            name,
+            empty_file: true,
+            ..Self::default()
+        }
+    }
+}
+
+impl Folder {
+    fn unpack_size(&self) -> u64 {
+        self.unpack_sizes[self.main_output]
+    }
+
+    /// The packed streams of this folder within `archive`
+    fn packed<'a>(&self, archive: &'a [u8]) -> Option<&'a [u8]> {
+        let start = START_HEADER_LEN.checked_add(usize::try_from(self.pack_start).ok()?)?;
+        let len = self.pack_sizes.iter().try_fold(0usize, |len, size| {
+            len.checked_add(usize::try_from(*size).ok()?)
+        })?;
+        archive.get(start..start.checked_add(len)?)
+    }
+}
+
+impl Header {
+    fn parse(raw: &[u8]) -> Option<Self> {
+        let mut reader = Reader::new(raw);
+        if reader.byte()? != K_HEADER {
+            return None;
+        }
+        let mut header = Header::default();
+        let mut id = reader.byte()?;
+        if id == K_MAIN_STREAMS_INFO {
+            header.folders = reader.streams_info()?;
+            id = reader.byte()?;
+        }
+        if id == K_FILES_INFO {
+            header.files = reader.files_info()?;
+            id = reader.byte()?;
+        }
+        // Archive properties and additional streams aren't carried over
+        (id == K_END).then_some(header)
+    }
+
+    fn write(&self) -> Vec<u8> {
+        let mut out = vec![K_HEADER];
+        if !self.folders.is_empty() {
+            out.push(K_MAIN_STREAMS_INFO);
+            write_streams_info(&mut out, 0, &self.folders);
+        }
+        if !self.files.is_empty() {
+            write_files_info(&mut out, &self.files);
+        }
+        out.push(K_END);
+        out
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn byte(&mut self) -> Option<u8> {
+        let byte = *self.data.get(self.pos)?;
+        self.pos += 1;
+        Some(byte)
+    }
+
+    fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        let bytes = self.data.get(self.pos..self.pos.checked_add(len)?)?;
+        self.pos += len;
+        Some(bytes)
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.bytes(4)?.try_into().ok()?))
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_le_bytes(self.bytes(8)?.try_into().ok()?))
+    }
+
+    /// A 7z variable-length number: the leading one bits of the first byte
+    /// count the bytes that follow
+    fn number(&mut self) -> Option<u64> {
+        let first = self.byte()?;
+        let mut value = 0u64;
+        for i in 0..8 {
+            let mask = 0x80u8 >> i;
+            if first & mask == 0 {
+                let high = u64::from(first & mask.wrapping_sub(1));
+                return Some(value | (high << (8 * i)));
+            }
+            value |= u64::from(self.byte()?) << (8 * i);
+        }
+        Some(value)
+    }
+
+    /// A count, bounded by the bytes left so a corrupt header can't make us
+    /// allocate without limit
+    fn count(&mut self) -> Option<usize> {
+        let count = usize::try_from(self.number()?).ok()?;
+        (count <= self.data.len() - self.pos).then_some(count)
+    }
+
+    fn bits(&mut self, len: usize) -> Option<Vec<bool>> {
+        let bytes = self.bytes(len.div_ceil(8))?;
+        Some(
+            (0..len)
+                .map(|i| bytes[i / 8] & (0x80 >> (i % 8)) != 0)
+                .collect(),
+        )
+    }
+
+    /// A defined-flag vector, either all set or stored
+    fn defined(&mut self, len: usize) -> Option<Vec<bool>> {
+        if self.byte()? != 0 {
+            Some(vec![true; len])
+        } else {
+            self.bits(len)
+        }
+    }
+
+    fn digests(&mut self, len: usize) -> Option<Vec<Option<u32>>> {
+        self.defined(len)?
+            .into_iter()
+            .map(|defined| {
+                if defined {
+                    self.u32().map(Some)
+                } else {
+                    Some(None)
+                }
+            })
+            .collect()
+    }
+
+    fn streams_info(&mut self) -> Option<Vec<Folder>> {
+        let mut pack_pos = 0;
+        let mut pack_sizes = Vec::new();
+        let mut folders = Vec::new();
+        let mut substream_counts = None;
+        let mut substream_sizes = None;
+        let mut substream_crcs = None;
+
+        loop {
+            match self.byte()? {
+                K_END => break,
+                K_PACK_INFO => {
+                    pack_pos = self.number()?;
+                    let count = self.count()?;
+                    loop {
+                        match self.byte()? {
+                            K_END => break,
+                            K_SIZE => {
+                                pack_sizes =
+                                    (0..count).map(|_| self.number()).collect::<Option<_>>()?
+                            }
+                            // Packed stream CRCs are optional and not kept
+                            K_CRC => {
+                                self.digests(count)?;
+                            }
+                            _ => return None,
+                        }
+                    }
+                }
+                K_UNPACK_INFO => {
+                    if self.byte()? != K_FOLDER {
+                        return None;
+                    }
+                    let count = self.count()?;
+                    if self.byte()? != 0 {
+                        return None;
+                    }
+                    folders = (0..count)
+                        .map(|_| self.folder())
+                        .collect::<Option<Vec<_>>>()?;
+                    if self.byte()? != K_CODERS_UNPACK_SIZE {
+                        return None;
+                    }
+                    for folder in &mut folders {
+                        for size in &mut folder.unpack_sizes {
+                            *size = self.number()?;
+                        }
+                    }
+                    loop {
+                        match self.byte()? {
+                            K_END => break,
+                            K_CRC => {
+                                for (folder, crc) in folders.iter_mut().zip(self.digests(count)?) {
+                                    folder.crc = crc;
+                                }
+                            }
+                            _ => return None,
+                        }
+                    }
+                }
+                K_SUB_STREAMS_INFO => loop {
+                    match self.byte()? {
+                        K_END => break,
+                        K_NUM_UNPACK_STREAM => {
+                            substream_counts = Some(
+                                folders
+                                    .iter()
+                                    .map(|_| self.count())
+                                    .collect::<Option<Vec<_>>>()?,
+                            );
+                        }
+                        K_SIZE => {
+                            let counts = substream_counts
+                                .clone()
+                                .unwrap_or_else(|| vec![1; folders.len()]);
+                            let mut sizes = Vec::new();
+                            for count in counts {
+                                sizes.push(
+                                    (1..count)
+                                        .map(|_| self.number())
+                                        .collect::<Option<Vec<_>>>()?,
+                                );
+                            }
+                            substream_sizes = Some(sizes);
+                        }
+                        K_CRC => {
+                            let counts = substream_counts
+                                .clone()
+                                .unwrap_or_else(|| vec![1; folders.len()]);
+                            let needed = folders
+                                .iter()
+                                .zip(&counts)
+                                .filter(|(folder, count)| **count != 1 || folder.crc.is_none())
+                                .map(|(_, count)| *count)
+                                .sum();
+                            substream_crcs = Some(self.digests(needed)?);
+                        }
+                        _ => return None,
+                    }
+                },
+                _ => return None,
+            }
+        }
+
+        // Hand each folder its packed streams, in order
+        let mut next_pack = 0;
+        let mut offset = pack_pos;
+        for folder in &mut folders {
+            folder.pack_start = offset;
+            let sizes = pack_sizes.get(next_pack..next_pack + folder.pack_sizes.len())?;
+            folder.pack_sizes.copy_from_slice(sizes);
+            next_pack += sizes.len();
+            offset = sizes
+                .iter()
+                .try_fold(offset, |offset, size| offset.checked_add(*size))?;
+        }
+
+        // And its substreams, one per folder unless the header says otherwise
+        let counts = substream_counts.unwrap_or_else(|| vec![1; folders.len()]);
+        let mut crcs = substream_crcs.unwrap_or_default().into_iter();
+        for (i, (folder, count)) in folders.iter_mut().zip(counts).enumerate() {
+            let mut sizes = match &substream_sizes {
+                Some(sizes) => sizes.get(i)?.clone(),
+                None if count <= 1 => Vec::new(),
+                None => return None,
+            };
+            if count > 0 {
+                let rest = folder.unpack_size().checked_sub(
+                    sizes
+                        .iter()
+                        .try_fold(0u64, |sum, size| sum.checked_add(*size))?,
+                )?;
+                sizes.push(rest);
+            }
+            folder.substreams = if count == 1 && folder.crc.is_some() {
+                vec![SubStream {
+                    size: sizes[0],
+                    crc: folder.crc,
+                }]
+            } else {
+                sizes
+                    .into_iter()
+                    .map(|size| SubStream {
+                        size,
+                        crc: crcs.next().flatten(),
+                    })
+                    .collect()
+            };
+        }
+        Some(folders)
+    }
+
+    fn folder(&mut self) -> Option<Folder> {
+        let start = self.pos;
+        let coder_count = self.count()?;
+        let mut coder_ids = Vec::with_capacity(coder_count);
+        let mut total_in = 0usize;
+        let mut total_out = 0usize;
+        for _ in 0..coder_count {
+            let flags = self.byte()?;
+            // Alternative methods were never used by any 7-Zip release
+            if flags & 0x80 != 0 {
+                return None;
+            }
+            coder_ids.push(self.bytes(usize::from(flags & 0x0F))?.to_vec());
+            if flags & 0x10 != 0 {
+                total_in = total_in.checked_add(self.count()?)?;
+                total_out = total_out.checked_add(self.count()?)?;
+            } else {
+                total_in += 1;
+                total_out += 1;
+            }
+            if flags & 0x20 != 0 {
+                let len = self.count()?;
+                self.bytes(len)?;
+            }
+        }
+
+        let bind_pairs = total_out.checked_sub(1)?;
+        let mut bound_outputs = HashSet::new();
+        for _ in 0..bind_pairs {
+            self.number()?;
+            bound_outputs.insert(self.number()?);
+        }
+        let packed_count = total_in.checked_sub(bind_pairs)?;
+        if packed_count > 1 {
+            for _ in 0..packed_count {
+                self.number()?;
+            }
+        }
+        let main_output = (0..total_out).find(|i| !bound_outputs.contains(&(*i as u64)))?;
+
+        Some(Folder {
+            definition: self.data[start..self.pos].to_vec(),
+            coder_ids,
+            main_output,
+            unpack_sizes: vec![0; total_out],
+            crc: None,
+            pack_start: 0,
+            pack_sizes: vec![0; packed_count],
+            substreams: Vec::new(),
+        })
+    }
+
+    fn files_info(&mut self) -> Option<Vec<FileEntry>> {
+        let count = self.count()?;
+        let mut files = vec![FileEntry::default(); count];
+        let mut empty_streams = vec![false; count];
+        let mut empty_files = Vec::new();
+
+        loop {
+            let id = self.byte()?;
+            if id == K_END {
+                break;
+            }
+            let len = self.count()?;
+            let mut property = Reader::new(self.bytes(len)?);
+            match id {
+                K_EMPTY_STREAM => empty_streams = property.bits(count)?,
+                K_EMPTY_FILE => {
+                    empty_files = property.bits(empty_streams.iter().filter(|e| **e).count())?
+                }
+                K_ANTI => {
+                    let anti = property.bits(empty_streams.iter().filter(|e| **e).count())?;
+                    if anti.contains(&true) {
+                        return None;
+                    }
+                }
+                K_NAME => {
+                    if property.byte()? != 0 {
+                        return None;
+                    }
+                    for file in &mut files {
+                        let mut units = Vec::new();
+                        loop {
+                            let unit = u16::from_le_bytes(property.bytes(2)?.try_into().ok()?);
+                            if unit == 0 {
+                                break;
+                            }
+                            units.push(unit);
+                        }
+                        file.name = String::from_utf16(&units).ok()?;
+                    }
+                }
+                K_CTIME | K_ATIME | K_MTIME => {
+                    let defined = property.defined(count)?;
+                    if property.byte()? != 0 {
+                        return None;
+                    }
+                    for (file, defined) in files.iter_mut().zip(defined) {
+                        let time = if defined { Some(property.u64()?) } else { None };
+                        match id {
+                            K_CTIME => file.ctime = time,
+                            K_ATIME => file.atime = time,
+                            _ => file.mtime = time,
+                        }
+                    }
+                }
+                K_WIN_ATTRIBUTES => {
+                    let defined = property.defined(count)?;
+                    if property.byte()? != 0 {
+                        return None;
+                    }
+                    for (file, defined) in files.iter_mut().zip(defined) {
+                        file.attributes = if defined { Some(property.u32()?) } else { None };
+                    }
+                }
+                K_DUMMY => {}
+                _ => return None,
+            }
+        }
+
+        let mut empty_index = 0;
+        for (file, empty) in files.iter_mut().zip(empty_streams) {
+            file.has_stream = !empty;
+            if empty {
+                file.empty_file = empty_files.get(empty_index).copied().unwrap_or(false);
+                empty_index += 1;
+            }
+        }
+        Some(files)
+    }
+}
+
+fn write_number(out: &mut Vec<u8>, value: u64) {
+    let mut first = 0u8;
+    let mut extra = 0;
+    while extra < 8 && value >= 1u64 << (7 * (extra + 1)) {
+        first |= 0x80 >> extra;
+        extra += 1;
+    }
+    if extra < 8 {
+        first |= (value >> (8 * extra)) as u8;
+    }
+    out.push(first);
+    out.extend_from_slice(&value.to_le_bytes()[..extra]);
+}
+
+fn write_bits(out: &mut Vec<u8>, bits: &[bool]) {
+    let mut bytes = vec![0u8; bits.len().div_ceil(8)];
+    for (i, _) in bits.iter().enumerate().filter(|(_, bit)| **bit) {
+        bytes[i / 8] |= 0x80 >> (i % 8);
+    }
+    out.extend_from_slice(&bytes);
+}
+
+fn write_defined(out: &mut Vec<u8>, defined: &[bool]) {
+    if defined.iter().all(|d| *d) {
+        out.push(1);
+    } else {
+        out.push(0);
+        write_bits(out, defined);
+    }
+}
+
+fn write_digests(out: &mut Vec<u8>, crcs: &[Option<u32>]) {
+    let defined: Vec<bool> = crcs.iter().map(Option::is_some).collect();
+    write_defined(out, &defined);
+    for crc in crcs.iter().flatten() {
+        out.extend_from_slice(&crc.to_le_bytes());
+    }
+}
+
+/// Write `folders` with their packed streams laid out back to back from
+/// `pack_pos`
+fn write_streams_info(out: &mut Vec<u8>, pack_pos: u64, folders: &[Folder]) {
+    let pack_sizes: Vec<u64> = folders
+        .iter()
+        .flat_map(|folder| folder.pack_sizes.iter().copied())
+        .collect();
+    out.push(K_PACK_INFO);
+    write_number(out, pack_pos);
+    write_number(out, pack_sizes.len() as u64);
+    out.push(K_SIZE);
+    for size in pack_sizes {
+        write_number(out, size);
+    }
+    out.push(K_END);
+
+    out.push(K_UNPACK_INFO);
+    out.push(K_FOLDER);
+    write_number(out, folders.len() as u64);
+    out.push(0);
+    for folder in folders {
+        out.extend_from_slice(&folder.definition);
+    }
+    out.push(K_CODERS_UNPACK_SIZE);
+    for size in folders.iter().flat_map(|folder| &folder.unpack_sizes) {
+        write_number(out, *size);
+    }
+    if folders.iter().any(|folder| folder.crc.is_some()) {
+        out.push(K_CRC);
+        let crcs: Vec<Option<u32>> = folders.iter().map(|folder| folder.crc).collect();
+        write_digests(out, &crcs);
+    }
+    out.push(K_END);
+
+    let mut substreams = Vec::new();
+    if folders.iter().any(|folder| folder.substreams.len() != 1) {
+        substreams.push(K_NUM_UNPACK_STREAM);
+        for folder in folders {
+            write_number(&mut substreams, folder.substreams.len() as u64);
+        }
+        substreams.push(K_SIZE);
+        for folder in folders {
+            if let Some((_, sizes)) = folder.substreams.split_last() {
+                for substream in sizes {
+                    write_number(&mut substreams, substream.size);
+                }
+            }
+        }
+    }
+    let crcs: Vec<Option<u32>> = folders
+        .iter()
+        .filter(|folder| folder.substreams.len() != 1 || folder.crc.is_none())
+        .flat_map(|folder| folder.substreams.iter().map(|substream| substream.crc))
+        .collect();
+    if !crcs.is_empty() {
+        substreams.push(K_CRC);
+        write_digests(&mut substreams, &crcs);
+    }
+    // sevenz-rust2 insists on the section even when it holds nothing
+    out.push(K_SUB_STREAMS_INFO);
+    out.extend_from_slice(&substreams);
+    out.push(K_END);
+    out.push(K_END);
+}
+
+fn write_files_info(out: &mut Vec<u8>, files: &[FileEntry]) {
+    out.push(K_FILES_INFO);
+    write_number(out, files.len() as u64);
+
+    let property = |out: &mut Vec<u8>, id: u8, data: Vec<u8>| {
+        out.push(id);
+        write_number(out, data.len() as u64);
+        out.extend_from_slice(&data);
+    };
+
+    let empty_streams: Vec<bool> = files.iter().map(|file| !file.has_stream).collect();
+    if empty_streams.contains(&true) {
+        let mut data = Vec::new();
+        write_bits(&mut data, &empty_streams);
+        property(out, K_EMPTY_STREAM, data);
+
+        let empty_files: Vec<bool> = files
+            .iter()
+            .filter(|file| !file.has_stream)
+            .map(|file| file.empty_file)
+            .collect();
+        if empty_files.contains(&true) {
+            let mut data = Vec::new();
+            write_bits(&mut data, &empty_files);
+            property(out, K_EMPTY_FILE, data);
+        }
+    }
+
+    let mut names = vec![0u8];
+    for file in files {
+        for unit in file.name.encode_utf16().chain([0]) {
+            names.extend_from_slice(&unit.to_le_bytes());
+        }
+    }
+    property(out, K_NAME, names);
+
+    for (id, time) in [
+        (
+            K_CTIME,
+            (|file: &FileEntry| file.ctime) as fn(&FileEntry) -> Option<u64>,
+        ),
+        (K_ATIME, |file| file.atime),
+        (K_MTIME, |file| file.mtime),
+    ] {
+        let times: Vec<Option<u64>> = files.iter().map(time).collect();
+        if times.iter().any(Option::is_some) {
+            let mut data = Vec::new();
+            write_defined(
+                &mut data,
+                &times.iter().map(Option::is_some).collect::<Vec<_>>(),
+            );
+            data.push(0);
+            for time in times.into_iter().flatten() {
+                data.extend_from_slice(&time.to_le_bytes());
+            }
+            property(out, id, data);
+        }
+    }
+
+    let attributes: Vec<Option<u32>> = files.iter().map(|file| file.attributes).collect();
+    if attributes.iter().any(Option::is_some) {
+        let mut data = Vec::new();
+        write_defined(
+            &mut data,
+            &attributes.iter().map(Option::is_some).collect::<Vec<_>>(),
+        );
+        data.push(0);
+        for attribute in attributes.into_iter().flatten() {
+            data.extend_from_slice(&attribute.to_le_bytes());
+        }
+        property(out, K_WIN_ATTRIBUTES, data);
+    }
+
+    out.push(K_END);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files(entries: &[(&str, &str)]) -> FileMap {
+        entries
+            .iter()
+            .map(|(path, content)| (path.to_string(), content.as_bytes().to_vec()))
+            .collect()
+    }
+
+    /// The packed stream of `name` as stored in `archive`
+    fn packed_entry(archive: &[u8], password: &str, name: &str) -> Vec<u8> {
+        let header = Header::parse(&read_header(archive, password).unwrap().0).unwrap();
+        let mut entries = header.files.iter().filter(|file| file.has_stream);
+        for folder in &header.folders {
+            for _ in &folder.substreams {
+                if entries.next().unwrap().name == name {
+                    return folder.packed(archive).unwrap().to_vec();
+                }
+            }
+        }
+        panic!("{} not in archive", name);
+    }
+
+    fn check_update(options: &ArchiveOptions) {
+        let original = files(&[
+            ("metadata.yml", "version: 1"),
+            ("credentials/a/record.yml", "title: A"),
+            ("credentials/b/record.yml", "title: B"),
+            ("credentials/c/record.yml", "title: C"),
+        ]);
+        let archive = SevenZipBackend
+            .create_with_options(original, "password", options)
+            .unwrap();
+
+        let changed = files(&[
+            ("credentials/b/record.yml", "title: B2"),
+            ("credentials/d/record.yml", "title: D"),
+            ("credentials/e/notes.md", ""),
+        ]);
+        let removed = vec!["credentials/c/record.yml".to_string()];
+        let updated = update(&archive, "password", &changed, &removed).unwrap();
+
+        // AES IVs are random, so a re-encrypted entry never matches
+        for name in ["metadata.yml", "credentials/a/record.yml"] {
+            assert_eq!(
+                packed_entry(&archive, "password", name),
+                packed_entry(&updated, "password", name)
+            );
+        }
+        assert_ne!(
+            packed_entry(&archive, "password", "credentials/b/record.yml"),
+            packed_entry(&updated, "password", "credentials/b/record.yml")
+        );
+
+        let extracted = SevenZipBackend.extract(&updated, "password").unwrap();
+        assert_eq!(
+            extracted,
+            files(&[
+                ("metadata.yml", "version: 1"),
+                ("credentials/a/record.yml", "title: A"),
+                ("credentials/b/record.yml", "title: B2"),
+                ("credentials/d/record.yml", "title: D"),
+                ("credentials/e/notes.md", ""),
+            ])
+        );
+        assert_eq!(
+            read_header(&updated, "password").unwrap().1,
+            options.encrypt_header
+        );
+    }
+
+    #[test]
+    fn test_update_keeps_unchanged_entries() {
+        check_update(&ArchiveOptions::default());
+    }
+
+    #[test]
+    fn test_update_with_plain_header() {
+        check_update(&ArchiveOptions::default().with_encrypt_header(false));
+    }
+
+    #[test]
+    fn test_update_rejects_wrong_password() {
+        let archive = SevenZipBackend
+            .create(files(&[("a.yml", "a"), ("b.yml", "b")]), "password")
+            .unwrap();
+        let changed = files(&[("b.yml", "b2")]);
+        assert!(update(&archive, "wrong", &changed, &[]).is_none());
+
+        let plain_header = SevenZipBackend
+            .create_with_options(
+                files(&[("a.yml", "a"), ("b.yml", "b")]),
+                "password",
+                &ArchiveOptions::default().with_encrypt_header(false),
+            )
+            .unwrap();
+        assert!(update(&plain_header, "wrong", &changed, &[]).is_none());
+        assert!(matches!(
+            SevenZipBackend.update(&plain_header, "wrong", &changed, &[]),
+            Err(crate::core::FileError::InvalidPassword)
+        ));
+    }
+
+    #[test]
+    fn test_solid_archive_falls_back_to_repack() {
+        let solid = ArchiveOptions::default().with_solid(true);
+        let archive = SevenZipBackend
+            .create_with_options(files(&[("a.yml", "a"), ("b.yml", "b")]), "password", &solid)
+            .unwrap();
+        let changed = files(&[("b.yml", "b2")]);
+        assert!(update(&archive, "password", &changed, &[]).is_none());
+
+        let updated = SevenZipBackend
+            .update(&archive, "password", &changed, &[])
+            .unwrap();
+        assert_eq!(
+            SevenZipBackend.extract(&updated, "password").unwrap(),
+            files(&[("a.yml", "a"), ("b.yml", "b2")])
+        );
+    }
+
+    #[test]
+    fn test_numbers_round_trip() {
+        for value in [0, 1, 0x7F, 0x80, 0x3FFF, 0x4000, 1 << 40, u64::MAX] {
+            let mut out = Vec::new();
+            write_number(&mut out, value);
+            assert_eq!(Reader::new(&out).number(), Some(value));
+        }
+    }
+}
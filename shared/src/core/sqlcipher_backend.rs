@@ -0,0 +1,358 @@
+//! SQLCipher databases as repository archives
+//!
+//! For vaults with tens of thousands of records a 7z archive has to be
+//! unpacked and repacked whole. A SQLCipher database keeps every file of the
+//! file map as a row keyed by its path, so single entries or one folder can
+//! be read through the primary-key index and changed rows written without
+//! touching the rest. [`convert_archive`](crate::core::archive_backend::convert_archive)
+//! exports such a database back to 7z.
+//!
+//! SQLCipher is compiled in through `rusqlite`. Pages are decrypted in
+//! memory as they are read, rollback journals hold pages encrypted like the
+//! database and temporary tables are kept in memory, so no plaintext reaches
+//! the disk. SQLCipher cannot decrypt a database held in memory, so the
+//! byte-slice methods of [`ArchiveBackend`] open an encrypted scratch copy,
+//! which is overwritten before it is removed; [`SqlCipherBackend::extract_prefix_file`]
+//! and [`ArchiveBackend::update_file`] work on the vault file itself and only
+//! read or write the pages they need.
+
+use std::io::Write;
+use std::path::Path;
+
+use rusqlite::{params, params_from_iter, Connection, ErrorCode, OpenFlags};
+
+use crate::core::archive_backend::ArchiveBackend;
+use crate::core::errors::{FileError, FileResult};
+use crate::core::types::FileMap;
+use crate::utils::encryption::SecureMemory;
+
+/// Name of the SQLCipher backend
+pub const SQLCIPHER_BACKEND: &str = "sqlcipher";
+
+/// Page size of SQLCipher 4 databases; every database is a whole number of
+/// pages
+const PAGE_SIZE: usize = 4096;
+
+/// Start of an unencrypted SQLite database
+const SQLITE_HEADER: &[u8] = b"SQLite format 3\0";
+
+/// Table holding one row per file map entry
+const SCHEMA: &str =
+    "CREATE TABLE IF NOT EXISTS entries (path TEXT PRIMARY KEY, content BLOB NOT NULL);";
+
+/// Archives stored as SQLCipher databases
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SqlCipherBackend;
+
+impl SqlCipherBackend {
+    /// Read only the entries whose path starts with `prefix`, such as
+    /// `credentials/<id>/`, using the index on the path
+    pub fn extract_prefix(&self, data: &[u8], password: &str, prefix: &str) -> FileResult<FileMap> {
+        let database = scratch_database(Some(data))?;
+        let connection = connect(database.path(), password, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        read_entries(&connection, prefix)
+    }
+
+    /// Like [`SqlCipherBackend::extract_prefix`], reading the database at
+    /// `path` in place so that only the pages holding the entries are read
+    /// and decrypted
+    pub fn extract_prefix_file(
+        &self,
+        path: &Path,
+        password: &str,
+        prefix: &str,
+    ) -> FileResult<FileMap> {
+        if !path.is_file() {
+            return Err(FileError::NotFound {
+                path: path.to_string_lossy().to_string(),
+            });
+        }
+        let connection = connect(path, password, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        read_entries(&connection, prefix)
+    }
+
+    /// Apply `apply` to a copy of `data` (or a new database) and return the
+    /// resulting file
+    fn write_database(
+        &self,
+        data: Option<&[u8]>,
+        password: &str,
+        apply: impl FnOnce(&mut Connection) -> FileResult<()>,
+    ) -> FileResult<Vec<u8>> {
+        let database = scratch_database(data)?;
+        let mut connection = connect(
+            database.path(),
+            password,
+            OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE,
+        )?;
+        apply(&mut connection)?;
+        connection.close().map_err(|(_, e)| database_error(e))?;
+        std::fs::read(database.path()).map_err(|e| FileError::CreationFailed {
+            message: format!("Failed to read SQLCipher database: {}", e),
+        })
+    }
+}
+
+impl ArchiveBackend for SqlCipherBackend {
+    fn name(&self) -> &'static str {
+        SQLCIPHER_BACKEND
+    }
+
+    /// SQLCipher encrypts the whole file, header included, so this only
+    /// checks that `data` is a whole number of pages and not a plain SQLite
+    /// database; random data of such a length passes too
+    fn recognizes(&self, data: &[u8]) -> bool {
+        !data.is_empty() && data.len().is_multiple_of(PAGE_SIZE) && !data.starts_with(SQLITE_HEADER)
+    }
+
+    fn extract(&self, data: &[u8], password: &str) -> FileResult<FileMap> {
+        self.extract_prefix(data, password, "")
+    }
+
+    fn create(&self, files: FileMap, password: &str) -> FileResult<Vec<u8>> {
+        self.write_database(None, password, |connection| {
+            apply_changes(connection, &files, &[])
+        })
+    }
+
+    /// Changes only the affected rows, in one transaction
+    fn update(
+        &self,
+        data: &[u8],
+        password: &str,
+        changed: &FileMap,
+        removed: &[String],
+    ) -> FileResult<Vec<u8>> {
+        self.write_database(Some(data), password, |connection| {
+            apply_changes(connection, changed, removed)
+        })
+    }
+
+    /// Runs the transaction against the file itself, so only the changed
+    /// pages are written
+    fn update_file(
+        &self,
+        path: &Path,
+        password: &str,
+        changed: &FileMap,
+        removed: &[String],
+    ) -> FileResult<()> {
+        if !path.is_file() {
+            return Err(FileError::NotFound {
+                path: path.to_string_lossy().to_string(),
+            });
+        }
+        let mut connection = connect(path, password, OpenFlags::SQLITE_OPEN_READ_WRITE)?;
+        apply_changes(&mut connection, changed, removed)
+    }
+}
+
+/// Open the database at `path` with `password`
+///
+/// A wrong password is only noticed when the first page is decrypted, so
+/// the schema is read here to report it straight away.
+fn connect(path: &Path, password: &str, flags: OpenFlags) -> FileResult<Connection> {
+    let connection = Connection::open_with_flags(path, flags).map_err(database_error)?;
+    connection
+        .pragma_update(None, "key", password)
+        .map_err(database_error)?;
+    connection
+        .pragma_update(None, "temp_store", "MEMORY")
+        .map_err(database_error)?;
+    connection
+        .query_row("SELECT count(*) FROM sqlite_master", [], |_| Ok(()))
+        .map_err(database_error)?;
+    Ok(connection)
+}
+
+/// The entries whose path starts with `prefix`, in path order
+fn read_entries(connection: &Connection, prefix: &str) -> FileResult<FileMap> {
+    let (query, bounds) = match prefix_upper_bound(prefix) {
+        Some(upper) => (
+            "SELECT path, content FROM entries WHERE path >= ?1 AND path < ?2 ORDER BY path",
+            vec![prefix.to_string(), upper],
+        ),
+        None => (
+            "SELECT path, content FROM entries WHERE path >= ?1 ORDER BY path",
+            vec![prefix.to_string()],
+        ),
+    };
+    let mut statement = connection.prepare(query).map_err(database_error)?;
+    let rows = statement
+        .query_map(params_from_iter(bounds), |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })
+        .map_err(database_error)?;
+    rows.collect::<rusqlite::Result<FileMap>>()
+        .map_err(database_error)
+}
+
+/// Delete `removed` and write `changed` in one transaction
+fn apply_changes(
+    connection: &mut Connection,
+    changed: &FileMap,
+    removed: &[String],
+) -> FileResult<()> {
+    let transaction = connection.transaction().map_err(database_error)?;
+    transaction.execute_batch(SCHEMA).map_err(database_error)?;
+    {
+        let mut delete = transaction
+            .prepare("DELETE FROM entries WHERE path = ?1")
+            .map_err(database_error)?;
+        for path in removed {
+            delete.execute(params![path]).map_err(database_error)?;
+        }
+        let mut insert = transaction
+            .prepare("INSERT OR REPLACE INTO entries (path, content) VALUES (?1, ?2)")
+            .map_err(database_error)?;
+        let mut paths: Vec<&String> = changed.keys().collect();
+        paths.sort();
+        for path in paths {
+            insert
+                .execute(params![path, changed[path]])
+                .map_err(database_error)?;
+        }
+    }
+    transaction.commit().map_err(database_error)
+}
+
+fn database_error(error: rusqlite::Error) -> FileError {
+    match error.sqlite_error_code() {
+        // SQLCipher cannot tell a wrong key from a file that is not a database
+        Some(ErrorCode::NotADatabase) => FileError::InvalidPassword,
+        _ => FileError::ExtractionFailed {
+            message: format!("SQLCipher failed: {}", error),
+        },
+    }
+}
+
+/// A temporary copy of an encrypted database, wiped when dropped
+struct ScratchDatabase(tempfile::NamedTempFile);
+
+impl ScratchDatabase {
+    fn path(&self) -> &Path {
+        self.0.path()
+    }
+}
+
+impl Drop for ScratchDatabase {
+    fn drop(&mut self) {
+        let _ = SecureMemory::wipe_file(self.0.path());
+    }
+}
+
+/// A temporary database file, empty or holding `data`
+fn scratch_database(data: Option<&[u8]>) -> FileResult<ScratchDatabase> {
+    let mut file = tempfile::NamedTempFile::new().map_err(|e| FileError::IoError {
+        message: format!("Failed to create temporary database: {}", e),
+    })?;
+    if let Some(data) = data {
+        file.write_all(data).map_err(|e| FileError::IoError {
+            message: format!("Failed to write temporary database: {}", e),
+        })?;
+    }
+    Ok(ScratchDatabase(file))
+}
+
+/// Smallest string greater than every string starting with `prefix`
+fn prefix_upper_bound(prefix: &str) -> Option<String> {
+    let mut chars: Vec<char> = prefix.chars().collect();
+    while let Some(last) = chars.pop() {
+        if let Some(next) = char::from_u32(last as u32 + 1) {
+            chars.push(next);
+            return Some(chars.into_iter().collect());
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_files() -> FileMap {
+        let mut files = FileMap::new();
+        files.insert("metadata.yml".to_string(), b"version: 1.0".to_vec());
+        files.insert(
+            "credentials/abc/record.yml".to_string(),
+            b"title: Bank".to_vec(),
+        );
+        files.insert(
+            "credentials/abcd/record.yml".to_string(),
+            b"title: Mail".to_vec(),
+        );
+        files.insert("empty.txt".to_string(), Vec::new());
+        files
+    }
+
+    #[test]
+    fn test_create_open_and_query_a_database() {
+        let backend = SqlCipherBackend;
+        let files = sample_files();
+
+        let database = backend.create(files.clone(), "password").unwrap();
+        assert!(backend.recognizes(&database));
+        assert!(!database.windows(11).any(|w| w == b"title: Bank"));
+        assert_eq!(backend.extract(&database, "password").unwrap(), files);
+        assert!(matches!(
+            backend.extract(&database, "wrong"),
+            Err(FileError::InvalidPassword)
+        ));
+
+        let entry = backend
+            .extract_prefix(&database, "password", "credentials/abc/")
+            .unwrap();
+        assert_eq!(entry.len(), 1);
+        assert_eq!(entry["credentials/abc/record.yml"], b"title: Bank");
+
+        let mut changed = FileMap::new();
+        changed.insert("metadata.yml".to_string(), b"version: 2.0".to_vec());
+        let updated = backend
+            .update(&database, "password", &changed, &["empty.txt".to_string()])
+            .unwrap();
+        let reopened = backend.extract(&updated, "password").unwrap();
+        assert_eq!(reopened["metadata.yml"], b"version: 2.0");
+        assert!(!reopened.contains_key("empty.txt"));
+        assert_eq!(reopened.len(), 3);
+    }
+
+    #[test]
+    fn test_file_is_read_and_updated_in_place() {
+        let backend = SqlCipherBackend;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vault.db");
+        std::fs::write(&path, backend.create(sample_files(), "password").unwrap()).unwrap();
+
+        let mut changed = FileMap::new();
+        changed.insert(
+            "credentials/abc/record.yml".to_string(),
+            b"title: Savings".to_vec(),
+        );
+        backend
+            .update_file(&path, "password", &changed, &[])
+            .unwrap();
+        assert!(matches!(
+            backend.update_file(&path, "wrong", &changed, &[]),
+            Err(FileError::InvalidPassword)
+        ));
+
+        let entry = backend
+            .extract_prefix_file(&path, "password", "credentials/abc/")
+            .unwrap();
+        assert_eq!(entry.len(), 1);
+        assert_eq!(entry["credentials/abc/record.yml"], b"title: Savings");
+        assert!(matches!(
+            backend.extract_prefix_file(&dir.path().join("missing.db"), "password", ""),
+            Err(FileError::NotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn test_prefix_range_covers_only_the_prefix() {
+        let upper = prefix_upper_bound("credentials/abc/").unwrap();
+        assert_eq!(upper, "credentials/abc0");
+        assert!("credentials/abc/record.yml" < upper.as_str());
+        assert!("credentials/abcd/record.yml" > upper.as_str());
+        assert_eq!(prefix_upper_bound(""), None);
+    }
+}
@@ -0,0 +1,221 @@
+//! Reproducible outputs of the vault cryptography for fixed inputs
+//!
+//! Built with the `test-vectors` feature. Another client that reads or
+//! writes ZipLock archives can check its implementation against these
+//! instead of reverse-engineering the format: each vector names the
+//! algorithm, gives every input including the salts and nonces that are
+//! normally random, and the exact output ZipLock produces. The same values
+//! are listed in `docs/technical/test-vectors.md`.
+//!
+//! Byte strings are lowercase hex; passwords and other text are UTF-8 as is.
+
+use std::collections::BTreeMap;
+
+use base64::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::core::errors::{CoreError, CoreResult};
+use crate::core::key_file::KeyFile;
+use crate::core::self_test::{KNOWN_ARCHIVE, KNOWN_ARCHIVE_CONTENT, KNOWN_ARCHIVE_PASSWORD};
+use crate::utils::encryption::{CredentialCrypto, EncryptionError, EncryptionUtils, FreshNonce};
+
+/// Password used by every vector that takes one
+const PASSWORD: &str = "correct horse battery staple";
+
+/// One algorithm applied to fixed inputs
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TestVector {
+    /// Stable identifier, e.g. `key-derivation`
+    pub name: String,
+    /// How the outputs are computed from the inputs
+    pub algorithm: String,
+    pub inputs: BTreeMap<String, String>,
+    pub outputs: BTreeMap<String, String>,
+}
+
+/// Every vector, with the versions they apply to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TestVectorSet {
+    pub library_version: String,
+    pub archive_format_version: String,
+    pub vectors: Vec<TestVector>,
+}
+
+/// Compute every vector
+pub fn generate() -> CoreResult<TestVectorSet> {
+    Ok(TestVectorSet {
+        library_version: crate::VERSION.to_string(),
+        archive_format_version: crate::ARCHIVE_FORMAT_VERSION.to_string(),
+        vectors: vec![
+            key_derivation()?,
+            key_file_composite()?,
+            field_envelope()?,
+            archive(),
+        ],
+    })
+}
+
+fn vector(
+    name: &str,
+    algorithm: &str,
+    inputs: &[(&str, String)],
+    outputs: &[(&str, String)],
+) -> TestVector {
+    let collect = |pairs: &[(&str, String)]| {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.clone()))
+            .collect()
+    };
+    TestVector {
+        name: name.to_string(),
+        algorithm: algorithm.to_string(),
+        inputs: collect(inputs),
+        outputs: collect(outputs),
+    }
+}
+
+fn crypto_error(error: EncryptionError) -> CoreError {
+    CoreError::InternalError {
+        message: format!("Test vector computation failed: {}", error),
+    }
+}
+
+/// `count` consecutive byte values starting at `first`
+fn sequence(first: u8, count: u8) -> Vec<u8> {
+    (0..count).map(|i| first.wrapping_add(i)).collect()
+}
+
+fn key_derivation() -> CoreResult<TestVector> {
+    let salt = sequence(0x00, 32);
+    let key = EncryptionUtils::derive_key(PASSWORD, &salt).map_err(crypto_error)?;
+    Ok(vector(
+        "key-derivation",
+        "h = password || salt; repeat 100000 times: h = SHA-256(h); key = h",
+        &[
+            ("password", PASSWORD.to_string()),
+            ("salt", EncryptionUtils::to_hex(&salt)),
+        ],
+        &[("key", EncryptionUtils::to_hex(key.expose()))],
+    ))
+}
+
+fn key_file_composite() -> CoreResult<TestVector> {
+    let contents = sequence(0x40, 64);
+    let composite = KeyFile::from_bytes(&contents)?.composite_password(PASSWORD);
+    Ok(vector(
+        "key-file-composite",
+        "archive password = lowercase hex of SHA-256(SHA-256(password) || SHA-256(key file))",
+        &[
+            ("password", PASSWORD.to_string()),
+            ("key_file", EncryptionUtils::to_hex(&contents)),
+        ],
+        &[("archive_password", composite)],
+    ))
+}
+
+fn field_envelope() -> CoreResult<TestVector> {
+    let value = "1234-5678-9012";
+    let field_key = sequence(0x80, 32);
+    let salt = sequence(0xa0, 32);
+    let key_nonce = sequence(0xc0, 12);
+    let nonce = sequence(0xd0, 12);
+    let envelope = CredentialCrypto::seal_envelope_with(
+        value,
+        PASSWORD,
+        &field_key,
+        &salt,
+        FreshNonce::fixed(&key_nonce).map_err(crypto_error)?,
+        FreshNonce::fixed(&nonce).map_err(crypto_error)?,
+    )
+    .map_err(crypto_error)?;
+    let hex_of = |base64: &str| {
+        BASE64_STANDARD
+            .decode(base64)
+            .map(|bytes| EncryptionUtils::to_hex(&bytes))
+            .map_err(|e| CoreError::InternalError {
+                message: format!("Envelope is not base64: {}", e),
+            })
+    };
+
+    Ok(vector(
+        "field-envelope",
+        "ciphertext = ChaCha20-Poly1305(field_key, nonce, value, aad \"ziplock-field-value-v1\"); \
+         wrapped_key = ChaCha20-Poly1305(key-derivation(passphrase, salt), key_nonce, field_key, \
+         aad \"ziplock-field-key-v1\"); both include the 16-byte tag and are stored base64",
+        &[
+            ("value", value.to_string()),
+            ("passphrase", PASSWORD.to_string()),
+            ("field_key", EncryptionUtils::to_hex(&field_key)),
+            ("salt", EncryptionUtils::to_hex(&salt)),
+            ("key_nonce", EncryptionUtils::to_hex(&key_nonce)),
+            ("nonce", EncryptionUtils::to_hex(&nonce)),
+        ],
+        &[
+            ("ciphertext", hex_of(&envelope.ciphertext)?),
+            ("wrapped_key", hex_of(&envelope.wrapped_key)?),
+        ],
+    ))
+}
+
+/// 7z picks its own IV when writing, so this vector is one to read
+fn archive() -> TestVector {
+    vector(
+        "archive",
+        "7z archive with encrypted header; AES-256-CBC keyed by the 7z SHA-256 key \
+         derivation (2^19 rounds) of the UTF-16LE password",
+        &[
+            ("password", KNOWN_ARCHIVE_PASSWORD.to_string()),
+            ("archive", KNOWN_ARCHIVE.to_string()),
+        ],
+        &[(
+            "self-test.txt",
+            EncryptionUtils::to_hex(KNOWN_ARCHIVE_CONTENT),
+        )],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::self_test::from_hex;
+    use crate::models::FieldProtection;
+
+    #[test]
+    fn test_vectors_are_stable_and_open() {
+        let set = generate().unwrap();
+        assert_eq!(set, generate().unwrap());
+        let output = |name: &str, key: &str| {
+            set.vectors.iter().find(|v| v.name == name).unwrap().outputs[key].clone()
+        };
+
+        // Cross-checked against Python's hashlib and the cryptography package
+        assert_eq!(
+            output("key-derivation", "key"),
+            "cadaf3bf4679faa20f1a0f2a7c82adc5144756f6c60af04717788fc999bf70c2"
+        );
+        assert_eq!(
+            output("key-file-composite", "archive_password"),
+            "0b49328382a7cce9af2775bf2a11b867a74044fca6767e29997e983bd3f6cd94"
+        );
+        assert_eq!(
+            output("field-envelope", "ciphertext"),
+            "4f3d1fe109e6eeb3fe6f2be26b8e722659586b8cc84e1e34e89800beaa7d"
+        );
+        assert_eq!(
+            output("field-envelope", "wrapped_key"),
+            "0600573116869406b5ca29c9d23008addd54c8159e9f50e1cd7f1191a4394\
+             7bb0546b72ccec81ac0d7390f571b92b7d7"
+        );
+
+        let envelope = FieldProtection {
+            salt: BASE64_STANDARD.encode(sequence(0xa0, 32)),
+            key_nonce: BASE64_STANDARD.encode(sequence(0xc0, 12)),
+            wrapped_key: BASE64_STANDARD.encode(from_hex(&output("field-envelope", "wrapped_key"))),
+            nonce: BASE64_STANDARD.encode(sequence(0xd0, 12)),
+            ciphertext: BASE64_STANDARD.encode(from_hex(&output("field-envelope", "ciphertext"))),
+        };
+        let opened = CredentialCrypto::open_envelope(&envelope, PASSWORD).unwrap();
+        assert_eq!(opened.as_str(), "1234-5678-9012");
+    }
+}
@@ -0,0 +1,161 @@
+//! Trash of deleted credentials and the quotas that bound it
+//!
+//! With a trash enabled in the [`RetentionQuotas`], deleting a credential
+//! moves it to the trash, stored under `trash/` in the archive, where it can
+//! be restored until a save prunes it. By default there is none and deleting
+//! removes the credential outright. Each save also trims password history to
+//! the per-credential limit and reports what was removed in a
+//! [`PruneReport`].
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::memory_budget::wipe_record;
+use crate::models::{CredentialRecord, FieldHistoryEntry};
+
+/// Directory of trashed credentials within the archive
+pub const TRASH_DIR: &str = "trash";
+
+/// How much deleted and replaced data a repository keeps
+///
+/// Stored as the `quotas` section of a
+/// [`RepositoryConfig`](crate::config::RepositoryConfig), which opening the
+/// repository applies, or set with
+/// [`UnifiedRepositoryManager::set_retention_quotas`](crate::core::UnifiedRepositoryManager::set_retention_quotas).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetentionQuotas {
+    /// Earlier values kept per password field; 0 keeps none
+    pub max_revisions_per_credential: usize,
+
+    /// Credentials kept in the trash, oldest dropped first; 0, the default,
+    /// deletes credentials outright
+    pub max_trash_items: usize,
+
+    /// Days a credential stays in the trash; 0 keeps it until
+    /// `max_trash_items` pushes it out
+    pub max_trash_age_days: u32,
+}
+
+impl Default for RetentionQuotas {
+    fn default() -> Self {
+        Self {
+            max_revisions_per_credential: crate::models::DEFAULT_FIELD_HISTORY_LIMIT,
+            max_trash_items: 0,
+            max_trash_age_days: 30,
+        }
+    }
+}
+
+impl RetentionQuotas {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_max_revisions_per_credential(mut self, max: usize) -> Self {
+        self.max_revisions_per_credential = max;
+        self
+    }
+
+    pub fn with_max_trash_items(mut self, max: usize) -> Self {
+        self.max_trash_items = max;
+        self
+    }
+
+    pub fn with_max_trash_age_days(mut self, days: u32) -> Self {
+        self.max_trash_age_days = days;
+        self
+    }
+
+    /// Whether a credential deleted at `deleted_at` is past the age limit
+    /// at `now`
+    pub fn is_expired(&self, deleted_at: i64, now: i64) -> bool {
+        self.max_trash_age_days > 0
+            && now.saturating_sub(deleted_at) > i64::from(self.max_trash_age_days) * 86_400
+    }
+}
+
+/// A deleted credential, with its notes inline
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrashedCredential {
+    /// Unix time of the deletion
+    pub deleted_at: i64,
+    pub credential: CredentialRecord,
+}
+
+/// What a save removed to stay within the [`RetentionQuotas`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PruneReport {
+    /// IDs of credentials dropped from the trash for good
+    pub trash_removed: Vec<String>,
+
+    /// IDs of credentials whose password history was trimmed
+    pub history_trimmed: Vec<String>,
+
+    /// Password history entries removed across all credentials
+    pub history_entries_removed: usize,
+}
+
+impl PruneReport {
+    /// Whether nothing was removed
+    pub fn is_empty(&self) -> bool {
+        self.trash_removed.is_empty() && self.history_entries_removed == 0
+    }
+}
+
+/// What a prune took out of a repository, kept until the save it ran for
+/// has written the archive
+///
+/// [`UnifiedMemoryRepository::restore_pruned`](crate::core::UnifiedMemoryRepository)
+/// puts it back if the save fails; dropping it wipes the trashed credentials.
+#[derive(Debug)]
+pub(crate) struct PrunedEntries {
+    pub(crate) trash: Vec<TrashedCredential>,
+
+    /// Password history of each trimmed credential as it was before
+    pub(crate) history: Vec<(String, Vec<FieldHistoryEntry>)>,
+
+    pub(crate) history_prune_pending: bool,
+    pub(crate) modified: bool,
+}
+
+impl Drop for PrunedEntries {
+    fn drop(&mut self) {
+        for removed in &mut self.trash {
+            wipe_record(&mut removed.credential);
+        }
+    }
+}
+
+/// Path of a trashed credential within the archive
+pub fn trash_path(id: &str) -> String {
+    format!("{}/{}.yml", TRASH_DIR, id)
+}
+
+/// Whether `path` is a trashed credential, matching what loading accepts
+pub fn is_trash_path(path: &str) -> bool {
+    let path = path.replace('\\', "/");
+    path.starts_with(&format!("{}/", TRASH_DIR)) && path.ends_with(".yml")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expiry() {
+        let quotas = RetentionQuotas::new().with_max_trash_age_days(2);
+        assert!(!quotas.is_expired(0, 2 * 86_400));
+        assert!(quotas.is_expired(0, 2 * 86_400 + 1));
+
+        let forever = quotas.with_max_trash_age_days(0);
+        assert!(!forever.is_expired(0, i64::MAX));
+    }
+
+    #[test]
+    fn test_trash_paths() {
+        assert_eq!(trash_path("abc"), "trash/abc.yml");
+        assert!(is_trash_path("trash/abc.yml"));
+        assert!(is_trash_path("trash\\abc.yml"));
+        assert!(!is_trash_path("credentials/abc/record.yml"));
+    }
+}
@@ -5,7 +5,10 @@
 
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::core::archive_backend::SEVEN_ZIP_BACKEND;
+use crate::models::{FolderDefaultsMap, RecurringCredential, VaultMember};
 
 /// Repository metadata containing version and structural information
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -30,6 +33,36 @@ pub struct RepositoryMetadata {
 
     /// Generator identifier
     pub generator: String,
+
+    /// Defaults set on folders, keyed by normalized folder path
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub folders: FolderDefaultsMap,
+
+    /// Credentials created again from a template on a schedule
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub recurring: Vec<RecurringCredential>,
+
+    /// People the vault is shared with
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub members: Vec<VaultMember>,
+
+    /// Stable identifier of the repository, recorded as the origin of exports
+    ///
+    /// Repositories created before it existed get one when they are next saved.
+    #[serde(default = "new_repository_id")]
+    pub repository_id: String,
+
+    /// Archive backend the repository was last saved with
+    #[serde(default = "default_archive_backend")]
+    pub archive_backend: String,
+}
+
+fn new_repository_id() -> String {
+    uuid::Uuid::new_v4().to_string()
+}
+
+fn default_archive_backend() -> String {
+    SEVEN_ZIP_BACKEND.to_string()
 }
 
 impl Default for RepositoryMetadata {
@@ -43,6 +76,11 @@ impl Default for RepositoryMetadata {
             credential_count: 0,
             structure_version: "1.0".to_string(),
             generator: "ziplock-unified".to_string(),
+            folders: FolderDefaultsMap::new(),
+            recurring: Vec::new(),
+            members: Vec::new(),
+            repository_id: new_repository_id(),
+            archive_backend: default_archive_backend(),
         }
     }
 }
@@ -67,11 +105,39 @@ pub struct RepositoryStats {
     pub modified: bool,
 }
 
+/// Steps of a master password change, reported in order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PasswordChangeStage {
+    /// Encrypting the repository with the new password
+    Encrypting,
+    /// Writing the new archive next to the current one
+    Writing,
+    /// Decrypting the written archive to check it is complete
+    Verifying,
+    /// Swapping the new archive in for the current one
+    Replacing,
+    /// The repository is encrypted with the new password
+    Complete,
+}
+
+impl PasswordChangeStage {
+    /// Number of stages, for progress bars
+    pub const COUNT: u32 = 5;
+
+    /// One-based position of this stage
+    pub fn step(&self) -> u32 {
+        *self as u32 + 1
+    }
+}
+
 /// Constants for repository structure
 pub const METADATA_FILE: &str = "metadata.yml";
 pub const CREDENTIALS_INDEX_FILE: &str = "credentials/index.yml";
 pub const CREDENTIALS_DIR: &str = "credentials";
 pub const ATTACHMENTS_DIR: &str = "attachments";
+pub const QUARANTINE_DIR: &str = "quarantine";
+pub const TEMPLATES_DIR: &str = "templates";
 
 /// Repository format constants
 pub const CURRENT_VERSION: &str = "1.0";
@@ -82,6 +148,14 @@ pub const GENERATOR_NAME: &str = "ziplock-unified";
 /// Maximum field value length to prevent memory issues
 pub const MAX_FIELD_VALUE_LENGTH: usize = 10_000;
 
+/// Maximum length of multi-line text fields, such as the content of a
+/// secure note
+pub const MAX_NOTE_LENGTH: usize = 1_000_000;
+
+/// Multi-line text fields longer than this are stored in an archive entry of
+/// their own and only decoded when their credential is opened
+pub const NOTE_INLINE_LENGTH: usize = 64 * 1024;
+
 /// Maximum number of fields per credential
 pub const MAX_FIELDS_PER_CREDENTIAL: usize = 50;
 
@@ -0,0 +1,291 @@
+//! Throttling of failed unlock attempts
+//!
+//! Every wrong master password makes the next attempt wait longer, starting
+//! at [`UnlockThrottlePolicy::backoff_secs`] and doubling; after
+//! `max_attempts` failures in a row the repository is locked out for
+//! `lockout_secs`. The [`UnlockAttempts`] of each repository are kept in the
+//! app config rather than the archive, so a throttled vault is never
+//! rewritten and a copy of it opens normally on another device.
+//!
+//! This slows down guessing through the app; it does nothing against
+//! someone who attacks a copy of the archive directly, which only a strong
+//! master password prevents.
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::config::SecurityConfig;
+use crate::core::errors::{CoreError, CoreResult, FileError};
+
+/// Upper limit of the backoff delay when lockouts are disabled
+const MAX_BACKOFF_SECS: u64 = 3600;
+
+/// How failed unlock attempts are throttled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UnlockThrottlePolicy {
+    /// Failures in a row that trigger a lockout (0 = never lock out)
+    pub max_attempts: u32,
+    /// Seconds a lockout lasts
+    pub lockout_secs: u64,
+    /// Seconds to wait after the first failure, doubled after each further
+    /// one up to the lockout length (0 = no delay)
+    pub backoff_secs: u64,
+}
+
+impl Default for UnlockThrottlePolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            lockout_secs: 300,
+            backoff_secs: 1,
+        }
+    }
+}
+
+/// Failed attempts on one repository
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UnlockAttempts {
+    /// Failures since the last success or lockout
+    pub failures: u32,
+    /// Earliest time of the next attempt
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_at: Option<DateTime<Utc>>,
+    /// Whether `retry_at` ends a lockout rather than a backoff delay
+    pub locked_out: bool,
+}
+
+impl UnlockAttempts {
+    /// Whether nothing is left to remember, so the entry can be dropped
+    pub fn is_clear(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// The state as of `now`, with an ended lockout forgotten
+    fn current(&self, now: DateTime<Utc>) -> Self {
+        match self.retry_at {
+            Some(retry_at) if self.locked_out && retry_at <= now => Self::default(),
+            _ => self.clone(),
+        }
+    }
+}
+
+/// What a lock screen shows about the next attempt
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnlockStatus {
+    /// Attempts left before a lockout; `None` when lockouts are disabled
+    pub remaining_attempts: Option<u32>,
+    /// Seconds until the next attempt is allowed (0 = now)
+    pub retry_in_secs: u64,
+    /// Whether the wait is a lockout
+    pub locked_out: bool,
+}
+
+impl UnlockThrottlePolicy {
+    pub fn new(max_attempts: u32, lockout_secs: u64, backoff_secs: u64) -> Self {
+        Self {
+            max_attempts,
+            lockout_secs,
+            backoff_secs,
+        }
+    }
+
+    /// The policy set in the security settings
+    pub fn from_config(security: &SecurityConfig) -> Self {
+        Self::new(
+            security.max_auth_attempts,
+            security.lockout_duration,
+            security.unlock_backoff_secs,
+        )
+    }
+
+    /// Where `attempts` stand as of `now`
+    pub fn status(&self, attempts: &UnlockAttempts, now: DateTime<Utc>) -> UnlockStatus {
+        let attempts = attempts.current(now);
+        let retry_in_secs = attempts
+            .retry_at
+            .map(|retry_at| {
+                // Rounded up, so a countdown never shows 0 while still waiting
+                let millis = (retry_at - now).num_milliseconds().max(0) as u64;
+                millis.div_ceil(1000)
+            })
+            .unwrap_or(0);
+        UnlockStatus {
+            remaining_attempts: (self.max_attempts > 0).then(|| {
+                if attempts.locked_out {
+                    0
+                } else {
+                    self.max_attempts.saturating_sub(attempts.failures)
+                }
+            }),
+            retry_in_secs,
+            locked_out: attempts.locked_out && retry_in_secs > 0,
+        }
+    }
+
+    /// Refuse an attempt while a delay or lockout runs
+    pub fn check(&self, attempts: &UnlockAttempts, now: DateTime<Utc>) -> CoreResult<()> {
+        let status = self.status(attempts, now);
+        if status.retry_in_secs > 0 {
+            return Err(CoreError::UnlockThrottled {
+                retry_in_secs: status.retry_in_secs,
+                locked_out: status.locked_out,
+            });
+        }
+        Ok(())
+    }
+
+    /// Count a wrong password at `now`
+    pub fn record_failure(&self, attempts: &mut UnlockAttempts, now: DateTime<Utc>) {
+        let mut next = attempts.current(now);
+        next.failures = next.failures.saturating_add(1);
+        if self.max_attempts > 0 && next.failures >= self.max_attempts {
+            next = UnlockAttempts {
+                failures: 0,
+                retry_at: Some(now + seconds(self.lockout_secs)),
+                locked_out: true,
+            };
+        } else {
+            let delay = self.delay_after(next.failures);
+            next.retry_at = (delay > 0).then(|| now + seconds(delay));
+            next.locked_out = false;
+        }
+        *attempts = next;
+    }
+
+    /// Forget the failures after a successful unlock
+    pub fn record_success(&self, attempts: &mut UnlockAttempts) {
+        *attempts = UnlockAttempts::default();
+    }
+
+    /// Run `unlock` unless throttled, and record whether the password was
+    /// right; errors other than a wrong password are not counted
+    pub fn attempt<T>(
+        &self,
+        attempts: &mut UnlockAttempts,
+        now: DateTime<Utc>,
+        unlock: impl FnOnce() -> CoreResult<T>,
+    ) -> CoreResult<T> {
+        self.check(attempts, now)?;
+        let result = unlock();
+        match &result {
+            Ok(_) => self.record_success(attempts),
+            Err(CoreError::FileOperation(FileError::InvalidPassword)) => {
+                self.record_failure(attempts, now)
+            }
+            Err(_) => {}
+        }
+        result
+    }
+
+    /// Backoff after the `failures`-th failure in a row
+    fn delay_after(&self, failures: u32) -> u64 {
+        if self.backoff_secs == 0 || failures == 0 {
+            return 0;
+        }
+        let cap = if self.lockout_secs > 0 {
+            self.lockout_secs
+        } else {
+            MAX_BACKOFF_SECS
+        };
+        let factor = 1u64.checked_shl(failures - 1).unwrap_or(u64::MAX);
+        self.backoff_secs.saturating_mul(factor).min(cap)
+    }
+}
+
+fn seconds(secs: u64) -> Duration {
+    Duration::seconds(secs.min(i64::MAX as u64 / 1000) as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_then_lockout() {
+        let policy = UnlockThrottlePolicy::new(4, 300, 2);
+        let start = Utc::now();
+        let secs = |s| start + Duration::seconds(s);
+        let mut attempts = UnlockAttempts::default();
+
+        policy.record_failure(&mut attempts, start);
+        assert_eq!(
+            policy.status(&attempts, start),
+            UnlockStatus {
+                remaining_attempts: Some(3),
+                retry_in_secs: 2,
+                locked_out: false,
+            }
+        );
+        assert_eq!(
+            policy.check(&attempts, secs(1)),
+            Err(CoreError::UnlockThrottled {
+                retry_in_secs: 1,
+                locked_out: false,
+            })
+        );
+        assert_eq!(policy.check(&attempts, secs(2)), Ok(()));
+
+        policy.record_failure(&mut attempts, secs(2));
+        assert_eq!(policy.status(&attempts, secs(2)).retry_in_secs, 4);
+        policy.record_failure(&mut attempts, secs(6));
+        assert_eq!(policy.status(&attempts, secs(6)).retry_in_secs, 8);
+
+        policy.record_failure(&mut attempts, secs(14));
+        let status = policy.status(&attempts, secs(14));
+        assert_eq!(status.remaining_attempts, Some(0));
+        assert_eq!(status.retry_in_secs, 300);
+        assert!(status.locked_out);
+
+        // A fresh round once the lockout is over
+        let status = policy.status(&attempts, secs(314));
+        assert_eq!(status.remaining_attempts, Some(4));
+        assert!(!status.locked_out);
+        policy.record_failure(&mut attempts, secs(314));
+        assert_eq!(attempts.failures, 1);
+        assert_eq!(policy.status(&attempts, secs(314)).retry_in_secs, 2);
+    }
+
+    #[test]
+    fn test_attempt_counts_only_wrong_passwords() {
+        let policy = UnlockThrottlePolicy::new(0, 0, 1);
+        let now = Utc::now();
+        let mut attempts = UnlockAttempts::default();
+
+        let result: CoreResult<()> = policy.attempt(&mut attempts, now, || {
+            Err(CoreError::FileOperation(FileError::NotFound {
+                path: "/vault.7z".into(),
+            }))
+        });
+        assert!(result.is_err());
+        assert!(attempts.is_clear());
+
+        let result: CoreResult<()> = policy.attempt(&mut attempts, now, || {
+            Err(CoreError::FileOperation(FileError::InvalidPassword))
+        });
+        assert!(result.is_err());
+        assert_eq!(attempts.failures, 1);
+        assert_eq!(policy.status(&attempts, now).remaining_attempts, None);
+
+        // Throttled: the closure does not run
+        let result = policy.attempt(&mut attempts, now, || -> CoreResult<()> {
+            panic!("should not be called")
+        });
+        assert!(matches!(result, Err(CoreError::UnlockThrottled { .. })));
+
+        let later = now + Duration::seconds(1);
+        assert_eq!(policy.attempt(&mut attempts, later, || Ok(7)), Ok(7));
+        assert!(attempts.is_clear());
+    }
+
+    #[test]
+    fn test_backoff_is_capped() {
+        let policy = UnlockThrottlePolicy::new(0, 0, 1);
+        assert_eq!(policy.delay_after(1), 1);
+        assert_eq!(policy.delay_after(12), 2048);
+        assert_eq!(policy.delay_after(13), MAX_BACKOFF_SECS);
+        assert_eq!(policy.delay_after(200), MAX_BACKOFF_SECS);
+        assert_eq!(UnlockThrottlePolicy::new(3, 60, 1).delay_after(10), 60);
+    }
+}
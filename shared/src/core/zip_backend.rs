@@ -0,0 +1,637 @@
+//! AES-encrypted ZIP files as repository archives
+//!
+//! Entries are encrypted with WinZip's AE-2 scheme (AES-256 in counter mode
+//! with an HMAC-SHA1 tag per entry), which 7-Zip, WinZip, libarchive's
+//! `bsdtar` and most other archivers can open, so a vault can be unpacked
+//! without ZipLock. The format has two weaknesses compared to 7z: entry
+//! names are stored in the clear, and the key is derived with only 1,000
+//! rounds of PBKDF2-HMAC-SHA1, so a ZIP vault needs a long master password.
+//!
+//! Only what repositories need is supported: stored and deflated entries,
+//! no ZIP64, no traditional PKWARE encryption.
+
+use std::io::{Read, Write};
+
+use aes::Aes256;
+use ctr::cipher::{KeyIvInit, StreamCipher};
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use hmac::{Hmac, Mac};
+use rand::{rngs::OsRng, RngCore};
+use sha1::Sha1;
+use tracing::debug;
+use zeroize::Zeroizing;
+
+use crate::core::archive_backend::{ArchiveBackend, ArchiveOptions};
+use crate::core::errors::{FileError, FileResult};
+use crate::core::types::FileMap;
+use crate::utils::encryption::{EncryptionUtils, SecretBytes};
+
+/// Name of the ZIP backend
+pub const ZIP_BACKEND: &str = "zip";
+
+const LOCAL_HEADER_SIGNATURE: u32 = 0x0403_4b50;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x0201_4b50;
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x0605_4b50;
+const END_OF_CENTRAL_DIRECTORY_LEN: usize = 22;
+
+const METHOD_STORED: u16 = 0;
+const METHOD_DEFLATED: u16 = 8;
+const METHOD_AES: u16 = 99;
+
+const FLAG_ENCRYPTED: u16 = 1;
+const FLAG_UTF8: u16 = 1 << 11;
+
+/// Made by a Unix system, specification version 6.3
+const VERSION_MADE_BY: u16 = (3 << 8) | 63;
+const VERSION_DEFLATE: u16 = 20;
+const VERSION_AES: u16 = 51;
+
+/// Header ID, vendor version 2 (AE-2, no CRC), vendor ID, AES-256
+const AES_EXTRA_ID: u16 = 0x9901;
+const AES_VENDOR_ID: &[u8; 2] = b"AE";
+const AE_1: u16 = 1;
+const AE_2: u16 = 2;
+const AES_256: u8 = 3;
+
+const SALT_LEN: usize = 16;
+const KEY_LEN: usize = 32;
+const VERIFIER_LEN: usize = 2;
+const TAG_LEN: usize = 10;
+const KDF_ROUNDS: u32 = 1000;
+
+type Aes256Ctr = ctr::Ctr128LE<Aes256>;
+type HmacSha1 = Hmac<Sha1>;
+
+/// ZIP files with AES-256 encrypted entries
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ZipAesBackend;
+
+impl ArchiveBackend for ZipAesBackend {
+    fn name(&self) -> &'static str {
+        ZIP_BACKEND
+    }
+
+    fn recognizes(&self, data: &[u8]) -> bool {
+        [LOCAL_HEADER_SIGNATURE, END_OF_CENTRAL_DIRECTORY_SIGNATURE]
+            .iter()
+            .any(|signature| data.starts_with(&signature.to_le_bytes()))
+    }
+
+    fn extract(&self, data: &[u8], password: &str) -> FileResult<FileMap> {
+        debug!("Extracting ZIP archive: {} bytes", data.len());
+        let mut files = FileMap::new();
+        for entry in central_directory(data)? {
+            if entry.name.ends_with('/') {
+                continue;
+            }
+            let content = entry.read(data, password)?;
+            files.insert(entry.name.replace('\\', "/"), content);
+        }
+        Ok(files)
+    }
+
+    fn create(&self, files: FileMap, password: &str) -> FileResult<Vec<u8>> {
+        self.create_with_options(files, password, &ArchiveOptions::default())
+    }
+
+    /// Deflates at the compression level, or stores entries at level 0;
+    /// the other options only apply to 7z
+    fn create_with_options(
+        &self,
+        files: FileMap,
+        password: &str,
+        options: &ArchiveOptions,
+    ) -> FileResult<Vec<u8>> {
+        let (time, date) = dos_timestamp(chrono::Utc::now().naive_utc());
+        let mut paths: Vec<&String> = files.keys().collect();
+        paths.sort();
+
+        let mut archive = Vec::new();
+        let mut central = Vec::new();
+        for path in &paths {
+            let content = &files[*path];
+            let method = if options.compression_level == 0 {
+                METHOD_STORED
+            } else {
+                METHOD_DEFLATED
+            };
+            let packed = compress(content, method, options.compression_level)?;
+
+            let mut header = EntryHeader {
+                name: path.to_string(),
+                version_needed: VERSION_DEFLATE,
+                flags: FLAG_UTF8,
+                method,
+                time,
+                date,
+                crc: crc32fast::hash(content),
+                compressed_size: 0,
+                size: to_u32(content.len(), path)?,
+                extra: Vec::new(),
+                local_offset: to_u32(archive.len(), path)?,
+            };
+            let data = if password.is_empty() {
+                packed
+            } else {
+                header.version_needed = VERSION_AES;
+                header.flags |= FLAG_ENCRYPTED;
+                header.method = METHOD_AES;
+                header.crc = 0;
+                header.extra = aes_extra_field(method);
+                seal(&packed, password)
+            };
+            header.compressed_size = to_u32(data.len(), path)?;
+
+            header.write_local(&mut archive);
+            archive.extend_from_slice(&data);
+            header.write_central(&mut central);
+        }
+
+        let count = u16::try_from(paths.len()).map_err(|_| FileError::CreationFailed {
+            message: "Too many entries for a ZIP archive".to_string(),
+        })?;
+        let central_offset = to_u32(archive.len(), "central directory")?;
+        let central_size = to_u32(central.len(), "central directory")?;
+        archive.extend_from_slice(&central);
+        put_u32(&mut archive, END_OF_CENTRAL_DIRECTORY_SIGNATURE);
+        put_u16(&mut archive, 0);
+        put_u16(&mut archive, 0);
+        put_u16(&mut archive, count);
+        put_u16(&mut archive, count);
+        put_u32(&mut archive, central_size);
+        put_u32(&mut archive, central_offset);
+        put_u16(&mut archive, 0);
+        debug!("ZIP archive created: {} bytes", archive.len());
+        Ok(archive)
+    }
+}
+
+/// Fields shared by an entry's local and central directory headers
+struct EntryHeader {
+    name: String,
+    version_needed: u16,
+    flags: u16,
+    method: u16,
+    time: u16,
+    date: u16,
+    crc: u32,
+    compressed_size: u32,
+    size: u32,
+    extra: Vec<u8>,
+    local_offset: u32,
+}
+
+impl EntryHeader {
+    fn write_local(&self, out: &mut Vec<u8>) {
+        put_u32(out, LOCAL_HEADER_SIGNATURE);
+        put_u16(out, self.version_needed);
+        self.write_common(out);
+        out.extend_from_slice(self.name.as_bytes());
+        out.extend_from_slice(&self.extra);
+    }
+
+    fn write_central(&self, out: &mut Vec<u8>) {
+        put_u32(out, CENTRAL_HEADER_SIGNATURE);
+        put_u16(out, VERSION_MADE_BY);
+        put_u16(out, self.version_needed);
+        self.write_common(out);
+        put_u16(out, 0); // comment length
+        put_u16(out, 0); // disk number
+        put_u16(out, 0); // internal attributes
+        put_u32(out, 0o100600 << 16); // regular file, rw-------
+        put_u32(out, self.local_offset);
+        out.extend_from_slice(self.name.as_bytes());
+        out.extend_from_slice(&self.extra);
+    }
+
+    fn write_common(&self, out: &mut Vec<u8>) {
+        put_u16(out, self.flags);
+        put_u16(out, self.method);
+        put_u16(out, self.time);
+        put_u16(out, self.date);
+        put_u32(out, self.crc);
+        put_u32(out, self.compressed_size);
+        put_u32(out, self.size);
+        put_u16(out, self.name.len() as u16);
+        put_u16(out, self.extra.len() as u16);
+    }
+
+    /// The entry's content, decrypted and decompressed
+    fn read(&self, archive: &[u8], password: &str) -> FileResult<Vec<u8>> {
+        let mut local = Cursor::at(archive, self.local_offset as usize);
+        if local.u32()? != LOCAL_HEADER_SIGNATURE {
+            return Err(corrupted(format!(
+                "Entry '{}' has no local header",
+                self.name
+            )));
+        }
+        local.skip(22)?;
+        let name_len = local.u16()? as usize;
+        let extra_len = local.u16()? as usize;
+        local.skip(name_len + extra_len)?;
+        let data = local.take(self.compressed_size as usize)?;
+
+        let mut method = self.method;
+        let mut check_crc = true;
+        let packed = if self.flags & FLAG_ENCRYPTED != 0 {
+            if self.method != METHOD_AES {
+                return Err(FileError::ExtractionFailed {
+                    message: format!(
+                        "Entry '{}' uses ZipCrypto encryption, which is not supported",
+                        self.name
+                    ),
+                });
+            }
+            let aes = self.aes_field()?;
+            method = aes.method;
+            check_crc = aes.version == AE_1;
+            open(data, password, &self.name)?
+        } else if !password.is_empty() {
+            return Err(FileError::ExtractionFailed {
+                message: format!("Entry '{}' is not encrypted", self.name),
+            });
+        } else {
+            Zeroizing::new(data.to_vec())
+        };
+
+        let content = decompress(&packed, method, self.size as usize, &self.name)?;
+        if check_crc && crc32fast::hash(&content) != self.crc {
+            return Err(corrupted(format!(
+                "Entry '{}' fails its CRC check",
+                self.name
+            )));
+        }
+        Ok(content)
+    }
+
+    fn aes_field(&self) -> FileResult<AesField> {
+        let mut extra = Cursor::at(&self.extra, 0);
+        while extra.remaining() >= 4 {
+            let id = extra.u16()?;
+            let len = extra.u16()? as usize;
+            let field = extra.take(len)?;
+            if id != AES_EXTRA_ID {
+                continue;
+            }
+            let mut field = Cursor::at(field, 0);
+            let version = field.u16()?;
+            let vendor = field.take(2)?;
+            let strength = field.take(1)?[0];
+            let method = field.u16()?;
+            if vendor != AES_VENDOR_ID || !matches!(version, AE_1 | AE_2) {
+                return Err(corrupted(format!(
+                    "Entry '{}' has an unknown encryption header",
+                    self.name
+                )));
+            }
+            if strength != AES_256 {
+                return Err(FileError::ExtractionFailed {
+                    message: format!(
+                        "Entry '{}' is not encrypted with AES-256, which is the only key size supported",
+                        self.name
+                    ),
+                });
+            }
+            return Ok(AesField { version, method });
+        }
+        Err(corrupted(format!(
+            "Entry '{}' is missing its encryption header",
+            self.name
+        )))
+    }
+}
+
+/// The WinZip AES extra field of an entry
+struct AesField {
+    version: u16,
+    /// Compression method applied before encryption
+    method: u16,
+}
+
+fn aes_extra_field(method: u16) -> Vec<u8> {
+    let mut field = Vec::with_capacity(11);
+    put_u16(&mut field, AES_EXTRA_ID);
+    put_u16(&mut field, 7);
+    put_u16(&mut field, AE_2);
+    field.extend_from_slice(AES_VENDOR_ID);
+    field.push(AES_256);
+    put_u16(&mut field, method);
+    field
+}
+
+/// Headers of every entry, in central directory order
+fn central_directory(archive: &[u8]) -> FileResult<Vec<EntryHeader>> {
+    let end = find_end_of_central_directory(archive)?;
+    let mut cursor = Cursor::at(archive, end + 10);
+    let count = cursor.u16()?;
+    cursor.skip(4)?;
+    let offset = cursor.u32()?;
+    if count == u16::MAX || offset == u32::MAX {
+        return Err(FileError::ExtractionFailed {
+            message: "ZIP64 archives are not supported".to_string(),
+        });
+    }
+
+    let mut cursor = Cursor::at(archive, offset as usize);
+    let mut entries = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        if cursor.u32()? != CENTRAL_HEADER_SIGNATURE {
+            return Err(corrupted("Invalid central directory".to_string()));
+        }
+        cursor.skip(2)?;
+        let version_needed = cursor.u16()?;
+        let flags = cursor.u16()?;
+        let method = cursor.u16()?;
+        let time = cursor.u16()?;
+        let date = cursor.u16()?;
+        let crc = cursor.u32()?;
+        let compressed_size = cursor.u32()?;
+        let size = cursor.u32()?;
+        let name_len = cursor.u16()? as usize;
+        let extra_len = cursor.u16()? as usize;
+        let comment_len = cursor.u16()? as usize;
+        cursor.skip(8)?;
+        let local_offset = cursor.u32()?;
+        let name = String::from_utf8(cursor.take(name_len)?.to_vec())
+            .map_err(|_| corrupted("Entry name is not valid UTF-8".to_string()))?;
+        let extra = cursor.take(extra_len)?.to_vec();
+        cursor.skip(comment_len)?;
+        if compressed_size == u32::MAX || size == u32::MAX || local_offset == u32::MAX {
+            return Err(FileError::ExtractionFailed {
+                message: "ZIP64 archives are not supported".to_string(),
+            });
+        }
+        entries.push(EntryHeader {
+            name,
+            version_needed,
+            flags,
+            method,
+            time,
+            date,
+            crc,
+            compressed_size,
+            size,
+            extra,
+            local_offset,
+        });
+    }
+    Ok(entries)
+}
+
+/// Offset of the end of central directory record, which is followed by a
+/// comment of up to 64 KiB
+fn find_end_of_central_directory(archive: &[u8]) -> FileResult<usize> {
+    let last = archive
+        .len()
+        .checked_sub(END_OF_CENTRAL_DIRECTORY_LEN)
+        .ok_or_else(|| corrupted("Archive is too short".to_string()))?;
+    let first = last.saturating_sub(u16::MAX as usize);
+    let signature = END_OF_CENTRAL_DIRECTORY_SIGNATURE.to_le_bytes();
+    (first..=last)
+        .rev()
+        .find(|&offset| archive[offset..].starts_with(&signature))
+        .ok_or_else(|| corrupted("End of central directory not found".to_string()))
+}
+
+/// Keys derived from the password and an entry's salt
+struct EntryKeys {
+    encryption: SecretBytes,
+    authentication: SecretBytes,
+    verifier: [u8; VERIFIER_LEN],
+}
+
+impl EntryKeys {
+    fn derive(password: &str, salt: &[u8]) -> Self {
+        let mut derived = SecretBytes::zeroed(2 * KEY_LEN + VERIFIER_LEN);
+        pbkdf2::pbkdf2_hmac::<Sha1>(password.as_bytes(), salt, KDF_ROUNDS, derived.expose_mut());
+        let derived = derived.expose();
+        let mut verifier = [0; VERIFIER_LEN];
+        verifier.copy_from_slice(&derived[2 * KEY_LEN..]);
+        Self {
+            encryption: SecretBytes::from_slice(&derived[..KEY_LEN]),
+            authentication: SecretBytes::from_slice(&derived[KEY_LEN..2 * KEY_LEN]),
+            verifier,
+        }
+    }
+
+    /// Encrypt or decrypt in place; the counter is little endian and
+    /// starts at 1
+    fn apply_keystream(&self, data: &mut [u8]) {
+        let mut counter = [0u8; 16];
+        counter[0] = 1;
+        Aes256Ctr::new(self.encryption.expose().into(), &counter.into()).apply_keystream(data);
+    }
+
+    fn authenticator(&self) -> HmacSha1 {
+        <HmacSha1 as Mac>::new_from_slice(self.authentication.expose())
+            .expect("HMAC takes any key length")
+    }
+}
+
+/// Salt, password verifier, ciphertext and authentication tag
+fn seal(packed: &[u8], password: &str) -> Vec<u8> {
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let keys = EntryKeys::derive(password, &salt);
+
+    let mut sealed = Vec::with_capacity(SALT_LEN + VERIFIER_LEN + packed.len() + TAG_LEN);
+    sealed.extend_from_slice(&salt);
+    sealed.extend_from_slice(&keys.verifier);
+    let start = sealed.len();
+    sealed.extend_from_slice(packed);
+    keys.apply_keystream(&mut sealed[start..]);
+    let mut mac = keys.authenticator();
+    mac.update(&sealed[start..]);
+    sealed.extend_from_slice(&mac.finalize().into_bytes()[..TAG_LEN]);
+    sealed
+}
+
+fn open(sealed: &[u8], password: &str, name: &str) -> FileResult<Zeroizing<Vec<u8>>> {
+    if sealed.len() < SALT_LEN + VERIFIER_LEN + TAG_LEN {
+        return Err(corrupted(format!("Entry '{}' is truncated", name)));
+    }
+    let (salt, rest) = sealed.split_at(SALT_LEN);
+    let (verifier, rest) = rest.split_at(VERIFIER_LEN);
+    let (ciphertext, tag) = rest.split_at(rest.len() - TAG_LEN);
+
+    let keys = EntryKeys::derive(password, salt);
+    if !EncryptionUtils::secure_compare(&keys.verifier, verifier) {
+        return Err(FileError::InvalidPassword);
+    }
+    let mut mac = keys.authenticator();
+    mac.update(ciphertext);
+    // One wrong password in 65,536 passes the verifier
+    mac.verify_truncated_left(tag)
+        .map_err(|_| FileError::InvalidPassword)?;
+
+    let mut packed = Zeroizing::new(ciphertext.to_vec());
+    keys.apply_keystream(&mut packed);
+    Ok(packed)
+}
+
+fn compress(content: &[u8], method: u16, level: u32) -> FileResult<Vec<u8>> {
+    if method == METHOD_STORED {
+        return Ok(content.to_vec());
+    }
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::new(level.min(9)));
+    encoder
+        .write_all(content)
+        .and_then(|_| encoder.finish())
+        .map_err(|e| FileError::CreationFailed {
+            message: format!("Failed to compress ZIP entry: {}", e),
+        })
+}
+
+fn decompress(packed: &[u8], method: u16, size: usize, name: &str) -> FileResult<Vec<u8>> {
+    let content = match method {
+        METHOD_STORED => packed.to_vec(),
+        METHOD_DEFLATED => {
+            // Never inflate past the recorded size
+            let mut content = Vec::with_capacity(size);
+            DeflateDecoder::new(packed)
+                .take(size as u64 + 1)
+                .read_to_end(&mut content)
+                .map_err(|e| corrupted(format!("Entry '{}' does not inflate: {}", name, e)))?;
+            content
+        }
+        other => {
+            return Err(FileError::ExtractionFailed {
+                message: format!(
+                    "Entry '{}' uses compression method {}, which is not supported",
+                    name, other
+                ),
+            })
+        }
+    };
+    if content.len() != size {
+        return Err(corrupted(format!("Entry '{}' has the wrong size", name)));
+    }
+    Ok(content)
+}
+
+/// MS-DOS time and date, as stored in ZIP headers
+fn dos_timestamp(now: chrono::NaiveDateTime) -> (u16, u16) {
+    use chrono::{Datelike, Timelike};
+    let year = now.year().clamp(1980, 2107) as u16;
+    let time =
+        ((now.hour() as u16) << 11) | ((now.minute() as u16) << 5) | (now.second() as u16 / 2);
+    let date = ((year - 1980) << 9) | ((now.month() as u16) << 5) | now.day() as u16;
+    (time, date)
+}
+
+fn to_u32(value: usize, what: &str) -> FileResult<u32> {
+    u32::try_from(value).map_err(|_| FileError::CreationFailed {
+        message: format!("'{}' is too large for a ZIP archive", what),
+    })
+}
+
+fn put_u16(out: &mut Vec<u8>, value: u16) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u32(out: &mut Vec<u8>, value: u32) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn corrupted(message: String) -> FileError {
+    FileError::CorruptedArchive { message }
+}
+
+/// Little-endian reads that fail on truncated data
+struct Cursor<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn at(data: &'a [u8], position: usize) -> Self {
+        Self { data, position }
+    }
+
+    fn remaining(&self) -> usize {
+        self.data.len().saturating_sub(self.position)
+    }
+
+    fn take(&mut self, len: usize) -> FileResult<&'a [u8]> {
+        let end = self
+            .position
+            .checked_add(len)
+            .filter(|end| *end <= self.data.len())
+            .ok_or_else(|| corrupted("Archive is truncated".to_string()))?;
+        let bytes = &self.data[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn skip(&mut self, len: usize) -> FileResult<()> {
+        self.take(len).map(|_| ())
+    }
+
+    fn u16(&mut self) -> FileResult<u16> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> FileResult<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files() -> FileMap {
+        let mut files = FileMap::new();
+        files.insert("metadata.yml".to_string(), b"version: 1.0".to_vec());
+        files.insert("attachments/empty".to_string(), Vec::new());
+        files.insert(
+            "credentials/abc/record.yml".to_string(),
+            b"id: abc\n".repeat(100),
+        );
+        files
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let backend = ZipAesBackend;
+        let archive = backend.create(files(), "password").unwrap();
+        assert!(backend.recognizes(&archive));
+        assert_eq!(backend.extract(&archive, "password").unwrap(), files());
+        assert!(matches!(
+            backend.extract(&archive, "wrong"),
+            Err(FileError::InvalidPassword)
+        ));
+
+        let stored = backend
+            .create_with_options(
+                files(),
+                "password",
+                &ArchiveOptions::new().with_compression_level(0),
+            )
+            .unwrap();
+        assert!(stored.len() > archive.len());
+        assert_eq!(backend.extract(&stored, "password").unwrap(), files());
+
+        // Without a password it is a plain ZIP file
+        let plain = backend.create(files(), "").unwrap();
+        assert_eq!(backend.extract(&plain, "").unwrap(), files());
+        assert!(backend.extract(&plain, "password").is_err());
+    }
+
+    #[test]
+    fn test_tampering_is_detected() {
+        let mut archive = ZipAesBackend.create(files(), "password").unwrap();
+        let entries = central_directory(&archive).unwrap();
+        let record = entries
+            .iter()
+            .find(|entry| entry.name == "credentials/abc/record.yml")
+            .unwrap();
+        // First byte of the ciphertext, after the headers, salt and verifier
+        let ciphertext = record.local_offset as usize + 30 + record.name.len() + 11 + 18;
+        archive[ciphertext] ^= 1;
+        assert!(ZipAesBackend.extract(&archive, "password").is_err());
+        assert!(ZipAesBackend.extract(b"PK\x03\x04", "password").is_err());
+    }
+}
@@ -0,0 +1,491 @@
+//! Apple credential provider FFI interface for ZipLock
+//!
+//! The surface an iOS Password AutoFill extension or a macOS Credential
+//! Provider extension needs. Extensions run under a tight memory limit and
+//! only ever fill one credential, so unlike the mobile interface nothing here
+//! builds a repository: the archive is decrypted one entry at a time, and only
+//! the credential being filled is kept after it was read.
+//!
+//! # Usage Pattern
+//!
+//! 1. The main app stores a biometric token from
+//!    `ziplock_mobile_enable_biometric_unlock` in the shared Keychain group
+//! 2. The extension opens the archive with `ziplock_apple_quick_unlock` once
+//!    Face ID / Touch ID released the Keychain secret
+//! 3. `ziplock_apple_service_identities` lists what to register with
+//!    `ASCredentialIdentityStore` for QuickType suggestions
+//! 4. When the user picks a suggestion, `ziplock_apple_get_credential` reads
+//!    that one credential by its record identifier
+
+use std::os::raw::c_char;
+use std::ptr;
+
+use serde::Serialize;
+use url::Url;
+use zeroize::Zeroizing;
+
+use crate::core::keyprotection::BiometricToken;
+use crate::core::types::CREDENTIALS_DIR;
+use crate::core::{ArchiveBackend, DuressArchive, FileError, SevenZipBackend};
+use crate::ffi::common::{c_string_to_rust, rust_string_to_c, ZipLockError};
+use crate::ffi::widget::device_secret;
+use crate::models::{CredentialRecord, FieldType};
+use crate::utils::yaml::deserialize_credential;
+use crate::utils::{registrable_domain, UrlMatchMode, ANDROID_APP_SCHEME};
+
+/// Handle type for an unlocked credential provider
+pub type AppleProviderHandle = *mut AppleProviderInstance;
+
+/// An unlocked archive; holds the encrypted bytes and the password, never
+/// the decrypted repository
+pub struct AppleProviderInstance {
+    archive: Vec<u8>,
+    password: Zeroizing<String>,
+}
+
+impl AppleProviderInstance {
+    /// Call `visit` with each credential in the archive until it returns
+    /// `false`; unreadable records are skipped
+    fn for_each_credential(
+        &self,
+        mut visit: impl FnMut(CredentialRecord) -> bool,
+    ) -> Result<(), ZipLockError> {
+        SevenZipBackend
+            .for_each_file(&self.archive, &self.password, &mut |path, content| {
+                if !is_record_path(path) {
+                    return true;
+                }
+                match std::str::from_utf8(content).map(deserialize_credential) {
+                    Ok(Ok(credential)) => visit(credential),
+                    _ => true,
+                }
+            })
+            .map_err(|e| match e {
+                FileError::InvalidPassword => ZipLockError::InvalidPassword,
+                _ => ZipLockError::CorruptedArchive,
+            })
+    }
+}
+
+/// How Apple's `ASCredentialServiceIdentifier` names a service
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ServiceIdentifierType {
+    Domain,
+    Url,
+}
+
+/// One entry for `ASCredentialServiceIdentifier`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ServiceIdentifier {
+    pub identifier: String,
+    #[serde(rename = "type")]
+    pub identifier_type: ServiceIdentifierType,
+}
+
+/// A credential as registered with `ASCredentialIdentityStore`; holds no
+/// secrets
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ServiceIdentity {
+    /// Passed back to `ziplock_apple_get_credential` as the record identifier
+    pub credential_id: String,
+    pub title: String,
+    /// Username, or email address when there is no username
+    pub user: String,
+    pub service_identifiers: Vec<ServiceIdentifier>,
+}
+
+impl ServiceIdentity {
+    /// The identity of `credential`, if it has a user and a URL that Safari
+    /// can offer it for
+    ///
+    /// URL fields matched by domain become the registrable domain, those
+    /// matched by host the host, and exact or prefix matches the URL itself.
+    /// Regex and never-matched URLs, app links and protected fields are
+    /// left out, since QuickType would offer them more widely than ZipLock.
+    fn of_credential(credential: &CredentialRecord) -> Option<Self> {
+        let value_of = |field_type: FieldType| {
+            credential
+                .fields
+                .values()
+                .find(|f| f.field_type == field_type && !f.is_protected() && !f.value.is_empty())
+                .map(|f| f.value.clone())
+        };
+        let user = value_of(FieldType::Username).or_else(|| value_of(FieldType::Email))?;
+
+        let mut service_identifiers: Vec<ServiceIdentifier> = credential
+            .fields
+            .values()
+            .filter(|field| field.field_type == FieldType::Url && !field.is_protected())
+            .filter_map(|field| {
+                let saved = field.value.trim();
+                let url = Url::parse(saved)
+                    .ok()
+                    .filter(Url::has_host)
+                    .or_else(|| Url::parse(&format!("https://{}", saved)).ok())?;
+                if url.scheme() == ANDROID_APP_SCHEME {
+                    return None;
+                }
+                let host = url.host_str()?.trim_end_matches('.').to_ascii_lowercase();
+                match UrlMatchMode::of_field(field) {
+                    UrlMatchMode::Domain => Some(ServiceIdentifier {
+                        identifier: registrable_domain(&host).unwrap_or(host),
+                        identifier_type: ServiceIdentifierType::Domain,
+                    }),
+                    UrlMatchMode::Host => Some(ServiceIdentifier {
+                        identifier: host,
+                        identifier_type: ServiceIdentifierType::Domain,
+                    }),
+                    UrlMatchMode::Exact | UrlMatchMode::StartsWith => Some(ServiceIdentifier {
+                        identifier: url.to_string(),
+                        identifier_type: ServiceIdentifierType::Url,
+                    }),
+                    UrlMatchMode::Regex | UrlMatchMode::Never => None,
+                }
+            })
+            .collect();
+        if service_identifiers.is_empty() {
+            return None;
+        }
+        service_identifiers.sort_by(|a, b| a.identifier.cmp(&b.identifier));
+        service_identifiers.dedup();
+
+        Some(Self {
+            credential_id: credential.id.clone(),
+            title: credential.title.clone(),
+            user,
+            service_identifiers,
+        })
+    }
+}
+
+/// Open an archive for a credential provider extension with a biometric
+/// token
+///
+/// The archive is decrypted once to check the password; nothing decrypted
+/// is kept.
+///
+/// # Arguments
+/// * `token` - Token from `ziplock_mobile_enable_biometric_unlock`
+/// * `keystore_secret` - Secret bytes released by the Keychain
+/// * `keystore_secret_len` - Length of `keystore_secret`
+/// * `archive_path` - Path to the 7z archive file
+/// * `handle_out` - Receives the provider handle, for
+///   `ziplock_apple_provider_destroy`
+///
+/// # Returns
+/// * `ZipLockError::Success` with `handle_out` set
+/// * `ZipLockError::KeyProtectionError` if the token expired or the Keychain
+///   secret changed; open the main app to unlock with the master password
+/// * `ZipLockError::InvalidPassword` if the master password changed since the token was made
+/// * `ZipLockError::FileNotFound` or `ZipLockError::FileError` if the archive cannot be read
+///
+/// # Safety
+/// `token` and `archive_path` must be null or point to NUL-terminated
+/// strings, `keystore_secret` must be null or point to
+/// `keystore_secret_len` readable bytes, and `handle_out` must be null or
+/// point to writable memory for a handle.
+#[no_mangle]
+pub unsafe extern "C" fn ziplock_apple_quick_unlock(
+    token: *const c_char,
+    keystore_secret: *const u8,
+    keystore_secret_len: usize,
+    archive_path: *const c_char,
+    handle_out: *mut AppleProviderHandle,
+) -> ZipLockError {
+    if handle_out.is_null() {
+        return ZipLockError::InvalidParameter;
+    }
+    // SAFETY: checked for null above; the caller owns the pointee
+    unsafe { *handle_out = ptr::null_mut() };
+
+    let (Some(token), Some(secret), Some(path)) = (
+        c_string_to_rust(token).and_then(|token| BiometricToken::decode(&token).ok()),
+        device_secret(keystore_secret, keystore_secret_len),
+        c_string_to_rust(archive_path),
+    ) else {
+        return ZipLockError::InvalidParameter;
+    };
+
+    let password = match token.unlock(secret, chrono::Utc::now()) {
+        Ok(password) => password,
+        Err(e) => {
+            tracing::info!("Biometric token rejected: {}", e);
+            return ZipLockError::KeyProtectionError;
+        }
+    };
+    let Ok(password) = std::str::from_utf8(&password) else {
+        return ZipLockError::KeyProtectionError;
+    };
+
+    let archive = match std::fs::read(&path) {
+        Ok(archive) => archive,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return ZipLockError::FileNotFound,
+        Err(_) => return ZipLockError::FileError,
+    };
+    // The password may open the archive or its hidden section
+    let password = Zeroizing::new(password.to_string());
+    let archive = match DuressArchive::from_bytes(&archive) {
+        Ok(container) => container.part(&password).0.into_owned(),
+        Err(_) => archive,
+    };
+    let instance = AppleProviderInstance {
+        archive,
+        password: password.clone(),
+    };
+    // Reading the first entry is enough to tell a wrong password
+    match instance.for_each_credential(|_| false) {
+        Ok(()) => {
+            // SAFETY: checked for null above
+            unsafe { *handle_out = Box::into_raw(Box::new(instance)) };
+            ZipLockError::Success
+        }
+        Err(e) => e,
+    }
+}
+
+/// List the credentials to register for QuickType suggestions
+///
+/// # Arguments
+/// * `handle` - Provider handle
+///
+/// # Returns
+/// * JSON array of `{credential_id, title, user, service_identifiers}`,
+///   where each service identifier is `{identifier, type}` with type
+///   `domain` or `url` (must be freed with `ziplock_free_string`)
+/// * Null on error
+///
+/// # Safety
+/// `handle` must be null or a handle from `ziplock_apple_quick_unlock` that
+/// was not destroyed yet.
+#[no_mangle]
+pub unsafe extern "C" fn ziplock_apple_service_identities(
+    handle: AppleProviderHandle,
+) -> *mut c_char {
+    if handle.is_null() {
+        return ptr::null_mut();
+    }
+    // SAFETY: the caller guarantees `handle` came from `ziplock_apple_quick_unlock`
+    let instance = unsafe { &*handle };
+
+    let mut identities = Vec::new();
+    let result = instance.for_each_credential(|credential| {
+        identities.extend(ServiceIdentity::of_credential(&credential));
+        true
+    });
+    if result.is_err() {
+        return ptr::null_mut();
+    }
+    identities.sort_by(|a, b| {
+        a.title
+            .to_lowercase()
+            .cmp(&b.title.to_lowercase())
+            .then_with(|| a.credential_id.cmp(&b.credential_id))
+    });
+
+    match serde_json::to_string(&identities) {
+        Ok(json) => rust_string_to_c(json),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Read one credential by its ID
+///
+/// Decrypting stops at the credential's entry, and no other credential is
+/// parsed.
+///
+/// # Arguments
+/// * `handle` - Provider handle
+/// * `credential_id` - Record identifier from `ziplock_apple_service_identities`
+///
+/// # Returns
+/// * JSON string containing credential data, as from
+///   `ziplock_mobile_get_credential` (must be freed with `ziplock_free_string`)
+/// * Null if not found or error
+///
+/// # Safety
+/// `handle` must be null or a handle from `ziplock_apple_quick_unlock` that
+/// was not destroyed yet, and `credential_id` must be null or point to a
+/// NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn ziplock_apple_get_credential(
+    handle: AppleProviderHandle,
+    credential_id: *const c_char,
+) -> *mut c_char {
+    if handle.is_null() {
+        return ptr::null_mut();
+    }
+    let Some(id) = c_string_to_rust(credential_id) else {
+        return ptr::null_mut();
+    };
+    // SAFETY: the caller guarantees `handle` came from `ziplock_apple_quick_unlock`
+    let instance = unsafe { &*handle };
+
+    let record_path = format!("{}/{}/record.yml", CREDENTIALS_DIR, id);
+    let mut found = None;
+    let result = SevenZipBackend.for_each_file(
+        &instance.archive,
+        &instance.password,
+        &mut |path, content| {
+            if path != record_path {
+                return true;
+            }
+            found = std::str::from_utf8(content)
+                .ok()
+                .and_then(|yaml| deserialize_credential(yaml).ok());
+            false
+        },
+    );
+
+    match (result, found) {
+        (Ok(()), Some(credential)) if credential.id == id => {
+            match serde_json::to_string(&credential) {
+                Ok(json) => rust_string_to_c(json),
+                Err(_) => ptr::null_mut(),
+            }
+        }
+        _ => ptr::null_mut(),
+    }
+}
+
+/// Close a provider handle
+///
+/// # Safety
+/// `handle` must be null or a handle from `ziplock_apple_quick_unlock` that
+/// was not destroyed yet; it must not be used after this call.
+#[no_mangle]
+pub unsafe extern "C" fn ziplock_apple_provider_destroy(handle: AppleProviderHandle) {
+    if handle.is_null() {
+        return;
+    }
+
+    unsafe {
+        let _ = Box::from_raw(handle);
+    }
+}
+
+fn is_record_path(path: &str) -> bool {
+    path.starts_with(CREDENTIALS_DIR) && path.ends_with("/record.yml")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{DesktopFileProvider, UnifiedRepositoryManager};
+    use crate::ffi::common::ziplock_free_string;
+    use crate::models::CredentialField;
+    use crate::utils::URL_MATCH_METADATA_KEY;
+    use std::ffi::{CStr, CString};
+
+    fn take_json(ptr: *mut c_char) -> serde_json::Value {
+        assert!(!ptr.is_null());
+        let json = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+        unsafe { ziplock_free_string(ptr) };
+        serde_json::from_str(&json).unwrap()
+    }
+
+    #[test]
+    fn test_service_identity() {
+        let mut credential = CredentialRecord::new("Example".to_string(), "login".to_string());
+        assert_eq!(ServiceIdentity::of_credential(&credential), None);
+        credential.set_field("username", CredentialField::username("jane"));
+        credential.set_field("url", CredentialField::url("https://login.example.co.uk/"));
+        credential.set_field(
+            "signin",
+            CredentialField::url("https://example.com/signin")
+                .with_metadata(URL_MATCH_METADATA_KEY, "exact"),
+        );
+        credential.set_field(
+            "old",
+            CredentialField::url("https://old.example.com")
+                .with_metadata(URL_MATCH_METADATA_KEY, "never"),
+        );
+        credential.set_field("app", CredentialField::url("androidapp://com.example.app"));
+
+        let identity = ServiceIdentity::of_credential(&credential).unwrap();
+        assert_eq!(identity.user, "jane");
+        assert_eq!(
+            identity.service_identifiers,
+            vec![
+                ServiceIdentifier {
+                    identifier: "example.co.uk".to_string(),
+                    identifier_type: ServiceIdentifierType::Domain,
+                },
+                ServiceIdentifier {
+                    identifier: "https://example.com/signin".to_string(),
+                    identifier_type: ServiceIdentifierType::Url,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_quick_unlock_and_lookup() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vault.7z");
+        let path_str = path.to_str().unwrap();
+
+        let mut manager = UnifiedRepositoryManager::new(DesktopFileProvider::new());
+        manager.create_repository(path_str, "password").unwrap();
+        let mut credential = CredentialRecord::new("Example".to_string(), "login".to_string());
+        credential.set_field("url", CredentialField::url("https://example.com"));
+        credential.set_field("username", CredentialField::username("jane"));
+        credential.set_field("password", CredentialField::password("s3cret"));
+        let id = credential.id.clone();
+        manager.add_credential(credential).unwrap();
+        manager.close_repository(true).unwrap();
+
+        let secret = [7u8; 32];
+        let token = BiometricToken::create(
+            &secret,
+            b"password",
+            chrono::Duration::days(1),
+            chrono::Utc::now(),
+        )
+        .unwrap()
+        .encode();
+        let token = CString::new(token).unwrap();
+        let archive_path = CString::new(path_str).unwrap();
+
+        let mut handle = ptr::null_mut();
+        let result = unsafe {
+            ziplock_apple_quick_unlock(
+                token.as_ptr(),
+                secret.as_ptr(),
+                secret.len(),
+                archive_path.as_ptr(),
+                &mut handle,
+            )
+        };
+        assert_eq!(result, ZipLockError::Success);
+
+        let identities = take_json(unsafe { ziplock_apple_service_identities(handle) });
+        assert_eq!(identities[0]["credential_id"], id.as_str());
+        assert_eq!(identities[0]["user"], "jane");
+        assert_eq!(
+            identities[0]["service_identifiers"][0],
+            serde_json::json!({ "identifier": "example.com", "type": "domain" })
+        );
+
+        let c_id = CString::new(id.clone()).unwrap();
+        let credential = take_json(unsafe { ziplock_apple_get_credential(handle, c_id.as_ptr()) });
+        assert_eq!(credential["fields"]["password"]["value"], "s3cret");
+        let missing = CString::new("missing").unwrap();
+        assert!(unsafe { ziplock_apple_get_credential(handle, missing.as_ptr()) }.is_null());
+        unsafe { ziplock_apple_provider_destroy(handle) };
+
+        // A Keychain secret that changed is reported, not treated as a bad password
+        let wrong_secret = [8u8; 32];
+        let result = unsafe {
+            ziplock_apple_quick_unlock(
+                token.as_ptr(),
+                wrong_secret.as_ptr(),
+                wrong_secret.len(),
+                archive_path.as_ptr(),
+                &mut handle,
+            )
+        };
+        assert_eq!(result, ZipLockError::KeyProtectionError);
+        assert!(handle.is_null());
+    }
+}
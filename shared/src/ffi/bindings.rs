@@ -0,0 +1,437 @@
+//! Typed repository API for mobile bindings
+//!
+//! The C functions in [`super::mobile`] pass credentials back and forth as
+//! JSON strings that Kotlin and Swift parse by hand, so a renamed key only
+//! shows up at run time. This module offers the same repository operations
+//! with typed values instead, in the shape binding generators export:
+//!
+//! - [`Credential`] and [`Field`] are plain records of strings, numbers,
+//!   lists and maps, with [`FieldKind`] as a simple enum
+//! - every operation returns [`BindingError`], never a status code
+//! - [`MobileVault`] is an object whose methods take `&self`, so the
+//!   generated class can share it between threads
+//!
+//! Archive I/O stays with the app as in the C interface: it extracts the
+//! archive into a file map, hands it to [`MobileVault::load_files`] and
+//! writes [`MobileVault::files`] back.
+//!
+//! With the `uniffi` feature these types are exported through UniFFI;
+//! `scripts/build/generate-bindings.sh` writes the Kotlin and Swift
+//! sources for them.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Mutex, MutexGuard};
+
+use crate::core::{CoreError, FileMap, UnifiedMemoryRepository};
+use crate::models::{CredentialField, CredentialRecord, FieldType};
+
+/// Kind of a [`Field`], deciding how apps show and fill it
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "uniffi", derive(uniffi::Enum))]
+pub enum FieldKind {
+    Text,
+    Password,
+    Email,
+    Url,
+    Username,
+    Phone,
+    CreditCardNumber,
+    ExpiryDate,
+    Cvv,
+    TotpSecret,
+    TextArea,
+    Number,
+    Date,
+    PgpPrivateKey,
+    PgpRevocationCertificate,
+    Custom { name: String },
+}
+
+impl From<&FieldType> for FieldKind {
+    fn from(field_type: &FieldType) -> Self {
+        match field_type {
+            FieldType::Text => Self::Text,
+            FieldType::Password => Self::Password,
+            FieldType::Email => Self::Email,
+            FieldType::Url => Self::Url,
+            FieldType::Username => Self::Username,
+            FieldType::Phone => Self::Phone,
+            FieldType::CreditCardNumber => Self::CreditCardNumber,
+            FieldType::ExpiryDate => Self::ExpiryDate,
+            FieldType::Cvv => Self::Cvv,
+            FieldType::TotpSecret => Self::TotpSecret,
+            FieldType::TextArea => Self::TextArea,
+            FieldType::Number => Self::Number,
+            FieldType::Date => Self::Date,
+            FieldType::PgpPrivateKey => Self::PgpPrivateKey,
+            FieldType::PgpRevocationCertificate => Self::PgpRevocationCertificate,
+            FieldType::Custom(name) => Self::Custom { name: name.clone() },
+        }
+    }
+}
+
+impl From<FieldKind> for FieldType {
+    fn from(kind: FieldKind) -> Self {
+        match kind {
+            FieldKind::Text => Self::Text,
+            FieldKind::Password => Self::Password,
+            FieldKind::Email => Self::Email,
+            FieldKind::Url => Self::Url,
+            FieldKind::Username => Self::Username,
+            FieldKind::Phone => Self::Phone,
+            FieldKind::CreditCardNumber => Self::CreditCardNumber,
+            FieldKind::ExpiryDate => Self::ExpiryDate,
+            FieldKind::Cvv => Self::Cvv,
+            FieldKind::TotpSecret => Self::TotpSecret,
+            FieldKind::TextArea => Self::TextArea,
+            FieldKind::Number => Self::Number,
+            FieldKind::Date => Self::Date,
+            FieldKind::PgpPrivateKey => Self::PgpPrivateKey,
+            FieldKind::PgpRevocationCertificate => Self::PgpRevocationCertificate,
+            FieldKind::Custom { name } => Self::Custom(name),
+        }
+    }
+}
+
+/// One field of a [`Credential`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "uniffi", derive(uniffi::Record))]
+pub struct Field {
+    pub name: String,
+    pub kind: FieldKind,
+    pub value: String,
+    /// Whether apps should mask the value
+    pub sensitive: bool,
+    pub label: Option<String>,
+    pub metadata: HashMap<String, String>,
+    /// Whether the value is sealed with a passphrase of its own; `value`
+    /// then only holds a placeholder. Read-only: updates keep the sealed
+    /// value of a field that is still marked protected.
+    pub protected: bool,
+}
+
+/// A credential without the archive-only details apps do not edit
+///
+/// Icon, color, auto-type sequence and compliance details are kept from the
+/// stored record when a credential is updated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "uniffi", derive(uniffi::Record))]
+pub struct Credential {
+    /// Empty for a credential that was not added yet
+    pub id: String,
+    pub title: String,
+    pub credential_type: String,
+    /// Fields ordered by name
+    pub fields: Vec<Field>,
+    pub tags: Vec<String>,
+    pub notes: Option<String>,
+    pub folder_path: Option<String>,
+    pub favorite: bool,
+    /// Unix timestamps, set by the repository
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+impl From<&CredentialRecord> for Credential {
+    fn from(record: &CredentialRecord) -> Self {
+        let mut fields: Vec<Field> = record
+            .fields
+            .iter()
+            .map(|(name, field)| Field {
+                name: name.clone(),
+                kind: FieldKind::from(&field.field_type),
+                value: field.value.clone(),
+                sensitive: field.sensitive,
+                label: field.label.clone(),
+                metadata: field.metadata.clone(),
+                protected: field.is_protected(),
+            })
+            .collect();
+        fields.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Self {
+            id: record.id.clone(),
+            title: record.title.clone(),
+            credential_type: record.credential_type.clone(),
+            fields,
+            tags: record.tags.clone(),
+            notes: record.notes.clone(),
+            folder_path: record.folder_path.clone(),
+            favorite: record.favorite,
+            created_at: record.created_at,
+            updated_at: record.updated_at,
+        }
+    }
+}
+
+impl Credential {
+    /// Apply this credential's values to `record`
+    fn apply_to(self, record: &mut CredentialRecord) {
+        let mut fields = HashMap::with_capacity(self.fields.len());
+        for field in self.fields {
+            let kept = record
+                .fields
+                .get(&field.name)
+                .filter(|existing| field.protected && existing.is_protected())
+                .cloned();
+            let value = kept.unwrap_or_else(|| CredentialField {
+                field_type: field.kind.into(),
+                value: field.value,
+                sensitive: field.sensitive,
+                label: field.label,
+                metadata: field.metadata,
+                protection: None,
+                value_updated_at: None,
+            });
+            fields.insert(field.name, value);
+        }
+
+        record.title = self.title;
+        record.credential_type = self.credential_type;
+        record.fields = fields;
+        record.tags = self.tags;
+        record.notes = self.notes;
+        record.folder_path = self.folder_path;
+        record.favorite = self.favorite;
+    }
+}
+
+/// Why a [`MobileVault`] operation failed
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "uniffi", derive(uniffi::Error))]
+pub enum BindingError {
+    /// No repository was loaded or initialized
+    NotInitialized,
+    CredentialNotFound {
+        id: String,
+    },
+    /// The credential or file map was rejected
+    Invalid {
+        message: String,
+    },
+    Internal {
+        message: String,
+    },
+}
+
+impl fmt::Display for BindingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotInitialized => write!(f, "Repository not initialized"),
+            Self::CredentialNotFound { id } => write!(f, "Credential not found: {id}"),
+            Self::Invalid { message } => write!(f, "Invalid data: {message}"),
+            Self::Internal { message } => write!(f, "Internal error: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for BindingError {}
+
+impl From<CoreError> for BindingError {
+    fn from(error: CoreError) -> Self {
+        match error {
+            CoreError::NotInitialized => Self::NotInitialized,
+            CoreError::CredentialNotFound { id } => Self::CredentialNotFound { id },
+            CoreError::ValidationError { message }
+            | CoreError::SerializationError { message }
+            | CoreError::InvalidCredential { message }
+            | CoreError::StructureError { message } => Self::Invalid { message },
+            other => Self::Internal {
+                message: other.to_string(),
+            },
+        }
+    }
+}
+
+pub type BindingResult<T> = Result<T, BindingError>;
+
+/// An in-memory repository for a mobile app
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "uniffi", derive(uniffi::Object))]
+pub struct MobileVault {
+    repository: Mutex<UnifiedMemoryRepository>,
+}
+
+impl MobileVault {
+    fn repository(&self) -> BindingResult<MutexGuard<'_, UnifiedMemoryRepository>> {
+        self.repository.lock().map_err(|_| BindingError::Internal {
+            message: "Repository lock poisoned".to_string(),
+        })
+    }
+}
+
+#[cfg_attr(feature = "uniffi", uniffi::export)]
+impl MobileVault {
+    #[cfg_attr(feature = "uniffi", uniffi::constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start an empty repository, for a new archive
+    pub fn initialize(&self) -> BindingResult<()> {
+        Ok(self.repository()?.initialize()?)
+    }
+
+    /// Load the files extracted from an archive, replacing what is loaded
+    pub fn load_files(&self, files: HashMap<String, Vec<u8>>) -> BindingResult<()> {
+        let mut repository = self.repository()?;
+        repository.wipe();
+        Ok(repository.load_from_files(files)?)
+    }
+
+    /// Files to write into the archive
+    pub fn files(&self) -> BindingResult<HashMap<String, Vec<u8>>> {
+        let files: FileMap = self.repository()?.serialize_to_files()?;
+        Ok(files)
+    }
+
+    /// All credentials, ordered by title
+    pub fn list_credentials(&self) -> BindingResult<Vec<Credential>> {
+        let mut credentials: Vec<Credential> = self
+            .repository()?
+            .list_credentials()?
+            .iter()
+            .map(Credential::from)
+            .collect();
+        credentials.sort_by(|a, b| {
+            a.title
+                .to_lowercase()
+                .cmp(&b.title.to_lowercase())
+                .then_with(|| a.id.cmp(&b.id))
+        });
+        Ok(credentials)
+    }
+
+    pub fn get_credential(&self, id: String) -> BindingResult<Credential> {
+        Ok(Credential::from(
+            self.repository()?.get_credential_readonly(&id)?,
+        ))
+    }
+
+    /// Add `credential` and return its ID, generated when it has none
+    pub fn add_credential(&self, credential: Credential) -> BindingResult<String> {
+        let mut record = CredentialRecord::new(String::new(), String::new());
+        if !credential.id.is_empty() {
+            record.id = credential.id.clone();
+        }
+        credential.apply_to(&mut record);
+        let id = record.id.clone();
+        self.repository()?.add_credential(record)?;
+        Ok(id)
+    }
+
+    pub fn update_credential(&self, credential: Credential) -> BindingResult<()> {
+        let mut repository = self.repository()?;
+        let mut record = repository.get_credential_readonly(&credential.id)?.clone();
+        credential.apply_to(&mut record);
+        Ok(repository.update_credential(record)?)
+    }
+
+    pub fn delete_credential(&self, id: String) -> BindingResult<()> {
+        self.repository()?.delete_credential(&id)?;
+        Ok(())
+    }
+
+    /// Whether there are changes [`files`](Self::files) has not been saved with
+    pub fn is_modified(&self) -> BindingResult<bool> {
+        Ok(self.repository()?.is_modified())
+    }
+
+    /// Record that the archive was written
+    pub fn mark_saved(&self) -> BindingResult<()> {
+        self.repository()?.mark_saved();
+        Ok(())
+    }
+
+    /// Forget all credentials, e.g. when the app locks
+    pub fn close(&self) -> BindingResult<()> {
+        self.repository()?.wipe();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn login() -> Credential {
+        Credential {
+            id: String::new(),
+            title: "Example".to_string(),
+            credential_type: "login".to_string(),
+            fields: vec![
+                Field {
+                    name: "username".to_string(),
+                    kind: FieldKind::Username,
+                    value: "jane".to_string(),
+                    sensitive: false,
+                    label: None,
+                    metadata: HashMap::new(),
+                    protected: false,
+                },
+                Field {
+                    name: "pin".to_string(),
+                    kind: FieldKind::Custom {
+                        name: "pin".to_string(),
+                    },
+                    value: "1234".to_string(),
+                    sensitive: true,
+                    label: Some("PIN".to_string()),
+                    metadata: HashMap::new(),
+                    protected: false,
+                },
+            ],
+            tags: vec!["work".to_string()],
+            notes: None,
+            folder_path: None,
+            favorite: false,
+            created_at: 0,
+            updated_at: 0,
+        }
+    }
+
+    #[test]
+    fn test_round_trip_through_files() {
+        let vault = MobileVault::new();
+        assert_eq!(vault.list_credentials(), Err(BindingError::NotInitialized));
+        vault.initialize().unwrap();
+
+        let id = vault.add_credential(login()).unwrap();
+        assert!(!id.is_empty());
+        let mut stored = vault.get_credential(id.clone()).unwrap();
+        assert_eq!(stored.fields[0].name, "pin");
+        assert_eq!(
+            stored.fields[0].kind,
+            FieldKind::Custom {
+                name: "pin".to_string()
+            }
+        );
+        assert!(stored.created_at > 0);
+
+        stored.title = "Renamed".to_string();
+        stored.fields.retain(|f| f.name == "username");
+        vault.update_credential(stored).unwrap();
+        assert!(vault.is_modified().unwrap());
+
+        let files = vault.files().unwrap();
+        let reopened = MobileVault::new();
+        reopened.load_files(files).unwrap();
+        let credentials = reopened.list_credentials().unwrap();
+        assert_eq!(credentials.len(), 1);
+        assert_eq!(credentials[0].title, "Renamed");
+        assert_eq!(credentials[0].fields.len(), 1);
+
+        assert_eq!(
+            reopened.delete_credential("missing".to_string()),
+            Err(BindingError::CredentialNotFound {
+                id: "missing".to_string()
+            })
+        );
+        reopened.close().unwrap();
+        assert_eq!(
+            reopened.list_credentials(),
+            Err(BindingError::NotInitialized)
+        );
+    }
+}
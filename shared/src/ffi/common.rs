@@ -4,10 +4,20 @@
 //! FFI interfaces, including error code conversion, string handling, and
 //! common data structures.
 
+use std::cell::RefCell;
 use std::ffi::{CStr, CString};
-use std::os::raw::c_char;
+use std::os::raw::{c_char, c_int, c_void};
 
 use crate::core::errors::{CoreError, FileError};
+use crate::core::progress::{ArchivePhase, ProgressObserver};
+use crate::models::CredentialRecord;
+use crate::utils::localization::{Localizer, Message, DEFAULT_LOCALE};
+use crate::utils::validation;
+
+thread_local! {
+    /// Messages for the last failed call on this thread
+    static LAST_ERROR: RefCell<Vec<Message>> = const { RefCell::new(Vec::new()) };
+}
 
 /// FFI-compatible error codes
 #[repr(C)]
@@ -39,6 +49,12 @@ pub enum ZipLockError {
     PermissionDenied = 11,
     /// File not found
     FileNotFound = 12,
+    /// Security key missing, refused or did not match; fall back to the password
+    KeyProtectionError = 13,
+    /// The unlocked session timed out and the repository was locked
+    SessionExpired = 14,
+    /// Too many wrong passwords; wait before the next unlock attempt
+    UnlockThrottled = 15,
     /// Internal error
     InternalError = 99,
 }
@@ -55,6 +71,12 @@ impl From<CoreError> for ZipLockError {
             CoreError::StructureError { .. } => ZipLockError::SerializationError,
             CoreError::InternalError { .. } => ZipLockError::InternalError,
             CoreError::FileOperation(file_error) => file_error.into(),
+            CoreError::KeyProtection(_) => ZipLockError::KeyProtectionError,
+            CoreError::SessionExpired { .. } => ZipLockError::SessionExpired,
+            CoreError::FieldPassphraseInvalid { .. } | CoreError::KeyFileRequired => {
+                ZipLockError::InvalidPassword
+            }
+            CoreError::UnlockThrottled { .. } => ZipLockError::UnlockThrottled,
         }
     }
 }
@@ -69,8 +91,70 @@ impl From<FileError> for ZipLockError {
             FileError::InvalidPassword => ZipLockError::InvalidPassword,
             FileError::CorruptedArchive { .. } => ZipLockError::CorruptedArchive,
             FileError::IoError { .. } => ZipLockError::FileError,
+            FileError::Conflict { .. }
+            | FileError::DiskFull { .. }
+            | FileError::SyncFailed { .. }
+            | FileError::VerificationFailed { .. }
+            | FileError::BackupFailed { .. }
+            | FileError::ReplaceFailed { .. } => ZipLockError::FileError,
+        }
+    }
+}
+
+impl ZipLockError {
+    /// Catalog message describing the status code on its own
+    pub fn message(&self) -> Message {
+        Message::new(match self {
+            ZipLockError::Success => "status.success",
+            ZipLockError::InvalidParameter => "status.invalid_parameter",
+            ZipLockError::NotInitialized => "status.not_initialized",
+            ZipLockError::AlreadyInitialized => "status.already_initialized",
+            ZipLockError::SerializationError => "status.serialization_error",
+            ZipLockError::ValidationError => "status.validation_error",
+            ZipLockError::OutOfMemory => "status.out_of_memory",
+            ZipLockError::FileError => "status.file_error",
+            ZipLockError::CredentialNotFound => "status.credential_not_found",
+            ZipLockError::InvalidPassword => "status.invalid_password",
+            ZipLockError::CorruptedArchive => "status.corrupted_archive",
+            ZipLockError::PermissionDenied => "status.permission_denied",
+            ZipLockError::FileNotFound => "status.file_not_found",
+            ZipLockError::KeyProtectionError => "status.key_protection_error",
+            ZipLockError::SessionExpired => "status.session_expired",
+            ZipLockError::UnlockThrottled => "status.unlock_throttled",
+            ZipLockError::InternalError => "status.internal_error",
+        })
+    }
+}
+
+/// Remember why the current call failed, for `ziplock_get_last_error`
+pub(crate) fn set_last_error(messages: Vec<Message>) {
+    LAST_ERROR.with(|last| *last.borrow_mut() = messages);
+}
+
+/// Remember `error` as the reason the current call failed
+pub(crate) fn record_error(error: &CoreError) {
+    set_last_error(vec![error.message()]);
+}
+
+/// Remember why saving `credential` failed
+///
+/// The repository reports invalid credentials as one English message, so
+/// the credential is validated again to record each problem with its code.
+pub(crate) fn record_credential_error(credential: &CredentialRecord, error: &CoreError) {
+    if matches!(error, CoreError::ValidationError { .. }) {
+        let mut credential = credential.clone();
+        validation::repair_credential_id(&mut credential);
+        let result = validation::validate_credential(&credential);
+        if !result.is_valid {
+            set_last_error(result.error_messages);
+            return;
         }
     }
+    record_error(error);
+}
+
+fn take_locale(locale: *const c_char) -> Localizer {
+    Localizer::new(&c_string_to_rust(locale).unwrap_or_else(|| DEFAULT_LOCALE.to_string()))
 }
 
 /// Convert a Rust string to a C string
@@ -115,6 +199,43 @@ pub unsafe extern "C" fn ziplock_free_string(ptr: *mut c_char) {
     }
 }
 
+/// Progress callback for opening and saving archives
+///
+/// Called with the phase (0 = reading, 1 = decrypting, 2 = loading,
+/// 3 = serializing, 4 = encrypting, 5 = writing, 6 = complete), the
+/// percentage of the operation done and the registered `user_data`. It runs
+/// on the thread doing the work.
+pub type ZipLockProgressCallback =
+    Option<extern "C" fn(phase: c_int, percent: c_int, user_data: *mut c_void)>;
+
+/// A registered progress callback and its `user_data`
+#[derive(Clone, Copy)]
+pub(crate) struct FfiProgressObserver {
+    callback: extern "C" fn(c_int, c_int, *mut c_void),
+    user_data: *mut c_void,
+}
+
+// The caller owns `user_data` and accepts calls from any thread when it
+// registers the callback
+unsafe impl Send for FfiProgressObserver {}
+unsafe impl Sync for FfiProgressObserver {}
+
+impl FfiProgressObserver {
+    /// `None` when no callback was given
+    pub(crate) fn new(callback: ZipLockProgressCallback, user_data: *mut c_void) -> Option<Self> {
+        callback.map(|callback| Self {
+            callback,
+            user_data,
+        })
+    }
+}
+
+impl ProgressObserver for FfiProgressObserver {
+    fn on_progress(&self, phase: ArchivePhase, percent: u8) {
+        (self.callback)(phase as c_int, percent as c_int, self.user_data);
+    }
+}
+
 /// Repository handle type for FFI
 pub type RepositoryHandle = *mut std::ffi::c_void;
 
@@ -243,15 +364,78 @@ pub extern "C" fn ziplock_get_version() -> VersionInfo {
     VersionInfo::from_version_string(env!("CARGO_PKG_VERSION"))
 }
 
-/// Get last error message (if any)
+/// Run the cryptographic self-test
 ///
-/// This is not thread-safe and should only be used for debugging.
-/// Returns null if no error message is available.
+/// Returns a JSON `SelfTestReport`, whose `checks` say which known-answer
+/// tests passed. Apps call this once at startup, before opening a vault.
+/// The string must be freed with `ziplock_free_string`.
+#[no_mangle]
+pub extern "C" fn ziplock_self_test() -> *mut c_char {
+    let report = crate::core::self_test::run();
+    match serde_json::to_string(&report) {
+        Ok(json) => rust_string_to_c(json),
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+
+/// Compute the published key-derivation and encryption test vectors
+///
+/// Only in builds with the `test-vectors` feature. Returns a JSON
+/// `TestVectorSet`, or null if a vector could not be computed. The string
+/// must be freed with `ziplock_free_string`.
+#[cfg(feature = "test-vectors")]
+#[no_mangle]
+pub extern "C" fn ziplock_test_vectors() -> *mut c_char {
+    match crate::core::test_vectors::generate().map(|set| serde_json::to_string(&set)) {
+        Ok(Ok(json)) => rust_string_to_c(json),
+        _ => std::ptr::null_mut(),
+    }
+}
+
+/// Get the English message for the last failed call on this thread
+///
+/// Only calls that record a reason update it; check the return codes first.
+/// The string must be freed with `ziplock_free_string`.
 #[no_mangle]
 pub extern "C" fn ziplock_get_last_error() -> *mut c_char {
-    // In a real implementation, we might store the last error in thread-local storage
-    // For now, we return a generic message
-    rust_string_to_c("Check function return codes for error information".to_string())
+    let messages = LAST_ERROR.with(|last| last.borrow().clone());
+    if messages.is_empty() {
+        return rust_string_to_c("Check function return codes for error information".to_string());
+    }
+    let text: Vec<String> = messages.iter().map(Message::to_string).collect();
+    rust_string_to_c(text.join("; "))
+}
+
+/// Get the messages for the last failed call on this thread in `locale`
+///
+/// `locale` is a language tag such as `de` or `pt-BR`; null means English.
+/// Returns a JSON array of `{"code", "args", "message"}` objects, one per
+/// problem, or null if no call has recorded a reason. The string must be
+/// freed with `ziplock_free_string`.
+#[no_mangle]
+pub extern "C" fn ziplock_get_last_error_localized(locale: *const c_char) -> *mut c_char {
+    let messages = LAST_ERROR.with(|last| last.borrow().clone());
+    if messages.is_empty() {
+        return std::ptr::null_mut();
+    }
+    let localizer = take_locale(locale);
+    let localized: Vec<_> = messages
+        .iter()
+        .map(|message| localizer.localize(message))
+        .collect();
+    match serde_json::to_string(&localized) {
+        Ok(json) => rust_string_to_c(json),
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+
+/// Get a short message for a status code in `locale` (null for English)
+///
+/// For showing a failure that recorded no more detailed reason. The string
+/// must be freed with `ziplock_free_string`.
+#[no_mangle]
+pub extern "C" fn ziplock_error_message(error: ZipLockError, locale: *const c_char) -> *mut c_char {
+    rust_string_to_c(take_locale(locale).format(&error.message()))
 }
 
 /// Log level constants for FFI
@@ -405,6 +589,42 @@ mod tests {
         assert!(!crate::logging::is_debug_enabled());
     }
 
+    #[test]
+    fn test_localized_last_error() {
+        set_last_error(Vec::new());
+        assert!(ziplock_get_last_error_localized(ptr::null()).is_null());
+
+        let mut credential = CredentialRecord::new(String::new(), "login".to_string());
+        credential.tags = vec!["a".to_string(), "A".to_string()];
+        record_credential_error(
+            &credential,
+            &CoreError::ValidationError {
+                message: "Title cannot be empty; Duplicate tag: 'A'".to_string(),
+            },
+        );
+
+        let locale = CString::new("fr_FR.UTF-8").unwrap();
+        let json_ptr = ziplock_get_last_error_localized(locale.as_ptr());
+        let json: serde_json::Value =
+            serde_json::from_str(&c_string_to_rust(json_ptr).unwrap()).unwrap();
+        unsafe { ziplock_free_string(json_ptr) };
+        assert_eq!(json[0]["code"], "validation.title_empty");
+        assert_eq!(json[0]["message"], "Le titre ne peut pas être vide");
+        assert_eq!(json[1]["args"]["tag"], "A");
+
+        let text_ptr = ziplock_get_last_error();
+        assert_eq!(
+            c_string_to_rust(text_ptr).unwrap(),
+            "Title cannot be empty; Duplicate tag: 'A'"
+        );
+        unsafe { ziplock_free_string(text_ptr) };
+
+        let locale = CString::new("de").unwrap();
+        let text_ptr = ziplock_error_message(ZipLockError::InvalidPassword, locale.as_ptr());
+        assert_eq!(c_string_to_rust(text_ptr).unwrap(), "Falsches Passwort");
+        unsafe { ziplock_free_string(text_ptr) };
+    }
+
     #[test]
     fn test_get_last_error() {
         let error_ptr = ziplock_get_last_error();
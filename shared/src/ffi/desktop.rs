@@ -27,13 +27,19 @@
 //! 5. Close repository when done
 
 use std::ffi::CString;
-use std::os::raw::{c_char, c_int};
+use std::os::raw::{c_char, c_int, c_void};
 use std::ptr;
 use std::sync::Mutex;
+use zeroize::Zeroizing;
 
-use crate::core::{CoreError, DesktopFileProvider, UnifiedRepositoryManager};
-use crate::ffi::common::{c_string_to_rust, rust_string_to_c, ZipLockError};
-use crate::models::CredentialRecord;
+use crate::core::keyprotection::{Fido2Protector, LibFido2Device};
+use crate::core::{CoreError, DesktopFileProvider, PasswordChangeStage, UnifiedRepositoryManager};
+use crate::ffi::common::{
+    c_string_to_rust, record_credential_error, record_error, rust_string_to_c, FfiProgressObserver,
+    ZipLockError, ZipLockProgressCallback,
+};
+use crate::models::{CredentialRecord, CredentialTemplate, FolderDefaults};
+use crate::utils::AuditOptions;
 
 /// Handle type for desktop repository manager instances
 pub type DesktopManagerHandle = *mut DesktopManagerInstance;
@@ -90,6 +96,9 @@ pub enum DesktopError {
     OutOfMemory = 10,
     InternalError = 11,
     RepositoryNotOpen = 12,
+    KeyProtectionError = 13,
+    SessionExpired = 14,
+    UnlockThrottled = 15,
 }
 
 impl From<ZipLockError> for DesktopError {
@@ -109,6 +118,9 @@ impl From<ZipLockError> for DesktopError {
             ZipLockError::PermissionDenied => DesktopError::PermissionDenied,
             ZipLockError::FileNotFound => DesktopError::FileNotFound,
             ZipLockError::OutOfMemory => DesktopError::OutOfMemory,
+            ZipLockError::KeyProtectionError => DesktopError::KeyProtectionError,
+            ZipLockError::SessionExpired => DesktopError::SessionExpired,
+            ZipLockError::UnlockThrottled => DesktopError::UnlockThrottled,
         }
     }
 }
@@ -243,7 +255,11 @@ pub extern "C" fn ziplock_desktop_open_repository(
             None => return DesktopError::InvalidPassword,
         };
 
-        match manager.open_repository(&path_str, &password_str) {
+        let result = manager.open_repository(&path_str, &password_str);
+        if let Err(error) = &result {
+            record_error(error);
+        }
+        match result {
             Ok(()) => DesktopError::Success,
             Err(CoreError::FileOperation(crate::core::FileError::NotFound { .. })) => {
                 DesktopError::FileNotFound
@@ -262,6 +278,103 @@ pub extern "C" fn ziplock_desktop_open_repository(
     }
 }
 
+/// Open a repository with a registered FIDO2 security key
+///
+/// Uses the first connected key and blocks until it is touched. On any
+/// error the repository stays closed; ask for the master password instead.
+///
+/// # Arguments
+/// * `handle` - Manager handle
+/// * `path` - Path to the repository archive
+///
+/// # Returns
+/// * `DesktopError::Success` on success
+/// * `DesktopError::InvalidParameter` if parameters are invalid
+/// * `DesktopError::KeyProtectionError` if no key is connected or registered,
+///   or the key did not unlock the repository
+/// * `DesktopError::InvalidPassword` if the key unwrapped an outdated password
+#[no_mangle]
+pub extern "C" fn ziplock_desktop_open_with_security_key(
+    handle: DesktopManagerHandle,
+    path: *const c_char,
+) -> DesktopError {
+    if handle.is_null() || path.is_null() {
+        return DesktopError::InvalidParameter;
+    }
+    let Some(path_str) = c_string_to_rust(path) else {
+        return DesktopError::InvalidParameter;
+    };
+    let device = match LibFido2Device::first() {
+        Ok(device) => device,
+        Err(_) => return DesktopError::KeyProtectionError,
+    };
+
+    unsafe {
+        let instance = &*handle;
+        let mut manager = match instance.manager.lock() {
+            Ok(mgr) => mgr,
+            Err(_) => return DesktopError::InternalError,
+        };
+
+        match manager.open_repository_with_protector(&path_str, &Fido2Protector::new(device)) {
+            Ok(()) => DesktopError::Success,
+            Err(CoreError::KeyProtection(_)) => DesktopError::KeyProtectionError,
+            Err(CoreError::AlreadyInitialized) => DesktopError::AlreadyInitialized,
+            Err(CoreError::FileOperation(crate::core::FileError::NotFound { .. })) => {
+                DesktopError::FileNotFound
+            }
+            Err(CoreError::FileOperation(crate::core::FileError::InvalidPassword)) => {
+                DesktopError::InvalidPassword
+            }
+            Err(_) => DesktopError::InternalError,
+        }
+    }
+}
+
+/// Register the first connected FIDO2 security key for the open repository
+///
+/// Blocks until the key is touched (twice: once to create the credential,
+/// once to derive the wrapping key).
+///
+/// # Arguments
+/// * `handle` - Manager handle
+/// * `label` - Name for the key, shown when listing registered keys
+///
+/// # Returns
+/// * JSON `ProtectorRecord` (must be freed with `ziplock_desktop_free_string`)
+/// * Null if no repository is open, no key is connected or registration failed
+#[no_mangle]
+pub extern "C" fn ziplock_desktop_register_security_key(
+    handle: DesktopManagerHandle,
+    label: *const c_char,
+) -> *mut c_char {
+    if handle.is_null() {
+        return ptr::null_mut();
+    }
+    let Some(label) = c_string_to_rust(label) else {
+        return ptr::null_mut();
+    };
+    let Ok(device) = LibFido2Device::first() else {
+        return ptr::null_mut();
+    };
+
+    unsafe {
+        let instance = &*handle;
+        let mut manager = match instance.manager.lock() {
+            Ok(mgr) => mgr,
+            Err(_) => return ptr::null_mut(),
+        };
+
+        match manager.register_key_protector(&Fido2Protector::new(device), &label) {
+            Ok(record) => match serde_json::to_string(&record) {
+                Ok(json) => rust_string_to_c(json),
+                Err(_) => ptr::null_mut(),
+            },
+            Err(_) => ptr::null_mut(),
+        }
+    }
+}
+
 /// Save the repository to disk
 ///
 /// # Arguments
@@ -327,6 +440,44 @@ pub extern "C" fn ziplock_desktop_close_repository(handle: DesktopManagerHandle)
     }
 }
 
+/// Save pending changes and wipe key material before suspend or hibernate
+///
+/// Call from the platform's sleep notification. The repository is closed
+/// afterwards and must be reopened with the master password. If saving
+/// fails the secrets are wiped anyway.
+///
+/// # Arguments
+/// * `handle` - Manager handle
+///
+/// # Returns
+/// * `DesktopError::Success` if the repository was locked (or none was open)
+/// * `DesktopError::InvalidParameter` if handle is invalid
+/// * Other errors if pending changes could not be saved
+#[no_mangle]
+pub extern "C" fn ziplock_desktop_prepare_for_suspend(
+    handle: DesktopManagerHandle,
+) -> DesktopError {
+    if handle.is_null() {
+        return DesktopError::InvalidParameter;
+    }
+
+    unsafe {
+        let instance = &*handle;
+        let mut manager = match instance.manager.lock() {
+            Ok(mgr) => mgr,
+            Err(_) => return DesktopError::InternalError,
+        };
+
+        match manager.prepare_for_suspend() {
+            Ok(_) => DesktopError::Success,
+            Err(CoreError::FileOperation(crate::core::FileError::PermissionDenied { .. })) => {
+                DesktopError::PermissionDenied
+            }
+            Err(_) => DesktopError::InternalError,
+        }
+    }
+}
+
 /// Add a new credential to the repository
 ///
 /// # Arguments
@@ -369,10 +520,15 @@ pub extern "C" fn ziplock_desktop_add_credential(
             Err(_) => return DesktopError::SerializationError,
         };
 
-        match manager.add_credential(credential) {
+        match manager.add_credential(credential.clone()) {
             Ok(()) => DesktopError::Success,
-            Err(CoreError::ValidationError { .. }) => DesktopError::ValidationError,
-            Err(_) => DesktopError::InternalError,
+            Err(error) => {
+                record_credential_error(&credential, &error);
+                match error {
+                    CoreError::ValidationError { .. } => DesktopError::ValidationError,
+                    _ => DesktopError::InternalError,
+                }
+            }
         }
     }
 }
@@ -463,11 +619,16 @@ pub extern "C" fn ziplock_desktop_update_credential(
             Err(_) => return DesktopError::SerializationError,
         };
 
-        match manager.update_credential(credential) {
+        match manager.update_credential(credential.clone()) {
             Ok(()) => DesktopError::Success,
-            Err(CoreError::CredentialNotFound { .. }) => DesktopError::InvalidParameter,
-            Err(CoreError::ValidationError { .. }) => DesktopError::ValidationError,
-            Err(_) => DesktopError::InternalError,
+            Err(error) => {
+                record_credential_error(&credential, &error);
+                match error {
+                    CoreError::CredentialNotFound { .. } => DesktopError::InvalidParameter,
+                    CoreError::ValidationError { .. } => DesktopError::ValidationError,
+                    _ => DesktopError::InternalError,
+                }
+            }
         }
     }
 }
@@ -669,22 +830,522 @@ pub extern "C" fn ziplock_desktop_get_stats(handle: DesktopManagerHandle) -> *mu
     }
 }
 
+/// Run a security audit over all credentials
+///
+/// May block on network requests when online breach checking is enabled.
+///
+/// # Arguments
+/// * `handle` - Manager handle
+/// * `options_json` - JSON `AuditOptions`, or null for defaults (no breach check)
+///
+/// # Returns
+/// * JSON string containing the audit report (must be freed with `ziplock_desktop_free_string`)
+/// * Null if error
+#[no_mangle]
+pub extern "C" fn ziplock_desktop_security_audit(
+    handle: DesktopManagerHandle,
+    options_json: *const c_char,
+) -> *mut c_char {
+    if handle.is_null() {
+        return ptr::null_mut();
+    }
+
+    let options = if options_json.is_null() {
+        AuditOptions::default()
+    } else {
+        match c_string_to_rust(options_json).map(|json| serde_json::from_str(&json)) {
+            Some(Ok(options)) => options,
+            _ => return ptr::null_mut(),
+        }
+    };
+
+    unsafe {
+        let instance = &*handle;
+        let manager = match instance.manager.lock() {
+            Ok(mgr) => mgr,
+            Err(_) => return ptr::null_mut(),
+        };
+
+        match manager.security_audit(options) {
+            Ok(report) => match serde_json::to_string(&report) {
+                Ok(json) => rust_string_to_c(json),
+                Err(_) => ptr::null_mut(),
+            },
+            Err(_) => ptr::null_mut(),
+        }
+    }
+}
+
+/// Build the access review report from the credentials' compliance metadata
+///
+/// # Arguments
+/// * `handle` - Manager handle
+/// * `options_json` - JSON `AuditOptions`, or null for defaults (90 day review interval)
+/// * `format` - `"json"` or `"csv"`; null means JSON
+///
+/// # Returns
+/// * Report string (must be freed with `ziplock_desktop_free_string`)
+/// * Null if error
+#[no_mangle]
+pub extern "C" fn ziplock_desktop_compliance_report(
+    handle: DesktopManagerHandle,
+    options_json: *const c_char,
+    format: *const c_char,
+) -> *mut c_char {
+    if handle.is_null() {
+        return ptr::null_mut();
+    }
+
+    let options = if options_json.is_null() {
+        AuditOptions::default()
+    } else {
+        match c_string_to_rust(options_json).map(|json| serde_json::from_str(&json)) {
+            Some(Ok(options)) => options,
+            _ => return ptr::null_mut(),
+        }
+    };
+    let as_csv = match c_string_to_rust(format).as_deref() {
+        None | Some("json") => false,
+        Some("csv") => true,
+        Some(_) => return ptr::null_mut(),
+    };
+
+    unsafe {
+        let instance = &*handle;
+        let manager = match instance.manager.lock() {
+            Ok(mgr) => mgr,
+            Err(_) => return ptr::null_mut(),
+        };
+
+        let report = match manager.compliance_report(options) {
+            Ok(report) => report,
+            Err(_) => return ptr::null_mut(),
+        };
+        let output = if as_csv {
+            report.to_csv().ok()
+        } else {
+            serde_json::to_string(&report).ok()
+        };
+        output.map_or(ptr::null_mut(), rust_string_to_c)
+    }
+}
+
+/// Set the icon, color and template new credentials in a folder inherit
+///
+/// # Arguments
+/// * `handle` - Manager handle
+/// * `folder_path` - Folder path such as `Work/Servers`
+/// * `defaults_json` - JSON `FolderDefaults`; an empty object clears the folder's defaults
+///
+/// # Returns
+/// * `DesktopError::Success` on success
+/// * `DesktopError::InvalidParameter` if parameters are invalid
+/// * `DesktopError::RepositoryNotOpen` if no repository is open
+/// * `DesktopError::SerializationError` if JSON parsing fails
+/// * `DesktopError::ValidationError` if the path or a default is invalid
+#[no_mangle]
+pub extern "C" fn ziplock_desktop_set_folder_defaults(
+    handle: DesktopManagerHandle,
+    folder_path: *const c_char,
+    defaults_json: *const c_char,
+) -> DesktopError {
+    if handle.is_null() || folder_path.is_null() || defaults_json.is_null() {
+        return DesktopError::InvalidParameter;
+    }
+
+    let (Some(folder_path), Some(json_str)) = (
+        c_string_to_rust(folder_path),
+        c_string_to_rust(defaults_json),
+    ) else {
+        return DesktopError::InvalidParameter;
+    };
+    let defaults: FolderDefaults = match serde_json::from_str(&json_str) {
+        Ok(defaults) => defaults,
+        Err(_) => return DesktopError::SerializationError,
+    };
+
+    unsafe {
+        let instance = &*handle;
+        let mut manager = match instance.manager.lock() {
+            Ok(mgr) => mgr,
+            Err(_) => return DesktopError::InternalError,
+        };
+
+        match manager.set_folder_defaults(&folder_path, defaults) {
+            Ok(()) => DesktopError::Success,
+            Err(CoreError::NotInitialized) => DesktopError::RepositoryNotOpen,
+            Err(CoreError::ValidationError { .. }) => DesktopError::ValidationError,
+            Err(_) => DesktopError::InternalError,
+        }
+    }
+}
+
+/// Get the form for a new credential, with its folder's defaults resolved
+///
+/// # Arguments
+/// * `handle` - Manager handle
+/// * `folder_path` - Folder the credential is created in, or null
+/// * `template` - Template name chosen by the user, or null to use the folder's
+///
+/// # Returns
+/// * JSON `CredentialFormSchema` (must be freed with `ziplock_desktop_free_string`)
+/// * Null if no repository is open or the template is unknown
+#[no_mangle]
+pub extern "C" fn ziplock_desktop_credential_form_schema(
+    handle: DesktopManagerHandle,
+    folder_path: *const c_char,
+    template: *const c_char,
+) -> *mut c_char {
+    if handle.is_null() {
+        return ptr::null_mut();
+    }
+
+    let folder_path = c_string_to_rust(folder_path);
+    let template = c_string_to_rust(template);
+
+    unsafe {
+        let instance = &*handle;
+        let manager = match instance.manager.lock() {
+            Ok(mgr) => mgr,
+            Err(_) => return ptr::null_mut(),
+        };
+
+        match manager.credential_form_schema(folder_path.as_deref(), template.as_deref()) {
+            Ok(schema) => match serde_json::to_string(&schema) {
+                Ok(json) => rust_string_to_c(json),
+                Err(_) => ptr::null_mut(),
+            },
+            Err(_) => ptr::null_mut(),
+        }
+    }
+}
+
+/// List the user-defined credential templates
+///
+/// # Arguments
+/// * `handle` - Manager handle
+///
+/// # Returns
+/// * JSON array of `CredentialTemplate`s sorted by name (must be freed with `ziplock_desktop_free_string`)
+/// * Null if no repository is open
+#[no_mangle]
+pub extern "C" fn ziplock_desktop_list_templates(handle: DesktopManagerHandle) -> *mut c_char {
+    if handle.is_null() {
+        return ptr::null_mut();
+    }
+
+    unsafe {
+        let instance = &*handle;
+        let manager = match instance.manager.lock() {
+            Ok(mgr) => mgr,
+            Err(_) => return ptr::null_mut(),
+        };
+
+        match manager.list_templates() {
+            Ok(templates) => match serde_json::to_string(&templates) {
+                Ok(json) => rust_string_to_c(json),
+                Err(_) => ptr::null_mut(),
+            },
+            Err(_) => ptr::null_mut(),
+        }
+    }
+}
+
+/// Save a new user-defined credential template
+///
+/// # Arguments
+/// * `handle` - Manager handle
+/// * `template_json` - JSON `CredentialTemplate`
+///
+/// # Returns
+/// * `DesktopError::Success` on success
+/// * `DesktopError::InvalidParameter` if parameters are invalid
+/// * `DesktopError::RepositoryNotOpen` if no repository is open
+/// * `DesktopError::SerializationError` if JSON parsing fails
+/// * `DesktopError::ValidationError` if the name is taken or field names collide
+#[no_mangle]
+pub extern "C" fn ziplock_desktop_add_template(
+    handle: DesktopManagerHandle,
+    template_json: *const c_char,
+) -> DesktopError {
+    save_template(handle, template_json, false)
+}
+
+/// Replace a user-defined credential template with the same name
+///
+/// # Arguments
+/// * `handle` - Manager handle
+/// * `template_json` - JSON `CredentialTemplate`
+///
+/// # Returns
+/// * `DesktopError::Success` on success
+/// * `DesktopError::InvalidParameter` if parameters are invalid
+/// * `DesktopError::RepositoryNotOpen` if no repository is open
+/// * `DesktopError::SerializationError` if JSON parsing fails
+/// * `DesktopError::ValidationError` if the template does not exist or field names collide
+#[no_mangle]
+pub extern "C" fn ziplock_desktop_update_template(
+    handle: DesktopManagerHandle,
+    template_json: *const c_char,
+) -> DesktopError {
+    save_template(handle, template_json, true)
+}
+
+fn save_template(
+    handle: DesktopManagerHandle,
+    template_json: *const c_char,
+    update: bool,
+) -> DesktopError {
+    if handle.is_null() || template_json.is_null() {
+        return DesktopError::InvalidParameter;
+    }
+
+    let Some(json_str) = c_string_to_rust(template_json) else {
+        return DesktopError::InvalidParameter;
+    };
+    let template: CredentialTemplate = match serde_json::from_str(&json_str) {
+        Ok(template) => template,
+        Err(_) => return DesktopError::SerializationError,
+    };
+
+    unsafe {
+        let instance = &*handle;
+        let mut manager = match instance.manager.lock() {
+            Ok(mgr) => mgr,
+            Err(_) => return DesktopError::InternalError,
+        };
+
+        let result = if update {
+            manager.update_template(template)
+        } else {
+            manager.add_template(template)
+        };
+        match result {
+            Ok(()) => DesktopError::Success,
+            Err(CoreError::NotInitialized) => DesktopError::RepositoryNotOpen,
+            Err(CoreError::ValidationError { .. }) => DesktopError::ValidationError,
+            Err(_) => DesktopError::InternalError,
+        }
+    }
+}
+
+/// Encrypt a field with a passphrase of its own
+///
+/// Afterwards the field's value reads as `"[protected]"` until it is opened
+/// with `ziplock_desktop_unlock_field`.
+///
+/// # Arguments
+/// * `handle` - Manager handle
+/// * `credential_id` - ID of the credential
+/// * `field_name` - Name of the field
+/// * `passphrase` - Passphrase for this field only
+///
+/// # Returns
+/// * `DesktopError::Success` on success
+/// * `DesktopError::InvalidParameter` if parameters are invalid or the credential does not exist
+/// * `DesktopError::RepositoryNotOpen` if no repository is open
+/// * `DesktopError::ValidationError` if the field does not exist or is already protected
+#[no_mangle]
+pub extern "C" fn ziplock_desktop_protect_field(
+    handle: DesktopManagerHandle,
+    credential_id: *const c_char,
+    field_name: *const c_char,
+    passphrase: *const c_char,
+) -> DesktopError {
+    change_field_protection(handle, credential_id, field_name, passphrase, true)
+}
+
+/// Remove the protection of a field so it is stored like any other again
+///
+/// # Arguments
+/// * `handle` - Manager handle
+/// * `credential_id` - ID of the credential
+/// * `field_name` - Name of the field
+/// * `passphrase` - Passphrase of the field
+///
+/// # Returns
+/// * `DesktopError::Success` on success
+/// * `DesktopError::InvalidParameter` if parameters are invalid or the credential does not exist
+/// * `DesktopError::RepositoryNotOpen` if no repository is open
+/// * `DesktopError::ValidationError` if the field does not exist or is not protected
+/// * `DesktopError::InvalidPassword` if the passphrase is wrong
+#[no_mangle]
+pub extern "C" fn ziplock_desktop_unprotect_field(
+    handle: DesktopManagerHandle,
+    credential_id: *const c_char,
+    field_name: *const c_char,
+    passphrase: *const c_char,
+) -> DesktopError {
+    change_field_protection(handle, credential_id, field_name, passphrase, false)
+}
+
+/// Read the real value of a protected field
+///
+/// # Arguments
+/// * `handle` - Manager handle
+/// * `credential_id` - ID of the credential
+/// * `field_name` - Name of the field
+/// * `passphrase` - Passphrase of the field
+///
+/// # Returns
+/// * The value (must be freed with `ziplock_free_string`)
+/// * Null if parameters are invalid, the field is not protected or the
+///   passphrase is wrong
+#[no_mangle]
+pub extern "C" fn ziplock_desktop_unlock_field(
+    handle: DesktopManagerHandle,
+    credential_id: *const c_char,
+    field_name: *const c_char,
+    passphrase: *const c_char,
+) -> *mut c_char {
+    if handle.is_null() {
+        return ptr::null_mut();
+    }
+    let Some((id, field, passphrase)) = field_arguments(credential_id, field_name, passphrase)
+    else {
+        return ptr::null_mut();
+    };
+
+    unsafe {
+        let instance = &*handle;
+        let manager = match instance.manager.lock() {
+            Ok(guard) => guard,
+            Err(_) => return ptr::null_mut(),
+        };
+
+        match manager.unlock_field(&id, &field, &passphrase) {
+            Ok(value) => rust_string_to_c(value.to_string()),
+            Err(_) => ptr::null_mut(),
+        }
+    }
+}
+
+fn change_field_protection(
+    handle: DesktopManagerHandle,
+    credential_id: *const c_char,
+    field_name: *const c_char,
+    passphrase: *const c_char,
+    protect: bool,
+) -> DesktopError {
+    if handle.is_null() {
+        return DesktopError::InvalidParameter;
+    }
+    let Some((id, field, passphrase)) = field_arguments(credential_id, field_name, passphrase)
+    else {
+        return DesktopError::InvalidParameter;
+    };
+
+    unsafe {
+        let instance = &*handle;
+        let mut manager = match instance.manager.lock() {
+            Ok(guard) => guard,
+            Err(_) => return DesktopError::InternalError,
+        };
+
+        let result = if protect {
+            manager.protect_field(&id, &field, &passphrase)
+        } else {
+            manager.unprotect_field(&id, &field, &passphrase)
+        };
+        match result {
+            Ok(()) => DesktopError::Success,
+            Err(CoreError::NotInitialized) => DesktopError::RepositoryNotOpen,
+            Err(CoreError::CredentialNotFound { .. }) => DesktopError::InvalidParameter,
+            Err(CoreError::ValidationError { .. }) => DesktopError::ValidationError,
+            Err(CoreError::FieldPassphraseInvalid { .. }) => DesktopError::InvalidPassword,
+            Err(_) => DesktopError::InternalError,
+        }
+    }
+}
+
+/// Credential ID, field name and passphrase of a protected-field call
+fn field_arguments(
+    credential_id: *const c_char,
+    field_name: *const c_char,
+    passphrase: *const c_char,
+) -> Option<(String, String, Zeroizing<String>)> {
+    Some((
+        c_string_to_rust(credential_id)?,
+        c_string_to_rust(field_name)?,
+        Zeroizing::new(c_string_to_rust(passphrase)?),
+    ))
+}
+
+/// Report progress of opening and saving repositories
+///
+/// Every later open, create and save on this manager calls `callback` as it
+/// moves through the phases. Pass a null callback to stop reporting.
+///
+/// # Arguments
+/// * `handle` - Manager handle
+/// * `callback` - Progress callback (may be null)
+/// * `user_data` - Passed through to `callback`; must stay valid until the
+///   callback is replaced or the manager is destroyed
+///
+/// # Returns
+/// * `DesktopError::Success` on success
+/// * `DesktopError::InvalidParameter` if the handle is null
+#[no_mangle]
+pub extern "C" fn ziplock_desktop_set_progress_callback(
+    handle: DesktopManagerHandle,
+    callback: ZipLockProgressCallback,
+    user_data: *mut c_void,
+) -> DesktopError {
+    if handle.is_null() {
+        return DesktopError::InvalidParameter;
+    }
+
+    unsafe {
+        let instance = &*handle;
+        let mut manager = match instance.manager.lock() {
+            Ok(mgr) => mgr,
+            Err(_) => return DesktopError::InternalError,
+        };
+
+        manager.set_progress_observer(
+            FfiProgressObserver::new(callback, user_data)
+                .map(|observer| Box::new(observer) as Box<_>),
+        );
+        DesktopError::Success
+    }
+}
+
+/// Progress callback for `ziplock_desktop_change_password`
+///
+/// Called with the one-based stage number, the total number of stages and
+/// the `user_data` pointer given to the call.
+pub type DesktopProgressCallback =
+    Option<extern "C" fn(stage: c_int, total: c_int, user_data: *mut c_void)>;
+
 /// Change the master password of the repository
 ///
+/// The archive is re-encrypted into a new file, verified and then swapped in
+/// for the original, so the old password keeps working if any step fails.
+/// Key protectors registered for the archive are removed, as they wrap the
+/// old password.
+///
 /// # Arguments
 /// * `handle` - Manager handle
+/// * `old_password` - Current master password
 /// * `new_password` - New master password
+/// * `progress` - Optional callback reporting each stage (may be null)
+/// * `user_data` - Passed through to `progress`
 ///
 /// # Returns
 /// * `DesktopError::Success` on success
 /// * `DesktopError::InvalidParameter` if parameters are invalid
 /// * `DesktopError::RepositoryNotOpen` if no repository is open
+/// * `DesktopError::InvalidPassword` if `old_password` is wrong
+/// * `DesktopError::ValidationError` if `new_password` is empty
 #[no_mangle]
 pub extern "C" fn ziplock_desktop_change_password(
     handle: DesktopManagerHandle,
+    old_password: *const c_char,
     new_password: *const c_char,
+    progress: DesktopProgressCallback,
+    user_data: *mut c_void,
 ) -> DesktopError {
-    if handle.is_null() || new_password.is_null() {
+    if handle.is_null() || old_password.is_null() || new_password.is_null() {
         return DesktopError::InvalidParameter;
     }
 
@@ -699,13 +1360,36 @@ pub extern "C" fn ziplock_desktop_change_password(
             return DesktopError::RepositoryNotOpen;
         }
 
-        let password_str = match c_string_to_rust(new_password) {
-            Some(s) => s,
-            None => return DesktopError::InvalidParameter,
+        let (old_str, new_str) = match (
+            c_string_to_rust(old_password),
+            c_string_to_rust(new_password),
+        ) {
+            (Some(old), Some(new)) => (old, new),
+            _ => return DesktopError::InvalidParameter,
         };
 
-        match manager.change_master_password(&password_str) {
-            Ok(()) => DesktopError::Success,
+        let report = |stage: PasswordChangeStage| {
+            if let Some(callback) = progress {
+                callback(
+                    stage.step() as c_int,
+                    PasswordChangeStage::COUNT as c_int,
+                    user_data,
+                );
+            }
+        };
+
+        match manager.change_master_password_with_progress(&old_str, &new_str, report) {
+            Ok(_) => DesktopError::Success,
+            Err(CoreError::FileOperation(crate::core::FileError::InvalidPassword)) => {
+                DesktopError::InvalidPassword
+            }
+            Err(CoreError::FileOperation(crate::core::FileError::CorruptedArchive { .. })) => {
+                DesktopError::ArchiveCorrupted
+            }
+            Err(CoreError::FileOperation(crate::core::FileError::PermissionDenied { .. })) => {
+                DesktopError::PermissionDenied
+            }
+            Err(CoreError::ValidationError { .. }) => DesktopError::ValidationError,
             Err(_) => DesktopError::InternalError,
         }
     }
@@ -981,9 +1665,44 @@ mod tests {
             ptr::null(),
         );
 
+        extern "C" fn count_stages(stage: c_int, total: c_int, user_data: *mut c_void) {
+            assert!(stage >= 1 && stage <= total);
+            unsafe { *(user_data as *mut c_int) = stage };
+        }
+        let mut last_stage: c_int = 0;
+
+        // A wrong current password leaves the repository untouched
+        let result = ziplock_desktop_change_password(
+            handle,
+            new_password_cstr.as_ptr(),
+            new_password_cstr.as_ptr(),
+            None,
+            ptr::null_mut(),
+        );
+        assert_eq!(result, DesktopError::InvalidPassword);
+
         // Change password
-        let result = ziplock_desktop_change_password(handle, new_password_cstr.as_ptr());
+        let result = ziplock_desktop_change_password(
+            handle,
+            old_password_cstr.as_ptr(),
+            new_password_cstr.as_ptr(),
+            Some(count_stages),
+            &mut last_stage as *mut c_int as *mut c_void,
+        );
         assert_eq!(result, DesktopError::Success);
+        assert_eq!(last_stage, PasswordChangeStage::COUNT as c_int);
+        assert!(!std::path::Path::new(&format!("{}.rekey", repo_path_str)).exists());
+
+        // Only the new password opens the archive
+        ziplock_desktop_close_repository(handle);
+        assert_eq!(
+            ziplock_desktop_open_repository(handle, path_cstr.as_ptr(), old_password_cstr.as_ptr()),
+            DesktopError::InvalidPassword
+        );
+        assert_eq!(
+            ziplock_desktop_open_repository(handle, path_cstr.as_ptr(), new_password_cstr.as_ptr()),
+            DesktopError::Success
+        );
 
         ziplock_desktop_manager_destroy(handle);
     }
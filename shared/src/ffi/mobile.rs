@@ -24,17 +24,56 @@
 //! 4. All credential operations happen via FFI calls
 //! 5. Platform code retrieves file map as JSON when saving needed
 //! 6. Platform code creates new archive and writes to storage
+//!
+//! Apps that only get a byte stream from the platform (e.g. a SAF document
+//! or a Files app URL) can skip the file map instead and pass the archive
+//! bytes to `ziplock_mobile_load_from_bytes` and
+//! `ziplock_mobile_save_to_bytes`, leaving only the storage to native code.
 
 use base64::prelude::*;
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::ffi::CString;
-use std::os::raw::{c_char, c_int};
+use std::os::raw::{c_char, c_int, c_void};
 use std::ptr;
+use std::slice;
 use std::sync::Mutex;
-
-use crate::core::{CoreError, UnifiedMemoryRepository};
-use crate::ffi::common::{c_string_to_rust, rust_string_to_c, ZipLockError};
-use crate::models::CredentialRecord;
+use zeroize::Zeroizing;
+
+use crate::core::keyprotection::biometric::DEFAULT_TOKEN_TTL_DAYS;
+use crate::core::keyprotection::BiometricToken;
+use crate::core::progress::{self, ArchivePhase};
+use crate::core::types::ATTACHMENTS_DIR;
+use crate::core::{
+    ArchiveBackend, ArchiveFormat, AutoLockPolicy, CoreError, DuressArchive, MemoryBudget,
+    OpenedArchive, SevenZipBackend, UnifiedMemoryRepository, UnlockAttempts, UnlockThrottlePolicy,
+};
+use crate::ffi::common::{
+    c_string_to_rust, record_credential_error, rust_string_to_c, FfiProgressObserver, ZipLockError,
+    ZipLockProgressCallback,
+};
+use crate::ffi::widget::device_secret;
+use crate::models::{CredentialRecord, CredentialTemplate, FolderDefaults};
+use crate::utils::{
+    autofill_candidates, validate_credential, AuditOptions, AutofillDataset, AutofillTarget,
+    BackupManager, ClipInbox, ClipItem, ClipKind, ClipMessage, ExportFormat, ExportOptions,
+    Localizer, Message, ReminderEngine, SecurityAuditor, UrlMatcher, VaultHealthAnalyzer,
+    WatchAuthorizer, WatchPairing, WatchRequest, WidgetKey, WidgetSnapshot,
+};
+
+/// Progress callback for the archive functions, set by
+/// `ziplock_mobile_set_progress_callback`
+static PROGRESS_OBSERVER: Mutex<Option<FfiProgressObserver>> = Mutex::new(None);
+
+/// Caps set by `ziplock_mobile_set_memory_budget`
+static MEMORY_BUDGET: Mutex<MemoryBudget> = Mutex::new(MemoryBudget {
+    max_decrypted_records: None,
+    max_attachment_buffer: None,
+});
+
+fn memory_budget() -> MemoryBudget {
+    *MEMORY_BUDGET.lock().unwrap_or_else(|e| e.into_inner())
+}
 
 /// Handle type for mobile repository instances
 pub type MobileRepositoryHandle = *mut MobileRepositoryInstance;
@@ -42,12 +81,27 @@ pub type MobileRepositoryHandle = *mut MobileRepositoryInstance;
 /// Internal repository instance for mobile platforms
 pub struct MobileRepositoryInstance {
     repository: Mutex<UnifiedMemoryRepository>,
+    watch: Mutex<WatchAuthorizer>,
+    clip_inbox: Mutex<ClipInbox>,
+    /// Format of the archive loaded with `ziplock_mobile_load_from_bytes`,
+    /// used again by `ziplock_mobile_save_to_bytes`
+    archive_format: Mutex<ArchiveFormat>,
+    /// 7z archive and hidden section loaded with
+    /// `ziplock_mobile_load_from_bytes`, so that saving keeps the part that
+    /// was not opened
+    container: Mutex<Option<OpenedArchive>>,
 }
 
 impl MobileRepositoryInstance {
     fn new() -> Self {
+        let mut repository = UnifiedMemoryRepository::new();
+        repository.set_memory_budget(memory_budget());
         Self {
-            repository: Mutex::new(UnifiedMemoryRepository::new()),
+            repository: Mutex::new(repository),
+            watch: Mutex::new(WatchAuthorizer::new()),
+            clip_inbox: Mutex::new(ClipInbox::new()),
+            archive_format: Mutex::new(ArchiveFormat::default()),
+            container: Mutex::new(None),
         }
     }
 }
@@ -253,6 +307,185 @@ pub extern "C" fn ziplock_mobile_repository_serialize_to_files(
     }
 }
 
+/// Load the repository from the bytes of an encrypted archive
+///
+/// Does the whole extraction in memory, for hosts that read the vault
+/// through a content URI or document picker and have no file path to give.
+/// The archive format (7z, ZIP or age) is detected from the data and
+/// remembered for `ziplock_mobile_save_to_bytes`, as is the
+/// [hidden section](crate::core::duress) of a 7z archive. Unlike
+/// `ziplock_mobile_extract_temp_archive`, attachments over the memory
+/// budget are kept, because leaving them out would drop them on the next
+/// save.
+///
+/// # Arguments
+/// * `handle` - Repository handle, not yet initialized
+/// * `data` - Archive bytes
+/// * `len` - Length of `data`
+/// * `password` - Archive password
+///
+/// # Returns
+/// * `ZipLockError::Success` on success
+/// * `ZipLockError::InvalidParameter` if a pointer is null, `len` is 0 or
+///   the password is empty
+/// * `ZipLockError::AlreadyInitialized` if the repository already holds data
+/// * `ZipLockError::CorruptedArchive` if the data is not a supported archive
+/// * `ZipLockError::InvalidPassword` if the password is wrong
+#[no_mangle]
+pub extern "C" fn ziplock_mobile_load_from_bytes(
+    handle: MobileRepositoryHandle,
+    data: *const u8,
+    len: usize,
+    password: *const c_char,
+) -> ZipLockError {
+    if handle.is_null() || data.is_null() || len == 0 {
+        return ZipLockError::InvalidParameter;
+    }
+    let Some(password) = c_string_to_rust(password)
+        .filter(|password| !password.is_empty())
+        .map(Zeroizing::new)
+    else {
+        return ZipLockError::InvalidParameter;
+    };
+
+    // SAFETY: the caller guarantees `data` points to `len` readable bytes
+    let data = unsafe { slice::from_raw_parts(data, len) };
+    let instance = unsafe { &*handle };
+
+    report_progress(ArchivePhase::Decrypting);
+    let Some(format) = ArchiveFormat::detect(data) else {
+        return ZipLockError::CorruptedArchive;
+    };
+    let opened = match DuressArchive::from_bytes(data) {
+        Ok(container) => container
+            .open(&password, |archive, password| {
+                SevenZipBackend.extract(archive, password)
+            })
+            .map(|(file_map, slot)| (file_map, Some((container, slot)))),
+        Err(_) => format
+            .backend()
+            .extract(data, &password)
+            .map(|file_map| (file_map, None)),
+    };
+    let (file_map, container) = match opened {
+        Ok(opened) => opened,
+        Err(e) => return e.into(),
+    };
+
+    report_progress(ArchivePhase::Loading);
+    let mut repo = match instance.repository.lock() {
+        Ok(repo) => repo,
+        Err(_) => return ZipLockError::InternalError,
+    };
+    if let Err(e) = repo.load_from_files(file_map) {
+        return e.into();
+    }
+    *instance
+        .archive_format
+        .lock()
+        .unwrap_or_else(|e| e.into_inner()) = format;
+    *instance.container.lock().unwrap_or_else(|e| e.into_inner()) = container;
+    report_progress(ArchivePhase::Complete);
+    ZipLockError::Success
+}
+
+/// Save the repository as the bytes of an encrypted archive
+///
+/// The archive is written in the format it was loaded in, or 7z for a new
+/// repository. The repository stays modified; call
+/// `ziplock_mobile_mark_saved` once the host has stored the bytes.
+///
+/// # Arguments
+/// * `handle` - Repository handle
+/// * `password` - Archive password
+/// * `data_out` - Receives the archive bytes (free with `ziplock_mobile_free_bytes`)
+/// * `len_out` - Receives the length of the archive
+///
+/// # Returns
+/// * `ZipLockError::Success` with both outputs set
+/// * `ZipLockError::InvalidParameter` if a pointer is null or the password is empty
+/// * `ZipLockError::NotInitialized` if the repository is not initialized
+/// * `ZipLockError::FileError` if the archive could not be created
+#[no_mangle]
+pub extern "C" fn ziplock_mobile_save_to_bytes(
+    handle: MobileRepositoryHandle,
+    password: *const c_char,
+    data_out: *mut *mut u8,
+    len_out: *mut usize,
+) -> ZipLockError {
+    if handle.is_null() || data_out.is_null() || len_out.is_null() {
+        return ZipLockError::InvalidParameter;
+    }
+
+    unsafe {
+        *data_out = ptr::null_mut();
+        *len_out = 0;
+
+        let Some(password) = c_string_to_rust(password)
+            .filter(|password| !password.is_empty())
+            .map(Zeroizing::new)
+        else {
+            return ZipLockError::InvalidParameter;
+        };
+        let instance = &*handle;
+
+        report_progress(ArchivePhase::Serializing);
+        let file_map = {
+            let repo = match instance.repository.lock() {
+                Ok(repo) => repo,
+                Err(_) => return ZipLockError::InternalError,
+            };
+            match repo.serialize_to_files() {
+                Ok(file_map) => file_map,
+                Err(e) => return e.into(),
+            }
+        };
+
+        report_progress(ArchivePhase::Encrypting);
+        let format = *instance
+            .archive_format
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let mut container = instance.container.lock().unwrap_or_else(|e| e.into_inner());
+        let archive = match format
+            .backend()
+            .create(file_map, &password)
+            .and_then(|archive| DuressArchive::with_archive(container.as_ref(), archive, &password))
+        {
+            Ok((archive, saved)) => {
+                *container = saved;
+                archive.into_boxed_slice()
+            }
+            Err(e) => return e.into(),
+        };
+
+        report_progress(ArchivePhase::Complete);
+        *len_out = archive.len();
+        *data_out = Box::into_raw(archive) as *mut u8;
+        ZipLockError::Success
+    }
+}
+
+/// Free archive bytes returned by `ziplock_mobile_save_to_bytes`
+///
+/// # Arguments
+/// * `data` - Pointer from `data_out` (may be null)
+/// * `len` - Length from `len_out`
+///
+/// # Safety
+/// `data` and `len` must come from the same successful call, and `data`
+/// must not be used afterwards
+#[no_mangle]
+pub extern "C" fn ziplock_mobile_free_bytes(data: *mut u8, len: usize) {
+    if data.is_null() {
+        return;
+    }
+
+    unsafe {
+        let _ = Box::from_raw(ptr::slice_from_raw_parts_mut(data, len));
+    }
+}
+
 /// Add a new credential to the repository
 ///
 /// # Arguments
@@ -264,6 +497,8 @@ pub extern "C" fn ziplock_mobile_repository_serialize_to_files(
 /// * `ZipLockError::InvalidParameter` if parameters are invalid
 /// * `ZipLockError::NotInitialized` if repository not initialized
 /// * `ZipLockError::SerializationError` if JSON parsing fails
+/// * `ZipLockError::ValidationError` if the credential is invalid; the
+///   problems are available from `ziplock_get_last_error_localized`
 #[no_mangle]
 pub extern "C" fn ziplock_mobile_add_credential(
     handle: MobileRepositoryHandle,
@@ -290,11 +525,16 @@ pub extern "C" fn ziplock_mobile_add_credential(
             Err(_) => return ZipLockError::SerializationError,
         };
 
-        match repo.add_credential(credential) {
+        match repo.add_credential(credential.clone()) {
             Ok(()) => ZipLockError::Success,
-            Err(CoreError::NotInitialized) => ZipLockError::NotInitialized,
-            Err(CoreError::ValidationError { .. }) => ZipLockError::ValidationError,
-            Err(_) => ZipLockError::InternalError,
+            Err(error) => {
+                record_credential_error(&credential, &error);
+                match error {
+                    CoreError::NotInitialized => ZipLockError::NotInitialized,
+                    CoreError::ValidationError { .. } => ZipLockError::ValidationError,
+                    _ => ZipLockError::InternalError,
+                }
+            }
         }
     }
 }
@@ -339,6 +579,51 @@ pub extern "C" fn ziplock_mobile_get_credential(
     }
 }
 
+/// Check a credential before saving it, with messages in `locale`
+///
+/// Runs the same checks as `ziplock_mobile_add_credential`, without a
+/// repository, so forms can show every problem at once.
+///
+/// # Arguments
+/// * `credential_json` - JSON string containing credential data
+/// * `locale` - Language tag such as `de` or `pt-BR`; null for English
+///
+/// # Returns
+/// * JSON `{"valid", "errors", "warnings"}`, where errors and warnings are
+///   `{"code", "args", "message"}` objects (must be freed with `ziplock_free_string`)
+/// * Null if the JSON cannot be parsed
+#[no_mangle]
+pub extern "C" fn ziplock_mobile_validate_credential(
+    credential_json: *const c_char,
+    locale: *const c_char,
+) -> *mut c_char {
+    let Some(json_str) = c_string_to_rust(credential_json) else {
+        return ptr::null_mut();
+    };
+    let mut credential: CredentialRecord = match serde_json::from_str(&json_str) {
+        Ok(credential) => credential,
+        Err(_) => return ptr::null_mut(),
+    };
+
+    // New credentials get an ID when they are added
+    crate::utils::validation::repair_credential_id(&mut credential);
+    let result = validate_credential(&credential);
+    let localizer = Localizer::new(&c_string_to_rust(locale).unwrap_or_default());
+    let localize = |messages: &[Message]| -> Vec<_> {
+        messages
+            .iter()
+            .map(|message| localizer.localize(message))
+            .collect()
+    };
+
+    let report = serde_json::json!({
+        "valid": result.is_valid,
+        "errors": localize(&result.error_messages),
+        "warnings": localize(&result.warning_messages),
+    });
+    rust_string_to_c(report.to_string())
+}
+
 /// Update an existing credential
 ///
 /// # Arguments
@@ -350,6 +635,8 @@ pub extern "C" fn ziplock_mobile_get_credential(
 /// * `ZipLockError::InvalidParameter` if parameters are invalid
 /// * `ZipLockError::NotInitialized` if repository not initialized
 /// * `ZipLockError::SerializationError` if JSON parsing fails
+/// * `ZipLockError::ValidationError` if the credential is invalid; the
+///   problems are available from `ziplock_get_last_error_localized`
 #[no_mangle]
 pub extern "C" fn ziplock_mobile_update_credential(
     handle: MobileRepositoryHandle,
@@ -376,12 +663,17 @@ pub extern "C" fn ziplock_mobile_update_credential(
             Err(_) => return ZipLockError::SerializationError,
         };
 
-        match repo.update_credential(credential) {
+        match repo.update_credential(credential.clone()) {
             Ok(()) => ZipLockError::Success,
-            Err(CoreError::NotInitialized) => ZipLockError::NotInitialized,
-            Err(CoreError::CredentialNotFound { .. }) => ZipLockError::InvalidParameter,
-            Err(CoreError::ValidationError { .. }) => ZipLockError::ValidationError,
-            Err(_) => ZipLockError::InternalError,
+            Err(error) => {
+                record_credential_error(&credential, &error);
+                match error {
+                    CoreError::NotInitialized => ZipLockError::NotInitialized,
+                    CoreError::CredentialNotFound { .. } => ZipLockError::InvalidParameter,
+                    CoreError::ValidationError { .. } => ZipLockError::ValidationError,
+                    _ => ZipLockError::InternalError,
+                }
+            }
         }
     }
 }
@@ -554,6 +846,50 @@ pub extern "C" fn ziplock_mobile_mark_saved(handle: MobileRepositoryHandle) -> Z
     }
 }
 
+/// Wipe decrypted credentials before the app moves to the background
+///
+/// Call from the platform's background transition (`onStop` on Android,
+/// `sceneDidEnterBackground` on iOS) so secrets are not kept in a process
+/// that may be swapped or snapshotted. Afterwards the handle is
+/// uninitialized; load the archive again after the user unlocks.
+///
+/// Unsaved changes are kept unless `discard_unsaved` is non-zero: persist
+/// the output of `ziplock_mobile_repository_serialize_to_files`, call
+/// `ziplock_mobile_mark_saved`, then call this again.
+///
+/// # Arguments
+/// * `handle` - Repository handle
+/// * `discard_unsaved` - Non-zero to wipe even if there are unsaved changes
+///
+/// # Returns
+/// * `ZipLockError::Success` if the repository was wiped (or was not loaded)
+/// * `ZipLockError::ValidationError` if there are unsaved changes
+/// * `ZipLockError::InvalidParameter` if handle is invalid
+#[no_mangle]
+pub extern "C" fn ziplock_mobile_prepare_for_background(
+    handle: MobileRepositoryHandle,
+    discard_unsaved: c_int,
+) -> ZipLockError {
+    if handle.is_null() {
+        return ZipLockError::InvalidParameter;
+    }
+
+    unsafe {
+        let instance = &*handle;
+        let mut repo = match instance.repository.lock() {
+            Ok(repo) => repo,
+            Err(_) => return ZipLockError::InternalError,
+        };
+
+        if repo.is_initialized() && repo.is_modified() && discard_unsaved == 0 {
+            return ZipLockError::ValidationError;
+        }
+
+        repo.wipe();
+        ZipLockError::Success
+    }
+}
+
 /// Get repository statistics
 ///
 /// # Arguments
@@ -585,154 +921,1312 @@ pub extern "C" fn ziplock_mobile_get_stats(handle: MobileRepositoryHandle) -> *m
     }
 }
 
-/// Clear all credentials from the repository
+/// Get the vault health score and checklist
 ///
 /// # Arguments
 /// * `handle` - Repository handle
+/// * `last_backup_timestamp` - Unix time of the last successful backup, or 0 if unknown
 ///
 /// # Returns
-/// * `ZipLockError::Success` on success
-/// * `ZipLockError::InvalidParameter` if handle is invalid
-/// * `ZipLockError::NotInitialized` if repository not initialized
+/// * JSON string containing the health report (must be freed with `ziplock_free_string`)
+/// * Null if error
 #[no_mangle]
-pub extern "C" fn ziplock_mobile_clear_credentials(handle: MobileRepositoryHandle) -> ZipLockError {
+pub extern "C" fn ziplock_mobile_get_health(
+    handle: MobileRepositoryHandle,
+    last_backup_timestamp: i64,
+) -> *mut c_char {
     if handle.is_null() {
-        return ZipLockError::InvalidParameter;
+        return ptr::null_mut();
     }
 
     unsafe {
         let instance = &*handle;
-        let mut repo = match instance.repository.lock() {
+        let repo = match instance.repository.lock() {
             Ok(repo) => repo,
-            Err(_) => return ZipLockError::InternalError,
+            Err(_) => return ptr::null_mut(),
         };
 
-        match repo.clear() {
-            Ok(()) => ZipLockError::Success,
-            Err(CoreError::NotInitialized) => ZipLockError::NotInitialized,
-            Err(_) => ZipLockError::InternalError,
+        let credentials = match repo.list_credentials() {
+            Ok(credentials) => credentials,
+            Err(_) => return ptr::null_mut(),
+        };
+
+        let last_backup = match last_backup_timestamp {
+            0 => None,
+            ts => chrono::DateTime::from_timestamp(ts, 0),
+        };
+        let health = VaultHealthAnalyzer::new()
+            .with_last_backup(last_backup)
+            .analyze(&credentials);
+
+        match serde_json::to_string(&health) {
+            Ok(json) => rust_string_to_c(json),
+            Err(_) => ptr::null_mut(),
         }
     }
 }
 
-/// Free a string returned by this library
+/// Get the expirations and password changes that are overdue or coming up
+///
+/// Each reminder carries `due_at`, so the app can schedule a local
+/// notification for it; call again after unlocking or saving.
 ///
 /// # Arguments
-/// * `str_ptr` - String pointer to free
+/// * `handle` - Repository handle
+/// * `lead_days` - How many days ahead to look; 0 for overdue reminders only
 ///
-/// # Safety
-/// Pointer must have been returned by this library and not already freed
+/// # Returns
+/// * JSON array of reminders, soonest first (must be freed with `ziplock_free_string`)
+/// * Null if error
 #[no_mangle]
-pub extern "C" fn ziplock_mobile_free_string(str_ptr: *mut c_char) {
-    if str_ptr.is_null() {
-        return;
+pub extern "C" fn ziplock_mobile_get_reminders(
+    handle: MobileRepositoryHandle,
+    lead_days: u32,
+) -> *mut c_char {
+    if handle.is_null() {
+        return ptr::null_mut();
     }
 
     unsafe {
-        let _ = CString::from_raw(str_ptr);
+        let instance = &*handle;
+        let repo = match instance.repository.lock() {
+            Ok(repo) => repo,
+            Err(_) => return ptr::null_mut(),
+        };
+
+        let credentials = match repo.list_credentials() {
+            Ok(credentials) => credentials,
+            Err(_) => return ptr::null_mut(),
+        };
+
+        let reminders = ReminderEngine::new()
+            .with_lead_days(lead_days)
+            .reminders(&credentials);
+        match serde_json::to_string(&reminders) {
+            Ok(json) => rust_string_to_c(json),
+            Err(_) => ptr::null_mut(),
+        }
     }
 }
 
-/// Create an encrypted archive from file map JSON to a temporary file location
+/// Export credentials as an encrypted, read-only HTML page
 ///
-/// This function creates a properly encrypted 7z archive using sevenz-rust2 and saves it
-/// to a temporary file that can be accessed by the mobile platform for moving to the
-/// final destination using platform-specific file operations (like SAF on Android).
+/// The page opens in any browser and decrypts itself with the passphrase,
+/// for emergency access from a device without ZipLock. Key stretching makes
+/// this slow; call it from a background thread.
 ///
 /// # Arguments
-/// * `files_json` - JSON string containing file map (path -> base64 content mappings)
-/// * `password` - Password for AES-256 archive encryption
-/// * `temp_path_out` - Output buffer to receive the temporary file path (caller must free)
+/// * `handle` - Repository handle
+/// * `ids_json` - JSON array of credential IDs, or null for all credentials
+/// * `passphrase` - Passphrase for the page, at least 8 characters
 ///
 /// # Returns
-/// * `ZipLockError::Success` on success
-/// * `ZipLockError::InvalidParameter` if parameters are invalid
-/// * `ZipLockError::SerializationError` if JSON parsing fails
-/// * `ZipLockError::CryptoError` if archive creation/encryption fails
-/// * `ZipLockError::OutOfMemory` if memory allocation fails
-///
-/// # Safety
-/// The caller must free the returned temp_path_out string using ziplock_mobile_free_string
+/// * The HTML page (must be freed with `ziplock_free_string`)
+/// * Null if error
 #[no_mangle]
-pub extern "C" fn ziplock_mobile_create_temp_archive(
-    files_json: *const c_char,
-    password: *const c_char,
-    temp_path_out: *mut *mut c_char,
-) -> ZipLockError {
-    if files_json.is_null() || password.is_null() || temp_path_out.is_null() {
-        return ZipLockError::InvalidParameter;
+pub extern "C" fn ziplock_mobile_export_html_viewer(
+    handle: MobileRepositoryHandle,
+    ids_json: *const c_char,
+    passphrase: *const c_char,
+) -> *mut c_char {
+    if handle.is_null() {
+        return ptr::null_mut();
     }
 
-    unsafe {
-        // Initialize output to null
-        *temp_path_out = ptr::null_mut();
-
-        // Parse input parameters
-        let json_str = match c_string_to_rust(files_json) {
-            Some(s) => s,
-            None => return ZipLockError::InvalidParameter,
-        };
+    let Some(passphrase) = c_string_to_rust(passphrase) else {
+        return ptr::null_mut();
+    };
+    let credential_ids = match c_string_to_rust(ids_json) {
+        Some(json) => match serde_json::from_str::<Vec<String>>(&json) {
+            Ok(ids) => Some(ids),
+            Err(_) => return ptr::null_mut(),
+        },
+        None => None,
+    };
 
-        let password_str = match c_string_to_rust(password) {
-            Some(s) => s,
-            None => return ZipLockError::InvalidParameter,
+    unsafe {
+        let instance = &*handle;
+        let repo = match instance.repository.lock() {
+            Ok(repo) => repo,
+            Err(_) => return ptr::null_mut(),
         };
 
-        if password_str.is_empty() {
-            return ZipLockError::InvalidParameter; // Archive must be encrypted
-        }
-
-        // Parse JSON file map
-        let file_map_raw: HashMap<String, String> = match serde_json::from_str(&json_str) {
-            Ok(map) => map,
-            Err(_) => return ZipLockError::SerializationError,
+        let options = ExportOptions {
+            format: ExportFormat::HtmlViewer,
+            credential_ids,
+            encryption_password: Some(passphrase),
+            ..Default::default()
         };
-
-        // Convert base64 content to bytes
-        let mut file_map = HashMap::new();
-        for (path, base64_content) in file_map_raw {
-            let content = match BASE64_STANDARD.decode(base64_content) {
-                Ok(bytes) => bytes,
-                Err(_) => return ZipLockError::SerializationError,
-            };
-            file_map.insert(path, content);
-        }
-
-        // Create temporary file path
-        let temp_id = uuid::Uuid::new_v4();
-        let temp_path = std::env::temp_dir().join(format!("ziplock_temp_{}.7z", temp_id));
-
-        // Use DesktopFileProvider to create encrypted archive
-        use crate::core::file_provider::{DesktopFileProvider, FileOperationProvider};
-        let provider = DesktopFileProvider::new();
-
-        match provider.create_archive(file_map, &password_str) {
-            Ok(archive_data) => {
-                // Write archive to temporary file
-                match std::fs::write(&temp_path, archive_data) {
-                    Ok(()) => {
-                        // Return temporary file path
-                        let path_string = temp_path.to_string_lossy().to_string();
-                        *temp_path_out = rust_string_to_c(path_string);
-                        ZipLockError::Success
-                    }
-                    Err(_) => ZipLockError::InternalError,
-                }
-            }
-            Err(_) => ZipLockError::FileError,
+        match BackupManager::export_repository(&repo, &options)
+            .ok()
+            .and_then(|html| String::from_utf8(html).ok())
+        {
+            Some(html) => rust_string_to_c(html),
+            None => ptr::null_mut(),
         }
     }
 }
 
-/// Extract archive from temporary file path to file map (JSON)
+/// Run a security audit over all credentials
 ///
-/// This function complements the temp archive creation by providing
-/// FFI-based extraction that ensures proper decryption using sevenz-rust2.
+/// With breach checking enabled this blocks on network requests (online
+/// mode) or file reads (bloom filter mode); call it from a background thread.
 ///
-/// # Parameters
-/// * `archive_path` - Path to the 7z archive file
-/// * `password` - Password for decryption
-/// * `files_json_out` - Output parameter for JSON file map (path -> base64 content)
+/// # Arguments
+/// * `handle` - Repository handle
+/// * `options_json` - JSON `AuditOptions`, or null for defaults (no breach check)
+///
+/// # Returns
+/// * JSON string containing the audit report (must be freed with `ziplock_free_string`)
+/// * Null if error
+#[no_mangle]
+pub extern "C" fn ziplock_mobile_security_audit(
+    handle: MobileRepositoryHandle,
+    options_json: *const c_char,
+) -> *mut c_char {
+    if handle.is_null() {
+        return ptr::null_mut();
+    }
+
+    let options = if options_json.is_null() {
+        AuditOptions::default()
+    } else {
+        match c_string_to_rust(options_json).map(|json| serde_json::from_str(&json)) {
+            Some(Ok(options)) => options,
+            _ => return ptr::null_mut(),
+        }
+    };
+
+    unsafe {
+        let instance = &*handle;
+        let credentials = match instance.repository.lock() {
+            Ok(repo) => match repo.list_credentials() {
+                Ok(credentials) => credentials,
+                Err(_) => return ptr::null_mut(),
+            },
+            Err(_) => return ptr::null_mut(),
+        };
+
+        // The repository lock is released before any breach lookups
+        let report = SecurityAuditor::new(options).audit(&credentials);
+        match serde_json::to_string(&report) {
+            Ok(json) => rust_string_to_c(json),
+            Err(_) => ptr::null_mut(),
+        }
+    }
+}
+
+/// Build the access review report from the credentials' compliance metadata
+///
+/// # Arguments
+/// * `handle` - Repository handle
+/// * `options_json` - JSON `AuditOptions`, or null for defaults (90 day review interval)
+/// * `format` - `"json"` or `"csv"`; null means JSON
+///
+/// # Returns
+/// * Report string (must be freed with `ziplock_free_string`)
+/// * Null if error
+#[no_mangle]
+pub extern "C" fn ziplock_mobile_compliance_report(
+    handle: MobileRepositoryHandle,
+    options_json: *const c_char,
+    format: *const c_char,
+) -> *mut c_char {
+    if handle.is_null() {
+        return ptr::null_mut();
+    }
+
+    let options = if options_json.is_null() {
+        AuditOptions::default()
+    } else {
+        match c_string_to_rust(options_json).map(|json| serde_json::from_str(&json)) {
+            Some(Ok(options)) => options,
+            _ => return ptr::null_mut(),
+        }
+    };
+    let as_csv = match c_string_to_rust(format).as_deref() {
+        None | Some("json") => false,
+        Some("csv") => true,
+        Some(_) => return ptr::null_mut(),
+    };
+
+    unsafe {
+        let instance = &*handle;
+        let credentials = match instance.repository.lock() {
+            Ok(repo) => match repo.list_credentials() {
+                Ok(credentials) => credentials,
+                Err(_) => return ptr::null_mut(),
+            },
+            Err(_) => return ptr::null_mut(),
+        };
+
+        let report = SecurityAuditor::new(options).compliance_report(&credentials);
+        let output = if as_csv {
+            report.to_csv().ok()
+        } else {
+            serde_json::to_string(&report).ok()
+        };
+        output.map_or(ptr::null_mut(), rust_string_to_c)
+    }
+}
+
+/// Set the icon, color and template new credentials in a folder inherit
+///
+/// # Arguments
+/// * `handle` - Repository handle
+/// * `folder_path` - Folder path such as `Work/Servers`
+/// * `defaults_json` - JSON `FolderDefaults`; an empty object clears the folder's defaults
+///
+/// # Returns
+/// * `ZipLockError::Success` on success
+/// * `ZipLockError::InvalidParameter` if parameters are invalid
+/// * `ZipLockError::NotInitialized` if repository not initialized
+/// * `ZipLockError::SerializationError` if JSON parsing fails
+/// * `ZipLockError::ValidationError` if the path or a default is invalid
+#[no_mangle]
+pub extern "C" fn ziplock_mobile_set_folder_defaults(
+    handle: MobileRepositoryHandle,
+    folder_path: *const c_char,
+    defaults_json: *const c_char,
+) -> ZipLockError {
+    if handle.is_null() || folder_path.is_null() || defaults_json.is_null() {
+        return ZipLockError::InvalidParameter;
+    }
+
+    let (Some(folder_path), Some(json_str)) = (
+        c_string_to_rust(folder_path),
+        c_string_to_rust(defaults_json),
+    ) else {
+        return ZipLockError::InvalidParameter;
+    };
+    let defaults: FolderDefaults = match serde_json::from_str(&json_str) {
+        Ok(defaults) => defaults,
+        Err(_) => return ZipLockError::SerializationError,
+    };
+
+    unsafe {
+        let instance = &*handle;
+        let mut repo = match instance.repository.lock() {
+            Ok(repo) => repo,
+            Err(_) => return ZipLockError::InternalError,
+        };
+
+        match repo.set_folder_defaults(&folder_path, defaults) {
+            Ok(()) => ZipLockError::Success,
+            Err(CoreError::NotInitialized) => ZipLockError::NotInitialized,
+            Err(CoreError::ValidationError { .. }) => ZipLockError::ValidationError,
+            Err(_) => ZipLockError::InternalError,
+        }
+    }
+}
+
+/// Get the form for a new credential, with its folder's defaults resolved
+///
+/// # Arguments
+/// * `handle` - Repository handle
+/// * `folder_path` - Folder the credential is created in, or null
+/// * `template` - Template name chosen by the user, or null to use the folder's
+///
+/// # Returns
+/// * JSON `CredentialFormSchema` (must be freed with `ziplock_free_string`)
+/// * Null if the repository is not initialized or the template is unknown
+#[no_mangle]
+pub extern "C" fn ziplock_mobile_credential_form_schema(
+    handle: MobileRepositoryHandle,
+    folder_path: *const c_char,
+    template: *const c_char,
+) -> *mut c_char {
+    if handle.is_null() {
+        return ptr::null_mut();
+    }
+
+    let folder_path = c_string_to_rust(folder_path);
+    let template = c_string_to_rust(template);
+
+    unsafe {
+        let instance = &*handle;
+        let repo = match instance.repository.lock() {
+            Ok(repo) => repo,
+            Err(_) => return ptr::null_mut(),
+        };
+
+        match repo.credential_form_schema(folder_path.as_deref(), template.as_deref()) {
+            Ok(schema) => match serde_json::to_string(&schema) {
+                Ok(json) => rust_string_to_c(json),
+                Err(_) => ptr::null_mut(),
+            },
+            Err(_) => ptr::null_mut(),
+        }
+    }
+}
+
+/// List the user-defined credential templates
+///
+/// # Arguments
+/// * `handle` - Repository handle
+///
+/// # Returns
+/// * JSON array of `CredentialTemplate`s sorted by name (must be freed with `ziplock_free_string`)
+/// * Null if the repository is not initialized
+#[no_mangle]
+pub extern "C" fn ziplock_mobile_list_templates(handle: MobileRepositoryHandle) -> *mut c_char {
+    if handle.is_null() {
+        return ptr::null_mut();
+    }
+
+    unsafe {
+        let instance = &*handle;
+        let repo = match instance.repository.lock() {
+            Ok(repo) => repo,
+            Err(_) => return ptr::null_mut(),
+        };
+
+        match repo.list_templates() {
+            Ok(templates) => match serde_json::to_string(&templates) {
+                Ok(json) => rust_string_to_c(json),
+                Err(_) => ptr::null_mut(),
+            },
+            Err(_) => ptr::null_mut(),
+        }
+    }
+}
+
+/// Save a new user-defined credential template
+///
+/// # Arguments
+/// * `handle` - Repository handle
+/// * `template_json` - JSON `CredentialTemplate`
+///
+/// # Returns
+/// * `ZipLockError::Success` on success
+/// * `ZipLockError::InvalidParameter` if parameters are invalid
+/// * `ZipLockError::NotInitialized` if repository not initialized
+/// * `ZipLockError::SerializationError` if JSON parsing fails
+/// * `ZipLockError::ValidationError` if the name is taken or field names collide
+#[no_mangle]
+pub extern "C" fn ziplock_mobile_add_template(
+    handle: MobileRepositoryHandle,
+    template_json: *const c_char,
+) -> ZipLockError {
+    save_template(handle, template_json, false)
+}
+
+/// Replace a user-defined credential template with the same name
+///
+/// # Arguments
+/// * `handle` - Repository handle
+/// * `template_json` - JSON `CredentialTemplate`
+///
+/// # Returns
+/// * `ZipLockError::Success` on success
+/// * `ZipLockError::InvalidParameter` if parameters are invalid
+/// * `ZipLockError::NotInitialized` if repository not initialized
+/// * `ZipLockError::SerializationError` if JSON parsing fails
+/// * `ZipLockError::ValidationError` if the template does not exist or field names collide
+#[no_mangle]
+pub extern "C" fn ziplock_mobile_update_template(
+    handle: MobileRepositoryHandle,
+    template_json: *const c_char,
+) -> ZipLockError {
+    save_template(handle, template_json, true)
+}
+
+fn save_template(
+    handle: MobileRepositoryHandle,
+    template_json: *const c_char,
+    update: bool,
+) -> ZipLockError {
+    if handle.is_null() || template_json.is_null() {
+        return ZipLockError::InvalidParameter;
+    }
+
+    let Some(json_str) = c_string_to_rust(template_json) else {
+        return ZipLockError::InvalidParameter;
+    };
+    let template: CredentialTemplate = match serde_json::from_str(&json_str) {
+        Ok(template) => template,
+        Err(_) => return ZipLockError::SerializationError,
+    };
+
+    unsafe {
+        let instance = &*handle;
+        let mut repo = match instance.repository.lock() {
+            Ok(repo) => repo,
+            Err(_) => return ZipLockError::InternalError,
+        };
+
+        let result = if update {
+            repo.update_template(template)
+        } else {
+            repo.add_template(template)
+        };
+        match result {
+            Ok(()) => ZipLockError::Success,
+            Err(CoreError::NotInitialized) => ZipLockError::NotInitialized,
+            Err(CoreError::ValidationError { .. }) => ZipLockError::ValidationError,
+            Err(_) => ZipLockError::InternalError,
+        }
+    }
+}
+
+/// Encrypt a field with a passphrase of its own
+///
+/// Afterwards the field's value reads as `"[protected]"` until it is opened
+/// with `ziplock_mobile_unlock_field`.
+///
+/// # Arguments
+/// * `handle` - Repository handle
+/// * `credential_id` - ID of the credential
+/// * `field_name` - Name of the field
+/// * `passphrase` - Passphrase for this field only
+///
+/// # Returns
+/// * `ZipLockError::Success` on success
+/// * `ZipLockError::InvalidParameter` if parameters are invalid
+/// * `ZipLockError::NotInitialized` if repository not initialized
+/// * `ZipLockError::CredentialNotFound` if the credential does not exist
+/// * `ZipLockError::ValidationError` if the field does not exist or is already protected
+#[no_mangle]
+pub extern "C" fn ziplock_mobile_protect_field(
+    handle: MobileRepositoryHandle,
+    credential_id: *const c_char,
+    field_name: *const c_char,
+    passphrase: *const c_char,
+) -> ZipLockError {
+    change_field_protection(handle, credential_id, field_name, passphrase, true)
+}
+
+/// Remove the protection of a field so it is stored like any other again
+///
+/// # Arguments
+/// * `handle` - Repository handle
+/// * `credential_id` - ID of the credential
+/// * `field_name` - Name of the field
+/// * `passphrase` - Passphrase of the field
+///
+/// # Returns
+/// * `ZipLockError::Success` on success
+/// * `ZipLockError::InvalidParameter` if parameters are invalid
+/// * `ZipLockError::NotInitialized` if repository not initialized
+/// * `ZipLockError::CredentialNotFound` if the credential does not exist
+/// * `ZipLockError::ValidationError` if the field does not exist or is not protected
+/// * `ZipLockError::InvalidPassword` if the passphrase is wrong
+#[no_mangle]
+pub extern "C" fn ziplock_mobile_unprotect_field(
+    handle: MobileRepositoryHandle,
+    credential_id: *const c_char,
+    field_name: *const c_char,
+    passphrase: *const c_char,
+) -> ZipLockError {
+    change_field_protection(handle, credential_id, field_name, passphrase, false)
+}
+
+/// Read the real value of a protected field
+///
+/// # Arguments
+/// * `handle` - Repository handle
+/// * `credential_id` - ID of the credential
+/// * `field_name` - Name of the field
+/// * `passphrase` - Passphrase of the field
+///
+/// # Returns
+/// * The value (must be freed with `ziplock_free_string`)
+/// * Null if parameters are invalid, the field is not protected or the
+///   passphrase is wrong
+#[no_mangle]
+pub extern "C" fn ziplock_mobile_unlock_field(
+    handle: MobileRepositoryHandle,
+    credential_id: *const c_char,
+    field_name: *const c_char,
+    passphrase: *const c_char,
+) -> *mut c_char {
+    if handle.is_null() {
+        return ptr::null_mut();
+    }
+    let Some((id, field, passphrase)) = field_arguments(credential_id, field_name, passphrase)
+    else {
+        return ptr::null_mut();
+    };
+
+    unsafe {
+        let instance = &*handle;
+        let repository = match instance.repository.lock() {
+            Ok(guard) => guard,
+            Err(_) => return ptr::null_mut(),
+        };
+
+        match repository.unlock_field(&id, &field, &passphrase) {
+            Ok(value) => rust_string_to_c(value.to_string()),
+            Err(_) => ptr::null_mut(),
+        }
+    }
+}
+
+fn change_field_protection(
+    handle: MobileRepositoryHandle,
+    credential_id: *const c_char,
+    field_name: *const c_char,
+    passphrase: *const c_char,
+    protect: bool,
+) -> ZipLockError {
+    if handle.is_null() {
+        return ZipLockError::InvalidParameter;
+    }
+    let Some((id, field, passphrase)) = field_arguments(credential_id, field_name, passphrase)
+    else {
+        return ZipLockError::InvalidParameter;
+    };
+
+    unsafe {
+        let instance = &*handle;
+        let mut repository = match instance.repository.lock() {
+            Ok(guard) => guard,
+            Err(_) => return ZipLockError::InternalError,
+        };
+
+        let result = if protect {
+            repository.protect_field(&id, &field, &passphrase)
+        } else {
+            repository.unprotect_field(&id, &field, &passphrase)
+        };
+        match result {
+            Ok(()) => ZipLockError::Success,
+            Err(CoreError::NotInitialized) => ZipLockError::NotInitialized,
+            Err(CoreError::CredentialNotFound { .. }) => ZipLockError::CredentialNotFound,
+            Err(CoreError::ValidationError { .. }) => ZipLockError::ValidationError,
+            Err(CoreError::FieldPassphraseInvalid { .. }) => ZipLockError::InvalidPassword,
+            Err(_) => ZipLockError::InternalError,
+        }
+    }
+}
+
+/// Credential ID, field name and passphrase of a protected-field call
+fn field_arguments(
+    credential_id: *const c_char,
+    field_name: *const c_char,
+    passphrase: *const c_char,
+) -> Option<(String, String, Zeroizing<String>)> {
+    Some((
+        c_string_to_rust(credential_id)?,
+        c_string_to_rust(field_name)?,
+        Zeroizing::new(c_string_to_rust(passphrase)?),
+    ))
+}
+
+/// Evaluate the auto-lock policy at the device's local time
+///
+/// Apps call this from their activity timer, and with `idle_seconds` 0
+/// before unlocking to honour hard-lock windows.
+///
+/// # Arguments
+/// * `policy_json` - JSON `AutoLockPolicy` (idle timeout and lock windows)
+/// * `idle_seconds` - Seconds since the last user activity
+///
+/// # Returns
+/// * JSON `LockDecision` (must be freed with `ziplock_free_string`)
+/// * Null if the policy is invalid
+#[no_mangle]
+pub extern "C" fn ziplock_mobile_evaluate_lock_policy(
+    policy_json: *const c_char,
+    idle_seconds: u64,
+) -> *mut c_char {
+    let policy: AutoLockPolicy =
+        match c_string_to_rust(policy_json).map(|json| serde_json::from_str(&json)) {
+            Some(Ok(policy)) => policy,
+            _ => return ptr::null_mut(),
+        };
+
+    let decision = policy.evaluate(
+        std::time::Duration::from_secs(idle_seconds),
+        chrono::Local::now().naive_local(),
+    );
+    match serde_json::to_string(&decision) {
+        Ok(json) => rust_string_to_c(json),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// How unlocking is throttled after failed attempts, for a countdown on
+/// the lock screen
+///
+/// The app keeps the attempts state in its own settings, not the archive,
+/// and updates it with `ziplock_mobile_record_unlock_attempt`.
+///
+/// # Arguments
+/// * `policy_json` - JSON `UnlockThrottlePolicy`; null for the defaults
+/// * `attempts_json` - JSON `UnlockAttempts` saved for this archive; null if none
+///
+/// # Returns
+/// * JSON `UnlockStatus` with `remaining_attempts`, `retry_in_secs` and
+///   `locked_out` (must be freed with `ziplock_free_string`)
+/// * Null if either argument is invalid
+#[no_mangle]
+pub extern "C" fn ziplock_mobile_unlock_status(
+    policy_json: *const c_char,
+    attempts_json: *const c_char,
+) -> *mut c_char {
+    let Some((policy, attempts)) = unlock_throttle_state(policy_json, attempts_json) else {
+        return ptr::null_mut();
+    };
+    match serde_json::to_string(&policy.status(&attempts, chrono::Utc::now())) {
+        Ok(json) => rust_string_to_c(json),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Record the outcome of an unlock attempt
+///
+/// Call before unlocking to check `ziplock_mobile_unlock_status` is not
+/// waiting, then with the result; only a wrong password counts as failed.
+///
+/// # Arguments
+/// * `policy_json` - JSON `UnlockThrottlePolicy`; null for the defaults
+/// * `attempts_json` - JSON `UnlockAttempts` saved for this archive; null if none
+/// * `succeeded` - 1 if the password was right, 0 if it was wrong
+///
+/// # Returns
+/// * JSON `UnlockAttempts` to save in place of the old one (must be freed
+///   with `ziplock_free_string`)
+/// * Null if either argument is invalid
+#[no_mangle]
+pub extern "C" fn ziplock_mobile_record_unlock_attempt(
+    policy_json: *const c_char,
+    attempts_json: *const c_char,
+    succeeded: c_int,
+) -> *mut c_char {
+    let Some((policy, mut attempts)) = unlock_throttle_state(policy_json, attempts_json) else {
+        return ptr::null_mut();
+    };
+    if succeeded != 0 {
+        policy.record_success(&mut attempts);
+    } else {
+        policy.record_failure(&mut attempts, chrono::Utc::now());
+    }
+    match serde_json::to_string(&attempts) {
+        Ok(json) => rust_string_to_c(json),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Parse the optional policy and attempts arguments of the throttle calls
+fn unlock_throttle_state(
+    policy_json: *const c_char,
+    attempts_json: *const c_char,
+) -> Option<(UnlockThrottlePolicy, UnlockAttempts)> {
+    fn parse<T: serde::de::DeserializeOwned + Default>(json: *const c_char) -> Option<T> {
+        if json.is_null() {
+            return Some(T::default());
+        }
+        serde_json::from_str(&c_string_to_rust(json)?).ok()
+    }
+    Some((parse(policy_json)?, parse(attempts_json)?))
+}
+
+/// Create a sealed widget snapshot of the pinned credentials
+///
+/// Only titles and TOTP parameters are included; see `ffi::widget` for the
+/// functions a widget uses to read it.
+///
+/// # Arguments
+/// * `handle` - Repository handle
+/// * `pinned_ids_json` - JSON array of credential IDs, in display order
+/// * `widget_key` - Widget key string from `ziplock_widget_generate_key`
+///
+/// # Returns
+/// * Sealed snapshot string (must be freed with `ziplock_free_string`)
+/// * Null if error
+#[no_mangle]
+pub extern "C" fn ziplock_mobile_create_widget_snapshot(
+    handle: MobileRepositoryHandle,
+    pinned_ids_json: *const c_char,
+    widget_key: *const c_char,
+) -> *mut c_char {
+    if handle.is_null() {
+        return ptr::null_mut();
+    }
+
+    let pinned_ids: Vec<String> =
+        match c_string_to_rust(pinned_ids_json).and_then(|json| serde_json::from_str(&json).ok()) {
+            Some(ids) => ids,
+            None => return ptr::null_mut(),
+        };
+    let key: WidgetKey = match c_string_to_rust(widget_key).and_then(|k| k.parse().ok()) {
+        Some(key) => key,
+        None => return ptr::null_mut(),
+    };
+
+    unsafe {
+        let instance = &*handle;
+        let repo = match instance.repository.lock() {
+            Ok(repo) => repo,
+            Err(_) => return ptr::null_mut(),
+        };
+
+        let credentials = match repo.list_credentials() {
+            Ok(credentials) => credentials,
+            Err(_) => return ptr::null_mut(),
+        };
+
+        match WidgetSnapshot::from_credentials(&credentials, &pinned_ids).seal(&key) {
+            Ok(sealed) => rust_string_to_c(sealed),
+            Err(_) => ptr::null_mut(),
+        }
+    }
+}
+
+/// Pair a new watch
+///
+/// The returned pairing string holds the shared secret; persist it in secure
+/// storage, hand it to the watch once, and restore it with
+/// `ziplock_mobile_watch_add_pairing` on later launches.
+///
+/// # Returns
+/// * Pairing string (must be freed with `ziplock_free_string`)
+/// * Null if error
+#[no_mangle]
+pub extern "C" fn ziplock_mobile_watch_pair(handle: MobileRepositoryHandle) -> *mut c_char {
+    if handle.is_null() {
+        return ptr::null_mut();
+    }
+
+    unsafe {
+        let instance = &*handle;
+        let mut watch = match instance.watch.lock() {
+            Ok(watch) => watch,
+            Err(_) => return ptr::null_mut(),
+        };
+
+        let pairing = WatchPairing::generate();
+        let encoded = pairing.to_string();
+        watch.add_pairing(pairing);
+        rust_string_to_c(encoded)
+    }
+}
+
+/// Trust a previously paired watch
+///
+/// # Arguments
+/// * `handle` - Repository handle
+/// * `pairing` - Pairing string from `ziplock_mobile_watch_pair`
+///
+/// # Returns
+/// * `ZipLockError::Success` on success
+/// * `ZipLockError::InvalidParameter` if the handle or pairing is invalid
+#[no_mangle]
+pub extern "C" fn ziplock_mobile_watch_add_pairing(
+    handle: MobileRepositoryHandle,
+    pairing: *const c_char,
+) -> ZipLockError {
+    if handle.is_null() {
+        return ZipLockError::InvalidParameter;
+    }
+    let pairing: WatchPairing = match c_string_to_rust(pairing).and_then(|p| p.parse().ok()) {
+        Some(pairing) => pairing,
+        None => return ZipLockError::InvalidParameter,
+    };
+
+    unsafe {
+        let instance = &*handle;
+        match instance.watch.lock() {
+            Ok(mut watch) => {
+                watch.add_pairing(pairing);
+                ZipLockError::Success
+            }
+            Err(_) => ZipLockError::InternalError,
+        }
+    }
+}
+
+/// Answer a watch's request for codes
+///
+/// The request must be signed by a paired watch, recent, and not seen
+/// before. Only credentials in `pinned_ids_json` are ever returned.
+///
+/// # Arguments
+/// * `handle` - Repository handle
+/// * `request` - Encoded request received from the watch
+/// * `pinned_ids_json` - JSON array of credential IDs the watch may see
+///
+/// # Returns
+/// * Encoded response to send back (must be freed with `ziplock_free_string`)
+/// * Null if the request is rejected or a parameter is invalid
+#[no_mangle]
+pub extern "C" fn ziplock_mobile_watch_respond(
+    handle: MobileRepositoryHandle,
+    request: *const c_char,
+    pinned_ids_json: *const c_char,
+) -> *mut c_char {
+    if handle.is_null() {
+        return ptr::null_mut();
+    }
+    let request = match c_string_to_rust(request).and_then(|r| WatchRequest::decode(&r).ok()) {
+        Some(request) => request,
+        None => return ptr::null_mut(),
+    };
+    let pinned_ids: Vec<String> =
+        match c_string_to_rust(pinned_ids_json).and_then(|json| serde_json::from_str(&json).ok()) {
+            Some(ids) => ids,
+            None => return ptr::null_mut(),
+        };
+
+    unsafe {
+        let instance = &*handle;
+        let credentials = match instance.repository.lock() {
+            Ok(repo) => match repo.list_credentials() {
+                Ok(credentials) => credentials,
+                Err(_) => return ptr::null_mut(),
+            },
+            Err(_) => return ptr::null_mut(),
+        };
+        let mut watch = match instance.watch.lock() {
+            Ok(watch) => watch,
+            Err(_) => return ptr::null_mut(),
+        };
+
+        match watch.respond(&request, &credentials, &pinned_ids, chrono::Utc::now()) {
+            Ok(response) => rust_string_to_c(response.encode()),
+            Err(e) => {
+                tracing::warn!("Rejected watch request: {}", e);
+                ptr::null_mut()
+            }
+        }
+    }
+}
+
+/// Send a username, password or current TOTP code to a paired device
+///
+/// The value is encrypted for the pairing and expires after `ttl_secs`;
+/// deliver the returned message over any transport and open it on the other
+/// device with `ziplock_mobile_clip_receive`.
+///
+/// # Arguments
+/// * `handle` - Repository handle
+/// * `device_id` - ID of a pairing added with `ziplock_mobile_watch_pair` or
+///   `ziplock_mobile_watch_add_pairing`
+/// * `credential_id` - Credential to take the value from
+/// * `kind` - `"username"`, `"password"` or `"totp"`
+/// * `ttl_secs` - Seconds the value stays readable (0 = 60, at most 300)
+///
+/// # Returns
+/// * Encoded message (must be freed with `ziplock_free_string`)
+/// * Null if a parameter is invalid or the credential has no such value
+#[no_mangle]
+pub extern "C" fn ziplock_mobile_clip_send(
+    handle: MobileRepositoryHandle,
+    device_id: *const c_char,
+    credential_id: *const c_char,
+    kind: *const c_char,
+    ttl_secs: c_int,
+) -> *mut c_char {
+    if handle.is_null() {
+        return ptr::null_mut();
+    }
+    let (Some(device_id), Some(credential_id), Some(kind)) = (
+        c_string_to_rust(device_id),
+        c_string_to_rust(credential_id),
+        c_string_to_rust(kind).and_then(|kind| ClipKind::from_name(&kind)),
+    ) else {
+        return ptr::null_mut();
+    };
+    let ttl_secs = match ttl_secs {
+        0 => crate::utils::DEFAULT_CLIP_TTL_SECS,
+        ttl => ttl as i64,
+    };
+
+    unsafe {
+        let instance = &*handle;
+        let now = chrono::Utc::now();
+        let item = match instance.repository.lock() {
+            Ok(repo) => match repo.get_credential_readonly(&credential_id) {
+                Ok(credential) => ClipItem::from_credential(credential, kind, now),
+                Err(_) => return ptr::null_mut(),
+            },
+            Err(_) => return ptr::null_mut(),
+        };
+        let watch = match instance.watch.lock() {
+            Ok(watch) => watch,
+            Err(_) => return ptr::null_mut(),
+        };
+        let Some(pairing) = watch.pairing(&device_id) else {
+            return ptr::null_mut();
+        };
+
+        match item.and_then(|item| ClipMessage::seal(pairing, &item, ttl_secs, now)) {
+            Ok(message) => rust_string_to_c(message.encode()),
+            Err(e) => {
+                tracing::warn!("Could not send to paired device: {}", e);
+                ptr::null_mut()
+            }
+        }
+    }
+}
+
+/// Open a value sent from a paired device
+///
+/// Works while the vault is locked, as long as the pairing has been added.
+/// Each message opens once; show the value until `expires_at` and then
+/// discard it.
+///
+/// # Arguments
+/// * `handle` - Repository handle
+/// * `message` - Message received from the other device
+///
+/// # Returns
+/// * JSON `{kind, title, value, expires_at}` (must be freed with `ziplock_free_string`)
+/// * Null if the message is invalid, expired, already opened or from an unpaired device
+#[no_mangle]
+pub extern "C" fn ziplock_mobile_clip_receive(
+    handle: MobileRepositoryHandle,
+    message: *const c_char,
+) -> *mut c_char {
+    if handle.is_null() {
+        return ptr::null_mut();
+    }
+    let message = match c_string_to_rust(message).and_then(|m| ClipMessage::decode(&m).ok()) {
+        Some(message) => message,
+        None => return ptr::null_mut(),
+    };
+
+    unsafe {
+        let instance = &*handle;
+        let watch = match instance.watch.lock() {
+            Ok(watch) => watch,
+            Err(_) => return ptr::null_mut(),
+        };
+        let Some(pairing) = watch.pairing(&message.device_id) else {
+            tracing::warn!("Rejected clipboard message from unpaired device");
+            return ptr::null_mut();
+        };
+        let mut inbox = match instance.clip_inbox.lock() {
+            Ok(inbox) => inbox,
+            Err(_) => return ptr::null_mut(),
+        };
+
+        match inbox.accept(pairing, &message, chrono::Utc::now()) {
+            Ok(item) => serde_json::to_string(&item).map_or(ptr::null_mut(), rust_string_to_c),
+            Err(e) => {
+                tracing::warn!("Rejected clipboard message: {}", e);
+                ptr::null_mut()
+            }
+        }
+    }
+}
+
+/// Find the credentials to suggest to the Android Autofill framework
+///
+/// The result names each credential with its title and username but holds
+/// no secrets; fetch the values with `ziplock_mobile_autofill_dataset` once
+/// the user picked one. Apps only match credentials with an
+/// `androidapp://<package>` URL field; web pages are matched by URL.
+///
+/// # Arguments
+/// * `handle` - Repository handle
+/// * `package_name_or_url` - Package name of the app being filled, or the
+///   page URL built from the web scheme and domain of the view structure
+///
+/// # Returns
+/// * JSON array of `{credential_id, title, username, match_mode, has_password,
+///   has_totp}`, best match first (must be freed with `ziplock_free_string`)
+/// * Null if a parameter is invalid
+#[no_mangle]
+pub extern "C" fn ziplock_mobile_autofill_match(
+    handle: MobileRepositoryHandle,
+    package_name_or_url: *const c_char,
+) -> *mut c_char {
+    if handle.is_null() {
+        return ptr::null_mut();
+    }
+    let target =
+        match c_string_to_rust(package_name_or_url).and_then(|t| AutofillTarget::parse(&t).ok()) {
+            Some(target) => target,
+            None => return ptr::null_mut(),
+        };
+
+    unsafe {
+        let instance = &*handle;
+        let credentials = match instance.repository.lock() {
+            Ok(repo) => match repo.list_credentials() {
+                Ok(credentials) => credentials,
+                Err(_) => return ptr::null_mut(),
+            },
+            Err(_) => return ptr::null_mut(),
+        };
+
+        match autofill_candidates(&target, &credentials, &UrlMatcher::new()) {
+            Ok(candidates) => {
+                serde_json::to_string(&candidates).map_or(ptr::null_mut(), rust_string_to_c)
+            }
+            Err(_) => ptr::null_mut(),
+        }
+    }
+}
+
+/// Build the autofill dataset for one credential
+///
+/// Call with `confirmed` = 0 for the dataset shown before the user confirms
+/// (passwords, card numbers, TOTP codes and sensitive fields are `null`),
+/// and with 1 after they authenticated to fill it.
+///
+/// # Arguments
+/// * `handle` - Repository handle
+/// * `credential_id` - Credential picked from `ziplock_mobile_autofill_match`
+/// * `confirmed` - Non-zero to include sensitive values
+///
+/// # Returns
+/// * JSON `{credential_id, title, redacted, values: [{hint, field_name, value}]}`
+///   (must be freed with `ziplock_free_string`); hints are Android autofill
+///   hints such as `"username"` and `"password"`
+/// * Null if a parameter is invalid or the credential does not exist
+#[no_mangle]
+pub extern "C" fn ziplock_mobile_autofill_dataset(
+    handle: MobileRepositoryHandle,
+    credential_id: *const c_char,
+    confirmed: c_int,
+) -> *mut c_char {
+    if handle.is_null() {
+        return ptr::null_mut();
+    }
+    let Some(credential_id) = c_string_to_rust(credential_id) else {
+        return ptr::null_mut();
+    };
+
+    unsafe {
+        let instance = &*handle;
+        let repo = match instance.repository.lock() {
+            Ok(repo) => repo,
+            Err(_) => return ptr::null_mut(),
+        };
+        let credential = match repo.get_credential_readonly(&credential_id) {
+            Ok(credential) => credential,
+            Err(_) => return ptr::null_mut(),
+        };
+
+        let dataset =
+            AutofillDataset::from_credential(credential, confirmed != 0, chrono::Utc::now());
+        match serde_json::to_string(&dataset) {
+            Ok(json) => rust_string_to_c(json),
+            Err(_) => ptr::null_mut(),
+        }
+    }
+}
+
+/// Clear all credentials from the repository
+///
+/// # Arguments
+/// * `handle` - Repository handle
+///
+/// # Returns
+/// * `ZipLockError::Success` on success
+/// * `ZipLockError::InvalidParameter` if handle is invalid
+/// * `ZipLockError::NotInitialized` if repository not initialized
+#[no_mangle]
+pub extern "C" fn ziplock_mobile_clear_credentials(handle: MobileRepositoryHandle) -> ZipLockError {
+    if handle.is_null() {
+        return ZipLockError::InvalidParameter;
+    }
+
+    unsafe {
+        let instance = &*handle;
+        let mut repo = match instance.repository.lock() {
+            Ok(repo) => repo,
+            Err(_) => return ZipLockError::InternalError,
+        };
+
+        match repo.clear() {
+            Ok(()) => ZipLockError::Success,
+            Err(CoreError::NotInitialized) => ZipLockError::NotInitialized,
+            Err(_) => ZipLockError::InternalError,
+        }
+    }
+}
+
+/// Free a string returned by this library
+///
+/// # Arguments
+/// * `str_ptr` - String pointer to free
+///
+/// # Safety
+/// Pointer must have been returned by this library and not already freed
+#[no_mangle]
+pub extern "C" fn ziplock_mobile_free_string(str_ptr: *mut c_char) {
+    if str_ptr.is_null() {
+        return;
+    }
+
+    unsafe {
+        let _ = CString::from_raw(str_ptr);
+    }
+}
+
+/// Create an encrypted archive from file map JSON to a temporary file location
+///
+/// This function creates a properly encrypted 7z archive using sevenz-rust2 and saves it
+/// to a temporary file that can be accessed by the mobile platform for moving to the
+/// final destination using platform-specific file operations (like SAF on Android).
+///
+/// The archive is new, with a [hidden section](crate::core::duress) of random
+/// bytes, so it drops a duress password set on the vault it replaces; hosts
+/// that keep one use `ziplock_mobile_load_from_bytes` and
+/// `ziplock_mobile_save_to_bytes`.
+///
+/// # Arguments
+/// * `files_json` - JSON string containing file map (path -> base64 content mappings)
+/// * `password` - Password for AES-256 archive encryption
+/// * `temp_path_out` - Output buffer to receive the temporary file path (caller must free)
+///
+/// # Returns
+/// * `ZipLockError::Success` on success
+/// * `ZipLockError::InvalidParameter` if parameters are invalid
+/// * `ZipLockError::SerializationError` if JSON parsing fails
+/// * `ZipLockError::CryptoError` if archive creation/encryption fails
+/// * `ZipLockError::OutOfMemory` if memory allocation fails
+///
+/// # Safety
+/// The caller must free the returned temp_path_out string using ziplock_mobile_free_string
+#[no_mangle]
+pub extern "C" fn ziplock_mobile_create_temp_archive(
+    files_json: *const c_char,
+    password: *const c_char,
+    temp_path_out: *mut *mut c_char,
+) -> ZipLockError {
+    if files_json.is_null() || password.is_null() || temp_path_out.is_null() {
+        return ZipLockError::InvalidParameter;
+    }
+
+    unsafe {
+        // Initialize output to null
+        *temp_path_out = ptr::null_mut();
+
+        // Parse input parameters
+        let json_str = match c_string_to_rust(files_json) {
+            Some(s) => s,
+            None => return ZipLockError::InvalidParameter,
+        };
+
+        let password_str = match c_string_to_rust(password) {
+            Some(s) => s,
+            None => return ZipLockError::InvalidParameter,
+        };
+
+        if password_str.is_empty() {
+            return ZipLockError::InvalidParameter; // Archive must be encrypted
+        }
+
+        // Parse JSON file map
+        report_progress(ArchivePhase::Serializing);
+        let file_map_raw: HashMap<String, String> = match serde_json::from_str(&json_str) {
+            Ok(map) => map,
+            Err(_) => return ZipLockError::SerializationError,
+        };
+
+        // Convert base64 content to bytes
+        let mut file_map = HashMap::new();
+        for (path, base64_content) in file_map_raw {
+            let content = match BASE64_STANDARD.decode(base64_content) {
+                Ok(bytes) => bytes,
+                Err(_) => return ZipLockError::SerializationError,
+            };
+            file_map.insert(path, content);
+        }
+
+        report_progress(ArchivePhase::Encrypting);
+
+        // Create temporary file path
+        let temp_id = uuid::Uuid::new_v4();
+        let temp_path = std::env::temp_dir().join(format!("ziplock_temp_{}.7z", temp_id));
+
+        // Use DesktopFileProvider to create encrypted archive
+        use crate::core::file_provider::{DesktopFileProvider, FileOperationProvider};
+        let provider = DesktopFileProvider::new();
+
+        match provider
+            .create_archive(file_map, &password_str)
+            .and_then(|archive| DuressArchive::with_archive(None, archive, &password_str))
+        {
+            Ok((archive_data, _)) => {
+                // Write archive to temporary file
+                report_progress(ArchivePhase::Writing);
+                match std::fs::write(&temp_path, archive_data) {
+                    Ok(()) => {
+                        report_progress(ArchivePhase::Complete);
+                        // Return temporary file path
+                        let path_string = temp_path.to_string_lossy().to_string();
+                        *temp_path_out = rust_string_to_c(path_string);
+                        ZipLockError::Success
+                    }
+                    Err(_) => ZipLockError::InternalError,
+                }
+            }
+            Err(_) => ZipLockError::FileError,
+        }
+    }
+}
+
+/// Report progress of the archive functions
+///
+/// `ziplock_mobile_create_temp_archive`, `ziplock_mobile_extract_temp_archive`,
+/// `ziplock_mobile_unlock_with_token` and the archive-bytes functions call
+/// `callback` as they move through the phases, so the app can show a
+/// progress bar while a large vault is encrypted or decrypted. Pass a null callback to stop reporting.
+///
+/// # Arguments
+/// * `callback` - Progress callback (may be null); runs on the calling thread
+/// * `user_data` - Passed through to `callback`; must stay valid until the
+///   callback is replaced
+///
+/// # Returns
+/// * `ZipLockError::Success`
+#[no_mangle]
+pub extern "C" fn ziplock_mobile_set_progress_callback(
+    callback: ZipLockProgressCallback,
+    user_data: *mut c_void,
+) -> ZipLockError {
+    *PROGRESS_OBSERVER.lock().unwrap_or_else(|e| e.into_inner()) =
+        FfiProgressObserver::new(callback, user_data);
+    ZipLockError::Success
+}
+
+/// Limit the memory repositories and archive extraction may use
+///
+/// Meant for low-RAM devices and called once at startup, before any
+/// repository is created. Repositories created afterwards keep at most
+/// `max_decrypted_records` credentials decoded and seal the rest in memory
+/// until they are used again. `ziplock_mobile_extract_temp_archive` leaves
+/// attachments larger than `max_attachment_buffer` bytes out of the file map.
+///
+/// # Arguments
+/// * `budget_json` - `{"max_decrypted_records":200,"max_attachment_buffer":4194304}`;
+///   missing members or a null pointer mean no cap
+///
+/// # Returns
+/// * `ZipLockError::Success`
+/// * `ZipLockError::SerializationError` if the JSON is invalid
+#[no_mangle]
+pub extern "C" fn ziplock_mobile_set_memory_budget(budget_json: *const c_char) -> ZipLockError {
+    let budget = if budget_json.is_null() {
+        MemoryBudget::unlimited()
+    } else {
+        let Some(json) = c_string_to_rust(budget_json) else {
+            return ZipLockError::InvalidParameter;
+        };
+        match serde_json::from_str::<MemoryBudget>(&json) {
+            Ok(budget) => budget,
+            Err(_) => return ZipLockError::SerializationError,
+        }
+    };
+    *MEMORY_BUDGET.lock().unwrap_or_else(|e| e.into_inner()) = budget;
+    ZipLockError::Success
+}
+
+/// Extract archive from temporary file path to file map (JSON)
+///
+/// This function complements the temp archive creation by providing
+/// FFI-based extraction that ensures proper decryption using sevenz-rust2.
+///
+/// # Parameters
+/// * `archive_path` - Path to the 7z archive file
+/// * `password` - Password for decryption
+/// * `files_json_out` - Output parameter for JSON file map (path -> base64 content)
 ///
 /// # Returns
 /// * `ZipLockError::Success` on success with file map in `files_json_out`
@@ -766,46 +2260,204 @@ pub extern "C" fn ziplock_mobile_extract_temp_archive(
             return ZipLockError::InvalidParameter; // Password required for encrypted archive
         }
 
-        // Check if archive file exists
-        let archive_file_path = std::path::Path::new(&path_str);
-        if !archive_file_path.exists() {
-            return ZipLockError::FileNotFound;
+        match extract_archive_file(&path_str, &password_str) {
+            Ok(json_str) => {
+                *files_json_out = rust_string_to_c(json_str);
+                ZipLockError::Success
+            }
+            Err(e) => e,
         }
+    }
+}
 
-        // Use DesktopFileProvider to extract encrypted archive
-        use crate::core::file_provider::{DesktopFileProvider, FileOperationProvider};
-        let provider = DesktopFileProvider::new();
+/// Enable fingerprint or face unlock
+///
+/// Seals the archive password with a secret from a platform keystore key
+/// that can only be used after biometric authentication (e.g. the output of
+/// an Android Keystore HMAC key over a fixed challenge, or a random value in
+/// an iOS Keychain item with `biometryCurrentSet`). The token is useless
+/// without that key, so it can be kept in ordinary app storage. The password
+/// is checked against the archive first, so a mistyped password is never
+/// sealed into a token.
+///
+/// # Arguments
+/// * `archive_path` - Path to the 7z archive file
+/// * `password` - Archive password
+/// * `keystore_secret` - Secret bytes released by the keystore
+/// * `keystore_secret_len` - Length of `keystore_secret`
+/// * `ttl_days` - Days the token stays valid (0 = 30)
+/// * `token_out` - Receives the token
+///
+/// # Returns
+/// * `ZipLockError::Success` with `token_out` set (free it with `ziplock_free_string`)
+/// * `ZipLockError::InvalidPassword` if `password` does not open the archive
+/// * `ZipLockError::FileNotFound` or `ZipLockError::FileError` if the archive cannot be read
+#[no_mangle]
+pub extern "C" fn ziplock_mobile_enable_biometric_unlock(
+    archive_path: *const c_char,
+    password: *const c_char,
+    keystore_secret: *const u8,
+    keystore_secret_len: usize,
+    ttl_days: c_int,
+    token_out: *mut *mut c_char,
+) -> ZipLockError {
+    if token_out.is_null() {
+        return ZipLockError::InvalidParameter;
+    }
+
+    unsafe {
+        *token_out = ptr::null_mut();
+
+        let (Some(path_str), Some(password), Some(secret)) = (
+            c_string_to_rust(archive_path),
+            c_string_to_rust(password).filter(|password| !password.is_empty()),
+            device_secret(keystore_secret, keystore_secret_len),
+        ) else {
+            return ZipLockError::InvalidParameter;
+        };
+        let ttl_days = match ttl_days {
+            0 => DEFAULT_TOKEN_TTL_DAYS,
+            days => days as i64,
+        };
+
+        if let Err(e) = extract_archive_file(&path_str, &password) {
+            return e;
+        }
+
+        match BiometricToken::create(
+            secret,
+            password.as_bytes(),
+            chrono::Duration::days(ttl_days),
+            chrono::Utc::now(),
+        ) {
+            Ok(token) => {
+                *token_out = rust_string_to_c(token.encode());
+                ZipLockError::Success
+            }
+            Err(_) => ZipLockError::KeyProtectionError,
+        }
+    }
+}
+
+/// Unlock an archive with a biometric token
+///
+/// Recovers the password from the token and checks it against the archive,
+/// so a token made before the master password was changed stops working.
+/// On any error other than `InvalidParameter` or a file error, delete the
+/// token and ask for the master password.
+///
+/// # Arguments
+/// * `token` - Token from `ziplock_mobile_enable_biometric_unlock`
+/// * `keystore_secret` - Secret bytes released by the keystore
+/// * `keystore_secret_len` - Length of `keystore_secret`
+/// * `archive_path` - Path to the 7z archive file
+/// * `password_out` - Receives the archive password, for saving later
+/// * `files_json_out` - Receives the JSON file map, as from
+///   `ziplock_mobile_extract_temp_archive`
+///
+/// # Returns
+/// * `ZipLockError::Success` with both outputs set (free them with `ziplock_free_string`)
+/// * `ZipLockError::KeyProtectionError` if the token expired or the keystore
+///   key changed (e.g. a new fingerprint was enrolled)
+/// * `ZipLockError::InvalidPassword` if the master password changed since the token was made
+/// * `ZipLockError::FileNotFound` or `ZipLockError::FileError` if the archive cannot be read
+#[no_mangle]
+pub extern "C" fn ziplock_mobile_unlock_with_token(
+    token: *const c_char,
+    keystore_secret: *const u8,
+    keystore_secret_len: usize,
+    archive_path: *const c_char,
+    password_out: *mut *mut c_char,
+    files_json_out: *mut *mut c_char,
+) -> ZipLockError {
+    if password_out.is_null() || files_json_out.is_null() {
+        return ZipLockError::InvalidParameter;
+    }
+
+    unsafe {
+        *password_out = ptr::null_mut();
+        *files_json_out = ptr::null_mut();
 
-        // Read archive data from file
-        let archive_data = match std::fs::read(archive_file_path) {
-            Ok(data) => data,
-            Err(_) => return ZipLockError::FileError,
+        let (Some(token), Some(secret), Some(path_str)) = (
+            c_string_to_rust(token).and_then(|token| BiometricToken::decode(&token).ok()),
+            device_secret(keystore_secret, keystore_secret_len),
+            c_string_to_rust(archive_path),
+        ) else {
+            return ZipLockError::InvalidParameter;
         };
 
-        // Extract using the file provider
-        match provider.extract_archive(&archive_data, &password_str) {
-            Ok(file_map) => {
-                // Convert byte content to base64 for JSON transport
-                let mut base64_map = HashMap::new();
-                for (path, content) in file_map {
-                    let base64_content = BASE64_STANDARD.encode(content);
-                    base64_map.insert(path, base64_content);
-                }
+        let password = match token.unlock(secret, chrono::Utc::now()) {
+            Ok(password) => password,
+            Err(e) => {
+                tracing::info!("Biometric token rejected: {}", e);
+                return ZipLockError::KeyProtectionError;
+            }
+        };
+        let Ok(password_str) = std::str::from_utf8(&password) else {
+            return ZipLockError::KeyProtectionError;
+        };
 
-                // Serialize to JSON
-                match serde_json::to_string(&base64_map) {
-                    Ok(json_str) => {
-                        *files_json_out = rust_string_to_c(json_str);
-                        ZipLockError::Success
-                    }
-                    Err(_) => ZipLockError::SerializationError,
-                }
+        match extract_archive_file(&path_str, password_str) {
+            Ok(json_str) => {
+                *password_out = rust_string_to_c(password_str.to_string());
+                *files_json_out = rust_string_to_c(json_str);
+                ZipLockError::Success
             }
-            Err(_) => ZipLockError::InvalidPassword,
+            Err(e) => e,
         }
     }
 }
 
+/// Extract the archive at `path` into a JSON file map (path -> base64 content)
+fn extract_archive_file(path: &str, password: &str) -> Result<String, ZipLockError> {
+    // Check if archive file exists
+    let archive_file_path = std::path::Path::new(path);
+    if !archive_file_path.exists() {
+        return Err(ZipLockError::FileNotFound);
+    }
+
+    // Read archive data from file
+    report_progress(ArchivePhase::Reading);
+    let archive_data = std::fs::read(archive_file_path).map_err(|_| ZipLockError::FileError)?;
+
+    // Extract entry by entry, encoding as base64 for JSON transport, so an
+    // attachment over the budget is never held twice
+    report_progress(ArchivePhase::Decrypting);
+    let budget = memory_budget();
+    let attachments = format!("{}/", ATTACHMENTS_DIR);
+    let mut base64_map: HashMap<String, String> = HashMap::new();
+    let container = DuressArchive::from_bytes(&archive_data).ok();
+    let archive = match &container {
+        Some(container) => container.part(password).0,
+        None => Cow::Borrowed(&archive_data[..]),
+    };
+    SevenZipBackend
+        .for_each_file(&archive, password, &mut |path, content| {
+            let path = path.replace('\\', "/");
+            if path.starts_with(&attachments) && !budget.allows_attachment(content.len()) {
+                return true;
+            }
+            base64_map.insert(path, BASE64_STANDARD.encode(content));
+            true
+        })
+        .map_err(|_| ZipLockError::InvalidPassword)?;
+
+    let json = serde_json::to_string(&base64_map).map_err(|_| ZipLockError::SerializationError)?;
+    report_progress(ArchivePhase::Complete);
+    Ok(json)
+}
+
+fn report_progress(phase: ArchivePhase) {
+    // Copy the observer out so a callback that re-registers cannot deadlock
+    let observer = *PROGRESS_OBSERVER.lock().unwrap_or_else(|e| e.into_inner());
+    progress::report(
+        observer
+            .as_ref()
+            .map(|o| o as &dyn progress::ProgressObserver),
+        phase,
+    );
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -872,6 +2524,144 @@ mod tests {
         ziplock_mobile_repository_destroy(handle);
     }
 
+    #[test]
+    fn test_protected_field_round_trip() {
+        let handle = ziplock_mobile_repository_create();
+        ziplock_mobile_repository_initialize(handle);
+
+        let mut credential = CredentialRecord::new("Bank".to_string(), "login".to_string());
+        credential.set_field("pin", CredentialField::password("4321"));
+        let c_json = CString::new(serde_json::to_string(&credential).unwrap()).unwrap();
+        ziplock_mobile_add_credential(handle, c_json.as_ptr());
+
+        let id = CString::new(credential.id.clone()).unwrap();
+        let field = CString::new("pin").unwrap();
+        let passphrase = CString::new("extra").unwrap();
+        let wrong = CString::new("wrong").unwrap();
+        assert_eq!(
+            ziplock_mobile_protect_field(handle, id.as_ptr(), field.as_ptr(), passphrase.as_ptr()),
+            ZipLockError::Success
+        );
+
+        let record_ptr = ziplock_mobile_get_credential(handle, id.as_ptr());
+        let record: serde_json::Value =
+            serde_json::from_str(&c_string_to_rust(record_ptr).unwrap()).unwrap();
+        ziplock_mobile_free_string(record_ptr);
+        assert_eq!(record["fields"]["pin"]["value"], "[protected]");
+
+        assert!(
+            ziplock_mobile_unlock_field(handle, id.as_ptr(), field.as_ptr(), wrong.as_ptr())
+                .is_null()
+        );
+        let value_ptr =
+            ziplock_mobile_unlock_field(handle, id.as_ptr(), field.as_ptr(), passphrase.as_ptr());
+        assert_eq!(c_string_to_rust(value_ptr).unwrap(), "4321");
+        ziplock_mobile_free_string(value_ptr);
+
+        assert_eq!(
+            ziplock_mobile_unprotect_field(handle, id.as_ptr(), field.as_ptr(), wrong.as_ptr()),
+            ZipLockError::InvalidPassword
+        );
+        assert_eq!(
+            ziplock_mobile_unprotect_field(
+                handle,
+                id.as_ptr(),
+                field.as_ptr(),
+                passphrase.as_ptr()
+            ),
+            ZipLockError::Success
+        );
+
+        ziplock_mobile_repository_destroy(handle);
+    }
+
+    #[test]
+    fn test_archive_bytes_round_trip() {
+        let handle = ziplock_mobile_repository_create();
+        ziplock_mobile_repository_initialize(handle);
+        let credential = CredentialRecord::new("Router".to_string(), "login".to_string());
+        let c_json = CString::new(serde_json::to_string(&credential).unwrap()).unwrap();
+        ziplock_mobile_add_credential(handle, c_json.as_ptr());
+
+        let password = CString::new("password").unwrap();
+        let mut data = ptr::null_mut();
+        let mut len = 0;
+        assert_eq!(
+            ziplock_mobile_save_to_bytes(handle, ptr::null(), &mut data, &mut len),
+            ZipLockError::InvalidParameter
+        );
+        assert_eq!(
+            ziplock_mobile_save_to_bytes(handle, password.as_ptr(), &mut data, &mut len),
+            ZipLockError::Success
+        );
+        let archive = unsafe { slice::from_raw_parts(data, len) }.to_vec();
+        ziplock_mobile_free_bytes(data, len);
+        assert_eq!(
+            ArchiveFormat::detect(&archive),
+            Some(ArchiveFormat::SevenZip)
+        );
+        ziplock_mobile_repository_destroy(handle);
+
+        let handle = ziplock_mobile_repository_create();
+        let wrong = CString::new("wrong").unwrap();
+        assert_eq!(
+            ziplock_mobile_load_from_bytes(handle, archive.as_ptr(), archive.len(), wrong.as_ptr()),
+            ZipLockError::InvalidPassword
+        );
+        assert_eq!(
+            ziplock_mobile_load_from_bytes(handle, b"junk".as_ptr(), 4, password.as_ptr()),
+            ZipLockError::CorruptedArchive
+        );
+        assert_eq!(
+            ziplock_mobile_load_from_bytes(
+                handle,
+                archive.as_ptr(),
+                archive.len(),
+                password.as_ptr()
+            ),
+            ZipLockError::Success
+        );
+        let c_id = CString::new(credential.id.clone()).unwrap();
+        let retrieved_ptr = ziplock_mobile_get_credential(handle, c_id.as_ptr());
+        assert!(!retrieved_ptr.is_null());
+        ziplock_mobile_free_string(retrieved_ptr);
+        ziplock_mobile_repository_destroy(handle);
+    }
+
+    #[test]
+    fn test_archive_bytes_keep_their_format() {
+        let mut repo = UnifiedMemoryRepository::new();
+        repo.initialize().unwrap();
+        let archive = ArchiveFormat::Zip
+            .backend()
+            .create(repo.serialize_to_files().unwrap(), "password")
+            .unwrap();
+
+        let handle = ziplock_mobile_repository_create();
+        let password = CString::new("password").unwrap();
+        assert_eq!(
+            ziplock_mobile_load_from_bytes(
+                handle,
+                archive.as_ptr(),
+                archive.len(),
+                password.as_ptr()
+            ),
+            ZipLockError::Success
+        );
+        let mut data = ptr::null_mut();
+        let mut len = 0;
+        assert_eq!(
+            ziplock_mobile_save_to_bytes(handle, password.as_ptr(), &mut data, &mut len),
+            ZipLockError::Success
+        );
+        assert_eq!(
+            ArchiveFormat::detect(unsafe { slice::from_raw_parts(data, len) }),
+            Some(ArchiveFormat::Zip)
+        );
+        ziplock_mobile_free_bytes(data, len);
+        ziplock_mobile_repository_destroy(handle);
+    }
+
     #[test]
     fn test_file_map_serialization() {
         let handle = ziplock_mobile_repository_create();
@@ -935,4 +2725,658 @@ mod tests {
 
         ziplock_mobile_repository_destroy(handle);
     }
+
+    #[test]
+    fn test_repository_health() {
+        let handle = ziplock_mobile_repository_create();
+        ziplock_mobile_repository_initialize(handle);
+
+        assert!(ziplock_mobile_get_health(ptr::null_mut(), 0).is_null());
+
+        let health_ptr = ziplock_mobile_get_health(handle, chrono::Utc::now().timestamp());
+        assert!(!health_ptr.is_null());
+        let json = unsafe { std::ffi::CStr::from_ptr(health_ptr) }
+            .to_str()
+            .unwrap()
+            .to_string();
+        ziplock_mobile_free_string(health_ptr);
+
+        let health: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert!(health["score"].as_u64().unwrap() > 90);
+        assert!(health["checklist"].is_array());
+
+        ziplock_mobile_repository_destroy(handle);
+    }
+
+    #[test]
+    fn test_reminders() {
+        let handle = ziplock_mobile_repository_create();
+        ziplock_mobile_repository_initialize(handle);
+
+        assert!(ziplock_mobile_get_reminders(ptr::null_mut(), 30).is_null());
+
+        let mut credential = CredentialRecord::new("Passport".to_string(), "identity".to_string());
+        credential.expires_at = Some(chrono::Utc::now().timestamp() + 10 * 86_400);
+        let c_json = CString::new(serde_json::to_string(&credential).unwrap()).unwrap();
+        ziplock_mobile_add_credential(handle, c_json.as_ptr());
+
+        let read = |lead_days| {
+            let reminders_ptr = ziplock_mobile_get_reminders(handle, lead_days);
+            assert!(!reminders_ptr.is_null());
+            let json = unsafe { std::ffi::CStr::from_ptr(reminders_ptr) }
+                .to_str()
+                .unwrap()
+                .to_string();
+            ziplock_mobile_free_string(reminders_ptr);
+            serde_json::from_str::<serde_json::Value>(&json).unwrap()
+        };
+        assert_eq!(read(0).as_array().unwrap().len(), 0);
+        let reminders = read(30);
+        assert_eq!(reminders[0]["kind"], "expiry");
+        assert_eq!(reminders[0]["status"], "upcoming");
+
+        ziplock_mobile_repository_destroy(handle);
+    }
+
+    #[test]
+    fn test_validation_messages_localized() {
+        let handle = ziplock_mobile_repository_create();
+        ziplock_mobile_repository_initialize(handle);
+
+        let mut credential = CredentialRecord::new(String::new(), "login".to_string());
+        credential.set_field("website", CredentialField::url("example.com"));
+        let c_json = CString::new(serde_json::to_string(&credential).unwrap()).unwrap();
+        let locale = CString::new("de-DE").unwrap();
+
+        let report_ptr = ziplock_mobile_validate_credential(c_json.as_ptr(), locale.as_ptr());
+        let report: serde_json::Value =
+            serde_json::from_str(&c_string_to_rust(report_ptr).unwrap()).unwrap();
+        ziplock_mobile_free_string(report_ptr);
+        assert_eq!(report["valid"], false);
+        let codes: Vec<&str> = report["errors"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|error| error["code"].as_str().unwrap())
+            .collect();
+        assert!(codes.contains(&"validation.title_empty"));
+        assert!(codes.contains(&"validation.invalid_url"));
+        assert!(report["errors"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|error| error["message"] == "Der Titel darf nicht leer sein"));
+
+        assert_eq!(
+            ziplock_mobile_add_credential(handle, c_json.as_ptr()),
+            ZipLockError::ValidationError
+        );
+        let errors_ptr = crate::ffi::common::ziplock_get_last_error_localized(locale.as_ptr());
+        let errors: serde_json::Value =
+            serde_json::from_str(&c_string_to_rust(errors_ptr).unwrap()).unwrap();
+        ziplock_mobile_free_string(errors_ptr);
+        assert_eq!(errors, report["errors"]);
+
+        assert!(ziplock_mobile_validate_credential(ptr::null(), ptr::null()).is_null());
+
+        ziplock_mobile_repository_destroy(handle);
+    }
+
+    #[test]
+    fn test_export_html_viewer_rejects_bad_input() {
+        let handle = ziplock_mobile_repository_create();
+        ziplock_mobile_repository_initialize(handle);
+
+        let passphrase = CString::new("correct horse battery").unwrap();
+        assert!(ziplock_mobile_export_html_viewer(
+            ptr::null_mut(),
+            ptr::null(),
+            passphrase.as_ptr()
+        )
+        .is_null());
+        assert!(ziplock_mobile_export_html_viewer(handle, ptr::null(), ptr::null()).is_null());
+
+        let short = CString::new("short").unwrap();
+        assert!(ziplock_mobile_export_html_viewer(handle, ptr::null(), short.as_ptr()).is_null());
+
+        let bad_ids = CString::new("not json").unwrap();
+        assert!(
+            ziplock_mobile_export_html_viewer(handle, bad_ids.as_ptr(), passphrase.as_ptr())
+                .is_null()
+        );
+
+        ziplock_mobile_repository_destroy(handle);
+    }
+
+    #[test]
+    fn test_security_audit() {
+        let handle = ziplock_mobile_repository_create();
+        ziplock_mobile_repository_initialize(handle);
+
+        assert!(ziplock_mobile_security_audit(ptr::null_mut(), ptr::null()).is_null());
+
+        let mut credential = CredentialRecord::new("Weak".to_string(), "login".to_string());
+        credential.set_field(
+            "password",
+            crate::models::CredentialField::password("password"),
+        );
+        let c_json = CString::new(serde_json::to_string(&credential).unwrap()).unwrap();
+        ziplock_mobile_add_credential(handle, c_json.as_ptr());
+
+        let options = CString::new(r#"{"max_password_age_days": 0}"#).unwrap();
+        let report_ptr = ziplock_mobile_security_audit(handle, options.as_ptr());
+        assert!(!report_ptr.is_null());
+        let report: serde_json::Value =
+            serde_json::from_str(&c_string_to_rust(report_ptr).unwrap()).unwrap();
+        ziplock_mobile_free_string(report_ptr);
+
+        assert_eq!(report["weak"][0]["title"], "Weak");
+        assert_eq!(report["breach_check_performed"], false);
+
+        let invalid = CString::new("not json").unwrap();
+        assert!(ziplock_mobile_security_audit(handle, invalid.as_ptr()).is_null());
+
+        ziplock_mobile_repository_destroy(handle);
+    }
+
+    #[test]
+    fn test_compliance_report() {
+        let handle = ziplock_mobile_repository_create();
+        ziplock_mobile_repository_initialize(handle);
+
+        let mut credential = CredentialRecord::new("Payroll".to_string(), "login".to_string());
+        credential.compliance_mut().owner = Some("Finance".to_string());
+        let c_json = CString::new(serde_json::to_string(&credential).unwrap()).unwrap();
+        ziplock_mobile_add_credential(handle, c_json.as_ptr());
+
+        let report_ptr = ziplock_mobile_compliance_report(handle, ptr::null(), ptr::null());
+        assert!(!report_ptr.is_null());
+        let report: serde_json::Value =
+            serde_json::from_str(&c_string_to_rust(report_ptr).unwrap()).unwrap();
+        ziplock_mobile_free_string(report_ptr);
+        assert_eq!(report["entries"][0]["owner"], "Finance");
+        assert_eq!(report["never_reviewed_count"], 1);
+
+        let csv = CString::new("csv").unwrap();
+        let csv_ptr = ziplock_mobile_compliance_report(handle, ptr::null(), csv.as_ptr());
+        let csv_text = c_string_to_rust(csv_ptr).unwrap();
+        ziplock_mobile_free_string(csv_ptr);
+        assert!(csv_text.contains("Payroll,Finance"));
+
+        let pdf = CString::new("pdf").unwrap();
+        assert!(ziplock_mobile_compliance_report(handle, ptr::null(), pdf.as_ptr()).is_null());
+
+        ziplock_mobile_repository_destroy(handle);
+    }
+
+    #[test]
+    fn test_folder_defaults_and_form_schema() {
+        let handle = ziplock_mobile_repository_create();
+        ziplock_mobile_repository_initialize(handle);
+
+        let folder = CString::new("Work").unwrap();
+        let defaults =
+            CString::new(r##"{"icon": "server", "color": "#1e88e5", "template": "database"}"##)
+                .unwrap();
+        assert_eq!(
+            ziplock_mobile_set_folder_defaults(handle, folder.as_ptr(), defaults.as_ptr()),
+            ZipLockError::Success
+        );
+        let invalid = CString::new(r#"{"color": "blue"}"#).unwrap();
+        assert_eq!(
+            ziplock_mobile_set_folder_defaults(handle, folder.as_ptr(), invalid.as_ptr()),
+            ZipLockError::ValidationError
+        );
+
+        let subfolder = CString::new("Work/DB").unwrap();
+        let schema_ptr =
+            ziplock_mobile_credential_form_schema(handle, subfolder.as_ptr(), ptr::null());
+        assert!(!schema_ptr.is_null());
+        let schema: serde_json::Value =
+            serde_json::from_str(&c_string_to_rust(schema_ptr).unwrap()).unwrap();
+        ziplock_mobile_free_string(schema_ptr);
+        assert_eq!(schema["template"]["name"], "database");
+        assert_eq!(schema["template_inherited"], true);
+        assert_eq!(schema["color"], "#1e88e5");
+
+        let unknown = CString::new("nope").unwrap();
+        assert!(
+            ziplock_mobile_credential_form_schema(handle, ptr::null(), unknown.as_ptr()).is_null()
+        );
+
+        ziplock_mobile_repository_destroy(handle);
+    }
+
+    #[test]
+    fn test_user_defined_templates() {
+        let handle = ziplock_mobile_repository_create();
+        ziplock_mobile_repository_initialize(handle);
+
+        let template = CString::new(
+            r#"{"name": "vpn", "description": "VPN", "default_tags": [], "fields": [
+                {"name": "server", "label": "Server", "field_type": "Url", "required": true,
+                 "sensitive": false, "default_value": null, "validation": null}]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            ziplock_mobile_add_template(handle, template.as_ptr()),
+            ZipLockError::Success
+        );
+        assert_eq!(
+            ziplock_mobile_add_template(handle, template.as_ptr()),
+            ZipLockError::ValidationError
+        );
+        assert_eq!(
+            ziplock_mobile_update_template(handle, template.as_ptr()),
+            ZipLockError::Success
+        );
+
+        let list_ptr = ziplock_mobile_list_templates(handle);
+        assert!(!list_ptr.is_null());
+        let templates: serde_json::Value =
+            serde_json::from_str(&c_string_to_rust(list_ptr).unwrap()).unwrap();
+        ziplock_mobile_free_string(list_ptr);
+        assert_eq!(templates[0]["name"], "vpn");
+
+        let name = CString::new("vpn").unwrap();
+        let schema_ptr = ziplock_mobile_credential_form_schema(handle, ptr::null(), name.as_ptr());
+        assert!(!schema_ptr.is_null());
+        ziplock_mobile_free_string(schema_ptr);
+
+        ziplock_mobile_repository_destroy(handle);
+    }
+
+    #[test]
+    fn test_clip_bridge_between_devices() {
+        let phone = ziplock_mobile_repository_create();
+        ziplock_mobile_repository_initialize(phone);
+        let tablet = ziplock_mobile_repository_create();
+
+        let mut credential = CredentialRecord::new("Bank".to_string(), "login".to_string());
+        credential.set_field(
+            "password",
+            crate::models::CredentialField::password("s3cret"),
+        );
+        let c_json = CString::new(serde_json::to_string(&credential).unwrap()).unwrap();
+        ziplock_mobile_add_credential(phone, c_json.as_ptr());
+
+        let pairing_ptr = ziplock_mobile_watch_pair(phone);
+        let pairing = c_string_to_rust(pairing_ptr).unwrap();
+        ziplock_mobile_free_string(pairing_ptr);
+        let c_pairing = CString::new(pairing.clone()).unwrap();
+        ziplock_mobile_watch_add_pairing(tablet, c_pairing.as_ptr());
+        let device_id = pairing
+            .parse::<WatchPairing>()
+            .unwrap()
+            .device_id()
+            .to_string();
+
+        let c_device = CString::new(device_id).unwrap();
+        let c_id = CString::new(credential.id.clone()).unwrap();
+        let c_kind = CString::new("password").unwrap();
+        let message_ptr =
+            ziplock_mobile_clip_send(phone, c_device.as_ptr(), c_id.as_ptr(), c_kind.as_ptr(), 0);
+        assert!(!message_ptr.is_null());
+
+        let item_ptr = ziplock_mobile_clip_receive(tablet, message_ptr);
+        let item: serde_json::Value =
+            serde_json::from_str(&c_string_to_rust(item_ptr).unwrap()).unwrap();
+        ziplock_mobile_free_string(item_ptr);
+        assert_eq!(item["value"], "s3cret");
+        assert_eq!(item["kind"], "password");
+
+        // Each message opens once
+        assert!(ziplock_mobile_clip_receive(tablet, message_ptr).is_null());
+        ziplock_mobile_free_string(message_ptr);
+
+        let c_kind = CString::new("totp").unwrap();
+        assert!(ziplock_mobile_clip_send(
+            phone,
+            c_device.as_ptr(),
+            c_id.as_ptr(),
+            c_kind.as_ptr(),
+            0
+        )
+        .is_null());
+
+        ziplock_mobile_repository_destroy(phone);
+        ziplock_mobile_repository_destroy(tablet);
+    }
+
+    #[test]
+    fn test_autofill_match_and_dataset() {
+        let handle = ziplock_mobile_repository_create();
+        ziplock_mobile_repository_initialize(handle);
+
+        let mut credential = CredentialRecord::new("Shop".to_string(), "login".to_string());
+        credential.set_field("url", CredentialField::url("androidapp://com.shop.app"));
+        credential.set_field("username", CredentialField::username("jane"));
+        credential.set_field("password", CredentialField::password("s3cret"));
+        let c_json = CString::new(serde_json::to_string(&credential).unwrap()).unwrap();
+        ziplock_mobile_add_credential(handle, c_json.as_ptr());
+
+        let c_package = CString::new("com.shop.app").unwrap();
+        let found_ptr = ziplock_mobile_autofill_match(handle, c_package.as_ptr());
+        let found = c_string_to_rust(found_ptr).unwrap();
+        ziplock_mobile_free_string(found_ptr);
+        assert!(!found.contains("s3cret"));
+        let found: serde_json::Value = serde_json::from_str(&found).unwrap();
+        assert_eq!(found[0]["credential_id"], credential.id.as_str());
+        assert_eq!(found[0]["username"], "jane");
+        assert_eq!(found[0]["match_mode"], "exact");
+
+        let c_id = CString::new(credential.id.clone()).unwrap();
+        for (confirmed, password) in [(0, serde_json::Value::Null), (1, "s3cret".into())] {
+            let dataset_ptr = ziplock_mobile_autofill_dataset(handle, c_id.as_ptr(), confirmed);
+            let dataset: serde_json::Value =
+                serde_json::from_str(&c_string_to_rust(dataset_ptr).unwrap()).unwrap();
+            ziplock_mobile_free_string(dataset_ptr);
+            assert_eq!(dataset["values"][0]["hint"], "password");
+            assert_eq!(dataset["values"][0]["value"], password);
+            assert_eq!(dataset["values"][1]["value"], "jane");
+        }
+
+        let c_bad = CString::new("not a package").unwrap();
+        assert!(ziplock_mobile_autofill_match(handle, c_bad.as_ptr()).is_null());
+        ziplock_mobile_repository_destroy(handle);
+    }
+
+    #[test]
+    fn test_prepare_for_background() {
+        let handle = ziplock_mobile_repository_create();
+        ziplock_mobile_repository_initialize(handle);
+
+        let credential = CredentialRecord::new("Bank".to_string(), "login".to_string());
+        let c_json = CString::new(serde_json::to_string(&credential).unwrap()).unwrap();
+        ziplock_mobile_add_credential(handle, c_json.as_ptr());
+
+        // Unsaved changes are kept until the app saves or discards them
+        assert_eq!(
+            ziplock_mobile_prepare_for_background(handle, 0),
+            ZipLockError::ValidationError
+        );
+        assert_eq!(ziplock_mobile_repository_is_initialized(handle), 1);
+
+        ziplock_mobile_mark_saved(handle);
+        assert_eq!(
+            ziplock_mobile_prepare_for_background(handle, 0),
+            ZipLockError::Success
+        );
+        assert_eq!(ziplock_mobile_repository_is_initialized(handle), 0);
+        assert!(ziplock_mobile_list_credentials(handle).is_null());
+
+        ziplock_mobile_repository_destroy(handle);
+    }
+
+    #[test]
+    fn test_evaluate_lock_policy() {
+        let policy = CString::new(r#"{"idle_timeout_secs":300}"#).unwrap();
+
+        let decision_ptr = ziplock_mobile_evaluate_lock_policy(policy.as_ptr(), 60);
+        let decision: serde_json::Value =
+            serde_json::from_str(&c_string_to_rust(decision_ptr).unwrap()).unwrap();
+        ziplock_mobile_free_string(decision_ptr);
+        assert_eq!(decision["decision"], "unlocked");
+        assert_eq!(decision["lock_in_secs"], 240);
+
+        let decision_ptr = ziplock_mobile_evaluate_lock_policy(policy.as_ptr(), 300);
+        let decision: serde_json::Value =
+            serde_json::from_str(&c_string_to_rust(decision_ptr).unwrap()).unwrap();
+        ziplock_mobile_free_string(decision_ptr);
+        assert_eq!(decision["decision"], "lock");
+        assert_eq!(decision["reason"], "idle");
+
+        // A window covering the whole day, every day, is always a hard lock
+        let always =
+            CString::new(r#"{"windows":[{"label":"Maintenance","start":"00:00","end":"00:00"}]}"#)
+                .unwrap();
+        let decision_ptr = ziplock_mobile_evaluate_lock_policy(always.as_ptr(), 0);
+        let decision: serde_json::Value =
+            serde_json::from_str(&c_string_to_rust(decision_ptr).unwrap()).unwrap();
+        ziplock_mobile_free_string(decision_ptr);
+        assert_eq!(decision["reason"], "scheduled");
+        assert_eq!(decision["label"], "Maintenance");
+
+        assert!(ziplock_mobile_evaluate_lock_policy(ptr::null(), 0).is_null());
+    }
+
+    #[test]
+    fn test_unlock_throttle() {
+        let policy = CString::new(r#"{"max_attempts":2,"lockout_secs":60}"#).unwrap();
+        let json = |ptr: *mut c_char| -> serde_json::Value {
+            let value = serde_json::from_str(&c_string_to_rust(ptr).unwrap()).unwrap();
+            ziplock_mobile_free_string(ptr);
+            value
+        };
+
+        let attempts_ptr = ziplock_mobile_record_unlock_attempt(policy.as_ptr(), ptr::null(), 0);
+        let attempts = CString::new(c_string_to_rust(attempts_ptr).unwrap()).unwrap();
+        ziplock_mobile_free_string(attempts_ptr);
+        let status = json(ziplock_mobile_unlock_status(
+            policy.as_ptr(),
+            attempts.as_ptr(),
+        ));
+        assert_eq!(status["remaining_attempts"], 1);
+        assert_eq!(status["locked_out"], false);
+
+        let attempts_ptr =
+            ziplock_mobile_record_unlock_attempt(policy.as_ptr(), attempts.as_ptr(), 0);
+        let attempts = CString::new(c_string_to_rust(attempts_ptr).unwrap()).unwrap();
+        ziplock_mobile_free_string(attempts_ptr);
+        let status = json(ziplock_mobile_unlock_status(
+            policy.as_ptr(),
+            attempts.as_ptr(),
+        ));
+        assert_eq!(status["remaining_attempts"], 0);
+        assert_eq!(status["locked_out"], true);
+        assert!(status["retry_in_secs"].as_u64().unwrap() > 0);
+
+        let cleared = json(ziplock_mobile_record_unlock_attempt(
+            policy.as_ptr(),
+            attempts.as_ptr(),
+            1,
+        ));
+        assert_eq!(cleared["failures"], 0);
+        assert_eq!(cleared["locked_out"], false);
+
+        let invalid = CString::new("not json").unwrap();
+        assert!(ziplock_mobile_unlock_status(invalid.as_ptr(), ptr::null()).is_null());
+    }
+
+    #[test]
+    fn test_widget_snapshot() {
+        let handle = ziplock_mobile_repository_create();
+        ziplock_mobile_repository_initialize(handle);
+
+        let credential = CredentialRecord::new("Pinned".to_string(), "login".to_string());
+        let c_json = CString::new(serde_json::to_string(&credential).unwrap()).unwrap();
+        ziplock_mobile_add_credential(handle, c_json.as_ptr());
+
+        let key = WidgetKey::generate();
+        let c_key = CString::new(key.to_string()).unwrap();
+        let c_ids = CString::new(format!("[\"{}\"]", credential.id)).unwrap();
+        let sealed_ptr =
+            ziplock_mobile_create_widget_snapshot(handle, c_ids.as_ptr(), c_key.as_ptr());
+        assert!(!sealed_ptr.is_null());
+        let sealed = c_string_to_rust(sealed_ptr).unwrap();
+        ziplock_mobile_free_string(sealed_ptr);
+
+        let snapshot = WidgetSnapshot::open(&sealed, &key).unwrap();
+        assert_eq!(snapshot.entries.len(), 1);
+        assert_eq!(snapshot.entries[0].title, "Pinned");
+
+        assert!(
+            ziplock_mobile_create_widget_snapshot(handle, ptr::null(), c_key.as_ptr()).is_null()
+        );
+
+        ziplock_mobile_repository_destroy(handle);
+    }
+
+    #[test]
+    fn test_watch_request_flow() {
+        let handle = ziplock_mobile_repository_create();
+        ziplock_mobile_repository_initialize(handle);
+
+        let mut credential = CredentialRecord::new("GitHub".to_string(), "login".to_string());
+        credential.set_field(
+            "totp_secret",
+            CredentialField::totp_secret("JBSWY3DPEHPK3PXP"),
+        );
+        let c_json = CString::new(serde_json::to_string(&credential).unwrap()).unwrap();
+        ziplock_mobile_add_credential(handle, c_json.as_ptr());
+
+        let pairing_ptr = ziplock_mobile_watch_pair(handle);
+        let pairing: WatchPairing = c_string_to_rust(pairing_ptr).unwrap().parse().unwrap();
+        ziplock_mobile_free_string(pairing_ptr);
+
+        let request = WatchRequest::new(&pairing, Vec::new(), chrono::Utc::now());
+        let c_request = CString::new(request.encode()).unwrap();
+        let c_ids = CString::new(format!("[\"{}\"]", credential.id)).unwrap();
+        let response_ptr = ziplock_mobile_watch_respond(handle, c_request.as_ptr(), c_ids.as_ptr());
+        assert!(!response_ptr.is_null());
+        let response = c_string_to_rust(response_ptr).unwrap();
+        ziplock_mobile_free_string(response_ptr);
+
+        let codes = crate::utils::WatchResponse::decode(&response)
+            .unwrap()
+            .open(&pairing, &request)
+            .unwrap();
+        assert_eq!(codes[0].title, "GitHub");
+
+        // The same request cannot be answered twice
+        assert!(ziplock_mobile_watch_respond(handle, c_request.as_ptr(), c_ids.as_ptr()).is_null());
+
+        ziplock_mobile_repository_destroy(handle);
+    }
+
+    #[test]
+    fn test_archive_progress_callback() {
+        static SEEN: Mutex<Vec<(c_int, c_int)>> = Mutex::new(Vec::new());
+        extern "C" fn record(phase: c_int, percent: c_int, user_data: *mut c_void) {
+            assert_eq!(user_data as usize, 42);
+            SEEN.lock().unwrap().push((phase, percent));
+        }
+        ziplock_mobile_set_progress_callback(Some(record), 42 as *mut c_void);
+
+        let handle = ziplock_mobile_repository_create();
+        ziplock_mobile_repository_initialize(handle);
+        let files_ptr = ziplock_mobile_repository_serialize_to_files(handle);
+        ziplock_mobile_repository_destroy(handle);
+
+        let c_password = CString::new("password").unwrap();
+        let mut path_ptr = ptr::null_mut();
+        let result =
+            ziplock_mobile_create_temp_archive(files_ptr, c_password.as_ptr(), &mut path_ptr);
+        assert_eq!(result, ZipLockError::Success);
+        ziplock_mobile_free_string(files_ptr);
+
+        let mut json_ptr = ptr::null_mut();
+        let result =
+            ziplock_mobile_extract_temp_archive(path_ptr, c_password.as_ptr(), &mut json_ptr);
+        assert_eq!(result, ZipLockError::Success);
+        let _ = std::fs::remove_file(c_string_to_rust(path_ptr).unwrap());
+        ziplock_mobile_free_string(path_ptr);
+        ziplock_mobile_free_string(json_ptr);
+        ziplock_mobile_set_progress_callback(None, ptr::null_mut());
+
+        // Other tests may create archives at the same time
+        let seen = SEEN.lock().unwrap();
+        for phase in [
+            ArchivePhase::Serializing,
+            ArchivePhase::Encrypting,
+            ArchivePhase::Writing,
+            ArchivePhase::Reading,
+            ArchivePhase::Decrypting,
+            ArchivePhase::Complete,
+        ] {
+            assert!(seen.contains(&(phase as c_int, phase.percent() as c_int)));
+        }
+    }
+
+    #[test]
+    fn test_biometric_token_unlock() {
+        let handle = ziplock_mobile_repository_create();
+        ziplock_mobile_repository_initialize(handle);
+        let files_ptr = ziplock_mobile_repository_serialize_to_files(handle);
+        let files_json = c_string_to_rust(files_ptr).unwrap();
+        ziplock_mobile_free_string(files_ptr);
+        ziplock_mobile_repository_destroy(handle);
+
+        let c_files = CString::new(files_json).unwrap();
+        let create_archive = |password: &str| {
+            let c_password = CString::new(password).unwrap();
+            let mut path_ptr = ptr::null_mut();
+            let result = ziplock_mobile_create_temp_archive(
+                c_files.as_ptr(),
+                c_password.as_ptr(),
+                &mut path_ptr,
+            );
+            assert_eq!(result, ZipLockError::Success);
+            let path = c_string_to_rust(path_ptr).unwrap();
+            ziplock_mobile_free_string(path_ptr);
+            path
+        };
+        let unlock = |token: &CString, secret: &[u8], path: &str| {
+            let c_path = CString::new(path).unwrap();
+            let (mut password_ptr, mut files_ptr) = (ptr::null_mut(), ptr::null_mut());
+            let result = ziplock_mobile_unlock_with_token(
+                token.as_ptr(),
+                secret.as_ptr(),
+                secret.len(),
+                c_path.as_ptr(),
+                &mut password_ptr,
+                &mut files_ptr,
+            );
+            let password = c_string_to_rust(password_ptr);
+            ziplock_mobile_free_string(password_ptr);
+            ziplock_mobile_free_string(files_ptr);
+            (result, password)
+        };
+
+        let secret = [5u8; 32];
+        let path = create_archive("correct horse");
+        let enable = |password: &str| {
+            let c_path = CString::new(path.as_str()).unwrap();
+            let c_password = CString::new(password).unwrap();
+            let mut token_ptr = ptr::null_mut();
+            let result = ziplock_mobile_enable_biometric_unlock(
+                c_path.as_ptr(),
+                c_password.as_ptr(),
+                secret.as_ptr(),
+                secret.len(),
+                0,
+                &mut token_ptr,
+            );
+            let token = c_string_to_rust(token_ptr);
+            ziplock_mobile_free_string(token_ptr);
+            (result, token)
+        };
+
+        // A mistyped password is refused rather than sealed
+        let (result, token) = enable("correct hose");
+        assert_eq!(result, ZipLockError::InvalidPassword);
+        assert_eq!(token, None);
+
+        let (result, token) = enable("correct horse");
+        assert_eq!(result, ZipLockError::Success);
+        let token = CString::new(token.unwrap()).unwrap();
+
+        let (result, password) = unlock(&token, &secret, &path);
+        assert_eq!(result, ZipLockError::Success);
+        assert_eq!(password.as_deref(), Some("correct horse"));
+
+        // A new fingerprint enrolment replaces the keystore key
+        let (result, password) = unlock(&token, &[6u8; 32], &path);
+        assert_eq!(result, ZipLockError::KeyProtectionError);
+        assert_eq!(password, None);
+
+        // Changing the master password invalidates the token
+        let changed = create_archive("battery staple");
+        std::fs::rename(&changed, &path).unwrap();
+        let (result, password) = unlock(&token, &secret, &path);
+        assert_eq!(result, ZipLockError::InvalidPassword);
+        assert_eq!(password, None);
+
+        std::fs::remove_file(&path).unwrap();
+    }
 }
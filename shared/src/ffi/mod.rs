@@ -4,37 +4,68 @@
 //! with the ZipLock shared library. It includes platform-specific optimizations
 //! and interfaces that respect the capabilities and constraints of each target.
 
+pub mod apple;
+pub mod bindings;
 pub mod common;
 pub mod desktop;
 pub mod mobile;
+#[cfg(target_arch = "wasm32")]
+pub mod wasm;
+pub mod widget;
 
 // Re-export common functionality
+#[cfg(feature = "test-vectors")]
+pub use common::ziplock_test_vectors;
 pub use common::{
-    c_string_to_rust, rust_string_to_c, ziplock_free_string, ziplock_get_version,
-    ziplock_set_log_level, CredentialHandle, FfiLogLevel, RepositoryHandle, VersionInfo,
-    ZipLockError,
+    c_string_to_rust, rust_string_to_c, ziplock_error_message, ziplock_free_string,
+    ziplock_get_last_error, ziplock_get_last_error_localized, ziplock_get_version,
+    ziplock_self_test, ziplock_set_log_level, CredentialHandle, FfiLogLevel, RepositoryHandle,
+    VersionInfo, ZipLockError, ZipLockProgressCallback,
 };
 
 // Re-export platform-specific modules
+pub use apple::{
+    ziplock_apple_get_credential, ziplock_apple_provider_destroy, ziplock_apple_quick_unlock,
+    ziplock_apple_service_identities, AppleProviderHandle,
+};
 pub use desktop::{
     ziplock_desktop_add_credential, ziplock_desktop_change_password,
-    ziplock_desktop_close_repository, ziplock_desktop_create_repository,
+    ziplock_desktop_close_repository, ziplock_desktop_compliance_report,
+    ziplock_desktop_create_repository, ziplock_desktop_credential_form_schema,
     ziplock_desktop_current_path, ziplock_desktop_delete_credential, ziplock_desktop_free_string,
     ziplock_desktop_get_credential, ziplock_desktop_get_stats, ziplock_desktop_is_modified,
     ziplock_desktop_is_open, ziplock_desktop_list_credentials, ziplock_desktop_manager_create,
     ziplock_desktop_manager_destroy, ziplock_desktop_open_repository,
-    ziplock_desktop_save_repository, ziplock_desktop_update_credential, DesktopArchiveConfig,
-    DesktopError, DesktopManagerHandle,
+    ziplock_desktop_open_with_security_key, ziplock_desktop_prepare_for_suspend,
+    ziplock_desktop_register_security_key, ziplock_desktop_save_repository,
+    ziplock_desktop_security_audit, ziplock_desktop_set_folder_defaults,
+    ziplock_desktop_set_progress_callback, ziplock_desktop_update_credential, DesktopArchiveConfig,
+    DesktopError, DesktopManagerHandle, DesktopProgressCallback,
 };
 pub use mobile::{
-    ziplock_mobile_add_credential, ziplock_mobile_clear_credentials,
-    ziplock_mobile_create_temp_archive, ziplock_mobile_delete_credential,
+    ziplock_mobile_add_credential, ziplock_mobile_clear_credentials, ziplock_mobile_clip_receive,
+    ziplock_mobile_clip_send, ziplock_mobile_compliance_report, ziplock_mobile_create_temp_archive,
+    ziplock_mobile_create_widget_snapshot, ziplock_mobile_credential_form_schema,
+    ziplock_mobile_delete_credential, ziplock_mobile_enable_biometric_unlock,
+    ziplock_mobile_evaluate_lock_policy, ziplock_mobile_export_html_viewer,
     ziplock_mobile_extract_temp_archive, ziplock_mobile_free_string, ziplock_mobile_get_credential,
-    ziplock_mobile_get_stats, ziplock_mobile_is_modified, ziplock_mobile_list_credentials,
-    ziplock_mobile_mark_saved, ziplock_mobile_repository_create, ziplock_mobile_repository_destroy,
-    ziplock_mobile_repository_initialize, ziplock_mobile_repository_is_initialized,
-    ziplock_mobile_repository_load_from_files, ziplock_mobile_repository_serialize_to_files,
-    ziplock_mobile_update_credential, MobileRepositoryHandle,
+    ziplock_mobile_get_health, ziplock_mobile_get_reminders, ziplock_mobile_get_stats,
+    ziplock_mobile_is_modified, ziplock_mobile_list_credentials, ziplock_mobile_mark_saved,
+    ziplock_mobile_prepare_for_background, ziplock_mobile_repository_create,
+    ziplock_mobile_repository_destroy, ziplock_mobile_repository_initialize,
+    ziplock_mobile_repository_is_initialized, ziplock_mobile_repository_load_from_files,
+    ziplock_mobile_repository_serialize_to_files, ziplock_mobile_security_audit,
+    ziplock_mobile_set_folder_defaults, ziplock_mobile_set_memory_budget,
+    ziplock_mobile_set_progress_callback, ziplock_mobile_unlock_with_token,
+    ziplock_mobile_update_credential, ziplock_mobile_validate_credential,
+    ziplock_mobile_watch_add_pairing, ziplock_mobile_watch_pair, ziplock_mobile_watch_respond,
+    MobileRepositoryHandle,
+};
+#[cfg(target_arch = "wasm32")]
+pub use wasm::WasmVault;
+pub use widget::{
+    ziplock_watch_create_request, ziplock_watch_open_response, ziplock_widget_generate_key,
+    ziplock_widget_get_codes, ziplock_widget_unwrap_key, ziplock_widget_wrap_key,
 };
 
 /// Check if this is a mobile platform build
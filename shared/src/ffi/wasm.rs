@@ -0,0 +1,173 @@
+//! wasm-bindgen exports for web apps
+//!
+//! Built for `wasm32-unknown-unknown` only. [`WasmVault`] is a repository
+//! manager over a [`WasmFileProvider`] whose read and write callbacks are
+//! JavaScript functions, so the web app decides where archive bytes live
+//! (OPFS or IndexedDB). Credentials cross the boundary as the same JSON the
+//! C FFI uses.
+//!
+//! ```js
+//! const vault = new WasmVault(
+//!   (path) => cache.get(path) ?? null,
+//!   (path, bytes) => { cache.set(path, bytes); persist(path, bytes); },
+//! );
+//! vault.open("personal", password);
+//! const credentials = JSON.parse(vault.listCredentials());
+//! ```
+
+use js_sys::{Function, Uint8Array};
+use wasm_bindgen::prelude::*;
+
+use crate::core::{CoreError, FileError, FileResult, UnifiedRepositoryManager, WasmFileProvider};
+use crate::models::CredentialRecord;
+
+/// A JavaScript callback held by a [`WasmFileProvider`]
+///
+/// `FileOperationProvider` must be `Send + Sync`, which JS values are not.
+/// Without the atomics target feature the module runs on a single thread,
+/// so they never actually cross one.
+struct JsCallback(Function);
+
+// SAFETY: wasm32-unknown-unknown without atomics has exactly one thread
+unsafe impl Send for JsCallback {}
+unsafe impl Sync for JsCallback {}
+
+impl JsCallback {
+    fn read(&self, path: &str) -> FileResult<Vec<u8>> {
+        let value = self
+            .0
+            .call1(&JsValue::NULL, &JsValue::from_str(path))
+            .map_err(|e| js_error("read", path, e))?;
+        if value.is_null() || value.is_undefined() {
+            return Err(FileError::NotFound {
+                path: path.to_string(),
+            });
+        }
+        Ok(Uint8Array::new(&value).to_vec())
+    }
+
+    fn write(&self, path: &str, data: &[u8]) -> FileResult<()> {
+        self.0
+            .call2(
+                &JsValue::NULL,
+                &JsValue::from_str(path),
+                &Uint8Array::from(data).into(),
+            )
+            .map(|_| ())
+            .map_err(|e| js_error("write", path, e))
+    }
+}
+
+fn js_error(action: &str, path: &str, error: JsValue) -> FileError {
+    FileError::IoError {
+        message: format!("Failed to {} archive '{}': {:?}", action, path, error),
+    }
+}
+
+fn to_js(error: CoreError) -> JsError {
+    JsError::new(&error.to_string())
+}
+
+fn to_json<T: serde::Serialize>(value: &T) -> Result<String, JsError> {
+    serde_json::to_string(value).map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// A vault opened in the browser
+#[wasm_bindgen]
+pub struct WasmVault {
+    manager: UnifiedRepositoryManager<WasmFileProvider>,
+}
+
+#[wasm_bindgen]
+impl WasmVault {
+    /// `read(path)` returns a `Uint8Array`, or `null` if nothing is stored
+    /// under `path`; `write(path, bytes)` stores the archive. Both must
+    /// finish before they return.
+    #[wasm_bindgen(constructor)]
+    pub fn new(read: Function, write: Function) -> WasmVault {
+        let (read, write) = (JsCallback(read), JsCallback(write));
+        let provider = WasmFileProvider::new(
+            move |path| read.read(path),
+            move |path, data| write.write(path, data),
+        );
+        WasmVault {
+            manager: UnifiedRepositoryManager::new(provider),
+        }
+    }
+
+    pub fn create(&mut self, path: &str, password: &str) -> Result<(), JsError> {
+        self.manager
+            .create_repository(path, password)
+            .map_err(to_js)
+    }
+
+    pub fn open(&mut self, path: &str, password: &str) -> Result<(), JsError> {
+        self.manager.open_repository(path, password).map_err(to_js)
+    }
+
+    pub fn save(&mut self) -> Result<(), JsError> {
+        self.manager.save_repository().map_err(to_js)
+    }
+
+    pub fn close(&mut self, save_if_modified: bool) -> Result<(), JsError> {
+        self.manager
+            .close_repository(save_if_modified)
+            .map_err(to_js)
+    }
+
+    #[wasm_bindgen(js_name = isOpen)]
+    pub fn is_open(&self) -> bool {
+        self.manager.is_open()
+    }
+
+    #[wasm_bindgen(js_name = isModified)]
+    pub fn is_modified(&self) -> bool {
+        self.manager.is_modified()
+    }
+
+    /// JSON array of every credential
+    #[wasm_bindgen(js_name = listCredentials)]
+    pub fn list_credentials(&self) -> Result<String, JsError> {
+        to_json(&self.manager.list_credentials().map_err(to_js)?)
+    }
+
+    /// JSON array of the credentials matching `query`
+    #[wasm_bindgen(js_name = searchCredentials)]
+    pub fn search_credentials(&self, query: &str) -> Result<String, JsError> {
+        to_json(&self.manager.search_credentials(query).map_err(to_js)?)
+    }
+
+    /// JSON of the credential with `id`
+    #[wasm_bindgen(js_name = getCredential)]
+    pub fn get_credential(&mut self, id: &str) -> Result<String, JsError> {
+        to_json(self.manager.get_credential(id).map_err(to_js)?)
+    }
+
+    /// Add a credential given as JSON and return its id
+    #[wasm_bindgen(js_name = addCredential)]
+    pub fn add_credential(&mut self, json: &str) -> Result<String, JsError> {
+        let credential = parse_credential(json)?;
+        let id = credential.id.clone();
+        self.manager.add_credential(credential).map_err(to_js)?;
+        Ok(id)
+    }
+
+    /// Replace the credential with the id of the one given as JSON
+    #[wasm_bindgen(js_name = updateCredential)]
+    pub fn update_credential(&mut self, json: &str) -> Result<(), JsError> {
+        let credential = parse_credential(json)?;
+        self.manager.update_credential(credential).map_err(to_js)
+    }
+
+    #[wasm_bindgen(js_name = deleteCredential)]
+    pub fn delete_credential(&mut self, id: &str) -> Result<(), JsError> {
+        self.manager
+            .delete_credential(id)
+            .map(|_| ())
+            .map_err(to_js)
+    }
+}
+
+fn parse_credential(json: &str) -> Result<CredentialRecord, JsError> {
+    serde_json::from_str(json).map_err(|e| JsError::new(&format!("Invalid credential: {}", e)))
+}
@@ -0,0 +1,302 @@
+//! Widget FFI interface for ZipLock
+//!
+//! A minimal, read-only surface for home-screen widgets and watch apps. These
+//! functions never touch the repository: they only decrypt the snapshot the
+//! main app wrote with `ziplock_mobile_create_widget_snapshot` and turn it
+//! into TOTP codes.
+//!
+//! # Usage Pattern
+//!
+//! 1. The main app generates a widget key once and wraps it with a device
+//!    secret from the Keychain/Keystore (shared with the widget extension)
+//! 2. Whenever the vault is unlocked or pins change, the main app writes a
+//!    fresh snapshot to app-group storage
+//! 3. The widget unwraps the key and asks for the current codes
+//!
+//! A watch without its own snapshot instead signs a request with its pairing
+//! string (`ziplock_watch_create_request`), sends it to the phone, which
+//! answers with `ziplock_mobile_watch_respond`, and decrypts the reply with
+//! `ziplock_watch_open_response`.
+
+use std::os::raw::c_char;
+use std::ptr;
+use std::slice;
+
+use crate::ffi::common::{c_string_to_rust, rust_string_to_c};
+use crate::utils::{WatchPairing, WatchRequest, WatchResponse, WidgetKey, WidgetSnapshot};
+
+/// Generate a new widget key
+///
+/// # Returns
+/// * Key string (must be freed with `ziplock_free_string`)
+#[no_mangle]
+pub extern "C" fn ziplock_widget_generate_key() -> *mut c_char {
+    rust_string_to_c(WidgetKey::generate().to_string())
+}
+
+/// Wrap a widget key with a device secret
+///
+/// # Arguments
+/// * `key` - Key string from `ziplock_widget_generate_key`
+/// * `secret` - Device secret bytes
+/// * `secret_len` - Length of `secret`
+///
+/// # Returns
+/// * Wrapped key string (must be freed with `ziplock_free_string`)
+/// * Null if a parameter is invalid
+#[no_mangle]
+pub extern "C" fn ziplock_widget_wrap_key(
+    key: *const c_char,
+    secret: *const u8,
+    secret_len: usize,
+) -> *mut c_char {
+    let (Some(key), Some(secret)) = (
+        c_string_to_rust(key).and_then(|k| k.parse::<WidgetKey>().ok()),
+        device_secret(secret, secret_len),
+    ) else {
+        return ptr::null_mut();
+    };
+
+    match key.wrap(secret) {
+        Ok(wrapped) => rust_string_to_c(wrapped),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Unwrap a widget key with the device secret it was wrapped with
+///
+/// # Returns
+/// * Key string (must be freed with `ziplock_free_string`)
+/// * Null if the secret is wrong or a parameter is invalid
+#[no_mangle]
+pub extern "C" fn ziplock_widget_unwrap_key(
+    wrapped: *const c_char,
+    secret: *const u8,
+    secret_len: usize,
+) -> *mut c_char {
+    let (Some(wrapped), Some(secret)) =
+        (c_string_to_rust(wrapped), device_secret(secret, secret_len))
+    else {
+        return ptr::null_mut();
+    };
+
+    match WidgetKey::unwrap(&wrapped, secret) {
+        Ok(key) => rust_string_to_c(key.to_string()),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Get the current codes for the pinned credentials in a snapshot
+///
+/// # Arguments
+/// * `snapshot` - Sealed snapshot string
+/// * `key` - Widget key string
+/// * `timestamp` - Unix time to generate codes for, or 0 for now
+///
+/// # Returns
+/// * JSON array of `{id, title, code, period, seconds_remaining}` (must be
+///   freed with `ziplock_free_string`)
+/// * Null if the snapshot cannot be opened with this key
+#[no_mangle]
+pub extern "C" fn ziplock_widget_get_codes(
+    snapshot: *const c_char,
+    key: *const c_char,
+    timestamp: i64,
+) -> *mut c_char {
+    let (Some(snapshot), Some(key)) = (
+        c_string_to_rust(snapshot),
+        c_string_to_rust(key).and_then(|k| k.parse::<WidgetKey>().ok()),
+    ) else {
+        return ptr::null_mut();
+    };
+
+    let snapshot = match WidgetSnapshot::open(&snapshot, &key) {
+        Ok(snapshot) => snapshot,
+        Err(_) => return ptr::null_mut(),
+    };
+
+    let timestamp = match timestamp {
+        0 => chrono::Utc::now().timestamp(),
+        ts => ts,
+    };
+    match serde_json::to_string(&snapshot.codes_at(timestamp.max(0) as u64)) {
+        Ok(json) => rust_string_to_c(json),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Create a signed request for codes from a paired watch
+///
+/// # Arguments
+/// * `pairing` - Pairing string received from the phone
+/// * `credential_ids_json` - JSON array of wanted IDs; null or `[]` for all pinned
+///
+/// # Returns
+/// * Encoded request to send to the phone; keep it to open the response
+///   (must be freed with `ziplock_free_string`)
+/// * Null if a parameter is invalid
+#[no_mangle]
+pub extern "C" fn ziplock_watch_create_request(
+    pairing: *const c_char,
+    credential_ids_json: *const c_char,
+) -> *mut c_char {
+    let Some(pairing) = c_string_to_rust(pairing).and_then(|p| p.parse::<WatchPairing>().ok())
+    else {
+        return ptr::null_mut();
+    };
+    let ids: Vec<String> = match c_string_to_rust(credential_ids_json) {
+        Some(json) => match serde_json::from_str(&json) {
+            Ok(ids) => ids,
+            Err(_) => return ptr::null_mut(),
+        },
+        None => Vec::new(),
+    };
+
+    rust_string_to_c(WatchRequest::new(&pairing, ids, chrono::Utc::now()).encode())
+}
+
+/// Decrypt the phone's response to a watch request
+///
+/// # Arguments
+/// * `pairing` - Pairing string
+/// * `request` - The encoded request this response answers
+/// * `response` - Encoded response from the phone
+///
+/// # Returns
+/// * JSON array of codes, as from `ziplock_widget_get_codes` (must be freed
+///   with `ziplock_free_string`)
+/// * Null if the response does not belong to this request
+#[no_mangle]
+pub extern "C" fn ziplock_watch_open_response(
+    pairing: *const c_char,
+    request: *const c_char,
+    response: *const c_char,
+) -> *mut c_char {
+    let (Some(pairing), Some(request), Some(response)) = (
+        c_string_to_rust(pairing).and_then(|p| p.parse::<WatchPairing>().ok()),
+        c_string_to_rust(request).and_then(|r| WatchRequest::decode(&r).ok()),
+        c_string_to_rust(response).and_then(|r| WatchResponse::decode(&r).ok()),
+    ) else {
+        return ptr::null_mut();
+    };
+
+    match response
+        .open(&pairing, &request)
+        .ok()
+        .and_then(|codes| serde_json::to_string(&codes).ok())
+    {
+        Some(json) => rust_string_to_c(json),
+        None => ptr::null_mut(),
+    }
+}
+
+pub(crate) fn device_secret<'a>(secret: *const u8, len: usize) -> Option<&'a [u8]> {
+    if secret.is_null() || len == 0 {
+        return None;
+    }
+    // SAFETY: the caller guarantees `secret` points to `len` readable bytes
+    Some(unsafe { slice::from_raw_parts(secret, len) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ffi::common::ziplock_free_string;
+    use std::ffi::CString;
+
+    fn take(ptr: *mut c_char) -> String {
+        assert!(!ptr.is_null());
+        let s = c_string_to_rust(ptr).unwrap();
+        unsafe { ziplock_free_string(ptr) };
+        s
+    }
+
+    #[test]
+    fn test_wrap_and_unwrap_key() {
+        let key = take(ziplock_widget_generate_key());
+        let c_key = CString::new(key.clone()).unwrap();
+        let secret = b"device secret";
+
+        let wrapped = take(ziplock_widget_wrap_key(
+            c_key.as_ptr(),
+            secret.as_ptr(),
+            secret.len(),
+        ));
+        let c_wrapped = CString::new(wrapped).unwrap();
+        let unwrapped = take(ziplock_widget_unwrap_key(
+            c_wrapped.as_ptr(),
+            secret.as_ptr(),
+            secret.len(),
+        ));
+        assert_eq!(unwrapped, key);
+
+        let wrong = b"wrong";
+        assert!(
+            ziplock_widget_unwrap_key(c_wrapped.as_ptr(), wrong.as_ptr(), wrong.len()).is_null()
+        );
+        assert!(ziplock_widget_wrap_key(c_key.as_ptr(), ptr::null(), 0).is_null());
+    }
+
+    #[test]
+    fn test_get_codes() {
+        let mut credential =
+            crate::models::CredentialRecord::new("GitHub".to_string(), "login".to_string());
+        credential.set_field(
+            "totp_secret",
+            crate::models::CredentialField::totp_secret("JBSWY3DPEHPK3PXP"),
+        );
+        let key = WidgetKey::generate();
+        let sealed = WidgetSnapshot::from_credentials(&[credential.clone()], &[credential.id])
+            .seal(&key)
+            .unwrap();
+
+        let c_snapshot = CString::new(sealed).unwrap();
+        let c_key = CString::new(key.to_string()).unwrap();
+        let json = take(ziplock_widget_get_codes(
+            c_snapshot.as_ptr(),
+            c_key.as_ptr(),
+            59,
+        ));
+        let codes: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(codes[0]["title"], "GitHub");
+        assert_eq!(codes[0]["code"].as_str().unwrap().len(), 6);
+
+        let other = CString::new(WidgetKey::generate().to_string()).unwrap();
+        assert!(ziplock_widget_get_codes(c_snapshot.as_ptr(), other.as_ptr(), 0).is_null());
+    }
+
+    #[test]
+    fn test_watch_request_and_response() {
+        let credential =
+            crate::models::CredentialRecord::new("Bank".to_string(), "login".to_string());
+        let pairing = WatchPairing::generate();
+        let mut authorizer = crate::utils::WatchAuthorizer::new();
+        authorizer.add_pairing(pairing.clone());
+
+        let c_pairing = CString::new(pairing.to_string()).unwrap();
+        let request = take(ziplock_watch_create_request(
+            c_pairing.as_ptr(),
+            ptr::null(),
+        ));
+        let response = authorizer
+            .respond(
+                &WatchRequest::decode(&request).unwrap(),
+                std::slice::from_ref(&credential),
+                std::slice::from_ref(&credential.id),
+                chrono::Utc::now(),
+            )
+            .unwrap()
+            .encode();
+
+        let c_request = CString::new(request).unwrap();
+        let c_response = CString::new(response).unwrap();
+        let json = take(ziplock_watch_open_response(
+            c_pairing.as_ptr(),
+            c_request.as_ptr(),
+            c_response.as_ptr(),
+        ));
+        let codes: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(codes[0]["title"], "Bank");
+        assert!(codes[0]["code"].is_null());
+    }
+}
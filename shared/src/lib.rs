@@ -60,10 +60,15 @@ pub mod logging;
 pub mod models;
 pub mod utils;
 
+// Scaffolding for the types in `ffi::bindings` exported through UniFFI
+#[cfg(feature = "uniffi")]
+uniffi::setup_scaffolding!();
+
 // Re-export core functionality
 pub use core::{
-    CoreError, CoreResult, DesktopFileProvider, FileError, FileOperationProvider, FileResult,
-    UnifiedMemoryRepository, UnifiedRepositoryManager,
+    AsyncFileOperationProvider, AsyncRepositoryManager, BlockingFileProvider, CoreError,
+    CoreResult, DesktopFileProvider, FileError, FileOperationProvider, FileResult, KeyFile,
+    RepositoryRegistry, UnifiedMemoryRepository, UnifiedRepositoryManager,
 };
 
 // Re-export configuration management
@@ -221,6 +221,7 @@ impl CredentialUtils {
             "password" => CommonTemplates::password().create_credential(title).ok(),
             "document" | "file" => CommonTemplates::document().create_credential(title).ok(),
             "ssh_key" | "ssh" => CommonTemplates::ssh_key().create_credential(title).ok(),
+            "pgp_key" | "pgp" | "gpg" => CommonTemplates::pgp_key().create_credential(title).ok(),
             "bank_account" | "bank" => CommonTemplates::bank_account()
                 .create_credential(title)
                 .ok(),
@@ -451,6 +452,92 @@ impl CredentialExport {
     }
 }
 
+/// How sensitive the account behind a credential is, for access reviews
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DataClassification {
+    Public,
+    Internal,
+    Confidential,
+    Restricted,
+}
+
+impl DataClassification {
+    pub fn all() -> [DataClassification; 4] {
+        [
+            DataClassification::Public,
+            DataClassification::Internal,
+            DataClassification::Confidential,
+            DataClassification::Restricted,
+        ]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DataClassification::Public => "public",
+            DataClassification::Internal => "internal",
+            DataClassification::Confidential => "confidential",
+            DataClassification::Restricted => "restricted",
+        }
+    }
+}
+
+/// Optional access-review metadata attached to a credential
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ComplianceInfo {
+    /// Person or team accountable for the account
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+
+    /// Person who signs off the periodic access review
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reviewer: Option<String>,
+
+    /// When access was last reviewed (Unix timestamp)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_reviewed_at: Option<i64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub classification: Option<DataClassification>,
+
+    /// Who may use the account and why
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub access_notes: Option<String>,
+}
+
+impl ComplianceInfo {
+    /// Whether no compliance field has been filled in
+    pub fn is_empty(&self) -> bool {
+        self == &ComplianceInfo::default()
+    }
+
+    /// Record a completed review
+    pub fn mark_reviewed<S: Into<String>>(&mut self, reviewer: S, at: i64) {
+        self.reviewer = Some(reviewer.into());
+        self.last_reviewed_at = Some(at);
+    }
+}
+
+/// Previous values kept per field unless the repository sets another limit
+pub const DEFAULT_FIELD_HISTORY_LIMIT: usize = 5;
+
+/// A value a password field held before it was changed or removed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FieldHistoryEntry {
+    /// Name of the field the value was in
+    pub field: String,
+
+    pub value: String,
+
+    /// When the value was replaced or the field removed (Unix timestamp)
+    pub replaced_at: i64,
+
+    /// Whether the field was removed rather than given a new value
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub removed: bool,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -472,6 +559,8 @@ mod tests {
             ("file", "My File", "document"),
             ("ssh_key", "My SSH Key", "ssh_key"),
             ("ssh", "My SSH", "ssh_key"),
+            ("pgp_key", "My PGP Key", "pgp_key"),
+            ("gpg", "My GPG Key", "pgp_key"),
             ("bank_account", "My Bank Account", "bank_account"),
             ("bank", "My Bank", "bank_account"),
             ("api_credentials", "My API", "api_credentials"),
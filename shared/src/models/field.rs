@@ -78,6 +78,8 @@ impl TextFieldBuilder {
             sensitive: self.sensitive,
             label: self.label,
             metadata: self.metadata,
+            protection: None,
+            value_updated_at: None,
         }
     }
 }
@@ -120,6 +122,8 @@ impl PasswordFieldBuilder {
             sensitive: true, // Always sensitive for passwords
             label: self.label,
             metadata: self.metadata,
+            protection: None,
+            value_updated_at: None,
         }
     }
 }
@@ -162,6 +166,8 @@ impl EmailFieldBuilder {
             sensitive: false,
             label: self.label,
             metadata: self.metadata,
+            protection: None,
+            value_updated_at: None,
         }
     }
 }
@@ -204,6 +210,8 @@ impl UrlFieldBuilder {
             sensitive: false,
             label: self.label,
             metadata: self.metadata,
+            protection: None,
+            value_updated_at: None,
         }
     }
 }
@@ -0,0 +1,273 @@
+//! Folder defaults inherited by new credentials
+//!
+//! A folder can name an icon, a color and a template. A credential created
+//! in that folder, or in any folder below it, starts out with them unless it
+//! sets its own. Each value is inherited separately from the nearest folder
+//! that sets it, so `Work` can pick the color while `Work/Servers` only
+//! picks the template.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use super::{find_template, CredentialRecord, CredentialTemplate, TemplateMap};
+
+/// Template used when neither the caller nor a folder picks one
+pub const DEFAULT_TEMPLATE: &str = "login";
+
+/// Defaults a folder passes on to the credentials created in it
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct FolderDefaults {
+    /// Icon name, e.g. a credential type such as `server` or `ssh_key`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+
+    /// Color as `#rrggbb`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+
+    /// Name of the template new credentials are created from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+}
+
+impl FolderDefaults {
+    /// Whether no default is set
+    pub fn is_empty(&self) -> bool {
+        self.icon.is_none() && self.color.is_none() && self.template.is_none()
+    }
+
+    /// Check the values, returning one message per problem
+    ///
+    /// The template may be a built-in one or one of the user's `templates`.
+    pub fn validate(&self, templates: &TemplateMap) -> Result<(), Vec<String>> {
+        let mut errors = Vec::new();
+        if self
+            .icon
+            .as_deref()
+            .is_some_and(|icon| icon.trim().is_empty())
+        {
+            errors.push("Folder icon cannot be empty".to_string());
+        }
+        if let Some(color) = &self.color {
+            if !is_hex_color(color) {
+                errors.push(format!("Folder color '{}' is not a #rrggbb color", color));
+            }
+        }
+        if let Some(template) = &self.template {
+            if find_template(templates, template).is_none() {
+                errors.push(format!("Unknown template '{}'", template));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Take any value not set here from `parent`
+    fn inherit(&mut self, parent: &FolderDefaults) {
+        if self.icon.is_none() {
+            self.icon.clone_from(&parent.icon);
+        }
+        if self.color.is_none() {
+            self.color.clone_from(&parent.color);
+        }
+        if self.template.is_none() {
+            self.template.clone_from(&parent.template);
+        }
+    }
+}
+
+/// Everything a credential form needs to show the same defaults on every
+/// platform
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CredentialFormSchema {
+    /// Folder the credential will be created in
+    pub folder_path: Option<String>,
+
+    /// Template the form's fields come from
+    pub template: CredentialTemplate,
+
+    /// Whether the template was picked by the folder rather than the caller
+    pub template_inherited: bool,
+
+    /// Icon the new credential will get
+    pub icon: Option<String>,
+
+    /// Color the new credential will get
+    pub color: Option<String>,
+}
+
+/// Folder paths mapped to the defaults set directly on them
+pub type FolderDefaultsMap = BTreeMap<String, FolderDefaults>;
+
+/// Normalize a folder path: trims surrounding whitespace and slashes and
+/// drops empty segments, so `/Work//Email/` becomes `Work/Email`
+pub fn normalize_folder_path(path: &str) -> String {
+    path.split('/')
+        .map(str::trim)
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// The defaults that apply in `folder_path`, inherited through its parents
+pub fn resolve_folder_defaults(folders: &FolderDefaultsMap, folder_path: &str) -> FolderDefaults {
+    let path = normalize_folder_path(folder_path);
+    let mut resolved = FolderDefaults::default();
+    let mut current = path.as_str();
+    while !current.is_empty() {
+        if let Some(defaults) = folders.get(current) {
+            resolved.inherit(defaults);
+        }
+        current = current.rsplit_once('/').map_or("", |(parent, _)| parent);
+    }
+    resolved
+}
+
+/// Give a new credential the icon and color of its folder where it has none
+pub fn apply_folder_defaults(folders: &FolderDefaultsMap, credential: &mut CredentialRecord) {
+    let Some(folder_path) = credential.folder_path.as_deref() else {
+        return;
+    };
+    let defaults = resolve_folder_defaults(folders, folder_path);
+    if credential.icon.is_none() {
+        credential.icon = defaults.icon;
+    }
+    if credential.color.is_none() {
+        credential.color = defaults.color;
+    }
+}
+
+/// Build the form for a new credential in `folder_path`
+///
+/// An explicit `template` wins over the folder's; with neither, the login
+/// template is used. Names are looked up in the user's `templates` first.
+pub fn credential_form_schema(
+    folders: &FolderDefaultsMap,
+    templates: &TemplateMap,
+    folder_path: Option<&str>,
+    template: Option<&str>,
+) -> Result<CredentialFormSchema, String> {
+    let folder_path = folder_path
+        .map(normalize_folder_path)
+        .filter(|path| !path.is_empty());
+    let defaults = folder_path
+        .as_deref()
+        .map(|path| resolve_folder_defaults(folders, path))
+        .unwrap_or_default();
+
+    let template_inherited = template.is_none() && defaults.template.is_some();
+    let name = template
+        .or(defaults.template.as_deref())
+        .unwrap_or(DEFAULT_TEMPLATE);
+    let template =
+        find_template(templates, name).ok_or_else(|| format!("Unknown template '{}'", name))?;
+
+    Ok(CredentialFormSchema {
+        folder_path,
+        template,
+        template_inherited,
+        icon: defaults.icon,
+        color: defaults.color,
+    })
+}
+
+fn is_hex_color(color: &str) -> bool {
+    color.len() == 7 && color.starts_with('#') && color[1..].chars().all(|c| c.is_ascii_hexdigit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn folders() -> FolderDefaultsMap {
+        let mut folders = FolderDefaultsMap::new();
+        folders.insert(
+            "Work".to_string(),
+            FolderDefaults {
+                icon: Some("briefcase".to_string()),
+                color: Some("#1e88e5".to_string()),
+                template: None,
+            },
+        );
+        folders.insert(
+            "Work/Servers".to_string(),
+            FolderDefaults {
+                icon: Some("server".to_string()),
+                color: None,
+                template: Some("ssh_key".to_string()),
+            },
+        );
+        folders
+    }
+
+    #[test]
+    fn test_defaults_are_inherited_per_value() {
+        let folders = folders();
+
+        let nested = resolve_folder_defaults(&folders, "/Work/Servers/Prod/");
+        assert_eq!(nested.icon.as_deref(), Some("server"));
+        assert_eq!(nested.color.as_deref(), Some("#1e88e5"));
+        assert_eq!(nested.template.as_deref(), Some("ssh_key"));
+
+        let work = resolve_folder_defaults(&folders, "Work/Email");
+        assert_eq!(work.icon.as_deref(), Some("briefcase"));
+        assert_eq!(work.template, None);
+
+        assert!(resolve_folder_defaults(&folders, "Personal").is_empty());
+        assert!(resolve_folder_defaults(&folders, "Workshop").is_empty());
+    }
+
+    #[test]
+    fn test_form_schema_and_new_credentials_use_folder_defaults() {
+        let folders = folders();
+
+        let templates = TemplateMap::new();
+        let schema =
+            credential_form_schema(&folders, &templates, Some("Work/Servers"), None).unwrap();
+        assert_eq!(schema.template.name, "ssh_key");
+        assert!(schema.template_inherited);
+        assert_eq!(schema.icon.as_deref(), Some("server"));
+        assert_eq!(schema.color.as_deref(), Some("#1e88e5"));
+
+        let explicit =
+            credential_form_schema(&folders, &templates, Some("Work/Servers"), Some("database"))
+                .unwrap();
+        assert_eq!(explicit.template.name, "database");
+        assert!(!explicit.template_inherited);
+
+        let unfiled = credential_form_schema(&folders, &templates, None, None).unwrap();
+        assert_eq!(unfiled.template.name, DEFAULT_TEMPLATE);
+        assert_eq!(unfiled.icon, None);
+        assert!(credential_form_schema(&folders, &templates, None, Some("nope")).is_err());
+
+        let mut credential = CredentialRecord::new("Bastion".to_string(), "ssh_key".to_string());
+        credential.folder_path = Some("Work/Servers".to_string());
+        credential.color = Some("#ff0000".to_string());
+        apply_folder_defaults(&folders, &mut credential);
+        assert_eq!(credential.icon.as_deref(), Some("server"));
+        assert_eq!(credential.color.as_deref(), Some("#ff0000"));
+    }
+
+    #[test]
+    fn test_validate_folder_defaults() {
+        let mut templates = TemplateMap::new();
+        assert!(folders().values().all(|d| d.validate(&templates).is_ok()));
+
+        let invalid = FolderDefaults {
+            icon: Some(" ".to_string()),
+            color: Some("blue".to_string()),
+            template: Some("nope".to_string()),
+        };
+        assert_eq!(invalid.validate(&templates).unwrap_err().len(), 3);
+
+        templates.insert(
+            "nope".to_string(),
+            CredentialTemplate::new("nope", "Custom"),
+        );
+        assert_eq!(invalid.validate(&templates).unwrap_err().len(), 2);
+    }
+}
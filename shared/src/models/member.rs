@@ -0,0 +1,97 @@
+//! Members of a shared vault
+//!
+//! A vault shared by a team lists its members in the archive metadata, each
+//! with the `zlpk1…` public key of their [`ShareIdentity`]. A credential's
+//! `shared_with` names the members who may see it, and
+//! [`SharedVault`](crate::utils::SharedVault) encrypts that subset to each
+//! member's key.
+//!
+//! [`ShareIdentity`]: crate::utils::ShareIdentity
+
+use serde::{Deserialize, Serialize};
+
+use crate::utils::sharing::{ShareRecipient, SharingResult};
+
+/// Longest member name accepted
+pub const MAX_MEMBER_NAME_LENGTH: usize = 64;
+
+/// Someone a vault is shared with
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VaultMember {
+    /// Unique name, used in credential access lists and export file names
+    pub name: String,
+
+    /// The member's public sharing key (`zlpk1…`)
+    pub public_key: String,
+
+    /// When the member was added (Unix timestamp)
+    pub added_at: i64,
+}
+
+impl VaultMember {
+    pub fn new<S: Into<String>>(name: S, recipient: &ShareRecipient) -> Self {
+        Self {
+            name: name.into(),
+            public_key: recipient.to_string(),
+            added_at: chrono::Utc::now().timestamp(),
+        }
+    }
+
+    /// The key credentials are encrypted to
+    pub fn recipient(&self) -> SharingResult<ShareRecipient> {
+        self.public_key.parse()
+    }
+
+    /// Check the name and key
+    ///
+    /// Names are limited to letters, digits, `.`, `_` and `-` so they can be
+    /// used as file names in a synced folder.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.is_empty() || self.name.len() > MAX_MEMBER_NAME_LENGTH {
+            return Err(format!(
+                "Member name must be 1 to {} characters",
+                MAX_MEMBER_NAME_LENGTH
+            ));
+        }
+        if self.name.starts_with('.')
+            || !self
+                .name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))
+        {
+            return Err(format!(
+                "Member name '{}' may only contain letters, digits, '.', '_' and '-'",
+                self.name
+            ));
+        }
+        self.recipient()
+            .map_err(|_| format!("Member '{}' has an invalid public key", self.name))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::sharing::ShareIdentity;
+
+    #[test]
+    fn test_validate() {
+        let recipient = ShareIdentity::generate().recipient();
+        assert!(VaultMember::new("alice", &recipient).validate().is_ok());
+        assert!(VaultMember::new("ops.team-2", &recipient)
+            .validate()
+            .is_ok());
+
+        for name in ["", "../alice", ".hidden", "a b", "x".repeat(65).as_str()] {
+            assert!(
+                VaultMember::new(name, &recipient).validate().is_err(),
+                "{name}"
+            );
+        }
+
+        let mut member = VaultMember::new("alice", &recipient);
+        member.public_key = "zlpk1short".to_string();
+        assert!(member.validate().is_err());
+    }
+}
@@ -7,16 +7,27 @@
 
 pub mod credential;
 pub mod field;
+pub mod folder;
+pub mod member;
+pub mod note;
+pub mod recurring;
 pub mod template;
+pub mod wifi;
 
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize, Serializer};
+use std::collections::{BTreeMap, HashMap};
 
 use uuid::Uuid;
+use zeroize::Zeroize;
 
 pub use credential::*;
 pub use field::*;
+pub use folder::*;
+pub use member::*;
+pub use note::*;
+pub use recurring::*;
 pub use template::*;
+pub use wifi::*;
 
 /// A complete credential record as stored in the archive
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -31,6 +42,7 @@ pub struct CredentialRecord {
     pub credential_type: String,
 
     /// Map of field names to field values
+    #[serde(serialize_with = "serialize_sorted")]
     pub fields: HashMap<String, CredentialField>,
 
     /// Tags for organization and searching
@@ -53,6 +65,61 @@ pub struct CredentialRecord {
 
     /// Optional folder path for organization
     pub folder_path: Option<String>,
+
+    /// Optional icon name, inherited from the folder when not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+
+    /// Optional `#rrggbb` color, inherited from the folder when not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+
+    /// Optional auto-type keystroke sequence (e.g. `{USERNAME}{TAB}{PASSWORD}{ENTER}`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_type: Option<String>,
+
+    /// Optional owner, reviewer and classification for access reviews
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compliance: Option<ComplianceInfo>,
+
+    /// Earlier values of password fields, oldest first; kept by the
+    /// repository, which ignores what callers put here
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub field_history: Vec<FieldHistoryEntry>,
+
+    /// When the credential stops being valid, such as a certificate's or
+    /// passport's expiry (Unix timestamp)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<i64>,
+
+    /// Days after which the password should be changed; counted from the
+    /// last change in `field_history`, or from creation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rotation_days: Option<u32>,
+
+    /// Names of the vault members this credential is shared with
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub shared_with: Vec<String>,
+
+    /// Layout version of the record; records written before it existed
+    /// are version 1
+    #[serde(default = "first_schema_version")]
+    pub schema_version: u32,
+}
+
+/// Record layout version written by this version of ZipLock
+pub const CREDENTIAL_SCHEMA_VERSION: u32 = 1;
+
+fn first_schema_version() -> u32 {
+    1
+}
+
+/// Write a map in key order, so a record always serializes to the same bytes
+fn serialize_sorted<V: Serialize, S: Serializer>(
+    map: &HashMap<String, V>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    map.iter().collect::<BTreeMap<_, _>>().serialize(serializer)
 }
 
 /// A credential field that can hold different types of data
@@ -71,7 +138,41 @@ pub struct CredentialField {
     pub label: Option<String>,
 
     /// Field-specific metadata
+    #[serde(serialize_with = "serialize_sorted")]
     pub metadata: HashMap<String, String>,
+
+    /// Set when the value is encrypted with a passphrase of its own; `value`
+    /// then only holds [`PROTECTED_FIELD_PLACEHOLDER`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub protection: Option<FieldProtection>,
+
+    /// When the value last changed (Unix timestamp); kept by
+    /// [`CredentialRecord::set_field`] and the repository, unknown for
+    /// fields written before it was tracked
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value_updated_at: Option<i64>,
+}
+
+/// What read APIs return as the value of a protected field
+pub const PROTECTED_FIELD_PLACEHOLDER: &str = "[protected]";
+
+/// Envelope of a field value encrypted with a secondary passphrase
+///
+/// The value is sealed with a random field key, and the field key with a key
+/// derived from the passphrase, so changing the passphrase only re-wraps the
+/// field key. All members are base64. See
+/// [`CredentialCrypto`](crate::utils::CredentialCrypto) for the helpers
+/// that create and open it.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct FieldProtection {
+    /// Salt the passphrase key is derived from
+    pub salt: String,
+    pub key_nonce: String,
+    /// Field key sealed with the passphrase key
+    pub wrapped_key: String,
+    pub nonce: String,
+    /// Field value sealed with the field key
+    pub ciphertext: String,
 }
 
 /// Types of fields that can be stored in credentials
@@ -116,6 +217,12 @@ pub enum FieldType {
     /// Date field
     Date,
 
+    /// ASCII-armored PGP secret key block
+    PgpPrivateKey,
+
+    /// ASCII-armored PGP revocation certificate
+    PgpRevocationCertificate,
+
     /// Custom field type
     Custom(String),
 }
@@ -141,6 +248,15 @@ impl CredentialRecord {
             accessed_at: now,
             favorite: false,
             folder_path: None,
+            icon: None,
+            color: None,
+            auto_type: None,
+            compliance: None,
+            field_history: Vec::new(),
+            expires_at: None,
+            rotation_days: None,
+            shared_with: Vec::new(),
+            schema_version: CREDENTIAL_SCHEMA_VERSION,
         }
     }
 
@@ -157,9 +273,18 @@ impl CredentialRecord {
     }
 
     /// Add or update a field
-    pub fn set_field<S: Into<String>>(&mut self, name: S, field: CredentialField) {
-        self.fields.insert(name.into(), field);
-        self.updated_at = chrono::Utc::now().timestamp();
+    ///
+    /// The field's `value_updated_at` is carried over when the value is the
+    /// one already stored, and set to now otherwise.
+    pub fn set_field<S: Into<String>>(&mut self, name: S, mut field: CredentialField) {
+        let name = name.into();
+        let now = chrono::Utc::now().timestamp();
+        field.value_updated_at = match self.fields.get(&name) {
+            Some(old) if old.same_value(&field) => old.value_updated_at,
+            _ => Some(now),
+        };
+        self.fields.insert(name, field);
+        self.updated_at = now;
     }
 
     /// Get a field by name
@@ -198,6 +323,16 @@ impl CredentialRecord {
         self.tags.contains(&tag.to_string())
     }
 
+    /// Whether the vault member `name` may see this credential
+    pub fn is_shared_with(&self, name: &str) -> bool {
+        self.shared_with.iter().any(|member| member == name)
+    }
+
+    /// Compliance metadata, created empty on first use
+    pub fn compliance_mut(&mut self) -> &mut ComplianceInfo {
+        self.compliance.get_or_insert_with(ComplianceInfo::default)
+    }
+
     /// Get all sensitive fields
     pub fn sensitive_fields(&self) -> Vec<(&String, &CredentialField)> {
         self.fields
@@ -248,9 +383,90 @@ impl CredentialRecord {
                 field.value = "***".to_string();
             }
         }
+        for entry in &mut sanitized.field_history {
+            entry.value = "***".to_string();
+        }
 
         sanitized
     }
+
+    /// Earlier values of `field`, most recent first
+    pub fn previous_values(&self, field: &str) -> Vec<&FieldHistoryEntry> {
+        self.field_history
+            .iter()
+            .rev()
+            .filter(|entry| entry.field == field)
+            .collect()
+    }
+
+    /// Add the password values of `previous` that this version changed or
+    /// removed to the history, keeping the newest `limit` per field
+    ///
+    /// Protected fields are skipped; their values are sealed and only the
+    /// placeholder is at hand.
+    pub fn record_field_history(&mut self, previous: &CredentialRecord, at: i64, limit: usize) {
+        let mut names: Vec<&String> = previous.fields.keys().collect();
+        names.sort();
+        for name in names {
+            let old = &previous.fields[name];
+            if old.field_type != FieldType::Password || old.is_protected() || old.value.is_empty() {
+                continue;
+            }
+            let removed = match self.fields.get(name) {
+                Some(new) if new.value == old.value => continue,
+                Some(_) => false,
+                None => true,
+            };
+            self.field_history.push(FieldHistoryEntry {
+                field: name.clone(),
+                value: old.value.clone(),
+                replaced_at: at,
+                removed,
+            });
+        }
+        self.trim_field_history(limit);
+    }
+
+    /// Set `value_updated_at` on each field from `previous`, the version
+    /// this one replaces: kept where the value is the same, `at` where it
+    /// changed or the field is new
+    ///
+    /// Without a previous version, as for a new credential, fields keep a
+    /// time they already carry, such as one from an export, unless it lies
+    /// after `at`.
+    pub fn stamp_field_changes(&mut self, previous: Option<&CredentialRecord>, at: i64) {
+        for (name, field) in self.fields.iter_mut() {
+            field.value_updated_at = match previous {
+                Some(previous) => match previous.fields.get(name) {
+                    Some(old) if old.same_value(field) => old.value_updated_at,
+                    _ => Some(at),
+                },
+                None => Some(field.value_updated_at.filter(|&t| t <= at).unwrap_or(at)),
+            };
+        }
+    }
+
+    /// When the value of `name` last changed, if known
+    pub fn field_changed_at(&self, name: &str) -> Option<i64> {
+        self.fields.get(name)?.value_updated_at
+    }
+
+    /// Drop all but the newest `limit` entries of each field
+    pub fn trim_field_history(&mut self, limit: usize) {
+        let mut kept: HashMap<String, usize> = HashMap::new();
+        let mut newest_first: Vec<FieldHistoryEntry> = self
+            .field_history
+            .drain(..)
+            .rev()
+            .filter(|entry| {
+                let count = kept.entry(entry.field.clone()).or_default();
+                *count += 1;
+                *count <= limit
+            })
+            .collect();
+        newest_first.reverse();
+        self.field_history = newest_first;
+    }
 }
 
 impl CredentialField {
@@ -262,6 +478,8 @@ impl CredentialField {
             sensitive,
             label: None,
             metadata: HashMap::new(),
+            protection: None,
+            value_updated_at: None,
         }
     }
 
@@ -273,6 +491,8 @@ impl CredentialField {
             sensitive: false,
             label: None,
             metadata: HashMap::new(),
+            protection: None,
+            value_updated_at: None,
         }
     }
 
@@ -284,6 +504,8 @@ impl CredentialField {
             sensitive: true,
             label: None,
             metadata: HashMap::new(),
+            protection: None,
+            value_updated_at: None,
         }
     }
 
@@ -295,6 +517,8 @@ impl CredentialField {
             sensitive: false,
             label: None,
             metadata: HashMap::new(),
+            protection: None,
+            value_updated_at: None,
         }
     }
 
@@ -306,6 +530,8 @@ impl CredentialField {
             sensitive: false,
             label: None,
             metadata: HashMap::new(),
+            protection: None,
+            value_updated_at: None,
         }
     }
 
@@ -317,6 +543,8 @@ impl CredentialField {
             sensitive: false,
             label: None,
             metadata: HashMap::new(),
+            protection: None,
+            value_updated_at: None,
         }
     }
 
@@ -328,6 +556,8 @@ impl CredentialField {
             sensitive: true,
             label: None,
             metadata: HashMap::new(),
+            protection: None,
+            value_updated_at: None,
         }
     }
 
@@ -353,8 +583,24 @@ impl CredentialField {
         self
     }
 
+    /// Whether the value is encrypted with a passphrase of its own
+    pub fn is_protected(&self) -> bool {
+        self.protection.is_some()
+    }
+
     /// Validate this field
     pub fn validate(&self) -> Result<(), Vec<String>> {
+        // The real value is sealed and was validated before it was protected
+        if self.is_protected() {
+            return if self.value == PROTECTED_FIELD_PLACEHOLDER {
+                Ok(())
+            } else {
+                Err(vec![
+                    "Protected fields must be unprotected before they are edited".to_string(),
+                ])
+            };
+        }
+
         let mut errors = Vec::new();
 
         // Basic validation based on field type
@@ -386,9 +632,21 @@ impl CredentialField {
         }
     }
 
+    /// Whether `other` holds the same value; protecting or unprotecting a
+    /// field does not change it, re-sealing it with a new value does
+    pub fn same_value(&self, other: &CredentialField) -> bool {
+        match (&self.protection, &other.protection) {
+            (Some(a), Some(b)) => a == b,
+            (None, None) => self.value == other.value,
+            _ => true,
+        }
+    }
+
     /// Get a display-safe version of the value
     pub fn display_value(&self) -> String {
-        if self.sensitive {
+        if self.is_protected() {
+            PROTECTED_FIELD_PLACEHOLDER.to_string()
+        } else if self.sensitive {
             "***".to_string()
         } else {
             self.value.clone()
@@ -396,6 +654,16 @@ impl CredentialField {
     }
 }
 
+/// Wipes the value, so a field's plaintext does not outlive it on the heap
+///
+/// Copies made earlier, e.g. when the `String` grew or was cloned for
+/// display, are not covered.
+impl Drop for CredentialField {
+    fn drop(&mut self) {
+        self.value.zeroize();
+    }
+}
+
 impl Default for CredentialField {
     fn default() -> Self {
         Self {
@@ -404,6 +672,8 @@ impl Default for CredentialField {
             sensitive: false,
             label: None,
             metadata: HashMap::new(),
+            protection: None,
+            value_updated_at: None,
         }
     }
 }
@@ -425,6 +695,8 @@ impl FieldType {
             FieldType::TextArea,
             FieldType::Number,
             FieldType::Date,
+            FieldType::PgpPrivateKey,
+            FieldType::PgpRevocationCertificate,
         ]
     }
 
@@ -444,6 +716,8 @@ impl FieldType {
             FieldType::TextArea => "Text Area",
             FieldType::Number => "Number",
             FieldType::Date => "Date",
+            FieldType::PgpPrivateKey => "PGP Private Key",
+            FieldType::PgpRevocationCertificate => "PGP Revocation Certificate",
             FieldType::Custom(name) => name,
         }
     }
@@ -456,6 +730,16 @@ impl FieldType {
                 | FieldType::CreditCardNumber
                 | FieldType::Cvv
                 | FieldType::TotpSecret
+                | FieldType::PgpPrivateKey
+                | FieldType::PgpRevocationCertificate
+        )
+    }
+
+    /// Whether values span several lines and need a multi-line editor
+    pub fn is_multiline(&self) -> bool {
+        matches!(
+            self,
+            FieldType::TextArea | FieldType::PgpPrivateKey | FieldType::PgpRevocationCertificate
         )
     }
 }
@@ -534,6 +818,36 @@ mod tests {
         assert!(cred.get_field("username").is_none());
     }
 
+    #[test]
+    fn test_field_change_times() {
+        let mut cred = CredentialRecord::new("Test".to_string(), "login".to_string());
+        cred.set_field("password", CredentialField::password("first"));
+        let changed = cred.field_changed_at("password").unwrap();
+
+        // Setting the same value again keeps the time
+        cred.fields.get_mut("password").unwrap().value_updated_at = Some(changed - 100);
+        cred.set_field("password", CredentialField::password("first"));
+        assert_eq!(cred.field_changed_at("password"), Some(changed - 100));
+
+        let previous = cred.clone();
+        cred.fields.get_mut("password").unwrap().value = "second".to_string();
+        cred.set_field("username", CredentialField::username("someone"));
+        cred.stamp_field_changes(Some(&previous), changed + 50);
+        assert_eq!(cred.field_changed_at("password"), Some(changed + 50));
+        assert_eq!(cred.field_changed_at("username"), Some(changed + 50));
+
+        let mut protected = cred.get_field("password").unwrap().clone();
+        protected.value.clear();
+        protected.protection = Some(FieldProtection {
+            salt: "s".to_string(),
+            key_nonce: "k".to_string(),
+            wrapped_key: "w".to_string(),
+            nonce: "n".to_string(),
+            ciphertext: "c".to_string(),
+        });
+        assert!(cred.get_field("password").unwrap().same_value(&protected));
+    }
+
     #[test]
     fn test_field_validation() {
         // Valid email
@@ -0,0 +1,170 @@
+//! Long-form note content
+//!
+//! Multi-line text fields, such as the content of a secure note, may be
+//! written as plain text or as Markdown; the format is kept in the field's
+//! metadata under [`CONTENT_TYPE_KEY`]. Bodies longer than
+//! [`NOTE_INLINE_LENGTH`](crate::core::types::NOTE_INLINE_LENGTH) are written
+//! to an archive entry of their own, named in the field's metadata under
+//! [`NOTE_FILE_KEY`], and are only decoded when the credential is opened.
+
+use serde::{Deserialize, Serialize};
+
+use super::CredentialField;
+
+/// Field metadata key holding the MIME type of a note body
+pub const CONTENT_TYPE_KEY: &str = "content_type";
+
+/// Field metadata key naming the archive entry a note body is stored in,
+/// relative to the credential's directory
+pub const NOTE_FILE_KEY: &str = "note_file";
+
+/// How the text of a note is meant to be read
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NoteFormat {
+    #[default]
+    Plain,
+    Markdown,
+}
+
+impl NoteFormat {
+    pub const ALL: [NoteFormat; 2] = [NoteFormat::Plain, NoteFormat::Markdown];
+
+    /// MIME type stored in the field metadata
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            NoteFormat::Plain => "text/plain",
+            NoteFormat::Markdown => "text/markdown",
+        }
+    }
+
+    /// The format of a stored MIME type; anything unknown is plain text
+    pub fn from_content_type(content_type: &str) -> Self {
+        match content_type.trim().to_ascii_lowercase().as_str() {
+            "text/markdown" | "text/x-markdown" => NoteFormat::Markdown,
+            _ => NoteFormat::Plain,
+        }
+    }
+
+    /// Extension of the archive entry a large body is written to
+    pub fn extension(&self) -> &'static str {
+        match self {
+            NoteFormat::Plain => "txt",
+            NoteFormat::Markdown => "md",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            NoteFormat::Plain => "Plain text",
+            NoteFormat::Markdown => "Markdown",
+        }
+    }
+}
+
+impl std::fmt::Display for NoteFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.label())
+    }
+}
+
+impl CredentialField {
+    /// The format of the field's text; plain unless set otherwise
+    pub fn note_format(&self) -> NoteFormat {
+        self.metadata
+            .get(CONTENT_TYPE_KEY)
+            .map(|content_type| NoteFormat::from_content_type(content_type))
+            .unwrap_or_default()
+    }
+
+    /// Set the format of the field's text; plain text is not recorded
+    pub fn set_note_format(&mut self, format: NoteFormat) {
+        match format {
+            NoteFormat::Plain => {
+                self.metadata.remove(CONTENT_TYPE_KEY);
+            }
+            format => {
+                self.metadata.insert(
+                    CONTENT_TYPE_KEY.to_string(),
+                    format.content_type().to_string(),
+                );
+            }
+        }
+    }
+
+    /// The archive entry holding the body, while it has not been loaded
+    pub fn note_file(&self) -> Option<&str> {
+        self.metadata.get(NOTE_FILE_KEY).map(String::as_str)
+    }
+
+    /// Whether the body is still in the archive and `value` is empty
+    pub fn is_note_unloaded(&self) -> bool {
+        self.note_file().is_some()
+    }
+}
+
+/// Name of the archive entry for the body of `field_name`, unique among
+/// `taken`
+pub fn note_file_name(field_name: &str, format: NoteFormat, taken: &[String]) -> String {
+    let stem: String = field_name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let mut name = format!("{}.{}", stem, format.extension());
+    let mut n = 2;
+    while taken.contains(&name) {
+        name = format!("{}-{}.{}", stem, n, format.extension());
+        n += 1;
+    }
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::FieldType;
+
+    #[test]
+    fn test_note_format_metadata() {
+        let mut field = CredentialField::new(FieldType::TextArea, "# Wi-Fi".to_string(), false);
+        assert_eq!(field.note_format(), NoteFormat::Plain);
+
+        field.set_note_format(NoteFormat::Markdown);
+        assert_eq!(field.metadata[CONTENT_TYPE_KEY], "text/markdown");
+        assert_eq!(field.note_format(), NoteFormat::Markdown);
+        field.set_note_format(NoteFormat::Plain);
+        assert!(field.metadata.is_empty());
+
+        assert_eq!(
+            NoteFormat::from_content_type("Text/X-Markdown"),
+            NoteFormat::Markdown
+        );
+        assert_eq!(
+            NoteFormat::from_content_type("text/html"),
+            NoteFormat::Plain
+        );
+    }
+
+    #[test]
+    fn test_note_file_names() {
+        assert_eq!(
+            note_file_name("content", NoteFormat::Markdown, &[]),
+            "content.md"
+        );
+        assert_eq!(
+            note_file_name("../notes", NoteFormat::Plain, &[]),
+            "___notes.txt"
+        );
+        let taken = vec!["a_b.txt".to_string()];
+        assert_eq!(
+            note_file_name("a b", NoteFormat::Plain, &taken),
+            "a_b-2.txt"
+        );
+    }
+}
@@ -0,0 +1,313 @@
+//! Credentials created again on a schedule
+//!
+//! A [`RecurringCredential`] stored in the vault names a template and an
+//! interval, e.g. a new door code every month. Each time it falls due the
+//! repository creates a draft from the template, tagged [`INBOX_TAG`], for
+//! the user to fill in; removing the tag files it like any other credential.
+
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{DateTime, Months, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::template::CredentialTemplate;
+use super::CredentialRecord;
+
+/// Tag of drafts waiting to be filled in
+pub const INBOX_TAG: &str = "inbox";
+
+/// How often a recurring credential falls due
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "unit", content = "every", rename_all = "snake_case")]
+pub enum RecurrenceInterval {
+    Days(u32),
+    Weeks(u32),
+    Months(u32),
+}
+
+impl RecurrenceInterval {
+    /// The time one interval after `from`; month steps keep the day of the
+    /// month where it exists and use the month's last day otherwise
+    pub fn after(&self, from: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match *self {
+            RecurrenceInterval::Days(days) => {
+                from.checked_add_signed(TimeDelta::try_days(i64::from(days))?)
+            }
+            RecurrenceInterval::Weeks(weeks) => {
+                from.checked_add_signed(TimeDelta::try_weeks(i64::from(weeks))?)
+            }
+            RecurrenceInterval::Months(months) => from.checked_add_months(Months::new(months)),
+        }
+    }
+
+    fn count(&self) -> u32 {
+        match *self {
+            RecurrenceInterval::Days(n)
+            | RecurrenceInterval::Weeks(n)
+            | RecurrenceInterval::Months(n) => n,
+        }
+    }
+}
+
+impl fmt::Display for RecurrenceInterval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            RecurrenceInterval::Days(n) => write!(f, "{}d", n),
+            RecurrenceInterval::Weeks(n) => write!(f, "{}w", n),
+            RecurrenceInterval::Months(n) => write!(f, "{}m", n),
+        }
+    }
+}
+
+/// Parses the [`Display`](fmt::Display) form, e.g. `30d`, `2w` or `1m`
+impl FromStr for RecurrenceInterval {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let invalid = || format!("'{}' is not an interval like 30d, 2w or 1m", s);
+        let unit = s.chars().last().ok_or_else(invalid)?;
+        let count: u32 = s[..s.len() - unit.len_utf8()]
+            .parse()
+            .map_err(|_| invalid())?;
+        match unit.to_ascii_lowercase() {
+            'd' => Ok(RecurrenceInterval::Days(count)),
+            'w' => Ok(RecurrenceInterval::Weeks(count)),
+            'm' => Ok(RecurrenceInterval::Months(count)),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// A credential created from a template each time it falls due
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecurringCredential {
+    pub id: String,
+
+    /// Name of the built-in or user-defined template drafts are made from
+    pub template: String,
+
+    /// Title of each draft; `{date}`, `{month}` and `{year}` are replaced
+    /// from the day it fell due, e.g. `Door code {month} {year}`
+    pub title: String,
+
+    pub interval: RecurrenceInterval,
+
+    /// When the next draft is due (Unix timestamp)
+    pub next_due: i64,
+
+    /// Folder drafts are created in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub folder_path: Option<String>,
+
+    /// Tags added to each draft besides [`INBOX_TAG`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+
+    /// Copy the non-sensitive values of the previous draft, e.g. which door
+    /// the code is for; sensitive fields always start empty
+    #[serde(default)]
+    pub carry_over: bool,
+
+    /// The draft created last, if it still exists
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_credential_id: Option<String>,
+}
+
+/// A draft created for a recurring credential
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecurringDraft {
+    pub recurring_id: String,
+    pub credential_id: String,
+    pub title: String,
+}
+
+impl RecurringCredential {
+    /// A definition whose first draft is due at `first_due`
+    pub fn new<T: Into<String>, U: Into<String>>(
+        template: T,
+        title: U,
+        interval: RecurrenceInterval,
+        first_due: i64,
+    ) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            template: template.into(),
+            title: title.into(),
+            interval,
+            next_due: first_due,
+            folder_path: None,
+            tags: Vec::new(),
+            carry_over: false,
+            last_credential_id: None,
+        }
+    }
+
+    pub fn with_folder<S: Into<String>>(mut self, folder_path: S) -> Self {
+        self.folder_path = Some(folder_path.into());
+        self
+    }
+
+    pub fn with_tags(mut self, tags: Vec<String>) -> Self {
+        self.tags = tags;
+        self
+    }
+
+    pub fn with_carry_over(mut self, carry_over: bool) -> Self {
+        self.carry_over = carry_over;
+        self
+    }
+
+    /// Check the definition before it is stored
+    pub fn validate(&self) -> Result<(), String> {
+        if self.template.trim().is_empty() {
+            return Err("A recurring credential needs a template".to_string());
+        }
+        if self.title.trim().is_empty() {
+            return Err("A recurring credential needs a title".to_string());
+        }
+        if self.interval.count() == 0 {
+            return Err("The interval must be at least 1".to_string());
+        }
+        if DateTime::from_timestamp(self.next_due, 0).is_none() {
+            return Err(format!("{} is not a valid due date", self.next_due));
+        }
+        Ok(())
+    }
+
+    pub fn is_due(&self, now: i64) -> bool {
+        self.next_due <= now
+    }
+
+    /// The title of the draft due at `due`
+    pub fn title_for(&self, due: i64) -> String {
+        let Some(due) = DateTime::from_timestamp(due, 0) else {
+            return self.title.clone();
+        };
+        self.title
+            .replace("{date}", &due.format("%Y-%m-%d").to_string())
+            .replace("{month}", &due.format("%B").to_string())
+            .replace("{year}", &due.format("%Y").to_string())
+    }
+
+    /// Move `next_due` past `now`
+    ///
+    /// Periods missed while the vault was closed are skipped, so one draft is
+    /// made however long it has been.
+    pub fn advance(&mut self, now: i64) {
+        let Some(mut due) = DateTime::from_timestamp(self.next_due, 0) else {
+            return;
+        };
+        while due.timestamp() <= now {
+            match self.interval.after(due) {
+                Some(next) if next > due => due = next,
+                _ => return,
+            }
+        }
+        self.next_due = due.timestamp();
+    }
+
+    /// The draft for the period due now, made from `template`
+    pub fn create_draft(
+        &self,
+        template: &CredentialTemplate,
+        previous: Option<&CredentialRecord>,
+    ) -> Result<CredentialRecord, String> {
+        let mut draft = template.create_credential(self.title_for(self.next_due))?;
+        draft.folder_path = self.folder_path.clone();
+        for tag in &self.tags {
+            draft.add_tag(tag.clone());
+        }
+        draft.add_tag(INBOX_TAG);
+
+        if let Some(previous) = previous.filter(|_| self.carry_over) {
+            for (name, field) in draft.fields.iter_mut() {
+                let Some(old) = previous.fields.get(name) else {
+                    continue;
+                };
+                if !field.sensitive && !old.sensitive && !old.is_protected() {
+                    field.value = old.value.clone();
+                }
+            }
+        }
+        Ok(draft)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{CommonTemplates, CredentialField};
+    use chrono::TimeZone;
+
+    fn at(year: i32, month: u32, day: u32) -> i64 {
+        Utc.with_ymd_and_hms(year, month, day, 9, 0, 0)
+            .unwrap()
+            .timestamp()
+    }
+
+    #[test]
+    fn test_advance_skips_missed_periods() {
+        let mut recurring = RecurringCredential::new(
+            "secure_note",
+            "Door code {month} {year}",
+            RecurrenceInterval::Months(1),
+            at(2026, 1, 31),
+        );
+        assert!(recurring.validate().is_ok());
+        assert_eq!(
+            recurring.title_for(recurring.next_due),
+            "Door code January 2026"
+        );
+
+        recurring.advance(at(2026, 1, 31));
+        assert_eq!(recurring.next_due, at(2026, 2, 28));
+        recurring.advance(at(2026, 5, 1));
+        assert_eq!(recurring.next_due, at(2026, 5, 28));
+        assert!(!recurring.is_due(at(2026, 5, 27)));
+
+        let mut weekly = RecurringCredential::new(
+            "login",
+            "Guest Wi-Fi {date}",
+            RecurrenceInterval::Weeks(2),
+            0,
+        );
+        weekly.advance(0);
+        assert_eq!(weekly.next_due, 14 * 86_400);
+        weekly.interval = RecurrenceInterval::Days(0);
+        assert!(weekly.validate().is_err());
+
+        assert_eq!("2w".parse(), Ok(RecurrenceInterval::Weeks(2)));
+        assert_eq!(RecurrenceInterval::Months(3).to_string(), "3m");
+        assert!("w".parse::<RecurrenceInterval>().is_err());
+        assert!("2y".parse::<RecurrenceInterval>().is_err());
+    }
+
+    #[test]
+    fn test_draft_carries_over_plain_values() {
+        let recurring = RecurringCredential::new(
+            "login",
+            "Door code {month}",
+            RecurrenceInterval::Months(1),
+            at(2026, 3, 1),
+        )
+        .with_folder("Home")
+        .with_tags(vec!["door".to_string()])
+        .with_carry_over(true);
+        let template = CommonTemplates::login();
+
+        let mut previous = recurring.create_draft(&template, None).unwrap();
+        previous.set_field("username", CredentialField::username("front door"));
+        previous.set_field("password", CredentialField::password("4821"));
+
+        let draft = recurring.create_draft(&template, Some(&previous)).unwrap();
+        assert_eq!(draft.title, "Door code March");
+        assert_eq!(draft.folder_path.as_deref(), Some("Home"));
+        assert!(draft
+            .tags
+            .ends_with(&["door".to_string(), INBOX_TAG.to_string()]));
+        assert_eq!(draft.get_field("username").unwrap().value, "front door");
+        assert_eq!(draft.get_field("password").unwrap().value, "");
+    }
+}
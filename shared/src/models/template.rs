@@ -2,9 +2,13 @@
 //!
 //! This module provides template functionality for creating standardized
 //! credential types with predefined fields and validation rules.
+//!
+//! Besides the built-in [`CommonTemplates`], users can design their own
+//! templates. Those are stored in the archive under `templates/` and looked
+//! up before the built-in ones with [`find_template`].
 
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 use super::{CredentialField, CredentialRecord, FieldType};
 use crate::core::types::{MAX_FIELDS_PER_CREDENTIAL, MAX_TAGS_PER_CREDENTIAL};
@@ -25,6 +29,9 @@ pub struct CredentialTemplate {
     pub default_tags: Vec<String>,
 }
 
+/// User-defined templates, keyed by name
+pub type TemplateMap = BTreeMap<String, CredentialTemplate>;
+
 /// Template for individual fields
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct FieldTemplate {
@@ -128,6 +135,8 @@ impl CredentialTemplate {
                 sensitive: field_template.sensitive,
                 label: Some(field_template.label.clone()),
                 metadata: HashMap::new(),
+                protection: None,
+                value_updated_at: None,
             };
 
             credential.set_field(&field_template.name, field);
@@ -215,6 +224,74 @@ impl CredentialTemplate {
             .join(" ")
     }
 
+    /// Check a user-defined template, returning one message per problem
+    ///
+    /// The name becomes a file name in the archive, so it is limited to
+    /// lowercase letters, digits, `_` and `-`. Field names must be unique
+    /// ignoring case and surrounding whitespace, since credentials created
+    /// from the template store one value per name.
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = Vec::new();
+        if self.name.is_empty() {
+            errors.push("Template name cannot be empty".to_string());
+        } else if !self
+            .name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-')
+        {
+            errors.push(format!(
+                "Template name '{}' may only contain lowercase letters, digits, '_' and '-'",
+                self.name
+            ));
+        }
+        if self.fields.is_empty() {
+            errors.push("Template needs at least one field".to_string());
+        }
+        if self.fields.len() > MAX_FIELDS_PER_CREDENTIAL {
+            errors.push(format!(
+                "Cannot have more than {} fields in a template",
+                MAX_FIELDS_PER_CREDENTIAL
+            ));
+        }
+        if self.default_tags.len() > MAX_TAGS_PER_CREDENTIAL {
+            errors.push(format!(
+                "Cannot have more than {} tags in a template",
+                MAX_TAGS_PER_CREDENTIAL
+            ));
+        }
+
+        let mut seen = HashSet::new();
+        for field in &self.fields {
+            let key = field.name.trim().to_lowercase();
+            if key.is_empty() {
+                errors.push(format!("Field '{}' needs a name", field.label));
+            } else if !seen.insert(key) {
+                errors.push(format!(
+                    "Field name '{}' is used more than once",
+                    field.name.trim()
+                ));
+            }
+            if let Some(pattern) = field
+                .validation
+                .as_ref()
+                .and_then(|validation| validation.pattern.as_deref())
+            {
+                if regex::Regex::new(pattern).is_err() {
+                    errors.push(format!(
+                        "Field '{}' has an invalid pattern '{}'",
+                        field.name, pattern
+                    ));
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
     /// Get field template by name
     pub fn get_field_template(&self, name: &str) -> Option<&FieldTemplate> {
         self.fields.iter().find(|f| f.name == name)
@@ -511,6 +588,11 @@ impl CommonTemplates {
     }
 
     /// Wi-Fi credentials template
+    ///
+    /// `security` is one of the names [`WifiSecurity`](super::WifiSecurity)
+    /// accepts, and `hidden` is `true` for networks that do not broadcast
+    /// their SSID. [`WifiNetwork`](super::WifiNetwork) reads these fields
+    /// to build the `WIFI:` payload guests scan to join.
     pub fn wifi() -> CredentialTemplate {
         let mut template = CredentialTemplate::new("wifi", "Wi-Fi network credentials");
 
@@ -523,22 +605,38 @@ impl CommonTemplates {
             ))
             .unwrap();
 
+        // Open networks have no password
         template
             .add_field(FieldTemplate::new(
                 "password",
                 "Password",
                 FieldType::Password,
-                true,
+                false,
             ))
             .unwrap();
 
         template
-            .add_field(FieldTemplate::new(
-                "security",
-                "Security Type",
-                FieldType::Text,
-                false,
-            ))
+            .add_field(
+                FieldTemplate::new("security", "Security Type", FieldType::Text, false)
+                    .default_value("WPA")
+                    .validation(
+                        FieldValidation::new()
+                            .pattern(r"(?i)^(|wpa|wpa2|wpa3|sae|wep|nopass|none|open)$")
+                            .message("Must be WPA, WPA2, WPA3, WEP or none"),
+                    ),
+            )
+            .unwrap();
+
+        template
+            .add_field(
+                FieldTemplate::new("hidden", "Hidden Network", FieldType::Text, false)
+                    .default_value("false")
+                    .validation(
+                        FieldValidation::new()
+                            .pattern(r"^(|true|false)$")
+                            .message("Must be true or false"),
+                    ),
+            )
             .unwrap();
 
         template.add_tag("wifi").unwrap();
@@ -703,6 +801,11 @@ impl CommonTemplates {
     }
 
     /// SSH key template
+    ///
+    /// `private_key` holds an OpenSSH private key, encrypted with
+    /// `passphrase` or not; the ssh-agent serves keys stored this way.
+    /// Username and hostname are optional because an agent key is usually
+    /// not tied to one server.
     pub fn ssh_key() -> CredentialTemplate {
         let mut template = CredentialTemplate::new("ssh_key", "SSH key credentials");
 
@@ -711,7 +814,7 @@ impl CommonTemplates {
                 "username",
                 "Username",
                 FieldType::Username,
-                true,
+                false,
             ))
             .unwrap();
 
@@ -720,7 +823,7 @@ impl CommonTemplates {
                 "hostname",
                 "Hostname/Server",
                 FieldType::Text,
-                true,
+                false,
             ))
             .unwrap();
 
@@ -742,12 +845,108 @@ impl CommonTemplates {
             ))
             .unwrap();
 
+        template
+            .add_field(FieldTemplate::new(
+                "public_key",
+                "Public Key",
+                FieldType::TextArea,
+                false,
+            ))
+            .unwrap();
+
+        template
+            .add_field(FieldTemplate::new(
+                "comment",
+                "Key Comment",
+                FieldType::Text,
+                false,
+            ))
+            .unwrap();
+
         template.add_tag("ssh").unwrap();
         template.add_tag("server").unwrap();
 
         template
     }
 
+    /// PGP key template
+    ///
+    /// Keeps a backup of a PGP signing key: the armored secret key as
+    /// `gpg --export-secret-keys --armor` writes it, its passphrase, the public
+    /// key and the revocation certificate from `openpgp-revocs.d`.
+    /// [`PgpKeyExporter`](crate::utils::PgpKeyExporter) writes them back out.
+    pub fn pgp_key() -> CredentialTemplate {
+        let mut template = CredentialTemplate::new("pgp_key", "PGP key pair backup");
+
+        template
+            .add_field(FieldTemplate::new(
+                "user_id",
+                "User ID",
+                FieldType::Text,
+                false,
+            ))
+            .unwrap();
+
+        template
+            .add_field(FieldTemplate::new(
+                "fingerprint",
+                "Fingerprint",
+                FieldType::Text,
+                false,
+            ))
+            .unwrap();
+
+        template
+            .add_field(FieldTemplate::new(
+                "private_key",
+                "Private Key",
+                FieldType::PgpPrivateKey,
+                true,
+            ))
+            .unwrap();
+
+        template
+            .add_field(FieldTemplate::new(
+                "passphrase",
+                "Key Passphrase",
+                FieldType::Password,
+                false,
+            ))
+            .unwrap();
+
+        template
+            .add_field(FieldTemplate::new(
+                "public_key",
+                "Public Key",
+                FieldType::TextArea,
+                false,
+            ))
+            .unwrap();
+
+        template
+            .add_field(FieldTemplate::new(
+                "revocation_certificate",
+                "Revocation Certificate",
+                FieldType::PgpRevocationCertificate,
+                false,
+            ))
+            .unwrap();
+
+        template
+            .add_field(FieldTemplate::new(
+                "expires",
+                "Expires",
+                FieldType::Date,
+                false,
+            ))
+            .unwrap();
+
+        template.add_tag("pgp").unwrap();
+        template.add_tag("gpg").unwrap();
+
+        template
+    }
+
     /// Bank account template
     pub fn bank_account() -> CredentialTemplate {
         let mut template = CredentialTemplate::new("bank_account", "Bank account credentials");
@@ -942,6 +1141,7 @@ impl CommonTemplates {
             Self::password(),
             Self::document(),
             Self::ssh_key(),
+            Self::pgp_key(),
             Self::bank_account(),
             Self::api_credentials(),
             Self::crypto_wallet(),
@@ -955,6 +1155,14 @@ impl CommonTemplates {
     }
 }
 
+/// Look up `name` among the user-defined `templates`, then the built-in ones
+pub fn find_template(templates: &TemplateMap, name: &str) -> Option<CredentialTemplate> {
+    templates
+        .get(name)
+        .cloned()
+        .or_else(|| CommonTemplates::get_by_name(name))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1126,4 +1334,44 @@ mod tests {
             validation_result
         );
     }
+
+    #[test]
+    fn test_custom_template_validation() {
+        let mut template = CredentialTemplate::new("vpn", "VPN account");
+        template.fields = vec![
+            FieldTemplate::new("server", "Server", FieldType::Url, true),
+            FieldTemplate::new("password", "Password", FieldType::Password, true),
+        ];
+        assert!(template.validate().is_ok());
+        for built_in in CommonTemplates::all() {
+            assert!(built_in.validate().is_ok(), "{}", built_in.name);
+        }
+
+        template.fields.push(FieldTemplate::new(
+            " Server ",
+            "Backup server",
+            FieldType::Url,
+            false,
+        ));
+        template.fields.push(
+            FieldTemplate::new("pin", "PIN", FieldType::Number, false)
+                .validation(FieldValidation::new().pattern("[0-9")),
+        );
+        template.name = "My VPN".to_string();
+        let errors = template.validate().unwrap_err();
+        assert_eq!(errors.len(), 3, "{:?}", errors);
+        assert!(errors[0].contains("lowercase"));
+        assert!(errors[1].contains("'Server' is used more than once"));
+        assert!(errors[2].contains("invalid pattern"));
+
+        let mut templates = TemplateMap::new();
+        template.name = "vpn".to_string();
+        templates.insert(template.name.clone(), template.clone());
+        assert_eq!(find_template(&templates, "vpn"), Some(template));
+        assert_eq!(
+            find_template(&templates, "wifi"),
+            Some(CommonTemplates::wifi())
+        );
+        assert_eq!(find_template(&templates, "nope"), None);
+    }
 }
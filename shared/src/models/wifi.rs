@@ -0,0 +1,201 @@
+//! Wi-Fi networks and the `WIFI:` join payload
+//!
+//! Phones join a network by scanning a QR code holding
+//! `WIFI:T:WPA;S:<ssid>;P:<password>;H:true;;`, where `\`, `;`, `,`, `:`
+//! and `"` in the SSID and password are escaped with a backslash. A
+//! [`WifiNetwork`] is read from a credential made with the `wifi` template;
+//! [`QrCode::for_wifi`](crate::utils::QrCode::for_wifi) turns it into the
+//! QR code.
+
+use serde::{Deserialize, Serialize};
+
+use super::CredentialRecord;
+
+/// How a Wi-Fi network is secured
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WifiSecurity {
+    /// WPA or WPA2 personal
+    #[default]
+    Wpa,
+    /// WPA3 personal (SAE)
+    Wpa3,
+    Wep,
+    /// Open network without a password
+    None,
+}
+
+impl WifiSecurity {
+    /// Parse the `security` field of a Wi-Fi credential
+    ///
+    /// Accepts `WPA`, `WPA2`, `WPA3`, `SAE`, `WEP` and `none`, `open` or
+    /// `nopass` for open networks, in any case.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "wpa" | "wpa2" | "wpa/wpa2" => Some(WifiSecurity::Wpa),
+            "wpa3" | "sae" => Some(WifiSecurity::Wpa3),
+            "wep" => Some(WifiSecurity::Wep),
+            "none" | "open" | "nopass" => Some(WifiSecurity::None),
+            _ => None,
+        }
+    }
+
+    /// Value of the `T:` element of the join payload
+    pub fn payload_type(&self) -> &'static str {
+        match self {
+            WifiSecurity::Wpa => "WPA",
+            WifiSecurity::Wpa3 => "SAE",
+            WifiSecurity::Wep => "WEP",
+            WifiSecurity::None => "nopass",
+        }
+    }
+}
+
+/// The details needed to join a Wi-Fi network
+#[derive(Clone, PartialEq, Eq)]
+pub struct WifiNetwork {
+    pub ssid: String,
+    pub security: WifiSecurity,
+    /// Empty for open networks
+    pub password: String,
+    /// Whether the network does not broadcast its SSID
+    pub hidden: bool,
+}
+
+impl std::fmt::Debug for WifiNetwork {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WifiNetwork")
+            .field("ssid", &self.ssid)
+            .field("security", &self.security)
+            .field("hidden", &self.hidden)
+            .finish_non_exhaustive()
+    }
+}
+
+impl WifiNetwork {
+    pub fn new<S: Into<String>>(ssid: S, security: WifiSecurity, password: S) -> Self {
+        Self {
+            ssid: ssid.into(),
+            security,
+            password: password.into(),
+            hidden: false,
+        }
+    }
+
+    pub fn hidden(mut self, hidden: bool) -> Self {
+        self.hidden = hidden;
+        self
+    }
+
+    /// Read the fields of a credential made with the `wifi` template
+    ///
+    /// Returns `None` without an SSID. A missing or unknown security type
+    /// means WPA, or an open network when there is no password.
+    pub fn from_credential(credential: &CredentialRecord) -> Option<Self> {
+        let value = |name: &str| {
+            credential
+                .get_field(name)
+                .map(|field| field.value.as_str())
+                .unwrap_or_default()
+        };
+        let ssid = value("ssid");
+        if ssid.is_empty() {
+            return None;
+        }
+        let password = value("password");
+        let security =
+            WifiSecurity::from_name(value("security")).unwrap_or(if password.is_empty() {
+                WifiSecurity::None
+            } else {
+                WifiSecurity::Wpa
+            });
+        let hidden = value("hidden").trim().eq_ignore_ascii_case("true");
+
+        Some(Self {
+            ssid: ssid.to_string(),
+            security,
+            password: password.to_string(),
+            hidden,
+        })
+    }
+
+    /// The `WIFI:` payload phones join the network from
+    pub fn to_payload(&self) -> String {
+        let mut payload = format!(
+            "WIFI:T:{};S:{};",
+            self.security.payload_type(),
+            escape(&self.ssid)
+        );
+        if self.security != WifiSecurity::None {
+            payload.push_str(&format!("P:{};", escape(&self.password)));
+        }
+        if self.hidden {
+            payload.push_str("H:true;");
+        }
+        payload.push(';');
+        payload
+    }
+}
+
+/// Backslash-escape the characters with a meaning in the payload
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '\\' | ';' | ',' | ':' | '"') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{CommonTemplates, CredentialField};
+
+    #[test]
+    fn test_payload() {
+        let network = WifiNetwork::new("Home", WifiSecurity::Wpa, "secret");
+        assert_eq!(network.to_payload(), "WIFI:T:WPA;S:Home;P:secret;;");
+
+        let network =
+            WifiNetwork::new(r#"Cafe; "Free""#, WifiSecurity::Wpa3, r"a:b,c\d").hidden(true);
+        assert_eq!(
+            network.to_payload(),
+            r#"WIFI:T:SAE;S:Cafe\; \"Free\";P:a\:b\,c\\d;H:true;;"#
+        );
+
+        let open = WifiNetwork::new("Guest", WifiSecurity::None, "ignored");
+        assert_eq!(open.to_payload(), "WIFI:T:nopass;S:Guest;;");
+        assert!(!format!("{:?}", network).contains("a:b"));
+    }
+
+    #[test]
+    fn test_from_template_credential() {
+        let template = CommonTemplates::wifi();
+        let mut credential = template.create_credential("Home".to_string()).unwrap();
+        assert_eq!(WifiNetwork::from_credential(&credential), None);
+
+        credential.set_field("ssid", CredentialField::text("Home"));
+        credential.set_field("password", CredentialField::password("secret"));
+        assert!(template.validate_credential(&credential).is_ok());
+        let network = WifiNetwork::from_credential(&credential).unwrap();
+        assert_eq!(
+            network,
+            WifiNetwork::new("Home", WifiSecurity::Wpa, "secret")
+        );
+
+        credential.set_field("security", CredentialField::text("wpa3"));
+        credential.set_field("hidden", CredentialField::text("true"));
+        let network = WifiNetwork::from_credential(&credential).unwrap();
+        assert_eq!(network.security, WifiSecurity::Wpa3);
+        assert!(network.hidden);
+
+        // No password and no recognizable security type: an open network
+        credential.set_field("security", CredentialField::text("WPA-Enterprise"));
+        credential.set_field("password", CredentialField::password(""));
+        let network = WifiNetwork::from_credential(&credential).unwrap();
+        assert_eq!(network.security, WifiSecurity::None);
+    }
+}
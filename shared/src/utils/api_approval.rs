@@ -0,0 +1,184 @@
+//! Interactive approval of scripting API reads
+//!
+//! A token's scopes say what a script may do in general. Some credentials,
+//! such as bank logins, should additionally not leave the vault without the
+//! user noticing. An [`ApprovalPolicy`] names those credentials by tag, and
+//! can require approval for every read of sensitive values. The app serving
+//! the API turns each such read into an [`ApprovalRequest`] shown to the
+//! user, who answers with an [`ApprovalDecision`]. "Allow always" is kept as
+//! an [`ApprovalGrant`] in the policy so the same script is not asked again.
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use crate::models::CredentialRecord;
+
+/// Which API reads need the user's approval, and standing approvals
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ApprovalPolicy {
+    /// Reading a credential with any of these tags needs approval
+    pub tags: Vec<String>,
+    /// Revealing sensitive fields of any credential needs approval
+    pub reveal: bool,
+    /// Reads the user chose to allow always
+    pub grants: Vec<ApprovalGrant>,
+}
+
+/// A standing "allow always" decision
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApprovalGrant {
+    /// Label of the API token the script uses
+    pub client: String,
+    pub credential_id: String,
+    /// Whether sensitive fields may be revealed, not just read masked
+    pub reveal: bool,
+    /// When the grant was made (Unix timestamp)
+    pub granted_at: i64,
+}
+
+/// One read waiting for the user's decision
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApprovalRequest {
+    /// Identifies the request when the decision comes back
+    pub id: u64,
+    /// Label of the API token asking
+    pub client: String,
+    pub credential_id: String,
+    pub credential_title: String,
+    /// Whether sensitive fields are to be revealed
+    pub reveal: bool,
+    #[serde(default)]
+    pub purpose: ApprovalPurpose,
+}
+
+/// What an [`ApprovalRequest`] asks for
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApprovalPurpose {
+    /// A script reads the credential over the API
+    #[default]
+    Read,
+    /// An SSH client asks the ssh-agent to sign with the stored key; never
+    /// granted always, since the key was marked to confirm every use
+    SshSign,
+}
+
+/// The user's answer to an [`ApprovalRequest`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApprovalDecision {
+    AllowOnce,
+    AllowAlways,
+    Deny,
+}
+
+impl ApprovalDecision {
+    pub fn is_allowed(&self) -> bool {
+        !matches!(self, ApprovalDecision::Deny)
+    }
+}
+
+impl ApprovalPolicy {
+    /// Whether `client` has to ask before reading `credential`
+    pub fn requires_approval(
+        &self,
+        client: &str,
+        credential: &CredentialRecord,
+        reveal: bool,
+    ) -> bool {
+        let guarded = (reveal && self.reveal)
+            || credential
+                .tags
+                .iter()
+                .any(|tag| self.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)));
+        guarded && !self.is_granted(client, &credential.id, reveal)
+    }
+
+    /// Whether an earlier "allow always" covers this read; a grant to reveal
+    /// also covers masked reads
+    pub fn is_granted(&self, client: &str, credential_id: &str, reveal: bool) -> bool {
+        self.grants.iter().any(|grant| {
+            grant.client == client
+                && grant.credential_id == credential_id
+                && (grant.reveal || !reveal)
+        })
+    }
+
+    /// Remember that reads like `request` are allowed from now on
+    pub fn grant(&mut self, request: &ApprovalRequest) {
+        if request.purpose != ApprovalPurpose::Read
+            || self.is_granted(&request.client, &request.credential_id, request.reveal)
+        {
+            return;
+        }
+        self.grants.retain(|grant| {
+            grant.client != request.client || grant.credential_id != request.credential_id
+        });
+        self.grants.push(ApprovalGrant {
+            client: request.client.clone(),
+            credential_id: request.credential_id.clone(),
+            reveal: request.reveal,
+            granted_at: Utc::now().timestamp(),
+        });
+    }
+
+    /// Forget every standing approval for `client`; returns how many there
+    /// were
+    pub fn revoke_client(&mut self, client: &str) -> usize {
+        let before = self.grants.len();
+        self.grants.retain(|grant| grant.client != client);
+        before - self.grants.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tagged_and_revealed_reads_need_approval_until_granted() {
+        let policy_yaml = "tags: [Banking]\nreveal: true\n";
+        let mut policy: ApprovalPolicy = serde_yaml::from_str(policy_yaml).unwrap();
+        assert!(policy.grants.is_empty());
+
+        let mut bank = CredentialRecord::new("Bank".to_string(), "login".to_string());
+        bank.add_tag("banking");
+        let forum = CredentialRecord::new("Forum".to_string(), "login".to_string());
+
+        assert!(policy.requires_approval("backup", &bank, false));
+        assert!(!policy.requires_approval("backup", &forum, false));
+        assert!(policy.requires_approval("backup", &forum, true));
+
+        let request = ApprovalRequest {
+            id: 1,
+            client: "backup".to_string(),
+            credential_id: bank.id.clone(),
+            credential_title: bank.title.clone(),
+            reveal: false,
+            purpose: ApprovalPurpose::Read,
+        };
+        policy.grant(&ApprovalRequest {
+            purpose: ApprovalPurpose::SshSign,
+            ..request.clone()
+        });
+        assert!(policy.grants.is_empty());
+        policy.grant(&request);
+        assert!(!policy.requires_approval("backup", &bank, false));
+        assert!(policy.requires_approval("backup", &bank, true));
+        assert!(policy.requires_approval("other", &bank, false));
+
+        policy.grant(&ApprovalRequest {
+            reveal: true,
+            ..request.clone()
+        });
+        policy.grant(&request);
+        assert_eq!(policy.grants.len(), 1);
+        assert!(!policy.requires_approval("backup", &bank, true));
+
+        assert_eq!(policy.revoke_client("backup"), 1);
+        assert!(policy.requires_approval("backup", &bank, false));
+        assert!(!ApprovalDecision::Deny.is_allowed());
+        assert!(ApprovalDecision::AllowOnce.is_allowed());
+    }
+}
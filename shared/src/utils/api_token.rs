@@ -0,0 +1,593 @@
+//! Short-lived API tokens for scripting against an unlocked vault
+//!
+//! The desktop app issues tokens from Settings and answers read-only
+//! [`ApiRequest`]s from local scripts that present one. Only a SHA-256 hash of
+//! each token is kept, tokens expire on their own, and the whole registry is
+//! cleared when the vault locks, so a leaked token is useless once the
+//! session ends.
+
+use base64::prelude::*;
+use chrono::{DateTime, Duration, Utc};
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+
+use super::encryption::EncryptionUtils;
+use crate::core::{RepositoryEvent, SessionExpiry};
+use crate::models::CredentialRecord;
+
+const TOKEN_PREFIX: &str = "zlapi1_";
+const SESSION_ID_LEN: usize = 8;
+const SECRET_LEN: usize = 32;
+
+/// Longest lifetime a token can be issued with
+pub const MAX_TOKEN_TTL_HOURS: i64 = 24;
+
+/// Path of the socket the desktop app serves the API on
+pub fn api_socket_path() -> PathBuf {
+    dirs::runtime_dir()
+        .or_else(dirs::cache_dir)
+        .unwrap_or_else(std::env::temp_dir)
+        .join("ziplock")
+        .join("api.sock")
+}
+
+/// Error types for API token operations
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ApiTokenError {
+    /// Token is malformed, unknown or has been revoked
+    InvalidToken,
+    /// The token expired at the given time
+    Expired(DateTime<Utc>),
+    /// The token was not issued with the scope this request needs
+    ScopeDenied(ApiScope),
+    /// Requested lifetime is zero or longer than [`MAX_TOKEN_TTL_HOURS`]
+    InvalidTtl,
+    /// Tokens must be issued with at least one scope
+    NoScopes,
+}
+
+impl fmt::Display for ApiTokenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiTokenError::InvalidToken => write!(f, "Invalid or revoked API token"),
+            ApiTokenError::Expired(at) => write!(f, "API token expired on {}", at.to_rfc3339()),
+            ApiTokenError::ScopeDenied(scope) => {
+                write!(f, "API token does not have the '{}' scope", scope.name())
+            }
+            ApiTokenError::InvalidTtl => write!(
+                f,
+                "Token lifetime must be between 1 minute and {} hours",
+                MAX_TOKEN_TTL_HOURS
+            ),
+            ApiTokenError::NoScopes => write!(f, "API token needs at least one scope"),
+        }
+    }
+}
+
+impl std::error::Error for ApiTokenError {}
+
+/// Result type for API token operations
+pub type ApiTokenResult<T> = Result<T, ApiTokenError>;
+
+/// What a token is allowed to do; every scope is read-only
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiScope {
+    /// List, search and read credentials with sensitive fields masked
+    Read,
+    /// Read the values of sensitive fields such as passwords
+    ReadSecrets,
+}
+
+impl ApiScope {
+    /// Name used in the wire protocol and error messages
+    pub fn name(&self) -> &'static str {
+        match self {
+            ApiScope::Read => "read",
+            ApiScope::ReadSecrets => "read_secrets",
+        }
+    }
+}
+
+/// Public details of an issued token, shown in the sessions list
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApiSession {
+    /// Identifier embedded in the token, used to revoke it
+    pub id: String,
+    /// Name the user gave the token, e.g. the script using it
+    pub label: String,
+    pub scopes: Vec<ApiScope>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub request_count: u64,
+}
+
+impl ApiSession {
+    /// Whether the token has passed its expiry time
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        now >= self.expires_at
+    }
+
+    pub fn has_scope(&self, scope: ApiScope) -> bool {
+        self.scopes.contains(&scope)
+    }
+}
+
+/// A newly issued token; `token` is shown to the user once and never stored
+#[derive(Debug, Clone)]
+pub struct IssuedToken {
+    pub token: String,
+    pub session: ApiSession,
+}
+
+/// In-memory registry of issued tokens
+#[derive(Debug, Default)]
+pub struct ApiTokenRegistry {
+    sessions: HashMap<String, (ApiSession, [u8; 32])>,
+}
+
+impl ApiTokenRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Issue a token valid for `ttl`
+    pub fn issue(
+        &mut self,
+        label: &str,
+        scopes: &[ApiScope],
+        ttl: Duration,
+    ) -> ApiTokenResult<IssuedToken> {
+        self.issue_at(label, scopes, ttl, Utc::now())
+    }
+
+    /// Issue a token as of `now`
+    pub fn issue_at(
+        &mut self,
+        label: &str,
+        scopes: &[ApiScope],
+        ttl: Duration,
+        now: DateTime<Utc>,
+    ) -> ApiTokenResult<IssuedToken> {
+        if ttl < Duration::minutes(1) || ttl > Duration::hours(MAX_TOKEN_TTL_HOURS) {
+            return Err(ApiTokenError::InvalidTtl);
+        }
+        if scopes.is_empty() {
+            return Err(ApiTokenError::NoScopes);
+        }
+
+        let mut id_bytes = [0u8; SESSION_ID_LEN];
+        OsRng.fill_bytes(&mut id_bytes);
+        let id = EncryptionUtils::to_hex(&id_bytes);
+
+        let mut secret = [0u8; SECRET_LEN];
+        OsRng.fill_bytes(&mut secret);
+        let token = format!(
+            "{}{}_{}",
+            TOKEN_PREFIX,
+            id,
+            BASE64_URL_SAFE_NO_PAD.encode(secret)
+        );
+
+        let mut unique_scopes = Vec::new();
+        for scope in scopes {
+            if !unique_scopes.contains(scope) {
+                unique_scopes.push(*scope);
+            }
+        }
+
+        let label = label.trim();
+        let session = ApiSession {
+            id: id.clone(),
+            label: if label.is_empty() {
+                "Unnamed token".to_string()
+            } else {
+                label.to_string()
+            },
+            scopes: unique_scopes,
+            created_at: now,
+            expires_at: now + ttl,
+            last_used_at: None,
+            request_count: 0,
+        };
+        self.sessions
+            .insert(id, (session.clone(), Sha256::digest(&token).into()));
+
+        Ok(IssuedToken { token, session })
+    }
+
+    /// Check `token` for `scope` and record the use
+    pub fn authorize(&mut self, token: &str, scope: ApiScope) -> ApiTokenResult<ApiSession> {
+        self.authorize_at(token, scope, Utc::now())
+    }
+
+    /// Check `token` for `scope` as of `now` and record the use
+    pub fn authorize_at(
+        &mut self,
+        token: &str,
+        scope: ApiScope,
+        now: DateTime<Utc>,
+    ) -> ApiTokenResult<ApiSession> {
+        let id = token
+            .strip_prefix(TOKEN_PREFIX)
+            .and_then(|rest| rest.split_once('_'))
+            .map(|(id, _)| id)
+            .ok_or(ApiTokenError::InvalidToken)?;
+
+        let (session, hash) = self
+            .sessions
+            .get_mut(id)
+            .ok_or(ApiTokenError::InvalidToken)?;
+        let presented: [u8; 32] = Sha256::digest(token).into();
+        if !EncryptionUtils::secure_compare(&presented, hash) {
+            return Err(ApiTokenError::InvalidToken);
+        }
+        if session.is_expired(now) {
+            let expired_at = session.expires_at;
+            self.sessions.remove(id);
+            return Err(ApiTokenError::Expired(expired_at));
+        }
+        if !session.has_scope(scope) {
+            return Err(ApiTokenError::ScopeDenied(scope));
+        }
+
+        session.last_used_at = Some(now);
+        session.request_count += 1;
+        Ok(session.clone())
+    }
+
+    /// Revoke the token with session `id`; returns whether it existed
+    pub fn revoke(&mut self, id: &str) -> bool {
+        self.sessions.remove(id).is_some()
+    }
+
+    /// Revoke every token, e.g. when the vault locks
+    pub fn clear(&mut self) {
+        self.sessions.clear();
+    }
+
+    /// Drop expired tokens
+    pub fn purge_expired(&mut self, now: DateTime<Utc>) {
+        self.sessions
+            .retain(|_, (session, _)| !session.is_expired(now));
+    }
+
+    /// Tokens that have not expired, newest first
+    pub fn sessions(&self, now: DateTime<Utc>) -> Vec<ApiSession> {
+        let mut sessions: Vec<ApiSession> = self
+            .sessions
+            .values()
+            .map(|(session, _)| session)
+            .filter(|session| !session.is_expired(now))
+            .cloned()
+            .collect();
+        sessions.sort_by_key(|session| std::cmp::Reverse(session.created_at));
+        sessions
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sessions.is_empty()
+    }
+}
+
+/// Read-only operations available to scripts
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "method", rename_all = "snake_case")]
+pub enum ApiMethod {
+    /// Titles, types and tags of every credential
+    List,
+    /// Credentials whose title or non-sensitive fields match `query`
+    Search { query: String },
+    /// One credential; sensitive fields need [`ApiScope::ReadSecrets`]
+    Get {
+        id: String,
+        #[serde(default)]
+        reveal: bool,
+    },
+    /// Push an [`ApiEvent::Changed`] line whenever the repository changes,
+    /// until the connection closes
+    Subscribe,
+}
+
+impl ApiMethod {
+    /// Scope a token needs for this call
+    pub fn required_scope(&self) -> ApiScope {
+        match self {
+            ApiMethod::Get { reveal: true, .. } => ApiScope::ReadSecrets,
+            _ => ApiScope::Read,
+        }
+    }
+}
+
+/// One request line sent to the desktop app
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApiRequest {
+    pub token: String,
+    #[serde(flatten)]
+    pub method: ApiMethod,
+}
+
+/// Credential without field values, returned by `list` and `search`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApiCredentialSummary {
+    pub id: String,
+    pub title: String,
+    pub credential_type: String,
+    pub tags: Vec<String>,
+}
+
+impl From<&CredentialRecord> for ApiCredentialSummary {
+    fn from(credential: &CredentialRecord) -> Self {
+        Self {
+            id: credential.id.clone(),
+            title: credential.title.clone(),
+            credential_type: credential.credential_type.clone(),
+            tags: credential.tags.clone(),
+        }
+    }
+}
+
+/// One response line sent back to the script
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ApiResponse {
+    Ok {
+        ok: bool,
+        result: serde_json::Value,
+    },
+    Error {
+        ok: bool,
+        error: String,
+        /// Set for failures a script may want to react to specifically
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        code: Option<ApiErrorCode>,
+    },
+    /// Sent unprompted: repository changes to subscribed scripts, and
+    /// shutdown to every connected script before the app exits
+    Event {
+        ok: bool,
+        event: ApiEvent,
+    },
+}
+
+/// Machine-readable kind of an error response
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiErrorCode {
+    /// The vault was locked because its session ran past the configured idle
+    /// timeout or maximum lifetime; it has to be unlocked in the app again
+    SessionExpired,
+    /// The read needed the user's approval in the app, which was refused or
+    /// not given in time
+    ApprovalDenied,
+}
+
+/// Notices the app sends without a request
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiEvent {
+    /// The app is shutting down and closes the connection
+    ServerShutdown,
+    /// The repository changed; only sent after [`ApiMethod::Subscribe`]
+    Changed(RepositoryEvent),
+    /// The connection fell behind and `missed` changes were dropped, so the
+    /// script should list the credentials again
+    Lagged { missed: u64 },
+}
+
+impl ApiResponse {
+    pub fn ok(result: serde_json::Value) -> Self {
+        ApiResponse::Ok { ok: true, result }
+    }
+
+    pub fn error(error: impl fmt::Display) -> Self {
+        ApiResponse::Error {
+            ok: false,
+            error: error.to_string(),
+            code: None,
+        }
+    }
+
+    pub fn session_expired(reason: SessionExpiry) -> Self {
+        ApiResponse::Error {
+            ok: false,
+            error: reason.describe().to_string(),
+            code: Some(ApiErrorCode::SessionExpired),
+        }
+    }
+
+    pub fn approval_denied() -> Self {
+        ApiResponse::Error {
+            ok: false,
+            error: "The request was not approved in ZipLock".to_string(),
+            code: Some(ApiErrorCode::ApprovalDenied),
+        }
+    }
+
+    pub fn event(event: ApiEvent) -> Self {
+        ApiResponse::Event {
+            ok: event != ApiEvent::ServerShutdown,
+            event,
+        }
+    }
+
+    pub fn server_shutdown() -> Self {
+        ApiResponse::Event {
+            ok: false,
+            event: ApiEvent::ServerShutdown,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_issue_and_authorize() {
+        let mut registry = ApiTokenRegistry::new();
+        let now = Utc::now();
+        let issued = registry
+            .issue_at(
+                "backup script",
+                &[ApiScope::Read],
+                Duration::minutes(15),
+                now,
+            )
+            .unwrap();
+
+        assert!(issued.token.starts_with(TOKEN_PREFIX));
+        assert_eq!(issued.session.expires_at, now + Duration::minutes(15));
+
+        let session = registry
+            .authorize_at(&issued.token, ApiScope::Read, now)
+            .unwrap();
+        assert_eq!(session.label, "backup script");
+        assert_eq!(session.request_count, 1);
+        assert_eq!(
+            registry.authorize_at(&issued.token, ApiScope::ReadSecrets, now),
+            Err(ApiTokenError::ScopeDenied(ApiScope::ReadSecrets))
+        );
+    }
+
+    #[test]
+    fn test_tampered_and_expired_tokens_are_rejected() {
+        let mut registry = ApiTokenRegistry::new();
+        let now = Utc::now();
+        let issued = registry
+            .issue_at("", &[ApiScope::Read], Duration::minutes(5), now)
+            .unwrap();
+        assert_eq!(issued.session.label, "Unnamed token");
+
+        let mut tampered = issued.token.clone();
+        tampered.pop();
+        tampered.push('A');
+        if tampered == issued.token {
+            tampered.pop();
+            tampered.push('B');
+        }
+        assert_eq!(
+            registry.authorize_at(&tampered, ApiScope::Read, now),
+            Err(ApiTokenError::InvalidToken)
+        );
+        assert_eq!(
+            registry.authorize_at("not a token", ApiScope::Read, now),
+            Err(ApiTokenError::InvalidToken)
+        );
+
+        let later = now + Duration::minutes(5);
+        assert_eq!(
+            registry.authorize_at(&issued.token, ApiScope::Read, later),
+            Err(ApiTokenError::Expired(issued.session.expires_at))
+        );
+        assert!(registry.is_empty());
+    }
+
+    #[test]
+    fn test_revoke_and_sessions_list() {
+        let mut registry = ApiTokenRegistry::new();
+        let now = Utc::now();
+        let first = registry
+            .issue_at("first", &[ApiScope::Read], Duration::hours(1), now)
+            .unwrap();
+        let second = registry
+            .issue_at(
+                "second",
+                &[ApiScope::Read, ApiScope::ReadSecrets, ApiScope::Read],
+                Duration::minutes(10),
+                now + Duration::seconds(1),
+            )
+            .unwrap();
+        assert_eq!(
+            second.session.scopes,
+            vec![ApiScope::Read, ApiScope::ReadSecrets]
+        );
+
+        let sessions = registry.sessions(now + Duration::seconds(2));
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions[0].label, "second");
+
+        assert!(registry.revoke(&first.session.id));
+        assert!(!registry.revoke(&first.session.id));
+        assert_eq!(
+            registry.authorize_at(&first.token, ApiScope::Read, now),
+            Err(ApiTokenError::InvalidToken)
+        );
+
+        registry.purge_expired(now + Duration::minutes(11));
+        assert!(registry.is_empty());
+    }
+
+    #[test]
+    fn test_invalid_issue_parameters() {
+        let mut registry = ApiTokenRegistry::new();
+        assert_eq!(
+            registry
+                .issue("x", &[ApiScope::Read], Duration::hours(25))
+                .unwrap_err(),
+            ApiTokenError::InvalidTtl
+        );
+        assert_eq!(
+            registry.issue("x", &[], Duration::minutes(15)).unwrap_err(),
+            ApiTokenError::NoScopes
+        );
+    }
+
+    #[test]
+    fn test_request_wire_format() {
+        let request: ApiRequest =
+            serde_json::from_str(r#"{"token":"t","method":"get","id":"abc","reveal":true}"#)
+                .unwrap();
+        assert_eq!(request.method.required_scope(), ApiScope::ReadSecrets);
+
+        let request: ApiRequest =
+            serde_json::from_str(r#"{"token":"t","method":"search","query":"mail"}"#).unwrap();
+        assert_eq!(
+            request.method,
+            ApiMethod::Search {
+                query: "mail".to_string()
+            }
+        );
+        assert_eq!(request.method.required_scope(), ApiScope::Read);
+
+        let error = serde_json::to_value(ApiResponse::error("locked")).unwrap();
+        assert_eq!(error["ok"], false);
+        assert_eq!(error["error"], "locked");
+        assert!(error.get("code").is_none());
+
+        let expired = ApiResponse::session_expired(SessionExpiry::Idle);
+        let line = serde_json::to_string(&expired).unwrap();
+        assert_eq!(
+            line,
+            r#"{"ok":false,"error":"Session expired after a period of inactivity","code":"session_expired"}"#
+        );
+        assert_eq!(serde_json::from_str::<ApiResponse>(&line).unwrap(), expired);
+
+        let line = serde_json::to_string(&ApiResponse::server_shutdown()).unwrap();
+        assert_eq!(line, r#"{"ok":false,"event":"server_shutdown"}"#);
+        assert_eq!(
+            serde_json::from_str::<ApiResponse>(&line).unwrap(),
+            ApiResponse::server_shutdown()
+        );
+
+        let request: ApiRequest =
+            serde_json::from_str(r#"{"token":"t","method":"subscribe"}"#).unwrap();
+        assert_eq!(request.method, ApiMethod::Subscribe);
+        assert_eq!(request.method.required_scope(), ApiScope::Read);
+
+        let changed = ApiResponse::event(ApiEvent::Changed(RepositoryEvent::CredentialUpdated {
+            id: "abc".to_string(),
+        }));
+        let line = serde_json::to_string(&changed).unwrap();
+        assert_eq!(
+            line,
+            r#"{"ok":true,"event":{"changed":{"type":"credential_updated","id":"abc"}}}"#
+        );
+        assert_eq!(serde_json::from_str::<ApiResponse>(&line).unwrap(), changed);
+    }
+}
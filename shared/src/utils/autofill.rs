@@ -0,0 +1,323 @@
+//! Data for platform autofill services
+//!
+//! The Android Autofill framework hands the app either the package name of
+//! the app being filled or the scheme and domain of a web page. The app first
+//! offers [`AutofillCandidate`]s, which name a credential without revealing
+//! any secret, and only builds the [`AutofillDataset`] with real values once
+//! the user has picked one and confirmed it. Web pages go through
+//! [`UrlMatcher`]; apps only match credentials that list them as
+//! `androidapp://<package>`, since package names are not verified and
+//! guessing a website from one would hand its password to any app that
+//! claims the name.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use url::Url;
+use zeroize::Zeroize;
+
+use super::totp::TotpGenerator;
+use super::url_match::{UrlMatchMode, UrlMatcher};
+use crate::core::{CoreError, CoreResult};
+use crate::models::{CredentialRecord, FieldType};
+
+/// URL scheme naming an Android app in a URL field
+pub const ANDROID_APP_SCHEME: &str = "androidapp";
+
+/// What is being filled
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AutofillTarget {
+    /// A web page, by its URL
+    Web(String),
+    /// An Android app, by its package name
+    App(String),
+}
+
+impl AutofillTarget {
+    /// Parse a page URL (`https://example.com`), an `androidapp://` URL or a
+    /// bare package name (`com.example.app`)
+    pub fn parse(value: &str) -> CoreResult<Self> {
+        let value = value.trim();
+        let invalid = || CoreError::ValidationError {
+            message: format!("Not a URL or package name: '{}'", value),
+        };
+        if value.contains("://") {
+            let url = Url::parse(value).map_err(|_| invalid())?;
+            return match (url.scheme(), url.host_str()) {
+                (ANDROID_APP_SCHEME, Some(package)) => Ok(Self::App(package.to_string())),
+                ("http" | "https", Some(_)) => Ok(Self::Web(value.to_string())),
+                _ => Err(invalid()),
+            };
+        }
+
+        let is_identifier = |part: &str| {
+            part.chars()
+                .next()
+                .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+                && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        };
+        let parts: Vec<&str> = value.split('.').collect();
+        if parts.len() >= 2 && parts.iter().all(|part| is_identifier(part)) {
+            Ok(Self::App(value.to_ascii_lowercase()))
+        } else {
+            Err(invalid())
+        }
+    }
+}
+
+/// A credential to offer in the autofill suggestions; holds no secrets
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AutofillCandidate {
+    pub credential_id: String,
+    pub title: String,
+    /// Username, or email address when there is no username
+    pub username: Option<String>,
+    /// How the credential matched; more specific matches come first
+    pub match_mode: UrlMatchMode,
+    pub has_password: bool,
+    pub has_totp: bool,
+}
+
+impl AutofillCandidate {
+    fn new(credential: &CredentialRecord, match_mode: UrlMatchMode) -> Self {
+        let value_of = |field_type: FieldType| {
+            credential
+                .fields
+                .values()
+                .find(|f| f.field_type == field_type && !f.is_protected() && !f.value.is_empty())
+                .map(|f| f.value.clone())
+        };
+        Self {
+            credential_id: credential.id.clone(),
+            title: credential.title.clone(),
+            username: value_of(FieldType::Username).or_else(|| value_of(FieldType::Email)),
+            match_mode,
+            has_password: value_of(FieldType::Password).is_some(),
+            has_totp: value_of(FieldType::TotpSecret).is_some(),
+        }
+    }
+}
+
+/// Credentials to suggest for `target`, best first
+pub fn autofill_candidates(
+    target: &AutofillTarget,
+    credentials: &[CredentialRecord],
+    matcher: &UrlMatcher,
+) -> CoreResult<Vec<AutofillCandidate>> {
+    let by_id = |id: &str| credentials.iter().find(|c| c.id == id);
+    match target {
+        AutofillTarget::Web(url) => Ok(matcher
+            .find(url, credentials)?
+            .into_iter()
+            .filter_map(|found| {
+                by_id(&found.credential_id).map(|c| AutofillCandidate::new(c, found.mode))
+            })
+            .collect()),
+        AutofillTarget::App(package) => {
+            let app_url = format!("{}://{}", ANDROID_APP_SCHEME, package);
+            let mut matching: Vec<&CredentialRecord> = credentials
+                .iter()
+                .filter(|credential| {
+                    credential.fields.values().any(|field| {
+                        field.field_type == FieldType::Url
+                            && !field.is_protected()
+                            && UrlMatchMode::of_field(field) != UrlMatchMode::Never
+                            && field
+                                .value
+                                .trim()
+                                .trim_end_matches('/')
+                                .eq_ignore_ascii_case(&app_url)
+                    })
+                })
+                .collect();
+            matching.sort_by(|a, b| {
+                b.favorite
+                    .cmp(&a.favorite)
+                    .then_with(|| a.title.to_lowercase().cmp(&b.title.to_lowercase()))
+            });
+            Ok(matching
+                .into_iter()
+                .map(|c| AutofillCandidate::new(c, UrlMatchMode::Exact))
+                .collect())
+        }
+    }
+}
+
+/// One value to fill, keyed by an Android autofill hint
+#[derive(Clone, PartialEq, Eq, Serialize)]
+pub struct AutofillValue {
+    /// Autofill hint such as `username`, `password` or `creditCardNumber`
+    pub hint: String,
+    /// Name of the credential field the value comes from
+    pub field_name: String,
+    /// The value, or `None` while a sensitive value is redacted
+    pub value: Option<String>,
+}
+
+impl Drop for AutofillValue {
+    fn drop(&mut self) {
+        self.value.zeroize();
+    }
+}
+
+impl std::fmt::Debug for AutofillValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AutofillValue")
+            .field("hint", &self.hint)
+            .field("field_name", &self.field_name)
+            .field("value", &self.value.as_ref().map(|_| "[REDACTED]"))
+            .finish()
+    }
+}
+
+/// The values of one credential, ready for an autofill dataset
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AutofillDataset {
+    pub credential_id: String,
+    pub title: String,
+    /// Whether sensitive values were left out
+    pub redacted: bool,
+    pub values: Vec<AutofillValue>,
+}
+
+impl AutofillDataset {
+    /// Values of `credential` that autofill can use, in a stable order
+    ///
+    /// Passwords, card numbers and security codes, TOTP codes and fields
+    /// marked sensitive are `None` unless `reveal` is set, which the app
+    /// should only do once the user confirmed the fill. TOTP secrets are
+    /// filled as the code current at `now`. Protected fields are left out.
+    pub fn from_credential(
+        credential: &CredentialRecord,
+        reveal: bool,
+        now: DateTime<Utc>,
+    ) -> Self {
+        let mut fields: Vec<_> = credential
+            .fields
+            .iter()
+            .filter(|(_, field)| !field.is_protected() && !field.value.is_empty())
+            .filter_map(|(name, field)| {
+                let hint = autofill_hint(&field.field_type)?;
+                Some((hint, name, field))
+            })
+            .collect();
+        fields.sort_by(|a, b| a.0.cmp(b.0).then_with(|| a.1.cmp(b.1)));
+
+        let values = fields
+            .into_iter()
+            .filter_map(|(hint, name, field)| {
+                let sensitive = field.sensitive
+                    || matches!(
+                        field.field_type,
+                        FieldType::Password
+                            | FieldType::CreditCardNumber
+                            | FieldType::Cvv
+                            | FieldType::TotpSecret
+                    );
+                let value = if sensitive && !reveal {
+                    None
+                } else if field.field_type == FieldType::TotpSecret {
+                    let code = TotpGenerator::from_field(field)
+                        .and_then(|generator| generator.generate_at(now.timestamp().max(0) as u64))
+                        .ok()?;
+                    Some(code)
+                } else {
+                    Some(field.value.clone())
+                };
+                Some(AutofillValue {
+                    hint: hint.to_string(),
+                    field_name: name.clone(),
+                    value,
+                })
+            })
+            .collect();
+
+        Self {
+            credential_id: credential.id.clone(),
+            title: credential.title.clone(),
+            redacted: !reveal,
+            values,
+        }
+    }
+}
+
+/// Android autofill hint for a field type, for the types autofill can use
+fn autofill_hint(field_type: &FieldType) -> Option<&'static str> {
+    match field_type {
+        FieldType::Username => Some("username"),
+        FieldType::Email => Some("emailAddress"),
+        FieldType::Password => Some("password"),
+        FieldType::Phone => Some("phone"),
+        FieldType::CreditCardNumber => Some("creditCardNumber"),
+        FieldType::ExpiryDate => Some("creditCardExpirationDate"),
+        FieldType::Cvv => Some("creditCardSecurityCode"),
+        FieldType::TotpSecret => Some("2faAppOTPCode"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::CredentialField;
+
+    #[test]
+    fn test_targets() {
+        assert_eq!(
+            AutofillTarget::parse("com.example.app").unwrap(),
+            AutofillTarget::App("com.example.app".to_string())
+        );
+        assert_eq!(
+            AutofillTarget::parse("androidapp://com.example.app").unwrap(),
+            AutofillTarget::App("com.example.app".to_string())
+        );
+        assert!(matches!(
+            AutofillTarget::parse("https://example.com/login").unwrap(),
+            AutofillTarget::Web(_)
+        ));
+        assert!(AutofillTarget::parse("ftp://example.com").is_err());
+        assert!(AutofillTarget::parse("not a package").is_err());
+    }
+
+    #[test]
+    fn test_candidates_and_redacted_dataset() {
+        let mut web = CredentialRecord::new("Example".to_string(), "login".to_string());
+        web.set_field("url", CredentialField::url("https://example.com"));
+        web.set_field("username", CredentialField::username("jane"));
+        web.set_field("password", CredentialField::password("s3cret"));
+        web.set_field("totp", CredentialField::totp_secret("JBSWY3DPEHPK3PXP"));
+        let mut app = CredentialRecord::new("Example app".to_string(), "login".to_string());
+        app.set_field("url", CredentialField::url("androidapp://com.example.app"));
+        app.set_field("email", CredentialField::email("jane@example.com"));
+        let credentials = vec![web.clone(), app.clone()];
+        let matcher = UrlMatcher::new();
+
+        let target = AutofillTarget::parse("https://login.example.com/").unwrap();
+        let found = autofill_candidates(&target, &credentials, &matcher).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].credential_id, web.id);
+        assert_eq!(found[0].username.as_deref(), Some("jane"));
+        assert!(found[0].has_password && found[0].has_totp);
+
+        let target = AutofillTarget::parse("com.example.app").unwrap();
+        let found = autofill_candidates(&target, &credentials, &matcher).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].username.as_deref(), Some("jane@example.com"));
+        let target = AutofillTarget::parse("com.example.other").unwrap();
+        assert!(autofill_candidates(&target, &credentials, &matcher)
+            .unwrap()
+            .is_empty());
+
+        let now = DateTime::from_timestamp(59, 0).unwrap();
+        let dataset = AutofillDataset::from_credential(&web, false, now);
+        assert!(dataset.redacted);
+        let hints: Vec<&str> = dataset.values.iter().map(|v| v.hint.as_str()).collect();
+        assert_eq!(hints, vec!["2faAppOTPCode", "password", "username"]);
+        assert_eq!(dataset.values[2].value.as_deref(), Some("jane"));
+        assert!(dataset.values[..2].iter().all(|v| v.value.is_none()));
+        assert!(!format!("{:?}", dataset).contains("s3cret"));
+
+        let dataset = AutofillDataset::from_credential(&web, true, now);
+        assert_eq!(dataset.values[1].value.as_deref(), Some("s3cret"));
+        assert_eq!(dataset.values[0].value.as_deref().map(str::len), Some(6));
+    }
+}
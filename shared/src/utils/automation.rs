@@ -0,0 +1,590 @@
+//! Automation scripts run on vault events
+//!
+//! Power users and administrators register small [Rhai](https://rhai.rs)
+//! scripts that run when a credential is created, before the repository is
+//! saved, and after a security audit. They enforce house rules the built-in
+//! validation does not know about, such as naming conventions or required
+//! fields, without a plugin build.
+//!
+//! A script sees the vault through a single variable, `credential` or
+//! `audit`, and talks back with `reject(message)` and `warn(message)`.
+//! Scripts cannot import modules, `eval` code, print or reach files or the
+//! network, and each run is cut off after a fixed number of operations, so
+//! a runaway loop fails the hook rather than hanging the app.
+//!
+//! Scripts are stored in the app config; running them needs the
+//! `automation` feature.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// When a script runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AutomationEvent {
+    /// A credential is about to be added; changes the script makes to
+    /// `credential.title`, `credential.folder` and `credential.tags` are
+    /// kept, and a rejection stops the credential being added
+    CredentialCreated,
+    /// The repository is about to be saved; runs once per credential, and
+    /// a rejection stops the save
+    BeforeSave,
+    /// A security audit finished; messages are added to the report
+    AuditCompleted,
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+/// A script registered for an event
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AutomationScript {
+    /// Name shown with the script's messages; unique among scripts
+    pub name: String,
+    pub event: AutomationEvent,
+    /// Rhai source
+    pub source: String,
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+}
+
+impl AutomationScript {
+    pub fn new<S: Into<String>>(name: S, event: AutomationEvent, source: S) -> Self {
+        Self {
+            name: name.into(),
+            event,
+            source: source.into(),
+            enabled: true,
+        }
+    }
+}
+
+/// A message a script passed to `reject` or `warn`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScriptMessage {
+    /// Name of the script
+    pub script: String,
+    /// Credential the script ran on, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub credential_id: Option<String>,
+    pub message: String,
+}
+
+/// Messages from the scripts run for one event
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HookOutcome {
+    pub rejections: Vec<ScriptMessage>,
+    pub warnings: Vec<ScriptMessage>,
+}
+
+impl HookOutcome {
+    /// Whether a script rejected the operation
+    pub fn is_rejected(&self) -> bool {
+        !self.rejections.is_empty()
+    }
+
+    /// Rejections and warnings, rejections first
+    pub fn into_messages(self) -> Vec<ScriptMessage> {
+        let mut messages = self.rejections;
+        messages.extend(self.warnings);
+        messages
+    }
+}
+
+/// Error types for automation scripts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AutomationError {
+    /// The script does not compile
+    Compile { script: String, message: String },
+    /// The script failed while running or ran past its limits
+    Runtime { script: String, message: String },
+}
+
+impl fmt::Display for AutomationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AutomationError::Compile { script, message } => {
+                write!(
+                    f,
+                    "Automation script '{}' does not compile: {}",
+                    script, message
+                )
+            }
+            AutomationError::Runtime { script, message } => {
+                write!(f, "Automation script '{}' failed: {}", script, message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for AutomationError {}
+
+/// Result type for automation scripts
+pub type AutomationResult<T> = Result<T, AutomationError>;
+
+#[cfg(feature = "automation")]
+pub use engine::AutomationEngine;
+
+#[cfg(feature = "automation")]
+mod engine {
+    use super::*;
+    use crate::models::CredentialRecord;
+    use crate::utils::audit::SecurityAuditReport;
+    use rhai::module_resolvers::DummyModuleResolver;
+    use rhai::{Array, Dynamic, Engine, Map, Scope, AST};
+    use std::sync::{Arc, Mutex, PoisonError};
+
+    /// Operations one script run may take
+    const MAX_OPERATIONS: u64 = 100_000;
+    const MAX_CALL_LEVELS: usize = 32;
+    const MAX_STRING_SIZE: usize = 64 * 1024;
+    const MAX_COLLECTION_SIZE: usize = 10_000;
+
+    #[derive(Clone, Copy)]
+    enum Kind {
+        Reject,
+        Warn,
+    }
+
+    /// Compiled scripts and the sandboxed engine that runs them
+    pub struct AutomationEngine {
+        engine: Engine,
+        scripts: Vec<(AutomationScript, AST)>,
+        /// Filled by `reject` and `warn` during a run
+        messages: Arc<Mutex<Vec<(Kind, String)>>>,
+        /// Held for a whole run, so runs on other threads wait
+        run_lock: Mutex<()>,
+    }
+
+    impl Default for AutomationEngine {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl AutomationEngine {
+        /// An engine without scripts
+        pub fn new() -> Self {
+            let messages: Arc<Mutex<Vec<(Kind, String)>>> = Arc::default();
+
+            let mut engine = Engine::new();
+            engine
+                .set_module_resolver(DummyModuleResolver::new())
+                .set_max_operations(MAX_OPERATIONS)
+                .set_max_call_levels(MAX_CALL_LEVELS)
+                .set_max_string_size(MAX_STRING_SIZE)
+                .set_max_array_size(MAX_COLLECTION_SIZE)
+                .set_max_map_size(MAX_COLLECTION_SIZE)
+                .disable_symbol("eval")
+                .on_print(|_| {})
+                .on_debug(|_, _, _| {});
+            for (name, kind) in [("reject", Kind::Reject), ("warn", Kind::Warn)] {
+                let messages = Arc::clone(&messages);
+                engine.register_fn(name, move |message: &str| {
+                    messages
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .push((kind, message.to_string()));
+                });
+            }
+
+            Self {
+                engine,
+                scripts: Vec::new(),
+                messages,
+                run_lock: Mutex::new(()),
+            }
+        }
+
+        /// An engine with `scripts` compiled, as stored in the app config
+        pub fn from_scripts(scripts: &[AutomationScript]) -> AutomationResult<Self> {
+            let mut engine = Self::new();
+            for script in scripts {
+                engine.add_script(script.clone())?;
+            }
+            Ok(engine)
+        }
+
+        /// Compile and register a script, replacing one of the same name
+        pub fn add_script(&mut self, script: AutomationScript) -> AutomationResult<()> {
+            let ast =
+                self.engine
+                    .compile(&script.source)
+                    .map_err(|e| AutomationError::Compile {
+                        script: script.name.clone(),
+                        message: e.to_string(),
+                    })?;
+            self.remove_script(&script.name);
+            self.scripts.push((script, ast));
+            Ok(())
+        }
+
+        /// Remove the script called `name`, returning whether there was one
+        pub fn remove_script(&mut self, name: &str) -> bool {
+            let before = self.scripts.len();
+            self.scripts.retain(|(script, _)| script.name != name);
+            self.scripts.len() != before
+        }
+
+        /// Registered scripts, in the order they run
+        pub fn scripts(&self) -> impl Iterator<Item = &AutomationScript> {
+            self.scripts.iter().map(|(script, _)| script)
+        }
+
+        /// Run the [`CredentialCreated`](AutomationEvent::CredentialCreated)
+        /// scripts on a new credential, keeping their changes to its title,
+        /// folder and tags
+        pub fn credential_created(
+            &self,
+            credential: &mut CredentialRecord,
+        ) -> AutomationResult<HookOutcome> {
+            let mut outcome = HookOutcome::default();
+            for (script, ast) in self.scripts_for(AutomationEvent::CredentialCreated) {
+                let mut scope = Scope::new();
+                scope.push("credential", credential_map(credential));
+                self.run(script, ast, &mut scope, Some(&credential.id), &mut outcome)?;
+                let map = scope.get_value::<Map>("credential").unwrap_or_default();
+                apply_changes(credential, &map).map_err(|message| AutomationError::Runtime {
+                    script: script.name.clone(),
+                    message,
+                })?;
+            }
+            Ok(outcome)
+        }
+
+        /// Run the [`BeforeSave`](AutomationEvent::BeforeSave) scripts on
+        /// every credential; changes they make are ignored
+        pub fn before_save(
+            &self,
+            credentials: &[CredentialRecord],
+        ) -> AutomationResult<HookOutcome> {
+            let mut outcome = HookOutcome::default();
+            for (script, ast) in self.scripts_for(AutomationEvent::BeforeSave) {
+                for credential in credentials {
+                    let mut scope = Scope::new();
+                    scope.push("credential", credential_map(credential));
+                    self.run(script, ast, &mut scope, Some(&credential.id), &mut outcome)?;
+                }
+            }
+            Ok(outcome)
+        }
+
+        /// Run the [`AuditCompleted`](AutomationEvent::AuditCompleted)
+        /// scripts on the counts of an audit report
+        pub fn audit_completed(
+            &self,
+            report: &SecurityAuditReport,
+        ) -> AutomationResult<HookOutcome> {
+            let mut outcome = HookOutcome::default();
+            for (script, ast) in self.scripts_for(AutomationEvent::AuditCompleted) {
+                let mut scope = Scope::new();
+                scope.push("audit", audit_map(report));
+                self.run(script, ast, &mut scope, None, &mut outcome)?;
+            }
+            Ok(outcome)
+        }
+
+        fn scripts_for(
+            &self,
+            event: AutomationEvent,
+        ) -> impl Iterator<Item = (&AutomationScript, &AST)> {
+            self.scripts
+                .iter()
+                .filter(move |(script, _)| script.enabled && script.event == event)
+                .map(|(script, ast)| (script, ast))
+        }
+
+        fn run(
+            &self,
+            script: &AutomationScript,
+            ast: &AST,
+            scope: &mut Scope,
+            credential_id: Option<&str>,
+            outcome: &mut HookOutcome,
+        ) -> AutomationResult<()> {
+            let _run = self.run_lock.lock().unwrap_or_else(PoisonError::into_inner);
+            let result = self.engine.run_ast_with_scope(scope, ast);
+            let messages =
+                std::mem::take(&mut *self.messages.lock().unwrap_or_else(PoisonError::into_inner));
+            result.map_err(|e| AutomationError::Runtime {
+                script: script.name.clone(),
+                message: e.to_string(),
+            })?;
+
+            for (kind, message) in messages {
+                let message = ScriptMessage {
+                    script: script.name.clone(),
+                    credential_id: credential_id.map(str::to_string),
+                    message,
+                };
+                match kind {
+                    Kind::Reject => outcome.rejections.push(message),
+                    Kind::Warn => outcome.warnings.push(message),
+                }
+            }
+            Ok(())
+        }
+    }
+
+    fn credential_map(credential: &CredentialRecord) -> Map {
+        let mut fields = Map::new();
+        for (name, field) in &credential.fields {
+            fields.insert(name.as_str().into(), field.value.clone().into());
+        }
+        let tags: Array = credential.tags.iter().cloned().map(Dynamic::from).collect();
+
+        let mut map = Map::new();
+        map.insert("id".into(), credential.id.clone().into());
+        map.insert("title".into(), credential.title.clone().into());
+        map.insert("type".into(), credential.credential_type.clone().into());
+        map.insert(
+            "folder".into(),
+            credential
+                .folder_path
+                .clone()
+                .map_or(Dynamic::UNIT, Dynamic::from),
+        );
+        map.insert("tags".into(), tags.into());
+        map.insert(
+            "notes".into(),
+            credential.notes.clone().unwrap_or_default().into(),
+        );
+        map.insert("favorite".into(), credential.favorite.into());
+        map.insert("fields".into(), fields.into());
+        map.insert("created_at".into(), credential.created_at.into());
+        map.insert("updated_at".into(), credential.updated_at.into());
+        map
+    }
+
+    /// Copy the title, folder and tags a script set back to `credential`
+    fn apply_changes(credential: &mut CredentialRecord, map: &Map) -> Result<(), String> {
+        if let Some(title) = map.get("title") {
+            let title = title
+                .clone()
+                .into_string()
+                .map_err(|_| "credential.title must be a string".to_string())?;
+            if title != credential.title {
+                credential.title = title;
+            }
+        }
+
+        if let Some(folder) = map.get("folder") {
+            let folder = if folder.is_unit() {
+                None
+            } else {
+                let folder = folder
+                    .clone()
+                    .into_string()
+                    .map_err(|_| "credential.folder must be a string or ()".to_string())?;
+                Some(folder).filter(|f| !f.is_empty())
+            };
+            credential.folder_path = folder;
+        }
+
+        if let Some(tags) = map.get("tags") {
+            let tags = tags
+                .clone()
+                .into_array()
+                .map_err(|_| "credential.tags must be an array".to_string())?;
+            let tags = tags
+                .into_iter()
+                .map(|tag| tag.into_string())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| "credential.tags must hold strings".to_string())?;
+            if tags != credential.tags {
+                credential.tags.clear();
+                for tag in tags {
+                    credential.add_tag(tag);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn audit_map(report: &SecurityAuditReport) -> Map {
+        let count = |n: usize| Dynamic::from(n as i64);
+        let mut map = Map::new();
+        map.insert("total_credentials".into(), count(report.total_credentials));
+        map.insert(
+            "credentials_with_passwords".into(),
+            count(report.credentials_with_passwords),
+        );
+        map.insert("issue_count".into(), count(report.issue_count()));
+        map.insert("reused".into(), count(report.reused.len()));
+        map.insert("weak".into(), count(report.weak.len()));
+        map.insert("old".into(), count(report.old.len()));
+        map.insert("missing_totp".into(), count(report.missing_totp.len()));
+        map.insert("breached".into(), count(report.breached.len()));
+        map.insert(
+            "exposed_secrets".into(),
+            count(report.exposed_secrets.len()),
+        );
+        map.insert("recycled".into(), count(report.recycled.len()));
+        map.insert(
+            "breach_check_performed".into(),
+            report.breach_check_performed.into(),
+        );
+        map
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::models::CredentialField;
+        use crate::utils::audit::{AuditOptions, SecurityAuditor};
+
+        fn login(title: &str, password: &str) -> CredentialRecord {
+            let mut credential = CredentialRecord::new(title.to_string(), "login".to_string());
+            credential.set_field("password", CredentialField::password(password));
+            credential
+        }
+
+        #[test]
+        fn test_credential_created_naming_convention() {
+            let engine = AutomationEngine::from_scripts(&[AutomationScript::new(
+                "naming",
+                AutomationEvent::CredentialCreated,
+                r#"
+                    if credential.type == "login" && !credential.title.starts_with("[") {
+                        credential.title = "[Web] " + credential.title;
+                        credential.tags.push("web");
+                        credential.folder = "Web";
+                    }
+                    if credential.title.len() > 20 { warn("Long title"); }
+                "#,
+            )])
+            .unwrap();
+
+            let mut credential = login("Example", "correct horse battery staple");
+            let outcome = engine.credential_created(&mut credential).unwrap();
+            assert!(!outcome.is_rejected());
+            assert!(outcome.warnings.is_empty());
+            assert_eq!(credential.title, "[Web] Example");
+            assert_eq!(credential.tags, vec!["web"]);
+            assert_eq!(credential.folder_path.as_deref(), Some("Web"));
+
+            let mut credential = login("A very long credential title", "x");
+            let outcome = engine.credential_created(&mut credential).unwrap();
+            assert_eq!(outcome.warnings.len(), 1);
+            assert_eq!(outcome.warnings[0].script, "naming");
+            assert_eq!(
+                outcome.warnings[0].credential_id.as_deref(),
+                Some(credential.id.as_str())
+            );
+        }
+
+        #[test]
+        fn test_before_save_rejects() {
+            let mut engine = AutomationEngine::new();
+            engine
+                .add_script(AutomationScript::new(
+                    "min-length",
+                    AutomationEvent::BeforeSave,
+                    r#"
+                        let password = credential.fields.password;
+                        if password != () && password.len() < 12 {
+                            reject(credential.title + ": password shorter than 12 characters");
+                        }
+                    "#,
+                ))
+                .unwrap();
+
+            let credentials = vec![login("Short", "abc"), login("Long", "abcdefghijklmnop")];
+            let outcome = engine.before_save(&credentials).unwrap();
+            assert!(outcome.is_rejected());
+            assert_eq!(outcome.rejections.len(), 1);
+            assert_eq!(
+                outcome.rejections[0].message,
+                "Short: password shorter than 12 characters"
+            );
+
+            // Disabled scripts do not run
+            let mut script = engine.scripts().next().unwrap().clone();
+            script.enabled = false;
+            engine.add_script(script).unwrap();
+            assert!(!engine.before_save(&credentials).unwrap().is_rejected());
+            assert!(engine.remove_script("min-length"));
+            assert_eq!(engine.scripts().count(), 0);
+        }
+
+        #[test]
+        fn test_audit_completed() {
+            let engine = AutomationEngine::from_scripts(&[AutomationScript::new(
+                "policy",
+                AutomationEvent::AuditCompleted,
+                r#"if audit.weak > 0 { warn(`${audit.weak} weak passwords`); }"#,
+            )])
+            .unwrap();
+            let report =
+                SecurityAuditor::new(AuditOptions::default()).audit(&[login("Weak", "abc")]);
+            let messages = engine.audit_completed(&report).unwrap().into_messages();
+            assert_eq!(messages.len(), 1);
+            assert_eq!(messages[0].message, "1 weak passwords");
+            assert_eq!(messages[0].credential_id, None);
+        }
+
+        #[test]
+        fn test_sandbox() {
+            let script = |source: &str| {
+                AutomationScript::new("sandbox", AutomationEvent::BeforeSave, source)
+            };
+            let mut engine = AutomationEngine::new();
+
+            assert!(matches!(
+                engine.add_script(script("this is not rhai (")),
+                Err(AutomationError::Compile { .. })
+            ));
+            assert!(matches!(
+                engine.add_script(script(r#"eval("reject(\"x\")")"#)),
+                Err(AutomationError::Compile { .. })
+            ));
+
+            let credentials = [login("Example", "secret")];
+            for source in [
+                "loop {}",
+                r#"import "/etc/passwd" as secrets;"#,
+                "credential.title.bogus_method()",
+            ] {
+                engine.add_script(script(source)).unwrap();
+                assert!(
+                    matches!(
+                        engine.before_save(&credentials),
+                        Err(AutomationError::Runtime { .. })
+                    ),
+                    "{} should fail",
+                    source
+                );
+            }
+
+            // Messages from a failed run do not leak into the next one
+            engine
+                .add_script(script(r#"reject("first"); throw "stop";"#))
+                .unwrap();
+            assert!(engine.before_save(&credentials).is_err());
+            engine
+                .add_script(script("print(credential.fields)"))
+                .unwrap();
+            assert_eq!(
+                engine.before_save(&credentials).unwrap(),
+                HookOutcome::default()
+            );
+        }
+
+        #[test]
+        fn test_invalid_changes_fail() {
+            let engine = AutomationEngine::from_scripts(&[AutomationScript::new(
+                "bad",
+                AutomationEvent::CredentialCreated,
+                "credential.tags = 42;",
+            )])
+            .unwrap();
+            let mut credential = login("Example", "secret");
+            let error = engine.credential_created(&mut credential).unwrap_err();
+            assert!(error
+                .to_string()
+                .contains("credential.tags must be an array"));
+        }
+    }
+}
@@ -6,6 +6,9 @@
 
 use crate::core::{CoreError, CoreResult, UnifiedMemoryRepository};
 use crate::models::CredentialRecord;
+use crate::utils::bitwarden::BitwardenExporter;
+use crate::utils::html_viewer::HtmlViewerExporter;
+use crate::utils::import::ImportReport;
 use crate::utils::time_utils;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -23,9 +26,41 @@ pub enum ExportFormat {
     Yaml,
     /// Encrypted ZipLock backup format
     ZipLockBackup,
+    /// Unencrypted Bitwarden JSON, importable by Bitwarden and compatible tools
+    BitwardenJson,
+    /// Read-only HTML page, decrypted in the browser with a passphrase
+    HtmlViewer,
 }
 
 impl ExportFormat {
+    /// Every built-in format; plugins can add more, see
+    /// [`PluginManager::export_formats`](crate::core::PluginManager::export_formats)
+    pub const ALL: [ExportFormat; 6] = [
+        ExportFormat::Json,
+        ExportFormat::Csv,
+        ExportFormat::Yaml,
+        ExportFormat::ZipLockBackup,
+        ExportFormat::BitwardenJson,
+        ExportFormat::HtmlViewer,
+    ];
+
+    /// Identifier used to pick the format by name
+    pub fn id(&self) -> &'static str {
+        match self {
+            ExportFormat::Json => "json",
+            ExportFormat::Csv => "csv",
+            ExportFormat::Yaml => "yaml",
+            ExportFormat::ZipLockBackup => "backup",
+            ExportFormat::BitwardenJson => "bitwarden",
+            ExportFormat::HtmlViewer => "html",
+        }
+    }
+
+    /// The built-in format with identifier `id`
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|format| format.id() == id)
+    }
+
     /// Get file extension for the format
     pub fn extension(&self) -> &'static str {
         match self {
@@ -33,6 +68,8 @@ impl ExportFormat {
             ExportFormat::Csv => "csv",
             ExportFormat::Yaml => "yaml",
             ExportFormat::ZipLockBackup => "zlb",
+            ExportFormat::BitwardenJson => "json",
+            ExportFormat::HtmlViewer => "html",
         }
     }
 
@@ -43,6 +80,8 @@ impl ExportFormat {
             ExportFormat::Csv => "text/csv",
             ExportFormat::Yaml => "text/yaml",
             ExportFormat::ZipLockBackup => "application/octet-stream",
+            ExportFormat::BitwardenJson => "application/json",
+            ExportFormat::HtmlViewer => "text/html",
         }
     }
 
@@ -53,6 +92,8 @@ impl ExportFormat {
             ExportFormat::Csv => "CSV (Comma-Separated Values)",
             ExportFormat::Yaml => "YAML Format",
             ExportFormat::ZipLockBackup => "ZipLock Backup",
+            ExportFormat::BitwardenJson => "Bitwarden JSON (unencrypted)",
+            ExportFormat::HtmlViewer => "Encrypted HTML viewer",
         }
     }
 }
@@ -74,7 +115,10 @@ pub struct ExportOptions {
     pub credential_types: Option<Vec<String>>,
     /// Filter by tags
     pub required_tags: Option<Vec<String>>,
-    /// Encryption password for backup format
+    /// Export only these credentials, by ID
+    pub credential_ids: Option<Vec<String>>,
+    /// Encryption password for the backup format, and the passphrase of an
+    /// HTML viewer
     pub encryption_password: Option<String>,
 }
 
@@ -88,6 +132,7 @@ impl Default for ExportOptions {
             include_notes: true,
             credential_types: None,
             required_tags: None,
+            credential_ids: None,
             encryption_password: None,
         }
     }
@@ -106,6 +151,9 @@ pub struct BackupMetadata {
     pub credential_count: usize,
     /// Original repository path (if available)
     pub source_path: Option<String>,
+    /// ID of the repository the credentials were exported from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_repository_id: Option<String>,
     /// Backup description
     pub description: Option<String>,
     /// Checksum for integrity verification
@@ -124,6 +172,9 @@ pub struct BackupData {
 }
 
 /// CSV export record for spreadsheet compatibility
+///
+/// `id` and `origin` come last so the layout stays readable as ZipLock CSV,
+/// which imports the ID and ignores the origin.
 #[derive(Debug, Clone, Serialize)]
 struct CsvRecord {
     title: String,
@@ -137,6 +188,8 @@ struct CsvRecord {
     tags: String,
     created_at: String,
     updated_at: String,
+    id: String,
+    origin: String,
 }
 
 /// Backup and export utilities
@@ -153,7 +206,18 @@ impl BackupManager {
             return Err(CoreError::NotInitialized);
         }
 
-        let credentials = repository.list_credentials()?;
+        let credentials = repository
+            .list_credentials()?
+            .into_iter()
+            // Large note bodies stay in the archive until asked for
+            .map(|credential| {
+                if credential.fields.values().any(|f| f.is_note_unloaded()) {
+                    repository.get_credential_with_notes(&credential.id)
+                } else {
+                    Ok(credential)
+                }
+            })
+            .collect::<CoreResult<Vec<_>>>()?;
         let filtered_credentials = Self::filter_credentials(&credentials, options);
 
         let backup_data = BackupData {
@@ -163,6 +227,7 @@ impl BackupManager {
                 format_version: "1.0".to_string(),
                 credential_count: filtered_credentials.len(),
                 source_path: None,
+                source_repository_id: Some(repository.get_metadata().repository_id.clone()),
                 description,
                 checksum: Self::calculate_checksum(&filtered_credentials),
             },
@@ -185,9 +250,23 @@ impl BackupManager {
             ExportFormat::Csv => Self::export_csv(&backup, options),
             ExportFormat::Yaml => Self::export_yaml(&backup, options),
             ExportFormat::ZipLockBackup => Self::export_backup(&backup, options),
+            ExportFormat::BitwardenJson => BitwardenExporter::export(&backup.credentials),
+            ExportFormat::HtmlViewer => Self::export_viewer(&backup, options),
         }
     }
 
+    /// Export to an encrypted HTML viewer; needs a passphrase
+    fn export_viewer(backup: &BackupData, options: &ExportOptions) -> CoreResult<Vec<u8>> {
+        let passphrase =
+            options
+                .encryption_password
+                .as_deref()
+                .ok_or_else(|| CoreError::ValidationError {
+                    message: "An HTML viewer export needs a passphrase".to_string(),
+                })?;
+        HtmlViewerExporter::new(passphrase).export(&backup.credentials)
+    }
+
     /// Export to JSON format
     fn export_json(backup: &BackupData, _options: &ExportOptions) -> CoreResult<Vec<u8>> {
         serde_json::to_vec_pretty(backup).map_err(|e| CoreError::SerializationError {
@@ -219,6 +298,12 @@ impl BackupManager {
                 },
                 created_at: time_utils::format_timestamp(credential.created_at),
                 updated_at: time_utils::format_timestamp(credential.updated_at),
+                id: credential.id.clone(),
+                origin: backup
+                    .metadata
+                    .source_repository_id
+                    .clone()
+                    .unwrap_or_default(),
             };
 
             writer
@@ -270,10 +355,35 @@ impl BackupManager {
                 message: format!("Backup import failed: {}", e),
             })
         } else {
-            // Try as plain JSON
-            serde_json::from_slice(data).map_err(|e| CoreError::SerializationError {
-                message: format!("Backup import failed: {}", e),
-            })
+            // Try as plain JSON, then as a YAML export
+            serde_json::from_slice(data)
+                .or_else(|e| serde_yaml::from_slice(data).map_err(|_| e))
+                .map_err(|e| CoreError::SerializationError {
+                    message: format!("Backup import failed: {}", e),
+                })
+        }
+    }
+
+    /// Import a JSON or ZipLock backup into a repository
+    ///
+    /// Credentials keep the IDs they were exported with, so importing the
+    /// same export again updates them in place instead of adding copies.
+    /// With `assign_new_ids` every credential is added as a new one.
+    pub fn import_into(
+        data: &[u8],
+        password: Option<&str>,
+        repository: &mut UnifiedMemoryRepository,
+        assign_new_ids: bool,
+    ) -> CoreResult<ImportReport> {
+        let backup = Self::import_backup(data, password)?;
+        let report = ImportReport {
+            credentials: backup.credentials,
+            ..Default::default()
+        };
+        if assign_new_ids {
+            report.assign_new_ids().import_into(repository)
+        } else {
+            report.import_into(repository)
         }
     }
 
@@ -298,6 +408,12 @@ impl BackupManager {
                     }
                 }
 
+                if let Some(ref ids) = options.credential_ids {
+                    if !ids.contains(&cred.id) {
+                        return false;
+                    }
+                }
+
                 // Filter by required tags
                 if let Some(ref required_tags) = options.required_tags {
                     if !required_tags.iter().all(|tag| cred.tags.contains(tag)) {
@@ -376,6 +492,8 @@ impl BackupManager {
             ExportFormat::Csv => Self::export_csv(backup, options)?,
             ExportFormat::Yaml => Self::export_yaml(backup, options)?,
             ExportFormat::ZipLockBackup => Self::export_backup(backup, options)?,
+            ExportFormat::BitwardenJson => BitwardenExporter::export(&backup.credentials)?,
+            ExportFormat::HtmlViewer => Self::export_viewer(backup, options)?,
         };
 
         fs::write(path, data).map_err(|e| CoreError::SerializationError {
@@ -549,6 +667,22 @@ mod tests {
         assert!(csv_str.contains("Test Login"));
     }
 
+    #[test]
+    fn test_export_bitwarden_json() {
+        let repo = create_test_repository();
+        let options = ExportOptions {
+            format: ExportFormat::BitwardenJson,
+            ..Default::default()
+        };
+
+        let data = BackupManager::export_repository(&repo, &options).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&data).unwrap();
+
+        assert_eq!(json["encrypted"], false);
+        assert_eq!(json["items"].as_array().unwrap().len(), 2);
+        assert!(json.get("metadata").is_none());
+    }
+
     #[test]
     fn test_filtering() {
         let repo = create_test_repository();
@@ -560,6 +694,29 @@ mod tests {
         let backup = BackupManager::create_backup(&repo, &options, None).unwrap();
         assert_eq!(backup.credentials.len(), 1);
         assert_eq!(backup.credentials[0].credential_type, "login");
+
+        let id = backup.credentials[0].id.clone();
+        let options = ExportOptions {
+            credential_ids: Some(vec![id.clone()]),
+            ..Default::default()
+        };
+        let backup = BackupManager::create_backup(&repo, &options, None).unwrap();
+        assert_eq!(backup.credentials.len(), 1);
+        assert_eq!(backup.credentials[0].id, id);
+    }
+
+    #[test]
+    fn test_html_viewer_needs_passphrase() {
+        let repo = create_test_repository();
+        let options = ExportOptions {
+            format: ExportFormat::HtmlViewer,
+            ..Default::default()
+        };
+        assert!(matches!(
+            BackupManager::export_repository(&repo, &options),
+            Err(CoreError::ValidationError { .. })
+        ));
+        assert_eq!(options.format.extension(), "html");
     }
 
     #[test]
@@ -611,6 +768,47 @@ mod tests {
         assert!(BackupManager::verify_backup(&imported_backup));
     }
 
+    #[test]
+    fn test_reimport_keeps_ids() {
+        let mut repo = create_test_repository();
+        let repository_id = repo.get_metadata().repository_id.clone();
+
+        for format in [ExportFormat::Json, ExportFormat::Yaml] {
+            let options = ExportOptions {
+                format,
+                ..Default::default()
+            };
+            let exported = BackupManager::export_repository(&repo, &options).unwrap();
+            let backup = BackupManager::import_backup(&exported, None).unwrap();
+            assert_eq!(
+                backup.metadata.source_repository_id.as_deref(),
+                Some(repository_id.as_str())
+            );
+
+            let report = BackupManager::import_into(&exported, None, &mut repo, false).unwrap();
+            assert_eq!(report.updated.len(), 2, "{:?}", format);
+            assert_eq!(report.imported_count(), 0);
+            assert_eq!(repo.list_credentials().unwrap().len(), 2);
+        }
+
+        let exported = BackupManager::export_repository(&repo, &ExportOptions::default()).unwrap();
+        let report = BackupManager::import_into(&exported, None, &mut repo, true).unwrap();
+        assert_eq!(report.imported_count(), 2);
+        assert_eq!(repo.list_credentials().unwrap().len(), 4);
+
+        let csv = BackupManager::export_repository(
+            &repo,
+            &ExportOptions {
+                format: ExportFormat::Csv,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert!(csv.lines().next().unwrap().ends_with(",id,origin"));
+        assert!(csv.contains(&repository_id));
+    }
+
     #[test]
     fn test_file_operations() {
         let test_dir = get_test_results_dir();
@@ -653,11 +851,17 @@ mod tests {
             ExportFormat::Csv,
             ExportFormat::Yaml,
             ExportFormat::ZipLockBackup,
+            ExportFormat::BitwardenJson,
         ] {
             assert!(!format.extension().is_empty());
             assert!(!format.mime_type().is_empty());
             assert!(!format.description().is_empty());
         }
+
+        for format in ExportFormat::ALL {
+            assert_eq!(ExportFormat::from_id(format.id()), Some(format));
+        }
+        assert_eq!(ExportFormat::from_id("keepass"), None);
     }
 
     #[test]
@@ -0,0 +1,394 @@
+//! Automatic local backups with rotation
+//!
+//! [`BackupScheduler`] copies the saved repository archive into a backup
+//! directory, at most once per interval, and prunes old copies with a
+//! grandfather-father-son policy: the newest backup of each of the last few
+//! hours, days and weeks is kept. Like off-site backups, the copies are the
+//! archive itself and stay encrypted with the master password.
+//!
+//! The repository manager runs the scheduler after every save (see
+//! `UnifiedRepositoryManager::set_backup_scheduler`) and can restore a
+//! backup over the current archive.
+
+use chrono::{DateTime, Datelike, Duration, NaiveDateTime, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::core::{CoreError, CoreResult, FileError};
+use crate::utils::offsite_backup::{OffsiteUploader, BACKUP_EXTENSION, BACKUP_PREFIX};
+
+/// The hour, day or week a backup falls in
+type Slot = fn(&DateTime<Utc>) -> (i32, u32, u32);
+
+/// How many backups to keep at each granularity
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RotationPolicy {
+    /// Newest backup of each of the last `hourly` hours with a backup
+    pub hourly: usize,
+    /// Newest backup of each of the last `daily` days with a backup
+    pub daily: usize,
+    /// Newest backup of each of the last `weekly` ISO weeks with a backup
+    pub weekly: usize,
+}
+
+impl Default for RotationPolicy {
+    fn default() -> Self {
+        Self {
+            hourly: 24,
+            daily: 7,
+            weekly: 4,
+        }
+    }
+}
+
+impl RotationPolicy {
+    /// Which of `backups` to keep; the newest one always is
+    pub fn retain<'a>(&self, backups: &'a [BackupEntry]) -> Vec<&'a BackupEntry> {
+        let mut newest_first: Vec<&BackupEntry> = backups.iter().collect();
+        newest_first.sort_by_key(|backup| Reverse(backup.created_at));
+
+        let mut kept: HashSet<&Path> = HashSet::new();
+        if let Some(newest) = newest_first.first() {
+            kept.insert(&newest.path);
+        }
+        let slots: [(usize, Slot); 3] = [
+            (self.hourly, |t| (t.year(), t.ordinal(), t.hour())),
+            (self.daily, |t| (t.year(), t.ordinal(), 0)),
+            (self.weekly, |t| {
+                let week = t.iso_week();
+                (week.year(), week.week(), 0)
+            }),
+        ];
+        for (limit, slot) in slots {
+            let mut seen = HashSet::new();
+            for backup in &newest_first {
+                if seen.len() == limit {
+                    break;
+                }
+                if seen.insert(slot(&backup.created_at)) {
+                    kept.insert(&backup.path);
+                }
+            }
+        }
+
+        newest_first
+            .into_iter()
+            .filter(|backup| kept.contains(backup.path.as_path()))
+            .collect()
+    }
+}
+
+/// A backup file in the backup directory
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupEntry {
+    /// Location of the backup
+    pub path: PathBuf,
+    /// When the backup was taken, from its file name
+    pub created_at: DateTime<Utc>,
+    /// Size in bytes
+    pub size: u64,
+}
+
+/// Result of a completed backup
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupOutcome {
+    /// The new backup
+    pub path: PathBuf,
+    /// Old backups removed by rotation
+    pub removed: Vec<PathBuf>,
+    /// Rotation failure; the backup itself still succeeded
+    pub rotation_error: Option<String>,
+}
+
+/// Writes rotating backups of a repository archive to a directory
+#[derive(Debug, Clone)]
+pub struct BackupScheduler {
+    directory: PathBuf,
+    policy: RotationPolicy,
+    interval: Duration,
+    last_backup: Option<DateTime<Utc>>,
+    last_error: Option<String>,
+}
+
+impl BackupScheduler {
+    /// Back up into `directory` on every save with the default policy
+    ///
+    /// Use one directory per repository; rotation treats every backup in it
+    /// as belonging to the same one.
+    pub fn new<P: Into<PathBuf>>(directory: P) -> Self {
+        Self {
+            directory: directory.into(),
+            policy: RotationPolicy::default(),
+            interval: Duration::zero(),
+            last_backup: None,
+            last_error: None,
+        }
+    }
+
+    /// Keep backups according to `policy`
+    pub fn with_policy(mut self, policy: RotationPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Take at most one backup per `interval`
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Directory the backups are written to
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// The rotation policy
+    pub fn policy(&self) -> RotationPolicy {
+        self.policy
+    }
+
+    /// Error from the last scheduled backup, cleared when one succeeds
+    pub fn last_error(&self) -> Option<&str> {
+        self.last_error.as_deref()
+    }
+
+    pub(crate) fn set_last_error(&mut self, error: Option<String>) {
+        self.last_error = error;
+    }
+
+    /// Whether the interval has passed since the newest backup
+    pub fn is_due(&self, now: DateTime<Utc>) -> bool {
+        let last = self.last_backup.or_else(|| {
+            self.list()
+                .ok()
+                .and_then(|backups| backups.first().map(|b| b.created_at))
+        });
+        match last {
+            Some(last) => now - last >= self.interval,
+            None => true,
+        }
+    }
+
+    /// Back up `archive` if the interval has passed and it differs from the
+    /// newest backup
+    pub fn backup(
+        &mut self,
+        archive: &[u8],
+        now: DateTime<Utc>,
+    ) -> CoreResult<Option<BackupOutcome>> {
+        if !self.is_due(now) {
+            return Ok(None);
+        }
+        if let Some(newest) = self.list()?.first() {
+            if fs::read(&newest.path).is_ok_and(|data| data == archive) {
+                return Ok(None);
+            }
+        }
+        self.backup_now(archive, now).map(Some)
+    }
+
+    /// Back up `archive` regardless of the interval and rotate old copies
+    pub fn backup_now(&mut self, archive: &[u8], now: DateTime<Utc>) -> CoreResult<BackupOutcome> {
+        fs::create_dir_all(&self.directory).map_err(|e| {
+            io_error(format!(
+                "Failed to create backup directory {}: {}",
+                self.directory.display(),
+                e
+            ))
+        })?;
+
+        let path = self.directory.join(OffsiteUploader::backup_name(now));
+        let partial = path.with_extension("partial");
+        fs::write(&partial, archive)
+            .and_then(|_| fs::rename(&partial, &path))
+            .map_err(|e| {
+                let _ = fs::remove_file(&partial);
+                io_error(format!("Failed to write backup {}: {}", path.display(), e))
+            })?;
+        self.last_backup = Some(now);
+
+        let (removed, rotation_error) = match self.prune() {
+            Ok(removed) => (removed, None),
+            Err(e) => (Vec::new(), Some(e.to_string())),
+        };
+        Ok(BackupOutcome {
+            path,
+            removed,
+            rotation_error,
+        })
+    }
+
+    /// Backups in the directory, newest first
+    pub fn list(&self) -> CoreResult<Vec<BackupEntry>> {
+        let entries = match fs::read_dir(&self.directory) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => {
+                return Err(io_error(format!(
+                    "Failed to read backup directory {}: {}",
+                    self.directory.display(),
+                    e
+                )))
+            }
+        };
+
+        let mut backups: Vec<BackupEntry> = entries
+            .flatten()
+            .filter_map(|entry| {
+                let created_at = parse_backup_name(entry.file_name().to_str()?)?;
+                let size = entry.metadata().ok()?.len();
+                Some(BackupEntry {
+                    path: entry.path(),
+                    created_at,
+                    size,
+                })
+            })
+            .collect();
+        backups.sort_by_key(|backup| Reverse(backup.created_at));
+        Ok(backups)
+    }
+
+    /// Delete the backups the policy does not keep
+    pub fn prune(&self) -> CoreResult<Vec<PathBuf>> {
+        let backups = self.list()?;
+        let kept: HashSet<&Path> = self
+            .policy
+            .retain(&backups)
+            .into_iter()
+            .map(|backup| backup.path.as_path())
+            .collect();
+
+        let mut removed = Vec::new();
+        for backup in &backups {
+            if !kept.contains(backup.path.as_path()) {
+                fs::remove_file(&backup.path).map_err(|e| {
+                    io_error(format!(
+                        "Failed to remove old backup {}: {}",
+                        backup.path.display(),
+                        e
+                    ))
+                })?;
+                removed.push(backup.path.clone());
+            }
+        }
+        Ok(removed)
+    }
+}
+
+/// When a backup named by [`OffsiteUploader::backup_name`] was taken
+fn parse_backup_name(name: &str) -> Option<DateTime<Utc>> {
+    let stamp = name
+        .strip_prefix(BACKUP_PREFIX)?
+        .strip_suffix(BACKUP_EXTENSION)?;
+    NaiveDateTime::parse_from_str(stamp, "%Y%m%dT%H%M%SZ")
+        .ok()
+        .map(|time| time.and_utc())
+}
+
+fn io_error(message: String) -> CoreError {
+    CoreError::FileOperation(FileError::IoError { message })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 3, day, hour, minute, 0).unwrap()
+    }
+
+    fn entry(time: DateTime<Utc>) -> BackupEntry {
+        BackupEntry {
+            path: PathBuf::from(OffsiteUploader::backup_name(time)),
+            created_at: time,
+            size: 0,
+        }
+    }
+
+    #[test]
+    fn test_parse_backup_name() {
+        let time = at(5, 14, 30);
+        assert_eq!(
+            parse_backup_name(&OffsiteUploader::backup_name(time)),
+            Some(time)
+        );
+        assert_eq!(parse_backup_name("notes.txt"), None);
+        assert_eq!(parse_backup_name("ziplock-backup-yesterday.7z"), None);
+    }
+
+    #[test]
+    fn test_rotation_keeps_newest_per_slot() {
+        let policy = RotationPolicy {
+            hourly: 2,
+            daily: 2,
+            weekly: 2,
+        };
+        // 2024-03-04 is a Monday
+        let backups: Vec<BackupEntry> = [
+            at(1, 9, 0),   // previous week
+            at(4, 9, 0),   // Monday
+            at(5, 9, 0),   // Tuesday, superseded by 11:00
+            at(5, 11, 0),  // Tuesday
+            at(6, 9, 0),   // Wednesday morning
+            at(6, 10, 0),  // superseded within the hour
+            at(6, 10, 30), // newest
+        ]
+        .into_iter()
+        .map(entry)
+        .collect();
+
+        let kept: Vec<DateTime<Utc>> = policy
+            .retain(&backups)
+            .into_iter()
+            .map(|backup| backup.created_at)
+            .collect();
+        assert_eq!(
+            kept,
+            vec![at(6, 10, 30), at(6, 9, 0), at(5, 11, 0), at(1, 9, 0)]
+        );
+
+        let nothing = RotationPolicy {
+            hourly: 0,
+            daily: 0,
+            weekly: 0,
+        };
+        assert_eq!(nothing.retain(&backups).len(), 1);
+    }
+
+    #[test]
+    fn test_scheduler_writes_and_prunes() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut scheduler = BackupScheduler::new(dir.path().join("vault"))
+            .with_interval(Duration::minutes(30))
+            .with_policy(RotationPolicy {
+                hourly: 2,
+                daily: 0,
+                weekly: 0,
+            });
+        assert!(scheduler.list().unwrap().is_empty());
+
+        let first = scheduler.backup(b"v1", at(6, 9, 0)).unwrap().unwrap();
+        assert_eq!(fs::read(&first.path).unwrap(), b"v1");
+        // Within the interval, and unchanged archives are skipped
+        assert!(scheduler.backup(b"v2", at(6, 9, 10)).unwrap().is_none());
+        assert!(scheduler.backup(b"v1", at(6, 10, 0)).unwrap().is_none());
+
+        scheduler.backup(b"v2", at(6, 10, 0)).unwrap().unwrap();
+        let outcome = scheduler.backup(b"v3", at(6, 11, 0)).unwrap().unwrap();
+        assert_eq!(outcome.removed, vec![first.path]);
+
+        let backups = scheduler.list().unwrap();
+        assert_eq!(backups.len(), 2);
+        assert_eq!(backups[0].created_at, at(6, 11, 0));
+        assert_eq!(backups[0].size, 2);
+
+        // Other files in the directory are left alone
+        fs::write(scheduler.directory().join("notes.txt"), b"keep").unwrap();
+        scheduler.prune().unwrap();
+        assert!(scheduler.directory().join("notes.txt").exists());
+    }
+}
@@ -0,0 +1,548 @@
+//! Bitwarden-compatible JSON export
+//!
+//! Produces the unencrypted `.json` layout accepted by Bitwarden's importer
+//! (and by most tools that read Bitwarden exports): a list of folders and a
+//! list of typed items with custom fields.
+
+use crate::core::{CoreError, CoreResult};
+use crate::models::{CredentialField, CredentialRecord, FieldType};
+use chrono::{TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use uuid::Uuid;
+
+/// Bitwarden item type codes
+pub(crate) const ITEM_LOGIN: u8 = 1;
+pub(crate) const ITEM_SECURE_NOTE: u8 = 2;
+pub(crate) const ITEM_CARD: u8 = 3;
+pub(crate) const ITEM_IDENTITY: u8 = 4;
+pub(crate) const ITEM_SSH_KEY: u8 = 5;
+
+/// Bitwarden custom field type codes
+pub(crate) const FIELD_TEXT: u8 = 0;
+pub(crate) const FIELD_HIDDEN: u8 = 1;
+pub(crate) const FIELD_BOOLEAN: u8 = 2;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct BitwardenExport {
+    #[serde(default)]
+    pub encrypted: bool,
+    #[serde(default)]
+    pub folders: Vec<BitwardenFolder>,
+    /// Only present in organization exports
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub collections: Vec<BitwardenFolder>,
+    #[serde(default)]
+    pub items: Vec<BitwardenItem>,
+}
+
+/// A folder, or an organization collection, which has the same shape
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct BitwardenFolder {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct BitwardenItem {
+    #[serde(default)]
+    pub id: String,
+    #[serde(default)]
+    pub folder_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub collection_ids: Option<Vec<String>>,
+    #[serde(rename = "type")]
+    pub item_type: u8,
+    #[serde(default)]
+    pub reprompt: u8,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub notes: Option<String>,
+    #[serde(default)]
+    pub favorite: bool,
+    #[serde(default)]
+    pub fields: Vec<BitwardenField>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub login: Option<BitwardenLogin>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secure_note: Option<BitwardenSecureNote>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub card: Option<BitwardenCard>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub identity: Option<BitwardenIdentity>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssh_key: Option<BitwardenSshKey>,
+    #[serde(default)]
+    pub creation_date: Option<String>,
+    #[serde(default)]
+    pub revision_date: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct BitwardenField {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub value: Option<String>,
+    #[serde(rename = "type", default)]
+    pub field_type: u8,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct BitwardenLogin {
+    #[serde(default)]
+    pub uris: Vec<BitwardenUri>,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    #[serde(default)]
+    pub totp: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub(crate) struct BitwardenUri {
+    #[serde(rename = "match", default)]
+    pub match_type: Option<u8>,
+    #[serde(default)]
+    pub uri: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub(crate) struct BitwardenSecureNote {
+    #[serde(rename = "type", default)]
+    pub note_type: u8,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct BitwardenCard {
+    #[serde(default)]
+    pub cardholder_name: Option<String>,
+    #[serde(default)]
+    pub brand: Option<String>,
+    #[serde(default)]
+    pub number: Option<String>,
+    #[serde(default)]
+    pub exp_month: Option<String>,
+    #[serde(default)]
+    pub exp_year: Option<String>,
+    #[serde(default)]
+    pub code: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct BitwardenIdentity {
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub first_name: Option<String>,
+    #[serde(default)]
+    pub middle_name: Option<String>,
+    #[serde(default)]
+    pub last_name: Option<String>,
+    #[serde(default)]
+    pub address1: Option<String>,
+    #[serde(default)]
+    pub address2: Option<String>,
+    #[serde(default)]
+    pub address3: Option<String>,
+    #[serde(default)]
+    pub city: Option<String>,
+    #[serde(default)]
+    pub state: Option<String>,
+    #[serde(default)]
+    pub postal_code: Option<String>,
+    #[serde(default)]
+    pub country: Option<String>,
+    #[serde(default)]
+    pub company: Option<String>,
+    #[serde(default)]
+    pub email: Option<String>,
+    #[serde(default)]
+    pub phone: Option<String>,
+    #[serde(default)]
+    pub ssn: Option<String>,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub passport_number: Option<String>,
+    #[serde(default)]
+    pub license_number: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct BitwardenSshKey {
+    #[serde(default)]
+    pub private_key: Option<String>,
+    #[serde(default)]
+    pub public_key: Option<String>,
+    #[serde(default)]
+    pub key_fingerprint: Option<String>,
+}
+
+/// Exporter for Bitwarden's unencrypted JSON format
+pub struct BitwardenExporter;
+
+impl BitwardenExporter {
+    /// Export credentials as Bitwarden JSON bytes
+    pub fn export(credentials: &[CredentialRecord]) -> CoreResult<Vec<u8>> {
+        let export = Self::build(credentials);
+        serde_json::to_vec_pretty(&export).map_err(|e| CoreError::SerializationError {
+            message: format!("Bitwarden export failed: {}", e),
+        })
+    }
+
+    pub(crate) fn build(credentials: &[CredentialRecord]) -> BitwardenExport {
+        // Bitwarden nests folders by name ("Work/Servers"), matching folder_path
+        let mut folder_ids: BTreeMap<String, String> = BTreeMap::new();
+        for credential in credentials {
+            if let Some(path) = credential.folder_path.as_deref().filter(|p| !p.is_empty()) {
+                folder_ids
+                    .entry(path.to_string())
+                    .or_insert_with(|| Uuid::new_v4().to_string());
+            }
+        }
+
+        let items = credentials
+            .iter()
+            .map(|credential| {
+                let folder_id = credential
+                    .folder_path
+                    .as_ref()
+                    .and_then(|path| folder_ids.get(path))
+                    .cloned();
+                Self::item(credential, folder_id)
+            })
+            .collect();
+
+        BitwardenExport {
+            encrypted: false,
+            folders: folder_ids
+                .into_iter()
+                .map(|(name, id)| BitwardenFolder { id, name })
+                .collect(),
+            collections: Vec::new(),
+            items,
+        }
+    }
+
+    fn item(credential: &CredentialRecord, folder_id: Option<String>) -> BitwardenItem {
+        let mut fields = ItemFields::new(credential);
+
+        let mut item = BitwardenItem {
+            id: credential.id.clone(),
+            folder_id,
+            name: credential.title.clone(),
+            notes: credential.notes.clone().filter(|n| !n.is_empty()),
+            favorite: credential.favorite,
+            creation_date: iso_date(credential.created_at),
+            revision_date: iso_date(credential.updated_at),
+            ..Default::default()
+        };
+
+        match credential.credential_type.as_str() {
+            "credit_card" => {
+                item.item_type = ITEM_CARD;
+                let (exp_month, exp_year) = fields
+                    .take_type(FieldType::ExpiryDate)
+                    .and_then(|expiry| split_expiry(&expiry))
+                    .unzip();
+                item.card = Some(BitwardenCard {
+                    cardholder_name: fields.take_named(&["cardholder", "cardholder_name", "name"]),
+                    brand: fields.take_named(&["brand", "card_type"]),
+                    number: fields.take_type(FieldType::CreditCardNumber),
+                    exp_month,
+                    exp_year,
+                    code: fields.take_type(FieldType::Cvv),
+                });
+            }
+            "identity" => {
+                item.item_type = ITEM_IDENTITY;
+                item.identity = Some(BitwardenIdentity {
+                    title: fields.take_named(&["title"]),
+                    first_name: fields.take_named(&["first_name"]),
+                    middle_name: fields.take_named(&["middle_name"]),
+                    last_name: fields.take_named(&["last_name"]),
+                    address1: fields.take_named(&["address", "address1", "street"]),
+                    address2: fields.take_named(&["address2"]),
+                    address3: fields.take_named(&["address3"]),
+                    city: fields.take_named(&["city"]),
+                    state: fields.take_named(&["state", "province"]),
+                    postal_code: fields.take_named(&["postal_code", "zip", "zip_code"]),
+                    country: fields.take_named(&["country"]),
+                    company: fields.take_named(&["company"]),
+                    email: fields.take_type(FieldType::Email),
+                    phone: fields.take_type(FieldType::Phone),
+                    ssn: fields.take_named(&["ssn"]),
+                    username: fields.take_type(FieldType::Username),
+                    passport_number: fields.take_named(&["passport_number"]),
+                    license_number: fields.take_named(&["license_number"]),
+                });
+            }
+            _ if fields.has_login_data() => {
+                item.item_type = ITEM_LOGIN;
+                item.login = Some(BitwardenLogin {
+                    uris: fields
+                        .take_all(FieldType::Url)
+                        .into_iter()
+                        .map(|uri| BitwardenUri {
+                            match_type: None,
+                            uri: Some(uri),
+                        })
+                        .collect(),
+                    username: fields.take_type(FieldType::Username),
+                    password: fields.take_type(FieldType::Password),
+                    totp: fields.take_totp(),
+                });
+            }
+            _ => {
+                item.item_type = ITEM_SECURE_NOTE;
+                item.secure_note = Some(BitwardenSecureNote { note_type: 0 });
+            }
+        }
+
+        item.fields = fields.remaining();
+        if !credential.tags.is_empty() {
+            item.fields.push(BitwardenField {
+                name: Some("Tags".to_string()),
+                value: Some(credential.tags.join(", ")),
+                field_type: FIELD_TEXT,
+            });
+        }
+
+        item
+    }
+}
+
+/// Tracks which credential fields have been mapped onto Bitwarden properties
+struct ItemFields<'a> {
+    fields: Vec<(&'a String, &'a CredentialField)>,
+    used: HashSet<&'a str>,
+}
+
+impl<'a> ItemFields<'a> {
+    fn new(credential: &'a CredentialRecord) -> Self {
+        let mut fields: Vec<_> = credential
+            .fields
+            .iter()
+            .filter(|(_, f)| !f.value.is_empty())
+            .collect();
+        fields.sort_by(|a, b| a.0.cmp(b.0));
+        Self {
+            fields,
+            used: HashSet::new(),
+        }
+    }
+
+    fn has_login_data(&self) -> bool {
+        self.fields.iter().any(|(_, f)| {
+            matches!(
+                f.field_type,
+                FieldType::Username | FieldType::Password | FieldType::Url | FieldType::TotpSecret
+            )
+        })
+    }
+
+    /// Take the first unused field matching a predicate, trying `preferred` names first
+    fn take_where<P>(&mut self, preferred: &[&str], predicate: P) -> Option<&'a CredentialField>
+    where
+        P: Fn(&str, &CredentialField) -> bool,
+    {
+        let available = |(name, field): &&(&String, &CredentialField)| {
+            !self.used.contains(name.as_str()) && predicate(name, field)
+        };
+        let (name, field) = preferred
+            .iter()
+            .find_map(|wanted| {
+                self.fields
+                    .iter()
+                    .filter(available)
+                    .find(|(name, _)| name.as_str() == *wanted)
+            })
+            .or_else(|| self.fields.iter().find(available))
+            .copied()?;
+
+        self.used.insert(name.as_str());
+        Some(field)
+    }
+
+    fn take_named(&mut self, names: &[&str]) -> Option<String> {
+        self.take_where(names, |name, _| names.contains(&name))
+            .map(|f| f.value.clone())
+    }
+
+    fn take_type(&mut self, field_type: FieldType) -> Option<String> {
+        self.take_where(&[], |_, f| f.field_type == field_type)
+            .map(|f| f.value.clone())
+    }
+
+    fn take_all(&mut self, field_type: FieldType) -> Vec<String> {
+        std::iter::from_fn(|| self.take_type(field_type.clone())).collect()
+    }
+
+    /// TOTP as a bare secret, or an otpauth URI when it uses non-default parameters
+    fn take_totp(&mut self) -> Option<String> {
+        let field = self.take_where(&[], |_, f| f.field_type == FieldType::TotpSecret)?;
+        let custom = ["algorithm", "digits", "period"]
+            .iter()
+            .any(|key| field.metadata.contains_key(*key));
+        if !custom {
+            return Some(field.value.clone());
+        }
+
+        let mut uri = url::Url::parse("otpauth://totp/").expect("static URI is valid");
+        {
+            let mut query = uri.query_pairs_mut();
+            query.append_pair("secret", &field.value);
+            for key in ["issuer", "algorithm", "digits", "period"] {
+                if let Some(value) = field.metadata.get(key) {
+                    query.append_pair(key, value);
+                }
+            }
+        }
+        Some(uri.to_string())
+    }
+
+    /// Unmapped fields as Bitwarden custom fields
+    fn remaining(&self) -> Vec<BitwardenField> {
+        self.fields
+            .iter()
+            .filter(|(name, _)| !self.used.contains(name.as_str()))
+            .map(|(name, field)| BitwardenField {
+                name: Some(field.label.clone().unwrap_or_else(|| name.to_string())),
+                value: Some(field.value.clone()),
+                field_type: if field.sensitive {
+                    FIELD_HIDDEN
+                } else {
+                    FIELD_TEXT
+                },
+            })
+            .collect()
+    }
+}
+
+/// Split ZipLock's `MM/YY` expiry into Bitwarden's month and four-digit year
+fn split_expiry(expiry: &str) -> Option<(String, String)> {
+    let (month, year) = expiry.split_once('/')?;
+    let month = month.trim().trim_start_matches('0').to_string();
+    let year = match year.trim() {
+        y if y.len() == 2 => format!("20{}", y),
+        y => y.to_string(),
+    };
+    Some((month, year))
+}
+
+fn iso_date(timestamp: i64) -> Option<String> {
+    Utc.timestamp_opt(timestamp, 0)
+        .single()
+        .map(|dt| dt.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn export(credentials: &[CredentialRecord]) -> serde_json::Value {
+        serde_json::from_slice(&BitwardenExporter::export(credentials).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_login_export() {
+        let mut login = CredentialRecord::new("GitHub".to_string(), "login".to_string());
+        login.folder_path = Some("Work/Dev".to_string());
+        login.favorite = true;
+        login.tags = vec!["code".to_string()];
+        login.set_field("username", CredentialField::username("octocat"));
+        login.set_field("password", CredentialField::password("hunter2"));
+        login.set_field("url", CredentialField::url("https://github.com"));
+        login.set_field("url_2", CredentialField::url("https://gist.github.com"));
+        login.set_field(
+            "totp_secret",
+            CredentialField::totp_secret("JBSWY3DPEHPK3PXP"),
+        );
+        login.set_field(
+            "recovery_pin",
+            CredentialField::password("1234").with_label("Recovery PIN"),
+        );
+
+        let json = export(&[login]);
+        assert_eq!(json["encrypted"], false);
+        assert_eq!(json["folders"][0]["name"], "Work/Dev");
+
+        let item = &json["items"][0];
+        assert_eq!(item["type"], ITEM_LOGIN);
+        assert_eq!(item["folderId"], json["folders"][0]["id"]);
+        assert_eq!(item["favorite"], true);
+        assert_eq!(item["login"]["username"], "octocat");
+        assert_eq!(item["login"]["password"], "hunter2");
+        assert_eq!(item["login"]["totp"], "JBSWY3DPEHPK3PXP");
+        assert_eq!(item["login"]["uris"][0]["uri"], "https://github.com");
+        assert_eq!(item["login"]["uris"][1]["uri"], "https://gist.github.com");
+
+        let fields = item["fields"].as_array().unwrap();
+        assert_eq!(fields.len(), 2);
+        assert_eq!(fields[0]["name"], "Recovery PIN");
+        assert_eq!(fields[0]["type"], FIELD_HIDDEN);
+        assert_eq!(fields[1]["name"], "Tags");
+    }
+
+    #[test]
+    fn test_card_and_note_export() {
+        let mut card = CredentialRecord::new("Visa".to_string(), "credit_card".to_string());
+        card.set_field("cardholder", CredentialField::text("Jane Doe"));
+        card.set_field(
+            "number",
+            CredentialField::new(FieldType::CreditCardNumber, "4111111111111111".into(), true),
+        );
+        card.set_field(
+            "expiry",
+            CredentialField::new(FieldType::ExpiryDate, "04/27".into(), false),
+        );
+        card.set_field(
+            "cvv",
+            CredentialField::new(FieldType::Cvv, "123".into(), true),
+        );
+
+        let mut note = CredentialRecord::new("Alarm".to_string(), "secure_note".to_string());
+        note.notes = Some("4242".to_string());
+
+        let json = export(&[card, note]);
+        let card = &json["items"][0];
+        assert_eq!(card["type"], ITEM_CARD);
+        assert_eq!(card["card"]["cardholderName"], "Jane Doe");
+        assert_eq!(card["card"]["expMonth"], "4");
+        assert_eq!(card["card"]["expYear"], "2027");
+        assert_eq!(card["card"]["code"], "123");
+        assert!(card["fields"].as_array().unwrap().is_empty());
+        assert!(card.get("login").is_none());
+
+        let note = &json["items"][1];
+        assert_eq!(note["type"], ITEM_SECURE_NOTE);
+        assert_eq!(note["secureNote"]["type"], 0);
+        assert_eq!(note["notes"], "4242");
+        assert!(note["folderId"].is_null());
+    }
+
+    #[test]
+    fn test_totp_with_custom_parameters() {
+        let mut login = CredentialRecord::new("Steam".to_string(), "login".to_string());
+        login.set_field(
+            "totp",
+            CredentialField::totp_secret("JBSWY3DPEHPK3PXP").with_metadata("digits", "8"),
+        );
+
+        let json = export(&[login]);
+        assert_eq!(
+            json["items"][0]["login"]["totp"],
+            "otpauth://totp/?secret=JBSWY3DPEHPK3PXP&digits=8"
+        );
+    }
+}
@@ -0,0 +1,491 @@
+//! Password breach checking against Have I Been Pwned
+//!
+//! Online checks use the Pwned Passwords range API with k-anonymity: only the
+//! first five hex characters of the password's SHA-1 hash leave the device,
+//! and the matching suffix is looked up locally in the returned list. For
+//! air-gapped machines a bloom filter built from the Pwned Passwords hash
+//! list can be used instead; it reports whether a password was seen but not
+//! how often.
+
+use sha1::{Digest, Sha1};
+#[cfg(not(target_arch = "wasm32"))]
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::Mutex;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Duration;
+
+use super::encryption::EncryptionUtils;
+
+/// Public Pwned Passwords API
+pub const HIBP_API_BASE: &str = "https://api.pwnedpasswords.com";
+
+#[cfg(not(target_arch = "wasm32"))]
+const PREFIX_LEN: usize = 5;
+const BLOOM_MAGIC: &[u8; 8] = b"ZLBLOOM1";
+const BLOOM_HEADER_LEN: usize = 8 + 8 + 4;
+
+/// Error types for breach checks
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BreachError {
+    /// The range API could not be reached or returned an error status
+    Network(String),
+    /// The range API response could not be parsed
+    InvalidResponse(String),
+    /// The bloom filter file is missing, unreadable or malformed
+    InvalidBloomFilter(String),
+}
+
+impl fmt::Display for BreachError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BreachError::Network(msg) => write!(f, "Breach check failed: {}", msg),
+            BreachError::InvalidResponse(msg) => {
+                write!(f, "Invalid response from breach API: {}", msg)
+            }
+            BreachError::InvalidBloomFilter(msg) => {
+                write!(f, "Invalid breach bloom filter: {}", msg)
+            }
+        }
+    }
+}
+
+impl std::error::Error for BreachError {}
+
+/// Result type for breach checks
+pub type BreachResult<T> = Result<T, BreachError>;
+
+/// Outcome of checking one password
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreachStatus {
+    /// The password does not appear in any known breach
+    NotFound,
+    /// The password appeared this many times across known breaches
+    Found(u64),
+    /// The offline filter matched; there is a small chance of a false positive
+    /// and no count is available
+    ProbablyFound,
+}
+
+impl BreachStatus {
+    /// Whether the password should be treated as compromised
+    pub fn is_breached(&self) -> bool {
+        !matches!(self, BreachStatus::NotFound)
+    }
+
+    /// Number of times the password was seen, when known
+    pub fn count(&self) -> Option<u64> {
+        match self {
+            BreachStatus::Found(count) => Some(*count),
+            _ => None,
+        }
+    }
+
+    /// Short description suitable for strength reports
+    pub fn describe(&self) -> Option<String> {
+        match self {
+            BreachStatus::NotFound => None,
+            BreachStatus::Found(1) => Some("Seen in 1 data breach".to_string()),
+            BreachStatus::Found(count) => Some(format!("Seen in {} data breaches", count)),
+            BreachStatus::ProbablyFound => {
+                Some("Appears in the Pwned Passwords breach list".to_string())
+            }
+        }
+    }
+}
+
+/// Uppercase hex SHA-1 of a password, as used by Pwned Passwords
+pub fn sha1_hex(password: &str) -> String {
+    EncryptionUtils::to_hex(&Sha1::digest(password.as_bytes())).to_ascii_uppercase()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+/// Parse a range API response into suffix → count
+///
+/// Padding entries (count 0, returned when `Add-Padding` is requested) are
+/// dropped.
+fn parse_range_response(body: &str) -> BreachResult<HashMap<String, u64>> {
+    let mut suffixes = HashMap::new();
+    for line in body.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        let (suffix, count) = line
+            .split_once(':')
+            .ok_or_else(|| BreachError::InvalidResponse(format!("malformed line '{}'", line)))?;
+        let count: u64 = count
+            .trim()
+            .parse()
+            .map_err(|_| BreachError::InvalidResponse(format!("invalid count in '{}'", line)))?;
+        if count > 0 {
+            suffixes.insert(suffix.to_ascii_uppercase(), count);
+        }
+    }
+    Ok(suffixes)
+}
+
+/// Bloom filter over SHA-1 hashes of breached passwords
+///
+/// File layout: the magic `ZLBLOOM1`, the bit count as a little-endian u64,
+/// the hash count as a little-endian u32, then the bit array. Bit positions
+/// are derived from the SHA-1 digest by double hashing, so a filter can be
+/// built straight from the published hash list without the plaintexts.
+#[derive(Debug, Clone)]
+pub struct BloomFilter {
+    bits: Vec<u8>,
+    bit_count: u64,
+    hash_count: u32,
+}
+
+impl BloomFilter {
+    /// Empty filter sized for `expected_items` at the given false positive rate
+    pub fn with_capacity(expected_items: u64, false_positive_rate: f64) -> Self {
+        let n = expected_items.max(1) as f64;
+        let p = false_positive_rate.clamp(1e-9, 0.5);
+        let ln2 = std::f64::consts::LN_2;
+        let bit_count = (-(n * p.ln()) / (ln2 * ln2)).ceil().max(8.0) as u64;
+        let hash_count = ((bit_count as f64 / n) * ln2).round().clamp(1.0, 32.0) as u32;
+        Self {
+            bits: vec![0; bit_count.div_ceil(8) as usize],
+            bit_count,
+            hash_count,
+        }
+    }
+
+    /// Parse a serialized filter
+    pub fn from_bytes(bytes: &[u8]) -> BreachResult<Self> {
+        if bytes.len() < BLOOM_HEADER_LEN || &bytes[..8] != BLOOM_MAGIC {
+            return Err(BreachError::InvalidBloomFilter(
+                "missing ZLBLOOM1 header".to_string(),
+            ));
+        }
+        let bit_count = u64::from_le_bytes(bytes[8..16].try_into().expect("8 bytes"));
+        let hash_count = u32::from_le_bytes(bytes[16..20].try_into().expect("4 bytes"));
+        let bits = bytes[BLOOM_HEADER_LEN..].to_vec();
+
+        if bit_count == 0 || hash_count == 0 || bits.len() as u64 != bit_count.div_ceil(8) {
+            return Err(BreachError::InvalidBloomFilter(format!(
+                "header declares {} bits and {} hashes but {} bytes of data follow",
+                bit_count,
+                hash_count,
+                bits.len()
+            )));
+        }
+
+        Ok(Self {
+            bits,
+            bit_count,
+            hash_count,
+        })
+    }
+
+    /// Serialize the filter
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(BLOOM_HEADER_LEN + self.bits.len());
+        bytes.extend_from_slice(BLOOM_MAGIC);
+        bytes.extend_from_slice(&self.bit_count.to_le_bytes());
+        bytes.extend_from_slice(&self.hash_count.to_le_bytes());
+        bytes.extend_from_slice(&self.bits);
+        bytes
+    }
+
+    /// Load a filter from disk
+    pub fn load<P: AsRef<Path>>(path: P) -> BreachResult<Self> {
+        let path = path.as_ref();
+        let bytes = fs::read(path).map_err(|e| {
+            BreachError::InvalidBloomFilter(format!("failed to read {}: {}", path.display(), e))
+        })?;
+        Self::from_bytes(&bytes)
+    }
+
+    /// Write the filter to disk
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> BreachResult<()> {
+        let path = path.as_ref();
+        fs::write(path, self.to_bytes()).map_err(|e| {
+            BreachError::InvalidBloomFilter(format!("failed to write {}: {}", path.display(), e))
+        })
+    }
+
+    /// Add a hash from the Pwned Passwords list (40 hex characters,
+    /// optionally followed by `:count`)
+    pub fn insert_hash(&mut self, line: &str) -> BreachResult<()> {
+        let digest = parse_digest(line)?;
+        for index in self.indexes(&digest) {
+            self.bits[(index / 8) as usize] |= 1 << (index % 8);
+        }
+        Ok(())
+    }
+
+    /// Add a plaintext password
+    pub fn insert_password(&mut self, password: &str) {
+        let digest: [u8; 20] = Sha1::digest(password.as_bytes()).into();
+        for index in self.indexes(&digest) {
+            self.bits[(index / 8) as usize] |= 1 << (index % 8);
+        }
+    }
+
+    /// Whether the password may be in the filter
+    pub fn contains_password(&self, password: &str) -> bool {
+        let digest: [u8; 20] = Sha1::digest(password.as_bytes()).into();
+        self.indexes(&digest)
+            .all(|index| self.bits[(index / 8) as usize] & (1 << (index % 8)) != 0)
+    }
+
+    fn indexes(&self, digest: &[u8; 20]) -> impl Iterator<Item = u64> {
+        let h1 = u64::from_le_bytes(digest[0..8].try_into().expect("8 bytes"));
+        let h2 = u64::from_le_bytes(digest[8..16].try_into().expect("8 bytes")) | 1;
+        let bit_count = self.bit_count;
+        (0..self.hash_count as u64).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % bit_count)
+    }
+}
+
+fn parse_digest(line: &str) -> BreachResult<[u8; 20]> {
+    let hex = line.split(':').next().unwrap_or_default().trim();
+    if hex.len() != 40 || !hex.is_ascii() {
+        return Err(BreachError::InvalidBloomFilter(format!(
+            "'{}' is not a SHA-1 hash",
+            line
+        )));
+    }
+    let mut digest = [0u8; 20];
+    for (i, byte) in digest.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).map_err(|_| {
+            BreachError::InvalidBloomFilter(format!("'{}' is not a SHA-1 hash", line))
+        })?;
+    }
+    Ok(digest)
+}
+
+enum BreachSource {
+    #[cfg(not(target_arch = "wasm32"))]
+    Online {
+        client: reqwest::blocking::Client,
+        api_base: String,
+        cache: Mutex<HashMap<String, HashMap<String, u64>>>,
+    },
+    Offline(BloomFilter),
+}
+
+/// Checks passwords against known breaches
+pub struct BreachChecker {
+    source: BreachSource,
+}
+
+impl BreachChecker {
+    /// Query the public Pwned Passwords API
+    pub fn online() -> BreachResult<Self> {
+        Self::with_api_base(HIBP_API_BASE)
+    }
+
+    /// Query a compatible range API at `api_base` (e.g. a self-hosted mirror)
+    ///
+    /// Not available in browsers, which have no blocking HTTP client.
+    #[cfg(target_arch = "wasm32")]
+    pub fn with_api_base(_api_base: &str) -> BreachResult<Self> {
+        Err(BreachError::Network(
+            "the online breach check is not available in browsers".to_string(),
+        ))
+    }
+
+    /// Query a compatible range API at `api_base` (e.g. a self-hosted mirror)
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_api_base(api_base: &str) -> BreachResult<Self> {
+        let client = reqwest::blocking::Client::builder()
+            .timeout(Duration::from_secs(15))
+            .user_agent(concat!("ZipLock/", env!("CARGO_PKG_VERSION")))
+            .build()
+            .map_err(|e| BreachError::Network(format!("failed to create HTTP client: {}", e)))?;
+
+        Ok(Self {
+            source: BreachSource::Online {
+                client,
+                api_base: api_base.trim_end_matches('/').to_string(),
+                cache: Mutex::new(HashMap::new()),
+            },
+        })
+    }
+
+    /// Check against a local bloom filter without any network access
+    pub fn offline(filter: BloomFilter) -> Self {
+        Self {
+            source: BreachSource::Offline(filter),
+        }
+    }
+
+    /// Check against a bloom filter file
+    pub fn from_bloom_file<P: AsRef<Path>>(path: P) -> BreachResult<Self> {
+        Ok(Self::offline(BloomFilter::load(path)?))
+    }
+
+    /// Whether this checker sends hash prefixes over the network
+    pub fn is_online(&self) -> bool {
+        !matches!(self.source, BreachSource::Offline(_))
+    }
+
+    /// Check a single password
+    pub fn check(&self, password: &str) -> BreachResult<BreachStatus> {
+        match &self.source {
+            BreachSource::Offline(filter) => Ok(if filter.contains_password(password) {
+                BreachStatus::ProbablyFound
+            } else {
+                BreachStatus::NotFound
+            }),
+            #[cfg(not(target_arch = "wasm32"))]
+            BreachSource::Online {
+                client,
+                api_base,
+                cache,
+            } => {
+                let hash = sha1_hex(password);
+                let (prefix, suffix) = hash.split_at(PREFIX_LEN);
+
+                if let Some(range) = cache.lock().unwrap().get(prefix) {
+                    return Ok(range
+                        .get(suffix)
+                        .map_or(BreachStatus::NotFound, |c| BreachStatus::Found(*c)));
+                }
+
+                let range = fetch_range(client, api_base, prefix)?;
+                let status = range
+                    .get(suffix)
+                    .map_or(BreachStatus::NotFound, |c| BreachStatus::Found(*c));
+                cache.lock().unwrap().insert(prefix.to_string(), range);
+                Ok(status)
+            }
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn fetch_range(
+    client: &reqwest::blocking::Client,
+    api_base: &str,
+    prefix: &str,
+) -> BreachResult<HashMap<String, u64>> {
+    let url = format!("{}/range/{}", api_base, prefix);
+    let response = client
+        .get(&url)
+        .header("Add-Padding", "true")
+        .send()
+        .map_err(|e| BreachError::Network(e.to_string()))?;
+
+    if !response.status().is_success() {
+        return Err(BreachError::Network(format!(
+            "{} returned {}",
+            url,
+            response.status()
+        )));
+    }
+
+    let body = response
+        .text()
+        .map_err(|e| BreachError::InvalidResponse(e.to_string()))?;
+    parse_range_response(&body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_sha1_hex() {
+        assert_eq!(
+            sha1_hex("password"),
+            "5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD8"
+        );
+    }
+
+    #[test]
+    fn test_parse_range_response_skips_padding() {
+        let body = "1E4C9B93F3F0682250B6CF8331B7EE68FD8:3861493\r\n\
+                    0018A45C4D1DEF81644B54AB7F969B88D65:0\r\n\
+                    00D4F6E8FA6EECAD2A3AA415EEC418D38EC:2\r\n";
+        let range = parse_range_response(body).unwrap();
+        assert_eq!(range.len(), 2);
+        assert_eq!(range["1E4C9B93F3F0682250B6CF8331B7EE68FD8"], 3861493);
+
+        assert!(parse_range_response("not a range line").is_err());
+    }
+
+    #[test]
+    fn test_online_check_sends_only_prefix() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0u8; 1024];
+            let read = stream.read(&mut request).unwrap();
+            let request = String::from_utf8_lossy(&request[..read]).to_string();
+            let body = "1E4C9B93F3F0682250B6CF8331B7EE68FD8:42\r\n";
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            )
+            .unwrap();
+            request
+        });
+
+        let checker = BreachChecker::with_api_base(&format!("http://{}", addr)).unwrap();
+        assert_eq!(checker.check("password").unwrap(), BreachStatus::Found(42));
+
+        let request = server.join().unwrap();
+        assert!(request.starts_with("GET /range/5BAA6 "));
+        assert!(!request.contains("1E4C9B93"));
+
+        // Served from the cache; the server has already shut down
+        assert_eq!(checker.check("password").unwrap(), BreachStatus::Found(42));
+    }
+
+    #[test]
+    fn test_bloom_filter_roundtrip() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("pwned.bloom");
+
+        let mut filter = BloomFilter::with_capacity(1000, 0.001);
+        filter
+            .insert_hash("5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD8:3861493")
+            .unwrap();
+        filter.insert_password("hunter2");
+        filter.save(&path).unwrap();
+
+        let checker = BreachChecker::from_bloom_file(&path).unwrap();
+        assert!(!checker.is_online());
+        assert_eq!(
+            checker.check("password").unwrap(),
+            BreachStatus::ProbablyFound
+        );
+        assert!(checker.check("hunter2").unwrap().is_breached());
+        assert_eq!(
+            checker.check("x7#Kq!m2Lp@9vRt4").unwrap(),
+            BreachStatus::NotFound
+        );
+    }
+
+    #[test]
+    fn test_bloom_filter_rejects_bad_input() {
+        assert!(BloomFilter::from_bytes(b"nope").is_err());
+
+        let mut bytes = BloomFilter::with_capacity(10, 0.01).to_bytes();
+        bytes.pop();
+        assert!(BloomFilter::from_bytes(&bytes).is_err());
+
+        let mut filter = BloomFilter::with_capacity(10, 0.01);
+        assert!(filter.insert_hash("ZZZZ").is_err());
+    }
+
+    #[test]
+    fn test_status_description() {
+        assert_eq!(BreachStatus::NotFound.describe(), None);
+        assert_eq!(
+            BreachStatus::Found(3).describe().unwrap(),
+            "Seen in 3 data breaches"
+        );
+        assert_eq!(BreachStatus::Found(3).count(), Some(3));
+        assert_eq!(BreachStatus::ProbablyFound.count(), None);
+    }
+}
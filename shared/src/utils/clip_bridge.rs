@@ -0,0 +1,435 @@
+//! Encrypted clipboard bridge between paired devices
+//!
+//! Sends a single username, password or current TOTP code from one device to
+//! another that shares a [`WatchPairing`] with it, so a login can be typed on
+//! a second device without going through a cloud clipboard. Each message is
+//! encrypted with ChaCha20-Poly1305 under a key derived from the pairing
+//! secret and a random salt, carries a short expiry bound into the
+//! ciphertext, and is accepted at most once. The receiving app shows the
+//! value until it expires and should never put it in the system clipboard
+//! history.
+//!
+//! Messages are compact strings; the platform picks the transport.
+
+use base64::prelude::*;
+use chacha20poly1305::aead::{Aead, Payload};
+use chacha20poly1305::Nonce;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use zeroize::Zeroize;
+
+use super::encryption::{EncryptionUtils, SecureMemory};
+use super::totp::TotpGenerator;
+use super::watch::WatchPairing;
+use crate::models::{CredentialRecord, FieldType};
+
+const MESSAGE_PREFIX: &str = "zlclip1:";
+const PROTOCOL_INFO: &[u8] = b"ziplock-clip-v1";
+const SALT_LEN: usize = 16;
+const CIPHER_NONCE_LEN: usize = 12;
+
+/// How long a sent value stays readable by default
+pub const DEFAULT_CLIP_TTL_SECS: i64 = 60;
+
+/// Longest lifetime a sender may give a value
+pub const MAX_CLIP_TTL_SECS: i64 = 5 * 60;
+
+/// Error types for the clipboard bridge
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClipError {
+    /// Message is malformed
+    InvalidMessage(String),
+    /// Credential has nothing of the requested kind
+    MissingValue(ClipKind),
+    /// Lifetime is not between 1 second and [`MAX_CLIP_TTL_SECS`]
+    InvalidTtl,
+    /// Message came from a device that is not paired
+    UnknownDevice(String),
+    /// Message was not encrypted for this pairing, or was tampered with
+    DecryptionFailed,
+    /// Message expired before it was opened
+    Expired,
+    /// Message was already opened
+    Replayed,
+}
+
+impl fmt::Display for ClipError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClipError::InvalidMessage(msg) => write!(f, "Invalid clipboard message: {}", msg),
+            ClipError::MissingValue(kind) => {
+                write!(f, "Credential has no {} to send", kind.name())
+            }
+            ClipError::InvalidTtl => write!(
+                f,
+                "Clipboard lifetime must be between 1 and {} seconds",
+                MAX_CLIP_TTL_SECS
+            ),
+            ClipError::UnknownDevice(id) => write!(f, "Device is not paired: {}", id),
+            ClipError::DecryptionFailed => {
+                write!(f, "Clipboard message could not be decrypted")
+            }
+            ClipError::Expired => write!(f, "Clipboard message has expired"),
+            ClipError::Replayed => write!(f, "Clipboard message was already opened"),
+        }
+    }
+}
+
+impl std::error::Error for ClipError {}
+
+/// Result type for clipboard bridge operations
+pub type ClipResult<T> = Result<T, ClipError>;
+
+/// What part of a credential is sent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClipKind {
+    Username,
+    Password,
+    /// The TOTP code current when the message was sent
+    Totp,
+}
+
+impl ClipKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ClipKind::Username => "username",
+            ClipKind::Password => "password",
+            ClipKind::Totp => "TOTP code",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "username" => Some(ClipKind::Username),
+            "password" => Some(ClipKind::Password),
+            "totp" => Some(ClipKind::Totp),
+            _ => None,
+        }
+    }
+}
+
+/// A value received from a paired device
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClipItem {
+    pub kind: ClipKind,
+    /// Title of the credential the value came from
+    pub title: String,
+    pub value: String,
+    /// Unix time after which the value should no longer be shown
+    #[serde(default)]
+    pub expires_at: i64,
+}
+
+impl ClipItem {
+    /// Take the requested value from a credential
+    ///
+    /// TOTP codes are generated at `now`.
+    pub fn from_credential(
+        credential: &CredentialRecord,
+        kind: ClipKind,
+        now: DateTime<Utc>,
+    ) -> ClipResult<Self> {
+        let field_of = |field_type: FieldType| {
+            credential
+                .fields
+                .values()
+                .find(|field| field.field_type == field_type && !field.value.is_empty())
+        };
+        let value = match kind {
+            ClipKind::Username => field_of(FieldType::Username).map(|field| field.value.clone()),
+            ClipKind::Password => field_of(FieldType::Password).map(|field| field.value.clone()),
+            ClipKind::Totp => field_of(FieldType::TotpSecret)
+                .and_then(|field| TotpGenerator::from_field(field).ok())
+                .and_then(|generator| generator.generate_at(now.timestamp().max(0) as u64).ok()),
+        };
+
+        Ok(Self {
+            kind,
+            title: credential.title.clone(),
+            value: value.ok_or(ClipError::MissingValue(kind))?,
+            expires_at: 0,
+        })
+    }
+
+    /// Seconds left before the value should be hidden
+    pub fn seconds_remaining(&self, now: DateTime<Utc>) -> i64 {
+        (self.expires_at - now.timestamp()).max(0)
+    }
+}
+
+impl Drop for ClipItem {
+    fn drop(&mut self) {
+        self.value.zeroize();
+    }
+}
+
+impl fmt::Debug for ClipItem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ClipItem")
+            .field("kind", &self.kind)
+            .field("title", &self.title)
+            .field("expires_at", &self.expires_at)
+            .finish_non_exhaustive()
+    }
+}
+
+/// An encrypted value in transit between paired devices
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClipMessage {
+    /// Pairing the message was encrypted with
+    #[serde(rename = "d")]
+    pub device_id: String,
+    #[serde(rename = "s")]
+    salt: String,
+    #[serde(rename = "n")]
+    nonce: String,
+    /// Unix time the message was sent
+    #[serde(rename = "t")]
+    pub sent_at: i64,
+    /// Unix time after which the message is rejected
+    #[serde(rename = "e")]
+    pub expires_at: i64,
+    #[serde(rename = "c")]
+    ciphertext: String,
+}
+
+impl ClipMessage {
+    /// Encrypt a value for the paired device
+    pub fn seal(
+        pairing: &WatchPairing,
+        item: &ClipItem,
+        ttl_secs: i64,
+        now: DateTime<Utc>,
+    ) -> ClipResult<Self> {
+        if !(1..=MAX_CLIP_TTL_SECS).contains(&ttl_secs) {
+            return Err(ClipError::InvalidTtl);
+        }
+
+        let salt = EncryptionUtils::random_bytes(SALT_LEN);
+        let nonce = EncryptionUtils::random_bytes(CIPHER_NONCE_LEN);
+        let mut message = Self {
+            device_id: pairing.device_id().to_string(),
+            salt: BASE64_URL_SAFE_NO_PAD.encode(&salt),
+            nonce: BASE64_URL_SAFE_NO_PAD.encode(&nonce),
+            sent_at: now.timestamp(),
+            expires_at: now.timestamp() + ttl_secs,
+            ciphertext: String::new(),
+        };
+
+        let mut plaintext =
+            serde_json::to_vec(item).map_err(|e| ClipError::InvalidMessage(e.to_string()))?;
+        let ciphertext = pairing
+            .derive_cipher(&salt, PROTOCOL_INFO)
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &plaintext,
+                    aad: &message.associated_data(),
+                },
+            )
+            .map_err(|_| ClipError::InvalidMessage("encryption failed".to_string()));
+        SecureMemory::zero_memory(&mut plaintext);
+
+        message.ciphertext = BASE64_URL_SAFE_NO_PAD.encode(ciphertext?);
+        Ok(message)
+    }
+
+    /// Encode for transport
+    pub fn encode(&self) -> String {
+        format!(
+            "{}{}",
+            MESSAGE_PREFIX,
+            BASE64_URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).unwrap_or_default())
+        )
+    }
+
+    /// Decode a received message (does not decrypt it)
+    pub fn decode(s: &str) -> ClipResult<Self> {
+        let encoded = s
+            .trim()
+            .strip_prefix(MESSAGE_PREFIX)
+            .ok_or_else(|| ClipError::InvalidMessage("unrecognized format".to_string()))?;
+        let bytes = BASE64_URL_SAFE_NO_PAD
+            .decode(encoded)
+            .map_err(|e| ClipError::InvalidMessage(e.to_string()))?;
+        serde_json::from_slice(&bytes).map_err(|e| ClipError::InvalidMessage(e.to_string()))
+    }
+
+    /// Decrypt the value, checking the expiry but not replay
+    pub fn open(&self, pairing: &WatchPairing, now: DateTime<Utc>) -> ClipResult<ClipItem> {
+        if pairing.device_id() != self.device_id {
+            return Err(ClipError::UnknownDevice(self.device_id.clone()));
+        }
+        if now.timestamp() > self.expires_at || self.expires_at - self.sent_at > MAX_CLIP_TTL_SECS {
+            return Err(ClipError::Expired);
+        }
+
+        let decode = |value: &str, len: usize, what: &str| {
+            BASE64_URL_SAFE_NO_PAD
+                .decode(value)
+                .ok()
+                .filter(|bytes| len == 0 || bytes.len() == len)
+                .ok_or_else(|| ClipError::InvalidMessage(format!("bad {}", what)))
+        };
+        let salt = decode(&self.salt, SALT_LEN, "salt")?;
+        let nonce = decode(&self.nonce, CIPHER_NONCE_LEN, "nonce")?;
+        let ciphertext = decode(&self.ciphertext, 0, "ciphertext")?;
+
+        let mut plaintext = pairing
+            .derive_cipher(&salt, PROTOCOL_INFO)
+            .decrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &ciphertext,
+                    aad: &self.associated_data(),
+                },
+            )
+            .map_err(|_| ClipError::DecryptionFailed)?;
+        let item: ClipResult<ClipItem> = serde_json::from_slice(&plaintext)
+            .map_err(|e| ClipError::InvalidMessage(e.to_string()));
+        SecureMemory::zero_memory(&mut plaintext);
+
+        let mut item = item?;
+        item.expires_at = self.expires_at;
+        Ok(item)
+    }
+
+    fn associated_data(&self) -> Vec<u8> {
+        format!(
+            "\n{}\n{}\n{}\n{}",
+            self.device_id, self.salt, self.sent_at, self.expires_at
+        )
+        .into_bytes()
+    }
+}
+
+/// Receiving side of the bridge
+///
+/// Remembers the messages it has opened until they expire, so each value is
+/// shown once even if the transport delivers it again.
+#[derive(Debug, Default)]
+pub struct ClipInbox {
+    seen: HashMap<String, i64>,
+}
+
+impl ClipInbox {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Decrypt a message from a paired device and consume it
+    pub fn accept(
+        &mut self,
+        pairing: &WatchPairing,
+        message: &ClipMessage,
+        now: DateTime<Utc>,
+    ) -> ClipResult<ClipItem> {
+        let item = message.open(pairing, now)?;
+
+        let now = now.timestamp();
+        self.seen.retain(|_, expires_at| *expires_at >= now);
+        let key = format!("{}:{}", message.device_id, message.salt);
+        if self.seen.contains_key(&key) {
+            return Err(ClipError::Replayed);
+        }
+        self.seen.insert(key, message.expires_at);
+        Ok(item)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::CredentialField;
+    use chrono::Duration;
+
+    fn now() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2024-06-01T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn credential() -> CredentialRecord {
+        let mut credential = CredentialRecord::new("GitHub".to_string(), "login".to_string());
+        credential.set_field("username", CredentialField::username("octocat"));
+        credential.set_field("password", CredentialField::password("hunter2"));
+        credential.set_field(
+            "totp_secret",
+            CredentialField::totp_secret("JBSWY3DPEHPK3PXP"),
+        );
+        credential
+    }
+
+    #[test]
+    fn test_round_trip_over_the_wire() {
+        let pairing = WatchPairing::generate();
+        let peer: WatchPairing = pairing.to_string().parse().unwrap();
+
+        let item = ClipItem::from_credential(&credential(), ClipKind::Password, now()).unwrap();
+        let wire = ClipMessage::seal(&pairing, &item, 30, now())
+            .unwrap()
+            .encode();
+        assert!(!wire.contains("hunter2"));
+
+        let received = ClipInbox::new()
+            .accept(&peer, &ClipMessage::decode(&wire).unwrap(), now())
+            .unwrap();
+        assert_eq!(received.value, "hunter2");
+        assert_eq!(received.title, "GitHub");
+        assert_eq!(received.seconds_remaining(now()), 30);
+    }
+
+    #[test]
+    fn test_totp_and_missing_values() {
+        let totp = ClipItem::from_credential(&credential(), ClipKind::Totp, now()).unwrap();
+        assert_eq!(totp.value.len(), 6);
+
+        let empty = CredentialRecord::new("Note".to_string(), "secure_note".to_string());
+        assert_eq!(
+            ClipItem::from_credential(&empty, ClipKind::Username, now()),
+            Err(ClipError::MissingValue(ClipKind::Username))
+        );
+    }
+
+    #[test]
+    fn test_expired_replayed_and_foreign_messages_are_rejected() {
+        let pairing = WatchPairing::generate();
+        let item = ClipItem::from_credential(&credential(), ClipKind::Username, now()).unwrap();
+        let message = ClipMessage::seal(&pairing, &item, 60, now()).unwrap();
+        let mut inbox = ClipInbox::new();
+
+        assert!(inbox.accept(&pairing, &message, now()).is_ok());
+        assert_eq!(
+            inbox.accept(&pairing, &message, now()),
+            Err(ClipError::Replayed)
+        );
+        assert_eq!(
+            message.open(&pairing, now() + Duration::seconds(61)),
+            Err(ClipError::Expired)
+        );
+        assert!(matches!(
+            message.open(&WatchPairing::generate(), now()),
+            Err(ClipError::UnknownDevice(_))
+        ));
+        assert_eq!(
+            ClipMessage::seal(&pairing, &item, MAX_CLIP_TTL_SECS + 1, now()),
+            Err(ClipError::InvalidTtl)
+        );
+    }
+
+    #[test]
+    fn test_expiry_cannot_be_extended() {
+        let pairing = WatchPairing::generate();
+        let item = ClipItem::from_credential(&credential(), ClipKind::Username, now()).unwrap();
+        let mut message = ClipMessage::seal(&pairing, &item, 60, now()).unwrap();
+
+        message.expires_at += 60;
+        assert_eq!(
+            message.open(&pairing, now()),
+            Err(ClipError::DecryptionFailed)
+        );
+    }
+}
@@ -0,0 +1,779 @@
+//! CSV import and export for ZipLock
+//!
+//! This module maps CSV columns onto credential fields using configurable
+//! column mappings. Presets cover the CSV exports produced by common password
+//! managers and browsers so that users can migrate without manual editing.
+
+use crate::core::{CoreError, CoreResult, UnifiedMemoryRepository};
+use crate::models::{CredentialField, CredentialRecord, FieldType};
+use crate::utils::import::{self, ImportFailure, ImportReport};
+use serde::{Deserialize, Serialize};
+
+/// Where the value of a CSV column ends up on a credential
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CsvTarget {
+    /// Credential title
+    Title,
+    /// Credential notes
+    Notes,
+    /// Credential tags, split on the mapping's tag separator
+    Tags,
+    /// Folder path
+    Folder,
+    /// Favorite flag ("1", "true", "yes")
+    Favorite,
+    /// Credential type (e.g. "login", "secure_note")
+    CredentialType,
+    /// Stable credential ID; importing a row with a known ID updates that
+    /// credential instead of adding a copy
+    Id,
+    /// A named credential field
+    Field { name: String, field_type: FieldType },
+    /// Column is read but discarded
+    Ignore,
+}
+
+impl CsvTarget {
+    /// Shorthand for a field target
+    pub fn field<S: Into<String>>(name: S, field_type: FieldType) -> Self {
+        CsvTarget::Field {
+            name: name.into(),
+            field_type,
+        }
+    }
+}
+
+/// A single column-to-target mapping
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CsvColumnMapping {
+    /// Column header as it appears in the CSV file
+    pub column: String,
+    /// Target on the credential
+    pub target: CsvTarget,
+}
+
+impl CsvColumnMapping {
+    /// Create a new column mapping
+    pub fn new<S: Into<String>>(column: S, target: CsvTarget) -> Self {
+        Self {
+            column: column.into(),
+            target,
+        }
+    }
+}
+
+/// Known CSV export layouts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CsvPreset {
+    /// ZipLock's own CSV layout
+    ZipLock,
+    /// LastPass "Export" CSV
+    LastPass,
+    /// Bitwarden unencrypted CSV export
+    Bitwarden,
+    /// 1Password 8 CSV export
+    OnePassword,
+    /// Chrome / Chromium "Export passwords"
+    Chrome,
+    /// Firefox "Export Logins"
+    Firefox,
+}
+
+impl CsvPreset {
+    /// All available presets
+    pub fn all() -> Vec<CsvPreset> {
+        vec![
+            CsvPreset::ZipLock,
+            CsvPreset::LastPass,
+            CsvPreset::Bitwarden,
+            CsvPreset::OnePassword,
+            CsvPreset::Chrome,
+            CsvPreset::Firefox,
+        ]
+    }
+
+    /// Human-readable name
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            CsvPreset::ZipLock => "ZipLock",
+            CsvPreset::LastPass => "LastPass",
+            CsvPreset::Bitwarden => "Bitwarden",
+            CsvPreset::OnePassword => "1Password",
+            CsvPreset::Chrome => "Chrome",
+            CsvPreset::Firefox => "Firefox",
+        }
+    }
+
+    /// Build the column mapping for this preset
+    pub fn mapping(&self) -> CsvMapping {
+        use CsvTarget::*;
+
+        let col = CsvColumnMapping::new::<&str>;
+        let field = |column: &str, name: &str, field_type: FieldType| {
+            CsvColumnMapping::new(column, CsvTarget::field(name, field_type))
+        };
+
+        match self {
+            CsvPreset::ZipLock => CsvMapping::new(vec![
+                col("title", Title),
+                col("type", CredentialType),
+                field("username", "username", FieldType::Username),
+                field("password", "password", FieldType::Password),
+                field("email", "email", FieldType::Email),
+                field("url", "url", FieldType::Url),
+                field("totp", "totp", FieldType::TotpSecret),
+                col("notes", Notes),
+                col("tags", Tags),
+                col("folder", Folder),
+                col("favorite", Favorite),
+                col("id", Id),
+            ]),
+            CsvPreset::LastPass => CsvMapping::new(vec![
+                field("url", "url", FieldType::Url),
+                field("username", "username", FieldType::Username),
+                field("password", "password", FieldType::Password),
+                field("totp", "totp", FieldType::TotpSecret),
+                col("extra", Notes),
+                col("name", Title),
+                col("grouping", Folder),
+                col("fav", Favorite),
+            ]),
+            CsvPreset::Bitwarden => CsvMapping::new(vec![
+                col("folder", Folder),
+                col("favorite", Favorite),
+                col("type", CredentialType),
+                col("name", Title),
+                col("notes", Notes),
+                col("fields", Ignore),
+                col("reprompt", Ignore),
+                field("login_uri", "url", FieldType::Url),
+                field("login_username", "username", FieldType::Username),
+                field("login_password", "password", FieldType::Password),
+                field("login_totp", "totp", FieldType::TotpSecret),
+            ]),
+            CsvPreset::OnePassword => CsvMapping::new(vec![
+                col("Title", Title),
+                field("Url", "url", FieldType::Url),
+                field("Username", "username", FieldType::Username),
+                field("Password", "password", FieldType::Password),
+                field("OTPAuth", "totp", FieldType::TotpSecret),
+                col("Favorite", Favorite),
+                col("Archived", Ignore),
+                col("Tags", Tags),
+                col("Notes", Notes),
+            ])
+            .with_tag_separator(','),
+            CsvPreset::Chrome => CsvMapping::new(vec![
+                col("name", Title),
+                field("url", "url", FieldType::Url),
+                field("username", "username", FieldType::Username),
+                field("password", "password", FieldType::Password),
+                col("note", Notes),
+            ]),
+            CsvPreset::Firefox => CsvMapping::new(vec![
+                field("url", "url", FieldType::Url),
+                field("username", "username", FieldType::Username),
+                field("password", "password", FieldType::Password),
+                col("httpRealm", Ignore),
+                col("formActionOrigin", Ignore),
+                col("guid", Ignore),
+                col("timeCreated", Ignore),
+                col("timeLastUsed", Ignore),
+                col("timePasswordChanged", Ignore),
+            ]),
+        }
+    }
+
+    /// Guess the preset that produced a CSV file from its header row
+    pub fn detect(headers: &[String]) -> Option<CsvPreset> {
+        let has = |name: &str| headers.iter().any(|h| h.eq_ignore_ascii_case(name));
+
+        if has("login_uri") && has("login_password") {
+            Some(CsvPreset::Bitwarden)
+        } else if has("grouping") && has("extra") {
+            Some(CsvPreset::LastPass)
+        } else if has("formActionOrigin") || has("timePasswordChanged") {
+            Some(CsvPreset::Firefox)
+        } else if has("OTPAuth") {
+            Some(CsvPreset::OnePassword)
+        } else if has("title") && has("type") {
+            Some(CsvPreset::ZipLock)
+        } else if has("name") && has("url") && has("password") {
+            Some(CsvPreset::Chrome)
+        } else {
+            None
+        }
+    }
+}
+
+/// A full set of column mappings plus parsing options
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CsvMapping {
+    /// Column mappings in export order
+    pub columns: Vec<CsvColumnMapping>,
+    /// Credential type used when no type column is mapped or it is empty
+    pub default_credential_type: String,
+    /// Separator used to split and join tags
+    pub tag_separator: char,
+    /// Import unmapped columns as custom text fields instead of dropping them
+    pub keep_unmapped_columns: bool,
+}
+
+impl CsvMapping {
+    /// Create a mapping from a list of columns
+    pub fn new(columns: Vec<CsvColumnMapping>) -> Self {
+        Self {
+            columns,
+            default_credential_type: "login".to_string(),
+            tag_separator: ';',
+            keep_unmapped_columns: false,
+        }
+    }
+
+    /// Set the default credential type
+    pub fn with_default_type<S: Into<String>>(mut self, credential_type: S) -> Self {
+        self.default_credential_type = credential_type.into();
+        self
+    }
+
+    /// Set the tag separator
+    pub fn with_tag_separator(mut self, separator: char) -> Self {
+        self.tag_separator = separator;
+        self
+    }
+
+    /// Keep unmapped columns as custom text fields
+    pub fn keep_unmapped(mut self, keep: bool) -> Self {
+        self.keep_unmapped_columns = keep;
+        self
+    }
+
+    /// Add or replace the mapping for a column
+    pub fn map_column<S: Into<String>>(mut self, column: S, target: CsvTarget) -> Self {
+        let column = column.into();
+        match self
+            .columns
+            .iter_mut()
+            .find(|m| m.column.eq_ignore_ascii_case(&column))
+        {
+            Some(existing) => existing.target = target,
+            None => self.columns.push(CsvColumnMapping::new(column, target)),
+        }
+        self
+    }
+
+    /// Find the target for a header (case-insensitive)
+    pub fn target_for(&self, header: &str) -> Option<&CsvTarget> {
+        self.columns
+            .iter()
+            .find(|m| m.column.eq_ignore_ascii_case(header.trim()))
+            .map(|m| &m.target)
+    }
+}
+
+impl Default for CsvMapping {
+    fn default() -> Self {
+        CsvPreset::ZipLock.mapping()
+    }
+}
+
+/// Imports credentials from CSV using a column mapping
+#[derive(Debug, Clone)]
+pub struct CsvImporter {
+    mapping: CsvMapping,
+    dry_run: bool,
+    assign_new_ids: bool,
+}
+
+impl CsvImporter {
+    /// Create an importer with an explicit mapping
+    pub fn new(mapping: CsvMapping) -> Self {
+        Self {
+            mapping,
+            dry_run: false,
+            assign_new_ids: false,
+        }
+    }
+
+    /// Create an importer for a known preset
+    pub fn from_preset(preset: CsvPreset) -> Self {
+        Self::new(preset.mapping())
+    }
+
+    /// Create an importer whose preset is detected from the CSV header
+    pub fn detect(data: &str) -> CoreResult<Self> {
+        let headers = Self::read_headers(data)?;
+        CsvPreset::detect(&headers)
+            .map(Self::from_preset)
+            .ok_or_else(|| CoreError::ValidationError {
+                message: "Unrecognized CSV layout; provide an explicit mapping".to_string(),
+            })
+    }
+
+    /// Enable or disable dry-run mode
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Ignore IDs in the file and import every row as a new credential
+    pub fn assign_new_ids(mut self, assign: bool) -> Self {
+        self.assign_new_ids = assign;
+        self
+    }
+
+    /// The mapping in use
+    pub fn mapping(&self) -> &CsvMapping {
+        &self.mapping
+    }
+
+    /// Parse CSV data into credentials without touching any repository
+    pub fn parse(&self, data: &str) -> CoreResult<ImportReport> {
+        let mut reader = ::csv::ReaderBuilder::new()
+            .flexible(true)
+            .trim(::csv::Trim::All)
+            .from_reader(data.as_bytes());
+
+        let headers: Vec<String> = reader
+            .headers()
+            .map_err(|e| CoreError::SerializationError {
+                message: format!("Failed to read CSV header: {}", e),
+            })?
+            .iter()
+            .map(|h| h.trim_start_matches('\u{feff}').to_string())
+            .collect();
+
+        let mut report = ImportReport {
+            dry_run: self.dry_run,
+            ..Default::default()
+        };
+
+        for (index, record) in reader.records().enumerate() {
+            let line = index + 2;
+            let record = match record {
+                Ok(record) => record,
+                Err(e) => {
+                    report.push(
+                        line,
+                        Err(ImportFailure::new(
+                            None,
+                            format!("Malformed CSV row: {}", e),
+                        )),
+                    );
+                    continue;
+                }
+            };
+
+            if record.iter().all(|value| value.is_empty()) {
+                report.skipped_rows += 1;
+                continue;
+            }
+
+            report.push(line, self.row_to_credential(&headers, &record));
+        }
+
+        Ok(report)
+    }
+
+    /// Import CSV data into a repository
+    ///
+    /// In dry-run mode nothing is added and the report lists what would be
+    /// created.
+    pub fn import_into(
+        &self,
+        data: &str,
+        repository: &mut UnifiedMemoryRepository,
+    ) -> CoreResult<ImportReport> {
+        if !repository.is_initialized() {
+            return Err(CoreError::NotInitialized);
+        }
+
+        let mut report = self.parse(data)?;
+        if self.assign_new_ids {
+            report = report.assign_new_ids();
+        }
+        if self.dry_run {
+            return Ok(report);
+        }
+
+        report.import_into(repository)
+    }
+
+    fn read_headers(data: &str) -> CoreResult<Vec<String>> {
+        let mut reader = ::csv::ReaderBuilder::new()
+            .trim(::csv::Trim::All)
+            .from_reader(data.as_bytes());
+        reader
+            .headers()
+            .map(|h| {
+                h.iter()
+                    .map(|s| s.trim_start_matches('\u{feff}').to_string())
+                    .collect()
+            })
+            .map_err(|e| CoreError::SerializationError {
+                message: format!("Failed to read CSV header: {}", e),
+            })
+    }
+
+    fn row_to_credential(
+        &self,
+        headers: &[String],
+        record: &::csv::StringRecord,
+    ) -> Result<CredentialRecord, ImportFailure> {
+        let mut credential =
+            CredentialRecord::new(String::new(), self.mapping.default_credential_type.clone());
+        let mut unmapped = Vec::new();
+
+        for (header, value) in headers.iter().zip(record.iter()) {
+            if value.is_empty() {
+                continue;
+            }
+
+            match self.mapping.target_for(header) {
+                Some(CsvTarget::Title) => credential.title = value.to_string(),
+                Some(CsvTarget::Notes) => credential.notes = Some(value.to_string()),
+                Some(CsvTarget::Tags) => {
+                    for tag in value.split(self.mapping.tag_separator) {
+                        let tag = tag.trim();
+                        if !tag.is_empty() {
+                            credential.add_tag(tag);
+                        }
+                    }
+                }
+                Some(CsvTarget::Folder) => credential.folder_path = Some(value.to_string()),
+                Some(CsvTarget::Favorite) => credential.favorite = parse_bool(value),
+                Some(CsvTarget::CredentialType) => {
+                    credential.credential_type = normalize_type(value)
+                }
+                Some(CsvTarget::Id) => credential.id = value.to_string(),
+                Some(CsvTarget::Field { name, field_type }) => {
+                    let field = match field_type {
+                        FieldType::TotpSecret => import::totp_field(value),
+                        FieldType::Url => import::url_field(value),
+                        _ => Some(CredentialField::new(
+                            field_type.clone(),
+                            value.to_string(),
+                            field_type.is_sensitive_by_default(),
+                        )),
+                    };
+                    if let Some(field) = field {
+                        credential.set_field(name.clone(), field);
+                    }
+                }
+                Some(CsvTarget::Ignore) => {}
+                None => unmapped.push((header.clone(), value.to_string())),
+            }
+        }
+
+        if self.mapping.keep_unmapped_columns {
+            for (header, value) in unmapped {
+                let name = header.trim().to_lowercase().replace(' ', "_");
+                if !name.is_empty() && credential.get_field(&name).is_none() {
+                    credential.set_field(name, CredentialField::text(value).with_label(header));
+                }
+            }
+        }
+
+        import::finish(credential)
+    }
+}
+
+/// Exports credentials to CSV using a column mapping
+#[derive(Debug, Clone)]
+pub struct CsvExporter {
+    mapping: CsvMapping,
+    include_sensitive: bool,
+}
+
+impl CsvExporter {
+    /// Create an exporter with an explicit mapping
+    pub fn new(mapping: CsvMapping) -> Self {
+        Self {
+            mapping,
+            include_sensitive: true,
+        }
+    }
+
+    /// Create an exporter for a known preset
+    pub fn from_preset(preset: CsvPreset) -> Self {
+        Self::new(preset.mapping())
+    }
+
+    /// Whether sensitive field values are written (blank otherwise)
+    pub fn include_sensitive(mut self, include: bool) -> Self {
+        self.include_sensitive = include;
+        self
+    }
+
+    /// Export credentials to CSV bytes
+    pub fn export(&self, credentials: &[CredentialRecord]) -> CoreResult<Vec<u8>> {
+        let mut writer = ::csv::Writer::from_writer(Vec::new());
+
+        writer
+            .write_record(self.mapping.columns.iter().map(|m| m.column.as_str()))
+            .map_err(Self::csv_error)?;
+
+        for credential in credentials {
+            let row: Vec<String> = self
+                .mapping
+                .columns
+                .iter()
+                .map(|m| self.value_for(credential, &m.target))
+                .collect();
+            writer.write_record(&row).map_err(Self::csv_error)?;
+        }
+
+        writer
+            .into_inner()
+            .map_err(|e| CoreError::SerializationError {
+                message: format!("CSV export failed: {}", e),
+            })
+    }
+
+    /// Export credentials to a CSV string
+    pub fn export_to_string(&self, credentials: &[CredentialRecord]) -> CoreResult<String> {
+        String::from_utf8(self.export(credentials)?).map_err(|e| CoreError::SerializationError {
+            message: format!("CSV export produced invalid UTF-8: {}", e),
+        })
+    }
+
+    fn value_for(&self, credential: &CredentialRecord, target: &CsvTarget) -> String {
+        match target {
+            CsvTarget::Title => credential.title.clone(),
+            CsvTarget::Notes => credential.notes.clone().unwrap_or_default(),
+            CsvTarget::Tags => credential
+                .tags
+                .join(&self.mapping.tag_separator.to_string()),
+            CsvTarget::Folder => credential.folder_path.clone().unwrap_or_default(),
+            CsvTarget::Favorite => if credential.favorite { "1" } else { "0" }.to_string(),
+            CsvTarget::CredentialType => credential.credential_type.clone(),
+            CsvTarget::Id => credential.id.clone(),
+            CsvTarget::Field { name, field_type } => {
+                let field = credential.get_field(name).or_else(|| {
+                    // Fall back to the first field of the same type
+                    credential
+                        .fields
+                        .values()
+                        .find(|f| &f.field_type == field_type)
+                });
+                match field {
+                    Some(f) if f.sensitive && !self.include_sensitive => String::new(),
+                    Some(f) => f.value.clone(),
+                    None => String::new(),
+                }
+            }
+            CsvTarget::Ignore => String::new(),
+        }
+    }
+
+    fn csv_error(e: ::csv::Error) -> CoreError {
+        CoreError::SerializationError {
+            message: format!("CSV serialization failed: {}", e),
+        }
+    }
+}
+
+/// Parse a boolean-ish CSV value
+fn parse_bool(value: &str) -> bool {
+    matches!(
+        value.trim().to_lowercase().as_str(),
+        "1" | "true" | "yes" | "y" | "on"
+    )
+}
+
+/// Normalize foreign credential type names to ZipLock's
+fn normalize_type(value: &str) -> String {
+    match value.trim().to_lowercase().as_str() {
+        "note" | "securenote" | "secure note" => "secure_note".to_string(),
+        "card" => "credit_card".to_string(),
+        other => other.replace(' ', "_"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LASTPASS_CSV: &str = "url,username,password,totp,extra,name,grouping,fav\n\
+        https://github.com,octocat,hunter2,JBSWY3DPEHPK3PXP,my notes,GitHub,Work,1\n\
+        https://example.com,user,pass,,,,Personal,0\n";
+
+    #[test]
+    fn test_lastpass_import() {
+        let report = CsvImporter::from_preset(CsvPreset::LastPass)
+            .parse(LASTPASS_CSV)
+            .unwrap();
+
+        assert!(!report.has_errors());
+        assert_eq!(report.imported_count(), 2);
+
+        let github = &report.credentials[0];
+        assert_eq!(github.title, "GitHub");
+        assert_eq!(github.get_field("username").unwrap().value, "octocat");
+        assert!(github.get_field("password").unwrap().sensitive);
+        assert_eq!(
+            github.get_field("totp").unwrap().field_type,
+            FieldType::TotpSecret
+        );
+        assert_eq!(github.folder_path.as_deref(), Some("Work"));
+        assert!(github.favorite);
+        assert_eq!(github.notes.as_deref(), Some("my notes"));
+
+        // Missing title falls back to the URL's domain
+        assert_eq!(report.credentials[1].title, "example.com");
+    }
+
+    #[test]
+    fn test_preset_detection() {
+        let importer = CsvImporter::detect(LASTPASS_CSV).unwrap();
+        assert_eq!(importer.mapping(), &CsvPreset::LastPass.mapping());
+
+        let firefox = "\"url\",\"username\",\"password\",\"httpRealm\",\"formActionOrigin\",\"guid\",\"timeCreated\",\"timeLastUsed\",\"timePasswordChanged\"\n";
+        assert!(CsvImporter::detect(firefox).is_ok());
+
+        assert!(CsvImporter::detect("foo,bar\n1,2\n").is_err());
+    }
+
+    #[test]
+    fn test_quoted_values_and_bom() {
+        let data = "\u{feff}name,url,username,password,note\n\
+            \"Acme, Inc\",https://acme.test,\"a\"\"b\",pw,\"line1\nline2\"\n";
+        let report = CsvImporter::from_preset(CsvPreset::Chrome)
+            .parse(data)
+            .unwrap();
+
+        assert_eq!(report.imported_count(), 1);
+        let credential = &report.credentials[0];
+        assert_eq!(credential.title, "Acme, Inc");
+        assert_eq!(credential.get_field("username").unwrap().value, "a\"b");
+        assert_eq!(credential.notes.as_deref(), Some("line1\nline2"));
+    }
+
+    #[test]
+    fn test_per_row_errors() {
+        let long_title = "x".repeat(500);
+        let data = format!(
+            "name,url,username,password,note\nGood,https://a.test,u,p,\n{},https://b.test,u,p,\n",
+            long_title
+        );
+        let report = CsvImporter::from_preset(CsvPreset::Chrome)
+            .parse(&data)
+            .unwrap();
+
+        assert_eq!(report.imported_count(), 1);
+        assert_eq!(report.errors.len(), 1);
+        assert_eq!(report.errors[0].position, 3);
+    }
+
+    #[test]
+    fn test_dry_run_does_not_mutate() {
+        let mut repo = UnifiedMemoryRepository::new();
+        repo.initialize().unwrap();
+        repo.mark_saved();
+
+        let report = CsvImporter::from_preset(CsvPreset::LastPass)
+            .dry_run(true)
+            .import_into(LASTPASS_CSV, &mut repo)
+            .unwrap();
+
+        assert!(report.dry_run);
+        assert_eq!(report.imported_count(), 2);
+        assert!(repo.list_credentials().unwrap().is_empty());
+        assert!(!repo.is_modified());
+
+        let report = CsvImporter::from_preset(CsvPreset::LastPass)
+            .import_into(LASTPASS_CSV, &mut repo)
+            .unwrap();
+        assert_eq!(report.imported_count(), 2);
+        assert_eq!(repo.list_credentials().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_custom_mapping_and_unmapped_columns() {
+        let mapping = CsvMapping::new(vec![
+            CsvColumnMapping::new("Site", CsvTarget::Title),
+            CsvColumnMapping::new("Login", CsvTarget::field("username", FieldType::Username)),
+            CsvColumnMapping::new("Secret", CsvTarget::field("password", FieldType::Password)),
+        ])
+        .keep_unmapped(true)
+        .map_column("Labels", CsvTarget::Tags);
+
+        let data = "Site,Login,Secret,Labels,Recovery Code\nBank,me,pw,finance;important,12345\n";
+        let report = CsvImporter::new(mapping).parse(data).unwrap();
+        let credential = &report.credentials[0];
+
+        assert_eq!(credential.title, "Bank");
+        assert_eq!(credential.tags, vec!["finance", "important"]);
+        let extra = credential.get_field("recovery_code").unwrap();
+        assert_eq!(extra.value, "12345");
+        assert_eq!(extra.label.as_deref(), Some("Recovery Code"));
+    }
+
+    #[test]
+    fn test_export_round_trip() {
+        let mut credential = CredentialRecord::new("GitHub".to_string(), "login".to_string());
+        credential.set_field("username", CredentialField::username("octocat"));
+        credential.set_field("password", CredentialField::password("hunter2"));
+        credential.set_field("url", CredentialField::url("https://github.com"));
+        credential.tags = vec!["dev".to_string(), "work".to_string()];
+        credential.favorite = true;
+
+        for preset in CsvPreset::all() {
+            let exported = CsvExporter::from_preset(preset)
+                .export_to_string(std::slice::from_ref(&credential))
+                .unwrap();
+            let report = CsvImporter::from_preset(preset).parse(&exported).unwrap();
+
+            assert_eq!(report.imported_count(), 1, "preset {:?}", preset);
+            let imported = &report.credentials[0];
+            assert_eq!(imported.get_field("password").unwrap().value, "hunter2");
+            assert_eq!(imported.get_field("username").unwrap().value, "octocat");
+        }
+    }
+
+    #[test]
+    fn test_reimport_updates_by_id() {
+        let mut repo = UnifiedMemoryRepository::new();
+        repo.initialize().unwrap();
+        let mut credential = CredentialRecord::new("GitHub".to_string(), "login".to_string());
+        credential.set_field("password", CredentialField::password("hunter2"));
+        repo.add_credential(credential.clone()).unwrap();
+
+        credential.set_field("password", CredentialField::password("changed"));
+        let exported = CsvExporter::from_preset(CsvPreset::ZipLock)
+            .export_to_string(std::slice::from_ref(&credential))
+            .unwrap();
+
+        let report = CsvImporter::from_preset(CsvPreset::ZipLock)
+            .import_into(&exported, &mut repo)
+            .unwrap();
+        assert_eq!(report.imported_count(), 0);
+        assert_eq!(report.updated.len(), 1);
+        let credentials = repo.list_credentials().unwrap();
+        assert_eq!(credentials.len(), 1);
+        assert_eq!(
+            credentials[0].get_field("password").unwrap().value,
+            "changed"
+        );
+
+        let report = CsvImporter::from_preset(CsvPreset::ZipLock)
+            .assign_new_ids(true)
+            .import_into(&exported, &mut repo)
+            .unwrap();
+        assert_eq!(report.imported_count(), 1);
+        assert_ne!(report.credentials[0].id, credential.id);
+        assert_eq!(repo.list_credentials().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_export_without_sensitive() {
+        let mut credential = CredentialRecord::new("Test".to_string(), "login".to_string());
+        credential.set_field("password", CredentialField::password("secret"));
+
+        let exported = CsvExporter::from_preset(CsvPreset::Chrome)
+            .include_sensitive(false)
+            .export_to_string(&[credential])
+            .unwrap();
+        assert!(!exported.contains("secret"));
+    }
+}
@@ -5,9 +5,17 @@
 //! memory handling for sensitive operations.
 
 use base64::prelude::*;
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
 use rand::{thread_rng, RngCore};
 use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
 use std::convert::TryInto;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, MutexGuard, OnceLock, PoisonError};
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::models::{CredentialField, FieldProtection, PROTECTED_FIELD_PLACEHOLDER};
 
 /// Error types for encryption operations
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -53,10 +61,117 @@ pub const AES_IV_SIZE: usize = 12; // 96 bits for GCM
 pub const AES_TAG_SIZE: usize = 16; // 128 bits
 pub const SALT_SIZE: usize = 32; // 256 bits
 pub const PBKDF2_ITERATIONS: u32 = 100_000;
+/// Highest iteration count accepted from a blob header, 100 times the
+/// default; anything larger is treated as a crafted header meant to make
+/// decryption hang
+pub const MAX_KDF_ITERATIONS: u32 = 100 * PBKDF2_ITERATIONS;
+
+/// Marks an [`EncryptedData`] blob that starts with a parameter header;
+/// the last byte is the header version
+const HEADER_MAGIC: &[u8; 4] = b"ZLE\x01";
+/// Magic, cipher id, KDF id and the iteration count
+const HEADER_SIZE: usize = HEADER_MAGIC.len() + 2 + 4;
+
+/// Associated data binding each half of a field envelope to its purpose
+const FIELD_KEY_AAD: &[u8] = b"ziplock-field-key-v1";
+const FIELD_VALUE_AAD: &[u8] = b"ziplock-field-value-v1";
+
+/// Cipher used for an [`EncryptedData`] blob
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CipherAlgorithm {
+    /// SHA-256 keystream with a truncated SHA-256 tag
+    Sha256Stream,
+}
+
+impl CipherAlgorithm {
+    fn id(self) -> u8 {
+        match self {
+            CipherAlgorithm::Sha256Stream => 1,
+        }
+    }
+
+    fn from_id(id: u8) -> EncryptionResult<Self> {
+        match id {
+            1 => Ok(CipherAlgorithm::Sha256Stream),
+            _ => Err(EncryptionError::InvalidInput),
+        }
+    }
+}
+
+/// Key derivation used for an [`EncryptedData`] blob
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KdfAlgorithm {
+    /// SHA-256 applied repeatedly to password || salt
+    IteratedSha256,
+}
+
+impl KdfAlgorithm {
+    fn id(self) -> u8 {
+        match self {
+            KdfAlgorithm::IteratedSha256 => 1,
+        }
+    }
+
+    fn from_id(id: u8) -> EncryptionResult<Self> {
+        match id {
+            1 => Ok(KdfAlgorithm::IteratedSha256),
+            _ => Err(EncryptionError::InvalidInput),
+        }
+    }
+}
+
+/// Cipher and key derivation parameters, stored in the blob header so they
+/// can change without breaking older blobs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CryptoParams {
+    pub cipher: CipherAlgorithm,
+    pub kdf: KdfAlgorithm,
+    pub kdf_iterations: u32,
+}
+
+impl Default for CryptoParams {
+    fn default() -> Self {
+        Self {
+            cipher: CipherAlgorithm::Sha256Stream,
+            kdf: KdfAlgorithm::IteratedSha256,
+            kdf_iterations: PBKDF2_ITERATIONS,
+        }
+    }
+}
+
+impl CryptoParams {
+    /// The header written in front of a blob
+    fn header(&self) -> [u8; HEADER_SIZE] {
+        let mut header = [0u8; HEADER_SIZE];
+        header[..4].copy_from_slice(HEADER_MAGIC);
+        header[4] = self.cipher.id();
+        header[5] = self.kdf.id();
+        header[6..].copy_from_slice(&self.kdf_iterations.to_le_bytes());
+        header
+    }
+
+    fn from_header(header: &[u8]) -> EncryptionResult<Self> {
+        let kdf_iterations = u32::from_le_bytes(
+            header[6..HEADER_SIZE]
+                .try_into()
+                .map_err(|_| EncryptionError::InvalidInput)?,
+        );
+        if kdf_iterations == 0 || kdf_iterations > MAX_KDF_ITERATIONS {
+            return Err(EncryptionError::InvalidInput);
+        }
+        Ok(Self {
+            cipher: CipherAlgorithm::from_id(header[4])?,
+            kdf: KdfAlgorithm::from_id(header[5])?,
+            kdf_iterations,
+        })
+    }
+}
 
 /// Encrypted data container
 #[derive(Debug, Clone)]
 pub struct EncryptedData {
+    /// Cipher and key derivation the data was encrypted with
+    pub params: CryptoParams,
     /// Salt used for key derivation
     pub salt: Vec<u8>,
     /// Initialization vector
@@ -65,12 +180,15 @@ pub struct EncryptedData {
     pub ciphertext: Vec<u8>,
     /// Authentication tag
     pub tag: Vec<u8>,
+    /// Whether the blob was read with a header, which the tag then covers;
+    /// false only for blobs from before the header existed
+    has_header: bool,
 }
 
 impl EncryptedData {
-    /// Serialize encrypted data to bytes
+    /// Serialize encrypted data to bytes, starting with the parameter header
     pub fn to_bytes(&self) -> Vec<u8> {
-        let mut data = Vec::new();
+        let mut data = self.params.header().to_vec();
         data.extend_from_slice(&(self.salt.len() as u32).to_le_bytes());
         data.extend_from_slice(&self.salt);
         data.extend_from_slice(&(self.iv.len() as u32).to_le_bytes());
@@ -82,7 +200,20 @@ impl EncryptedData {
     }
 
     /// Deserialize encrypted data from bytes
+    ///
+    /// Blobs written before the header existed are read with the default
+    /// parameters.
     pub fn from_bytes(data: &[u8]) -> EncryptionResult<Self> {
+        let (params, data) = if data.starts_with(HEADER_MAGIC) {
+            if data.len() < HEADER_SIZE {
+                return Err(EncryptionError::InvalidInput);
+            }
+            let (header, rest) = data.split_at(HEADER_SIZE);
+            (Some(CryptoParams::from_header(header)?), rest)
+        } else {
+            (None, data)
+        };
+
         if data.len() < 12 {
             // Minimum size: 3 * u32 length fields
             return Err(EncryptionError::InvalidInput);
@@ -136,12 +267,57 @@ impl EncryptedData {
         let ciphertext = data[offset..].to_vec();
 
         Ok(EncryptedData {
+            params: params.unwrap_or_default(),
+            has_header: params.is_some(),
             salt,
             iv,
             ciphertext,
             tag,
         })
     }
+
+    /// Bytes covered by the tag besides the ciphertext
+    fn authenticated_header(&self) -> Vec<u8> {
+        if self.has_header {
+            self.params.header().to_vec()
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+/// A nonce that can encrypt exactly one message
+///
+/// It is neither `Clone` nor `Copy` and sealing consumes it, so the same
+/// nonce cannot be handed to the cipher twice by accident.
+pub struct FreshNonce([u8; AES_IV_SIZE]);
+
+impl FreshNonce {
+    /// A nonce from the system's secure random generator
+    pub fn random() -> Self {
+        let mut nonce = [0u8; AES_IV_SIZE];
+        thread_rng().fill_bytes(&mut nonce);
+        Self(nonce)
+    }
+
+    /// A nonce with the given bytes, for reproducible test vectors only
+    #[cfg(feature = "test-vectors")]
+    pub(crate) fn fixed(bytes: &[u8]) -> EncryptionResult<Self> {
+        bytes
+            .try_into()
+            .map(Self)
+            .map_err(|_| EncryptionError::InvalidIvLength)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl std::fmt::Debug for FreshNonce {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "FreshNonce({})", BASE64_STANDARD.encode(self.0))
+    }
 }
 
 /// Secure encryption utilities
@@ -163,41 +339,55 @@ impl EncryptionUtils {
     }
 
     /// Derive encryption key from password using PBKDF2
-    pub fn derive_key(password: &str, salt: &[u8]) -> EncryptionResult<Vec<u8>> {
-        if salt.len() < 16 {
+    pub fn derive_key(password: &str, salt: &[u8]) -> EncryptionResult<SecretBytes> {
+        Self::derive_key_with(password, salt, PBKDF2_ITERATIONS)
+    }
+
+    /// [`Self::derive_key`] with the iteration count from a blob's header
+    pub fn derive_key_with(
+        password: &str,
+        salt: &[u8],
+        iterations: u32,
+    ) -> EncryptionResult<SecretBytes> {
+        if salt.len() < 16 || iterations == 0 || iterations > MAX_KDF_ITERATIONS {
             return Err(EncryptionError::KeyDerivationFailed);
         }
 
-        let mut key = vec![0u8; AES_KEY_SIZE];
-
         // Simple PBKDF2 implementation using SHA-256
         // Note: In production, use a proper PBKDF2 library like `pbkdf2` crate
         let mut hasher = Sha256::new();
-        let mut current = password.as_bytes().to_vec();
+        let mut current = Zeroizing::new(Vec::with_capacity(password.len() + salt.len()));
+        current.extend_from_slice(password.as_bytes());
         current.extend_from_slice(salt);
 
-        for _ in 0..PBKDF2_ITERATIONS {
-            hasher.update(&current);
-            current = hasher.finalize_reset().to_vec();
+        for _ in 0..iterations {
+            hasher.update(&*current);
+            current = Zeroizing::new(hasher.finalize_reset().to_vec());
         }
 
-        key.copy_from_slice(&current[..AES_KEY_SIZE]);
-        Ok(key)
+        Ok(SecretBytes::from_slice(&current[..AES_KEY_SIZE]))
     }
 
     /// Encrypt data using AES-256-GCM (simplified implementation)
+    ///
+    /// The salt and nonce are always fresh, so encrypting the same data
+    /// twice gives different ciphertexts.
     pub fn encrypt(plaintext: &[u8], password: &str) -> EncryptionResult<EncryptedData> {
+        let params = CryptoParams::default();
         let salt = Self::generate_salt();
-        let iv = Self::generate_iv();
-        let key = Self::derive_key(password, &salt)?;
+        let iv = FreshNonce::random();
+        let key = Self::derive_key_with(password, &salt, params.kdf_iterations)?;
 
         // Simplified AES encryption (in production, use a proper AES-GCM library)
-        let ciphertext = Self::simple_encrypt(plaintext, &key, &iv)?;
-        let tag = Self::compute_auth_tag(&ciphertext, &key, &iv);
+        let ciphertext = Self::simple_encrypt(plaintext, key.expose(), iv.as_bytes())?;
+        let tag =
+            Self::compute_auth_tag(&params.header(), &ciphertext, key.expose(), iv.as_bytes());
 
         Ok(EncryptedData {
+            params,
+            has_header: true,
             salt,
-            iv,
+            iv: iv.as_bytes().to_vec(),
             ciphertext,
             tag,
         })
@@ -205,18 +395,24 @@ impl EncryptionUtils {
 
     /// Decrypt data using AES-256-GCM (simplified implementation)
     pub fn decrypt(encrypted: &EncryptedData, password: &str) -> EncryptionResult<Vec<u8>> {
-        let key = Self::derive_key(password, &encrypted.salt)?;
-
-        // Verify authentication tag
-        let expected_tag = Self::compute_auth_tag(&encrypted.ciphertext, &key, &encrypted.iv);
-        if expected_tag != encrypted.tag {
+        let key =
+            Self::derive_key_with(password, &encrypted.salt, encrypted.params.kdf_iterations)?;
+
+        // Verify authentication tag, which also covers the parameters
+        let expected_tag = Self::compute_auth_tag(
+            &encrypted.authenticated_header(),
+            &encrypted.ciphertext,
+            key.expose(),
+            &encrypted.iv,
+        );
+        if !Self::secure_compare(&expected_tag, &encrypted.tag) {
             return Err(EncryptionError::DecryptionFailed(
                 "Authentication failed".to_string(),
             ));
         }
 
         // Decrypt data
-        let plaintext = Self::simple_decrypt(&encrypted.ciphertext, &key, &encrypted.iv)?;
+        let plaintext = Self::simple_decrypt(&encrypted.ciphertext, key.expose(), &encrypted.iv)?;
         Ok(plaintext)
     }
 
@@ -269,15 +465,18 @@ impl EncryptionUtils {
     }
 
     /// Compute authentication tag (simplified HMAC)
-    fn compute_auth_tag(data: &[u8], key: &[u8], iv: &[u8]) -> Vec<u8> {
+    fn compute_auth_tag(header: &[u8], data: &[u8], key: &[u8], iv: &[u8]) -> Vec<u8> {
         let mut hasher = Sha256::new();
         hasher.update(key);
         hasher.update(iv);
+        hasher.update(header);
         hasher.update(data);
         hasher.finalize()[..AES_TAG_SIZE].to_vec()
     }
 
     /// Securely compare two byte arrays (constant time)
+    ///
+    /// Only the lengths leak; use this for tags, MACs and password hashes.
     pub fn secure_compare(a: &[u8], b: &[u8]) -> bool {
         if a.len() != b.len() {
             return false;
@@ -285,7 +484,8 @@ impl EncryptionUtils {
 
         let mut result = 0u8;
         for (x, y) in a.iter().zip(b.iter()) {
-            result |= x ^ y;
+            // Keeps the optimizer from turning the loop into an early exit
+            result = std::hint::black_box(result | (x ^ y));
         }
         result == 0
     }
@@ -304,16 +504,166 @@ impl EncryptionUtils {
         hasher.finalize().to_vec()
     }
 
+    /// Lowercase hex encoding of `bytes`
+    pub fn to_hex(bytes: &[u8]) -> String {
+        const DIGITS: &[u8; 16] = b"0123456789abcdef";
+        let mut hex = String::with_capacity(bytes.len() * 2);
+        for &byte in bytes {
+            hex.push(DIGITS[usize::from(byte >> 4)] as char);
+            hex.push(DIGITS[usize::from(byte & 0x0f)] as char);
+        }
+        hex
+    }
+
     /// Generate a secure random key
-    pub fn generate_key() -> Vec<u8> {
-        Self::random_bytes(AES_KEY_SIZE)
+    pub fn generate_key() -> SecretBytes {
+        let mut key = SecretBytes::zeroed(AES_KEY_SIZE);
+        thread_rng().fill_bytes(key.expose_mut());
+        key
     }
 }
 
+/// Whether [`SecretBytes`] try to lock their pages in RAM
+static MEMORY_LOCKING: AtomicBool = AtomicBool::new(true);
+
+/// Number of locked buffers on each locked page, by page address
+///
+/// `mlock` and `VirtualLock` work on whole pages and do not nest, so
+/// unlocking one buffer would otherwise unlock its neighbours on the same
+/// page too.
+static LOCKED_PAGES: Mutex<BTreeMap<usize, usize>> = Mutex::new(BTreeMap::new());
+
+fn locked_pages() -> MutexGuard<'static, BTreeMap<usize, usize>> {
+    LOCKED_PAGES.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn page_size() -> usize {
+    static PAGE_SIZE: OnceLock<usize> = OnceLock::new();
+    *PAGE_SIZE.get_or_init(|| {
+        #[cfg(unix)]
+        {
+            // SAFETY: sysconf has no preconditions
+            let size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+            if size > 0 {
+                return size as usize;
+            }
+        }
+        4096
+    })
+}
+
+/// Addresses of the pages `data` spans
+fn pages(data: &[u8]) -> impl Iterator<Item = usize> {
+    let page = page_size();
+    let start = data.as_ptr() as usize / page * page;
+    (start..data.as_ptr() as usize + data.len()).step_by(page)
+}
+
+fn lock_page(page: usize) -> bool {
+    #[cfg(unix)]
+    {
+        // SAFETY: the page belongs to a live allocation of the caller's
+        unsafe { libc::mlock(page as *const libc::c_void, page_size()) == 0 }
+    }
+    #[cfg(windows)]
+    {
+        // SAFETY: the page belongs to a live allocation of the caller's
+        unsafe {
+            windows::Win32::System::Memory::VirtualLock(page as *const _, page_size()).is_ok()
+        }
+    }
+    #[cfg(not(any(unix, windows)))]
+    {
+        let _ = page;
+        false
+    }
+}
+
+fn unlock_page(page: usize) {
+    #[cfg(unix)]
+    // SAFETY: the page belongs to a live allocation of the caller's
+    unsafe {
+        libc::munlock(page as *const libc::c_void, page_size());
+    }
+    #[cfg(windows)]
+    // SAFETY: the page belongs to a live allocation of the caller's
+    unsafe {
+        let _ = windows::Win32::System::Memory::VirtualUnlock(page as *const _, page_size());
+    }
+    #[cfg(not(any(unix, windows)))]
+    let _ = page;
+}
+
 /// Secure memory utilities for handling sensitive data
 pub struct SecureMemory;
 
 impl SecureMemory {
+    /// Turn locking of key buffers in RAM on or off for buffers created
+    /// from now on
+    ///
+    /// On by default. Locking keeps keys out of swap and hibernation files,
+    /// but each locked buffer pins at least one page and counts against
+    /// `RLIMIT_MEMLOCK` on Unix, so a process that holds many keys may want
+    /// to turn it off.
+    pub fn set_memory_locking(enabled: bool) {
+        MEMORY_LOCKING.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Whether new key buffers are locked in RAM
+    pub fn memory_locking() -> bool {
+        MEMORY_LOCKING.load(Ordering::Relaxed)
+    }
+
+    /// Lock the pages holding `data` in RAM with `mlock` or `VirtualLock`
+    ///
+    /// Returns false where the platform has no such call or refuses, e.g.
+    /// because the process is over its locked-memory limit. Pages are
+    /// counted, so that buffers sharing a page keep it locked until the last
+    /// of them is unlocked.
+    pub fn lock(data: &[u8]) -> bool {
+        if data.is_empty() {
+            return false;
+        }
+        let mut counts = locked_pages();
+        let mut newly_locked = Vec::new();
+        for page in pages(data) {
+            if counts.contains_key(&page) {
+                continue;
+            }
+            if !lock_page(page) {
+                for page in newly_locked {
+                    unlock_page(page);
+                }
+                return false;
+            }
+            newly_locked.push(page);
+        }
+        for page in pages(data) {
+            *counts.entry(page).or_insert(0) += 1;
+        }
+        true
+    }
+
+    /// Undo a [`Self::lock`] that returned true
+    ///
+    /// Pages that another locked buffer still uses stay locked.
+    pub fn unlock(data: &[u8]) {
+        if data.is_empty() {
+            return;
+        }
+        let mut counts = locked_pages();
+        for page in pages(data) {
+            let Some(count) = counts.get_mut(&page) else {
+                continue;
+            };
+            *count -= 1;
+            if *count == 0 {
+                counts.remove(&page);
+                unlock_page(page);
+            }
+        }
+    }
+
     /// Securely zero out memory
     pub fn zero_memory(data: &mut [u8]) {
         // Prevent compiler optimization with volatile write
@@ -328,6 +678,29 @@ impl SecureMemory {
     pub fn secure_string(s: String) -> SecureString {
         SecureString::new(s)
     }
+
+    /// Overwrite a file with zeros, flush it to disk and unlink it
+    ///
+    /// Copy-on-write and journaling filesystems or SSD wear levelling may
+    /// still keep old blocks, so sensitive data should not reach the disk
+    /// unencrypted in the first place.
+    pub fn wipe_file(path: &std::path::Path) -> std::io::Result<()> {
+        use std::io::{Seek, SeekFrom, Write};
+
+        let mut file = std::fs::OpenOptions::new().write(true).open(path)?;
+        let len = file.metadata()?.len();
+        let zeros = [0u8; 8192];
+        let mut written = 0u64;
+        file.seek(SeekFrom::Start(0))?;
+        while written < len {
+            let chunk = (len - written).min(zeros.len() as u64) as usize;
+            file.write_all(&zeros[..chunk])?;
+            written += chunk as u64;
+        }
+        file.sync_all()?;
+        drop(file);
+        std::fs::remove_file(path)
+    }
 }
 
 /// A string that securely zeros its memory on drop
@@ -368,6 +741,110 @@ impl std::fmt::Debug for SecureString {
     }
 }
 
+/// Key material that is zeroed on drop and, while
+/// [`SecureMemory::memory_locking`] is on, locked in RAM
+///
+/// The buffer never grows, so the bytes are never copied to a new
+/// allocation behind the caller's back. Locking is best effort; check
+/// [`Self::is_locked`] where it matters.
+pub struct SecretBytes {
+    data: Box<[u8]>,
+    locked: bool,
+}
+
+impl SecretBytes {
+    /// Take ownership of `data`
+    pub fn new(mut data: Vec<u8>) -> Self {
+        if data.len() == data.capacity() {
+            return Self::from_box(data.into_boxed_slice());
+        }
+        // Shrinking would reallocate and leave a copy behind
+        let secret = Self::from_slice(&data);
+        data.zeroize();
+        secret
+    }
+
+    /// Copy `data` into a new secret
+    pub fn from_slice(data: &[u8]) -> Self {
+        Self::from_box(data.into())
+    }
+
+    /// A secret of `len` zero bytes, to be filled through [`Self::expose_mut`]
+    pub fn zeroed(len: usize) -> Self {
+        Self::from_box(vec![0u8; len].into_boxed_slice())
+    }
+
+    fn from_box(data: Box<[u8]>) -> Self {
+        let locked = SecureMemory::memory_locking() && SecureMemory::lock(&data);
+        Self { data, locked }
+    }
+
+    /// The secret bytes
+    pub fn expose(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// The secret bytes, for filling in place
+    pub fn expose_mut(&mut self) -> &mut [u8] {
+        &mut self.data
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Whether the pages holding the secret are locked in RAM
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+}
+
+impl Drop for SecretBytes {
+    fn drop(&mut self) {
+        self.data.zeroize();
+        if self.locked {
+            SecureMemory::unlock(&self.data);
+        }
+    }
+}
+
+impl Clone for SecretBytes {
+    fn clone(&self) -> Self {
+        Self::from_slice(&self.data)
+    }
+}
+
+/// Compares in time that depends only on the lengths
+impl PartialEq for SecretBytes {
+    fn eq(&self, other: &Self) -> bool {
+        EncryptionUtils::secure_compare(&self.data, &other.data)
+    }
+}
+
+impl Eq for SecretBytes {}
+
+impl AsRef<[u8]> for SecretBytes {
+    fn as_ref(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+impl From<Vec<u8>> for SecretBytes {
+    fn from(data: Vec<u8>) -> Self {
+        Self::new(data)
+    }
+}
+
+impl std::fmt::Debug for SecretBytes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SecretBytes([REDACTED; {}])", self.data.len())
+    }
+}
+
 /// Credential encryption helper
 pub struct CredentialCrypto;
 
@@ -397,6 +874,195 @@ impl CredentialCrypto {
         // Simple heuristic: encrypted values are base64 and reasonably long
         value.len() > 50 && BASE64_STANDARD.decode(value).is_ok()
     }
+
+    /// Seal `value` in an envelope that only `passphrase` opens
+    pub fn seal_envelope(value: &str, passphrase: &str) -> EncryptionResult<FieldProtection> {
+        if passphrase.is_empty() {
+            return Err(EncryptionError::InvalidInput);
+        }
+        let field_key = EncryptionUtils::generate_key();
+        Self::seal_envelope_with(
+            value,
+            passphrase,
+            field_key.expose(),
+            &EncryptionUtils::generate_salt(),
+            FreshNonce::random(),
+            FreshNonce::random(),
+        )
+    }
+
+    /// [`Self::seal_envelope`] with the field key, salt and nonces given
+    /// rather than random, so the result is reproducible
+    pub(crate) fn seal_envelope_with(
+        value: &str,
+        passphrase: &str,
+        field_key: &[u8],
+        salt: &[u8],
+        key_nonce: FreshNonce,
+        nonce: FreshNonce,
+    ) -> EncryptionResult<FieldProtection> {
+        let encoded_nonce = BASE64_STANDARD.encode(nonce.as_bytes());
+        let ciphertext = Self::seal(field_key, nonce, value.as_bytes(), FIELD_VALUE_AAD)?;
+        let mut envelope = FieldProtection {
+            salt: String::new(),
+            key_nonce: String::new(),
+            wrapped_key: String::new(),
+            nonce: encoded_nonce,
+            ciphertext: BASE64_STANDARD.encode(ciphertext),
+        };
+        Self::wrap_field_key(&mut envelope, field_key, passphrase, salt, key_nonce)?;
+        Ok(envelope)
+    }
+
+    /// Recover the value sealed in `envelope`
+    pub fn open_envelope(
+        envelope: &FieldProtection,
+        passphrase: &str,
+    ) -> EncryptionResult<Zeroizing<String>> {
+        let field_key = Self::unwrap_field_key(envelope, passphrase)?;
+        let plaintext = Self::open(
+            &field_key,
+            &Self::decode(&envelope.nonce)?,
+            &Self::decode(&envelope.ciphertext)?,
+            FIELD_VALUE_AAD,
+        )?;
+        String::from_utf8(plaintext.to_vec())
+            .map(Zeroizing::new)
+            .map_err(|_| EncryptionError::DecryptionFailed("Invalid UTF-8".to_string()))
+    }
+
+    /// Make `envelope` open with `new_passphrase` instead of `old_passphrase`
+    ///
+    /// Only the field key is re-wrapped; the sealed value is unchanged.
+    pub fn rewrap_envelope(
+        envelope: &FieldProtection,
+        old_passphrase: &str,
+        new_passphrase: &str,
+    ) -> EncryptionResult<FieldProtection> {
+        if new_passphrase.is_empty() {
+            return Err(EncryptionError::InvalidInput);
+        }
+        let field_key = Self::unwrap_field_key(envelope, old_passphrase)?;
+        let mut rewrapped = envelope.clone();
+        Self::wrap_field_key(
+            &mut rewrapped,
+            &field_key,
+            new_passphrase,
+            &EncryptionUtils::generate_salt(),
+            FreshNonce::random(),
+        )?;
+        Ok(rewrapped)
+    }
+
+    /// Encrypt `field` with `passphrase`, leaving the placeholder as its value
+    pub fn protect_field(field: &mut CredentialField, passphrase: &str) -> EncryptionResult<()> {
+        if field.is_protected() {
+            return Err(EncryptionError::InvalidInput);
+        }
+        field.protection = Some(Self::seal_envelope(&field.value, passphrase)?);
+        field.value = PROTECTED_FIELD_PLACEHOLDER.to_string();
+        Ok(())
+    }
+
+    /// The real value of a protected `field`
+    pub fn unlock_field(
+        field: &CredentialField,
+        passphrase: &str,
+    ) -> EncryptionResult<Zeroizing<String>> {
+        match &field.protection {
+            Some(envelope) => Self::open_envelope(envelope, passphrase),
+            None => Err(EncryptionError::InvalidInput),
+        }
+    }
+
+    /// Decrypt a protected `field` in place so it is stored like any other
+    pub fn unprotect_field(field: &mut CredentialField, passphrase: &str) -> EncryptionResult<()> {
+        let value = Self::unlock_field(field, passphrase)?;
+        field.value = value.to_string();
+        field.protection = None;
+        Ok(())
+    }
+
+    fn wrap_field_key(
+        envelope: &mut FieldProtection,
+        field_key: &[u8],
+        passphrase: &str,
+        salt: &[u8],
+        key_nonce: FreshNonce,
+    ) -> EncryptionResult<()> {
+        let passphrase_key = EncryptionUtils::derive_key(passphrase, salt)?;
+        envelope.key_nonce = BASE64_STANDARD.encode(key_nonce.as_bytes());
+        let wrapped_key = Self::seal(passphrase_key.expose(), key_nonce, field_key, FIELD_KEY_AAD)?;
+        envelope.salt = BASE64_STANDARD.encode(salt);
+        envelope.wrapped_key = BASE64_STANDARD.encode(wrapped_key);
+        Ok(())
+    }
+
+    fn unwrap_field_key(
+        envelope: &FieldProtection,
+        passphrase: &str,
+    ) -> EncryptionResult<Zeroizing<Vec<u8>>> {
+        let salt = Self::decode(&envelope.salt)?;
+        let passphrase_key = EncryptionUtils::derive_key(passphrase, &salt)?;
+        Self::open(
+            passphrase_key.expose(),
+            &Self::decode(&envelope.key_nonce)?,
+            &Self::decode(&envelope.wrapped_key)?,
+            FIELD_KEY_AAD,
+        )
+        .map_err(|_| EncryptionError::DecryptionFailed("Wrong passphrase".to_string()))
+    }
+
+    /// Consumes `nonce` so it cannot seal a second message
+    fn seal(
+        key: &[u8],
+        nonce: FreshNonce,
+        plaintext: &[u8],
+        aad: &[u8],
+    ) -> EncryptionResult<Vec<u8>> {
+        if key.len() != AES_KEY_SIZE {
+            return Err(EncryptionError::InvalidKeyLength);
+        }
+        ChaCha20Poly1305::new(Key::from_slice(key))
+            .encrypt(
+                Nonce::from_slice(nonce.as_bytes()),
+                Payload {
+                    msg: plaintext,
+                    aad,
+                },
+            )
+            .map_err(|e| EncryptionError::EncryptionFailed(e.to_string()))
+    }
+
+    fn open(
+        key: &[u8],
+        nonce: &[u8],
+        ciphertext: &[u8],
+        aad: &[u8],
+    ) -> EncryptionResult<Zeroizing<Vec<u8>>> {
+        if key.len() != AES_KEY_SIZE {
+            return Err(EncryptionError::InvalidKeyLength);
+        }
+        if nonce.len() != AES_IV_SIZE {
+            return Err(EncryptionError::InvalidIvLength);
+        }
+        ChaCha20Poly1305::new(Key::from_slice(key))
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad,
+                },
+            )
+            .map(Zeroizing::new)
+            .map_err(|_| EncryptionError::DecryptionFailed("Authentication failed".to_string()))
+    }
+
+    fn decode(value: &str) -> EncryptionResult<Vec<u8>> {
+        BASE64_STANDARD
+            .decode(value)
+            .map_err(|_| EncryptionError::DecryptionFailed("Invalid base64".to_string()))
+    }
 }
 
 #[cfg(test)]
@@ -471,10 +1137,97 @@ mod tests {
         let bytes = encrypted.to_bytes();
         let deserialized = EncryptedData::from_bytes(&bytes).unwrap();
 
+        assert!(bytes.starts_with(HEADER_MAGIC));
+        assert_eq!(deserialized.params, CryptoParams::default());
         assert_eq!(encrypted.salt, deserialized.salt);
         assert_eq!(encrypted.iv, deserialized.iv);
         assert_eq!(encrypted.ciphertext, deserialized.ciphertext);
         assert_eq!(encrypted.tag, deserialized.tag);
+        assert_eq!(
+            EncryptionUtils::decrypt(&deserialized, password).unwrap(),
+            plaintext
+        );
+    }
+
+    #[test]
+    fn test_legacy_blob_without_header() {
+        let password = "legacy";
+        let salt = EncryptionUtils::generate_salt();
+        let iv = EncryptionUtils::generate_iv();
+        let key = EncryptionUtils::derive_key(password, &salt).unwrap();
+        let ciphertext = EncryptionUtils::simple_encrypt(b"old data", key.expose(), &iv).unwrap();
+        let tag = EncryptionUtils::compute_auth_tag(&[], &ciphertext, key.expose(), &iv);
+
+        let mut bytes = Vec::new();
+        for part in [&salt, &iv, &tag] {
+            bytes.extend_from_slice(&(part.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(part);
+        }
+        bytes.extend_from_slice(&ciphertext);
+
+        let legacy = EncryptedData::from_bytes(&bytes).unwrap();
+        assert_eq!(legacy.params, CryptoParams::default());
+        assert_eq!(
+            EncryptionUtils::decrypt(&legacy, password).unwrap(),
+            b"old data"
+        );
+    }
+
+    #[test]
+    fn test_header_is_authenticated() {
+        let encrypted = EncryptionUtils::encrypt(b"data", "password").unwrap();
+        let mut bytes = encrypted.to_bytes();
+
+        // A lower iteration count derives another key and fails the tag
+        bytes[6..HEADER_SIZE].copy_from_slice(&1000u32.to_le_bytes());
+        let tampered = EncryptedData::from_bytes(&bytes).unwrap();
+        assert_eq!(tampered.params.kdf_iterations, 1000);
+        assert!(EncryptionUtils::decrypt(&tampered, "password").is_err());
+
+        bytes[4] = 99;
+        assert_eq!(
+            EncryptedData::from_bytes(&bytes).unwrap_err(),
+            EncryptionError::InvalidInput
+        );
+    }
+
+    #[test]
+    fn test_header_iterations_are_capped() {
+        let mut bytes = EncryptionUtils::encrypt(b"data", "password")
+            .unwrap()
+            .to_bytes();
+
+        bytes[6..HEADER_SIZE].copy_from_slice(&MAX_KDF_ITERATIONS.to_le_bytes());
+        assert_eq!(
+            EncryptedData::from_bytes(&bytes)
+                .unwrap()
+                .params
+                .kdf_iterations,
+            MAX_KDF_ITERATIONS
+        );
+
+        for iterations in [MAX_KDF_ITERATIONS + 1, u32::MAX, 0] {
+            bytes[6..HEADER_SIZE].copy_from_slice(&iterations.to_le_bytes());
+            assert_eq!(
+                EncryptedData::from_bytes(&bytes).unwrap_err(),
+                EncryptionError::InvalidInput
+            );
+        }
+        assert!(EncryptionUtils::derive_key_with(
+            "password",
+            &EncryptionUtils::generate_salt(),
+            u32::MAX
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_fresh_nonce() {
+        assert_ne!(
+            FreshNonce::random().as_bytes(),
+            FreshNonce::random().as_bytes()
+        );
+        assert_eq!(FreshNonce::random().as_bytes().len(), AES_IV_SIZE);
     }
 
     #[test]
@@ -501,6 +1254,97 @@ mod tests {
         drop(secure);
     }
 
+    #[test]
+    fn test_secret_bytes() {
+        let secret = SecretBytes::new(vec![7u8; 32]);
+        assert_eq!(secret.expose(), &[7u8; 32]);
+        assert_eq!(secret.clone(), secret);
+        assert_ne!(secret, SecretBytes::zeroed(32));
+        assert_ne!(secret, SecretBytes::new(vec![7u8; 31]));
+        assert_eq!(format!("{:?}", secret), "SecretBytes([REDACTED; 32])");
+
+        SecureMemory::set_memory_locking(false);
+        assert!(!SecretBytes::zeroed(32).is_locked());
+        SecureMemory::set_memory_locking(true);
+    }
+
+    #[test]
+    fn test_shared_pages_stay_locked() {
+        // A page of its own, so other tests' buffers cannot share it
+        let page = page_size();
+        let layout = std::alloc::Layout::from_size_align(page, page).unwrap();
+        // SAFETY: the layout has a non-zero size
+        let ptr = unsafe { std::alloc::alloc_zeroed(layout) };
+        assert!(!ptr.is_null());
+        // SAFETY: ptr points to `page` zeroed bytes owned by this test
+        let buffer = unsafe { std::slice::from_raw_parts(ptr, page) };
+        let (first, second) = buffer.split_at(page / 2);
+        let count = || locked_pages().get(&(ptr as usize)).copied();
+
+        // Locking may be refused, e.g. over RLIMIT_MEMLOCK
+        if SecureMemory::lock(first) {
+            assert!(SecureMemory::lock(second));
+            assert_eq!(count(), Some(2));
+            SecureMemory::unlock(first);
+            assert_eq!(count(), Some(1));
+            SecureMemory::unlock(second);
+            assert_eq!(count(), None);
+        }
+        // SAFETY: allocated above with the same layout
+        unsafe { std::alloc::dealloc(ptr, layout) };
+    }
+
+    #[test]
+    fn test_protected_field_envelope() {
+        let mut field = CredentialField::text("recovery-1 recovery-2");
+        CredentialCrypto::protect_field(&mut field, "second secret").unwrap();
+        assert!(field.is_protected());
+        assert_eq!(field.value, PROTECTED_FIELD_PLACEHOLDER);
+        assert_eq!(field.display_value(), PROTECTED_FIELD_PLACEHOLDER);
+        assert!(field.validate().is_ok());
+        assert_eq!(
+            CredentialCrypto::protect_field(&mut field, "again"),
+            Err(EncryptionError::InvalidInput)
+        );
+
+        assert!(CredentialCrypto::unlock_field(&field, "wrong").is_err());
+        assert_eq!(
+            CredentialCrypto::unlock_field(&field, "second secret")
+                .unwrap()
+                .as_str(),
+            "recovery-1 recovery-2"
+        );
+
+        let envelope = field.protection.clone().unwrap();
+        let rewrapped =
+            CredentialCrypto::rewrap_envelope(&envelope, "second secret", "new secret").unwrap();
+        assert_eq!(rewrapped.ciphertext, envelope.ciphertext);
+        assert_ne!(rewrapped.wrapped_key, envelope.wrapped_key);
+        assert!(CredentialCrypto::open_envelope(&rewrapped, "second secret").is_err());
+        field.protection = Some(rewrapped);
+
+        field.value = "edited".to_string();
+        assert!(field.validate().is_err());
+        CredentialCrypto::unprotect_field(&mut field, "new secret").unwrap();
+        assert!(!field.is_protected());
+        assert_eq!(field.value, "recovery-1 recovery-2");
+    }
+
+    #[test]
+    fn test_wipe_file_overwrites_before_unlinking() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("scratch");
+        let link = dir.path().join("link");
+        std::fs::write(&path, b"plaintext secret").unwrap();
+        // A second link keeps the inode alive so the overwrite can be seen
+        std::fs::hard_link(&path, &link).unwrap();
+
+        SecureMemory::wipe_file(&path).unwrap();
+        assert!(!path.exists());
+        assert_eq!(std::fs::read(&link).unwrap(), vec![0u8; 16]);
+        assert!(SecureMemory::wipe_file(&path).is_err());
+    }
+
     #[test]
     fn test_credential_crypto() {
         let field_value = "sensitive_password";
@@ -546,6 +1390,15 @@ mod tests {
         assert_ne!(hash1, hash3);
     }
 
+    #[test]
+    fn test_to_hex() {
+        assert_eq!(EncryptionUtils::to_hex(&[]), "");
+        assert_eq!(
+            EncryptionUtils::to_hex(&[0x00, 0x0f, 0xa5, 0xff]),
+            "000fa5ff"
+        );
+    }
+
     #[test]
     fn test_random_bytes() {
         let bytes1 = EncryptionUtils::random_bytes(16);
@@ -572,4 +1425,50 @@ mod tests {
         let result = EncryptedData::from_bytes(invalid_data);
         assert!(result.is_err());
     }
+
+    mod properties {
+        use super::*;
+        use proptest::prelude::*;
+
+        proptest! {
+            // Each case derives several keys, so keep the count low
+            #![proptest_config(ProptestConfig::with_cases(8))]
+
+            #[test]
+            fn encryption_is_not_deterministic(
+                plaintext in proptest::collection::vec(any::<u8>(), 0..256),
+                password in "[ -~]{1,32}",
+            ) {
+                let first = EncryptionUtils::encrypt(&plaintext, &password).unwrap();
+                let second = EncryptionUtils::encrypt(&plaintext, &password).unwrap();
+                prop_assert_ne!(&first.salt, &second.salt);
+                prop_assert_ne!(&first.iv, &second.iv);
+                prop_assert_ne!(first.to_bytes(), second.to_bytes());
+                prop_assert_eq!(EncryptionUtils::decrypt(&first, &password).unwrap(), plaintext);
+            }
+
+            #[test]
+            fn envelopes_are_not_deterministic(
+                value in "\\PC{0,64}",
+                passphrase in "[ -~]{1,32}",
+            ) {
+                let first = CredentialCrypto::seal_envelope(&value, &passphrase).unwrap();
+                let second = CredentialCrypto::seal_envelope(&value, &passphrase).unwrap();
+                prop_assert_ne!(&first.nonce, &second.nonce);
+                prop_assert_ne!(&first.key_nonce, &second.key_nonce);
+                prop_assert_ne!(&first.ciphertext, &second.ciphertext);
+                let opened = CredentialCrypto::open_envelope(&first, &passphrase).unwrap();
+                prop_assert_eq!(opened.as_str(), value.as_str());
+            }
+
+            #[test]
+            fn secure_compare_matches_equality(
+                a in proptest::collection::vec(any::<u8>(), 0..64),
+                b in proptest::collection::vec(any::<u8>(), 0..64),
+            ) {
+                prop_assert_eq!(EncryptionUtils::secure_compare(&a, &b), a == b);
+                prop_assert!(EncryptionUtils::secure_compare(&a, &a));
+            }
+        }
+    }
 }
@@ -0,0 +1,599 @@
+//! Vault health score
+//!
+//! Rolls password strength, password reuse, two-factor coverage, backup
+//! recency and key derivation strength into a single 0-100 score, plus a
+//! checklist of fixes ordered by how many points each would recover.
+
+use crate::models::{CredentialRecord, FieldType};
+use crate::utils::password::{PasswordAnalyzer, PasswordStrength};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Points available for password strength
+const STRENGTH_WEIGHT: f64 = 35.0;
+/// Points available for avoiding password reuse
+const REUSE_WEIGHT: f64 = 25.0;
+/// Points available for two-factor coverage of logins
+const TWO_FACTOR_WEIGHT: f64 = 15.0;
+/// Points available for backup recency
+const BACKUP_WEIGHT: f64 = 15.0;
+/// Points available for key derivation strength
+const KDF_WEIGHT: f64 = 10.0;
+
+/// Backups younger than this earn full credit
+const BACKUP_FRESH_DAYS: f64 = 30.0;
+/// Backups older than this earn nothing
+const BACKUP_STALE_DAYS: f64 = 180.0;
+
+/// Iteration count that earns full credit for iterated-hash KDFs
+const RECOMMENDED_ITERATIONS: u32 = 600_000;
+
+/// Key derivation used by the 7z archive itself (SHA-256, 2^19 rounds)
+pub const ARCHIVE_KDF: (&str, u32) = ("sha256", 1 << 19);
+
+/// Area of vault hygiene a score component or checklist item covers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthCategory {
+    PasswordStrength,
+    PasswordReuse,
+    TwoFactor,
+    Backup,
+    KeyDerivation,
+}
+
+impl HealthCategory {
+    /// Human-readable name
+    pub fn name(&self) -> &'static str {
+        match self {
+            HealthCategory::PasswordStrength => "Password strength",
+            HealthCategory::PasswordReuse => "Password reuse",
+            HealthCategory::TwoFactor => "Two-factor coverage",
+            HealthCategory::Backup => "Backups",
+            HealthCategory::KeyDerivation => "Key derivation",
+        }
+    }
+}
+
+/// How urgently a checklist item should be addressed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthPriority {
+    High,
+    Medium,
+    Low,
+}
+
+impl HealthPriority {
+    fn from_points(points: u8) -> Self {
+        match points {
+            10.. => HealthPriority::High,
+            4..=9 => HealthPriority::Medium,
+            _ => HealthPriority::Low,
+        }
+    }
+}
+
+/// Contribution of one category to the overall score
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HealthComponent {
+    pub category: HealthCategory,
+    /// Points earned
+    pub score: u8,
+    /// Points available
+    pub max: u8,
+}
+
+/// A single actionable fix
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HealthCheckItem {
+    pub category: HealthCategory,
+    pub priority: HealthPriority,
+    pub title: String,
+    pub detail: String,
+    /// Credentials the fix applies to, if any
+    pub credential_ids: Vec<String>,
+    /// Points the score would recover once fixed
+    pub points: u8,
+}
+
+/// Overall vault health
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VaultHealth {
+    /// Overall score, 0-100
+    pub score: u8,
+    pub components: Vec<HealthComponent>,
+    /// Fixes, most valuable first
+    pub checklist: Vec<HealthCheckItem>,
+    pub analyzed_at: DateTime<Utc>,
+}
+
+impl VaultHealth {
+    /// Short label for the score
+    pub fn grade(&self) -> &'static str {
+        match self.score {
+            90.. => "Excellent",
+            75..=89 => "Good",
+            50..=74 => "Fair",
+            _ => "Poor",
+        }
+    }
+}
+
+/// Computes a [`VaultHealth`] for a set of credentials
+#[derive(Debug, Clone)]
+pub struct VaultHealthAnalyzer {
+    last_backup: Option<DateTime<Utc>>,
+    kdf: (String, u32),
+    now: Option<DateTime<Utc>>,
+}
+
+impl Default for VaultHealthAnalyzer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl VaultHealthAnalyzer {
+    /// Create an analyzer assuming no backups and the archive's own KDF
+    pub fn new() -> Self {
+        Self {
+            last_backup: None,
+            kdf: (ARCHIVE_KDF.0.to_string(), ARCHIVE_KDF.1),
+            now: None,
+        }
+    }
+
+    /// Time of the most recent successful backup
+    pub fn with_last_backup(mut self, last_backup: Option<DateTime<Utc>>) -> Self {
+        self.last_backup = last_backup;
+        self
+    }
+
+    /// Key derivation function protecting the vault
+    pub fn with_kdf<S: Into<String>>(mut self, name: S, iterations: u32) -> Self {
+        self.kdf = (name.into(), iterations);
+        self
+    }
+
+    /// Evaluate against a fixed clock instead of the current time
+    pub fn with_now(mut self, now: DateTime<Utc>) -> Self {
+        self.now = Some(now);
+        self
+    }
+
+    /// Score the given credentials
+    pub fn analyze(&self, credentials: &[CredentialRecord]) -> VaultHealth {
+        let now = self.now.unwrap_or_else(Utc::now);
+        let mut components = Vec::new();
+        let mut checklist = Vec::new();
+
+        let passwords: Vec<(&CredentialRecord, &str)> = credentials
+            .iter()
+            .filter_map(|credential| password_of(credential).map(|p| (credential, p)))
+            .collect();
+
+        self.score_strength(&passwords, &mut components, &mut checklist);
+        self.score_reuse(&passwords, &mut components, &mut checklist);
+        self.score_two_factor(&passwords, &mut components, &mut checklist);
+        self.score_backup(now, &mut components, &mut checklist);
+        self.score_kdf(&mut components, &mut checklist);
+
+        checklist.sort_by(|a, b| {
+            b.points
+                .cmp(&a.points)
+                .then_with(|| a.priority.cmp(&b.priority))
+        });
+
+        let score = components
+            .iter()
+            .map(|c| u32::from(c.score))
+            .sum::<u32>()
+            .min(100) as u8;
+
+        VaultHealth {
+            score,
+            components,
+            checklist,
+            analyzed_at: now,
+        }
+    }
+
+    fn score_strength(
+        &self,
+        passwords: &[(&CredentialRecord, &str)],
+        components: &mut Vec<HealthComponent>,
+        checklist: &mut Vec<HealthCheckItem>,
+    ) {
+        let analyses: Vec<_> = passwords
+            .iter()
+            .map(|(credential, password)| (*credential, PasswordAnalyzer::analyze(password)))
+            .collect();
+
+        let ratio = if analyses.is_empty() {
+            1.0
+        } else {
+            analyses
+                .iter()
+                .map(|(_, analysis)| f64::from(analysis.score.min(100)) / 100.0)
+                .sum::<f64>()
+                / analyses.len() as f64
+        };
+
+        let weak: Vec<String> = analyses
+            .iter()
+            .filter(|(_, analysis)| {
+                analysis.is_common
+                    || matches!(
+                        analysis.strength,
+                        PasswordStrength::VeryWeak | PasswordStrength::Weak
+                    )
+            })
+            .map(|(credential, _)| credential.id.clone())
+            .collect();
+
+        let component = component(HealthCategory::PasswordStrength, ratio, STRENGTH_WEIGHT);
+        let lost = component.max - component.score;
+        components.push(component);
+
+        if !weak.is_empty() {
+            checklist.push(item(
+                HealthCategory::PasswordStrength,
+                format!("Change {} weak password{}", weak.len(), plural(weak.len())),
+                "Replace these with long, randomly generated passwords.".to_string(),
+                weak,
+                lost,
+            ));
+        }
+    }
+
+    fn score_reuse(
+        &self,
+        passwords: &[(&CredentialRecord, &str)],
+        components: &mut Vec<HealthComponent>,
+        checklist: &mut Vec<HealthCheckItem>,
+    ) {
+        let mut by_password: HashMap<&str, Vec<&str>> = HashMap::new();
+        for (credential, password) in passwords {
+            by_password
+                .entry(password)
+                .or_default()
+                .push(credential.id.as_str());
+        }
+
+        let mut reused: Vec<String> = by_password
+            .values()
+            .filter(|ids| ids.len() > 1)
+            .flatten()
+            .map(|id| id.to_string())
+            .collect();
+        reused.sort();
+
+        let ratio = if passwords.is_empty() {
+            1.0
+        } else {
+            1.0 - reused.len() as f64 / passwords.len() as f64
+        };
+
+        let component = component(HealthCategory::PasswordReuse, ratio, REUSE_WEIGHT);
+        let lost = component.max - component.score;
+        components.push(component);
+
+        if !reused.is_empty() {
+            checklist.push(item(
+                HealthCategory::PasswordReuse,
+                format!("Stop reusing passwords across {} credentials", reused.len()),
+                "A breach of one site exposes every account sharing its password.".to_string(),
+                reused,
+                lost,
+            ));
+        }
+    }
+
+    fn score_two_factor(
+        &self,
+        passwords: &[(&CredentialRecord, &str)],
+        components: &mut Vec<HealthComponent>,
+        checklist: &mut Vec<HealthCheckItem>,
+    ) {
+        let logins: Vec<&CredentialRecord> = passwords
+            .iter()
+            .map(|(credential, _)| *credential)
+            .filter(|credential| credential.credential_type == "login")
+            .collect();
+
+        let missing: Vec<String> = logins
+            .iter()
+            .filter(|credential| !has_totp(credential))
+            .map(|credential| credential.id.clone())
+            .collect();
+
+        let ratio = if logins.is_empty() {
+            1.0
+        } else {
+            1.0 - missing.len() as f64 / logins.len() as f64
+        };
+
+        let component = component(HealthCategory::TwoFactor, ratio, TWO_FACTOR_WEIGHT);
+        let lost = component.max - component.score;
+        components.push(component);
+
+        if !missing.is_empty() {
+            checklist.push(item(
+                HealthCategory::TwoFactor,
+                format!(
+                    "Enable two-factor authentication on {} login{}",
+                    missing.len(),
+                    plural(missing.len())
+                ),
+                "Add a TOTP secret wherever the site supports it.".to_string(),
+                missing,
+                lost,
+            ));
+        }
+    }
+
+    fn score_backup(
+        &self,
+        now: DateTime<Utc>,
+        components: &mut Vec<HealthComponent>,
+        checklist: &mut Vec<HealthCheckItem>,
+    ) {
+        let age_days = self
+            .last_backup
+            .map(|at| (now - at).num_seconds().max(0) as f64 / 86_400.0);
+
+        let ratio = match age_days {
+            None => 0.0,
+            Some(days) if days <= BACKUP_FRESH_DAYS => 1.0,
+            Some(days) => (1.0
+                - (days - BACKUP_FRESH_DAYS) / (BACKUP_STALE_DAYS - BACKUP_FRESH_DAYS))
+                .max(0.0),
+        };
+
+        let component = component(HealthCategory::Backup, ratio, BACKUP_WEIGHT);
+        let lost = component.max - component.score;
+        components.push(component);
+
+        if lost > 0 {
+            let (title, detail) = match age_days {
+                None => (
+                    "Set up backups".to_string(),
+                    "No backup of this vault has been recorded.".to_string(),
+                ),
+                Some(days) => (
+                    "Back up your vault".to_string(),
+                    format!("The last backup is {} days old.", days.floor() as u64),
+                ),
+            };
+            checklist.push(item(
+                HealthCategory::Backup,
+                title,
+                detail,
+                Vec::new(),
+                lost,
+            ));
+        }
+    }
+
+    fn score_kdf(
+        &self,
+        components: &mut Vec<HealthComponent>,
+        checklist: &mut Vec<HealthCheckItem>,
+    ) {
+        let (name, iterations) = (&self.kdf.0, self.kdf.1);
+        let name = name.to_ascii_lowercase();
+
+        let ratio = if name.starts_with("argon2") || name == "scrypt" {
+            1.0
+        } else if name.contains("pbkdf2") || name.starts_with("sha") {
+            (f64::from(iterations) / f64::from(RECOMMENDED_ITERATIONS)).min(1.0)
+        } else {
+            0.5
+        };
+
+        let component = component(HealthCategory::KeyDerivation, ratio, KDF_WEIGHT);
+        let lost = component.max - component.score;
+        components.push(component);
+
+        if lost > 0 {
+            checklist.push(item(
+                HealthCategory::KeyDerivation,
+                "Strengthen key derivation".to_string(),
+                format!(
+                    "{} with {} iterations; use Argon2 or at least {} iterations.",
+                    self.kdf.0, iterations, RECOMMENDED_ITERATIONS
+                ),
+                Vec::new(),
+                lost,
+            ));
+        }
+    }
+}
+
+fn component(category: HealthCategory, ratio: f64, weight: f64) -> HealthComponent {
+    HealthComponent {
+        category,
+        score: (ratio.clamp(0.0, 1.0) * weight).round() as u8,
+        max: weight as u8,
+    }
+}
+
+fn item(
+    category: HealthCategory,
+    title: String,
+    detail: String,
+    credential_ids: Vec<String>,
+    points: u8,
+) -> HealthCheckItem {
+    HealthCheckItem {
+        category,
+        priority: HealthPriority::from_points(points),
+        title,
+        detail,
+        credential_ids,
+        points,
+    }
+}
+
+fn password_of(credential: &CredentialRecord) -> Option<&str> {
+    credential
+        .fields
+        .values()
+        .find(|field| field.field_type == FieldType::Password && !field.value.is_empty())
+        .map(|field| field.value.as_str())
+}
+
+fn has_totp(credential: &CredentialRecord) -> bool {
+    credential
+        .fields
+        .values()
+        .any(|field| field.field_type == FieldType::TotpSecret && !field.value.trim().is_empty())
+}
+
+fn plural(count: usize) -> &'static str {
+    if count == 1 {
+        ""
+    } else {
+        "s"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::CredentialField;
+    use chrono::Duration;
+
+    fn login(id: &str, password: &str, totp: bool) -> CredentialRecord {
+        let mut credential = CredentialRecord::new(id.to_string(), "login".to_string());
+        credential.id = id.to_string();
+        credential.set_field("password", CredentialField::password(password));
+        if totp {
+            credential.set_field(
+                "totp_secret",
+                CredentialField::totp_secret("JBSWY3DPEHPK3PXP"),
+            );
+        }
+        credential
+    }
+
+    fn now() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2024-06-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_healthy_vault_scores_high() {
+        let credentials = vec![
+            login("a", "x7$Kq!m2Vz#9pLw@4Rt", true),
+            login("b", "N8&hY3!cQe*6uJ1^sD0", true),
+        ];
+        let health = VaultHealthAnalyzer::new()
+            .with_now(now())
+            .with_last_backup(Some(now() - Duration::days(3)))
+            .with_kdf("argon2id", 3)
+            .analyze(&credentials);
+
+        assert!(health.score >= 90, "score was {}", health.score);
+        assert_eq!(health.grade(), "Excellent");
+        assert!(health
+            .checklist
+            .iter()
+            .all(|item| item.category == HealthCategory::PasswordStrength));
+    }
+
+    #[test]
+    fn test_reuse_and_missing_totp_are_listed() {
+        let credentials = vec![
+            login("a", "x7$Kq!m2Vz#9pLw@4Rt", false),
+            login("b", "x7$Kq!m2Vz#9pLw@4Rt", true),
+            login("c", "N8&hY3!cQe*6uJ1^sD0", true),
+        ];
+        let health = VaultHealthAnalyzer::new()
+            .with_now(now())
+            .with_last_backup(Some(now()))
+            .analyze(&credentials);
+
+        let reuse = health
+            .checklist
+            .iter()
+            .find(|item| item.category == HealthCategory::PasswordReuse)
+            .unwrap();
+        assert_eq!(reuse.credential_ids, vec!["a", "b"]);
+
+        let totp = health
+            .checklist
+            .iter()
+            .find(|item| item.category == HealthCategory::TwoFactor)
+            .unwrap();
+        assert_eq!(totp.credential_ids, vec!["a"]);
+        assert_eq!(totp.points, 5);
+    }
+
+    #[test]
+    fn test_checklist_is_ordered_by_points() {
+        let credentials = vec![login("a", "password", false), login("b", "password", false)];
+        let health = VaultHealthAnalyzer::new()
+            .with_now(now())
+            .with_kdf("pbkdf2", 100_000)
+            .analyze(&credentials);
+
+        assert!(health.score < 30);
+        assert_eq!(health.grade(), "Poor");
+        let points: Vec<u8> = health.checklist.iter().map(|item| item.points).collect();
+        let mut sorted = points.clone();
+        sorted.sort_by(|a, b| b.cmp(a));
+        assert_eq!(points, sorted);
+        assert_eq!(health.checklist[0].priority, HealthPriority::High);
+    }
+
+    #[test]
+    fn test_backup_recency_decays() {
+        let score_at = |days: i64| {
+            VaultHealthAnalyzer::new()
+                .with_now(now())
+                .with_last_backup(Some(now() - Duration::days(days)))
+                .analyze(&[])
+                .components
+                .iter()
+                .find(|c| c.category == HealthCategory::Backup)
+                .unwrap()
+                .score
+        };
+
+        assert_eq!(score_at(10), 15);
+        assert_eq!(score_at(105), 8);
+        assert_eq!(score_at(400), 0);
+
+        let none = VaultHealthAnalyzer::new().with_now(now()).analyze(&[]);
+        assert!(none
+            .checklist
+            .iter()
+            .any(|item| item.title == "Set up backups"));
+    }
+
+    #[test]
+    fn test_empty_vault_only_penalizes_vault_settings() {
+        let health = VaultHealthAnalyzer::new()
+            .with_now(now())
+            .with_last_backup(Some(now()))
+            .analyze(&[]);
+        // Archive KDF is 524288 of the recommended 600000 iterations
+        assert_eq!(health.score, 99);
+        assert_eq!(health.components.len(), 5);
+    }
+
+    #[test]
+    fn test_health_serializes_for_ffi() {
+        let health = VaultHealthAnalyzer::new()
+            .with_now(now())
+            .analyze(&[login("a", "hunter2", false)]);
+        let json = serde_json::to_string(&health).unwrap();
+        assert!(json.contains("\"category\":\"password_reuse\"") || json.contains("two_factor"));
+        let parsed: VaultHealth = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, health);
+    }
+}
@@ -0,0 +1,281 @@
+//! Encrypted, read-only HTML viewer export
+//!
+//! Writes selected credentials into a single HTML file that opens in any
+//! browser, offline, for emergency access from a device without ZipLock.
+//! The credentials are encrypted with a passphrase the same way the page
+//! decrypts them with WebCrypto: PBKDF2-HMAC-SHA256 stretches the passphrase
+//! into an AES-256-GCM key, and the page holds only the salt, nonce and
+//! ciphertext. The page loads nothing from the network and its content
+//! security policy forbids it from doing so.
+
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::prelude::*;
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use zeroize::Zeroizing;
+
+use crate::core::{CoreError, CoreResult};
+use crate::models::{CredentialRecord, FieldType};
+use crate::utils::encryption::SecretBytes;
+use crate::utils::time_utils;
+
+const TEMPLATE: &str = include_str!("html_viewer.html");
+const VAULT_PLACEHOLDER: &str = "{{VAULT}}";
+const VAULT_START: &str = r#"<script id="vault" type="application/json">"#;
+const ENVELOPE_VERSION: u8 = 1;
+const SALT_LENGTH: usize = 16;
+const NONCE_LENGTH: usize = 12;
+
+/// PBKDF2 iterations for new exports
+pub const DEFAULT_VIEWER_ITERATIONS: u32 = 600_000;
+
+/// Shortest passphrase accepted for a viewer; the file can be attacked
+/// offline by anyone who gets a copy
+pub const MIN_VIEWER_PASSPHRASE_LENGTH: usize = 8;
+
+/// What the page decrypts: the credentials reduced to what a reader needs
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ViewerDocument {
+    pub title: String,
+    pub exported_at: i64,
+    pub entries: Vec<ViewerEntry>,
+}
+
+/// One credential as shown by the viewer
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ViewerEntry {
+    pub title: String,
+    /// Credential type, such as `login`
+    pub kind: String,
+    pub folder: Option<String>,
+    pub tags: Vec<String>,
+    pub fields: Vec<ViewerField>,
+    pub notes: Option<String>,
+}
+
+/// One field as shown by the viewer; sensitive values start hidden
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ViewerField {
+    pub label: String,
+    pub value: String,
+    pub sensitive: bool,
+}
+
+/// The encrypted document embedded in the page
+#[derive(Debug, Serialize, Deserialize)]
+struct Envelope {
+    version: u8,
+    iterations: u32,
+    salt: String,
+    iv: String,
+    ciphertext: String,
+}
+
+/// Builds encrypted HTML viewers
+pub struct HtmlViewerExporter {
+    passphrase: Zeroizing<String>,
+    iterations: u32,
+    title: String,
+}
+
+impl HtmlViewerExporter {
+    /// Encrypt with `passphrase` and [`DEFAULT_VIEWER_ITERATIONS`]
+    pub fn new(passphrase: impl Into<String>) -> Self {
+        Self {
+            passphrase: Zeroizing::new(passphrase.into()),
+            iterations: DEFAULT_VIEWER_ITERATIONS,
+            title: "ZipLock".to_string(),
+        }
+    }
+
+    /// Stretch the passphrase with this many PBKDF2 iterations
+    pub fn with_iterations(mut self, iterations: u32) -> Self {
+        self.iterations = iterations.max(1);
+        self
+    }
+
+    /// Name shown above the credentials once the page is opened
+    pub fn with_title(mut self, title: impl Into<String>) -> Self {
+        self.title = title.into();
+        self
+    }
+
+    /// The viewer page for `credentials`, in the order given
+    pub fn export(&self, credentials: &[CredentialRecord]) -> CoreResult<Vec<u8>> {
+        if self.passphrase.chars().count() < MIN_VIEWER_PASSPHRASE_LENGTH {
+            return Err(CoreError::ValidationError {
+                message: format!(
+                    "The viewer passphrase must be at least {} characters",
+                    MIN_VIEWER_PASSPHRASE_LENGTH
+                ),
+            });
+        }
+
+        let document = ViewerDocument {
+            title: self.title.clone(),
+            exported_at: time_utils::current_timestamp(),
+            entries: credentials.iter().map(viewer_entry).collect(),
+        };
+        let plaintext = Zeroizing::new(serde_json::to_vec(&document).map_err(|e| {
+            CoreError::SerializationError {
+                message: format!("Viewer export failed: {}", e),
+            }
+        })?);
+
+        let mut salt = [0u8; SALT_LENGTH];
+        let mut nonce = [0u8; NONCE_LENGTH];
+        OsRng.fill_bytes(&mut salt);
+        OsRng.fill_bytes(&mut nonce);
+        let cipher = cipher(&self.passphrase, &salt, self.iterations);
+        let ciphertext = cipher
+            .encrypt(Nonce::from_slice(&nonce), plaintext.as_slice())
+            .map_err(|_| CoreError::InternalError {
+                message: "Viewer encryption failed".to_string(),
+            })?;
+
+        let envelope = Envelope {
+            version: ENVELOPE_VERSION,
+            iterations: self.iterations,
+            salt: BASE64_STANDARD.encode(salt),
+            iv: BASE64_STANDARD.encode(nonce),
+            ciphertext: BASE64_STANDARD.encode(ciphertext),
+        };
+        let envelope =
+            serde_json::to_string(&envelope).map_err(|e| CoreError::SerializationError {
+                message: format!("Viewer export failed: {}", e),
+            })?;
+        Ok(TEMPLATE.replace(VAULT_PLACEHOLDER, &envelope).into_bytes())
+    }
+
+    /// Decrypt a viewer page as the browser would, to check an export
+    pub fn open(html: &str, passphrase: &str) -> CoreResult<ViewerDocument> {
+        let invalid = |message: &str| CoreError::ValidationError {
+            message: format!("Not a ZipLock viewer: {}", message),
+        };
+        let start = html.find(VAULT_START).ok_or_else(|| invalid("no vault"))? + VAULT_START.len();
+        let end = html[start..]
+            .find("</script>")
+            .ok_or_else(|| invalid("unterminated vault"))?;
+        let envelope: Envelope =
+            serde_json::from_str(&html[start..start + end]).map_err(|e| invalid(&e.to_string()))?;
+        if envelope.version != ENVELOPE_VERSION {
+            return Err(invalid(&format!("version {}", envelope.version)));
+        }
+
+        let decode = |text: &str| {
+            BASE64_STANDARD
+                .decode(text)
+                .map_err(|e| invalid(&e.to_string()))
+        };
+        let salt = decode(&envelope.salt)?;
+        let nonce = decode(&envelope.iv)?;
+        if nonce.len() != NONCE_LENGTH {
+            return Err(invalid("bad nonce"));
+        }
+        let plaintext = Zeroizing::new(
+            cipher(passphrase, &salt, envelope.iterations)
+                .decrypt(
+                    Nonce::from_slice(&nonce),
+                    decode(&envelope.ciphertext)?.as_slice(),
+                )
+                .map_err(|_| CoreError::ValidationError {
+                    message: "Wrong passphrase, or the viewer is damaged".to_string(),
+                })?,
+        );
+        serde_json::from_slice(&plaintext).map_err(|e| CoreError::SerializationError {
+            message: format!("Viewer document is invalid: {}", e),
+        })
+    }
+}
+
+fn cipher(passphrase: &str, salt: &[u8], iterations: u32) -> Aes256Gcm {
+    let mut key = SecretBytes::zeroed(32);
+    pbkdf2::pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), salt, iterations, key.expose_mut());
+    Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key.expose()))
+}
+
+/// Non-empty fields sorted by label; protected fields are sealed with a
+/// passphrase of their own, so only their presence is shown
+fn viewer_entry(credential: &CredentialRecord) -> ViewerEntry {
+    let mut fields: Vec<ViewerField> = credential
+        .fields
+        .iter()
+        .filter(|(_, field)| field.is_protected() || !field.value.is_empty())
+        .map(|(name, field)| ViewerField {
+            label: field.label.clone().unwrap_or_else(|| name.clone()),
+            value: if field.is_protected() {
+                "(protected by its own passphrase)".to_string()
+            } else {
+                field.value.clone()
+            },
+            sensitive: !field.is_protected()
+                && (field.sensitive || field.field_type == FieldType::Password),
+        })
+        .collect();
+    fields.sort_by_key(|field| field.label.to_lowercase());
+
+    ViewerEntry {
+        title: credential.title.clone(),
+        kind: credential.credential_type.clone(),
+        folder: credential.folder_path.clone(),
+        tags: credential.tags.clone(),
+        fields,
+        notes: credential.notes.clone().filter(|notes| !notes.is_empty()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::CredentialField;
+
+    fn exporter() -> HtmlViewerExporter {
+        HtmlViewerExporter::new("correct horse battery").with_iterations(1_000)
+    }
+
+    #[test]
+    fn test_viewer_round_trip() {
+        let mut login = CredentialRecord::new("Bank </script>".into(), "login".into());
+        login.set_field("username", CredentialField::username("jane"));
+        login.set_field("password", CredentialField::password("s3cret-Pa55"));
+        login.set_field("empty", CredentialField::username(""));
+        login.notes = Some("Call <b>first</b>".into());
+
+        let html =
+            String::from_utf8(exporter().with_title("Family").export(&[login]).unwrap()).unwrap();
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("default-src 'none'"));
+        assert!(!html.contains("s3cret-Pa55"));
+        assert!(!html.contains("jane"));
+        assert!(!html.contains(VAULT_PLACEHOLDER));
+
+        let document = HtmlViewerExporter::open(&html, "correct horse battery").unwrap();
+        assert_eq!(document.title, "Family");
+        let entry = &document.entries[0];
+        assert_eq!(entry.title, "Bank </script>");
+        assert_eq!(entry.notes.as_deref(), Some("Call <b>first</b>"));
+        let fields: Vec<(&str, &str, bool)> = entry
+            .fields
+            .iter()
+            .map(|f| (f.label.as_str(), f.value.as_str(), f.sensitive))
+            .collect();
+        assert_eq!(
+            fields,
+            [
+                ("password", "s3cret-Pa55", true),
+                ("username", "jane", false)
+            ]
+        );
+
+        assert!(HtmlViewerExporter::open(&html, "wrong horse battery").is_err());
+        assert!(HtmlViewerExporter::open("<html></html>", "correct horse battery").is_err());
+    }
+
+    #[test]
+    fn test_short_passphrase_rejected() {
+        let result = HtmlViewerExporter::new("short").export(&[]);
+        assert!(matches!(result, Err(CoreError::ValidationError { .. })));
+    }
+}
@@ -0,0 +1,241 @@
+//! macOS / iOS Passwords CSV importer
+//!
+//! The Passwords app (and Safari before it) exports one row per saved
+//! website, so a single account used on `www.example.com` and
+//! `login.example.com` shows up twice. Rows for the same site, username and
+//! password are merged into one credential with several URLs.
+
+use super::{finish, ImportReport};
+use crate::core::{CoreError, CoreResult};
+use crate::models::{CredentialRecord, FieldType};
+use crate::utils::csv::{CsvColumnMapping, CsvImporter, CsvMapping, CsvTarget};
+use crate::utils::string_utils;
+use crate::utils::url_match::registrable_domain;
+
+/// Importer for Apple Passwords / iCloud Keychain CSV exports
+pub struct ApplePasswordsImporter;
+
+impl ApplePasswordsImporter {
+    /// Column mapping for the `Title,URL,Username,Password,Notes,OTPAuth` layout
+    pub fn mapping() -> CsvMapping {
+        CsvMapping::new(vec![
+            CsvColumnMapping::new("Title", CsvTarget::Title),
+            CsvColumnMapping::new("URL", CsvTarget::field("url", FieldType::Url)),
+            CsvColumnMapping::new(
+                "Username",
+                CsvTarget::field("username", FieldType::Username),
+            ),
+            CsvColumnMapping::new(
+                "Password",
+                CsvTarget::field("password", FieldType::Password),
+            ),
+            CsvColumnMapping::new("Notes", CsvTarget::Notes),
+            CsvColumnMapping::new("OTPAuth", CsvTarget::field("totp", FieldType::TotpSecret)),
+        ])
+    }
+
+    /// Parse an Apple Passwords CSV export, merging per-subdomain duplicates
+    pub fn parse_csv(data: &str) -> CoreResult<ImportReport> {
+        let header = data
+            .trim_start_matches('\u{feff}')
+            .lines()
+            .next()
+            .unwrap_or("");
+        if !header.contains("Title") || !header.contains("OTPAuth") {
+            return Err(CoreError::ValidationError {
+                message: "Not an Apple Passwords CSV export".to_string(),
+            });
+        }
+
+        let parsed = CsvImporter::new(Self::mapping()).parse(data)?;
+        let mut report = ImportReport {
+            errors: parsed.errors,
+            skipped_rows: parsed.skipped_rows,
+            ..Default::default()
+        };
+
+        let mut merged: Vec<CredentialRecord> = Vec::new();
+        for credential in parsed.credentials {
+            let key = Self::merge_key(&credential);
+            match key.as_ref().and_then(|key| {
+                merged
+                    .iter_mut()
+                    .find(|c| Self::merge_key(c).as_ref() == Some(key))
+            }) {
+                Some(existing) => Self::absorb(existing, credential),
+                None => merged.push(credential),
+            }
+        }
+
+        for (index, mut credential) in merged.into_iter().enumerate() {
+            let has_secret = credential.get_field("username").is_some()
+                || credential.get_field("password").is_some()
+                || credential.get_field("totp").is_some();
+            if !has_secret && credential.notes.is_some() {
+                credential.credential_type = "secure_note".to_string();
+                report.warn(
+                    index + 1,
+                    &credential.title,
+                    "Entry only contains notes and was imported as a secure note",
+                );
+            }
+            report.push(index + 1, finish(credential));
+        }
+
+        Ok(report)
+    }
+
+    /// Rows sharing a registrable domain, username and password are duplicates
+    fn merge_key(credential: &CredentialRecord) -> Option<(String, String, String)> {
+        let url = credential.get_field("url")?;
+        let domain = string_utils::extract_domain(&url.value)?;
+        let value = |name: &str| {
+            credential
+                .get_field(name)
+                .map(|f| f.value.clone())
+                .unwrap_or_default()
+        };
+        Some((base_domain(&domain), value("username"), value("password")))
+    }
+
+    /// Fold a duplicate row into an existing credential
+    fn absorb(existing: &mut CredentialRecord, duplicate: CredentialRecord) {
+        if let Some(url) = duplicate.get_field("url") {
+            let known = existing
+                .fields
+                .iter()
+                .any(|(name, f)| name.starts_with("url") && f.value == url.value);
+            if !known {
+                let mut index = 2;
+                while existing.get_field(&format!("url_{}", index)).is_some() {
+                    index += 1;
+                }
+                existing.set_field(format!("url_{}", index), url.clone());
+            }
+        }
+
+        if existing.get_field("totp").is_none() {
+            if let Some(totp) = duplicate.get_field("totp") {
+                existing.set_field("totp", totp.clone());
+            }
+        }
+
+        if let Some(notes) = duplicate.notes {
+            match &mut existing.notes {
+                Some(current) if current.contains(&notes) => {}
+                Some(current) => {
+                    current.push_str("\n\n");
+                    current.push_str(&notes);
+                }
+                None => existing.notes = Some(notes),
+            }
+        }
+
+        // Prefer the shorter, less host-specific title
+        if !duplicate.title.is_empty() && duplicate.title.len() < existing.title.len() {
+            existing.title = duplicate.title;
+        }
+    }
+}
+
+impl super::Importer for ApplePasswordsImporter {
+    fn name(&self) -> &'static str {
+        "Apple Passwords"
+    }
+
+    fn parse(&self, data: &[u8]) -> CoreResult<ImportReport> {
+        Self::parse_csv(super::text(data)?)
+    }
+}
+
+/// Reduce a hostname to its registrable domain (`login.example.co.uk` -> `example.co.uk`)
+fn base_domain(host: &str) -> String {
+    let host = host.trim().trim_start_matches("www.").to_lowercase();
+    registrable_domain(&host).unwrap_or(host)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURE: &str = include_str!("../../../tests/fixtures/import/apple_passwords.csv");
+
+    #[test]
+    fn test_merges_subdomains() {
+        let report = ApplePasswordsImporter::parse_csv(FIXTURE).unwrap();
+        assert!(!report.has_errors(), "{:?}", report.errors);
+
+        // Five rows: two example.com duplicates, a different user, a notes-only row, and a bank
+        assert_eq!(report.imported_count(), 4);
+
+        let merged = report
+            .credentials
+            .iter()
+            .find(|c| c.get_field("username").map(|f| f.value.as_str()) == Some("jane"))
+            .unwrap();
+        assert_eq!(
+            merged.get_field("url").unwrap().value,
+            "https://www.example.com/"
+        );
+        assert_eq!(
+            merged.get_field("url_2").unwrap().value,
+            "https://login.example.com/"
+        );
+        assert_eq!(merged.get_field("totp").unwrap().value, "JBSWY3DPEHPK3PXP");
+        assert_eq!(
+            merged.notes.as_deref(),
+            Some("Recovery email is backup@example.com")
+        );
+        assert_eq!(merged.title, "example.com (jane)");
+
+        let other_user = report
+            .credentials
+            .iter()
+            .filter(|c| {
+                c.get_field("url")
+                    .is_some_and(|f| f.value.contains("example.com"))
+            })
+            .count();
+        assert_eq!(other_user, 2);
+    }
+
+    #[test]
+    fn test_flags_notes_only_entries() {
+        let report = ApplePasswordsImporter::parse_csv(FIXTURE).unwrap();
+        assert_eq!(report.warnings.len(), 1);
+        assert_eq!(report.warnings[0].title.as_deref(), Some("Alarm code"));
+
+        let note = report
+            .credentials
+            .iter()
+            .find(|c| c.title == "Alarm code")
+            .unwrap();
+        assert_eq!(note.credential_type, "secure_note");
+    }
+
+    #[test]
+    fn test_totp_metadata_preserved() {
+        let report = ApplePasswordsImporter::parse_csv(FIXTURE).unwrap();
+        let bank = report
+            .credentials
+            .iter()
+            .find(|c| c.title.contains("bank.co.uk"))
+            .unwrap();
+        let totp = bank.get_field("totp").unwrap();
+        assert_eq!(totp.value, "KRSXG5CTMVRXEZLU");
+        assert_eq!(totp.metadata.get("period").unwrap(), "60");
+    }
+
+    #[test]
+    fn test_rejects_other_layouts() {
+        assert!(ApplePasswordsImporter::parse_csv("name,url,username,password\n").is_err());
+    }
+
+    #[test]
+    fn test_base_domain() {
+        assert_eq!(base_domain("login.example.com"), "example.com");
+        assert_eq!(base_domain("www.example.com"), "example.com");
+        assert_eq!(base_domain("secure.bank.co.uk"), "bank.co.uk");
+        assert_eq!(base_domain("localhost"), "localhost");
+    }
+}
@@ -0,0 +1,331 @@
+//! Bitwarden JSON export importer
+//!
+//! Reads the unencrypted `.json` export, the same layout the Bitwarden
+//! exporter writes. Folders become folder paths; organization exports put
+//! items in collections instead, so an item without a folder goes in its
+//! first collection and is tagged with the others. Item IDs are kept, which
+//! makes re-importing a ZipLock export update the credentials it came from.
+
+use super::{
+    field_name, finish, text, totp_field, unused_name, url_field, ImportFailure, ImportReport,
+};
+use crate::core::{CoreError, CoreResult};
+use crate::models::{CredentialField, CredentialRecord, FieldType};
+use crate::utils::bitwarden::{
+    BitwardenExport, BitwardenItem, FIELD_BOOLEAN, FIELD_HIDDEN, FIELD_TEXT, ITEM_CARD,
+    ITEM_IDENTITY, ITEM_LOGIN, ITEM_SECURE_NOTE, ITEM_SSH_KEY,
+};
+use chrono::DateTime;
+use std::collections::HashMap;
+
+/// Importer for Bitwarden JSON exports
+pub struct BitwardenImporter;
+
+impl BitwardenImporter {
+    /// Parse a Bitwarden JSON export
+    pub fn parse_json(json: &str) -> CoreResult<ImportReport> {
+        let export: BitwardenExport =
+            serde_json::from_str(json).map_err(|e| CoreError::SerializationError {
+                message: format!("Invalid Bitwarden export: {}", e),
+            })?;
+
+        if export.encrypted {
+            return Err(CoreError::ValidationError {
+                message:
+                    "Encrypted Bitwarden exports are not supported; export as unencrypted JSON"
+                        .to_string(),
+            });
+        }
+
+        let folders: HashMap<&str, &str> = export
+            .folders
+            .iter()
+            .map(|f| (f.id.as_str(), f.name.as_str()))
+            .collect();
+        let collections: HashMap<&str, &str> = export
+            .collections
+            .iter()
+            .map(|c| (c.id.as_str(), c.name.as_str()))
+            .collect();
+
+        let mut report = ImportReport::default();
+        for (index, item) in export.items.iter().enumerate() {
+            report.push(index + 1, Self::convert(item, &folders, &collections));
+        }
+
+        Ok(report)
+    }
+
+    fn convert(
+        item: &BitwardenItem,
+        folders: &HashMap<&str, &str>,
+        collections: &HashMap<&str, &str>,
+    ) -> Result<CredentialRecord, ImportFailure> {
+        let credential_type = match item.item_type {
+            ITEM_LOGIN => "login",
+            ITEM_SECURE_NOTE => "secure_note",
+            ITEM_CARD => "credit_card",
+            ITEM_IDENTITY => "identity",
+            ITEM_SSH_KEY => "ssh_key",
+            other => {
+                return Err(ImportFailure::new(
+                    Some(item.name.clone()),
+                    format!("Unsupported Bitwarden item type {}", other),
+                ))
+            }
+        };
+
+        let mut credential = CredentialRecord::new(item.name.clone(), credential_type.to_string());
+        if !item.id.is_empty() {
+            credential.id = item.id.clone();
+        }
+        credential.notes = item.notes.clone().filter(|n| !n.is_empty());
+        credential.favorite = item.favorite;
+
+        let mut item_collections = item
+            .collection_ids
+            .iter()
+            .flatten()
+            .filter_map(|id| collections.get(id.as_str()).copied());
+        credential.folder_path = item
+            .folder_id
+            .as_deref()
+            .and_then(|id| folders.get(id).copied())
+            .or_else(|| item_collections.next())
+            .map(str::to_string);
+        for collection in item_collections {
+            credential.add_tag(collection.to_string());
+        }
+
+        let mut set = |name: &str, field_type: FieldType, value: &Option<String>| {
+            if let Some(value) = value.as_deref().filter(|v| !v.is_empty()) {
+                let sensitive = field_type.is_sensitive_by_default();
+                credential.set_field(
+                    name,
+                    CredentialField::new(field_type, value.to_string(), sensitive),
+                );
+            }
+        };
+
+        if let Some(login) = &item.login {
+            set("username", FieldType::Username, &login.username);
+            set("password", FieldType::Password, &login.password);
+        }
+        if let Some(card) = &item.card {
+            set("cardholder", FieldType::Text, &card.cardholder_name);
+            set("card_number", FieldType::CreditCardNumber, &card.number);
+            set("cvv", FieldType::Cvv, &card.code);
+            set("brand", FieldType::Text, &card.brand);
+            set(
+                "expiry_date",
+                FieldType::ExpiryDate,
+                &expiry(card.exp_month.as_deref(), card.exp_year.as_deref()),
+            );
+        }
+        if let Some(identity) = &item.identity {
+            let text_fields = [
+                ("title", &identity.title),
+                ("first_name", &identity.first_name),
+                ("middle_name", &identity.middle_name),
+                ("last_name", &identity.last_name),
+                ("address", &identity.address1),
+                ("address2", &identity.address2),
+                ("address3", &identity.address3),
+                ("city", &identity.city),
+                ("state", &identity.state),
+                ("postal_code", &identity.postal_code),
+                ("country", &identity.country),
+                ("company", &identity.company),
+                ("passport_number", &identity.passport_number),
+                ("license_number", &identity.license_number),
+            ];
+            for (name, value) in text_fields {
+                set(name, FieldType::Text, value);
+            }
+            set("email", FieldType::Email, &identity.email);
+            set("phone", FieldType::Phone, &identity.phone);
+            set("username", FieldType::Username, &identity.username);
+            set("ssn", FieldType::Password, &identity.ssn);
+        }
+        if let Some(ssh_key) = &item.ssh_key {
+            set("private_key", FieldType::Password, &ssh_key.private_key);
+            set("public_key", FieldType::TextArea, &ssh_key.public_key);
+            set("fingerprint", FieldType::Text, &ssh_key.key_fingerprint);
+        }
+
+        if let Some(login) = &item.login {
+            let urls = login
+                .uris
+                .iter()
+                .filter_map(|u| u.uri.as_deref())
+                .filter_map(url_field);
+            for (i, url) in urls.enumerate() {
+                let name = if i == 0 {
+                    "url".to_string()
+                } else {
+                    format!("url_{}", i + 1)
+                };
+                credential.set_field(name, url);
+            }
+            if let Some(totp) = login.totp.as_deref().and_then(totp_field) {
+                credential.set_field("totp", totp);
+            }
+        }
+
+        for field in &item.fields {
+            let label = field.name.clone().unwrap_or_default();
+            let value = field.value.clone().unwrap_or_default();
+            if value.is_empty() {
+                continue;
+            }
+            if label == "Tags" && field.field_type == FIELD_TEXT {
+                for tag in value.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+                    credential.add_tag(tag.to_string());
+                }
+                continue;
+            }
+
+            let custom = match field.field_type {
+                FIELD_TEXT | FIELD_BOOLEAN => CredentialField::text(value),
+                FIELD_HIDDEN => CredentialField::password(value),
+                // Linked fields point at another property and hold no value
+                _ => continue,
+            };
+            let mut name = field_name(&label);
+            if name.is_empty() {
+                name = "field".to_string();
+            }
+            let name = unused_name(&credential, name);
+            credential.set_field(name, custom.with_label(label));
+        }
+
+        // Last, since setting fields and tags bumps the modification time
+        if let Some(created) = item.creation_date.as_deref().and_then(timestamp) {
+            credential.created_at = created;
+        }
+        if let Some(revised) = item.revision_date.as_deref().and_then(timestamp) {
+            credential.updated_at = revised.max(credential.created_at);
+        }
+
+        finish(credential)
+    }
+}
+
+impl super::Importer for BitwardenImporter {
+    fn name(&self) -> &'static str {
+        "Bitwarden"
+    }
+
+    fn parse(&self, data: &[u8]) -> CoreResult<ImportReport> {
+        Self::parse_json(text(data)?)
+    }
+}
+
+/// Join Bitwarden's month and year into ZipLock's `MM/YY`
+fn expiry(month: Option<&str>, year: Option<&str>) -> Option<String> {
+    let month: u32 = month?
+        .trim()
+        .parse()
+        .ok()
+        .filter(|m| (1..=12).contains(m))?;
+    let year = year?.trim();
+    let year = year.get(year.len().checked_sub(2)?..)?;
+    Some(format!("{:02}/{}", month, year))
+}
+
+fn timestamp(date: &str) -> Option<i64> {
+    DateTime::parse_from_rfc3339(date)
+        .ok()
+        .map(|dt| dt.timestamp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::bitwarden::BitwardenExporter;
+
+    const FIXTURE: &str = include_str!("../../../tests/fixtures/import/bitwarden.json");
+
+    #[test]
+    fn test_parse_json() {
+        let report = BitwardenImporter::parse_json(FIXTURE).unwrap();
+        assert_eq!(report.imported_count(), 5);
+        assert_eq!(report.errors.len(), 1);
+        assert_eq!(report.errors[0].title.as_deref(), Some("Passkey only"));
+
+        let login = report
+            .credentials
+            .iter()
+            .find(|c| c.title == "GitHub")
+            .unwrap();
+        assert_eq!(login.id, "b3c4a0a6-1f7e-4b8e-9d0a-2f3f6d9c1a01");
+        assert_eq!(login.credential_type, "login");
+        assert_eq!(login.folder_path.as_deref(), Some("Work"));
+        assert!(login.favorite);
+        assert_eq!(login.tags, vec!["Engineering", "dev"]);
+        assert_eq!(login.get_field("username").unwrap().value, "octocat");
+        assert_eq!(login.get_field("url").unwrap().value, "https://github.com");
+        assert_eq!(
+            login.get_field("url_2").unwrap().value,
+            "https://gist.github.com"
+        );
+        let totp = login.get_field("totp").unwrap();
+        assert_eq!(totp.value, "JBSWY3DPEHPK3PXP");
+        assert_eq!(totp.metadata.get("digits").unwrap(), "8");
+        let pin = login.get_field("recovery_pin").unwrap();
+        assert!(pin.sensitive);
+        assert_eq!(pin.label.as_deref(), Some("Recovery PIN"));
+        assert_eq!(login.created_at, 1672531200);
+
+        let card = report
+            .credentials
+            .iter()
+            .find(|c| c.credential_type == "credit_card")
+            .unwrap();
+        assert_eq!(card.folder_path.as_deref(), Some("Shared Finance"));
+        assert_eq!(card.get_field("expiry_date").unwrap().value, "04/27");
+        assert_eq!(card.get_field("cvv").unwrap().value, "123");
+
+        let identity = report
+            .credentials
+            .iter()
+            .find(|c| c.credential_type == "identity")
+            .unwrap();
+        assert_eq!(identity.get_field("first_name").unwrap().value, "Jane");
+        assert_eq!(
+            identity.get_field("phone").unwrap().field_type,
+            FieldType::Phone
+        );
+
+        let key = report
+            .credentials
+            .iter()
+            .find(|c| c.credential_type == "ssh_key")
+            .unwrap();
+        assert!(key.get_field("private_key").unwrap().sensitive);
+    }
+
+    #[test]
+    fn test_round_trip_keeps_ids_and_tags() {
+        let mut credential = CredentialRecord::new("Server".to_string(), "login".to_string());
+        credential.set_field("username", CredentialField::username("root"));
+        credential.set_field("password", CredentialField::password("s3cret"));
+        credential.set_field("url", CredentialField::url("https://example.com"));
+        credential.add_tag("infra".to_string());
+        credential.folder_path = Some("Work/Servers".to_string());
+
+        let exported = BitwardenExporter::export(std::slice::from_ref(&credential)).unwrap();
+        let report = BitwardenImporter::parse_json(text(&exported).unwrap()).unwrap();
+        let imported = &report.credentials[0];
+        assert_eq!(imported.id, credential.id);
+        assert_eq!(imported.tags, credential.tags);
+        assert_eq!(imported.folder_path, credential.folder_path);
+        assert_eq!(imported.get_field("password").unwrap().value, "s3cret");
+    }
+
+    #[test]
+    fn test_encrypted_export_rejected() {
+        let result = BitwardenImporter::parse_json(r#"{"encrypted": true, "items": []}"#);
+        assert!(matches!(result, Err(CoreError::ValidationError { .. })));
+    }
+}
@@ -0,0 +1,402 @@
+//! Dashlane export importer
+//!
+//! Current Dashlane versions export a zip of CSV files (`credentials.csv`,
+//! `securenotes.csv`, `payments.csv`, ...). Older versions produced a single
+//! JSON document keyed by item kind. Both are supported.
+
+use super::{
+    finish, is_zip, read_zip_entries, text, totp_field, url_field, ImportFailure, ImportReport,
+};
+use crate::core::{CoreError, CoreResult};
+use crate::models::{CredentialField, CredentialRecord, FieldType};
+use crate::utils::csv::{CsvColumnMapping, CsvImporter, CsvMapping, CsvTarget};
+use serde_json::Value;
+
+/// Converts one legacy JSON item into a credential
+type ItemConverter = fn(&Value) -> Result<CredentialRecord, ImportFailure>;
+
+/// Layouts of the CSV files inside a Dashlane export
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DashlaneCsv {
+    Credentials,
+    SecureNotes,
+    Payments,
+}
+
+impl DashlaneCsv {
+    fn detect(headers: &[String]) -> Option<Self> {
+        let has = |name: &str| headers.iter().any(|h| h == name);
+        if has("otpSecret") || (has("username") && has("password") && has("url")) {
+            Some(DashlaneCsv::Credentials)
+        } else if has("cc_number") || has("account_holder") {
+            Some(DashlaneCsv::Payments)
+        } else if has("title") && has("note") {
+            Some(DashlaneCsv::SecureNotes)
+        } else {
+            None
+        }
+    }
+
+    fn mapping(&self) -> CsvMapping {
+        use CsvTarget::*;
+
+        let field = |column: &str, name: &str, field_type: FieldType| {
+            CsvColumnMapping::new(column, CsvTarget::field(name, field_type))
+        };
+
+        match self {
+            DashlaneCsv::Credentials => CsvMapping::new(vec![
+                field("username", "username", FieldType::Username),
+                field("username2", "username_2", FieldType::Username),
+                field("username3", "username_3", FieldType::Username),
+                CsvColumnMapping::new("title", Title),
+                field("password", "password", FieldType::Password),
+                CsvColumnMapping::new("note", Notes),
+                field("url", "url", FieldType::Url),
+                CsvColumnMapping::new("category", Folder),
+                field("otpSecret", "totp", FieldType::TotpSecret),
+            ]),
+            DashlaneCsv::SecureNotes => CsvMapping::new(vec![
+                CsvColumnMapping::new("title", Title),
+                CsvColumnMapping::new("note", Notes),
+                CsvColumnMapping::new("category", Folder),
+            ])
+            .with_default_type("secure_note"),
+            DashlaneCsv::Payments => CsvMapping::new(vec![
+                CsvColumnMapping::new("type", Ignore),
+                CsvColumnMapping::new("account_name", Title),
+                field("account_holder", "cardholder", FieldType::Text),
+                field("cc_number", "card_number", FieldType::CreditCardNumber),
+                field("code", "cvv", FieldType::Cvv),
+                field("expiration_month", "expiration_month", FieldType::Text),
+                field("expiration_year", "expiration_year", FieldType::Text),
+                field("account_number", "account_number", FieldType::Password),
+                field("routing_number", "routing_number", FieldType::Text),
+                field("issuing_bank", "bank", FieldType::Text),
+                CsvColumnMapping::new("note", Notes),
+            ])
+            .with_default_type("credit_card"),
+        }
+    }
+}
+
+/// Importer for Dashlane exports
+pub struct DashlaneImporter;
+
+impl DashlaneImporter {
+    /// Parse a Dashlane export: a zip of CSVs, a single CSV, or legacy JSON
+    pub fn parse(data: &[u8]) -> CoreResult<ImportReport> {
+        if is_zip(data) {
+            return Self::parse_zip(data);
+        }
+
+        let content = text(data)?;
+        if content.trim_start().starts_with('{') {
+            Self::parse_json(content)
+        } else {
+            Self::parse_csv(content)
+        }
+    }
+
+    /// Parse every recognised CSV inside a Dashlane zip export
+    pub fn parse_zip(data: &[u8]) -> CoreResult<ImportReport> {
+        let mut report = ImportReport::default();
+        let mut recognised = false;
+
+        for (_, contents) in read_zip_entries(data, |name| name.ends_with(".csv"))? {
+            match Self::parse_csv(text(&contents)?) {
+                Ok(parsed) => {
+                    recognised = true;
+                    report.merge(parsed);
+                }
+                // Dashlane also exports ids.csv / personalInfo.csv which have no mapping
+                Err(CoreError::ValidationError { .. }) => continue,
+                Err(e) => return Err(e),
+            }
+        }
+
+        if !recognised {
+            return Err(CoreError::ValidationError {
+                message: "Dashlane export does not contain any supported CSV files".to_string(),
+            });
+        }
+
+        Ok(report)
+    }
+
+    /// Parse one Dashlane CSV file, detecting its layout from the header
+    pub fn parse_csv(data: &str) -> CoreResult<ImportReport> {
+        let headers: Vec<String> = data
+            .lines()
+            .next()
+            .unwrap_or("")
+            .split(',')
+            .map(|h| h.trim().trim_matches('"').to_string())
+            .collect();
+
+        let layout = DashlaneCsv::detect(&headers).ok_or_else(|| CoreError::ValidationError {
+            message: "Unrecognized Dashlane CSV layout".to_string(),
+        })?;
+
+        let mut report = CsvImporter::new(layout.mapping()).parse(data)?;
+        if layout == DashlaneCsv::Payments {
+            let credentials = std::mem::take(&mut report.credentials);
+            for credential in credentials {
+                report.push(0, Self::finish_payment(credential));
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Parse a legacy Dashlane JSON export
+    pub fn parse_json(json: &str) -> CoreResult<ImportReport> {
+        let root: Value =
+            serde_json::from_str(json).map_err(|e| CoreError::SerializationError {
+                message: format!("Invalid Dashlane export: {}", e),
+            })?;
+
+        let mut report = ImportReport::default();
+        let mut position = 0;
+
+        let sections: [(&str, ItemConverter); 3] = [
+            ("AUTHENTIFIANT", Self::convert_login),
+            ("SECURENOTE", Self::convert_note),
+            ("PAYMENTMEANS_CREDITCARD", Self::convert_card),
+        ];
+
+        for (key, convert) in sections {
+            for item in root
+                .get(key)
+                .and_then(|v| v.as_array())
+                .into_iter()
+                .flatten()
+            {
+                position += 1;
+                report.push(position, convert(item));
+            }
+        }
+
+        Ok(report)
+    }
+
+    fn convert_login(item: &Value) -> Result<CredentialRecord, ImportFailure> {
+        let get = |key: &str| str_value(item, key);
+        let mut credential = CredentialRecord::new(get("title"), "login".to_string());
+
+        if !get("login").is_empty() {
+            credential.set_field("username", CredentialField::username(get("login")));
+        }
+        if !get("secondaryLogin").is_empty() {
+            credential.set_field(
+                "username_2",
+                CredentialField::username(get("secondaryLogin")),
+            );
+        }
+        if !get("email").is_empty() {
+            credential.set_field("email", CredentialField::email(get("email")));
+        }
+        if !get("password").is_empty() {
+            credential.set_field("password", CredentialField::password(get("password")));
+        }
+        if let Some(url) = url_field(&get("domain")) {
+            credential.set_field("url", url);
+        }
+        if let Some(totp) = totp_field(&get("otpSecret")) {
+            credential.set_field("totp", totp);
+        }
+        if !get("note").is_empty() {
+            credential.notes = Some(get("note"));
+        }
+
+        finish(credential)
+    }
+
+    fn convert_note(item: &Value) -> Result<CredentialRecord, ImportFailure> {
+        let mut credential =
+            CredentialRecord::new(str_value(item, "title"), "secure_note".to_string());
+        let content = str_value(item, "content");
+        if !content.is_empty() {
+            credential.notes = Some(content);
+        }
+        let category = str_value(item, "category");
+        if !category.is_empty() {
+            credential.folder_path = Some(category);
+        }
+        finish(credential)
+    }
+
+    fn convert_card(item: &Value) -> Result<CredentialRecord, ImportFailure> {
+        let get = |key: &str| str_value(item, key);
+        let title = match get("name") {
+            name if name.is_empty() => get("bank"),
+            name => name,
+        };
+        let mut credential = CredentialRecord::new(title, "credit_card".to_string());
+
+        for (key, name, field_type) in [
+            ("owner", "cardholder", FieldType::Text),
+            ("cardNumber", "card_number", FieldType::CreditCardNumber),
+            ("securityCode", "cvv", FieldType::Cvv),
+            ("bank", "bank", FieldType::Text),
+        ] {
+            if !get(key).is_empty() {
+                let sensitive = field_type.is_sensitive_by_default();
+                credential.set_field(name, CredentialField::new(field_type, get(key), sensitive));
+            }
+        }
+        if let Some(expiry) = expiry_date(&get("expireMonth"), &get("expireYear")) {
+            credential.set_field(
+                "expiry_date",
+                CredentialField::new(FieldType::ExpiryDate, expiry, false),
+            );
+        }
+
+        finish(credential)
+    }
+
+    /// Combine the split expiry columns and classify bank accounts
+    fn finish_payment(mut credential: CredentialRecord) -> Result<CredentialRecord, ImportFailure> {
+        let month = credential.remove_field("expiration_month");
+        let year = credential.remove_field("expiration_year");
+        if let (Some(month), Some(year)) = (month, year) {
+            if let Some(expiry) = expiry_date(&month.value, &year.value) {
+                credential.set_field(
+                    "expiry_date",
+                    CredentialField::new(FieldType::ExpiryDate, expiry, false),
+                );
+            }
+        }
+
+        if credential.get_field("card_number").is_none()
+            && credential.get_field("account_number").is_some()
+        {
+            credential.credential_type = "bank_account".to_string();
+        }
+
+        finish(credential)
+    }
+}
+
+impl super::Importer for DashlaneImporter {
+    fn name(&self) -> &'static str {
+        "Dashlane"
+    }
+
+    fn parse(&self, data: &[u8]) -> CoreResult<ImportReport> {
+        Self::parse(data)
+    }
+}
+
+/// Read a JSON string (or number) value, empty if absent
+fn str_value(item: &Value, key: &str) -> String {
+    match item.get(key) {
+        Some(Value::String(s)) => s.trim().to_string(),
+        Some(Value::Number(n)) => n.to_string(),
+        _ => String::new(),
+    }
+}
+
+/// Format a month and a 2- or 4-digit year as `MM/YY`
+fn expiry_date(month: &str, year: &str) -> Option<String> {
+    let month: u32 = month.trim().parse().ok()?;
+    let year = year.trim();
+    if !(1..=12).contains(&month) || year.len() < 2 {
+        return None;
+    }
+    Some(format!("{:02}/{}", month, &year[year.len() - 2..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::import::test_support::build_zip;
+
+    const CREDENTIALS_CSV: &str =
+        include_str!("../../../tests/fixtures/import/dashlane_credentials.csv");
+    const SECURENOTES_CSV: &str =
+        include_str!("../../../tests/fixtures/import/dashlane_securenotes.csv");
+    const PAYMENTS_CSV: &str = include_str!("../../../tests/fixtures/import/dashlane_payments.csv");
+    const LEGACY_JSON: &str = include_str!("../../../tests/fixtures/import/dashlane.json");
+
+    #[test]
+    fn test_parse_credentials_csv() {
+        let report = DashlaneImporter::parse_csv(CREDENTIALS_CSV).unwrap();
+        assert!(!report.has_errors(), "{:?}", report.errors);
+        assert_eq!(report.imported_count(), 2);
+
+        let github = &report.credentials[0];
+        assert_eq!(github.title, "GitHub");
+        assert_eq!(github.get_field("username").unwrap().value, "octocat");
+        assert_eq!(
+            github.get_field("username_2").unwrap().value,
+            "octo@example.com"
+        );
+        assert_eq!(github.get_field("totp").unwrap().value, "JBSWY3DPEHPK3PXP");
+        assert_eq!(github.folder_path.as_deref(), Some("Development"));
+
+        // otpauth URIs are reduced to their secret
+        let bank = &report.credentials[1];
+        assert_eq!(bank.get_field("totp").unwrap().value, "KRSXG5CTMVRXEZLU");
+        assert_eq!(
+            bank.get_field("totp")
+                .unwrap()
+                .metadata
+                .get("digits")
+                .unwrap(),
+            "8"
+        );
+    }
+
+    #[test]
+    fn test_parse_zip() {
+        let zip = build_zip(&[
+            ("credentials.csv", CREDENTIALS_CSV),
+            ("securenotes.csv", SECURENOTES_CSV),
+            ("payments.csv", PAYMENTS_CSV),
+            ("ids.csv", "type,number,name\npassport,123,Jane\n"),
+        ]);
+        let report = DashlaneImporter::parse(&zip).unwrap();
+        assert!(!report.has_errors(), "{:?}", report.errors);
+        assert_eq!(report.imported_count(), 5);
+
+        let note = report
+            .credentials
+            .iter()
+            .find(|c| c.credential_type == "secure_note")
+            .unwrap();
+        assert_eq!(note.title, "Wi-Fi");
+
+        let card = report
+            .credentials
+            .iter()
+            .find(|c| c.credential_type == "credit_card")
+            .unwrap();
+        assert_eq!(card.get_field("expiry_date").unwrap().value, "03/28");
+        assert!(card.get_field("expiration_month").is_none());
+
+        assert!(report
+            .credentials
+            .iter()
+            .any(|c| c.credential_type == "bank_account"));
+    }
+
+    #[test]
+    fn test_parse_legacy_json() {
+        let report = DashlaneImporter::parse(LEGACY_JSON.as_bytes()).unwrap();
+        assert!(!report.has_errors(), "{:?}", report.errors);
+        assert_eq!(report.imported_count(), 3);
+
+        let login = &report.credentials[0];
+        assert_eq!(login.get_field("url").unwrap().value, "https://example.com");
+        assert_eq!(login.get_field("email").unwrap().value, "me@example.com");
+
+        let card = &report.credentials[2];
+        assert_eq!(card.get_field("expiry_date").unwrap().value, "11/26");
+    }
+
+    #[test]
+    fn test_unknown_csv_rejected() {
+        assert!(DashlaneImporter::parse_csv("foo,bar\n1,2\n").is_err());
+    }
+}
@@ -0,0 +1,270 @@
+//! Enpass JSON export importer
+//!
+//! Enpass items carry a flat list of typed fields. Known field types map onto
+//! ZipLock field types; everything else becomes a labelled text field.
+
+use super::{field_name, finish, totp_field, url_field, ImportFailure, ImportReport};
+use crate::core::{CoreError, CoreResult};
+use crate::models::{CredentialField, CredentialRecord, FieldType};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+#[derive(Debug, Deserialize)]
+struct EnpassExport {
+    #[serde(default)]
+    folders: Vec<EnpassFolder>,
+    #[serde(default)]
+    items: Vec<EnpassItem>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EnpassFolder {
+    uuid: String,
+    title: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct EnpassItem {
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    note: String,
+    #[serde(default)]
+    category: String,
+    #[serde(default)]
+    favorite: u8,
+    #[serde(default)]
+    trashed: u8,
+    #[serde(default)]
+    folders: Vec<String>,
+    #[serde(default)]
+    fields: Vec<EnpassField>,
+    #[serde(default, rename = "createdAt")]
+    created_at: Option<i64>,
+    #[serde(default)]
+    updated_at: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EnpassField {
+    #[serde(default)]
+    label: String,
+    #[serde(rename = "type", default)]
+    field_type: String,
+    #[serde(default)]
+    value: String,
+    #[serde(default)]
+    sensitive: u8,
+    #[serde(default)]
+    deleted: u8,
+}
+
+/// Importer for Enpass JSON exports
+pub struct EnpassImporter;
+
+impl EnpassImporter {
+    /// Parse an Enpass JSON export
+    pub fn parse_json(json: &str) -> CoreResult<ImportReport> {
+        let export: EnpassExport =
+            serde_json::from_str(json).map_err(|e| CoreError::SerializationError {
+                message: format!("Invalid Enpass export: {}", e),
+            })?;
+
+        let folders: HashMap<&str, &str> = export
+            .folders
+            .iter()
+            .map(|f| (f.uuid.as_str(), f.title.as_str()))
+            .collect();
+
+        let mut report = ImportReport::default();
+        for (index, item) in export.items.iter().enumerate() {
+            if item.trashed != 0 {
+                report.skip(index + 1, &item.title, "In the Enpass trash");
+                continue;
+            }
+            report.push(index + 1, Self::convert(item, &folders));
+        }
+
+        Ok(report)
+    }
+
+    fn convert(
+        item: &EnpassItem,
+        folders: &HashMap<&str, &str>,
+    ) -> Result<CredentialRecord, ImportFailure> {
+        let credential_type = match item.category.as_str() {
+            "login" | "password" => "login",
+            "creditcard" => "credit_card",
+            "note" => "secure_note",
+            "identity" => "identity",
+            "finance" => "bank_account",
+            "computer" => "database",
+            "license" => "software_license",
+            "travel" | "misc" | "" => "login",
+            other => other,
+        };
+
+        let mut credential = CredentialRecord::new(item.title.clone(), credential_type.to_string());
+        credential.favorite = item.favorite != 0;
+        if !item.note.is_empty() {
+            credential.notes = Some(item.note.clone());
+        }
+        credential.folder_path = item
+            .folders
+            .iter()
+            .find_map(|uuid| folders.get(uuid.as_str()))
+            .map(|title| title.to_string());
+        if let Some(created) = item.created_at {
+            credential.created_at = created;
+        }
+        if let Some(updated) = item.updated_at {
+            credential.updated_at = updated.max(credential.created_at);
+        }
+
+        for field in &item.fields {
+            if field.deleted != 0 || field.value.is_empty() || field.field_type == "section" {
+                continue;
+            }
+
+            let (name, converted) = Self::convert_field(field);
+            let Some(converted) = converted else { continue };
+
+            // Keep the first field of each name; later duplicates get a suffix
+            let mut unique = name.clone();
+            let mut counter = 2;
+            while credential.get_field(&unique).is_some() {
+                unique = format!("{}_{}", name, counter);
+                counter += 1;
+            }
+            credential.set_field(unique, converted);
+        }
+
+        finish(credential)
+    }
+
+    fn convert_field(field: &EnpassField) -> (String, Option<CredentialField>) {
+        let value = field.value.as_str();
+        let typed = |name: &str, field_type: FieldType| {
+            let sensitive = field.sensitive != 0 || field_type.is_sensitive_by_default();
+            (
+                name.to_string(),
+                Some(CredentialField::new(
+                    field_type,
+                    value.to_string(),
+                    sensitive,
+                )),
+            )
+        };
+
+        match field.field_type.as_str() {
+            "username" => typed("username", FieldType::Username),
+            "email" => typed("email", FieldType::Email),
+            "password" => typed("password", FieldType::Password),
+            "url" => ("url".to_string(), url_field(value)),
+            "totp" => ("totp".to_string(), totp_field(value)),
+            "phone" => typed("phone", FieldType::Phone),
+            "ccNumber" => typed("card_number", FieldType::CreditCardNumber),
+            "ccCvc" => typed("cvv", FieldType::Cvv),
+            "ccName" => typed("cardholder", FieldType::Text),
+            "ccExpiry" => typed("expiry_date", FieldType::ExpiryDate),
+            "pin" => typed("pin", FieldType::Password),
+            "multiline" => {
+                let name = field_name(&field.label);
+                (
+                    name,
+                    Some(
+                        CredentialField::new(FieldType::TextArea, value.to_string(), false)
+                            .with_label(field.label.clone()),
+                    ),
+                )
+            }
+            _ => {
+                let name = match field_name(&field.label) {
+                    name if name.is_empty() => "field".to_string(),
+                    name => name,
+                };
+                (
+                    name,
+                    Some(
+                        CredentialField::text(value)
+                            .with_sensitive(field.sensitive != 0)
+                            .with_label(field.label.clone()),
+                    ),
+                )
+            }
+        }
+    }
+}
+
+impl super::Importer for EnpassImporter {
+    fn name(&self) -> &'static str {
+        "Enpass"
+    }
+
+    fn parse(&self, data: &[u8]) -> CoreResult<ImportReport> {
+        Self::parse_json(super::text(data)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURE: &str = include_str!("../../../tests/fixtures/import/enpass.json");
+
+    #[test]
+    fn test_parse_json() {
+        let report = EnpassImporter::parse_json(FIXTURE).unwrap();
+        assert!(!report.has_errors(), "{:?}", report.errors);
+        assert_eq!(report.imported_count(), 3);
+        assert_eq!(report.skipped_rows, 1);
+
+        let login = &report.credentials[0];
+        assert_eq!(login.title, "GitHub");
+        assert!(login.favorite);
+        assert_eq!(login.folder_path.as_deref(), Some("Work"));
+        assert_eq!(login.get_field("username").unwrap().value, "octocat");
+        assert_eq!(login.get_field("url").unwrap().value, "https://github.com");
+        assert_eq!(login.get_field("totp").unwrap().value, "JBSWY3DPEHPK3PXP");
+        assert!(login.get_field("security_answer").unwrap().sensitive);
+        // Deleted and section fields are dropped
+        assert!(login.get_field("old_password").is_none());
+        assert!(login.get_field("additional_details").is_none());
+
+        let card = &report.credentials[1];
+        assert_eq!(card.credential_type, "credit_card");
+        assert_eq!(card.get_field("expiry_date").unwrap().value, "09/27");
+
+        let note = &report.credentials[2];
+        assert_eq!(note.credential_type, "secure_note");
+        assert_eq!(
+            note.get_field("recovery_codes").unwrap().field_type,
+            FieldType::TextArea
+        );
+    }
+
+    #[test]
+    fn test_duplicate_field_names() {
+        let json = r#"{"items":[{"title":"Two emails","category":"login","fields":[
+            {"label":"E-mail","type":"email","value":"a@example.com"},
+            {"label":"E-mail","type":"email","value":"b@example.com"}]}]}"#;
+        let report = EnpassImporter::parse_json(json).unwrap();
+        let credential = &report.credentials[0];
+        assert_eq!(
+            credential.get_field("email").unwrap().value,
+            "a@example.com"
+        );
+        assert_eq!(
+            credential.get_field("email_2").unwrap().value,
+            "b@example.com"
+        );
+    }
+
+    #[test]
+    fn test_invalid_json() {
+        assert!(matches!(
+            EnpassImporter::parse_json("not json"),
+            Err(CoreError::SerializationError { .. })
+        ));
+    }
+}
@@ -0,0 +1,623 @@
+//! Importers for other password managers
+//!
+//! Each submodule parses one vendor's export format into `CredentialRecord`s
+//! and implements [`Importer`]. Parsing never touches a repository; the
+//! resulting `ImportReport` can be previewed (dry run), checked against the
+//! repository with `ImportReport::detect_duplicates`, and then applied with
+//! `ImportReport::import_into`.
+
+pub mod apple_passwords;
+pub mod bitwarden;
+pub mod dashlane;
+pub mod enpass;
+pub mod one_password;
+pub mod proton_pass;
+
+pub use apple_passwords::ApplePasswordsImporter;
+pub use bitwarden::BitwardenImporter;
+pub use dashlane::DashlaneImporter;
+pub use enpass::EnpassImporter;
+pub use one_password::OnePasswordImporter;
+pub use proton_pass::ProtonPassImporter;
+
+use crate::core::{CoreError, CoreResult, UnifiedMemoryRepository};
+use crate::models::{CredentialField, CredentialRecord, FieldType};
+use crate::utils::{string_utils, validation};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{Cursor, Read};
+
+/// A parser for one vendor's export format
+pub trait Importer {
+    /// Name of the password manager, as shown to users
+    fn name(&self) -> &'static str;
+
+    /// Parse raw export bytes into credentials
+    fn parse(&self, data: &[u8]) -> CoreResult<ImportReport>;
+}
+
+/// An entry in the source export that could not be imported
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImportRowError {
+    /// 1-based position in the source (CSV line number or item index); 0 if unknown
+    pub position: usize,
+    /// Title of the entry, if one could be determined
+    pub title: Option<String>,
+    /// What went wrong
+    pub message: String,
+}
+
+/// Outcome of an import
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ImportReport {
+    /// Credentials created (or that would be created in dry-run mode)
+    pub credentials: Vec<CredentialRecord>,
+    /// Existing credentials updated in place because the import carried
+    /// their ID
+    #[serde(default)]
+    pub updated: Vec<CredentialRecord>,
+    /// Entries that could not be imported
+    pub errors: Vec<ImportRowError>,
+    /// Entries that were imported but need the user's attention
+    #[serde(default)]
+    pub warnings: Vec<ImportRowError>,
+    /// Entries that were intentionally skipped (blank rows, trashed items)
+    pub skipped_rows: usize,
+    /// Why entries were skipped, for those the importer can name
+    #[serde(default)]
+    pub skipped: Vec<ImportRowError>,
+    /// Credentials that match ones already in the repository, held back
+    /// from the import; see [`ImportReport::detect_duplicates`]
+    #[serde(default)]
+    pub duplicates: Vec<ImportDuplicate>,
+    /// Whether this was a dry run
+    pub dry_run: bool,
+}
+
+/// An imported credential that matches one already in the repository
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportDuplicate {
+    pub credential: CredentialRecord,
+    /// ID of the matching credential in the repository
+    pub existing_id: String,
+}
+
+impl ImportReport {
+    /// Number of credentials imported
+    pub fn imported_count(&self) -> usize {
+        self.credentials.len()
+    }
+
+    /// Give every credential a fresh ID, so that nothing is updated in place
+    pub fn assign_new_ids(mut self) -> Self {
+        for credential in &mut self.credentials {
+            credential.id = validation::generate_credential_id();
+        }
+        self
+    }
+
+    /// Whether any entry failed
+    pub fn has_errors(&self) -> bool {
+        !self.errors.is_empty()
+    }
+
+    /// Flag an imported entry for review
+    pub(crate) fn warn<S: Into<String>>(&mut self, position: usize, title: &str, message: S) {
+        self.warnings.push(ImportRowError {
+            position,
+            title: Some(title.to_string()),
+            message: message.into(),
+        });
+    }
+
+    /// Skip an entry on purpose, saying why
+    pub(crate) fn skip<S: Into<String>>(&mut self, position: usize, title: &str, reason: S) {
+        self.skipped_rows += 1;
+        self.skipped.push(ImportRowError {
+            position,
+            title: Some(title.to_string()).filter(|t| !t.is_empty()),
+            message: reason.into(),
+        });
+    }
+
+    /// Hold back credentials that duplicate one in `existing` or an earlier
+    /// one in this import
+    ///
+    /// Two credentials are duplicates when they have the same type,
+    /// username and password and are for the same site: the domain of the
+    /// first URL, or the title when there is none. Entries without username
+    /// and password, such as notes, match on title and notes instead.
+    /// Credentials carrying the ID of an existing one are updates, not
+    /// duplicates. Held-back credentials move to `duplicates`; put any of
+    /// them back in `credentials` to import it anyway.
+    pub fn detect_duplicates(mut self, existing: &[CredentialRecord]) -> Self {
+        let mut seen: HashMap<String, String> = existing
+            .iter()
+            .map(|credential| (duplicate_key(credential), credential.id.clone()))
+            .collect();
+        let existing_ids: std::collections::HashSet<&str> =
+            existing.iter().map(|c| c.id.as_str()).collect();
+
+        let mut unique = Vec::with_capacity(self.credentials.len());
+        for credential in self.credentials {
+            if existing_ids.contains(credential.id.as_str()) {
+                unique.push(credential);
+                continue;
+            }
+            let key = duplicate_key(&credential);
+            match seen.get(&key) {
+                Some(existing_id) => self.duplicates.push(ImportDuplicate {
+                    existing_id: existing_id.clone(),
+                    credential,
+                }),
+                None => {
+                    seen.insert(key, credential.id.clone());
+                    unique.push(credential);
+                }
+            }
+        }
+        self.credentials = unique;
+        self
+    }
+
+    /// Record the outcome of converting a single entry
+    pub(crate) fn push(
+        &mut self,
+        position: usize,
+        result: Result<CredentialRecord, ImportFailure>,
+    ) {
+        match result {
+            Ok(credential) => self.credentials.push(credential),
+            Err(failure) => self.errors.push(ImportRowError {
+                position,
+                title: failure.title,
+                message: failure.message,
+            }),
+        }
+    }
+
+    /// Append another report's results to this one
+    pub fn merge(&mut self, other: ImportReport) {
+        self.credentials.extend(other.credentials);
+        self.updated.extend(other.updated);
+        self.errors.extend(other.errors);
+        self.warnings.extend(other.warnings);
+        self.skipped_rows += other.skipped_rows;
+        self.skipped.extend(other.skipped);
+        self.duplicates.extend(other.duplicates);
+    }
+
+    /// Add the parsed credentials to a repository
+    ///
+    /// A credential whose ID is already in the repository replaces that
+    /// credential (keeping its creation time) and is moved to `updated`;
+    /// exports carry stable IDs, so importing one twice does not duplicate
+    /// anything. Credentials the repository rejects are moved to `errors`.
+    pub fn import_into(self, repository: &mut UnifiedMemoryRepository) -> CoreResult<ImportReport> {
+        if !repository.is_initialized() {
+            return Err(CoreError::NotInitialized);
+        }
+
+        let mut report = ImportReport {
+            errors: self.errors,
+            warnings: self.warnings,
+            skipped_rows: self.skipped_rows,
+            skipped: self.skipped,
+            duplicates: self.duplicates,
+            ..Default::default()
+        };
+
+        for credential in self.credentials {
+            let exists = repository.get_credential_readonly(&credential.id).is_ok();
+            let result = if exists {
+                repository.update_credential(credential.clone())
+            } else {
+                repository.add_credential(credential.clone())
+            };
+            match result {
+                Ok(()) if exists => report.updated.push(credential),
+                Ok(()) => report.credentials.push(credential),
+                Err(e) => report.errors.push(ImportRowError {
+                    position: 0,
+                    title: Some(credential.title),
+                    message: e.to_string(),
+                }),
+            }
+        }
+
+        Ok(report)
+    }
+}
+
+/// Supported vendor-specific import sources
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ImportSource {
+    /// Proton Pass export (`.zip` or `data.json`)
+    ProtonPass,
+    /// Dashlane export (`.zip`, CSV or JSON)
+    Dashlane,
+    /// Enpass JSON export
+    Enpass,
+    /// macOS / iOS Passwords (Keychain) CSV export
+    ApplePasswords,
+    /// Bitwarden unencrypted JSON export
+    Bitwarden,
+    /// 1Password `.1pux` export
+    OnePassword,
+}
+
+impl ImportSource {
+    /// Human-readable name
+    pub fn display_name(&self) -> &'static str {
+        self.importer().name()
+    }
+
+    /// The importer that reads this source
+    pub fn importer(&self) -> &'static dyn Importer {
+        match self {
+            ImportSource::ProtonPass => &ProtonPassImporter,
+            ImportSource::Dashlane => &DashlaneImporter,
+            ImportSource::Enpass => &EnpassImporter,
+            ImportSource::ApplePasswords => &ApplePasswordsImporter,
+            ImportSource::Bitwarden => &BitwardenImporter,
+            ImportSource::OnePassword => &OnePasswordImporter,
+        }
+    }
+
+    /// Parse raw export bytes for this source
+    pub fn parse(&self, data: &[u8]) -> CoreResult<ImportReport> {
+        self.importer().parse(data)
+    }
+}
+
+/// Why a single entry was rejected
+#[derive(Debug, Clone)]
+pub(crate) struct ImportFailure {
+    pub title: Option<String>,
+    pub message: String,
+}
+
+impl ImportFailure {
+    pub fn new<S: Into<String>>(title: Option<String>, message: S) -> Self {
+        Self {
+            title,
+            message: message.into(),
+        }
+    }
+}
+
+/// Fill in a missing title and validate a converted credential
+pub(crate) fn finish(mut credential: CredentialRecord) -> Result<CredentialRecord, ImportFailure> {
+    if credential.title.trim().is_empty() {
+        credential.title = credential
+            .get_field("url")
+            .and_then(|f| string_utils::extract_domain(&f.value))
+            .unwrap_or_else(|| "Untitled".to_string());
+    }
+
+    let result = validation::validate_credential(&credential);
+    if !result.is_valid {
+        return Err(ImportFailure::new(
+            Some(credential.title),
+            result.errors.join("; "),
+        ));
+    }
+
+    Ok(credential)
+}
+
+/// What makes two credentials the same entry; see
+/// [`ImportReport::detect_duplicates`]
+fn duplicate_key(credential: &CredentialRecord) -> String {
+    let value = |field_type: FieldType| {
+        credential
+            .fields
+            .values()
+            .filter(|field| field.field_type == field_type && !field.value.is_empty())
+            .map(|field| field.value.trim())
+            .min()
+            .unwrap_or("")
+            .to_string()
+    };
+    let username = value(FieldType::Username).to_lowercase();
+    let password = value(FieldType::Password);
+    let title = credential.title.trim().to_lowercase();
+    if username.is_empty() && password.is_empty() {
+        let notes = credential.notes.as_deref().unwrap_or("").trim();
+        return format!("{}\0{}\0{}", credential.credential_type, title, notes);
+    }
+
+    let site = credential
+        .fields
+        .iter()
+        .filter(|(_, field)| field.field_type == FieldType::Url)
+        .min_by_key(|(name, _)| name.as_str())
+        .and_then(|(_, field)| string_utils::extract_domain(&field.value))
+        .map(|domain| domain.to_lowercase())
+        .unwrap_or(title);
+    format!(
+        "{}\0{}\0{}\0{}",
+        credential.credential_type, site, username, password
+    )
+}
+
+/// Build a TOTP field from a bare secret or an `otpauth://` URI
+///
+/// Parameters from the URI (algorithm, digits, period, issuer) are kept as
+/// field metadata since the field value itself must be a base32 secret.
+pub(crate) fn totp_field(value: &str) -> Option<CredentialField> {
+    let value = value.trim();
+    if value.is_empty() {
+        return None;
+    }
+
+    if !value.to_lowercase().starts_with("otpauth://") {
+        return Some(CredentialField::totp_secret(value.replace(' ', "")));
+    }
+
+    let uri = url::Url::parse(value).ok()?;
+    let mut secret = None;
+    let mut field = CredentialField::totp_secret("");
+    for (key, param) in uri.query_pairs() {
+        match key.to_lowercase().as_str() {
+            "secret" => secret = Some(param.replace(' ', "")),
+            "algorithm" | "digits" | "period" | "issuer" | "encoder" => {
+                field = field.with_metadata(key.to_lowercase(), param.to_string());
+            }
+            _ => {}
+        }
+    }
+
+    field.value = secret?;
+    Some(field)
+}
+
+/// Build a URL field, adding a scheme to bare hostnames
+///
+/// Returns `None` for placeholders that aren't real addresses (e.g. the
+/// `http://sn` LastPass uses for secure notes).
+pub(crate) fn url_field(value: &str) -> Option<CredentialField> {
+    let value = value.trim();
+    if value.is_empty() {
+        return None;
+    }
+
+    let url = if validation::is_valid_url(value) {
+        value.to_string()
+    } else if !value.contains("://") && value.contains('.') && !value.contains(' ') {
+        format!("https://{}", value)
+    } else {
+        return None;
+    };
+
+    match string_utils::extract_domain(&url) {
+        Some(domain) if domain.contains('.') || domain == "localhost" => {
+            Some(CredentialField::url(url))
+        }
+        _ => None,
+    }
+}
+
+/// Turn a vendor label such as "Recovery Code" into a field name
+pub(crate) fn field_name(label: &str) -> String {
+    let mut name = String::new();
+    let mut prev_lower = false;
+    for c in label.trim().chars() {
+        if c.is_uppercase() && prev_lower {
+            name.push('_');
+        }
+        if c.is_alphanumeric() {
+            name.extend(c.to_lowercase());
+            prev_lower = c.is_lowercase();
+        } else if !name.ends_with('_') && !name.is_empty() {
+            name.push('_');
+            prev_lower = false;
+        }
+    }
+    name.trim_end_matches('_').to_string()
+}
+
+/// `name`, or `name_2`, `name_3`, ... if the credential already has it
+pub(crate) fn unused_name(credential: &CredentialRecord, name: String) -> String {
+    if credential.get_field(&name).is_none() {
+        return name;
+    }
+    (2..)
+        .map(|n| format!("{}_{}", name, n))
+        .find(|candidate| credential.get_field(candidate).is_none())
+        .expect("some suffix is free")
+}
+
+/// Read every file in a zip archive whose name matches a predicate
+pub(crate) fn read_zip_entries<P>(
+    data: &[u8],
+    mut predicate: P,
+) -> CoreResult<Vec<(String, Vec<u8>)>>
+where
+    P: FnMut(&str) -> bool,
+{
+    let mut archive =
+        zip::ZipArchive::new(Cursor::new(data)).map_err(|e| CoreError::SerializationError {
+            message: format!("Failed to open zip export: {}", e),
+        })?;
+
+    let mut entries = Vec::new();
+    for index in 0..archive.len() {
+        let mut file = archive
+            .by_index(index)
+            .map_err(|e| CoreError::SerializationError {
+                message: format!("Failed to read zip entry: {}", e),
+            })?;
+
+        if file.is_dir() || !predicate(file.name()) {
+            continue;
+        }
+
+        let name = file.name().to_string();
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents)
+            .map_err(|e| CoreError::SerializationError {
+                message: format!("Failed to read '{}' from zip export: {}", name, e),
+            })?;
+        entries.push((name, contents));
+    }
+
+    Ok(entries)
+}
+
+/// Whether the data starts with a zip local file header
+pub(crate) fn is_zip(data: &[u8]) -> bool {
+    data.starts_with(b"PK\x03\x04")
+}
+
+/// Interpret export bytes as UTF-8 text, dropping any byte order mark
+pub(crate) fn text(data: &[u8]) -> CoreResult<&str> {
+    std::str::from_utf8(data)
+        .map(|s| s.trim_start_matches('\u{feff}'))
+        .map_err(|e| CoreError::SerializationError {
+            message: format!("Export is not valid UTF-8: {}", e),
+        })
+}
+
+#[cfg(test)]
+pub(crate) mod test_support {
+    use std::io::Write;
+
+    /// Build an in-memory zip archive from (name, contents) pairs
+    pub fn build_zip(files: &[(&str, &str)]) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        let options = zip::write::SimpleFileOptions::default();
+        for (name, contents) in files {
+            writer.start_file(*name, options).unwrap();
+            writer.write_all(contents.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_totp_field_from_uri() {
+        let field = totp_field(
+            "otpauth://totp/GitHub:octocat?secret=JBSW%20Y3DP&issuer=GitHub&algorithm=SHA256&digits=8",
+        )
+        .unwrap();
+
+        assert_eq!(field.field_type, FieldType::TotpSecret);
+        assert_eq!(field.value, "JBSWY3DP");
+        assert_eq!(field.metadata.get("algorithm").unwrap(), "SHA256");
+        assert_eq!(field.metadata.get("digits").unwrap(), "8");
+        assert_eq!(field.metadata.get("issuer").unwrap(), "GitHub");
+
+        assert_eq!(totp_field("JBSW Y3DP").unwrap().value, "JBSWY3DP");
+        assert!(totp_field("otpauth://totp/NoSecret").is_none());
+        assert!(totp_field("").is_none());
+    }
+
+    #[test]
+    fn test_url_field() {
+        assert_eq!(
+            url_field("https://example.com/login").unwrap().value,
+            "https://example.com/login"
+        );
+        assert_eq!(
+            url_field("example.com").unwrap().value,
+            "https://example.com"
+        );
+        assert!(url_field("http://sn").is_none());
+        assert!(url_field("android://com.example.app").is_none());
+    }
+
+    #[test]
+    fn test_finish_fills_title() {
+        let mut credential = CredentialRecord::new(String::new(), "login".to_string());
+        credential.set_field("url", url_field("https://example.com").unwrap());
+        assert_eq!(finish(credential).unwrap().title, "example.com");
+    }
+
+    #[test]
+    fn test_report_import_into() {
+        let mut repo = UnifiedMemoryRepository::new();
+        repo.initialize().unwrap();
+
+        let mut report = ImportReport::default();
+        let credential = CredentialRecord::new("One".to_string(), "login".to_string());
+        report.push(1, Ok(credential.clone()));
+        report.push(2, Ok(credential));
+
+        // The second copy has the same ID and updates the first
+        let applied = report.clone().import_into(&mut repo).unwrap();
+        assert_eq!(applied.imported_count(), 1);
+        assert_eq!(applied.updated.len(), 1);
+        assert!(applied.errors.is_empty());
+        assert_eq!(repo.list_credentials().unwrap().len(), 1);
+
+        let copies = report.assign_new_ids().import_into(&mut repo).unwrap();
+        assert_eq!(copies.imported_count(), 2);
+        assert!(copies.updated.is_empty());
+        assert_eq!(repo.list_credentials().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_detect_duplicates() {
+        let login = |title: &str, url: &str, password: &str| {
+            let mut credential = CredentialRecord::new(title.to_string(), "login".to_string());
+            credential.set_field("username", CredentialField::username("alice"));
+            credential.set_field("password", CredentialField::password(password));
+            credential.set_field("url", CredentialField::url(url));
+            credential
+        };
+        let existing = vec![login("Example", "https://example.com/login", "one")];
+
+        let mut report = ImportReport::default();
+        // Same site and login under another title
+        report.push(1, Ok(login("example.com", "https://example.com", "one")));
+        // Different password
+        report.push(2, Ok(login("Example", "https://example.com", "two")));
+        // Repeated within the import
+        report.push(3, Ok(login("Example 2", "https://example.com", "two")));
+        // Carries the existing ID, so it is an update
+        let mut update = login("Example", "https://example.com", "one");
+        update.id = existing[0].id.clone();
+        report.push(4, Ok(update));
+
+        let report = report.detect_duplicates(&existing);
+        let titles: Vec<_> = report
+            .credentials
+            .iter()
+            .map(|c| c.title.as_str())
+            .collect();
+        assert_eq!(titles, vec!["Example", "Example"]);
+        assert_eq!(report.duplicates.len(), 2);
+        assert_eq!(report.duplicates[0].existing_id, existing[0].id);
+        assert_eq!(report.duplicates[1].existing_id, report.credentials[0].id);
+
+        let mut note = CredentialRecord::new("Door".to_string(), "secure_note".to_string());
+        note.notes = Some("4242".to_string());
+        let mut other = note.clone();
+        other.id = "other".to_string();
+        let mut report = ImportReport::default();
+        report.push(1, Ok(other));
+        assert_eq!(report.detect_duplicates(&[note]).duplicates.len(), 1);
+    }
+
+    #[test]
+    fn test_field_name() {
+        assert_eq!(field_name("Recovery PIN"), "recovery_pin");
+        assert_eq!(field_name("firstName"), "first_name");
+        assert_eq!(field_name("  2FA code "), "2fa_code");
+    }
+
+    #[test]
+    fn test_is_zip() {
+        let zip = test_support::build_zip(&[("a.txt", "hello")]);
+        assert!(is_zip(&zip));
+        assert!(!is_zip(b"{}"));
+
+        let entries = read_zip_entries(&zip, |name| name.ends_with(".txt")).unwrap();
+        assert_eq!(entries, vec![("a.txt".to_string(), b"hello".to_vec())]);
+    }
+}
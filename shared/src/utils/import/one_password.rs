@@ -0,0 +1,558 @@
+//! 1Password `.1pux` export importer
+//!
+//! A 1PUX file is a zip holding `export.data`, a JSON tree of accounts,
+//! vaults and items. Each vault becomes a folder. Items carry their login
+//! fields separately from sections of typed fields; known field IDs of each
+//! category map onto the matching ZipLock template field, and the rest are
+//! kept as labelled custom fields.
+
+use super::{
+    field_name, finish, is_zip, read_zip_entries, text, totp_field, unused_name, url_field,
+    ImportFailure, ImportReport,
+};
+use crate::core::{CoreError, CoreResult};
+use crate::models::{CredentialField, CredentialRecord, FieldType};
+use chrono::{TimeZone, Utc};
+use serde::Deserialize;
+use serde_json::Value;
+
+#[derive(Debug, Deserialize)]
+struct OnePuxExport {
+    #[serde(default)]
+    accounts: Vec<OnePuxAccount>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OnePuxAccount {
+    #[serde(default)]
+    vaults: Vec<OnePuxVault>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OnePuxVault {
+    #[serde(default)]
+    attrs: OnePuxVaultAttrs,
+    #[serde(default)]
+    items: Vec<OnePuxItem>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct OnePuxVaultAttrs {
+    #[serde(default)]
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OnePuxItem {
+    #[serde(default)]
+    fav_index: i64,
+    #[serde(default)]
+    created_at: Option<i64>,
+    #[serde(default)]
+    updated_at: Option<i64>,
+    /// "active" or "archived"
+    #[serde(default)]
+    state: String,
+    #[serde(default)]
+    category_uuid: String,
+    #[serde(default)]
+    details: OnePuxDetails,
+    #[serde(default)]
+    overview: OnePuxOverview,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OnePuxDetails {
+    #[serde(default)]
+    login_fields: Vec<OnePuxLoginField>,
+    #[serde(default)]
+    notes_plain: Option<String>,
+    #[serde(default)]
+    sections: Vec<OnePuxSection>,
+    /// Set on items of the Password category
+    #[serde(default)]
+    password: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OnePuxLoginField {
+    #[serde(default)]
+    value: String,
+    #[serde(default)]
+    name: String,
+    /// "T" text, "E" email, "P" password, ...
+    #[serde(default)]
+    field_type: String,
+    #[serde(default)]
+    designation: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct OnePuxSection {
+    #[serde(default)]
+    fields: Vec<OnePuxField>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OnePuxField {
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    id: String,
+    /// An object with a single key naming the value's kind
+    #[serde(default)]
+    value: Value,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct OnePuxOverview {
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    url: String,
+    #[serde(default)]
+    urls: Vec<OnePuxUrl>,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OnePuxUrl {
+    #[serde(default)]
+    url: String,
+}
+
+/// Importer for 1Password 1PUX exports
+pub struct OnePasswordImporter;
+
+impl OnePasswordImporter {
+    /// Parse a 1PUX export, either the zip or the bare `export.data`
+    pub fn parse(data: &[u8]) -> CoreResult<ImportReport> {
+        if is_zip(data) {
+            Self::parse_zip(data)
+        } else {
+            Self::parse_json(text(data)?)
+        }
+    }
+
+    /// Parse a `.1pux` zip
+    pub fn parse_zip(data: &[u8]) -> CoreResult<ImportReport> {
+        let entries = read_zip_entries(data, |name| name == "export.data")?;
+        let (_, json) = entries
+            .into_iter()
+            .next()
+            .ok_or_else(|| CoreError::ValidationError {
+                message: "1Password export does not contain export.data".to_string(),
+            })?;
+        Self::parse_json(text(&json)?)
+    }
+
+    /// Parse the `export.data` from a 1PUX export
+    pub fn parse_json(json: &str) -> CoreResult<ImportReport> {
+        let export: OnePuxExport =
+            serde_json::from_str(json).map_err(|e| CoreError::SerializationError {
+                message: format!("Invalid 1Password export: {}", e),
+            })?;
+
+        let mut report = ImportReport::default();
+        let mut position = 0;
+
+        for vault in export.accounts.iter().flat_map(|a| &a.vaults) {
+            for item in &vault.items {
+                position += 1;
+                let title = &item.overview.title;
+                if item.state == "archived" {
+                    report.skip(position, title, "Archived in 1Password");
+                    continue;
+                }
+                if category(&item.category_uuid).is_none() {
+                    report.warn(
+                        position,
+                        title,
+                        format!(
+                            "Unknown 1Password category '{}'; imported as a secure note",
+                            item.category_uuid
+                        ),
+                    );
+                }
+                report.push(position, Self::convert(item, &vault.attrs.name));
+            }
+        }
+
+        Ok(report)
+    }
+
+    fn convert(item: &OnePuxItem, vault_name: &str) -> Result<CredentialRecord, ImportFailure> {
+        let credential_type = category(&item.category_uuid).unwrap_or("secure_note");
+        let mut credential =
+            CredentialRecord::new(item.overview.title.clone(), credential_type.to_string());
+        credential.favorite = item.fav_index > 0;
+        credential.notes = item.details.notes_plain.clone().filter(|n| !n.is_empty());
+        if !vault_name.is_empty() {
+            credential.folder_path = Some(vault_name.to_string());
+        }
+        for tag in &item.overview.tags {
+            credential.add_tag(tag.clone());
+        }
+
+        let urls = if item.overview.urls.is_empty() {
+            vec![item.overview.url.as_str()]
+        } else {
+            item.overview.urls.iter().map(|u| u.url.as_str()).collect()
+        };
+        for (i, url) in urls.into_iter().filter_map(url_field).enumerate() {
+            let name = if i == 0 {
+                "url".to_string()
+            } else {
+                format!("url_{}", i + 1)
+            };
+            credential.set_field(name, url);
+        }
+
+        for login_field in &item.details.login_fields {
+            if login_field.value.is_empty() {
+                continue;
+            }
+            let (name, field) = match (
+                login_field.designation.as_str(),
+                login_field.field_type.as_str(),
+            ) {
+                ("username", _) => (
+                    "username".to_string(),
+                    CredentialField::username(&login_field.value),
+                ),
+                ("password", _) => (
+                    "password".to_string(),
+                    CredentialField::password(&login_field.value),
+                ),
+                (_, "P") => (
+                    custom_name(&login_field.name),
+                    CredentialField::password(&login_field.value)
+                        .with_label(login_field.name.clone()),
+                ),
+                (_, "E") => (
+                    custom_name(&login_field.name),
+                    CredentialField::email(&login_field.value).with_label(login_field.name.clone()),
+                ),
+                _ => (
+                    custom_name(&login_field.name),
+                    CredentialField::text(&login_field.value).with_label(login_field.name.clone()),
+                ),
+            };
+            let name = unused_name(&credential, name);
+            credential.set_field(name, field);
+        }
+        if let Some(password) = item.details.password.as_deref().filter(|p| !p.is_empty()) {
+            credential.set_field("password", CredentialField::password(password));
+        }
+
+        for field in item.details.sections.iter().flat_map(|s| &s.fields) {
+            Self::add_section_field(&mut credential, credential_type, field);
+        }
+
+        // Last, since setting fields and tags bumps the modification time
+        if let Some(created) = item.created_at.filter(|&t| t > 0) {
+            credential.created_at = created;
+        }
+        if let Some(updated) = item.updated_at.filter(|&t| t > 0) {
+            credential.updated_at = updated.max(credential.created_at);
+        }
+
+        finish(credential)
+    }
+
+    fn add_section_field(
+        credential: &mut CredentialRecord,
+        credential_type: &str,
+        field: &OnePuxField,
+    ) {
+        let Some((kind, value)) = field.value.as_object().and_then(|o| o.iter().next()) else {
+            return;
+        };
+        let string = value.as_str().unwrap_or("");
+
+        let converted = match kind.as_str() {
+            "concealed" => Some(CredentialField::password(string)),
+            "string" | "menu" | "creditCardType" | "gender" => Some(CredentialField::text(string)),
+            "totp" => totp_field(string),
+            "url" => url_field(string),
+            "email" => {
+                // Newer exports wrap the address in an object
+                let address = value
+                    .get("email_address")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or(string);
+                Some(CredentialField::email(address))
+            }
+            "phone" => Some(CredentialField::new(
+                FieldType::Phone,
+                string.to_string(),
+                false,
+            )),
+            "creditCardNumber" => Some(CredentialField::new(
+                FieldType::CreditCardNumber,
+                string.to_string(),
+                true,
+            )),
+            "monthYear" => value
+                .as_i64()
+                .and_then(month_year)
+                .map(|expiry| CredentialField::new(FieldType::ExpiryDate, expiry, false)),
+            "date" => value
+                .as_i64()
+                .and_then(|t| Utc.timestamp_opt(t, 0).single())
+                .map(|date| {
+                    CredentialField::new(
+                        FieldType::Date,
+                        date.format("%Y-%m-%d").to_string(),
+                        false,
+                    )
+                }),
+            "address" => Some(CredentialField::text(address(value))),
+            "sshKey" => {
+                let metadata = value.get("metadata");
+                let get = |v: Option<&Value>, key: &str| {
+                    v.and_then(|v| v.get(key))
+                        .and_then(|v| v.as_str())
+                        .unwrap_or("")
+                        .to_string()
+                };
+                let public_key = get(metadata, "publicKey");
+                if !public_key.is_empty() {
+                    credential.set_field(
+                        "public_key",
+                        CredentialField::new(FieldType::TextArea, public_key, false),
+                    );
+                }
+                let fingerprint = get(metadata, "fingerprint");
+                if !fingerprint.is_empty() {
+                    credential.set_field("fingerprint", CredentialField::text(fingerprint));
+                }
+                Some(CredentialField::password(get(Some(value), "privateKey")))
+            }
+            // Attachments and references to other items have no equivalent
+            _ => None,
+        };
+        let Some(mut converted) = converted.filter(|f| !f.value.is_empty()) else {
+            return;
+        };
+
+        let name = match known_field(credential_type, &field.id) {
+            Some(name) => name.to_string(),
+            None if converted.field_type == FieldType::TotpSecret
+                && credential.get_field("totp").is_none() =>
+            {
+                "totp".to_string()
+            }
+            None => {
+                let label = if field.title.is_empty() {
+                    &field.id
+                } else {
+                    &field.title
+                };
+                converted = converted.with_label(label.clone());
+                custom_name(label)
+            }
+        };
+        if name == "security" {
+            converted.value = wifi_security(&converted.value).to_string();
+        }
+        let name = unused_name(credential, name);
+        credential.set_field(name, converted);
+    }
+}
+
+impl super::Importer for OnePasswordImporter {
+    fn name(&self) -> &'static str {
+        "1Password"
+    }
+
+    fn parse(&self, data: &[u8]) -> CoreResult<ImportReport> {
+        Self::parse(data)
+    }
+}
+
+/// ZipLock credential type of a 1Password category
+fn category(uuid: &str) -> Option<&'static str> {
+    Some(match uuid {
+        "001" | "110" => "login",
+        "002" => "credit_card",
+        "003" => "secure_note",
+        "004" => "identity",
+        "005" => "password",
+        "006" => "document",
+        "100" => "software_license",
+        "101" => "bank_account",
+        "102" => "database",
+        "109" => "wifi",
+        "112" => "api_credentials",
+        "114" => "ssh_key",
+        "115" => "crypto_wallet",
+        _ => return None,
+    })
+}
+
+/// ZipLock field name of a section field 1Password gives a fixed ID
+fn known_field(credential_type: &str, id: &str) -> Option<&'static str> {
+    Some(match (credential_type, id) {
+        ("credit_card", "cardholder") => "cardholder",
+        ("credit_card", "type") => "brand",
+        ("credit_card", "ccnum") => "card_number",
+        ("credit_card", "cvv") => "cvv",
+        ("credit_card", "expiry") => "expiry_date",
+        ("credit_card", "pin") => "pin",
+        ("identity", "firstname") => "first_name",
+        ("identity", "initial") => "middle_name",
+        ("identity", "lastname") => "last_name",
+        ("identity", "birthdate") => "birth_date",
+        ("identity", "address") => "address",
+        ("identity", "defphone") => "phone",
+        ("identity", "email") => "email",
+        ("identity", "company") => "company",
+        ("software_license", "reg_code") => "license_key",
+        ("software_license", "product_version") => "version",
+        ("software_license", "reg_email") => "email",
+        ("bank_account", "bankName") => "bank_name",
+        ("bank_account", "accountNo") => "account_number",
+        ("bank_account", "routingNo") => "routing_number",
+        ("bank_account", "iban") => "iban",
+        ("bank_account", "swift") => "swift",
+        ("database", "hostname") => "host",
+        ("database", "port") => "port",
+        ("database", "database") => "database",
+        ("database", "username") => "username",
+        ("database", "password") => "password",
+        ("login", "url") => "url",
+        ("login", "username") => "username",
+        ("login", "password") => "password",
+        ("wifi", "network_name") => "ssid",
+        ("wifi", "wireless_password") => "password",
+        ("wifi", "wireless_security") => "security",
+        ("api_credentials", "credential") => "api_key",
+        ("api_credentials", "username") => "username",
+        ("api_credentials", "hostname") => "endpoint",
+        ("ssh_key", "private_key") => "private_key",
+        ("crypto_wallet", "recoveryPhrase") => "seed_phrase",
+        ("crypto_wallet", "walletAddress") => "wallet_address",
+        ("crypto_wallet", "password") => "password",
+        _ => return None,
+    })
+}
+
+/// Field name for a field without a fixed meaning
+fn custom_name(label: &str) -> String {
+    match field_name(label) {
+        name if name.is_empty() => "field".to_string(),
+        name => name,
+    }
+}
+
+/// Convert 1Password's `YYYYMM` expiry to ZipLock's `MM/YY`
+fn month_year(value: i64) -> Option<String> {
+    let (year, month) = (value / 100, value % 100);
+    if !(1000..=9999).contains(&year) || !(1..=12).contains(&month) {
+        return None;
+    }
+    Some(format!("{:02}/{:02}", month, year % 100))
+}
+
+/// One line of an address value's parts, skipping the empty ones
+fn address(value: &Value) -> String {
+    ["street", "city", "state", "zip", "country"]
+        .iter()
+        .filter_map(|key| value.get(*key).and_then(|v| v.as_str()))
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Map 1Password's security menu values (`wpa2p`, `wpa3e`, ...) to the
+/// names the Wi-Fi template uses
+fn wifi_security(value: &str) -> &'static str {
+    match value.to_ascii_lowercase() {
+        v if v.starts_with("wpa3") => "WPA3",
+        v if v.starts_with("wpa") => "WPA",
+        v if v.starts_with("wep") => "WEP",
+        _ => "none",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::import::test_support::build_zip;
+
+    const FIXTURE: &str = include_str!("../../../tests/fixtures/import/one_password.json");
+
+    fn find<'a>(report: &'a ImportReport, title: &str) -> &'a CredentialRecord {
+        report
+            .credentials
+            .iter()
+            .find(|c| c.title == title)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_parse_json() {
+        let report = OnePasswordImporter::parse_json(FIXTURE).unwrap();
+        assert!(!report.has_errors(), "{:?}", report.errors);
+        assert_eq!(report.imported_count(), 5);
+        assert_eq!(report.skipped_rows, 1);
+        assert_eq!(report.skipped[0].title.as_deref(), Some("Old Forum"));
+        assert_eq!(report.warnings.len(), 1);
+
+        let login = find(&report, "GitHub");
+        assert_eq!(login.credential_type, "login");
+        assert_eq!(login.folder_path.as_deref(), Some("Personal"));
+        assert!(login.favorite);
+        assert_eq!(login.tags, vec!["dev"]);
+        assert_eq!(login.get_field("username").unwrap().value, "octocat");
+        assert_eq!(login.get_field("password").unwrap().value, "hunter2");
+        assert_eq!(login.get_field("url").unwrap().value, "https://github.com");
+        assert_eq!(login.get_field("totp").unwrap().value, "JBSWY3DPEHPK3PXP");
+        assert!(login.get_field("recovery_code").unwrap().sensitive);
+        assert_eq!(login.created_at, 1672531200);
+
+        let card = find(&report, "Visa");
+        assert_eq!(card.credential_type, "credit_card");
+        assert_eq!(
+            card.get_field("card_number").unwrap().value,
+            "4111111111111111"
+        );
+        assert_eq!(card.get_field("expiry_date").unwrap().value, "04/27");
+        assert_eq!(card.get_field("cvv").unwrap().value, "123");
+
+        let wifi = find(&report, "Home Wi-Fi");
+        assert_eq!(wifi.credential_type, "wifi");
+        assert_eq!(wifi.get_field("ssid").unwrap().value, "HomeNet");
+        assert_eq!(wifi.get_field("security").unwrap().value, "WPA");
+        assert_eq!(wifi.get_field("password").unwrap().value, "correct horse");
+
+        let identity = find(&report, "Me");
+        assert_eq!(
+            identity.get_field("address").unwrap().value,
+            "1 Main St, Springfield, 12345, US"
+        );
+        assert_eq!(
+            identity.get_field("birth_date").unwrap().value,
+            "1990-01-01"
+        );
+
+        let unknown = find(&report, "Passport");
+        assert_eq!(unknown.credential_type, "secure_note");
+        assert_eq!(unknown.get_field("number").unwrap().value, "X1234567");
+    }
+
+    #[test]
+    fn test_parse_zip() {
+        let zip = build_zip(&[("export.attributes", "{}"), ("export.data", FIXTURE)]);
+        let report = OnePasswordImporter::parse(&zip).unwrap();
+        assert_eq!(report.imported_count(), 5);
+
+        let empty = build_zip(&[("export.attributes", "{}")]);
+        assert!(OnePasswordImporter::parse(&empty).is_err());
+    }
+}
@@ -0,0 +1,356 @@
+//! Proton Pass export importer
+//!
+//! Proton Pass exports a zip containing `Proton Pass/data.json`. Each vault
+//! becomes a folder; extra fields and TOTP URIs are carried over as fields.
+
+use super::{
+    field_name, finish, is_zip, read_zip_entries, text, totp_field, url_field, ImportFailure,
+    ImportReport,
+};
+use crate::core::{CoreError, CoreResult};
+use crate::models::{CredentialField, CredentialRecord, FieldType};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ProtonExport {
+    #[serde(default)]
+    encrypted: bool,
+    #[serde(default)]
+    vaults: BTreeMap<String, ProtonVault>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ProtonVault {
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    items: Vec<ProtonItem>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ProtonItem {
+    data: ProtonItemData,
+    /// 1 = active, 2 = trashed
+    #[serde(default)]
+    state: Option<u8>,
+    #[serde(default)]
+    pinned: bool,
+    #[serde(default)]
+    create_time: Option<i64>,
+    #[serde(default)]
+    modify_time: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ProtonItemData {
+    metadata: ProtonMetadata,
+    #[serde(rename = "type")]
+    item_type: String,
+    #[serde(default)]
+    content: serde_json::Value,
+    #[serde(default)]
+    extra_fields: Vec<ProtonExtraField>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ProtonMetadata {
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    note: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ProtonExtraField {
+    field_name: String,
+    #[serde(rename = "type")]
+    field_type: String,
+    #[serde(default)]
+    data: serde_json::Value,
+}
+
+/// Importer for Proton Pass exports
+pub struct ProtonPassImporter;
+
+impl ProtonPassImporter {
+    /// Parse a Proton Pass export, either the zip or the bare `data.json`
+    pub fn parse(data: &[u8]) -> CoreResult<ImportReport> {
+        if is_zip(data) {
+            Self::parse_zip(data)
+        } else {
+            Self::parse_json(text(data)?)
+        }
+    }
+
+    /// Parse a Proton Pass zip export
+    pub fn parse_zip(data: &[u8]) -> CoreResult<ImportReport> {
+        let entries = read_zip_entries(data, |name| name.ends_with("data.json"))?;
+        let (_, json) = entries
+            .into_iter()
+            .next()
+            .ok_or_else(|| CoreError::ValidationError {
+                message: "Proton Pass export does not contain data.json".to_string(),
+            })?;
+        Self::parse_json(text(&json)?)
+    }
+
+    /// Parse the `data.json` from a Proton Pass export
+    pub fn parse_json(json: &str) -> CoreResult<ImportReport> {
+        let export: ProtonExport =
+            serde_json::from_str(json).map_err(|e| CoreError::SerializationError {
+                message: format!("Invalid Proton Pass export: {}", e),
+            })?;
+
+        if export.encrypted {
+            return Err(CoreError::ValidationError {
+                message:
+                    "Encrypted Proton Pass exports are not supported; export without PGP encryption"
+                        .to_string(),
+            });
+        }
+
+        let mut report = ImportReport::default();
+        let mut position = 0;
+
+        for vault in export.vaults.values() {
+            for item in &vault.items {
+                position += 1;
+                if item.state == Some(2) {
+                    report.skip(
+                        position,
+                        &item.data.metadata.name,
+                        "In the Proton Pass trash",
+                    );
+                    continue;
+                }
+                report.push(position, Self::convert(item, &vault.name));
+            }
+        }
+
+        Ok(report)
+    }
+
+    fn convert(item: &ProtonItem, vault_name: &str) -> Result<CredentialRecord, ImportFailure> {
+        let data = &item.data;
+        let title = data.metadata.name.clone();
+        let credential_type = match data.item_type.as_str() {
+            "login" => "login",
+            "note" => "secure_note",
+            "creditCard" => "credit_card",
+            "identity" => "identity",
+            "alias" => "login",
+            other => {
+                return Err(ImportFailure::new(
+                    Some(title),
+                    format!("Unsupported Proton Pass item type '{}'", other),
+                ))
+            }
+        };
+
+        let mut credential = CredentialRecord::new(title, credential_type.to_string());
+        if !data.metadata.note.is_empty() {
+            credential.notes = Some(data.metadata.note.clone());
+        }
+        if !vault_name.is_empty() {
+            credential.folder_path = Some(vault_name.to_string());
+        }
+        credential.favorite = item.pinned;
+        if let Some(created) = item.create_time {
+            credential.created_at = created;
+        }
+        if let Some(modified) = item.modify_time {
+            credential.updated_at = modified.max(credential.created_at);
+        }
+
+        let content = &data.content;
+        let get = |key: &str| content.get(key).and_then(|v| v.as_str()).unwrap_or("");
+
+        match data.item_type.as_str() {
+            "login" | "alias" => {
+                let username = match get("itemUsername") {
+                    "" => get("username"),
+                    other => other,
+                };
+                if !username.is_empty() {
+                    credential.set_field("username", CredentialField::username(username));
+                }
+                if !get("itemEmail").is_empty() {
+                    credential.set_field("email", CredentialField::email(get("itemEmail")));
+                }
+                if !get("password").is_empty() {
+                    credential.set_field("password", CredentialField::password(get("password")));
+                }
+                let urls = content
+                    .get("urls")
+                    .and_then(|v| v.as_array())
+                    .map(|urls| urls.iter().filter_map(|u| u.as_str()).collect::<Vec<_>>())
+                    .unwrap_or_default();
+                for (i, url) in urls.into_iter().filter_map(url_field).enumerate() {
+                    let name = if i == 0 {
+                        "url".to_string()
+                    } else {
+                        format!("url_{}", i + 1)
+                    };
+                    credential.set_field(name, url);
+                }
+                if let Some(totp) = totp_field(get("totpUri")) {
+                    credential.set_field("totp", totp);
+                }
+            }
+            "creditCard" => {
+                let card_fields = [
+                    ("cardholderName", "cardholder", FieldType::Text),
+                    ("number", "card_number", FieldType::CreditCardNumber),
+                    ("verificationNumber", "cvv", FieldType::Cvv),
+                    ("pin", "pin", FieldType::Password),
+                ];
+                for (key, name, field_type) in card_fields {
+                    if !get(key).is_empty() {
+                        let sensitive = field_type.is_sensitive_by_default();
+                        credential.set_field(
+                            name,
+                            CredentialField::new(field_type, get(key).to_string(), sensitive),
+                        );
+                    }
+                }
+                if let Some(expiry) = expiry_from_year_month(get("expirationDate")) {
+                    credential.set_field(
+                        "expiry_date",
+                        CredentialField::new(FieldType::ExpiryDate, expiry, false),
+                    );
+                }
+            }
+            "identity" => {
+                if let Some(object) = content.as_object() {
+                    for (key, value) in object {
+                        if let Some(value) = value.as_str().filter(|v| !v.is_empty()) {
+                            credential.set_field(
+                                field_name(key),
+                                CredentialField::text(value).with_label(key.clone()),
+                            );
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        for extra in &data.extra_fields {
+            let name = field_name(&extra.field_name);
+            let content = extra
+                .data
+                .get("content")
+                .or_else(|| extra.data.get("totpUri"))
+                .and_then(|v| v.as_str())
+                .unwrap_or("");
+            let field = match extra.field_type.as_str() {
+                "totp" => totp_field(content),
+                "hidden" => Some(CredentialField::password(content)),
+                _ => Some(CredentialField::text(content)),
+            };
+            if let Some(field) = field.filter(|f| !f.value.is_empty()) {
+                if credential.get_field(&name).is_none() {
+                    credential.set_field(name, field.with_label(extra.field_name.clone()));
+                }
+            }
+        }
+
+        finish(credential)
+    }
+}
+
+impl super::Importer for ProtonPassImporter {
+    fn name(&self) -> &'static str {
+        "Proton Pass"
+    }
+
+    fn parse(&self, data: &[u8]) -> CoreResult<ImportReport> {
+        Self::parse(data)
+    }
+}
+
+/// Convert Proton's `YYYY-MM` card expiry to ZipLock's `MM/YY`
+fn expiry_from_year_month(value: &str) -> Option<String> {
+    let (year, month) = value.split_once('-')?;
+    if year.len() != 4 || month.len() != 2 {
+        return None;
+    }
+    Some(format!("{}/{}", month, &year[2..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::import::test_support::build_zip;
+
+    const FIXTURE: &str = include_str!("../../../tests/fixtures/import/proton_pass.json");
+
+    #[test]
+    fn test_parse_json() {
+        let report = ProtonPassImporter::parse_json(FIXTURE).unwrap();
+        assert!(!report.has_errors(), "{:?}", report.errors);
+        assert_eq!(report.imported_count(), 3);
+        assert_eq!(report.skipped_rows, 1);
+
+        let login = report
+            .credentials
+            .iter()
+            .find(|c| c.title == "GitHub")
+            .unwrap();
+        assert_eq!(login.credential_type, "login");
+        assert_eq!(login.folder_path.as_deref(), Some("Personal"));
+        assert!(login.favorite);
+        assert_eq!(login.get_field("username").unwrap().value, "octocat");
+        assert_eq!(
+            login.get_field("email").unwrap().value,
+            "octocat@example.com"
+        );
+        assert_eq!(login.get_field("url").unwrap().value, "https://github.com");
+        assert_eq!(
+            login.get_field("url_2").unwrap().value,
+            "https://gist.github.com"
+        );
+        assert_eq!(login.get_field("totp").unwrap().value, "JBSWY3DPEHPK3PXP");
+        assert!(login.get_field("recovery_pin").unwrap().sensitive);
+        assert_eq!(
+            login.get_field("backup_totp").unwrap().field_type,
+            FieldType::TotpSecret
+        );
+
+        let card = report
+            .credentials
+            .iter()
+            .find(|c| c.credential_type == "credit_card")
+            .unwrap();
+        assert_eq!(card.get_field("expiry_date").unwrap().value, "04/27");
+        assert_eq!(card.get_field("cvv").unwrap().value, "123");
+
+        let note = report
+            .credentials
+            .iter()
+            .find(|c| c.credential_type == "secure_note")
+            .unwrap();
+        assert_eq!(note.notes.as_deref(), Some("Door code is 4242"));
+    }
+
+    #[test]
+    fn test_parse_zip() {
+        let zip = build_zip(&[("Proton Pass/data.json", FIXTURE)]);
+        let report = ProtonPassImporter::parse(&zip).unwrap();
+        assert_eq!(report.imported_count(), 3);
+
+        let empty = build_zip(&[("readme.txt", "nothing here")]);
+        assert!(ProtonPassImporter::parse(&empty).is_err());
+    }
+
+    #[test]
+    fn test_encrypted_export_rejected() {
+        let result = ProtonPassImporter::parse_json(r#"{"encrypted": true, "vaults": {}}"#);
+        assert!(matches!(result, Err(CoreError::ValidationError { .. })));
+    }
+}
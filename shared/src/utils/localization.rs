@@ -0,0 +1,279 @@
+//! Translated error and validation messages
+//!
+//! Errors and validation problems carry a [`Message`]: a stable code such as
+//! `validation.title_empty` plus the values it mentions. Password strength
+//! feedback uses the same catalogs, under `strength`. The text for a code
+//! comes from a message catalog, one TOML file per language under
+//! `utils/locales/`, built into the library so every app shows the same
+//! wording. English is the reference catalog and reproduces the `Display`
+//! text of the core errors; a code missing from another catalog falls back
+//! to it.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+/// Locale used when none is given or the requested one has no catalog
+pub const DEFAULT_LOCALE: &str = "en";
+
+/// Languages with a message catalog
+pub const SUPPORTED_LOCALES: &[&str] = &["en", "de", "fr", "es"];
+
+const CATALOG_SOURCES: &[(&str, &str)] = &[
+    ("en", include_str!("locales/en.toml")),
+    ("de", include_str!("locales/de.toml")),
+    ("fr", include_str!("locales/fr.toml")),
+    ("es", include_str!("locales/es.toml")),
+];
+
+/// Code for text that has no catalog entry; its only argument is `text`
+pub const UNTRANSLATED_CODE: &str = "text";
+
+/// A message code and the values filled into its text
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Message {
+    pub code: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub args: BTreeMap<String, String>,
+}
+
+impl Message {
+    /// A message without arguments
+    pub fn new(code: &str) -> Self {
+        Self {
+            code: code.to_string(),
+            args: BTreeMap::new(),
+        }
+    }
+
+    /// Text that is shown as is in every locale
+    pub fn untranslated(text: impl Into<String>) -> Self {
+        Self::new(UNTRANSLATED_CODE).with_arg("text", text.into())
+    }
+
+    /// Fill `{name}` in the message text with `value`
+    pub fn with_arg(mut self, name: &str, value: impl ToString) -> Self {
+        self.args.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    /// The message text in `locale`, such as `de` or `pt-BR`
+    pub fn localize(&self, locale: &str) -> String {
+        Localizer::new(locale).format(self)
+    }
+}
+
+/// Text without a catalog entry
+impl From<String> for Message {
+    fn from(text: String) -> Self {
+        Message::untranslated(text)
+    }
+}
+
+/// The English text
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&Localizer::new(DEFAULT_LOCALE).format(self))
+    }
+}
+
+/// A message and its text in one locale, as returned over FFI
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LocalizedMessage {
+    pub code: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub args: BTreeMap<String, String>,
+    pub message: String,
+}
+
+/// Formats messages in one locale
+#[derive(Debug, Clone, Copy)]
+pub struct Localizer {
+    locale: &'static str,
+}
+
+impl Localizer {
+    /// The closest catalog to `locale`
+    ///
+    /// Accepts language tags (`fr`, `fr-CA`) and POSIX locales
+    /// (`fr_CA.UTF-8`); a region without its own catalog uses the
+    /// language's, and an unknown language uses English.
+    pub fn new(locale: &str) -> Self {
+        let tag = locale
+            .split(['.', '@'])
+            .next()
+            .unwrap_or_default()
+            .replace('_', "-")
+            .to_ascii_lowercase();
+        let language = tag.split('-').next().unwrap_or_default();
+        let locale = SUPPORTED_LOCALES
+            .iter()
+            .find(|supported| **supported == tag)
+            .or_else(|| SUPPORTED_LOCALES.iter().find(|s| **s == language))
+            .copied()
+            .unwrap_or(DEFAULT_LOCALE);
+        Self { locale }
+    }
+
+    /// The catalog this localizer uses
+    pub fn locale(&self) -> &'static str {
+        self.locale
+    }
+
+    /// The text for `message`, or its code when no catalog knows it
+    pub fn format(&self, message: &Message) -> String {
+        let Some(template) =
+            lookup(self.locale, &message.code).or_else(|| lookup(DEFAULT_LOCALE, &message.code))
+        else {
+            return message.code.clone();
+        };
+
+        // One pass, so values that look like placeholders stay as they are
+        let mut text = String::with_capacity(template.len());
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            text.push_str(&rest[..start]);
+            let after = &rest[start + 1..];
+            match after
+                .find('}')
+                .and_then(|end| Some((message.args.get(&after[..end])?, end)))
+            {
+                Some((value, end)) => {
+                    text.push_str(value);
+                    rest = &after[end + 1..];
+                }
+                None => {
+                    text.push('{');
+                    rest = after;
+                }
+            }
+        }
+        text.push_str(rest);
+        text
+    }
+
+    /// `message` with its text, for callers that want both
+    pub fn localize(&self, message: &Message) -> LocalizedMessage {
+        LocalizedMessage {
+            code: message.code.clone(),
+            args: message.args.clone(),
+            message: self.format(message),
+        }
+    }
+}
+
+impl Default for Localizer {
+    fn default() -> Self {
+        Self {
+            locale: DEFAULT_LOCALE,
+        }
+    }
+}
+
+fn lookup(locale: &str, code: &str) -> Option<&'static str> {
+    catalogs().get(locale)?.get(code).map(String::as_str)
+}
+
+/// Every catalog, with `[section] key` flattened to `section.key`
+fn catalogs() -> &'static HashMap<&'static str, HashMap<String, String>> {
+    static CATALOGS: OnceLock<HashMap<&'static str, HashMap<String, String>>> = OnceLock::new();
+    CATALOGS.get_or_init(|| {
+        CATALOG_SOURCES
+            .iter()
+            .map(|(locale, source)| {
+                let mut messages = HashMap::new();
+                match source.parse::<toml::Table>() {
+                    Ok(table) => flatten("", &table, &mut messages),
+                    Err(e) => warn!("Message catalog '{}' is invalid: {}", locale, e),
+                }
+                (*locale, messages)
+            })
+            .collect()
+    })
+}
+
+fn flatten(prefix: &str, table: &toml::Table, messages: &mut HashMap<String, String>) {
+    for (key, value) in table {
+        let code = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{}.{}", prefix, key)
+        };
+        match value {
+            toml::Value::String(text) => {
+                messages.insert(code, text.clone());
+            }
+            toml::Value::Table(section) => flatten(&code, section, messages),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn placeholders(text: &str) -> Vec<&str> {
+        let mut names: Vec<&str> = text
+            .split('{')
+            .skip(1)
+            .filter_map(|rest| rest.split_once('}').map(|(name, _)| name))
+            .collect();
+        names.sort_unstable();
+        names
+    }
+
+    #[test]
+    fn test_catalogs_match_english() {
+        let catalogs = catalogs();
+        let english = &catalogs[DEFAULT_LOCALE];
+        assert!(english.contains_key("validation.title_empty"));
+        for locale in SUPPORTED_LOCALES {
+            let catalog = &catalogs[locale];
+            assert!(!catalog.is_empty(), "{}", locale);
+            for (code, text) in catalog {
+                let reference = english
+                    .get(code)
+                    .unwrap_or_else(|| panic!("{}: unknown code {}", locale, code));
+                assert_eq!(
+                    placeholders(text),
+                    placeholders(reference),
+                    "{}: {}",
+                    locale,
+                    code
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_locale_resolution_and_formatting() {
+        assert_eq!(Localizer::new("de").locale(), "de");
+        assert_eq!(Localizer::new("fr_CA.UTF-8").locale(), "fr");
+        assert_eq!(Localizer::new("ES-mx").locale(), "es");
+        assert_eq!(Localizer::new("ja").locale(), "en");
+        assert_eq!(Localizer::new("").locale(), "en");
+
+        let message = Message::new("validation.invalid_email").with_arg("field", "Email");
+        assert_eq!(
+            message.to_string(),
+            "Field 'Email' is not a valid email address"
+        );
+        assert_eq!(
+            message.localize("de-AT"),
+            "„Email“ ist keine gültige E-Mail-Adresse"
+        );
+        assert_eq!(
+            Message::untranslated("{text} as is").localize("fr"),
+            "{text} as is"
+        );
+        assert_eq!(Message::new("no.such_code").localize("fr"), "no.such_code");
+
+        let localized = Localizer::new("es").localize(&message);
+        assert_eq!(localized.code, "validation.invalid_email");
+        assert_eq!(localized.args["field"], "Email");
+    }
+}
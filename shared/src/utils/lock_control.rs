@@ -0,0 +1,73 @@
+//! Locking the vault from the desktop session
+//!
+//! Screen lockers, window manager key bindings and status bar widgets need
+//! to lock ZipLock or show whether it is unlocked without holding an API
+//! token. The desktop app therefore serves a second socket next to the
+//! scripting API one, answering one [`LockCommand`] per line with an
+//! [`ApiResponse`](super::ApiResponse) line. It asks for no token: the state
+//! only says whether a vault is open, and locking can only take access away.
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use super::api_token::api_socket_path;
+
+/// Path of the socket the desktop app accepts lock commands on
+pub fn lock_socket_path() -> PathBuf {
+    api_socket_path().with_file_name("lock.sock")
+}
+
+/// One request line sent to the lock socket
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "method", rename_all = "snake_case")]
+pub enum LockCommand {
+    /// Report the [`LockStatus`]
+    Status,
+    /// Save pending changes, wipe the keys and lock every open vault, then
+    /// report the [`LockStatus`]
+    Lock,
+}
+
+/// Whether a vault is open
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VaultLockState {
+    Locked,
+    Unlocked,
+}
+
+impl VaultLockState {
+    /// Name used on the wire and printed by the command line
+    pub fn name(&self) -> &'static str {
+        match self {
+            VaultLockState::Locked => "locked",
+            VaultLockState::Unlocked => "unlocked",
+        }
+    }
+}
+
+/// The result of every [`LockCommand`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockStatus {
+    pub state: VaultLockState,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wire_format() {
+        let command: LockCommand = serde_json::from_str(r#"{"method":"lock"}"#).unwrap();
+        assert_eq!(command, LockCommand::Lock);
+        let status = LockStatus {
+            state: VaultLockState::Unlocked,
+        };
+        assert_eq!(
+            serde_json::to_string(&status).unwrap(),
+            r#"{"state":"unlocked"}"#
+        );
+        assert_eq!(lock_socket_path().parent(), api_socket_path().parent());
+    }
+}
@@ -0,0 +1,217 @@
+//! Versioned JSON documents for scripts
+//!
+//! The CLI's `--json` output, and anything else handing vault data to other
+//! programs, goes through these types rather than serializing internal
+//! structs, so a refactor of [`CredentialRecord`] does not silently break
+//! someone's shell pipeline. Every document starts with a `schema`
+//! identifier such as `ziplock.credential-list.v1`. Within a version fields
+//! are only ever added; renaming or removing one bumps the version.
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::{CredentialRecord, FieldType};
+use crate::utils::audit::SecurityAuditReport;
+
+/// Schema of [`CredentialList`]
+pub const CREDENTIAL_LIST_SCHEMA: &str = "ziplock.credential-list.v1";
+/// Schema of [`SearchResults`]
+pub const SEARCH_RESULTS_SCHEMA: &str = "ziplock.search-results.v1";
+/// Schema of [`CredentialDocument`]
+pub const CREDENTIAL_SCHEMA: &str = "ziplock.credential.v1";
+/// Schema of [`AuditDocument`]
+pub const AUDIT_REPORT_SCHEMA: &str = "ziplock.audit-report.v1";
+/// Schema of [`ErrorDocument`]
+pub const ERROR_SCHEMA: &str = "ziplock.error.v1";
+
+/// A credential without any field values except username and URL
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CredentialSummary {
+    pub id: String,
+    pub title: String,
+    pub credential_type: String,
+    pub username: Option<String>,
+    pub url: Option<String>,
+    pub folder: Option<String>,
+    pub tags: Vec<String>,
+    pub favorite: bool,
+    /// Whether a TOTP secret is stored
+    pub has_totp: bool,
+    /// Unix timestamps
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+impl From<&CredentialRecord> for CredentialSummary {
+    fn from(credential: &CredentialRecord) -> Self {
+        let value = |name: &str| {
+            credential
+                .get_field(name)
+                .filter(|field| !field.sensitive && !field.value.is_empty())
+                .map(|field| field.value.clone())
+        };
+        Self {
+            id: credential.id.clone(),
+            title: credential.title.clone(),
+            credential_type: credential.credential_type.clone(),
+            username: value("username"),
+            url: value("url"),
+            folder: credential.folder_path.clone(),
+            tags: credential.tags.clone(),
+            favorite: credential.favorite,
+            has_totp: credential
+                .fields
+                .values()
+                .any(|f| f.field_type == FieldType::TotpSecret && !f.value.is_empty()),
+            created_at: credential.created_at,
+            updated_at: credential.updated_at,
+        }
+    }
+}
+
+/// Every credential of a vault, in the order given
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CredentialList {
+    pub schema: String,
+    pub count: usize,
+    pub credentials: Vec<CredentialSummary>,
+}
+
+impl CredentialList {
+    pub fn new(credentials: &[CredentialRecord]) -> Self {
+        Self {
+            schema: CREDENTIAL_LIST_SCHEMA.to_string(),
+            count: credentials.len(),
+            credentials: credentials.iter().map(CredentialSummary::from).collect(),
+        }
+    }
+}
+
+/// Credentials matching a search query
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SearchResults {
+    pub schema: String,
+    pub query: String,
+    pub count: usize,
+    pub credentials: Vec<CredentialSummary>,
+}
+
+impl SearchResults {
+    pub fn new(query: impl Into<String>, credentials: &[CredentialRecord]) -> Self {
+        Self {
+            schema: SEARCH_RESULTS_SCHEMA.to_string(),
+            query: query.into(),
+            count: credentials.len(),
+            credentials: credentials.iter().map(CredentialSummary::from).collect(),
+        }
+    }
+}
+
+/// One full credential
+///
+/// Sensitive values are masked unless `revealed` is set.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CredentialDocument {
+    pub schema: String,
+    pub revealed: bool,
+    pub credential: CredentialRecord,
+}
+
+impl CredentialDocument {
+    pub fn new(credential: &CredentialRecord, reveal: bool) -> Self {
+        Self {
+            schema: CREDENTIAL_SCHEMA.to_string(),
+            revealed: reveal,
+            credential: if reveal {
+                credential.clone()
+            } else {
+                credential.sanitized()
+            },
+        }
+    }
+}
+
+/// A security audit with its totals up front
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditDocument {
+    pub schema: String,
+    pub issue_count: usize,
+    pub clean: bool,
+    pub report: SecurityAuditReport,
+}
+
+impl AuditDocument {
+    pub fn new(report: SecurityAuditReport) -> Self {
+        Self {
+            schema: AUDIT_REPORT_SCHEMA.to_string(),
+            issue_count: report.issue_count(),
+            clean: report.is_clean(),
+            report,
+        }
+    }
+}
+
+/// Why a command failed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErrorDocument {
+    pub schema: String,
+    /// Stable snake_case code, e.g. `wrong_password`
+    pub error: String,
+    /// Human-readable description; wording may change between releases
+    pub message: String,
+}
+
+impl ErrorDocument {
+    pub fn new(error: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            schema: ERROR_SCHEMA.to_string(),
+            error: error.into(),
+            message: message.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::CredentialField;
+    use crate::utils::audit::SecurityAuditor;
+
+    fn login() -> CredentialRecord {
+        let mut credential = CredentialRecord::new("Bank".to_string(), "login".to_string());
+        credential.set_field("username", CredentialField::username("jane"));
+        credential.set_field("password", CredentialField::password("s3cret"));
+        credential.set_field("totp", CredentialField::totp_secret("JBSWY3DPEHPK3PXP"));
+        credential.add_tag("finance");
+        credential
+    }
+
+    #[test]
+    fn test_documents_carry_schema_and_hide_secrets() {
+        let credentials = vec![login()];
+
+        let list = serde_json::to_value(CredentialList::new(&credentials)).unwrap();
+        assert_eq!(list["schema"], CREDENTIAL_LIST_SCHEMA);
+        assert_eq!(list["count"], 1);
+        let summary = &list["credentials"][0];
+        assert_eq!(summary["username"], "jane");
+        assert_eq!(summary["has_totp"], true);
+        assert!(summary["url"].is_null());
+        assert!(!list.to_string().contains("s3cret"));
+
+        let found = SearchResults::new("bank", &credentials);
+        assert_eq!(found.schema, SEARCH_RESULTS_SCHEMA);
+        assert_eq!(found.credentials[0].tags, vec!["finance".to_string()]);
+
+        let masked = serde_json::to_string(&CredentialDocument::new(&credentials[0], false));
+        assert!(!masked.unwrap().contains("s3cret"));
+        let revealed = serde_json::to_string(&CredentialDocument::new(&credentials[0], true));
+        assert!(revealed.unwrap().contains("s3cret"));
+
+        let audit = AuditDocument::new(SecurityAuditor::default().audit(&credentials));
+        assert_eq!(audit.schema, AUDIT_REPORT_SCHEMA);
+        assert_eq!(audit.clean, audit.issue_count == 0);
+        let round_trip: AuditDocument =
+            serde_json::from_str(&serde_json::to_string(&audit).unwrap()).unwrap();
+        assert_eq!(round_trip, audit);
+    }
+}
@@ -0,0 +1,376 @@
+//! A small Markdown reader for previewing notes
+//!
+//! Notes are short documents, not web pages, so only the common subset is
+//! understood: ATX headings, paragraphs, bullet, numbered and task lists,
+//! block quotes, fenced code blocks and horizontal rules, with strong and
+//! emphasized text, inline code and links inside them. HTML is never
+//! interpreted; it shows as the text it is. Front ends turn the blocks into
+//! widgets of their own.
+
+/// A run of text with one style
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InlineSpan {
+    pub text: String,
+    pub strong: bool,
+    pub emphasis: bool,
+    pub code: bool,
+    /// Target of a link
+    pub link: Option<String>,
+}
+
+impl InlineSpan {
+    fn plain(text: String) -> Self {
+        Self {
+            text,
+            ..Self::default()
+        }
+    }
+}
+
+/// A block of a Markdown document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MarkdownBlock {
+    /// Heading of level 1 to 6
+    Heading {
+        level: u8,
+        spans: Vec<InlineSpan>,
+    },
+    Paragraph(Vec<InlineSpan>),
+    /// An item of a list; `depth` counts nesting from 0, `number` is set for
+    /// numbered lists and `checked` for task list items
+    ListItem {
+        depth: usize,
+        number: Option<u32>,
+        checked: Option<bool>,
+        spans: Vec<InlineSpan>,
+    },
+    Quote(Vec<InlineSpan>),
+    CodeBlock {
+        language: Option<String>,
+        code: String,
+    },
+    Rule,
+}
+
+/// Split `text` into blocks
+pub fn parse_markdown(text: &str) -> Vec<MarkdownBlock> {
+    let mut blocks = Vec::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+    let mut quote: Vec<&str> = Vec::new();
+    let mut lines = text.lines();
+
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim();
+        let is_quote = trimmed.starts_with('>');
+        if !is_quote && !quote.is_empty() {
+            blocks.push(MarkdownBlock::Quote(parse_inline(&quote.join(" "))));
+            quote.clear();
+        }
+
+        if trimmed.is_empty() {
+            flush_paragraph(&mut paragraph, &mut blocks);
+        } else if let Some(fence) = ["```", "~~~"].into_iter().find(|f| trimmed.starts_with(f)) {
+            flush_paragraph(&mut paragraph, &mut blocks);
+            let language = trimmed[fence.len()..].trim();
+            let mut code = Vec::new();
+            for line in lines.by_ref() {
+                if line.trim_start().starts_with(fence) {
+                    break;
+                }
+                code.push(line);
+            }
+            blocks.push(MarkdownBlock::CodeBlock {
+                language: (!language.is_empty()).then(|| language.to_string()),
+                code: code.join("\n"),
+            });
+        } else if is_quote {
+            flush_paragraph(&mut paragraph, &mut blocks);
+            let content = trimmed[1..].trim();
+            if !content.is_empty() {
+                quote.push(content);
+            }
+        } else if let Some((level, heading)) = heading(trimmed) {
+            flush_paragraph(&mut paragraph, &mut blocks);
+            blocks.push(MarkdownBlock::Heading {
+                level,
+                spans: parse_inline(heading),
+            });
+        } else if is_rule(trimmed) {
+            flush_paragraph(&mut paragraph, &mut blocks);
+            blocks.push(MarkdownBlock::Rule);
+        } else if let Some(item) = list_item(line) {
+            flush_paragraph(&mut paragraph, &mut blocks);
+            blocks.push(item);
+        } else {
+            paragraph.push(trimmed);
+        }
+    }
+    flush_paragraph(&mut paragraph, &mut blocks);
+    if !quote.is_empty() {
+        blocks.push(MarkdownBlock::Quote(parse_inline(&quote.join(" "))));
+    }
+    blocks
+}
+
+fn flush_paragraph(lines: &mut Vec<&str>, blocks: &mut Vec<MarkdownBlock>) {
+    if !lines.is_empty() {
+        blocks.push(MarkdownBlock::Paragraph(parse_inline(&lines.join(" "))));
+        lines.clear();
+    }
+}
+
+fn heading(line: &str) -> Option<(u8, &str)> {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    let rest = &line[level..];
+    if !(1..=6).contains(&level) || !(rest.is_empty() || rest.starts_with(' ')) {
+        return None;
+    }
+    Some((level as u8, rest.trim().trim_end_matches('#').trim_end()))
+}
+
+fn is_rule(line: &str) -> bool {
+    let marks: String = line.chars().filter(|c| !c.is_whitespace()).collect();
+    marks.len() >= 3
+        && ['-', '*', '_']
+            .iter()
+            .any(|&mark| marks.chars().all(|c| c == mark))
+}
+
+fn list_item(line: &str) -> Option<MarkdownBlock> {
+    let indent: usize = line
+        .chars()
+        .take_while(|c| c.is_whitespace())
+        .map(|c| if c == '\t' { 4 } else { 1 })
+        .sum();
+    let line = line.trim_start();
+
+    let (number, rest) = if let Some(rest) = ["- ", "* ", "+ "]
+        .iter()
+        .find_map(|bullet| line.strip_prefix(bullet))
+    {
+        (None, rest)
+    } else {
+        let digits = line.chars().take_while(char::is_ascii_digit).count();
+        let rest = line[digits..]
+            .strip_prefix(". ")
+            .or_else(|| line[digits..].strip_prefix(") "))?;
+        (Some(line[..digits].parse().ok()?), rest)
+    };
+
+    let (checked, rest) = match rest.get(..4) {
+        Some("[ ] ") => (Some(false), &rest[4..]),
+        Some("[x] ") | Some("[X] ") => (Some(true), &rest[4..]),
+        _ => (None, rest),
+    };
+    Some(MarkdownBlock::ListItem {
+        depth: indent / 2,
+        number,
+        checked,
+        spans: parse_inline(rest.trim()),
+    })
+}
+
+/// Split a line of text into styled runs
+pub fn parse_inline(text: &str) -> Vec<InlineSpan> {
+    let mut spans = Vec::new();
+    let mut current = String::new();
+    let mut strong = false;
+    let mut emphasis = false;
+    let chars: Vec<char> = text.chars().collect();
+    let mut i = 0;
+
+    let flush = |current: &mut String, spans: &mut Vec<InlineSpan>, strong, emphasis| {
+        if !current.is_empty() {
+            spans.push(InlineSpan {
+                text: std::mem::take(current),
+                strong,
+                emphasis,
+                ..InlineSpan::default()
+            });
+        }
+    };
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        match c {
+            '\\' if next.is_some_and(|n| n.is_ascii_punctuation()) => {
+                current.push(chars[i + 1]);
+                i += 2;
+            }
+            '`' => match find(&chars, i + 1, &['`']) {
+                Some(end) => {
+                    flush(&mut current, &mut spans, strong, emphasis);
+                    spans.push(InlineSpan {
+                        text: chars[i + 1..end].iter().collect(),
+                        code: true,
+                        ..InlineSpan::default()
+                    });
+                    i = end + 1;
+                }
+                None => {
+                    current.push(c);
+                    i += 1;
+                }
+            },
+            '*' | '_' if next == Some(c) && (strong || closes(&chars, i + 2, &[c, c])) => {
+                flush(&mut current, &mut spans, strong, emphasis);
+                strong = !strong;
+                i += 2;
+            }
+            '*' | '_' if emphasis || (opens(&chars, i) && closes(&chars, i + 1, &[c])) => {
+                flush(&mut current, &mut spans, strong, emphasis);
+                emphasis = !emphasis;
+                i += 1;
+            }
+            '[' => match link(&chars, i) {
+                Some((label, url, end)) => {
+                    flush(&mut current, &mut spans, strong, emphasis);
+                    spans.push(InlineSpan {
+                        text: label,
+                        strong,
+                        emphasis,
+                        link: Some(url),
+                        ..InlineSpan::default()
+                    });
+                    i = end;
+                }
+                None => {
+                    current.push(c);
+                    i += 1;
+                }
+            },
+            _ => {
+                current.push(c);
+                i += 1;
+            }
+        }
+    }
+    flush(&mut current, &mut spans, strong, emphasis);
+    if spans.is_empty() {
+        spans.push(InlineSpan::plain(String::new()));
+    }
+    spans
+}
+
+/// Whether the marker at `at` can open emphasis: `_` only at the start of
+/// a word, so `snake_case` stays as it is, and neither before a space
+fn opens(chars: &[char], at: usize) -> bool {
+    let after_word = at > 0 && chars[at - 1].is_alphanumeric();
+    let before_space = chars.get(at + 1).is_none_or(|c| c.is_whitespace());
+    !before_space && (chars[at] == '*' || !after_word)
+}
+
+/// Whether `marker` occurs at or after `from`
+fn closes(chars: &[char], from: usize, marker: &[char]) -> bool {
+    chars
+        .get(from..)
+        .is_some_and(|rest| rest.windows(marker.len()).any(|w| w == marker))
+}
+
+fn find(chars: &[char], from: usize, targets: &[char]) -> Option<usize> {
+    (from..chars.len()).find(|&i| targets.contains(&chars[i]))
+}
+
+/// `[label](url)` starting at `start`: the label, the URL and the index
+/// after the closing parenthesis
+fn link(chars: &[char], start: usize) -> Option<(String, String, usize)> {
+    let close = find(chars, start + 1, &[']'])?;
+    if chars.get(close + 1) != Some(&'(') {
+        return None;
+    }
+    let end = find(chars, close + 2, &[')'])?;
+    let url: String = chars[close + 2..end].iter().collect();
+    if url.trim().is_empty() || url.contains(' ') {
+        return None;
+    }
+    Some((chars[start + 1..close].iter().collect(), url, end + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(spans: &[InlineSpan]) -> String {
+        spans.iter().map(|span| span.text.as_str()).collect()
+    }
+
+    #[test]
+    fn test_blocks() {
+        let note = "# Server room\n\
+                    Door code is\nchanged monthly.\n\n\
+                    - [x] Badge\n  - Spare key\n2. Alarm\n\n\
+                    > Ask facilities\n> first\n\
+                    ---\n\
+                    ```sh\nssh admin@10.0.0.2\n```\n\
+                    #hashtag";
+        let blocks = parse_markdown(note);
+        assert_eq!(blocks.len(), 9);
+        assert!(
+            matches!(&blocks[0], MarkdownBlock::Heading { level: 1, spans } if text(spans) == "Server room")
+        );
+        assert!(
+            matches!(&blocks[1], MarkdownBlock::Paragraph(spans) if text(spans) == "Door code is changed monthly.")
+        );
+        assert!(matches!(
+            &blocks[2],
+            MarkdownBlock::ListItem {
+                depth: 0,
+                number: None,
+                checked: Some(true),
+                ..
+            }
+        ));
+        assert!(matches!(
+            &blocks[3],
+            MarkdownBlock::ListItem { depth: 1, .. }
+        ));
+        assert!(matches!(
+            &blocks[4],
+            MarkdownBlock::ListItem {
+                number: Some(2),
+                ..
+            }
+        ));
+        assert!(
+            matches!(&blocks[5], MarkdownBlock::Quote(spans) if text(spans) == "Ask facilities first")
+        );
+        assert_eq!(blocks[6], MarkdownBlock::Rule);
+        assert_eq!(
+            blocks[7],
+            MarkdownBlock::CodeBlock {
+                language: Some("sh".to_string()),
+                code: "ssh admin@10.0.0.2".to_string()
+            }
+        );
+        assert!(matches!(&blocks[8], MarkdownBlock::Paragraph(spans) if text(spans) == "#hashtag"));
+    }
+
+    #[test]
+    fn test_inline_styles() {
+        let spans =
+            parse_inline("PIN **4821**, *not* `0000`; see [wiki](https://wiki.example) \\*");
+        let styled: Vec<(&str, bool, bool, bool)> = spans
+            .iter()
+            .map(|s| (s.text.as_str(), s.strong, s.emphasis, s.code))
+            .collect();
+        assert_eq!(
+            styled,
+            vec![
+                ("PIN ", false, false, false),
+                ("4821", true, false, false),
+                (", ", false, false, false),
+                ("not", false, true, false),
+                (" ", false, false, false),
+                ("0000", false, false, true),
+                ("; see ", false, false, false),
+                ("wiki", false, false, false),
+                (" *", false, false, false),
+            ]
+        );
+        assert_eq!(spans[7].link.as_deref(), Some("https://wiki.example"));
+        assert_eq!(text(&parse_inline("snake_case_name")), "snake_case_name");
+        assert!(parse_inline("5 * 3 = 15").iter().all(|s| !s.emphasis));
+        assert_eq!(text(&parse_inline("[not a link] (x)")), "[not a link] (x)");
+    }
+}
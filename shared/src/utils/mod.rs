@@ -4,30 +4,138 @@
 //! the ZipLock shared library, including TOTP generation, YAML serialization,
 //! validation, and search functionality.
 
+pub mod api_approval;
+pub mod api_token;
+pub mod audit;
+pub mod autofill;
+pub mod automation;
 pub mod backup;
+pub mod backup_scheduler;
+pub mod bitwarden;
+pub mod breach;
+pub mod clip_bridge;
+pub mod csv;
 pub mod encryption;
+pub mod health;
+pub mod html_viewer;
+pub mod import;
+pub mod localization;
+pub mod lock_control;
+pub mod machine_output;
+pub mod markdown;
+pub mod offsite_backup;
 pub mod password;
+pub mod password_store;
+pub mod pgp;
+pub mod qr;
+pub mod reminders;
 pub mod search;
+pub mod search_index;
+pub mod secret_scan;
+pub mod secure_store;
+pub mod sharing;
+pub mod ssh_agent;
+pub mod strength;
 pub mod totp;
+pub mod url_match;
 pub mod validation;
+pub mod watch;
+pub mod widget;
 pub mod yaml;
 
 // Re-export commonly used items for convenience
+pub use api_approval::{
+    ApprovalDecision, ApprovalGrant, ApprovalPolicy, ApprovalPurpose, ApprovalRequest,
+};
+pub use api_token::{
+    api_socket_path, ApiCredentialSummary, ApiErrorCode, ApiEvent, ApiMethod, ApiRequest,
+    ApiResponse, ApiScope, ApiSession, ApiTokenError, ApiTokenRegistry, ApiTokenResult,
+    IssuedToken,
+};
+pub use audit::{
+    AuditOptions, BreachCheckMode, BreachedPassword, ComplianceEntry, ComplianceReport,
+    MissingTotp, OldPassword, RecycledPassword, ReusedPassword, ReviewStatus, SecurityAuditReport,
+    SecurityAuditor, WeakPassword,
+};
+pub use autofill::{
+    autofill_candidates, AutofillCandidate, AutofillDataset, AutofillTarget, AutofillValue,
+    ANDROID_APP_SCHEME,
+};
+#[cfg(feature = "automation")]
+pub use automation::AutomationEngine;
+pub use automation::{
+    AutomationError, AutomationEvent, AutomationResult, AutomationScript, HookOutcome,
+    ScriptMessage,
+};
 pub use backup::{
     BackupData, BackupManager, BackupMetadata, BackupStats, ExportFormat, ExportOptions,
     MigrationManager,
 };
+pub use backup_scheduler::{BackupEntry, BackupOutcome, BackupScheduler, RotationPolicy};
+pub use bitwarden::BitwardenExporter;
+pub use breach::{BloomFilter, BreachChecker, BreachError, BreachResult, BreachStatus};
+pub use clip_bridge::{
+    ClipError, ClipInbox, ClipItem, ClipKind, ClipMessage, ClipResult, DEFAULT_CLIP_TTL_SECS,
+    MAX_CLIP_TTL_SECS,
+};
+pub use csv::{CsvColumnMapping, CsvExporter, CsvImporter, CsvMapping, CsvPreset, CsvTarget};
 pub use encryption::{
-    CredentialCrypto, EncryptedData, EncryptionError, EncryptionResult, EncryptionUtils,
-    SecureMemory, SecureString,
+    CipherAlgorithm, CredentialCrypto, CryptoParams, EncryptedData, EncryptionError,
+    EncryptionResult, EncryptionUtils, FreshNonce, KdfAlgorithm, SecretBytes, SecureMemory,
+    SecureString,
+};
+pub use health::{
+    HealthCategory, HealthCheckItem, HealthComponent, HealthPriority, VaultHealth,
+    VaultHealthAnalyzer,
+};
+pub use html_viewer::{HtmlViewerExporter, ViewerDocument, ViewerEntry, ViewerField};
+pub use import::{
+    ApplePasswordsImporter, BitwardenImporter, DashlaneImporter, EnpassImporter, ImportDuplicate,
+    ImportReport, ImportRowError, ImportSource, Importer, OnePasswordImporter, ProtonPassImporter,
 };
+pub use localization::{LocalizedMessage, Localizer, Message, SUPPORTED_LOCALES};
+pub use lock_control::{lock_socket_path, LockCommand, LockStatus, VaultLockState};
+pub use machine_output::{
+    AuditDocument, CredentialDocument, CredentialList, CredentialSummary, ErrorDocument,
+    SearchResults,
+};
+#[cfg(not(target_arch = "wasm32"))]
+pub use markdown::{parse_markdown, InlineSpan, MarkdownBlock};
+#[cfg(not(target_arch = "wasm32"))]
+pub use offsite_backup::WebDavDestination;
+pub use offsite_backup::{BackupDestination, OffsiteUploader, SftpDestination, UploadOutcome};
 pub use password::{
-    PasswordAnalysis, PasswordAnalyzer, PasswordGenerator, PasswordOptions, PasswordStrength,
-    PasswordUtils,
+    Capitalization, GeneratedPassphrase, PassphraseOptions, PasswordAnalysis, PasswordAnalyzer,
+    PasswordGenerator, PasswordOptions, PasswordStrength, PasswordUtils, Wordlist,
 };
+pub use password_store::{GpgCommand, PassCrypto, PasswordStoreExporter, PasswordStoreImporter};
+pub use pgp::{armored_block, PgpExportParts, PgpKeyExporter};
+pub use qr::{QrCode, QrError, QrResult};
+pub use reminders::{Reminder, ReminderEngine, ReminderKind, ReminderStatus};
 pub use search::{CredentialSearchEngine, SearchQuery, SearchResult};
-pub use totp::{format_totp_secret, generate_totp, validate_totp_secret};
+pub use search_index::{IndexedField, ParsedSearch, SearchHit, SearchIndex};
+pub use secret_scan::{ExposedSecret, SecretKind, SecretScanner};
+pub use secure_store::SecureStore;
+pub use sharing::{
+    CredentialSharer, FileViewLedger, MemoryViewLedger, ShareIdentity, SharePayload, SharePolicy,
+    ShareRecipient, ShareViewLedger, SharedVault, SharedVaultPackage, SharingError, SharingResult,
+    SHARED_VAULT_EXTENSION,
+};
+pub use ssh_agent::{
+    ssh_agent_socket_path, AgentRequest, AgentResponse, SshAgentError, SshAgentKey, SshAgentResult,
+};
+pub use totp::{
+    format_totp_secret, generate_totp, validate_totp_secret, TotpAlgorithm, TotpEncoding,
+    TotpGenerator,
+};
+pub use url_match::{
+    registrable_domain, UrlMatch, UrlMatchMode, UrlMatcher, URL_MATCH_METADATA_KEY,
+};
 pub use validation::{validate_credential, validate_field, ValidationResult};
+pub use watch::{
+    WatchAuthorizer, WatchError, WatchPairing, WatchRequest, WatchResponse, WatchResult,
+};
+pub use widget::{WidgetCode, WidgetEntry, WidgetError, WidgetKey, WidgetResult, WidgetSnapshot};
 pub use yaml::{
     deserialize_credential, deserialize_file_map, serialize_credential, serialize_file_map,
 };
@@ -122,7 +230,7 @@ pub mod string_utils {
 
 /// Utility functions for working with time
 pub mod time_utils {
-    use chrono::{TimeZone, Utc};
+    use chrono::{NaiveDate, TimeZone, Utc};
 
     /// Format a Unix timestamp for display
     pub fn format_timestamp(timestamp: i64) -> String {
@@ -139,6 +247,12 @@ pub mod time_utils {
         Utc::now().timestamp()
     }
 
+    /// Parse a `YYYY-MM-DD` date as the Unix timestamp of its start, in UTC
+    pub fn parse_date(date: &str) -> Option<i64> {
+        let date = NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d").ok()?;
+        Some(date.and_hms_opt(0, 0, 0)?.and_utc().timestamp())
+    }
+
     /// Format duration in human-readable form
     pub fn format_duration_since(timestamp: i64) -> String {
         let now = current_timestamp();
@@ -190,6 +304,13 @@ pub mod time_utils {
             assert!(formatted.contains("00:00:00"));
         }
 
+        #[test]
+        fn test_parse_date() {
+            assert_eq!(parse_date("2022-01-01"), Some(1640995200));
+            assert_eq!(parse_date("2022-02-30"), None);
+            assert_eq!(parse_date("01/01/2022"), None);
+        }
+
         #[test]
         fn test_current_timestamp() {
             let timestamp = current_timestamp();
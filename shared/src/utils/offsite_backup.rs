@@ -0,0 +1,707 @@
+//! Off-site backup uploads
+//!
+//! Copies the repository archive to a remote destination and rotates old
+//! copies. The archive is already encrypted with the master password, so it is
+//! uploaded unchanged. SFTP goes through the system `sftp` client, which
+//! picks up the user's SSH keys and agent; WebDAV talks HTTP directly.
+
+use crate::config::{BackupDestinationConfig, OffsiteBackupConfig};
+use crate::core::{CoreError, CoreResult, FileError};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Duration;
+
+/// Prefix of every backup file this module creates; rotation ignores other files
+pub const BACKUP_PREFIX: &str = "ziplock-backup-";
+
+/// Extension of uploaded backups
+pub const BACKUP_EXTENSION: &str = ".7z";
+
+/// A remote location that can store backup files
+pub trait BackupDestination: Send + Sync {
+    /// Where backups go, for status messages (never includes secrets)
+    fn describe(&self) -> String;
+
+    /// Store `data` under `name`, replacing nothing
+    fn upload(&self, name: &str, data: &[u8]) -> CoreResult<()>;
+
+    /// Names of the files currently stored at the destination
+    fn list(&self) -> CoreResult<Vec<String>>;
+
+    /// Remove a stored file
+    fn delete(&self, name: &str) -> CoreResult<()>;
+}
+
+/// Result of a completed upload
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UploadOutcome {
+    /// Destination description
+    pub destination: String,
+    /// Remote name of the new backup
+    pub file_name: String,
+    /// Old backups removed by rotation
+    pub removed: Vec<String>,
+    /// Rotation failure; the upload itself still succeeded
+    pub rotation_error: Option<String>,
+}
+
+/// Uploads backups to a destination and keeps the newest `keep` copies
+pub struct OffsiteUploader {
+    destination: Box<dyn BackupDestination>,
+    keep: usize,
+}
+
+impl OffsiteUploader {
+    /// Create an uploader; `keep == 0` disables rotation
+    pub fn new(destination: Box<dyn BackupDestination>, keep: usize) -> Self {
+        Self { destination, keep }
+    }
+
+    /// Build an uploader from settings
+    ///
+    /// `password` is only used by WebDAV destinations.
+    pub fn from_config(config: &OffsiteBackupConfig, password: Option<String>) -> CoreResult<Self> {
+        let destination: Box<dyn BackupDestination> = match &config.destination {
+            Some(BackupDestinationConfig::Sftp {
+                host,
+                port,
+                username,
+                remote_dir,
+                identity_file,
+            }) => {
+                let mut sftp = SftpDestination::new(host, username, remote_dir).with_port(*port);
+                if let Some(identity) = identity_file {
+                    sftp = sftp.with_identity_file(identity);
+                }
+                Box::new(sftp)
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            Some(BackupDestinationConfig::WebDav { url, username, .. }) => Box::new(
+                WebDavDestination::new(url, username, password.unwrap_or_default())?,
+            ),
+            #[cfg(target_arch = "wasm32")]
+            Some(BackupDestinationConfig::WebDav { .. }) => {
+                drop(password);
+                return Err(CoreError::ValidationError {
+                    message: "WebDAV backups are not available in the browser".to_string(),
+                });
+            }
+            None => {
+                return Err(CoreError::ValidationError {
+                    message: "No off-site backup destination configured".to_string(),
+                })
+            }
+        };
+
+        Ok(Self::new(destination, config.keep as usize))
+    }
+
+    /// Remote file name for a backup taken at `at`
+    ///
+    /// Names sort chronologically, which is what rotation relies on.
+    pub fn backup_name(at: DateTime<Utc>) -> String {
+        format!(
+            "{}{}{}",
+            BACKUP_PREFIX,
+            at.format("%Y%m%dT%H%M%SZ"),
+            BACKUP_EXTENSION
+        )
+    }
+
+    /// Upload an archive file and rotate old copies
+    pub fn upload_file(&self, archive: &Path, at: DateTime<Utc>) -> CoreResult<UploadOutcome> {
+        let data = std::fs::read(archive).map_err(|e| {
+            io_error(format!(
+                "Failed to read archive {}: {}",
+                archive.display(),
+                e
+            ))
+        })?;
+        self.upload(&data, at)
+    }
+
+    /// Upload archive bytes and rotate old copies
+    pub fn upload(&self, data: &[u8], at: DateTime<Utc>) -> CoreResult<UploadOutcome> {
+        let file_name = Self::backup_name(at);
+        self.destination.upload(&file_name, data)?;
+
+        let (removed, rotation_error) = match self.rotate() {
+            Ok(removed) => (removed, None),
+            Err(e) => (Vec::new(), Some(e.to_string())),
+        };
+
+        Ok(UploadOutcome {
+            destination: self.destination.describe(),
+            file_name,
+            removed,
+            rotation_error,
+        })
+    }
+
+    /// Delete all but the newest `keep` backups
+    pub fn rotate(&self) -> CoreResult<Vec<String>> {
+        if self.keep == 0 {
+            return Ok(Vec::new());
+        }
+
+        let mut backups: Vec<String> = self
+            .destination
+            .list()?
+            .into_iter()
+            .filter(|name| name.starts_with(BACKUP_PREFIX) && name.ends_with(BACKUP_EXTENSION))
+            .collect();
+        backups.sort();
+
+        let excess = backups.len().saturating_sub(self.keep);
+        let mut removed = Vec::new();
+        for name in backups.into_iter().take(excess) {
+            self.destination.delete(&name)?;
+            removed.push(name);
+        }
+
+        Ok(removed)
+    }
+}
+
+/// Upload over SFTP with the system OpenSSH client
+///
+/// Runs `sftp` in batch mode, so authentication must not prompt: use a key
+/// loaded in the SSH agent or an unencrypted deploy key.
+#[derive(Debug, Clone)]
+pub struct SftpDestination {
+    program: String,
+    host: String,
+    port: u16,
+    username: String,
+    remote_dir: String,
+    identity_file: Option<PathBuf>,
+}
+
+impl SftpDestination {
+    /// Create a destination for `username@host:remote_dir`
+    pub fn new(host: &str, username: &str, remote_dir: &str) -> Self {
+        Self {
+            program: "sftp".to_string(),
+            host: host.to_string(),
+            port: 22,
+            username: username.to_string(),
+            remote_dir: remote_dir.trim_end_matches('/').to_string(),
+            identity_file: None,
+        }
+    }
+
+    /// Connect to a non-standard port
+    pub fn with_port(mut self, port: u16) -> Self {
+        self.port = port;
+        self
+    }
+
+    /// Authenticate with a specific private key
+    pub fn with_identity_file<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.identity_file = Some(path.into());
+        self
+    }
+
+    /// Use a different `sftp` executable
+    pub fn with_program<S: Into<String>>(mut self, program: S) -> Self {
+        self.program = program.into();
+        self
+    }
+
+    fn remote_path(&self, name: &str) -> String {
+        if self.remote_dir.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}/{}", self.remote_dir, name)
+        }
+    }
+
+    /// `username@host`, refusing values `sftp` or `ssh` would read as options
+    fn target(&self) -> CoreResult<String> {
+        for (what, value) in [("host", &self.host), ("username", &self.username)] {
+            if value.is_empty() || value.starts_with('-') || value.chars().any(char::is_control) {
+                return Err(CoreError::ValidationError {
+                    message: format!("Invalid SFTP {}: {:?}", what, value),
+                });
+            }
+        }
+        Ok(format!("{}@{}", self.username, self.host))
+    }
+
+    /// Run a batch script, returning stdout
+    fn run(&self, script: &str) -> CoreResult<String> {
+        let mut command = Command::new(&self.program);
+        command
+            .args(["-b", "-", "-o", "BatchMode=yes", "-P"])
+            .arg(self.port.to_string());
+        if let Some(identity) = &self.identity_file {
+            command.arg("-i").arg(identity);
+        }
+        command
+            .arg("--")
+            .arg(self.target()?)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+
+        let mut child = command
+            .spawn()
+            .map_err(|e| io_error(format!("Failed to run {}: {}", self.program, e)))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin
+                .write_all(script.as_bytes())
+                .map_err(|e| io_error(format!("Failed to send sftp commands: {}", e)))?;
+        }
+
+        let output = child
+            .wait_with_output()
+            .map_err(|e| io_error(format!("Failed to run {}: {}", self.program, e)))?;
+        if !output.status.success() {
+            return Err(io_error(format!(
+                "sftp to {} failed: {}",
+                self.host,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+}
+
+impl BackupDestination for SftpDestination {
+    fn describe(&self) -> String {
+        format!(
+            "sftp://{}@{}:{}/{}",
+            self.username,
+            self.host,
+            self.port,
+            self.remote_dir.trim_start_matches('/')
+        )
+    }
+
+    fn upload(&self, name: &str, data: &[u8]) -> CoreResult<()> {
+        let mut local = tempfile::NamedTempFile::new().map_err(FileError::from)?;
+        local
+            .write_all(data)
+            .and_then(|_| local.flush())
+            .map_err(FileError::from)?;
+
+        // Upload under a temporary name so a dropped connection never leaves
+        // a truncated file that looks like a complete backup
+        let target = self.remote_path(name);
+        let partial = format!("{}.part", target);
+        let script = format!(
+            "put {} {}\nrename {} {}\n",
+            sftp_quote(&local.path().to_string_lossy())?,
+            sftp_quote(&partial)?,
+            sftp_quote(&partial)?,
+            sftp_quote(&target)?
+        );
+        self.run(&script).map(|_| ())
+    }
+
+    fn list(&self) -> CoreResult<Vec<String>> {
+        let dir = if self.remote_dir.is_empty() {
+            "."
+        } else {
+            &self.remote_dir
+        };
+        let output = self.run(&format!("ls -1 {}\n", sftp_quote(dir)?))?;
+        Ok(parse_sftp_listing(&output))
+    }
+
+    fn delete(&self, name: &str) -> CoreResult<()> {
+        self.run(&format!("rm {}\n", sftp_quote(&self.remote_path(name))?))
+            .map(|_| ())
+    }
+}
+
+/// Quote a path for an sftp batch file
+///
+/// Each line of the batch file is a command, so a path with a line break
+/// or any other control character is refused rather than quoted.
+fn sftp_quote(path: &str) -> CoreResult<String> {
+    if path.chars().any(char::is_control) {
+        return Err(CoreError::ValidationError {
+            message: format!("SFTP path contains a control character: {:?}", path),
+        });
+    }
+    Ok(format!(
+        "\"{}\"",
+        path.replace('\\', "\\\\").replace('"', "\\\"")
+    ))
+}
+
+/// File names from `ls -1` output; batch mode echoes each command as `sftp> ...`
+fn parse_sftp_listing(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with("sftp>"))
+        .filter_map(|line| line.rsplit('/').next())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Upload to a WebDAV collection with HTTP basic authentication
+#[cfg(not(target_arch = "wasm32"))]
+pub struct WebDavDestination {
+    client: reqwest::blocking::Client,
+    base: url::Url,
+    username: String,
+    password: String,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl WebDavDestination {
+    /// Create a destination for the collection at `url`
+    pub fn new(url: &str, username: &str, password: String) -> CoreResult<Self> {
+        let mut base = url::Url::parse(url).map_err(|e| CoreError::ValidationError {
+            message: format!("Invalid WebDAV URL '{}': {}", url, e),
+        })?;
+        if !matches!(base.scheme(), "http" | "https") {
+            return Err(CoreError::ValidationError {
+                message: format!("WebDAV URL must use http or https: {}", url),
+            });
+        }
+        if !base.path().ends_with('/') {
+            base.set_path(&format!("{}/", base.path()));
+        }
+
+        let client = reqwest::blocking::Client::builder()
+            .timeout(Duration::from_secs(300))
+            .build()
+            .map_err(|e| io_error(format!("Failed to create HTTP client: {}", e)))?;
+
+        Ok(Self {
+            client,
+            base,
+            username: username.to_string(),
+            password,
+        })
+    }
+
+    fn file_url(&self, name: &str) -> CoreResult<url::Url> {
+        self.base
+            .join(name)
+            .map_err(|e| CoreError::ValidationError {
+                message: format!("Invalid backup name '{}': {}", name, e),
+            })
+    }
+
+    fn send(
+        &self,
+        method: &str,
+        url: url::Url,
+        body: Option<Vec<u8>>,
+    ) -> CoreResult<reqwest::blocking::Response> {
+        let method = reqwest::Method::from_bytes(method.as_bytes())
+            .map_err(|e| io_error(format!("Invalid HTTP method {}: {}", method, e)))?;
+        let mut request = self
+            .client
+            .request(method.clone(), url)
+            .basic_auth(&self.username, Some(&self.password));
+        if method.as_str() == "PROPFIND" {
+            request = request
+                .header("Depth", "1")
+                .header("Content-Type", "application/xml");
+        }
+        if let Some(body) = body {
+            request = request.body(body);
+        }
+
+        request
+            .send()
+            .map_err(|e| io_error(format!("WebDAV {} failed: {}", method, e)))
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl BackupDestination for WebDavDestination {
+    fn describe(&self) -> String {
+        let mut url = self.base.clone();
+        let _ = url.set_username("");
+        let _ = url.set_password(None);
+        url.to_string()
+    }
+
+    fn upload(&self, name: &str, data: &[u8]) -> CoreResult<()> {
+        let url = self.file_url(name)?;
+        let mut response = self.send("PUT", url.clone(), Some(data.to_vec()))?;
+
+        // 409 means the collection doesn't exist yet
+        if response.status() == reqwest::StatusCode::CONFLICT {
+            let created = self.send("MKCOL", self.base.clone(), None)?;
+            if !created.status().is_success() {
+                return Err(http_error("create the backup folder", created.status()));
+            }
+            response = self.send("PUT", url, Some(data.to_vec()))?;
+        }
+
+        if !response.status().is_success() {
+            return Err(http_error("upload the backup", response.status()));
+        }
+        Ok(())
+    }
+
+    fn list(&self) -> CoreResult<Vec<String>> {
+        let body = br#"<?xml version="1.0" encoding="utf-8"?><propfind xmlns="DAV:"><prop><resourcetype/></prop></propfind>"#;
+        let response = self.send("PROPFIND", self.base.clone(), Some(body.to_vec()))?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(Vec::new());
+        }
+        if !response.status().is_success() {
+            return Err(http_error("list backups", response.status()));
+        }
+
+        let text = response
+            .text()
+            .map_err(|e| io_error(format!("Failed to read WebDAV listing: {}", e)))?;
+        Ok(parse_propfind_names(&text))
+    }
+
+    fn delete(&self, name: &str) -> CoreResult<()> {
+        let response = self.send("DELETE", self.file_url(name)?, None)?;
+        if !response.status().is_success() && response.status() != reqwest::StatusCode::NOT_FOUND {
+            return Err(http_error("delete an old backup", response.status()));
+        }
+        Ok(())
+    }
+}
+
+/// File names from a PROPFIND multistatus response
+///
+/// Servers use different namespace prefixes (`d:`, `D:`, none), so this scans
+/// for `href` elements rather than parsing the full document.
+#[cfg(not(target_arch = "wasm32"))]
+fn parse_propfind_names(xml: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut rest = xml;
+    while let Some(index) = rest.find("href>") {
+        let opening = rest[..index]
+            .rfind('<')
+            .is_some_and(|tag| !rest[tag + 1..].starts_with('/'));
+        rest = &rest[index + "href>".len()..];
+        if !opening {
+            continue;
+        }
+
+        if let Some(end) = rest.find("</") {
+            let href = rest[..end].trim();
+            if let Some(name) = href.trim_end_matches('/').rsplit('/').next() {
+                // Collections end in '/', and the first entry is the folder itself
+                if !href.ends_with('/') && !name.is_empty() {
+                    names.push(name.to_string());
+                }
+            }
+        }
+    }
+    names
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn http_error(action: &str, status: reqwest::StatusCode) -> CoreError {
+    io_error(format!("Failed to {}: server returned {}", action, status))
+}
+
+fn io_error(message: String) -> CoreError {
+    CoreError::FileOperation(FileError::IoError { message })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use std::sync::{Arc, Mutex};
+
+    /// In-memory destination shared with the test through an `Arc`
+    #[derive(Clone, Default)]
+    struct MemoryDestination {
+        files: Arc<Mutex<BTreeMap<String, Vec<u8>>>>,
+    }
+
+    impl BackupDestination for MemoryDestination {
+        fn describe(&self) -> String {
+            "memory".to_string()
+        }
+
+        fn upload(&self, name: &str, data: &[u8]) -> CoreResult<()> {
+            self.files
+                .lock()
+                .unwrap()
+                .insert(name.to_string(), data.to_vec());
+            Ok(())
+        }
+
+        fn list(&self) -> CoreResult<Vec<String>> {
+            Ok(self.files.lock().unwrap().keys().cloned().collect())
+        }
+
+        fn delete(&self, name: &str) -> CoreResult<()> {
+            self.files.lock().unwrap().remove(name);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_backup_name_sorts_chronologically() {
+        let early = "2024-01-31T23:59:59Z".parse::<DateTime<Utc>>().unwrap();
+        let late = "2024-02-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap();
+
+        let name = OffsiteUploader::backup_name(early);
+        assert_eq!(name, "ziplock-backup-20240131T235959Z.7z");
+        assert!(name < OffsiteUploader::backup_name(late));
+    }
+
+    #[test]
+    fn test_upload_rotates_old_backups() {
+        let destination = MemoryDestination::default();
+        destination.upload("notes.txt", b"unrelated").unwrap();
+        let uploader = OffsiteUploader::new(Box::new(destination.clone()), 2);
+
+        let start = "2024-01-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        for month in 0..4 {
+            let outcome = uploader
+                .upload(b"archive", start + chrono::Duration::days(30 * month))
+                .unwrap();
+            assert!(outcome.rotation_error.is_none());
+        }
+
+        let files = destination.list().unwrap();
+        assert_eq!(
+            files,
+            vec![
+                "notes.txt".to_string(),
+                "ziplock-backup-20240301T000000Z.7z".to_string(),
+                "ziplock-backup-20240331T000000Z.7z".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_keep_zero_disables_rotation() {
+        let destination = MemoryDestination::default();
+        let uploader = OffsiteUploader::new(Box::new(destination.clone()), 0);
+        let now = Utc::now();
+        uploader.upload(b"a", now).unwrap();
+        uploader
+            .upload(b"b", now + chrono::Duration::seconds(1))
+            .unwrap();
+        assert_eq!(destination.list().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_upload_file() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let archive = dir.path().join("vault.7z");
+        std::fs::write(&archive, b"encrypted").unwrap();
+
+        let destination = MemoryDestination::default();
+        let uploader = OffsiteUploader::new(Box::new(destination.clone()), 3);
+        let outcome = uploader.upload_file(&archive, Utc::now()).unwrap();
+
+        let files = destination.files.lock().unwrap();
+        assert_eq!(files.get(&outcome.file_name).unwrap(), b"encrypted");
+        assert!(uploader
+            .upload_file(&dir.path().join("missing.7z"), Utc::now())
+            .is_err());
+    }
+
+    #[test]
+    fn test_sftp_listing_and_quoting() {
+        let output = "sftp> ls -1 \"backups\"\nbackups/ziplock-backup-1.7z\nbackups/other\n";
+        assert_eq!(
+            parse_sftp_listing(output),
+            vec!["ziplock-backup-1.7z".to_string(), "other".to_string()]
+        );
+        assert_eq!(sftp_quote("my \"dir\"").unwrap(), "\"my \\\"dir\\\"\"");
+        for path in ["a\nrm b", "a\rrm b", "a\u{0}b", "a\tb", "a\u{7f}b"] {
+            assert!(matches!(
+                sftp_quote(path),
+                Err(CoreError::ValidationError { .. })
+            ));
+        }
+        let sftp = SftpDestination::new("host", "jane", "backups\nrm x");
+        assert!(matches!(
+            sftp.list(),
+            Err(CoreError::ValidationError { .. })
+        ));
+
+        let sftp = SftpDestination::new("host", "jane", "backups/").with_port(2222);
+        assert_eq!(sftp.remote_path("a.7z"), "backups/a.7z");
+        assert_eq!(sftp.describe(), "sftp://jane@host:2222/backups");
+    }
+
+    #[test]
+    fn test_sftp_refuses_option_like_destinations() {
+        for (host, username) in [
+            ("-oProxyCommand=touch x", "jane"),
+            ("host", "-F/tmp/c"),
+            ("", "jane"),
+        ] {
+            let sftp = SftpDestination::new(host, username, "backups")
+                .with_program("/nonexistent/ziplock-sftp");
+            assert!(matches!(
+                sftp.list(),
+                Err(CoreError::ValidationError { .. })
+            ));
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_sftp_ends_options_before_destination() {
+        // echo prints the arguments, which the listing parser passes through
+        let sftp = SftpDestination::new("host", "jane", "backups").with_program("echo");
+        let arguments = sftp.list().unwrap();
+        assert!(arguments[0].ends_with(" -- jane@host"), "{:?}", arguments);
+    }
+
+    #[test]
+    fn test_sftp_missing_program() {
+        let sftp = SftpDestination::new("host", "jane", "backups")
+            .with_program("/nonexistent/ziplock-sftp");
+        assert!(sftp.list().is_err());
+    }
+
+    #[test]
+    fn test_parse_propfind_names() {
+        let xml = r#"<?xml version="1.0"?>
+<d:multistatus xmlns:d="DAV:">
+  <d:response><d:href>/remote.php/dav/files/jane/backups/</d:href></d:response>
+  <d:response><d:href>/remote.php/dav/files/jane/backups/ziplock-backup-20240101T000000Z.7z</d:href></d:response>
+  <d:response><d:href>/remote.php/dav/files/jane/backups/old/</d:href></d:response>
+</d:multistatus>"#;
+        assert_eq!(
+            parse_propfind_names(xml),
+            vec!["ziplock-backup-20240101T000000Z.7z".to_string()]
+        );
+
+        let unprefixed = "<multistatus><response><href>/dav/a.7z</href></response></multistatus>";
+        assert_eq!(parse_propfind_names(unprefixed), vec!["a.7z".to_string()]);
+    }
+
+    #[test]
+    fn test_webdav_url_handling() {
+        let dav = WebDavDestination::new("https://dav.example.com/backups", "jane", String::new())
+            .unwrap();
+        assert_eq!(dav.describe(), "https://dav.example.com/backups/");
+        assert_eq!(
+            dav.file_url("a.7z").unwrap().as_str(),
+            "https://dav.example.com/backups/a.7z"
+        );
+
+        assert!(WebDavDestination::new("ftp://example.com", "jane", String::new()).is_err());
+        assert!(WebDavDestination::new("not a url", "jane", String::new()).is_err());
+    }
+
+    #[test]
+    fn test_from_config_requires_destination() {
+        let config = OffsiteBackupConfig::default();
+        assert!(OffsiteUploader::from_config(&config, None).is_err());
+    }
+}
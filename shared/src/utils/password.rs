@@ -5,7 +5,11 @@
 
 use rand::{thread_rng, Rng, RngCore};
 use sha2::{Digest, Sha256};
-use std::collections::HashSet;
+use std::sync::OnceLock;
+use tracing::warn;
+
+use super::breach::{BreachChecker, BreachStatus};
+use super::strength;
 
 /// Password character sets for generation
 pub struct CharacterSets;
@@ -51,6 +55,122 @@ impl Default for PasswordOptions {
     }
 }
 
+/// Word lists passphrases are drawn from
+///
+/// Both are derived from the EFF's diceware lists
+/// (<https://www.eff.org/dice>, CC BY 3.0 US): the long list for five dice,
+/// and the first short list for four dice, whose words are at most five
+/// letters long and easier to type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Wordlist {
+    #[default]
+    Long,
+    Short,
+}
+
+impl Wordlist {
+    /// Words of the list, in alphabetical order
+    pub fn words(&self) -> &'static [&'static str] {
+        static LONG: OnceLock<Vec<&'static str>> = OnceLock::new();
+        static SHORT: OnceLock<Vec<&'static str>> = OnceLock::new();
+        match self {
+            Wordlist::Long => LONG.get_or_init(|| EFF_LONG.split_whitespace().collect()),
+            Wordlist::Short => SHORT.get_or_init(|| EFF_SHORT.split_whitespace().collect()),
+        }
+    }
+
+    /// Entropy each word adds, in bits
+    pub fn bits_per_word(&self) -> f64 {
+        (self.words().len() as f64).log2()
+    }
+}
+
+const EFF_LONG: &str = include_str!("wordlists/eff_long.txt");
+const EFF_SHORT: &str = include_str!("wordlists/eff_short.txt");
+
+/// How the words of a passphrase are capitalized
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Capitalization {
+    #[default]
+    Lowercase,
+    Uppercase,
+    /// Capitalize the first letter of every word
+    FirstLetter,
+    /// Capitalize the first letter of each word with even odds
+    Random,
+}
+
+/// Passphrase generation options
+#[derive(Debug, Clone)]
+pub struct PassphraseOptions {
+    /// List the words are drawn from
+    pub wordlist: Wordlist,
+    /// Number of words
+    pub word_count: usize,
+    /// Put between words
+    pub separator: String,
+    pub capitalization: Capitalization,
+    /// Append a random digit to one of the words
+    pub include_digit: bool,
+}
+
+impl Default for PassphraseOptions {
+    fn default() -> Self {
+        Self {
+            wordlist: Wordlist::Long,
+            word_count: 6,
+            separator: "-".to_string(),
+            capitalization: Capitalization::Lowercase,
+            include_digit: false,
+        }
+    }
+}
+
+impl PassphraseOptions {
+    /// Entropy of passphrases generated with these options, in bits
+    ///
+    /// Assumes the attacker knows the word list and options, so only the
+    /// random choices count.
+    pub fn entropy(&self) -> f64 {
+        if self.word_count == 0 {
+            return 0.0;
+        }
+        let words = self.word_count as f64;
+        let mut bits = words * self.wordlist.bits_per_word();
+        if self.capitalization == Capitalization::Random {
+            bits += words;
+        }
+        if self.include_digit {
+            bits += 10f64.log2() + words.log2();
+        }
+        bits
+    }
+}
+
+/// A generated passphrase
+#[derive(Debug, Clone)]
+pub struct GeneratedPassphrase {
+    pub passphrase: String,
+    /// Entropy in bits, in the same units as [`PasswordAnalysis::entropy`]
+    pub entropy: f64,
+}
+
+impl GeneratedPassphrase {
+    /// Strength level for the entropy, on the scale
+    /// [`PasswordAnalyzer`] uses
+    pub fn strength(&self) -> PasswordStrength {
+        PasswordStrength::from_guesses_log10(self.entropy / std::f64::consts::LOG2_10)
+    }
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
 /// Password strength levels
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PasswordStrength {
@@ -75,6 +195,18 @@ impl PasswordStrength {
         }
     }
 
+    /// Strength level for a password needing `10^guesses_log10` guesses
+    pub fn from_guesses_log10(guesses_log10: f64) -> Self {
+        match guesses_log10 {
+            g if g < 3.0 => PasswordStrength::VeryWeak,
+            g if g < 6.0 => PasswordStrength::Weak,
+            g if g < 8.0 => PasswordStrength::Fair,
+            g if g < 10.0 => PasswordStrength::Good,
+            g if g < 12.0 => PasswordStrength::Strong,
+            _ => PasswordStrength::VeryStrong,
+        }
+    }
+
     /// Get a score from 0-100 for the strength level
     pub fn score(&self) -> u8 {
         match self {
@@ -95,7 +227,7 @@ pub struct PasswordAnalysis {
     pub strength: PasswordStrength,
     /// Detailed score (0-100)
     pub score: u8,
-    /// Estimated entropy in bits
+    /// Estimated entropy in bits (log2 of the guesses needed)
     pub entropy: f64,
     /// Whether password appears in common password lists
     pub is_common: bool,
@@ -103,6 +235,8 @@ pub struct PasswordAnalysis {
     pub diversity: u8,
     /// Feedback messages for improvement
     pub feedback: Vec<String>,
+    /// Breach status, when the password was checked against known breaches
+    pub breach: Option<BreachStatus>,
 }
 
 /// Password generator
@@ -138,74 +272,50 @@ impl PasswordGenerator {
         Ok(password)
     }
 
-    /// Generate a passphrase using word lists
+    /// Generate a passphrase of `word_count` words from the long word list
     pub fn generate_passphrase(word_count: usize, separator: &str) -> Result<String, &'static str> {
-        if word_count == 0 {
+        Self::generate_passphrase_with(&PassphraseOptions {
+            word_count,
+            separator: separator.to_string(),
+            ..PassphraseOptions::default()
+        })
+        .map(|generated| generated.passphrase)
+    }
+
+    /// Generate a passphrase with the given options, together with its
+    /// entropy
+    pub fn generate_passphrase_with(
+        options: &PassphraseOptions,
+    ) -> Result<GeneratedPassphrase, &'static str> {
+        if options.word_count == 0 {
             return Err("Word count must be greater than 0");
         }
 
-        // Simple word list - in a real implementation, this would come from a larger dictionary
-        let words = [
-            "apple",
-            "beach",
-            "cloud",
-            "dance",
-            "eagle",
-            "flame",
-            "grace",
-            "house",
-            "island",
-            "jungle",
-            "kite",
-            "lemon",
-            "mountain",
-            "ocean",
-            "piano",
-            "quiet",
-            "river",
-            "sunset",
-            "tiger",
-            "umbrella",
-            "valley",
-            "whale",
-            "xylophone",
-            "yacht",
-            "zebra",
-            "anchor",
-            "bridge",
-            "castle",
-            "dragon",
-            "forest",
-            "guitar",
-            "harmony",
-            "ivory",
-            "jewel",
-            "knight",
-            "lighthouse",
-            "melody",
-            "nature",
-            "orchid",
-            "phoenix",
-            "quartz",
-            "rainbow",
-            "serenity",
-            "thunder",
-            "unicorn",
-            "violet",
-            "wisdom",
-            "crystal",
-            "yonder",
-        ];
-
         let mut rng = thread_rng();
-        let selected_words: Vec<&str> = (0..word_count)
+        let words = options.wordlist.words();
+        let mut selected: Vec<String> = (0..options.word_count)
             .map(|_| {
-                let idx = rng.gen_range(0..words.len());
-                words[idx]
+                let word = words[rng.gen_range(0..words.len())];
+                match options.capitalization {
+                    Capitalization::Lowercase => word.to_string(),
+                    Capitalization::Uppercase => word.to_uppercase(),
+                    Capitalization::FirstLetter => capitalize(word),
+                    Capitalization::Random if rng.gen_bool(0.5) => capitalize(word),
+                    Capitalization::Random => word.to_string(),
+                }
             })
             .collect();
 
-        Ok(selected_words.join(separator))
+        if options.include_digit {
+            let index = rng.gen_range(0..selected.len());
+            let digit = rng.gen_range(0..10);
+            selected[index].push_str(&digit.to_string());
+        }
+
+        Ok(GeneratedPassphrase {
+            passphrase: selected.join(&options.separator),
+            entropy: options.entropy(),
+        })
     }
 
     /// Build character set based on options
@@ -234,224 +344,116 @@ impl PasswordGenerator {
 }
 
 /// Password analyzer for strength assessment
+///
+/// Strength comes from the number of guesses estimated by the
+/// pattern-aware model in [`super::strength`], not from the mix of
+/// character classes.
 pub struct PasswordAnalyzer;
 
 impl PasswordAnalyzer {
     /// Analyze password strength and provide feedback
     pub fn analyze(password: &str) -> PasswordAnalysis {
-        let mut score = 0u8;
-        let mut feedback = Vec::new();
-
-        // Length scoring
-        let length_score = Self::score_length(password, &mut feedback);
-        score = score.saturating_add(length_score);
-
-        // Character diversity scoring
-        let diversity_score = Self::score_diversity(password, &mut feedback);
-        score = score.saturating_add(diversity_score);
-
-        // Pattern scoring
-        let pattern_score = Self::score_patterns(password, &mut feedback);
-        score = score.saturating_add(pattern_score);
-
-        // Common password check
-        let is_common = Self::is_common_password(password);
-        if is_common {
-            score = score.saturating_sub(30);
-            feedback.push("This appears to be a common password".to_string());
-        }
+        Self::analyze_localized(password, "en")
+    }
 
-        // Calculate entropy
-        let entropy = Self::calculate_entropy(password);
-
-        // Determine strength level
-        let strength = match score {
-            0..=20 => PasswordStrength::VeryWeak,
-            21..=40 => PasswordStrength::Weak,
-            41..=60 => PasswordStrength::Fair,
-            61..=80 => PasswordStrength::Good,
-            81..=95 => PasswordStrength::Strong,
-            96..=100 => PasswordStrength::VeryStrong,
-            _ => PasswordStrength::VeryStrong,
-        };
+    /// Analyze password strength with feedback in the given language
+    ///
+    /// `language` is an ISO 639-1 code such as `ui.language` from the app
+    /// config; see [`strength::SUPPORTED_LANGUAGES`]. Other languages get
+    /// English feedback.
+    pub fn analyze_localized(password: &str, language: &str) -> PasswordAnalysis {
+        let estimate = strength::estimate(password);
+        let guesses_log10 = estimate.guesses_log10();
 
         PasswordAnalysis {
-            strength,
-            score,
-            entropy,
-            is_common,
-            diversity: diversity_score,
-            feedback,
+            strength: PasswordStrength::from_guesses_log10(guesses_log10),
+            score: Self::score(guesses_log10),
+            entropy: guesses_log10 * std::f64::consts::LOG2_10,
+            is_common: estimate.is_common(),
+            diversity: Self::score_diversity(password),
+            feedback: strength::feedback(&estimate, language),
+            breach: None,
         }
     }
 
-    /// Score password based on length
-    fn score_length(password: &str, feedback: &mut Vec<String>) -> u8 {
-        let len = password.len();
-        match len {
-            0..=4 => {
-                feedback.push("Password is too short - use at least 8 characters".to_string());
-                5
+    /// Analyze password strength and check it against known breaches
+    ///
+    /// A breached password is reported as very weak regardless of its
+    /// composition. If the check itself fails the analysis is returned
+    /// without breach information.
+    pub fn analyze_with_breach_check(password: &str, checker: &BreachChecker) -> PasswordAnalysis {
+        let mut analysis = Self::analyze(password);
+
+        match checker.check(password) {
+            Ok(status) => {
+                if let Some(description) = status.describe() {
+                    analysis.score = analysis.score.min(10);
+                    analysis.strength = PasswordStrength::VeryWeak;
+                    analysis
+                        .feedback
+                        .push(format!("{} - choose a different password", description));
+                }
+                analysis.breach = Some(status);
             }
-            5..=7 => {
-                feedback
-                    .push("Password is short - consider using at least 12 characters".to_string());
-                15
-            }
-            8..=11 => 25,
-            12..=15 => 30,
-            16..=20 => 35,
-            _ => 40,
-        }
-    }
-
-    /// Score password based on character diversity
-    fn score_diversity(password: &str, feedback: &mut Vec<String>) -> u8 {
-        let mut score = 0u8;
-        let mut has_lower = false;
-        let mut has_upper = false;
-        let mut has_digit = false;
-        let mut has_symbol = false;
-
-        for c in password.chars() {
-            if c.is_ascii_lowercase() {
-                has_lower = true;
-            } else if c.is_ascii_uppercase() {
-                has_upper = true;
-            } else if c.is_ascii_digit() {
-                has_digit = true;
-            } else if !c.is_ascii_alphanumeric() {
-                has_symbol = true;
-            }
-        }
-
-        if has_lower {
-            score += 10;
-        } else {
-            feedback.push("Add lowercase letters".to_string());
-        }
-
-        if has_upper {
-            score += 10;
-        } else {
-            feedback.push("Add uppercase letters".to_string());
-        }
-
-        if has_digit {
-            score += 10;
-        } else {
-            feedback.push("Add numbers".to_string());
+            Err(e) => warn!("Skipping breach check: {}", e),
         }
 
-        if has_symbol {
-            score += 15;
-        } else {
-            feedback.push("Add symbols (!@#$%^&*)".to_string());
-        }
-
-        score
+        analysis
     }
 
-    /// Score password based on patterns (repetition, sequences, etc.)
-    fn score_patterns(password: &str, feedback: &mut Vec<String>) -> u8 {
-        let mut score = 15u8; // Start with full pattern score
-
-        // Check for repeated characters
-        let mut char_counts = std::collections::HashMap::new();
-        for c in password.chars() {
-            *char_counts.entry(c).or_insert(0) += 1;
-        }
-
-        let max_repeat = char_counts.values().max().unwrap_or(&1);
-        if *max_repeat > password.len() / 3 {
-            score = score.saturating_sub(10);
-            feedback.push("Avoid repeating the same character too often".to_string());
-        }
-
-        // Check for sequential characters (abc, 123)
-        let chars: Vec<char> = password.chars().collect();
-        let mut sequential_count = 0;
-        for window in chars.windows(3) {
-            if window[1] as u8 == window[0] as u8 + 1 && window[2] as u8 == window[1] as u8 + 1 {
-                sequential_count += 1;
-            }
-        }
-
-        if sequential_count > 0 {
-            score = score.saturating_sub(5);
-            feedback.push("Avoid sequential characters (abc, 123)".to_string());
-        }
+    /// Map the estimated guesses onto 0-100, in line with the strength levels
+    fn score(guesses_log10: f64) -> u8 {
+        // (log10 guesses, score) at the strength level boundaries
+        const POINTS: [(f64, f64); 7] = [
+            (0.0, 0.0),
+            (3.0, 21.0),
+            (6.0, 41.0),
+            (8.0, 61.0),
+            (10.0, 81.0),
+            (12.0, 96.0),
+            (14.0, 100.0),
+        ];
+        let g = guesses_log10.max(0.0);
+        let score = POINTS
+            .windows(2)
+            .find(|pair| g < pair[1].0)
+            .map(|pair| {
+                let ((g0, s0), (g1, s1)) = (pair[0], pair[1]);
+                s0 + (g - g0) / (g1 - g0) * (s1 - s0)
+            })
+            .unwrap_or(100.0);
+        score.floor() as u8
+    }
 
-        score
+    /// Score password based on character diversity
+    fn score_diversity(password: &str) -> u8 {
+        let has_lower = password.chars().any(|c| c.is_ascii_lowercase());
+        let has_upper = password.chars().any(|c| c.is_ascii_uppercase());
+        let has_digit = password.chars().any(|c| c.is_ascii_digit());
+        let has_symbol = password.chars().any(|c| !c.is_ascii_alphanumeric());
+
+        [
+            (has_lower, 10),
+            (has_upper, 10),
+            (has_digit, 10),
+            (has_symbol, 15),
+        ]
+        .iter()
+        .filter(|(present, _)| *present)
+        .map(|(_, points)| points)
+        .sum()
     }
 
-    /// Check if password is commonly used (simplified check)
+    /// Check if password is commonly used
+    #[cfg(test)]
     fn is_common_password(password: &str) -> bool {
-        let common_passwords = [
-            "password",
-            "123456",
-            "123456789",
-            "12345678",
-            "12345",
-            "1234567",
-            "123",
-            "password123",
-            "admin",
-            "qwerty",
-            "abc123",
-            "Password1",
-            "welcome",
-            "monkey",
-            "dragon",
-            "letmein",
-            "trustno1",
-            "sunshine",
-            "master",
-            "hello",
-            "freedom",
-            "whatever",
-            "qazwsx",
-            "123321",
-            "654321",
-        ];
-
-        let lower_password = password.to_lowercase();
-        common_passwords.contains(&lower_password.as_str())
+        strength::estimate(password).is_common()
     }
 
     /// Calculate password entropy in bits
+    #[cfg(test)]
     fn calculate_entropy(password: &str) -> f64 {
-        if password.is_empty() {
-            return 0.0;
-        }
-
-        let mut charset_size = 0;
-        let chars: HashSet<char> = password.chars().collect();
-
-        // Estimate character set size based on characters used
-        let has_lower = chars.iter().any(|c| c.is_ascii_lowercase());
-        let has_upper = chars.iter().any(|c| c.is_ascii_uppercase());
-        let has_digit = chars.iter().any(|c| c.is_ascii_digit());
-        let has_symbol = chars.iter().any(|c| !c.is_ascii_alphanumeric());
-
-        if has_lower {
-            charset_size += 26;
-        }
-        if has_upper {
-            charset_size += 26;
-        }
-        if has_digit {
-            charset_size += 10;
-        }
-        if has_symbol {
-            charset_size += 32; // Approximate
-        }
-
-        if charset_size == 0 {
-            charset_size = 1; // Avoid log(0)
-        }
-
-        password.len() as f64 * (charset_size as f64).log2()
+        strength::estimate_guesses(password).log2()
     }
 }
 
@@ -530,6 +532,32 @@ impl PasswordUtils {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::breach::BloomFilter;
+
+    #[test]
+    fn test_analysis_with_breach_check() {
+        let mut filter = BloomFilter::with_capacity(100, 0.001);
+        filter.insert_password("Tr0ub4dor&3-horse!");
+        let checker = BreachChecker::offline(filter);
+
+        let breached = PasswordAnalyzer::analyze_with_breach_check("Tr0ub4dor&3-horse!", &checker);
+        assert_eq!(breached.strength, PasswordStrength::VeryWeak);
+        assert_eq!(breached.breach, Some(BreachStatus::ProbablyFound));
+        assert!(breached
+            .feedback
+            .iter()
+            .any(|f| f.contains("Pwned Passwords")));
+
+        let clean = PasswordAnalyzer::analyze_with_breach_check("x7#Kq!m2Lp@9vRt4", &checker);
+        assert_eq!(clean.breach, Some(BreachStatus::NotFound));
+        assert_eq!(
+            clean.strength,
+            PasswordAnalyzer::analyze("x7#Kq!m2Lp@9vRt4").strength
+        );
+        assert!(PasswordAnalyzer::analyze("x7#Kq!m2Lp@9vRt4")
+            .breach
+            .is_none());
+    }
 
     #[test]
     fn test_password_generation() {
@@ -564,6 +592,90 @@ mod tests {
         assert_eq!(words.len(), 4);
     }
 
+    #[test]
+    fn test_wordlists() {
+        for wordlist in [Wordlist::Long, Wordlist::Short] {
+            let words = wordlist.words();
+            let unique: std::collections::HashSet<_> = words.iter().collect();
+            assert_eq!(unique.len(), words.len());
+            assert!(words.windows(2).all(|pair| pair[0] < pair[1]));
+        }
+        assert!(Wordlist::Long.words().len() > 7700);
+        assert_eq!(Wordlist::Short.words().len(), 1296);
+        assert!(Wordlist::Short.words().iter().all(|word| word.len() <= 5));
+        assert!((Wordlist::Short.bits_per_word() - 1296f64.log2()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_passphrase_options() {
+        let options = PassphraseOptions {
+            wordlist: Wordlist::Short,
+            word_count: 5,
+            separator: " ".to_string(),
+            capitalization: Capitalization::FirstLetter,
+            include_digit: true,
+        };
+        let generated = PasswordGenerator::generate_passphrase_with(&options).unwrap();
+        let words: Vec<&str> = generated.passphrase.split(' ').collect();
+        assert_eq!(words.len(), 5);
+        assert!(words
+            .iter()
+            .all(|word| word.starts_with(|c: char| c.is_ascii_uppercase())));
+        assert_eq!(
+            generated
+                .passphrase
+                .chars()
+                .filter(|c| c.is_ascii_digit())
+                .count(),
+            1
+        );
+        for word in words {
+            let word = word.trim_end_matches(|c: char| c.is_ascii_digit());
+            assert!(Wordlist::Short
+                .words()
+                .contains(&word.to_lowercase().as_str()));
+        }
+
+        let expected = 5.0 * 1296f64.log2() + 10f64.log2() + 5f64.log2();
+        assert!((generated.entropy - expected).abs() < 1e-9);
+
+        let uppercase = PasswordGenerator::generate_passphrase_with(&PassphraseOptions {
+            capitalization: Capitalization::Uppercase,
+            ..PassphraseOptions::default()
+        })
+        .unwrap();
+        assert_eq!(uppercase.passphrase, uppercase.passphrase.to_uppercase());
+        assert!(
+            PasswordGenerator::generate_passphrase_with(&PassphraseOptions {
+                word_count: 0,
+                ..PassphraseOptions::default()
+            })
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_passphrase_entropy_matches_analyzer_scale() {
+        // Six long-list words are about 77 bits
+        let generated =
+            PasswordGenerator::generate_passphrase_with(&PassphraseOptions::default()).unwrap();
+        assert!(generated.entropy > 77.0);
+        assert_eq!(generated.strength(), PasswordStrength::VeryStrong);
+
+        let two_words = PassphraseOptions {
+            word_count: 2,
+            capitalization: Capitalization::Random,
+            ..PassphraseOptions::default()
+        };
+        assert!((two_words.entropy() - 2.0 * (Wordlist::Long.bits_per_word() + 1.0)).abs() < 1e-9);
+        let generated = PasswordGenerator::generate_passphrase_with(&two_words).unwrap();
+        assert_eq!(
+            generated.strength(),
+            PasswordStrength::from_guesses_log10(two_words.entropy() / std::f64::consts::LOG2_10)
+        );
+        assert_eq!(generated.strength(), PasswordStrength::Good);
+    }
+
     #[test]
     fn test_password_analysis() {
         let weak_password = "123";
@@ -579,6 +691,37 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_predictable_passwords_are_weak() {
+        // Character class rules would rate these as strong
+        for password in ["P@ssw0rd!", "Qwerty123!", "Iloveyou1!"] {
+            let analysis = PasswordAnalyzer::analyze(password);
+            assert!(
+                matches!(
+                    analysis.strength,
+                    PasswordStrength::VeryWeak | PasswordStrength::Weak
+                ),
+                "{} rated {:?}",
+                password,
+                analysis.strength
+            );
+            assert!(!analysis.feedback.is_empty());
+        }
+
+        let passphrase = PasswordAnalyzer::analyze("plaid wombat juggles kettle");
+        assert!(matches!(
+            passphrase.strength,
+            PasswordStrength::Strong | PasswordStrength::VeryStrong
+        ));
+        assert!(passphrase.feedback.is_empty());
+
+        let localized = PasswordAnalyzer::analyze_localized("iloveyou", "fr");
+        assert_eq!(
+            localized.feedback[0],
+            "Ce mot de passe fait partie des 100 plus courants"
+        );
+    }
+
     #[test]
     fn test_common_password_detection() {
         assert!(PasswordAnalyzer::is_common_password("password"));
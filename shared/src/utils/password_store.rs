@@ -0,0 +1,687 @@
+//! Import and export for `pass`, the standard Unix password manager
+//!
+//! A password store is a directory of gpg-encrypted files. The first line of
+//! each file is the password and the following `key: value` lines hold extra
+//! data, so a file's path maps to `folder_path` + title and its lines to fields.
+//! Encryption goes through the [`PassCrypto`] trait; [`GpgCommand`] shells out
+//! to `gpg` the same way `pass` itself does.
+
+use crate::core::{CoreError, CoreResult, FileError};
+use crate::models::{CredentialField, CredentialRecord, FieldType};
+use crate::utils::import::{
+    field_name, finish, totp_field, url_field, ImportFailure, ImportReport,
+};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Decrypts and encrypts individual password store entries
+pub trait PassCrypto {
+    /// Decrypt the entry stored at `path`
+    fn decrypt(&self, path: &Path) -> CoreResult<String>;
+
+    /// Encrypt an entry's plaintext for the given gpg recipients
+    fn encrypt(&self, plaintext: &str, recipients: &[String]) -> CoreResult<Vec<u8>>;
+}
+
+/// [`PassCrypto`] implementation that invokes the `gpg` binary
+#[derive(Debug, Clone)]
+pub struct GpgCommand {
+    program: PathBuf,
+}
+
+impl GpgCommand {
+    /// Use `gpg` from `PATH`
+    pub fn new() -> Self {
+        Self {
+            program: PathBuf::from("gpg"),
+        }
+    }
+
+    /// Use a specific gpg binary (e.g. `gpg2`)
+    pub fn with_program<P: Into<PathBuf>>(mut self, program: P) -> Self {
+        self.program = program.into();
+        self
+    }
+
+    /// Import armored keys into the user's keyring, returning gpg's report
+    /// of what was imported
+    pub fn import_keys(&self, armored: &str) -> CoreResult<String> {
+        // gpg reports on stderr; stdout stays empty
+        let output = self.spawn(&["--batch", "--import"], Some(armored.as_bytes()))?;
+        Ok(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    }
+
+    fn run(&self, args: &[&str], stdin: Option<&[u8]>) -> CoreResult<Vec<u8>> {
+        self.spawn(args, stdin).map(|output| output.stdout)
+    }
+
+    fn spawn(&self, args: &[&str], stdin: Option<&[u8]>) -> CoreResult<std::process::Output> {
+        let mut child = Command::new(&self.program)
+            .args(args)
+            .stdin(if stdin.is_some() {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| io_error(&self.program, e))?;
+
+        if let (Some(input), Some(mut pipe)) = (stdin, child.stdin.take()) {
+            pipe.write_all(input)
+                .map_err(|e| io_error(&self.program, e))?;
+        }
+
+        let output = child
+            .wait_with_output()
+            .map_err(|e| io_error(&self.program, e))?;
+        if !output.status.success() {
+            return Err(CoreError::InternalError {
+                message: format!(
+                    "gpg failed: {}",
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ),
+            });
+        }
+        Ok(output)
+    }
+}
+
+impl Default for GpgCommand {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PassCrypto for GpgCommand {
+    fn decrypt(&self, path: &Path) -> CoreResult<String> {
+        let path = path.to_string_lossy();
+        let plaintext = self.run(
+            &[
+                "--quiet",
+                "--yes",
+                "--batch",
+                "--use-agent",
+                "--decrypt",
+                &path,
+            ],
+            None,
+        )?;
+        String::from_utf8(plaintext).map_err(|e| CoreError::SerializationError {
+            message: format!("Decrypted entry is not valid UTF-8: {}", e),
+        })
+    }
+
+    fn encrypt(&self, plaintext: &str, recipients: &[String]) -> CoreResult<Vec<u8>> {
+        let mut args = vec!["--quiet", "--yes", "--batch", "--encrypt"];
+        for recipient in recipients {
+            args.push("--recipient");
+            args.push(recipient);
+        }
+        args.extend(["--output", "-"]);
+        self.run(&args, Some(plaintext.as_bytes()))
+    }
+}
+
+/// Importer for `pass` password stores
+pub struct PasswordStoreImporter;
+
+impl PasswordStoreImporter {
+    /// Decrypt and import every entry under a password store directory
+    ///
+    /// Entries that fail to decrypt are reported as errors rather than
+    /// aborting the import.
+    pub fn import_dir(root: &Path, crypto: &dyn PassCrypto) -> CoreResult<ImportReport> {
+        let mut files = Vec::new();
+        collect_entries(root, &mut files)?;
+        files.sort();
+
+        let mut report = ImportReport::default();
+        for (index, file) in files.iter().enumerate() {
+            let entry = entry_name(root, file);
+            let result = crypto
+                .decrypt(file)
+                .map_err(|e| ImportFailure::new(Some(entry.clone()), e.to_string()))
+                .and_then(|contents| Self::parse_entry(&entry, &contents));
+            report.push(index + 1, result);
+        }
+
+        Ok(report)
+    }
+
+    /// Import already-decrypted entries as `(path, contents)` pairs
+    ///
+    /// Paths are relative to the store root, with or without the `.gpg`
+    /// extension (e.g. `email/example.com`).
+    pub fn parse_entries<I, P, S>(entries: I) -> ImportReport
+    where
+        I: IntoIterator<Item = (P, S)>,
+        P: AsRef<str>,
+        S: AsRef<str>,
+    {
+        let mut report = ImportReport::default();
+        for (index, (path, contents)) in entries.into_iter().enumerate() {
+            let path = path.as_ref().trim_end_matches(".gpg");
+            report.push(index + 1, Self::parse_entry(path, contents.as_ref()));
+        }
+        report
+    }
+
+    fn parse_entry(path: &str, contents: &str) -> Result<CredentialRecord, ImportFailure> {
+        let path = path.trim_matches('/');
+        let (folder, title) = match path.rsplit_once('/') {
+            Some((folder, title)) => (Some(folder.to_string()), title),
+            None => (None, path),
+        };
+
+        let mut credential = CredentialRecord::new(title.to_string(), "login".to_string());
+        credential.folder_path = folder;
+
+        let mut lines = contents.lines().peekable();
+        let password = lines.next().unwrap_or("").trim_end();
+        if !password.is_empty() {
+            credential.set_field("password", CredentialField::password(password));
+        }
+
+        let mut notes: Vec<String> = Vec::new();
+        while let Some(line) = lines.next() {
+            if line.trim_start().to_lowercase().starts_with("otpauth://") {
+                if let Some(totp) = totp_field(line) {
+                    add_field(&mut credential, "totp", totp);
+                }
+                continue;
+            }
+
+            let Some((key, value)) = split_key_value(line) else {
+                notes.push(line.to_string());
+                continue;
+            };
+
+            // `key: |` starts a block of two-space indented lines
+            let value = if value == "|" {
+                let mut block = Vec::new();
+                while let Some(next) = lines.next_if(|l| l.starts_with("  ") || l.is_empty()) {
+                    block.push(next.strip_prefix("  ").unwrap_or(next));
+                }
+                block.join("\n").trim_end().to_string()
+            } else {
+                value.to_string()
+            };
+
+            Self::apply(&mut credential, key, value, &mut notes);
+        }
+
+        let notes = notes.join("\n").trim().to_string();
+        if !notes.is_empty() {
+            credential.notes = Some(notes);
+        }
+
+        if credential.fields.is_empty() && credential.notes.is_some() {
+            credential.credential_type = "secure_note".to_string();
+        }
+
+        finish(credential)
+    }
+
+    fn apply(credential: &mut CredentialRecord, key: &str, value: String, notes: &mut Vec<String>) {
+        if value.is_empty() {
+            return;
+        }
+
+        let name = field_name(key);
+        match name.as_str() {
+            "user" | "username" | "login" => {
+                add_field(credential, "username", CredentialField::username(value))
+            }
+            "email" | "e_mail" | "mail" => {
+                add_field(credential, "email", CredentialField::email(value))
+            }
+            "url" | "website" | "site" => match url_field(&value) {
+                Some(url) => add_field(credential, "url", url),
+                None => notes.push(format!("{}: {}", key, value)),
+            },
+            "otp" | "totp" => {
+                if let Some(totp) = totp_field(&value) {
+                    add_field(credential, "totp", totp);
+                }
+            }
+            "password" | "pass" | "pin" => add_field(
+                credential,
+                &name,
+                CredentialField::password(value).with_label(key.to_string()),
+            ),
+            "notes" | "note" | "comment" | "comments" => notes.push(value),
+            "tags" => credential.tags.extend(
+                value
+                    .split(',')
+                    .map(|t| t.trim().to_string())
+                    .filter(|t| !t.is_empty()),
+            ),
+            _ if name.starts_with("url_") => match url_field(&value) {
+                Some(url) => add_field(credential, &name, url),
+                None => notes.push(format!("{}: {}", key, value)),
+            },
+            "" => notes.push(format!("{}: {}", key, value)),
+            _ => {
+                let field = if value.contains('\n') {
+                    CredentialField::new(FieldType::TextArea, value, false)
+                } else {
+                    CredentialField::text(value)
+                };
+                add_field(credential, &name, field.with_label(key.to_string()))
+            }
+        }
+    }
+}
+
+/// Exporter that writes credentials as `pass` entries
+pub struct PasswordStoreExporter;
+
+impl PasswordStoreExporter {
+    /// Store-relative path (without `.gpg`) for a credential
+    pub fn entry_path(credential: &CredentialRecord) -> String {
+        let title = match sanitize_segment(&credential.title) {
+            title if title.is_empty() => credential.id.clone(),
+            title => title,
+        };
+
+        let folder: Vec<String> = credential
+            .folder_path
+            .as_deref()
+            .unwrap_or("")
+            .split('/')
+            .map(sanitize_segment)
+            .filter(|s| !s.is_empty())
+            .collect();
+
+        if folder.is_empty() {
+            title
+        } else {
+            format!("{}/{}", folder.join("/"), title)
+        }
+    }
+
+    /// Render a credential in `pass` format
+    pub fn format_entry(credential: &CredentialRecord) -> String {
+        let password_name = credential
+            .get_field("password")
+            .map(|_| "password".to_string())
+            .or_else(|| {
+                credential
+                    .fields
+                    .iter()
+                    .find(|(_, f)| f.field_type == FieldType::Password)
+                    .map(|(name, _)| name.clone())
+            });
+
+        let mut out = String::new();
+        if let Some(name) = &password_name {
+            out.push_str(&credential.fields[name].value);
+        }
+        out.push('\n');
+
+        // Well-known fields first, then the rest in a stable order
+        let mut names: Vec<&String> = credential
+            .fields
+            .keys()
+            .filter(|name| Some(*name) != password_name.as_ref())
+            .collect();
+        names.sort_by_key(|name| {
+            let rank = match name.as_str() {
+                "username" => 0,
+                "email" => 1,
+                "url" => 2,
+                _ => 3,
+            };
+            (rank, name.as_str())
+        });
+
+        for name in names {
+            let field = &credential.fields[name];
+            if field.value.is_empty() {
+                continue;
+            }
+            if field.field_type == FieldType::TotpSecret {
+                out.push_str(&otpauth_uri(&credential.title, field));
+                out.push('\n');
+            } else {
+                // Prefer the original label when it still maps back to this field name
+                let key = field
+                    .label
+                    .as_deref()
+                    .filter(|label| field_name(label) == *name)
+                    .unwrap_or(name);
+                write_value(&mut out, key, &field.value);
+            }
+        }
+
+        if !credential.tags.is_empty() {
+            write_value(&mut out, "tags", &credential.tags.join(", "));
+        }
+        if let Some(notes) = credential.notes.as_deref().filter(|n| !n.is_empty()) {
+            write_value(&mut out, "notes", notes);
+        }
+
+        out
+    }
+
+    /// Render credentials as `(path, plaintext)` pairs, de-duplicating paths
+    pub fn export_entries(credentials: &[CredentialRecord]) -> Vec<(String, String)> {
+        let mut entries: Vec<(String, String)> = Vec::with_capacity(credentials.len());
+        for credential in credentials {
+            let base = Self::entry_path(credential);
+            let mut path = base.clone();
+            let mut counter = 2;
+            while entries.iter().any(|(existing, _)| existing == &path) {
+                path = format!("{} ({})", base, counter);
+                counter += 1;
+            }
+            entries.push((path, Self::format_entry(credential)));
+        }
+        entries
+    }
+
+    /// Encrypt credentials into a password store directory
+    ///
+    /// Recipients are read from the store's `.gpg-id`. Existing entries are
+    /// never overwritten. Returns the number of entries written.
+    pub fn export_dir(
+        root: &Path,
+        credentials: &[CredentialRecord],
+        crypto: &dyn PassCrypto,
+    ) -> CoreResult<usize> {
+        let gpg_id = root.join(".gpg-id");
+        let recipients: Vec<String> = std::fs::read_to_string(&gpg_id)
+            .map_err(|_| CoreError::ValidationError {
+                message: format!(
+                    "{} is not an initialized password store (missing .gpg-id)",
+                    root.display()
+                ),
+            })?
+            .lines()
+            .map(|l| l.trim().to_string())
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .collect();
+
+        let entries = Self::export_entries(credentials);
+        for (path, _) in &entries {
+            let file = root.join(format!("{}.gpg", path));
+            if file.exists() {
+                return Err(CoreError::ValidationError {
+                    message: format!("Password store entry '{}' already exists", path),
+                });
+            }
+        }
+
+        for (path, plaintext) in &entries {
+            let file = root.join(format!("{}.gpg", path));
+            if let Some(parent) = file.parent() {
+                std::fs::create_dir_all(parent).map_err(|e| io_error(parent, e))?;
+            }
+            let ciphertext = crypto.encrypt(plaintext, &recipients)?;
+            std::fs::write(&file, ciphertext).map_err(|e| io_error(&file, e))?;
+        }
+
+        Ok(entries.len())
+    }
+}
+
+/// Recursively find `.gpg` entries, skipping hidden files and directories
+fn collect_entries(dir: &Path, files: &mut Vec<PathBuf>) -> CoreResult<()> {
+    let read_dir = std::fs::read_dir(dir).map_err(|e| io_error(dir, e))?;
+    for entry in read_dir {
+        let path = entry.map_err(|e| io_error(dir, e))?.path();
+        let hidden = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(|n| n.starts_with('.'));
+        if hidden {
+            continue;
+        }
+
+        if path.is_dir() {
+            collect_entries(&path, files)?;
+        } else if path.extension().is_some_and(|ext| ext == "gpg") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Store-relative entry name with `/` separators and no extension
+fn entry_name(root: &Path, file: &Path) -> String {
+    let relative = file.strip_prefix(root).unwrap_or(file).with_extension("");
+    relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Split a `key: value` line; keys are short and can't look like a URL scheme
+fn split_key_value(line: &str) -> Option<(&str, &str)> {
+    if line.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let (key, value) = line.split_once(':')?;
+    if key.is_empty() || key.len() > 40 || !(value.is_empty() || value.starts_with(' ')) {
+        return None;
+    }
+    Some((key.trim(), value.trim()))
+}
+
+/// Add a field, suffixing the name if it is already taken
+fn add_field(credential: &mut CredentialRecord, name: &str, field: CredentialField) {
+    let mut unique = name.to_string();
+    let mut counter = 2;
+    while credential.get_field(&unique).is_some() {
+        unique = format!("{}_{}", name, counter);
+        counter += 1;
+    }
+    credential.set_field(unique, field);
+}
+
+/// Write `key: value`, using an indented block for multi-line values
+fn write_value(out: &mut String, key: &str, value: &str) {
+    if value.contains('\n') {
+        out.push_str(key);
+        out.push_str(": |\n");
+        for line in value.lines() {
+            out.push_str("  ");
+            out.push_str(line);
+            out.push('\n');
+        }
+    } else {
+        out.push_str(&format!("{}: {}\n", key, value));
+    }
+}
+
+/// Rebuild the `otpauth://` URI pass-otp expects from a TOTP field
+fn otpauth_uri(title: &str, field: &CredentialField) -> String {
+    let mut uri = url::Url::parse("otpauth://totp/").expect("static URI is valid");
+    uri.set_path(title);
+    {
+        let mut query = uri.query_pairs_mut();
+        query.append_pair("secret", &field.value);
+        for key in ["issuer", "algorithm", "digits", "period"] {
+            if let Some(value) = field.metadata.get(key) {
+                query.append_pair(key, value);
+            }
+        }
+    }
+    uri.to_string()
+}
+
+/// Make a title or folder name safe to use as a path segment
+fn sanitize_segment(segment: &str) -> String {
+    segment
+        .trim()
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | '\0' => '-',
+            c if c.is_control() => '-',
+            c => c,
+        })
+        .collect::<String>()
+        .trim_start_matches('.')
+        .to_string()
+}
+
+fn io_error(path: impl AsRef<Path>, e: std::io::Error) -> CoreError {
+    CoreError::FileOperation(FileError::IoError {
+        message: format!("{}: {}", path.as_ref().display(), e),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// Stand-in for gpg that stores plaintext as-is
+    struct PlainCrypto;
+
+    impl PassCrypto for PlainCrypto {
+        fn decrypt(&self, path: &Path) -> CoreResult<String> {
+            if path.ends_with("broken.gpg") {
+                return Err(CoreError::InternalError {
+                    message: "gpg: decryption failed: No secret key".to_string(),
+                });
+            }
+            std::fs::read_to_string(path).map_err(|e| io_error(path, e))
+        }
+
+        fn encrypt(&self, plaintext: &str, recipients: &[String]) -> CoreResult<Vec<u8>> {
+            assert_eq!(recipients, ["jane@example.com"]);
+            Ok(plaintext.as_bytes().to_vec())
+        }
+    }
+
+    const ENTRY: &str = "hunter2\n\
+        login: jane\n\
+        url: example.com\n\
+        otpauth://totp/Example:jane?secret=JBSWY3DPEHPK3PXP&issuer=Example\n\
+        Security Question: First pet?\n\
+        Recovery codes: |\n  1111\n  2222\n\
+        Remember to rotate yearly\n";
+
+    #[test]
+    fn test_parse_entry() {
+        let report =
+            PasswordStoreImporter::parse_entries([("websites/shopping/example.gpg", ENTRY)]);
+        assert!(!report.has_errors(), "{:?}", report.errors);
+
+        let credential = &report.credentials[0];
+        assert_eq!(credential.title, "example");
+        assert_eq!(credential.folder_path.as_deref(), Some("websites/shopping"));
+        assert_eq!(credential.get_field("password").unwrap().value, "hunter2");
+        assert_eq!(credential.get_field("username").unwrap().value, "jane");
+        assert_eq!(
+            credential.get_field("url").unwrap().value,
+            "https://example.com"
+        );
+        assert_eq!(
+            credential.get_field("totp").unwrap().value,
+            "JBSWY3DPEHPK3PXP"
+        );
+        assert_eq!(
+            credential.get_field("security_question").unwrap().value,
+            "First pet?"
+        );
+        let codes = credential.get_field("recovery_codes").unwrap();
+        assert_eq!(codes.value, "1111\n2222");
+        assert_eq!(codes.field_type, FieldType::TextArea);
+        assert_eq!(
+            credential.notes.as_deref(),
+            Some("Remember to rotate yearly")
+        );
+    }
+
+    #[test]
+    fn test_notes_only_entry() {
+        let report =
+            PasswordStoreImporter::parse_entries([("wifi", "\nThe router is in the hall")]);
+        let credential = &report.credentials[0];
+        assert_eq!(credential.credential_type, "secure_note");
+        assert_eq!(
+            credential.notes.as_deref(),
+            Some("The router is in the hall")
+        );
+    }
+
+    #[test]
+    fn test_format_entry_round_trip() {
+        let original =
+            &PasswordStoreImporter::parse_entries([("websites/example", ENTRY)]).credentials[0];
+        let formatted = PasswordStoreExporter::format_entry(original);
+        assert!(formatted.starts_with("hunter2\nusername: jane\nurl: https://example.com\n"));
+        assert!(formatted.contains("otpauth://totp/example?secret=JBSWY3DPEHPK3PXP&issuer=Example"));
+
+        let reimported =
+            &PasswordStoreImporter::parse_entries([("websites/example", formatted)]).credentials[0];
+        assert_eq!(reimported.fields, original.fields);
+        assert_eq!(reimported.notes, original.notes);
+    }
+
+    #[test]
+    fn test_entry_paths() {
+        let mut credential = CredentialRecord::new("a/b".to_string(), "login".to_string());
+        credential.folder_path = Some("Work//.hidden".to_string());
+        assert_eq!(
+            PasswordStoreExporter::entry_path(&credential),
+            "Work/hidden/a-b"
+        );
+
+        let duplicate = credential.clone();
+        let entries = PasswordStoreExporter::export_entries(&[credential, duplicate]);
+        assert_eq!(entries[1].0, "Work/hidden/a-b (2)");
+    }
+
+    #[test]
+    fn test_directory_round_trip() {
+        let store = TempDir::new().unwrap();
+        std::fs::write(store.path().join(".gpg-id"), "jane@example.com\n").unwrap();
+
+        let report = PasswordStoreImporter::parse_entries([
+            ("email/example.com", ENTRY),
+            ("bank", "s3cret\nuser: jdoe\n"),
+        ]);
+        let written =
+            PasswordStoreExporter::export_dir(store.path(), &report.credentials, &PlainCrypto)
+                .unwrap();
+        assert_eq!(written, 2);
+        assert!(store.path().join("email/example.com.gpg").is_file());
+
+        // Exporting again would clobber existing entries
+        assert!(
+            PasswordStoreExporter::export_dir(store.path(), &report.credentials, &PlainCrypto)
+                .is_err()
+        );
+
+        std::fs::create_dir(store.path().join(".git")).unwrap();
+        std::fs::write(store.path().join(".git/ignored.gpg"), "nope").unwrap();
+        std::fs::write(store.path().join("broken.gpg"), "").unwrap();
+
+        let imported = PasswordStoreImporter::import_dir(store.path(), &PlainCrypto).unwrap();
+        assert_eq!(imported.imported_count(), 2);
+        assert_eq!(imported.errors.len(), 1);
+        assert_eq!(imported.errors[0].title.as_deref(), Some("broken"));
+
+        let bank = imported
+            .credentials
+            .iter()
+            .find(|c| c.title == "bank")
+            .unwrap();
+        assert_eq!(bank.folder_path, None);
+        assert_eq!(bank.get_field("username").unwrap().value, "jdoe");
+    }
+
+    #[test]
+    fn test_export_requires_gpg_id() {
+        let store = TempDir::new().unwrap();
+        let result = PasswordStoreExporter::export_dir(store.path(), &[], &PlainCrypto);
+        assert!(matches!(result, Err(CoreError::ValidationError { .. })));
+    }
+}
@@ -0,0 +1,211 @@
+//! QR codes for authenticator enrollment and credential sharing
+//!
+//! A [`QrCode`] is the module matrix of one QR symbol. Apps either draw the
+//! matrix themselves or take the SVG from [`QrCode::to_svg`]. The payloads
+//! are the `otpauth://` URI of a TOTP secret, for scanning into an
+//! authenticator app, and the link form of a [`SharePayload`], for moving a
+//! credential to a phone camera, and the `WIFI:` payload of a
+//! [`WifiNetwork`], for guests to join it. All of them carry secrets: show
+//! the code only while the user asks for it.
+
+use qrcode::types::QrError as EncodeError;
+use qrcode::{Color, EcLevel};
+use std::fmt;
+use std::fmt::Write as _;
+
+use super::sharing::SharePayload;
+use super::totp::TotpGenerator;
+use crate::models::WifiNetwork;
+
+/// Light modules required around the symbol for scanners to find it
+pub const QUIET_ZONE: usize = 4;
+
+/// Error types for QR code generation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QrError {
+    /// The payload does not fit in the largest QR symbol
+    DataTooLong,
+    /// The payload could not be encoded for another reason
+    Encoding(String),
+}
+
+impl fmt::Display for QrError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QrError::DataTooLong => write!(f, "Too much data for a QR code"),
+            QrError::Encoding(msg) => write!(f, "Failed to create QR code: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for QrError {}
+
+/// Result type for QR code generation
+pub type QrResult<T> = Result<T, QrError>;
+
+/// Module matrix of a QR symbol, without the quiet zone
+#[derive(Clone, PartialEq, Eq)]
+pub struct QrCode {
+    width: usize,
+    modules: Vec<bool>,
+}
+
+impl fmt::Debug for QrCode {
+    // The matrix encodes the payload, which may be a secret
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("QrCode")
+            .field("width", &self.width)
+            .finish_non_exhaustive()
+    }
+}
+
+impl QrCode {
+    /// Encode `data` with medium error correction
+    pub fn encode(data: &str) -> QrResult<Self> {
+        Self::encode_with(data, EcLevel::M)
+    }
+
+    /// The `otpauth://` URI of `generator`, for authenticator apps
+    pub fn for_totp(generator: &TotpGenerator) -> QrResult<Self> {
+        Self::encode(&generator.to_uri())
+    }
+
+    /// The link form of a shared credential, for the app on another device
+    ///
+    /// Uses low error correction, since share payloads are long and the
+    /// code is read from a screen rather than print.
+    pub fn for_share(payload: &SharePayload) -> QrResult<Self> {
+        Self::encode_with(&payload.to_link(), EcLevel::L)
+    }
+
+    /// The `WIFI:` payload of a network, for guests to join it
+    pub fn for_wifi(network: &WifiNetwork) -> QrResult<Self> {
+        Self::encode(&network.to_payload())
+    }
+
+    fn encode_with(data: &str, level: EcLevel) -> QrResult<Self> {
+        let code =
+            qrcode::QrCode::with_error_correction_level(data, level).map_err(|e| match e {
+                EncodeError::DataTooLong => QrError::DataTooLong,
+                other => QrError::Encoding(other.to_string()),
+            })?;
+        Ok(Self {
+            width: code.width(),
+            modules: code
+                .into_colors()
+                .into_iter()
+                .map(|color| color == Color::Dark)
+                .collect(),
+        })
+    }
+
+    /// Modules per side
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Whether the module in column `x` of row `y` is dark; outside the
+    /// symbol, as in the quiet zone, everything is light
+    pub fn is_dark(&self, x: usize, y: usize) -> bool {
+        x < self.width && y < self.width && self.modules[y * self.width + x]
+    }
+
+    /// Rows from top to bottom, `true` for dark modules
+    pub fn to_matrix(&self) -> Vec<Vec<bool>> {
+        self.modules
+            .chunks(self.width)
+            .map(|row| row.to_vec())
+            .collect()
+    }
+
+    /// Standalone SVG, `module_size` pixels per module, with the quiet zone
+    ///
+    /// Dark modules are black on a white background whatever the app's
+    /// theme, since scanners expect dark on light.
+    pub fn to_svg(&self, module_size: u32) -> String {
+        let side = self.width + 2 * QUIET_ZONE;
+        let pixels = side as u64 * u64::from(module_size.max(1));
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{pixels}\" height=\"{pixels}\" \
+             viewBox=\"0 0 {side} {side}\" shape-rendering=\"crispEdges\">\
+             <rect width=\"{side}\" height=\"{side}\" fill=\"#fff\"/><path fill=\"#000\" d=\""
+        );
+        for y in 0..self.width {
+            let mut x = 0;
+            while x < self.width {
+                if !self.is_dark(x, y) {
+                    x += 1;
+                    continue;
+                }
+                // One rectangle per run of dark modules in the row
+                let start = x;
+                while x < self.width && self.is_dark(x, y) {
+                    x += 1;
+                }
+                let _ = write!(
+                    svg,
+                    "M{} {}h{}v1h-{}z",
+                    start + QUIET_ZONE,
+                    y + QUIET_ZONE,
+                    x - start,
+                    x - start
+                );
+            }
+        }
+        svg.push_str("\"/></svg>");
+        svg
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::WifiSecurity;
+    use crate::utils::sharing::{CredentialSharer, ShareIdentity, SharePolicy};
+    use crate::CredentialRecord;
+
+    #[test]
+    fn test_matrix_and_svg() {
+        let code = QrCode::encode("https://example.com").unwrap();
+        assert_eq!(code.width(), 25);
+        let matrix = code.to_matrix();
+        assert_eq!(matrix.len(), 25);
+        assert!(matrix.iter().all(|row| row.len() == 25));
+
+        // Finder pattern in the top-left corner
+        assert!(code.is_dark(0, 0) && code.is_dark(6, 6));
+        assert!(!code.is_dark(1, 1) && code.is_dark(2, 2));
+        assert!(!code.is_dark(25, 0));
+
+        let svg = code.to_svg(4);
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"132\""));
+        assert!(svg.contains("viewBox=\"0 0 33 33\""));
+        assert!(svg.contains("M4 4h7v1h-7z"));
+        assert!(svg.ends_with("\"/></svg>"));
+    }
+
+    #[test]
+    fn test_payloads() {
+        let totp = TotpGenerator::new("JBSWY3DPEHPK3PXP")
+            .unwrap()
+            .with_label(Some("Example"), Some("alice@example.com"));
+        let code = QrCode::for_totp(&totp).unwrap();
+        assert!(code.width() > 21);
+        assert!(!format!("{:?}", code).contains("true"));
+
+        let identity = ShareIdentity::generate();
+        let credential = CredentialRecord::new("Wi-Fi".to_string(), "login".to_string());
+        let payload =
+            CredentialSharer::share(&credential, &identity.recipient(), SharePolicy::new())
+                .unwrap();
+        assert!(QrCode::for_share(&payload).is_ok());
+
+        let network = WifiNetwork::new("Home", WifiSecurity::Wpa, "secret");
+        assert_eq!(
+            QrCode::for_wifi(&network),
+            QrCode::encode("WIFI:T:WPA;S:Home;P:secret;;")
+        );
+
+        assert_eq!(QrCode::encode(&"x".repeat(5000)), Err(QrError::DataTooLong));
+    }
+}
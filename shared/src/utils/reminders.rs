@@ -0,0 +1,351 @@
+//! Expiry and renewal reminders
+//!
+//! Collects the dates by which something in the vault has to be renewed:
+//! a credential's own `expires_at`, the expiry of a payment card, date
+//! fields such as a PGP key's "Expires", and password rotation policies.
+//! Reminders are only computed, never stored; the desktop app lists them as
+//! needing attention and mobile apps schedule notifications from them.
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::models::{CredentialRecord, FieldType};
+
+const DAY: i64 = 86_400;
+
+/// Default number of days before a due date that a reminder appears
+pub const DEFAULT_LEAD_DAYS: u32 = 30;
+
+/// What has to be renewed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReminderKind {
+    /// The credential's `expires_at`
+    Expiry,
+    /// A payment card's expiry date field
+    CardExpiry,
+    /// A date field named like an expiry date
+    FieldExpiry,
+    /// The password is older than the credential's rotation policy allows
+    Rotation,
+}
+
+impl ReminderKind {
+    /// Human-readable description
+    pub fn describe(&self) -> &'static str {
+        match self {
+            ReminderKind::Expiry => "Expires",
+            ReminderKind::CardExpiry => "Card expires",
+            ReminderKind::FieldExpiry => "Expires",
+            ReminderKind::Rotation => "Password change due",
+        }
+    }
+}
+
+/// Whether the due date has passed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReminderStatus {
+    Overdue,
+    Upcoming,
+}
+
+/// Something in a credential due for renewal
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Reminder {
+    pub credential_id: String,
+    pub title: String,
+    pub kind: ReminderKind,
+    /// Field the date was read from, if any
+    pub field: Option<String>,
+    /// When it expires or falls due (Unix timestamp)
+    pub due_at: i64,
+    pub status: ReminderStatus,
+    /// Whole days until `due_at`, negative once overdue
+    pub days_left: i64,
+}
+
+impl Reminder {
+    /// One-line summary such as "Card expires in 12 days"
+    pub fn summary(&self) -> String {
+        let when = match self.days_left {
+            0 => "today".to_string(),
+            1 => "tomorrow".to_string(),
+            -1 => "1 day ago".to_string(),
+            days if days < 0 => format!("{} days ago", -days),
+            days => format!("in {} days", days),
+        };
+        format!("{} {}", self.kind.describe(), when)
+    }
+}
+
+/// Computes reminders for a set of credentials
+#[derive(Debug, Clone)]
+pub struct ReminderEngine {
+    lead_days: u32,
+    now: Option<i64>,
+}
+
+impl Default for ReminderEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ReminderEngine {
+    /// Remind [`DEFAULT_LEAD_DAYS`] ahead, against the current time
+    pub fn new() -> Self {
+        Self {
+            lead_days: DEFAULT_LEAD_DAYS,
+            now: None,
+        }
+    }
+
+    /// Days before a due date that its reminder appears
+    pub fn with_lead_days(mut self, lead_days: u32) -> Self {
+        self.lead_days = lead_days;
+        self
+    }
+
+    /// Evaluate against a fixed clock instead of the current time
+    pub fn with_now(mut self, now: i64) -> Self {
+        self.now = Some(now);
+        self
+    }
+
+    /// Overdue and upcoming reminders, soonest first
+    pub fn reminders(&self, credentials: &[CredentialRecord]) -> Vec<Reminder> {
+        let now = self.now.unwrap_or_else(|| Utc::now().timestamp());
+        let horizon = now + i64::from(self.lead_days) * DAY;
+
+        let mut reminders: Vec<Reminder> = credentials
+            .iter()
+            .flat_map(|credential| {
+                due_dates(credential)
+                    .into_iter()
+                    .filter(|&(_, _, due_at)| due_at <= horizon)
+                    .map(move |(kind, field, due_at)| Reminder {
+                        credential_id: credential.id.clone(),
+                        title: credential.title.clone(),
+                        kind,
+                        field,
+                        due_at,
+                        status: if due_at <= now {
+                            ReminderStatus::Overdue
+                        } else {
+                            ReminderStatus::Upcoming
+                        },
+                        days_left: (due_at - now).div_euclid(DAY),
+                    })
+            })
+            .collect();
+
+        reminders.sort_by(|a, b| a.due_at.cmp(&b.due_at).then(a.title.cmp(&b.title)));
+        reminders
+    }
+
+    /// Reminders whose due date has passed
+    pub fn overdue(&self, credentials: &[CredentialRecord]) -> Vec<Reminder> {
+        self.reminders(credentials)
+            .into_iter()
+            .filter(|reminder| reminder.status == ReminderStatus::Overdue)
+            .collect()
+    }
+}
+
+/// Every renewal date of a credential, whether near or not
+fn due_dates(credential: &CredentialRecord) -> Vec<(ReminderKind, Option<String>, i64)> {
+    let mut dates = Vec::new();
+    if let Some(expires_at) = credential.expires_at {
+        dates.push((ReminderKind::Expiry, None, expires_at));
+    }
+
+    let mut names: Vec<&String> = credential.fields.keys().collect();
+    names.sort();
+    for name in names {
+        let field = &credential.fields[name];
+        let due_at = match field.field_type {
+            FieldType::ExpiryDate => {
+                card_expiry(&field.value).map(|at| (ReminderKind::CardExpiry, at))
+            }
+            FieldType::Date if name.to_ascii_lowercase().contains("expir") => {
+                parse_field_date(&field.value).map(|at| (ReminderKind::FieldExpiry, at))
+            }
+            _ => None,
+        };
+        if let Some((kind, at)) = due_at {
+            dates.push((kind, Some(name.clone()), at));
+        }
+    }
+
+    if let Some(days) = credential.rotation_days.filter(|&days| days > 0) {
+        if let Some(changed_at) = last_password_change(credential) {
+            dates.push((
+                ReminderKind::Rotation,
+                None,
+                changed_at + i64::from(days) * DAY,
+            ));
+        }
+    }
+    dates
+}
+
+/// A card is valid through the last day of its `MM/YY` month
+fn card_expiry(value: &str) -> Option<i64> {
+    let (month, year) = value.trim().split_once('/')?;
+    let month: u32 = month.trim().parse().ok().filter(|m| (1..=12).contains(m))?;
+    let year: i32 = match year.trim() {
+        year if year.len() == 2 => 2000 + year.parse::<i32>().ok()?,
+        year => year.parse().ok()?,
+    };
+    let (year, month) = if month == 12 {
+        (year + 1, 1)
+    } else {
+        (year, month + 1)
+    };
+    start_of(NaiveDate::from_ymd_opt(year, month, 1)?)
+}
+
+/// A date in any of the formats date fields accept
+fn parse_field_date(value: &str) -> Option<i64> {
+    ["%Y-%m-%d", "%Y/%m/%d", "%m/%d/%Y", "%m-%d-%Y"]
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(value.trim(), format).ok())
+        .and_then(start_of)
+}
+
+fn start_of(date: NaiveDate) -> Option<i64> {
+    Some(date.and_hms_opt(0, 0, 0)?.and_utc().timestamp())
+}
+
+/// When the password was last set: the time kept on the field, or for
+/// fields written before that was tracked, its latest replacement in the
+/// history, or the credential's creation if it never changed
+fn last_password_change(credential: &CredentialRecord) -> Option<i64> {
+    let passwords: Vec<_> = credential
+        .fields
+        .values()
+        .filter(|field| field.field_type == FieldType::Password && !field.value.is_empty())
+        .collect();
+    if passwords.is_empty() {
+        return None;
+    }
+    if let Some(changed) = passwords
+        .iter()
+        .filter_map(|field| field.value_updated_at)
+        .max()
+    {
+        return Some(changed);
+    }
+    let changed = credential
+        .field_history
+        .iter()
+        .filter(|entry| {
+            credential
+                .fields
+                .get(&entry.field)
+                .is_some_and(|field| field.field_type == FieldType::Password)
+        })
+        .map(|entry| entry.replaced_at)
+        .max();
+    Some(changed.unwrap_or(credential.created_at))
+}
+
+/// The `YYYY-MM-DD` date of a due time, in UTC
+pub fn due_date_label(due_at: i64) -> String {
+    DateTime::<Utc>::from_timestamp(due_at, 0)
+        .map(|at| at.format("%Y-%m-%d").to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{CredentialField, FieldHistoryEntry};
+    use crate::utils::time_utils::parse_date;
+
+    fn at(date: &str) -> i64 {
+        parse_date(date).unwrap()
+    }
+
+    #[test]
+    fn test_expiry_sources() {
+        let now = at("2026-03-10");
+        let mut passport = CredentialRecord::new("Passport".into(), "identity".into());
+        passport.expires_at = Some(at("2026-03-25"));
+
+        let mut card = CredentialRecord::new("Visa".into(), "credit_card".into());
+        card.set_field(
+            "expiry",
+            CredentialField::new(FieldType::ExpiryDate, "02/26".into(), true),
+        );
+
+        let mut key = CredentialRecord::new("Signing key".into(), "pgp_key".into());
+        key.set_field(
+            "expires",
+            CredentialField::new(FieldType::Date, "2026/06/01".into(), false),
+        );
+
+        let engine = ReminderEngine::new().with_now(now);
+        let reminders = engine.reminders(&[passport.clone(), card, key.clone()]);
+        let found: Vec<(&str, ReminderKind, ReminderStatus, i64)> = reminders
+            .iter()
+            .map(|r| (r.title.as_str(), r.kind, r.status, r.days_left))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (
+                    "Visa",
+                    ReminderKind::CardExpiry,
+                    ReminderStatus::Overdue,
+                    -9
+                ),
+                (
+                    "Passport",
+                    ReminderKind::Expiry,
+                    ReminderStatus::Upcoming,
+                    15
+                ),
+            ]
+        );
+        assert_eq!(reminders[0].summary(), "Card expires 9 days ago");
+        assert_eq!(reminders[0].field.as_deref(), Some("expiry"));
+
+        let later = engine.with_lead_days(90).reminders(&[key]);
+        assert_eq!(later.len(), 1);
+        assert_eq!(later[0].kind, ReminderKind::FieldExpiry);
+        assert_eq!(due_date_label(later[0].due_at), "2026-06-01");
+    }
+
+    #[test]
+    fn test_rotation_counts_from_last_change() {
+        let mut login = CredentialRecord::new("Router".into(), "login".into());
+        login.created_at = at("2025-01-01");
+        login.rotation_days = Some(90);
+        login.set_field("password", CredentialField::password("n3w-secret"));
+        // As for a vault written before change times were kept
+        login.fields.get_mut("password").unwrap().value_updated_at = None;
+
+        let engine = ReminderEngine::new().with_now(at("2026-01-01"));
+        assert_eq!(engine.overdue(std::slice::from_ref(&login)).len(), 1);
+
+        login.field_history.push(FieldHistoryEntry {
+            field: "password".into(),
+            value: "old-secret".into(),
+            replaced_at: at("2025-11-01"),
+            removed: false,
+        });
+        let reminders = engine.reminders(std::slice::from_ref(&login));
+        assert_eq!(reminders.len(), 1);
+        assert_eq!(reminders[0].status, ReminderStatus::Upcoming);
+        assert_eq!(due_date_label(reminders[0].due_at), "2026-01-30");
+
+        login.fields.get_mut("password").unwrap().value_updated_at = Some(at("2025-10-20"));
+        let reminders = engine.reminders(std::slice::from_ref(&login));
+        assert_eq!(due_date_label(reminders[0].due_at), "2026-01-18");
+
+        login.rotation_days = None;
+        assert!(engine.reminders(&[login]).is_empty());
+    }
+}
@@ -7,7 +7,7 @@
 use regex::{Regex, RegexBuilder};
 use std::collections::{HashMap, HashSet};
 
-use crate::models::{CredentialRecord, FieldType};
+use crate::models::{CredentialRecord, DataClassification, FieldType};
 
 /// Search query with multiple criteria
 #[derive(Debug, Clone, PartialEq)]
@@ -27,7 +27,9 @@ pub struct SearchQuery {
     /// Field type filters
     pub field_types: Vec<FieldType>,
 
-    /// Search in sensitive fields
+    /// Search in the values of sensitive fields such as passwords
+    ///
+    /// Passphrase-protected fields are never searched.
     pub include_sensitive: bool,
 
     /// Case sensitive search
@@ -39,6 +41,9 @@ pub struct SearchQuery {
     /// Search in field values
     pub search_field_values: bool,
 
+    /// Search in field names, e.g. those of custom fields
+    pub search_field_names: bool,
+
     /// Search in notes
     pub search_notes: bool,
 
@@ -47,6 +52,15 @@ pub struct SearchQuery {
 
     /// Folder path filter
     pub folder_path: Option<String>,
+
+    /// Compliance owner (case-insensitive exact match)
+    pub owner: Option<String>,
+
+    /// Data classifications to include
+    pub classifications: Vec<DataClassification>,
+
+    /// Only credentials never reviewed or last reviewed before this Unix timestamp
+    pub reviewed_before: Option<i64>,
 }
 
 impl Default for SearchQuery {
@@ -61,9 +75,13 @@ impl Default for SearchQuery {
             case_sensitive: false,
             use_regex: false,
             search_field_values: true,
+            search_field_names: false,
             search_notes: true,
             favorites_only: false,
             folder_path: None,
+            owner: None,
+            classifications: Vec::new(),
+            reviewed_before: None,
         }
     }
 }
@@ -105,6 +123,8 @@ pub struct SearchMatch {
 pub enum MatchLocation {
     Title,
     FieldValue,
+    /// The name a field is stored under
+    FieldName,
     FieldLabel,
     Notes,
     Tag,
@@ -158,6 +178,24 @@ impl SearchQuery {
         self
     }
 
+    /// Search in field values
+    pub fn search_field_values(mut self, search: bool) -> Self {
+        self.search_field_values = search;
+        self
+    }
+
+    /// Search in field names
+    pub fn search_field_names(mut self, search: bool) -> Self {
+        self.search_field_names = search;
+        self
+    }
+
+    /// Search in notes
+    pub fn search_notes(mut self, search: bool) -> Self {
+        self.search_notes = search;
+        self
+    }
+
     /// Use case sensitive search
     pub fn case_sensitive(mut self, sensitive: bool) -> Self {
         self.case_sensitive = sensitive;
@@ -181,6 +219,38 @@ impl SearchQuery {
         self.folder_path = Some(folder.into());
         self
     }
+
+    /// Filter by compliance owner
+    pub fn owned_by<S: Into<String>>(mut self, owner: S) -> Self {
+        self.owner = Some(owner.into());
+        self
+    }
+
+    /// Filter by data classification
+    pub fn classification(mut self, classification: DataClassification) -> Self {
+        self.classifications.push(classification);
+        self
+    }
+
+    /// Only credentials whose access review is older than `timestamp`
+    pub fn reviewed_before(mut self, timestamp: i64) -> Self {
+        self.reviewed_before = Some(timestamp);
+        self
+    }
+}
+
+impl SearchResult {
+    /// Names of the fields the text was found in, by value, name or label,
+    /// in the order they were matched
+    pub fn matched_fields(&self) -> Vec<&str> {
+        let mut fields: Vec<&str> = Vec::new();
+        for name in self.matches.iter().filter_map(|m| m.field_name.as_deref()) {
+            if !fields.contains(&name) {
+                fields.push(name);
+            }
+        }
+        fields
+    }
 }
 
 /// Search engine for credentials
@@ -234,6 +304,11 @@ impl CredentialSearchEngine {
             }
         }
 
+        // Filter by compliance metadata
+        if !Self::matches_compliance(credential, query) {
+            return None;
+        }
+
         // Filter by credential types
         if !query.credential_types.is_empty() {
             if !query.credential_types.contains(&credential.credential_type) {
@@ -295,6 +370,40 @@ impl CredentialSearchEngine {
         })
     }
 
+    /// Check the owner, classification and review date filters
+    fn matches_compliance(credential: &CredentialRecord, query: &SearchQuery) -> bool {
+        let compliance = credential.compliance.as_ref();
+
+        if let Some(owner) = &query.owner {
+            let owned = compliance
+                .and_then(|c| c.owner.as_deref())
+                .is_some_and(|o| o.trim().eq_ignore_ascii_case(owner.trim()));
+            if !owned {
+                return false;
+            }
+        }
+
+        if !query.classifications.is_empty() {
+            let classified = compliance
+                .and_then(|c| c.classification)
+                .is_some_and(|c| query.classifications.contains(&c));
+            if !classified {
+                return false;
+            }
+        }
+
+        if let Some(before) = query.reviewed_before {
+            let reviewed_recently = compliance
+                .and_then(|c| c.last_reviewed_at)
+                .is_some_and(|at| at >= before);
+            if reviewed_recently {
+                return false;
+            }
+        }
+
+        true
+    }
+
     /// Search for text within a credential
     fn search_text_in_credential(
         credential: &CredentialRecord,
@@ -338,11 +447,28 @@ impl CredentialSearchEngine {
             }
         }
 
+        // Search in field names
+        if query.search_field_names {
+            for field_name in credential.fields.keys() {
+                if let Some((score, name_matches)) = Self::search_in_text(
+                    field_name,
+                    search_text,
+                    query,
+                    MatchLocation::FieldName,
+                    Some(field_name.clone()),
+                ) {
+                    total_score += score;
+                    matches.extend(name_matches);
+                }
+            }
+        }
+
         // Search in field values and labels
         if query.search_field_values {
             for (field_name, field) in &credential.fields {
-                // Skip sensitive fields if not included
-                if field.sensitive && !query.include_sensitive {
+                // Skip sensitive fields if not included; protected values
+                // are only a placeholder
+                if (field.sensitive && !query.include_sensitive) || field.is_protected() {
                     continue;
                 }
 
@@ -746,6 +872,47 @@ mod tests {
         assert_eq!(results[0].credential.title, "Work Email");
     }
 
+    #[test]
+    fn test_compliance_filters() {
+        let mut credentials = HashMap::new();
+        let mut aws = create_test_credential("AWS Root", "login");
+        let compliance = aws.compliance_mut();
+        compliance.owner = Some("Platform Team".to_string());
+        compliance.classification = Some(DataClassification::Restricted);
+        compliance.last_reviewed_at = Some(1_000);
+        let mut wiki = create_test_credential("Wiki", "login");
+        wiki.compliance_mut().owner = Some("platform team".to_string());
+        wiki.compliance_mut().last_reviewed_at = Some(5_000);
+        let unowned = create_test_credential("Printer", "login");
+
+        for credential in [aws, wiki, unowned] {
+            credentials.insert(credential.id.clone(), credential);
+        }
+
+        let owned = CredentialSearchEngine::search(
+            &credentials,
+            &SearchQuery::default().owned_by("Platform Team"),
+        );
+        assert_eq!(owned.len(), 2);
+
+        let restricted = CredentialSearchEngine::search(
+            &credentials,
+            &SearchQuery::default().classification(DataClassification::Restricted),
+        );
+        assert_eq!(restricted.len(), 1);
+        assert_eq!(restricted[0].credential.title, "AWS Root");
+
+        let mut overdue: Vec<String> = CredentialSearchEngine::search(
+            &credentials,
+            &SearchQuery::default().reviewed_before(2_000),
+        )
+        .into_iter()
+        .map(|result| result.credential.title)
+        .collect();
+        overdue.sort();
+        assert_eq!(overdue, vec!["AWS Root", "Printer"]);
+    }
+
     #[test]
     fn test_case_insensitive_search() {
         let mut credentials = HashMap::new();
@@ -810,6 +977,63 @@ mod tests {
         assert_eq!(results.len(), 1);
     }
 
+    #[test]
+    fn test_field_names_and_matched_fields() {
+        let mut credentials = HashMap::new();
+        let mut credential = create_test_credential("Server", "login");
+        credential.set_field("recovery_email", CredentialField::email("ops@example.com"));
+        credential.set_field("pin", CredentialField::password("4711"));
+        credential.notes = Some("Reset via the ops portal".to_string());
+        credentials.insert(credential.id.clone(), credential);
+
+        // Field names are only searched on request
+        let query = SearchQuery::text("recovery");
+        assert!(CredentialSearchEngine::search(&credentials, &query).is_empty());
+        let results = CredentialSearchEngine::search(&credentials, &query.search_field_names(true));
+        assert_eq!(results[0].matched_fields(), vec!["recovery_email"]);
+        assert_eq!(results[0].matches[0].location, MatchLocation::FieldName);
+
+        let results = CredentialSearchEngine::search(&credentials, &SearchQuery::text("ops"));
+        assert_eq!(results[0].matched_fields(), vec!["recovery_email"]);
+        assert!(results[0]
+            .matches
+            .iter()
+            .any(|m| m.location == MatchLocation::Notes));
+        let values_only = SearchQuery::text("ops").search_notes(false);
+        let results = CredentialSearchEngine::search(&credentials, &values_only);
+        assert!(results[0]
+            .matches
+            .iter()
+            .all(|m| m.location == MatchLocation::FieldValue));
+        assert!(CredentialSearchEngine::search(
+            &credentials,
+            &SearchQuery::text("ops")
+                .search_notes(false)
+                .search_field_values(false)
+        )
+        .is_empty());
+
+        let pin = SearchQuery::text("4711");
+        assert!(CredentialSearchEngine::search(&credentials, &pin).is_empty());
+        let results = CredentialSearchEngine::search(&credentials, &pin.include_sensitive(true));
+        assert_eq!(results[0].matched_fields(), vec!["pin"]);
+    }
+
+    #[test]
+    fn test_protected_fields_are_never_searched() {
+        use crate::utils::CredentialCrypto;
+
+        let mut credentials = HashMap::new();
+        let mut credential = create_test_credential("Vault", "login");
+        let mut field = CredentialField::text("secret answer");
+        CredentialCrypto::protect_field(&mut field, "extra").unwrap();
+        credential.set_field("answer", field);
+        credentials.insert(credential.id.clone(), credential);
+
+        let query = SearchQuery::text("protected").include_sensitive(true);
+        assert!(CredentialSearchEngine::search(&credentials, &query).is_empty());
+    }
+
     #[test]
     fn test_favorites_filter() {
         let mut credentials = HashMap::new();
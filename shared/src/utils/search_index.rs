@@ -0,0 +1,497 @@
+//! Indexed, typo-tolerant credential search
+//!
+//! [`CredentialSearchEngine`](crate::utils::search::CredentialSearchEngine)
+//! scans every credential for a substring, which is fine for a one-off filter
+//! but neither tolerates typos nor scales with the vault. A [`SearchIndex`]
+//! keeps the searchable words of each credential together with a trigram
+//! index over them. The owner of the credentials updates it whenever one is
+//! added, changed or deleted, so a search only has to look at credentials
+//! sharing trigrams with the query.
+//!
+//! Queries are parsed by [`ParsedSearch`]: `tag:bank type:login gmail`
+//! finds logins tagged "bank" that mention something like "gmail". Matches
+//! in the title rank above the username, which ranks above tags, other
+//! fields and finally notes.
+//!
+//! Sensitive and passphrase-protected fields are never indexed, so a search
+//! cannot be used to probe their values.
+
+use std::collections::{HashMap, HashSet};
+
+use zeroize::Zeroize;
+
+use crate::models::{CredentialRecord, FieldType};
+
+/// Padding around each word so trigrams also mark where words start and end
+const WORD_BOUNDARY: char = '\u{0}';
+
+/// Bonus for favorites, enough to order otherwise equal hits
+const FAVORITE_BONUS: f64 = 0.1;
+
+type Trigram = [char; 3];
+
+/// Where in a credential a word was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexedField {
+    Title,
+    /// Fields of type username or email
+    Username,
+    Tag,
+    /// Any other field that is neither sensitive nor protected
+    Field,
+    Notes,
+}
+
+impl IndexedField {
+    /// How much a match here counts towards a credential's rank
+    pub fn weight(self) -> f64 {
+        match self {
+            IndexedField::Title => 4.0,
+            IndexedField::Username => 3.0,
+            IndexedField::Tag => 2.0,
+            IndexedField::Field => 1.5,
+            IndexedField::Notes => 1.0,
+        }
+    }
+}
+
+/// A search string split into free text and filters
+///
+/// Words of the form `key:value` with a known key are filters, everything
+/// else is text. Values containing spaces can be quoted, as in
+/// `tag:"online banking"`. Every text word and every `tag:` filter has to
+/// match; several `type:` filters match any of the types.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParsedSearch {
+    /// Lowercased words that must each match somewhere
+    pub terms: Vec<String>,
+    /// `tag:` filters, all required
+    pub tags: Vec<String>,
+    /// `type:` filters, any one suffices
+    pub types: Vec<String>,
+    /// `folder:` filter, matching the folder and its subfolders
+    pub folder: Option<String>,
+}
+
+impl ParsedSearch {
+    /// Parse a search string as typed by the user
+    pub fn parse(query: &str) -> Self {
+        let mut parsed = Self::default();
+        for word in split_query(query) {
+            let filter = word.split_once(':').and_then(|(key, value)| {
+                let value = value.trim();
+                (!value.is_empty()).then(|| (key.to_ascii_lowercase(), value.to_lowercase()))
+            });
+            match filter {
+                Some((key, value)) if key == "tag" => parsed.tags.push(value),
+                Some((key, value)) if key == "type" => parsed.types.push(value),
+                Some((key, value)) if key == "folder" => {
+                    parsed.folder = Some(value.trim_matches('/').to_string())
+                }
+                _ => parsed.terms.extend(tokenize(&word)),
+            }
+        }
+        parsed
+    }
+
+    /// Whether the search matches every credential
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+            && self.tags.is_empty()
+            && self.types.is_empty()
+            && self.folder.is_none()
+    }
+}
+
+/// A credential found by [`SearchIndex::search`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchHit {
+    pub id: String,
+    /// Higher is better; only meaningful relative to other hits
+    pub score: f64,
+}
+
+/// What the index knows about one credential
+#[derive(Debug, Clone)]
+struct IndexedCredential {
+    title: String,
+    credential_type: String,
+    tags: Vec<String>,
+    folder: Option<String>,
+    favorite: bool,
+    words: Vec<(IndexedField, String)>,
+}
+
+impl IndexedCredential {
+    fn new(credential: &CredentialRecord) -> Self {
+        let mut words = Vec::new();
+        let mut add = |field, text: &str| {
+            words.extend(tokenize(text).into_iter().map(|word| (field, word)));
+        };
+
+        add(IndexedField::Title, &credential.title);
+        for tag in &credential.tags {
+            add(IndexedField::Tag, tag);
+        }
+        for field in credential.fields.values() {
+            if field.sensitive || field.is_protected() {
+                continue;
+            }
+            let kind = match field.field_type {
+                FieldType::Username | FieldType::Email => IndexedField::Username,
+                _ => IndexedField::Field,
+            };
+            add(kind, &field.value);
+        }
+        if let Some(notes) = &credential.notes {
+            add(IndexedField::Notes, notes);
+        }
+        words.sort_by(|a, b| a.1.cmp(&b.1).then(b.0.weight().total_cmp(&a.0.weight())));
+        words.dedup_by(|a, b| a.1 == b.1);
+
+        Self {
+            title: credential.title.to_lowercase(),
+            credential_type: credential.credential_type.to_lowercase(),
+            tags: credential.tags.iter().map(|t| t.to_lowercase()).collect(),
+            folder: credential
+                .folder_path
+                .as_ref()
+                .map(|f| f.trim_matches('/').to_lowercase()),
+            favorite: credential.favorite,
+            words,
+        }
+    }
+
+    fn passes_filters(&self, search: &ParsedSearch) -> bool {
+        search.tags.iter().all(|tag| self.tags.contains(tag))
+            && (search.types.is_empty() || search.types.contains(&self.credential_type))
+            && search.folder.as_ref().is_none_or(|folder| {
+                self.folder
+                    .as_deref()
+                    .is_some_and(|own| own == folder || own.starts_with(&format!("{folder}/")))
+            })
+    }
+
+    /// Rank against the search terms, or `None` if a term matches nothing
+    fn score(&self, terms: &[String]) -> Option<f64> {
+        let mut total = 0.0;
+        for term in terms {
+            total += self
+                .words
+                .iter()
+                .filter_map(|(field, word)| match_quality(term, word).map(|q| q * field.weight()))
+                .max_by(f64::total_cmp)?;
+        }
+        if self.favorite {
+            total += FAVORITE_BONUS;
+        }
+        Some(total)
+    }
+
+    fn trigrams(&self) -> HashSet<Trigram> {
+        self.words
+            .iter()
+            .flat_map(|(_, word)| word_trigrams(word))
+            .collect()
+    }
+}
+
+/// Incrementally maintained full-text index over a set of credentials
+#[derive(Debug, Clone, Default)]
+pub struct SearchIndex {
+    credentials: HashMap<String, IndexedCredential>,
+    trigrams: HashMap<Trigram, HashSet<String>>,
+}
+
+impl SearchIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Index exactly the given credentials
+    pub fn rebuild<'a>(&mut self, credentials: impl IntoIterator<Item = &'a CredentialRecord>) {
+        self.clear();
+        for credential in credentials {
+            self.insert(credential);
+        }
+    }
+
+    /// Add a credential, replacing what was indexed under its ID before
+    pub fn insert(&mut self, credential: &CredentialRecord) {
+        self.remove(&credential.id);
+        let indexed = IndexedCredential::new(credential);
+        for trigram in indexed.trigrams() {
+            self.trigrams
+                .entry(trigram)
+                .or_default()
+                .insert(credential.id.clone());
+        }
+        self.credentials.insert(credential.id.clone(), indexed);
+    }
+
+    /// Forget a credential; returns whether it was indexed
+    pub fn remove(&mut self, id: &str) -> bool {
+        let Some(indexed) = self.credentials.remove(id) else {
+            return false;
+        };
+        for trigram in indexed.trigrams() {
+            if let Some(ids) = self.trigrams.get_mut(&trigram) {
+                ids.remove(id);
+                if ids.is_empty() {
+                    self.trigrams.remove(&trigram);
+                }
+            }
+        }
+        true
+    }
+
+    /// Forget every credential
+    pub fn clear(&mut self) {
+        self.credentials.clear();
+        self.trigrams.clear();
+    }
+
+    /// Overwrite the indexed words before forgetting them, see
+    /// [`UnifiedMemoryRepository::wipe`](crate::core::UnifiedMemoryRepository::wipe)
+    pub fn wipe(&mut self) {
+        for (_, mut indexed) in self.credentials.drain() {
+            indexed.title.zeroize();
+            for (_, word) in indexed.words.iter_mut() {
+                word.zeroize();
+            }
+        }
+        self.trigrams = HashMap::new();
+    }
+
+    /// Number of indexed credentials
+    pub fn len(&self) -> usize {
+        self.credentials.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.credentials.is_empty()
+    }
+
+    /// Parse `query` and search for it, best hits first
+    pub fn search(&self, query: &str) -> Vec<SearchHit> {
+        self.search_parsed(&ParsedSearch::parse(query))
+    }
+
+    /// Search for an already parsed query, best hits first
+    ///
+    /// Without text terms every credential passing the filters is a hit,
+    /// ordered by title.
+    pub fn search_parsed(&self, search: &ParsedSearch) -> Vec<SearchHit> {
+        let mut hits: Vec<(SearchHit, &str)> = self
+            .candidates(&search.terms)
+            .into_iter()
+            .filter_map(|id| {
+                let (id, indexed) = self.credentials.get_key_value(id)?;
+                if !indexed.passes_filters(search) {
+                    return None;
+                }
+                let score = indexed.score(&search.terms)?;
+                let hit = SearchHit {
+                    id: id.clone(),
+                    score,
+                };
+                Some((hit, indexed.title.as_str()))
+            })
+            .collect();
+
+        hits.sort_by(|(a, a_title), (b, b_title)| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| a_title.cmp(b_title))
+                .then_with(|| a.id.cmp(&b.id))
+        });
+        hits.into_iter().map(|(hit, _)| hit).collect()
+    }
+
+    /// IDs that can possibly match every term
+    ///
+    /// A term that is found, or found with up to `k` typos, shares at least
+    /// one trigram with the word it matches as long as it is longer than
+    /// `4 * k` characters. Shorter terms fall back to every credential.
+    fn candidates(&self, terms: &[String]) -> HashSet<&String> {
+        let mut candidates: Option<HashSet<&String>> = None;
+        for term in terms {
+            let length = term.chars().count();
+            if length < 3 || length <= 4 * max_typos(length) {
+                continue;
+            }
+            let ids: HashSet<&String> = word_trigrams(term)
+                .iter()
+                .filter_map(|trigram| self.trigrams.get(trigram))
+                .flatten()
+                .collect();
+            candidates = Some(match candidates {
+                Some(previous) => previous.intersection(&ids).copied().collect(),
+                None => ids,
+            });
+        }
+        candidates.unwrap_or_else(|| self.credentials.keys().collect())
+    }
+}
+
+/// Split a query into words, keeping quoted parts together
+fn split_query(query: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in query.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !current.is_empty() {
+                    words.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+    words
+}
+
+/// Lowercase alphanumeric words of `text`
+fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+fn word_trigrams(word: &str) -> Vec<Trigram> {
+    let padded: Vec<char> = std::iter::once(WORD_BOUNDARY)
+        .chain(word.chars())
+        .chain(std::iter::once(WORD_BOUNDARY))
+        .collect();
+    padded.windows(3).map(|w| [w[0], w[1], w[2]]).collect()
+}
+
+/// Typos tolerated in a term of `length` characters
+fn max_typos(length: usize) -> usize {
+    match length {
+        0..=3 => 0,
+        4..=8 => 1,
+        _ => 2,
+    }
+}
+
+/// How well `term` matches `word`, from 1.0 for the word itself down to
+/// close misspellings of it or of its beginning
+fn match_quality(term: &str, word: &str) -> Option<f64> {
+    if word == term {
+        return Some(1.0);
+    }
+    if word.starts_with(term) {
+        return Some(0.8);
+    }
+    if word.contains(term) {
+        return Some(0.6);
+    }
+
+    let term: Vec<char> = term.chars().collect();
+    let word: Vec<char> = word.chars().collect();
+    let allowed = max_typos(term.len());
+    if allowed == 0 {
+        return None;
+    }
+    let prefix = &word[..word.len().min(term.len())];
+    let distance = edit_distance(&term, &word).min(edit_distance(&term, prefix));
+    (distance <= allowed).then(|| 0.5 / distance as f64)
+}
+
+/// Edit distance counting a swap of adjacent characters as one edit
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    let width = b.len() + 1;
+    let mut rows = vec![0usize; (a.len() + 1) * width];
+    for (j, cell) in rows.iter_mut().take(width).enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        rows[i * width] = i;
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut best = (rows[(i - 1) * width + j] + 1)
+                .min(rows[i * width + j - 1] + 1)
+                .min(rows[(i - 1) * width + j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                best = best.min(rows[(i - 2) * width + j - 2] + 1);
+            }
+            rows[i * width + j] = best;
+        }
+    }
+    rows[a.len() * width + b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::CredentialField;
+
+    fn login(id: &str, title: &str, username: &str, tags: &[&str]) -> CredentialRecord {
+        let mut credential = CredentialRecord::new(title.to_string(), "login".to_string());
+        credential.id = id.to_string();
+        credential.set_field("username", CredentialField::username(username));
+        credential.set_field("password", CredentialField::password("gmail-secret"));
+        for tag in tags {
+            credential.add_tag(*tag);
+        }
+        credential
+    }
+
+    fn ids(hits: Vec<SearchHit>) -> Vec<String> {
+        hits.into_iter().map(|hit| hit.id).collect()
+    }
+
+    #[test]
+    fn test_parse_filters_and_quoted_values() {
+        let parsed = ParsedSearch::parse(r#"Tag:Bank type:login tag:"online banking" GMail.com"#);
+        assert_eq!(parsed.tags, vec!["bank", "online banking"]);
+        assert_eq!(parsed.types, vec!["login"]);
+        assert_eq!(parsed.terms, vec!["gmail", "com"]);
+        assert_eq!(ParsedSearch::parse("https://example.org").terms.len(), 3);
+        assert!(ParsedSearch::parse("  ").is_empty());
+    }
+
+    #[test]
+    fn test_ranking_typos_and_filters() {
+        let mut index = SearchIndex::new();
+        let mut notes = login("notes", "Forum", "someone", &[]);
+        notes.notes = Some("recovery mail goes to gmail".to_string());
+        index.insert(&notes);
+        index.insert(&login("user", "Calendar", "me@gmail.com", &["bank"]));
+        index.insert(&login("title", "Gmail", "me", &["bank"]));
+
+        assert_eq!(ids(index.search("gmail")), vec!["title", "user", "notes"]);
+        assert_eq!(ids(index.search("gmial")), vec!["title", "user", "notes"]);
+        assert_eq!(ids(index.search("tag:bank gmail")), vec!["title", "user"]);
+        assert!(index.search("type:note gmail").is_empty());
+        assert!(index.search("calendar forum").is_empty());
+        // Password values are not indexed
+        assert!(index.search("secret").is_empty());
+
+        index.insert(&login("title", "Outlook", "me", &[]));
+        assert_eq!(ids(index.search("gmail")), vec!["user", "notes"]);
+        assert!(index.remove("user"));
+        assert!(!index.remove("user"));
+        assert_eq!(ids(index.search("tag:bank")), Vec::<String>::new());
+        assert_eq!(ids(index.search("")), vec!["notes", "title"]);
+
+        index.wipe();
+        assert!(index.is_empty());
+    }
+
+    #[test]
+    fn test_edit_distance_counts_swaps_once() {
+        let chars = |s: &str| s.chars().collect::<Vec<_>>();
+        assert_eq!(edit_distance(&chars("gmial"), &chars("gmail")), 1);
+        assert_eq!(edit_distance(&chars("kitten"), &chars("sitting")), 3);
+        assert_eq!(match_quality("bnk", "bank"), None);
+        assert!(match_quality("paypl", "paypal").is_some());
+    }
+}
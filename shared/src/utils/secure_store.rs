@@ -0,0 +1,205 @@
+//! Encrypted per-vault key-value store for state kept outside the archive
+//!
+//! Some state belongs to one vault on one device, such as where the user was
+//! in the window when the vault locked. It should not travel with the archive
+//! to other devices, yet it can name credentials and search terms, so it may
+//! not be written in plain text either. A [`SecureStore`] keeps such values in
+//! a file of its own, sealed with the vault's password like a protected
+//! field, so it only opens while the vault is unlocked.
+//!
+//! [`UnifiedRepositoryManager`](crate::core::UnifiedRepositoryManager) opens
+//! the store of a vault when it is opened and writes it back when the vault
+//! is closed or locked, see
+//! [`set_secure_store_dir`](crate::core::UnifiedRepositoryManager::set_secure_store_dir).
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tracing::warn;
+use zeroize::Zeroizing;
+
+use super::encryption::{CredentialCrypto, EncryptionUtils};
+use crate::core::{CoreError, CoreResult};
+use crate::models::FieldProtection;
+
+/// Values of one vault, sealed with its password
+pub struct SecureStore {
+    path: PathBuf,
+    passphrase: Zeroizing<String>,
+    values: BTreeMap<String, serde_json::Value>,
+    modified: bool,
+}
+
+impl std::fmt::Debug for SecureStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SecureStore")
+            .field("path", &self.path)
+            .field("keys", &self.values.keys().collect::<Vec<_>>())
+            .field("modified", &self.modified)
+            .finish()
+    }
+}
+
+impl SecureStore {
+    /// File in `directory` holding the store of the archive at `archive_path`
+    ///
+    /// The name is a hash of the path, so the directory does not list which
+    /// vaults exist.
+    pub fn path_for(directory: &Path, archive_path: &str) -> PathBuf {
+        let digest = Sha256::digest(archive_path.as_bytes());
+        let name = EncryptionUtils::to_hex(&digest[..16]);
+        directory.join(format!("{}.store", name))
+    }
+
+    /// Open the store at `path`
+    ///
+    /// A missing file is an empty store. So is one that `passphrase` does not
+    /// open, e.g. after the master password was changed elsewhere; it is
+    /// replaced on the next save.
+    pub fn open(path: impl Into<PathBuf>, passphrase: &str) -> CoreResult<Self> {
+        let path = path.into();
+        let mut store = Self {
+            path,
+            passphrase: Zeroizing::new(passphrase.to_string()),
+            values: BTreeMap::new(),
+            modified: false,
+        };
+
+        let data = match std::fs::read(&store.path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(store),
+            Err(e) => return Err(CoreError::FileOperation(e.into())),
+        };
+        let opened = serde_json::from_slice::<FieldProtection>(&data)
+            .ok()
+            .and_then(|envelope| CredentialCrypto::open_envelope(&envelope, passphrase).ok())
+            .and_then(|json| serde_json::from_str(&json).ok());
+        match opened {
+            Some(values) => store.values = values,
+            None => warn!("Discarding unreadable secure store {:?}", store.path),
+        }
+        Ok(store)
+    }
+
+    /// The value stored under `key`, if there is one of type `T`
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        self.values
+            .get(key)
+            .and_then(|value| serde_json::from_value(value.clone()).ok())
+    }
+
+    /// Store `value` under `key`
+    pub fn set<T: Serialize>(&mut self, key: &str, value: &T) -> CoreResult<()> {
+        let value = serde_json::to_value(value).map_err(|e| CoreError::SerializationError {
+            message: e.to_string(),
+        })?;
+        if self.values.get(key) != Some(&value) {
+            self.values.insert(key.to_string(), value);
+            self.modified = true;
+        }
+        Ok(())
+    }
+
+    /// Forget the value under `key`
+    pub fn remove(&mut self, key: &str) {
+        if self.values.remove(key).is_some() {
+            self.modified = true;
+        }
+    }
+
+    /// Whether values changed since the store was opened or saved
+    pub fn is_modified(&self) -> bool {
+        self.modified
+    }
+
+    /// Seal the store with `passphrase` from now on, e.g. after the master
+    /// password was changed
+    pub fn set_passphrase(&mut self, passphrase: &str) {
+        self.passphrase = Zeroizing::new(passphrase.to_string());
+        self.modified = true;
+    }
+
+    /// Write the store if it changed
+    pub fn save(&mut self) -> CoreResult<()> {
+        if !self.modified {
+            return Ok(());
+        }
+        let json = Zeroizing::new(serde_json::to_string(&self.values).map_err(|e| {
+            CoreError::SerializationError {
+                message: e.to_string(),
+            }
+        })?);
+        let envelope = CredentialCrypto::seal_envelope(&json, &self.passphrase).map_err(|e| {
+            CoreError::InternalError {
+                message: format!("Could not seal secure store: {}", e),
+            }
+        })?;
+        let data = serde_json::to_vec(&envelope).map_err(|e| CoreError::SerializationError {
+            message: e.to_string(),
+        })?;
+        write_private(&self.path, &data).map_err(|e| CoreError::FileOperation(e.into()))?;
+        self.modified = false;
+        Ok(())
+    }
+}
+
+/// Write `data` to `path` through a temporary file, readable by the owner only
+fn write_private(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    use std::io::Write;
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let temp = path.with_extension("store.tmp");
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(&temp)?;
+    file.write_all(data)?;
+    file.sync_all()?;
+    std::fs::rename(&temp, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_and_wrong_passphrase() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = SecureStore::path_for(dir.path(), "/home/jane/vault.7z");
+        assert_ne!(
+            path,
+            SecureStore::path_for(dir.path(), "/home/jane/other.7z")
+        );
+
+        let mut store = SecureStore::open(&path, "password").unwrap();
+        assert_eq!(store.get::<String>("search"), None);
+        store.set("search", &"github").unwrap();
+        store.set("scroll", &0.5f32).unwrap();
+        store.save().unwrap();
+        assert!(!store.is_modified());
+        let on_disk = std::fs::read_to_string(&path).unwrap();
+        assert!(!on_disk.contains("github"));
+
+        let mut store = SecureStore::open(&path, "password").unwrap();
+        assert_eq!(store.get::<String>("search").as_deref(), Some("github"));
+        assert_eq!(store.get::<f32>("scroll"), Some(0.5));
+        assert_eq!(store.get::<u32>("search"), None);
+        store.set("search", &"github").unwrap();
+        assert!(!store.is_modified());
+        store.remove("search");
+        assert!(store.is_modified());
+
+        // Another password sees an empty store instead of failing
+        let store = SecureStore::open(&path, "changed").unwrap();
+        assert_eq!(store.get::<f32>("scroll"), None);
+    }
+}
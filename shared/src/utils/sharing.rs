@@ -0,0 +1,948 @@
+//! Credential sharing with encrypted, self-expiring payloads
+//!
+//! A single credential is encrypted for one recipient and can be sent over
+//! any channel. The construction follows age: an ephemeral X25519 key agrees
+//! a secret with the recipient's public key, HKDF-SHA256 derives a
+//! ChaCha20-Poly1305 key from it, and the sharing policy (expiry, view limit)
+//! is bound to the ciphertext as associated data so it cannot be edited.
+
+use base64::prelude::*;
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use chrono::{DateTime, Utc};
+use hkdf::Hkdf;
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use x25519_dalek::{EphemeralSecret, PublicKey, StaticSecret};
+
+use super::import::{finish, ImportReport};
+use crate::core::UnifiedMemoryRepository;
+use crate::models::{CredentialRecord, VaultMember};
+
+const PUBLIC_KEY_PREFIX: &str = "zlpk1";
+const SECRET_KEY_PREFIX: &str = "zlsk1";
+const PAYLOAD_PREFIX: &str = "zlshare1:";
+const LINK_PREFIX: &str = "ziplock://share/";
+const HKDF_INFO: &[u8] = b"ziplock-share-v1";
+const PAYLOAD_VERSION: u8 = 1;
+const VAULT_AAD_TAG: &str = "ziplock-vault-v1";
+const VAULT_VERSION: u8 = 1;
+
+/// Extension of the files [`SharedVault::write_to_folder`] writes
+pub const SHARED_VAULT_EXTENSION: &str = "zlvault";
+
+/// Error types for sharing operations
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SharingError {
+    /// Key string is malformed
+    InvalidKey,
+    /// Payload is malformed or uses an unsupported version
+    InvalidPayload(String),
+    /// Payload was not encrypted for this key, or was tampered with
+    DecryptionFailed,
+    /// The share expired at the given time
+    Expired(DateTime<Utc>),
+    /// The share has already been opened the maximum number of times
+    ViewLimitReached(u32),
+    /// Recording a view or writing a shared vault file failed
+    Storage(String),
+    /// The credentials to share could not be read from the repository
+    Repository(String),
+}
+
+impl fmt::Display for SharingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SharingError::InvalidKey => write!(f, "Invalid sharing key"),
+            SharingError::InvalidPayload(msg) => write!(f, "Invalid shared credential: {}", msg),
+            SharingError::DecryptionFailed => {
+                write!(f, "Shared credential could not be decrypted with this key")
+            }
+            SharingError::Expired(at) => {
+                write!(f, "Shared credential expired on {}", at.to_rfc3339())
+            }
+            SharingError::ViewLimitReached(max) => write!(
+                f,
+                "Shared credential has already been opened {} time(s)",
+                max
+            ),
+            SharingError::Storage(msg) => write!(f, "Failed to store shared data: {}", msg),
+            SharingError::Repository(msg) => {
+                write!(f, "Failed to read credentials to share: {}", msg)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SharingError {}
+
+/// Result type for sharing operations
+pub type SharingResult<T> = Result<T, SharingError>;
+
+/// A recipient's secret key, used to open credentials shared with them
+pub struct ShareIdentity {
+    secret: StaticSecret,
+}
+
+impl ShareIdentity {
+    /// Generate a new random identity
+    pub fn generate() -> Self {
+        Self {
+            secret: StaticSecret::random_from_rng(OsRng),
+        }
+    }
+
+    /// Public key to give to people who want to share with this identity
+    pub fn recipient(&self) -> ShareRecipient {
+        ShareRecipient {
+            key: PublicKey::from(&self.secret),
+        }
+    }
+}
+
+impl fmt::Display for ShareIdentity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}{}",
+            SECRET_KEY_PREFIX,
+            BASE64_URL_SAFE_NO_PAD.encode(self.secret.as_bytes())
+        )
+    }
+}
+
+impl fmt::Debug for ShareIdentity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ShareIdentity")
+            .field("recipient", &self.recipient().to_string())
+            .finish_non_exhaustive()
+    }
+}
+
+impl FromStr for ShareIdentity {
+    type Err = SharingError;
+
+    fn from_str(s: &str) -> SharingResult<Self> {
+        let bytes = decode_key(s, SECRET_KEY_PREFIX)?;
+        Ok(Self {
+            secret: StaticSecret::from(bytes),
+        })
+    }
+}
+
+/// A recipient's public key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShareRecipient {
+    key: PublicKey,
+}
+
+impl fmt::Display for ShareRecipient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}{}",
+            PUBLIC_KEY_PREFIX,
+            BASE64_URL_SAFE_NO_PAD.encode(self.key.as_bytes())
+        )
+    }
+}
+
+impl FromStr for ShareRecipient {
+    type Err = SharingError;
+
+    fn from_str(s: &str) -> SharingResult<Self> {
+        let bytes = decode_key(s, PUBLIC_KEY_PREFIX)?;
+        Ok(Self {
+            key: PublicKey::from(bytes),
+        })
+    }
+}
+
+fn decode_key(s: &str, prefix: &str) -> SharingResult<[u8; 32]> {
+    let encoded = s
+        .trim()
+        .strip_prefix(prefix)
+        .ok_or(SharingError::InvalidKey)?;
+    BASE64_URL_SAFE_NO_PAD
+        .decode(encoded)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or(SharingError::InvalidKey)
+}
+
+/// Limits on how long and how often a share can be opened
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SharePolicy {
+    /// After this time the share can no longer be opened
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Number of times the share can be opened
+    pub max_views: Option<u32>,
+}
+
+impl SharePolicy {
+    /// A policy without limits
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Expire at a fixed time
+    pub fn expires_at(mut self, at: DateTime<Utc>) -> Self {
+        self.expires_at = Some(at);
+        self
+    }
+
+    /// Expire after a duration from now
+    pub fn expires_in(self, duration: chrono::Duration) -> Self {
+        self.expires_at(Utc::now() + duration)
+    }
+
+    /// Allow at most `views` openings (1 = one-time share)
+    pub fn max_views(mut self, views: u32) -> Self {
+        self.max_views = Some(views.max(1));
+        self
+    }
+}
+
+/// An encrypted credential ready to be sent
+///
+/// The header fields are readable without the key (so an expired share can
+/// be rejected early) but are authenticated: changing any of them makes
+/// decryption fail.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SharePayload {
+    /// Format version
+    pub version: u8,
+    /// Random identifier used to count views
+    pub id: String,
+    /// Expiry time, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+    /// View limit, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_views: Option<u32>,
+    /// Sender's ephemeral X25519 public key (base64url)
+    pub ephemeral_key: String,
+    /// ChaCha20-Poly1305 nonce (base64url)
+    pub nonce: String,
+    /// Encrypted credential JSON (base64url)
+    pub ciphertext: String,
+}
+
+impl SharePayload {
+    /// Compact text form (`zlshare1:...`) for pasting into any channel
+    pub fn to_text(&self) -> String {
+        let json = serde_json::to_vec(self).expect("share payload serializes");
+        format!("{}{}", PAYLOAD_PREFIX, BASE64_URL_SAFE_NO_PAD.encode(json))
+    }
+
+    /// Link form (`ziplock://share/zlshare1:...`) that the apps can open
+    pub fn to_link(&self) -> String {
+        format!("{}{}", LINK_PREFIX, self.to_text())
+    }
+
+    /// Parse the text or link form
+    pub fn parse(s: &str) -> SharingResult<Self> {
+        let s = s.trim();
+        let encoded = s
+            .strip_prefix(LINK_PREFIX)
+            .unwrap_or(s)
+            .strip_prefix(PAYLOAD_PREFIX)
+            .ok_or_else(|| SharingError::InvalidPayload("not a ZipLock share".to_string()))?;
+
+        let json = BASE64_URL_SAFE_NO_PAD
+            .decode(encoded)
+            .map_err(|e| SharingError::InvalidPayload(e.to_string()))?;
+        let payload: SharePayload = serde_json::from_slice(&json)
+            .map_err(|e| SharingError::InvalidPayload(e.to_string()))?;
+
+        if payload.version != PAYLOAD_VERSION {
+            return Err(SharingError::InvalidPayload(format!(
+                "unsupported version {}",
+                payload.version
+            )));
+        }
+        Ok(payload)
+    }
+
+    /// Whether the share has expired at `now`
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|at| now >= at)
+    }
+
+    /// Header bytes bound to the ciphertext
+    fn associated_data(&self) -> Vec<u8> {
+        format!(
+            "{}\n{}\n{}\n{}\n{}\n{}",
+            String::from_utf8_lossy(HKDF_INFO),
+            self.version,
+            self.id,
+            self.expires_at
+                .map(|at| at.timestamp().to_string())
+                .unwrap_or_default(),
+            self.max_views.map(|n| n.to_string()).unwrap_or_default(),
+            self.ephemeral_key
+        )
+        .into_bytes()
+    }
+}
+
+/// Counts how often each share has been opened on this device
+///
+/// View limits are enforced by the recipient's client. They stop a link from
+/// being reopened or forwarded and used again, but can't stop a recipient
+/// from copying the credential once it has been opened.
+pub trait ShareViewLedger {
+    /// Number of times the share has been opened
+    fn views(&self, id: &str) -> u32;
+
+    /// Record one more opening
+    fn record_view(&mut self, id: &str) -> SharingResult<()>;
+}
+
+/// View ledger kept in memory
+#[derive(Debug, Clone, Default)]
+pub struct MemoryViewLedger {
+    views: HashMap<String, u32>,
+}
+
+impl ShareViewLedger for MemoryViewLedger {
+    fn views(&self, id: &str) -> u32 {
+        self.views.get(id).copied().unwrap_or(0)
+    }
+
+    fn record_view(&mut self, id: &str) -> SharingResult<()> {
+        *self.views.entry(id.to_string()).or_insert(0) += 1;
+        Ok(())
+    }
+}
+
+/// View ledger persisted to a JSON file
+#[derive(Debug, Clone)]
+pub struct FileViewLedger {
+    path: PathBuf,
+    views: HashMap<String, u32>,
+}
+
+impl FileViewLedger {
+    /// Load the ledger at `path`, starting empty if it doesn't exist
+    pub fn open<P: AsRef<Path>>(path: P) -> SharingResult<Self> {
+        let path = path.as_ref().to_path_buf();
+        let views = match std::fs::read(&path) {
+            Ok(data) => serde_json::from_slice(&data)
+                .map_err(|e| SharingError::Storage(format!("{}: {}", path.display(), e)))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+            Err(e) => return Err(SharingError::Storage(format!("{}: {}", path.display(), e))),
+        };
+        Ok(Self { path, views })
+    }
+}
+
+impl ShareViewLedger for FileViewLedger {
+    fn views(&self, id: &str) -> u32 {
+        self.views.get(id).copied().unwrap_or(0)
+    }
+
+    fn record_view(&mut self, id: &str) -> SharingResult<()> {
+        *self.views.entry(id.to_string()).or_insert(0) += 1;
+
+        let storage = |e: String| SharingError::Storage(format!("{}: {}", self.path.display(), e));
+        let json = serde_json::to_vec(&self.views).map_err(|e| storage(e.to_string()))?;
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| storage(e.to_string()))?;
+        }
+        std::fs::write(&self.path, json).map_err(|e| storage(e.to_string()))
+    }
+}
+
+/// Encrypts credentials for sharing and opens shared credentials
+pub struct CredentialSharer;
+
+impl CredentialSharer {
+    /// Encrypt a credential for `recipient`
+    pub fn share(
+        credential: &CredentialRecord,
+        recipient: &ShareRecipient,
+        policy: SharePolicy,
+    ) -> SharingResult<SharePayload> {
+        let mut payload = SharePayload {
+            version: PAYLOAD_VERSION,
+            id: uuid::Uuid::new_v4().to_string(),
+            expires_at: policy.expires_at,
+            max_views: policy.max_views,
+            ephemeral_key: String::new(),
+            nonce: String::new(),
+            ciphertext: String::new(),
+        };
+
+        // Old passwords stay with the sender
+        let mut credential = credential.clone();
+        credential.field_history.clear();
+        let plaintext = serde_json::to_vec(&credential)
+            .map_err(|e| SharingError::InvalidPayload(e.to_string()))?;
+        let sealed = seal(recipient, &plaintext, |ephemeral_key| {
+            payload.ephemeral_key = ephemeral_key.to_string();
+            payload.associated_data()
+        })?;
+
+        payload.nonce = sealed.nonce;
+        payload.ciphertext = sealed.ciphertext;
+        Ok(payload)
+    }
+
+    /// Decrypt a shared credential, enforcing its expiry and view limit
+    ///
+    /// A successful open counts as one view. The returned credential gets a
+    /// new ID so it never collides with the sender's copy.
+    pub fn open(
+        payload: &SharePayload,
+        identity: &ShareIdentity,
+        ledger: &mut dyn ShareViewLedger,
+        now: DateTime<Utc>,
+    ) -> SharingResult<CredentialRecord> {
+        if payload.is_expired(now) {
+            return Err(SharingError::Expired(payload.expires_at.unwrap_or(now)));
+        }
+        if let Some(max) = payload.max_views {
+            if ledger.views(&payload.id) >= max {
+                return Err(SharingError::ViewLimitReached(max));
+            }
+        }
+
+        let plaintext = unseal(
+            identity,
+            &payload.ephemeral_key,
+            &payload.nonce,
+            &payload.ciphertext,
+            &payload.associated_data(),
+        )?;
+
+        let mut credential: CredentialRecord = serde_json::from_slice(&plaintext)
+            .map_err(|e| SharingError::InvalidPayload(e.to_string()))?;
+        ledger.record_view(&payload.id)?;
+
+        credential.id = uuid::Uuid::new_v4().to_string();
+        Ok(credential)
+    }
+
+    /// Open a share (text or link form) as an import report
+    ///
+    /// The report can be previewed and applied like any other import.
+    pub fn import(
+        text: &str,
+        identity: &ShareIdentity,
+        ledger: &mut dyn ShareViewLedger,
+    ) -> SharingResult<ImportReport> {
+        let payload = SharePayload::parse(text)?;
+        let credential = Self::open(&payload, identity, ledger, Utc::now())?;
+
+        let mut report = ImportReport::default();
+        report.push(1, finish(credential));
+        Ok(report)
+    }
+}
+
+/// The credentials of a shared vault that one member may see, encrypted to
+/// that member's key
+///
+/// Like [`SharePayload`], the header is readable and authenticated.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SharedVaultPackage {
+    /// Format version
+    pub version: u8,
+    /// Repository the credentials come from
+    pub repository_id: String,
+    /// Member the package is encrypted to
+    pub member: String,
+    pub created_at: DateTime<Utc>,
+    /// Sender's ephemeral X25519 public key (base64url)
+    pub ephemeral_key: String,
+    /// ChaCha20-Poly1305 nonce (base64url)
+    pub nonce: String,
+    /// Encrypted JSON list of credentials (base64url)
+    pub ciphertext: String,
+}
+
+impl SharedVaultPackage {
+    /// Name of the member's file in a synced folder
+    pub fn file_name(&self) -> String {
+        format!("{}.{}", self.member, SHARED_VAULT_EXTENSION)
+    }
+
+    /// Parse a package written by [`SharedVault::write_to_folder`]
+    pub fn parse(data: &[u8]) -> SharingResult<Self> {
+        let package: SharedVaultPackage = serde_json::from_slice(data)
+            .map_err(|e| SharingError::InvalidPayload(e.to_string()))?;
+        if package.version != VAULT_VERSION {
+            return Err(SharingError::InvalidPayload(format!(
+                "unsupported version {}",
+                package.version
+            )));
+        }
+        Ok(package)
+    }
+
+    fn associated_data(&self) -> Vec<u8> {
+        format!(
+            "{}\n{}\n{}\n{}\n{}\n{}",
+            VAULT_AAD_TAG,
+            self.version,
+            self.repository_id,
+            self.member,
+            self.created_at.timestamp(),
+            self.ephemeral_key
+        )
+        .into_bytes()
+    }
+}
+
+/// Re-encrypts the shared credentials of a vault to each member's key
+///
+/// Only the credentials whose `shared_with` lists a member go into that
+/// member's package, and they keep their IDs: importing a newer package
+/// updates the copies from the last one instead of duplicating them.
+pub struct SharedVault;
+
+impl SharedVault {
+    /// Encrypt the credentials `member` may see, out of `credentials`
+    pub fn seal(
+        credentials: &[CredentialRecord],
+        member: &VaultMember,
+        repository_id: &str,
+    ) -> SharingResult<SharedVaultPackage> {
+        let recipient = member.recipient()?;
+        let shared: Vec<CredentialRecord> = credentials
+            .iter()
+            .filter(|credential| credential.is_shared_with(&member.name))
+            .map(|credential| {
+                let mut credential = credential.clone();
+                credential.field_history.clear();
+                credential
+            })
+            .collect();
+
+        let mut package = SharedVaultPackage {
+            version: VAULT_VERSION,
+            repository_id: repository_id.to_string(),
+            member: member.name.clone(),
+            created_at: Utc::now(),
+            ephemeral_key: String::new(),
+            nonce: String::new(),
+            ciphertext: String::new(),
+        };
+        let plaintext =
+            serde_json::to_vec(&shared).map_err(|e| SharingError::InvalidPayload(e.to_string()))?;
+        let sealed = seal(&recipient, &plaintext, |ephemeral_key| {
+            package.ephemeral_key = ephemeral_key.to_string();
+            package.associated_data()
+        })?;
+
+        package.nonce = sealed.nonce;
+        package.ciphertext = sealed.ciphertext;
+        Ok(package)
+    }
+
+    /// One package for every member of the repository
+    ///
+    /// A member nothing is shared with still gets a package, so revoking
+    /// their access replaces the copies they were last sent.
+    pub fn export(repository: &UnifiedMemoryRepository) -> SharingResult<Vec<SharedVaultPackage>> {
+        let credentials = repository
+            .list_credentials()
+            .map_err(|e| SharingError::Repository(e.to_string()))?;
+        let repository_id = &repository.get_metadata().repository_id;
+        repository
+            .members()
+            .iter()
+            .map(|member| Self::seal(&credentials, member, repository_id))
+            .collect()
+    }
+
+    /// Write every member's package into `folder`, e.g. a synced directory
+    ///
+    /// Each file is written next to its final name and then renamed, so a
+    /// sync client never picks up half a package.
+    pub fn write_to_folder<P: AsRef<Path>>(
+        repository: &UnifiedMemoryRepository,
+        folder: P,
+    ) -> SharingResult<Vec<PathBuf>> {
+        let folder = folder.as_ref();
+        let storage = |path: &Path, e: std::io::Error| {
+            SharingError::Storage(format!("{}: {}", path.display(), e))
+        };
+        std::fs::create_dir_all(folder).map_err(|e| storage(folder, e))?;
+
+        let mut written = Vec::new();
+        for package in Self::export(repository)? {
+            let path = folder.join(package.file_name());
+            let partial = path.with_extension(format!("{}.partial", SHARED_VAULT_EXTENSION));
+            let json = serde_json::to_vec_pretty(&package)
+                .map_err(|e| SharingError::InvalidPayload(e.to_string()))?;
+            std::fs::write(&partial, json).map_err(|e| storage(&partial, e))?;
+            std::fs::rename(&partial, &path).map_err(|e| storage(&path, e))?;
+            written.push(path);
+        }
+        Ok(written)
+    }
+
+    /// Decrypt a package as an import report
+    ///
+    /// Applying the report with [`ImportReport::import_into`] updates the
+    /// credentials imported from an earlier package.
+    pub fn open(
+        package: &SharedVaultPackage,
+        identity: &ShareIdentity,
+    ) -> SharingResult<ImportReport> {
+        let plaintext = unseal(
+            identity,
+            &package.ephemeral_key,
+            &package.nonce,
+            &package.ciphertext,
+            &package.associated_data(),
+        )?;
+        let credentials: Vec<CredentialRecord> = serde_json::from_slice(&plaintext)
+            .map_err(|e| SharingError::InvalidPayload(e.to_string()))?;
+
+        let mut report = ImportReport::default();
+        for (index, credential) in credentials.into_iter().enumerate() {
+            report.push(index + 1, finish(credential));
+        }
+        Ok(report)
+    }
+}
+
+/// Base64url nonce and ciphertext; the ephemeral key has already gone into
+/// the header through `associated_data`
+struct Sealed {
+    nonce: String,
+    ciphertext: String,
+}
+
+/// Encrypt `plaintext` to `recipient` under a fresh ephemeral key
+///
+/// `associated_data` gets the encoded ephemeral key, so headers that include
+/// it can be bound to the ciphertext.
+fn seal(
+    recipient: &ShareRecipient,
+    plaintext: &[u8],
+    associated_data: impl FnOnce(&str) -> Vec<u8>,
+) -> SharingResult<Sealed> {
+    let ephemeral = EphemeralSecret::random_from_rng(OsRng);
+    let ephemeral_public = PublicKey::from(&ephemeral);
+    let shared = ephemeral.diffie_hellman(&recipient.key);
+    if !shared.was_contributory() {
+        return Err(SharingError::InvalidKey);
+    }
+
+    let mut nonce = [0u8; 12];
+    OsRng.fill_bytes(&mut nonce);
+    let ephemeral_key = BASE64_URL_SAFE_NO_PAD.encode(ephemeral_public.as_bytes());
+    let ciphertext = cipher(shared.as_bytes(), &ephemeral_public, &recipient.key)
+        .encrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: plaintext,
+                aad: &associated_data(&ephemeral_key),
+            },
+        )
+        .map_err(|_| SharingError::InvalidPayload("encryption failed".to_string()))?;
+
+    Ok(Sealed {
+        nonce: BASE64_URL_SAFE_NO_PAD.encode(nonce),
+        ciphertext: BASE64_URL_SAFE_NO_PAD.encode(ciphertext),
+    })
+}
+
+/// Decrypt what [`seal`] produced for `identity`
+fn unseal(
+    identity: &ShareIdentity,
+    ephemeral_key: &str,
+    nonce: &str,
+    ciphertext: &str,
+    associated_data: &[u8],
+) -> SharingResult<Vec<u8>> {
+    let decode = |value: &str| {
+        BASE64_URL_SAFE_NO_PAD
+            .decode(value)
+            .map_err(|e| SharingError::InvalidPayload(e.to_string()))
+    };
+    let ephemeral_public: [u8; 32] = decode(ephemeral_key)?
+        .try_into()
+        .map_err(|_| SharingError::InvalidPayload("bad ephemeral key".to_string()))?;
+    let nonce = decode(nonce)?;
+    if nonce.len() != 12 {
+        return Err(SharingError::InvalidPayload("bad nonce".to_string()));
+    }
+    let ciphertext = decode(ciphertext)?;
+
+    let ephemeral_public = PublicKey::from(ephemeral_public);
+    let shared = identity.secret.diffie_hellman(&ephemeral_public);
+    if !shared.was_contributory() {
+        return Err(SharingError::DecryptionFailed);
+    }
+
+    let recipient = identity.recipient();
+    cipher(shared.as_bytes(), &ephemeral_public, &recipient.key)
+        .decrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: &ciphertext,
+                aad: associated_data,
+            },
+        )
+        .map_err(|_| SharingError::DecryptionFailed)
+}
+
+/// ChaCha20-Poly1305 keyed from the X25519 shared secret
+fn cipher(shared: &[u8; 32], ephemeral: &PublicKey, recipient: &PublicKey) -> ChaCha20Poly1305 {
+    let mut salt = [0u8; 64];
+    salt[..32].copy_from_slice(ephemeral.as_bytes());
+    salt[32..].copy_from_slice(recipient.as_bytes());
+
+    let mut key = [0u8; 32];
+    Hkdf::<Sha256>::new(Some(&salt), shared)
+        .expand(HKDF_INFO, &mut key)
+        .expect("32 bytes is a valid HKDF-SHA256 output length");
+    ChaCha20Poly1305::new(Key::from_slice(&key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::CredentialField;
+
+    fn credential() -> CredentialRecord {
+        let mut credential = CredentialRecord::new("GitHub".to_string(), "login".to_string());
+        credential.set_field("username", CredentialField::username("octocat"));
+        credential.set_field("password", CredentialField::password("hunter2"));
+        credential
+    }
+
+    #[test]
+    fn test_share_round_trip() {
+        let identity = ShareIdentity::generate();
+        let original = credential();
+        let payload =
+            CredentialSharer::share(&original, &identity.recipient(), SharePolicy::new()).unwrap();
+
+        let parsed = SharePayload::parse(&payload.to_link()).unwrap();
+        assert_eq!(parsed, payload);
+
+        let mut ledger = MemoryViewLedger::default();
+        let opened = CredentialSharer::open(&parsed, &identity, &mut ledger, Utc::now()).unwrap();
+        assert_eq!(opened.title, "GitHub");
+        assert_eq!(opened.get_field("password").unwrap().value, "hunter2");
+        assert_ne!(opened.id, original.id);
+        assert!(!payload.to_text().contains("hunter2"));
+    }
+
+    #[test]
+    fn test_shared_vault_round_trip() {
+        let alice = ShareIdentity::generate();
+        let bob = ShareIdentity::generate();
+        let mut repository = UnifiedMemoryRepository::new();
+        repository.initialize().unwrap();
+        repository
+            .set_member(VaultMember::new("alice", &alice.recipient()))
+            .unwrap();
+        repository
+            .set_member(VaultMember::new("bob", &bob.recipient()))
+            .unwrap();
+
+        let mut shared = credential();
+        shared.shared_with = vec!["alice".to_string()];
+        repository.add_credential(shared.clone()).unwrap();
+        repository
+            .add_credential(CredentialRecord::new(
+                "Private".to_string(),
+                "note".to_string(),
+            ))
+            .unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let written = SharedVault::write_to_folder(&repository, dir.path()).unwrap();
+        assert_eq!(written.len(), 2);
+        let read = |name: &str| {
+            SharedVaultPackage::parse(&std::fs::read(dir.path().join(name)).unwrap()).unwrap()
+        };
+        let for_alice = read("alice.zlvault");
+        let for_bob = read("bob.zlvault");
+
+        // Each member gets only their subset, and only with their own key
+        let report = SharedVault::open(&for_alice, &alice).unwrap();
+        assert_eq!(report.credentials.len(), 1);
+        assert_eq!(report.credentials[0].id, shared.id);
+        assert!(SharedVault::open(&for_bob, &bob)
+            .unwrap()
+            .credentials
+            .is_empty());
+        assert_eq!(
+            SharedVault::open(&for_alice, &bob).unwrap_err(),
+            SharingError::DecryptionFailed
+        );
+
+        let mut renamed = for_alice.clone();
+        renamed.member = "bob".to_string();
+        assert_eq!(
+            SharedVault::open(&renamed, &alice).unwrap_err(),
+            SharingError::DecryptionFailed
+        );
+
+        // A newer package updates the copies from the last one
+        let mut own = UnifiedMemoryRepository::new();
+        own.initialize().unwrap();
+        report.import_into(&mut own).unwrap();
+        let again = SharedVault::open(&read("alice.zlvault"), &alice)
+            .unwrap()
+            .import_into(&mut own)
+            .unwrap();
+        assert_eq!(again.updated.len(), 1);
+        assert_eq!(own.list_credentials().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_key_strings_round_trip() {
+        let identity = ShareIdentity::generate();
+        let secret = identity.to_string();
+        assert!(secret.starts_with("zlsk1"));
+
+        let restored: ShareIdentity = secret.parse().unwrap();
+        assert_eq!(restored.recipient(), identity.recipient());
+
+        let recipient = identity.recipient().to_string();
+        assert!(recipient.starts_with("zlpk1"));
+        assert_eq!(
+            recipient.parse::<ShareRecipient>().unwrap(),
+            identity.recipient()
+        );
+
+        assert!("zlpk1short".parse::<ShareRecipient>().is_err());
+        assert!(recipient.parse::<ShareIdentity>().is_err());
+    }
+
+    #[test]
+    fn test_wrong_identity_cannot_open() {
+        let payload = CredentialSharer::share(
+            &credential(),
+            &ShareIdentity::generate().recipient(),
+            SharePolicy::new(),
+        )
+        .unwrap();
+
+        let result = CredentialSharer::open(
+            &payload,
+            &ShareIdentity::generate(),
+            &mut MemoryViewLedger::default(),
+            Utc::now(),
+        );
+        assert_eq!(result.unwrap_err(), SharingError::DecryptionFailed);
+    }
+
+    #[test]
+    fn test_expiry_enforced() {
+        let identity = ShareIdentity::generate();
+        let policy = SharePolicy::new().expires_in(chrono::Duration::hours(1));
+        let payload =
+            CredentialSharer::share(&credential(), &identity.recipient(), policy).unwrap();
+        let mut ledger = MemoryViewLedger::default();
+
+        let later = Utc::now() + chrono::Duration::hours(2);
+        assert!(matches!(
+            CredentialSharer::open(&payload, &identity, &mut ledger, later),
+            Err(SharingError::Expired(_))
+        ));
+        assert!(CredentialSharer::open(&payload, &identity, &mut ledger, Utc::now()).is_ok());
+    }
+
+    #[test]
+    fn test_policy_cannot_be_edited() {
+        let identity = ShareIdentity::generate();
+        let policy = SharePolicy::new()
+            .expires_in(chrono::Duration::hours(1))
+            .max_views(1);
+        let mut payload =
+            CredentialSharer::share(&credential(), &identity.recipient(), policy).unwrap();
+
+        // Extending the expiry or lifting the limit breaks authentication
+        payload.expires_at = Some(Utc::now() + chrono::Duration::days(365));
+        let result = CredentialSharer::open(
+            &payload,
+            &identity,
+            &mut MemoryViewLedger::default(),
+            Utc::now(),
+        );
+        assert_eq!(result.unwrap_err(), SharingError::DecryptionFailed);
+
+        payload.expires_at = policy.expires_at;
+        payload.max_views = None;
+        let result = CredentialSharer::open(
+            &payload,
+            &identity,
+            &mut MemoryViewLedger::default(),
+            Utc::now(),
+        );
+        assert_eq!(result.unwrap_err(), SharingError::DecryptionFailed);
+    }
+
+    #[test]
+    fn test_view_limit_enforced() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let ledger_path = dir.path().join("share-views.json");
+
+        let identity = ShareIdentity::generate();
+        let payload = CredentialSharer::share(
+            &credential(),
+            &identity.recipient(),
+            SharePolicy::new().max_views(2),
+        )
+        .unwrap();
+        let text = payload.to_text();
+
+        for _ in 0..2 {
+            let mut ledger = FileViewLedger::open(&ledger_path).unwrap();
+            let report = CredentialSharer::import(&text, &identity, &mut ledger).unwrap();
+            assert_eq!(report.imported_count(), 1);
+        }
+
+        // The count survives reloading the ledger
+        let mut ledger = FileViewLedger::open(&ledger_path).unwrap();
+        assert_eq!(ledger.views(&payload.id), 2);
+        assert_eq!(
+            CredentialSharer::import(&text, &identity, &mut ledger).unwrap_err(),
+            SharingError::ViewLimitReached(2)
+        );
+    }
+
+    #[test]
+    fn test_failed_open_does_not_count_as_view() {
+        let identity = ShareIdentity::generate();
+        let payload = CredentialSharer::share(
+            &credential(),
+            &identity.recipient(),
+            SharePolicy::new().max_views(1),
+        )
+        .unwrap();
+
+        let mut ledger = MemoryViewLedger::default();
+        assert!(CredentialSharer::open(
+            &payload,
+            &ShareIdentity::generate(),
+            &mut ledger,
+            Utc::now()
+        )
+        .is_err());
+        assert_eq!(ledger.views(&payload.id), 0);
+        assert!(CredentialSharer::open(&payload, &identity, &mut ledger, Utc::now()).is_ok());
+    }
+
+    #[test]
+    fn test_parse_rejects_garbage() {
+        assert!(SharePayload::parse("hello").is_err());
+        assert!(SharePayload::parse("zlshare1:!!!").is_err());
+        assert!(SharePayload::parse("ziplock://share/zlshare1:e30").is_err());
+    }
+}
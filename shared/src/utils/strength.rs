@@ -0,0 +1,1242 @@
+//! Pattern-aware password strength estimation
+//!
+//! Estimates how many guesses an attacker needs, in the manner of Dropbox's
+//! zxcvbn. The password is matched against the patterns cracking tools try
+//! first: ranked dictionaries (also reversed and with l33t substitutions),
+//! keyboard walks, repeats, sequences, years and dates. The cheapest way to
+//! cover the whole password with those matches, brute-forcing any gaps, gives
+//! the estimate. Counting character classes rates `P@ssw0rd1` as strong;
+//! this rates it as the common password it is, and a few random words as
+//! strong without any symbols.
+//!
+//! Feedback names the weakest pattern found. Its text comes from the
+//! `strength` section of the message catalogs, so it is available in the
+//! languages in [`SUPPORTED_LANGUAGES`].
+
+use chrono::Datelike;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::OnceLock;
+
+use crate::utils::localization::{Localizer, Message, SUPPORTED_LOCALES};
+
+/// Languages feedback is available in (ISO 639-1), English being the default
+pub const SUPPORTED_LANGUAGES: &[&str] = SUPPORTED_LOCALES;
+
+const BRUTEFORCE_CARDINALITY: f64 = 10.0;
+const MIN_SUBMATCH_GUESSES_SINGLE_CHAR: f64 = 10.0;
+const MIN_SUBMATCH_GUESSES_MULTI_CHAR: f64 = 50.0;
+const MIN_YEAR_SPACE: i32 = 20;
+const DATE_MIN_YEAR: i32 = 1000;
+const DATE_MAX_YEAR: i32 = 2050;
+const MAX_SEQUENCE_DELTA: i32 = 5;
+const MAX_L33T_COMBINATIONS: usize = 64;
+const MAX_ANALYZED_LENGTH: usize = 100;
+
+/// Number of guesses needed to find `password`
+pub fn estimate_guesses(password: &str) -> f64 {
+    estimate(password).guesses
+}
+
+/// Ranked word lists
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Dictionary {
+    Passwords,
+    English,
+    Names,
+}
+
+/// What part of a password looks like
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Pattern {
+    Dictionary {
+        dictionary: Dictionary,
+        rank: usize,
+        reversed: bool,
+        l33t: bool,
+    },
+    Spatial {
+        turns: usize,
+    },
+    Repeat {
+        base_len: usize,
+    },
+    Sequence,
+    Year,
+    Date,
+    Bruteforce,
+}
+
+/// A pattern found at chars `i..=j` of the password
+#[derive(Debug, Clone)]
+pub(crate) struct Match {
+    pub i: usize,
+    pub j: usize,
+    pub token: String,
+    pub pattern: Pattern,
+    pub guesses: f64,
+}
+
+/// Guess estimate and the patterns it is made of
+#[derive(Debug, Clone)]
+pub(crate) struct Estimate {
+    pub guesses: f64,
+    pub sequence: Vec<Match>,
+}
+
+impl Estimate {
+    pub fn guesses_log10(&self) -> f64 {
+        self.guesses.log10()
+    }
+
+    /// Whether the password is a listed common password, possibly
+    /// capitalized, reversed or with l33t substitutions
+    pub fn is_common(&self) -> bool {
+        matches!(
+            self.sequence.as_slice(),
+            [Match {
+                pattern: Pattern::Dictionary {
+                    dictionary: Dictionary::Passwords,
+                    ..
+                },
+                ..
+            }]
+        )
+    }
+}
+
+/// Find the least guessable way to cover `password` with patterns
+///
+/// Only the first [`MAX_ANALYZED_LENGTH`] characters are analyzed; any
+/// password that long is out of reach of guessing anyway.
+pub(crate) fn estimate(password: &str) -> Estimate {
+    let chars: Vec<char> = password.chars().take(MAX_ANALYZED_LENGTH).collect();
+    if chars.is_empty() {
+        return Estimate {
+            guesses: 1.0,
+            sequence: Vec::new(),
+        };
+    }
+    most_guessable_sequence(&chars, omnimatch(&chars))
+}
+
+// ---------------------------------------------------------------------------
+// Matching
+
+fn omnimatch(chars: &[char]) -> Vec<Match> {
+    let mut matches = dictionary_matches(chars);
+    matches.extend(reversed_dictionary_matches(chars));
+    matches.extend(l33t_matches(chars));
+    matches.extend(spatial_matches(chars));
+    matches.extend(repeat_matches(chars));
+    matches.extend(sequence_matches(chars));
+    matches.extend(year_matches(chars));
+    matches.extend(date_matches(chars));
+    matches
+}
+
+fn token(chars: &[char], i: usize, j: usize) -> String {
+    chars[i..=j].iter().collect()
+}
+
+fn dictionary_matches(chars: &[char]) -> Vec<Match> {
+    let lower: Vec<char> = chars
+        .iter()
+        .map(|c| c.to_lowercase().next().unwrap_or(*c))
+        .collect();
+    let max_len = dictionaries().max_word_len;
+    let mut matches = Vec::new();
+    for i in 0..lower.len() {
+        for j in i..lower.len().min(i + max_len) {
+            let word: String = lower[i..=j].iter().collect();
+            for (dictionary, ranks) in &dictionaries().ranked {
+                if let Some(&rank) = ranks.get(&word) {
+                    let token = token(chars, i, j);
+                    let guesses = rank as f64 * uppercase_variations(&token);
+                    matches.push(Match {
+                        i,
+                        j,
+                        token,
+                        pattern: Pattern::Dictionary {
+                            dictionary: *dictionary,
+                            rank,
+                            reversed: false,
+                            l33t: false,
+                        },
+                        guesses,
+                    });
+                }
+            }
+        }
+    }
+    matches
+}
+
+fn reversed_dictionary_matches(chars: &[char]) -> Vec<Match> {
+    let reversed: Vec<char> = chars.iter().rev().copied().collect();
+    if reversed == chars {
+        return Vec::new();
+    }
+    let n = chars.len();
+    dictionary_matches(&reversed)
+        .into_iter()
+        .filter(|m| m.j > m.i)
+        .map(|m| {
+            let (i, j) = (n - 1 - m.j, n - 1 - m.i);
+            let Pattern::Dictionary {
+                dictionary, rank, ..
+            } = m.pattern
+            else {
+                unreachable!("dictionary matcher returns dictionary matches")
+            };
+            Match {
+                i,
+                j,
+                token: token(chars, i, j),
+                pattern: Pattern::Dictionary {
+                    dictionary,
+                    rank,
+                    reversed: true,
+                    l33t: false,
+                },
+                guesses: m.guesses * 2.0,
+            }
+        })
+        .collect()
+}
+
+/// Letters and the characters commonly substituted for them
+const L33T_TABLE: [(char, &[char]); 12] = [
+    ('a', &['4', '@']),
+    ('b', &['8']),
+    ('c', &['(', '{', '[', '<']),
+    ('e', &['3']),
+    ('g', &['6', '9']),
+    ('i', &['1', '!', '|']),
+    ('l', &['1', '|', '7']),
+    ('o', &['0']),
+    ('s', &['$', '5']),
+    ('t', &['+', '7']),
+    ('x', &['%']),
+    ('z', &['2']),
+];
+
+fn l33t_matches(chars: &[char]) -> Vec<Match> {
+    // Each substituted character present may stand for one of several letters
+    let mut candidates: Vec<(char, Vec<char>)> = Vec::new();
+    for c in chars {
+        if candidates.iter().any(|(sub, _)| sub == c) {
+            continue;
+        }
+        let letters: Vec<char> = L33T_TABLE
+            .iter()
+            .filter(|(_, subs)| subs.contains(c))
+            .map(|(letter, _)| *letter)
+            .collect();
+        if !letters.is_empty() {
+            candidates.push((*c, letters));
+        }
+    }
+    if candidates.is_empty() {
+        return Vec::new();
+    }
+
+    let mut substitutions: Vec<HashMap<char, char>> = vec![HashMap::new()];
+    for (sub, letters) in &candidates {
+        substitutions = substitutions
+            .into_iter()
+            .flat_map(|map| {
+                letters.iter().map(move |letter| {
+                    let mut map = map.clone();
+                    map.insert(*sub, *letter);
+                    map
+                })
+            })
+            .take(MAX_L33T_COMBINATIONS)
+            .collect();
+    }
+
+    let mut matches: Vec<Match> = Vec::new();
+    for map in substitutions {
+        let translated: Vec<char> = chars.iter().map(|c| *map.get(c).unwrap_or(c)).collect();
+        for m in dictionary_matches(&translated) {
+            let original = &chars[m.i..=m.j];
+            let used: Vec<(char, char)> = map
+                .iter()
+                .filter(|(sub, _)| original.contains(sub))
+                .map(|(sub, letter)| (*sub, *letter))
+                .collect();
+            // Single characters are better covered by brute force
+            if used.is_empty() || m.i == m.j {
+                continue;
+            }
+            if matches.iter().any(|other| {
+                other.i == m.i && other.j == m.j && other.pattern == m.pattern_with_l33t()
+            }) {
+                continue;
+            }
+            let token = token(chars, m.i, m.j);
+            let guesses = m.guesses * l33t_variations(&token, &used);
+            matches.push(Match {
+                pattern: m.pattern_with_l33t(),
+                token,
+                guesses,
+                ..m
+            });
+        }
+    }
+    matches
+}
+
+impl Match {
+    fn pattern_with_l33t(&self) -> Pattern {
+        match self.pattern {
+            Pattern::Dictionary {
+                dictionary,
+                rank,
+                reversed,
+                ..
+            } => Pattern::Dictionary {
+                dictionary,
+                rank,
+                reversed,
+                l33t: true,
+            },
+            ref other => other.clone(),
+        }
+    }
+}
+
+/// A keyboard layout as adjacency between keys
+struct KeyboardGraph {
+    /// Per character, the key in each direction and whether it is shifted
+    neighbours: HashMap<char, Vec<Option<&'static str>>>,
+    /// Characters typed with shift
+    shifted: Vec<char>,
+    starting_positions: f64,
+    average_degree: f64,
+}
+
+impl KeyboardGraph {
+    /// Build a graph from rows of keys, each row given with the column of its
+    /// first key
+    fn new(rows: &[(i32, &'static str)], directions: &[(i32, i32)]) -> Self {
+        let mut positions: HashMap<(i32, i32), &'static str> = HashMap::new();
+        for (y, (start, row)) in rows.iter().enumerate() {
+            for (x, key) in row.split_whitespace().enumerate() {
+                positions.insert((start + x as i32, y as i32), key);
+            }
+        }
+
+        let mut neighbours = HashMap::new();
+        let mut shifted = Vec::new();
+        let mut degree_total = 0usize;
+        for (&(x, y), key) in &positions {
+            let adjacent: Vec<Option<&'static str>> = directions
+                .iter()
+                .map(|(dx, dy)| positions.get(&(x + dx, y + dy)).copied())
+                .collect();
+            degree_total += adjacent.iter().flatten().count();
+            for (index, c) in key.chars().enumerate() {
+                if index == 1 {
+                    shifted.push(c);
+                }
+                neighbours.insert(c, adjacent.clone());
+            }
+        }
+
+        Self {
+            neighbours,
+            shifted,
+            starting_positions: positions.len() as f64,
+            average_degree: degree_total as f64 / positions.len() as f64,
+        }
+    }
+}
+
+fn keyboard_graphs() -> &'static [KeyboardGraph; 2] {
+    static GRAPHS: OnceLock<[KeyboardGraph; 2]> = OnceLock::new();
+    GRAPHS.get_or_init(|| {
+        // Rows are offset by half a key, so each key touches six others
+        let slanted = [(-1, 0), (0, -1), (1, -1), (1, 0), (0, 1), (-1, 1)];
+        let aligned = [
+            (-1, 0),
+            (-1, -1),
+            (0, -1),
+            (1, -1),
+            (1, 0),
+            (1, 1),
+            (0, 1),
+            (-1, 1),
+        ];
+        [
+            KeyboardGraph::new(
+                &[
+                    (0, "`~ 1! 2@ 3# 4$ 5% 6^ 7& 8* 9( 0) -_ =+"),
+                    (1, "qQ wW eE rR tT yY uU iI oO pP [{ ]} \\|"),
+                    (1, "aA sS dD fF gG hH jJ kK lL ;: '\""),
+                    (1, "zZ xX cC vV bB nN mM ,< .> /?"),
+                ],
+                &slanted,
+            ),
+            KeyboardGraph::new(
+                &[
+                    (1, "/ * -"),
+                    (0, "7 8 9 +"),
+                    (0, "4 5 6"),
+                    (0, "1 2 3"),
+                    (1, "0 ."),
+                ],
+                &aligned,
+            ),
+        ]
+    })
+}
+
+fn spatial_matches(chars: &[char]) -> Vec<Match> {
+    let mut matches = Vec::new();
+    for graph in keyboard_graphs() {
+        let mut i = 0;
+        while i + 1 < chars.len() {
+            let mut j = i + 1;
+            let mut last_direction = None;
+            let mut turns = 0;
+            let mut shifted = usize::from(graph.shifted.contains(&chars[i]));
+            loop {
+                let found = j < chars.len()
+                    && graph
+                        .neighbours
+                        .get(&chars[j - 1])
+                        .and_then(|adjacent| {
+                            adjacent.iter().enumerate().find_map(|(direction, key)| {
+                                key.and_then(|key| key.chars().position(|c| c == chars[j]))
+                                    .map(|index| (direction, index))
+                            })
+                        })
+                        .map(|(direction, index)| {
+                            if index == 1 {
+                                shifted += 1;
+                            }
+                            if last_direction != Some(direction) {
+                                turns += 1;
+                                last_direction = Some(direction);
+                            }
+                        })
+                        .is_some();
+                if found {
+                    j += 1;
+                    continue;
+                }
+                if j - i > 2 {
+                    let token = token(chars, i, j - 1);
+                    let guesses = spatial_guesses(graph, token.chars().count(), turns, shifted);
+                    matches.push(Match {
+                        i,
+                        j: j - 1,
+                        token,
+                        pattern: Pattern::Spatial { turns },
+                        guesses,
+                    });
+                }
+                i = j;
+                break;
+            }
+        }
+    }
+    matches
+}
+
+fn spatial_guesses(graph: &KeyboardGraph, length: usize, turns: usize, shifted: usize) -> f64 {
+    let mut guesses = 0.0;
+    for i in 2..=length {
+        for j in 1..=turns.min(i - 1) {
+            guesses += n_choose_k(i - 1, j - 1)
+                * graph.starting_positions
+                * graph.average_degree.powi(j as i32);
+        }
+    }
+    let unshifted = length - shifted.min(length);
+    if shifted > 0 {
+        if unshifted == 0 {
+            guesses *= 2.0;
+        } else {
+            guesses *= (1..=shifted.min(unshifted))
+                .map(|i| n_choose_k(shifted + unshifted, i))
+                .sum::<f64>();
+        }
+    }
+    guesses
+}
+
+fn repeat_matches(chars: &[char]) -> Vec<Match> {
+    let mut matches = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        // Longest repeated run starting here, preferring the shortest base
+        let mut best: Option<(usize, usize)> = None;
+        for base_len in 1..=(chars.len() - i) / 2 {
+            let base = &chars[i..i + base_len];
+            let mut count = 1;
+            while i + (count + 1) * base_len <= chars.len()
+                && &chars[i + count * base_len..i + (count + 1) * base_len] == base
+            {
+                count += 1;
+            }
+            if count > 1 && best.is_none_or(|(len, n)| count * base_len > len * n) {
+                best = Some((base_len, count));
+            }
+        }
+        let Some((base_len, count)) = best else {
+            i += 1;
+            continue;
+        };
+
+        let base: String = chars[i..i + base_len].iter().collect();
+        let j = i + base_len * count - 1;
+        matches.push(Match {
+            i,
+            j,
+            token: token(chars, i, j),
+            pattern: Pattern::Repeat { base_len },
+            guesses: estimate_guesses(&base) * count as f64,
+        });
+        i = j + 1;
+    }
+    matches
+}
+
+fn sequence_matches(chars: &[char]) -> Vec<Match> {
+    let mut matches = Vec::new();
+    if chars.len() < 2 {
+        return matches;
+    }
+
+    let mut push = |i: usize, j: usize, delta: i32| {
+        if j == i
+            || !(j - i > 1 || delta.abs() == 1)
+            || !(1..=MAX_SEQUENCE_DELTA).contains(&delta.abs())
+        {
+            return;
+        }
+        let run = &chars[i..=j];
+        let base = if run.iter().all(|c| c.is_ascii_lowercase())
+            || run.iter().all(|c| c.is_ascii_uppercase())
+        {
+            26.0
+        } else if run.iter().all(|c| c.is_ascii_digit()) {
+            10.0
+        } else {
+            return;
+        };
+        let base = if "aAzZ019".contains(run[0]) {
+            4.0
+        } else {
+            base
+        };
+        let direction = if delta > 0 { 1.0 } else { 2.0 };
+        matches.push(Match {
+            i,
+            j,
+            token: token(chars, i, j),
+            pattern: Pattern::Sequence,
+            guesses: base * direction * run.len() as f64,
+        });
+    };
+
+    let mut i = 0;
+    let mut last_delta: Option<i32> = None;
+    for k in 1..chars.len() {
+        let delta = chars[k] as i32 - chars[k - 1] as i32;
+        if last_delta.is_none() || last_delta == Some(delta) {
+            last_delta = Some(delta);
+            continue;
+        }
+        push(i, k - 1, last_delta.unwrap_or_default());
+        i = k - 1;
+        last_delta = Some(delta);
+    }
+    push(i, chars.len() - 1, last_delta.unwrap_or_default());
+    matches
+}
+
+fn reference_year() -> i32 {
+    chrono::Utc::now().year()
+}
+
+fn year_space(year: i32) -> f64 {
+    (year - reference_year()).abs().max(MIN_YEAR_SPACE) as f64
+}
+
+fn year_matches(chars: &[char]) -> Vec<Match> {
+    let mut matches = Vec::new();
+    for i in 0..chars.len().saturating_sub(3) {
+        let token = token(chars, i, i + 3);
+        let Ok(year) = token.parse::<i32>() else {
+            continue;
+        };
+        if token.chars().all(|c| c.is_ascii_digit()) && (1900..=2099).contains(&year) {
+            matches.push(Match {
+                i,
+                j: i + 3,
+                token,
+                pattern: Pattern::Year,
+                guesses: year_space(year),
+            });
+        }
+    }
+    matches
+}
+
+fn date_matches(chars: &[char]) -> Vec<Match> {
+    let mut matches: Vec<Match> = Vec::new();
+    let mut push = |i: usize, j: usize, year: i32, separator: bool| {
+        let mut guesses = year_space(year) * 365.0;
+        if separator {
+            guesses *= 4.0;
+        }
+        matches.push(Match {
+            i,
+            j,
+            token: token(chars, i, j),
+            pattern: Pattern::Date,
+            guesses,
+        });
+    };
+
+    // Without separators: 4 to 8 digits split into day, month and year
+    for i in 0..chars.len() {
+        for j in i + 3..chars.len().min(i + 8) {
+            let digits = &chars[i..=j];
+            if !digits.iter().all(|c| c.is_ascii_digit()) {
+                continue;
+            }
+            let text: String = digits.iter().collect();
+            let splits: &[(usize, usize)] = match text.len() {
+                4 => &[(1, 2), (2, 3)],
+                5 => &[(1, 3), (2, 3)],
+                6 => &[(1, 2), (2, 4), (4, 5)],
+                7 => &[(1, 3), (2, 3), (4, 5), (4, 6)],
+                _ => &[(2, 4), (4, 6)],
+            };
+            let best = splits
+                .iter()
+                .filter_map(|&(k, l)| {
+                    let parts = [&text[..k], &text[k..l], &text[l..]];
+                    let ints = parts.map(|part| part.parse::<i32>().unwrap_or_default());
+                    year_of_date(ints)
+                })
+                .min_by_key(|year| (year - reference_year()).abs());
+            if let Some(year) = best {
+                push(i, j, year, false);
+            }
+        }
+    }
+
+    // With a repeated separator: 1-4 digits, 1-2 digits, 1-4 digits
+    for i in 0..chars.len() {
+        for j in i + 5..chars.len().min(i + 10) {
+            if let Some(year) = separated_date(&chars[i..=j]) {
+                push(i, j, year, true);
+            }
+        }
+    }
+
+    // A date inside another date is not a separate pattern
+    let spans: Vec<(usize, usize)> = matches.iter().map(|m| (m.i, m.j)).collect();
+    matches.retain(|m| {
+        !spans
+            .iter()
+            .any(|&(i, j)| (i, j) != (m.i, m.j) && i <= m.i && m.j <= j)
+    });
+    matches
+}
+
+fn separated_date(chars: &[char]) -> Option<i32> {
+    let is_separator = |c: &char| c.is_whitespace() || "/\\_.-".contains(*c);
+    let first = chars.iter().position(is_separator)?;
+    let separator = chars[first];
+    let second = first + 1 + chars[first + 1..].iter().position(|c| *c == separator)?;
+    let parts = [
+        &chars[..first],
+        &chars[first + 1..second],
+        &chars[second + 1..],
+    ];
+    let lengths_ok = (1..=4).contains(&parts[0].len())
+        && (1..=2).contains(&parts[1].len())
+        && (1..=4).contains(&parts[2].len());
+    if !lengths_ok || !parts.iter().all(|p| p.iter().all(|c| c.is_ascii_digit())) {
+        return None;
+    }
+    let ints = parts.map(|p| {
+        p.iter()
+            .collect::<String>()
+            .parse::<i32>()
+            .unwrap_or_default()
+    });
+    year_of_date(ints)
+}
+
+/// The year, if the three numbers can be read as a day, month and year
+fn year_of_date(ints: [i32; 3]) -> Option<i32> {
+    if ints[1] > 31 || ints[1] <= 0 {
+        return None;
+    }
+    let (mut over_12, mut over_31, mut under_1) = (0, 0, 0);
+    for int in ints {
+        if (100..DATE_MIN_YEAR).contains(&int) || int > DATE_MAX_YEAR {
+            return None;
+        }
+        over_31 += usize::from(int > 31);
+        over_12 += usize::from(int > 12);
+        under_1 += usize::from(int <= 0);
+    }
+    if over_31 >= 2 || over_12 == 3 || under_1 >= 2 {
+        return None;
+    }
+
+    let is_day_month = |a: i32, b: i32| {
+        ((1..=31).contains(&a) && (1..=12).contains(&b))
+            || ((1..=31).contains(&b) && (1..=12).contains(&a))
+    };
+    let splits = [(ints[2], ints[0], ints[1]), (ints[0], ints[1], ints[2])];
+    for (year, a, b) in splits {
+        if (DATE_MIN_YEAR..=DATE_MAX_YEAR).contains(&year) {
+            return is_day_month(a, b).then_some(year);
+        }
+    }
+    for (year, a, b) in splits {
+        if is_day_month(a, b) {
+            return Some(match year {
+                0..=50 => year + 2000,
+                51..=99 => year + 1900,
+                _ => year,
+            });
+        }
+    }
+    None
+}
+
+// ---------------------------------------------------------------------------
+// Guess estimation
+
+fn n_choose_k(n: usize, k: usize) -> f64 {
+    if k > n {
+        return 0.0;
+    }
+    (1..=k).fold(1.0, |result, d| result * (n - k + d) as f64 / d as f64)
+}
+
+fn factorial(n: usize) -> f64 {
+    (2..=n).map(|i| i as f64).product()
+}
+
+fn uppercase_variations(token: &str) -> f64 {
+    let upper = token.chars().filter(|c| c.is_uppercase()).count();
+    let lower = token.chars().filter(|c| c.is_lowercase()).count();
+    if upper == 0 {
+        return 1.0;
+    }
+    let first_upper = token.chars().next().is_some_and(char::is_uppercase);
+    let last_upper = token.chars().last().is_some_and(char::is_uppercase);
+    // Capitalized, upper at the end, or all caps are tried first
+    if lower == 0 || (upper == 1 && (first_upper || last_upper)) {
+        return 2.0;
+    }
+    (1..=upper.min(lower))
+        .map(|i| n_choose_k(upper + lower, i))
+        .sum()
+}
+
+fn l33t_variations(token: &str, substitutions: &[(char, char)]) -> f64 {
+    let lower = token.to_lowercase();
+    substitutions
+        .iter()
+        .map(|(sub, letter)| {
+            let subbed = lower.chars().filter(|c| c == sub).count();
+            let unsubbed = lower.chars().filter(|c| c == letter).count();
+            if subbed == 0 || unsubbed == 0 {
+                2.0
+            } else {
+                (1..=subbed.min(unsubbed))
+                    .map(|i| n_choose_k(subbed + unsubbed, i))
+                    .sum()
+            }
+        })
+        .product()
+}
+
+fn bruteforce_match(chars: &[char], i: usize, j: usize) -> Match {
+    let length = (j - i + 1) as i32;
+    let min_guesses = if length == 1 {
+        MIN_SUBMATCH_GUESSES_SINGLE_CHAR
+    } else {
+        MIN_SUBMATCH_GUESSES_MULTI_CHAR
+    } + 1.0;
+    Match {
+        i,
+        j,
+        token: token(chars, i, j),
+        pattern: Pattern::Bruteforce,
+        guesses: BRUTEFORCE_CARDINALITY.powi(length).max(min_guesses),
+    }
+}
+
+/// Guesses for a match within a password of `length` characters
+///
+/// Parts of a password cannot be cheaper than a few guesses each, or a long
+/// password made of tiny matches would look weaker than it is.
+fn match_guesses(m: &Match, length: usize) -> f64 {
+    let token_length = m.j - m.i + 1;
+    let min_guesses = if token_length == length {
+        1.0
+    } else if token_length == 1 {
+        MIN_SUBMATCH_GUESSES_SINGLE_CHAR
+    } else {
+        MIN_SUBMATCH_GUESSES_MULTI_CHAR
+    };
+    m.guesses.max(min_guesses)
+}
+
+/// Best sequence of `l` matches ending at a position: (match, product, total)
+type Optimal = BTreeMap<usize, (usize, f64, f64)>;
+
+/// Cover the password with the sequence of matches that needs the fewest
+/// guesses, counting an attacker's cost of trying each sequence length
+fn most_guessable_sequence(chars: &[char], matches: Vec<Match>) -> Estimate {
+    let n = chars.len();
+    let mut arena = matches;
+    let mut by_end: Vec<Vec<usize>> = vec![Vec::new(); n];
+    for (index, m) in arena.iter().enumerate() {
+        by_end[m.j].push(index);
+    }
+    let mut optimal: Vec<Optimal> = vec![BTreeMap::new(); n];
+
+    let update = |arena: &[Match], optimal: &mut [Optimal], index: usize, l: usize| {
+        let m = &arena[index];
+        let mut product = match_guesses(m, n);
+        if l > 1 {
+            product *= optimal[m.i - 1][&(l - 1)].1;
+        }
+        let guesses = factorial(l) * product + 10000f64.powi(l as i32 - 1);
+        if optimal[m.j]
+            .iter()
+            .any(|(&other_l, &(_, _, other))| other_l <= l && other <= guesses)
+        {
+            return;
+        }
+        optimal[m.j].insert(l, (index, product, guesses));
+    };
+
+    for (k, ending_here) in by_end.iter().enumerate() {
+        for &index in ending_here {
+            let i = arena[index].i;
+            if i > 0 {
+                let lengths: Vec<usize> = optimal[i - 1].keys().copied().collect();
+                for l in lengths {
+                    update(&arena, &mut optimal, index, l + 1);
+                }
+            } else {
+                update(&arena, &mut optimal, index, 1);
+            }
+        }
+
+        arena.push(bruteforce_match(chars, 0, k));
+        update(&arena, &mut optimal, arena.len() - 1, 1);
+        for i in 1..=k {
+            arena.push(bruteforce_match(chars, i, k));
+            let index = arena.len() - 1;
+            let previous: Vec<(usize, usize)> = optimal[i - 1]
+                .iter()
+                .map(|(&l, &(last, _, _))| (l, last))
+                .collect();
+            for (l, last) in previous {
+                // Adjacent brute force would be one longer brute force match
+                if arena[last].pattern != Pattern::Bruteforce {
+                    update(&arena, &mut optimal, index, l + 1);
+                }
+            }
+        }
+    }
+
+    let (&best_l, &(_, _, guesses)) = optimal[n - 1]
+        .iter()
+        .min_by(|a, b| a.1 .2.total_cmp(&b.1 .2))
+        .expect("brute force always covers the password");
+    let mut l = best_l;
+    let mut sequence = Vec::new();
+    let mut k = n - 1;
+    loop {
+        let (index, _, _) = optimal[k][&l];
+        let m = arena[index].clone();
+        let start = m.i;
+        sequence.push(m);
+        if start == 0 {
+            break;
+        }
+        k = start - 1;
+        l -= 1;
+    }
+    sequence.reverse();
+
+    Estimate { guesses, sequence }
+}
+
+// ---------------------------------------------------------------------------
+// Feedback
+
+/// Feedback messages, each a code in the message catalogs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Feedback {
+    TopTen,
+    TopHundred,
+    VeryCommon,
+    SimilarToCommon,
+    WordByItself,
+    NamesByThemselves,
+    CommonNames,
+    StraightRows,
+    ShortKeyboardPatterns,
+    RepeatedCharacters,
+    RepeatedPatterns,
+    Sequences,
+    RecentYears,
+    Dates,
+    UseFewWords,
+    NoNeedForSymbols,
+    AddAnotherWord,
+    CapitalizationDoesntHelp,
+    AllUppercase,
+    ReversedWords,
+    PredictableSubstitutions,
+    LongerKeyboardPattern,
+    AvoidRepeats,
+    AvoidSequences,
+    AvoidRecentYears,
+    AvoidPersonalYears,
+    AvoidPersonalDates,
+}
+
+impl Feedback {
+    /// Message code in the `strength` section of the catalogs
+    fn code(self) -> &'static str {
+        match self {
+            Feedback::TopTen => "strength.top_ten",
+            Feedback::TopHundred => "strength.top_hundred",
+            Feedback::VeryCommon => "strength.very_common",
+            Feedback::SimilarToCommon => "strength.similar_to_common",
+            Feedback::WordByItself => "strength.word_by_itself",
+            Feedback::NamesByThemselves => "strength.names_by_themselves",
+            Feedback::CommonNames => "strength.common_names",
+            Feedback::StraightRows => "strength.straight_rows",
+            Feedback::ShortKeyboardPatterns => "strength.short_keyboard_patterns",
+            Feedback::RepeatedCharacters => "strength.repeated_characters",
+            Feedback::RepeatedPatterns => "strength.repeated_patterns",
+            Feedback::Sequences => "strength.sequences",
+            Feedback::RecentYears => "strength.recent_years",
+            Feedback::Dates => "strength.dates",
+            Feedback::UseFewWords => "strength.use_few_words",
+            Feedback::NoNeedForSymbols => "strength.no_need_for_symbols",
+            Feedback::AddAnotherWord => "strength.add_another_word",
+            Feedback::CapitalizationDoesntHelp => "strength.capitalization_doesnt_help",
+            Feedback::AllUppercase => "strength.all_uppercase",
+            Feedback::ReversedWords => "strength.reversed_words",
+            Feedback::PredictableSubstitutions => "strength.predictable_substitutions",
+            Feedback::LongerKeyboardPattern => "strength.longer_keyboard_pattern",
+            Feedback::AvoidRepeats => "strength.avoid_repeats",
+            Feedback::AvoidSequences => "strength.avoid_sequences",
+            Feedback::AvoidRecentYears => "strength.avoid_recent_years",
+            Feedback::AvoidPersonalYears => "strength.avoid_personal_years",
+            Feedback::AvoidPersonalDates => "strength.avoid_personal_dates",
+        }
+    }
+}
+
+/// Warning and suggestions for a password that is easy to guess
+///
+/// Passwords needing at least 10^8 guesses get no feedback. `language` is
+/// an ISO 639-1 code, optionally with a region (`de-AT`); unsupported
+/// languages fall back to English.
+pub(crate) fn feedback(estimate: &Estimate, language: &str) -> Vec<String> {
+    let localizer = Localizer::new(language);
+    let messages = if estimate.sequence.is_empty() {
+        vec![Feedback::UseFewWords, Feedback::NoNeedForSymbols]
+    } else if estimate.guesses_log10() >= 8.0 {
+        Vec::new()
+    } else {
+        let longest = estimate
+            .sequence
+            .iter()
+            .max_by_key(|m| m.j - m.i)
+            .expect("sequence is not empty");
+        let mut messages = match_feedback(longest, estimate.sequence.len() == 1);
+        messages.push(Feedback::AddAnotherWord);
+        messages
+    };
+
+    messages
+        .into_iter()
+        .map(|message| localizer.format(&Message::new(message.code())))
+        .collect()
+}
+
+/// Warning and suggestions for the longest match in a weak password
+fn match_feedback(m: &Match, sole_match: bool) -> Vec<Feedback> {
+    match &m.pattern {
+        Pattern::Dictionary {
+            dictionary,
+            rank,
+            reversed,
+            l33t,
+        } => {
+            let mut messages = Vec::new();
+            let warning = match dictionary {
+                Dictionary::Passwords if sole_match && !l33t && !reversed => Some(match rank {
+                    0..=10 => Feedback::TopTen,
+                    11..=100 => Feedback::TopHundred,
+                    _ => Feedback::VeryCommon,
+                }),
+                Dictionary::Passwords if m.guesses.log10() <= 4.0 => {
+                    Some(Feedback::SimilarToCommon)
+                }
+                Dictionary::English if sole_match => Some(Feedback::WordByItself),
+                Dictionary::Names if sole_match => Some(Feedback::NamesByThemselves),
+                Dictionary::Names => Some(Feedback::CommonNames),
+                _ => None,
+            };
+            messages.extend(warning);
+
+            let mut letters = m.token.chars().filter(|c| c.is_alphabetic());
+            let first_upper = letters.next().is_some_and(char::is_uppercase);
+            let all_upper =
+                m.token.chars().any(char::is_uppercase) && !m.token.chars().any(char::is_lowercase);
+            if all_upper {
+                messages.push(Feedback::AllUppercase);
+            } else if first_upper && letters.all(char::is_lowercase) {
+                messages.push(Feedback::CapitalizationDoesntHelp);
+            }
+            if *reversed && m.token.chars().count() >= 4 {
+                messages.push(Feedback::ReversedWords);
+            }
+            if *l33t {
+                messages.push(Feedback::PredictableSubstitutions);
+            }
+            messages
+        }
+        Pattern::Spatial { turns } => vec![
+            if *turns == 1 {
+                Feedback::StraightRows
+            } else {
+                Feedback::ShortKeyboardPatterns
+            },
+            Feedback::LongerKeyboardPattern,
+        ],
+        Pattern::Repeat { base_len } => vec![
+            if *base_len == 1 {
+                Feedback::RepeatedCharacters
+            } else {
+                Feedback::RepeatedPatterns
+            },
+            Feedback::AvoidRepeats,
+        ],
+        Pattern::Sequence => vec![Feedback::Sequences, Feedback::AvoidSequences],
+        Pattern::Year => vec![
+            Feedback::RecentYears,
+            Feedback::AvoidRecentYears,
+            Feedback::AvoidPersonalYears,
+        ],
+        Pattern::Date => vec![Feedback::Dates, Feedback::AvoidPersonalDates],
+        Pattern::Bruteforce => Vec::new(),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Dictionaries
+
+struct Dictionaries {
+    ranked: Vec<(Dictionary, HashMap<String, usize>)>,
+    max_word_len: usize,
+}
+
+fn dictionaries() -> &'static Dictionaries {
+    static DICTIONARIES: OnceLock<Dictionaries> = OnceLock::new();
+    DICTIONARIES.get_or_init(|| {
+        let ranked: Vec<(Dictionary, HashMap<String, usize>)> = [
+            (Dictionary::Passwords, PASSWORDS),
+            (Dictionary::English, ENGLISH_WORDS),
+            (Dictionary::Names, NAMES),
+        ]
+        .into_iter()
+        .map(|(dictionary, words)| {
+            let mut ranks = HashMap::new();
+            for (rank, word) in words.split_whitespace().enumerate() {
+                ranks.entry(word.to_string()).or_insert(rank + 1);
+            }
+            (dictionary, ranks)
+        })
+        .collect();
+        let max_word_len = ranked
+            .iter()
+            .flat_map(|(_, ranks)| ranks.keys())
+            .map(|word| word.chars().count())
+            .max()
+            .unwrap_or(1);
+        Dictionaries {
+            ranked,
+            max_word_len,
+        }
+    })
+}
+
+/// Most common passwords from public breach corpora, most common first
+const PASSWORDS: &str = "
+password 123456 12345678 qwerty 123456789 12345 1234 111111 1234567 dragon
+123123 baseball abc123 football monkey letmein shadow master 696969 mustang
+666666 qwertyuiop 123321 1234567890 superman 121212 000000 qazwsx 7777777 michael
+654321 batman trustno1 sunshine iloveyou princess starwars passw0rd welcome
+login admin solo charlie donald password1 qwerty123 hello freedom whatever
+ashley bailey access flower hottie loveme zaq1zaq1 hunter2 hunter jessica
+pepper 555555 lovely 7777 888888 123qwe ninja azerty 1qaz2wsx jordan23
+harley ranger buster thomas tigger robert soccer hockey killer george andrew
+daniel computer michelle jennifer joshua maggie cheese amanda summer love
+ginger 11111111 nicole chelsea biteme matthew yankees austin 131313 secret
+merlin diamond matrix corvette taylor silver orange thunder cookie 1111 banana
+internet welcome1 changeme default guest test test123 admin123 root toor
+letmein1 password123 p@ssw0rd abcdef abcd1234 qwe123 asdfgh asdfghjkl zxcvbnm
+1q2w3e4r 1q2w3e4r5t qwertyu 159753 987654321 11111 monkey1 iloveyou1 sunshine1
+football1 baseball1 superman1 princess1 shadow1 master1 dragon1 michael1
+samsung apple google facebook linkedin twitter linkin pokemon naruto
+blink182 metallica liverpool arsenal chelsea1 barcelona realmadrid juventus
+pass pass123 pass1234 mypassword secret123 starwars1 whatever1 qwerty1
+passwort hallo123 motdepasse contraseña
+";
+
+/// Frequent English words, most common first
+const ENGLISH_WORDS: &str = "
+the you and that was for are with his they this have from one had word but not
+what all were when your can said there use each which she how their will other
+about out many then them these some her would make like him into time has look
+two more write see number way could people than first water been call who oil
+its now find long down day did get come made may part over new sound take only
+little work know place year live back give most very after thing our just name
+good sentence man think say great where help through much before line right too
+mean old any same tell boy follow came want show also around form three small
+set put end does another well large must big even such because turn here why
+ask went men read need land different home move try kind hand picture again
+change off play spell air away animal house point page letter mother answer
+found study still learn should america world high every near add food between
+own below country plant last school father keep tree never start city earth eye
+light thought head under story saw left few while along might close something
+seem next hard open example begin life always those both paper together got
+group often run important until children side feet car mile night walk white
+sea began grow took river four carry state once book hear stop without second
+later miss idea enough eat face watch far indian really almost let above girl
+sometimes mountain cut young talk soon list song being leave family love secure
+secret welcome summer winter spring autumn hello sunshine money happy friend
+friends dream music dance magic power heart angel baby honey sweet star moon sun
+rain snow fire ice dog cat bird fish horse tiger lion bear wolf eagle dragon
+king queen prince princess knight castle garden flower rose apple orange banana
+cherry lemon coffee chocolate cookie pizza computer phone internet email online
+game player soccer football baseball hockey golf tennis ocean beach island forest
+river lake sky blue red green black yellow purple silver gold diamond crystal
+shadow thunder storm freedom peace hope faith trust truth justice liberty
+victory winner master super hero battle fight army war guitar piano drum rock
+jazz blues party holiday travel adventure journey mountain valley desert horse
+correct battery staple pass word login access admin user letmein private
+personal company office business system network server database cloud account
+";
+
+/// Common first names and surnames, most common first
+const NAMES: &str = "
+james john robert michael william david richard joseph thomas charles
+christopher daniel matthew anthony mark donald steven paul andrew joshua kenneth
+kevin brian george timothy ronald edward jason jeffrey ryan jacob gary nicholas
+eric jonathan stephen larry justin scott brandon benjamin samuel gregory
+alexander frank patrick raymond jack dennis jerry tyler aaron jose adam nathan
+henry douglas zachary peter kyle ethan walter noah jeremy christian keith roger
+terry gerald harold sean austin carl arthur lawrence dylan jesse jordan bryan
+billy joe bruce gabriel logan albert willie alan juan wayne elijah randy roy
+vincent ralph eugene russell bobby mason philip louis mary patricia jennifer
+linda elizabeth barbara susan jessica sarah karen lisa nancy betty margaret
+sandra ashley kimberly emily donna michelle carol amanda dorothy melissa
+deborah stephanie rebecca sharon laura cynthia kathleen amy angela shirley
+anna brenda pamela emma nicole helen samantha katherine christine debra rachel
+carolyn janet catherine maria heather diane ruth julie olivia joyce virginia
+victoria kelly lauren christina joan evelyn judith megan andrea cheryl hannah
+jacqueline martha gloria teresa ann sara madison frances kathryn janice jean
+abigail alice julia judy sophia grace denise amber doris marilyn danielle
+beverly isabella theresa diana natalie brittany charlotte marie kayla alexis
+lori smith johnson williams brown jones garcia miller davis rodriguez martinez
+hernandez lopez gonzalez wilson anderson taylor moore jackson martin lee perez
+thompson white harris sanchez clark ramirez lewis robinson walker young allen
+king wright scott torres nguyen hill flores green adams nelson baker hall
+rivera campbell mitchell carter roberts muller schmidt schneider fischer weber
+meyer wagner becker schulz hoffmann dubois durand leroy moreau simon laurent
+";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn patterns(password: &str) -> Vec<Pattern> {
+        estimate(password)
+            .sequence
+            .into_iter()
+            .map(|m| m.pattern)
+            .collect()
+    }
+
+    #[test]
+    fn test_patterns_are_recognized() {
+        assert!(matches!(
+            patterns("P@ssw0rd").as_slice(),
+            [Pattern::Dictionary {
+                dictionary: Dictionary::Passwords,
+                l33t: true,
+                ..
+            }]
+        ));
+        assert!(matches!(
+            patterns("drowssap").as_slice(),
+            [Pattern::Dictionary { reversed: true, .. }]
+        ));
+        assert!(matches!(
+            patterns("zxcvfr").as_slice(),
+            [Pattern::Spatial { turns: 2 }]
+        ));
+        assert_eq!(patterns("aaaaaaaa"), vec![Pattern::Repeat { base_len: 1 }]);
+        assert_eq!(patterns("jklmnop"), vec![Pattern::Sequence]);
+        assert_eq!(patterns("13.05.1987"), vec![Pattern::Date]);
+    }
+
+    #[test]
+    fn test_estimates_follow_predictability() {
+        let log10 = |password: &str| estimate(password).guesses_log10();
+
+        assert!(log10("password") < 1.0);
+        assert!(log10("P@ssw0rd1") < 5.0);
+        assert!(log10("qwertyuiop") < 3.0);
+        assert!(log10("Jennifer1987") < 7.0);
+        // Four random common words beat a short "complex" password
+        assert!(log10("correct horse battery staple") > log10("Tr0ub4dor&3"));
+        assert!(log10("x7$Kq!m2Vz#9pLw@4Rt") > 15.0);
+
+        assert!(estimate("Password").is_common());
+        assert!(!estimate("password horse").is_common());
+    }
+
+    #[test]
+    fn test_feedback_is_localized() {
+        let weak = estimate("password");
+        assert_eq!(feedback(&weak, "en")[0], "This is a top-10 common password");
+        assert_eq!(
+            feedback(&weak, "de-AT")[0],
+            "Dies ist eines der 10 häufigsten Passwörter"
+        );
+        assert_eq!(
+            feedback(&weak, "fr_FR.UTF-8")[0],
+            "Ce mot de passe fait partie des 10 plus courants"
+        );
+        assert_eq!(feedback(&weak, "xx"), feedback(&weak, "en"));
+        assert!(feedback(&estimate("x7$Kq!m2Vz#9pLw@4Rt"), "fr").is_empty());
+        assert_eq!(feedback(&estimate(""), "es").len(), 2);
+    }
+}
@@ -1,14 +1,481 @@
 //! TOTP (Time-based One-Time Password) utilities
 //!
 //! This module provides functions for generating TOTP codes according to RFC 6238.
-//! TOTP codes are commonly used for two-factor authentication.
+//! TOTP codes are commonly used for two-factor authentication. Besides the
+//! standard 6-digit SHA1 codes, [`TotpGenerator`] handles SHA256/SHA512,
+//! longer codes, custom periods, Steam Guard codes, `otpauth://` URIs and
+//! Google Authenticator `otpauth-migration://` exports.
 
 use anyhow::{anyhow, Result};
+use base64::Engine;
 use hmac::{Hmac, Mac};
 use sha1::Sha1;
+use sha2::{Sha256, Sha512};
 use std::time::{SystemTime, UNIX_EPOCH};
 
-type HmacSha1 = Hmac<Sha1>;
+use crate::models::CredentialField;
+
+/// Characters used by Steam Guard codes
+const STEAM_ALPHABET: &[u8] = b"23456789BCDFGHJKMNPQRTVWXY";
+
+/// HMAC algorithm used to derive codes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TotpAlgorithm {
+    #[default]
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+impl TotpAlgorithm {
+    /// Parse an algorithm name as used in `otpauth://` URIs (`SHA1`, `sha256`, ...)
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_uppercase().replace('-', "").as_str() {
+            "SHA1" => Some(Self::Sha1),
+            "SHA256" => Some(Self::Sha256),
+            "SHA512" => Some(Self::Sha512),
+            _ => None,
+        }
+    }
+
+    /// Name as written in `otpauth://` URIs
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Sha1 => "SHA1",
+            Self::Sha256 => "SHA256",
+            Self::Sha512 => "SHA512",
+        }
+    }
+
+    fn hmac(&self, key: &[u8], message: &[u8]) -> Result<Vec<u8>> {
+        let invalid = |_| anyhow!("Invalid secret length for HMAC");
+        Ok(match self {
+            Self::Sha1 => {
+                let mut mac = Hmac::<Sha1>::new_from_slice(key).map_err(invalid)?;
+                mac.update(message);
+                mac.finalize().into_bytes().to_vec()
+            }
+            Self::Sha256 => {
+                let mut mac = Hmac::<Sha256>::new_from_slice(key).map_err(invalid)?;
+                mac.update(message);
+                mac.finalize().into_bytes().to_vec()
+            }
+            Self::Sha512 => {
+                let mut mac = Hmac::<Sha512>::new_from_slice(key).map_err(invalid)?;
+                mac.update(message);
+                mac.finalize().into_bytes().to_vec()
+            }
+        })
+    }
+}
+
+/// How the truncated HMAC value is rendered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TotpEncoding {
+    /// Decimal digits (RFC 6238)
+    #[default]
+    Numeric,
+    /// Steam Guard's five alphanumeric characters
+    Steam,
+}
+
+/// A configured TOTP generator
+///
+/// # Example
+/// ```
+/// use ziplock_shared::utils::totp::{TotpAlgorithm, TotpGenerator};
+///
+/// let totp = TotpGenerator::new("JBSWY3DPEHPK3PXP")
+///     .unwrap()
+///     .with_algorithm(TotpAlgorithm::Sha256)
+///     .with_digits(8)
+///     .unwrap();
+/// assert_eq!(totp.generate().unwrap().len(), 8);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TotpGenerator {
+    secret: Vec<u8>,
+    algorithm: TotpAlgorithm,
+    digits: u32,
+    period: u64,
+    encoding: TotpEncoding,
+    issuer: Option<String>,
+    account: Option<String>,
+}
+
+impl TotpGenerator {
+    /// Create a standard SHA1, 6-digit, 30-second generator from a base32 secret
+    pub fn new(secret: &str) -> Result<Self> {
+        let clean_secret = secret.replace(' ', "").to_uppercase();
+        if clean_secret.is_empty() {
+            return Err(anyhow!("TOTP secret cannot be empty"));
+        }
+        if !clean_secret
+            .chars()
+            .all(|c| "ABCDEFGHIJKLMNOPQRSTUVWXYZ234567=".contains(c))
+        {
+            return Err(anyhow!(
+                "Invalid base32 secret: contains invalid characters"
+            ));
+        }
+        let bytes = base32_decode(&clean_secret).map_err(|_| anyhow!("Invalid base32 secret"))?;
+        Ok(Self::from_bytes(bytes))
+    }
+
+    /// Create a standard generator from raw secret bytes
+    pub fn from_bytes(secret: Vec<u8>) -> Self {
+        Self {
+            secret,
+            algorithm: TotpAlgorithm::Sha1,
+            digits: 6,
+            period: 30,
+            encoding: TotpEncoding::Numeric,
+            issuer: None,
+            account: None,
+        }
+    }
+
+    /// Use a different HMAC algorithm
+    pub fn with_algorithm(mut self, algorithm: TotpAlgorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// Use a different code length (4 to 9 digits)
+    pub fn with_digits(mut self, digits: u32) -> Result<Self> {
+        if !(4..=9).contains(&digits) {
+            return Err(anyhow!("Unsupported TOTP code length: {}", digits));
+        }
+        self.digits = digits;
+        Ok(self)
+    }
+
+    /// Use a different time step
+    pub fn with_period(mut self, period: u64) -> Result<Self> {
+        if period == 0 {
+            return Err(anyhow!("TOTP period must be greater than zero"));
+        }
+        self.period = period;
+        Ok(self)
+    }
+
+    /// Produce Steam Guard codes instead of digits
+    pub fn steam(mut self) -> Self {
+        self.encoding = TotpEncoding::Steam;
+        self.digits = 5;
+        self
+    }
+
+    /// Set the issuer and account shown in authenticator apps
+    pub fn with_label<S: Into<String>>(mut self, issuer: Option<S>, account: Option<S>) -> Self {
+        self.issuer = issuer.map(Into::into);
+        self.account = account.map(Into::into);
+        self
+    }
+
+    /// Parse an `otpauth://totp/...` (or `otpauth://steam/...`) URI
+    pub fn from_uri(uri: &str) -> Result<Self> {
+        let url = url::Url::parse(uri.trim()).map_err(|e| anyhow!("Invalid otpauth URI: {}", e))?;
+        if url.scheme() != "otpauth" {
+            return Err(anyhow!("Not an otpauth URI"));
+        }
+
+        let steam_type = match url.host_str().map(|h| h.to_lowercase()).as_deref() {
+            Some("totp") => false,
+            Some("steam") => true,
+            Some("hotp") => return Err(anyhow!("Counter-based (HOTP) codes are not supported")),
+            other => return Err(anyhow!("Unknown OTP type: {}", other.unwrap_or(""))),
+        };
+
+        let label = url
+            .path_segments()
+            .and_then(|mut segments| segments.next())
+            .and_then(|segment| {
+                // Reuse the query decoder to percent-decode the label
+                url::form_urlencoded::parse(format!("label={}", segment).as_bytes())
+                    .next()
+                    .map(|(_, label)| label.to_string())
+            })
+            .unwrap_or_default();
+        let (mut issuer, account) = match label.split_once(':') {
+            Some((issuer, account)) => (Some(issuer.trim().to_string()), account.trim()),
+            None => (None, label.trim()),
+        };
+
+        let mut secret = None;
+        let mut algorithm = TotpAlgorithm::Sha1;
+        let mut digits = None;
+        let mut period = 30;
+        let mut steam = steam_type;
+        for (key, value) in url.query_pairs() {
+            match key.to_lowercase().as_str() {
+                "secret" => secret = Some(value.to_string()),
+                "algorithm" => {
+                    algorithm = TotpAlgorithm::from_name(&value)
+                        .ok_or_else(|| anyhow!("Unsupported TOTP algorithm: {}", value))?
+                }
+                "digits" => {
+                    digits = Some(
+                        value
+                            .parse()
+                            .map_err(|_| anyhow!("Invalid digits: {}", value))?,
+                    )
+                }
+                "period" => {
+                    period = value
+                        .parse()
+                        .map_err(|_| anyhow!("Invalid period: {}", value))?
+                }
+                "issuer" => issuer = Some(value.to_string()),
+                "encoder" => steam |= value.eq_ignore_ascii_case("steam"),
+                _ => {}
+            }
+        }
+
+        let secret = secret.ok_or_else(|| anyhow!("otpauth URI has no secret"))?;
+        let mut generator = Self::new(&secret)?
+            .with_algorithm(algorithm)
+            .with_period(period)?;
+        generator = if steam {
+            generator.steam()
+        } else {
+            generator.with_digits(digits.unwrap_or(6))?
+        };
+
+        let account = (!account.is_empty()).then(|| account.to_string());
+        Ok(generator.with_label(issuer.filter(|i| !i.is_empty()), account))
+    }
+
+    /// Build a generator from a TOTP credential field
+    ///
+    /// The value may be a bare secret or an `otpauth://` URI; `algorithm`,
+    /// `digits`, `period` and `encoder` metadata override the defaults, as
+    /// stored by the importers.
+    pub fn from_field(field: &CredentialField) -> Result<Self> {
+        if field.value.trim().to_lowercase().starts_with("otpauth://") {
+            return Self::from_uri(&field.value);
+        }
+
+        let mut generator = Self::new(&field.value)?;
+        let meta = |key: &str| field.metadata.get(key).map(|v| v.trim());
+        if let Some(algorithm) = meta("algorithm") {
+            generator = generator.with_algorithm(
+                TotpAlgorithm::from_name(algorithm)
+                    .ok_or_else(|| anyhow!("Unsupported TOTP algorithm: {}", algorithm))?,
+            );
+        }
+        if let Some(period) = meta("period") {
+            generator = generator.with_period(
+                period
+                    .parse()
+                    .map_err(|_| anyhow!("Invalid period: {}", period))?,
+            )?;
+        }
+        let issuer = meta("issuer").map(str::to_string);
+        if meta("encoder").is_some_and(|e| e.eq_ignore_ascii_case("steam"))
+            || issuer
+                .as_deref()
+                .is_some_and(|i| i.eq_ignore_ascii_case("steam"))
+        {
+            generator = generator.steam();
+        } else if let Some(digits) = meta("digits") {
+            generator = generator.with_digits(
+                digits
+                    .parse()
+                    .map_err(|_| anyhow!("Invalid digits: {}", digits))?,
+            )?;
+        }
+        Ok(generator.with_label(issuer, None))
+    }
+
+    /// Decode a Google Authenticator `otpauth-migration://offline?data=...` export
+    ///
+    /// Counter-based (HOTP) entries are skipped since ZipLock only stores TOTP.
+    pub fn from_migration_uri(uri: &str) -> Result<Vec<Self>> {
+        let url = url::Url::parse(uri.trim())
+            .map_err(|e| anyhow!("Invalid otpauth-migration URI: {}", e))?;
+        if url.scheme() != "otpauth-migration" {
+            return Err(anyhow!("Not an otpauth-migration URI"));
+        }
+
+        let data = url
+            .query_pairs()
+            .find(|(key, _)| key == "data")
+            .map(|(_, value)| value.replace(' ', "+"))
+            .ok_or_else(|| anyhow!("otpauth-migration URI has no data"))?;
+        let payload = base64::engine::general_purpose::STANDARD_NO_PAD
+            .decode(data.trim_end_matches('='))
+            .map_err(|e| anyhow!("Invalid migration payload: {}", e))?;
+
+        let mut generators = Vec::new();
+        for (field, value) in ProtoReader::new(&payload).fields()? {
+            if let (1, ProtoValue::Bytes(params)) = (field, value) {
+                if let Some(generator) = Self::from_migration_params(params)? {
+                    generators.push(generator);
+                }
+            }
+        }
+        Ok(generators)
+    }
+
+    fn from_migration_params(data: &[u8]) -> Result<Option<Self>> {
+        let mut secret = Vec::new();
+        let mut name = String::new();
+        let mut issuer = String::new();
+        let mut algorithm = TotpAlgorithm::Sha1;
+        let mut digits = 6;
+        let mut otp_type = 2;
+
+        for (field, value) in ProtoReader::new(data).fields()? {
+            match (field, value) {
+                (1, ProtoValue::Bytes(bytes)) => secret = bytes.to_vec(),
+                (2, ProtoValue::Bytes(bytes)) => name = String::from_utf8_lossy(bytes).to_string(),
+                (3, ProtoValue::Bytes(bytes)) => {
+                    issuer = String::from_utf8_lossy(bytes).to_string()
+                }
+                (4, ProtoValue::Varint(value)) => {
+                    algorithm = match value {
+                        0 | 1 => TotpAlgorithm::Sha1,
+                        2 => TotpAlgorithm::Sha256,
+                        3 => TotpAlgorithm::Sha512,
+                        _ => return Err(anyhow!("Unsupported algorithm in migration payload")),
+                    }
+                }
+                (5, ProtoValue::Varint(value)) => digits = if value == 2 { 8 } else { 6 },
+                (6, ProtoValue::Varint(value)) => otp_type = value,
+                _ => {}
+            }
+        }
+
+        // 1 = HOTP, 2 = TOTP (0 is unspecified and treated as TOTP)
+        if otp_type == 1 {
+            return Ok(None);
+        }
+        if secret.is_empty() {
+            return Err(anyhow!("Migration entry has no secret"));
+        }
+
+        let account = match name.split_once(':') {
+            Some((_, account)) => account.trim().to_string(),
+            None => name,
+        };
+        let generator = Self::from_bytes(secret)
+            .with_algorithm(algorithm)
+            .with_digits(digits)?
+            .with_label(
+                (!issuer.is_empty()).then_some(issuer),
+                (!account.is_empty()).then_some(account),
+            );
+        Ok(Some(generator))
+    }
+
+    /// Serialize back to an `otpauth://` URI
+    pub fn to_uri(&self) -> String {
+        let host = match self.encoding {
+            TotpEncoding::Numeric => "totp",
+            TotpEncoding::Steam => "steam",
+        };
+        let label = match (&self.issuer, &self.account) {
+            (Some(issuer), Some(account)) => format!("{}:{}", issuer, account),
+            (Some(label), None) | (None, Some(label)) => label.clone(),
+            (None, None) => String::new(),
+        };
+
+        let mut url =
+            url::Url::parse(&format!("otpauth://{}/", host)).expect("static URI is valid");
+        url.set_path(&label);
+        {
+            let mut query = url.query_pairs_mut();
+            query.append_pair("secret", &self.secret_base32());
+            if let Some(issuer) = &self.issuer {
+                query.append_pair("issuer", issuer);
+            }
+            if self.algorithm != TotpAlgorithm::Sha1 {
+                query.append_pair("algorithm", self.algorithm.name());
+            }
+            if self.encoding == TotpEncoding::Numeric && self.digits != 6 {
+                query.append_pair("digits", &self.digits.to_string());
+            }
+            if self.period != 30 {
+                query.append_pair("period", &self.period.to_string());
+            }
+        }
+        url.to_string()
+    }
+
+    /// The secret as unpadded base32
+    pub fn secret_base32(&self) -> String {
+        base32_encode(&self.secret)
+    }
+
+    pub fn algorithm(&self) -> TotpAlgorithm {
+        self.algorithm
+    }
+
+    pub fn digits(&self) -> u32 {
+        self.digits
+    }
+
+    pub fn period(&self) -> u64 {
+        self.period
+    }
+
+    pub fn encoding(&self) -> TotpEncoding {
+        self.encoding
+    }
+
+    pub fn issuer(&self) -> Option<&str> {
+        self.issuer.as_deref()
+    }
+
+    pub fn account(&self) -> Option<&str> {
+        self.account.as_deref()
+    }
+
+    /// Generate the code for the current time
+    pub fn generate(&self) -> Result<String> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|e| anyhow!("Failed to get current time: {}", e))?
+            .as_secs();
+        self.generate_at(now)
+    }
+
+    /// Generate the code for a specific Unix timestamp
+    pub fn generate_at(&self, timestamp: u64) -> Result<String> {
+        let counter = timestamp / self.period;
+        let hash = self.algorithm.hmac(&self.secret, &counter.to_be_bytes())?;
+
+        // Dynamic truncation according to RFC 4226
+        let offset = (hash[hash.len() - 1] & 0xf) as usize;
+        let mut value = u32::from_be_bytes([
+            hash[offset] & 0x7f,
+            hash[offset + 1],
+            hash[offset + 2],
+            hash[offset + 3],
+        ]);
+
+        Ok(match self.encoding {
+            TotpEncoding::Numeric => format!(
+                "{:0width$}",
+                value % 10u32.pow(self.digits),
+                width = self.digits as usize
+            ),
+            TotpEncoding::Steam => (0..self.digits)
+                .map(|_| {
+                    let c = STEAM_ALPHABET[(value as usize) % STEAM_ALPHABET.len()] as char;
+                    value /= STEAM_ALPHABET.len() as u32;
+                    c
+                })
+                .collect(),
+        })
+    }
+
+    /// Seconds until the current code expires
+    pub fn seconds_remaining(&self) -> u64 {
+        get_seconds_until_refresh(self.period)
+    }
+}
 
 /// Generate a 6-digit TOTP code from a base32-encoded secret
 ///
@@ -51,36 +518,9 @@ pub fn generate_totp(secret: &str, time_step: u64) -> Result<String> {
 /// * `Ok(String)` - 6-digit TOTP code
 /// * `Err(anyhow::Error)` - If secret is invalid or generation fails
 pub fn generate_totp_at_time(secret: &str, time_step: u64, timestamp: u64) -> Result<String> {
-    // Clean the secret - remove spaces and convert to uppercase
-    let clean_secret = secret.replace(' ', "").to_uppercase();
-
-    // Validate that the secret looks like base32
-    if clean_secret.is_empty() {
-        return Err(anyhow!("TOTP secret cannot be empty"));
-    }
-
-    // Check if secret contains only valid base32 characters
-    if !clean_secret
-        .chars()
-        .all(|c| "ABCDEFGHIJKLMNOPQRSTUVWXYZ234567=".contains(c))
-    {
-        return Err(anyhow!(
-            "Invalid base32 secret: contains invalid characters"
-        ));
-    }
-
-    // Decode base32 secret to bytes
-    let secret_bytes = match base32_decode(&clean_secret) {
-        Ok(bytes) => bytes,
-        Err(_) => return Err(anyhow!("Invalid base32 secret")),
-    };
-
-    // Calculate time counter (number of time steps since Unix epoch)
-    let time_counter = timestamp / time_step;
-
-    // Generate TOTP using HMAC-SHA1
-    let code = generate_totp_code(&secret_bytes, time_counter)?;
-    Ok(format!("{:06}", code))
+    TotpGenerator::new(secret)?
+        .with_period(time_step)?
+        .generate_at(timestamp)
 }
 
 /// Get the remaining seconds until the next TOTP refresh
@@ -175,32 +615,84 @@ fn base32_decode(input: &str) -> Result<Vec<u8>, &'static str> {
     Ok(output)
 }
 
-/// Generate TOTP code using HMAC-SHA1 according to RFC 6238
-fn generate_totp_code(secret: &[u8], time_counter: u64) -> Result<u32> {
-    // Convert time counter to big-endian bytes
-    let time_bytes = time_counter.to_be_bytes();
+/// Encode bytes as unpadded base32
+fn base32_encode(input: &[u8]) -> String {
+    let alphabet = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+    let mut output = String::new();
+    let mut buffer = 0u32;
+    let mut bits_left = 0;
+
+    for &byte in input {
+        buffer = (buffer << 8) | byte as u32;
+        bits_left += 8;
+        while bits_left >= 5 {
+            output.push(alphabet[((buffer >> (bits_left - 5)) & 0x1f) as usize] as char);
+            bits_left -= 5;
+        }
+    }
+    if bits_left > 0 {
+        output.push(alphabet[((buffer << (5 - bits_left)) & 0x1f) as usize] as char);
+    }
 
-    // Create HMAC-SHA1 instance
-    let mut mac =
-        HmacSha1::new_from_slice(secret).map_err(|_| anyhow!("Invalid secret length for HMAC"))?;
+    output
+}
 
-    // Update HMAC with time counter
-    mac.update(&time_bytes);
+/// A decoded protobuf field value (only the wire types the migration format uses)
+enum ProtoValue<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+}
 
-    // Get HMAC result
-    let result = mac.finalize().into_bytes();
+/// Minimal protobuf reader for Google Authenticator migration payloads
+struct ProtoReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
 
-    // Dynamic truncation according to RFC 4226
-    let offset = (result[19] & 0xf) as usize;
-    let truncated = u32::from_be_bytes([
-        result[offset] & 0x7f,
-        result[offset + 1],
-        result[offset + 2],
-        result[offset + 3],
-    ]);
+impl<'a> ProtoReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn varint(&mut self) -> Result<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = *self
+                .data
+                .get(self.pos)
+                .ok_or_else(|| anyhow!("Truncated migration payload"))?;
+            self.pos += 1;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(anyhow!("Invalid varint in migration payload"))
+    }
 
-    // Return 6-digit code
-    Ok(truncated % 1_000_000)
+    fn fields(mut self) -> Result<Vec<(u64, ProtoValue<'a>)>> {
+        let mut fields = Vec::new();
+        while self.pos < self.data.len() {
+            let key = self.varint()?;
+            let value = match key & 0x7 {
+                0 => ProtoValue::Varint(self.varint()?),
+                2 => {
+                    let len = self.varint()? as usize;
+                    let end = self
+                        .pos
+                        .checked_add(len)
+                        .filter(|end| *end <= self.data.len())
+                        .ok_or_else(|| anyhow!("Truncated migration payload"))?;
+                    let bytes = &self.data[self.pos..end];
+                    self.pos = end;
+                    ProtoValue::Bytes(bytes)
+                }
+                wire => return Err(anyhow!("Unsupported protobuf wire type {}", wire)),
+            };
+            fields.push((key >> 3, value));
+        }
+        Ok(fields)
+    }
 }
 
 #[cfg(test)]
@@ -340,4 +832,127 @@ mod tests {
             "Countdown should match expected calculation based on time boundaries"
         );
     }
+
+    #[test]
+    fn test_rfc6238_sha256_sha512_vectors() {
+        let sha256 = TotpGenerator::from_bytes(b"12345678901234567890123456789012".to_vec())
+            .with_algorithm(TotpAlgorithm::Sha256)
+            .with_digits(8)
+            .unwrap();
+        assert_eq!(sha256.generate_at(59).unwrap(), "46119246");
+        assert_eq!(sha256.generate_at(1111111109).unwrap(), "68084774");
+
+        let sha512 = TotpGenerator::from_bytes(
+            b"1234567890123456789012345678901234567890123456789012345678901234".to_vec(),
+        )
+        .with_algorithm(TotpAlgorithm::Sha512)
+        .with_digits(8)
+        .unwrap();
+        assert_eq!(sha512.generate_at(59).unwrap(), "90693936");
+        assert_eq!(sha512.generate_at(1111111109).unwrap(), "25091201");
+
+        let sha1 = TotpGenerator::new("GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ")
+            .unwrap()
+            .with_digits(8)
+            .unwrap();
+        assert_eq!(sha1.generate_at(1111111109).unwrap(), "07081804");
+    }
+
+    #[test]
+    fn test_steam_guard_codes() {
+        let steam = TotpGenerator::new("JBSWY3DPEHPK3PXP").unwrap().steam();
+        assert_eq!(steam.generate_at(59).unwrap(), "2YXGV");
+        assert_eq!(steam.generate_at(1111111109).unwrap(), "CWDGV");
+
+        let from_uri = TotpGenerator::from_uri(
+            "otpauth://totp/Steam:gabe?secret=JBSWY3DPEHPK3PXP&encoder=steam",
+        )
+        .unwrap();
+        assert_eq!(from_uri.encoding(), TotpEncoding::Steam);
+        assert_eq!(from_uri.generate_at(59).unwrap(), "2YXGV");
+    }
+
+    #[test]
+    fn test_custom_period() {
+        let totp = TotpGenerator::new("JBSWY3DPEHPK3PXP")
+            .unwrap()
+            .with_period(60)
+            .unwrap();
+        assert_eq!(totp.generate_at(0).unwrap(), totp.generate_at(59).unwrap());
+        assert_ne!(totp.generate_at(59).unwrap(), totp.generate_at(60).unwrap());
+        assert!(TotpGenerator::new("JBSWY3DPEHPK3PXP")
+            .unwrap()
+            .with_period(0)
+            .is_err());
+        assert!(TotpGenerator::new("JBSWY3DPEHPK3PXP")
+            .unwrap()
+            .with_digits(12)
+            .is_err());
+    }
+
+    #[test]
+    fn test_otpauth_uri_round_trip() {
+        let uri = "otpauth://totp/ACME%20Co:jane@example.com?secret=JBSWY3DPEHPK3PXP&issuer=ACME%20Co&algorithm=SHA512&digits=8&period=60";
+        let totp = TotpGenerator::from_uri(uri).unwrap();
+        assert_eq!(totp.issuer(), Some("ACME Co"));
+        assert_eq!(totp.account(), Some("jane@example.com"));
+        assert_eq!(totp.algorithm(), TotpAlgorithm::Sha512);
+        assert_eq!(totp.digits(), 8);
+        assert_eq!(totp.period(), 60);
+
+        let reparsed = TotpGenerator::from_uri(&totp.to_uri()).unwrap();
+        assert_eq!(reparsed, totp);
+
+        assert!(TotpGenerator::from_uri("otpauth://hotp/x?secret=JBSWY3DPEHPK3PXP").is_err());
+        assert!(TotpGenerator::from_uri("otpauth://totp/x").is_err());
+    }
+
+    #[test]
+    fn test_from_field_metadata() {
+        let field = CredentialField::totp_secret("JBSWY3DPEHPK3PXP")
+            .with_metadata("algorithm", "SHA256")
+            .with_metadata("digits", "7");
+        let totp = TotpGenerator::from_field(&field).unwrap();
+        assert_eq!(totp.algorithm(), TotpAlgorithm::Sha256);
+        assert_eq!(totp.generate_at(59).unwrap().len(), 7);
+
+        let steam =
+            CredentialField::totp_secret("JBSWY3DPEHPK3PXP").with_metadata("issuer", "Steam");
+        assert_eq!(
+            TotpGenerator::from_field(&steam).unwrap().encoding(),
+            TotpEncoding::Steam
+        );
+    }
+
+    #[test]
+    fn test_google_authenticator_migration() {
+        let single = TotpGenerator::from_migration_uri(
+            "otpauth-migration://offline?data=CjEKCkhlbGxvId6tvu8SGEV4YW1wbGU6YWxpY2VAZ29vZ2xlLmNvbRoHRXhhbXBsZTAC",
+        )
+        .unwrap();
+        assert_eq!(single.len(), 1);
+        assert_eq!(single[0].secret_base32(), "JBSWY3DPEHPK3PXP");
+        assert_eq!(single[0].issuer(), Some("Example"));
+        assert_eq!(single[0].account(), Some("alice@google.com"));
+
+        // SHA256 / 8-digit TOTP entry plus an HOTP entry that is skipped
+        let batch = TotpGenerator::from_migration_uri(
+            "otpauth-migration://offline?data=CjgKIDEyMzQ1Njc4OTAxMjM0NTY3ODkwMTIzNDU2Nzg5MDEyEghBQ01FOmJvYhoEQUNNRSACKAIwAgoZCgpIZWxsbyHerb7vEgdjb3VudGVyMAE4BRABGAE%3D",
+        )
+        .unwrap();
+        assert_eq!(batch.len(), 1);
+        assert_eq!(batch[0].algorithm(), TotpAlgorithm::Sha256);
+        assert_eq!(batch[0].digits(), 8);
+        assert_eq!(batch[0].generate_at(59).unwrap(), "46119246");
+
+        assert!(TotpGenerator::from_migration_uri("otpauth-migration://offline?data=CgU").is_err());
+    }
+
+    #[test]
+    fn test_base32_round_trip() {
+        for secret in ["JBSWY3DPEHPK3PXP", "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ"] {
+            let bytes = base32_decode(secret).unwrap();
+            assert_eq!(base32_encode(&bytes), secret);
+        }
+    }
 }
@@ -0,0 +1,437 @@
+//! Finding the credentials that belong to a web page
+//!
+//! Browser integration and autofill ask which credentials to offer for the
+//! page the user is on. [`UrlMatcher`] compares the page URL with every URL
+//! field of the credentials. By default a saved URL matches any page on the
+//! same registrable domain, so `https://example.com` covers
+//! `https://login.example.com/signin`, while `alice.github.io` and
+//! `bob.github.io` stay apart because `github.io` is a public suffix. A URL
+//! field can ask for a stricter or looser rule with a [`UrlMatchMode`] stored
+//! in its `match` metadata.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::core::{CoreError, CoreResult};
+use crate::models::{CredentialField, CredentialRecord, FieldType};
+
+/// Field metadata key holding the [`UrlMatchMode`] of a URL field
+pub const URL_MATCH_METADATA_KEY: &str = "match";
+
+/// How a saved URL is compared with the page URL, from most to least
+/// specific
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UrlMatchMode {
+    /// The whole URL is the same, ignoring any `#fragment`
+    Exact,
+    /// The page URL starts with the saved one
+    StartsWith,
+    /// The saved value is a regular expression the page URL has to match
+    Regex,
+    /// Same host and port
+    Host,
+    /// Same registrable domain, e.g. `example.co.uk` (the default)
+    Domain,
+    /// Never offered automatically
+    Never,
+}
+
+impl UrlMatchMode {
+    /// Name stored in field metadata
+    pub fn name(&self) -> &'static str {
+        match self {
+            UrlMatchMode::Exact => "exact",
+            UrlMatchMode::StartsWith => "starts_with",
+            UrlMatchMode::Regex => "regex",
+            UrlMatchMode::Host => "host",
+            UrlMatchMode::Domain => "domain",
+            UrlMatchMode::Never => "never",
+        }
+    }
+
+    /// Parse a name written by [`UrlMatchMode::name`]
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().replace('-', "_").as_str() {
+            "exact" => Some(UrlMatchMode::Exact),
+            "starts_with" => Some(UrlMatchMode::StartsWith),
+            "regex" => Some(UrlMatchMode::Regex),
+            "host" => Some(UrlMatchMode::Host),
+            "domain" => Some(UrlMatchMode::Domain),
+            "never" => Some(UrlMatchMode::Never),
+            _ => None,
+        }
+    }
+
+    /// The mode a URL field asks for; unknown names fall back to
+    /// [`UrlMatchMode::Domain`]
+    pub fn of_field(field: &CredentialField) -> Self {
+        field
+            .metadata
+            .get(URL_MATCH_METADATA_KEY)
+            .and_then(|name| Self::parse(name))
+            .unwrap_or(UrlMatchMode::Domain)
+    }
+}
+
+/// A credential offered for a page
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UrlMatch {
+    pub credential_id: String,
+    /// Name of the URL field that matched
+    pub field_name: String,
+    /// The rule it matched by; more specific rules sort first
+    pub mode: UrlMatchMode,
+}
+
+/// Matches page URLs against the URL fields of credentials
+#[derive(Debug, Clone)]
+pub struct UrlMatcher {
+    match_subdomains: bool,
+    match_scheme: bool,
+    match_port: bool,
+}
+
+impl Default for UrlMatcher {
+    fn default() -> Self {
+        Self {
+            match_subdomains: true,
+            match_scheme: true,
+            match_port: true,
+        }
+    }
+}
+
+impl UrlMatcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether a saved URL covers sibling subdomains in
+    /// [`UrlMatchMode::Domain`] (default: yes). When off, `login.example.com`
+    /// only matches itself and its own subdomains, not `mail.example.com`.
+    pub fn match_subdomains(mut self, enabled: bool) -> Self {
+        self.match_subdomains = enabled;
+        self
+    }
+
+    /// Whether the scheme has to agree for [`UrlMatchMode::Domain`] and
+    /// [`UrlMatchMode::Host`] (default: yes). A saved `http` URL still matches
+    /// an `https` page, never the other way round.
+    pub fn match_scheme(mut self, enabled: bool) -> Self {
+        self.match_scheme = enabled;
+        self
+    }
+
+    /// Whether a port in the saved URL has to agree for
+    /// [`UrlMatchMode::Domain`] (default: yes); [`UrlMatchMode::Host`] always
+    /// compares ports
+    pub fn match_port(mut self, enabled: bool) -> Self {
+        self.match_port = enabled;
+        self
+    }
+
+    /// Credentials to offer for `page_url`, the best match first
+    ///
+    /// Each credential appears once, with the most specific of its URL
+    /// fields that matched. Protected URL fields are not looked at.
+    pub fn find<'a>(
+        &self,
+        page_url: &str,
+        credentials: impl IntoIterator<Item = &'a CredentialRecord>,
+    ) -> CoreResult<Vec<UrlMatch>> {
+        let page = Url::parse(page_url.trim()).map_err(|e| CoreError::ValidationError {
+            message: format!("Invalid page URL '{}': {}", page_url, e),
+        })?;
+
+        let mut matches: Vec<(UrlMatch, &CredentialRecord)> = Vec::new();
+        for credential in credentials {
+            let best = credential
+                .fields
+                .iter()
+                .filter(|(_, field)| field.field_type == FieldType::Url && !field.is_protected())
+                .filter_map(|(name, field)| {
+                    let mode = UrlMatchMode::of_field(field);
+                    self.matches(&page, &field.value, mode)
+                        .then_some((name, mode))
+                })
+                .min_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(b.0)));
+            if let Some((name, mode)) = best {
+                let found = UrlMatch {
+                    credential_id: credential.id.clone(),
+                    field_name: name.clone(),
+                    mode,
+                };
+                matches.push((found, credential));
+            }
+        }
+
+        matches.sort_by(|(a, ca), (b, cb)| {
+            a.mode
+                .cmp(&b.mode)
+                .then_with(|| cb.favorite.cmp(&ca.favorite))
+                .then_with(|| ca.title.to_lowercase().cmp(&cb.title.to_lowercase()))
+        });
+        Ok(matches.into_iter().map(|(found, _)| found).collect())
+    }
+
+    /// Whether the saved URL `saved` matches `page` under `mode`
+    pub fn matches(&self, page: &Url, saved: &str, mode: UrlMatchMode) -> bool {
+        let saved = saved.trim();
+        if saved.is_empty() {
+            return false;
+        }
+        match mode {
+            UrlMatchMode::Never => false,
+            UrlMatchMode::Regex => match Regex::new(saved) {
+                Ok(regex) => regex.is_match(page.as_str()),
+                Err(_) => false,
+            },
+            UrlMatchMode::Exact => SavedUrl::parse(saved)
+                .is_some_and(|s| without_fragment(&s.url) == without_fragment(page)),
+            UrlMatchMode::StartsWith => SavedUrl::parse(saved)
+                .is_some_and(|s| page.as_str().starts_with(without_fragment(&s.url))),
+            UrlMatchMode::Host => SavedUrl::parse(saved).is_some_and(|s| {
+                self.scheme_agrees(&s, page)
+                    && host_of(&s.url) == host_of(page)
+                    && s.url.port_or_known_default() == page.port_or_known_default()
+            }),
+            UrlMatchMode::Domain => SavedUrl::parse(saved).is_some_and(|s| {
+                let (Some(saved_host), Some(page_host)) = (host_of(&s.url), host_of(page)) else {
+                    return false;
+                };
+                let same_site = if self.match_subdomains {
+                    match (
+                        registrable_domain(&saved_host),
+                        registrable_domain(&page_host),
+                    ) {
+                        (Some(a), Some(b)) => a == b,
+                        _ => saved_host == page_host,
+                    }
+                } else {
+                    page_host == saved_host || page_host.ends_with(&format!(".{}", saved_host))
+                };
+                let port_agrees = !self.match_port
+                    || s.url.port().is_none()
+                    || s.url.port_or_known_default() == page.port_or_known_default();
+                same_site && port_agrees && self.scheme_agrees(&s, page)
+            }),
+        }
+    }
+
+    fn scheme_agrees(&self, saved: &SavedUrl, page: &Url) -> bool {
+        if !self.match_scheme || !saved.explicit_scheme {
+            return true;
+        }
+        saved.url.scheme() == page.scheme()
+            || (saved.url.scheme() == "http" && page.scheme() == "https")
+    }
+}
+
+/// A saved URL, which may have been entered without a scheme
+struct SavedUrl {
+    url: Url,
+    explicit_scheme: bool,
+}
+
+impl SavedUrl {
+    fn parse(value: &str) -> Option<Self> {
+        if let Ok(url) = Url::parse(value) {
+            if url.has_host() {
+                return Some(Self {
+                    url,
+                    explicit_scheme: true,
+                });
+            }
+        }
+        Url::parse(&format!("https://{}", value))
+            .ok()
+            .filter(Url::has_host)
+            .map(|url| Self {
+                url,
+                explicit_scheme: false,
+            })
+    }
+}
+
+fn without_fragment(url: &Url) -> &str {
+    let text = url.as_str();
+    text.split_once('#').map_or(text, |(before, _)| before)
+}
+
+/// Lowercase host without a trailing dot or leading `www.`
+fn host_of(url: &Url) -> Option<String> {
+    let host = url.host_str()?.trim_end_matches('.').to_ascii_lowercase();
+    Some(
+        host.strip_prefix("www.")
+            .map(str::to_string)
+            .unwrap_or(host),
+    )
+}
+
+/// The registrable domain of a hostname: its public suffix plus one label
+/// (`login.example.co.uk` -> `example.co.uk`)
+///
+/// Returns `None` for IP addresses, single-label hosts like `localhost` and
+/// hosts that are a public suffix themselves.
+pub fn registrable_domain(host: &str) -> Option<String> {
+    let host = host.trim().trim_end_matches('.').to_ascii_lowercase();
+    if host.parse::<std::net::IpAddr>().is_ok() || host.starts_with('[') {
+        return None;
+    }
+    if host.split('.').any(|label| label.is_empty()) {
+        return None;
+    }
+    // Both sections of the list count: `a.myshopify.com` and
+    // `b.myshopify.com` belong to different shops
+    psl::domain_str(&host).map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn login(title: &str, url: &str) -> CredentialRecord {
+        let mut credential = CredentialRecord::new(title.to_string(), "login".to_string());
+        credential.set_field("url", CredentialField::url(url));
+        credential
+    }
+
+    #[test]
+    fn test_registrable_domain() {
+        assert_eq!(
+            registrable_domain("login.example.co.uk").as_deref(),
+            Some("example.co.uk")
+        );
+        assert_eq!(
+            registrable_domain("www.Example.com.").as_deref(),
+            Some("example.com")
+        );
+        assert_eq!(
+            registrable_domain("alice.github.io").as_deref(),
+            Some("alice.github.io")
+        );
+        assert_eq!(registrable_domain("github.io"), None);
+        assert_eq!(
+            registrable_domain("shop.example.co.jp").as_deref(),
+            Some("example.co.jp")
+        );
+        assert_eq!(registrable_domain("co.uk"), None);
+        assert_eq!(registrable_domain("localhost"), None);
+        assert_eq!(registrable_domain("192.168.1.1"), None);
+    }
+
+    #[test]
+    fn test_private_suffixes_keep_sites_apart() {
+        assert_eq!(
+            registrable_domain("a.myshopify.com").as_deref(),
+            Some("a.myshopify.com")
+        );
+        assert_eq!(registrable_domain("myshopify.com"), None);
+
+        let matcher = UrlMatcher::new();
+        for (page, saved) in [
+            ("https://b.myshopify.com/login", "https://a.myshopify.com"),
+            ("https://bob.blogspot.com/", "https://alice.blogspot.com"),
+            ("https://b.herokuapp.com/", "https://a.herokuapp.com"),
+        ] {
+            let page = Url::parse(page).unwrap();
+            assert!(
+                !matcher.matches(&page, saved, UrlMatchMode::Domain),
+                "{}",
+                saved
+            );
+        }
+        let page = Url::parse("https://admin.a.myshopify.com/").unwrap();
+        assert!(matcher.matches(&page, "https://a.myshopify.com", UrlMatchMode::Domain));
+    }
+
+    #[test]
+    fn test_modes() {
+        let matcher = UrlMatcher::new();
+        let page = Url::parse("https://login.example.com:443/signin?next=%2F#top").unwrap();
+
+        assert!(matcher.matches(&page, "example.com", UrlMatchMode::Domain));
+        assert!(matcher.matches(&page, "http://mail.example.com", UrlMatchMode::Domain));
+        assert!(!matcher.matches(&page, "https://example.org", UrlMatchMode::Domain));
+        assert!(!matcher.matches(&page, "https://example.com:8443", UrlMatchMode::Domain));
+        assert!(!matcher.clone().match_subdomains(false).matches(
+            &page,
+            "mail.example.com",
+            UrlMatchMode::Domain
+        ));
+        assert!(matcher.clone().match_subdomains(false).matches(
+            &page,
+            "example.com",
+            UrlMatchMode::Domain
+        ));
+
+        assert!(matcher.matches(&page, "https://login.example.com/", UrlMatchMode::Host));
+        assert!(!matcher.matches(&page, "https://example.com/", UrlMatchMode::Host));
+        assert!(matcher.matches(
+            &page,
+            "https://login.example.com/sign",
+            UrlMatchMode::StartsWith
+        ));
+        assert!(matcher.matches(
+            &page,
+            "https://login.example.com/signin?next=%2F",
+            UrlMatchMode::Exact
+        ));
+        assert!(!matcher.matches(
+            &page,
+            "https://login.example.com/signin",
+            UrlMatchMode::Exact
+        ));
+        assert!(matcher.matches(
+            &page,
+            r"^https://[a-z]+\.example\.com/",
+            UrlMatchMode::Regex
+        ));
+        assert!(!matcher.matches(&page, "(unclosed", UrlMatchMode::Regex));
+        assert!(!matcher.matches(&page, "example.com", UrlMatchMode::Never));
+
+        // An https credential is not offered on a plain http page
+        let insecure = Url::parse("http://example.com/").unwrap();
+        assert!(!matcher.matches(&insecure, "https://example.com", UrlMatchMode::Domain));
+        assert!(matcher.clone().match_scheme(false).matches(
+            &insecure,
+            "https://example.com",
+            UrlMatchMode::Domain
+        ));
+    }
+
+    #[test]
+    fn test_find_orders_by_specificity() {
+        let site = login("Example", "https://example.com");
+        let mut exact = login("Example signin", "https://login.example.com/signin");
+        exact.set_field(
+            "url",
+            CredentialField::url("https://login.example.com/signin")
+                .with_metadata(URL_MATCH_METADATA_KEY, "starts-with"),
+        );
+        let mut hidden = login("Old example", "https://example.com");
+        hidden.set_field(
+            "url",
+            CredentialField::url("https://example.com")
+                .with_metadata(URL_MATCH_METADATA_KEY, "never"),
+        );
+        let other = login("Pages", "https://alice.github.io");
+
+        let credentials = [site.clone(), exact.clone(), hidden, other];
+        let found = UrlMatcher::new()
+            .find("https://login.example.com/signin?x=1", &credentials)
+            .unwrap();
+        let ids: Vec<&str> = found.iter().map(|m| m.credential_id.as_str()).collect();
+        assert_eq!(ids, vec![exact.id.as_str(), site.id.as_str()]);
+        assert_eq!(found[0].mode, UrlMatchMode::StartsWith);
+        assert_eq!(found[1].field_name, "url");
+
+        let found = UrlMatcher::new()
+            .find("https://bob.github.io/", &credentials)
+            .unwrap();
+        assert!(found.is_empty());
+        assert!(UrlMatcher::new().find("not a url", &credentials).is_err());
+    }
+}
@@ -7,17 +7,25 @@ use regex::Regex;
 use std::collections::HashSet;
 
 use crate::core::types::{
-    MAX_FIELDS_PER_CREDENTIAL, MAX_FIELD_VALUE_LENGTH, MAX_NOTES_LENGTH, MAX_TAGS_PER_CREDENTIAL,
-    MAX_TAG_LENGTH, MAX_TITLE_LENGTH,
+    MAX_FIELDS_PER_CREDENTIAL, MAX_FIELD_VALUE_LENGTH, MAX_NOTES_LENGTH, MAX_NOTE_LENGTH,
+    MAX_TAGS_PER_CREDENTIAL, MAX_TAG_LENGTH, MAX_TITLE_LENGTH,
 };
-use crate::models::{CredentialField, CredentialRecord, FieldType};
+use crate::models::{CredentialField, CredentialRecord, FieldType, PROTECTED_FIELD_PLACEHOLDER};
+use crate::utils::localization::Message;
+use crate::utils::pgp::{armored_block, PRIVATE_KEY_LABEL, PUBLIC_KEY_LABEL};
 
 /// Validation result with detailed error information
+///
+/// `errors` and `warnings` hold the English text; the same problems are in
+/// `error_messages` and `warning_messages`, in the same order, as catalog
+/// messages that can be shown in other languages.
 #[derive(Debug, Clone, PartialEq)]
 pub struct ValidationResult {
     pub is_valid: bool,
     pub errors: Vec<String>,
     pub warnings: Vec<String>,
+    pub error_messages: Vec<Message>,
+    pub warning_messages: Vec<Message>,
 }
 
 impl ValidationResult {
@@ -27,33 +35,44 @@ impl ValidationResult {
             is_valid: true,
             errors: Vec::new(),
             warnings: Vec::new(),
+            error_messages: Vec::new(),
+            warning_messages: Vec::new(),
         }
     }
 
-    /// Create a failed validation result with errors
+    /// Create a failed validation result with errors that have no catalog
+    /// entry
     pub fn with_errors(errors: Vec<String>) -> Self {
         Self {
             is_valid: false,
+            error_messages: errors.iter().cloned().map(Message::untranslated).collect(),
             errors,
             warnings: Vec::new(),
+            warning_messages: Vec::new(),
         }
     }
 
     /// Add an error to this validation result
-    pub fn add_error(&mut self, error: String) {
-        self.errors.push(error);
+    pub fn add_error(&mut self, error: impl Into<Message>) {
+        let error = error.into();
+        self.errors.push(error.to_string());
+        self.error_messages.push(error);
         self.is_valid = false;
     }
 
     /// Add a warning to this validation result
-    pub fn add_warning(&mut self, warning: String) {
-        self.warnings.push(warning);
+    pub fn add_warning(&mut self, warning: impl Into<Message>) {
+        let warning = warning.into();
+        self.warnings.push(warning.to_string());
+        self.warning_messages.push(warning);
     }
 
     /// Merge another validation result into this one
     pub fn merge(&mut self, other: ValidationResult) {
         self.errors.extend(other.errors);
         self.warnings.extend(other.warnings);
+        self.error_messages.extend(other.error_messages);
+        self.warning_messages.extend(other.warning_messages);
         if !other.is_valid {
             self.is_valid = false;
         }
@@ -75,11 +94,11 @@ pub fn validate_credential(credential: &CredentialRecord) -> ValidationResult {
 
     // Validate field count
     if credential.fields.len() > MAX_FIELDS_PER_CREDENTIAL {
-        result.add_error(format!(
-            "Too many fields: {} (maximum {})",
-            credential.fields.len(),
-            MAX_FIELDS_PER_CREDENTIAL
-        ));
+        result.add_error(
+            Message::new("validation.too_many_fields")
+                .with_arg("count", credential.fields.len())
+                .with_arg("max", MAX_FIELDS_PER_CREDENTIAL),
+        );
     }
 
     // Validate each field
@@ -90,15 +109,15 @@ pub fn validate_credential(credential: &CredentialRecord) -> ValidationResult {
 
     // Validate timestamps
     if credential.created_at <= 0 {
-        result.add_error("Invalid created_at timestamp".to_string());
+        result.add_error(Message::new("validation.created_at_invalid"));
     }
 
     if credential.updated_at <= 0 {
-        result.add_error("Invalid updated_at timestamp".to_string());
+        result.add_error(Message::new("validation.updated_at_invalid"));
     }
 
     if credential.updated_at < credential.created_at {
-        result.add_error("Updated timestamp cannot be before created timestamp".to_string());
+        result.add_error(Message::new("validation.updated_before_created"));
     }
 
     // Check for duplicate field names (case-insensitive)
@@ -106,7 +125,9 @@ pub fn validate_credential(credential: &CredentialRecord) -> ValidationResult {
     for field_name in credential.fields.keys() {
         let lower_name = field_name.to_lowercase();
         if !field_names_lower.insert(lower_name) {
-            result.add_error(format!("Duplicate field name: {}", field_name));
+            result.add_error(
+                Message::new("validation.duplicate_field").with_arg("field", field_name),
+            );
         }
     }
 
@@ -118,19 +139,16 @@ pub fn validate_credential_id(id: &str) -> ValidationResult {
     let mut result = ValidationResult::success();
 
     if id.is_empty() {
-        result.add_error("Credential ID cannot be empty".to_string());
+        result.add_error(Message::new("validation.id_empty"));
     } else if id.len() > 100 {
-        result.add_error(format!(
-            "Credential ID too long: {} characters (maximum 100)",
-            id.len()
-        ));
+        result.add_error(Message::new("validation.id_too_long").with_arg("length", id.len()));
     }
 
     // Check if it's a valid UUID format (optional but recommended)
     if !id.is_empty() {
         use uuid::Uuid;
         if Uuid::parse_str(id).is_err() {
-            result.add_warning("Credential ID is not a valid UUID format".to_string());
+            result.add_warning(Message::new("validation.id_not_uuid"));
         }
     }
 
@@ -158,13 +176,13 @@ pub fn validate_credential_title(title: &str) -> ValidationResult {
     let mut result = ValidationResult::success();
 
     if title.is_empty() {
-        result.add_error("Title cannot be empty".to_string());
+        result.add_error(Message::new("validation.title_empty"));
     } else if title.len() > MAX_TITLE_LENGTH {
-        result.add_error(format!(
-            "Title too long: {} characters (maximum {})",
-            title.len(),
-            MAX_TITLE_LENGTH
-        ));
+        result.add_error(
+            Message::new("validation.title_too_long")
+                .with_arg("length", title.len())
+                .with_arg("max", MAX_TITLE_LENGTH),
+        );
     }
 
     // Check for control characters
@@ -172,12 +190,12 @@ pub fn validate_credential_title(title: &str) -> ValidationResult {
         .chars()
         .any(|c| c.is_control() && c != '\t' && c != '\n')
     {
-        result.add_error("Title contains invalid control characters".to_string());
+        result.add_error(Message::new("validation.title_control_characters"));
     }
 
     // Warn about leading/trailing whitespace
     if title != title.trim() {
-        result.add_warning("Title has leading or trailing whitespace".to_string());
+        result.add_warning(Message::new("validation.title_whitespace"));
     }
 
     result
@@ -188,9 +206,9 @@ pub fn validate_credential_type(credential_type: &str) -> ValidationResult {
     let mut result = ValidationResult::success();
 
     if credential_type.is_empty() {
-        result.add_error("Credential type cannot be empty".to_string());
+        result.add_error(Message::new("validation.type_empty"));
     } else if credential_type.len() > 50 {
-        result.add_error("Credential type too long (maximum 50 characters)".to_string());
+        result.add_error(Message::new("validation.type_too_long"));
     }
 
     // Check for invalid characters
@@ -198,10 +216,7 @@ pub fn validate_credential_type(credential_type: &str) -> ValidationResult {
         .chars()
         .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
     {
-        result.add_error(
-            "Credential type can only contain letters, numbers, hyphens, and underscores"
-                .to_string(),
-        );
+        result.add_error(Message::new("validation.type_characters"));
     }
 
     result
@@ -212,11 +227,11 @@ pub fn validate_credential_notes(notes: &str) -> ValidationResult {
     let mut result = ValidationResult::success();
 
     if notes.len() > MAX_NOTES_LENGTH {
-        result.add_error(format!(
-            "Notes too long: {} characters (maximum {})",
-            notes.len(),
-            MAX_NOTES_LENGTH
-        ));
+        result.add_error(
+            Message::new("validation.notes_too_long")
+                .with_arg("length", notes.len())
+                .with_arg("max", MAX_NOTES_LENGTH),
+        );
     }
 
     result
@@ -227,39 +242,40 @@ pub fn validate_credential_tags(tags: &[String]) -> ValidationResult {
     let mut result = ValidationResult::success();
 
     if tags.len() > MAX_TAGS_PER_CREDENTIAL {
-        result.add_error(format!(
-            "Too many tags: {} (maximum {})",
-            tags.len(),
-            MAX_TAGS_PER_CREDENTIAL
-        ));
+        result.add_error(
+            Message::new("validation.too_many_tags")
+                .with_arg("count", tags.len())
+                .with_arg("max", MAX_TAGS_PER_CREDENTIAL),
+        );
     }
 
     let mut unique_tags = HashSet::new();
     for tag in tags {
         // Check tag length
         if tag.len() > MAX_TAG_LENGTH {
-            result.add_error(format!(
-                "Tag too long: '{}' ({} characters, maximum {})",
-                tag,
-                tag.len(),
-                MAX_TAG_LENGTH
-            ));
+            result.add_error(
+                Message::new("validation.tag_too_long")
+                    .with_arg("tag", tag)
+                    .with_arg("length", tag.len())
+                    .with_arg("max", MAX_TAG_LENGTH),
+            );
         }
 
         // Check for empty tags
         if tag.trim().is_empty() {
-            result.add_error("Empty tag found".to_string());
+            result.add_error(Message::new("validation.tag_empty"));
         }
 
         // Check for duplicates (case-insensitive)
         let tag_lower = tag.to_lowercase();
         if !unique_tags.insert(tag_lower) {
-            result.add_error(format!("Duplicate tag: '{}'", tag));
+            result.add_error(Message::new("validation.duplicate_tag").with_arg("tag", tag));
         }
 
         // Check for invalid characters
         if tag.chars().any(|c| c.is_control()) {
-            result.add_error(format!("Tag contains control characters: '{}'", tag));
+            result
+                .add_error(Message::new("validation.tag_control_characters").with_arg("tag", tag));
         }
     }
 
@@ -272,36 +288,50 @@ pub fn validate_field(field_name: &str, field: &CredentialField) -> ValidationRe
 
     // Validate field name
     if field_name.is_empty() {
-        result.add_error("Field name cannot be empty".to_string());
+        result.add_error(Message::new("validation.field_name_empty"));
     } else if field_name.len() > 100 {
-        result.add_error(format!(
-            "Field name too long: '{}' ({} characters, maximum 100)",
-            field_name,
-            field_name.len()
-        ));
+        result.add_error(
+            Message::new("validation.field_name_too_long")
+                .with_arg("field", field_name)
+                .with_arg("length", field_name.len()),
+        );
     }
 
-    // Validate field value length
-    if field.value.len() > MAX_FIELD_VALUE_LENGTH {
-        result.add_error(format!(
-            "Field '{}' value too long: {} characters (maximum {})",
-            field_name,
-            field.value.len(),
-            MAX_FIELD_VALUE_LENGTH
-        ));
+    // Validate field value length; multi-line text may be long-form
+    let max_length = if field.field_type == FieldType::TextArea {
+        MAX_NOTE_LENGTH
+    } else {
+        MAX_FIELD_VALUE_LENGTH
+    };
+    if field.value.len() > max_length {
+        result.add_error(
+            Message::new("validation.field_value_too_long")
+                .with_arg("field", field_name)
+                .with_arg("length", field.value.len())
+                .with_arg("max", max_length),
+        );
     }
 
-    // Type-specific validation
-    result.merge(validate_field_by_type(field_name, field));
+    // A protected field's real value is sealed; only the placeholder may be
+    // stored next to it
+    if field.is_protected() {
+        if field.value != PROTECTED_FIELD_PLACEHOLDER {
+            result.add_error(
+                Message::new("validation.field_protected").with_arg("field", field_name),
+            );
+        }
+    } else {
+        result.merge(validate_field_by_type(field_name, field));
+    }
 
     // Validate field label if present
     if let Some(label) = &field.label {
         if label.len() > 200 {
-            result.add_error(format!(
-                "Field '{}' label too long: {} characters (maximum 200)",
-                field_name,
-                label.len()
-            ));
+            result.add_error(
+                Message::new("validation.field_label_too_long")
+                    .with_arg("field", field_name)
+                    .with_arg("length", label.len()),
+            );
         }
     }
 
@@ -315,61 +345,83 @@ pub fn validate_field_by_type(field_name: &str, field: &CredentialField) -> Vali
     match field.field_type {
         FieldType::Email => {
             if !field.value.is_empty() && !is_valid_email(&field.value) {
-                result.add_error(format!(
-                    "Field '{}' is not a valid email address",
-                    field_name
-                ));
+                result.add_error(
+                    Message::new("validation.invalid_email").with_arg("field", field_name),
+                );
             }
         }
         FieldType::Url => {
             if !field.value.is_empty() && !is_valid_url(&field.value) {
-                result.add_error(format!("Field '{}' is not a valid URL", field_name));
+                result.add_error(
+                    Message::new("validation.invalid_url").with_arg("field", field_name),
+                );
             }
         }
         FieldType::Phone => {
             if !field.value.is_empty() && !is_valid_phone(&field.value) {
-                result.add_warning(format!(
-                    "Field '{}' may not be a valid phone number",
-                    field_name
-                ));
+                result.add_warning(
+                    Message::new("validation.invalid_phone").with_arg("field", field_name),
+                );
             }
         }
         FieldType::CreditCardNumber => {
             if !field.value.is_empty() && !is_valid_credit_card(&field.value) {
-                result.add_error(format!(
-                    "Field '{}' is not a valid credit card number",
-                    field_name
-                ));
+                result.add_error(
+                    Message::new("validation.invalid_card_number").with_arg("field", field_name),
+                );
             }
         }
         FieldType::ExpiryDate => {
             if !field.value.is_empty() && !is_valid_expiry_date(&field.value) {
-                result.add_error(format!(
-                    "Field '{}' is not a valid expiry date (use MM/YY format)",
-                    field_name
-                ));
+                result.add_error(
+                    Message::new("validation.invalid_expiry_date").with_arg("field", field_name),
+                );
             }
         }
         FieldType::Cvv => {
             if !field.value.is_empty() && !is_valid_cvv(&field.value) {
-                result.add_error(format!("Field '{}' is not a valid CVV code", field_name));
+                result.add_error(
+                    Message::new("validation.invalid_cvv").with_arg("field", field_name),
+                );
             }
         }
         FieldType::TotpSecret => {
             if !field.value.is_empty() && !is_valid_totp_secret(&field.value) {
-                result.add_error(format!("Field '{}' is not a valid TOTP secret", field_name));
+                result.add_error(
+                    Message::new("validation.invalid_totp_secret").with_arg("field", field_name),
+                );
             }
         }
         FieldType::Number => {
             if !field.value.is_empty() && field.value.parse::<f64>().is_err() {
-                result.add_error(format!("Field '{}' is not a valid number", field_name));
+                result.add_error(
+                    Message::new("validation.invalid_number").with_arg("field", field_name),
+                );
             }
         }
         FieldType::Date => {
             if !field.value.is_empty() && !is_valid_date(&field.value) {
-                result.add_error(format!("Field '{}' is not a valid date", field_name));
+                result.add_error(
+                    Message::new("validation.invalid_date").with_arg("field", field_name),
+                );
             }
         }
+        FieldType::PgpPrivateKey
+            if !field.value.trim().is_empty()
+                && armored_block(&field.value, PRIVATE_KEY_LABEL).is_none() =>
+        {
+            result.add_error(
+                Message::new("validation.invalid_pgp_private_key").with_arg("field", field_name),
+            );
+        }
+        FieldType::PgpRevocationCertificate
+            if !field.value.trim().is_empty()
+                && armored_block(&field.value, PUBLIC_KEY_LABEL).is_none() =>
+        {
+            result.add_error(
+                Message::new("validation.invalid_pgp_revocation").with_arg("field", field_name),
+            );
+        }
         _ => {
             // No specific validation for other field types
         }
@@ -384,9 +436,13 @@ pub fn is_valid_email(email: &str) -> bool {
     email_regex.is_match(email) && email.len() <= 254
 }
 
-/// Validate a URL
+/// Validate a URL; `androidapp://<package>` names an Android app for autofill
 pub fn is_valid_url(url: &str) -> bool {
-    url.starts_with("http://") || url.starts_with("https://")
+    url.starts_with("http://")
+        || url.starts_with("https://")
+        || url
+            .strip_prefix("androidapp://")
+            .is_some_and(|package| !package.is_empty())
 }
 
 /// Validate a phone number (basic validation)
@@ -467,7 +523,7 @@ pub fn validate_password_strength(password: &str) -> ValidationResult {
     let mut result = ValidationResult::success();
 
     if password.is_empty() {
-        result.add_error("Password cannot be empty".to_string());
+        result.add_error(Message::new("validation.password_empty"));
         return result;
     }
 
@@ -478,40 +534,38 @@ pub fn validate_password_strength(password: &str) -> ValidationResult {
     let has_special = password.chars().any(|c| !c.is_alphanumeric());
 
     if length < 8 {
-        result.add_error("Password must be at least 8 characters long".to_string());
+        result.add_error(Message::new("validation.password_too_short"));
     } else if length < 12 {
-        result.add_warning(
-            "Password should be at least 12 characters for better security".to_string(),
-        );
+        result.add_warning(Message::new("validation.password_short"));
     }
 
     if !has_lowercase {
-        result.add_warning("Password should contain lowercase letters".to_string());
+        result.add_warning(Message::new("validation.password_no_lowercase"));
     }
 
     if !has_uppercase {
-        result.add_warning("Password should contain uppercase letters".to_string());
+        result.add_warning(Message::new("validation.password_no_uppercase"));
     }
 
     if !has_digits {
-        result.add_warning("Password should contain numbers".to_string());
+        result.add_warning(Message::new("validation.password_no_digits"));
     }
 
     if !has_special {
-        result.add_warning("Password should contain special characters".to_string());
+        result.add_warning(Message::new("validation.password_no_special"));
     }
 
     // Check for common patterns
     if password.to_lowercase().contains("password") {
-        result.add_warning("Password should not contain the word 'password'".to_string());
+        result.add_warning(Message::new("validation.password_contains_password"));
     }
 
     if is_sequential(&password) {
-        result.add_warning("Password should not contain sequential characters".to_string());
+        result.add_warning(Message::new("validation.password_sequential"));
     }
 
     if has_repeated_chars(&password) {
-        result.add_warning("Password should not have many repeated characters".to_string());
+        result.add_warning(Message::new("validation.password_repeated"));
     }
 
     result
@@ -585,6 +639,8 @@ mod tests {
         assert!(!is_valid_url("ftp://example.com"));
         assert!(!is_valid_url("example.com"));
         assert!(!is_valid_url("file:///path/to/file"));
+        assert!(is_valid_url("androidapp://com.example.app"));
+        assert!(!is_valid_url("androidapp://"));
     }
 
     #[test]
@@ -674,6 +730,21 @@ mod tests {
             .errors
             .iter()
             .any(|e| e.contains("not a valid email")));
+
+        let pasted_public_key = CredentialField::new(
+            FieldType::PgpPrivateKey,
+            "-----BEGIN PGP PUBLIC KEY BLOCK-----\n\nmDME\n-----END PGP PUBLIC KEY BLOCK-----"
+                .to_string(),
+            true,
+        );
+        assert!(!validate_field("private_key", &pasted_public_key).is_valid);
+        let revocation = CredentialField::new(
+            FieldType::PgpRevocationCertificate,
+            ":-----BEGIN PGP PUBLIC KEY BLOCK-----\n\niHgE\n-----END PGP PUBLIC KEY BLOCK-----"
+                .to_string(),
+            true,
+        );
+        assert!(validate_field("revocation_certificate", &revocation).is_valid);
     }
 
     #[test]
@@ -712,6 +783,36 @@ mod tests {
         assert!(result.errors.iter().any(|e| e.contains("Too many tags")));
     }
 
+    #[test]
+    fn test_validation_messages() {
+        let mut field = CredentialField::email("not an email");
+        field.label = Some("x".repeat(201));
+        let result = validate_field("email", &field);
+        assert_eq!(
+            result.errors,
+            [
+                "Field 'email' is not a valid email address",
+                "Field 'email' label too long: 201 characters (maximum 200)"
+            ]
+        );
+        let codes: Vec<&str> = result
+            .error_messages
+            .iter()
+            .map(|message| message.code.as_str())
+            .collect();
+        assert_eq!(
+            codes,
+            [
+                "validation.invalid_email",
+                "validation.field_label_too_long"
+            ]
+        );
+        assert_eq!(
+            result.error_messages[0].localize("es"),
+            "«email» no es una dirección de correo válida"
+        );
+    }
+
     #[test]
     fn test_validation_result_operations() {
         let mut result = ValidationResult::success();
@@ -0,0 +1,563 @@
+//! Watch companion protocol
+//!
+//! A paired watch asks the phone for the current TOTP codes of pinned
+//! credentials. Pairing shares one random secret between the two devices.
+//! Every request carries a fresh nonce and timestamp and is authenticated
+//! with HMAC-SHA256 under that secret; the phone rejects stale, replayed or
+//! forged requests. Each response is encrypted under a key derived from the
+//! secret and the request nonce, so it can only be read by the watch that
+//! made that request and is useless once the codes expire.
+//!
+//! Messages are compact strings suitable for BLE or the Wear OS data layer.
+
+use base64::prelude::*;
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use chrono::{DateTime, Utc};
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use super::encryption::{EncryptionUtils, SecureMemory};
+use super::widget::{WidgetCode, WidgetSnapshot};
+use crate::models::CredentialRecord;
+
+const PAIRING_PREFIX: &str = "zlwatch1:";
+const REQUEST_PREFIX: &str = "zlwr1:";
+const RESPONSE_PREFIX: &str = "zlwp1:";
+const PROTOCOL_INFO: &[u8] = b"ziplock-watch-v1";
+const NONCE_LEN: usize = 16;
+const CIPHER_NONCE_LEN: usize = 12;
+
+/// How far a request timestamp may differ from the phone's clock
+pub const DEFAULT_MAX_SKEW_SECS: i64 = 30;
+
+/// Error types for the watch protocol
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WatchError {
+    /// Pairing string is malformed
+    InvalidPairing,
+    /// Message is malformed
+    InvalidMessage(String),
+    /// Request came from a device that is not paired
+    UnknownDevice(String),
+    /// Request signature does not match
+    Unauthorized,
+    /// Request timestamp is outside the allowed window
+    Expired,
+    /// Request nonce was already used
+    Replayed,
+    /// Response was not encrypted for this request
+    DecryptionFailed,
+}
+
+impl fmt::Display for WatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WatchError::InvalidPairing => write!(f, "Invalid watch pairing"),
+            WatchError::InvalidMessage(msg) => write!(f, "Invalid watch message: {}", msg),
+            WatchError::UnknownDevice(id) => write!(f, "Watch is not paired: {}", id),
+            WatchError::Unauthorized => write!(f, "Watch request signature is invalid"),
+            WatchError::Expired => write!(f, "Watch request has expired"),
+            WatchError::Replayed => write!(f, "Watch request was already used"),
+            WatchError::DecryptionFailed => {
+                write!(f, "Watch response could not be decrypted for this request")
+            }
+        }
+    }
+}
+
+impl std::error::Error for WatchError {}
+
+/// Result type for watch protocol operations
+pub type WatchResult<T> = Result<T, WatchError>;
+
+/// Secret shared by a phone and one paired watch
+#[derive(Clone)]
+pub struct WatchPairing {
+    device_id: String,
+    secret: [u8; 32],
+}
+
+impl WatchPairing {
+    /// Pair a new watch
+    pub fn generate() -> Self {
+        let mut secret = [0u8; 32];
+        OsRng.fill_bytes(&mut secret);
+        Self {
+            device_id: uuid::Uuid::new_v4().simple().to_string(),
+            secret,
+        }
+    }
+
+    pub fn device_id(&self) -> &str {
+        &self.device_id
+    }
+
+    fn mac(&self, message: &[u8]) -> Hmac<Sha256> {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&self.secret)
+            .expect("HMAC accepts keys of any length");
+        mac.update(PROTOCOL_INFO);
+        mac.update(message);
+        mac
+    }
+
+    fn response_cipher(&self, request_nonce: &[u8]) -> ChaCha20Poly1305 {
+        self.derive_cipher(request_nonce, PROTOCOL_INFO)
+    }
+
+    /// Cipher keyed from the pairing secret, for protocols built on a pairing
+    pub(crate) fn derive_cipher(&self, salt: &[u8], info: &[u8]) -> ChaCha20Poly1305 {
+        let mut key = [0u8; 32];
+        Hkdf::<Sha256>::new(Some(salt), &self.secret)
+            .expand(info, &mut key)
+            .expect("32 bytes is a valid HKDF-SHA256 output length");
+        let cipher = ChaCha20Poly1305::new(Key::from_slice(&key));
+        SecureMemory::zero_memory(&mut key);
+        cipher
+    }
+}
+
+impl Drop for WatchPairing {
+    fn drop(&mut self) {
+        SecureMemory::zero_memory(&mut self.secret);
+    }
+}
+
+impl fmt::Display for WatchPairing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}{}:{}",
+            PAIRING_PREFIX,
+            self.device_id,
+            BASE64_URL_SAFE_NO_PAD.encode(self.secret)
+        )
+    }
+}
+
+impl fmt::Debug for WatchPairing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WatchPairing")
+            .field("device_id", &self.device_id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl FromStr for WatchPairing {
+    type Err = WatchError;
+
+    fn from_str(s: &str) -> WatchResult<Self> {
+        let (device_id, secret) = s
+            .trim()
+            .strip_prefix(PAIRING_PREFIX)
+            .and_then(|rest| rest.split_once(':'))
+            .ok_or(WatchError::InvalidPairing)?;
+        let secret = BASE64_URL_SAFE_NO_PAD
+            .decode(secret)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or(WatchError::InvalidPairing)?;
+        if device_id.is_empty() {
+            return Err(WatchError::InvalidPairing);
+        }
+        Ok(Self {
+            device_id: device_id.to_string(),
+            secret,
+        })
+    }
+}
+
+/// A signed request for codes, sent from the watch to the phone
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WatchRequest {
+    #[serde(rename = "d")]
+    pub device_id: String,
+    #[serde(rename = "n")]
+    nonce: String,
+    /// Unix time the request was made
+    #[serde(rename = "t")]
+    pub timestamp: i64,
+    /// Credential IDs wanted; empty means every pinned credential
+    #[serde(rename = "i", default, skip_serializing_if = "Vec::is_empty")]
+    pub credential_ids: Vec<String>,
+    #[serde(rename = "m")]
+    mac: String,
+}
+
+impl WatchRequest {
+    /// Create and sign a request
+    pub fn new(pairing: &WatchPairing, credential_ids: Vec<String>, now: DateTime<Utc>) -> Self {
+        let mut nonce = [0u8; NONCE_LEN];
+        OsRng.fill_bytes(&mut nonce);
+        let mut request = Self {
+            device_id: pairing.device_id.clone(),
+            nonce: BASE64_URL_SAFE_NO_PAD.encode(nonce),
+            timestamp: now.timestamp(),
+            credential_ids,
+            mac: String::new(),
+        };
+        request.mac = BASE64_URL_SAFE_NO_PAD
+            .encode(pairing.mac(&request.signed_bytes()).finalize().into_bytes());
+        request
+    }
+
+    /// Encode for transport
+    pub fn encode(&self) -> String {
+        format!(
+            "{}{}",
+            REQUEST_PREFIX,
+            BASE64_URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).unwrap_or_default())
+        )
+    }
+
+    /// Decode a request received from a watch (does not verify it)
+    pub fn decode(s: &str) -> WatchResult<Self> {
+        decode_message(s, REQUEST_PREFIX)
+    }
+
+    fn signed_bytes(&self) -> Vec<u8> {
+        format!(
+            "\n{}\n{}\n{}\n{}",
+            self.device_id,
+            self.nonce,
+            self.timestamp,
+            self.credential_ids.join(",")
+        )
+        .into_bytes()
+    }
+
+    fn verify(&self, pairing: &WatchPairing) -> WatchResult<()> {
+        let mac = BASE64_URL_SAFE_NO_PAD
+            .decode(&self.mac)
+            .map_err(|_| WatchError::Unauthorized)?;
+        pairing
+            .mac(&self.signed_bytes())
+            .verify_slice(&mac)
+            .map_err(|_| WatchError::Unauthorized)
+    }
+
+    fn nonce_bytes(&self) -> WatchResult<Vec<u8>> {
+        BASE64_URL_SAFE_NO_PAD
+            .decode(&self.nonce)
+            .ok()
+            .filter(|nonce| nonce.len() == NONCE_LEN)
+            .ok_or_else(|| WatchError::InvalidMessage("bad nonce".to_string()))
+    }
+}
+
+/// Encrypted codes, sent from the phone back to the watch
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WatchResponse {
+    #[serde(rename = "n")]
+    nonce: String,
+    #[serde(rename = "c")]
+    ciphertext: String,
+}
+
+impl WatchResponse {
+    /// Encode for transport
+    pub fn encode(&self) -> String {
+        format!(
+            "{}{}",
+            RESPONSE_PREFIX,
+            BASE64_URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).unwrap_or_default())
+        )
+    }
+
+    /// Decode a response received from the phone
+    pub fn decode(s: &str) -> WatchResult<Self> {
+        decode_message(s, RESPONSE_PREFIX)
+    }
+
+    /// Decrypt the codes on the watch
+    pub fn open(
+        &self,
+        pairing: &WatchPairing,
+        request: &WatchRequest,
+    ) -> WatchResult<Vec<WidgetCode>> {
+        let nonce = BASE64_URL_SAFE_NO_PAD
+            .decode(&self.nonce)
+            .ok()
+            .filter(|n| n.len() == CIPHER_NONCE_LEN)
+            .ok_or_else(|| WatchError::InvalidMessage("bad response nonce".to_string()))?;
+        let ciphertext = BASE64_URL_SAFE_NO_PAD
+            .decode(&self.ciphertext)
+            .map_err(|e| WatchError::InvalidMessage(e.to_string()))?;
+
+        let plaintext = pairing
+            .response_cipher(&request.nonce_bytes()?)
+            .decrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &ciphertext,
+                    aad: request.mac.as_bytes(),
+                },
+            )
+            .map_err(|_| WatchError::DecryptionFailed)?;
+        serde_json::from_slice(&plaintext).map_err(|e| WatchError::InvalidMessage(e.to_string()))
+    }
+}
+
+/// Phone-side authorization of watch requests
+///
+/// Keeps the paired devices and the nonces seen within the skew window, so
+/// each request can be answered exactly once.
+#[derive(Debug)]
+pub struct WatchAuthorizer {
+    pairings: HashMap<String, WatchPairing>,
+    seen: HashMap<String, i64>,
+    max_skew_secs: i64,
+}
+
+impl Default for WatchAuthorizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WatchAuthorizer {
+    pub fn new() -> Self {
+        Self {
+            pairings: HashMap::new(),
+            seen: HashMap::new(),
+            max_skew_secs: DEFAULT_MAX_SKEW_SECS,
+        }
+    }
+
+    /// Allow requests timestamped up to `secs` away from the phone's clock
+    pub fn with_max_skew(mut self, secs: i64) -> Self {
+        self.max_skew_secs = secs.max(1);
+        self
+    }
+
+    /// Trust a paired watch
+    pub fn add_pairing(&mut self, pairing: WatchPairing) {
+        self.pairings.insert(pairing.device_id.clone(), pairing);
+    }
+
+    /// A paired device by ID
+    pub fn pairing(&self, device_id: &str) -> Option<&WatchPairing> {
+        self.pairings.get(device_id)
+    }
+
+    /// Forget a watch; its requests are rejected from now on
+    pub fn remove_pairing(&mut self, device_id: &str) -> bool {
+        self.pairings.remove(device_id).is_some()
+    }
+
+    /// Verify a request and consume its nonce
+    pub fn authorize(&mut self, request: &WatchRequest, now: DateTime<Utc>) -> WatchResult<()> {
+        let pairing = self
+            .pairings
+            .get(&request.device_id)
+            .ok_or_else(|| WatchError::UnknownDevice(request.device_id.clone()))?;
+        request.verify(pairing)?;
+        request.nonce_bytes()?;
+
+        let now = now.timestamp();
+        if (now - request.timestamp).abs() > self.max_skew_secs {
+            return Err(WatchError::Expired);
+        }
+
+        // Nonces only need remembering while their request could still be accepted
+        let window = self.max_skew_secs;
+        self.seen.retain(|_, at| now - *at <= 2 * window);
+        let key = format!("{}:{}", request.device_id, request.nonce);
+        if self.seen.contains_key(&key) {
+            return Err(WatchError::Replayed);
+        }
+        self.seen.insert(key, request.timestamp);
+        Ok(())
+    }
+
+    /// Authorize a request and answer it with codes for pinned credentials
+    ///
+    /// Requested IDs that are not pinned are left out of the response.
+    pub fn respond(
+        &mut self,
+        request: &WatchRequest,
+        credentials: &[CredentialRecord],
+        pinned_ids: &[String],
+        now: DateTime<Utc>,
+    ) -> WatchResult<WatchResponse> {
+        self.authorize(request, now)?;
+
+        let ids: Vec<String> = if request.credential_ids.is_empty() {
+            pinned_ids.to_vec()
+        } else {
+            pinned_ids
+                .iter()
+                .filter(|id| request.credential_ids.contains(id))
+                .cloned()
+                .collect()
+        };
+        let codes = WidgetSnapshot::from_credentials(credentials, &ids)
+            .codes_at(now.timestamp().max(0) as u64);
+
+        let pairing = &self.pairings[&request.device_id];
+        let nonce = EncryptionUtils::random_bytes(CIPHER_NONCE_LEN);
+        let mut plaintext =
+            serde_json::to_vec(&codes).map_err(|e| WatchError::InvalidMessage(e.to_string()))?;
+        let ciphertext = pairing
+            .response_cipher(&request.nonce_bytes()?)
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &plaintext,
+                    aad: request.mac.as_bytes(),
+                },
+            )
+            .map_err(|_| WatchError::InvalidMessage("encryption failed".to_string()));
+        SecureMemory::zero_memory(&mut plaintext);
+
+        Ok(WatchResponse {
+            nonce: BASE64_URL_SAFE_NO_PAD.encode(nonce),
+            ciphertext: BASE64_URL_SAFE_NO_PAD.encode(ciphertext?),
+        })
+    }
+}
+
+fn decode_message<T: for<'de> Deserialize<'de>>(s: &str, prefix: &str) -> WatchResult<T> {
+    let encoded = s
+        .trim()
+        .strip_prefix(prefix)
+        .ok_or_else(|| WatchError::InvalidMessage("unrecognized format".to_string()))?;
+    let bytes = BASE64_URL_SAFE_NO_PAD
+        .decode(encoded)
+        .map_err(|e| WatchError::InvalidMessage(e.to_string()))?;
+    serde_json::from_slice(&bytes).map_err(|e| WatchError::InvalidMessage(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::CredentialField;
+    use chrono::Duration;
+
+    fn now() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2024-06-01T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn credentials() -> Vec<CredentialRecord> {
+        let mut github = CredentialRecord::new("GitHub".to_string(), "login".to_string());
+        github.set_field(
+            "totp_secret",
+            CredentialField::totp_secret("JBSWY3DPEHPK3PXP"),
+        );
+        let bank = CredentialRecord::new("Bank".to_string(), "login".to_string());
+        vec![github, bank]
+    }
+
+    fn paired() -> (WatchPairing, WatchAuthorizer) {
+        let pairing = WatchPairing::generate();
+        let mut authorizer = WatchAuthorizer::new();
+        authorizer.add_pairing(pairing.to_string().parse().unwrap());
+        (pairing, authorizer)
+    }
+
+    #[test]
+    fn test_round_trip_over_the_wire() {
+        let (pairing, mut authorizer) = paired();
+        let credentials = credentials();
+        let pinned = vec![credentials[0].id.clone()];
+
+        let request = WatchRequest::new(&pairing, Vec::new(), now());
+        let received = WatchRequest::decode(&request.encode()).unwrap();
+        let response = authorizer
+            .respond(&received, &credentials, &pinned, now())
+            .unwrap();
+
+        let codes = WatchResponse::decode(&response.encode())
+            .unwrap()
+            .open(&pairing, &request)
+            .unwrap();
+        assert_eq!(codes.len(), 1);
+        assert_eq!(codes[0].title, "GitHub");
+        assert_eq!(codes[0].code.as_ref().unwrap().len(), 6);
+    }
+
+    #[test]
+    fn test_only_pinned_credentials_are_returned() {
+        let (pairing, mut authorizer) = paired();
+        let credentials = credentials();
+        let pinned = vec![credentials[0].id.clone()];
+
+        let request = WatchRequest::new(&pairing, vec![credentials[1].id.clone()], now());
+        let response = authorizer
+            .respond(&request, &credentials, &pinned, now())
+            .unwrap();
+        assert!(response.open(&pairing, &request).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_replayed_and_stale_requests_are_rejected() {
+        let (pairing, mut authorizer) = paired();
+        let request = WatchRequest::new(&pairing, Vec::new(), now());
+
+        assert!(authorizer.authorize(&request, now()).is_ok());
+        assert_eq!(
+            authorizer.authorize(&request, now()),
+            Err(WatchError::Replayed)
+        );
+
+        let old = WatchRequest::new(&pairing, Vec::new(), now() - Duration::minutes(5));
+        assert_eq!(authorizer.authorize(&old, now()), Err(WatchError::Expired));
+    }
+
+    #[test]
+    fn test_forged_and_unpaired_requests_are_rejected() {
+        let (pairing, mut authorizer) = paired();
+
+        let mut tampered = WatchRequest::new(&pairing, Vec::new(), now());
+        tampered.credential_ids.push("something-else".to_string());
+        assert_eq!(
+            authorizer.authorize(&tampered, now()),
+            Err(WatchError::Unauthorized)
+        );
+
+        let stranger = WatchPairing::generate();
+        let request = WatchRequest::new(&stranger, Vec::new(), now());
+        assert!(matches!(
+            authorizer.authorize(&request, now()),
+            Err(WatchError::UnknownDevice(_))
+        ));
+
+        assert!(authorizer.remove_pairing(pairing.device_id()));
+        let request = WatchRequest::new(&pairing, Vec::new(), now());
+        assert!(authorizer.authorize(&request, now()).is_err());
+    }
+
+    #[test]
+    fn test_response_is_bound_to_its_request() {
+        let (pairing, mut authorizer) = paired();
+        let credentials = credentials();
+        let pinned = vec![credentials[0].id.clone()];
+
+        let first = WatchRequest::new(&pairing, Vec::new(), now());
+        let second = WatchRequest::new(&pairing, Vec::new(), now());
+        let response = authorizer
+            .respond(&first, &credentials, &pinned, now())
+            .unwrap();
+        assert_eq!(
+            response.open(&pairing, &second),
+            Err(WatchError::DecryptionFailed)
+        );
+    }
+
+    #[test]
+    fn test_pairing_string() {
+        let pairing = WatchPairing::generate();
+        let parsed: WatchPairing = pairing.to_string().parse().unwrap();
+        assert_eq!(parsed.device_id(), pairing.device_id());
+        assert!("zlwatch1:nope".parse::<WatchPairing>().is_err());
+        assert!(!format!("{:?}", pairing).contains(&pairing.to_string()));
+    }
+}
@@ -0,0 +1,392 @@
+//! Read-only data for home-screen widgets and watch apps
+//!
+//! While the vault is unlocked the app writes a [`WidgetSnapshot`] holding
+//! only the titles and TOTP parameters of pinned credentials, sealed with a
+//! [`WidgetKey`]. The widget key is unrelated to the master password: it is
+//! wrapped with a device secret from the platform keystore, so a widget
+//! process can show codes without ever being able to open the vault.
+
+use base64::prelude::*;
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use chrono::{DateTime, Utc};
+use hkdf::Hkdf;
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::fmt;
+use std::str::FromStr;
+
+use super::encryption::SecureMemory;
+use super::totp::TotpGenerator;
+use crate::models::{CredentialRecord, FieldType};
+
+const KEY_PREFIX: &str = "zlwk1";
+const WRAPPED_KEY_PREFIX: &str = "zlwkw1:";
+const SNAPSHOT_PREFIX: &str = "zlwidget1:";
+const WRAP_INFO: &[u8] = b"ziplock-widget-wrap-v1";
+const NONCE_LEN: usize = 12;
+
+/// Error types for widget data
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WidgetError {
+    /// Key string or wrapped key is malformed
+    InvalidKey,
+    /// Snapshot is malformed or uses an unsupported version
+    InvalidSnapshot(String),
+    /// Data was sealed with a different key, or was tampered with
+    DecryptionFailed,
+}
+
+impl fmt::Display for WidgetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WidgetError::InvalidKey => write!(f, "Invalid widget key"),
+            WidgetError::InvalidSnapshot(msg) => write!(f, "Invalid widget snapshot: {}", msg),
+            WidgetError::DecryptionFailed => {
+                write!(f, "Widget data could not be decrypted with this key")
+            }
+        }
+    }
+}
+
+impl std::error::Error for WidgetError {}
+
+/// Result type for widget operations
+pub type WidgetResult<T> = Result<T, WidgetError>;
+
+/// Low-privilege key that can only read widget snapshots
+pub struct WidgetKey {
+    bytes: [u8; 32],
+}
+
+impl WidgetKey {
+    /// Generate a new random key
+    pub fn generate() -> Self {
+        let mut bytes = [0u8; 32];
+        OsRng.fill_bytes(&mut bytes);
+        Self { bytes }
+    }
+
+    /// Wrap the key with a device secret so it can be stored next to the snapshot
+    pub fn wrap(&self, device_secret: &[u8]) -> WidgetResult<String> {
+        let sealed = seal(&wrapping_cipher(device_secret), &self.bytes, WRAP_INFO)?;
+        Ok(format!("{}{}", WRAPPED_KEY_PREFIX, sealed))
+    }
+
+    /// Recover a key wrapped with [`WidgetKey::wrap`]
+    pub fn unwrap(wrapped: &str, device_secret: &[u8]) -> WidgetResult<Self> {
+        let encoded = wrapped
+            .trim()
+            .strip_prefix(WRAPPED_KEY_PREFIX)
+            .ok_or(WidgetError::InvalidKey)?;
+        let mut plaintext = open(&wrapping_cipher(device_secret), encoded, WRAP_INFO)?;
+        let bytes = plaintext
+            .as_slice()
+            .try_into()
+            .map_err(|_| WidgetError::InvalidKey);
+        SecureMemory::zero_memory(&mut plaintext);
+        Ok(Self { bytes: bytes? })
+    }
+
+    fn cipher(&self) -> ChaCha20Poly1305 {
+        ChaCha20Poly1305::new(Key::from_slice(&self.bytes))
+    }
+}
+
+impl Drop for WidgetKey {
+    fn drop(&mut self) {
+        SecureMemory::zero_memory(&mut self.bytes);
+    }
+}
+
+impl fmt::Display for WidgetKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}{}",
+            KEY_PREFIX,
+            BASE64_URL_SAFE_NO_PAD.encode(self.bytes)
+        )
+    }
+}
+
+impl fmt::Debug for WidgetKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WidgetKey").finish_non_exhaustive()
+    }
+}
+
+impl FromStr for WidgetKey {
+    type Err = WidgetError;
+
+    fn from_str(s: &str) -> WidgetResult<Self> {
+        let bytes = s
+            .trim()
+            .strip_prefix(KEY_PREFIX)
+            .and_then(|encoded| BASE64_URL_SAFE_NO_PAD.decode(encoded).ok())
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or(WidgetError::InvalidKey)?;
+        Ok(Self { bytes })
+    }
+}
+
+/// A pinned credential as seen by a widget
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WidgetEntry {
+    pub id: String,
+    pub title: String,
+    /// `otpauth://` URI, if the credential has a TOTP secret
+    pub totp_uri: Option<String>,
+}
+
+impl WidgetEntry {
+    /// The entry's code at a Unix timestamp
+    pub fn code_at(&self, timestamp: u64) -> WidgetCode {
+        let generator = self
+            .totp_uri
+            .as_deref()
+            .and_then(|uri| TotpGenerator::from_uri(uri).ok());
+        let (code, period) = match &generator {
+            Some(generator) => (generator.generate_at(timestamp).ok(), generator.period()),
+            None => (None, 0),
+        };
+
+        WidgetCode {
+            id: self.id.clone(),
+            title: self.title.clone(),
+            code,
+            period,
+            seconds_remaining: if period == 0 {
+                0
+            } else {
+                period - timestamp % period
+            },
+        }
+    }
+}
+
+/// What a widget displays for one pinned credential
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WidgetCode {
+    pub id: String,
+    pub title: String,
+    /// Current TOTP code, or `None` if the credential has no usable secret
+    pub code: Option<String>,
+    /// Code lifetime in seconds (0 without a code)
+    pub period: u64,
+    pub seconds_remaining: u64,
+}
+
+/// Pinned credentials exported for widgets
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WidgetSnapshot {
+    pub created_at: DateTime<Utc>,
+    pub entries: Vec<WidgetEntry>,
+}
+
+impl WidgetSnapshot {
+    /// Build a snapshot of the pinned credentials, in pin order
+    ///
+    /// Only titles and TOTP parameters are copied; passwords and every other
+    /// field stay in the vault. Unknown IDs are skipped.
+    pub fn from_credentials(credentials: &[CredentialRecord], pinned_ids: &[String]) -> Self {
+        let entries = pinned_ids
+            .iter()
+            .filter_map(|id| credentials.iter().find(|c| &c.id == id))
+            .map(|credential| WidgetEntry {
+                id: credential.id.clone(),
+                title: credential.title.clone(),
+                totp_uri: credential
+                    .fields
+                    .values()
+                    .filter(|field| {
+                        field.field_type == FieldType::TotpSecret && !field.value.trim().is_empty()
+                    })
+                    .find_map(|field| TotpGenerator::from_field(field).ok())
+                    .map(|generator| generator.to_uri()),
+            })
+            .collect();
+
+        Self {
+            created_at: Utc::now(),
+            entries,
+        }
+    }
+
+    /// Encrypt the snapshot for storage where the widget can read it
+    pub fn seal(&self, key: &WidgetKey) -> WidgetResult<String> {
+        let mut plaintext =
+            serde_json::to_vec(self).map_err(|e| WidgetError::InvalidSnapshot(e.to_string()))?;
+        let sealed = seal(&key.cipher(), &plaintext, SNAPSHOT_PREFIX.as_bytes());
+        SecureMemory::zero_memory(&mut plaintext);
+        Ok(format!("{}{}", SNAPSHOT_PREFIX, sealed?))
+    }
+
+    /// Decrypt a sealed snapshot
+    pub fn open(sealed: &str, key: &WidgetKey) -> WidgetResult<Self> {
+        let encoded = sealed
+            .trim()
+            .strip_prefix(SNAPSHOT_PREFIX)
+            .ok_or_else(|| WidgetError::InvalidSnapshot("unrecognized format".to_string()))?;
+        let mut plaintext = open(&key.cipher(), encoded, SNAPSHOT_PREFIX.as_bytes())?;
+        let snapshot = serde_json::from_slice(&plaintext)
+            .map_err(|e| WidgetError::InvalidSnapshot(e.to_string()));
+        SecureMemory::zero_memory(&mut plaintext);
+        snapshot
+    }
+
+    /// Codes for every entry at a Unix timestamp
+    pub fn codes_at(&self, timestamp: u64) -> Vec<WidgetCode> {
+        self.entries
+            .iter()
+            .map(|entry| entry.code_at(timestamp))
+            .collect()
+    }
+}
+
+fn wrapping_cipher(device_secret: &[u8]) -> ChaCha20Poly1305 {
+    let mut key = [0u8; 32];
+    Hkdf::<Sha256>::new(None, device_secret)
+        .expand(WRAP_INFO, &mut key)
+        .expect("32 bytes is a valid HKDF-SHA256 output length");
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(&key));
+    SecureMemory::zero_memory(&mut key);
+    cipher
+}
+
+/// Encrypt with a random nonce, returning base64url(nonce || ciphertext)
+fn seal(cipher: &ChaCha20Poly1305, plaintext: &[u8], aad: &[u8]) -> WidgetResult<String> {
+    let mut nonce = [0u8; NONCE_LEN];
+    OsRng.fill_bytes(&mut nonce);
+    let ciphertext = cipher
+        .encrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: plaintext,
+                aad,
+            },
+        )
+        .map_err(|_| WidgetError::InvalidSnapshot("encryption failed".to_string()))?;
+
+    let mut out = nonce.to_vec();
+    out.extend_from_slice(&ciphertext);
+    Ok(BASE64_URL_SAFE_NO_PAD.encode(out))
+}
+
+fn open(cipher: &ChaCha20Poly1305, encoded: &str, aad: &[u8]) -> WidgetResult<Vec<u8>> {
+    let data = BASE64_URL_SAFE_NO_PAD
+        .decode(encoded)
+        .map_err(|e| WidgetError::InvalidSnapshot(e.to_string()))?;
+    if data.len() <= NONCE_LEN {
+        return Err(WidgetError::InvalidSnapshot("data too short".to_string()));
+    }
+    let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+    cipher
+        .decrypt(
+            Nonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad,
+            },
+        )
+        .map_err(|_| WidgetError::DecryptionFailed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::CredentialField;
+
+    fn credentials() -> Vec<CredentialRecord> {
+        let mut github = CredentialRecord::new("GitHub".to_string(), "login".to_string());
+        github.set_field("password", CredentialField::password("hunter2"));
+        github.set_field(
+            "totp_secret",
+            CredentialField::totp_secret("JBSWY3DPEHPK3PXP"),
+        );
+        let mut bank = CredentialRecord::new("Bank".to_string(), "login".to_string());
+        bank.set_field("password", CredentialField::password("letmein"));
+        let other = CredentialRecord::new("Unpinned".to_string(), "login".to_string());
+        vec![github, bank, other]
+    }
+
+    #[test]
+    fn test_snapshot_contains_only_pinned_titles_and_totp() {
+        let credentials = credentials();
+        let pinned = vec![credentials[1].id.clone(), credentials[0].id.clone()];
+        let snapshot = WidgetSnapshot::from_credentials(&credentials, &pinned);
+
+        assert_eq!(snapshot.entries.len(), 2);
+        assert_eq!(snapshot.entries[0].title, "Bank");
+        assert!(snapshot.entries[0].totp_uri.is_none());
+        assert!(snapshot.entries[1]
+            .totp_uri
+            .as_deref()
+            .unwrap()
+            .contains("JBSWY3DPEHPK3PXP"));
+
+        let json = serde_json::to_string(&snapshot).unwrap();
+        assert!(!json.contains("hunter2"));
+        assert!(!json.contains("letmein"));
+    }
+
+    #[test]
+    fn test_seal_and_open_round_trip() {
+        let credentials = credentials();
+        let snapshot = WidgetSnapshot::from_credentials(&credentials, &[credentials[0].id.clone()]);
+        let key = WidgetKey::generate();
+
+        let sealed = snapshot.seal(&key).unwrap();
+        assert!(sealed.starts_with(SNAPSHOT_PREFIX));
+        assert!(!sealed.contains("GitHub"));
+        assert_eq!(WidgetSnapshot::open(&sealed, &key).unwrap(), snapshot);
+
+        let other = WidgetKey::generate();
+        assert_eq!(
+            WidgetSnapshot::open(&sealed, &other),
+            Err(WidgetError::DecryptionFailed)
+        );
+    }
+
+    #[test]
+    fn test_codes_match_totp_generator() {
+        let credentials = credentials();
+        let pinned: Vec<String> = credentials.iter().map(|c| c.id.clone()).collect();
+        let snapshot = WidgetSnapshot::from_credentials(&credentials, &pinned);
+
+        let codes = snapshot.codes_at(59);
+        assert_eq!(codes.len(), 3);
+        let expected = TotpGenerator::new("JBSWY3DPEHPK3PXP")
+            .unwrap()
+            .generate_at(59)
+            .unwrap();
+        assert_eq!(codes[0].code.as_deref(), Some(expected.as_str()));
+        assert_eq!(codes[0].period, 30);
+        assert_eq!(codes[0].seconds_remaining, 1);
+        assert_eq!(codes[1].code, None);
+    }
+
+    #[test]
+    fn test_key_wrapping() {
+        let key = WidgetKey::generate();
+        let wrapped = key.wrap(b"device secret").unwrap();
+        assert!(wrapped.starts_with(WRAPPED_KEY_PREFIX));
+
+        let unwrapped = WidgetKey::unwrap(&wrapped, b"device secret").unwrap();
+        assert_eq!(unwrapped.to_string(), key.to_string());
+        assert_eq!(
+            WidgetKey::unwrap(&wrapped, b"another device").unwrap_err(),
+            WidgetError::DecryptionFailed
+        );
+    }
+
+    #[test]
+    fn test_key_string_round_trip() {
+        let key = WidgetKey::generate();
+        let parsed: WidgetKey = key.to_string().parse().unwrap();
+        assert_eq!(parsed.to_string(), key.to_string());
+        assert!("zlwk1nope".parse::<WidgetKey>().is_err());
+        assert!(!format!("{:?}", key).contains(&key.to_string()));
+    }
+}
@@ -490,14 +490,14 @@ fn test_archive_integrity_validation() {
         .expect("Failed to save repository");
 
     // Verify repository integrity
-    let integrity_issues = manager
+    let integrity_report = manager
         .verify_integrity()
         .expect("Failed to verify integrity");
 
     assert!(
-        integrity_issues.is_empty(),
+        integrity_report.is_healthy(),
         "Repository integrity should be valid: {:?}",
-        integrity_issues
+        integrity_report.issues
     );
 
     // Get statistics
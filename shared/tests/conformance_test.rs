@@ -0,0 +1,318 @@
+//! Archive-Format Conformance Test
+//!
+//! Runs `verify_compatibility` over `tests/fixtures/conformance`, so a change
+//! that stops ZipLock reading an archive it once wrote, or makes it write
+//! the same vault differently, fails here.
+//!
+//! New fixtures are added by describing them in `fixtures()` and running
+//!
+//! ```bash
+//! cargo test -p ziplock-shared --test conformance_test -- --ignored
+//! ```
+//!
+//! which writes the archives that do not exist yet and appends them to the
+//! manifest. Existing archives are never rewritten.
+
+use std::path::PathBuf;
+
+use ziplock_shared::core::conformance::{
+    read_manifest, FixtureManifest, ProtectedFieldCheck, MANIFEST_FILE,
+};
+use ziplock_shared::core::{
+    verify_compatibility, ArchiveBackend, Fixture, SevenZipBackend, UnifiedMemoryRepository,
+};
+use ziplock_shared::models::{
+    CredentialField, CredentialRecord, CredentialTemplate, FieldTemplate, FieldType,
+};
+use ziplock_shared::utils::yaml::{deserialize_metadata, serialize_metadata};
+
+fn fixtures_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/conformance")
+}
+
+#[test]
+fn test_fixtures_conform() {
+    let report = verify_compatibility(fixtures_dir()).unwrap();
+    assert!(!report.results.is_empty());
+    for result in report.failures() {
+        eprintln!("{}: {:?}", result.file, result.failures);
+    }
+    assert!(report.passed());
+}
+
+#[test]
+fn test_wrong_contents_are_reported() {
+    let manifest = read_manifest(fixtures_dir()).unwrap();
+    let fixture = &manifest.fixtures[0];
+    let archive = std::fs::read(fixtures_dir().join(&fixture.file)).unwrap();
+
+    let mut changed = fixture.clone();
+    changed.credentials[0].title.push_str(" (edited)");
+    assert_eq!(
+        changed.verify_read(&archive),
+        vec![format!("credential {} differs", changed.credentials[0].id)]
+    );
+
+    changed.password = "not the password".to_string();
+    assert!(changed.verify(&archive)[0].starts_with("archive does not open"));
+}
+
+// Fixture generation
+
+const PASSWORD: &str = "conformance";
+
+/// Contents of the key file used by `key-file.7z`
+const KEY_FILE: &str = "404142434445464748494a4b4c4d4e4f505152535455565758595a5b5c5d5e5f\
+                        606162636465666768696a6b6c6d6e6f707172737475767778797a7b7c7d7e7f";
+
+struct Spec {
+    file: &'static str,
+    description: &'static str,
+    key_file: Option<&'static str>,
+    build: fn(&mut UnifiedMemoryRepository) -> Vec<ProtectedFieldCheck>,
+    /// Rewrites the serialized files into an older layout
+    downgrade: Option<fn(&mut ziplock_shared::core::FileMap)>,
+}
+
+fn credential(id: u32, title: &str, credential_type: &str) -> CredentialRecord {
+    let mut credential = CredentialRecord::new(title.to_string(), credential_type.to_string());
+    credential.id = format!("00000000-0000-4000-8000-{:012}", id);
+    credential
+}
+
+fn login(id: u32, title: &str) -> CredentialRecord {
+    let mut credential = credential(id, title, "login");
+    credential.set_field("username", CredentialField::username("jane@example.com"));
+    credential.set_field("password", CredentialField::password("correct horse"));
+    credential.set_field("url", CredentialField::url("https://example.com/login"));
+    credential
+}
+
+fn fixtures() -> Vec<Spec> {
+    vec![
+        Spec {
+            file: "basic-login.7z",
+            description: "One login with a TOTP secret, tags and notes",
+            key_file: None,
+            build: |repository| {
+                let mut credential = login(1, "Example");
+                credential.set_field(
+                    "totp_secret",
+                    CredentialField::totp_secret("JBSWY3DPEHPK3PXP"),
+                );
+                credential.add_tag("work");
+                credential.add_tag("email");
+                credential.notes = Some("Recovery codes are in the safe".to_string());
+                repository.add_credential(credential).unwrap();
+                Vec::new()
+            },
+            downgrade: None,
+        },
+        Spec {
+            file: "credential-types.7z",
+            description: "Card, note and identity credentials with folders, favorites, \
+                          labelled fields, field metadata and non-ASCII text",
+            key_file: None,
+            build: |repository| {
+                let mut card = credential(1, "Visa ••••1111", "credit_card");
+                card.set_field(
+                    "cardholder",
+                    CredentialField::text("Zoë Müller").with_label("Cardholder"),
+                );
+                card.set_field(
+                    "number",
+                    CredentialField::new(
+                        FieldType::CreditCardNumber,
+                        "4111111111111111".to_string(),
+                        true,
+                    ),
+                );
+                card.set_field(
+                    "expiry",
+                    CredentialField::new(FieldType::ExpiryDate, "12/30".to_string(), false),
+                );
+                card.set_field(
+                    "cvv",
+                    CredentialField::new(FieldType::Cvv, "123".to_string(), true),
+                );
+                card.folder_path = Some("Finance/Cards".to_string());
+                card.favorite = true;
+
+                let mut note = credential(2, "日本語のメモ", "secure_note");
+                note.notes = Some("Line one\nLine two: \"quoted\" — ✓".to_string());
+                note.folder_path = Some("Personal".to_string());
+
+                let mut identity = credential(3, "Passport", "identity");
+                identity.set_field(
+                    "phone",
+                    CredentialField::new(FieldType::Phone, "+44 20 7946 0000".to_string(), false)
+                        .with_metadata("country", "GB")
+                        .with_metadata("kind", "mobile"),
+                );
+                identity.set_field("email", CredentialField::email("zoe@example.org"));
+                identity.icon = Some("passport".to_string());
+                identity.color = Some("#3366ff".to_string());
+
+                for credential in [card, note, identity] {
+                    repository.add_credential(credential).unwrap();
+                }
+                Vec::new()
+            },
+            downgrade: None,
+        },
+        Spec {
+            file: "key-file.7z",
+            description: "Encrypted with the password combined with a key file",
+            key_file: Some(KEY_FILE),
+            build: |repository| {
+                repository.add_credential(login(1, "Server")).unwrap();
+                Vec::new()
+            },
+            downgrade: None,
+        },
+        Spec {
+            file: "protected-field.7z",
+            description: "A field sealed with a passphrase of its own",
+            key_file: None,
+            build: |repository| {
+                let mut credential = login(1, "Bank");
+                credential.set_field("pin", CredentialField::password("2468"));
+                let id = credential.id.clone();
+                repository.add_credential(credential).unwrap();
+                repository
+                    .protect_field(&id, "pin", "field passphrase")
+                    .unwrap();
+                vec![ProtectedFieldCheck {
+                    credential_id: id,
+                    field: "pin".to_string(),
+                    passphrase: "field passphrase".to_string(),
+                    value: "2468".to_string(),
+                }]
+            },
+            downgrade: None,
+        },
+        Spec {
+            file: "templates.7z",
+            description: "A user-defined template and a credential created from it",
+            key_file: None,
+            build: |repository| {
+                let mut template = CredentialTemplate::new("router", "Router admin login");
+                template
+                    .add_field(FieldTemplate::new("ssid", "Network", FieldType::Text, true))
+                    .unwrap();
+                template
+                    .add_field(FieldTemplate::new(
+                        "password",
+                        "Password",
+                        FieldType::Password,
+                        true,
+                    ))
+                    .unwrap();
+                repository.add_template(template).unwrap();
+
+                let mut credential = credential(1, "Home router", "router");
+                credential.set_field("ssid", CredentialField::text("home-5g"));
+                credential.set_field("password", CredentialField::password("hunter22"));
+                repository.add_credential(credential).unwrap();
+                Vec::new()
+            },
+            downgrade: None,
+        },
+        Spec {
+            file: "empty.7z",
+            description: "A vault without credentials",
+            key_file: None,
+            build: |_| Vec::new(),
+            downgrade: None,
+        },
+        Spec {
+            file: "legacy-layout.7z",
+            description: "Layout from before repository IDs and credential schema versions \
+                          were recorded; both are added when the vault is saved",
+            key_file: None,
+            build: |repository| {
+                repository.add_credential(login(1, "Old account")).unwrap();
+                Vec::new()
+            },
+            downgrade: Some(|files| {
+                for (path, data) in files.iter_mut() {
+                    let text = String::from_utf8(data.clone()).unwrap();
+                    let kept: Vec<&str> = text
+                        .lines()
+                        .filter(|line| {
+                            !line.starts_with("repository_id:")
+                                && !line.starts_with("schema_version:")
+                        })
+                        .collect();
+                    assert_ne!(kept.len(), text.lines().count(), "{} unchanged", path);
+                    *data = format!("{}\n", kept.join("\n")).into_bytes();
+                }
+            }),
+        },
+    ]
+}
+
+fn generate(spec: &Spec) -> Fixture {
+    let mut repository = UnifiedMemoryRepository::new();
+    repository.initialize().unwrap();
+    let protected_fields = (spec.build)(&mut repository);
+    let mut files = repository.serialize_to_files().unwrap();
+
+    // Fixed repository timestamps, so they say nothing about when it was made
+    let metadata = files.get_mut("metadata.yml").unwrap();
+    let mut parsed = deserialize_metadata(std::str::from_utf8(metadata).unwrap()).unwrap();
+    parsed.created_at = 1_700_000_000;
+    parsed.last_modified = 1_700_000_000;
+    *metadata = serialize_metadata(&parsed).unwrap().into_bytes();
+    if let Some(downgrade) = spec.downgrade {
+        downgrade(&mut files);
+    }
+
+    let mut loaded = UnifiedMemoryRepository::new();
+    loaded.load_from_files(files.clone()).unwrap();
+    let mut credentials = loaded.list_credentials().unwrap();
+    credentials.sort_by(|a, b| a.id.cmp(&b.id));
+    let templates = loaded
+        .list_templates()
+        .unwrap()
+        .into_iter()
+        .map(|template| template.name)
+        .collect();
+
+    let fixture = Fixture {
+        file: spec.file.to_string(),
+        description: spec.description.to_string(),
+        generated_by: ziplock_shared::VERSION.to_string(),
+        archive_format_version: ziplock_shared::ARCHIVE_FORMAT_VERSION.to_string(),
+        password: PASSWORD.to_string(),
+        key_file: spec.key_file.map(str::to_string),
+        canonical: spec.downgrade.is_none(),
+        protected_fields,
+        templates,
+        credentials,
+    };
+    let archive = SevenZipBackend
+        .create(files, &fixture.archive_password().unwrap())
+        .unwrap();
+    std::fs::write(fixtures_dir().join(spec.file), archive).unwrap();
+    fixture
+}
+
+#[test]
+#[ignore = "writes fixture archives; run by hand when adding one"]
+fn generate_missing_fixtures() {
+    let dir = fixtures_dir();
+    std::fs::create_dir_all(&dir).unwrap();
+    let mut manifest = read_manifest(&dir).unwrap_or(FixtureManifest {
+        fixtures: Vec::new(),
+    });
+    for spec in fixtures() {
+        let listed = manifest.fixtures.iter().any(|f| f.file == spec.file);
+        if listed || dir.join(spec.file).exists() {
+            continue;
+        }
+        manifest.fixtures.push(generate(&spec));
+    }
+    let json = serde_json::to_string_pretty(&manifest).unwrap();
+    std::fs::write(dir.join(MANIFEST_FILE), json + "\n").unwrap();
+}
@@ -0,0 +1,200 @@
+//! Design tokens shared by the ZipLock frontends
+//!
+//! The colors, spacing and type scale from `docs/design.md`, named by what
+//! they are for (accent, surface, danger) rather than how they look. Every
+//! frontend builds its widget styles from these tokens instead of keeping its
+//! own copy of the palette, so a change to the design is made once. The crate
+//! has no dependencies; each frontend converts [`Rgba`] into its toolkit's
+//! color type.
+
+/// A color with straight (not premultiplied) alpha, channels in `0.0..=1.0`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rgba {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
+
+impl Rgba {
+    /// An opaque color from its channels
+    pub const fn rgb(r: f32, g: f32, b: f32) -> Self {
+        Self { r, g, b, a: 1.0 }
+    }
+
+    /// An opaque color from a `0xRRGGBB` value as written in the design
+    pub const fn hex(rgb: u32) -> Self {
+        Self::rgb(
+            ((rgb >> 16) & 0xff) as f32 / 255.0,
+            ((rgb >> 8) & 0xff) as f32 / 255.0,
+            (rgb & 0xff) as f32 / 255.0,
+        )
+    }
+
+    /// The same color with opacity `a`
+    pub const fn with_alpha(self, a: f32) -> Self {
+        Self { a, ..self }
+    }
+
+    /// The color channels as bytes, for toolkits and terminals taking 8-bit
+    /// RGB; alpha is dropped
+    pub fn to_rgb8(self) -> [u8; 3] {
+        let byte = |channel: f32| (channel.clamp(0.0, 1.0) * 255.0).round() as u8;
+        [byte(self.r), byte(self.g), byte(self.b)]
+    }
+}
+
+/// Semantic colors of the light theme
+pub mod palette {
+    use super::Rgba;
+
+    /// Brand purple for interactive elements and highlights (#8338ec)
+    pub const ACCENT: Rgba = Rgba::hex(0x8338ec);
+    /// Accent under the pointer
+    pub const ACCENT_HOVER: Rgba = Rgba::rgb(0.45, 0.18, 0.82);
+    /// Accent while pressed
+    pub const ACCENT_PRESSED: Rgba = Rgba::rgb(0.40, 0.15, 0.75);
+    /// Accent wash behind hovered items
+    pub const ACCENT_TINT: Rgba = ACCENT.with_alpha(0.1);
+    /// Accent wash behind pressed or selected items
+    pub const ACCENT_TINT_STRONG: Rgba = ACCENT.with_alpha(0.2);
+    /// Faint accent wash for large areas
+    pub const ACCENT_TINT_SUBTLE: Rgba = ACCENT.with_alpha(0.05);
+    /// Text and icons drawn on the accent
+    pub const ON_ACCENT: Rgba = Rgba::rgb(1.0, 1.0, 1.0);
+
+    /// Valid input and completed operations (#06d6a0)
+    pub const SUCCESS: Rgba = Rgba::hex(0x06d6a0);
+    /// Invalid input, errors and destructive actions (#ef476f)
+    pub const DANGER: Rgba = Rgba::hex(0xef476f);
+    /// Danger under the pointer
+    pub const DANGER_HOVER: Rgba = Rgba::rgb(0.85, 0.25, 0.40);
+    /// Danger while pressed
+    pub const DANGER_PRESSED: Rgba = Rgba::rgb(0.80, 0.22, 0.35);
+    /// Warnings (#fcbf49)
+    pub const WARNING: Rgba = Rgba::hex(0xfcbf49);
+
+    /// Window background (#F8F9FA)
+    pub const SURFACE: Rgba = Rgba::hex(0xf8f9fa);
+    /// Cards, inputs and other components on the window background
+    pub const SURFACE_RAISED: Rgba = Rgba::rgb(1.0, 1.0, 1.0);
+    /// Inputs that cannot be edited
+    pub const SURFACE_SUNKEN: Rgba = Rgba::rgb(0.95, 0.95, 0.95);
+    /// Body text (#212529)
+    pub const ON_SURFACE: Rgba = Rgba::hex(0x212529);
+    /// Help text and secondary labels
+    pub const ON_SURFACE_MUTED: Rgba = Rgba::rgb(0.6, 0.6, 0.6);
+    /// Icons and placeholders
+    pub const ICON: Rgba = Rgba::rgb(0.5, 0.5, 0.5);
+
+    /// Outline of inputs
+    pub const BORDER: Rgba = Rgba::rgb(0.8, 0.8, 0.8);
+    /// Outline of disabled elements
+    pub const BORDER_SUBTLE: Rgba = Rgba::rgb(0.9, 0.9, 0.9);
+    /// Shadow under raised components
+    pub const SHADOW: Rgba = Rgba::rgb(0.0, 0.0, 0.0).with_alpha(0.1);
+
+    /// Background of disabled buttons
+    pub const DISABLED: Rgba = Rgba::rgb(0.8, 0.8, 0.8);
+    /// Text of disabled buttons
+    pub const ON_DISABLED: Rgba = Rgba::rgb(0.5, 0.5, 0.5);
+    /// Border of disabled buttons
+    pub const DISABLED_BORDER: Rgba = Rgba::rgb(0.7, 0.7, 0.7);
+}
+
+/// Gaps and padding in logical pixels
+///
+/// Padding is `[vertical, horizontal]`.
+pub mod spacing {
+    /// Gap between the sections of a view
+    pub const STANDARD: u16 = 20;
+    /// Corner radius of buttons, inputs and cards
+    pub const RADIUS: f32 = 10.0;
+
+    pub const BUTTON: [u16; 2] = [10, 20];
+    pub const BUTTON_SMALL: [u16; 2] = [4, 8];
+    pub const BUTTON_STANDARD: [u16; 2] = [12, 24];
+    pub const BUTTON_WIDE: [u16; 2] = [12, 32];
+    pub const BUTTON_COMPLETION: [u16; 2] = [12, 20];
+    pub const BUTTON_ADD: [u16; 2] = [10, 16];
+    pub const TEXT_INPUT: [u16; 2] = [10, 15];
+    pub const TITLE_INPUT: [u16; 2] = [15, 20];
+    pub const SEARCH_BAR: [u16; 2] = [12, 16];
+    pub const LIST: [u16; 2] = [15, 20];
+    pub const ALERT: [u16; 2] = [15, 20];
+    pub const ERROR_CONTAINER: [u16; 2] = [20, 25];
+    pub const TOAST_DISMISS: [u16; 2] = [5, 8];
+    pub const SMALL_ELEMENT: [u16; 2] = [8, 12];
+    pub const LOGO_CONTAINER: [u16; 2] = [20, 40];
+    pub const MAIN_CONTENT: [u16; 2] = [20, 30];
+}
+
+/// Text sizes, relative to a base size the user can change
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TypeScale {
+    pub base: f32,
+}
+
+impl TypeScale {
+    /// Base size when the user has not picked one
+    pub const DEFAULT_BASE: f32 = 14.0;
+
+    pub const fn new(base: f32) -> Self {
+        Self { base }
+    }
+
+    /// Body text and inputs
+    pub const fn normal(self) -> f32 {
+        self.base
+    }
+
+    /// Captions and hints
+    pub const fn small(self) -> f32 {
+        self.base - 2.0
+    }
+
+    /// Emphasised text and title inputs
+    pub const fn medium(self) -> f32 {
+        self.base + 2.0
+    }
+
+    /// Section headers
+    pub const fn header(self) -> f32 {
+        self.base + 4.0
+    }
+
+    /// View titles
+    pub const fn large(self) -> f32 {
+        self.base + 6.0
+    }
+
+    /// Welcome and setup headlines
+    pub const fn extra_large(self) -> f32 {
+        self.base + 10.0
+    }
+}
+
+impl Default for TypeScale {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_BASE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokens_match_design() {
+        assert_eq!(palette::ACCENT.to_rgb8(), [0x83, 0x38, 0xec]);
+        assert_eq!(palette::DANGER.to_rgb8(), [0xef, 0x47, 0x6f]);
+        assert_eq!(palette::SURFACE.to_rgb8(), [0xf8, 0xf9, 0xfa]);
+        assert_eq!(palette::ACCENT_TINT.to_rgb8(), palette::ACCENT.to_rgb8());
+        assert_eq!(palette::ACCENT_TINT.a, 0.1);
+        assert_eq!(Rgba::rgb(1.5, -0.5, 0.5).to_rgb8(), [255, 0, 128]);
+
+        let scale = TypeScale::default();
+        assert_eq!(scale.normal(), 14.0);
+        assert_eq!(TypeScale::new(16.0).header(), 20.0);
+    }
+}